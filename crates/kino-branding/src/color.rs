@@ -0,0 +1,122 @@
+//! Hex color parsing, mixing and WCAG contrast.
+
+use std::fmt;
+
+/// An sRGB color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb {
+    /// Red channel
+    pub r: u8,
+    /// Green channel
+    pub g: u8,
+    /// Blue channel
+    pub b: u8,
+}
+
+/// Why a color string was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorError {
+    /// The rejected input
+    pub input: String,
+}
+
+impl fmt::Display for ColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid hex color {:?}: expected #rgb or #rrggbb", self.input)
+    }
+}
+
+impl std::error::Error for ColorError {}
+
+impl Rgb {
+    /// Create a color from its channels.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Parse `#rgb` or `#rrggbb`; the `#` is optional and case is ignored.
+    pub fn parse(input: &str) -> Result<Self, ColorError> {
+        let error = || ColorError { input: input.to_string() };
+        let hex = input.trim();
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(error());
+        }
+
+        let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| error());
+        match hex.len() {
+            3 => {
+                let short = |i: usize| channel(&hex[i..i + 1]).map(|v| v * 17);
+                Ok(Self::new(short(0)?, short(1)?, short(2)?))
+            }
+            6 => Ok(Self::new(channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?)),
+            _ => Err(error()),
+        }
+    }
+
+    /// Lowercase `#rrggbb`.
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    /// CSS `rgba()` with the given alpha.
+    pub fn rgba(self, alpha: f32) -> String {
+        format!("rgba({}, {}, {}, {})", self.r, self.g, self.b, alpha)
+    }
+
+    /// Blend towards `other` by `amount` (0 keeps this color, 1 gives `other`).
+    pub fn mix(self, other: Rgb, amount: f32) -> Self {
+        let amount = amount.clamp(0.0, 1.0);
+        let blend = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+        Self::new(blend(self.r, other.r), blend(self.g, other.g), blend(self.b, other.b))
+    }
+
+    /// WCAG 2.x relative luminance (0 for black, 1 for white).
+    pub fn relative_luminance(self) -> f64 {
+        let linear = |channel: u8| {
+            let c = channel as f64 / 255.0;
+            if c <= 0.03928 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        };
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+
+    /// WCAG contrast ratio against `other`, from 1 to 21.
+    pub fn contrast_ratio(self, other: Rgb) -> f64 {
+        let (a, b) = (self.relative_luminance(), other.relative_luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Rgb::parse("#9b30ff"), Ok(Rgb::new(155, 48, 255)));
+        assert_eq!(Rgb::parse("9B30FF"), Ok(Rgb::new(155, 48, 255)));
+        assert_eq!(Rgb::parse("#fa0"), Ok(Rgb::new(255, 170, 0)));
+        assert!(Rgb::parse("#9b30f").is_err());
+        assert!(Rgb::parse("#gg0000").is_err());
+        assert!(Rgb::parse("#+12345").is_err());
+        assert!(Rgb::parse("purple").is_err());
+        assert_eq!(Rgb::new(155, 48, 255).to_hex(), "#9b30ff");
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        let black = Rgb::new(0, 0, 0);
+        let white = Rgb::new(255, 255, 255);
+        assert!((black.contrast_ratio(white) - 21.0).abs() < 1e-9);
+        assert!((white.contrast_ratio(white) - 1.0).abs() < 1e-9);
+        // #777 on white is the classic just-below-AA grey
+        assert!((Rgb::parse("#777").unwrap().contrast_ratio(white) - 4.48).abs() < 0.01);
+        assert_eq!(black.mix(white, 0.5), Rgb::new(128, 128, 128));
+    }
+}
@@ -0,0 +1,21 @@
+//! Kino color palette and theming shared by kino-core and kino-wasm.
+//!
+//! Holds the Purple Squirrel Media palette, the rules for deriving a full
+//! palette from a few brand color overrides, and the CSS / JS theme
+//! generation, so the native and browser players emit identical styles
+//! for the same colors.
+//!
+//! The crate does no I/O and only depends on serde, keeping it cheap to
+//! include in the WASM bundle.
+
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+pub mod color;
+pub mod theme;
+
+pub use color::{ColorError, Rgb};
+pub use theme::{
+    ColorOverrides, ContrastWarning, CssVariables, JsTheme, KinoColors, MIN_TEXT_CONTRAST,
+    MIN_UI_CONTRAST,
+};
@@ -0,0 +1,442 @@
+//! Kino palette, color overrides and CSS / JS theme generation.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::{ColorError, Rgb};
+
+/// WCAG AA minimum contrast for body text.
+pub const MIN_TEXT_CONTRAST: f64 = 4.5;
+
+/// WCAG AA minimum contrast for UI components such as the progress bar.
+pub const MIN_UI_CONTRAST: f64 = 3.0;
+
+const BLACK: Rgb = Rgb::new(0, 0, 0);
+
+/// Official Kino color palette
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KinoColors {
+    /// Primary purple - #9b30ff (RGB: 155, 48, 255)
+    pub primary: String,
+    /// Darker primary for hover states - #7a1fe8
+    pub primary_dark: String,
+    /// Deep purple for accents - #3b0b7d
+    pub primary_deep: String,
+    /// Main background color - #0c0a12
+    pub background: String,
+    /// Lighter background for cards/panels - #0f0b18
+    pub background_light: String,
+    /// Surface color for elevated elements - #1a1625
+    pub surface: String,
+    /// Main text color - #f6f2ff
+    pub text: String,
+    /// Soft/muted text color - #d4cde9
+    pub text_soft: String,
+    /// Success color - #22c55e
+    pub success: String,
+    /// Warning color - #f59e0b
+    pub warning: String,
+    /// Error color - #ef4444
+    pub error: String,
+}
+
+impl Default for KinoColors {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl KinoColors {
+    /// The standard dark palette
+    pub fn dark() -> Self {
+        Self {
+            primary: "#9b30ff".to_string(),
+            primary_dark: "#7a1fe8".to_string(),
+            primary_deep: "#3b0b7d".to_string(),
+            background: "#0c0a12".to_string(),
+            background_light: "#0f0b18".to_string(),
+            surface: "#1a1625".to_string(),
+            text: "#f6f2ff".to_string(),
+            text_soft: "#d4cde9".to_string(),
+            success: "#22c55e".to_string(),
+            warning: "#f59e0b".to_string(),
+            error: "#ef4444".to_string(),
+        }
+    }
+
+    /// Light palette with the same brand and status colors
+    pub fn light() -> Self {
+        Self {
+            background: "#f7f5fb".to_string(),
+            background_light: "#efeaf7".to_string(),
+            surface: "#ffffff".to_string(),
+            text: "#1a1625".to_string(),
+            text_soft: "#4a4458".to_string(),
+            ..Self::dark()
+        }
+    }
+
+    /// Get primary color as RGB tuple
+    pub fn primary_rgb(&self) -> (u8, u8, u8) {
+        let rgb = rgb(&self.primary);
+        (rgb.r, rgb.g, rgb.b)
+    }
+
+    /// Get primary color as RGBA with custom alpha
+    pub fn primary_rgba(&self, alpha: f32) -> String {
+        rgb(&self.primary).rgba(alpha)
+    }
+
+    /// Get background as RGBA with custom alpha
+    pub fn background_rgba(&self, alpha: f32) -> String {
+        rgb(&self.background).rgba(alpha)
+    }
+
+    /// Apply brand color overrides, deriving the dependent shades.
+    ///
+    /// Hover and accent shades follow the primary color, panel shades follow
+    /// the background and the muted text follows the text color. Fails on the
+    /// first color that is not valid hex, leaving nothing applied.
+    pub fn with_overrides(&self, overrides: &ColorOverrides) -> Result<Self, ColorError> {
+        let parse = |value: &Option<String>| value.as_deref().map(Rgb::parse).transpose();
+        let primary = parse(&overrides.primary)?;
+        let secondary = parse(&overrides.secondary)?;
+        let background = parse(&overrides.background)?;
+        let text = parse(&overrides.text)?;
+
+        let mut colors = self.clone();
+        if let Some(primary) = primary {
+            colors.primary = primary.to_hex();
+            colors.primary_dark = primary.mix(BLACK, 0.2).to_hex();
+            colors.primary_deep = primary.mix(BLACK, 0.55).to_hex();
+        }
+        if let Some(secondary) = secondary {
+            colors.primary_deep = secondary.to_hex();
+        }
+
+        let text_rgb = text.unwrap_or_else(|| rgb(&colors.text));
+        if let Some(background) = background {
+            colors.background = background.to_hex();
+            colors.background_light = background.mix(text_rgb, 0.02).to_hex();
+            colors.surface = background.mix(text_rgb, 0.08).to_hex();
+        }
+        if let Some(text) = text {
+            colors.text = text.to_hex();
+            colors.text_soft = text.mix(rgb(&colors.background), 0.2).to_hex();
+        }
+        Ok(colors)
+    }
+
+    /// Color pairs that fall below WCAG AA contrast
+    pub fn contrast_warnings(&self) -> Vec<ContrastWarning> {
+        [
+            ("text", &self.text, "background", &self.background, MIN_TEXT_CONTRAST),
+            ("text", &self.text, "surface", &self.surface, MIN_TEXT_CONTRAST),
+            ("primary", &self.primary, "background", &self.background, MIN_UI_CONTRAST),
+        ]
+        .into_iter()
+        .filter_map(|(foreground, fg, background, bg, minimum)| {
+            let ratio = rgb(fg).contrast_ratio(rgb(bg));
+            (ratio < minimum).then_some(ContrastWarning { foreground, background, ratio, minimum })
+        })
+        .collect()
+    }
+}
+
+// Palette fields are validated on override, so only hand-edited values can fail
+fn rgb(hex: &str) -> Rgb {
+    Rgb::parse(hex).unwrap_or_default()
+}
+
+/// Brand colors to override, as hex strings
+///
+/// Any field left out keeps the base palette's color.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorOverrides {
+    /// Main brand color
+    pub primary: Option<String>,
+    /// Accent color; derived from `primary` when not set
+    pub secondary: Option<String>,
+    /// Player background
+    pub background: Option<String>,
+    /// Main text color
+    pub text: Option<String>,
+}
+
+impl ColorOverrides {
+    /// Whether no colors are overridden
+    pub fn is_empty(&self) -> bool {
+        self.primary.is_none() && self.secondary.is_none() && self.background.is_none() && self.text.is_none()
+    }
+}
+
+/// A color pair below the WCAG AA contrast minimum
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContrastWarning {
+    /// Palette role drawn on top
+    pub foreground: &'static str,
+    /// Palette role underneath
+    pub background: &'static str,
+    /// Measured contrast ratio
+    pub ratio: f64,
+    /// Required contrast ratio
+    pub minimum: f64,
+}
+
+impl fmt::Display for ContrastWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} on {} has contrast {:.2}:1, below the WCAG AA minimum of {}:1",
+            self.foreground, self.background, self.ratio, self.minimum
+        )
+    }
+}
+
+/// CSS variable definitions for web players
+pub struct CssVariables;
+
+impl CssVariables {
+    /// Generate CSS custom properties for the Kino theme
+    pub fn generate() -> String {
+        Self::generate_for(&KinoColors::default())
+    }
+
+    /// Generate CSS custom properties for a palette
+    pub fn generate_for(colors: &KinoColors) -> String {
+        format!(
+            r#":root {{
+  /* Kino Primary Colors */
+  --kino-primary: {};
+  --kino-primary-dark: {};
+  --kino-primary-deep: {};
+
+  /* Kino Background Colors */
+  --kino-background: {};
+  --kino-background-light: {};
+  --kino-surface: {};
+
+  /* Kino Text Colors */
+  --kino-text: {};
+  --kino-text-soft: {};
+
+  /* Kino Status Colors */
+  --kino-success: {};
+  --kino-warning: {};
+  --kino-error: {};
+
+  /* Kino Gradients */
+  --kino-gradient-primary: linear-gradient(145deg, {}, {});
+  --kino-gradient-controls: linear-gradient(transparent, {});
+
+  /* Kino Shadows */
+  --kino-shadow-primary: 0 4px 20px {};
+  --kino-shadow-glow: 0 0 10px {};
+
+  /* Plyr compatibility */
+  --plyr-color-main: {};
+  --plyr-video-background: {};
+  --plyr-menu-background: {};
+  --plyr-menu-color: {};
+}}"#,
+            colors.primary,
+            colors.primary_dark,
+            colors.primary_deep,
+            colors.background,
+            colors.background_light,
+            colors.surface,
+            colors.text,
+            colors.text_soft,
+            colors.success,
+            colors.warning,
+            colors.error,
+            colors.primary_dark,
+            colors.primary_deep,
+            colors.background_rgba(0.9),
+            colors.primary_rgba(0.4),
+            colors.primary_rgba(0.5),
+            colors.primary,
+            colors.background,
+            colors.background_rgba(0.95),
+            colors.text,
+        )
+    }
+
+    /// Generate player-specific CSS
+    pub fn player_css() -> String {
+        Self::player_css_for(&KinoColors::default())
+    }
+
+    /// Generate player-specific CSS for a palette
+    pub fn player_css_for(colors: &KinoColors) -> String {
+        let panel = colors.background_rgba(0.95);
+        let accent = colors.primary_rgba(0.3);
+        format!(
+            r#"
+/* Kino Styles */
+.kino {{
+  background: var(--kino-background);
+  font-family: system-ui, -apple-system, sans-serif;
+}}
+
+.kino__controls {{
+  background: var(--kino-gradient-controls) !important;
+}}
+
+.kino__play-button {{
+  background: var(--kino-gradient-primary) !important;
+  box-shadow: var(--kino-shadow-primary);
+  border: none;
+  cursor: pointer;
+  transition: all 0.2s ease;
+}}
+
+.kino__play-button:hover {{
+  transform: scale(1.05);
+  box-shadow: var(--kino-shadow-glow);
+}}
+
+.kino__progress {{
+  background: var(--kino-background-light);
+}}
+
+.kino__progress-bar {{
+  background: var(--kino-primary);
+}}
+
+.kino__tooltip {{
+  background: {panel};
+  color: var(--kino-text);
+  border: 1px solid {accent};
+}}
+
+.kino__menu {{
+  background: {panel};
+  border: 1px solid {accent};
+  color: var(--kino-text);
+}}
+
+.kino__watermark {{
+  position: absolute;
+  bottom: 45px;
+  right: 10px;
+  font-size: 10px;
+  color: {accent};
+  pointer-events: none;
+  z-index: 1;
+}}
+"#
+        )
+    }
+}
+
+/// JavaScript-compatible theme object for hls.js/React integrations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsTheme {
+    /// Main accent color
+    pub primary_color: String,
+    /// Control bar background, translucent
+    pub controls_background: String,
+    /// Played portion of the progress bar
+    pub progress_color: String,
+    /// Buffered portion of the progress bar
+    pub buffer_color: String,
+    /// Control and caption text
+    pub text_color: String,
+    /// Corner radius in pixels
+    pub border_radius: u8,
+}
+
+impl Default for JsTheme {
+    fn default() -> Self {
+        Self::from_colors(&KinoColors::default())
+    }
+}
+
+impl JsTheme {
+    /// Build the JS theme for a palette
+    pub fn from_colors(colors: &KinoColors) -> Self {
+        Self {
+            primary_color: colors.primary.clone(),
+            controls_background: colors.background_rgba(0.7),
+            progress_color: colors.primary.clone(),
+            buffer_color: "rgba(255, 255, 255, 0.3)".to_string(),
+            text_color: colors.text.clone(),
+            border_radius: 8,
+        }
+    }
+
+    /// Export as JSON for JavaScript consumption
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(json: &str) -> ColorOverrides {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_default_palette() {
+        let colors = KinoColors::default();
+        assert_eq!(colors.primary_rgb(), (155, 48, 255));
+        assert_eq!(colors.primary_rgba(0.5), "rgba(155, 48, 255, 0.5)");
+        assert_eq!(colors.background_rgba(0.9), "rgba(12, 10, 18, 0.9)");
+        assert!(colors.contrast_warnings().is_empty());
+        assert!(KinoColors::light().contrast_warnings().is_empty());
+
+        let css = CssVariables::generate();
+        assert!(css.contains("--kino-gradient-controls: linear-gradient(transparent, rgba(12, 10, 18, 0.9));"));
+        assert!(css.contains("--kino-shadow-glow: 0 0 10px rgba(155, 48, 255, 0.5);"));
+        assert!(css.contains("--plyr-menu-background: rgba(12, 10, 18, 0.95);"));
+        assert!(CssVariables::player_css().contains("border: 1px solid rgba(155, 48, 255, 0.3);"));
+    }
+
+    #[test]
+    fn test_overrides() {
+        let base = KinoColors::default();
+        assert_eq!(base.with_overrides(&ColorOverrides::default()).unwrap(), base);
+
+        let colors = base.with_overrides(&overrides(r##"{"primary": "#FF0066", "background": "#000"}"##)).unwrap();
+        assert_eq!(colors.primary, "#ff0066");
+        assert_eq!(colors.primary_dark, "#cc0052");
+        assert_eq!(colors.background, "#000000");
+        assert_eq!(colors.text, base.text);
+        assert_eq!(colors.success, base.success);
+
+        let css = CssVariables::generate_for(&colors);
+        assert!(css.contains("--kino-primary: #ff0066;"));
+        assert!(css.contains("--kino-shadow-primary: 0 4px 20px rgba(255, 0, 102, 0.4);"));
+        assert!(css.contains("--plyr-menu-background: rgba(0, 0, 0, 0.95);"));
+        assert_eq!(JsTheme::from_colors(&colors).primary_color, "#ff0066");
+
+        let colors = base.with_overrides(&overrides(r##"{"primary": "#ff0066", "secondary": "#112233"}"##)).unwrap();
+        assert_eq!(colors.primary_deep, "#112233");
+    }
+
+    #[test]
+    fn test_invalid_overrides() {
+        let base = KinoColors::default();
+        let err = base.with_overrides(&overrides(r##"{"primary": "#ff0066", "text": "white"}"##)).unwrap_err();
+        assert_eq!(err.input, "white");
+        assert!(serde_json::from_str::<ColorOverrides>(r##"{"primry": "#ff0066"}"##).is_err());
+    }
+
+    #[test]
+    fn test_contrast_warnings() {
+        let colors = KinoColors::light()
+            .with_overrides(&overrides(r##"{"primary": "#ffe066", "text": "#999999"}"##))
+            .unwrap();
+        let warnings = colors.contrast_warnings();
+        let pairs: Vec<_> = warnings.iter().map(|w| (w.foreground, w.background)).collect();
+        assert_eq!(pairs, [("text", "background"), ("text", "surface"), ("primary", "background")]);
+        assert!(warnings[0].to_string().contains("below the WCAG AA minimum of 4.5:1"));
+    }
+}
@@ -1,9 +1,49 @@
 //! CLI command implementations
 
-use kino_core::manifest::create_parser;
-use std::path::PathBuf;
+use kino_core::manifest::{create_parser, create_parser_with, detect_manifest_type, HlsParser, ManifestType};
+use kino_core::{
+    CsvWriter, DrmConfig, EncryptionMethod, Error, HttpFetcher, IntegrityPolicy, ReqwestFetcher, Segment,
+    SegmentDecryptor, SegmentIntegrity, SegmentVerifier,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use url::Url;
 
+use crate::compare::{self, CompareOptions, Playlist, Severity, StreamSnapshot};
+use crate::monitor::{self, Alert, MonitorState, MonitorThresholds};
+use crate::probe::{Prober, Verdict};
+
+/// Build a DRM config carrying `Name: value` headers for key requests
+fn key_drm_config(key_headers: &[String]) -> anyhow::Result<DrmConfig> {
+    key_headers.iter().try_fold(DrmConfig::default(), |config, header| {
+        let (name, value) = header.split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Invalid key header '{}', expected 'Name: value'", header))?;
+        Ok(config.with_header(name.trim(), value.trim()))
+    })
+}
+
+/// Download a segment and verify its integrity, decrypting it when it is
+/// AES-128 encrypted
+async fn check_segment(
+    fetcher: &dyn HttpFetcher,
+    verifier: &SegmentVerifier,
+    decryptor: &SegmentDecryptor,
+    segment: &Segment,
+) -> kino_core::Result<()> {
+    let response = fetcher.get(&segment.uri, &HashMap::new(), segment.byte_range)
+        .await?
+        .error_for_status()?;
+    let content_length = response.content_length();
+    let data = response.bytes().await?;
+
+    verifier.verify(segment, &segment.uri, content_length, &data).await?;
+    if segment.encryption.as_ref().is_some_and(|e| e.method == EncryptionMethod::Aes128) {
+        decryptor.decrypt(segment, data).await?;
+    }
+    Ok(())
+}
+
 /// Analyze a manifest
 pub async fn analyze(manifest_url: &str, _format: &str) -> anyhow::Result<()> {
     println!("Analyzing manifest: {}", manifest_url);
@@ -36,14 +76,21 @@ pub async fn validate(
     manifest_url: &str,
     segments: usize,
     all_renditions: bool,
-    _format: &str,
+    key_headers: &[String],
+    bitrate_tolerance: f64,
+    concurrency: usize,
+    format: &str,
 ) -> anyhow::Result<()> {
-    println!("Validating stream: {}", manifest_url);
-    println!("  Testing {} segments", segments);
-    println!("  All renditions: {}", all_renditions);
+    let json = format == "json";
+    if !json {
+        println!("Validating stream: {}", manifest_url);
+        println!("  Testing {} segments", segments);
+        println!("  All renditions: {}", all_renditions);
+    }
 
     let url = Url::parse(manifest_url)?;
-    let parser = create_parser(&url);
+    let fetcher: Arc<dyn HttpFetcher> = Arc::new(ReqwestFetcher::default());
+    let parser = create_parser_with(&url, fetcher.clone());
     let manifest = parser.parse(&url).await?;
 
     let renditions_to_test = if all_renditions {
@@ -62,44 +109,57 @@ pub async fn validate(
         r
     };
 
-    let mut passed = 0;
-    let mut failed = 0;
+    let prober = Arc::new(Prober {
+        parser: Arc::from(parser),
+        fetcher: fetcher.clone(),
+        decryptor: Arc::new(SegmentDecryptor::with_drm_config(fetcher, &key_drm_config(key_headers)?)),
+        segments,
+        tolerance: bitrate_tolerance,
+    });
 
-    for rendition in &renditions_to_test {
-        print!("  Testing {} ({})... ", rendition.id, rendition.bandwidth);
+    let results = prober.probe_all(renditions_to_test, concurrency).await;
 
-        // Fetch segment playlist
-        match parser.parse_variant(&rendition.uri).await {
-            Ok(segments_list) => {
-                let test_count = segments.min(segments_list.len());
-                let mut seg_passed = 0;
+    let count = |verdict: Verdict| results.iter().filter(|r| r.verdict == verdict).count();
+    let (passed, warned, failed) = (count(Verdict::Pass), count(Verdict::Warn), count(Verdict::Fail));
 
-                let client = reqwest::Client::new();
-                for seg in segments_list.iter().take(test_count) {
-                    // Try to HEAD request each segment
-                    if let Ok(resp) = client.head(seg.uri.as_str()).send().await {
-                        if resp.status().is_success() {
-                            seg_passed += 1;
-                        }
-                    }
-                }
+    if json {
+        let report = serde_json::json!({
+            "manifest": manifest_url,
+            "bitrate_tolerance": bitrate_tolerance,
+            "renditions": results,
+            "passed": passed,
+            "warned": warned,
+            "failed": failed,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("\nABR Ladder:");
+        println!("  {:12} {:>10} {:>10} {:>10} {:>10} {:>8} {:>9} {:>9} {:>9} {:>7}",
+            "Rendition", "Declared", "Measured", "Peak", "Thruput", "TTFB", "Min Seg", "Avg Seg", "Max Seg", "Verdict");
+        for r in &results {
+            println!("  {:12} {:>10} {:>10} {:>10} {:>10} {:>6.0}ms {:>9} {:>9} {:>9} {:>7}",
+                r.id,
+                format_bitrate(r.declared_bandwidth),
+                format_bitrate(r.measured_bitrate),
+                format_bitrate(r.peak_bitrate),
+                format_bitrate(r.throughput),
+                r.avg_ttfb_ms,
+                format_bytes(r.min_segment_bytes),
+                format_bytes(r.avg_segment_bytes),
+                format_bytes(r.max_segment_bytes),
+                r.verdict.as_str()
+            );
+        }
 
-                if seg_passed == test_count {
-                    println!("PASS ({}/{})", seg_passed, test_count);
-                    passed += 1;
-                } else {
-                    println!("PARTIAL ({}/{})", seg_passed, test_count);
-                    failed += 1;
-                }
-            }
-            Err(e) => {
-                println!("FAIL ({})", e);
-                failed += 1;
+        for r in results.iter().filter(|r| !r.issues.is_empty()) {
+            println!("\n  {} ({}/{} segments):", r.id, r.segments_passed, r.segments_tested);
+            for issue in &r.issues {
+                println!("    - {}", issue);
             }
         }
-    }
 
-    println!("\nResults: {} passed, {} failed", passed, failed);
+        println!("\nResults: {} passed, {} warned, {} failed", passed, warned, failed);
+    }
 
     if failed > 0 {
         std::process::exit(1);
@@ -108,21 +168,42 @@ pub async fn validate(
     Ok(())
 }
 
+/// Format a bitrate in bits per second for display
+fn format_bitrate(bps: u64) -> String {
+    if bps >= 1_000_000 {
+        format!("{:.2}Mbps", bps as f64 / 1_000_000.0)
+    } else {
+        format!("{}kbps", bps / 1000)
+    }
+}
+
+/// Format a byte count for display
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1_000_000 {
+        format!("{:.1}MB", bytes as f64 / 1_000_000.0)
+    } else {
+        format!("{:.1}KB", bytes as f64 / 1000.0)
+    }
+}
+
 /// Run QC checks
 pub async fn qc(
     manifest_url: &str,
     output: Option<PathBuf>,
     strict: bool,
+    key_headers: &[String],
+    checksum_template: Option<String>,
     _format: &str,
 ) -> anyhow::Result<()> {
     println!("Running QC on: {}", manifest_url);
 
     let url = Url::parse(manifest_url)?;
-    let parser = create_parser(&url);
+    let fetcher: Arc<dyn HttpFetcher> = Arc::new(ReqwestFetcher::default());
+    let parser = create_parser_with(&url, fetcher.clone());
     let manifest = parser.parse(&url).await?;
 
     let mut warnings: Vec<&str> = Vec::new();
-    let errors: Vec<&str> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
 
     // Check: Must have at least 2 renditions for ABR
     if manifest.renditions.len() < 2 {
@@ -154,8 +235,49 @@ pub async fn qc(
         warnings.push("No low-bitrate rendition for mobile");
     }
 
+    // Check: The first segment of each rendition must download intact,
+    // and decrypt with its playlist key when encrypted
+    let decryptor = SegmentDecryptor::with_drm_config(fetcher.clone(), &key_drm_config(key_headers)?);
+    let verifier = SegmentVerifier::new(fetcher.clone(), SegmentIntegrity {
+        policy: IntegrityPolicy::Strict,
+        checksum_url_template: checksum_template,
+        ..Default::default()
+    });
+    let mut corrupt_segments = Vec::new();
+    for rendition in &manifest.renditions {
+        let segments = parser.parse_variant(&rendition.uri).await.unwrap_or_default();
+        let Some(segment) = segments.first() else {
+            continue;
+        };
+
+        let method = segment.encryption.as_ref().map(|e| e.method);
+        if matches!(method, Some(EncryptionMethod::SampleAes | EncryptionMethod::SampleAesCtr))
+            && !warnings.contains(&"SAMPLE-AES segments can't be verified without a CDM")
+        {
+            warnings.push("SAMPLE-AES segments can't be verified without a CDM");
+        }
+
+        match check_segment(fetcher.as_ref(), &verifier, &decryptor, segment).await {
+            Ok(()) => {}
+            Err(Error::SegmentIntegrity { failure, .. }) => {
+                errors.push(format!("Segment {} is corrupt: {}", segment.uri, failure));
+                corrupt_segments.push(serde_json::json!({
+                    "rendition": rendition.id,
+                    "uri": segment.uri.as_str(),
+                    "kind": failure.kind(),
+                    "detail": failure.to_string(),
+                }));
+            }
+            Err(e) if method == Some(EncryptionMethod::Aes128) => {
+                errors.push(format!("Encrypted segment {} could not be decrypted: {}", segment.uri, e));
+            }
+            Err(e) => errors.push(format!("Segment {} could not be fetched: {}", segment.uri, e)),
+        }
+    }
+
     println!("\nQC Report:");
     println!("  Renditions: {}", manifest.renditions.len());
+    println!("  Corrupt segments: {}", corrupt_segments.len());
     println!("  Errors: {}", errors.len());
     println!("  Warnings: {}", warnings.len());
 
@@ -178,6 +300,7 @@ pub async fn qc(
         let report = serde_json::json!({
             "url": manifest_url,
             "renditions": manifest.renditions.len(),
+            "corrupt_segments": corrupt_segments,
             "errors": errors,
             "warnings": warnings,
         });
@@ -230,30 +353,91 @@ pub async fn extract(manifest_url: &str, what: &str, _format: &str) -> anyhow::R
     Ok(())
 }
 
-/// Compare two streams
-pub async fn compare(manifest1: &str, manifest2: &str, _format: &str) -> anyhow::Result<()> {
-    println!("Comparing streams:");
-    println!("  1: {}", manifest1);
-    println!("  2: {}", manifest2);
+/// Fetch a manifest and all of its renditions' media playlists
+async fn snapshot(manifest_url: &str) -> anyhow::Result<StreamSnapshot> {
+    let url = Url::parse(manifest_url)?;
+    let parser = create_parser(&url);
+    let manifest = parser.parse(&url).await?;
+    let hls = (detect_manifest_type(&url, None) == ManifestType::Hls).then(HlsParser::new);
+
+    let mut playlists = Vec::new();
+    for rendition in &manifest.renditions {
+        let playlist = match &hls {
+            Some(hls) => hls.parse_variant_playlist(&rendition.uri).await.map(|p| Playlist {
+                segments: p.segments,
+                target_duration: p.target_duration,
+                is_live: p.is_live,
+            }),
+            None => parser.parse_variant(&rendition.uri).await.map(|segments| Playlist {
+                segments,
+                target_duration: manifest.target_duration,
+                is_live: manifest.is_live,
+            }),
+        };
+        playlists.push(playlist.map_err(|e| e.to_string()));
+    }
 
-    let url1 = Url::parse(manifest1)?;
-    let url2 = Url::parse(manifest2)?;
+    Ok(StreamSnapshot { manifest, playlists })
+}
 
-    let parser1 = create_parser(&url1);
-    let parser2 = create_parser(&url2);
+/// Compare two streams
+///
+/// Exits with status 1 when a difference at or above `fail_on` is found.
+pub async fn compare(
+    manifest1: &str,
+    manifest2: &str,
+    options: CompareOptions,
+    fail_on: &str,
+    format: &str,
+) -> anyhow::Result<()> {
+    let json = format == "json";
+    let fail_on = Severity::parse(fail_on)
+        .ok_or_else(|| anyhow::anyhow!("Unknown severity '{}', expected info, warning or error", fail_on))?;
+
+    if !json {
+        println!("Comparing streams:");
+        println!("  1: {}", manifest1);
+        println!("  2: {}", manifest2);
+    }
 
-    let m1 = parser1.parse(&url1).await?;
-    let m2 = parser2.parse(&url2).await?;
+    // Live windows slide, so both streams are fetched at the same moment
+    let (a, b) = tokio::join!(snapshot(manifest1), snapshot(manifest2));
+    let (a, b) = (a?, b?);
+    let report = compare::compare(manifest1, &a, manifest2, &b, &options);
 
-    println!("\nComparison:");
-    println!("  {:20} {:>15} {:>15}", "Property", "Stream 1", "Stream 2");
-    println!("  {:20} {:>15} {:>15}", "Type", format!("{:?}", m1.manifest_type), format!("{:?}", m2.manifest_type));
-    println!("  {:20} {:>15} {:>15}", "Live", m1.is_live, m2.is_live);
-    println!("  {:20} {:>15} {:>15}", "Renditions", m1.renditions.len(), m2.renditions.len());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let (m1, m2) = (&a.manifest, &b.manifest);
+        println!("\nComparison:");
+        println!("  {:20} {:>15} {:>15}", "Property", "Stream 1", "Stream 2");
+        println!("  {:20} {:>15} {:>15}", "Type", format!("{:?}", m1.manifest_type), format!("{:?}", m2.manifest_type));
+        println!("  {:20} {:>15} {:>15}", "Live", a.is_live(), b.is_live());
+        println!("  {:20} {:>15} {:>15}", "Renditions", m1.renditions.len(), m2.renditions.len());
+        println!("  {:20} {:>15} {:>15}", "Audio tracks", m1.audio_tracks.len(), m2.audio_tracks.len());
+        println!("  {:20} {:>15} {:>15}", "Text tracks", m1.text_tracks.len(), m2.text_tracks.len());
+
+        let max_br1 = m1.renditions.iter().map(|r| r.bandwidth).max().unwrap_or(0);
+        let max_br2 = m2.renditions.iter().map(|r| r.bandwidth).max().unwrap_or(0);
+        println!("  {:20} {:>15} {:>15}", "Max Bitrate", format_bitrate(max_br1), format_bitrate(max_br2));
+        println!("  {:20} {:>15}", "Matched renditions", report.matched_renditions);
+
+        if report.differences.is_empty() {
+            println!("\nNo differences.");
+        } else {
+            println!("\nDifferences:");
+            for d in &report.differences {
+                let rendition = d.rendition.as_deref().map(|r| format!("{}: ", r)).unwrap_or_default();
+                println!("  {:7} {:22} {}{}", d.severity.as_str(), d.kind.as_str(), rendition, d.message);
+            }
+        }
 
-    let max_br1 = m1.renditions.iter().map(|r| r.bandwidth).max().unwrap_or(0);
-    let max_br2 = m2.renditions.iter().map(|r| r.bandwidth).max().unwrap_or(0);
-    println!("  {:20} {:>15} {:>15}", "Max Bitrate", format!("{}Mbps", max_br1 / 1_000_000), format!("{}Mbps", max_br2 / 1_000_000));
+        println!("\nResults: {} errors, {} warnings, {} info", report.errors, report.warnings, report.info);
+    }
+
+    if report.fails(fail_on) {
+        std::process::exit(1);
+    }
 
     Ok(())
 }
@@ -263,58 +447,151 @@ pub async fn monitor(
     manifest_url: &str,
     interval: u64,
     duration: u64,
-    _format: &str,
+    thresholds: MonitorThresholds,
+    alert_webhook: Option<&str>,
+    csv_path: Option<&Path>,
+    format: &str,
 ) -> anyhow::Result<()> {
-    println!("Monitoring: {}", manifest_url);
-    println!("  Interval: {}s", interval);
-    println!("  Duration: {}", if duration == 0 { "indefinite".to_string() } else { format!("{}s", duration) });
+    let json = format == "json";
+    if !json {
+        println!("Monitoring: {}", manifest_url);
+        println!("  Interval: {}s", interval);
+        println!("  Duration: {}", if duration == 0 { "indefinite".to_string() } else { format!("{}s", duration) });
+    }
 
     let url = Url::parse(manifest_url)?;
     let parser = create_parser(&url);
+    let hls = (detect_manifest_type(&url, None) == ManifestType::Hls).then(HlsParser::new);
+    let client = reqwest::Client::new();
 
+    let mut state = MonitorState::new(manifest_url, thresholds);
     let start = std::time::Instant::now();
-    let mut last_sequence = 0u64;
+
+    let mut csv = csv_path
+        .map(|path| {
+            let file = std::fs::File::create(path)?;
+            CsvWriter::new(file, &["timestamp", "elapsed_secs", "healthy", "renditions", "new_segments", "alerts"])
+        })
+        .transpose()?;
+
+    let emit = |alert: &Alert| {
+        if json {
+            println!("{}", serde_json::to_string(alert).unwrap_or_default());
+        } else {
+            println!("[{}] ALERT {}{}: {}",
+                alert.timestamp.format("%H:%M:%S"),
+                alert.kind.as_str(),
+                alert.rendition.as_deref().map(|r| format!(" ({})", r)).unwrap_or_default(),
+                alert.message
+            );
+        }
+    };
+
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
 
     loop {
         // Check duration limit
         if duration > 0 && start.elapsed().as_secs() >= duration {
-            println!("\nMonitoring complete.");
             break;
         }
 
+        let mut alerts = Vec::new();
+        let mut fetched = true;
+        let mut renditions = 0;
+        let mut new_segments = 0;
+
         // Fetch and check manifest
         match parser.parse(&url).await {
             Ok(manifest) => {
-                if manifest.is_live {
-                    // For live, check for new segments
-                    if let Some(r) = manifest.renditions.first() {
-                        if let Ok(segments) = parser.parse_variant(&r.uri).await {
-                            let max_seq = segments.iter().map(|s| s.number).max().unwrap_or(0);
-                            if max_seq > last_sequence {
-                                println!("[{}] New segments: {} -> {}",
-                                    chrono::Utc::now().format("%H:%M:%S"),
-                                    last_sequence,
-                                    max_seq
-                                );
-                                last_sequence = max_seq;
+                alerts.extend(state.observe_master(&manifest.renditions));
+                renditions = manifest.renditions.len();
+
+                for rendition in &manifest.renditions {
+                    let playlist = match &hls {
+                        Some(hls) => hls.refresh_variant_playlist(&rendition.uri).await
+                            .map(|(p, freshness)| (p.is_live, p.segments, p.target_duration, p.discontinuity_sequence, freshness)),
+                        None => parser.refresh_segments(&rendition.uri, 0).await
+                            .map(|r| (manifest.is_live, r.segments, manifest.target_duration, 0, r.freshness)),
+                    };
+
+                    match playlist {
+                        // Finished playlists never advance, so only live ones are tracked
+                        Ok((false, ..)) => {}
+                        Ok((true, segments, target_duration, discontinuity_base, freshness)) => {
+                            let before = state.last_sequence(rendition);
+                            alerts.extend(state.observe_playlist(
+                                rendition, &segments, target_duration, discontinuity_base, &freshness,
+                            ));
+                            if let (Some(before), Some(after)) = (before, state.last_sequence(rendition)) {
+                                new_segments += after.saturating_sub(before);
                             }
                         }
+                        Err(e) => {
+                            fetched = false;
+                            alerts.push(state.observe_error(Some(&rendition.id), &e.to_string()));
+                        }
                     }
                 }
-                println!("[{}] OK - {} renditions",
-                    chrono::Utc::now().format("%H:%M:%S"),
-                    manifest.renditions.len()
-                );
+
+                if !json {
+                    println!("[{}] OK - {} renditions, {} new segments",
+                        chrono::Utc::now().format("%H:%M:%S"),
+                        manifest.renditions.len(),
+                        new_segments
+                    );
+                }
             }
             Err(e) => {
-                println!("[{}] ERROR: {}",
-                    chrono::Utc::now().format("%H:%M:%S"),
-                    e
-                );
+                fetched = false;
+                alerts.push(state.observe_error(None, &e.to_string()));
             }
         }
 
-        tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+        let healthy = state.finish_refresh(fetched);
+
+        if let Some(csv) = &mut csv {
+            csv.write_row([
+                chrono::Utc::now().to_rfc3339(),
+                format!("{:.1}", start.elapsed().as_secs_f64()),
+                healthy.to_string(),
+                renditions.to_string(),
+                new_segments.to_string(),
+                alerts.len().to_string(),
+            ])?;
+        }
+
+        for alert in &alerts {
+            emit(alert);
+            if let Some(webhook) = alert_webhook {
+                if let Err(e) = monitor::send_webhook(&client, webhook, alert).await {
+                    eprintln!("Failed to deliver alert to webhook: {}", e);
+                }
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(interval)) => {}
+            _ = &mut shutdown => break,
+        }
+    }
+
+    let summary = state.summary(start.elapsed());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        println!("\nMonitoring complete.");
+        println!("  Elapsed: {}s", summary.elapsed_secs);
+        println!("  Refreshes: {} ({} healthy)", summary.refreshes, summary.healthy_refreshes);
+        println!("  Uptime: {:.2}%", summary.uptime_percent);
+        if summary.incidents.is_empty() {
+            println!("  Incidents: none");
+        } else {
+            println!("  Incidents:");
+            for (kind, count) in &summary.incidents {
+                println!("    {}: {}", kind.as_str(), count);
+            }
+        }
     }
 
     Ok(())
@@ -0,0 +1,435 @@
+//! Stream comparison for packaging migrations
+//!
+//! Diffs two streams' rendition ladders, per-rendition segment timelines
+//! and alternate tracks. Renditions are matched by resolution and video
+//! codec rather than ID, since packagers name variants differently.
+//! Live playlists are aligned on EXT-X-PROGRAM-DATE-TIME when both carry
+//! it, otherwise on media sequence, so sliding windows fetched a moment
+//! apart don't show up as differences.
+
+use kino_core::manifest::Manifest;
+use kino_core::{AudioTrack, Rendition, Segment, TextTrack};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// How much a difference matters for the migration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "INFO",
+            Severity::Warning => "WARNING",
+            Severity::Error => "ERROR",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "info" => Some(Severity::Info),
+            "warning" | "warn" => Some(Severity::Warning),
+            "error" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+}
+
+/// What differs between the streams
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    ManifestType,
+    LiveMismatch,
+    RenditionMissing,
+    Bandwidth,
+    PlaylistUnavailable,
+    TargetDuration,
+    SegmentCount,
+    SegmentDuration,
+    SegmentAlignment,
+    AudioTrackMissing,
+    TextTrackMissing,
+}
+
+impl DiffKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiffKind::ManifestType => "manifest_type",
+            DiffKind::LiveMismatch => "live_mismatch",
+            DiffKind::RenditionMissing => "rendition_missing",
+            DiffKind::Bandwidth => "bandwidth",
+            DiffKind::PlaylistUnavailable => "playlist_unavailable",
+            DiffKind::TargetDuration => "target_duration",
+            DiffKind::SegmentCount => "segment_count",
+            DiffKind::SegmentDuration => "segment_duration",
+            DiffKind::SegmentAlignment => "segment_alignment",
+            DiffKind::AudioTrackMissing => "audio_track_missing",
+            DiffKind::TextTrackMissing => "text_track_missing",
+        }
+    }
+}
+
+/// A single difference
+#[derive(Debug, Clone, Serialize)]
+pub struct Difference {
+    pub severity: Severity,
+    pub kind: DiffKind,
+    /// Rendition the difference applies to, described by resolution,
+    /// codec and bandwidth
+    pub rendition: Option<String>,
+    pub message: String,
+}
+
+/// Tolerances below which values count as equal
+#[derive(Debug, Clone)]
+pub struct CompareOptions {
+    /// Allowed bandwidth difference between matched renditions, in percent
+    pub bandwidth_tolerance: f64,
+    /// Allowed segment duration and timing difference, in seconds
+    pub duration_tolerance: f64,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            bandwidth_tolerance: 10.0,
+            duration_tolerance: 0.1,
+        }
+    }
+}
+
+/// A rendition's media playlist at snapshot time
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    pub segments: Vec<Segment>,
+    pub target_duration: Duration,
+    pub is_live: bool,
+}
+
+/// One stream's manifest and playlists, fetched together
+#[derive(Debug, Clone)]
+pub struct StreamSnapshot {
+    pub manifest: Manifest,
+    /// Media playlist of each rendition, in manifest order
+    pub playlists: Vec<Result<Playlist, String>>,
+}
+
+impl StreamSnapshot {
+    /// HLS master playlists don't say; their media playlists do
+    pub fn is_live(&self) -> bool {
+        self.manifest.is_live || self.playlists.iter().flatten().any(|p| p.is_live)
+    }
+}
+
+/// Result of comparing two streams
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonReport {
+    pub manifest1: String,
+    pub manifest2: String,
+    /// Renditions found in both streams
+    pub matched_renditions: usize,
+    pub differences: Vec<Difference>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub info: usize,
+}
+
+impl ComparisonReport {
+    /// Whether any difference is at least as severe as `threshold`
+    pub fn fails(&self, threshold: Severity) -> bool {
+        self.differences.iter().any(|d| d.severity >= threshold)
+    }
+}
+
+/// Compare stream 2 against stream 1
+pub fn compare(
+    manifest1: &str,
+    a: &StreamSnapshot,
+    manifest2: &str,
+    b: &StreamSnapshot,
+    options: &CompareOptions,
+) -> ComparisonReport {
+    let mut diffs = Diffs::default();
+
+    if a.manifest.manifest_type != b.manifest.manifest_type {
+        diffs.push(Severity::Info, DiffKind::ManifestType, None, format!(
+            "{:?} vs {:?}", a.manifest.manifest_type, b.manifest.manifest_type
+        ));
+    }
+    if a.is_live() != b.is_live() {
+        diffs.push(Severity::Error, DiffKind::LiveMismatch, None, format!(
+            "stream 1 is {}, stream 2 is {}", live_str(a.is_live()), live_str(b.is_live())
+        ));
+    }
+
+    let pairs = match_renditions(&a.manifest.renditions, &b.manifest.renditions);
+    for (i, r) in a.manifest.renditions.iter().enumerate() {
+        if !pairs.iter().any(|&(ai, _)| ai == i) {
+            diffs.push(Severity::Error, DiffKind::RenditionMissing, Some(describe(r)), "only in stream 1".to_string());
+        }
+    }
+    for (j, r) in b.manifest.renditions.iter().enumerate() {
+        if !pairs.iter().any(|&(_, bj)| bj == j) {
+            diffs.push(Severity::Error, DiffKind::RenditionMissing, Some(describe(r)), "only in stream 2".to_string());
+        }
+    }
+
+    for &(i, j) in &pairs {
+        let (ra, rb) = (&a.manifest.renditions[i], &b.manifest.renditions[j]);
+        let label = describe(ra);
+
+        let delta = (rb.bandwidth as f64 - ra.bandwidth as f64) / ra.bandwidth.max(1) as f64 * 100.0;
+        if delta.abs() > options.bandwidth_tolerance {
+            diffs.push(Severity::Warning, DiffKind::Bandwidth, Some(label.clone()), format!(
+                "{} vs {} ({:+.1}%)", format_bitrate(ra.bandwidth), format_bitrate(rb.bandwidth), delta
+            ));
+        }
+
+        match (a.playlists.get(i), b.playlists.get(j)) {
+            (Some(Ok(pa)), Some(Ok(pb))) => compare_playlists(&label, pa, pb, options, &mut diffs),
+            (pa, pb) => {
+                for (stream, playlist) in [(1, pa), (2, pb)] {
+                    if let Some(Err(e)) = playlist {
+                        diffs.push(Severity::Error, DiffKind::PlaylistUnavailable, Some(label.clone()), format!(
+                            "stream {} playlist could not be fetched: {}", stream, e
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    compare_audio(&a.manifest.audio_tracks, &b.manifest.audio_tracks, &mut diffs);
+    compare_text(&a.manifest.text_tracks, &b.manifest.text_tracks, &mut diffs);
+
+    let differences = diffs.0;
+    let count = |severity: Severity| differences.iter().filter(|d| d.severity == severity).count();
+    ComparisonReport {
+        manifest1: manifest1.to_string(),
+        manifest2: manifest2.to_string(),
+        matched_renditions: pairs.len(),
+        errors: count(Severity::Error),
+        warnings: count(Severity::Warning),
+        info: count(Severity::Info),
+        differences,
+    }
+}
+
+#[derive(Default)]
+struct Diffs(Vec<Difference>);
+
+impl Diffs {
+    fn push(&mut self, severity: Severity, kind: DiffKind, rendition: Option<String>, message: String) {
+        self.0.push(Difference { severity, kind, rendition, message });
+    }
+}
+
+/// Pair renditions with the same resolution and video codec; when several
+/// share them, the closest bandwidths pair up
+fn match_renditions(a: &[Rendition], b: &[Rendition]) -> Vec<(usize, usize)> {
+    let key = |r: &Rendition| (r.resolution.map(|res| (res.width, res.height)), r.video_codec);
+
+    let mut candidates: Vec<(u64, usize, usize)> = Vec::new();
+    for (i, ra) in a.iter().enumerate() {
+        for (j, rb) in b.iter().enumerate() {
+            if key(ra) == key(rb) {
+                candidates.push((ra.bandwidth.abs_diff(rb.bandwidth), i, j));
+            }
+        }
+    }
+    candidates.sort();
+
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    for (_, i, j) in candidates {
+        if !pairs.iter().any(|&(pi, pj)| pi == i || pj == j) {
+            pairs.push((i, j));
+        }
+    }
+    pairs.sort();
+    pairs
+}
+
+fn compare_playlists(label: &str, a: &Playlist, b: &Playlist, options: &CompareOptions, diffs: &mut Diffs) {
+    let tolerance = options.duration_tolerance;
+    let rendition = || Some(label.to_string());
+
+    if a.target_duration != b.target_duration {
+        diffs.push(Severity::Warning, DiffKind::TargetDuration, rendition(), format!(
+            "{}s vs {}s", a.target_duration.as_secs_f64(), b.target_duration.as_secs_f64()
+        ));
+    }
+
+    let pairs = if a.is_live && b.is_live {
+        align_live(label, &a.segments, &b.segments, tolerance, diffs)
+    } else {
+        if a.segments.len() != b.segments.len() {
+            let total = |s: &[Segment]| s.iter().map(|s| s.duration.as_secs_f64()).sum::<f64>();
+            diffs.push(Severity::Warning, DiffKind::SegmentCount, rendition(), format!(
+                "{} vs {} segments ({:.3}s vs {:.3}s total)",
+                a.segments.len(), b.segments.len(), total(&a.segments), total(&b.segments)
+            ));
+        }
+        a.segments.iter().zip(&b.segments).collect()
+    };
+
+    let mismatched: Vec<_> = pairs
+        .iter()
+        .filter(|(sa, sb)| (sa.duration.as_secs_f64() - sb.duration.as_secs_f64()).abs() > tolerance)
+        .collect();
+    if let Some((sa, sb)) = mismatched.first() {
+        diffs.push(Severity::Warning, DiffKind::SegmentDuration, rendition(), format!(
+            "{} of {} compared segments differ in duration, first #{}: {:.3}s vs {:.3}s",
+            mismatched.len(), pairs.len(), sa.number, sa.duration.as_secs_f64(), sb.duration.as_secs_f64()
+        ));
+    }
+}
+
+/// Pair up segments of two live windows over the span both cover
+fn align_live<'a>(
+    label: &str,
+    a: &'a [Segment],
+    b: &'a [Segment],
+    tolerance: f64,
+    diffs: &mut Diffs,
+) -> Vec<(&'a Segment, &'a Segment)> {
+    let mut pairs = Vec::new();
+    let rendition = || Some(label.to_string());
+
+    let (Some(times_a), Some(times_b)) = (program_times(a), program_times(b)) else {
+        // Without wall-clock times, media sequence numbers line up
+        for sa in a {
+            if let Some(sb) = b.iter().find(|sb| sb.number == sa.number) {
+                pairs.push((sa, sb));
+            }
+        }
+        if pairs.is_empty() && !a.is_empty() && !b.is_empty() {
+            diffs.push(Severity::Warning, DiffKind::SegmentAlignment, rendition(),
+                "no media sequence numbers in common and no EXT-X-PROGRAM-DATE-TIME to align on".to_string());
+        }
+        return pairs;
+    };
+
+    let end = |segments: &[Segment], times: &[f64]| {
+        times.last().copied().unwrap_or(0.0) + segments.last().map_or(0.0, |s| s.duration.as_secs_f64())
+    };
+    let start = times_a[0].max(times_b[0]);
+    let stop = end(a, &times_a).min(end(b, &times_b));
+    if start >= stop {
+        diffs.push(Severity::Warning, DiffKind::SegmentAlignment, rendition(),
+            "playlist windows don't overlap in program date-time".to_string());
+        return pairs;
+    }
+
+    let mut unaligned = 0;
+    for (sa, &ta) in a.iter().zip(&times_a) {
+        // Only segments entirely within the shared span are comparable
+        if ta < start - tolerance || ta + sa.duration.as_secs_f64() > stop + tolerance {
+            continue;
+        }
+        match times_b.iter().position(|&tb| (tb - ta).abs() <= tolerance) {
+            Some(j) => pairs.push((sa, &b[j])),
+            None => unaligned += 1,
+        }
+    }
+    if unaligned > 0 {
+        diffs.push(Severity::Warning, DiffKind::SegmentAlignment, rendition(), format!(
+            "{} segments in the shared window start at times stream 2 has no segment boundary at",
+            unaligned
+        ));
+    }
+    pairs
+}
+
+/// Wall-clock start of each segment, in seconds since the epoch
+///
+/// EXT-X-PROGRAM-DATE-TIME applies to the segment it tags; untagged
+/// segments follow from their neighbours' durations.
+fn program_times(segments: &[Segment]) -> Option<Vec<f64>> {
+    let anchor = segments.iter().position(|s| s.program_date_time.is_some())?;
+    let seconds = |t: chrono::DateTime<chrono::Utc>| t.timestamp_millis() as f64 / 1000.0;
+    let mut times = vec![0.0; segments.len()];
+
+    let mut t = 0.0;
+    for (i, segment) in segments.iter().enumerate().skip(anchor) {
+        if let Some(pdt) = segment.program_date_time {
+            t = seconds(pdt);
+        }
+        times[i] = t;
+        t += segment.duration.as_secs_f64();
+    }
+
+    let mut t = times[anchor];
+    for i in (0..anchor).rev() {
+        t -= segments[i].duration.as_secs_f64();
+        times[i] = t;
+    }
+
+    Some(times)
+}
+
+fn compare_audio(a: &[AudioTrack], b: &[AudioTrack], diffs: &mut Diffs) {
+    let key = |t: &AudioTrack| (t.language.clone(), t.channels, t.is_audio_description);
+    let describe = |t: &AudioTrack| {
+        let channels = t.channels.map(|c| format!(", {}ch", c)).unwrap_or_default();
+        let description = if t.is_audio_description { ", audio description" } else { "" };
+        format!("{} ({}{}{})", t.label, t.language, channels, description)
+    };
+
+    for (stream, from, to) in [(1, a, b), (2, b, a)] {
+        let mut seen = HashSet::new();
+        for track in from {
+            if to.iter().any(|t| key(t) == key(track)) || !seen.insert(key(track)) {
+                continue;
+            }
+            // A missing language matters more than a missing variant of it
+            let severity = if to.iter().any(|t| t.language == track.language) { Severity::Warning } else { Severity::Error };
+            diffs.push(severity, DiffKind::AudioTrackMissing, None, format!(
+                "audio {} only in stream {}", describe(track), stream
+            ));
+        }
+    }
+}
+
+fn compare_text(a: &[TextTrack], b: &[TextTrack], diffs: &mut Diffs) {
+    // Captions moving between CEA-608 and WebVTT still count as present
+    let key = |t: &TextTrack| (t.language.clone(), t.kind, t.is_forced);
+    let describe = |t: &TextTrack| {
+        let forced = if t.is_forced { ", forced" } else { "" };
+        format!("{:?} {} ({}{})", t.kind, t.label, t.language, forced).to_lowercase()
+    };
+
+    for (stream, from, to) in [(1, a, b), (2, b, a)] {
+        let mut seen = HashSet::new();
+        for track in from {
+            if to.iter().any(|t| key(t) == key(track)) || !seen.insert(key(track)) {
+                continue;
+            }
+            let severity = if to.iter().any(|t| t.language == track.language) { Severity::Warning } else { Severity::Error };
+            diffs.push(severity, DiffKind::TextTrackMissing, None, format!(
+                "{} only in stream {}", describe(track), stream
+            ));
+        }
+    }
+}
+
+/// Resolution, codec and bandwidth, e.g. "1280x720 H.264/AVC 3.00Mbps"
+fn describe(r: &Rendition) -> String {
+    let resolution = r.resolution.map(|res| format!("{}x{}", res.width, res.height)).unwrap_or_else(|| "audio-only".to_string());
+    let codec = r.video_codec.map(|c| c.to_string()).unwrap_or_else(|| "unknown codec".to_string());
+    format!("{} {} {}", resolution, codec, format_bitrate(r.bandwidth))
+}
+
+fn format_bitrate(bps: u64) -> String {
+    format!("{:.2}Mbps", bps as f64 / 1_000_000.0)
+}
+
+fn live_str(is_live: bool) -> &'static str {
+    if is_live { "live" } else { "VOD" }
+}
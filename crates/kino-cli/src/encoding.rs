@@ -3,13 +3,20 @@
 //! Provides encoding commands for:
 //! - Transcoding video files to adaptive streaming formats
 //! - Generating HLS/DASH manifests
-//! - Applying Kino encoding presets
+//! - Applying Kino encoding presets or custom ladders
+//! - Muxing WebVTT subtitles and an audio-only rendition into HLS
+//! - Encoding HLS renditions in parallel, resuming interrupted runs
+//! - Burning a watermark and end card into the video renditions
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
 
+use crate::jobs::{self, EncodeJob, JobReport, JobStatus, PROGRESS_ARGS};
+use crate::ladder::{AudioSettings, VideoCodec};
+use crate::overlay::{OverlayInputs, Overlays};
+
 /// Kino encoding presets
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EncodingPreset {
@@ -93,18 +100,50 @@ impl EncodingPreset {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RenditionSpec {
     pub height: u32,
+    /// Explicit width; derived from the source aspect ratio when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
     pub bitrate: u32,
+    #[serde(default = "default_framerate")]
     pub framerate: u32,
+    #[serde(default)]
+    pub codec: VideoCodec,
+}
+
+fn default_framerate() -> u32 {
+    30
 }
 
 impl RenditionSpec {
     pub fn new(height: u32, bitrate: u32, framerate: u32) -> Self {
-        Self { height, bitrate, framerate }
+        Self { height, width: None, bitrate, framerate, codec: VideoCodec::H264 }
     }
 
     pub fn width(&self) -> u32 {
         // Calculate 16:9 width
-        (self.height as f64 * 16.0 / 9.0).round() as u32
+        self.width.unwrap_or_else(|| (self.height as f64 * 16.0 / 9.0).round() as u32)
+    }
+
+    /// Output width for a source of `source` dimensions, keeping its aspect
+    /// ratio unless the rung sets a width
+    pub fn output_width(&self, source: Option<(u32, u32)>) -> u32 {
+        match (self.width, source) {
+            (Some(width), _) => width,
+            (None, Some((w, h))) if w > 0 && h > 0 => {
+                ((w as f64 * self.height as f64 / h as f64 / 2.0).round() as u32 * 2).max(2)
+            }
+            _ => self.width() + self.width() % 2,
+        }
+    }
+
+    /// Output frame rate: the rung's, but never above the source's
+    pub fn output_framerate(&self, source_fps: u32) -> u32 {
+        if source_fps > 0 { self.framerate.min(source_fps) } else { self.framerate }
+    }
+
+    /// Variant name, used for playlist and segment file names
+    pub fn variant_name(&self) -> String {
+        format!("{}p_{}k", self.height, self.bitrate / 1000)
     }
 
     pub fn quality_name(&self) -> &'static str {
@@ -226,196 +265,533 @@ pub struct InputInfo {
     pub has_audio: bool,
 }
 
-/// Encode video to HLS
-pub fn encode_hls(
-    input: &Path,
-    output_dir: &Path,
-    preset: EncodingPreset,
-    segment_duration: f64,
-    _progress_callback: Option<Box<dyn Fn(f64)>>,
-) -> Result<()> {
-    let input_info = probe_input(input)?;
-    let renditions = preset.renditions();
+/// What to encode and how to package it
+#[derive(Debug, Clone)]
+pub struct EncodeOptions {
+    /// Description of the ladder, for progress output
+    pub label: String,
+    pub renditions: Vec<RenditionSpec>,
+    pub segment_duration: f64,
+    pub audio: AudioSettings,
+    /// WebVTT file muxed into HLS as a subtitle group
+    pub subtitles: Option<PathBuf>,
+    /// Also list the audio rendition as an audio-only HLS variant
+    pub audio_only: bool,
+    /// Watermark and end card composited onto the video rungs
+    pub overlays: Overlays,
+    /// Print the FFmpeg command(s) and playlists instead of encoding
+    pub dry_run: bool,
+    /// Renditions encoded at the same time
+    pub jobs: usize,
+    /// Re-encode renditions whose output from an earlier run is complete
+    pub force: bool,
+}
 
-    std::fs::create_dir_all(output_dir)?;
+impl EncodeOptions {
+    pub fn from_preset(preset: EncodingPreset, segment_duration: f64) -> Self {
+        Self {
+            label: format!("{} preset", preset.description()),
+            renditions: preset.renditions(),
+            segment_duration,
+            audio: AudioSettings::default(),
+            subtitles: None,
+            audio_only: false,
+            overlays: Overlays::default(),
+            dry_run: false,
+            jobs: 2,
+            force: false,
+        }
+    }
+}
 
-    println!("Encoding to HLS with {} preset", preset.description());
-    println!("Input: {}x{} @ {}fps, {:.1}s",
-        input_info.width, input_info.height, input_info.framerate, input_info.duration);
+/// FFmpeg invocations and playlists for an HLS encode
+#[derive(Debug)]
+pub struct HlsPlan {
+    /// One job per video rung, then the audio rendition
+    pub jobs: Vec<EncodeJob>,
+    /// Multivariant playlist, written as `master.m3u8`
+    pub master_playlist: String,
+    /// Subtitle media playlist, written as `subtitles.m3u8`
+    pub subtitle_playlist: Option<String>,
+}
 
-    // Build FFmpeg command for multi-rendition HLS
-    let mut args: Vec<String> = vec![
-        "-i".to_string(),
-        input.to_string_lossy().to_string(),
-        "-y".to_string(),  // Overwrite
-    ];
+/// Audio rendition name, used for its job and playlist
+const AUDIO_VARIANT: &str = "audio";
+/// Audio and subtitle group IDs in the multivariant playlist
+const AUDIO_GROUP: &str = "audio";
+const SUBTITLE_GROUP: &str = "subs";
+
+/// Probe the input; dry runs fall back to an 8K source with audio so the
+/// command can still be shown without FFprobe or the input file
+fn source_info(input: &Path, dry_run: bool) -> Result<InputInfo> {
+    match probe_input(input) {
+        Ok(info) => Ok(info),
+        Err(e) if dry_run => {
+            println!("Could not probe input ({:#}); assuming an 8K source with audio", e);
+            Ok(InputInfo { width: 7680, height: 4320, framerate: 30, duration: 0.0, has_audio: true })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Renditions that fit the source, skipping those taller than it
+fn select_renditions<'a>(options: &'a EncodeOptions, info: &InputInfo) -> Result<Vec<&'a RenditionSpec>> {
+    let selected: Vec<&RenditionSpec> = options.renditions.iter()
+        .filter(|r| r.height <= info.height)
+        .collect();
 
-    // Filter complex for scaling
-    let mut filter_complex = String::new();
+    if selected.is_empty() {
+        bail!("Source resolution is lower than all ladder renditions");
+    }
+    Ok(selected)
+}
+
+/// Scale and overlay filter graph and per-stream encoder options for the
+/// video rungs; overlay images are read from `inputs`
+fn video_args(
+    renditions: &[&RenditionSpec],
+    info: &InputInfo,
+    overlays: &Overlays,
+    inputs: &OverlayInputs,
+) -> (String, Vec<String>) {
+    let source = Some((info.width, info.height));
+    let mut filters: Vec<String> = Vec::new();
     let mut map_args: Vec<String> = Vec::new();
-    let mut stream_map = String::new();
 
+    // Output stream indices count only the renditions actually encoded
     for (i, r) in renditions.iter().enumerate() {
-        // Skip renditions higher than source
-        if r.height > input_info.height {
-            continue;
+        let fps = r.output_framerate(info.framerate);
+        let width = r.output_width(source);
+        match overlays.filter(inputs, i, width, r.height, info.duration) {
+            Some(overlay) => filters.push(format!("[0:v]scale={}:{}[s{}];{}", width, r.height, i, overlay)),
+            None => filters.push(format!("[0:v]scale={}:{}[v{}]", width, r.height, i)),
         }
 
-        // Scale filter
-        filter_complex.push_str(&format!(
-            "[0:v]scale={}:{}:force_original_aspect_ratio=decrease[v{}];",
-            r.width(), r.height, i
-        ));
-
-        // Video output
         map_args.extend([
             "-map".to_string(), format!("[v{}]", i),
-            format!("-c:v:{}", i), "libx264".to_string(),
+            format!("-c:v:{}", i), r.codec.encoder().to_string(),
             format!("-b:v:{}", i), format!("{}", r.bitrate),
             format!("-maxrate:v:{}", i), format!("{}", (r.bitrate as f64 * 1.1) as u32),
             format!("-bufsize:v:{}", i), format!("{}", r.bitrate * 2),
-            format!("-preset:v:{}", i), "medium".to_string(),
-            format!("-g:v:{}", i), format!("{}", r.framerate * 2),  // GOP size
-            format!("-keyint_min:v:{}", i), format!("{}", r.framerate),
+            format!("-g:v:{}", i), format!("{}", fps * 2),  // GOP size
+            format!("-keyint_min:v:{}", i), format!("{}", fps),
         ]);
-
-        // Audio (copy to all variants)
-        if input_info.has_audio {
-            map_args.extend([
-                "-map".to_string(), "0:a".to_string(),
-                format!("-c:a:{}", i), "aac".to_string(),
-                format!("-b:a:{}", i), "128k".to_string(),
-            ]);
+        if fps < info.framerate {
+            map_args.extend([format!("-r:v:{}", i), format!("{}", fps)]);
         }
 
-        if !stream_map.is_empty() {
-            stream_map.push(' ');
+        match r.codec {
+            VideoCodec::H264 => {
+                let level = crate::ladder::h264_level(r.height);
+                map_args.extend([
+                    format!("-preset:v:{}", i), "medium".to_string(),
+                    format!("-profile:v:{}", i), "high".to_string(),
+                    format!("-level:v:{}", i), format!("{}.{}", level / 10, level % 10),
+                ]);
+            }
+            VideoCodec::Hevc => map_args.extend([
+                format!("-preset:v:{}", i), "medium".to_string(),
+                format!("-tag:v:{}", i), "hvc1".to_string(),
+            ]),
+            // SVT-AV1 presets are numeric; 8 is a real-time-ish default
+            VideoCodec::Av1 => map_args.extend([
+                format!("-preset:v:{}", i), "8".to_string(),
+            ]),
         }
-        stream_map.push_str(&format!("v:{},a:{}", i, i));
     }
 
-    if filter_complex.is_empty() {
-        bail!("Source resolution is lower than all preset renditions");
+    (filters.join(";"), map_args)
+}
+
+/// Encoder options for the single shared audio rendition
+fn audio_args(audio: &AudioSettings) -> Vec<String> {
+    vec![
+        "-map".to_string(), "0:a:0".to_string(),
+        "-c:a:0".to_string(), audio.codec.encoder().to_string(),
+        "-b:a:0".to_string(), format!("{}", audio.bitrate),
+    ]
+}
+
+/// Build the FFmpeg jobs and playlists for an HLS encode.
+///
+/// Each video rung and the audio rendition is a separate FFmpeg job
+/// writing its own media playlist, so rungs can be encoded in parallel
+/// and an interrupted encode resumed; the multivariant playlist ties them
+/// together with an audio group, the optional subtitle group and the
+/// optional audio-only variant.
+pub fn plan_hls(input: &Path, output_dir: &Path, info: &InputInfo, options: &EncodeOptions) -> Result<HlsPlan> {
+    let renditions = select_renditions(options, info)?;
+    if options.audio_only && !info.has_audio {
+        bail!("--audio-only needs an input with an audio stream");
+    }
+    let subtitles = options.subtitles.as_deref().map(read_webvtt).transpose()?;
+    options.overlays.check_duration(info.duration)?;
+
+    let mut jobs: Vec<EncodeJob> = renditions.iter()
+        .map(|r| {
+            let inputs = options.overlays.inputs(&[r.height]);
+            let (filter_complex, map_args) = video_args(&[r], info, &options.overlays, &inputs);
+            let mut args = vec!["-filter_complex".to_string(), filter_complex];
+            args.extend(map_args);
+            hls_job(input, &inputs, output_dir, &r.variant_name(), args, r.codec.needs_fmp4(), options.segment_duration)
+        })
+        .collect();
+    // The audio rendition never reads the overlay images
+    if info.has_audio {
+        let mut args = audio_args(&options.audio);
+        args.push("-vn".to_string());
+        let no_overlays = OverlayInputs::default();
+        jobs.push(hls_job(input, &no_overlays, output_dir, AUDIO_VARIANT, args, options.audio.codec.needs_fmp4(), options.segment_duration));
     }
 
-    // Remove trailing semicolon
-    filter_complex.pop();
+    let fmp4 = renditions.iter().any(|r| r.codec.needs_fmp4())
+        || (info.has_audio && options.audio.codec.needs_fmp4());
+    let subtitle_name = options.subtitles.as_deref()
+        .and_then(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().replace('"', "'"))
+        .unwrap_or_else(|| "Subtitles".to_string());
+    let master_playlist = master_playlist(&renditions, info, options, fmp4, subtitles.is_some().then_some(subtitle_name.as_str()));
+    let subtitle_playlist = subtitles.map(|text| subtitle_playlist(info.duration.max(webvtt_end_secs(&text))));
 
-    args.extend([
-        "-filter_complex".to_string(),
-        filter_complex,
-    ]);
-    args.extend(map_args);
+    Ok(HlsPlan { jobs, master_playlist, subtitle_playlist })
+}
+
+/// FFmpeg job encoding one rendition into `stream_<name>.m3u8`
+fn hls_job(
+    input: &Path,
+    overlay_inputs: &OverlayInputs,
+    output_dir: &Path,
+    name: &str,
+    stream_args: Vec<String>,
+    fmp4: bool,
+    segment_duration: f64,
+) -> EncodeJob {
+    let mut args: Vec<String> = vec![
+        "-i".to_string(),
+        input.to_string_lossy().to_string(),
+    ];
+    args.extend(overlay_inputs.args());
+    args.push("-y".to_string());  // Overwrite
+    args.extend(PROGRESS_ARGS.iter().map(|arg| arg.to_string()));
+    args.extend(stream_args);
 
     // HLS options
     args.extend([
         "-f".to_string(), "hls".to_string(),
         "-hls_time".to_string(), format!("{}", segment_duration as u32),
         "-hls_playlist_type".to_string(), "vod".to_string(),
+        "-hls_flags".to_string(), "independent_segments".to_string(),
+    ]);
+    if fmp4 {
+        args.extend([
+            "-hls_segment_type".to_string(), "fmp4".to_string(),
+            "-hls_fmp4_init_filename".to_string(), format!("init_{}.mp4", name),
+        ]);
+    }
+
+    let segment_ext = if fmp4 { "m4s" } else { "ts" };
+    let output = output_dir.join(format!("stream_{}.m3u8", name));
+    args.extend([
         "-hls_segment_filename".to_string(),
-        output_dir.join("stream_%v_%03d.ts").to_string_lossy().to_string(),
-        "-master_pl_name".to_string(), "master.m3u8".to_string(),
-        "-var_stream_map".to_string(), stream_map,
-        output_dir.join("stream_%v.m3u8").to_string_lossy().to_string(),
+        output_dir.join(format!("stream_{}_%03d.{}", name, segment_ext)).to_string_lossy().to_string(),
+        output.to_string_lossy().to_string(),
     ]);
 
-    println!("Running FFmpeg...");
+    EncodeJob { name: name.to_string(), args, output }
+}
 
-    let status = Command::new("ffmpeg")
-        .args(&args)
-        .status()
-        .context("FFmpeg execution failed")?;
+/// Multivariant playlist over the planned media playlists
+fn master_playlist(
+    renditions: &[&RenditionSpec],
+    info: &InputInfo,
+    options: &EncodeOptions,
+    fmp4: bool,
+    subtitle_name: Option<&str>,
+) -> String {
+    let mut lines = vec![
+        "#EXTM3U".to_string(),
+        format!("#EXT-X-VERSION:{}", if fmp4 { 7 } else { 3 }),
+        "#EXT-X-INDEPENDENT-SEGMENTS".to_string(),
+    ];
 
-    if !status.success() {
-        bail!("FFmpeg encoding failed");
+    let audio_bitrate = if info.has_audio { options.audio.bitrate } else { 0 };
+    let mut groups = String::new();
+    if info.has_audio {
+        lines.push(format!(
+            "#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"{}\",NAME=\"Audio\",DEFAULT=YES,AUTOSELECT=YES,URI=\"stream_{}.m3u8\"",
+            AUDIO_GROUP, AUDIO_VARIANT
+        ));
+        groups.push_str(&format!(",AUDIO=\"{}\"", AUDIO_GROUP));
+    }
+    if let Some(name) = subtitle_name {
+        lines.push(format!(
+            "#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"{}\",NAME=\"{}\",DEFAULT=NO,AUTOSELECT=YES,URI=\"subtitles.m3u8\"",
+            SUBTITLE_GROUP, name
+        ));
+        groups.push_str(&format!(",SUBTITLES=\"{}\"", SUBTITLE_GROUP));
+    }
+    lines.push(String::new());
+
+    let source = Some((info.width, info.height));
+    for r in renditions {
+        let mut codecs = r.codec.codec_string(r.height);
+        if info.has_audio {
+            codecs.push(',');
+            codecs.push_str(options.audio.codec.codec_string());
+        }
+        lines.push(format!(
+            "#EXT-X-STREAM-INF:BANDWIDTH={},AVERAGE-BANDWIDTH={},CODECS=\"{}\",RESOLUTION={}x{},FRAME-RATE={:.3}{}",
+            (r.bitrate as f64 * 1.1) as u32 + audio_bitrate,
+            r.bitrate + audio_bitrate,
+            codecs,
+            r.output_width(source),
+            r.height,
+            r.output_framerate(info.framerate) as f64,
+            groups
+        ));
+        lines.push(format!("stream_{}.m3u8", r.variant_name()));
     }
 
-    println!("HLS encoding complete!");
-    println!("Output: {}", output_dir.display());
-    println!("Master playlist: {}", output_dir.join("master.m3u8").display());
+    if options.audio_only {
+        lines.push(format!(
+            "#EXT-X-STREAM-INF:BANDWIDTH={},AVERAGE-BANDWIDTH={},CODECS=\"{}\"{}",
+            (audio_bitrate as f64 * 1.1) as u32,
+            audio_bitrate,
+            options.audio.codec.codec_string(),
+            groups
+        ));
+        lines.push(format!("stream_{}.m3u8", AUDIO_VARIANT));
+    }
 
-    Ok(())
+    lines.join("\n") + "\n"
 }
 
-/// Encode video to DASH
-pub fn encode_dash(
+/// Read a WebVTT file, checking its header
+fn read_webvtt(path: &Path) -> Result<String> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read subtitles {}", path.display()))?;
+    if !text.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+        bail!("{} is not a WebVTT file (missing WEBVTT header)", path.display());
+    }
+    Ok(text)
+}
+
+/// End time of the last cue in a WebVTT file, in seconds
+fn webvtt_end_secs(text: &str) -> f64 {
+    text.lines()
+        .filter_map(|line| line.split_once("-->"))
+        .filter_map(|(_, end)| end.split_whitespace().next())
+        .filter_map(parse_webvtt_timestamp)
+        .fold(0.0, f64::max)
+}
+
+/// Parse `hh:mm:ss.ttt` or `mm:ss.ttt`
+fn parse_webvtt_timestamp(s: &str) -> Option<f64> {
+    s.split(':').try_fold(0.0, |secs, part| Some(secs * 60.0 + part.parse::<f64>().ok()?))
+}
+
+/// Media playlist serving the whole subtitle file as one segment
+fn subtitle_playlist(duration: f64) -> String {
+    format!(
+        "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:{}\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n#EXTINF:{:.3},\nsubtitles.vtt\n#EXT-X-ENDLIST\n",
+        duration.ceil().max(1.0) as u64,
+        duration
+    )
+}
+
+/// Print each filter chain of an FFmpeg command's filter graph on its own
+/// line, so overlay placement and timing can be checked
+fn print_filters(args: &[String]) {
+    let graph = args.iter()
+        .position(|arg| arg == "-filter_complex")
+        .and_then(|i| args.get(i + 1));
+    for chain in graph.into_iter().flat_map(|graph| graph.split(';')) {
+        println!("#   {}", chain);
+    }
+}
+
+/// Print an FFmpeg command line, quoted for a POSIX shell
+fn print_command(args: &[String]) {
+    let quoted: Vec<String> = args.iter()
+        .map(|arg| {
+            let plain = !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,%+@".contains(c));
+            if plain { arg.clone() } else { format!("'{}'", arg.replace('\'', "'\\''")) }
+        })
+        .collect();
+    println!("ffmpeg {}", quoted.join(" "));
+}
+
+/// Encode video to HLS.
+///
+/// Renditions are encoded `options.jobs` at a time with a progress bar
+/// each. Unless `options.force` is set, renditions whose playlist and
+/// segments from an earlier run cover the whole source are kept. The
+/// multivariant playlist is only written once every rendition is done;
+/// otherwise the failed rungs are listed and an error returned, and a
+/// re-run retries just those.
+pub fn encode_hls(
     input: &Path,
     output_dir: &Path,
-    preset: EncodingPreset,
-    segment_duration: f64,
+    options: &EncodeOptions,
+    _progress_callback: Option<Box<dyn Fn(f64)>>,
 ) -> Result<()> {
-    let input_info = probe_input(input)?;
-    let renditions = preset.renditions();
+    let input_info = source_info(input, options.dry_run)?;
 
-    std::fs::create_dir_all(output_dir)?;
+    println!("Encoding to HLS with {}", options.label);
+    println!("Input: {}x{} @ {}fps, {:.1}s",
+        input_info.width, input_info.height, input_info.framerate, input_info.duration);
 
-    println!("Encoding to DASH with {} preset", preset.description());
+    let plan = plan_hls(input, output_dir, &input_info, options)?;
 
-    // For DASH, we encode to fragmented MP4 first, then use MP4Box or ffmpeg dash muxer
-    let mut args: Vec<String> = vec![
-        "-i".to_string(),
-        input.to_string_lossy().to_string(),
-        "-y".to_string(),
-    ];
+    if options.dry_run {
+        for job in &plan.jobs {
+            println!("\n# {}", job.name);
+            if !options.overlays.is_empty() {
+                print_filters(&job.args);
+            }
+            print_command(&job.args);
+        }
+        println!("\n# {}", output_dir.join("master.m3u8").display());
+        print!("{}", plan.master_playlist);
+        if let Some(playlist) = &plan.subtitle_playlist {
+            println!("\n# {}", output_dir.join("subtitles.m3u8").display());
+            print!("{}", playlist);
+        }
+        return Ok(());
+    }
 
-    // Filter complex for scaling
-    let mut filter_complex = String::new();
-    let mut map_args: Vec<String> = Vec::new();
+    std::fs::create_dir_all(output_dir)?;
 
-    for (i, r) in renditions.iter().enumerate() {
-        if r.height > input_info.height {
+    // Keep renditions a previous run finished
+    let mut pending: Vec<EncodeJob> = Vec::new();
+    let mut kept: Vec<JobReport> = Vec::new();
+    for job in &plan.jobs {
+        if options.force {
+            pending.push(job.clone());
             continue;
         }
+        match jobs::check_hls_complete(&job.output, input_info.duration, options.segment_duration) {
+            Ok(()) => kept.push(JobReport::skipped(&job.name)),
+            Err(_) => pending.push(job.clone()),
+        }
+    }
+    if !kept.is_empty() {
+        println!("Keeping {} complete rendition(s) from an earlier run (use --force to re-encode)", kept.len());
+    }
 
-        filter_complex.push_str(&format!(
-            "[0:v]scale={}:{}:force_original_aspect_ratio=decrease[v{}];",
-            r.width(), r.height, i
-        ));
+    println!("Running FFmpeg ({} rendition(s), {} at a time)...", pending.len(), options.jobs.max(1));
+    let mut encoded = jobs::run_jobs(&pending, input_info.duration, options.jobs).into_iter();
+
+    // Summary in plan order
+    let reports: Vec<JobReport> = plan.jobs.iter()
+        .filter_map(|job| match kept.iter().position(|r| r.name == job.name) {
+            Some(i) => Some(kept[i].clone()),
+            None => encoded.next(),
+        })
+        .collect();
+    if !jobs::print_summary(&reports) {
+        let failed: Vec<&str> = reports.iter()
+            .filter(|r| matches!(r.status, JobStatus::Failed(_)))
+            .map(|r| r.name.as_str())
+            .collect();
+        bail!("HLS encoding failed for {}; re-run to retry them", failed.join(", "));
+    }
 
-        map_args.extend([
-            "-map".to_string(), format!("[v{}]", i),
-            format!("-c:v:{}", i), "libx264".to_string(),
-            format!("-b:v:{}", i), format!("{}", r.bitrate),
-            format!("-preset:v:{}", i), "medium".to_string(),
-        ]);
+    std::fs::write(output_dir.join("master.m3u8"), &plan.master_playlist)?;
+    if let (Some(playlist), Some(subtitles)) = (&plan.subtitle_playlist, &options.subtitles) {
+        std::fs::copy(subtitles, output_dir.join("subtitles.vtt"))
+            .with_context(|| format!("Failed to copy subtitles {}", subtitles.display()))?;
+        std::fs::write(output_dir.join("subtitles.m3u8"), playlist)?;
+    }
 
-        if input_info.has_audio {
-            map_args.extend([
-                "-map".to_string(), "0:a".to_string(),
-                format!("-c:a:{}", i), "aac".to_string(),
-                format!("-b:a:{}", i), "128k".to_string(),
-            ]);
-        }
+    println!("HLS encoding complete!");
+    println!("Output: {}", output_dir.display());
+    println!("Master playlist: {}", output_dir.join("master.m3u8").display());
+
+    Ok(())
+}
+
+/// Build the FFmpeg job for a DASH encode.
+///
+/// Video rungs share one adaptation set and the audio rendition gets its
+/// own, so players can also stream audio alone. All representations go
+/// into one manifest, so DASH is encoded as a single job.
+pub fn plan_dash(input: &Path, output_dir: &Path, info: &InputInfo, options: &EncodeOptions) -> Result<EncodeJob> {
+    let renditions = select_renditions(options, info)?;
+    if options.audio_only && !info.has_audio {
+        bail!("--audio-only needs an input with an audio stream");
     }
 
-    filter_complex.pop();
+    options.overlays.check_duration(info.duration)?;
 
-    args.extend([
-        "-filter_complex".to_string(),
-        filter_complex,
-    ]);
+    // For DASH, we encode to fragmented MP4 first, then use MP4Box or ffmpeg dash muxer
+    let heights: Vec<u32> = renditions.iter().map(|r| r.height).collect();
+    let inputs = options.overlays.inputs(&heights);
+    let mut args: Vec<String> = vec![
+        "-i".to_string(),
+        input.to_string_lossy().to_string(),
+    ];
+    args.extend(inputs.args());
+    args.push("-y".to_string());
+    args.extend(PROGRESS_ARGS.iter().map(|arg| arg.to_string()));
+
+    let (filter_complex, map_args) = video_args(&renditions, info, &options.overlays, &inputs);
+    args.extend(["-filter_complex".to_string(), filter_complex]);
     args.extend(map_args);
 
+    let adaptation_sets = if info.has_audio {
+        args.extend(audio_args(&options.audio));
+        "id=0,streams=v id=1,streams=a"
+    } else {
+        "id=0,streams=v"
+    };
+
     // DASH options
+    let output = output_dir.join("manifest.mpd");
     args.extend([
         "-f".to_string(), "dash".to_string(),
-        "-seg_duration".to_string(), format!("{}", segment_duration as u32),
+        "-seg_duration".to_string(), format!("{}", options.segment_duration as u32),
         "-use_template".to_string(), "1".to_string(),
         "-use_timeline".to_string(), "1".to_string(),
+        "-adaptation_sets".to_string(), adaptation_sets.to_string(),
         "-init_seg_name".to_string(), "init_$RepresentationID$.mp4".to_string(),
         "-media_seg_name".to_string(), "segment_$RepresentationID$_$Number$.m4s".to_string(),
-        output_dir.join("manifest.mpd").to_string_lossy().to_string(),
+        output.to_string_lossy().to_string(),
     ]);
 
-    println!("Running FFmpeg for DASH...");
+    Ok(EncodeJob { name: "dash".to_string(), args, output })
+}
+
+/// Encode video to DASH
+pub fn encode_dash(
+    input: &Path,
+    output_dir: &Path,
+    options: &EncodeOptions,
+) -> Result<()> {
+    let input_info = source_info(input, options.dry_run)?;
 
-    let status = Command::new("ffmpeg")
-        .args(&args)
-        .status()
-        .context("FFmpeg execution failed")?;
+    println!("Encoding to DASH with {}", options.label);
+    if options.subtitles.is_some() {
+        println!("Subtitles are only muxed into HLS output; skipping them for DASH");
+    }
+
+    let job = plan_dash(input, output_dir, &input_info, options)?;
+
+    if options.dry_run {
+        if !options.overlays.is_empty() {
+            print_filters(&job.args);
+        }
+        print_command(&job.args);
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(output_dir)?;
+
+    println!("Running FFmpeg for DASH...");
 
-    if !status.success() {
-        bail!("FFmpeg DASH encoding failed");
+    let reports = jobs::run_jobs(std::slice::from_ref(&job), input_info.duration, 1);
+    if let Some(JobStatus::Failed(reason)) = reports.first().map(|r| &r.status) {
+        bail!("FFmpeg DASH encoding failed: {}", reason);
     }
 
     println!("DASH encoding complete!");
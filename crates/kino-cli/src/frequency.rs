@@ -1,31 +1,109 @@
 //! Frequency analysis CLI commands
 //!
 //! Provides CLI commands for audio frequency analysis:
+//! - Band-pass and dominant-frequency filtering to WAV
 //! - Fingerprint generation and verification
 //! - Auto-tagging content
 //! - Thumbnail selection
+//! - Chapter suggestions at natural breaks
+//! - Shared intro and outro markers across the episodes of a series
+//! - Audio-video sync drift checks
 //! - Recommendation similarity
+//! - Seek-bar overviews (waveform and frequency heatmap) for the player UI
 
-use std::path::PathBuf;
-use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use kino_frequency::{
+    AnalyzedAudio,
     AudioAnalyzer,
-    fingerprint::Fingerprinter,
-    tagging::ContentTagger,
-    thumbnail::ThumbnailSelector,
-    recommend::RecommendationEngine,
+    AudioExtractOptions,
+    CancellationToken,
+    FrequencyAnalyzer,
+    derive_content_id,
+    generate_overview,
+    spectrogram::{Colormap, SpectrogramOptions},
+    fingerprint::{EpisodeMarkers, Fingerprinter, IntroConfig, IntroDetector, IntroMarker},
+    tagging::{ContentTagger, QualityReport, TagTaxonomy, TaggingConfig},
+    thumbnail::{ThumbnailCandidate, ThumbnailSelector},
+    breaks::{BreakConfig, BreakDetector},
+    sync::{SyncChecker, SyncConfig},
+    recommend::{RecommendationEngine, RecommendationFilter},
     types::*,
 };
 
+/// Spectral analysis printed by `frequency --json` and served at `/analyze`.
+#[derive(Debug, Serialize)]
+pub struct FrequencyReport {
+    pub dominant_frequencies: Vec<DominantFrequency>,
+    pub spectral_features: SpectralFeatures,
+    pub band_energies: BandEnergies,
+    pub music: Option<MusicInfo>,
+    /// Why music analysis failed, for the text output
+    #[serde(skip)]
+    pub music_error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SpectralFeatures {
+    pub centroid: f32,
+    pub rolloff: f32,
+    pub flatness: f32,
+    pub zcr: f32,
+}
+
+impl FrequencyReport {
+    /// Analyze `audio`, keeping the `top_k` dominant frequencies.
+    pub fn new(analyzer: &AudioAnalyzer, audio: &AudioData, top_k: usize) -> Result<Self> {
+        let dominant_frequencies = analyzer.dominant_frequencies(audio, top_k)?;
+        let analysis = analyzer.analyze(audio)?;
+        let (music, music_error) = match analyzer.analyze_music(audio) {
+            Ok(music) => (Some(music), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        Ok(Self {
+            dominant_frequencies,
+            spectral_features: SpectralFeatures {
+                centroid: analysis.spectral_centroid,
+                rolloff: analysis.spectral_rolloff,
+                flatness: analysis.spectral_flatness,
+                zcr: analysis.zero_crossing_rate,
+            },
+            band_energies: analysis.band_energies,
+            music,
+            music_error,
+        })
+    }
+}
+
 /// Analyze audio frequencies in a video file.
+///
+/// `format_json` prints only the JSON report; `output_json` appends it to
+/// the text output.
 pub async fn analyze_frequency(
     input: &PathBuf,
     top_k: usize,
     output_json: bool,
+    format_json: bool,
+    spectrogram: Option<PathBuf>,
+    spectrogram_options: SpectrogramOptions,
+    audio: &AudioExtractOptions,
 ) -> Result<()> {
+    let analyzer = AudioAnalyzer::new(44100).with_extract_options(audio.clone());
+    if format_json {
+        let audio = analyzer.extract_audio(input).await?;
+        println!("{}", serde_json::to_string_pretty(&FrequencyReport::new(&analyzer, &audio, top_k)?)?);
+        if let Some(path) = spectrogram {
+            analyzer.write_spectrogram(&audio, &spectrogram_options, &path)?;
+        }
+        return Ok(());
+    }
+
     println!("Analyzing frequencies: {}", input.display());
 
-    let analyzer = AudioAnalyzer::new(44100);
     let audio = analyzer.extract_audio(input).await?;
 
     println!("\nAudio Info:");
@@ -33,14 +111,13 @@ pub async fn analyze_frequency(
     println!("  Sample Rate: {} Hz", audio.sample_rate);
     println!("  Duration: {:.2}s", audio.samples.len() as f64 / audio.sample_rate as f64);
 
-    // Get dominant frequencies
-    let dominant = analyzer.dominant_frequencies(&audio, top_k)?;
+    let report = FrequencyReport::new(&analyzer, &audio, top_k)?;
 
     println!("\nDominant Frequencies:");
     println!("  {:>4}  {:>12}  {:>10}", "Rank", "Frequency", "Magnitude");
     println!("  {:->4}  {:->12}  {:->10}", "", "", "");
 
-    for freq in &dominant {
+    for freq in &report.dominant_frequencies {
         println!(
             "  {:>4}  {:>10.1} Hz  {:>9.1}%",
             freq.rank,
@@ -49,58 +126,154 @@ pub async fn analyze_frequency(
         );
     }
 
-    // Compute spectral analysis
-    let analysis = analyzer.analyze(&audio)?;
-
+    let features = &report.spectral_features;
     println!("\nSpectral Features:");
-    println!("  Centroid: {:.1} Hz (brightness)", analysis.spectral_centroid);
-    println!("  Rolloff: {:.1} Hz (95% energy)", analysis.spectral_rolloff);
-    println!("  Flatness: {:.4} (0=tonal, 1=noise)", analysis.spectral_flatness);
-    println!("  ZCR: {:.4} (zero crossing rate)", analysis.zero_crossing_rate);
+    println!("  Centroid: {:.1} Hz (brightness)", features.centroid);
+    println!("  Rolloff: {:.1} Hz (95% energy)", features.rolloff);
+    println!("  Flatness: {:.4} (0=tonal, 1=noise)", features.flatness);
+    println!("  ZCR: {:.4} (zero crossing rate)", features.zcr);
 
+    let bands = &report.band_energies;
     println!("\nBand Energies:");
-    println!("  Sub-bass (20-60 Hz):    {:>5.1}%", analysis.band_energies.sub_bass * 100.0);
-    println!("  Bass (60-250 Hz):       {:>5.1}%", analysis.band_energies.bass * 100.0);
-    println!("  Low-mid (250-500 Hz):   {:>5.1}%", analysis.band_energies.low_mid * 100.0);
-    println!("  Mid (500-2000 Hz):      {:>5.1}%", analysis.band_energies.mid * 100.0);
-    println!("  High-mid (2000-4000 Hz):{:>5.1}%", analysis.band_energies.high_mid * 100.0);
-    println!("  High (4000+ Hz):        {:>5.1}%", analysis.band_energies.high * 100.0);
+    println!("  Sub-bass (20-60 Hz):    {:>5.1}%", bands.sub_bass() * 100.0);
+    println!("  Bass (60-250 Hz):       {:>5.1}%", bands.bass() * 100.0);
+    println!("  Low-mid (250-500 Hz):   {:>5.1}%", bands.low_mid() * 100.0);
+    println!("  Mid (500-2000 Hz):      {:>5.1}%", bands.mid() * 100.0);
+    println!("  High-mid (2000-4000 Hz):{:>5.1}%", bands.high_mid() * 100.0);
+    println!("  High (4000+ Hz):        {:>5.1}%", bands.high() * 100.0);
+
+    match (&report.music, &report.music_error) {
+        (Some(music), _) => {
+            println!("\nMusic:");
+            println!("  Tempo: {:.1} BPM ({:.0}% confidence)", music.bpm, music.bpm_confidence * 100.0);
+            println!("  Key: {} ({:.0}% confidence)", music.key_name(), music.key_confidence * 100.0);
+        }
+        (None, error) => println!("\nMusic: unavailable ({})", error.as_deref().unwrap_or("unknown error")),
+    }
 
     if output_json {
-        let result = serde_json::json!({
-            "dominant_frequencies": dominant,
-            "spectral_features": {
-                "centroid": analysis.spectral_centroid,
-                "rolloff": analysis.spectral_rolloff,
-                "flatness": analysis.spectral_flatness,
-                "zcr": analysis.zero_crossing_rate,
-            },
-            "band_energies": analysis.band_energies,
-        });
         println!("\nJSON Output:");
-        println!("{}", serde_json::to_string_pretty(&result)?);
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    if let Some(path) = spectrogram {
+        analyzer.write_spectrogram(&audio, &spectrogram_options, &path)?;
+        println!("\nSpectrogram saved to: {}", path.display());
     }
 
     Ok(())
 }
 
+/// Build spectrogram options from CLI flags.
+pub fn spectrogram_options(colormap: &str, log_frequency: bool) -> Result<SpectrogramOptions> {
+    let colormap = match colormap.to_lowercase().as_str() {
+        "viridis" => Colormap::Viridis,
+        "grayscale" | "gray" => Colormap::Grayscale,
+        other => anyhow::bail!("Unknown colormap '{}', expected viridis or grayscale", other),
+    };
+
+    Ok(SpectrogramOptions {
+        colormap,
+        log_frequency,
+        ..Default::default()
+    })
+}
+
+/// What `filter` keeps of the audio.
+pub enum FilterMode {
+    /// Frequencies between `low` and `high` Hz
+    Band { low: f32, high: f32 },
+    /// The K dominant frequencies
+    Dominant(usize),
+}
+
+/// Filter the audio of a video or WAV file and write it as a 32-bit float WAV.
+///
+/// WAV input keeps its sample rate; other input, or WAV input with a track
+/// or range selected, is extracted at 44.1 kHz.
+pub async fn filter(input: &Path, output: &Path, mode: FilterMode, audio: &AudioExtractOptions) -> Result<()> {
+    println!("Filtering audio: {}", input.display());
+
+    let analyzer = AudioAnalyzer::new(44100).with_extract_options(audio.clone());
+    let is_wav = input.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+    let audio = if is_wav && *audio == AudioExtractOptions::default() {
+        analyzer.load_wav(input)?.to_mono().into_owned()
+    } else {
+        analyzer.extract_audio(input).await?
+    };
+    println!("  Duration: {:.2}s at {} Hz", audio.duration_secs, audio.sample_rate);
+
+    let fft = FrequencyAnalyzer::new(4096, 2048);
+    let filtered = match mode {
+        FilterMode::Band { low, high } => {
+            if !(low >= 0.0 && low < high) {
+                anyhow::bail!("Invalid band {}-{} Hz: --low must be non-negative and below --high", low, high);
+            }
+            println!("  Keeping: {:.0}-{:.0} Hz", low, high);
+            fft.bandpass_filter(&audio.samples, audio.sample_rate, low, high)?
+        }
+        FilterMode::Dominant(top_k) => {
+            if top_k == 0 {
+                anyhow::bail!("--top-k must be at least 1");
+            }
+            println!("  Keeping: {} dominant frequencies", top_k);
+            fft.project_to_dominant(&audio.samples, audio.sample_rate, top_k)?
+        }
+    };
+
+    let energy = |samples: &[f32]| samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>();
+    let input_energy = energy(&audio.samples);
+    if input_energy > 0.0 {
+        println!("  Energy kept: {:.1}%", energy(&filtered) / input_energy * 100.0);
+    }
+
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: audio.sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(output, spec)
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+    for &sample in &filtered {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+
+    println!("\nFiltered audio saved to: {}", output.display());
+    Ok(())
+}
+
 /// Generate audio fingerprint for content verification.
+///
+/// With `json` only the fingerprint, or the verification result, is printed.
 pub async fn fingerprint(
     input: &PathBuf,
     output: Option<PathBuf>,
     verify_hash: Option<String>,
+    json: bool,
+    audio: &AudioExtractOptions,
 ) -> Result<()> {
-    println!("Generating fingerprint: {}", input.display());
+    if !json {
+        println!("Generating fingerprint: {}", input.display());
+    }
 
-    let analyzer = AudioAnalyzer::new(44100);
+    let analyzer = AudioAnalyzer::new(44100).with_extract_options(audio.clone());
     let audio = analyzer.extract_audio(input).await?;
 
     let fingerprinter = Fingerprinter::new();
 
     if let Some(expected_hash) = verify_hash {
         // Verification mode
-        println!("\nVerifying against hash: {}", expected_hash);
         let result = fingerprinter.verify(&audio, &expected_hash)?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&result)?);
+            if !result.verified {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        println!("\nVerifying against hash: {}", expected_hash);
 
         if result.verified {
             println!("\n✓ VERIFIED - Content matches fingerprint");
@@ -113,6 +286,20 @@ pub async fn fingerprint(
     } else {
         // Generation mode
         let fp = fingerprinter.fingerprint(&audio)?;
+        if fp.low_entropy {
+            eprintln!(
+                "{}Warning: low-entropy fingerprint ({} points); short or mostly silent audio can match unrelated content",
+                if json { "" } else { "\n" },
+                fp.points.len()
+            );
+        }
+        if json {
+            if let Some(path) = output {
+                std::fs::write(&path, serde_json::to_string_pretty(&fp)?)?;
+            }
+            println!("{}", serde_json::to_string_pretty(&fp)?);
+            return Ok(());
+        }
 
         println!("\nFingerprint Generated:");
         println!("  Hash: {}", fp.hash);
@@ -134,69 +321,263 @@ pub async fn fingerprint(
     Ok(())
 }
 
+/// Tagger for `config`, with the taxonomy at `taxonomy` or the built-in one.
+fn load_tagger(config: TaggingConfig, taxonomy: Option<&Path>) -> Result<ContentTagger> {
+    let tagger = ContentTagger::with_config(config);
+    Ok(match taxonomy {
+        Some(path) => tagger.with_taxonomy(TagTaxonomy::load(path)?),
+        None => tagger,
+    })
+}
+
+/// Name printed for a tag: its display name in `locale`, or its label.
+fn tag_name<'a>(taxonomy: &'a TagTaxonomy, tag: &'a ContentTag, locale: Option<&str>) -> &'a str {
+    match locale {
+        Some(_) => taxonomy.tag_name(tag, locale),
+        None => &tag.label,
+    }
+}
+
+/// Tags printed by `autotag --format json` and served at `/tags`.
+#[derive(Debug, Serialize)]
+pub struct TagReport {
+    /// The `max_tags` most confident tags at or above `min_confidence`
+    pub tags: Vec<ContentTag>,
+    /// Audio quality estimate, when asked for
+    pub quality: Option<QualityReport>,
+}
+
+impl TagReport {
+    /// Tag `audio`, keeping at most `max_tags` tags of `min_confidence` or more.
+    pub fn new(tagger: &ContentTagger, audio: &AudioData, max_tags: usize, min_confidence: f32, quality: bool) -> Result<Self> {
+        let tags = tagger.predict(audio)?
+            .into_iter()
+            .filter(|t| t.confidence >= min_confidence)
+            .take(max_tags)
+            .collect();
+        let quality = if quality { Some(tagger.analyze_quality(audio)?) } else { None };
+        Ok(Self { tags, quality })
+    }
+}
+
 /// Auto-tag content based on audio analysis.
+#[allow(clippy::too_many_arguments)]
 pub async fn autotag(
     input: &PathBuf,
     max_tags: usize,
     min_confidence: f32,
+    quality: bool,
+    taxonomy: Option<&Path>,
+    locale: Option<&str>,
+    json: bool,
+    audio: &AudioExtractOptions,
 ) -> Result<()> {
-    println!("Auto-tagging: {}", input.display());
+    if !json {
+        println!("Auto-tagging: {}", input.display());
+    }
 
-    let analyzer = AudioAnalyzer::new(44100);
+    let analyzer = AudioAnalyzer::new(44100).with_extract_options(audio.clone());
     let audio = analyzer.extract_audio(input).await?;
 
-    let tagger = ContentTagger::new();
-    let tags = tagger.predict(&audio)?;
+    let tagger = load_tagger(TaggingConfig::default(), taxonomy)?;
+    let report = TagReport::new(&tagger, &audio, max_tags, min_confidence, quality)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
 
     println!("\nSuggested Tags:");
     println!("  {:>20}  {:>10}", "Tag", "Confidence");
     println!("  {:->20}  {:->10}", "", "");
 
-    let filtered: Vec<_> = tags.iter()
-        .filter(|t| t.confidence >= min_confidence)
-        .take(max_tags)
+    let quality_tags = report.quality.iter().flat_map(|r| &r.tags);
+    let filtered: Vec<_> = report.tags.iter()
+        .chain(quality_tags.filter(|t| t.confidence >= min_confidence))
         .collect();
 
     if filtered.is_empty() {
         println!("  No tags above confidence threshold ({:.0}%)", min_confidence * 100.0);
     } else {
         for tag in filtered {
-            println!("  {:>20}  {:>9.0}%", tag.label, tag.confidence * 100.0);
+            println!("  {:>20}  {:>9.0}%", tag_name(tagger.taxonomy(), tag, locale), tag.confidence * 100.0);
         }
     }
 
+    if let Some(quality) = &report.quality {
+        print_quality(quality, tagger.taxonomy(), locale);
+    }
+
+    Ok(())
+}
+
+/// Print a quality report, naming tags in `locale` when given.
+fn print_quality(report: &QualityReport, taxonomy: &TagTaxonomy, locale: Option<&str>) {
+    println!("\nQuality:");
+    println!("  Bandwidth ceiling: {:.1} kHz (Nyquist {:.1} kHz)", report.bandwidth_hz / 1000.0, report.nyquist_hz / 1000.0);
+    if report.codec_lowpass {
+        println!("  Codec low-pass:    yes ({:.0} dB drop)", report.cutoff_drop_db);
+    } else {
+        println!("  Codec low-pass:    no");
+    }
+    println!("  Bitrate class:     {:?}", report.bitrate_class);
+    println!("  Noise floor:       {:.1} dBFS ({:.1} dB below the loudest passages)", report.noise_floor_db, report.signal_to_noise_db);
+    let tags: Vec<String> = report.tags.iter()
+        .map(|t| format!("{} ({:.0}%)", tag_name(taxonomy, t, locale), t.confidence * 100.0))
+        .collect();
+    println!("  Tags:              {}", if tags.is_empty() { "-".to_string() } else { tags.join(", ") });
+}
+
+/// Print a timeline of tagged segments, timed from the start of the media.
+#[allow(clippy::too_many_arguments)]
+pub async fn autotag_segments(
+    input: &PathBuf,
+    max_tags: usize,
+    min_confidence: f32,
+    window_secs: f64,
+    quality: bool,
+    taxonomy: Option<&Path>,
+    locale: Option<&str>,
+    audio: &AudioExtractOptions,
+) -> Result<()> {
+    println!("Auto-tagging segments: {}", input.display());
+
+    let offset = audio.start.unwrap_or(0.0);
+    let analyzer = AudioAnalyzer::new(44100).with_extract_options(audio.clone());
+    let audio = analyzer.extract_audio(input).await?;
+
+    let config = TaggingConfig {
+        max_tags,
+        min_confidence,
+        ..Default::default()
+    };
+    let tagger = load_tagger(config, taxonomy)?;
+    // Half-window hop so changepoints land within half a window
+    let segments = tagger.predict_segments(&audio, window_secs, window_secs / 2.0)?;
+
+    println!("\nTimeline:");
+    println!("  {:>8}  {:>8}  Tags", "Start", "End");
+    println!("  {:->8}  {:->8}  {:->30}", "", "", "");
+
+    for segment in &segments {
+        let tags: Vec<String> = segment.tags.iter()
+            .map(|t| format!("{} ({:.0}%)", tag_name(tagger.taxonomy(), t, locale), t.confidence * 100.0))
+            .collect();
+        println!(
+            "  {:>8}  {:>8}  {}",
+            format_timestamp(offset + segment.start_secs),
+            format_timestamp(offset + segment.end_secs),
+            if tags.is_empty() { "-".to_string() } else { tags.join(", ") }
+        );
+    }
+
+    if quality {
+        print_quality(&tagger.analyze_quality(&audio)?, tagger.taxonomy(), locale);
+    }
+
+    Ok(())
+}
+
+/// List the audio streams of a media file, for picking one with `--audio-track`.
+pub fn tracks(input: &Path, json: bool) -> Result<()> {
+    let tracks = AudioAnalyzer::new(44100).probe_audio_tracks(input)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tracks)?);
+        return Ok(());
+    }
+
+    println!("Audio tracks: {}", input.display());
+    if tracks.is_empty() {
+        println!("  No audio streams");
+        return Ok(());
+    }
+    println!("  {:>5}  {:>8}  {:>8}  {:>8}  {:>12}  Title", "Index", "Language", "Codec", "Channels", "Layout");
+    println!("  {:->5}  {:->8}  {:->8}  {:->8}  {:->12}  {:->20}", "", "", "", "", "", "");
+    for track in &tracks {
+        println!(
+            "  {:>5}  {:>8}  {:>8}  {:>8}  {:>12}  {}{}",
+            track.index,
+            track.language.as_deref().unwrap_or("-"),
+            track.codec.as_deref().unwrap_or("-"),
+            track.channels,
+            track.channel_layout.as_deref().unwrap_or("-"),
+            track.title.as_deref().unwrap_or("-"),
+            if track.default { " (default)" } else { "" }
+        );
+    }
     Ok(())
 }
 
+fn format_timestamp(secs: f64) -> String {
+    let total = secs.round() as u64;
+    format!("{:02}:{:02}:{:02}", total / 3600, total / 60 % 60, total % 60)
+}
+
+/// Thumbnail choice printed by `thumbnail --format json` and served at
+/// `/thumbnail`.
+#[derive(Debug, Serialize)]
+pub struct ThumbnailReport {
+    /// Best timestamp in seconds
+    pub timestamp: f64,
+    /// Scored candidates, best first; empty when only one was asked for
+    pub candidates: Vec<ThumbnailCandidate>,
+}
+
+impl ThumbnailReport {
+    /// Pick the best thumbnail of `input`, scoring `num_candidates` if more than one.
+    pub fn new(selector: &ThumbnailSelector, input: &Path, audio: &AudioData, num_candidates: usize) -> Result<Self> {
+        if num_candidates > 1 {
+            let candidates = selector.find_candidates(input, audio, num_candidates)?;
+            let timestamp = candidates.first()
+                .map(|c| c.timestamp)
+                .context("No thumbnail candidates found")?;
+            Ok(Self { timestamp, candidates })
+        } else {
+            Ok(Self { timestamp: selector.find_best_timestamp(input, audio)?, candidates: Vec::new() })
+        }
+    }
+}
+
 /// Select optimal thumbnail timestamp.
 pub async fn thumbnail(
     input: &PathBuf,
     output: Option<PathBuf>,
     num_candidates: usize,
+    json: bool,
 ) -> Result<()> {
-    println!("Finding optimal thumbnail: {}", input.display());
+    if !json {
+        println!("Finding optimal thumbnail: {}", input.display());
+    }
 
     let analyzer = AudioAnalyzer::new(44100);
     let audio = analyzer.extract_audio(input).await?;
 
     let selector = ThumbnailSelector::new();
+    let report = ThumbnailReport::new(&selector, input, &audio, num_candidates)?;
+    if json {
+        if let Some(path) = output {
+            selector.extract_thumbnail(input, report.timestamp, &path)?;
+        }
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
 
     if num_candidates > 1 {
         // Show multiple candidates
-        let candidates = selector.find_candidates(input, &audio, num_candidates)?;
+        let candidates = &report.candidates;
 
         println!("\nThumbnail Candidates:");
-        println!("  {:>4}  {:>10}  {:>10}  {:>10}  {:>10}",
-            "Rank", "Timestamp", "Sharpness", "Contrast", "Score");
-        println!("  {:->4}  {:->10}  {:->10}  {:->10}  {:->10}", "", "", "", "", "");
+        println!("  {:>4}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+            "Rank", "Timestamp", "Sharpness", "Contrast", "Text", "Score");
+        println!("  {:->4}  {:->10}  {:->10}  {:->10}  {:->10}  {:->10}", "", "", "", "", "", "");
 
         for (i, c) in candidates.iter().enumerate() {
             println!(
-                "  {:>4}  {:>9.2}s  {:>9.1}%  {:>9.1}%  {:>9.3}",
+                "  {:>4}  {:>9.2}s  {:>9.1}%  {:>9.1}%  {:>9.1}%  {:>9.3}",
                 i + 1,
                 c.timestamp,
                 c.sharpness * 100.0,
                 c.contrast * 100.0,
+                c.text_likeness * 100.0,
                 c.total_score
             );
         }
@@ -210,7 +591,7 @@ pub async fn thumbnail(
         }
     } else {
         // Just get best timestamp
-        let timestamp = selector.find_best_timestamp(input, &audio)?;
+        let timestamp = report.timestamp;
         println!("\nBest timestamp: {:.2}s", timestamp);
 
         if let Some(path) = output {
@@ -225,6 +606,237 @@ pub async fn thumbnail(
     Ok(())
 }
 
+/// Suggest chapters at natural breaks and write them as WebVTT or JSON.
+pub async fn chapters(
+    input: &PathBuf,
+    output: Option<PathBuf>,
+    output_json: bool,
+    min_silence: f64,
+    min_chapter: f64,
+) -> Result<()> {
+    println!("Finding chapter breaks: {}", input.display());
+
+    let analyzer = AudioAnalyzer::new(44100);
+    let audio = analyzer.extract_audio(input).await?;
+
+    let detector = BreakDetector::with_config(BreakConfig {
+        min_silence_secs: min_silence,
+        min_chapter_secs: min_chapter,
+        ..Default::default()
+    });
+    let breaks = detector.detect(input, &audio)?;
+
+    if breaks.is_empty() {
+        println!("\nNo breaks found; the video is a single chapter.");
+    } else {
+        println!("\nBreak Candidates:");
+        println!("  {:>4}  {:>10}  {:>10}  {:>10}", "#", "Time", "Silence", "Confidence");
+        println!("  {:->4}  {:->10}  {:->10}  {:->10}", "", "", "", "");
+        for (i, b) in breaks.iter().enumerate() {
+            println!(
+                "  {:>4}  {:>9.2}s  {:>9.2}s  {:>9.0}%",
+                i + 1,
+                b.time,
+                b.silence_duration,
+                b.confidence * 100.0
+            );
+        }
+    }
+
+    // Chapters run from break to break, covering the whole video
+    let bounds: Vec<f64> = std::iter::once(0.0)
+        .chain(breaks.iter().map(|b| b.time))
+        .chain(std::iter::once(audio.duration_secs))
+        .collect();
+    let chapters: Vec<kino_core::Chapter> = bounds.windows(2)
+        .enumerate()
+        .map(|(i, span)| {
+            kino_core::Chapter::new(format!("chapter-{}", i + 1), format!("Chapter {}", i + 1), span[0], span[1])
+        })
+        .collect();
+
+    let document = if output_json {
+        serde_json::to_string_pretty(&chapters)?
+    } else {
+        kino_core::chapters_to_vtt(&chapters)
+    };
+
+    if let Some(path) = output {
+        std::fs::write(&path, &document)?;
+        println!("\n{} chapters saved to: {}", chapters.len(), path.display());
+    } else {
+        println!("\nChapters:");
+        println!("{}", document);
+    }
+
+    Ok(())
+}
+
+/// Intro and outro markers of one episode, as written by `introdetect`.
+#[derive(Serialize)]
+struct EpisodeReport<'a> {
+    input: &'a Path,
+    duration_secs: f64,
+    #[serde(flatten)]
+    markers: &'a EpisodeMarkers,
+}
+
+/// Find the intro and outro shared by a series' episodes and write them as
+/// JSON, and optionally as a WebVTT chapters file per episode.
+pub async fn introdetect(
+    inputs: &[PathBuf],
+    output: Option<PathBuf>,
+    chapters_dir: Option<PathBuf>,
+    search_secs: f64,
+    min_duration: f64,
+    min_similarity: f32,
+    detect_outro: bool,
+) -> Result<()> {
+    // Fingerprint one episode at a time so only one is decoded in memory
+    let fingerprinter = Fingerprinter::new();
+    let analyzer = AudioAnalyzer::new(fingerprinter.preferred_sample_rate());
+    let mut fingerprints = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        eprintln!("Fingerprinting: {}", input.display());
+        let audio = analyzer.extract_audio(input).await?;
+        let fingerprint = fingerprinter.fingerprint(&audio)
+            .with_context(|| format!("Failed to fingerprint {}", input.display()))?;
+        fingerprints.push(fingerprint);
+    }
+
+    let detector = IntroDetector::with_config(IntroConfig {
+        search_secs,
+        detect_outro,
+        min_duration_secs: min_duration,
+        min_similarity,
+        ..Default::default()
+    });
+    let markers = detector.detect(&fingerprints)?;
+
+    for (input, m) in inputs.iter().zip(&markers) {
+        let span = |marker: &Option<IntroMarker>| match marker {
+            Some(m) => format!("{:.2}s-{:.2}s ({:.0}%)", m.start, m.end, m.confidence * 100.0),
+            None => "-".to_string(),
+        };
+        eprintln!("  {}: intro {}, outro {}", input.display(), span(&m.intro), span(&m.outro));
+    }
+
+    let reports: Vec<EpisodeReport> = inputs.iter()
+        .zip(&fingerprints)
+        .zip(&markers)
+        .map(|((input, fp), markers)| EpisodeReport { input, duration_secs: fp.duration_secs, markers })
+        .collect();
+    let json = serde_json::to_string_pretty(&reports)?;
+    match output {
+        Some(path) => {
+            std::fs::write(&path, &json)?;
+            eprintln!("\nMarkers saved to: {}", path.display());
+        }
+        None => println!("{}", json),
+    }
+
+    if let Some(dir) = chapters_dir {
+        std::fs::create_dir_all(&dir)?;
+        for report in &reports {
+            let stem = report.input.file_stem().unwrap_or_default().to_string_lossy();
+            let path = dir.join(format!("{}.chapters.vtt", stem));
+            let chapters = marker_chapters(report.markers, report.duration_secs);
+            std::fs::write(&path, kino_core::chapters_to_vtt(&chapters))?;
+            eprintln!("Chapters saved to: {}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Chapters covering an episode: any cold open, the intro, the episode
+/// itself and the outro.
+fn marker_chapters(markers: &EpisodeMarkers, duration: f64) -> Vec<kino_core::Chapter> {
+    let mut chapters = Vec::new();
+    let mut position = 0.0;
+    if let Some(intro) = &markers.intro {
+        if intro.start > 0.0 {
+            chapters.push(kino_core::Chapter::new("cold-open", "Cold Open", 0.0, intro.start));
+        }
+        chapters.push(kino_core::Chapter::new("intro", "Intro", intro.start, intro.end));
+        position = intro.end;
+    }
+    let outro_start = markers.outro.as_ref().map_or(duration, |outro| outro.start.max(position));
+    if outro_start > position {
+        chapters.push(kino_core::Chapter::new("episode", "Episode", position, outro_start));
+    }
+    if let Some(outro) = &markers.outro {
+        chapters.push(kino_core::Chapter::new("outro", "Outro", outro_start, outro.end));
+    }
+    chapters
+}
+
+/// Check A/V sync drift and exit non-zero when the video needs review.
+pub async fn syncheck(
+    input: &PathBuf,
+    output_json: bool,
+    threshold_ms: f64,
+    checkpoints: usize,
+    window: f64,
+) -> Result<()> {
+    if !output_json {
+        println!("Checking A/V sync: {}", input.display());
+    }
+
+    let analyzer = AudioAnalyzer::new(44100);
+    let audio = analyzer.extract_audio(input).await?;
+
+    let checker = SyncChecker::with_config(SyncConfig {
+        threshold_ms,
+        checkpoints,
+        window_secs: window,
+        ..Default::default()
+    });
+    let report = checker.check(input, &audio)?;
+
+    if output_json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let ms = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:+.0} ms", v));
+
+        println!("\nCheckpoints:");
+        println!("  {:>10}  {:>10}  {:>10}  {:>6}", "Time", "Offset", "Confidence", "Cuts");
+        println!("  {:->10}  {:->10}  {:->10}  {:->6}", "", "", "", "");
+        for c in &report.checkpoints {
+            println!(
+                "  {:>9.2}s  {:>10}  {:>9.0}%  {:>6}",
+                c.time,
+                ms(c.offset_ms),
+                c.confidence * 100.0,
+                c.scene_changes
+            );
+        }
+
+        println!("\nSync:");
+        println!("  Start:  {}", ms(report.start_offset_ms));
+        println!("  Middle: {}", ms(report.middle_offset_ms));
+        println!("  End:    {}", ms(report.end_offset_ms));
+        println!("  Drift:  {}", ms(report.drift_ms));
+        if let Some(rate) = report.drift_rate_ms_per_min {
+            println!("  Rate:   {:+.1} ms/min", rate);
+        }
+
+        if report.flagged {
+            println!("\nFLAGGED: offset or drift exceeds {:.0} ms; review this video", report.threshold_ms);
+        } else if report.start_offset_ms.is_none() {
+            println!("\nNo offsets measured; the video has too few scene changes or audio onsets");
+        } else {
+            println!("\nIn sync within {:.0} ms", report.threshold_ms);
+        }
+    }
+
+    if report.flagged {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
 /// Find similar content using frequency signatures.
 pub async fn similar(
     input: &PathBuf,
@@ -235,7 +847,7 @@ pub async fn similar(
     println!("Scanning library: {}", library_dir.display());
 
     let analyzer = AudioAnalyzer::new(44100);
-    let mut engine = RecommendationEngine::new();
+    let engine = RecommendationEngine::new();
 
     // Index library
     let entries = std::fs::read_dir(library_dir)?;
@@ -266,7 +878,7 @@ pub async fn similar(
 
     // Analyze input
     let input_audio = analyzer.extract_audio(input).await?;
-    let recommendations = engine.get_recommendations_for_audio(&input_audio, limit)?;
+    let recommendations = engine.get_recommendations_for_audio(&input_audio, limit, &RecommendationFilter::default())?;
 
     if recommendations.is_empty() {
         println!("\nNo similar content found.");
@@ -289,72 +901,368 @@ pub async fn similar(
     Ok(())
 }
 
+/// Version of the `process_manifest.json` layout, bumped on incompatible changes.
+const PROCESS_MANIFEST_VERSION: u32 = 1;
+
+const ANALYSIS_FILE: &str = "analysis.json";
+const THUMBNAIL_FILE: &str = "thumbnail.jpg";
+const SIGNATURE_FILE: &str = "signature.bin";
+const TIMINGS_FILE: &str = "timings.json";
+const OVERVIEW_FILE: &str = "overview.json";
+const MANIFEST_FILE: &str = "process_manifest.json";
+
 /// Process a video through the complete frequency pipeline.
+///
+/// With `manifest_only`, the manifest is rebuilt from the artifacts of a
+/// previous run in `output_dir` without re-running analysis. Ctrl-C cancels
+/// the stage in flight; the stages that finished are still saved. The
+/// content id is `content_id` when given, and otherwise derived from the
+/// fingerprint or the input's bytes, so re-processing a file keeps its id.
+/// `audio` picks the audio track and time range; with a range the thumbnail
+/// stage is skipped, as thumbnails are chosen over the whole video.
+#[allow(clippy::too_many_arguments)]
 pub async fn process(
     input: &PathBuf,
     output_dir: &PathBuf,
     skip_fingerprint: bool,
     skip_tags: bool,
     skip_thumbnail: bool,
+    manifest_only: bool,
+    overview_buckets: usize,
+    content_id: Option<&str>,
+    audio: &AudioExtractOptions,
 ) -> Result<()> {
+    if manifest_only {
+        let manifest_path = write_manifest_from_artifacts(input, output_dir)?;
+        println!("✓ Manifest rebuilt: {}", manifest_path.display());
+        return Ok(());
+    }
+
     println!("Processing video: {}", input.display());
     println!("Output directory: {}", output_dir.display());
 
     std::fs::create_dir_all(output_dir)?;
 
-    let analyzer = AudioAnalyzer::new(44100);
-    let audio = analyzer.extract_audio(input).await?;
+    let cancel = cancel_on_ctrl_c();
+    let mut timings = BTreeMap::new();
+    let mut stage = Instant::now();
+    let mut finish_stage = |name: &str| {
+        timings.insert(name.to_string(), stage.elapsed().as_secs_f64() * 1000.0);
+        stage = Instant::now();
+    };
+
+    let analyzer = AudioAnalyzer::new(44100).with_extract_options(audio.clone());
+    let selection = analyzer.resolve_audio_options(input)?;
+    let analyzer = analyzer.with_extract_options(selection.clone());
+    let audio = tokio::select! {
+        audio = analyzer.extract_audio(input) => audio?,
+        _ = cancel.cancelled() => anyhow::bail!("Interrupted during audio extraction, nothing was saved"),
+    };
+    finish_stage("extraction");
+    if let Some(index) = selection.stream_index {
+        println!("Audio stream: {} ({})", index, selection.language.as_deref().unwrap_or("no language"));
+    }
 
     let mut result = ProcessingResult {
-        content_id: uuid::Uuid::new_v4().to_string(),
-        fingerprint: None,
-        tags: Vec::new(),
-        thumbnail_timestamp: None,
-        signature: None,
+        audio: Some(AnalyzedAudio::new(&selection, &audio)),
         dominant_frequencies: analyzer.dominant_frequencies(&audio, 10)?,
+        loudness: analyzer.measure_loudness(&audio).ok(),
+        // The id is derived once the fingerprint is known
+        ..ProcessingResult::new(String::new())
     };
+    finish_stage("analysis");
 
-    // Fingerprint
-    if !skip_fingerprint {
-        println!("\n[1/3] Generating fingerprint...");
-        let fingerprinter = Fingerprinter::new();
-        let fp = fingerprinter.fingerprint(&audio)?;
-        println!("  Hash: {}", fp.hash);
-        result.fingerprint = Some(fp);
+    if let Some(loudness) = &result.loudness {
+        println!(
+            "Loudness: {:.1} LUFS (range {:.1} LU, true peak {:.1} dBTP)",
+            loudness.integrated_lufs, loudness.loudness_range_lu, loudness.true_peak_dbtp
+        );
     }
 
-    // Tags
-    if !skip_tags {
-        println!("\n[2/3] Auto-tagging...");
-        let tagger = ContentTagger::new();
-        let tags = tagger.predict(&audio)?;
-        for tag in &tags {
-            println!("  {}: {:.0}%", tag.label, tag.confidence * 100.0);
+    let mut run_stages = || -> Result<()> {
+        // Fingerprint
+        if !skip_fingerprint {
+            println!("\n[1/5] Generating fingerprint...");
+            let fingerprinter = Fingerprinter::new();
+            let fp = fingerprinter.fingerprint_cancellable(&audio, &cancel)?;
+            println!("  Hash: {}", fp.hash);
+            result.fingerprint = Some(fp);
+            finish_stage("fingerprint");
         }
-        result.tags = tags;
-    }
 
-    // Thumbnail
-    if !skip_thumbnail {
-        println!("\n[3/3] Selecting thumbnail...");
-        let selector = ThumbnailSelector::new();
-        let timestamp = selector.find_best_timestamp(input, &audio)?;
-        println!("  Best timestamp: {:.2}s", timestamp);
+        // Tags
+        if !skip_tags {
+            println!("\n[2/5] Auto-tagging...");
+            let tagger = ContentTagger::new();
+            let tags = tagger.predict_cancellable(&audio, &cancel)?;
+            for tag in &tags {
+                println!("  {}: {:.0}%", tag.label, tag.confidence * 100.0);
+            }
+            let is_music = tags.iter().any(|t| {
+                t.confidence >= kino_frequency::music::MUSIC_INFO_MIN_CONFIDENCE && tagger.taxonomy().tag_is_a(t, "genre.music")
+            });
+            if is_music {
+                match analyzer.analyze_music(&audio) {
+                    Ok(music) => {
+                        println!("  Tempo: {:.1} BPM, key: {}", music.bpm, music.key_name());
+                        result.music = Some(music);
+                    }
+                    Err(e) => println!("  Music analysis skipped: {}", e),
+                }
+            }
+            result.tags = tags;
+            finish_stage("tagging");
+        }
 
-        let thumb_path = output_dir.join("thumbnail.jpg");
-        selector.extract_thumbnail(input, timestamp, &thumb_path)?;
-        println!("  Saved: {}", thumb_path.display());
+        // Thumbnail
+        if !skip_thumbnail && selection.is_range() {
+            println!("\n[3/5] Thumbnail skipped: it is chosen over the whole video, not a time range");
+        } else if !skip_thumbnail {
+            check_interrupted(&cancel)?;
+            println!("\n[3/5] Selecting thumbnail...");
+            let selector = ThumbnailSelector::new();
+            let timestamp = selector.find_best_timestamp(input, &audio)?;
+            println!("  Best timestamp: {:.2}s", timestamp);
 
-        result.thumbnail_timestamp = Some(timestamp);
-    }
+            let thumb_path = output_dir.join(THUMBNAIL_FILE);
+            selector.extract_thumbnail(input, timestamp, &thumb_path)?;
+            println!("  Saved: {}", thumb_path.display());
+
+            result.thumbnail_timestamp = Some(timestamp);
+            finish_stage("thumbnail");
+        }
+
+        // Signature
+        println!("\n[4/5] Computing signature...");
+        let signature = analyzer.compute_signature_cancellable(&audio, &cancel)?;
+        println!("  {} features", signature.features.len());
+        result.signature = Some(signature);
+        finish_stage("signature");
+
+        // Seek-bar overview
+        check_interrupted(&cancel)?;
+        println!("\n[5/5] Generating seek-bar overview...");
+        let overview = generate_overview(&audio, overview_buckets)?;
+        let overview_path = output_dir.join(OVERVIEW_FILE);
+        std::fs::write(&overview_path, serde_json::to_string(&overview)?)?;
+        println!("  {} buckets x {} bands: {}", overview.buckets(), overview.bands(), overview_path.display());
+        finish_stage("overview");
+        Ok(())
+    };
+
+    // An interrupted run still saves the stages that finished
+    let interrupted = match run_stages() {
+        Ok(()) => false,
+        Err(e) if matches!(e.downcast_ref(), Some(FrequencyError::Cancelled)) => true,
+        Err(e) => return Err(e),
+    };
+
+    let (id, source) = derive_content_id(content_id, result.fingerprint.as_ref(), Some(input), &audio)?;
+    result.content_id = id;
+    result.content_id_source = source;
 
     // Save complete result
-    let result_path = output_dir.join("analysis.json");
+    let result_path = output_dir.join(ANALYSIS_FILE);
     let json = serde_json::to_string_pretty(&result)?;
     std::fs::write(&result_path, &json)?;
+    std::fs::write(output_dir.join(TIMINGS_FILE), serde_json::to_string_pretty(&timings)?)?;
 
-    println!("\n✓ Processing complete!");
+    let manifest_path = write_manifest(input, output_dir, &result, timings)?;
+
+    if interrupted {
+        println!("\n✗ Interrupted, partial results saved");
+    } else {
+        println!("\n✓ Processing complete!");
+    }
+    println!("  Content ID: {}", result.content_id);
     println!("  Results saved to: {}", result_path.display());
+    println!("  Manifest: {}", manifest_path.display());
+
+    if interrupted {
+        anyhow::bail!("Processing was interrupted");
+    }
+    Ok(())
+}
+
+/// Token cancelled by the first Ctrl-C; a second one exits immediately.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let interrupt = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!("\nInterrupted, finishing the current step (press Ctrl-C again to quit)...");
+        interrupt.cancel();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+    cancel
+}
 
+/// Stop between stages that can't be cancelled partway through.
+pub fn check_interrupted(cancel: &CancellationToken) -> Result<()> {
+    if cancel.is_cancelled() {
+        return Err(FrequencyError::Cancelled.into());
+    }
     Ok(())
 }
+
+/// Machine-readable index of everything `process` wrote.
+///
+/// Artifact paths are relative to the output directory.
+#[derive(Debug, Serialize)]
+struct ProcessManifest {
+    schema_version: u32,
+    tool_version: &'static str,
+    content_id: String,
+    /// How `content_id` was derived
+    content_id_source: ContentIdSource,
+    input: ManifestInput,
+    /// Audio stream and time range that were analyzed
+    audio: Option<AnalyzedAudio>,
+    analysis_path: String,
+    fingerprint: Option<ManifestFingerprint>,
+    tags: Vec<ManifestTag>,
+    thumbnail: Option<ManifestThumbnail>,
+    signature: Option<ManifestSignature>,
+    dominant_frequencies: Vec<ManifestFrequency>,
+    /// Seek-bar overview; absent if it wasn't found when the manifest was written
+    overview_path: Option<String>,
+    /// Wall-clock time per stage in milliseconds
+    timings_ms: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestInput {
+    path: String,
+    sha256: String,
+    size_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestFingerprint {
+    hash: String,
+    version: u32,
+    algorithm: String,
+    duration_secs: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestTag {
+    label: String,
+    confidence: f32,
+    /// Taxonomy id, e.g. "genre.music"; absent for labels outside the taxonomy
+    id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestThumbnail {
+    timestamp_secs: f64,
+    /// Absent if the image wasn't found when the manifest was written
+    path: Option<String>,
+}
+
+/// Signature features stored as little-endian f32 values.
+#[derive(Debug, Serialize)]
+struct ManifestSignature {
+    path: String,
+    version: u32,
+    dimensions: usize,
+    encoding: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestFrequency {
+    frequency_hz: f32,
+    magnitude: f32,
+}
+
+/// Rebuild the manifest from `analysis.json` and the other artifacts of a previous run.
+fn write_manifest_from_artifacts(input: &Path, output_dir: &Path) -> Result<PathBuf> {
+    let analysis_path = output_dir.join(ANALYSIS_FILE);
+    let json = std::fs::read_to_string(&analysis_path)
+        .with_context(|| format!("No previous results at {}; run without --manifest-only first", analysis_path.display()))?;
+    let result = ProcessingResult::from_json(&json)
+        .with_context(|| format!("Failed to parse {}", analysis_path.display()))?;
+
+    // Timings are only known from the original run
+    let timings = match std::fs::read_to_string(output_dir.join(TIMINGS_FILE)) {
+        Ok(json) => serde_json::from_str(&json).context("Failed to parse stage timings")?,
+        Err(_) => BTreeMap::new(),
+    };
+
+    write_manifest(input, output_dir, &result, timings)
+}
+
+/// Write the signature file and `process_manifest.json` for a result.
+fn write_manifest(
+    input: &Path,
+    output_dir: &Path,
+    result: &ProcessingResult,
+    timings_ms: BTreeMap<String, f64>,
+) -> Result<PathBuf> {
+    let (sha256, size_bytes) = hash_file(input)?;
+
+    let signature = match &result.signature {
+        Some(signature) => {
+            let bytes: Vec<u8> = signature.features.iter().flat_map(|f| f.to_le_bytes()).collect();
+            std::fs::write(output_dir.join(SIGNATURE_FILE), bytes)?;
+            Some(ManifestSignature {
+                path: SIGNATURE_FILE.to_string(),
+                version: signature.version,
+                dimensions: signature.features.len(),
+                encoding: "f32le",
+            })
+        }
+        None => None,
+    };
+
+    let manifest = ProcessManifest {
+        schema_version: PROCESS_MANIFEST_VERSION,
+        tool_version: env!("CARGO_PKG_VERSION"),
+        content_id: result.content_id.clone(),
+        content_id_source: result.content_id_source,
+        input: ManifestInput {
+            path: input.display().to_string(),
+            sha256,
+            size_bytes,
+        },
+        audio: result.audio.clone(),
+        analysis_path: ANALYSIS_FILE.to_string(),
+        fingerprint: result.fingerprint.as_ref().map(|fp| ManifestFingerprint {
+            hash: fp.hash.clone(),
+            version: fp.version,
+            algorithm: fp.algorithm.to_string(),
+            duration_secs: fp.duration_secs,
+        }),
+        tags: result.tags.iter()
+            .map(|tag| ManifestTag { label: tag.label.clone(), confidence: tag.confidence, id: tag.id.clone() })
+            .collect(),
+        thumbnail: result.thumbnail_timestamp.map(|timestamp_secs| ManifestThumbnail {
+            timestamp_secs,
+            path: output_dir.join(THUMBNAIL_FILE).exists().then(|| THUMBNAIL_FILE.to_string()),
+        }),
+        signature,
+        dominant_frequencies: result.dominant_frequencies.iter()
+            .map(|f| ManifestFrequency { frequency_hz: f.frequency_hz, magnitude: f.magnitude })
+            .collect(),
+        overview_path: output_dir.join(OVERVIEW_FILE).exists().then(|| OVERVIEW_FILE.to_string()),
+        timings_ms,
+    };
+
+    let manifest_path = output_dir.join(MANIFEST_FILE);
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    Ok(manifest_path)
+}
+
+/// SHA-256 (hex) and size of a file.
+fn hash_file(path: &Path) -> Result<(String, u64)> {
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to hash {}", path.display()))?;
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
@@ -0,0 +1,247 @@
+//! FFmpeg job runner - parallel rendition encodes with progress and resume
+//!
+//! Each job is one FFmpeg invocation writing machine-readable progress
+//! (`-progress pipe:1`) to stdout. Jobs run on a bounded pool of worker
+//! threads, each with its own progress bar, and a failing job never stops
+//! the others. FFmpeg's own log goes to `<name>.log` in the output
+//! directory and is removed once the job succeeds.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result, anyhow, bail};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+/// Arguments that make FFmpeg report progress on stdout
+pub const PROGRESS_ARGS: [&str; 5] = ["-progress", "pipe:1", "-nostats", "-loglevel", "warning"];
+
+/// One FFmpeg invocation
+#[derive(Debug, Clone)]
+pub struct EncodeJob {
+    /// Rendition name, shown on the progress bar and in the summary
+    pub name: String,
+    /// FFmpeg arguments
+    pub args: Vec<String>,
+    /// Playlist or manifest the job writes
+    pub output: PathBuf,
+}
+
+impl EncodeJob {
+    fn log_path(&self) -> PathBuf {
+        self.output.with_file_name(format!("{}.log", self.name))
+    }
+}
+
+/// How a job ended
+#[derive(Debug, Clone)]
+pub enum JobStatus {
+    Encoded,
+    /// Complete output from an earlier run was kept
+    Skipped,
+    Failed(String),
+}
+
+/// Outcome of one job
+#[derive(Debug, Clone)]
+pub struct JobReport {
+    pub name: String,
+    pub status: JobStatus,
+    pub elapsed: Duration,
+}
+
+impl JobReport {
+    pub fn skipped(name: &str) -> Self {
+        Self { name: name.to_string(), status: JobStatus::Skipped, elapsed: Duration::ZERO }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        !matches!(self.status, JobStatus::Failed(_))
+    }
+}
+
+/// Latest values from FFmpeg's `-progress` output
+#[derive(Debug, Default)]
+struct FfmpegProgress {
+    frame: u64,
+    fps: f32,
+    out_time_us: u64,
+}
+
+impl FfmpegProgress {
+    /// Apply one `key=value` line; true at the end of each progress block
+    fn update(&mut self, line: &str) -> bool {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            return false;
+        };
+        // Values are "N/A" until the first frame is written
+        match key {
+            "frame" => self.frame = value.parse().unwrap_or(self.frame),
+            "fps" => self.fps = value.parse().unwrap_or(self.fps),
+            // out_time_ms is also in microseconds, for historical reasons
+            "out_time_us" | "out_time_ms" => self.out_time_us = value.parse().unwrap_or(self.out_time_us),
+            "progress" => return true,
+            _ => {}
+        }
+        false
+    }
+}
+
+/// Run `jobs` with at most `parallel` at once, one progress bar each.
+///
+/// `duration` is the source duration in seconds, used for the bars and
+/// ETA; with an unknown (zero) duration the bars only count frames.
+/// Reports come back in job order.
+pub fn run_jobs(jobs: &[EncodeJob], duration: f64, parallel: usize) -> Vec<JobReport> {
+    let progress = MultiProgress::new();
+    let bars: Vec<ProgressBar> = jobs.iter()
+        .map(|job| {
+            let bar = progress.add(new_bar(duration));
+            bar.set_prefix(job.name.clone());
+            bar.set_message("queued");
+            bar
+        })
+        .collect();
+
+    let next = AtomicUsize::new(0);
+    let reports: Mutex<Vec<Option<JobReport>>> = Mutex::new(vec![None; jobs.len()]);
+
+    std::thread::scope(|scope| {
+        for _ in 0..parallel.clamp(1, jobs.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let Some(job) = jobs.get(i) else { break };
+                let bar = &bars[i];
+
+                let started = Instant::now();
+                let status = match run_ffmpeg(job, bar) {
+                    Ok(()) => {
+                        bar.finish_with_message("done");
+                        JobStatus::Encoded
+                    }
+                    Err(e) => {
+                        bar.abandon_with_message("failed");
+                        JobStatus::Failed(format!("{:#}", e))
+                    }
+                };
+
+                let report = JobReport { name: job.name.clone(), status, elapsed: started.elapsed() };
+                reports.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(report);
+            });
+        }
+    });
+
+    reports.into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn new_bar(duration: f64) -> ProgressBar {
+    if duration > 0.0 {
+        let style = ProgressStyle::with_template("{prefix:>14} [{bar:30}] {percent:>3}% {msg} ETA {eta}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("=> ");
+        ProgressBar::new((duration * 1000.0) as u64).with_style(style)
+    } else {
+        let style = ProgressStyle::with_template("{prefix:>14} {spinner} {msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_spinner());
+        ProgressBar::new_spinner().with_style(style)
+    }
+}
+
+/// Run one FFmpeg job, feeding its progress to `bar`
+fn run_ffmpeg(job: &EncodeJob, bar: &ProgressBar) -> Result<()> {
+    let log_path = job.log_path();
+    let log = File::create(&log_path)
+        .with_context(|| format!("Failed to create {}", log_path.display()))?;
+
+    let mut child = Command::new("ffmpeg")
+        .args(&job.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(log)
+        .spawn()
+        .context("Failed to start FFmpeg")?;
+    let stdout = child.stdout.take().context("FFmpeg stdout unavailable")?;
+
+    let mut progress = FfmpegProgress::default();
+    for line in BufReader::new(stdout).lines() {
+        if progress.update(&line?) {
+            bar.set_position(progress.out_time_us / 1000);
+            bar.set_message(format!("frame {} @ {:.1} fps", progress.frame, progress.fps));
+        }
+    }
+
+    let status = child.wait().context("FFmpeg execution failed")?;
+    if !status.success() {
+        let log = std::fs::read_to_string(&log_path).unwrap_or_default();
+        let reason = log.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("no output");
+        bail!("FFmpeg exited with {} ({}); see {}", status, reason.trim(), log_path.display());
+    }
+
+    let _ = std::fs::remove_file(&log_path);
+    Ok(())
+}
+
+/// Check that an HLS media playlist left by an earlier run is complete.
+///
+/// The playlist must be closed with `EXT-X-ENDLIST`, cover the source
+/// duration to within a segment, have about as many segments as the
+/// duration implies, and every segment and init file must exist.
+pub fn check_hls_complete(playlist: &Path, duration: f64, segment_duration: f64) -> Result<()> {
+    let bytes = std::fs::read(playlist).context("no playlist")?;
+    let media = m3u8_rs::parse_media_playlist_res(&bytes)
+        .map_err(|e| anyhow!("unreadable playlist: {:?}", e))?;
+
+    if !media.end_list {
+        bail!("playlist is not finished");
+    }
+    if duration <= 0.0 {
+        bail!("source duration is unknown");
+    }
+
+    let covered: f64 = media.segments.iter().map(|s| s.duration as f64).sum();
+    if (covered - duration).abs() > segment_duration.max(1.0) {
+        bail!("covers {:.1}s of {:.1}s", covered, duration);
+    }
+
+    let expected = (duration / segment_duration.max(0.1)).ceil() as usize;
+    if media.segments.len().abs_diff(expected) > 1 {
+        bail!("has {} segments, expected about {}", media.segments.len(), expected);
+    }
+
+    let dir = playlist.parent().unwrap_or(Path::new("."));
+    let files = media.segments.iter()
+        .flat_map(|s| std::iter::once(&s.uri).chain(s.map.as_ref().map(|m| &m.uri)));
+    for uri in files {
+        if !dir.join(uri).is_file() {
+            bail!("missing {}", uri);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a per-rendition summary; true if every job succeeded
+pub fn print_summary(reports: &[JobReport]) -> bool {
+    println!("\n  {:<14}  {:<10}  {:>8}", "Rendition", "Status", "Time");
+    println!("  {:-<14}  {:-<10}  {:->8}", "", "", "");
+    for report in reports {
+        let (status, detail) = match &report.status {
+            JobStatus::Encoded => ("encoded", None),
+            JobStatus::Skipped => ("complete", None),
+            JobStatus::Failed(reason) => ("FAILED", Some(reason)),
+        };
+        println!("  {:<14}  {:<10}  {:>7.0}s", report.name, status, report.elapsed.as_secs_f64());
+        if let Some(reason) = detail {
+            println!("      {}", reason);
+        }
+    }
+
+    reports.iter().all(JobReport::succeeded)
+}
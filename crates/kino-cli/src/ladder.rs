@@ -0,0 +1,257 @@
+//! Custom ABR ladders and audio settings for the encoding pipeline
+//!
+//! A ladder file lists the video rungs to encode, as JSON or TOML:
+//!
+//! ```toml
+//! [[rungs]]
+//! height = 720
+//! bitrate = 2800000
+//! codec = "h264"
+//!
+//! [[rungs]]
+//! height = 1080
+//! bitrate = 5000000
+//! framerate = 60
+//! codec = "hevc"
+//! ```
+//!
+//! `width` is optional and defaults to the source aspect ratio; `framerate`
+//! defaults to 30 and `codec` to h264. Rungs are checked against sane
+//! limits before anything is encoded.
+
+use std::path::Path;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::RenditionSpec;
+
+/// Most rungs a ladder may have
+const MAX_RUNGS: usize = 12;
+/// Rung height limits (144p to 8K)
+const HEIGHT_RANGE: (u32, u32) = (144, 4320);
+/// Rung width limit (8K)
+const MAX_WIDTH: u32 = 7680;
+/// Video bitrate limits in bits per second
+const VIDEO_BITRATE_RANGE: (u32, u32) = (100_000, 100_000_000);
+/// Frame rate limits
+const FRAMERATE_RANGE: (u32, u32) = (1, 120);
+/// Audio bitrate limits in bits per second
+const AUDIO_BITRATE_RANGE: (u32, u32) = (32_000, 512_000);
+
+/// Video codec of a rung
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    #[serde(alias = "h265")]
+    Hevc,
+    Av1,
+}
+
+impl VideoCodec {
+    /// FFmpeg encoder
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            Self::H264 => "libx264",
+            Self::Hevc => "libx265",
+            Self::Av1 => "libsvtav1",
+        }
+    }
+
+    /// RFC 6381 codec string for the HLS `CODECS` attribute
+    ///
+    /// The level is the lowest that covers the rung's resolution at up to
+    /// 60 fps; H.264 rungs are encoded at that level explicitly.
+    pub fn codec_string(&self, height: u32) -> String {
+        match self {
+            Self::H264 => format!("avc1.6400{:02x}", h264_level(height)),
+            Self::Hevc => {
+                // general_level_idc is 30 times the level
+                let level = match height {
+                    0..=720 => 120,
+                    721..=1080 => 123,
+                    1081..=2160 => 153,
+                    _ => 183,
+                };
+                format!("hvc1.1.6.L{}.B0", level)
+            }
+            Self::Av1 => {
+                // seq_level_idx: 4.0, 4.1, 5.1, 6.1
+                let level = match height {
+                    0..=720 => 8,
+                    721..=1080 => 9,
+                    1081..=2160 => 13,
+                    _ => 17,
+                };
+                format!("av01.0.{:02}M.08", level)
+            }
+        }
+    }
+
+    /// Whether the codec needs fragmented MP4 segments in HLS
+    pub fn needs_fmp4(&self) -> bool {
+        !matches!(self, Self::H264)
+    }
+}
+
+/// H.264 level (times ten) covering `height` at up to 60 fps
+pub fn h264_level(height: u32) -> u32 {
+    match height {
+        0..=576 => 31,
+        577..=720 => 32,
+        721..=1080 => 42,
+        1081..=1440 => 51,
+        _ => 52,
+    }
+}
+
+/// Audio codec of the audio rendition
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AudioCodec {
+    #[default]
+    Aac,
+    Opus,
+    Ac3,
+    Eac3,
+}
+
+impl AudioCodec {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "aac" => Some(Self::Aac),
+            "opus" => Some(Self::Opus),
+            "ac3" | "ac-3" => Some(Self::Ac3),
+            "eac3" | "e-ac-3" | "ec-3" => Some(Self::Eac3),
+            _ => None,
+        }
+    }
+
+    /// FFmpeg encoder
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            Self::Aac => "aac",
+            Self::Opus => "libopus",
+            Self::Ac3 => "ac3",
+            Self::Eac3 => "eac3",
+        }
+    }
+
+    /// RFC 6381 codec string for the HLS `CODECS` attribute
+    pub fn codec_string(&self) -> &'static str {
+        match self {
+            Self::Aac => "mp4a.40.2",
+            Self::Opus => "opus",
+            Self::Ac3 => "ac-3",
+            Self::Eac3 => "ec-3",
+        }
+    }
+
+    /// Whether the codec needs fragmented MP4 segments in HLS
+    pub fn needs_fmp4(&self) -> bool {
+        matches!(self, Self::Opus)
+    }
+}
+
+/// Audio encoding settings shared by every rendition
+#[derive(Debug, Clone, Copy)]
+pub struct AudioSettings {
+    pub codec: AudioCodec,
+    /// Bitrate in bits per second
+    pub bitrate: u32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self { codec: AudioCodec::Aac, bitrate: 128_000 }
+    }
+}
+
+impl AudioSettings {
+    /// Build settings from `--audio-codec` and `--audio-bitrate` values
+    pub fn parse(codec: &str, bitrate: &str) -> Result<Self> {
+        let codec = AudioCodec::from_str(codec)
+            .with_context(|| format!("Unknown audio codec '{}' (expected aac, opus, ac3 or eac3)", codec))?;
+        let bitrate = parse_bitrate(bitrate)?;
+        if !(AUDIO_BITRATE_RANGE.0..=AUDIO_BITRATE_RANGE.1).contains(&bitrate) {
+            bail!("Audio bitrate {}k is outside {}k-{}k",
+                bitrate / 1000, AUDIO_BITRATE_RANGE.0 / 1000, AUDIO_BITRATE_RANGE.1 / 1000);
+        }
+        Ok(Self { codec, bitrate })
+    }
+}
+
+/// Parse a bitrate like "128k", "2.5M" or "96000" into bits per second
+pub fn parse_bitrate(s: &str) -> Result<u32> {
+    let s = s.trim();
+    let (number, scale) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1_000.0),
+        Some((i, 'm' | 'M')) => (&s[..i], 1_000_000.0),
+        _ => (s, 1.0),
+    };
+    let value: f64 = number.parse()
+        .with_context(|| format!("Invalid bitrate '{}'", s))?;
+    if !value.is_finite() || value <= 0.0 || value * scale > u32::MAX as f64 {
+        bail!("Invalid bitrate '{}'", s);
+    }
+    Ok((value * scale).round() as u32)
+}
+
+/// Video rungs loaded from a ladder file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ladder {
+    pub rungs: Vec<RenditionSpec>,
+}
+
+impl Ladder {
+    /// Load and validate a ladder from a `.json` or `.toml` file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read ladder file {}", path.display()))?;
+
+        let is_toml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        let mut ladder: Ladder = if is_toml {
+            toml::from_str(&text).with_context(|| format!("Invalid TOML ladder {}", path.display()))?
+        } else {
+            serde_json::from_str(&text).with_context(|| format!("Invalid JSON ladder {}", path.display()))?
+        };
+
+        ladder.validate()?;
+        ladder.rungs.sort_by_key(|r| (r.height, r.bitrate));
+        Ok(ladder)
+    }
+
+    /// Check rung count, dimensions, bitrates and frame rates
+    pub fn validate(&self) -> Result<()> {
+        if self.rungs.is_empty() {
+            bail!("Ladder has no rungs");
+        }
+        if self.rungs.len() > MAX_RUNGS {
+            bail!("Ladder has {} rungs; at most {} are supported", self.rungs.len(), MAX_RUNGS);
+        }
+
+        for (i, r) in self.rungs.iter().enumerate() {
+            let rung = format!("Rung {} ({}p @ {}k)", i + 1, r.height, r.bitrate / 1000);
+            if !(HEIGHT_RANGE.0..=HEIGHT_RANGE.1).contains(&r.height) || r.height % 2 != 0 {
+                bail!("{}: height must be even and within {}-{}", rung, HEIGHT_RANGE.0, HEIGHT_RANGE.1);
+            }
+            if let Some(width) = r.width {
+                if width == 0 || width > MAX_WIDTH || width % 2 != 0 {
+                    bail!("{}: width {} must be even and at most {}", rung, width, MAX_WIDTH);
+                }
+            }
+            if !(VIDEO_BITRATE_RANGE.0..=VIDEO_BITRATE_RANGE.1).contains(&r.bitrate) {
+                bail!("{}: bitrate must be within {}k-{}k",
+                    rung, VIDEO_BITRATE_RANGE.0 / 1000, VIDEO_BITRATE_RANGE.1 / 1000);
+            }
+            if !(FRAMERATE_RANGE.0..=FRAMERATE_RANGE.1).contains(&r.framerate) {
+                bail!("{}: framerate {} must be within {}-{}", rung, r.framerate, FRAMERATE_RANGE.0, FRAMERATE_RANGE.1);
+            }
+            if self.rungs[..i].iter().any(|other| other.height == r.height && other.bitrate == r.bitrate) {
+                bail!("{}: duplicate rung", rung);
+            }
+        }
+
+        Ok(())
+    }
+}
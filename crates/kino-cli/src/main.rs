@@ -7,14 +7,22 @@
 //! - ABR ladder analysis
 //! - DRM testing
 //! - FFmpeg encoding pipeline
+//! - Local HTTP API over the frequency pipeline
 
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 mod commands;
+mod compare;
 mod encoding;
 mod frequency;
+mod jobs;
+mod ladder;
+mod monitor;
 mod output;
+mod overlay;
+mod probe;
+mod serve;
 
 /// Kino CLI - Video streaming toolkit
 #[derive(Parser)]
@@ -55,6 +63,18 @@ enum Commands {
         /// Test all renditions
         #[arg(short, long)]
         all_renditions: bool,
+
+        /// Header for AES-128 key requests ("Name: value"), repeatable
+        #[arg(long = "key-header")]
+        key_headers: Vec<String>,
+
+        /// Percent a rendition's measured bitrate may exceed its declared BANDWIDTH
+        #[arg(long, default_value = "10")]
+        bitrate_tolerance: f64,
+
+        /// Maximum renditions probed in parallel
+        #[arg(short, long, default_value = "4")]
+        concurrency: usize,
     },
 
     /// Run QC checks on a stream
@@ -69,6 +89,14 @@ enum Commands {
         /// Fail on warnings
         #[arg(long)]
         strict: bool,
+
+        /// Header for AES-128 key requests ("Name: value"), repeatable
+        #[arg(long = "key-header")]
+        key_headers: Vec<String>,
+
+        /// Sidecar SHA-256 URL relative to each segment, e.g. "{name}.sha256"
+        #[arg(long)]
+        checksum_template: Option<String>,
     },
 
     /// Extract analytics/metadata
@@ -88,6 +116,18 @@ enum Commands {
 
         /// Second manifest URL
         manifest2: String,
+
+        /// Percent matched renditions' BANDWIDTH may differ
+        #[arg(long, default_value = "10")]
+        bandwidth_tolerance: f64,
+
+        /// Seconds segment durations and boundaries may differ
+        #[arg(long, default_value = "0.1")]
+        duration_tolerance: f64,
+
+        /// Lowest severity that fails the comparison (info, warning, error)
+        #[arg(long, default_value = "warning")]
+        fail_on: String,
     },
 
     /// Monitor a live stream
@@ -102,6 +142,30 @@ enum Commands {
         /// Duration to monitor (0 = indefinite)
         #[arg(short, long, default_value = "0")]
         duration: u64,
+
+        /// Refreshes without new segments before a playlist counts as stalled
+        #[arg(long, default_value = "3")]
+        stall_refreshes: u32,
+
+        /// Discontinuities within the window that raise a burst alert
+        #[arg(long, default_value = "3")]
+        discontinuity_burst: u32,
+
+        /// Number of refreshes discontinuities are counted over
+        #[arg(long, default_value = "10")]
+        discontinuity_window: usize,
+
+        /// Seconds a segment may exceed the target duration by
+        #[arg(long, default_value = "0.5")]
+        target_tolerance: f64,
+
+        /// Webhook URL that receives each alert as JSON
+        #[arg(long)]
+        alert_webhook: Option<String>,
+
+        /// Write one CSV row of metrics per refresh to this file
+        #[arg(long)]
+        csv: Option<PathBuf>,
     },
 
     /// Encode video to HLS/DASH
@@ -124,6 +188,41 @@ enum Commands {
         /// Segment duration in seconds
         #[arg(short, long)]
         segment_duration: Option<f64>,
+
+        /// Custom ABR ladder (.json or .toml) used instead of the preset
+        #[arg(long)]
+        ladder: Option<PathBuf>,
+
+        /// Audio bitrate (e.g. 128k)
+        #[arg(long, default_value = "128k")]
+        audio_bitrate: String,
+
+        /// Audio codec (aac, opus, ac3, eac3)
+        #[arg(long, default_value = "aac")]
+        audio_codec: String,
+
+        /// WebVTT file muxed into HLS as a subtitle group
+        #[arg(long)]
+        subtitles: Option<PathBuf>,
+
+        /// Also produce an audio-only HLS variant for background listening
+        #[arg(long)]
+        audio_only: bool,
+
+        #[command(flatten)]
+        overlay: Box<overlay::OverlayArgs>,
+
+        /// Print the FFmpeg command(s) and playlists without encoding
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Number of HLS renditions to encode in parallel
+        #[arg(short, long, default_value = "2")]
+        jobs: usize,
+
+        /// Re-encode renditions already completed by an earlier run
+        #[arg(long)]
+        force: bool,
     },
 
     /// Show encoding presets
@@ -149,6 +248,46 @@ enum Commands {
         /// Output as JSON
         #[arg(long)]
         json: bool,
+
+        /// Write a spectrogram PNG to this path
+        #[arg(long)]
+        spectrogram: Option<PathBuf>,
+
+        /// Spectrogram colormap (viridis, grayscale)
+        #[arg(long, default_value = "viridis")]
+        colormap: String,
+
+        /// Use a logarithmic frequency axis for the spectrogram
+        #[arg(long)]
+        log_frequency: bool,
+
+        #[command(flatten)]
+        audio: AudioArgs,
+    },
+
+    /// Filter audio to a frequency band or its dominant frequencies
+    Filter {
+        /// Input video or WAV file
+        input: PathBuf,
+
+        /// Output WAV file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Lower edge of the band to keep, in Hz
+        #[arg(long, requires = "high")]
+        low: Option<f32>,
+
+        /// Upper edge of the band to keep, in Hz
+        #[arg(long, requires = "low")]
+        high: Option<f32>,
+
+        /// Keep only the K dominant frequencies instead of a band
+        #[arg(short = 'k', long, conflicts_with_all = ["low", "high"], required_unless_present = "low")]
+        top_k: Option<usize>,
+
+        #[command(flatten)]
+        audio: AudioArgs,
     },
 
     /// Generate or verify audio fingerprint
@@ -163,6 +302,9 @@ enum Commands {
         /// Verify against existing hash
         #[arg(long)]
         verify: Option<String>,
+
+        #[command(flatten)]
+        audio: AudioArgs,
     },
 
     /// Auto-tag content based on audio analysis
@@ -177,6 +319,35 @@ enum Commands {
         /// Minimum confidence threshold (0-1)
         #[arg(short = 'c', long, default_value = "0.3")]
         min_confidence: f32,
+
+        /// Print a timeline of tagged segments instead of whole-file tags
+        #[arg(long)]
+        segments: bool,
+
+        /// Analysis window for --segments, in seconds
+        #[arg(long, default_value = "10")]
+        window: f64,
+
+        /// Also estimate audio quality (bandwidth ceiling, bitrate class, noise floor)
+        #[arg(long)]
+        quality: bool,
+
+        /// Tag taxonomy JSON file (default: built-in taxonomy)
+        #[arg(long)]
+        taxonomy: Option<PathBuf>,
+
+        /// Print tag names localized for this locale, e.g. "es" or "pt-BR"
+        #[arg(long)]
+        locale: Option<String>,
+
+        #[command(flatten)]
+        audio: AudioArgs,
+    },
+
+    /// List the audio tracks of a media file, for --audio-track
+    Tracks {
+        /// Input video file
+        input: PathBuf,
     },
 
     /// Select optimal thumbnail timestamp
@@ -193,6 +364,81 @@ enum Commands {
         candidates: usize,
     },
 
+    /// Suggest chapters at silences over black or static frames
+    Chapters {
+        /// Input video file
+        input: PathBuf,
+
+        /// Output chapters file (printed if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Write chapters as JSON instead of WebVTT
+        #[arg(long)]
+        json: bool,
+
+        /// Minimum silence length for a break, in seconds
+        #[arg(long, default_value = "0.5")]
+        min_silence: f64,
+
+        /// Minimum chapter length, in seconds
+        #[arg(long, default_value = "30")]
+        min_chapter: f64,
+    },
+
+    /// Find the intro and outro shared by the episodes of a series
+    Introdetect {
+        /// Episode video files
+        #[arg(long, num_args = 2.., required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Write the markers as JSON to this file (printed if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Also write a WebVTT chapters file per episode to this directory
+        #[arg(long)]
+        chapters: Option<PathBuf>,
+
+        /// Seconds searched from the start for the intro and from the end for the outro
+        #[arg(long, default_value = "300")]
+        search: f64,
+
+        /// Shortest intro or outro, in seconds
+        #[arg(long, default_value = "10")]
+        min_duration: f64,
+
+        /// Fraction of an episode's audio in the region that must match another episode (0-1)
+        #[arg(long, default_value = "0.3")]
+        min_similarity: f32,
+
+        /// Only look for intros
+        #[arg(long)]
+        no_outro: bool,
+    },
+
+    /// Check audio-video sync drift through a video
+    Syncheck {
+        /// Input video file
+        input: PathBuf,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Offset or drift that flags the video for review, in milliseconds
+        #[arg(long, default_value = "45")]
+        threshold_ms: f64,
+
+        /// Number of windows measured through the video
+        #[arg(long, default_value = "3")]
+        checkpoints: usize,
+
+        /// Length of each window, in seconds
+        #[arg(long, default_value = "10")]
+        window: f64,
+    },
+
     /// Find similar content in a library
     Similar {
         /// Input video file to match
@@ -227,9 +473,92 @@ enum Commands {
         /// Skip thumbnail selection
         #[arg(long)]
         skip_thumbnail: bool,
+
+        /// Rebuild process_manifest.json from the artifacts already in --output
+        /// without re-running analysis
+        #[arg(long)]
+        manifest_only: bool,
+
+        /// Time buckets in the seek-bar overview (overview.json)
+        #[arg(long, default_value = "1000")]
+        overview_buckets: usize,
+
+        /// Content id to record instead of deriving one from the fingerprint
+        /// (or from the file's SHA-256 with --skip-fingerprint)
+        #[arg(long)]
+        content_id: Option<String>,
+
+        #[command(flatten)]
+        audio: AudioArgs,
+    },
+
+    /// Serve the frequency pipeline as a local HTTP API
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value = "8080")]
+        port: u16,
+
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Directory whose files may be named by path (repeatable; uploads only if omitted)
+        #[arg(long = "root")]
+        roots: Vec<PathBuf>,
+
+        /// Requests analyzed at once; the rest wait for a free slot
+        #[arg(short, long, default_value = "2")]
+        jobs: usize,
+
+        /// Seconds a request may take, including its upload and wait
+        #[arg(long, default_value = "300")]
+        timeout: u64,
+
+        /// Largest accepted upload in megabytes
+        #[arg(long, default_value = "2048")]
+        max_upload_mb: usize,
+
+        /// Longest media /analyze, /tags and /thumbnail accept, in seconds
+        #[arg(long, default_value = "3600")]
+        max_duration: f64,
     },
 }
 
+/// Audio track and time range of the frequency commands
+#[derive(clap::Args)]
+struct AudioArgs {
+    /// Audio stream to analyze: its index (see `tracks`) or a language such as "eng"
+    #[arg(long)]
+    audio_track: Option<String>,
+
+    /// Seconds into the media to start analyzing at
+    #[arg(long)]
+    start: Option<f64>,
+
+    /// Seconds of audio to analyze (default: to the end)
+    #[arg(long)]
+    duration: Option<f64>,
+}
+
+impl AudioArgs {
+    fn options(&self) -> kino_frequency::AudioExtractOptions {
+        let (stream_index, language) = match &self.audio_track {
+            Some(track) => match track.parse() {
+                Ok(index) => (Some(index), None),
+                Err(_) => (None, Some(track.clone())),
+            },
+            None => (None, None),
+        };
+        kino_frequency::AudioExtractOptions {
+            stream_index,
+            language,
+            start: self.start,
+            duration: self.duration,
+            sample_rate_override: None,
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
@@ -244,28 +573,73 @@ async fn main() -> anyhow::Result<()> {
         Commands::Analyze { manifest } => {
             commands::analyze(&manifest, &cli.format).await?;
         }
-        Commands::Validate { manifest, segments, all_renditions } => {
-            commands::validate(&manifest, segments, all_renditions, &cli.format).await?;
+        Commands::Validate { manifest, segments, all_renditions, key_headers, bitrate_tolerance, concurrency } => {
+            commands::validate(
+                &manifest,
+                segments,
+                all_renditions,
+                &key_headers,
+                bitrate_tolerance,
+                concurrency,
+                &cli.format,
+            ).await?;
         }
-        Commands::Qc { manifest, output, strict } => {
-            commands::qc(&manifest, output, strict, &cli.format).await?;
+        Commands::Qc { manifest, output, strict, key_headers, checksum_template } => {
+            commands::qc(&manifest, output, strict, &key_headers, checksum_template, &cli.format).await?;
         }
         Commands::Extract { manifest, what } => {
             commands::extract(&manifest, &what, &cli.format).await?;
         }
-        Commands::Compare { manifest1, manifest2 } => {
-            commands::compare(&manifest1, &manifest2, &cli.format).await?;
+        Commands::Compare { manifest1, manifest2, bandwidth_tolerance, duration_tolerance, fail_on } => {
+            let options = compare::CompareOptions { bandwidth_tolerance, duration_tolerance };
+            commands::compare(&manifest1, &manifest2, options, &fail_on, &cli.format).await?;
         }
-        Commands::Monitor { manifest, interval, duration } => {
-            commands::monitor(&manifest, interval, duration, &cli.format).await?;
+        Commands::Monitor {
+            manifest,
+            interval,
+            duration,
+            stall_refreshes,
+            discontinuity_burst,
+            discontinuity_window,
+            target_tolerance,
+            alert_webhook,
+            csv,
+        } => {
+            let thresholds = monitor::MonitorThresholds {
+                stall_refreshes,
+                discontinuity_burst,
+                discontinuity_window,
+                target_tolerance,
+            };
+            commands::monitor(&manifest, interval, duration, thresholds, alert_webhook.as_deref(), csv.as_deref(), &cli.format).await?;
         }
-        Commands::Encode { input, output, format, preset, segment_duration } => {
+        Commands::Encode {
+            input,
+            output,
+            format,
+            preset,
+            segment_duration,
+            ladder,
+            audio_bitrate,
+            audio_codec,
+            subtitles,
+            audio_only,
+            overlay,
+            dry_run,
+            jobs,
+            force,
+        } => {
+            // Check the overlay images before spending time on anything else
+            let overlays = overlay.overlays()?;
+
             // Check FFmpeg
-            match encoding::check_ffmpeg() {
-                Ok(version) => println!("Using: {}", version),
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    std::process::exit(1);
+            if !dry_run {
+                match encoding::check_ffmpeg() {
+                    Ok(version) => println!("Using: {}", version),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
                 }
             }
 
@@ -277,21 +651,35 @@ async fn main() -> anyhow::Result<()> {
 
             let seg_dur = segment_duration.unwrap_or(enc_preset.segment_duration());
 
+            let mut options = encoding::EncodeOptions::from_preset(enc_preset, seg_dur);
+            if let Some(path) = &ladder {
+                let ladder = ladder::Ladder::load(path)?;
+                options.label = format!("custom ladder {} ({} rungs)", path.display(), ladder.rungs.len());
+                options.renditions = ladder.rungs;
+            }
+            options.audio = ladder::AudioSettings::parse(&audio_codec, &audio_bitrate)?;
+            options.subtitles = subtitles;
+            options.audio_only = audio_only;
+            options.overlays = overlays;
+            options.dry_run = dry_run;
+            options.jobs = jobs;
+            options.force = force;
+
             let output_format = encoding::OutputFormat::from_str(&format)
                 .unwrap_or(encoding::OutputFormat::Hls);
 
             match output_format {
                 encoding::OutputFormat::Hls => {
-                    encoding::encode_hls(&input, &output, enc_preset, seg_dur, None)?;
+                    encoding::encode_hls(&input, &output, &options, None)?;
                 }
                 encoding::OutputFormat::Dash => {
-                    encoding::encode_dash(&input, &output, enc_preset, seg_dur)?;
+                    encoding::encode_dash(&input, &output, &options)?;
                 }
                 encoding::OutputFormat::Both => {
                     let hls_dir = output.join("hls");
                     let dash_dir = output.join("dash");
-                    encoding::encode_hls(&input, &hls_dir, enc_preset, seg_dur, None)?;
-                    encoding::encode_dash(&input, &dash_dir, enc_preset, seg_dur)?;
+                    encoding::encode_hls(&input, &hls_dir, &options, None)?;
+                    encoding::encode_dash(&input, &dash_dir, &options)?;
                 }
             }
         }
@@ -304,23 +692,81 @@ async fn main() -> anyhow::Result<()> {
         }
 
         // Frequency analysis commands
-        Commands::Frequency { input, top_k, json } => {
-            frequency::analyze_frequency(&input, top_k, json).await?;
+        Commands::Frequency { input, top_k, json, spectrogram, colormap, log_frequency, audio } => {
+            let options = frequency::spectrogram_options(&colormap, log_frequency)?;
+            frequency::analyze_frequency(&input, top_k, json, cli.format == "json", spectrogram, options, &audio.options()).await?;
+        }
+        Commands::Filter { input, output, low, high, top_k, audio } => {
+            let mode = match (low, high, top_k) {
+                (Some(low), Some(high), None) => frequency::FilterMode::Band { low, high },
+                (None, None, Some(top_k)) => frequency::FilterMode::Dominant(top_k),
+                _ => anyhow::bail!("Pass either --low and --high, or --top-k"),
+            };
+            frequency::filter(&input, &output, mode, &audio.options()).await?;
         }
-        Commands::Fingerprint { input, output, verify } => {
-            frequency::fingerprint(&input, output, verify).await?;
+        Commands::Fingerprint { input, output, verify, audio } => {
+            frequency::fingerprint(&input, output, verify, cli.format == "json", &audio.options()).await?;
         }
-        Commands::Autotag { input, max_tags, min_confidence } => {
-            frequency::autotag(&input, max_tags, min_confidence).await?;
+        Commands::Autotag { input, max_tags, min_confidence, segments, window, quality, taxonomy, locale, audio } => {
+            let (taxonomy, locale, audio) = (taxonomy.as_deref(), locale.as_deref(), audio.options());
+            if segments {
+                frequency::autotag_segments(&input, max_tags, min_confidence, window, quality, taxonomy, locale, &audio).await?;
+            } else {
+                frequency::autotag(&input, max_tags, min_confidence, quality, taxonomy, locale, cli.format == "json", &audio).await?;
+            }
+        }
+        Commands::Tracks { input } => {
+            frequency::tracks(&input, cli.format == "json")?;
         }
         Commands::Thumbnail { input, output, candidates } => {
-            frequency::thumbnail(&input, output, candidates).await?;
+            frequency::thumbnail(&input, output, candidates, cli.format == "json").await?;
+        }
+        Commands::Chapters { input, output, json, min_silence, min_chapter } => {
+            frequency::chapters(&input, output, json, min_silence, min_chapter).await?;
+        }
+        Commands::Introdetect { inputs, output, chapters, search, min_duration, min_similarity, no_outro } => {
+            frequency::introdetect(&inputs, output, chapters, search, min_duration, min_similarity, !no_outro).await?;
+        }
+        Commands::Syncheck { input, json, threshold_ms, checkpoints, window } => {
+            frequency::syncheck(&input, json, threshold_ms, checkpoints, window).await?;
         }
         Commands::Similar { input, library, limit } => {
             frequency::similar(&input, &library, limit).await?;
         }
-        Commands::Process { input, output, skip_fingerprint, skip_tags, skip_thumbnail } => {
-            frequency::process(&input, &output, skip_fingerprint, skip_tags, skip_thumbnail).await?;
+        Commands::Process {
+            input,
+            output,
+            skip_fingerprint,
+            skip_tags,
+            skip_thumbnail,
+            manifest_only,
+            overview_buckets,
+            content_id,
+            audio,
+        } => {
+            frequency::process(
+                &input,
+                &output,
+                skip_fingerprint,
+                skip_tags,
+                skip_thumbnail,
+                manifest_only,
+                overview_buckets,
+                content_id.as_deref(),
+                &audio.options(),
+            ).await?;
+        }
+
+        Commands::Serve { port, host, roots, jobs, timeout, max_upload_mb, max_duration } => {
+            serve::serve(serve::ServeOptions {
+                host,
+                port,
+                roots,
+                jobs,
+                timeout: std::time::Duration::from_secs(timeout),
+                max_upload_bytes: max_upload_mb * 1024 * 1024,
+                max_duration_secs: max_duration,
+            }).await?;
         }
     }
 
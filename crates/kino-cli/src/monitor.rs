@@ -0,0 +1,324 @@
+//! Live stream health monitoring
+//!
+//! Keeps rolling state across manifest refreshes and raises alerts for:
+//! - Media sequence going backwards
+//! - Segments longer than the target duration
+//! - Playlists that stop advancing (stalled encoder)
+//! - Playlists a CDN keeps serving from a stale cached copy
+//! - Bursts of discontinuities
+//! - Renditions disappearing from the master playlist
+
+use kino_core::{PlaylistFreshness, Rendition, Segment};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+
+/// Thresholds for raising alerts
+#[derive(Debug, Clone)]
+pub struct MonitorThresholds {
+    /// Refreshes without new segments before a playlist counts as stalled
+    pub stall_refreshes: u32,
+    /// Discontinuities within the window that make up a burst
+    pub discontinuity_burst: u32,
+    /// Number of refreshes the discontinuity burst is counted over
+    pub discontinuity_window: usize,
+    /// Allowed overshoot of the target duration, in seconds
+    pub target_tolerance: f64,
+}
+
+impl Default for MonitorThresholds {
+    fn default() -> Self {
+        Self {
+            stall_refreshes: 3,
+            discontinuity_burst: 3,
+            discontinuity_window: 10,
+            target_tolerance: 0.5,
+        }
+    }
+}
+
+/// Kinds of incidents the monitor can detect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IncidentKind {
+    FetchError,
+    SequenceRegression,
+    TargetDurationExceeded,
+    Stalled,
+    CacheStale,
+    DiscontinuityBurst,
+    RenditionMissing,
+}
+
+impl IncidentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IncidentKind::FetchError => "fetch_error",
+            IncidentKind::SequenceRegression => "sequence_regression",
+            IncidentKind::TargetDurationExceeded => "target_duration_exceeded",
+            IncidentKind::Stalled => "stalled",
+            IncidentKind::CacheStale => "cache_stale",
+            IncidentKind::DiscontinuityBurst => "discontinuity_burst",
+            IncidentKind::RenditionMissing => "rendition_missing",
+        }
+    }
+}
+
+/// A single alert, serialized as-is for webhooks
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub kind: IncidentKind,
+    pub manifest: String,
+    pub rendition: Option<String>,
+    pub message: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Summary of a monitoring run
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorSummary {
+    pub manifest: String,
+    pub elapsed_secs: u64,
+    pub refreshes: u64,
+    pub healthy_refreshes: u64,
+    pub uptime_percent: f64,
+    pub incidents: BTreeMap<IncidentKind, u64>,
+}
+
+/// Playlist state carried between refreshes
+#[derive(Debug, Default)]
+struct PlaylistState {
+    /// First and last segment numbers of the previous window
+    first_sequence: u64,
+    last_sequence: u64,
+    /// Absolute discontinuity sequence of the last segment
+    last_discontinuity: u64,
+    /// Consecutive refreshes without new segments
+    unchanged_refreshes: u32,
+    /// New discontinuities per refresh, newest last
+    discontinuities: VecDeque<u32>,
+}
+
+/// Rolling monitor state for one manifest
+pub struct MonitorState {
+    manifest: String,
+    thresholds: MonitorThresholds,
+    /// Rendition URIs seen in the master, with their ids
+    known_renditions: HashMap<String, String>,
+    /// Renditions currently missing from the master
+    missing: HashMap<String, String>,
+    /// Playlist state keyed by rendition URI
+    playlists: HashMap<String, PlaylistState>,
+    refreshes: u64,
+    healthy_refreshes: u64,
+    incidents: BTreeMap<IncidentKind, u64>,
+}
+
+impl MonitorState {
+    pub fn new(manifest: &str, thresholds: MonitorThresholds) -> Self {
+        Self {
+            manifest: manifest.to_string(),
+            thresholds,
+            known_renditions: HashMap::new(),
+            missing: HashMap::new(),
+            playlists: HashMap::new(),
+            refreshes: 0,
+            healthy_refreshes: 0,
+            incidents: BTreeMap::new(),
+        }
+    }
+
+    fn alert(&mut self, kind: IncidentKind, rendition: Option<&str>, message: String) -> Alert {
+        *self.incidents.entry(kind).or_insert(0) += 1;
+        Alert {
+            kind,
+            manifest: self.manifest.clone(),
+            rendition: rendition.map(str::to_string),
+            message,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    /// Record a manifest or playlist that could not be fetched
+    pub fn observe_error(&mut self, rendition: Option<&str>, error: &str) -> Alert {
+        self.alert(IncidentKind::FetchError, rendition, format!("Fetch failed: {}", error))
+    }
+
+    /// Compare the master's renditions against those seen so far
+    pub fn observe_master(&mut self, renditions: &[Rendition]) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        let current: HashMap<String, String> = renditions
+            .iter()
+            .map(|r| (r.uri.to_string(), r.id.clone()))
+            .collect();
+
+        let mut gone: Vec<(String, String)> = self
+            .known_renditions
+            .iter()
+            .filter(|(uri, _)| !current.contains_key(*uri) && !self.missing.contains_key(*uri))
+            .map(|(uri, id)| (uri.clone(), id.clone()))
+            .collect();
+        gone.sort();
+
+        for (uri, id) in gone {
+            let message = format!("Rendition {} ({}) disappeared from the master playlist", id, uri);
+            alerts.push(self.alert(IncidentKind::RenditionMissing, Some(&id), message));
+            self.playlists.remove(&uri);
+            self.missing.insert(uri, id);
+        }
+
+        self.missing.retain(|uri, _| !current.contains_key(uri));
+        self.known_renditions.extend(current);
+        alerts
+    }
+
+    /// Check a refreshed media playlist against its previous state
+    ///
+    /// A playlist that stops advancing while a cache serves a copy older
+    /// than the target duration is reported as a stale cache rather than a
+    /// stalled encoder.
+    pub fn observe_playlist(
+        &mut self,
+        rendition: &Rendition,
+        segments: &[Segment],
+        target_duration: Duration,
+        discontinuity_base: u64,
+        freshness: &PlaylistFreshness,
+    ) -> Vec<Alert> {
+        let key = rendition.uri.to_string();
+        let rendition = rendition.id.as_str();
+        let mut alerts = Vec::new();
+        let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+            return alerts;
+        };
+        let last_discontinuity = discontinuity_base + last.discontinuity_sequence as u64;
+
+        let mut prev = self.playlists.remove(&key);
+        if let Some(p) = prev.as_ref().filter(|p| first.number < p.first_sequence) {
+            let message = format!(
+                "Media sequence went backwards: {} -> {}",
+                p.first_sequence, first.number
+            );
+            alerts.push(self.alert(IncidentKind::SequenceRegression, Some(rendition), message));
+            // The encoder restarted; treat the new window as a fresh playlist
+            prev = None;
+        }
+
+        // Segments already seen in the previous window were checked back then
+        let seen_up_to = prev.as_ref().map(|p| p.last_sequence);
+        let new_segments: Vec<&Segment> = segments
+            .iter()
+            .filter(|s| seen_up_to.is_none_or(|seen| s.number > seen))
+            .collect();
+
+        let limit = target_duration.as_secs_f64() + self.thresholds.target_tolerance;
+        for segment in &new_segments {
+            let duration = segment.duration.as_secs_f64();
+            if !target_duration.is_zero() && duration > limit {
+                let message = format!(
+                    "Segment {} is {:.3}s, target duration is {}s",
+                    segment.number, duration, target_duration.as_secs()
+                );
+                alerts.push(self.alert(IncidentKind::TargetDurationExceeded, Some(rendition), message));
+            }
+        }
+
+        let mut state = PlaylistState {
+            first_sequence: first.number,
+            last_sequence: last.number,
+            last_discontinuity,
+            ..Default::default()
+        };
+        let Some(prev) = prev else {
+            self.playlists.insert(key, state);
+            return alerts;
+        };
+
+        if new_segments.is_empty() {
+            state.unchanged_refreshes = prev.unchanged_refreshes + 1;
+            if state.unchanged_refreshes == self.thresholds.stall_refreshes {
+                let alert = if freshness.is_stale(target_duration) {
+                    let message = format!(
+                        "Cache is serving a {}s old copy stuck at segment {} for {} refreshes",
+                        freshness.age.as_secs(), last.number, state.unchanged_refreshes
+                    );
+                    self.alert(IncidentKind::CacheStale, Some(rendition), message)
+                } else {
+                    let message = format!(
+                        "Playlist has not advanced past segment {} for {} refreshes",
+                        last.number, state.unchanged_refreshes
+                    );
+                    self.alert(IncidentKind::Stalled, Some(rendition), message)
+                };
+                alerts.push(alert);
+            }
+        }
+
+        let added = last_discontinuity.saturating_sub(prev.last_discontinuity) as u32;
+        state.discontinuities = prev.discontinuities;
+        state.discontinuities.push_back(added);
+        while state.discontinuities.len() > self.thresholds.discontinuity_window.max(1) {
+            state.discontinuities.pop_front();
+        }
+        let in_window: u32 = state.discontinuities.iter().sum();
+        if added > 0 && in_window >= self.thresholds.discontinuity_burst {
+            let message = format!(
+                "{} discontinuities in the last {} refreshes",
+                in_window, state.discontinuities.len()
+            );
+            alerts.push(self.alert(IncidentKind::DiscontinuityBurst, Some(rendition), message));
+        }
+
+        self.playlists.insert(key, state);
+        alerts
+    }
+
+    /// Last segment number seen for a rendition
+    pub fn last_sequence(&self, rendition: &Rendition) -> Option<u64> {
+        self.playlists.get(rendition.uri.as_str()).map(|p| p.last_sequence)
+    }
+
+    /// Close out a refresh, returning whether it was healthy: everything
+    /// was fetched and no rendition is stalled or missing
+    pub fn finish_refresh(&mut self, fetched: bool) -> bool {
+        self.refreshes += 1;
+        let stalled = self
+            .playlists
+            .values()
+            .any(|p| p.unchanged_refreshes >= self.thresholds.stall_refreshes);
+        let healthy = fetched && !stalled && self.missing.is_empty();
+        if healthy {
+            self.healthy_refreshes += 1;
+        }
+        healthy
+    }
+
+    pub fn summary(&self, elapsed: Duration) -> MonitorSummary {
+        let uptime_percent = if self.refreshes > 0 {
+            self.healthy_refreshes as f64 / self.refreshes as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        MonitorSummary {
+            manifest: self.manifest.clone(),
+            elapsed_secs: elapsed.as_secs(),
+            refreshes: self.refreshes,
+            healthy_refreshes: self.healthy_refreshes,
+            uptime_percent,
+            incidents: self.incidents.clone(),
+        }
+    }
+}
+
+/// POST an alert to a webhook as JSON
+pub async fn send_webhook(client: &reqwest::Client, url: &str, alert: &Alert) -> anyhow::Result<()> {
+    client
+        .post(url)
+        .json(alert)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
@@ -0,0 +1,338 @@
+//! Watermark and end-card overlays for the encoding pipeline
+//!
+//! A watermark is a still image placed in a corner of the video, scaled
+//! relative to each rendition's height so it looks the same on every rung.
+//! It can be limited to a time range and to renditions within a height
+//! range (e.g. only 720p and up). An end card is a still image covering the
+//! frame for the last seconds of the video.
+//!
+//! Both are extra FFmpeg inputs composited with `overlay` filters after the
+//! rung is scaled. The overlays take their timing from the source, so
+//! segment boundaries don't move, and audio renditions never get them.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result, bail};
+use clap::Args;
+
+/// Watermark height limits, as a fraction of the output height
+const SCALE_RANGE: (f64, f64) = (0.01, 1.0);
+/// Largest margin, in percent of the output height
+const MAX_MARGIN: f64 = 25.0;
+
+/// Corner of the frame a watermark is anchored to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "top-left" => Ok(Self::TopLeft),
+            "top-right" => Ok(Self::TopRight),
+            "bottom-left" => Ok(Self::BottomLeft),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => bail!("Unknown watermark position '{}' (expected top-left, top-right, bottom-left or bottom-right)", s),
+        }
+    }
+
+    /// `overlay` filter position, `margin` pixels from the corner
+    fn position(&self, margin: u32) -> String {
+        let x = match self {
+            Self::TopLeft | Self::BottomLeft => margin.to_string(),
+            Self::TopRight | Self::BottomRight => format!("main_w-overlay_w-{}", margin),
+        };
+        let y = match self {
+            Self::TopLeft | Self::TopRight => margin.to_string(),
+            Self::BottomLeft | Self::BottomRight => format!("main_h-overlay_h-{}", margin),
+        };
+        format!("x={}:y={}", x, y)
+    }
+}
+
+/// Image burned into the corner of selected renditions
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    pub image: PathBuf,
+    pub corner: Corner,
+    /// Distance from the corner, in percent of the output height
+    pub margin: f64,
+    /// 0 (invisible) to 1 (opaque)
+    pub opacity: f64,
+    /// Watermark height as a fraction of the output height
+    pub scale: f64,
+    /// Seconds into the video the watermark appears
+    pub start: Option<f64>,
+    /// Seconds into the video the watermark disappears
+    pub end: Option<f64>,
+    /// Only watermark renditions at least this tall
+    pub min_height: Option<u32>,
+    /// Only watermark renditions at most this tall
+    pub max_height: Option<u32>,
+}
+
+impl Watermark {
+    /// Whether a rendition `height` pixels tall is watermarked
+    pub fn applies_to(&self, height: u32) -> bool {
+        self.min_height.is_none_or(|min| height >= min) && self.max_height.is_none_or(|max| height <= max)
+    }
+
+    /// `enable` expression limiting the watermark to its time range
+    fn enable(&self) -> Option<String> {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => Some(format!("between(t,{},{})", start, end)),
+            (Some(start), None) => Some(format!("gte(t,{})", start)),
+            (None, Some(end)) => Some(format!("lte(t,{})", end)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Image covering the frame for the end of the video
+#[derive(Debug, Clone)]
+pub struct EndCard {
+    pub image: PathBuf,
+    /// Seconds the end card is shown for
+    pub duration: f64,
+}
+
+/// Overlays composited onto the video renditions
+#[derive(Debug, Clone, Default)]
+pub struct Overlays {
+    pub watermark: Option<Watermark>,
+    pub endcard: Option<EndCard>,
+}
+
+/// Overlay images one FFmpeg job reads, as inputs after the source
+#[derive(Debug, Default)]
+pub struct OverlayInputs {
+    /// Images in input order, starting at input 1
+    pub images: Vec<PathBuf>,
+    watermark: Option<usize>,
+    endcard: Option<usize>,
+}
+
+impl OverlayInputs {
+    /// `-i` arguments for the images
+    pub fn args(&self) -> Vec<String> {
+        self.images.iter()
+            .flat_map(|image| ["-i".to_string(), image.to_string_lossy().to_string()])
+            .collect()
+    }
+}
+
+impl Overlays {
+    pub fn is_empty(&self) -> bool {
+        self.watermark.is_none() && self.endcard.is_none()
+    }
+
+    /// Check the settings and that the images exist in a supported format,
+    /// before anything is encoded
+    pub fn validate(&self) -> Result<()> {
+        if let Some(watermark) = &self.watermark {
+            check_image(&watermark.image).context("Invalid --watermark")?;
+            if !(watermark.opacity > 0.0 && watermark.opacity <= 1.0) {
+                bail!("Watermark opacity must be above 0 and at most 1, got {}", watermark.opacity);
+            }
+            if !(SCALE_RANGE.0..=SCALE_RANGE.1).contains(&watermark.scale) {
+                bail!("Watermark scale must be between {} and {}, got {}", SCALE_RANGE.0, SCALE_RANGE.1, watermark.scale);
+            }
+            if !(0.0..=MAX_MARGIN).contains(&watermark.margin) {
+                bail!("Watermark margin must be between 0 and {}%, got {}", MAX_MARGIN, watermark.margin);
+            }
+            for time in [watermark.start, watermark.end].into_iter().flatten() {
+                if !time.is_finite() || time < 0.0 {
+                    bail!("Watermark times must be positive, got {}", time);
+                }
+            }
+            if let (Some(start), Some(end)) = (watermark.start, watermark.end) {
+                if end <= start {
+                    bail!("Watermark end ({}s) must be after its start ({}s)", end, start);
+                }
+            }
+            if let (Some(min), Some(max)) = (watermark.min_height, watermark.max_height) {
+                if max < min {
+                    bail!("Watermark max height ({}) is below its min height ({})", max, min);
+                }
+            }
+        }
+        if let Some(endcard) = &self.endcard {
+            check_image(&endcard.image).context("Invalid --endcard")?;
+            if !endcard.duration.is_finite() || endcard.duration <= 0.0 {
+                bail!("End card duration must be positive, got {}", endcard.duration);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the end card fits in a source `duration` seconds long
+    pub fn check_duration(&self, duration: f64) -> Result<()> {
+        match &self.endcard {
+            Some(_) if duration <= 0.0 => bail!("--endcard needs the input duration, but it is unknown"),
+            Some(endcard) if endcard.duration > duration => {
+                bail!("End card ({}s) is longer than the input ({:.1}s)", endcard.duration, duration)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Images needed by a job encoding rungs of the given heights
+    pub fn inputs(&self, heights: &[u32]) -> OverlayInputs {
+        let mut inputs = OverlayInputs::default();
+        if let Some(watermark) = &self.watermark {
+            if heights.iter().any(|&h| watermark.applies_to(h)) {
+                inputs.images.push(watermark.image.clone());
+                inputs.watermark = Some(inputs.images.len());
+            }
+        }
+        if let Some(endcard) = &self.endcard {
+            inputs.images.push(endcard.image.clone());
+            inputs.endcard = Some(inputs.images.len());
+        }
+        inputs
+    }
+
+    /// Filters compositing the overlays onto rung `index`, scaled to
+    /// `width`x`height` as `[s<index>]`, producing `[v<index>]`; `None`
+    /// when no overlay applies and the scaled video is used as is
+    pub fn filter(&self, inputs: &OverlayInputs, index: usize, width: u32, height: u32, duration: f64) -> Option<String> {
+        let watermark = self.watermark.as_ref()
+            .zip(inputs.watermark)
+            .filter(|(watermark, _)| watermark.applies_to(height));
+        let endcard = self.endcard.as_ref().zip(inputs.endcard);
+
+        let mut filters: Vec<String> = Vec::new();
+        let mut current = format!("s{}", index);
+
+        if let Some((watermark, input)) = watermark {
+            let output = if endcard.is_some() { format!("w{}", index) } else { format!("v{}", index) };
+            // Even height so chroma-subsampled output scales cleanly
+            let wm_height = (((height as f64 * watermark.scale) / 2.0).round() as u32 * 2).max(2);
+            let margin = (height as f64 * watermark.margin / 100.0).round() as u32;
+            let enable = watermark.enable()
+                .map(|expr| format!(":enable='{}'", expr))
+                .unwrap_or_default();
+            filters.push(format!(
+                "[{}:v]scale=-2:{},format=rgba,colorchannelmixer=aa={}[wm{}]",
+                input, wm_height, watermark.opacity, index
+            ));
+            filters.push(format!(
+                "[{}][wm{}]overlay={}{}[{}]",
+                current, index, watermark.corner.position(margin), enable, output
+            ));
+            current = output;
+        }
+
+        if let Some((endcard, input)) = endcard {
+            // Letterboxed to the rung so any aspect ratio fits
+            filters.push(format!(
+                "[{input}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1[ec{index}]",
+                input = input, w = width, h = height, index = index
+            ));
+            filters.push(format!(
+                "[{}][ec{}]overlay=0:0:enable='gte(t,{})'[v{}]",
+                current, index, (duration - endcard.duration).max(0.0), index
+            ));
+        }
+
+        (!filters.is_empty()).then(|| filters.join(";"))
+    }
+}
+
+/// Encode command options for the overlays
+#[derive(Debug, Args)]
+pub struct OverlayArgs {
+    /// Image burned into the video renditions (PNG, JPEG, WebP or BMP)
+    #[arg(long)]
+    watermark: Option<PathBuf>,
+
+    /// Watermark corner (top-left, top-right, bottom-left, bottom-right)
+    #[arg(long, default_value = "top-right")]
+    watermark_position: String,
+
+    /// Watermark distance from the corner, in percent of the output height
+    #[arg(long, default_value = "3")]
+    watermark_margin: f64,
+
+    /// Watermark opacity (0-1)
+    #[arg(long, default_value = "0.8")]
+    watermark_opacity: f64,
+
+    /// Watermark height as a fraction of the output height
+    #[arg(long, default_value = "0.1")]
+    watermark_scale: f64,
+
+    /// Seconds into the video the watermark appears
+    #[arg(long)]
+    watermark_start: Option<f64>,
+
+    /// Seconds into the video the watermark disappears
+    #[arg(long)]
+    watermark_end: Option<f64>,
+
+    /// Only watermark renditions at least this tall (e.g. 720)
+    #[arg(long)]
+    watermark_min_height: Option<u32>,
+
+    /// Only watermark renditions at most this tall
+    #[arg(long)]
+    watermark_max_height: Option<u32>,
+
+    /// Image shown full-frame over the end of the video
+    #[arg(long)]
+    endcard: Option<PathBuf>,
+
+    /// Seconds the end card is shown for
+    #[arg(long, default_value = "5")]
+    endcard_duration: f64,
+}
+
+impl OverlayArgs {
+    /// Overlays to composite, validated
+    pub fn overlays(&self) -> Result<Overlays> {
+        let watermark = match &self.watermark {
+            Some(image) => Some(Watermark {
+                image: image.clone(),
+                corner: Corner::parse(&self.watermark_position)?,
+                margin: self.watermark_margin,
+                opacity: self.watermark_opacity,
+                scale: self.watermark_scale,
+                start: self.watermark_start,
+                end: self.watermark_end,
+                min_height: self.watermark_min_height,
+                max_height: self.watermark_max_height,
+            }),
+            None => None,
+        };
+        let overlays = Overlays {
+            watermark,
+            endcard: self.endcard.clone().map(|image| EndCard { image, duration: self.endcard_duration }),
+        };
+        overlays.validate()?;
+        Ok(overlays)
+    }
+}
+
+/// Check `path` is a PNG, JPEG, WebP or BMP image by its contents
+fn check_image(path: &Path) -> Result<()> {
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open image {}", path.display()))?;
+    let mut header = [0u8; 12];
+    let read = file.read(&mut header)
+        .with_context(|| format!("Failed to read image {}", path.display()))?;
+    let header = &header[..read];
+
+    let supported = header.starts_with(b"\x89PNG\r\n\x1a\n")
+        || header.starts_with(&[0xff, 0xd8, 0xff])
+        || (header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WEBP"))
+        || header.starts_with(b"BM");
+    if !supported {
+        bail!("{} is not a PNG, JPEG, WebP or BMP image", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,248 @@
+//! Segment download probing for ABR ladder validation
+//!
+//! Downloads sampled segments of each rendition, measuring time-to-first-byte
+//! and transfer rate, and compares the real segment bitrate with the
+//! BANDWIDTH declared in the manifest.
+
+use kino_core::{EncryptionMethod, HttpFetcher, ManifestParser, Rendition, Segment, SegmentDecryptor};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Verdict for one rendition of the ladder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Pass => "PASS",
+            Verdict::Warn => "WARN",
+            Verdict::Fail => "FAIL",
+        }
+    }
+}
+
+/// Measurements for a single downloaded segment
+#[derive(Debug, Clone, Serialize)]
+pub struct SegmentProbe {
+    pub number: u64,
+    pub bytes: u64,
+    pub duration_secs: f64,
+    pub ttfb_ms: f64,
+    pub transfer_ms: f64,
+}
+
+impl SegmentProbe {
+    /// Bitrate of the segment's media, in bits per second
+    pub fn bitrate(&self) -> f64 {
+        if self.duration_secs > 0.0 {
+            self.bytes as f64 * 8.0 / self.duration_secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Probe results for one rendition
+#[derive(Debug, Clone, Serialize)]
+pub struct RenditionProbe {
+    pub id: String,
+    pub declared_bandwidth: u64,
+    pub segments_tested: usize,
+    pub segments_passed: usize,
+    /// Average media bitrate over the sampled segments (bits/s)
+    pub measured_bitrate: u64,
+    /// Highest single-segment bitrate (bits/s)
+    pub peak_bitrate: u64,
+    /// Download throughput over the sampled segments (bits/s)
+    pub throughput: u64,
+    pub avg_ttfb_ms: f64,
+    pub min_segment_bytes: u64,
+    pub avg_segment_bytes: u64,
+    pub max_segment_bytes: u64,
+    pub verdict: Verdict,
+    pub issues: Vec<String>,
+}
+
+impl RenditionProbe {
+    fn new(rendition: &Rendition) -> Self {
+        Self {
+            id: rendition.id.clone(),
+            declared_bandwidth: rendition.bandwidth,
+            segments_tested: 0,
+            segments_passed: 0,
+            measured_bitrate: 0,
+            peak_bitrate: 0,
+            throughput: 0,
+            avg_ttfb_ms: 0.0,
+            min_segment_bytes: 0,
+            avg_segment_bytes: 0,
+            max_segment_bytes: 0,
+            verdict: Verdict::Pass,
+            issues: Vec::new(),
+        }
+    }
+
+    /// Fill in aggregate stats and decide the verdict
+    ///
+    /// Any failed download fails the rendition. An average bitrate above the
+    /// declared BANDWIDTH (plus tolerance) fails it too, since ABR logic will
+    /// pick a rendition the connection can't sustain; a single segment peaking
+    /// above it is only a warning.
+    fn finish(&mut self, probes: &[SegmentProbe], tolerance: f64) {
+        self.segments_passed = probes.len();
+
+        if !probes.is_empty() {
+            let total_bytes: u64 = probes.iter().map(|p| p.bytes).sum();
+            let total_duration: f64 = probes.iter().map(|p| p.duration_secs).sum();
+            let total_transfer_ms: f64 = probes.iter().map(|p| p.transfer_ms).sum();
+
+            if total_duration > 0.0 {
+                self.measured_bitrate = (total_bytes as f64 * 8.0 / total_duration) as u64;
+            }
+            if total_transfer_ms > 0.0 {
+                self.throughput = (total_bytes as f64 * 8.0 / (total_transfer_ms / 1000.0)) as u64;
+            }
+            self.peak_bitrate = probes.iter().map(|p| p.bitrate() as u64).max().unwrap_or(0);
+            self.avg_ttfb_ms = probes.iter().map(|p| p.ttfb_ms).sum::<f64>() / probes.len() as f64;
+            self.min_segment_bytes = probes.iter().map(|p| p.bytes).min().unwrap_or(0);
+            self.max_segment_bytes = probes.iter().map(|p| p.bytes).max().unwrap_or(0);
+            self.avg_segment_bytes = total_bytes / probes.len() as u64;
+        }
+
+        if self.segments_passed < self.segments_tested || self.segments_tested == 0 {
+            self.verdict = Verdict::Fail;
+        }
+
+        // Bandwidth is unknown for synthetic renditions (media playlist entry points)
+        if self.declared_bandwidth == 0 || probes.is_empty() {
+            return;
+        }
+        let limit = self.declared_bandwidth as f64 * (1.0 + tolerance / 100.0);
+
+        if self.measured_bitrate as f64 > limit {
+            self.issues.push(format!(
+                "Average bitrate {} exceeds declared {} by {:.1}%",
+                self.measured_bitrate,
+                self.declared_bandwidth,
+                excess_percent(self.measured_bitrate, self.declared_bandwidth)
+            ));
+            self.verdict = Verdict::Fail;
+        } else if self.peak_bitrate as f64 > limit {
+            self.issues.push(format!(
+                "Peak segment bitrate {} exceeds declared {} by {:.1}%",
+                self.peak_bitrate,
+                self.declared_bandwidth,
+                excess_percent(self.peak_bitrate, self.declared_bandwidth)
+            ));
+            if self.verdict == Verdict::Pass {
+                self.verdict = Verdict::Warn;
+            }
+        }
+    }
+}
+
+fn excess_percent(measured: u64, declared: u64) -> f64 {
+    (measured as f64 / declared as f64 - 1.0) * 100.0
+}
+
+/// Shared settings for probing a ladder
+pub struct Prober {
+    pub parser: Arc<dyn ManifestParser>,
+    pub fetcher: Arc<dyn HttpFetcher>,
+    pub decryptor: Arc<SegmentDecryptor>,
+    /// Number of segments sampled per rendition
+    pub segments: usize,
+    /// Allowed bitrate overshoot over BANDWIDTH, in percent
+    pub tolerance: f64,
+}
+
+impl Prober {
+    /// Probe renditions with at most `concurrency` running at once
+    ///
+    /// Results come back in the order the renditions were given.
+    pub async fn probe_all(self: Arc<Self>, renditions: Vec<Rendition>, concurrency: usize) -> Vec<RenditionProbe> {
+        let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+        let mut tasks = JoinSet::new();
+
+        for (idx, rendition) in renditions.into_iter().enumerate() {
+            let prober = self.clone();
+            let semaphore = semaphore.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                (idx, prober.probe(&rendition).await)
+            });
+        }
+
+        let mut results = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok(result) = joined {
+                results.push(result);
+            }
+        }
+        results.sort_by_key(|(idx, _)| *idx);
+        results.into_iter().map(|(_, probe)| probe).collect()
+    }
+
+    /// Probe the sampled segments of one rendition
+    pub async fn probe(&self, rendition: &Rendition) -> RenditionProbe {
+        let mut result = RenditionProbe::new(rendition);
+
+        let segments = match self.parser.parse_variant(&rendition.uri).await {
+            Ok(segments) => segments,
+            Err(e) => {
+                result.issues.push(format!("Playlist fetch failed: {}", e));
+                result.finish(&[], self.tolerance);
+                return result;
+            }
+        };
+
+        let mut probes = Vec::new();
+        for segment in segments.iter().take(self.segments) {
+            result.segments_tested += 1;
+            match self.probe_segment(segment).await {
+                Ok(probe) => probes.push(probe),
+                Err(e) => result.issues.push(format!("Segment {}: {}", segment.number, e)),
+            }
+        }
+
+        result.finish(&probes, self.tolerance);
+        result
+    }
+
+    /// Download one segment, timing the response and decrypting if needed
+    async fn probe_segment(&self, segment: &Segment) -> anyhow::Result<SegmentProbe> {
+        let start = Instant::now();
+        let response = self.fetcher
+            .get(&segment.uri, &HashMap::new(), segment.byte_range)
+            .await?
+            .error_for_status()?;
+        let ttfb = start.elapsed();
+        let data = response.bytes().await?;
+        let transfer = start.elapsed();
+
+        let encrypted = segment.encryption.as_ref()
+            .is_some_and(|e| e.method == EncryptionMethod::Aes128);
+        let bytes = data.len() as u64;
+        if encrypted {
+            self.decryptor.decrypt(segment, data).await?;
+        }
+
+        Ok(SegmentProbe {
+            number: segment.number,
+            bytes,
+            duration_secs: segment.duration.as_secs_f64(),
+            ttfb_ms: ttfb.as_secs_f64() * 1000.0,
+            transfer_ms: transfer.as_secs_f64() * 1000.0,
+        })
+    }
+}
@@ -0,0 +1,574 @@
+//! Local HTTP API over the frequency pipeline
+//!
+//! `kino-cli serve` answers each endpoint with the JSON the matching command
+//! prints with `--format json`:
+//! - `POST /analyze?top_k=10` - `frequency`
+//! - `POST /fingerprint` - `fingerprint`
+//! - `POST /tags?max_tags=5&min_confidence=0.3&quality=false` - `autotag`
+//! - `POST /thumbnail?candidates=1` - `thumbnail`
+//! - `GET /healthz` - liveness and job slot usage
+//!
+//! Media is either uploaded as the `file` field of a multipart form, spooled
+//! to a temporary file, or named by a JSON body `{"path": "..."}` under one
+//! of the `--root` directories. Errors are answered as `{"error": "..."}`.
+//!
+//! At most `--jobs` requests are analyzed at once; the rest wait for a slot.
+//! Every request, upload and wait included, must finish within `--timeout`.
+//! A request that times out or whose client disconnects is cancelled: FFmpeg
+//! is stopped and the analysis ends at its next checkpoint.
+//!
+//! Fingerprints are computed from audio streamed out of FFmpeg, so memory
+//! stays bounded whatever the length, and their points cover the last ten
+//! minutes. The other endpoints hold the decoded track and refuse media
+//! longer than `--max-duration`.
+
+use std::fmt::Display;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use axum::extract::{DefaultBodyLimit, FromRequest, Multipart, Query, Request, State};
+use axum::http::{header::CONTENT_TYPE, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+use kino_frequency::{
+    AudioAnalyzer,
+    CancellationToken,
+    FfmpegBackend,
+    MediaBackend,
+    StreamingFingerprinter,
+    tagging::ContentTagger,
+    thumbnail::ThumbnailSelector,
+    types::AudioFingerprint,
+};
+
+use crate::frequency::{check_interrupted, FrequencyReport, TagReport, ThumbnailReport};
+
+/// Sample rate audio is analyzed at, as in the CLI commands
+const SAMPLE_RATE: u32 = 44100;
+
+/// Settings for [`serve`].
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub host: String,
+    pub port: u16,
+    /// Directories whose files may be named by path; empty accepts uploads only
+    pub roots: Vec<PathBuf>,
+    /// Requests analyzed at once
+    pub jobs: usize,
+    /// Limit on a whole request, upload and wait included
+    pub timeout: Duration,
+    pub max_upload_bytes: usize,
+    /// Longest media accepted by the endpoints that decode it whole
+    pub max_duration_secs: f64,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            roots: Vec::new(),
+            jobs: 2,
+            timeout: Duration::from_secs(300),
+            max_upload_bytes: 2048 * 1024 * 1024,
+            max_duration_secs: 3600.0,
+        }
+    }
+}
+
+/// Serve the API until Ctrl-C.
+pub async fn serve(options: ServeOptions) -> Result<()> {
+    let address = format!("{}:{}", options.host, options.port);
+    let state = Arc::new(AppState::new(options, Arc::new(FfmpegBackend))?);
+    let listener = TcpListener::bind(&address).await
+        .with_context(|| format!("Failed to listen on {}", address))?;
+
+    println!("Serving the frequency pipeline on http://{}", listener.local_addr()?);
+    if state.roots.is_empty() {
+        println!("  Uploads only (pass --root to allow paths)");
+    }
+    for root in &state.roots {
+        println!("  Root: {}", root.display());
+    }
+    println!("  Jobs: {}, timeout: {}s", state.options.jobs, state.options.timeout.as_secs());
+
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/analyze", post(analyze))
+        .route("/fingerprint", post(fingerprint))
+        .route("/tags", post(tags))
+        .route("/thumbnail", post(thumbnail))
+        .layer(DefaultBodyLimit::max(state.options.max_upload_bytes))
+        .with_state(state)
+}
+
+struct AppState {
+    options: ServeOptions,
+    /// Canonical `options.roots`
+    roots: Vec<PathBuf>,
+    backend: Arc<dyn MediaBackend>,
+    jobs: Semaphore,
+}
+
+impl AppState {
+    fn new(options: ServeOptions, backend: Arc<dyn MediaBackend>) -> Result<Self> {
+        anyhow::ensure!(options.jobs > 0, "--jobs must be at least 1");
+        let roots = options.roots.iter()
+            .map(|root| root.canonicalize().with_context(|| format!("Invalid root {}", root.display())))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            jobs: Semaphore::new(options.jobs),
+            options,
+            roots,
+            backend,
+        })
+    }
+
+    fn analyzer(&self) -> AudioAnalyzer {
+        AudioAnalyzer::new(SAMPLE_RATE).with_backend(self.backend.clone())
+    }
+
+    /// Read the request's media, then run `job` on it once a job slot is
+    /// free, all within the request timeout.
+    ///
+    /// The token given to `job` is cancelled when the request ends early,
+    /// including when the client disconnects and the handler is dropped.
+    async fn run<T, F, Fut>(self: Arc<Self>, request: Request, job: F) -> Result<Json<T>, ApiError>
+    where
+        F: FnOnce(Arc<Self>, PathBuf, CancellationToken) -> Fut,
+        Fut: Future<Output = Result<T, ApiError>>,
+    {
+        let cancel = CancellationToken::new();
+        let _cancel_on_drop = cancel.clone().drop_guard();
+        let timeout = self.options.timeout;
+
+        let work = async {
+            let input = self.input(request).await?;
+            let _permit = self.jobs.acquire().await.map_err(ApiError::internal)?;
+            job(self.clone(), input.path.clone(), cancel.clone()).await
+        };
+        match tokio::time::timeout(timeout, work).await {
+            Ok(result) => result.map(Json),
+            Err(_) => Err(ApiError::new(
+                StatusCode::GATEWAY_TIMEOUT,
+                format!("Request timed out after {}s", timeout.as_secs_f64()),
+            )),
+        }
+    }
+
+    /// The media a request uploads or names.
+    async fn input(&self, request: Request) -> Result<Input, ApiError> {
+        let content_type = request.headers().get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        if content_type.starts_with("multipart/form-data") {
+            let multipart = Multipart::from_request(request, &()).await
+                .map_err(|e| ApiError::new(e.status(), e.body_text()))?;
+            return spool(multipart).await;
+        }
+
+        let Json(body) = Json::<PathRequest>::from_request(request, &()).await
+            .map_err(|e| ApiError::new(e.status(), e.body_text()))?;
+        Ok(Input { path: self.allowed_path(&body.path)?, _upload: None })
+    }
+
+    /// `path` resolved, if it lies under one of the roots.
+    fn allowed_path(&self, path: &Path) -> Result<PathBuf, ApiError> {
+        if self.roots.is_empty() {
+            return Err(ApiError::new(StatusCode::FORBIDDEN, "Paths are not accepted; upload the file instead"));
+        }
+        let resolved = path.canonicalize()
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("{}: {}", path.display(), e)))?;
+        if !self.roots.iter().any(|root| resolved.starts_with(root)) {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                format!("{} is outside the allowed roots", path.display()),
+            ));
+        }
+        Ok(resolved)
+    }
+
+    /// Refuse media too long to decode whole.
+    async fn check_duration(&self, path: &Path) -> Result<(), ApiError> {
+        let backend = self.backend.clone();
+        let owned = path.to_path_buf();
+        let duration = blocking(move || backend.duration(&owned)).await?;
+        if duration > self.options.max_duration_secs {
+            return Err(ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "Media is {:.0}s long; the limit is {:.0}s (/fingerprint has none)",
+                    duration,
+                    self.options.max_duration_secs
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Body of a request that names a file rather than uploading it
+#[derive(Debug, Deserialize)]
+struct PathRequest {
+    path: PathBuf,
+}
+
+/// Media to analyze, kept on disk until the request is done
+struct Input {
+    path: PathBuf,
+    _upload: Option<Upload>,
+}
+
+/// Spooled upload, removed when dropped
+struct Upload(PathBuf);
+
+impl Drop for Upload {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Write the `file` field to a temporary file, chunk by chunk.
+async fn spool(mut multipart: Multipart) -> Result<Input, ApiError> {
+    let field_error = |e: axum::extract::multipart::MultipartError| ApiError::new(e.status(), e.body_text());
+
+    while let Some(mut field) = multipart.next_field().await.map_err(field_error)? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        // Keep the extension so FFmpeg can tell the container apart
+        let extension = field.file_name()
+            .and_then(|name| Path::new(name).extension())
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()))
+            .map(|ext| format!(".{}", ext))
+            .unwrap_or_default();
+        let upload = Upload(std::env::temp_dir().join(format!("kino_upload_{}{}", uuid::Uuid::new_v4(), extension)));
+
+        let mut file = tokio::fs::File::create(&upload.0).await.map_err(ApiError::internal)?;
+        while let Some(chunk) = field.chunk().await.map_err(field_error)? {
+            file.write_all(&chunk).await.map_err(ApiError::internal)?;
+        }
+        file.flush().await.map_err(ApiError::internal)?;
+
+        return Ok(Input { path: upload.0.clone(), _upload: Some(upload) });
+    }
+    Err(ApiError::new(StatusCode::BAD_REQUEST, "Multipart body has no \"file\" field"))
+}
+
+/// Run CPU-bound work off the async workers.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T, ApiError> {
+    tokio::task::spawn_blocking(work).await
+        .map_err(ApiError::internal)?
+        .map_err(ApiError::from)
+}
+
+/// Error answered as `{"error": "..."}`
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Display) -> Self {
+        Self { status, message: message.to_string() }
+    }
+
+    fn internal(error: impl Display) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, error)
+    }
+}
+
+/// Analysis failures are blamed on the media.
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        Self::new(StatusCode::UNPROCESSABLE_ENTITY, format!("{:#}", error))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(serde_json::json!({ "error": self.message }))).into_response()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Health {
+    status: &'static str,
+    active_jobs: usize,
+    max_jobs: usize,
+}
+
+async fn healthz(State(state): State<Arc<AppState>>) -> Json<Health> {
+    Json(Health {
+        status: "ok",
+        active_jobs: state.options.jobs - state.jobs.available_permits(),
+        max_jobs: state.options.jobs,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct AnalyzeQuery {
+    top_k: usize,
+}
+
+impl Default for AnalyzeQuery {
+    fn default() -> Self {
+        Self { top_k: 10 }
+    }
+}
+
+async fn analyze(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AnalyzeQuery>,
+    request: Request,
+) -> Result<Json<FrequencyReport>, ApiError> {
+    state.run(request, |state, path, cancel| async move {
+        state.check_duration(&path).await?;
+        let analyzer = state.analyzer();
+        let audio = analyzer.extract_audio(&path).await?;
+        blocking(move || {
+            check_interrupted(&cancel)?;
+            FrequencyReport::new(&analyzer, &audio, query.top_k)
+        }).await
+    }).await
+}
+
+async fn fingerprint(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+) -> Result<Json<AudioFingerprint>, ApiError> {
+    state.run(request, |state, path, cancel| async move {
+        let mut fingerprinter = StreamingFingerprinter::new(SAMPLE_RATE);
+        state.analyzer().stream_audio(&path, &mut |chunk| {
+            check_interrupted(&cancel)?;
+            fingerprinter.process(chunk);
+            Ok(())
+        }).await?;
+        blocking(move || Ok(fingerprinter.finalize()?)).await
+    }).await
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct TagsQuery {
+    max_tags: usize,
+    min_confidence: f32,
+    quality: bool,
+}
+
+impl Default for TagsQuery {
+    fn default() -> Self {
+        Self { max_tags: 5, min_confidence: 0.3, quality: false }
+    }
+}
+
+async fn tags(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TagsQuery>,
+    request: Request,
+) -> Result<Json<TagReport>, ApiError> {
+    state.run(request, |state, path, cancel| async move {
+        state.check_duration(&path).await?;
+        let audio = state.analyzer().extract_audio(&path).await?;
+        blocking(move || {
+            check_interrupted(&cancel)?;
+            TagReport::new(&ContentTagger::new(), &audio, query.max_tags, query.min_confidence, query.quality)
+        }).await
+    }).await
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct ThumbnailQuery {
+    candidates: usize,
+}
+
+impl Default for ThumbnailQuery {
+    fn default() -> Self {
+        Self { candidates: 1 }
+    }
+}
+
+async fn thumbnail(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ThumbnailQuery>,
+    request: Request,
+) -> Result<Json<ThumbnailReport>, ApiError> {
+    state.run(request, |state, path, cancel| async move {
+        state.check_duration(&path).await?;
+        let audio = state.analyzer().extract_audio(&path).await?;
+        let selector = ThumbnailSelector::new().with_backend(state.backend.clone());
+        blocking(move || {
+            check_interrupted(&cancel)?;
+            ThumbnailReport::new(&selector, &path, &audio, query.candidates)
+        }).await
+    }).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use kino_frequency::testing::{self, MockBackend};
+    use kino_frequency::Fingerprinter;
+    use serde_json::Value;
+
+    fn backend(duration: f32) -> Arc<dyn MediaBackend> {
+        Arc::new(
+            MockBackend::new(duration as f64)
+                .with_audio(testing::sweep(200.0, 4000.0, SAMPLE_RATE, duration))
+                .with_frames(|t| Some(if t < 2.0 { testing::flat_frame(128) } else { testing::stripes_frame(4) })),
+        )
+    }
+
+    /// Serve on an ephemeral port, returning its base URL.
+    async fn start(options: ServeOptions, backend: Arc<dyn MediaBackend>) -> String {
+        let state = Arc::new(AppState::new(options, backend).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+        url
+    }
+
+    async fn upload(url: &str) -> (StatusCode, Value) {
+        let form = reqwest::multipart::Form::new()
+            .text("note", "ignored")
+            .part("file", reqwest::multipart::Part::bytes(vec![0u8; 1024]).file_name("clip.mp4"));
+        let response = reqwest::Client::new().post(url).multipart(form).send().await.unwrap();
+        (response.status(), response.json().await.unwrap())
+    }
+
+    async fn by_path(url: &str, path: &Path) -> (StatusCode, Value) {
+        let response = reqwest::Client::new().post(url)
+            .json(&serde_json::json!({ "path": path }))
+            .send().await.unwrap();
+        (response.status(), response.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_endpoints() {
+        let url = start(ServeOptions::default(), backend(6.0)).await;
+
+        let (status, report) = upload(&format!("{}/analyze?top_k=3", url)).await;
+        assert_eq!(status, StatusCode::OK, "{}", report);
+        assert_eq!(report["dominant_frequencies"].as_array().unwrap().len(), 3);
+        assert!(report["spectral_features"]["centroid"].as_f64().unwrap() > 0.0);
+
+        let (status, fp) = upload(&format!("{}/fingerprint", url)).await;
+        assert_eq!(status, StatusCode::OK, "{}", fp);
+        let audio = testing::sweep(200.0, 4000.0, SAMPLE_RATE, 6.0);
+        let batch = Fingerprinter::new().fingerprint(&audio).unwrap();
+        assert_eq!(fp["hash"], batch.hash.as_str());
+
+        let (status, tags) = upload(&format!("{}/tags?max_tags=2&quality=true", url)).await;
+        assert_eq!(status, StatusCode::OK, "{}", tags);
+        assert!(tags["tags"].as_array().unwrap().len() <= 2);
+        assert!(tags["quality"].is_object());
+
+        let (status, thumbnail) = upload(&format!("{}/thumbnail?candidates=3", url)).await;
+        assert_eq!(status, StatusCode::OK, "{}", thumbnail);
+        assert!(thumbnail["timestamp"].as_f64().unwrap() >= 2.0);
+        assert!(!thumbnail["candidates"].as_array().unwrap().is_empty());
+
+        let health: Value = reqwest::get(format!("{}/healthz", url)).await.unwrap().json().await.unwrap();
+        assert_eq!(health, serde_json::json!({ "status": "ok", "active_jobs": 0, "max_jobs": 2 }));
+    }
+
+    #[tokio::test]
+    async fn test_paths_and_limits() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let inside = root.path().join("clip.mp4");
+        std::fs::write(&inside, b"").unwrap();
+        std::fs::write(outside.path().join("clip.mp4"), b"").unwrap();
+
+        let options = ServeOptions {
+            roots: vec![root.path().to_path_buf()],
+            max_upload_bytes: 512,
+            max_duration_secs: 5.0,
+            ..Default::default()
+        };
+        let url = start(options, backend(6.0)).await;
+
+        let (status, _) = by_path(&format!("{}/fingerprint", url), &inside).await;
+        assert_eq!(status, StatusCode::OK);
+        let escape = root.path().join("..").join(outside.path().file_name().unwrap()).join("clip.mp4");
+        let (status, _) = by_path(&format!("{}/fingerprint", url), &escape).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = by_path(&format!("{}/fingerprint", url), &root.path().join("missing.mp4")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Too long to decode whole, too large to upload
+        let (status, error) = by_path(&format!("{}/tags", url), &inside).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(error["error"].as_str().unwrap().contains("limit is 5s"));
+        let (status, _) = upload(&format!("{}/fingerprint", url)).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let uploads_only = start(ServeOptions::default(), backend(6.0)).await;
+        let (status, _) = by_path(&format!("{}/fingerprint", uploads_only), &inside).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_disconnect() {
+        let root = tempfile::tempdir().unwrap();
+        let options = ServeOptions {
+            roots: vec![root.path().to_path_buf()],
+            jobs: 1,
+            timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let state = Arc::new(AppState::new(options, backend(6.0)).unwrap());
+        let request = || {
+            Request::builder()
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::json!({ "path": root.path() }).to_string()))
+                .unwrap()
+        };
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let timed_out = state.clone().run(request(), |_, _, cancel| async move {
+            tx.send(cancel).unwrap();
+            std::future::pending::<Result<(), ApiError>>().await
+        }).await.unwrap_err();
+        assert_eq!(timed_out.status, StatusCode::GATEWAY_TIMEOUT);
+        assert!(rx.await.unwrap().is_cancelled());
+        assert_eq!(state.jobs.available_permits(), 1);
+
+        // Dropping the handler, as a client disconnect does, cancels the job
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut job = Box::pin(state.clone().run(request(), |_, _, cancel| async move {
+            tx.send(cancel).unwrap();
+            std::future::pending::<Result<(), ApiError>>().await
+        }));
+        let cancel = tokio::select! {
+            _ = &mut job => unreachable!(),
+            cancel = rx => cancel.unwrap(),
+        };
+        assert!(!cancel.is_cancelled());
+        assert_eq!(state.jobs.available_permits(), 0);
+        drop(job);
+        assert!(cancel.is_cancelled());
+        assert_eq!(state.jobs.available_permits(), 1);
+    }
+}
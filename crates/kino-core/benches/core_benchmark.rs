@@ -9,6 +9,7 @@ use bytes::Bytes;
 
 use kino_core::abr::{AbrEngine, AbrContext};
 use kino_core::buffer::{BufferConfig, BufferManager};
+use kino_core::disk_cache::DiskCacheConfig;
 use kino_core::branding::{CssVariables, KinoColors, KinoTheme, JsTheme};
 use kino_core::types::*;
 use kino_core::analytics::QoeCalculator;
@@ -43,6 +44,7 @@ fn create_test_renditions() -> Vec<Rendition> {
             hdr: None,
             language: None,
             name: None,
+            audio_group: None,
         },
         Rendition {
             id: "360p".to_string(),
@@ -55,6 +57,7 @@ fn create_test_renditions() -> Vec<Rendition> {
             hdr: None,
             language: None,
             name: None,
+            audio_group: None,
         },
         Rendition {
             id: "480p".to_string(),
@@ -67,6 +70,7 @@ fn create_test_renditions() -> Vec<Rendition> {
             hdr: None,
             language: None,
             name: None,
+            audio_group: None,
         },
         Rendition {
             id: "720p".to_string(),
@@ -79,6 +83,7 @@ fn create_test_renditions() -> Vec<Rendition> {
             hdr: None,
             language: None,
             name: None,
+            audio_group: None,
         },
         Rendition {
             id: "1080p".to_string(),
@@ -91,6 +96,7 @@ fn create_test_renditions() -> Vec<Rendition> {
             hdr: None,
             language: None,
             name: None,
+            audio_group: None,
         },
         Rendition {
             id: "1080p60".to_string(),
@@ -103,6 +109,7 @@ fn create_test_renditions() -> Vec<Rendition> {
             hdr: None,
             language: None,
             name: None,
+            audio_group: None,
         },
         Rendition {
             id: "4k".to_string(),
@@ -115,6 +122,7 @@ fn create_test_renditions() -> Vec<Rendition> {
             hdr: Some(HdrFormat::Hdr10),
             language: None,
             name: None,
+            audio_group: None,
         },
     ]
 }
@@ -159,6 +167,38 @@ fn generate_hls_media(segment_count: usize) -> String {
 // Buffer Benchmarks
 // ============================================================================
 
+/// Segments played in a seek-heavy session: forward play with short seeks
+/// back and a jump ahead and back
+const SEEK_SCRIPT: &[std::ops::Range<u64>] = &[0..20, 12..24, 16..30, 40..50, 24..36, 44..50];
+
+/// Play `SEEK_SCRIPT` with room for 8 segments in memory, returning how many
+/// segments had to be downloaded
+async fn seek_heavy_session(disk_cache: Option<DiskCacheConfig>) -> usize {
+    let buffer = BufferManager::new(BufferConfig {
+        max_memory_bytes: 8 * 256 * 1024,
+        disk_cache,
+        ..Default::default()
+    });
+
+    let mut downloads = 0;
+    for run in SEEK_SCRIPT {
+        buffer.seek(run.start as f64 * 4.0).await.unwrap();
+        for num in run.clone() {
+            let segment = create_test_segment(num);
+            let data = match buffer.cached_segment(&segment).await {
+                Some(data) => data,
+                None => {
+                    downloads += 1;
+                    Bytes::from(vec![num as u8; 256 * 1024])
+                }
+            };
+            buffer.add_segment(segment, data).await.unwrap();
+            buffer.consume_segment(num).await;
+        }
+    }
+    downloads
+}
+
 fn bench_buffer_allocation(c: &mut Criterion) {
     let mut group = c.benchmark_group("Buffer Allocation");
 
@@ -246,6 +286,34 @@ fn bench_buffer_segment_sizes(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_disk_cache_seeks(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let disk_cache = DiskCacheConfig {
+        path: std::env::temp_dir().join("kino-bench-disk-cache"),
+        max_bytes: 64 * 1024 * 1024,
+    };
+
+    let played: u64 = SEEK_SCRIPT.iter().map(|run| run.end - run.start).sum();
+    let without = rt.block_on(seek_heavy_session(None));
+    let with = rt.block_on(seek_heavy_session(Some(disk_cache.clone())));
+    println!("Seek-heavy session, {} segments played: {} downloads without disk cache, {} with", played, without, with);
+
+    let mut group = c.benchmark_group("Buffer Disk Cache");
+
+    group.bench_function("seek_heavy_session/memory_only", |b| {
+        b.iter(|| rt.block_on(async { black_box(seek_heavy_session(None).await) }));
+    });
+
+    group.bench_function("seek_heavy_session/disk_cache", |b| {
+        b.iter(|| rt.block_on(async { black_box(seek_heavy_session(Some(disk_cache.clone())).await) }));
+    });
+
+    group.finish();
+}
+
 fn bench_buffer_queries(c: &mut Criterion) {
     let rt = tokio::runtime::Builder::new_current_thread()
         .enable_all()
@@ -457,13 +525,16 @@ fn bench_abr_rendition_selection(c: &mut Criterion) {
             playback_rate: 1.0,
             is_live: false,
             screen_width: Some(1920),
+            screen_height: None,
             max_bitrate: 0,
+            rebuffer_threshold: 0.0,
             network: NetworkInfo {
                 bandwidth_estimate: 20_000_000,
                 rtt_ms: 50,
                 connection_type: Some(ConnectionType::Wifi),
                 metered: false,
             },
+            device: Default::default(),
         };
         b.iter(|| {
             black_box(engine.select_rendition(black_box(&renditions), black_box(&context)))
@@ -482,13 +553,16 @@ fn bench_abr_rendition_selection(c: &mut Criterion) {
             playback_rate: 1.0,
             is_live: false,
             screen_width: Some(1280),
+            screen_height: None,
             max_bitrate: 0,
+            rebuffer_threshold: 0.0,
             network: NetworkInfo {
                 bandwidth_estimate: 800_000,
                 rtt_ms: 200,
                 connection_type: Some(ConnectionType::Cellular4G),
                 metered: true,
             },
+            device: Default::default(),
         };
         b.iter(|| {
             black_box(engine.select_rendition(black_box(&renditions), black_box(&context)))
@@ -508,11 +582,14 @@ fn bench_abr_rendition_selection(c: &mut Criterion) {
                     playback_rate: 1.0,
                     is_live: false,
                     screen_width: None,
+                    screen_height: None,
                     max_bitrate: 0,
+                    rebuffer_threshold: 0.0,
                     network: NetworkInfo {
                         bandwidth_estimate: 5_000_000,
                         ..Default::default()
                     },
+                    device: Default::default(),
                 };
                 b.iter(|| {
                     black_box(engine.select_rendition(black_box(&renditions), black_box(&context)))
@@ -533,13 +610,16 @@ fn bench_abr_rendition_selection(c: &mut Criterion) {
             playback_rate: 1.0,
             is_live: true,
             screen_width: Some(1920),
+            screen_height: None,
             max_bitrate: 10_000_000,
+            rebuffer_threshold: 0.0,
             network: NetworkInfo {
                 bandwidth_estimate: 8_000_000,
                 rtt_ms: 80,
                 connection_type: Some(ConnectionType::Wifi),
                 metered: false,
             },
+            device: Default::default(),
         };
         b.iter(|| {
             black_box(engine.select_rendition(black_box(&renditions), black_box(&context)))
@@ -739,6 +819,7 @@ fn bench_types(c: &mut Criterion) {
             stall_duration: 1.5,
             quality_switches: 4,
             throughput: 8_000_000,
+            hardware_backend: None,
         };
         b.iter(|| {
             black_box(metrics.qoe_score())
@@ -801,6 +882,7 @@ fn bench_memory_footprint(c: &mut Criterion) {
                     hdr: None,
                     language: None,
                     name: Some(format!("Variant {}", i)),
+                    audio_group: None,
                 });
             }
             black_box(renditions)
@@ -855,6 +937,7 @@ fn bench_memory_footprint(c: &mut Criterion) {
                     hdr: None,
                     language: None,
                     name: None,
+                    audio_group: None,
                 });
             }
 
@@ -892,6 +975,7 @@ fn bench_memory_footprint(c: &mut Criterion) {
             stall_duration: 1.5,
             quality_switches: 4,
             throughput: 8_000_000,
+            hardware_backend: None,
         };
         b.iter(|| {
             black_box(serde_json::to_string(black_box(&metrics)).unwrap())
@@ -911,6 +995,7 @@ criterion_group!(
     bench_buffer_segment_insertion,
     bench_buffer_segment_sizes,
     bench_buffer_queries,
+    bench_disk_cache_seeks,
 );
 
 criterion_group!(
@@ -4,15 +4,73 @@
 //! - Throughput-based: Simple bandwidth estimation
 //! - BOLA: Buffer Occupancy based Lyapunov Algorithm
 //! - Hybrid: Combines throughput and buffer metrics
-
+//!
+//! A manually selected rendition can be pinned with
+//! [`AbrEngine::set_override`] (until cleared) or
+//! [`AbrEngine::hold_rendition`] (for a fixed time). The engine then
+//! returns the pinned rendition instead of deciding, unless the buffer
+//! drops below [`AbrContext::rebuffer_threshold`]; in that emergency it may
+//! downgrade, and a timed hold is released.
+//!
+//! # Fast start
+//!
+//! With [`AbrEngine::with_fast_start`] the first decisions of a session are
+//! made from throughput alone, before the configured algorithm has any
+//! history to work with. The first pick uses the playlist downloads
+//! ([`AbrEngine::record_playlist_download`]) and any estimate carried over
+//! from a previous session ([`AbrEngine::with_initial_estimate`]); later
+//! startup picks follow the latest segment download and switch immediately,
+//! without the stability filter. These decisions carry
+//! [`QualityChangeReason::StartupPhase`].
+//!
+//! # Persisted history
+//!
+//! [`AbrEngine::export_history`] serializes recent throughput samples to an
+//! opaque string the embedder can keep between sessions, e.g. in
+//! `localStorage` or a file. [`AbrEngine::restore_history`] loads it into a
+//! new engine, dropping samples older than `max_age` or taken on a
+//! different [`ConnectionType`] than the one set with
+//! [`AbrEngine::set_connection_type`]. Restored samples are weighted by age
+//! and give way to fresh measurements as they arrive.
+//!
+//! The blob holds only throughput, the time of each sample and the
+//! connection type: no URLs, rendition IDs or anything else about the
+//! content played. It still reveals when the user was watching and roughly
+//! what their connection is like, so embedders should store it like other
+//! per-device preferences and clear it with them.
+//!
+//! # Device capabilities
+//!
+//! [`AbrContext::device`] describes what the device can decode, and
+//! [`AbrContext::screen_width`] / [`AbrContext::screen_height`] how large
+//! the video is shown. Renditions that fail either are removed before the
+//! algorithm scores the rest; see [`AbrContext::allows`]. Both may change
+//! mid-session, e.g. on a window resize: a rendition that no longer fits
+//! is left at once, while a larger window is used once the stability
+//! filter agrees.
+//!
+//! Decoder feedback is passed in with [`AbrEngine::record_frames`]. When
+//! more than [`DROPPED_FRAME_THRESHOLD`] of the frames in a
+//! [`DROPPED_FRAME_WINDOW`]-frame window are dropped, the engine caps the
+//! resolution one step below the playing rendition whatever the
+//! bandwidth, switching with [`QualityChangeReason::DecoderOverload`]. The
+//! cap is lifted after a window dropping less than
+//! [`DROPPED_FRAME_RELEASE`].
+
+use crate::analytics::QualityChangeReason;
 use crate::types::*;
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing::{debug, instrument};
 
 /// ABR algorithm trait
 pub trait AbrAlgorithm: Send + Sync {
     /// Select the best rendition given current conditions
+    ///
+    /// Renditions rejected by [`AbrContext::allows`] must not be selected.
     fn select_rendition<'a>(
         &self,
         renditions: &'a [Rendition],
@@ -39,10 +97,104 @@ pub struct AbrContext {
     pub is_live: bool,
     /// Screen width for resolution capping
     pub screen_width: Option<u32>,
+    /// Screen height for resolution capping
+    pub screen_height: Option<u32>,
     /// Maximum allowed bitrate (0 = unlimited)
     pub max_bitrate: u64,
+    /// Buffer level below which a pinned rendition may be downgraded
+    /// (0 = never)
+    pub rebuffer_threshold: f64,
     /// Network info
     pub network: NetworkInfo,
+    /// What the device can decode
+    pub device: DeviceCapabilities,
+}
+
+impl AbrContext {
+    /// Whether the buffer is low enough to override a manual selection
+    pub fn is_buffer_emergency(&self) -> bool {
+        self.buffer_level < self.rebuffer_threshold
+    }
+
+    /// Whether `rendition` may be selected: the device can decode it and
+    /// it is no larger than the screen
+    pub fn allows(&self, rendition: &Rendition) -> bool {
+        let fits_screen = rendition.resolution.is_none_or(|res| {
+            self.screen_width.is_none_or(|w| res.width <= w)
+                && self.screen_height.is_none_or(|h| res.height <= h)
+        });
+        fits_screen && self.device.supports(rendition)
+    }
+}
+
+/// Decoding capabilities of the playback device
+///
+/// `None` means unknown and restricts nothing. Renditions whose codec was
+/// not recognized are assumed playable.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceCapabilities {
+    /// Video codecs the device can decode
+    pub video_codecs: Option<Vec<VideoCodec>>,
+    /// Audio codecs the device can decode
+    pub audio_codecs: Option<Vec<AudioCodec>>,
+    /// Largest resolution the decoder handles
+    pub max_resolution: Option<Resolution>,
+    /// Highest frame rate the decoder handles
+    pub max_frame_rate: Option<f32>,
+    /// HDR formats the device can display; empty for SDR only
+    pub hdr_formats: Option<Vec<HdrFormat>>,
+}
+
+impl DeviceCapabilities {
+    /// Whether the device can play `rendition`
+    pub fn supports(&self, rendition: &Rendition) -> bool {
+        let video = match (&self.video_codecs, rendition.video_codec) {
+            (Some(codecs), Some(codec)) if codec != VideoCodec::Unknown => codecs.contains(&codec),
+            _ => true,
+        };
+        let audio = match (&self.audio_codecs, rendition.audio_codec) {
+            (Some(codecs), Some(codec)) if codec != AudioCodec::Unknown => codecs.contains(&codec),
+            _ => true,
+        };
+        let resolution = match (self.max_resolution, rendition.resolution) {
+            (Some(max), Some(res)) => res.width <= max.width && res.height <= max.height,
+            _ => true,
+        };
+        let frame_rate = match (self.max_frame_rate, rendition.frame_rate) {
+            (Some(max), Some(rate)) => rate <= max + 0.01,
+            _ => true,
+        };
+        let hdr = match (&self.hdr_formats, rendition.hdr) {
+            (Some(formats), Some(format)) => formats.contains(&format),
+            _ => true,
+        };
+        video && audio && resolution && frame_rate && hdr
+    }
+}
+
+/// A rendition chosen by [`AbrEngine::decide`] and why
+#[derive(Debug, Clone, Copy)]
+pub struct AbrDecision<'a> {
+    /// Selected rendition
+    pub rendition: &'a Rendition,
+    /// `Manual` for a pinned rendition, `Buffer` for an emergency
+    /// downgrade from one, `DecoderOverload` when dropped frames cap the
+    /// resolution, otherwise `Abr`
+    pub reason: QualityChangeReason,
+}
+
+/// A manually pinned rendition
+#[derive(Debug, Clone)]
+struct QualityHold {
+    rendition_id: String,
+    /// When the hold lapses; `None` holds until cleared
+    expires_at: Option<Instant>,
+}
+
+impl QualityHold {
+    fn is_active(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|at| now < at)
+    }
 }
 
 /// Bandwidth measurement sample
@@ -67,6 +219,128 @@ impl BandwidthMeasurement {
     }
 }
 
+/// Consecutive differing decisions required before switching rendition
+const SWITCH_STABILITY: u32 = 3;
+
+/// Share of the estimated bandwidth a fast start pick may use
+const FAST_START_SAFETY: f64 = 0.9;
+
+/// Version of the [`AbrEngine::export_history`] blob, bumped on
+/// incompatible changes
+const HISTORY_VERSION: u32 = 1;
+
+/// Age at which a restored sample counts for half as much
+const RESTORED_HALF_LIFE: Duration = Duration::from_secs(30 * 60);
+
+/// Fresh measurements that restored history counts for at most
+const RESTORED_MAX_WEIGHT: f64 = 3.0;
+
+/// Decoded frames a dropped-frame ratio is measured over
+pub const DROPPED_FRAME_WINDOW: u64 = 300;
+
+/// Share of dropped frames in a window that caps the resolution
+pub const DROPPED_FRAME_THRESHOLD: f64 = 0.15;
+
+/// Share of dropped frames in a window below which the cap is lifted
+pub const DROPPED_FRAME_RELEASE: f64 = 0.02;
+
+/// Dropped-frame ratio over the last [`DROPPED_FRAME_WINDOW`] frames
+#[derive(Debug, Clone, Default)]
+struct FrameDropWindow {
+    /// Decoded and dropped frames per report, oldest first
+    reports: VecDeque<(u64, u64)>,
+    /// Cumulative decoded and dropped frames at the last report
+    totals: (u64, u64),
+}
+
+impl FrameDropWindow {
+    /// Add cumulative counts, returning the ratio once a full window has
+    /// been seen
+    fn record(&mut self, decoded: u64, dropped: u64) -> Option<f64> {
+        // Counters that went backwards were reset; count from zero
+        let (last_decoded, last_dropped) = if decoded < self.totals.0 || dropped < self.totals.1 {
+            (0, 0)
+        } else {
+            self.totals
+        };
+        self.reports.push_back((decoded - last_decoded, dropped - last_dropped));
+        self.totals = (decoded, dropped);
+
+        let window_decoded = |reports: &VecDeque<(u64, u64)>| reports.iter().map(|r| r.0).sum::<u64>();
+        while self.reports.len() > 1
+            && window_decoded(&self.reports) - self.reports[0].0 >= DROPPED_FRAME_WINDOW
+        {
+            self.reports.pop_front();
+        }
+
+        let decoded = window_decoded(&self.reports);
+        let dropped: u64 = self.reports.iter().map(|r| r.1).sum();
+        (decoded >= DROPPED_FRAME_WINDOW).then(|| dropped as f64 / decoded as f64)
+    }
+
+    /// Start a new window, e.g. after the resolution changed
+    fn restart(&mut self) {
+        self.reports.clear();
+    }
+}
+
+/// A throughput sample as persisted between sessions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct HistorySample {
+    throughput_bps: u64,
+    /// Wall-clock time of the sample, in milliseconds since the Unix epoch
+    recorded_at_ms: u64,
+    connection_type: Option<ConnectionType>,
+}
+
+impl HistorySample {
+    /// Weight of the sample at `now_ms`, halving every [`RESTORED_HALF_LIFE`]
+    fn weight(&self, now_ms: u64) -> f64 {
+        let age = now_ms.saturating_sub(self.recorded_at_ms) as f64;
+        0.5f64.powf(age / RESTORED_HALF_LIFE.as_millis() as f64)
+    }
+}
+
+/// The persisted form of [`AbrEngine`] history
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryBlob {
+    version: u32,
+    samples: Vec<HistorySample>,
+}
+
+/// Milliseconds since the Unix epoch
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Throughput seen before the first segment download
+#[derive(Debug, Clone, Default)]
+struct StartupEstimate {
+    /// Playlist bytes downloaded
+    playlist_bytes: usize,
+    /// Time spent downloading playlists
+    playlist_time: Duration,
+    /// Estimate carried over from a previous session, in bps
+    hint: Option<u64>,
+}
+
+impl StartupEstimate {
+    /// Mean of the playlist throughput and the hint, whichever are known
+    fn bandwidth(&self) -> u64 {
+        let measured = (!self.playlist_time.is_zero())
+            .then(|| (self.playlist_bytes as f64 * 8.0 / self.playlist_time.as_secs_f64()) as u64);
+
+        match (measured, self.hint) {
+            (Some(measured), Some(hint)) => (measured + hint) / 2,
+            (Some(bandwidth), None) | (None, Some(bandwidth)) => bandwidth,
+            (None, None) => 0,
+        }
+    }
+}
+
 /// ABR Engine combining multiple algorithms
 pub struct AbrEngine {
     /// Active algorithm
@@ -81,6 +355,24 @@ pub struct AbrEngine {
     last_selection: Option<usize>,
     /// Stability counter (prevent oscillation)
     stability_counter: u32,
+    /// Manually pinned rendition
+    hold: Option<QualityHold>,
+    /// Decisions left in the fast start phase
+    fast_start_remaining: u32,
+    /// Bandwidth known before any segment download
+    startup: StartupEstimate,
+    /// Network the current measurements are taken on
+    connection_type: Option<ConnectionType>,
+    /// Measurements of this session, as persisted by `export_history`
+    samples: VecDeque<HistorySample>,
+    /// Samples restored from a previous session
+    restored: Vec<HistorySample>,
+    /// Recent dropped frames
+    frame_drops: FrameDropWindow,
+    /// Dropped frames exceeded the threshold; cap at the next decision
+    decoder_overloaded: bool,
+    /// Largest resolution allowed while the decoder is overloaded
+    resolution_cap: Option<Resolution>,
 }
 
 impl AbrEngine {
@@ -100,9 +392,231 @@ impl AbrEngine {
             bandwidth_estimate: 0,
             last_selection: None,
             stability_counter: 0,
+            hold: None,
+            fast_start_remaining: 0,
+            startup: StartupEstimate::default(),
+            connection_type: None,
+            samples: VecDeque::with_capacity(20),
+            restored: Vec::new(),
+            frame_drops: FrameDropWindow::default(),
+            decoder_overloaded: false,
+            resolution_cap: None,
+        }
+    }
+
+    /// Make the first `segments` decisions with the fast start ramp
+    /// (0 disables it)
+    pub fn with_fast_start(mut self, segments: u32) -> Self {
+        self.fast_start_remaining = segments;
+        self
+    }
+
+    /// Seed the bandwidth estimate, e.g. with one persisted from a previous
+    /// session (0 = no estimate)
+    pub fn with_initial_estimate(mut self, bandwidth_bps: u64) -> Self {
+        self.startup.hint = (bandwidth_bps > 0).then_some(bandwidth_bps);
+        self
+    }
+
+    /// Record a manifest or playlist download as a bandwidth sample for
+    /// the fast start
+    ///
+    /// Only used until the first segment download is recorded.
+    pub fn record_playlist_download(&mut self, bytes: usize, duration: Duration) {
+        self.startup.playlist_bytes += bytes;
+        self.startup.playlist_time += duration;
+        debug!(
+            bytes,
+            duration_ms = duration.as_millis(),
+            estimate_mbps = self.startup.bandwidth() as f64 / 1_000_000.0,
+            "Playlist download recorded"
+        );
+    }
+
+    /// Set the network measurements are taken on, e.g. from the Network
+    /// Information API; restored history must match it
+    pub fn set_connection_type(&mut self, connection_type: Option<ConnectionType>) {
+        self.connection_type = connection_type;
+    }
+
+    /// Network measurements are taken on, if known
+    pub fn connection_type(&self) -> Option<ConnectionType> {
+        self.connection_type
+    }
+
+    /// Serialize recent throughput samples, restored ones included, for
+    /// [`AbrEngine::restore_history`] in a later session
+    ///
+    /// The result is opaque; see the module docs for what it contains.
+    pub fn export_history(&self) -> Result<String> {
+        let mut samples: Vec<HistorySample> = self.restored.iter().chain(&self.samples).cloned().collect();
+        samples.sort_by_key(|sample| sample.recorded_at_ms);
+        let keep_from = samples.len().saturating_sub(self.max_history);
+        let blob = HistoryBlob { version: HISTORY_VERSION, samples: samples.split_off(keep_from) };
+        serde_json::to_string(&blob).map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// Restore history exported by a previous session, returning how many
+    /// samples were kept
+    ///
+    /// Samples older than `max_age`, or taken on a different connection
+    /// type than the current one, are discarded. A blob from another
+    /// version of the player is an error; embedders should drop it.
+    pub fn restore_history(&mut self, blob: &str, max_age: Duration) -> Result<usize> {
+        let blob: HistoryBlob = serde_json::from_str(blob)
+            .map_err(|e| Error::InvalidConfig(format!("bandwidth history: {}", e)))?;
+        if blob.version != HISTORY_VERSION {
+            return Err(Error::InvalidConfig(format!(
+                "bandwidth history version {} (expected {})",
+                blob.version, HISTORY_VERSION
+            )));
+        }
+
+        let now_ms = unix_millis();
+        let total = blob.samples.len();
+        self.restored = blob.samples.into_iter()
+            .filter(|sample| now_ms.saturating_sub(sample.recorded_at_ms) as u128 <= max_age.as_millis())
+            .filter(|sample| sample.connection_type == self.connection_type)
+            .filter(|sample| sample.throughput_bps > 0)
+            .collect();
+
+        debug!(
+            restored = self.restored.len(),
+            discarded = total - self.restored.len(),
+            connection_type = ?self.connection_type,
+            "Bandwidth history restored"
+        );
+        Ok(self.restored.len())
+    }
+
+    /// Recency-weighted mean of the restored samples and their total weight
+    fn restored_estimate(&self) -> Option<(f64, f64)> {
+        let now_ms = unix_millis();
+        let (sum, weight) = self.restored.iter().fold((0.0, 0.0), |(sum, weight), sample| {
+            let w = sample.weight(now_ms);
+            (sum + sample.throughput_bps as f64 * w, weight + w)
+        });
+        (weight > 0.0).then(|| (sum / weight, weight.min(RESTORED_MAX_WEIGHT)))
+    }
+
+    /// Record the decoder's cumulative decoded and dropped frame counts,
+    /// as in [`QualityMetrics`]
+    ///
+    /// See the module docs for how dropped frames cap the resolution.
+    pub fn record_frames(&mut self, decoded_frames: u64, dropped_frames: u64) {
+        let Some(ratio) = self.frame_drops.record(decoded_frames, dropped_frames) else {
+            return;
+        };
+
+        // A capped decoder that still drops frames is capped further
+        if ratio > DROPPED_FRAME_THRESHOLD {
+            if !self.decoder_overloaded {
+                debug!(ratio, "Decoder overloaded");
+            }
+            self.decoder_overloaded = true;
+        } else if self.resolution_cap.is_some() && ratio < DROPPED_FRAME_RELEASE {
+            debug!(ratio, cap = ?self.resolution_cap, "Decoder recovered, lifting resolution cap");
+            self.resolution_cap = None;
+            self.frame_drops.restart();
         }
     }
 
+    /// Largest resolution allowed because of dropped frames, if capped
+    pub fn resolution_cap(&self) -> Option<Resolution> {
+        self.resolution_cap
+    }
+
+    /// `context` with the dropped-frame cap applied
+    ///
+    /// If no rendition would be allowed the capabilities and screen size
+    /// are ignored, since playing something beats stalling.
+    fn effective_context<'c>(&self, renditions: &[Rendition], context: &'c AbrContext) -> Cow<'c, AbrContext> {
+        let mut context = Cow::Borrowed(context);
+        if let Some(cap) = self.resolution_cap {
+            let device = &mut context.to_mut().device;
+            device.max_resolution = Some(match device.max_resolution {
+                Some(max) => Resolution::new(max.width.min(cap.width), max.height.min(cap.height)),
+                None => cap,
+            });
+        }
+
+        if !renditions.iter().any(|r| context.allows(r)) {
+            debug!("No rendition fits the device, ignoring its capabilities");
+            let context = context.to_mut();
+            context.device = DeviceCapabilities::default();
+            context.screen_width = None;
+            context.screen_height = None;
+        }
+        context
+    }
+
+    /// Cap the resolution below the playing rendition after dropped
+    /// frames, returning the decision that leaves it
+    ///
+    /// Nothing is capped when the playing rendition is already the lowest
+    /// one the device allows.
+    fn cap_resolution<'a>(&mut self, renditions: &'a [Rendition], context: &AbrContext) -> Option<AbrDecision<'a>> {
+        self.decoder_overloaded = false;
+        self.frame_drops.restart();
+
+        let current = renditions.get(self.last_selection?)?.resolution?;
+        let cap = renditions.iter()
+            .filter(|r| context.allows(r))
+            .filter_map(|r| r.resolution)
+            .filter(|res| res.height < current.height)
+            .max_by_key(|res| (res.height, res.width))?;
+        self.resolution_cap = Some(cap);
+
+        let context = self.effective_context(renditions, context);
+        let rendition = self.algorithm.select_rendition(renditions, &context)
+            .filter(|r| context.allows(r))
+            .or_else(|| renditions.iter().filter(|r| context.allows(r)).max_by_key(|r| r.bandwidth))?;
+        self.last_selection = renditions.iter().position(|r| r.id == rendition.id);
+        self.stability_counter = 0;
+        debug!(
+            selected_id = %rendition.id,
+            from = ?current,
+            cap = ?cap,
+            "Dropped frames, capping resolution"
+        );
+        Some(AbrDecision { rendition, reason: QualityChangeReason::DecoderOverload })
+    }
+
+    /// Whether decisions are still made by the fast start ramp
+    pub fn is_fast_starting(&self) -> bool {
+        self.fast_start_remaining > 0
+    }
+
+    /// Pin a rendition until cleared; `None` returns to automatic selection
+    pub fn set_override(&mut self, rendition_id: Option<String>) {
+        self.hold = rendition_id.map(|rendition_id| QualityHold { rendition_id, expires_at: None });
+    }
+
+    /// Pin a rendition for `duration`, after which automatic selection resumes
+    pub fn hold_rendition(&mut self, rendition_id: impl Into<String>, duration: Duration) {
+        self.hold = Some(QualityHold {
+            rendition_id: rendition_id.into(),
+            expires_at: Some(Instant::now() + duration),
+        });
+    }
+
+    /// Point the stability filter at `current_id` after the rendition list
+    /// changed, e.g. when a live encoder adds or drops renditions
+    ///
+    /// Selections are remembered by position, which a changed list would
+    /// otherwise make refer to a different rendition.
+    pub fn rebase_selection(&mut self, renditions: &[Rendition], current_id: &str) {
+        self.last_selection = renditions.iter().position(|r| r.id == current_id);
+        self.stability_counter = 0;
+    }
+
+    /// ID of the pinned rendition, if any and not expired
+    pub fn override_id(&self) -> Option<&str> {
+        self.hold.as_ref()
+            .filter(|hold| hold.is_active(Instant::now()))
+            .map(|hold| hold.rendition_id.as_str())
+    }
+
     /// Record a bandwidth measurement
     #[instrument(skip(self))]
     pub fn record_measurement(&mut self, bytes: usize, duration: Duration) {
@@ -117,6 +631,14 @@ impl AbrEngine {
             self.bandwidth_history.pop_front();
         }
         self.bandwidth_history.push_back(measurement.clone());
+        if self.samples.len() >= self.max_history {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(HistorySample {
+            throughput_bps: measurement.throughput_bps(),
+            recorded_at_ms: unix_millis(),
+            connection_type: self.connection_type,
+        });
 
         // Update estimate using EWMA
         let sample = measurement.throughput_bps();
@@ -141,27 +663,160 @@ impl AbrEngine {
     }
 
     /// Select best rendition
-    #[instrument(skip(self, renditions))]
     pub fn select_rendition<'a>(
         &mut self,
         renditions: &'a [Rendition],
         context: &AbrContext,
     ) -> Option<&'a Rendition> {
+        self.decide(renditions, context).map(|decision| decision.rendition)
+    }
+
+    /// Select best rendition, reporting why it was chosen
+    ///
+    /// A pinned rendition is returned as is while it is among `renditions`;
+    /// otherwise the algorithm decides. In a buffer emergency the algorithm
+    /// may pick a lower rendition than the pinned one, bypassing the
+    /// stability filter. Dropped frames reported since the last decision
+    /// may cap the resolution, which also bypasses it.
+    #[instrument(skip(self, renditions))]
+    pub fn decide<'a>(
+        &mut self,
+        renditions: &'a [Rendition],
+        context: &AbrContext,
+    ) -> Option<AbrDecision<'a>> {
         if renditions.is_empty() {
             return None;
         }
+        let context = &*self.effective_context(renditions, context);
+
+        if self.hold.as_ref().is_some_and(|hold| !hold.is_active(Instant::now())) {
+            debug!("Quality hold expired");
+            self.hold = None;
+        }
 
+        // Every decision is a segment, so pinned ones count towards the ramp
+        let fast_start = self.is_fast_starting();
+        self.fast_start_remaining = self.fast_start_remaining.saturating_sub(1);
+
+        let pinned = self.hold.as_ref()
+            .and_then(|hold| renditions.iter().position(|r| r.id == hold.rendition_id));
+        if let Some(pinned) = pinned {
+            return Some(self.decide_pinned(renditions, pinned, context));
+        }
+
+        if self.decoder_overloaded {
+            if let Some(decision) = self.cap_resolution(renditions, context) {
+                return Some(decision);
+            }
+        }
+
+        if fast_start {
+            if let Some(index) = self.select_fast_start(renditions, context) {
+                self.last_selection = Some(index);
+                self.stability_counter = 0;
+                debug!(
+                    selected_id = %renditions[index].id,
+                    bandwidth = renditions[index].bandwidth,
+                    remaining = self.fast_start_remaining,
+                    "Fast start rendition selected"
+                );
+                return Some(AbrDecision { rendition: &renditions[index], reason: QualityChangeReason::StartupPhase });
+            }
+        }
+
+        let selected = self.select_automatic(renditions, context)?;
+        Some(AbrDecision { rendition: selected, reason: QualityChangeReason::Abr })
+    }
+
+    /// Bandwidth the fast start ramp decides on: the latest segment
+    /// download, or failing that what was known before the first one
+    fn fast_start_bandwidth(&self, context: &AbrContext) -> u64 {
+        match self.bandwidth_history.back() {
+            Some(latest) => latest.throughput_bps(),
+            None => self.bandwidth_estimate().max(context.network.bandwidth_estimate),
+        }
+    }
+
+    /// Index of the highest rendition the fast start bandwidth allows, or
+    /// `None` without any bandwidth information
+    fn select_fast_start(&self, renditions: &[Rendition], context: &AbrContext) -> Option<usize> {
+        let bandwidth = self.fast_start_bandwidth(context);
+        if bandwidth == 0 {
+            return None;
+        }
+
+        let mut budget = (bandwidth as f64 * FAST_START_SAFETY) as u64;
+        if context.max_bitrate > 0 {
+            budget = budget.min(context.max_bitrate);
+        }
+
+        let lowest = renditions.iter()
+            .enumerate()
+            .filter(|(_, r)| context.allows(r))
+            .min_by_key(|(_, r)| r.bandwidth)
+            .map(|(i, _)| i);
+        renditions.iter()
+            .enumerate()
+            .filter(|(_, r)| r.bandwidth <= budget && context.allows(r))
+            .max_by_key(|(_, r)| r.bandwidth)
+            .map(|(i, _)| i)
+            .or(lowest)
+    }
+
+    /// Decision while `renditions[pinned]` is pinned
+    fn decide_pinned<'a>(
+        &mut self,
+        renditions: &'a [Rendition],
+        pinned: usize,
+        context: &AbrContext,
+    ) -> AbrDecision<'a> {
+        let mut decision = AbrDecision { rendition: &renditions[pinned], reason: QualityChangeReason::Manual };
+
+        if context.is_buffer_emergency() {
+            // A timed hold gives way for good; a manual override resumes
+            // once the buffer recovers
+            if self.hold.as_ref().is_some_and(|hold| hold.expires_at.is_some()) {
+                self.hold = None;
+            }
+
+            let fallback = self.algorithm.select_rendition(renditions, context)
+                .filter(|r| r.bandwidth < decision.rendition.bandwidth);
+            if let Some(lower) = fallback {
+                debug!(
+                    pinned = %decision.rendition.id,
+                    selected = %lower.id,
+                    buffer_level = context.buffer_level,
+                    "Buffer emergency, downgrading pinned rendition"
+                );
+                decision = AbrDecision { rendition: lower, reason: QualityChangeReason::Buffer };
+            }
+        }
+
+        self.last_selection = renditions.iter().position(|r| r.id == decision.rendition.id);
+        self.stability_counter = 0;
+        decision
+    }
+
+    /// Algorithm decision, smoothed by the stability filter
+    fn select_automatic<'a>(
+        &mut self,
+        renditions: &'a [Rendition],
+        context: &AbrContext,
+    ) -> Option<&'a Rendition> {
         // Get algorithm recommendation
         let selected = self.algorithm.select_rendition(renditions, context)?;
 
         // Find index
         let new_index = renditions.iter().position(|r| r.id == selected.id)?;
 
-        // Apply stability filter to prevent oscillation
-        if let Some(last) = self.last_selection {
+        // Apply stability filter to prevent oscillation, unless the last
+        // rendition is no longer allowed, e.g. after a window resize
+        let last_allowed = self.last_selection
+            .filter(|&last| renditions.get(last).is_some_and(|r| context.allows(r)));
+        if let Some(last) = last_allowed {
             if new_index != last {
                 self.stability_counter += 1;
-                if self.stability_counter < 3 {
+                if self.stability_counter < SWITCH_STABILITY {
                     // Don't switch yet
                     return renditions.get(last);
                 }
@@ -181,9 +836,63 @@ impl AbrEngine {
         Some(selected)
     }
 
+    /// Predict the switch the engine is heading towards, without
+    /// recording a decision
+    ///
+    /// Returns the rendition the algorithm currently recommends and how
+    /// many more selections the stability filter will hold the current
+    /// rendition for, or `None` if no switch is pending.
+    pub fn predict_switch<'a>(
+        &self,
+        renditions: &'a [Rendition],
+        context: &AbrContext,
+    ) -> Option<(&'a Rendition, u32)> {
+        let context = &*self.effective_context(renditions, context);
+
+        // A pinned rendition is switched to on the next decision
+        let pinned = self.override_id()
+            .filter(|_| !context.is_buffer_emergency())
+            .and_then(|id| renditions.iter().find(|r| r.id == id));
+        if let Some(pinned) = pinned {
+            let last = renditions.get(self.last_selection?)?;
+            return (last.id != pinned.id).then_some((pinned, 0));
+        }
+
+        // The fast start ramp switches without the stability filter
+        if self.is_fast_starting() {
+            if let Some(index) = self.select_fast_start(renditions, context) {
+                return (self.last_selection? != index).then_some((&renditions[index], 0));
+            }
+        }
+
+        let selected = self.algorithm.select_rendition(renditions, context)?;
+        let new_index = renditions.iter().position(|r| r.id == selected.id)?;
+        let last = self.last_selection?;
+        if new_index == last {
+            return None;
+        }
+        Some((selected, SWITCH_STABILITY.saturating_sub(self.stability_counter + 1)))
+    }
+
     /// Get current bandwidth estimate
+    ///
+    /// Before the first segment download this is the fast start estimate
+    /// from playlist downloads and [`AbrEngine::with_initial_estimate`].
+    /// Restored history is blended in, each fresh measurement weighing as
+    /// much as a restored sample taken just now.
     pub fn bandwidth_estimate(&self) -> u64 {
-        self.bandwidth_estimate
+        let fresh = if self.bandwidth_estimate > 0 {
+            Some((self.bandwidth_estimate as f64, self.bandwidth_history.len() as f64))
+        } else {
+            let startup = self.startup.bandwidth();
+            (startup > 0).then_some((startup as f64, 1.0))
+        };
+
+        match (fresh, self.restored_estimate()) {
+            (Some((fresh, n)), Some((restored, w))) => ((fresh * n + restored * w) / (n + w)) as u64,
+            (Some((bandwidth, _)), None) | (None, Some((bandwidth, _))) => bandwidth as u64,
+            (None, None) => 0,
+        }
     }
 
     /// Get algorithm name
@@ -245,14 +954,7 @@ impl AbrAlgorithm for ThroughputAlgorithm {
         renditions
             .iter()
             .filter(|r| r.bandwidth <= max_bitrate)
-            .filter(|r| {
-                // Filter by screen resolution if available
-                if let (Some(res), Some(screen_w)) = (&r.resolution, context.screen_width) {
-                    res.width <= screen_w
-                } else {
-                    true
-                }
-            })
+            .filter(|r| context.allows(r))
             .max_by_key(|r| r.bandwidth)
     }
 
@@ -323,7 +1025,7 @@ impl AbrAlgorithm for BolaAlgorithm {
         let mut best: Option<&Rendition> = None;
         let mut best_score = f64::NEG_INFINITY;
 
-        for rendition in renditions {
+        for rendition in renditions.iter().filter(|r| context.allows(r)) {
             // Skip if over max bitrate
             if context.max_bitrate > 0 && rendition.bandwidth > context.max_bitrate {
                 continue;
@@ -343,7 +1045,7 @@ impl AbrAlgorithm for BolaAlgorithm {
 
         // Safety: if buffer is very low, pick lowest quality
         if buffer < self.buffer_min {
-            return renditions.first();
+            return renditions.iter().find(|r| context.allows(r));
         }
 
         best
@@ -403,12 +1105,12 @@ impl AbrAlgorithm for HybridAlgorithm {
                     let t_idx = renditions.iter().position(|r| r.id == t.id).unwrap_or(0);
                     let b_idx = renditions.iter().position(|r| r.id == b.id).unwrap_or(0);
                     let avg_idx = (t_idx + b_idx) / 2;
-                    renditions.get(avg_idx)
+                    renditions.get(avg_idx).filter(|r| context.allows(r)).or(Some(b))
                 }
             }
             (Some(t), None) => Some(t),
             (None, Some(b)) => Some(b),
-            (None, None) => renditions.first(),
+            (None, None) => renditions.iter().find(|r| context.allows(r)),
         }
     }
 
@@ -440,6 +1142,7 @@ mod tests {
                 hdr: None,
                 language: None,
                 name: None,
+                audio_group: None,
             },
             Rendition {
                 id: "720p".to_string(),
@@ -452,6 +1155,7 @@ mod tests {
                 hdr: None,
                 language: None,
                 name: None,
+                audio_group: None,
             },
             Rendition {
                 id: "1080p".to_string(),
@@ -464,6 +1168,7 @@ mod tests {
                 hdr: None,
                 language: None,
                 name: None,
+                audio_group: None,
             },
         ]
     }
@@ -514,4 +1219,377 @@ mod tests {
         let selected = algorithm.select_rendition(&renditions, &context);
         assert_eq!(selected.map(|r| &r.id), Some(&"360p".to_string()));
     }
+
+    #[test]
+    fn test_predict_switch() {
+        let renditions = create_test_renditions();
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput);
+        let context = |bandwidth_estimate| AbrContext {
+            buffer_level: 20.0,
+            network: NetworkInfo {
+                bandwidth_estimate,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let selected = engine.select_rendition(&renditions, &context(1_000_000));
+        assert_eq!(selected.map(|r| r.id.as_str()), Some("360p"));
+        assert!(engine.predict_switch(&renditions, &context(1_000_000)).is_none());
+
+        // The stability filter holds 360p for two more decisions
+        let high = context(10_000_000);
+        for remaining in (0..3).rev() {
+            let (predicted, after) = engine.predict_switch(&renditions, &high).unwrap();
+            assert_eq!(predicted.id, "1080p");
+            assert_eq!(after, remaining);
+            engine.select_rendition(&renditions, &high);
+        }
+        assert_eq!(engine.select_rendition(&renditions, &high).map(|r| r.id.as_str()), Some("1080p"));
+        assert!(engine.predict_switch(&renditions, &high).is_none());
+    }
+
+    #[test]
+    fn test_override_respected_under_good_bandwidth() {
+        let renditions = create_test_renditions();
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput);
+        let context = AbrContext {
+            buffer_level: 20.0,
+            rebuffer_threshold: 2.0,
+            network: NetworkInfo {
+                bandwidth_estimate: 10_000_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        engine.set_override(Some("360p".to_string()));
+        for _ in 0..5 {
+            let decision = engine.decide(&renditions, &context).unwrap();
+            assert_eq!(decision.rendition.id, "360p");
+            assert_eq!(decision.reason, QualityChangeReason::Manual);
+        }
+        assert!(engine.predict_switch(&renditions, &context).is_none());
+
+        // Clearing the override returns to automatic selection
+        engine.set_override(None);
+        let decision = engine.decide(&renditions, &context).unwrap();
+        assert_eq!(decision.reason, QualityChangeReason::Abr);
+
+        // An override that matches no rendition is ignored
+        engine.set_override(Some("4k".to_string()));
+        let decision = engine.decide(&renditions, &context).unwrap();
+        assert_eq!(decision.reason, QualityChangeReason::Abr);
+    }
+
+    #[test]
+    fn test_emergency_downgrade_below_rebuffer_threshold() {
+        let renditions = create_test_renditions();
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Bola);
+        let context = |buffer_level| AbrContext {
+            buffer_level,
+            target_buffer: 30.0,
+            rebuffer_threshold: 2.0,
+            ..Default::default()
+        };
+
+        engine.set_override(Some("1080p".to_string()));
+        let decision = engine.decide(&renditions, &context(1.0)).unwrap();
+        assert_eq!(decision.rendition.id, "360p");
+        assert_eq!(decision.reason, QualityChangeReason::Buffer);
+
+        // An indefinite override resumes once the buffer recovers
+        let decision = engine.decide(&renditions, &context(20.0)).unwrap();
+        assert_eq!(decision.rendition.id, "1080p");
+        assert_eq!(decision.reason, QualityChangeReason::Manual);
+
+        // A timed hold is released by the emergency
+        engine.hold_rendition("1080p", Duration::from_secs(60));
+        engine.decide(&renditions, &context(1.0));
+        assert_eq!(engine.override_id(), None);
+    }
+
+    /// Full ladder from 400 kbps to 8 Mbps
+    fn create_ladder() -> Vec<Rendition> {
+        let template = create_test_renditions().remove(0);
+        [400_000, 800_000, 1_500_000, 2_800_000, 5_000_000, 8_000_000]
+            .iter()
+            .map(|&bandwidth| Rendition { id: format!("{}k", bandwidth / 1000), bandwidth, ..template.clone() })
+            .collect()
+    }
+
+    /// Time to download `bytes` at `bandwidth` bps
+    fn transfer_time(bytes: usize, bandwidth: u64) -> Duration {
+        Duration::from_secs_f64(bytes as f64 * 8.0 / bandwidth as f64)
+    }
+
+    #[test]
+    fn test_fast_start_cold_start() {
+        let renditions = create_ladder();
+        let context = AbrContext { target_buffer: 30.0, rebuffer_threshold: 2.0, ..Default::default() };
+
+        for bandwidth in [600_000, 1_200_000, 2_000_000, 3_500_000, 6_000_000, 20_000_000] {
+            let ideal = renditions.iter().rposition(|r| r.bandwidth <= bandwidth).unwrap_or(0);
+
+            let mut engine = AbrEngine::new(AbrAlgorithmType::Bola).with_fast_start(3);
+            // Master playlist, then the first media playlist
+            engine.record_playlist_download(4_000, transfer_time(4_000, bandwidth));
+            engine.record_playlist_download(12_000, transfer_time(12_000, bandwidth));
+            let context = AbrContext {
+                network: NetworkInfo { bandwidth_estimate: engine.bandwidth_estimate(), ..Default::default() },
+                ..context.clone()
+            };
+
+            let decision = engine.decide(&renditions, &context).unwrap();
+            assert_eq!(decision.reason, QualityChangeReason::StartupPhase);
+            let picked = renditions.iter().position(|r| r.id == decision.rendition.id).unwrap();
+            assert!(
+                picked.abs_diff(ideal) <= 1,
+                "{} bps: picked {} but ideal is {}", bandwidth, decision.rendition.id, renditions[ideal].id
+            );
+
+            // Without fast start BOLA starts an empty buffer at the bottom
+            let mut cold = AbrEngine::new(AbrAlgorithmType::Bola);
+            assert_eq!(cold.decide(&renditions, &context).unwrap().rendition.id, "400k");
+        }
+    }
+
+    #[test]
+    fn test_fast_start_initial_estimate() {
+        let renditions = create_ladder();
+        let context = AbrContext::default();
+
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput)
+            .with_fast_start(2)
+            .with_initial_estimate(6_000_000);
+        assert_eq!(engine.bandwidth_estimate(), 6_000_000);
+        assert_eq!(engine.decide(&renditions, &context).unwrap().rendition.id, "5000k");
+
+        // A playlist sample is averaged with the hint
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput)
+            .with_fast_start(2)
+            .with_initial_estimate(6_000_000);
+        engine.record_playlist_download(10_000, transfer_time(10_000, 2_000_000));
+        assert_eq!(engine.bandwidth_estimate(), 4_000_000);
+        assert_eq!(engine.decide(&renditions, &context).unwrap().rendition.id, "2800k");
+
+        // Without any information the algorithm decides
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Bola).with_fast_start(2);
+        assert_eq!(engine.decide(&renditions, &context).unwrap().reason, QualityChangeReason::Abr);
+    }
+
+    #[test]
+    fn test_fast_start_ramp_and_handover() {
+        let renditions = create_ladder();
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput).with_fast_start(3);
+        engine.record_playlist_download(8_000, transfer_time(8_000, 1_000_000));
+        let context = |engine: &AbrEngine| AbrContext {
+            buffer_level: 20.0,
+            network: NetworkInfo { bandwidth_estimate: engine.bandwidth_estimate(), ..Default::default() },
+            ..Default::default()
+        };
+
+        let decision = engine.decide(&renditions, &context(&engine)).unwrap();
+        assert_eq!((decision.rendition.id.as_str(), decision.reason), ("800k", QualityChangeReason::StartupPhase));
+
+        // The first segment shows far more bandwidth; the ramp follows at once
+        engine.record_measurement(2_000_000, transfer_time(2_000_000, 10_000_000));
+        assert_eq!(engine.predict_switch(&renditions, &context(&engine)).map(|(r, n)| (r.id.as_str(), n)), Some(("8000k", 0)));
+        let decision = engine.decide(&renditions, &context(&engine)).unwrap();
+        assert_eq!((decision.rendition.id.as_str(), decision.reason), ("8000k", QualityChangeReason::StartupPhase));
+
+        engine.record_measurement(2_000_000, transfer_time(2_000_000, 10_000_000));
+        assert_eq!(engine.decide(&renditions, &context(&engine)).unwrap().reason, QualityChangeReason::StartupPhase);
+        assert!(!engine.is_fast_starting());
+
+        // After the ramp the algorithm decides, behind the stability filter
+        engine.record_measurement(500_000, transfer_time(500_000, 2_000_000));
+        let decision = engine.decide(&renditions, &context(&engine)).unwrap();
+        assert_eq!((decision.rendition.id.as_str(), decision.reason), ("8000k", QualityChangeReason::Abr));
+    }
+
+    #[test]
+    fn test_history_restore_and_blend() {
+        let mut previous = AbrEngine::new(AbrAlgorithmType::Throughput);
+        previous.set_connection_type(Some(ConnectionType::Wifi));
+        for _ in 0..4 {
+            previous.record_measurement(1_000_000, transfer_time(1_000_000, 5_000_000));
+        }
+        let blob = previous.export_history().unwrap();
+        assert!(!blob.contains("http"));
+
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput);
+        engine.set_connection_type(Some(ConnectionType::Wifi));
+        assert_eq!(engine.restore_history(&blob, Duration::from_secs(3600)).unwrap(), 4);
+        assert!(engine.bandwidth_estimate().abs_diff(5_000_000) < 10_000);
+
+        // Fresh measurements take over from the restored history
+        engine.record_measurement(250_000, transfer_time(250_000, 1_000_000));
+        let blended = engine.bandwidth_estimate();
+        assert!(blended.abs_diff(4_000_000) < 10_000, "{}", blended);
+        for _ in 0..9 {
+            engine.record_measurement(250_000, transfer_time(250_000, 1_000_000));
+        }
+        assert!(engine.bandwidth_estimate() < 2_000_000);
+
+        // Exported history carries on into the next session
+        let mut next = AbrEngine::new(AbrAlgorithmType::Throughput);
+        next.set_connection_type(Some(ConnectionType::Wifi));
+        assert_eq!(next.restore_history(&engine.export_history().unwrap(), Duration::from_secs(3600)).unwrap(), 14);
+    }
+
+    #[test]
+    fn test_history_ignores_stale_and_other_networks() {
+        let now = unix_millis();
+        let sample = |age: Duration, connection_type| HistorySample {
+            throughput_bps: 8_000_000,
+            recorded_at_ms: now - age.as_millis() as u64,
+            connection_type,
+        };
+        let blob = serde_json::to_string(&HistoryBlob {
+            version: HISTORY_VERSION,
+            samples: vec![
+                sample(Duration::from_secs(2 * 3600), Some(ConnectionType::Wifi)),
+                sample(Duration::from_secs(60), Some(ConnectionType::Cellular4G)),
+                sample(Duration::from_secs(60), Some(ConnectionType::Wifi)),
+            ],
+        }).unwrap();
+
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput);
+        engine.set_connection_type(Some(ConnectionType::Wifi));
+        assert_eq!(engine.restore_history(&blob, Duration::from_secs(3600)).unwrap(), 1);
+        assert_eq!(engine.restore_history(&blob, Duration::from_secs(30)).unwrap(), 0);
+        assert_eq!(engine.bandwidth_estimate(), 0);
+
+        engine.set_connection_type(Some(ConnectionType::Ethernet));
+        assert_eq!(engine.restore_history(&blob, Duration::from_secs(3 * 3600)).unwrap(), 0);
+        engine.set_connection_type(None);
+        assert_eq!(engine.restore_history(&blob, Duration::from_secs(3 * 3600)).unwrap(), 0);
+
+        assert!(engine.restore_history("not history", Duration::MAX).is_err());
+        assert!(engine.restore_history(r#"{"version":99,"samples":[]}"#, Duration::MAX).is_err());
+    }
+
+    #[test]
+    fn test_device_capabilities_filter_renditions() {
+        let mut renditions = create_test_renditions();
+        renditions[2].video_codec = Some(VideoCodec::H265);
+        renditions[2].hdr = Some(HdrFormat::Hdr10);
+        let context = |device| AbrContext {
+            buffer_level: 20.0,
+            network: NetworkInfo { bandwidth_estimate: 10_000_000, ..Default::default() },
+            device,
+            ..Default::default()
+        };
+
+        let any = context(DeviceCapabilities::default());
+        let h264_only = context(DeviceCapabilities { video_codecs: Some(vec![VideoCodec::H264]), ..Default::default() });
+        let sdr_only = context(DeviceCapabilities { hdr_formats: Some(Vec::new()), ..Default::default() });
+        for algorithm in [AbrAlgorithmType::Throughput, AbrAlgorithmType::Bola, AbrAlgorithmType::Hybrid] {
+            let pick = |context: &AbrContext| AbrEngine::new(algorithm).select_rendition(&renditions, context).unwrap().id.clone();
+            assert_eq!(pick(&any), "1080p", "{:?}", algorithm);
+            assert_eq!(pick(&h264_only), "720p", "{:?}", algorithm);
+            assert_eq!(pick(&sdr_only), "720p", "{:?}", algorithm);
+        }
+
+        let small = context(DeviceCapabilities {
+            max_resolution: Some(Resolution::new(1280, 720)),
+            audio_codecs: Some(vec![AudioCodec::Aac, AudioCodec::Opus]),
+            ..Default::default()
+        });
+        assert!(!small.allows(&renditions[2]) && small.allows(&renditions[1]));
+
+        // With nothing playable the capabilities are ignored rather than stalling
+        let none = context(DeviceCapabilities { video_codecs: Some(vec![VideoCodec::Av1]), ..Default::default() });
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput);
+        assert_eq!(engine.select_rendition(&renditions, &none).map(|r| r.id.as_str()), Some("1080p"));
+    }
+
+    #[test]
+    fn test_screen_resize_mid_session() {
+        let renditions = create_test_renditions();
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput);
+        let context = |width, height| AbrContext {
+            buffer_level: 20.0,
+            screen_width: Some(width),
+            screen_height: Some(height),
+            network: NetworkInfo { bandwidth_estimate: 10_000_000, ..Default::default() },
+            ..Default::default()
+        };
+
+        assert_eq!(engine.select_rendition(&renditions, &context(1920, 1080)).unwrap().id, "1080p");
+
+        // Shrinking the window leaves the larger rendition at once
+        assert_eq!(engine.select_rendition(&renditions, &context(1280, 720)).unwrap().id, "720p");
+
+        // Going fullscreen again waits for the stability filter
+        let fullscreen = context(1920, 1080);
+        assert_eq!(engine.predict_switch(&renditions, &fullscreen).map(|(r, n)| (r.id.as_str(), n)), Some(("1080p", 2)));
+        assert_eq!(engine.select_rendition(&renditions, &fullscreen).unwrap().id, "720p");
+    }
+
+    #[test]
+    fn test_dropped_frame_cap() {
+        let renditions = create_test_renditions();
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput);
+        let context = AbrContext {
+            buffer_level: 20.0,
+            network: NetworkInfo { bandwidth_estimate: 10_000_000, ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(engine.decide(&renditions, &context).unwrap().rendition.id, "1080p");
+
+        // Drops below the threshold, or in less than a window, are tolerated
+        engine.record_frames(200, 30);
+        assert_eq!(engine.decide(&renditions, &context).unwrap().rendition.id, "1080p");
+        engine.record_frames(300, 30);
+        assert_eq!(engine.decide(&renditions, &context).unwrap().reason, QualityChangeReason::Abr);
+
+        // 20% dropped over the last window caps the resolution at once
+        engine.record_frames(600, 90);
+        let decision = engine.decide(&renditions, &context).unwrap();
+        assert_eq!((decision.rendition.id.as_str(), decision.reason), ("720p", QualityChangeReason::DecoderOverload));
+        assert_eq!(engine.resolution_cap(), Some(Resolution::new(1280, 720)));
+        assert!(engine.predict_switch(&renditions, &context).is_none());
+
+        // The cap holds whatever the bandwidth until a clean window
+        engine.record_frames(750, 92);
+        for _ in 0..5 {
+            let decision = engine.decide(&renditions, &context).unwrap();
+            assert_eq!((decision.rendition.id.as_str(), decision.reason), ("720p", QualityChangeReason::Abr));
+        }
+        engine.record_frames(900, 93);
+        assert_eq!(engine.resolution_cap(), None);
+
+        // Released, the engine climbs back behind the stability filter
+        let ids: Vec<_> = (0..3).map(|_| engine.decide(&renditions, &context).unwrap().rendition.id.clone()).collect();
+        assert_eq!(ids, ["720p", "720p", "1080p"]);
+
+        // Nothing is capped below the lowest rendition
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput);
+        let low = AbrContext { network: NetworkInfo { bandwidth_estimate: 1_000_000, ..Default::default() }, ..context };
+        engine.decide(&renditions, &low);
+        engine.record_frames(300, 150);
+        assert_eq!(engine.decide(&renditions, &low).unwrap().reason, QualityChangeReason::Abr);
+        assert_eq!(engine.resolution_cap(), None);
+    }
+
+    #[test]
+    fn test_hold_expires() {
+        let renditions = create_test_renditions();
+        let mut engine = AbrEngine::new(AbrAlgorithmType::Throughput);
+        let context = AbrContext {
+            buffer_level: 20.0,
+            network: NetworkInfo {
+                bandwidth_estimate: 10_000_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        engine.hold_rendition("360p", Duration::ZERO);
+        assert_eq!(engine.override_id(), None);
+        let decision = engine.decide(&renditions, &context).unwrap();
+        assert_eq!(decision.reason, QualityChangeReason::Abr);
+    }
 }
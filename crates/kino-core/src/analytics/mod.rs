@@ -0,0 +1,970 @@
+//! Analytics event emission
+//!
+//! Captures playback events for:
+//! - Quality of Experience (QoE) metrics
+//! - Error tracking
+//! - Usage analytics
+//! - A/B testing
+//!
+//! Events are delivered through pluggable [`AnalyticsSink`]s: an
+//! [`HttpSink`] posting gzipped batches to a collector, or a
+//! [`JsonlFileSink`] for local debugging.
+//!
+//! A [`SessionRecorder`] keeps a timeline of sampled player metrics and
+//! events that can be exported as JSON or CSV for plotting.
+
+mod recorder;
+mod sink;
+
+pub use recorder::{
+    CsvWriter, RecorderConfig, SessionRecorder, SessionTimeline, TimelineCsv, TimelineEvent, TimelineSample,
+};
+pub use sink::{AnalyticsSink, BatchConfig, HttpSink, JsonlFileSink, SinkStats};
+
+use crate::types::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sink::SinkWorker;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Analytics event types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AnalyticsEvent {
+    /// Content loaded
+    Load {
+        url: String,
+        is_live: bool,
+    },
+
+    /// Playback started
+    Play {
+        position: f64,
+    },
+
+    /// Playback paused
+    Pause {
+        position: f64,
+    },
+
+    /// Seek performed
+    Seek {
+        from: f64,
+        to: f64,
+    },
+
+    /// Rebuffering started
+    Rebuffer {
+        position: f64,
+        buffer_level: f64,
+    },
+
+    /// Rebuffering ended
+    RebufferEnd {
+        position: f64,
+        duration: f64,
+    },
+
+    /// Quality change
+    QualityChange {
+        from_bitrate: u64,
+        to_bitrate: u64,
+        from_resolution: Option<Resolution>,
+        to_resolution: Option<Resolution>,
+        reason: QualityChangeReason,
+    },
+
+    /// Playback rate changed, possibly entering or leaving trick play
+    TrickMode {
+        from_rate: f64,
+        to_rate: f64,
+        /// Whether segments now come from an I-frame rendition
+        iframe_only: bool,
+        position: f64,
+    },
+
+    /// A downloaded segment failed integrity verification
+    SegmentIntegrityFailure {
+        segment: u64,
+        url: String,
+        /// [`crate::integrity::IntegrityFailure::kind`]
+        kind: String,
+        detail: String,
+        /// Whether the segment is fetched again
+        refetch: bool,
+    },
+
+    /// CDN pathway switched (content steering)
+    PathwaySwitch {
+        from: Option<String>,
+        to: String,
+        reason: PathwaySwitchReason,
+    },
+
+    /// Prefetch effectiveness summary
+    Prefetch {
+        issued: u64,
+        hits: u64,
+        misses: u64,
+        wasted: u64,
+        cancelled: u64,
+        hit_ratio: f64,
+    },
+
+    /// Audio track switched
+    AudioTrackChange {
+        from: Option<String>,
+        to: String,
+        position: f64,
+    },
+
+    /// State change
+    StateChange {
+        from: PlayerState,
+        to: PlayerState,
+        position: f64,
+    },
+
+    /// Playback ended
+    End {
+        position: f64,
+        watch_time: f64,
+    },
+
+    /// Hardware decoding was requested but a software decoder took over
+    DecoderFallback {
+        requested: String,
+        active: String,
+        decoder: Option<String>,
+        position: f64,
+    },
+
+    /// Error occurred
+    ///
+    /// `code` is the stable [`crate::Error::error_code`]; errors that are
+    /// not retryable are fatal.
+    Error {
+        code: String,
+        message: String,
+        fatal: bool,
+        position: f64,
+    },
+
+    /// Heartbeat (periodic)
+    Heartbeat {
+        position: f64,
+        buffer_level: f64,
+        bitrate: u64,
+        dropped_frames: u64,
+        decoded_frames: u64,
+    },
+
+    /// Custom event
+    Custom {
+        name: String,
+        data: serde_json::Value,
+    },
+}
+
+impl AnalyticsEvent {
+    /// Error event for `err` at `position`
+    pub fn error(err: &crate::Error, position: f64) -> Self {
+        AnalyticsEvent::Error {
+            code: err.error_code().to_string(),
+            message: err.to_string(),
+            fatal: !err.is_retryable(),
+            position,
+        }
+    }
+}
+
+/// Reason for quality change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityChangeReason {
+    /// ABR algorithm decision
+    Abr,
+    /// User manual selection
+    Manual,
+    /// Buffer-based downgrade
+    Buffer,
+    /// Initial selection
+    Initial,
+    /// Switch to or from an I-frame rendition for trick play
+    TrickPlay,
+    /// Fast start pick made before the ABR algorithm has taken over
+    StartupPhase,
+    /// The playing rendition was dropped from a refreshed live manifest
+    LadderChange,
+    /// Resolution capped because the decoder was dropping frames
+    DecoderOverload,
+}
+
+/// Reason for a CDN pathway switch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathwaySwitchReason {
+    /// Steering server changed the pathway priority
+    Steering,
+    /// Active pathway demoted after consecutive failures
+    Failover,
+}
+
+/// Analytics event with metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyticsEventRecord {
+    /// Unique event ID
+    pub id: Uuid,
+    /// Session ID
+    pub session_id: SessionId,
+    /// Player library version
+    #[serde(default)]
+    pub player_version: String,
+    /// Timestamp
+    pub timestamp: DateTime<Utc>,
+    /// Sequence number
+    pub sequence: u64,
+    /// The event
+    #[serde(flatten)]
+    pub event: AnalyticsEvent,
+}
+
+/// How long dropping an emitter waits for sinks to deliver pending events
+const FLUSH_ON_DROP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Analytics emitter
+///
+/// Every event is tagged with the session ID, player version and a
+/// sequence number, then handed to each registered [`AnalyticsSink`].
+/// Sinks are fed by their own background task, which batches and
+/// retries per the sink's [`BatchConfig`], so a slow sink never holds up
+/// playback. Dropping the emitter delivers whatever the sinks still hold.
+pub struct AnalyticsEmitter {
+    /// Session ID
+    session_id: SessionId,
+    /// Event sequence counter
+    sequence: RwLock<u64>,
+    /// Recently emitted events
+    buffer: RwLock<Vec<AnalyticsEventRecord>>,
+    /// Maximum buffer size before it is cleared
+    max_buffer_size: usize,
+    /// Registered sinks
+    sinks: Mutex<Vec<SinkWorker>>,
+}
+
+impl AnalyticsEmitter {
+    /// Create a new analytics emitter
+    pub fn new() -> Self {
+        Self::for_session(SessionId::new())
+    }
+
+    /// Create an emitter tagging events with an existing session ID
+    pub fn for_session(session_id: SessionId) -> Self {
+        Self {
+            session_id,
+            sequence: RwLock::new(0),
+            buffer: RwLock::new(Vec::new()),
+            max_buffer_size: 50,
+            sinks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Create with beacon endpoint
+    pub fn with_beacon(beacon_url: String) -> Self {
+        let emitter = Self::new();
+        emitter.add_sink(HttpSink::new(beacon_url));
+        emitter
+    }
+
+    /// Register a sink; it receives every event emitted from now on
+    pub fn add_sink(&self, sink: impl AnalyticsSink + 'static) {
+        let worker = SinkWorker::spawn(Arc::new(sink));
+        self.sinks.lock().unwrap_or_else(|e| e.into_inner()).push(worker);
+    }
+
+    /// Get session ID events are tagged with
+    pub fn session_id(&self) -> SessionId {
+        self.session_id
+    }
+
+    /// Emit an analytics event
+    pub async fn emit(&self, event: AnalyticsEvent) {
+        // Held until the record is queued so sinks see sequence order
+        let mut seq = self.sequence.write().await;
+        *seq += 1;
+
+        let record = AnalyticsEventRecord {
+            id: Uuid::new_v4(),
+            session_id: self.session_id,
+            player_version: crate::VERSION.to_string(),
+            timestamp: Utc::now(),
+            sequence: *seq,
+            event,
+        };
+
+        debug!(event_id = %record.id, sequence = record.sequence, event = ?record.event, "Analytics event");
+
+        for sink in self.sinks.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            sink.queue(record.clone());
+        }
+        drop(seq);
+
+        // Add to buffer, starting over once it is full
+        let mut buffer = self.buffer.write().await;
+        if buffer.len() >= self.max_buffer_size {
+            buffer.clear();
+        }
+        buffer.push(record);
+    }
+
+    /// Deliver everything the sinks hold, waiting until each has tried
+    pub async fn flush(&self) {
+        let pending: Vec<_> = self
+            .sinks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter_map(|sink| sink.flush())
+            .collect();
+
+        info!(sinks = pending.len(), "Flushing analytics events");
+        for done in pending {
+            let _ = done.await;
+        }
+    }
+
+    /// Delivery counters for each sink
+    pub fn sink_stats(&self) -> Vec<SinkStats> {
+        self.sinks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|sink| sink.stats())
+            .collect()
+    }
+
+    /// Get all buffered events
+    pub async fn get_events(&self) -> Vec<AnalyticsEventRecord> {
+        self.buffer.read().await.clone()
+    }
+
+    /// Clear buffer
+    pub async fn clear(&self) {
+        self.buffer.write().await.clear();
+    }
+
+    /// Set beacon endpoint
+    pub fn set_beacon_url(&mut self, url: String) {
+        self.add_sink(HttpSink::new(url));
+    }
+}
+
+impl Default for AnalyticsEmitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for AnalyticsEmitter {
+    fn drop(&mut self) {
+        let workers = std::mem::take(self.sinks.get_mut().unwrap_or_else(|e| e.into_inner()));
+        if workers.is_empty() {
+            return;
+        }
+
+        // Closing the channels makes each worker deliver what it holds and exit
+        let tasks: Vec<_> = workers.into_iter().map(SinkWorker::close).collect();
+
+        // Wait for delivery where the runtime allows blocking; on a
+        // current-thread runtime the workers finish on their own, so call
+        // `flush` first if the runtime is about to shut down
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        if handle.runtime_flavor() != tokio::runtime::RuntimeFlavor::MultiThread {
+            return;
+        }
+        tokio::task::block_in_place(|| {
+            handle.block_on(async {
+                let all = async {
+                    for task in tasks {
+                        let _ = task.await;
+                    }
+                };
+                if tokio::time::timeout(FLUSH_ON_DROP_TIMEOUT, all).await.is_err() {
+                    warn!("Timed out delivering analytics events on drop");
+                }
+            })
+        });
+    }
+}
+
+/// QoE scoring model
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QoeModel {
+    /// Heuristic penalties for startup delay, rebuffering and switches
+    #[default]
+    Simple,
+    /// ITU-T P.1203-inspired model (mode 0, metadata only)
+    ///
+    /// Follows the structure of P.1203.1 mode 0 for per-segment video
+    /// quality (coding, upscaling and frame-rate degradations on the R
+    /// scale) and of P.1203.3 for stalling. It is not a conformant
+    /// implementation: audio is not modelled, so audiovisual quality is
+    /// the video score, and temporal pooling is a recency-weighted mean.
+    P1203Mode0,
+}
+
+/// A played media segment, for per-segment quality scoring
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QoeSegment {
+    /// Media duration in seconds
+    pub duration: f64,
+    /// Video bitrate in bps
+    pub bitrate: u64,
+    /// Coded resolution (display resolution if unknown)
+    pub resolution: Option<Resolution>,
+    /// Frame rate (30 if unknown)
+    pub frame_rate: Option<f64>,
+}
+
+/// A stall in playback
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QoeStall {
+    /// Media position the stall happened at, in seconds
+    pub position: f64,
+    /// Stall length in seconds
+    pub duration: f64,
+}
+
+/// Score for one window of a session
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QoeWindow {
+    /// Window start in media seconds
+    pub start: f64,
+    /// Window end in media seconds
+    pub end: f64,
+    /// QoE score (0-100)
+    pub score: f64,
+    /// Mean opinion score (1-5)
+    pub mos: f64,
+}
+
+/// QoE (Quality of Experience) calculator
+pub struct QoeCalculator {
+    /// Scoring model
+    model: QoeModel,
+    /// Resolution the video is displayed at, for upscaling degradation
+    display_resolution: Resolution,
+    /// Initial buffer time
+    initial_buffer_time: f64,
+    /// Stalls after startup
+    stalls: Vec<QoeStall>,
+    /// Quality switches
+    quality_switches: Vec<(f64, u64)>, // (timestamp, bitrate)
+    /// Played segments with their start in the media timeline
+    segments: Vec<(f64, QoeSegment)>,
+}
+
+/// The part of a session a score is computed over
+struct QoeSpan {
+    start: f64,
+    end: f64,
+    initial_buffer_time: f64,
+    stalls: Vec<QoeStall>,
+    quality_switches: usize,
+    segments: Vec<(f64, QoeSegment)>,
+}
+
+impl QoeCalculator {
+    pub fn new() -> Self {
+        Self::with_model(QoeModel::Simple)
+    }
+
+    /// Create a calculator using the given scoring model
+    pub fn with_model(model: QoeModel) -> Self {
+        Self {
+            model,
+            display_resolution: Resolution::FHD_1080P,
+            initial_buffer_time: 0.0,
+            stalls: Vec::new(),
+            quality_switches: Vec::new(),
+            segments: Vec::new(),
+        }
+    }
+
+    /// Get the scoring model
+    pub fn model(&self) -> QoeModel {
+        self.model
+    }
+
+    /// Change the scoring model; recorded data is kept
+    pub fn set_model(&mut self, model: QoeModel) {
+        self.model = model;
+    }
+
+    /// Set the display resolution (defaults to 1080p)
+    pub fn set_display_resolution(&mut self, resolution: Resolution) {
+        self.display_resolution = resolution;
+    }
+
+    /// Record initial buffering time
+    pub fn record_initial_buffer(&mut self, duration: f64) {
+        self.initial_buffer_time = duration;
+    }
+
+    /// Record rebuffer event at the end of the media recorded so far
+    pub fn record_rebuffer(&mut self, duration: f64) {
+        let position = self.media_end();
+        self.record_stall(position, duration);
+    }
+
+    /// Record a stall at a media position
+    pub fn record_stall(&mut self, position: f64, duration: f64) {
+        self.stalls.push(QoeStall { position, duration });
+    }
+
+    /// Record quality switch
+    pub fn record_quality_switch(&mut self, timestamp: f64, bitrate: u64) {
+        self.quality_switches.push((timestamp, bitrate));
+    }
+
+    /// Record bitrate sample
+    pub fn record_bitrate(&mut self, duration: f64, bitrate: u64) {
+        self.record_segment(QoeSegment {
+            duration,
+            bitrate,
+            resolution: None,
+            frame_rate: None,
+        });
+    }
+
+    /// Record a played segment
+    pub fn record_segment(&mut self, segment: QoeSegment) {
+        let start = self.media_end();
+        self.segments.push((start, segment));
+    }
+
+    /// End of the recorded media in seconds
+    fn media_end(&self) -> f64 {
+        self.segments.last().map_or(0.0, |(start, s)| start + s.duration)
+    }
+
+    /// Calculate QoE score (0-100)
+    pub fn calculate_qoe(&self) -> f64 {
+        self.score(&self.session_span()).0
+    }
+
+    /// Calculate the mean opinion score (1-5)
+    pub fn calculate_mos(&self) -> f64 {
+        self.score(&self.session_span()).1
+    }
+
+    /// Score consecutive windows of `window_secs` media seconds
+    ///
+    /// Each window is scored on its own: segments are clipped to it,
+    /// stalls and switches count in the window they happen in, and the
+    /// startup delay counts in the first.
+    pub fn calculate_qoe_windowed(&self, window_secs: f64) -> Vec<QoeWindow> {
+        let end = self.media_end();
+        if window_secs <= 0.0 || end <= 0.0 {
+            return Vec::new();
+        }
+
+        let mut windows = Vec::new();
+        let mut start = 0.0;
+        while start < end {
+            let window_end = (start + window_secs).min(end);
+            let span = self.span(start, window_end);
+            let (score, mos) = self.score(&span);
+            windows.push(QoeWindow {
+                start,
+                end: window_end,
+                score,
+                mos,
+            });
+            start += window_secs;
+        }
+        windows
+    }
+
+    fn session_span(&self) -> QoeSpan {
+        QoeSpan {
+            start: 0.0,
+            end: self.media_end(),
+            initial_buffer_time: self.initial_buffer_time,
+            stalls: self.stalls.clone(),
+            quality_switches: self.quality_switches.len(),
+            segments: self.segments.clone(),
+        }
+    }
+
+    /// Data falling in `[start, end)`; the last window also takes events
+    /// at its end
+    fn span(&self, start: f64, end: f64) -> QoeSpan {
+        let is_last = end >= self.media_end();
+        let within = |t: f64| t >= start && (t < end || (is_last && t <= end));
+
+        let segments = self
+            .segments
+            .iter()
+            .filter_map(|(seg_start, segment)| {
+                let clipped_start = seg_start.max(start);
+                let clipped_end = (seg_start + segment.duration).min(end);
+                (clipped_end > clipped_start)
+                    .then_some((clipped_start, QoeSegment { duration: clipped_end - clipped_start, ..*segment }))
+            })
+            .collect();
+
+        QoeSpan {
+            start,
+            end,
+            initial_buffer_time: if start == 0.0 { self.initial_buffer_time } else { 0.0 },
+            stalls: self.stalls.iter().filter(|s| within(s.position)).copied().collect(),
+            quality_switches: self.quality_switches.iter().filter(|(t, _)| within(*t)).count(),
+            segments,
+        }
+    }
+
+    /// Score a span with the active model as (0-100 score, 1-5 MOS)
+    fn score(&self, span: &QoeSpan) -> (f64, f64) {
+        match self.model {
+            QoeModel::Simple => {
+                let score = simple_score(span);
+                (score, 1.0 + score * 0.04)
+            }
+            QoeModel::P1203Mode0 => {
+                let mos = self.p1203_mos(span);
+                ((mos - 1.0) * 25.0, mos)
+            }
+        }
+    }
+
+    /// P.1203-style MOS: pooled per-segment video quality scaled down by
+    /// the stalling impairment
+    fn p1203_mos(&self, span: &QoeSpan) -> f64 {
+        let length = span.end - span.start;
+        if length <= 0.0 {
+            return 5.0;
+        }
+
+        // Recency-weighted mean of per-segment quality: the end of the
+        // span weighs half again as much as its start
+        let mut weighted = 0.0;
+        let mut weights = 0.0;
+        for (start, segment) in &span.segments {
+            let middle = (start + segment.duration / 2.0 - span.start) / length;
+            let weight = segment.duration * (1.0 + 0.5 * middle);
+            weighted += weight * video_mos_mode0(segment, self.display_resolution);
+            weights += weight;
+        }
+        let quality = if weights > 0.0 { weighted / weights } else { 5.0 };
+
+        (1.0 + (quality - 1.0) * stalling_impairment(span, length)).clamp(1.0, 5.0)
+    }
+
+    /// Calculate average bitrate
+    fn average_bitrate(&self) -> u64 {
+        average_bitrate(&self.segments)
+    }
+
+    /// Get QoE breakdown
+    pub fn breakdown(&self) -> QoeBreakdown {
+        let span = self.session_span();
+        let (score, mos) = self.score(&span);
+        QoeBreakdown {
+            score,
+            mos,
+            initial_buffer_time: self.initial_buffer_time,
+            rebuffer_count: self.stalls.len() as u32,
+            rebuffer_duration: self.stalls.iter().map(|s| s.duration).sum(),
+            quality_switches: self.quality_switches.len() as u32,
+            average_bitrate: self.average_bitrate(),
+        }
+    }
+}
+
+impl Default for QoeCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Heuristic 0-100 score
+fn simple_score(span: &QoeSpan) -> f64 {
+    // MOS-like scoring based on:
+    // - Initial buffer time (startup delay)
+    // - Rebuffer frequency and duration
+    // - Average quality
+    // - Quality stability
+
+    let mut score = 100.0;
+
+    // Penalize initial buffer time
+    // > 2s starts reducing score
+    if span.initial_buffer_time > 2.0 {
+        score -= (span.initial_buffer_time - 2.0) * 5.0;
+    }
+
+    // Penalize rebuffers heavily
+    // Each rebuffer costs 10 points
+    score -= span.stalls.len() as f64 * 10.0;
+
+    // Penalize rebuffer duration
+    // Each second of rebuffering costs 5 points
+    score -= span.stalls.iter().map(|s| s.duration).sum::<f64>() * 5.0;
+
+    // Penalize quality switches
+    // Each switch costs 2 points
+    score -= span.quality_switches as f64 * 2.0;
+
+    // Bonus for high average bitrate
+    let avg_bitrate = average_bitrate(&span.segments);
+    if avg_bitrate > 5_000_000 {
+        score += 5.0;
+    } else if avg_bitrate > 2_000_000 {
+        score += 2.0;
+    }
+
+    score.clamp(0.0, 100.0)
+}
+
+/// Time-weighted average bitrate
+fn average_bitrate(segments: &[(f64, QoeSegment)]) -> u64 {
+    if segments.is_empty() {
+        return 0;
+    }
+
+    let total_duration: f64 = segments.iter().map(|(_, s)| s.duration).sum();
+    if total_duration == 0.0 {
+        return 0;
+    }
+
+    let weighted_sum: f64 = segments
+        .iter()
+        .map(|(_, s)| s.duration * s.bitrate as f64)
+        .sum();
+
+    (weighted_sum / total_duration) as u64
+}
+
+/// Per-segment video MOS following P.1203.1 mode 0: a coding
+/// degradation from bitrate and bits per pixel, plus upscaling and
+/// frame-rate degradations, summed on the R scale
+fn video_mos_mode0(segment: &QoeSegment, display: Resolution) -> f64 {
+    const A: [f64; 4] = [11.99835, -2.99992, 41.24751, 0.13183];
+    const Q: [f64; 3] = [4.66, -0.07, 4.06];
+    const U: [f64; 2] = [72.61, 0.32];
+    const T: [f64; 3] = [30.98, 1.29, 64.65];
+
+    let bitrate_kbps = (segment.bitrate as f64 / 1000.0).max(1.0);
+    let display_pixels = (display.width as f64 * display.height as f64).max(1.0);
+    let coded_pixels = segment
+        .resolution
+        .map(|r| r.width as f64 * r.height as f64)
+        .filter(|&p| p > 0.0)
+        .unwrap_or(display_pixels);
+    let frame_rate = segment.frame_rate.filter(|&f| f > 0.0).unwrap_or(30.0);
+
+    let bits_per_pixel = bitrate_kbps / (coded_pixels * frame_rate);
+    let quant = A[0] + A[1] * (A[2] + bitrate_kbps.ln() + (bitrate_kbps * bits_per_pixel + A[3]).ln()).ln();
+    let mos_coding = (Q[0] + Q[1] * (Q[2] * quant).exp()).clamp(1.0, 5.0);
+    let deg_coding = (100.0 - r_from_mos(mos_coding)).clamp(0.0, 100.0);
+
+    let scale = (display_pixels / coded_pixels).max(1.0);
+    let deg_scaling = (U[0] * (U[1] * (scale - 1.0) + 1.0).log10()).clamp(0.0, 100.0);
+
+    let deg_frame_rate = if frame_rate < 24.0 {
+        ((100.0 - deg_coding - deg_scaling) * (T[0] - T[1] * frame_rate) / (T[2] + frame_rate)).clamp(0.0, 100.0)
+    } else {
+        0.0
+    };
+
+    let deg_all = (deg_coding + deg_scaling + deg_frame_rate).clamp(0.0, 100.0);
+    mos_from_r(100.0 - deg_all)
+}
+
+/// P.1203.3-style stalling impairment in (0, 1] from the number of
+/// stalls, their total length and their spacing, relative to `length`
+///
+/// The startup delay counts as a stall at a third of its length.
+fn stalling_impairment(span: &QoeSpan, length: f64) -> f64 {
+    const S: [f64; 3] = [9.35158684, 0.91890815, 11.0567558];
+
+    let mut positions: Vec<f64> = span.stalls.iter().map(|s| s.position).collect();
+    let mut total = span.stalls.iter().map(|s| s.duration).sum::<f64>();
+    if span.initial_buffer_time > 0.0 {
+        positions.insert(0, span.start);
+        total += span.initial_buffer_time / 3.0;
+    }
+    if positions.is_empty() {
+        return 1.0;
+    }
+
+    positions.sort_by(f64::total_cmp);
+    let interval = if positions.len() > 1 {
+        (positions[positions.len() - 1] - positions[0]) / (positions.len() - 1) as f64
+    } else {
+        0.0
+    };
+
+    (-(positions.len() as f64) / S[0]).exp() * (-(total / length) / S[1]).exp() * (-(interval / length) / S[2]).exp()
+}
+
+/// MOS (1-5) from an R-scale quality (0-100)
+fn mos_from_r(q: f64) -> f64 {
+    const MOS_MIN: f64 = 1.05;
+    const MOS_MAX: f64 = 4.9;
+
+    if q <= 0.0 {
+        MOS_MIN
+    } else if q >= 100.0 {
+        MOS_MAX
+    } else {
+        let mos = MOS_MIN + (MOS_MAX - MOS_MIN) * q / 100.0 + q * (q - 60.0) * (100.0 - q) * 7.0e-6;
+        mos.clamp(1.0, 5.0)
+    }
+}
+
+/// Inverse of [`mos_from_r`], by bisection
+fn r_from_mos(mos: f64) -> f64 {
+    let (mut low, mut high) = (0.0, 100.0);
+    for _ in 0..50 {
+        let mid = (low + high) / 2.0;
+        if mos_from_r(mid) < mos {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// QoE score breakdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QoeBreakdown {
+    pub score: f64,
+    /// Mean opinion score (1-5)
+    pub mos: f64,
+    pub initial_buffer_time: f64,
+    pub rebuffer_count: u32,
+    pub rebuffer_duration: f64,
+    pub quality_switches: u32,
+    pub average_bitrate: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qoe_perfect() {
+        let calc = QoeCalculator::new();
+        assert_eq!(calc.calculate_qoe(), 100.0);
+    }
+
+    #[test]
+    fn test_qoe_with_rebuffers() {
+        let mut calc = QoeCalculator::new();
+        calc.record_rebuffer(1.0);
+        calc.record_rebuffer(2.0);
+
+        // 100 - 2*10 - 3*5 = 65
+        assert!((calc.calculate_qoe() - 65.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_qoe_with_initial_buffer() {
+        let mut calc = QoeCalculator::new();
+        calc.record_initial_buffer(5.0); // 3 seconds over threshold
+
+        // 100 - 3*5 = 85
+        assert!((calc.calculate_qoe() - 85.0).abs() < 0.1);
+    }
+
+    fn canned_session(resolution: Resolution, bitrate: u64, frame_rate: f64) -> QoeCalculator {
+        let mut calc = QoeCalculator::with_model(QoeModel::P1203Mode0);
+        for _ in 0..15 {
+            calc.record_segment(QoeSegment {
+                duration: 4.0,
+                bitrate,
+                resolution: Some(resolution),
+                frame_rate: Some(frame_rate),
+            });
+        }
+        calc
+    }
+
+    #[test]
+    fn test_qoe_p1203_mos() {
+        // Full HD at a healthy bitrate
+        let full_hd = canned_session(Resolution::FHD_1080P, 5_000_000, 30.0);
+        assert!((full_hd.calculate_mos() - 4.409).abs() < 0.01);
+
+        // 360p upscaled to a 1080p display
+        let low = canned_session(Resolution::new(640, 360), 800_000, 30.0);
+        assert!((low.calculate_mos() - 2.025).abs() < 0.01);
+
+        // 720p at 15 fps
+        let choppy = canned_session(Resolution::HD_720P, 1_500_000, 15.0);
+        assert!((choppy.calculate_mos() - 3.218).abs() < 0.01);
+
+        // Full HD with a slow start and two stalls
+        let mut stalled = canned_session(Resolution::FHD_1080P, 5_000_000, 30.0);
+        stalled.record_initial_buffer(3.0);
+        stalled.record_stall(20.0, 2.0);
+        stalled.record_stall(40.0, 4.0);
+        assert!((stalled.calculate_mos() - 3.114).abs() < 0.01);
+        assert!((stalled.calculate_qoe() - 52.84).abs() < 0.1);
+
+        let breakdown = stalled.breakdown();
+        assert_eq!(breakdown.rebuffer_count, 2);
+        assert_eq!(breakdown.mos, stalled.calculate_mos());
+    }
+
+    #[test]
+    fn test_qoe_windowed() {
+        let mut calc = canned_session(Resolution::FHD_1080P, 5_000_000, 30.0);
+        calc.record_initial_buffer(3.0);
+        calc.record_stall(20.0, 2.0);
+        calc.record_stall(40.0, 4.0);
+
+        let windows = calc.calculate_qoe_windowed(20.0);
+        let bounds: Vec<(f64, f64)> = windows.iter().map(|w| (w.start, w.end)).collect();
+        assert_eq!(bounds, vec![(0.0, 20.0), (20.0, 40.0), (40.0, 60.0)]);
+
+        // Startup delay lands in the first window, stalls in the ones they occur in
+        let mos: Vec<f64> = windows.iter().map(|w| w.mos).collect();
+        for (actual, expected) in mos.iter().zip([3.901, 3.747, 3.464]) {
+            assert!((actual - expected).abs() < 0.01, "{actual} != {expected}");
+        }
+
+        // The heuristic model scores windows the same way
+        calc.set_model(QoeModel::Simple);
+        let windows = calc.calculate_qoe_windowed(30.0);
+        assert_eq!(windows.len(), 2);
+        // 100 - 1*5 - 10 - 2*5 + 2 (bitrate bonus) = 77
+        assert!((windows[0].score - 77.0).abs() < 0.1);
+        assert!((windows[0].mos - 4.08).abs() < 0.01);
+        // 100 - 10 - 4*5 + 2 = 72
+        assert!((windows[1].score - 72.0).abs() < 0.1);
+
+        assert!(QoeCalculator::new().calculate_qoe_windowed(10.0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_analytics_emitter() {
+        let emitter = AnalyticsEmitter::new();
+
+        emitter.emit(AnalyticsEvent::Play { position: 0.0 }).await;
+        emitter.emit(AnalyticsEvent::Pause { position: 10.0 }).await;
+
+        let events = emitter.get_events().await;
+        assert_eq!(events.len(), 2);
+    }
+}
@@ -0,0 +1,407 @@
+//! Session timeline recording
+//!
+//! A [`SessionRecorder`] keeps a plottable timeline of one playback
+//! session: continuous metrics (buffer level, bitrate, bandwidth estimate,
+//! player state) sampled at a fixed interval, plus every discrete analytics
+//! event. It receives events as an [`AnalyticsSink`], so it sees exactly
+//! what other sinks see. Only the last `max_duration_secs` of both is kept.
+
+use super::{AnalyticsEvent, AnalyticsEventRecord, AnalyticsSink, BatchConfig};
+use crate::types::{PlayerState, SessionId};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Sampling and retention for a [`SessionRecorder`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecorderConfig {
+    /// Seconds between metric samples
+    pub sample_interval_secs: f64,
+    /// Seconds of timeline kept; older samples and events are dropped
+    pub max_duration_secs: f64,
+    /// Upper bound on retained events, for bursts within the window
+    pub max_events: usize,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            sample_interval_secs: 1.0,
+            max_duration_secs: 3600.0,
+            max_events: 10_000,
+        }
+    }
+}
+
+/// Continuous player metrics at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimelineSample {
+    /// Seconds since recording started
+    pub time: f64,
+    /// Playback position in seconds
+    pub position: f64,
+    /// Buffered media ahead of the playhead in seconds
+    pub buffer_level: f64,
+    /// Bitrate of the selected rendition in bps (0 if none)
+    pub bitrate: u64,
+    /// ABR bandwidth estimate in bps
+    pub bandwidth_estimate: u64,
+    /// Player state
+    pub state: PlayerState,
+}
+
+/// A discrete event on the timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    /// Seconds since recording started
+    pub time: f64,
+    /// When the event was emitted
+    pub timestamp: DateTime<Utc>,
+    /// Emitter sequence number
+    pub sequence: u64,
+    /// The event
+    #[serde(flatten)]
+    pub event: AnalyticsEvent,
+}
+
+/// Recorded timeline of a session, as exported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTimeline {
+    /// Session the timeline belongs to
+    pub session_id: SessionId,
+    /// Wall-clock time that `time` values count from
+    pub started_at: DateTime<Utc>,
+    /// Seconds between samples
+    pub sample_interval_secs: f64,
+    /// Metric samples, oldest first
+    pub samples: Vec<TimelineSample>,
+    /// Discrete events, oldest first
+    pub events: Vec<TimelineEvent>,
+}
+
+/// Timeline exported as two CSV tables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineCsv {
+    /// One row per sample: `time,position,buffer_level,bitrate,bandwidth_estimate,state`
+    pub samples: String,
+    /// One row per event: `time,timestamp,sequence,event,position,detail`, where
+    /// `detail` is the event's remaining fields as JSON
+    pub events: String,
+}
+
+/// Writes rows of a CSV table, quoting fields where RFC 4180 requires it
+pub struct CsvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Start a table by writing its header row
+    pub fn new(writer: W, header: &[&str]) -> std::io::Result<Self> {
+        let mut csv = Self { writer };
+        csv.write_row(header)?;
+        Ok(csv)
+    }
+
+    /// Write one row
+    pub fn write_row<T: Display>(&mut self, fields: impl IntoIterator<Item = T>) -> std::io::Result<()> {
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                self.writer.write_all(b",")?;
+            }
+            let field = field.to_string();
+            if field.contains([',', '"', '\n', '\r']) {
+                write!(self.writer, "\"{}\"", field.replace('"', "\"\""))?;
+            } else {
+                self.writer.write_all(field.as_bytes())?;
+            }
+        }
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    /// Get the underlying writer back
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Retained samples and events
+#[derive(Default)]
+struct Timeline {
+    samples: VecDeque<TimelineSample>,
+    events: VecDeque<TimelineEvent>,
+}
+
+/// Records a session timeline for debugging and plotting
+///
+/// Clones share the same timeline, so one can be registered as a sink on
+/// the session's [`super::AnalyticsEmitter`] while another is sampled into
+/// and exported. Events arrive through the sink's worker task; call
+/// [`super::AnalyticsEmitter::flush`] before exporting to include the
+/// latest ones.
+#[derive(Clone)]
+pub struct SessionRecorder {
+    session_id: SessionId,
+    config: RecorderConfig,
+    started_at: DateTime<Utc>,
+    timeline: Arc<Mutex<Timeline>>,
+}
+
+impl SessionRecorder {
+    /// Start recording a session now
+    pub fn new(session_id: SessionId, config: RecorderConfig) -> Self {
+        Self {
+            session_id,
+            config,
+            started_at: Utc::now(),
+            timeline: Arc::new(Mutex::new(Timeline::default())),
+        }
+    }
+
+    /// Sampling and retention settings
+    pub fn config(&self) -> &RecorderConfig {
+        &self.config
+    }
+
+    /// Seconds since recording started, for stamping samples
+    pub fn elapsed(&self) -> f64 {
+        self.seconds_since_start(Utc::now())
+    }
+
+    fn seconds_since_start(&self, timestamp: DateTime<Utc>) -> f64 {
+        (timestamp - self.started_at).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6
+    }
+
+    /// Most samples the retention window can hold
+    fn max_samples(&self) -> usize {
+        if self.config.sample_interval_secs > 0.0 {
+            (self.config.max_duration_secs / self.config.sample_interval_secs).ceil() as usize + 1
+        } else {
+            self.config.max_events
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Timeline> {
+        self.timeline.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Add a metric sample, dropping ones older than the retention window
+    pub fn record_sample(&self, sample: TimelineSample) {
+        let cutoff = sample.time - self.config.max_duration_secs;
+        let max_samples = self.max_samples();
+
+        let mut timeline = self.lock();
+        timeline.samples.push_back(sample);
+        while timeline.samples.front().is_some_and(|s| s.time < cutoff) || timeline.samples.len() > max_samples {
+            timeline.samples.pop_front();
+        }
+    }
+
+    /// Add an analytics event, dropping ones older than the retention window
+    ///
+    /// Heartbeats are skipped; the samples already cover what they carry.
+    pub fn record_event(&self, record: &AnalyticsEventRecord) {
+        if matches!(record.event, AnalyticsEvent::Heartbeat { .. }) {
+            return;
+        }
+
+        let event = TimelineEvent {
+            time: self.seconds_since_start(record.timestamp),
+            timestamp: record.timestamp,
+            sequence: record.sequence,
+            event: record.event.clone(),
+        };
+        let cutoff = event.time - self.config.max_duration_secs;
+
+        let mut timeline = self.lock();
+        timeline.events.push_back(event);
+        while timeline.events.front().is_some_and(|e| e.time < cutoff) || timeline.events.len() > self.config.max_events {
+            timeline.events.pop_front();
+        }
+    }
+
+    /// Snapshot of the retained timeline
+    pub fn timeline(&self) -> SessionTimeline {
+        let timeline = self.lock();
+        SessionTimeline {
+            session_id: self.session_id,
+            started_at: self.started_at,
+            sample_interval_secs: self.config.sample_interval_secs,
+            samples: timeline.samples.iter().copied().collect(),
+            events: timeline.events.iter().cloned().collect(),
+        }
+    }
+
+    /// Export the timeline as JSON
+    pub fn export_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(&self.timeline()).map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// Export the timeline as a samples table and an events table
+    pub fn export_csv(&self) -> Result<TimelineCsv> {
+        let timeline = self.timeline();
+
+        let mut samples = CsvWriter::new(
+            Vec::new(),
+            &["time", "position", "buffer_level", "bitrate", "bandwidth_estimate", "state"],
+        )?;
+        for sample in &timeline.samples {
+            samples.write_row([
+                format!("{:.3}", sample.time),
+                format!("{:.3}", sample.position),
+                format!("{:.3}", sample.buffer_level),
+                sample.bitrate.to_string(),
+                sample.bandwidth_estimate.to_string(),
+                sample.state.to_string(),
+            ])?;
+        }
+
+        let mut events = CsvWriter::new(
+            Vec::new(),
+            &["time", "timestamp", "sequence", "event", "position", "detail"],
+        )?;
+        for event in &timeline.events {
+            let mut fields = match serde_json::to_value(&event.event) {
+                Ok(serde_json::Value::Object(fields)) => fields,
+                _ => serde_json::Map::new(),
+            };
+            let name = fields.remove("event").and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+            let position = fields.remove("position").and_then(|v| v.as_f64()).map(|p| format!("{:.3}", p)).unwrap_or_default();
+
+            events.write_row([
+                format!("{:.3}", event.time),
+                event.timestamp.to_rfc3339(),
+                event.sequence.to_string(),
+                name,
+                position,
+                serde_json::Value::Object(fields).to_string(),
+            ])?;
+        }
+
+        Ok(TimelineCsv {
+            samples: String::from_utf8_lossy(&samples.into_inner()).into_owned(),
+            events: String::from_utf8_lossy(&events.into_inner()).into_owned(),
+        })
+    }
+}
+
+#[async_trait]
+impl AnalyticsSink for SessionRecorder {
+    fn name(&self) -> &str {
+        "session_recorder"
+    }
+
+    /// Every event is recorded as it arrives
+    fn batch_config(&self) -> BatchConfig {
+        BatchConfig {
+            max_events: 1,
+            ..Default::default()
+        }
+    }
+
+    async fn send(&self, batch: &[AnalyticsEventRecord]) -> Result<()> {
+        for record in batch {
+            self.record_event(record);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn sample(time: f64, state: PlayerState) -> TimelineSample {
+        TimelineSample {
+            time,
+            position: time,
+            buffer_level: 10.0,
+            bitrate: 2_000_000,
+            bandwidth_estimate: 5_000_000,
+            state,
+        }
+    }
+
+    fn record(recorder: &SessionRecorder, seconds: i64, sequence: u64, event: AnalyticsEvent) -> AnalyticsEventRecord {
+        AnalyticsEventRecord {
+            id: Uuid::new_v4(),
+            session_id: recorder.session_id,
+            player_version: crate::VERSION.to_string(),
+            timestamp: recorder.started_at + chrono::Duration::seconds(seconds),
+            sequence,
+            event,
+        }
+    }
+
+    #[test]
+    fn test_retention_window() {
+        let config = RecorderConfig {
+            sample_interval_secs: 1.0,
+            max_duration_secs: 10.0,
+            max_events: 3,
+        };
+        let recorder = SessionRecorder::new(SessionId::new(), config);
+
+        for t in 0..30 {
+            recorder.record_sample(sample(t as f64, PlayerState::Playing));
+        }
+        for (i, t) in [0, 15, 25, 26, 27, 28].into_iter().enumerate() {
+            recorder.record_event(&record(&recorder, t, i as u64, AnalyticsEvent::Play { position: t as f64 }));
+        }
+        recorder.record_event(&record(&recorder, 29, 9, AnalyticsEvent::Heartbeat {
+            position: 29.0,
+            buffer_level: 10.0,
+            bitrate: 0,
+            dropped_frames: 0,
+            decoded_frames: 0,
+        }));
+
+        let timeline = recorder.timeline();
+        let times: Vec<f64> = timeline.samples.iter().map(|s| s.time).collect();
+        assert_eq!(times, (19..30).map(|t| t as f64).collect::<Vec<_>>());
+        // The event cap applies within the window too
+        let sequences: Vec<u64> = timeline.events.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let recorder = SessionRecorder::new(SessionId::new(), RecorderConfig::default());
+        recorder.record_sample(sample(0.5, PlayerState::Buffering));
+        recorder.record_sample(sample(1.5, PlayerState::Playing));
+        recorder.record_event(&record(&recorder, 1, 1, AnalyticsEvent::Rebuffer { position: 12.25, buffer_level: 0.5 }));
+        recorder.record_event(&record(&recorder, 2, 2, AnalyticsEvent::Error {
+            code: "NETWORK_HTTP".to_string(),
+            message: "HTTP 503, retrying".to_string(),
+            fatal: false,
+            position: 13.0,
+        }));
+
+        let csv = recorder.export_csv().unwrap();
+        let samples: Vec<&str> = csv.samples.lines().collect();
+        assert_eq!(samples, vec![
+            "time,position,buffer_level,bitrate,bandwidth_estimate,state",
+            "0.500,0.500,10.000,2000000,5000000,buffering",
+            "1.500,1.500,10.000,2000000,5000000,playing",
+        ]);
+
+        let events: Vec<&str> = csv.events.lines().collect();
+        assert_eq!(events[0], "time,timestamp,sequence,event,position,detail");
+        assert!(events[1].starts_with("1.000,"));
+        assert!(events[1].ends_with(",1,rebuffer,12.250,\"{\"\"buffer_level\"\":0.5}\""));
+        // Fields with commas and quotes are quoted
+        assert!(events[2].contains(",2,error,13.000,\"{"));
+        assert!(events[2].contains("\"\"message\"\":\"\"HTTP 503, retrying\"\""));
+
+        let json: SessionTimeline = serde_json::from_str(&recorder.export_json().unwrap()).unwrap();
+        assert_eq!(json.samples.len(), 2);
+        assert!(matches!(json.events[0].event, AnalyticsEvent::Rebuffer { .. }));
+    }
+}
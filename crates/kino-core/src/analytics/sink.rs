@@ -0,0 +1,581 @@
+//! Analytics sinks
+//!
+//! A sink delivers batches of event records somewhere: an HTTP collector
+//! or a local file. Each sink registered with the emitter gets a worker
+//! task that batches records by count and age, retries failed batches
+//! with exponential backoff, and drops a batch (counting it) once the
+//! attempts run out.
+
+use super::AnalyticsEventRecord;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use flate2::{write::GzEncoder, Compression};
+use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// Batching and retry policy for a sink
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// Deliver once this many events are pending
+    pub max_events: usize,
+    /// Deliver once the oldest pending event is this old
+    pub max_age: Duration,
+    /// Attempts per batch before it is dropped
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further retry
+    pub initial_backoff: Duration,
+    /// Upper bound for the retry delay
+    pub max_backoff: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_events: 50,
+            max_age: Duration::from_secs(10),
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Destination for analytics events
+#[async_trait]
+pub trait AnalyticsSink: Send + Sync {
+    /// Sink name for logs and stats
+    fn name(&self) -> &str;
+
+    /// How events are batched and retried for this sink
+    fn batch_config(&self) -> BatchConfig {
+        BatchConfig::default()
+    }
+
+    /// Deliver one batch; a failed batch is retried per [`Self::batch_config`]
+    async fn send(&self, batch: &[AnalyticsEventRecord]) -> Result<()>;
+}
+
+/// Delivery counters for a sink
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SinkStats {
+    /// Sink name
+    pub name: String,
+    /// Events delivered
+    pub sent: u64,
+    /// Events dropped after running out of attempts
+    pub dropped: u64,
+    /// Failed attempts that were retried
+    pub retries: u64,
+}
+
+/// Sends batches as gzipped JSON arrays to an HTTP collector
+pub struct HttpSink {
+    client: Client,
+    endpoint: String,
+    config: BatchConfig,
+}
+
+impl HttpSink {
+    /// Create a sink posting to `endpoint`
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self::with_client(Client::new(), endpoint)
+    }
+
+    /// Create with a custom HTTP client
+    pub fn with_client(client: Client, endpoint: impl Into<String>) -> Self {
+        Self {
+            client,
+            endpoint: endpoint.into(),
+            config: BatchConfig::default(),
+        }
+    }
+
+    /// Set the batching and retry policy
+    pub fn with_batch_config(mut self, config: BatchConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+#[async_trait]
+impl AnalyticsSink for HttpSink {
+    fn name(&self) -> &str {
+        &self.endpoint
+    }
+
+    fn batch_config(&self) -> BatchConfig {
+        self.config.clone()
+    }
+
+    async fn send(&self, batch: &[AnalyticsEventRecord]) -> Result<()> {
+        let json = serde_json::to_vec(batch).map_err(|e| Error::AnalyticsSink(e.to_string()))?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&json)?;
+        let body = encoder.finish()?;
+
+        self.client
+            .post(&self.endpoint)
+            .header(CONTENT_TYPE, "application/json")
+            .header(CONTENT_ENCODING, "gzip")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        debug!(endpoint = %self.endpoint, events = batch.len(), "Analytics batch sent");
+        Ok(())
+    }
+}
+
+/// Appends events as newline-delimited JSON, for local debugging
+///
+/// Once the file would grow past `max_bytes` it is rotated: `events.jsonl`
+/// becomes `events.jsonl.1`, `.1` becomes `.2`, and so on, keeping at
+/// most `max_files` rotated files.
+pub struct JsonlFileSink {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    /// Serializes appends and rotation
+    lock: Mutex<()>,
+}
+
+impl JsonlFileSink {
+    /// Create a sink writing to `path`, rotating at 10 MB and keeping 3 files
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 3,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Set the rotation size and number of rotated files kept
+    pub fn with_rotation(mut self, max_bytes: u64, max_files: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self.max_files = max_files;
+        self
+    }
+
+    /// Path of the `index`th rotated file
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    async fn rotate(&self) -> Result<()> {
+        if self.max_files == 0 {
+            return Ok(tokio::fs::remove_file(&self.path).await?);
+        }
+        for index in (1..self.max_files).rev() {
+            rename_if_exists(&self.rotated_path(index), &self.rotated_path(index + 1)).await?;
+        }
+        rename_if_exists(&self.path, &self.rotated_path(1)).await?;
+        debug!(path = %self.path.display(), "Rotated analytics log");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AnalyticsSink for JsonlFileSink {
+    fn name(&self) -> &str {
+        self.path.to_str().unwrap_or("jsonl")
+    }
+
+    /// Every event is written as it arrives
+    fn batch_config(&self) -> BatchConfig {
+        BatchConfig {
+            max_events: 1,
+            ..Default::default()
+        }
+    }
+
+    async fn send(&self, batch: &[AnalyticsEventRecord]) -> Result<()> {
+        let mut lines = Vec::new();
+        for record in batch {
+            serde_json::to_writer(&mut lines, record).map_err(|e| Error::AnalyticsSink(e.to_string()))?;
+            lines.push(b'\n');
+        }
+
+        let _guard = self.lock.lock().await;
+        let size = match tokio::fs::metadata(&self.path).await {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+        if size > 0 && size + lines.len() as u64 > self.max_bytes {
+            self.rotate().await?;
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&lines).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+async fn rename_if_exists(from: &Path, to: &Path) -> Result<()> {
+    match tokio::fs::rename(from, to).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Message from the emitter to a sink worker
+enum SinkCommand {
+    Record(AnalyticsEventRecord),
+    Flush(oneshot::Sender<()>),
+}
+
+#[derive(Default)]
+struct SinkCounters {
+    sent: AtomicU64,
+    dropped: AtomicU64,
+    retries: AtomicU64,
+}
+
+/// Emitter-side handle to a sink's worker task
+pub(super) struct SinkWorker {
+    name: String,
+    tx: mpsc::UnboundedSender<SinkCommand>,
+    counters: Arc<SinkCounters>,
+    task: JoinHandle<()>,
+}
+
+impl SinkWorker {
+    /// Start a worker delivering to `sink`
+    pub(super) fn spawn(sink: Arc<dyn AnalyticsSink>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let counters = Arc::new(SinkCounters::default());
+        let name = sink.name().to_string();
+        let task = tokio::spawn(run_worker(sink, rx, counters.clone()));
+        Self { name, tx, counters, task }
+    }
+
+    /// Queue a record for delivery
+    pub(super) fn queue(&self, record: AnalyticsEventRecord) {
+        let _ = self.tx.send(SinkCommand::Record(record));
+    }
+
+    /// Ask the worker to deliver what it holds; resolves once it has tried
+    pub(super) fn flush(&self) -> Option<oneshot::Receiver<()>> {
+        let (done, receiver) = oneshot::channel();
+        self.tx.send(SinkCommand::Flush(done)).ok()?;
+        Some(receiver)
+    }
+
+    pub(super) fn stats(&self) -> SinkStats {
+        SinkStats {
+            name: self.name.clone(),
+            sent: self.counters.sent.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            retries: self.counters.retries.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Close the channel; the worker delivers what it holds and exits
+    pub(super) fn close(self) -> JoinHandle<()> {
+        self.task
+    }
+}
+
+/// Batch records until the count or age limit, then deliver
+async fn run_worker(
+    sink: Arc<dyn AnalyticsSink>,
+    mut rx: mpsc::UnboundedReceiver<SinkCommand>,
+    counters: Arc<SinkCounters>,
+) {
+    let config = sink.batch_config();
+    let mut batch = Vec::new();
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let command = match deadline {
+            Some(at) => match tokio::time::timeout_at(at, rx.recv()).await {
+                Ok(command) => command,
+                Err(_) => {
+                    deliver(sink.as_ref(), &config, &mut batch, &counters).await;
+                    deadline = None;
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+
+        match command {
+            Some(SinkCommand::Record(record)) => {
+                if batch.is_empty() {
+                    deadline = Some(Instant::now() + config.max_age);
+                }
+                batch.push(record);
+                if batch.len() >= config.max_events {
+                    deliver(sink.as_ref(), &config, &mut batch, &counters).await;
+                    deadline = None;
+                }
+            }
+            Some(SinkCommand::Flush(done)) => {
+                deliver(sink.as_ref(), &config, &mut batch, &counters).await;
+                deadline = None;
+                let _ = done.send(());
+            }
+            None => {
+                deliver(sink.as_ref(), &config, &mut batch, &counters).await;
+                break;
+            }
+        }
+    }
+}
+
+/// Send `batch`, retrying with exponential backoff, and empty it
+async fn deliver(sink: &dyn AnalyticsSink, config: &BatchConfig, batch: &mut Vec<AnalyticsEventRecord>, counters: &SinkCounters) {
+    if batch.is_empty() {
+        return;
+    }
+
+    let attempts = config.max_attempts.max(1);
+    let mut backoff = config.initial_backoff;
+    for attempt in 1..=attempts {
+        match sink.send(batch).await {
+            Ok(()) => {
+                counters.sent.fetch_add(batch.len() as u64, Ordering::Relaxed);
+                batch.clear();
+                return;
+            }
+            Err(e) if attempt < attempts => {
+                warn!(sink = sink.name(), attempt, error = %e, "Analytics batch failed, retrying");
+                counters.retries.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(config.max_backoff);
+            }
+            Err(e) => {
+                warn!(sink = sink.name(), events = batch.len(), error = %e, "Dropping analytics batch");
+            }
+        }
+    }
+
+    counters.dropped.fetch_add(batch.len() as u64, Ordering::Relaxed);
+    batch.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::{AnalyticsEmitter, AnalyticsEvent};
+    use std::io::Read;
+    use std::sync::Mutex as StdMutex;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    /// Records delivered batches by sequence number; fails the first
+    /// `failures` sends
+    struct MockSink {
+        config: BatchConfig,
+        failures: AtomicU64,
+        batches: Arc<StdMutex<Vec<Vec<u64>>>>,
+    }
+
+    impl MockSink {
+        fn new(config: BatchConfig, failures: u64) -> (Self, Arc<StdMutex<Vec<Vec<u64>>>>) {
+            let batches = Arc::new(StdMutex::new(Vec::new()));
+            let sink = Self {
+                config,
+                failures: AtomicU64::new(failures),
+                batches: batches.clone(),
+            };
+            (sink, batches)
+        }
+    }
+
+    #[async_trait]
+    impl AnalyticsSink for MockSink {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn batch_config(&self) -> BatchConfig {
+            self.config.clone()
+        }
+
+        async fn send(&self, batch: &[AnalyticsEventRecord]) -> Result<()> {
+            if self.failures.load(Ordering::Relaxed) > 0 {
+                self.failures.fetch_sub(1, Ordering::Relaxed);
+                return Err(Error::AnalyticsSink("unavailable".to_string()));
+            }
+            self.batches.lock().unwrap().push(batch.iter().map(|r| r.sequence).collect());
+            Ok(())
+        }
+    }
+
+    fn config(max_events: usize, max_age_ms: u64) -> BatchConfig {
+        BatchConfig {
+            max_events,
+            max_age: Duration::from_millis(max_age_ms),
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(40),
+        }
+    }
+
+    async fn emit_plays(emitter: &AnalyticsEmitter, count: usize) {
+        for i in 0..count {
+            emitter.emit(AnalyticsEvent::Play { position: i as f64 }).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batching_boundaries() {
+        let emitter = AnalyticsEmitter::new();
+        let (sink, batches) = MockSink::new(config(3, 150), 0);
+        emitter.add_sink(sink);
+
+        // Full batches go out immediately, the remainder on age
+        emit_plays(&emitter, 7).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(*batches.lock().unwrap(), vec![vec![1, 2, 3], vec![4, 5, 6]]);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(batches.lock().unwrap().last(), Some(&vec![7]));
+
+        // Flush delivers a partial batch right away
+        emit_plays(&emitter, 2).await;
+        emitter.flush().await;
+        assert_eq!(batches.lock().unwrap().last(), Some(&vec![8, 9]));
+        assert_eq!(emitter.sink_stats()[0].sent, 9);
+    }
+
+    #[tokio::test]
+    async fn test_retry_and_drop() {
+        let emitter = AnalyticsEmitter::new();
+
+        // Two failures are absorbed by the third attempt
+        let (flaky, delivered) = MockSink::new(config(2, 1000), 2);
+        emitter.add_sink(flaky);
+        // Four failures exhaust the attempts for the first batch only
+        let (down, recovered) = MockSink::new(config(2, 1000), 4);
+        emitter.add_sink(down);
+
+        emit_plays(&emitter, 4).await;
+        emitter.flush().await;
+
+        assert_eq!(*delivered.lock().unwrap(), vec![vec![1, 2], vec![3, 4]]);
+        assert_eq!(*recovered.lock().unwrap(), vec![vec![3, 4]]);
+
+        let stats = emitter.sink_stats();
+        assert_eq!((stats[0].sent, stats[0].dropped, stats[0].retries), (4, 0, 2));
+        assert_eq!((stats[1].sent, stats[1].dropped, stats[1].retries), (2, 2, 3));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tagging_and_flush_on_drop() {
+        let emitter = AnalyticsEmitter::new();
+        let session_id = emitter.session_id();
+        let (sink, batches) = MockSink::new(config(100, 60_000), 0);
+        emitter.add_sink(sink);
+
+        emit_plays(&emitter, 3).await;
+        let events = emitter.get_events().await;
+        assert!(events.iter().all(|e| e.session_id == session_id && e.player_version == crate::VERSION));
+
+        // Nothing is due yet; dropping delivers the pending batch
+        assert!(batches.lock().unwrap().is_empty());
+        drop(emitter);
+        assert_eq!(*batches.lock().unwrap(), vec![vec![1, 2, 3]]);
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_rotation() {
+        let dir = std::env::temp_dir().join(format!("kino-analytics-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("events.jsonl");
+
+        let emitter = AnalyticsEmitter::new();
+        emitter.add_sink(JsonlFileSink::new(&path).with_rotation(600, 2));
+        emit_plays(&emitter, 12).await;
+        emitter.flush().await;
+
+        // A few lines fit in each file; the oldest files are rotated away
+        let mut sequences = Vec::new();
+        for file in [dir.join("events.jsonl.2"), dir.join("events.jsonl.1"), path.clone()] {
+            let contents = tokio::fs::read_to_string(&file).await.unwrap();
+            assert!(contents.len() <= 600);
+            for line in contents.lines() {
+                let record: AnalyticsEventRecord = serde_json::from_str(line).unwrap();
+                sequences.push(record.sequence);
+            }
+        }
+        assert!(!dir.join("events.jsonl.3").exists());
+        assert_eq!(sequences.last(), Some(&12));
+        assert!(sequences.windows(2).all(|w| w[1] == w[0] + 1));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_http_sink_gzip() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = vec![0u8; 4096];
+            // Read headers, then the body by Content-Length
+            let (headers, body_start) = loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break (String::from_utf8_lossy(&request[..end]).to_lowercase(), end + 4);
+                }
+            };
+            let length: usize = headers
+                .lines()
+                .find_map(|l| l.strip_prefix("content-length: "))
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap();
+            while request.len() < body_start + length {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            socket
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            (headers, request[body_start..].to_vec())
+        });
+
+        let emitter = AnalyticsEmitter::new();
+        let sink = HttpSink::new(format!("http://{}/events", addr)).with_batch_config(config(2, 1000));
+        emitter.add_sink(sink);
+        emit_plays(&emitter, 2).await;
+
+        let (headers, body) = server.await.unwrap();
+        assert!(headers.contains("content-encoding: gzip"));
+
+        let mut json = String::new();
+        flate2::read::GzDecoder::new(&body[..]).read_to_string(&mut json).unwrap();
+        let records: Vec<AnalyticsEventRecord> = serde_json::from_str(&json).unwrap();
+        assert_eq!(records.iter().map(|r| r.sequence).collect::<Vec<_>>(), vec![1, 2]);
+
+        emitter.flush().await;
+        assert_eq!(emitter.sink_stats()[0].sent, 2);
+    }
+}
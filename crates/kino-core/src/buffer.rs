@@ -5,15 +5,34 @@
 //! - Buffer level monitoring
 //! - Seek buffer management
 //! - Memory-efficient storage
+//!
+//! Prefetching is planned by a [`PrefetchScheduler`] the manager owns:
+//! it tracks which buffered segments were prefetched so consumption can
+//! be counted as a hit or a miss, and seeks cancel prefetches in flight.
+//!
+//! Byte-range segments (single-file HLS) that come from the same response
+//! share one backing buffer: each segment holds a cheap slice of it, and
+//! memory is accounted once per backing allocation rather than per segment.
+//!
+//! With a [`DiskCacheConfig`] set, segments dropped from memory are spilled
+//! to a bounded on-disk cache and served from there by
+//! [`BufferManager::cached_segment`] instead of being downloaded again.
 
 use crate::{
+    integrity::{check_truncated, SegmentIntegrity},
+    prefetch::{PredictedSwitch, PrefetchConfig, PrefetchContext, PrefetchRequest, PrefetchScheduler, PrefetchStats},
     types::*,
+    Error,
     Result,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use crate::disk_cache::{DiskCache, DiskCacheConfig, DiskCacheStats};
 use bytes::Bytes;
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::{Mutex, RwLock};
 use tracing::{debug, warn, instrument};
+use url::Url;
 
 /// Buffered segment data
 #[derive(Debug, Clone)]
@@ -28,6 +47,41 @@ pub struct BufferedSegment {
     pub end_time: f64,
     /// Has this segment been consumed
     pub consumed: bool,
+    /// Was this segment prefetched ahead of need
+    pub prefetched: bool,
+    /// Backing buffer `data` is a slice of
+    backing_id: u64,
+}
+
+/// A downloaded buffer one or more segments are sliced from
+#[derive(Debug)]
+struct Backing {
+    uri: Url,
+    /// Offset of `data` within the resource at `uri`
+    offset: u64,
+    data: Bytes,
+    /// Number of buffered segments referencing this buffer
+    refs: usize,
+}
+
+impl Backing {
+    /// Whether `data` points into this buffer's allocation
+    fn contains_slice(&self, data: &Bytes) -> bool {
+        let base = self.data.as_ptr() as usize;
+        let ptr = data.as_ptr() as usize;
+        ptr >= base && ptr + data.len() <= base + self.data.len()
+    }
+
+    /// Whether this buffer holds the resource bytes in `range`
+    fn covers(&self, range: &ByteRange) -> bool {
+        range.start >= self.offset && range.start + range.length <= self.offset + self.data.len() as u64
+    }
+
+    /// Slice of this buffer holding `range` of the resource
+    fn slice(&self, range: &ByteRange) -> Bytes {
+        let start = (range.start - self.offset) as usize;
+        self.data.slice(start..start + range.length as usize)
+    }
 }
 
 /// Buffer configuration
@@ -45,6 +99,11 @@ pub struct BufferConfig {
     pub prefetch_enabled: bool,
     /// Number of segments to prefetch
     pub prefetch_count: usize,
+    /// Integrity policy; strict refuses obviously truncated segments
+    pub integrity: SegmentIntegrity,
+    /// Spill segments dropped from memory to disk (off by default)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub disk_cache: Option<DiskCacheConfig>,
 }
 
 impl Default for BufferConfig {
@@ -56,6 +115,9 @@ impl Default for BufferConfig {
             max_memory_bytes: 256 * 1024 * 1024, // 256 MB
             prefetch_enabled: true,
             prefetch_count: 3,
+            integrity: SegmentIntegrity::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            disk_cache: None,
         }
     }
 }
@@ -70,37 +132,85 @@ pub struct BufferManager {
     playback_position: RwLock<f64>,
     /// Total buffered duration
     buffered_duration: RwLock<f64>,
-    /// Total memory used
+    /// Total memory used, counting each backing buffer once
     memory_used: RwLock<usize>,
+    /// Backing buffers by ID
+    backings: RwLock<HashMap<u64, Backing>>,
+    next_backing_id: AtomicU64,
     /// Pending fetch queue
     fetch_queue: Mutex<VecDeque<Segment>>,
+    /// Prefetch planning and hit/miss tracking
+    prefetch: Mutex<PrefetchScheduler>,
+    /// Segments spilled from memory
+    #[cfg(not(target_arch = "wasm32"))]
+    disk_cache: Option<Mutex<DiskCache>>,
 }
 
 impl BufferManager {
     /// Create a new buffer manager
     pub fn new(config: BufferConfig) -> Self {
+        let prefetch = PrefetchScheduler::new(PrefetchConfig {
+            max_segments: config.prefetch_count,
+            max_buffer_time: config.max_buffer_time,
+            max_memory_bytes: config.max_memory_bytes,
+        });
+
+        // A cache that can't be created only costs re-downloads
+        #[cfg(not(target_arch = "wasm32"))]
+        let disk_cache = config.disk_cache.as_ref().and_then(|cache| match DiskCache::open(cache) {
+            Ok(cache) => Some(Mutex::new(cache)),
+            Err(e) => {
+                warn!(path = %cache.path.display(), error = %e, "Disk cache unavailable");
+                None
+            }
+        });
+
         Self {
             config,
             segments: RwLock::new(BTreeMap::new()),
             playback_position: RwLock::new(0.0),
             buffered_duration: RwLock::new(0.0),
             memory_used: RwLock::new(0),
+            backings: RwLock::new(HashMap::new()),
+            next_backing_id: AtomicU64::new(0),
             fetch_queue: Mutex::new(VecDeque::new()),
+            prefetch: Mutex::new(prefetch),
+            #[cfg(not(target_arch = "wasm32"))]
+            disk_cache,
         }
     }
 
     /// Add a segment to the buffer
+    ///
+    /// For byte-range segments `data` may be exactly the range, or the
+    /// resource from its first byte on (e.g. one response shared by every
+    /// range of a single-file stream). Data already held for the same URI
+    /// is reused instead of stored again.
+    ///
+    /// Under the strict integrity policy, data too small for the segment's
+    /// duration is refused.
     #[instrument(skip(self, data))]
     pub async fn add_segment(&self, segment: Segment, data: Bytes) -> Result<()> {
+        if let Some(failure) = check_truncated(&segment, data.len(), &self.config.integrity) {
+            warn!(segment = segment.number, %failure, "Refusing truncated segment");
+            return Err(Error::SegmentIntegrity { url: segment.uri, failure: Box::new(failure) });
+        }
+
         let segment_duration = segment.duration.as_secs_f64();
-        let segment_size = data.len();
 
-        // Check memory limit
-        let current_memory = *self.memory_used.read().await;
-        if current_memory + segment_size > self.config.max_memory_bytes {
-            // Evict old segments
-            self.evict_segments(segment_size).await?;
-        }
+        let (backing_id, data) = match self.find_backing(&segment, &data).await {
+            Some(shared) => shared,
+            None => {
+                // Check memory limit
+                let segment_size = data.len();
+                let current_memory = *self.memory_used.read().await;
+                if current_memory + segment_size > self.config.max_memory_bytes {
+                    // Evict old segments
+                    self.evict_segments(segment_size).await?;
+                }
+                self.insert_backing(&segment, data).await
+            }
+        };
 
         let segments = self.segments.read().await;
         let start_time = if let Some((_, last)) = segments.iter().last() {
@@ -116,26 +226,180 @@ impl BufferManager {
             start_time,
             end_time: start_time + segment_duration,
             consumed: false,
+            prefetched: false,
+            backing_id,
         };
 
-        // Add to buffer
+        // Add to buffer, releasing any segment it replaces
         let mut segments = self.segments.write().await;
-        segments.insert(segment.number, buffered_segment);
+        if let Some(replaced) = segments.insert(segment.number, buffered_segment) {
+            *self.buffered_duration.write().await -= replaced.segment.duration.as_secs_f64();
+            let freed = self.release_backing(replaced.backing_id).await;
+            *self.memory_used.write().await -= freed;
+        }
 
         // Update stats
-        *self.buffered_duration.write().await += segment_duration;
-        *self.memory_used.write().await += segment_size;
+        let buffer_level = {
+            let mut buffered = self.buffered_duration.write().await;
+            *buffered += segment_duration;
+            *buffered
+        };
 
         debug!(
             segment = segment.number,
             duration = segment_duration,
-            buffer_level = *self.buffered_duration.read().await,
+            buffer_level,
             "Segment added to buffer"
         );
 
         Ok(())
     }
 
+    /// Add a prefetched segment to the buffer
+    ///
+    /// Returns false, without buffering anything, if the prefetch was
+    /// cancelled while in flight.
+    pub async fn add_prefetched_segment(&self, request: &PrefetchRequest, data: Bytes) -> Result<bool> {
+        if !self.prefetch.lock().await.complete(request) {
+            debug!(segment = request.segment.number, "Discarding cancelled prefetch");
+            return Ok(false);
+        }
+
+        let number = request.segment.number;
+        self.add_segment(request.segment.clone(), data).await?;
+        if let Some(segment) = self.segments.write().await.get_mut(&number) {
+            segment.prefetched = true;
+        }
+        Ok(true)
+    }
+
+    /// Record a failed prefetch so the segment can be scheduled again
+    pub async fn prefetch_failed(&self, request: &PrefetchRequest) {
+        self.prefetch.lock().await.fail(request);
+    }
+
+    /// Plan prefetches for the segments after the buffered range
+    ///
+    /// `segments` is the active rendition's segment list; `predicted` is
+    /// the switch the ABR engine is heading towards, if any.
+    pub async fn schedule_prefetch(
+        &self,
+        rendition: &Rendition,
+        segments: &[Segment],
+        predicted: Option<PredictedSwitch<'_>>,
+        playback_rate: f64,
+    ) -> Vec<PrefetchRequest> {
+        if !self.config.prefetch_enabled {
+            return Vec::new();
+        }
+
+        let context = PrefetchContext {
+            position: *self.playback_position.read().await,
+            playback_rate,
+            buffer_level: self.buffer_level().await,
+            memory_used: *self.memory_used.read().await,
+            rendition,
+            segments,
+            predicted,
+        };
+        self.prefetch.lock().await.plan(&context)
+    }
+
+    /// Prefetch hit/miss statistics
+    pub async fn prefetch_stats(&self) -> PrefetchStats {
+        self.prefetch.lock().await.stats()
+    }
+
+    /// Data of a segment spilled to the disk cache, to buffer again
+    /// instead of downloading it
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn cached_segment(&self, segment: &Segment) -> Option<Bytes> {
+        self.disk_cache.as_ref()?.lock().await.get(segment)
+    }
+
+    /// Disk cache statistics, if the cache is enabled
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn disk_cache_stats(&self) -> Option<DiskCacheStats> {
+        Some(self.disk_cache.as_ref()?.lock().await.stats())
+    }
+
+    /// Keep a segment leaving memory in the disk cache, if enabled
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    async fn spill(&self, segment: &BufferedSegment) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(cache) = &self.disk_cache {
+            if let Err(e) = cache.lock().await.insert(&segment.segment, &segment.data) {
+                warn!(segment = segment.segment.number, error = %e, "Failed to spill segment to disk");
+            }
+        }
+    }
+
+    /// Find a backing buffer that already holds this segment's bytes and
+    /// take a reference to it
+    async fn find_backing(&self, segment: &Segment, data: &Bytes) -> Option<(u64, Bytes)> {
+        let range = segment.byte_range.as_ref()?;
+        let mut backings = self.backings.write().await;
+
+        let (&id, backing) = backings.iter_mut().find(|(_, b)| {
+            b.uri == segment.uri && (b.contains_slice(data) || b.covers(range))
+        })?;
+
+        // A slice of the backing allocation may not start at the range
+        let slice = if backing.covers(range) {
+            backing.slice(range)
+        } else {
+            let start = data.as_ptr() as usize - backing.data.as_ptr() as usize;
+            let len = data.len().min(range.length as usize);
+            backing.data.slice(start..start + len)
+        };
+
+        backing.refs += 1;
+        debug!(segment = segment.number, backing = id, refs = backing.refs, "Segment shares backing buffer");
+        Some((id, slice))
+    }
+
+    /// Store `data` as a new backing buffer and return the segment's slice
+    async fn insert_backing(&self, segment: &Segment, data: Bytes) -> (u64, Bytes) {
+        let id = self.next_backing_id.fetch_add(1, Ordering::Relaxed);
+
+        // Data longer than the range is taken to start at the resource's
+        // first byte; otherwise it is the range itself
+        let offset = match &segment.byte_range {
+            Some(range) if data.len() as u64 > range.length && data.len() as u64 >= range.start + range.length => 0,
+            Some(range) => range.start,
+            None => 0,
+        };
+
+        let backing = Backing {
+            uri: segment.uri.clone(),
+            offset,
+            data,
+            refs: 1,
+        };
+        let slice = match &segment.byte_range {
+            Some(range) if backing.covers(range) => backing.slice(range),
+            _ => backing.data.clone(),
+        };
+
+        *self.memory_used.write().await += backing.data.len();
+        self.backings.write().await.insert(id, backing);
+        (id, slice)
+    }
+
+    /// Drop a segment's reference to its backing buffer, returning the
+    /// bytes freed (zero while other segments still use it)
+    async fn release_backing(&self, id: u64) -> usize {
+        let mut backings = self.backings.write().await;
+        let Some(backing) = backings.get_mut(&id) else {
+            return 0;
+        };
+        backing.refs -= 1;
+        if backing.refs > 0 {
+            return 0;
+        }
+        backings.remove(&id).map_or(0, |b| b.data.len())
+    }
+
     /// Get the next segment to play
     pub async fn get_next_segment(&self) -> Option<BufferedSegment> {
         let playback_pos = *self.playback_position.read().await;
@@ -164,6 +428,9 @@ impl BufferManager {
     pub async fn consume_segment(&self, sequence: u64) {
         let mut segments = self.segments.write().await;
         if let Some(segment) = segments.get_mut(&sequence) {
+            if !segment.consumed {
+                self.prefetch.lock().await.record_consumed(sequence);
+            }
             segment.consumed = true;
         }
     }
@@ -223,9 +490,9 @@ impl BufferManager {
                     }
                     Some(_) => {
                         // Check for gap
-                        if (segment.start_time - current_end).abs() < 0.1 {
-                            // Contiguous
-                            current_end = segment.end_time;
+                        if segment.start_time <= current_end + 0.1 {
+                            // Contiguous or overlapping
+                            current_end = current_end.max(segment.end_time);
                         } else {
                             // Gap - start new range
                             ranges.push((current_start.unwrap(), current_end));
@@ -251,8 +518,15 @@ impl BufferManager {
         // Check if position is buffered
         let is_buffered = self.get_segment_at(position).await.is_some();
 
-        if !is_buffered {
-            // Clear buffer for fresh fetch
+        if is_buffered {
+            // Keep prefetches still ahead of the new position
+            self.prefetch.lock().await.cancel_outside(position);
+        } else {
+            // Clear buffer for fresh fetch, keeping what was buffered on
+            // disk in case of a seek back
+            for segment in self.segments.read().await.values() {
+                self.spill(segment).await;
+            }
             self.clear().await;
         }
 
@@ -263,6 +537,8 @@ impl BufferManager {
     pub async fn clear(&self) {
         let mut segments = self.segments.write().await;
         segments.clear();
+        self.backings.write().await.clear();
+        self.prefetch.lock().await.cancel_all();
 
         *self.buffered_duration.write().await = 0.0;
         *self.memory_used.write().await = 0;
@@ -281,23 +557,27 @@ impl BufferManager {
         let mut duration = self.buffered_duration.write().await;
 
         let mut freed = 0;
-        let mut to_remove = Vec::new();
 
-        // Find segments to remove (oldest first, already consumed, behind playback)
-        for (&seq, segment) in segments.iter() {
+        // Remove segments (oldest first, already consumed, behind playback).
+        // Shared backing buffers are only freed with their last segment.
+        let candidates: Vec<u64> = segments
+            .iter()
+            .filter(|(_, s)| s.consumed || s.end_time < playback_pos - 5.0)
+            .map(|(&seq, _)| seq)
+            .collect();
+
+        for seq in candidates {
             if freed >= needed_bytes {
                 break;
             }
-            if segment.consumed || segment.end_time < playback_pos - 5.0 {
-                to_remove.push(seq);
-                freed += segment.data.len();
-            }
-        }
-
-        // Remove segments
-        for seq in to_remove {
             if let Some(segment) = segments.remove(&seq) {
-                *memory -= segment.data.len();
+                if !segment.consumed {
+                    self.prefetch.lock().await.record_dropped(seq);
+                }
+                self.spill(&segment).await;
+                let released = self.release_backing(segment.backing_id).await;
+                freed += released;
+                *memory -= released;
                 *duration -= segment.segment.duration.as_secs_f64();
                 debug!(segment = seq, "Evicted segment from buffer");
             }
@@ -330,7 +610,8 @@ impl BufferManager {
 
         for seq in to_remove {
             if let Some(segment) = segments.remove(&seq) {
-                *memory -= segment.data.len();
+                self.spill(&segment).await;
+                *memory -= self.release_backing(segment.backing_id).await;
                 *duration -= segment.segment.duration.as_secs_f64();
             }
         }
@@ -340,13 +621,35 @@ impl BufferManager {
     pub async fn stats(&self) -> BufferStats {
         let segments = self.segments.read().await;
         let ranges = self.buffered_ranges().await;
+        let backings = self.backings.read().await;
+
+        let segment_info = segments
+            .values()
+            .map(|s| {
+                let backing = backings.get(&s.backing_id);
+                SegmentInfo {
+                    number: s.segment.number,
+                    uri: s.segment.uri.clone(),
+                    byte_range: s.segment.byte_range,
+                    start_time: s.start_time,
+                    end_time: s.end_time,
+                    size: s.data.len(),
+                    backing_size: backing.map_or(0, |b| b.data.len()),
+                    shared: backing.is_some_and(|b| b.refs > 1),
+                    consumed: s.consumed,
+                    prefetched: s.prefetched,
+                }
+            })
+            .collect();
 
         BufferStats {
             segment_count: segments.len(),
             buffer_level: self.buffer_level().await,
             memory_used: *self.memory_used.read().await,
+            backing_count: backings.len(),
             buffered_ranges: ranges,
             playback_position: *self.playback_position.read().await,
+            segments: segment_info,
         }
     }
 
@@ -371,8 +674,32 @@ pub struct BufferStats {
     pub segment_count: usize,
     pub buffer_level: f64,
     pub memory_used: usize,
+    /// Distinct backing buffers holding segment data
+    pub backing_count: usize,
     pub buffered_ranges: Vec<(f64, f64)>,
     pub playback_position: f64,
+    /// Per-segment details, in sequence order
+    pub segments: Vec<SegmentInfo>,
+}
+
+/// What the buffer holds for one segment
+#[derive(Debug, Clone)]
+pub struct SegmentInfo {
+    pub number: u64,
+    /// Origin URI
+    pub uri: Url,
+    /// Byte range within the origin, for single-file streams
+    pub byte_range: Option<ByteRange>,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// Bytes of the segment itself
+    pub size: usize,
+    /// Bytes of the backing buffer it is sliced from
+    pub backing_size: usize,
+    /// Whether other segments share the backing buffer
+    pub shared: bool,
+    pub consumed: bool,
+    pub prefetched: bool,
 }
 
 #[cfg(test)]
@@ -405,6 +732,23 @@ mod tests {
         assert_eq!(buffer.buffer_level().await, 4.0);
     }
 
+    #[tokio::test]
+    async fn test_strict_integrity_refuses_truncated_segment() {
+        use crate::integrity::IntegrityPolicy;
+
+        let buffer = BufferManager::new(BufferConfig {
+            integrity: SegmentIntegrity { policy: IntegrityPolicy::Strict, ..Default::default() },
+            ..Default::default()
+        });
+
+        let err = buffer.add_segment(create_test_segment(1), Bytes::from(vec![0u8; 188])).await.unwrap_err();
+        assert_eq!(err.error_code(), "SEGMENT_INTEGRITY");
+        assert_eq!(buffer.buffer_level().await, 0.0);
+
+        buffer.add_segment(create_test_segment(1), Bytes::from(vec![0u8; 4096])).await.unwrap();
+        assert_eq!(buffer.buffer_level().await, 4.0);
+    }
+
     #[tokio::test]
     async fn test_buffer_level() {
         let buffer = BufferManager::new(BufferConfig::default());
@@ -439,4 +783,156 @@ mod tests {
         let is_buffered = buffer.seek(100.0).await.unwrap();
         assert!(!is_buffered);
     }
+
+    fn create_range_segment(num: u64, start: u64, length: u64) -> Segment {
+        Segment {
+            number: num,
+            uri: Url::parse("https://example.com/video.mp4").unwrap(),
+            byte_range: Some(ByteRange { start, length }),
+            ..create_test_segment(num)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_byte_range_segments_share_backing() {
+        const RESOURCE: usize = 10 * 1024 * 1024;
+        const RANGE: u64 = (RESOURCE / 20) as u64;
+
+        let buffer = BufferManager::new(BufferConfig::default());
+        let resource = Bytes::from(vec![7u8; RESOURCE]);
+
+        // Every range arrives with the full response
+        for i in 0..20 {
+            let segment = create_range_segment(i, i * RANGE, RANGE);
+            buffer.add_segment(segment, resource.clone()).await.unwrap();
+        }
+
+        let stats = buffer.stats().await;
+        assert_eq!(stats.segment_count, 20);
+        assert_eq!(stats.memory_used, RESOURCE);
+        assert_eq!(stats.backing_count, 1);
+        assert_eq!(stats.buffered_ranges, vec![(0.0, 80.0)]);
+
+        let info = &stats.segments[3];
+        assert_eq!(info.size, RANGE as usize);
+        assert_eq!(info.backing_size, RESOURCE);
+        assert_eq!(info.byte_range.unwrap().start, 3 * RANGE);
+        assert!(info.shared);
+
+        // Segments hold only their own range
+        let segment = buffer.get_segment_at(13.0).await.unwrap();
+        assert_eq!(segment.segment.number, 3);
+        assert_eq!(segment.data.len(), RANGE as usize);
+    }
+
+    #[tokio::test]
+    async fn test_byte_range_slices_and_copies() {
+        let buffer = BufferManager::new(BufferConfig::default());
+        let resource = Bytes::from((0..=255u8).cycle().take(4096).collect::<Vec<_>>());
+
+        // Slices of one response are recognized as the same allocation
+        buffer.add_segment(create_range_segment(0, 0, 1024), resource.clone()).await.unwrap();
+        buffer.add_segment(create_range_segment(1, 1024, 1024), resource.slice(1024..2048)).await.unwrap();
+        // A separately downloaded copy of a held range is not stored again
+        let copy = Bytes::copy_from_slice(&resource[2048..3072]);
+        buffer.add_segment(create_range_segment(2, 2048, 1024), copy).await.unwrap();
+        assert_eq!(buffer.stats().await.memory_used, 4096);
+
+        let segment = buffer.get_segment_at(9.0).await.unwrap();
+        assert_eq!(&segment.data[..], &resource[2048..3072]);
+
+        // The backing buffer is freed with its last segment
+        for i in 0..3 {
+            buffer.consume_segment(i).await;
+        }
+        buffer.update_position(30.0).await;
+        let stats = buffer.stats().await;
+        assert_eq!(stats.segment_count, 0);
+        assert_eq!(stats.memory_used, 0);
+        assert_eq!(stats.backing_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_hits_and_seek() {
+        let buffer = BufferManager::new(BufferConfig {
+            prefetch_count: 2,
+            ..Default::default()
+        });
+        let rendition = Rendition {
+            id: "720p".to_string(),
+            bandwidth: 2_800_000,
+            resolution: None,
+            frame_rate: None,
+            video_codec: None,
+            audio_codec: None,
+            uri: Url::parse("https://example.com/720p.m3u8").unwrap(),
+            hdr: None,
+            language: None,
+            name: None,
+            audio_group: None,
+        };
+        let playlist: Vec<Segment> = (0..30).map(create_test_segment).collect();
+
+        // Segment 0 is fetched on demand, 1 and 2 are prefetched
+        buffer.add_segment(playlist[0].clone(), Bytes::from(vec![0u8; 1024])).await.unwrap();
+        let requests = buffer.schedule_prefetch(&rendition, &playlist, None, 1.0).await;
+        assert_eq!(requests.iter().map(|r| r.segment.number).collect::<Vec<_>>(), vec![1, 2]);
+        for request in &requests {
+            assert!(buffer.add_prefetched_segment(request, Bytes::from(vec![0u8; 1024])).await.unwrap());
+        }
+        assert!(buffer.stats().await.segments[1].prefetched);
+
+        buffer.consume_segment(0).await;
+        buffer.consume_segment(1).await;
+        buffer.consume_segment(1).await;
+
+        // Seeking away cancels in-flight prefetches and wastes segment 2
+        buffer.update_position(8.0).await;
+        let requests = buffer.schedule_prefetch(&rendition, &playlist, None, 1.0).await;
+        assert_eq!(requests.len(), 2);
+        assert!(!buffer.seek(100.0).await.unwrap());
+        assert!(requests.iter().all(|r| r.is_cancelled()));
+        assert!(!buffer.add_prefetched_segment(&requests[0], Bytes::new()).await.unwrap());
+
+        let stats = buffer.prefetch_stats().await;
+        assert_eq!(stats.issued, 4);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.wasted, 1);
+        assert_eq!(stats.cancelled, 2);
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_keeps_dropped_segments() {
+        let path = std::env::temp_dir().join(format!("kino-buffer-cache-{}", uuid::Uuid::new_v4()));
+        let buffer = BufferManager::new(BufferConfig {
+            max_memory_bytes: 3 * 1024,
+            disk_cache: Some(DiskCacheConfig { path: path.clone(), max_bytes: 64 * 1024 }),
+            ..Default::default()
+        });
+
+        // Memory holds three segments; older consumed ones are spilled
+        for num in 0..6 {
+            buffer.add_segment(create_test_segment(num), Bytes::from(vec![num as u8; 1024])).await.unwrap();
+            buffer.consume_segment(num).await;
+        }
+        assert!(buffer.stats().await.memory_used <= 3 * 1024);
+        assert_eq!(buffer.cached_segment(&create_test_segment(0)).await.unwrap(), Bytes::from(vec![0u8; 1024]));
+        assert!(buffer.cached_segment(&create_test_segment(4)).await.is_none());
+
+        // Seeking away spills what was still buffered
+        assert!(!buffer.seek(100.0).await.unwrap());
+        assert_eq!(buffer.cached_segment(&create_test_segment(4)).await.unwrap(), Bytes::from(vec![4u8; 1024]));
+
+        let stats = buffer.disk_cache_stats().await.unwrap();
+        assert_eq!((stats.entries, stats.spilled, stats.hits, stats.misses), (6, 6, 2, 1));
+
+        // Off by default
+        let uncached = BufferManager::new(BufferConfig::default());
+        assert!(uncached.cached_segment(&create_test_segment(0)).await.is_none());
+        assert!(uncached.disk_cache_stats().await.is_none());
+
+        drop(buffer);
+        std::fs::remove_dir_all(path).unwrap();
+    }
 }
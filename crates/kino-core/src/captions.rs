@@ -4,6 +4,10 @@
 //! - WebVTT (Web Video Text Tracks)
 //! - SRT (SubRip)
 //!
+//! [`load_text_track`] fetches and parses a sidecar track, and
+//! [`ActiveCues`] keeps the cues displayed at the playback position in
+//! sync with the player's clock.
+//!
 //! # Example
 //!
 //! ```rust
@@ -23,7 +27,8 @@
 //! ```
 
 use crate::error::{Error, Result};
-use crate::types::{TextCue, CueSettings, CueAlignment};
+use crate::http::HttpFetcher;
+use crate::types::{Chapter, TextCue, CueSettings, CueAlignment, TextTrack, TextTrackFormat};
 
 /// WebVTT parser
 pub struct WebVttParser;
@@ -37,7 +42,7 @@ impl WebVttParser {
         // Check for WEBVTT header
         let first_line = lines.next().unwrap_or("");
         if !first_line.starts_with("WEBVTT") {
-            return Err(Error::ManifestParse("Invalid WebVTT: missing WEBVTT header".to_string()));
+            return Err(Error::parse("Invalid WebVTT: missing WEBVTT header".to_string()));
         }
 
         // Skip header metadata until first blank line
@@ -141,7 +146,7 @@ impl WebVttParser {
     fn parse_timing_line(line: &str) -> Result<(f64, f64, Option<CueSettings>)> {
         let parts: Vec<&str> = line.split("-->").collect();
         if parts.len() != 2 {
-            return Err(Error::ManifestParse("Invalid timing line".to_string()));
+            return Err(Error::parse("Invalid timing line".to_string()));
         }
 
         let start = Self::parse_timestamp(parts[0].trim())?;
@@ -168,20 +173,20 @@ impl WebVttParser {
             // mm:ss.mmm
             2 => {
                 let minutes: f64 = parts[0].parse()
-                    .map_err(|_| Error::ManifestParse(format!("Invalid minutes: {}", parts[0])))?;
+                    .map_err(|_| Error::parse(format!("Invalid minutes: {}", parts[0])))?;
                 let seconds = Self::parse_seconds(parts[1])?;
                 Ok(minutes * 60.0 + seconds)
             }
             // hh:mm:ss.mmm
             3 => {
                 let hours: f64 = parts[0].parse()
-                    .map_err(|_| Error::ManifestParse(format!("Invalid hours: {}", parts[0])))?;
+                    .map_err(|_| Error::parse(format!("Invalid hours: {}", parts[0])))?;
                 let minutes: f64 = parts[1].parse()
-                    .map_err(|_| Error::ManifestParse(format!("Invalid minutes: {}", parts[1])))?;
+                    .map_err(|_| Error::parse(format!("Invalid minutes: {}", parts[1])))?;
                 let seconds = Self::parse_seconds(parts[2])?;
                 Ok(hours * 3600.0 + minutes * 60.0 + seconds)
             }
-            _ => Err(Error::ManifestParse(format!("Invalid timestamp: {}", ts))),
+            _ => Err(Error::parse(format!("Invalid timestamp: {}", ts))),
         }
     }
 
@@ -190,7 +195,7 @@ impl WebVttParser {
         // Handle both . and , as decimal separator
         let s = s.replace(',', ".");
         s.parse()
-            .map_err(|_| Error::ManifestParse(format!("Invalid seconds: {}", s)))
+            .map_err(|_| Error::parse(format!("Invalid seconds: {}", s)))
     }
 
     /// Parse cue settings
@@ -315,7 +320,7 @@ impl SrtParser {
     fn parse_timing_line(line: &str) -> Result<(f64, f64)> {
         let parts: Vec<&str> = line.split("-->").collect();
         if parts.len() != 2 {
-            return Err(Error::ManifestParse("Invalid SRT timing line".to_string()));
+            return Err(Error::parse("Invalid SRT timing line".to_string()));
         }
 
         let start = Self::parse_timestamp(parts[0].trim())?;
@@ -328,17 +333,17 @@ impl SrtParser {
     fn parse_timestamp(ts: &str) -> Result<f64> {
         let parts: Vec<&str> = ts.split(':').collect();
         if parts.len() != 3 {
-            return Err(Error::ManifestParse(format!("Invalid SRT timestamp: {}", ts)));
+            return Err(Error::parse(format!("Invalid SRT timestamp: {}", ts)));
         }
 
         let hours: f64 = parts[0].parse()
-            .map_err(|_| Error::ManifestParse(format!("Invalid hours: {}", parts[0])))?;
+            .map_err(|_| Error::parse(format!("Invalid hours: {}", parts[0])))?;
         let minutes: f64 = parts[1].parse()
-            .map_err(|_| Error::ManifestParse(format!("Invalid minutes: {}", parts[1])))?;
+            .map_err(|_| Error::parse(format!("Invalid minutes: {}", parts[1])))?;
 
         // SRT uses comma as decimal separator
         let seconds: f64 = parts[2].replace(',', ".").parse()
-            .map_err(|_| Error::ManifestParse(format!("Invalid seconds: {}", parts[2])))?;
+            .map_err(|_| Error::parse(format!("Invalid seconds: {}", parts[2])))?;
 
         Ok(hours * 3600.0 + minutes * 60.0 + seconds)
     }
@@ -366,11 +371,151 @@ pub fn srt_to_vtt(srt: &str) -> String {
     vtt
 }
 
+/// Write chapters as a WebVTT chapters track
+///
+/// Each chapter becomes a cue identified by its ID with its title as text.
+pub fn chapters_to_vtt(chapters: &[Chapter]) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+
+    for chapter in chapters {
+        vtt.push_str(&format!(
+            "\n{}\n{} --> {}\n{}\n",
+            chapter.id,
+            format_vtt_timestamp(chapter.start_time),
+            format_vtt_timestamp(chapter.end_time),
+            chapter.title
+        ));
+    }
+
+    vtt
+}
+
+/// Format seconds as a WebVTT `hh:mm:ss.ttt` timestamp
+fn format_vtt_timestamp(secs: f64) -> String {
+    let millis = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
 /// Find cues active at a given time
 pub fn cues_at_time(cues: &[TextCue], time: f64) -> Vec<&TextCue> {
     cues.iter().filter(|c| c.is_active_at(time)).collect()
 }
 
+/// Parse a text track file in the given format
+pub fn parse_text_track(input: &str, format: TextTrackFormat) -> Result<Vec<TextCue>> {
+    let input = input.trim_start_matches('\u{feff}');
+    match format {
+        TextTrackFormat::WebVtt => WebVttParser::parse(input),
+        TextTrackFormat::Srt => SrtParser::parse(input),
+        other => Err(Error::parse(format!("Unsupported text track format: {:?}", other))),
+    }
+}
+
+/// Fetch a sidecar text track and parse its cues
+///
+/// On native targets `file:` URLs are read from disk, so local subtitle
+/// files load like remote ones.
+pub async fn load_text_track(fetcher: &dyn HttpFetcher, track: &TextTrack) -> Result<Vec<TextCue>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if track.url.scheme() == "file" {
+        let path = track.url.to_file_path()
+            .map_err(|_| Error::parse(format!("Invalid file URL: {}", track.url)))?;
+        let bytes = std::fs::read(path)?;
+        return parse_text_track(&String::from_utf8_lossy(&bytes), track.format);
+    }
+
+    let text = fetcher.get_text(&track.url).await?;
+    parse_text_track(&text, track.format)
+}
+
+/// Longest forward move of the playback position treated as playback
+/// rather than a seek, in seconds
+const MAX_PLAYBACK_STEP: f64 = 1.0;
+
+/// Cues displayed at the playback position
+///
+/// Feed the position to [`update`](Self::update) on every clock tick.
+/// While playback moves forward only cues that started since the last
+/// tick are considered; a jump backward or further than a second ahead
+/// (a seek) clears the set and resolves it again from all cues.
+#[derive(Debug, Clone, Default)]
+pub struct ActiveCues {
+    /// Cues ordered by start time
+    cues: Vec<TextCue>,
+    /// Indices of the active cues, ascending
+    active: Vec<usize>,
+    /// Position of the last update
+    position: Option<f64>,
+}
+
+impl ActiveCues {
+    /// Track the given cues, in any order
+    pub fn new(mut cues: Vec<TextCue>) -> Self {
+        cues.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+        Self { cues, active: Vec::new(), position: None }
+    }
+
+    /// All cues, ordered by start time
+    pub fn cues(&self) -> &[TextCue] {
+        &self.cues
+    }
+
+    /// Cues active at the last update, ordered by start time
+    pub fn active(&self) -> Vec<&TextCue> {
+        self.active.iter().map(|&i| &self.cues[i]).collect()
+    }
+
+    /// Position of the last update, in seconds
+    pub fn position(&self) -> Option<f64> {
+        self.position
+    }
+
+    /// Re-resolve the active cues from scratch on the next update, e.g.
+    /// after a seek that may have moved less than a second
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    /// Move to `time` (seconds), returning whether the active cues changed
+    pub fn update(&mut self, time: f64) -> bool {
+        let previous = std::mem::take(&mut self.active);
+        let started = self.cues.partition_point(|c| c.start_time <= time);
+
+        self.active = match self.position {
+            Some(last) if time >= last && time - last <= MAX_PLAYBACK_STEP => {
+                let first_new = self.cues.partition_point(|c| c.start_time <= last);
+                previous.iter().copied()
+                    .chain(first_new..started)
+                    .filter(|&i| self.cues[i].is_active_at(time))
+                    .collect()
+            }
+            _ => (0..started).filter(|&i| self.cues[i].is_active_at(time)).collect(),
+        };
+        self.position = Some(time);
+        self.active != previous
+    }
+}
+
+/// Merge cue updates into a track's cues
+///
+/// An update replaces the cue with the same ID, e.g. an embedded caption
+/// whose provisional end time became known. Cues stay ordered by start.
+pub fn merge_cues(cues: &mut Vec<TextCue>, updates: Vec<TextCue>) {
+    for update in updates {
+        match cues.iter_mut().find(|cue| cue.id == update.id) {
+            Some(cue) => *cue = update,
+            None => cues.push(update),
+        }
+    }
+    cues.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -444,4 +589,84 @@ This is a subtitle.
         assert!(vtt.starts_with("WEBVTT"));
         assert!(vtt.contains("00:00:00.000 --> 00:00:04.000"));
     }
+
+    #[test]
+    fn test_parse_text_track() {
+        let vtt = "\u{feff}WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHi\n";
+        assert_eq!(parse_text_track(vtt, TextTrackFormat::WebVtt).unwrap()[0].text, "Hi");
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nHi\n";
+        assert_eq!(parse_text_track(srt, TextTrackFormat::Srt).unwrap()[0].start_time, 1.0);
+        assert!(parse_text_track(srt, TextTrackFormat::Ttml).is_err());
+        assert_eq!(TextTrackFormat::from_extension("SRT"), Some(TextTrackFormat::Srt));
+    }
+
+    #[tokio::test]
+    async fn test_load_text_track() {
+        let url = url::Url::parse("https://cdn.example.com/subs/en.vtt").unwrap();
+        let fetcher = crate::http::ScriptedFetcher::new()
+            .respond(url.as_str(), 200, "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nRemote\n");
+        let track = TextTrack::subtitles("en", "English", url);
+        assert_eq!(load_text_track(&fetcher, &track).await.unwrap()[0].text, "Remote");
+
+        let path = std::env::temp_dir().join(format!("kino-captions-{}.srt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "1\n00:00:03,000 --> 00:00:04,000\nLocal\n").unwrap();
+        let url = url::Url::from_file_path(&path).unwrap();
+        let track = TextTrack::new("local", crate::types::TextTrackKind::Subtitles, "en", "Local", url, TextTrackFormat::Srt);
+        let cues = load_text_track(&fetcher, &track).await;
+        std::fs::remove_file(&path).unwrap();
+        let cues = cues.unwrap();
+        assert_eq!((cues[0].text.as_str(), cues[0].start_time), ("Local", 3.0));
+        assert_eq!(fetcher.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_active_cues() {
+        let ids = |cues: &ActiveCues| cues.active().iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+        let mut cues = ActiveCues::new(vec![
+            TextCue::new("b", 2.0, 4.0, "B"),
+            TextCue::new("a", 0.0, 2.0, "A"),
+            TextCue::new("long", 1.0, 10.0, "Long"),
+            TextCue::new("c", 8.0, 9.0, "C"),
+        ]);
+
+        assert!(cues.update(0.5));
+        assert_eq!(ids(&cues), ["a"]);
+        assert!(cues.update(1.0));
+        assert_eq!(ids(&cues), ["a", "long"]);
+        assert!(!cues.update(1.5));
+        // End times are exclusive
+        assert!(cues.update(2.0));
+        assert_eq!(ids(&cues), ["long", "b"]);
+
+        // Seeking back or far ahead re-resolves, including cues that
+        // started before the new position
+        assert!(cues.update(0.2));
+        assert_eq!(ids(&cues), ["a"]);
+        assert!(cues.update(8.5));
+        assert_eq!(ids(&cues), ["long", "c"]);
+        assert!(cues.update(20.0));
+        assert!(cues.active().is_empty());
+
+        // After a reset the next update resolves from scratch
+        cues.update(2.5);
+        cues.reset();
+        assert!(!cues.update(3.0));
+        assert_eq!(ids(&cues), ["long", "b"]);
+    }
+
+    #[test]
+    fn test_chapters_to_vtt() {
+        let chapters = [
+            Chapter::new("chapter-1", "Intro", 0.0, 95.25),
+            Chapter::new("chapter-2", "Main", 95.25, 3725.5),
+        ];
+        let vtt = chapters_to_vtt(&chapters);
+        assert!(vtt.contains("00:01:35.250 --> 01:02:05.500"));
+
+        let cues = WebVttParser::parse(&vtt).unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[1].id, "chapter-2");
+        assert_eq!(cues[1].text, "Main");
+        assert_eq!((cues[1].start_time, cues[1].end_time), (95.25, 3725.5));
+    }
 }
@@ -0,0 +1,1314 @@
+//! Embedded CEA-608/708 caption extraction
+//!
+//! Broadcast-sourced streams often carry closed captions inside the video
+//! elementary stream instead of a separate text track: as ATSC A/53
+//! `cc_data` in H.264/H.265 SEI messages, or in MPEG-2 picture user data.
+//! [`CaptionExtractor`] demuxes MPEG-TS segments, collects the caption
+//! bytes in presentation order and decodes them into [`TextCue`]s:
+//!
+//! - CEA-608 pop-on, roll-up and paint-on captions from one channel
+//! - CEA-708 window text from one caption service
+//!
+//! Cue times follow the video PTS, anchored so the earliest picture of the
+//! first segment lands at the start time given for it. A cue still on
+//! screen at the end of a segment is returned with a provisional end time
+//! and returned again, under the same ID, once it is cleared; merge them
+//! into a track with [`merge_cues`](crate::captions::merge_cues).
+
+use crate::types::{TextCue, TextTrack, TextTrackFormat, TextTrackKind};
+use url::Url;
+
+const TS_PACKET_SIZE: usize = 188;
+const TS_SYNC_BYTE: u8 = 0x47;
+const PTS_CLOCK: f64 = 90_000.0;
+const PTS_WRAP: u64 = 1 << 33;
+
+/// Whether `data` looks like an MPEG transport stream
+pub fn is_transport_stream(data: &[u8]) -> bool {
+    data.len() >= TS_PACKET_SIZE
+        && data[0] == TS_SYNC_BYTE
+        && data.get(TS_PACKET_SIZE).is_none_or(|&b| b == TS_SYNC_BYTE)
+}
+
+/// CEA-608 caption channel
+///
+/// CC1 and CC2 are carried in field 1, CC3 and CC4 in field 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Cea608Channel {
+    #[default]
+    Cc1,
+    Cc2,
+    Cc3,
+    Cc4,
+}
+
+impl Cea608Channel {
+    /// Track ID, e.g. "cc1"
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Cc1 => "cc1",
+            Self::Cc2 => "cc2",
+            Self::Cc3 => "cc3",
+            Self::Cc4 => "cc4",
+        }
+    }
+
+    fn field(self) -> u8 {
+        match self {
+            Self::Cc1 | Self::Cc2 => 1,
+            Self::Cc3 | Self::Cc4 => 2,
+        }
+    }
+
+    fn data_channel(self) -> u8 {
+        match self {
+            Self::Cc1 | Self::Cc3 => 1,
+            Self::Cc2 | Self::Cc4 => 2,
+        }
+    }
+}
+
+/// Cues decoded from a segment
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedCues {
+    /// Cues of the CEA-608 channel
+    pub cea608: Vec<TextCue>,
+    /// Cues of the CEA-708 service
+    pub cea708: Vec<TextCue>,
+}
+
+impl EmbeddedCues {
+    /// No cues were decoded
+    pub fn is_empty(&self) -> bool {
+        self.cea608.is_empty() && self.cea708.is_empty()
+    }
+}
+
+/// Decodes captions embedded in the video of consecutive TS segments
+///
+/// Decoder state carries over from one segment to the next, so captions
+/// spanning a segment boundary come out whole. Call [`reset`](Self::reset)
+/// after a seek.
+pub struct CaptionExtractor {
+    demuxer: TsDemuxer,
+    /// PTS and timeline position it maps to
+    origin: Option<(u64, f64)>,
+    /// Discontinuity sequence the origin belongs to
+    discontinuity: Option<u32>,
+    /// Time of the latest picture decoded
+    last_time: f64,
+    cea608: Cea608Decoder,
+    cea708: Cea708Decoder,
+}
+
+impl Default for CaptionExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CaptionExtractor {
+    /// Extractor for CC1 and CEA-708 service 1
+    pub fn new() -> Self {
+        Self::with_channels(Cea608Channel::Cc1, 1)
+    }
+
+    /// Extractor for a CEA-608 channel and a CEA-708 service (1-63)
+    pub fn with_channels(channel: Cea608Channel, service: u8) -> Self {
+        Self {
+            demuxer: TsDemuxer::default(),
+            origin: None,
+            discontinuity: None,
+            last_time: 0.0,
+            cea608: Cea608Decoder::new(channel),
+            cea708: Cea708Decoder::new(service),
+        }
+    }
+
+    /// Track ID of the CEA-608 channel's cues
+    pub fn cea608_track_id(&self) -> &'static str {
+        self.cea608.channel.id()
+    }
+
+    /// Track ID of the CEA-708 service's cues
+    pub fn cea708_track_id(&self) -> String {
+        format!("service{}", self.cea708.service)
+    }
+
+    /// Synthetic text tracks for the caption formats seen so far
+    ///
+    /// `url` is the rendition the captions are embedded in; the track URL
+    /// points into it with the track ID as fragment.
+    pub fn tracks(&self, url: &Url) -> Vec<TextTrack> {
+        let track = |id: String, label: String, format| {
+            let mut url = url.clone();
+            url.set_fragment(Some(&id));
+            TextTrack::new(id, TextTrackKind::Captions, "und", label, url, format)
+        };
+
+        let mut tracks = Vec::new();
+        if self.cea608.detected {
+            let id = self.cea608_track_id();
+            tracks.push(track(id.to_string(), id.to_uppercase(), TextTrackFormat::Cea608));
+        }
+        if self.cea708.detected {
+            let label = format!("Service {}", self.cea708.service);
+            tracks.push(track(self.cea708_track_id(), label, TextTrackFormat::Cea708));
+        }
+        tracks
+    }
+
+    /// Re-anchor the timeline when a segment starts a new discontinuity
+    ///
+    /// Timestamps restart across a discontinuity, so the next segment's
+    /// start time becomes the new origin.
+    pub fn set_discontinuity_sequence(&mut self, sequence: u32) {
+        if self.discontinuity.is_some_and(|current| current != sequence) {
+            self.origin = None;
+        }
+        self.discontinuity = Some(sequence);
+    }
+
+    /// Decode the captions in a TS segment
+    ///
+    /// `start_time` anchors the timeline on the first call; later segments
+    /// are placed by their PTS.
+    pub fn extract(&mut self, data: &[u8], start_time: f64) -> EmbeddedCues {
+        let units = self.demuxer.demux(data);
+        let Some(first) = units.iter().map(|u| u.pts).min() else {
+            return self.take();
+        };
+        let (origin_pts, origin_time) = *self.origin.get_or_insert((first, start_time));
+
+        let mut units: Vec<(f64, AccessUnit)> = units
+            .into_iter()
+            .map(|unit| (origin_time + pts_delta(origin_pts, unit.pts) as f64 / PTS_CLOCK, unit))
+            .collect();
+        // Pictures arrive in decode order; captions are in presentation order
+        units.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (time, unit) in units {
+            self.last_time = self.last_time.max(time);
+            for triplet in unit.triplets {
+                match triplet.cc_type {
+                    0 | 1 => self.cea608.push(triplet.cc_type + 1, triplet.data, time),
+                    _ => self.cea708.push(triplet.cc_type == 3, triplet.data, time),
+                }
+            }
+        }
+
+        self.take()
+    }
+
+    /// Close the cues still on screen, e.g. at the end of the stream
+    pub fn flush(&mut self) -> EmbeddedCues {
+        self.cea608.finish(self.last_time);
+        self.cea708.finish(self.last_time);
+        self.take()
+    }
+
+    /// Drop decoder state after a seek
+    ///
+    /// The timeline is kept: PTS stays continuous across a seek within the
+    /// same discontinuity.
+    pub fn reset(&mut self) {
+        self.demuxer.pes.clear();
+        self.cea608.reset();
+        self.cea708.reset();
+    }
+
+    fn take(&mut self) -> EmbeddedCues {
+        EmbeddedCues {
+            cea608: self.cea608.cues.take(self.last_time),
+            cea708: self.cea708.cues.take(self.last_time),
+        }
+    }
+}
+
+/// Signed distance from `from` to `to` in 90kHz ticks, across PTS wrap
+fn pts_delta(from: u64, to: u64) -> i64 {
+    let delta = to.wrapping_sub(from) & (PTS_WRAP - 1);
+    if delta >= PTS_WRAP / 2 {
+        delta as i64 - PTS_WRAP as i64
+    } else {
+        delta as i64
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Transport stream demuxing
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VideoCodec {
+    H264,
+    H265,
+    Mpeg2,
+}
+
+/// One caption triplet from `cc_data`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CcTriplet {
+    /// 0/1: CEA-608 field 1/2, 2: DTVCC data, 3: DTVCC packet start
+    cc_type: u8,
+    data: [u8; 2],
+}
+
+/// Caption data of one video picture
+struct AccessUnit {
+    pts: u64,
+    triplets: Vec<CcTriplet>,
+}
+
+/// Follows PAT and PMT to the video PID and reassembles its PES packets
+#[derive(Default)]
+struct TsDemuxer {
+    pmt_pid: Option<u16>,
+    video: Option<(u16, VideoCodec)>,
+    pes: Vec<u8>,
+}
+
+impl TsDemuxer {
+    fn demux(&mut self, data: &[u8]) -> Vec<AccessUnit> {
+        let mut units = Vec::new();
+
+        for packet in data.chunks_exact(TS_PACKET_SIZE) {
+            if packet[0] != TS_SYNC_BYTE {
+                continue;
+            }
+            let unit_start = packet[1] & 0x40 != 0;
+            let pid = u16::from_be_bytes([packet[1] & 0x1F, packet[2]]);
+            let adaptation = (packet[3] >> 4) & 0x03;
+            if adaptation & 0x01 == 0 {
+                continue;
+            }
+            let offset = if adaptation & 0x02 != 0 { 5 + packet[4] as usize } else { 4 };
+            let Some(payload) = packet.get(offset..) else {
+                continue;
+            };
+
+            if pid == 0 {
+                if unit_start {
+                    self.parse_pat(payload);
+                }
+            } else if Some(pid) == self.pmt_pid {
+                if unit_start {
+                    self.parse_pmt(payload);
+                }
+            } else if self.video.is_some_and(|(video, _)| video == pid) {
+                if unit_start {
+                    self.finish_pes(&mut units);
+                }
+                self.pes.extend_from_slice(payload);
+            }
+        }
+
+        // Segments end on a picture boundary
+        self.finish_pes(&mut units);
+        units
+    }
+
+    fn parse_pat(&mut self, payload: &[u8]) {
+        let Some(section) = psi_section(payload, 0x00) else {
+            return;
+        };
+        let programs = section.get(8..).unwrap_or_default();
+        for entry in programs.chunks_exact(4) {
+            // Program 0 is the network PID
+            if u16::from_be_bytes([entry[0], entry[1]]) != 0 {
+                self.pmt_pid = Some(u16::from_be_bytes([entry[2] & 0x1F, entry[3]]));
+                return;
+            }
+        }
+    }
+
+    fn parse_pmt(&mut self, payload: &[u8]) {
+        let Some(section) = psi_section(payload, 0x02) else {
+            return;
+        };
+        let Some(&[high, low]) = section.get(10..12) else {
+            return;
+        };
+        let program_info = u16::from_be_bytes([high & 0x0F, low]) as usize;
+        let mut streams = section.get(12 + program_info..).unwrap_or_default();
+
+        while streams.len() >= 5 {
+            let pid = u16::from_be_bytes([streams[1] & 0x1F, streams[2]]);
+            let es_info = u16::from_be_bytes([streams[3] & 0x0F, streams[4]]) as usize;
+            let codec = match streams[0] {
+                0x1B => Some(VideoCodec::H264),
+                0x24 => Some(VideoCodec::H265),
+                0x01 | 0x02 => Some(VideoCodec::Mpeg2),
+                _ => None,
+            };
+            if let Some(codec) = codec {
+                self.video = Some((pid, codec));
+                return;
+            }
+            streams = streams.get(5 + es_info..).unwrap_or_default();
+        }
+    }
+
+    fn finish_pes(&mut self, units: &mut Vec<AccessUnit>) {
+        let pes = std::mem::take(&mut self.pes);
+        let Some((_, codec)) = self.video else {
+            return;
+        };
+        if pes.len() < 9 || pes[..3] != [0, 0, 1] {
+            return;
+        }
+        // Caption bytes are only placed with a PTS
+        if pes[7] & 0x80 == 0 {
+            return;
+        }
+        let (Some(pts), Some(es)) = (pes.get(9..14).map(read_timestamp), pes.get(9 + pes[8] as usize..)) else {
+            return;
+        };
+
+        let mut triplets = Vec::new();
+        match codec {
+            VideoCodec::H264 | VideoCodec::H265 => {
+                for nal in nal_units(es) {
+                    let (is_sei, header_len) = match codec {
+                        VideoCodec::H264 => (nal[0] & 0x1F == 6, 1),
+                        _ => (matches!((nal[0] >> 1) & 0x3F, 39 | 40), 2),
+                    };
+                    if is_sei {
+                        parse_sei(&unescape(nal.get(header_len..).unwrap_or_default()), &mut triplets);
+                    }
+                }
+            }
+            VideoCodec::Mpeg2 => {
+                // user_data_start_code: 00 00 01 B2
+                for (i, window) in es.windows(4).enumerate() {
+                    if window == [0, 0, 1, 0xB2] {
+                        if let Some(cc) = user_data_cc(&es[i + 4..]) {
+                            parse_cc_data(cc, &mut triplets);
+                        }
+                    }
+                }
+            }
+        }
+
+        if !triplets.is_empty() {
+            units.push(AccessUnit { pts, triplets });
+        }
+    }
+}
+
+/// PSI section body with `table_id`, without its CRC
+fn psi_section(payload: &[u8], table_id: u8) -> Option<&[u8]> {
+    let pointer = *payload.first()? as usize;
+    let section = payload.get(1 + pointer..)?;
+    if *section.first()? != table_id {
+        return None;
+    }
+    let length = u16::from_be_bytes([*section.get(1)? & 0x0F, *section.get(2)?]) as usize;
+    section.get(..(3 + length).checked_sub(4)?)
+}
+
+/// 33-bit PES timestamp
+fn read_timestamp(b: &[u8]) -> u64 {
+    (((b[0] >> 1) & 0x07) as u64) << 30
+        | (b[1] as u64) << 22
+        | ((b[2] >> 1) as u64) << 15
+        | (b[3] as u64) << 7
+        | (b[4] >> 1) as u64
+}
+
+/// NAL units of an Annex B byte stream
+fn nal_units(es: &[u8]) -> Vec<&[u8]> {
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= es.len() {
+        if es[i..i + 3] == [0, 0, 1] {
+            starts.push(i + 3);
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .filter_map(|(n, &start)| {
+            let end = starts.get(n + 1).map_or(es.len(), |&next| next - 3);
+            let mut nal = &es[start..end];
+            // Zero bytes before the next start code belong to it
+            while let [rest @ .., 0] = nal {
+                nal = rest;
+            }
+            (!nal.is_empty()).then_some(nal)
+        })
+        .collect()
+}
+
+/// Remove emulation prevention bytes (00 00 03 -> 00 00)
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+    for &b in data {
+        if zeros >= 2 && b == 0x03 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+        out.push(b);
+    }
+    out
+}
+
+/// Collect cc_data from the registered user data messages of an SEI RBSP
+fn parse_sei(rbsp: &[u8], triplets: &mut Vec<CcTriplet>) {
+    let mut i = 0;
+    // 0x80 is the RBSP trailing bits
+    while i < rbsp.len() && rbsp[i] != 0x80 {
+        let Some((payload_type, next)) = read_sei_value(rbsp, i) else {
+            return;
+        };
+        let Some((size, next)) = read_sei_value(rbsp, next) else {
+            return;
+        };
+        let Some(payload) = rbsp.get(next..next + size) else {
+            return;
+        };
+        // user_data_registered_itu_t_t35, United States, ATSC
+        if payload_type == 4 {
+            if let Some(cc) = payload.strip_prefix(&[0xB5, 0x00, 0x31]).and_then(user_data_cc) {
+                parse_cc_data(cc, triplets);
+            }
+        }
+        i = next + size;
+    }
+}
+
+/// SEI payload type or size: a run of 0xFF bytes plus a final byte
+fn read_sei_value(data: &[u8], mut i: usize) -> Option<(usize, usize)> {
+    let mut value = 0;
+    loop {
+        let b = *data.get(i)?;
+        i += 1;
+        value += b as usize;
+        if b != 0xFF {
+            return Some((value, i));
+        }
+    }
+}
+
+/// cc_data() of ATSC_user_data: "GA94" followed by user_data_type_code 3
+fn user_data_cc(data: &[u8]) -> Option<&[u8]> {
+    data.strip_prefix(b"GA94\x03")
+}
+
+fn parse_cc_data(cc: &[u8], triplets: &mut Vec<CcTriplet>) {
+    let Some(&flags) = cc.first() else {
+        return;
+    };
+    // process_cc_data_flag
+    if flags & 0x40 == 0 {
+        return;
+    }
+    let count = (flags & 0x1F) as usize;
+    for t in cc.get(2..).unwrap_or_default().chunks_exact(3).take(count) {
+        // cc_valid
+        if t[0] & 0x04 != 0 {
+            triplets.push(CcTriplet { cc_type: t[0] & 0x03, data: [t[1], t[2]] });
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Cue assembly
+// ---------------------------------------------------------------------------
+
+/// Turns a caption screen's updates into cues
+struct CueBuilder {
+    /// Cue ID prefix, the track ID
+    prefix: String,
+    next_id: u64,
+    /// Cue on screen, its end time not yet known
+    open: Option<TextCue>,
+    /// When text started appearing directly on screen (roll-up, paint-on)
+    pending: Option<f64>,
+    finished: Vec<TextCue>,
+}
+
+impl CueBuilder {
+    fn new(prefix: impl Into<String>) -> Self {
+        Self { prefix: prefix.into(), next_id: 0, open: None, pending: None, finished: Vec::new() }
+    }
+
+    /// Text was written to the visible screen at `time`
+    fn mark_pending(&mut self, time: f64) {
+        self.pending.get_or_insert(time);
+    }
+
+    /// Show the text written since the last cue, from when it started
+    fn flush_pending(&mut self, text: String) {
+        if let Some(start) = self.pending {
+            self.show(text, start);
+        }
+    }
+
+    /// Show `text` from `time` on, ending the cue on screen
+    fn show(&mut self, text: String, time: f64) {
+        self.pending = None;
+        if self.open.as_ref().map_or("", |cue| cue.text.as_str()) == text {
+            return;
+        }
+        if let Some(mut cue) = self.open.take() {
+            cue.end_time = time;
+            if cue.end_time > cue.start_time {
+                self.finished.push(cue);
+            }
+        }
+        if !text.is_empty() {
+            let id = format!("{}-{}", self.prefix, self.next_id);
+            self.next_id += 1;
+            self.open = Some(TextCue::new(id, time, time, text));
+        }
+    }
+
+    /// Finished cues, plus the open one ending provisionally at `now`
+    fn take(&mut self, now: f64) -> Vec<TextCue> {
+        let mut cues = std::mem::take(&mut self.finished);
+        if let Some(open) = &self.open {
+            let mut cue = open.clone();
+            cue.end_time = now.max(cue.start_time);
+            cues.push(cue);
+        }
+        cues
+    }
+
+    /// Forget the screen; IDs keep counting so they stay unique
+    fn reset(&mut self) {
+        self.open = None;
+        self.pending = None;
+        self.finished.clear();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// CEA-608
+// ---------------------------------------------------------------------------
+
+const ROWS: usize = 15;
+const COLUMNS: usize = 32;
+const BLANK_ROW: [char; COLUMNS] = [' '; COLUMNS];
+
+type Screen = [[char; COLUMNS]; ROWS];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cea608Mode {
+    PopOn,
+    RollUp(usize),
+    PaintOn,
+    /// Text service data, not captions
+    Text,
+}
+
+struct Cea608Decoder {
+    channel: Cea608Channel,
+    mode: Option<Cea608Mode>,
+    displayed: Screen,
+    non_displayed: Screen,
+    row: usize,
+    col: usize,
+    /// Data channel selected by the last control code
+    data_channel: u8,
+    /// Control codes are sent twice; the repeat is dropped
+    last_control: Option<[u8; 2]>,
+    detected: bool,
+    cues: CueBuilder,
+}
+
+impl Cea608Decoder {
+    fn new(channel: Cea608Channel) -> Self {
+        Self {
+            channel,
+            mode: None,
+            displayed: [BLANK_ROW; ROWS],
+            non_displayed: [BLANK_ROW; ROWS],
+            row: ROWS - 1,
+            col: 0,
+            data_channel: 1,
+            last_control: None,
+            detected: false,
+            cues: CueBuilder::new(channel.id()),
+        }
+    }
+
+    fn reset(&mut self) {
+        let cues = std::mem::replace(&mut self.cues, CueBuilder::new(""));
+        let detected = self.detected;
+        *self = Self::new(self.channel);
+        self.cues = cues;
+        self.cues.reset();
+        self.detected = detected;
+    }
+
+    fn finish(&mut self, time: f64) {
+        self.cues.flush_pending(screen_text(&self.displayed));
+        self.cues.show(String::new(), time);
+    }
+
+    fn push(&mut self, field: u8, pair: [u8; 2], time: f64) {
+        if field != self.channel.field() {
+            return;
+        }
+        // Strip odd parity
+        let (b1, b2) = (pair[0] & 0x7F, pair[1] & 0x7F);
+        match b1 {
+            0x10..=0x1F => self.control(b1, b2, time),
+            // XDS, field 2 only
+            0x01..=0x0F => {}
+            _ => {
+                if b1 == 0 && b2 == 0 {
+                    return;
+                }
+                self.last_control = None;
+                if self.data_channel != self.channel.data_channel() {
+                    return;
+                }
+                for b in [b1, b2] {
+                    if b >= 0x20 {
+                        self.detected = true;
+                        self.put_char(basic_char(b), time);
+                    }
+                }
+            }
+        }
+    }
+
+    fn control(&mut self, b1: u8, b2: u8, time: f64) {
+        if self.last_control == Some([b1, b2]) {
+            self.last_control = None;
+            return;
+        }
+        self.last_control = Some([b1, b2]);
+        self.data_channel = if b1 & 0x08 != 0 { 2 } else { 1 };
+        if self.data_channel != self.channel.data_channel() {
+            return;
+        }
+        self.detected = true;
+
+        // 0x10-0x17 with the channel bit cleared
+        let code = b1 & 0x17;
+        match (code, b2) {
+            // 0x15 carries the same commands in field 2
+            (0x14 | 0x15, 0x20..=0x2F) => self.command(b2, time),
+            // Tab offsets
+            (0x17, 0x21..=0x23) => self.col = (self.col + (b2 - 0x20) as usize).min(COLUMNS),
+            // Mid-row style changes take up a space
+            (0x11, 0x20..=0x2F) => self.put_char(' ', time),
+            (0x11, 0x30..=0x3F) => self.put_char(SPECIAL_CHARS[(b2 - 0x30) as usize], time),
+            // Extended characters replace the basic fallback sent before them
+            (0x12 | 0x13, 0x20..=0x3F) => {
+                self.col = self.col.saturating_sub(1);
+                let table = if code == 0x12 { &EXTENDED_CHARS_12 } else { &EXTENDED_CHARS_13 };
+                self.put_char(table[(b2 - 0x20) as usize], time);
+            }
+            (_, 0x40..=0x7F) => self.preamble(code, b2),
+            _ => {}
+        }
+    }
+
+    fn command(&mut self, code: u8, time: f64) {
+        match code {
+            // RCL: resume caption loading
+            0x20 => self.mode = Some(Cea608Mode::PopOn),
+            // BS: backspace
+            0x21 => {
+                self.col = self.col.saturating_sub(1).min(COLUMNS - 1);
+                let (row, col) = (self.row, self.col);
+                self.memory()[row][col] = ' ';
+            }
+            // DER: delete to end of row
+            0x24 => {
+                let (row, col) = (self.row, self.col.min(COLUMNS));
+                self.memory()[row][col..].fill(' ');
+            }
+            // RU2-RU4: roll-up captions
+            0x25..=0x27 => self.roll_up((code - 0x23) as usize, time),
+            // RDC: resume direct captioning
+            0x29 => self.mode = Some(Cea608Mode::PaintOn),
+            // TR, RTD: text restart, resume text display
+            0x2A | 0x2B => self.mode = Some(Cea608Mode::Text),
+            // EDM: erase displayed memory
+            0x2C => {
+                self.cues.flush_pending(screen_text(&self.displayed));
+                self.displayed = [BLANK_ROW; ROWS];
+                self.cues.show(String::new(), time);
+            }
+            // CR: carriage return
+            0x2D => self.carriage_return(),
+            // ENM: erase non-displayed memory
+            0x2E => self.non_displayed = [BLANK_ROW; ROWS],
+            // EOC: end of caption, flip memories
+            0x2F => {
+                self.cues.flush_pending(screen_text(&self.displayed));
+                std::mem::swap(&mut self.displayed, &mut self.non_displayed);
+                self.mode = Some(Cea608Mode::PopOn);
+                self.cues.show(screen_text(&self.displayed), time);
+            }
+            // FON and reserved codes
+            _ => {}
+        }
+    }
+
+    fn roll_up(&mut self, rows: usize, time: f64) {
+        if !matches!(self.mode, Some(Cea608Mode::RollUp(_))) {
+            // Entering roll-up erases both memories
+            self.cues.flush_pending(screen_text(&self.displayed));
+            self.displayed = [BLANK_ROW; ROWS];
+            self.non_displayed = [BLANK_ROW; ROWS];
+            self.cues.show(String::new(), time);
+            self.row = ROWS - 1;
+            self.col = 0;
+        }
+        self.mode = Some(Cea608Mode::RollUp(rows));
+    }
+
+    fn carriage_return(&mut self) {
+        // Only roll-up scrolls; the rolled screen shows with the next text
+        let Some(Cea608Mode::RollUp(rows)) = self.mode else {
+            return;
+        };
+        self.cues.flush_pending(screen_text(&self.displayed));
+
+        let top = (self.row + 1).saturating_sub(rows);
+        for row in 0..self.row {
+            self.displayed[row] = if row >= top { self.displayed[row + 1] } else { BLANK_ROW };
+        }
+        self.displayed[self.row] = BLANK_ROW;
+        self.col = 0;
+    }
+
+    /// Preamble address code: row and indent
+    fn preamble(&mut self, code: u8, b2: u8) {
+        let row = match (code, b2 & 0x20 != 0) {
+            (0x11, false) => 1,
+            (0x11, true) => 2,
+            (0x12, false) => 3,
+            (0x12, true) => 4,
+            (0x15, false) => 5,
+            (0x15, true) => 6,
+            (0x16, false) => 7,
+            (0x16, true) => 8,
+            (0x17, false) => 9,
+            (0x17, true) => 10,
+            (0x10, false) => 11,
+            (0x13, false) => 12,
+            (0x13, true) => 13,
+            (0x14, false) => 14,
+            (0x14, true) => 15,
+            _ => return,
+        } - 1;
+
+        // Roll-up text moves with its base row
+        if let Some(Cea608Mode::RollUp(rows)) = self.mode {
+            if row != self.row {
+                let window: Vec<_> = (0..rows)
+                    .map(|i| self.row.checked_sub(i).map_or(BLANK_ROW, |r| self.displayed[r]))
+                    .collect();
+                self.displayed = [BLANK_ROW; ROWS];
+                for (i, line) in window.into_iter().enumerate() {
+                    if let Some(r) = row.checked_sub(i) {
+                        self.displayed[r] = line;
+                    }
+                }
+            }
+        }
+
+        self.row = row;
+        self.col = if b2 & 0x10 != 0 { ((b2 & 0x0E) >> 1) as usize * 4 } else { 0 };
+    }
+
+    /// Memory that characters are written to in the current mode
+    fn memory(&mut self) -> &mut Screen {
+        match self.mode {
+            Some(Cea608Mode::RollUp(_) | Cea608Mode::PaintOn) => &mut self.displayed,
+            _ => &mut self.non_displayed,
+        }
+    }
+
+    fn put_char(&mut self, ch: char, time: f64) {
+        let Some(mode) = self.mode.filter(|&mode| mode != Cea608Mode::Text) else {
+            return;
+        };
+        // Past the last column, characters overwrite it
+        let (row, col) = (self.row, self.col.min(COLUMNS - 1));
+        self.memory()[row][col] = ch;
+        self.col = col + 1;
+        if mode != Cea608Mode::PopOn {
+            self.cues.mark_pending(time);
+        }
+    }
+}
+
+/// Rows of a caption screen with text, trimmed
+fn screen_text(screen: &Screen) -> String {
+    screen
+        .iter()
+        .map(|row| row.iter().collect::<String>().trim().to_string())
+        .filter(|row| !row.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// CEA-608 basic characters differ from ASCII in a few places
+fn basic_char(b: u8) -> char {
+    match b {
+        0x2A => 'á',
+        0x5C => 'é',
+        0x5E => 'í',
+        0x5F => 'ó',
+        0x60 => 'ú',
+        0x7B => 'ç',
+        0x7C => '÷',
+        0x7D => 'Ñ',
+        0x7E => 'ñ',
+        0x7F => '█',
+        _ => b as char,
+    }
+}
+
+const SPECIAL_CHARS: [char; 16] = [
+    '®', '°', '½', '¿', '™', '¢', '£', '♪', 'à', ' ', 'è', 'â', 'ê', 'î', 'ô', 'û',
+];
+
+const EXTENDED_CHARS_12: [char; 32] = [
+    'Á', 'É', 'Ó', 'Ú', 'Ü', 'ü', '‘', '¡', '*', '\'', '─', '©', '℠', '•', '“', '”',
+    'À', 'Â', 'Ç', 'È', 'Ê', 'Ë', 'ë', 'Î', 'Ï', 'ï', 'Ô', 'Ù', 'ù', 'Û', '«', '»',
+];
+
+const EXTENDED_CHARS_13: [char; 32] = [
+    'Ã', 'ã', 'Í', 'Ì', 'ì', 'Ò', 'ò', 'Õ', 'õ', '{', '}', '\\', '^', '_', '|', '~',
+    'Ä', 'ä', 'Ö', 'ö', 'ß', '¥', '¤', '│', 'Å', 'å', 'Ø', 'ø', '┌', '┐', '└', '┘',
+];
+
+// ---------------------------------------------------------------------------
+// CEA-708
+// ---------------------------------------------------------------------------
+
+/// Parameter bytes of the C1 window commands 0x80-0x9F
+const C1_PARAMS: [usize; 32] = [
+    0, 0, 0, 0, 0, 0, 0, 0, // CW0-CW7
+    1, 1, 1, 1, 1, 1, 0, 0, // CLW, DSW, HDW, TGW, DLW, DLY, DLC, RST
+    2, 3, 2, 0, 0, 0, 0, 4, // SPA, SPC, SPL, reserved, SWA
+    6, 6, 6, 6, 6, 6, 6, 6, // DF0-DF7
+];
+
+#[derive(Debug, Clone, Default)]
+struct Window {
+    visible: bool,
+    rows: Vec<String>,
+    max_rows: usize,
+    row: usize,
+}
+
+impl Window {
+    fn current_row(&mut self) -> &mut String {
+        if self.rows.len() <= self.row {
+            self.rows.resize(self.row + 1, String::new());
+        }
+        &mut self.rows[self.row]
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+        self.row = 0;
+    }
+}
+
+struct Cea708Decoder {
+    service: u8,
+    packet: Vec<u8>,
+    /// Length of the DTVCC packet being collected, without its header
+    packet_len: Option<usize>,
+    windows: [Option<Window>; 8],
+    current: usize,
+    detected: bool,
+    cues: CueBuilder,
+}
+
+impl Cea708Decoder {
+    fn new(service: u8) -> Self {
+        Self {
+            service,
+            packet: Vec::new(),
+            packet_len: None,
+            windows: Default::default(),
+            current: 0,
+            detected: false,
+            cues: CueBuilder::new(format!("service{}", service)),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.packet.clear();
+        self.packet_len = None;
+        self.windows = Default::default();
+        self.current = 0;
+        self.cues.reset();
+    }
+
+    fn finish(&mut self, time: f64) {
+        self.cues.flush_pending(self.text());
+        self.cues.show(String::new(), time);
+    }
+
+    fn push(&mut self, start: bool, pair: [u8; 2], time: f64) {
+        if start {
+            // A new packet ends an incomplete one; decode what arrived
+            if self.packet_len.is_some() {
+                self.decode_packet(time);
+            }
+            let size = (pair[0] & 0x3F) as usize;
+            self.packet_len = Some(if size == 0 { 127 } else { size * 2 - 1 });
+            self.packet.clear();
+            self.packet.push(pair[1]);
+        } else if self.packet_len.is_some() {
+            self.packet.extend_from_slice(&pair);
+        } else {
+            return;
+        }
+
+        if self.packet_len.is_some_and(|len| self.packet.len() >= len) {
+            self.decode_packet(time);
+        }
+    }
+
+    fn decode_packet(&mut self, time: f64) {
+        let mut packet = std::mem::take(&mut self.packet);
+        if let Some(len) = self.packet_len.take() {
+            packet.truncate(len);
+        }
+
+        let mut i = 0;
+        while let Some(&header) = packet.get(i) {
+            // Null service block: the rest is padding
+            if header == 0 {
+                break;
+            }
+            i += 1;
+            let mut service = header >> 5;
+            let size = (header & 0x1F) as usize;
+            if service == 7 && size != 0 {
+                let Some(&extended) = packet.get(i) else {
+                    break;
+                };
+                service = extended & 0x3F;
+                i += 1;
+            }
+            let Some(block) = packet.get(i..i + size) else {
+                break;
+            };
+            i += size;
+
+            if service == self.service && !block.is_empty() {
+                self.detected = true;
+                self.service_block(block, time);
+            }
+        }
+    }
+
+    fn service_block(&mut self, block: &[u8], time: f64) {
+        let mut i = 0;
+        while let Some(&code) = block.get(i) {
+            i += 1;
+            match code {
+                // ETX: end of text
+                0x03 => self.cues.flush_pending(self.text()),
+                // BS: backspace
+                0x08 => {
+                    if let Some(window) = self.window() {
+                        window.current_row().pop();
+                    }
+                }
+                // FF: form feed, clear the window
+                0x0C => self.update_windows(time, |decoder| {
+                    if let Some(window) = decoder.window() {
+                        window.clear();
+                    }
+                }),
+                // CR: carriage return
+                0x0D => self.carriage_return(),
+                // HCR: horizontal carriage return, clear the row
+                0x0E => {
+                    if let Some(window) = self.window() {
+                        window.current_row().clear();
+                    }
+                }
+                // EXT1: extended code sets
+                0x10 => {
+                    let Some(&ext) = block.get(i) else {
+                        break;
+                    };
+                    i += 1;
+                    i += match ext {
+                        // C2: reserved, with 0-3 parameter bytes
+                        0x00..=0x1F => (ext >> 3) as usize,
+                        0x20..=0x7F => {
+                            if let Some(ch) = g2_char(ext) {
+                                self.put_char(ch, time);
+                            }
+                            0
+                        }
+                        // C3: reserved, 4-5 parameter bytes or variable length
+                        0x80..=0x87 => 4,
+                        0x88..=0x8F => 5,
+                        0x90..=0x9F => 1 + block.get(i).map_or(0, |&b| (b & 0x3F) as usize),
+                        // G3: the [CC] icon only
+                        _ => 0,
+                    };
+                }
+                // P16 and reserved C0 codes with parameters
+                0x11..=0x17 => i += 1,
+                0x18..=0x1F => i += 2,
+                0x00..=0x1F => {}
+                0x7F => self.put_char('♪', time),
+                0x20..=0x7E => self.put_char(code as char, time),
+                0x80..=0x9F => {
+                    let len = C1_PARAMS[(code - 0x80) as usize];
+                    let Some(params) = block.get(i..i + len) else {
+                        break;
+                    };
+                    i += len;
+                    self.window_command(code, params, time);
+                }
+                // G1 is Latin-1
+                0xA0..=0xFF => self.put_char(code as char, time),
+            }
+        }
+    }
+
+    fn window_command(&mut self, code: u8, params: &[u8], time: f64) {
+        let selected = |bitmap: u8| (0..8).filter(move |i| bitmap & (1 << i) != 0);
+        match code {
+            // CW0-CW7: set current window
+            0x80..=0x87 => self.current = (code - 0x80) as usize,
+            // CLW: clear windows
+            0x88 => self.update_windows(time, |decoder| {
+                for id in selected(params[0]) {
+                    if let Some(window) = &mut decoder.windows[id] {
+                        window.clear();
+                    }
+                }
+            }),
+            // DSW, HDW, TGW: display, hide or toggle windows
+            0x89..=0x8B => self.update_windows(time, |decoder| {
+                for id in selected(params[0]) {
+                    if let Some(window) = &mut decoder.windows[id] {
+                        window.visible = match code {
+                            0x89 => true,
+                            0x8A => false,
+                            _ => !window.visible,
+                        };
+                    }
+                }
+            }),
+            // DLW: delete windows
+            0x8C => self.update_windows(time, |decoder| {
+                for id in selected(params[0]) {
+                    decoder.windows[id] = None;
+                }
+            }),
+            // RST: reset
+            0x8F => self.update_windows(time, |decoder| decoder.windows = Default::default()),
+            // SPL: set pen location
+            0x92 => {
+                if let Some(window) = self.window() {
+                    window.row = (params[0] & 0x0F) as usize;
+                }
+            }
+            // DF0-DF7: define window
+            0x98..=0x9F => {
+                let id = (code - 0x98) as usize;
+                self.current = id;
+                self.update_windows(time, |decoder| {
+                    let window = decoder.windows[id].get_or_insert_with(Window::default);
+                    window.visible = params[0] & 0x20 != 0;
+                    window.max_rows = (params[3] & 0x0F) as usize + 1;
+                });
+            }
+            // DLY, DLC, SPA, SPC, SWA: delays and styling are not rendered
+            _ => {}
+        }
+    }
+
+    /// Apply a change to the windows, cueing the visible text around it
+    fn update_windows(&mut self, time: f64, update: impl FnOnce(&mut Self)) {
+        self.cues.flush_pending(self.text());
+        update(self);
+        self.cues.show(self.text(), time);
+    }
+
+    fn carriage_return(&mut self) {
+        // The scrolled window shows with the next text
+        self.cues.flush_pending(self.text());
+        if let Some(window) = self.window() {
+            window.current_row();
+            window.row += 1;
+            if window.row >= window.max_rows {
+                window.rows.remove(0);
+                window.row = window.max_rows - 1;
+            }
+        }
+    }
+
+    fn window(&mut self) -> Option<&mut Window> {
+        self.windows[self.current].as_mut()
+    }
+
+    fn put_char(&mut self, ch: char, time: f64) {
+        let Some(window) = self.window() else {
+            return;
+        };
+        window.current_row().push(ch);
+        if window.visible {
+            self.cues.mark_pending(time);
+        }
+    }
+
+    /// Text of the visible windows, in window order
+    fn text(&self) -> String {
+        self.windows
+            .iter()
+            .flatten()
+            .filter(|window| window.visible)
+            .flat_map(|window| window.rows.iter().map(|row| row.trim()))
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// CEA-708 G2 characters
+fn g2_char(b: u8) -> Option<char> {
+    Some(match b {
+        0x20 | 0x21 => ' ',
+        0x25 => '…',
+        0x2A => 'Š',
+        0x2C => 'Œ',
+        0x30 => '█',
+        0x31 => '‘',
+        0x32 => '’',
+        0x33 => '“',
+        0x34 => '”',
+        0x35 => '•',
+        0x39 => '™',
+        0x3A => 'š',
+        0x3C => 'œ',
+        0x3D => '℠',
+        0x3F => 'Ÿ',
+        0x76 => '⅛',
+        0x77 => '⅜',
+        0x78 => '⅝',
+        0x79 => '⅞',
+        0x7A => '│',
+        0x7B => '┐',
+        0x7C => '└',
+        0x7D => '─',
+        0x7E => '┘',
+        0x7F => '┌',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::captions::merge_cues;
+
+    /// Golden cues written by tests/fixtures/generate_captions.py
+    fn assert_golden(cues: &[TextCue], golden: &str) {
+        let golden: Vec<serde_json::Value> = serde_json::from_str(golden).unwrap();
+        assert_eq!(cues.len(), golden.len(), "{:#?}", cues);
+        for (cue, expected) in cues.iter().zip(&golden) {
+            assert_eq!(cue.id, expected["id"].as_str().unwrap());
+            assert_eq!(cue.text, expected["text"].as_str().unwrap());
+            assert!((cue.start_time - expected["start"].as_f64().unwrap()).abs() < 1e-5, "{:?}", cue);
+            assert!((cue.end_time - expected["end"].as_f64().unwrap()).abs() < 1e-5, "{:?}", cue);
+        }
+    }
+
+    fn extract_all(data: &[u8]) -> EmbeddedCues {
+        let mut extractor = CaptionExtractor::new();
+        let mut cues = extractor.extract(data, 0.0);
+        let flushed = extractor.flush();
+        merge_cues(&mut cues.cea608, flushed.cea608);
+        merge_cues(&mut cues.cea708, flushed.cea708);
+        cues
+    }
+
+    #[test]
+    fn test_cea608_pop_on() {
+        let cues = extract_all(include_bytes!("../tests/fixtures/cea608_popon.ts"));
+        assert_golden(&cues.cea608, include_str!("../tests/fixtures/cea608_popon.json"));
+        assert!(cues.cea708.is_empty());
+    }
+
+    #[test]
+    fn test_cea608_roll_up() {
+        let cues = extract_all(include_bytes!("../tests/fixtures/cea608_rollup.ts"));
+        assert_golden(&cues.cea608, include_str!("../tests/fixtures/cea608_rollup.json"));
+    }
+
+    #[test]
+    fn test_cea708_service_windows() {
+        let cues = extract_all(include_bytes!("../tests/fixtures/cea708.ts"));
+        assert_golden(&cues.cea708, include_str!("../tests/fixtures/cea708.json"));
+        assert!(cues.cea608.is_empty());
+    }
+
+    #[test]
+    fn test_cues_continue_across_segments() {
+        let data = include_bytes!("../tests/fixtures/cea608_popon.ts");
+        let split = data.len() / TS_PACKET_SIZE / 2 * TS_PACKET_SIZE;
+        let mut extractor = CaptionExtractor::new();
+
+        let mut cues = extractor.extract(&data[..split], 0.0);
+        // The first caption is still on screen: provisional end
+        assert_eq!(cues.cea608.len(), 1);
+        assert!(cues.cea608[0].end_time < 2.0);
+
+        // The second segment's start time is ignored; its PTS places it
+        merge_cues(&mut cues.cea608, extractor.extract(&data[split..], 99.0).cea608);
+        merge_cues(&mut cues.cea608, extractor.flush().cea608);
+        assert_golden(&cues.cea608, include_str!("../tests/fixtures/cea608_popon.json"));
+    }
+
+    #[test]
+    fn test_tracks_for_detected_formats() {
+        let url = Url::parse("https://example.com/video/720p.m3u8").unwrap();
+        let mut extractor = CaptionExtractor::new();
+        assert!(extractor.tracks(&url).is_empty());
+
+        extractor.extract(include_bytes!("../tests/fixtures/cea708.ts"), 0.0);
+        let tracks = extractor.tracks(&url);
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].id, "service1");
+        assert_eq!(tracks[0].format, TextTrackFormat::Cea708);
+        assert_eq!(tracks[0].url.fragment(), Some("service1"));
+    }
+
+    #[test]
+    fn test_pts_wrap() {
+        assert_eq!(pts_delta(PTS_WRAP - 3000, 3000), 6000);
+        assert_eq!(pts_delta(3000, PTS_WRAP - 3000), -6000);
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(&[0, 0, 3, 1, 0, 0, 3, 0, 3]), vec![0, 0, 1, 0, 0, 0, 3]);
+    }
+
+    #[test]
+    fn test_not_transport_stream() {
+        assert!(!is_transport_stream(b"WEBVTT\n"));
+        let mut extractor = CaptionExtractor::new();
+        assert!(extractor.extract(&[0u8; 376], 0.0).is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+//! Bounded on-disk cache for segments evicted from memory
+//!
+//! With [`BufferConfig::disk_cache`](crate::buffer::BufferConfig::disk_cache)
+//! set, segments the [`BufferManager`](crate::buffer::BufferManager) drops
+//! (under memory pressure, behind playback, or on a seek away) are spilled
+//! here instead of discarded. When a segment is needed again, typically
+//! after a short seek back, it is read back into memory rather than
+//! downloaded again.
+//!
+//! Segments share one data file no larger than `max_bytes`; an in-memory
+//! index maps each segment's URI and byte range to its offset and length
+//! in the file. When a segment doesn't fit, the least recently used
+//! entries are dropped until it does. The file is private to the cache and
+//! removed when the cache is dropped.
+//!
+//! Not available on `wasm32`, which has no filesystem.
+
+use crate::{types::Segment, Result};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use tracing::{debug, warn};
+use url::Url;
+
+/// Disk cache configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskCacheConfig {
+    /// Directory holding the cache file
+    pub path: PathBuf,
+    /// Maximum bytes of segment data kept on disk
+    pub max_bytes: u64,
+}
+
+impl Default for DiskCacheConfig {
+    fn default() -> Self {
+        Self {
+            path: std::env::temp_dir().join("kino-segment-cache"),
+            max_bytes: 1024 * 1024 * 1024, // 1 GB
+        }
+    }
+}
+
+/// Disk cache statistics
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiskCacheStats {
+    /// Segments currently on disk
+    pub entries: usize,
+    /// Bytes of segment data on disk
+    pub bytes_used: u64,
+    /// Segments written to disk
+    pub spilled: u64,
+    /// Segments read back into memory
+    pub hits: u64,
+    /// Lookups for segments not on disk
+    pub misses: u64,
+    /// Segments dropped from disk to make room
+    pub evicted: u64,
+}
+
+/// Identifies a segment's bytes: its URI and, for single-file streams, its
+/// byte range
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    uri: Url,
+    byte_range: Option<(u64, u64)>,
+}
+
+impl CacheKey {
+    fn new(segment: &Segment) -> Self {
+        Self {
+            uri: segment.uri.clone(),
+            byte_range: segment.byte_range.map(|r| (r.start, r.length)),
+        }
+    }
+}
+
+/// Where a segment's bytes are in the data file
+#[derive(Debug, Clone)]
+struct Entry {
+    number: u64,
+    offset: u64,
+    len: u64,
+    /// Access clock value at the last insert or read
+    last_used: u64,
+}
+
+/// Segment data spilled to disk
+#[derive(Debug)]
+pub struct DiskCache {
+    max_bytes: u64,
+    path: PathBuf,
+    file: File,
+    entries: HashMap<CacheKey, Entry>,
+    /// Occupied extents of the data file, offset to length
+    extents: BTreeMap<u64, u64>,
+    clock: u64,
+    stats: DiskCacheStats,
+}
+
+impl DiskCache {
+    /// Create an empty cache with a new data file in `config.path`
+    pub fn open(config: &DiskCacheConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.path)?;
+        let path = config.path.join(format!("segments-{}.bin", uuid::Uuid::new_v4()));
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+        debug!(path = %path.display(), max_bytes = config.max_bytes, "Opened segment disk cache");
+
+        Ok(Self {
+            max_bytes: config.max_bytes,
+            path,
+            file,
+            entries: HashMap::new(),
+            extents: BTreeMap::new(),
+            clock: 0,
+            stats: DiskCacheStats::default(),
+        })
+    }
+
+    /// Whether the segment's bytes are on disk
+    pub fn contains(&self, segment: &Segment) -> bool {
+        self.entries.contains_key(&CacheKey::new(segment))
+    }
+
+    /// Write a segment's bytes, dropping least recently used segments to
+    /// make room
+    ///
+    /// Segments already on disk are only marked as used, and segments
+    /// larger than the whole cache are skipped.
+    pub fn insert(&mut self, segment: &Segment, data: &[u8]) -> Result<()> {
+        let key = CacheKey::new(segment);
+        let len = data.len() as u64;
+        let clock = self.tick();
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            if entry.len == len {
+                entry.last_used = clock;
+                return Ok(());
+            }
+            self.remove(&key);
+        }
+        if len > self.max_bytes {
+            debug!(segment = segment.number, len, "Segment larger than the disk cache, not spilled");
+            return Ok(());
+        }
+
+        let offset = loop {
+            if let Some(offset) = self.find_gap(len) {
+                break offset;
+            }
+            self.evict_lru();
+        };
+
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(data)?;
+
+        self.extents.insert(offset, len);
+        self.entries.insert(key, Entry { number: segment.number, offset, len, last_used: clock });
+        self.stats.bytes_used += len;
+        self.stats.spilled += 1;
+        debug!(segment = segment.number, offset, len, "Segment spilled to disk");
+        Ok(())
+    }
+
+    /// Read a segment's bytes back, if they are on disk
+    ///
+    /// Entries that can't be read are dropped and count as misses.
+    pub fn get(&mut self, segment: &Segment) -> Option<Bytes> {
+        let key = CacheKey::new(segment);
+        let Some(entry) = self.entries.get(&key).cloned() else {
+            self.stats.misses += 1;
+            return None;
+        };
+
+        let mut data = vec![0; entry.len as usize];
+        let read = self
+            .file
+            .seek(SeekFrom::Start(entry.offset))
+            .and_then(|_| self.file.read_exact(&mut data));
+        if let Err(e) = read {
+            warn!(segment = entry.number, error = %e, "Failed to read segment from disk cache");
+            self.remove(&key);
+            self.stats.misses += 1;
+            return None;
+        }
+
+        let clock = self.tick();
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.last_used = clock;
+        }
+        self.stats.hits += 1;
+        Some(Bytes::from(data))
+    }
+
+    /// Cache statistics
+    pub fn stats(&self) -> DiskCacheStats {
+        DiskCacheStats {
+            entries: self.entries.len(),
+            ..self.stats.clone()
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// First free offset with room for `len` bytes within `max_bytes`
+    fn find_gap(&self, len: u64) -> Option<u64> {
+        let mut free_from = 0;
+        for (&offset, &used) in &self.extents {
+            if offset - free_from >= len {
+                return Some(free_from);
+            }
+            free_from = offset + used;
+        }
+        (self.max_bytes - free_from >= len).then_some(free_from)
+    }
+
+    /// Drop the least recently used segment
+    fn evict_lru(&mut self) {
+        let Some(key) = self.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone()) else {
+            return;
+        };
+        if let Some(entry) = self.remove(&key) {
+            self.stats.evicted += 1;
+            debug!(segment = entry.number, len = entry.len, "Evicted segment from disk cache");
+        }
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.extents.remove(&entry.offset);
+        self.stats.bytes_used -= entry.len;
+        Some(entry)
+    }
+}
+
+impl Drop for DiskCache {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn segment(num: u64) -> Segment {
+        Segment {
+            number: num,
+            uri: Url::parse(&format!("https://example.com/seg{}.ts", num)).unwrap(),
+            duration: Duration::from_secs(4),
+            byte_range: None,
+            encryption: None,
+            discontinuity_sequence: 0,
+            program_date_time: None,
+        }
+    }
+
+    fn open(max_bytes: u64) -> DiskCache {
+        let path = std::env::temp_dir().join(format!("kino-disk-cache-{}", uuid::Uuid::new_v4()));
+        DiskCache::open(&DiskCacheConfig { path, max_bytes }).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut cache = open(1024);
+        cache.insert(&segment(1), &[1; 100]).unwrap();
+        cache.insert(&segment(2), &[2; 200]).unwrap();
+
+        assert_eq!(cache.get(&segment(2)).unwrap(), Bytes::from(vec![2; 200]));
+        assert_eq!(cache.get(&segment(1)).unwrap(), Bytes::from(vec![1; 100]));
+        assert!(cache.get(&segment(3)).is_none());
+
+        // Re-spilling a cached segment writes nothing
+        cache.insert(&segment(1), &[1; 100]).unwrap();
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.bytes_used, stats.spilled), (2, 300, 2));
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+    }
+
+    #[test]
+    fn test_lru_eviction_and_space_reuse() {
+        let mut cache = open(300);
+        for num in 0..3 {
+            cache.insert(&segment(num), &[num as u8; 100]).unwrap();
+        }
+        // Segment 0 is now the most recently used
+        assert!(cache.get(&segment(0)).is_some());
+
+        cache.insert(&segment(3), &[3; 100]).unwrap();
+        assert!(!cache.contains(&segment(1)));
+        assert_eq!(cache.get(&segment(3)).unwrap(), Bytes::from(vec![3; 100]));
+        assert_eq!(cache.get(&segment(0)).unwrap(), Bytes::from(vec![0; 100]));
+
+        // Two neighbouring segments go to fit a bigger one
+        cache.insert(&segment(4), &[4; 200]).unwrap();
+        assert_eq!(cache.get(&segment(4)).unwrap(), Bytes::from(vec![4; 200]));
+        let stats = cache.stats();
+        assert!(stats.bytes_used <= 300);
+        assert_eq!(stats.evicted, 3);
+
+        // Too big for the whole cache
+        cache.insert(&segment(5), &[5; 400]).unwrap();
+        assert!(!cache.contains(&segment(5)));
+    }
+
+    #[test]
+    fn test_file_removed_on_drop() {
+        let cache = open(1024);
+        let path = cache.path.clone();
+        assert!(path.exists());
+        drop(cache);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,1182 @@
+//! Offline downloads of HLS VOD content
+//!
+//! [`DownloadManager`] saves one rendition of a VOD stream to a local
+//! directory, together with the alternate audio tracks of its group and the
+//! subtitle tracks. Playlists are rewritten to reference the saved files and
+//! a `master.m3u8` is written once everything is on disk, so the download
+//! plays offline by passing [`OfflineContent::manifest_url`] to
+//! [`PlayerSession::load`](crate::PlayerSession::load).
+//!
+//! Files download in the background, a few at a time, and each one is
+//! checked against its Content-Length and the byte ranges that reference it.
+//! Completed files are recorded in `download.json` in the destination, so
+//! starting the same download again only fetches what is missing.
+//!
+//! Live streams, DASH and encrypted content (AES-128 or DRM) are rejected.
+//!
+//! ```no_run
+//! use kino_core::download::{DownloadConfig, DownloadManager, RenditionPolicy};
+//! # async fn example(url: url::Url) -> kino_core::Result<()> {
+//! let manager = DownloadManager::new(DownloadConfig {
+//!     policy: RenditionPolicy { max_height: Some(720), ..Default::default() },
+//!     ..Default::default()
+//! });
+//! let handle = manager.start(&url, "/var/lib/kino/offline/movie").await?;
+//!
+//! let mut progress = handle.subscribe();
+//! tokio::spawn(async move {
+//!     while progress.changed().await.is_ok() {
+//!         let p = progress.borrow().clone();
+//!         println!("{:.0}% ({} bytes, eta {:?})", p.percent, p.bytes_downloaded, p.eta);
+//!     }
+//! });
+//!
+//! if let Some(content) = handle.wait().await? {
+//!     let session = kino_core::PlayerSession::new(Default::default());
+//!     session.load(&content.manifest_url).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::error::{DrmErrorKind, Error, ManifestErrorKind, Result};
+use crate::http::{self, HttpFetcher, ReqwestFetcher};
+use crate::integrity::IntegrityFailure;
+use crate::manifest::{parse_attribute_list, HlsParser, VariantPlaylist};
+use crate::types::{Rendition, TextTrackKind};
+
+/// Master playlist written once a download completes
+pub const MASTER_PLAYLIST: &str = "master.m3u8";
+
+/// State file recording the completed files of a download
+pub const STATE_FILE: &str = "download.json";
+
+/// Directory holding the video rendition
+const VIDEO_DIR: &str = "video";
+
+/// Name of the rewritten media playlist in each directory
+const MEDIA_PLAYLIST: &str = "index.m3u8";
+
+/// Base delay between retries, multiplied by the attempt number
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// How [`DownloadManager`] picks the rendition to save
+///
+/// The highest-bandwidth rendition within every limit is chosen.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenditionPolicy {
+    /// Maximum height in pixels; renditions without a declared resolution pass
+    pub max_height: Option<u32>,
+    /// Maximum estimated size in bytes, from the rendition's bandwidth and
+    /// the content duration
+    pub max_bytes: Option<u64>,
+}
+
+impl RenditionPolicy {
+    /// Highest-bandwidth rendition within `max_height`, then `max_bytes`
+    /// given the content duration
+    pub fn select<'a>(&self, renditions: &'a [Rendition], duration: Duration) -> Option<&'a Rendition> {
+        renditions
+            .iter()
+            .filter(|r| self.fits_height(r) && self.fits_size(r, duration))
+            .max_by_key(|r| r.bandwidth)
+    }
+
+    fn fits_height(&self, rendition: &Rendition) -> bool {
+        match (self.max_height, rendition.resolution) {
+            (Some(max), Some(resolution)) => resolution.height <= max,
+            _ => true,
+        }
+    }
+
+    fn fits_size(&self, rendition: &Rendition, duration: Duration) -> bool {
+        self.max_bytes.is_none_or(|max| estimated_bytes(rendition, duration) <= max)
+    }
+}
+
+/// Size of a rendition estimated from its bandwidth
+fn estimated_bytes(rendition: &Rendition, duration: Duration) -> u64 {
+    (rendition.bandwidth as f64 / 8.0 * duration.as_secs_f64()) as u64
+}
+
+/// Download manager configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadConfig {
+    /// Which rendition to save
+    pub policy: RenditionPolicy,
+    /// Files downloaded at the same time
+    pub max_parallel: usize,
+    /// Retries per file after a retryable error
+    pub max_retries: u32,
+    /// Save subtitle tracks alongside the video
+    pub include_subtitles: bool,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            policy: RenditionPolicy::default(),
+            max_parallel: 4,
+            max_retries: 3,
+            include_subtitles: true,
+        }
+    }
+}
+
+/// State of a download
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DownloadStatus {
+    Downloading,
+    /// No new files are started; files in flight still finish
+    Paused,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+impl DownloadStatus {
+    /// Whether workers should stop
+    fn is_stopped(self) -> bool {
+        matches!(self, DownloadStatus::Cancelled | DownloadStatus::Failed)
+    }
+}
+
+/// Progress of a download, published after every file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DownloadProgress {
+    pub status: DownloadStatus,
+    /// Files saved, including those from earlier runs
+    pub completed_files: usize,
+    pub total_files: usize,
+    /// Bytes saved, including those from earlier runs
+    pub bytes_downloaded: u64,
+    /// Share of files saved, 0-100
+    pub percent: f64,
+    /// Estimated time to completion while downloading
+    pub eta: Option<Duration>,
+}
+
+/// A completed download
+#[derive(Debug, Clone)]
+pub struct OfflineContent {
+    /// `file://` URL of the local master playlist
+    pub manifest_url: Url,
+    /// Directory holding the download
+    pub directory: PathBuf,
+    /// The rendition that was saved
+    pub rendition: Rendition,
+    /// Files saved, excluding playlists
+    pub files: usize,
+    /// Total size of those files
+    pub bytes: u64,
+}
+
+/// Downloads HLS VOD content for offline playback
+pub struct DownloadManager {
+    fetcher: Arc<dyn HttpFetcher>,
+    config: DownloadConfig,
+}
+
+impl DownloadManager {
+    pub fn new(config: DownloadConfig) -> Self {
+        Self::with_fetcher(http::default_fetcher(Duration::from_secs(60)), config)
+    }
+
+    pub fn with_client(client: reqwest::Client, config: DownloadConfig) -> Self {
+        Self::with_fetcher(Arc::new(ReqwestFetcher::with_client(client)), config)
+    }
+
+    /// Create a manager that downloads with `fetcher`
+    pub fn with_fetcher(fetcher: Arc<dyn HttpFetcher>, config: DownloadConfig) -> Self {
+        Self { fetcher, config }
+    }
+
+    /// Start downloading `manifest_url` into `destination`
+    ///
+    /// Playlists are fetched and checked before this returns, so unsupported
+    /// or protected content fails here. The files themselves download in the
+    /// background; dropping the handle does not stop them.
+    pub async fn start(&self, manifest_url: &Url, destination: impl AsRef<Path>) -> Result<DownloadHandle> {
+        let plan = self.plan(manifest_url).await?;
+
+        tokio::fs::create_dir_all(destination.as_ref()).await?;
+        let destination = tokio::fs::canonicalize(destination.as_ref()).await?;
+
+        // The master playlist marks a complete download, so a stale one
+        // must not survive while files are replaced
+        match tokio::fs::remove_file(destination.join(MASTER_PLAYLIST)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        for (path, text) in &plan.playlists {
+            let path = destination.join(path);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(path, text).await?;
+        }
+        for resource in &plan.resources {
+            if let Some(parent) = destination.join(&resource.path).parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+        }
+
+        let saved = SavedState::load(&destination, manifest_url, &plan.rendition.uri).await;
+        let pending: VecDeque<Resource> = plan
+            .resources
+            .iter()
+            .filter(|r| !saved.completed.contains_key(&r.path))
+            .cloned()
+            .collect();
+        let tracker = Tracker {
+            total: plan.resources.len(),
+            completed: plan.resources.len() - pending.len(),
+            bytes: saved.completed.values().sum(),
+            completed_this_run: 0,
+            active: Duration::ZERO,
+            running_since: Some(Instant::now()),
+        };
+        info!(
+            url = %manifest_url,
+            rendition = %plan.rendition.id,
+            files = tracker.total,
+            already_saved = tracker.completed,
+            "Starting download"
+        );
+
+        let progress = tracker.progress(DownloadStatus::Downloading);
+        let shared = Arc::new(Shared {
+            fetcher: self.fetcher.clone(),
+            destination,
+            max_retries: self.config.max_retries,
+            queue: Mutex::new(pending),
+            saved: tokio::sync::Mutex::new(saved),
+            tracker: Mutex::new(tracker),
+            status: watch::channel(DownloadStatus::Downloading).0,
+            progress: watch::channel(progress).0,
+        });
+
+        let workers = self.config.max_parallel.max(1);
+        let task = tokio::spawn(run(shared.clone(), workers, plan));
+        Ok(DownloadHandle { shared, task })
+    }
+
+    /// Fetch and rewrite every playlist, and list the files to download
+    async fn plan(&self, url: &Url) -> Result<Plan> {
+        let parser = HlsParser::with_fetcher(self.fetcher.clone());
+        let content = parser.fetch_playlist(url).await?;
+        if !content.trim_start().starts_with("#EXTM3U") {
+            return Err(Error::manifest(
+                ManifestErrorKind::Unsupported,
+                "Offline downloads support HLS only",
+            ));
+        }
+        reject_keys(&content)?;
+
+        // A media playlist as the entry point is saved as the master
+        // playlist itself, pointing into the video directory
+        if !content.contains("#EXT-X-STREAM-INF") {
+            let playlist = fetched_media(&parser, &content, url)?;
+            let rendition = Rendition {
+                id: "default".to_string(),
+                bandwidth: 0,
+                resolution: None,
+                frame_rate: None,
+                video_codec: None,
+                audio_codec: None,
+                uri: url.clone(),
+                hdr: None,
+                language: None,
+                name: None,
+                audio_group: None,
+            };
+            let local = localize_media(&content, url, &playlist, VIDEO_DIR, &format!("{}/", VIDEO_DIR))?;
+            return Ok(Plan {
+                rendition,
+                master: local.text,
+                playlists: Vec::new(),
+                resources: local.resources,
+            });
+        }
+
+        let manifest = parser.parse_master(&content, url)?;
+        if !manifest.drm_init_data.is_empty() {
+            return Err(protected_content());
+        }
+
+        // The highest rendition within the height limit tells the duration
+        // needed to estimate sizes
+        let policy = &self.config.policy;
+        let top = manifest
+            .renditions
+            .iter()
+            .filter(|r| policy.fits_height(r))
+            .max_by_key(|r| r.bandwidth)
+            .ok_or(Error::NoSuitableRendition)?;
+        let (mut text, mut playlist) = fetch_media(&parser, &top.uri).await?;
+        let duration = playlist.duration.unwrap_or_default();
+        let rendition = policy
+            .select(&manifest.renditions, duration)
+            .ok_or(Error::NoSuitableRendition)?
+            .clone();
+        if rendition.uri != top.uri {
+            (text, playlist) = fetch_media(&parser, &rendition.uri).await?;
+        }
+        debug!(
+            rendition = %rendition.id,
+            bandwidth = rendition.bandwidth,
+            estimated_bytes = estimated_bytes(&rendition, duration),
+            "Rendition selected for download"
+        );
+
+        let video = localize_media(&text, &rendition.uri, &playlist, VIDEO_DIR, "")?;
+        let mut playlists = vec![(format!("{}/{}", VIDEO_DIR, MEDIA_PLAYLIST), video.text)];
+        let mut resources = video.resources;
+        let mut media = HashMap::new();
+
+        let audio = manifest
+            .audio_tracks
+            .iter()
+            .filter(|t| t.group_id.is_some() && t.group_id == rendition.audio_group)
+            .filter_map(|t| t.url.as_ref());
+        let subtitles = manifest
+            .text_tracks
+            .iter()
+            .filter(|t| self.config.include_subtitles && t.kind == TextTrackKind::Subtitles)
+            .map(|t| &t.url);
+        let tracks = audio
+            .enumerate()
+            .map(|(i, url)| (format!("audio_{}", i), url))
+            .chain(subtitles.enumerate().map(|(i, url)| (format!("subtitles_{}", i), url)));
+
+        for (dir, track_url) in tracks {
+            let (text, playlist) = fetch_media(&parser, track_url).await?;
+            let local = localize_media(&text, track_url, &playlist, &dir, "")?;
+            let path = format!("{}/{}", dir, MEDIA_PLAYLIST);
+            media.insert(track_url.clone(), path.clone());
+            playlists.push((path, local.text));
+            resources.extend(local.resources);
+        }
+
+        let master = localize_master(&content, url, &rendition, &media)?;
+        Ok(Plan { rendition, master, playlists, resources })
+    }
+}
+
+impl Default for DownloadManager {
+    fn default() -> Self {
+        Self::new(DownloadConfig::default())
+    }
+}
+
+/// Controls a running download
+pub struct DownloadHandle {
+    shared: Arc<Shared>,
+    task: JoinHandle<Result<Option<OfflineContent>>>,
+}
+
+impl DownloadHandle {
+    /// Stop starting new files; files in flight still finish
+    pub fn pause(&self) {
+        self.shared.transition(DownloadStatus::Downloading, DownloadStatus::Paused);
+    }
+
+    /// Continue a paused download
+    pub fn resume(&self) {
+        self.shared.transition(DownloadStatus::Paused, DownloadStatus::Downloading);
+    }
+
+    /// Stop the download, abandoning files in flight
+    ///
+    /// Saved files and the state file are kept, so starting the download
+    /// again resumes it.
+    pub fn cancel(&self) {
+        self.shared.transition(DownloadStatus::Downloading, DownloadStatus::Cancelled);
+        self.shared.transition(DownloadStatus::Paused, DownloadStatus::Cancelled);
+    }
+
+    /// Current progress
+    pub fn progress(&self) -> DownloadProgress {
+        self.shared.progress.borrow().clone()
+    }
+
+    /// Subscribe to progress updates
+    pub fn subscribe(&self) -> watch::Receiver<DownloadProgress> {
+        self.shared.progress.subscribe()
+    }
+
+    /// Wait for the download to finish, returning `None` if it was cancelled
+    pub async fn wait(self) -> Result<Option<OfflineContent>> {
+        self.task
+            .await
+            .map_err(|e| Error::Internal(format!("Download task failed: {}", e)))?
+    }
+}
+
+/// The result of planning a download
+struct Plan {
+    rendition: Rendition,
+    /// Master playlist, written last
+    master: String,
+    /// Media playlists by path in the destination
+    playlists: Vec<(String, String)>,
+    resources: Vec<Resource>,
+}
+
+/// A file to download
+#[derive(Debug, Clone)]
+struct Resource {
+    url: Url,
+    /// Path in the destination, `/`-separated
+    path: String,
+    /// Smallest valid size, from the byte ranges that reference the file
+    min_size: u64,
+}
+
+/// A media playlist rewritten to reference local files
+struct LocalPlaylist {
+    text: String,
+    resources: Vec<Resource>,
+}
+
+/// Completed files of a download, stored in [`STATE_FILE`]
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedState {
+    manifest_url: Url,
+    rendition_url: Url,
+    /// Sizes by path in the destination
+    completed: BTreeMap<String, u64>,
+}
+
+impl SavedState {
+    /// Load the state of an earlier run of the same download, keeping the
+    /// files that are still on disk at their recorded size
+    async fn load(destination: &Path, manifest_url: &Url, rendition_url: &Url) -> Self {
+        let previous = tokio::fs::read(destination.join(STATE_FILE))
+            .await
+            .ok()
+            .and_then(|data| serde_json::from_slice::<SavedState>(&data).ok())
+            .filter(|s| s.manifest_url == *manifest_url && s.rendition_url == *rendition_url);
+
+        let mut completed = BTreeMap::new();
+        for (path, size) in previous.map(|s| s.completed).unwrap_or_default() {
+            let on_disk = tokio::fs::metadata(destination.join(&path)).await;
+            if on_disk.is_ok_and(|m| m.len() == size) {
+                completed.insert(path, size);
+            }
+        }
+
+        Self {
+            manifest_url: manifest_url.clone(),
+            rendition_url: rendition_url.clone(),
+            completed,
+        }
+    }
+
+    /// Write the state, replacing the previous file atomically
+    async fn store(&self, destination: &Path) -> Result<()> {
+        let data = serde_json::to_vec_pretty(self).map_err(std::io::Error::from)?;
+        let partial = destination.join(format!("{}.part", STATE_FILE));
+        tokio::fs::write(&partial, data).await?;
+        tokio::fs::rename(&partial, destination.join(STATE_FILE)).await?;
+        Ok(())
+    }
+}
+
+/// Counts behind [`DownloadProgress`]
+struct Tracker {
+    total: usize,
+    completed: usize,
+    bytes: u64,
+    completed_this_run: usize,
+    /// Time spent downloading in this run, excluding pauses
+    active: Duration,
+    running_since: Option<Instant>,
+}
+
+impl Tracker {
+    fn set_running(&mut self, running: bool) {
+        match (running, self.running_since) {
+            (true, None) => self.running_since = Some(Instant::now()),
+            (false, Some(since)) => {
+                self.active += since.elapsed();
+                self.running_since = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn progress(&self, status: DownloadStatus) -> DownloadProgress {
+        let remaining = self.total - self.completed;
+        let active = self.active + self.running_since.map_or(Duration::ZERO, |since| since.elapsed());
+        let eta = (status == DownloadStatus::Downloading && self.completed_this_run > 0)
+            .then(|| active.mul_f64(remaining as f64 / self.completed_this_run as f64));
+        let percent = match self.total {
+            0 => 100.0,
+            total => self.completed as f64 * 100.0 / total as f64,
+        };
+
+        DownloadProgress {
+            status,
+            completed_files: self.completed,
+            total_files: self.total,
+            bytes_downloaded: self.bytes,
+            percent,
+            eta,
+        }
+    }
+}
+
+/// State shared by the handle and the workers
+struct Shared {
+    fetcher: Arc<dyn HttpFetcher>,
+    destination: PathBuf,
+    max_retries: u32,
+    queue: Mutex<VecDeque<Resource>>,
+    saved: tokio::sync::Mutex<SavedState>,
+    tracker: Mutex<Tracker>,
+    status: watch::Sender<DownloadStatus>,
+    progress: watch::Sender<DownloadProgress>,
+}
+
+impl Shared {
+    /// Move from `from` to `to`, doing nothing in any other state
+    fn transition(&self, from: DownloadStatus, to: DownloadStatus) {
+        let changed = self.status.send_if_modified(|status| {
+            let matches = *status == from;
+            if matches {
+                *status = to;
+            }
+            matches
+        });
+        if changed {
+            self.tracker.lock().unwrap().set_running(to == DownloadStatus::Downloading);
+            self.publish();
+        }
+    }
+
+    fn publish(&self) {
+        let status = *self.status.borrow();
+        let progress = self.tracker.lock().unwrap().progress(status);
+        self.progress.send_replace(progress);
+    }
+
+    /// Download a file, retrying transient failures
+    async fn fetch(&self, resource: &Resource) -> Result<Bytes> {
+        let mut attempts = 0;
+        loop {
+            match self.fetch_once(resource).await {
+                Err(err) if err.is_retryable() && attempts < self.max_retries => {
+                    attempts += 1;
+                    warn!(url = %resource.url, error = %err, attempts, "Retrying download");
+                    tokio::time::sleep(RETRY_DELAY * attempts).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn fetch_once(&self, resource: &Resource) -> Result<Bytes> {
+        let url = &resource.url;
+        let response = self
+            .fetcher
+            .get(url, &HashMap::new(), None)
+            .await?
+            .error_for_status()?;
+        let content_length = response.content_length();
+        let data = response.bytes().await?;
+
+        verify_size(url, content_length, resource.min_size, data.len() as u64)?;
+        Ok(data)
+    }
+
+    /// Write a downloaded file and record it as completed
+    async fn save(&self, resource: &Resource, data: Bytes) -> Result<()> {
+        let path = self.destination.join(&resource.path);
+        let partial = self.destination.join(format!("{}.part", resource.path));
+        tokio::fs::write(&partial, &data).await?;
+        tokio::fs::rename(&partial, &path).await?;
+
+        let size = data.len() as u64;
+        {
+            let mut saved = self.saved.lock().await;
+            saved.completed.insert(resource.path.clone(), size);
+            saved.store(&self.destination).await?;
+        }
+        {
+            let mut tracker = self.tracker.lock().unwrap();
+            tracker.completed += 1;
+            tracker.completed_this_run += 1;
+            tracker.bytes += size;
+        }
+        debug!(path = %resource.path, bytes = size, "File saved");
+        self.publish();
+        Ok(())
+    }
+}
+
+/// Run the workers and write the master playlist once every file is saved
+async fn run(shared: Arc<Shared>, workers: usize, plan: Plan) -> Result<Option<OfflineContent>> {
+    let mut set = JoinSet::new();
+    for _ in 0..workers {
+        set.spawn(worker(shared.clone()));
+    }
+
+    let mut failure = None;
+    while let Some(joined) = set.join_next().await {
+        let result = joined.unwrap_or_else(|e| Err(Error::Internal(format!("Download worker failed: {}", e))));
+        if let Err(err) = result {
+            if failure.is_none() {
+                // Stop the other workers
+                shared.status.send_replace(DownloadStatus::Failed);
+                failure = Some(err);
+            }
+        }
+    }
+
+    if let Some(err) = failure {
+        warn!(error = %err, "Download failed");
+        shared.publish();
+        return Err(err);
+    }
+    if *shared.status.borrow() == DownloadStatus::Cancelled {
+        info!("Download cancelled");
+        return Ok(None);
+    }
+
+    let master = shared.destination.join(MASTER_PLAYLIST);
+    tokio::fs::write(&master, &plan.master).await?;
+    let manifest_url = Url::from_file_path(&master)
+        .map_err(|_| Error::InvalidConfig(format!("Invalid download path: {}", master.display())))?;
+
+    shared.status.send_replace(DownloadStatus::Completed);
+    shared.tracker.lock().unwrap().set_running(false);
+    shared.publish();
+
+    let (files, bytes) = {
+        let tracker = shared.tracker.lock().unwrap();
+        (tracker.total, tracker.bytes)
+    };
+    info!(path = %master.display(), files, bytes, "Download complete");
+    Ok(Some(OfflineContent {
+        manifest_url,
+        directory: shared.destination.clone(),
+        rendition: plan.rendition,
+        files,
+        bytes,
+    }))
+}
+
+/// Download queued files until the queue is empty or the download stops
+async fn worker(shared: Arc<Shared>) -> Result<()> {
+    let mut status = shared.status.subscribe();
+    loop {
+        // Pauses take effect between files
+        let current = match status.wait_for(|s| *s != DownloadStatus::Paused).await {
+            Ok(current) => *current,
+            Err(_) => return Ok(()),
+        };
+        if current != DownloadStatus::Downloading {
+            return Ok(());
+        }
+        let Some(resource) = shared.queue.lock().unwrap().pop_front() else {
+            return Ok(());
+        };
+
+        let data = tokio::select! {
+            data = shared.fetch(&resource) => data?,
+            _ = status.wait_for(|s| s.is_stopped()) => return Ok(()),
+        };
+        shared.save(&resource, data).await?;
+    }
+}
+
+/// Check a downloaded file's size against its Content-Length and byte ranges
+fn verify_size(url: &Url, content_length: Option<u64>, min_size: u64, actual: u64) -> Result<()> {
+    let failure = match content_length {
+        Some(expected) if expected != actual => IntegrityFailure::ContentLength { expected, actual },
+        _ if actual < min_size => IntegrityFailure::ByteRange { expected: min_size, actual },
+        _ => return Ok(()),
+    };
+    Err(Error::SegmentIntegrity { url: url.clone(), failure: Box::new(failure) })
+}
+
+/// Error for encrypted or DRM-protected content
+fn protected_content() -> Error {
+    Error::drm(
+        None,
+        DrmErrorKind::Unsupported,
+        "Encrypted content can't be downloaded for offline playback",
+    )
+}
+
+/// Reject playlists with EXT-X-KEY or EXT-X-SESSION-KEY tags that encrypt
+fn reject_keys(content: &str) -> Result<()> {
+    let encrypted = content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("#EXT-X-KEY:").or_else(|| line.trim().strip_prefix("#EXT-X-SESSION-KEY:")))
+        .any(|attrs| parse_attribute_list(attrs).get("METHOD").is_none_or(|m| m != "NONE"));
+    if encrypted {
+        return Err(protected_content());
+    }
+    Ok(())
+}
+
+/// Fetch a media playlist, checking that it can be downloaded
+async fn fetch_media(parser: &HlsParser, url: &Url) -> Result<(String, VariantPlaylist)> {
+    let content = parser.fetch_playlist(url).await?;
+    reject_keys(&content)?;
+    let playlist = fetched_media(parser, &content, url)?;
+    Ok((content, playlist))
+}
+
+/// Parse a fetched media playlist, rejecting live and encrypted ones
+fn fetched_media(parser: &HlsParser, content: &str, url: &Url) -> Result<VariantPlaylist> {
+    let playlist = parser.parse_media(content, url)?;
+    if playlist.is_live {
+        return Err(Error::manifest(
+            ManifestErrorKind::Unsupported,
+            "Live streams can't be downloaded",
+        ));
+    }
+    if playlist.segments.iter().any(|s| s.encryption.is_some()) {
+        return Err(protected_content());
+    }
+    Ok(playlist)
+}
+
+/// Rewrite a media playlist to reference files saved in `dir`
+///
+/// Each distinct URI becomes one file, so byte-range segments sharing a
+/// resource are downloaded once and keep their EXT-X-BYTERANGE tags.
+/// References are prefixed with `link_prefix`, relative to where the
+/// playlist is written.
+fn localize_media(content: &str, base: &Url, playlist: &VariantPlaylist, dir: &str, link_prefix: &str) -> Result<LocalPlaylist> {
+    let mut names: HashMap<Url, String> = HashMap::new();
+    let mut resources = Vec::new();
+    let mut local = |uri: &str| -> Result<String> {
+        let url = resolve(base, uri)?;
+        let name = names.entry(url.clone()).or_insert_with(|| {
+            let name = format!("{:05}.{}", resources.len(), extension(&url));
+            resources.push(Resource { url, path: format!("{}/{}", dir, name), min_size: 0 });
+            name
+        });
+        Ok(format!("{}{}", link_prefix, name))
+    };
+
+    let mut lines = Vec::new();
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if line.starts_with('#') {
+            match tag_uri(line) {
+                Some(uri) => lines.push(with_uri(line, &uri, &local(&uri)?)),
+                None => lines.push(line.to_string()),
+            }
+        } else {
+            lines.push(local(line)?);
+        }
+    }
+
+    for segment in &playlist.segments {
+        let Some(range) = segment.byte_range else {
+            continue;
+        };
+        if let Some(resource) = resources.iter_mut().find(|r| r.url == segment.uri) {
+            resource.min_size = resource.min_size.max(range.start + range.length);
+        }
+    }
+
+    Ok(LocalPlaylist { text: lines.join("\n") + "\n", resources })
+}
+
+/// Rewrite a master playlist down to `rendition` and the media playlists
+/// in `media`, keyed by their original URL
+///
+/// Other variants, I-frame streams, content steering and alternate media
+/// that was not saved are dropped.
+fn localize_master(content: &str, base: &Url, rendition: &Rendition, media: &HashMap<Url, String>) -> Result<String> {
+    let mut lines = Vec::new();
+    let mut variant_tag = None;
+    let mut kept_variant = false;
+
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        // The URI line following EXT-X-STREAM-INF
+        if let Some(tag) = variant_tag.take() {
+            if !kept_variant && resolve(base, line)? == rendition.uri {
+                lines.push(tag);
+                lines.push(format!("{}/{}", VIDEO_DIR, MEDIA_PLAYLIST));
+                kept_variant = true;
+            }
+            continue;
+        }
+
+        if line.starts_with("#EXT-X-STREAM-INF") {
+            variant_tag = Some(line.to_string());
+        } else if line.starts_with("#EXT-X-I-FRAME-STREAM-INF") || line.starts_with("#EXT-X-CONTENT-STEERING") {
+            continue;
+        } else if line.starts_with("#EXT-X-MEDIA:") {
+            // Media without a URI, such as closed captions, stays as is
+            match tag_uri(line) {
+                Some(uri) => {
+                    if let Some(path) = media.get(&resolve(base, &uri)?) {
+                        lines.push(with_uri(line, &uri, path));
+                    }
+                }
+                None => lines.push(line.to_string()),
+            }
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+
+    Ok(lines.join("\n") + "\n")
+}
+
+/// The URI attribute of a tag line
+fn tag_uri(line: &str) -> Option<String> {
+    let (_, attrs) = line.split_once(':')?;
+    parse_attribute_list(attrs).remove("URI")
+}
+
+/// Replace the URI attribute of a tag line
+fn with_uri(line: &str, uri: &str, local: &str) -> String {
+    line.replacen(&format!("URI=\"{}\"", uri), &format!("URI=\"{}\"", local), 1)
+}
+
+fn resolve(base: &Url, uri: &str) -> Result<Url> {
+    base.join(uri)
+        .map_err(|e| Error::invalid_manifest(format!("Invalid URI '{}': {}", uri, e)))
+}
+
+/// File extension for a saved resource, from its URL
+fn extension(url: &Url) -> &str {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext)
+        .filter(|ext| !ext.is_empty() && ext.len() <= 5 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or("bin")
+}
+
+/// Path of a `file://` URL, for content saved by [`DownloadManager`]
+pub(crate) fn local_path(url: &Url) -> Option<PathBuf> {
+    if url.scheme() != "file" {
+        return None;
+    }
+    url.to_file_path().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::ManifestParser;
+    use crate::types::Resolution;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn rendition(id: &str, bandwidth: u64, height: u32) -> Rendition {
+        Rendition {
+            id: id.to_string(),
+            bandwidth,
+            resolution: Some(Resolution { width: height * 16 / 9, height }),
+            frame_rate: None,
+            video_codec: None,
+            audio_codec: None,
+            uri: Url::parse(&format!("https://cdn.example.com/{}.m3u8", id)).unwrap(),
+            hdr: None,
+            language: None,
+            name: None,
+            audio_group: None,
+        }
+    }
+
+    const MASTER: &str = "#EXTM3U
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",NAME=\"English\",LANGUAGE=\"en\",DEFAULT=YES,URI=\"audio/en.m3u8\"
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"hd\",NAME=\"English\",LANGUAGE=\"en\",URI=\"audio/hd.m3u8\"
+#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"subs\",NAME=\"English\",LANGUAGE=\"en\",URI=\"subs/en.m3u8\"
+#EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID=\"cc\",NAME=\"CC1\",INSTREAM-ID=\"CC1\"
+#EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,AUDIO=\"aud\",SUBTITLES=\"subs\"
+360p.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=2800000,RESOLUTION=1280x720,AUDIO=\"aud\",SUBTITLES=\"subs\"
+720p.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080,AUDIO=\"hd\",SUBTITLES=\"subs\"
+1080p.m3u8
+#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=200000,URI=\"iframe.m3u8\"
+";
+
+    fn media_playlist(dir: &str, ext: &str) -> String {
+        let mut playlist = "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-PLAYLIST-TYPE:VOD\n".to_string();
+        for n in 0..3 {
+            playlist += &format!("#EXTINF:4.0,\n{}/seg{}.{}\n", dir, n, ext);
+        }
+        playlist + "#EXT-X-ENDLIST\n"
+    }
+
+    /// Serve `files` over HTTP, counting requests for non-playlist files
+    async fn serve(files: HashMap<String, Vec<u8>>) -> (Url, Arc<AtomicUsize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split(' ').nth(1).unwrap_or("/").trim_start_matches('/').to_string();
+                if !path.ends_with(".m3u8") {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                }
+                let response = match files.get(&path) {
+                    Some(body) => {
+                        let mut response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            body.len()
+                        )
+                        .into_bytes();
+                        response.extend_from_slice(body);
+                        response
+                    }
+                    None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec(),
+                };
+                let _ = socket.write_all(&response).await;
+            }
+        });
+        (base, requests)
+    }
+
+    fn content_files() -> HashMap<String, Vec<u8>> {
+        let mut files = HashMap::new();
+        files.insert("master.m3u8".to_string(), MASTER.as_bytes().to_vec());
+        for (playlist, dir, ext) in [
+            ("360p.m3u8", "360p", "ts"),
+            ("720p.m3u8", "720p", "ts"),
+            ("1080p.m3u8", "1080p", "ts"),
+            ("audio/en.m3u8", "en", "aac"),
+            ("audio/hd.m3u8", "hd", "aac"),
+            ("subs/en.m3u8", "en", "vtt"),
+        ] {
+            files.insert(playlist.to_string(), media_playlist(dir, ext).into_bytes());
+            let prefix = playlist.rsplit_once('/').map_or(String::new(), |(p, _)| format!("{}/", p));
+            for n in 0..3 {
+                let body = format!("{} segment {} {}", dir, n, ext).repeat(10);
+                files.insert(format!("{}{}/seg{}.{}", prefix, dir, n, ext), body.into_bytes());
+            }
+        }
+        files
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("kino-download-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_rendition_policy() {
+        let renditions = [
+            rendition("360p", 800_000, 360),
+            rendition("720p", 2_800_000, 720),
+            rendition("1080p", 5_000_000, 1080),
+        ];
+        let duration = Duration::from_secs(600);
+
+        let pick = |policy: RenditionPolicy| policy.select(&renditions, duration).map(|r| r.id.clone());
+        assert_eq!(pick(RenditionPolicy::default()).as_deref(), Some("1080p"));
+        assert_eq!(pick(RenditionPolicy { max_height: Some(720), max_bytes: None }).as_deref(), Some("720p"));
+
+        // 720p is about 210 MB over ten minutes
+        let max_bytes = Some(250_000_000);
+        assert_eq!(pick(RenditionPolicy { max_height: None, max_bytes }).as_deref(), Some("720p"));
+        assert_eq!(pick(RenditionPolicy { max_height: Some(240), max_bytes }), None);
+    }
+
+    #[test]
+    fn test_localize_media_playlist() {
+        let content = "#EXTM3U
+#EXT-X-TARGETDURATION:4
+#EXT-X-MAP:URI=\"init.mp4\"
+#EXTINF:4.0,
+#EXT-X-BYTERANGE:1000@0
+main.mp4
+#EXTINF:4.0,
+#EXT-X-BYTERANGE:1500@1000
+main.mp4
+#EXTINF:4.0,
+https://other.example.com/tail.m4s?token=1
+#EXT-X-ENDLIST
+";
+        let base = Url::parse("https://cdn.example.com/vod/video.m3u8").unwrap();
+        let parser = HlsParser::new();
+        let playlist = parser.parse_media(content, &base).unwrap();
+        let local = localize_media(content, &base, &playlist, "video", "").unwrap();
+
+        let paths: Vec<&str> = local.resources.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["video/00000.mp4", "video/00001.mp4", "video/00002.m4s"]);
+        assert_eq!(local.resources[1].url.as_str(), "https://cdn.example.com/vod/main.mp4");
+        assert_eq!(local.resources[1].min_size, 2500);
+        assert!(local.text.contains("#EXT-X-MAP:URI=\"00000.mp4\""));
+        assert!(local.text.contains("#EXT-X-BYTERANGE:1500@1000\n00001.mp4\n"));
+        assert!(local.text.contains("00002.m4s\n#EXT-X-ENDLIST"));
+
+        // The rewritten playlist parses to the same segments
+        let rewritten = parser.parse_media(&local.text, &base).unwrap();
+        assert_eq!(rewritten.segments.len(), 3);
+        assert_eq!(rewritten.segments[1].byte_range.unwrap().start, 1000);
+    }
+
+    #[test]
+    fn test_localize_master() {
+        let base = Url::parse("https://cdn.example.com/master.m3u8").unwrap();
+        let chosen = rendition("720p", 2_800_000, 720);
+        let media = HashMap::from([
+            (base.join("audio/en.m3u8").unwrap(), "audio_0/index.m3u8".to_string()),
+        ]);
+        let master = localize_master(MASTER, &base, &chosen, &media).unwrap();
+
+        assert!(master.contains("AUDIO,GROUP-ID=\"aud\",NAME=\"English\",LANGUAGE=\"en\",DEFAULT=YES,URI=\"audio_0/index.m3u8\""));
+        assert!(master.contains("INSTREAM-ID=\"CC1\""));
+        assert!(master.contains("RESOLUTION=1280x720,AUDIO=\"aud\",SUBTITLES=\"subs\"\nvideo/index.m3u8\n"));
+        for dropped in ["audio/hd.m3u8", "subs/en.m3u8", "360p", "1080p", "I-FRAME"] {
+            assert!(!master.contains(dropped), "{} in {}", dropped, master);
+        }
+    }
+
+    #[test]
+    fn test_rejects_encrypted_content() {
+        assert!(reject_keys("#EXTM3U\n#EXT-X-KEY:METHOD=NONE\n").is_ok());
+        for key in [
+            "#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"",
+            "#EXT-X-SESSION-KEY:METHOD=SAMPLE-AES,URI=\"skd://key\",KEYFORMAT=\"com.apple.streamingkeydelivery\"",
+        ] {
+            let err = reject_keys(&format!("#EXTM3U\n{}\n", key)).unwrap_err();
+            assert_eq!(err.error_code(), "DRM_UNSUPPORTED");
+        }
+    }
+
+    #[test]
+    fn test_verify_size() {
+        let url = Url::parse("https://cdn.example.com/seg.ts").unwrap();
+        assert!(verify_size(&url, Some(100), 0, 100).is_ok());
+        assert!(verify_size(&url, None, 80, 100).is_ok());
+
+        let err = verify_size(&url, Some(100), 0, 60).unwrap_err();
+        assert!(err.to_string().contains("Content-Length was 100"), "{}", err);
+        assert!(matches!(verify_size(&url, None, 120, 100), Err(Error::SegmentIntegrity { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_download_and_play_offline() {
+        let (base, requests) = serve(content_files()).await;
+        let url = base.join("master.m3u8").unwrap();
+        let dir = temp_dir();
+
+        let manager = DownloadManager::new(DownloadConfig {
+            policy: RenditionPolicy { max_height: Some(720), max_bytes: None },
+            max_parallel: 2,
+            ..Default::default()
+        });
+        let handle = manager.start(&url, &dir).await.unwrap();
+        let content = handle.wait().await.unwrap().unwrap();
+        assert_eq!(content.rendition.id, "variant_1");
+        // Video, the "aud" audio track and subtitles, three segments each
+        assert_eq!(content.files, 9);
+        assert_eq!(requests.load(Ordering::SeqCst), 9);
+
+        let saved = std::fs::read(dir.join("video/00001.ts")).unwrap();
+        assert_eq!(saved, "720p segment 1 ts".repeat(10).into_bytes());
+        let state: SavedState = serde_json::from_slice(&std::fs::read(dir.join(STATE_FILE)).unwrap()).unwrap();
+        assert_eq!(state.completed.len(), 9);
+        assert_eq!(state.completed.values().sum::<u64>(), content.bytes);
+
+        // Starting again finds everything saved
+        let handle = manager.start(&url, &dir).await.unwrap();
+        let again = handle.wait().await.unwrap().unwrap();
+        assert_eq!(again.bytes, content.bytes);
+        assert_eq!(requests.load(Ordering::SeqCst), 9);
+
+        // The local master plays like the original
+        let session = crate::PlayerSession::new(Default::default());
+        session.load(&content.manifest_url).await.unwrap();
+        let playing = session.current_rendition().await.unwrap();
+        assert_eq!(playing.resolution.unwrap().height, 720);
+        assert_eq!(session.audio_tracks().await.len(), 1);
+        let manifest = HlsParser::new().parse(&content.manifest_url).await.unwrap();
+        let subtitles = manifest.text_tracks.iter().find(|t| t.kind == TextTrackKind::Subtitles).unwrap();
+        assert!(subtitles.url.path().ends_with("/subtitles_0/index.m3u8"));
+        let segments = HlsParser::new().parse_variant(&playing.uri).await.unwrap();
+        let data = session.fetch_segment(&segments[1]).await.unwrap();
+        assert_eq!(data.as_ref(), saved.as_slice());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pause_cancel_and_resume() {
+        let mut files = content_files();
+        files.insert(
+            "vod.m3u8".to_string(),
+            media_playlist("1080p", "ts").replace("1080p/", "").into_bytes(),
+        );
+        for n in 0..3 {
+            files.insert(format!("seg{}.ts", n), vec![n as u8; 4096]);
+        }
+        let (base, requests) = serve(files).await;
+        let url = base.join("vod.m3u8").unwrap();
+        let dir = temp_dir();
+
+        let manager = DownloadManager::new(DownloadConfig { max_parallel: 1, ..Default::default() });
+        let handle = manager.start(&url, &dir).await.unwrap();
+        handle.pause();
+        assert_eq!(handle.progress().status, DownloadStatus::Paused);
+        assert_eq!(handle.progress().eta, None);
+        handle.cancel();
+        assert!(handle.wait().await.unwrap().is_none());
+        assert!(!dir.join(MASTER_PLAYLIST).exists());
+        let fetched = requests.load(Ordering::SeqCst);
+        assert!(fetched <= 1);
+
+        // A media playlist entry point becomes the master playlist
+        let handle = manager.start(&url, &dir).await.unwrap();
+        let mut progress = handle.subscribe();
+        let content = handle.wait().await.unwrap().unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        let last = progress.borrow_and_update().clone();
+        assert_eq!((last.status, last.completed_files, last.percent), (DownloadStatus::Completed, 3, 100.0));
+        assert_eq!(last.bytes_downloaded, 3 * 4096);
+
+        let master = std::fs::read_to_string(dir.join(MASTER_PLAYLIST)).unwrap();
+        assert!(master.contains("#EXTINF:4.0,\nvideo/00000.ts\n"), "{}", master);
+        assert_eq!(std::fs::read(dir.join("video/00002.ts")).unwrap(), vec![2u8; 4096]);
+        assert_eq!(content.manifest_url, Url::from_file_path(dir.canonicalize().unwrap().join(MASTER_PLAYLIST)).unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_live_and_dash() {
+        let base = Url::parse("https://cdn.example.com/").unwrap();
+        let fetcher = http::ScriptedFetcher::new()
+            .respond("https://cdn.example.com/live.m3u8", 200, "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4.0,\nseg0.ts\n")
+            .respond("https://cdn.example.com/manifest.mpd", 200, "<?xml version=\"1.0\"?><MPD></MPD>");
+        let manager = DownloadManager::with_fetcher(Arc::new(fetcher), DownloadConfig::default());
+
+        for (path, message) in [("live.m3u8", "Live streams"), ("manifest.mpd", "HLS only")] {
+            let err = manager.start(&base.join(path).unwrap(), temp_dir()).await.err().unwrap();
+            assert_eq!(err.error_code(), "MANIFEST_UNSUPPORTED");
+            assert!(err.to_string().contains(message), "{}", err);
+        }
+    }
+}
@@ -27,15 +27,29 @@
 //! │              └─────────────┘                       │
 //! └─────────────────────────────────────────────────────┘
 //! ```
-
-use crate::error::{Error, Result};
-use crate::types::DrmSystem;
+//!
+//! License requests go through a [`LicenseTransport`]. The default
+//! [`HttpLicenseTransport`] posts challenges with an
+//! [`HttpFetcher`](crate::http::HttpFetcher); tests and platform bindings
+//! can supply their own transport or fetcher.
+//!
+//! When [`DrmConfig::persist_license`] is set and a [`LicenseStore`] is
+//! attached, licenses are saved by key ID and reused across restarts until
+//! they expire or are revoked.
+
+use crate::error::{DrmErrorKind, Error, NetworkErrorKind, Result};
+use crate::http::{self, HttpFetcher, ReqwestFetcher};
+use crate::types::{DrmSystem, EncryptionInfo};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, warn};
 use url::Url;
 
 /// PSSH (Protection System Specific Header) box data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PsshBox {
     /// DRM system ID (UUID)
     pub system_id: String,
@@ -70,6 +84,61 @@ impl PsshBox {
     pub fn data_bytes(&self) -> Result<Vec<u8>> {
         base64_decode(&self.data)
     }
+
+    /// Build init data from an HLS EXT-X-KEY or EXT-X-SESSION-KEY
+    ///
+    /// FairPlay `skd://` URIs become the init data as-is, with the asset ID
+    /// after the scheme as key ID. `data:` URIs carry a base64 PSSH box for
+    /// Widevine or PlayReady; version 1 boxes also yield their key IDs.
+    /// Keys without a recognized KEYFORMAT return None.
+    pub fn from_hls_key(info: &EncryptionInfo) -> Option<Self> {
+        let system = info.drm_system()?;
+        let uri = info.key_uri.as_ref()?;
+
+        match uri.scheme() {
+            "skd" => {
+                let mut pssh = Self::new(system.system_id(), uri.as_str().as_bytes());
+                if let Some(asset_id) = uri.as_str().strip_prefix("skd://").filter(|id| !id.is_empty()) {
+                    pssh.key_ids.push(asset_id.to_string());
+                }
+                Some(pssh)
+            }
+            "data" => {
+                let (header, payload) = uri.path().split_once(',')?;
+                if !header.ends_with(";base64") {
+                    warn!(key_format = ?info.key_format, "Ignoring non-base64 data URI key");
+                    return None;
+                }
+                let data = match base64_decode(payload) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        warn!(error = %e, "Invalid base64 in data URI key");
+                        return None;
+                    }
+                };
+                Some(Self {
+                    system_id: system.system_id().to_string(),
+                    key_ids: pssh_key_ids(&data),
+                    data: base64_encode(&data),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Key IDs from a version 1 `pssh` box, as lowercase hex
+fn pssh_key_ids(data: &[u8]) -> Vec<String> {
+    // size(4) type(4) version(1) flags(3) system_id(16) kid_count(4)
+    if data.len() < 32 || &data[4..8] != b"pssh" || data[8] != 1 {
+        return Vec::new();
+    }
+    let count = u32::from_be_bytes([data[28], data[29], data[30], data[31]]) as usize;
+    data[32..]
+        .chunks_exact(16)
+        .take(count)
+        .map(|kid| kid.iter().map(|b| format!("{:02x}", b)).collect())
+        .collect()
 }
 
 /// DRM configuration for a content item
@@ -93,6 +162,27 @@ pub struct DrmConfig {
     pub persist_license: bool,
     /// License duration in seconds (0 = forever)
     pub license_duration: u64,
+    /// License request timeout in milliseconds
+    #[serde(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    /// Retry attempts for failed license requests
+    #[serde(default = "default_retry_attempts")]
+    pub retry_attempts: u32,
+    /// Delay between license request retries in milliseconds
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+}
+
+fn default_request_timeout_ms() -> u64 {
+    10000
+}
+
+fn default_retry_attempts() -> u32 {
+    3
+}
+
+fn default_retry_delay_ms() -> u64 {
+    1000
 }
 
 impl Default for DrmConfig {
@@ -107,6 +197,9 @@ impl Default for DrmConfig {
             clearkey_keys: HashMap::new(),
             persist_license: false,
             license_duration: 0,
+            request_timeout_ms: default_request_timeout_ms(),
+            retry_attempts: default_retry_attempts(),
+            retry_delay_ms: default_retry_delay_ms(),
         }
     }
 }
@@ -194,6 +287,194 @@ pub struct LicenseResponse {
     pub expiration: u64,
 }
 
+/// Raw response returned by a [`LicenseTransport`]
+#[derive(Debug, Clone, Default)]
+pub struct TransportResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers (lowercase names)
+    pub headers: HashMap<String, String>,
+    /// Response body
+    pub body: Vec<u8>,
+}
+
+impl TransportResponse {
+    /// Check if the status code indicates success
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Sends license challenges to a license server
+///
+/// Transports only move bytes; status handling, retries and response
+/// unwrapping are done by [`DrmManager::acquire_license`].
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait LicenseTransport: Send + Sync {
+    /// POST the request challenge to its license URL
+    async fn send(&self, request: &LicenseRequest, timeout: Duration) -> Result<TransportResponse>;
+}
+
+/// License transport posting challenges with an [`HttpFetcher`]
+///
+/// [`DrmManager::acquire_license`] bounds each exchange by
+/// [`DrmConfig::request_timeout_ms`], whatever the fetcher's own timeout.
+#[derive(Clone)]
+pub struct HttpLicenseTransport {
+    fetcher: Arc<dyn HttpFetcher>,
+}
+
+impl HttpLicenseTransport {
+    /// Create a transport using an existing HTTP client
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self::with_fetcher(Arc::new(ReqwestFetcher::with_client(client)))
+    }
+
+    /// Create a transport that posts with `fetcher`
+    pub fn with_fetcher(fetcher: Arc<dyn HttpFetcher>) -> Self {
+        Self { fetcher }
+    }
+}
+
+impl Default for HttpLicenseTransport {
+    fn default() -> Self {
+        Self::with_fetcher(http::default_fetcher(Duration::from_millis(default_request_timeout_ms())))
+    }
+}
+
+impl std::fmt::Debug for HttpLicenseTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpLicenseTransport").finish_non_exhaustive()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl LicenseTransport for HttpLicenseTransport {
+    async fn send(&self, request: &LicenseRequest, _timeout: Duration) -> Result<TransportResponse> {
+        let mut headers = request.headers.clone();
+        if !headers.keys().any(|k| k.eq_ignore_ascii_case("content-type")) {
+            headers.insert("Content-Type".to_string(), "application/octet-stream".to_string());
+        }
+
+        let challenge = bytes::Bytes::from(request.challenge.clone());
+        let response = self.fetcher.post(&request.license_url, &headers, challenge).await?;
+        let status = response.status;
+        let headers = response.headers.clone();
+        let body = response.bytes().await?.to_vec();
+
+        Ok(TransportResponse { status, headers, body })
+    }
+}
+
+/// License persisted by a [`LicenseStore`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredLicense {
+    /// DRM system the license is for
+    pub system: DrmSystem,
+    /// Key IDs the license covers
+    pub key_ids: Vec<String>,
+    /// License data
+    pub license: Vec<u8>,
+    /// Expiration time (Unix timestamp, 0 = no expiration)
+    pub expiration: u64,
+}
+
+impl StoredLicense {
+    /// Check if the license has expired
+    pub fn is_expired(&self) -> bool {
+        self.expiration != 0 && unix_now() >= self.expiration
+    }
+
+    /// Storage key for a system and set of key IDs, independent of key ID order and case
+    pub fn storage_key(system: DrmSystem, key_ids: &[String]) -> String {
+        let mut ids: Vec<String> = key_ids.iter().map(|k| k.to_lowercase()).collect();
+        ids.sort();
+        ids.dedup();
+        format!("{:?}:{}", system, ids.join(","))
+    }
+}
+
+/// Persistent storage for licenses, keyed by DRM system and key IDs
+///
+/// Implementations must never return an expired license from `load`;
+/// expired entries found on load are deleted.
+pub trait LicenseStore: Send + Sync {
+    /// Save a license, replacing any existing one for the same keys
+    fn save(&self, system: DrmSystem, key_ids: &[String], license: &[u8], expiration: u64) -> Result<()>;
+
+    /// Load an unexpired license for the keys
+    fn load(&self, system: DrmSystem, key_ids: &[String]) -> Result<Option<StoredLicense>>;
+
+    /// Delete the license for the keys, if any
+    fn remove(&self, system: DrmSystem, key_ids: &[String]) -> Result<()>;
+
+    /// Delete all expired licenses, returning how many were removed
+    fn purge_expired(&self) -> Result<usize>;
+}
+
+/// In-memory license store, mainly for tests
+#[derive(Debug, Default)]
+pub struct MemoryLicenseStore {
+    licenses: Mutex<HashMap<String, StoredLicense>>,
+}
+
+impl MemoryLicenseStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored licenses, including expired ones not yet purged
+    pub fn len(&self) -> usize {
+        self.licenses.lock().unwrap().len()
+    }
+
+    /// Check if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl LicenseStore for MemoryLicenseStore {
+    fn save(&self, system: DrmSystem, key_ids: &[String], license: &[u8], expiration: u64) -> Result<()> {
+        let stored = StoredLicense {
+            system,
+            key_ids: key_ids.to_vec(),
+            license: license.to_vec(),
+            expiration,
+        };
+        self.licenses.lock().unwrap().insert(StoredLicense::storage_key(system, key_ids), stored);
+        Ok(())
+    }
+
+    fn load(&self, system: DrmSystem, key_ids: &[String]) -> Result<Option<StoredLicense>> {
+        let mut licenses = self.licenses.lock().unwrap();
+        let key = StoredLicense::storage_key(system, key_ids);
+
+        match licenses.get(&key) {
+            Some(stored) if stored.is_expired() => {
+                licenses.remove(&key);
+                Ok(None)
+            }
+            stored => Ok(stored.cloned()),
+        }
+    }
+
+    fn remove(&self, system: DrmSystem, key_ids: &[String]) -> Result<()> {
+        self.licenses.lock().unwrap().remove(&StoredLicense::storage_key(system, key_ids));
+        Ok(())
+    }
+
+    fn purge_expired(&self) -> Result<usize> {
+        let mut licenses = self.licenses.lock().unwrap();
+        let before = licenses.len();
+        licenses.retain(|_, stored| !stored.is_expired());
+        Ok(before - licenses.len())
+    }
+}
+
 /// DRM session state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DrmSessionState {
@@ -266,23 +547,56 @@ pub struct DrmManager {
     config: DrmConfig,
     sessions: HashMap<String, DrmSession>,
     pssh_boxes: Vec<PsshBox>,
+    transport: Arc<dyn LicenseTransport>,
+    store: Option<Arc<dyn LicenseStore>>,
 }
 
 impl DrmManager {
     /// Create a new DRM manager
     pub fn new(config: DrmConfig) -> Self {
+        let fetcher = http::default_fetcher(Duration::from_millis(config.request_timeout_ms));
+        Self::with_transport(config, Arc::new(HttpLicenseTransport::with_fetcher(fetcher)))
+    }
+
+    /// Create a DRM manager that sends license requests through `transport`
+    pub fn with_transport(config: DrmConfig, transport: Arc<dyn LicenseTransport>) -> Self {
         Self {
             config,
             sessions: HashMap::new(),
             pssh_boxes: Vec::new(),
+            transport,
+            store: None,
         }
     }
 
+    /// Attach a license store, used when `persist_license` is set
+    ///
+    /// Expired licenses are purged from the store when it is attached.
+    pub fn with_license_store(mut self, store: Arc<dyn LicenseStore>) -> Self {
+        match store.purge_expired() {
+            Ok(0) => {}
+            Ok(purged) => debug!(purged, "Purged expired licenses"),
+            Err(e) => warn!(error = %e, "Failed to purge expired licenses"),
+        }
+        self.store = Some(store);
+        self
+    }
+
     /// Set PSSH boxes from manifest or init segment
     pub fn set_pssh_boxes(&mut self, boxes: Vec<PsshBox>) {
         self.pssh_boxes = boxes;
     }
 
+    /// Add init data found while parsing, e.g. `Manifest::drm_init_data`,
+    /// skipping boxes already known
+    pub fn add_pssh_boxes(&mut self, boxes: &[PsshBox]) {
+        for pssh in boxes {
+            if !self.pssh_boxes.contains(pssh) {
+                self.pssh_boxes.push(pssh.clone());
+            }
+        }
+    }
+
     /// Get PSSH box for a specific DRM system
     pub fn get_pssh(&self, system: DrmSystem) -> Option<&PsshBox> {
         let target_id = system.system_id().to_lowercase();
@@ -292,7 +606,7 @@ impl DrmManager {
     /// Create a license request for Widevine
     pub fn create_widevine_request(&self, challenge: Vec<u8>) -> Result<LicenseRequest> {
         let license_url = self.config.widevine_license_url.clone()
-            .ok_or_else(|| Error::drm("Widevine license URL not configured"))?;
+            .ok_or_else(|| Error::drm(Some(DrmSystem::Widevine), DrmErrorKind::Config, "license URL not configured"))?;
 
         Ok(LicenseRequest {
             system: DrmSystem::Widevine,
@@ -305,7 +619,7 @@ impl DrmManager {
     /// Create a license request for FairPlay
     pub fn create_fairplay_request(&self, spc: Vec<u8>) -> Result<LicenseRequest> {
         let license_url = self.config.fairplay_license_url.clone()
-            .ok_or_else(|| Error::drm("FairPlay license URL not configured"))?;
+            .ok_or_else(|| Error::drm(Some(DrmSystem::FairPlay), DrmErrorKind::Config, "license URL not configured"))?;
 
         Ok(LicenseRequest {
             system: DrmSystem::FairPlay,
@@ -315,10 +629,204 @@ impl DrmManager {
         })
     }
 
+    /// Create a license request for PlayReady
+    pub fn create_playready_request(&self, challenge: Vec<u8>) -> Result<LicenseRequest> {
+        let license_url = self.config.playready_license_url.clone()
+            .ok_or_else(|| Error::drm(Some(DrmSystem::PlayReady), DrmErrorKind::Config, "license URL not configured"))?;
+
+        let mut headers = self.config.license_headers.clone();
+        if !headers.keys().any(|k| k.eq_ignore_ascii_case("content-type")) {
+            headers.insert("Content-Type".to_string(), "text/xml; charset=utf-8".to_string());
+        }
+
+        Ok(LicenseRequest {
+            system: DrmSystem::PlayReady,
+            challenge,
+            license_url,
+            headers,
+        })
+    }
+
+    /// Acquire a license for a session from its system's license server
+    ///
+    /// The session moves to `AwaitingLicense` while the request is in flight,
+    /// then to `Ready` or to `Error` with the failure recorded in
+    /// [`DrmSession::error`]. Timeouts, network errors, 429 and 5xx responses
+    /// are retried up to `retry_attempts` times. ClearKey sessions are served
+    /// from the configured keys without a request.
+    ///
+    /// With `persist_license` set, a stored license for the session's key IDs
+    /// is used instead of a request, and new licenses are saved.
+    pub async fn acquire_license(&mut self, session_id: &str, challenge: Vec<u8>) -> Result<LicenseResponse> {
+        let (system, key_ids) = self.sessions.get(session_id)
+            .map(|s| (s.system, s.key_ids.clone()))
+            .ok_or_else(|| Error::drm(None, DrmErrorKind::SessionNotFound, session_id))?;
+
+        if let Some(stored) = self.load_stored_license(system, &key_ids) {
+            debug!(?system, "Using persisted license");
+            let response = LicenseResponse {
+                system,
+                license: stored.license,
+                expiration: stored.expiration,
+            };
+            self.process_license(session_id, response.clone())?;
+            return Ok(response);
+        }
+
+        let result = match system {
+            DrmSystem::ClearKey => self.get_clearkey_license(),
+            _ => {
+                let request = match system {
+                    DrmSystem::Widevine => self.create_widevine_request(challenge),
+                    DrmSystem::FairPlay => self.create_fairplay_request(challenge),
+                    _ => self.create_playready_request(challenge),
+                };
+                match request {
+                    Ok(request) => {
+                        self.set_session_state(session_id, DrmSessionState::AwaitingLicense);
+                        self.send_license_request(&request).await
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        };
+
+        match result {
+            Ok(response) => {
+                self.save_license(&key_ids, &response);
+                self.process_license(session_id, response.clone())?;
+                Ok(response)
+            }
+            Err(e) => {
+                if let Some(session) = self.sessions.get_mut(session_id) {
+                    session.state = DrmSessionState::Error;
+                    session.error = Some(e.to_string());
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Send a license request, retrying transient failures
+    async fn send_license_request(&self, request: &LicenseRequest) -> Result<LicenseResponse> {
+        let timeout = Duration::from_millis(self.config.request_timeout_ms);
+        let mut attempt = 0;
+
+        loop {
+            let result = match tokio::time::timeout(timeout, self.transport.send(request, timeout)).await {
+                Ok(result) => result,
+                Err(_) => Err(Error::network(NetworkErrorKind::Timeout, Some(request.license_url.clone()))),
+            };
+
+            let error = match result {
+                Ok(response) if response.is_success() => {
+                    return self.parse_license_response(request.system, &response);
+                }
+                Ok(response) => {
+                    let error = Error::drm(Some(request.system), DrmErrorKind::License, format!(
+                        "License server returned HTTP {}: {}",
+                        response.status,
+                        server_error_message(&response.body)
+                    ));
+                    if response.status != 429 && response.status < 500 {
+                        return Err(error);
+                    }
+                    error
+                }
+                Err(e) if e.is_retryable() => e,
+                Err(e) => return Err(e),
+            };
+
+            if attempt >= self.config.retry_attempts {
+                return Err(error);
+            }
+            attempt += 1;
+            warn!(attempt, error = %error, url = %request.license_url, "Retrying license request");
+            tokio::time::sleep(Duration::from_millis(self.config.retry_delay_ms)).await;
+        }
+    }
+
+    /// Unwrap the license from a successful response
+    ///
+    /// Servers either return the license as the raw body or wrap it in JSON
+    /// with a base64 `license` field and optional expiration. ClearKey
+    /// licenses are JSON themselves and are returned as-is.
+    fn parse_license_response(&self, system: DrmSystem, response: &TransportResponse) -> Result<LicenseResponse> {
+        let json = serde_json::from_slice::<serde_json::Value>(&response.body)
+            .ok()
+            .filter(|v| v.is_object());
+
+        let (license, expiration) = match json {
+            Some(json) if system != DrmSystem::ClearKey => {
+                let encoded = json.get("license")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| Error::drm(Some(system), DrmErrorKind::License, format!(
+                        "License response has no license: {}",
+                        server_error_message(&response.body)
+                    )))?;
+                let license = base64_decode(&encoded.replace('-', "+").replace('_', "/"))?;
+                let expiration = ["expiration", "expires", "expires_at"]
+                    .iter()
+                    .find_map(|key| json.get(*key).and_then(|v| v.as_u64()))
+                    // Millisecond timestamps are normalized to seconds
+                    .map(|t| if t > 100_000_000_000 { t / 1000 } else { t })
+                    .unwrap_or(0);
+                (license, expiration)
+            }
+            _ => (response.body.clone(), 0),
+        };
+
+        if license.is_empty() {
+            return Err(Error::drm(Some(system), DrmErrorKind::License, "License server returned an empty license"));
+        }
+
+        let expiration = if expiration == 0 && self.config.license_duration > 0 {
+            unix_now() + self.config.license_duration
+        } else {
+            expiration
+        };
+
+        debug!(?system, bytes = license.len(), expiration, "License received");
+
+        Ok(LicenseResponse { system, license, expiration })
+    }
+
+    /// Store to use for a session's keys, if persistence applies
+    fn persistent_store(&self, key_ids: &[String]) -> Option<&Arc<dyn LicenseStore>> {
+        if !self.config.persist_license || key_ids.is_empty() {
+            return None;
+        }
+        self.store.as_ref()
+    }
+
+    fn load_stored_license(&self, system: DrmSystem, key_ids: &[String]) -> Option<StoredLicense> {
+        let store = self.persistent_store(key_ids)?;
+        match store.load(system, key_ids) {
+            Ok(stored) => stored.filter(|s| !s.is_expired()),
+            Err(e) => {
+                warn!(error = %e, "Failed to load persisted license");
+                None
+            }
+        }
+    }
+
+    fn save_license(&self, key_ids: &[String], response: &LicenseResponse) {
+        let Some(store) = self.persistent_store(key_ids) else { return };
+        if let Err(e) = store.save(response.system, key_ids, &response.license, response.expiration) {
+            warn!(error = %e, "Failed to persist license");
+        }
+    }
+
+    fn set_session_state(&mut self, session_id: &str, state: DrmSessionState) {
+        if let Some(session) = self.sessions.get_mut(session_id) {
+            session.state = state;
+        }
+    }
+
     /// Get ClearKey license (no server needed)
     pub fn get_clearkey_license(&self) -> Result<LicenseResponse> {
         if self.config.clearkey_keys.is_empty() {
-            return Err(Error::drm("No ClearKey keys configured"));
+            return Err(Error::drm(Some(DrmSystem::ClearKey), DrmErrorKind::Config, "No ClearKey keys configured"));
         }
 
         // Build ClearKey license JSON
@@ -345,8 +853,13 @@ impl DrmManager {
     }
 
     /// Create or get a session for a DRM system
+    ///
+    /// The session's key IDs are taken from the system's PSSH box, if any.
     pub fn create_session(&mut self, system: DrmSystem) -> &DrmSession {
-        let session = DrmSession::new(system);
+        let mut session = DrmSession::new(system);
+        if let Some(pssh) = self.get_pssh(system) {
+            session.key_ids = pssh.key_ids.clone();
+        }
         let id = session.id.clone();
         self.sessions.insert(id.clone(), session);
         self.sessions.get(&id).unwrap()
@@ -355,10 +868,11 @@ impl DrmManager {
     /// Update session with license response
     pub fn process_license(&mut self, session_id: &str, response: LicenseResponse) -> Result<()> {
         let session = self.sessions.get_mut(session_id)
-            .ok_or_else(|| Error::drm("Session not found"))?;
+            .ok_or_else(|| Error::drm(None, DrmErrorKind::SessionNotFound, session_id))?;
 
         session.state = DrmSessionState::Ready;
         session.expiration = response.expiration;
+        session.error = None;
 
         Ok(())
     }
@@ -379,10 +893,23 @@ impl DrmManager {
     }
 
     /// Close all sessions
+    ///
+    /// Persisted licenses are kept; use [`DrmManager::revoke`] to delete them.
     pub fn close_all_sessions(&mut self) {
         self.sessions.clear();
     }
 
+    /// Close a session and delete any persisted license for its keys
+    pub fn revoke(&mut self, id: &str) -> Result<()> {
+        let session = self.sessions.remove(id)
+            .ok_or_else(|| Error::drm(None, DrmErrorKind::SessionNotFound, id))?;
+
+        match &self.store {
+            Some(store) if !session.key_ids.is_empty() => store.remove(session.system, &session.key_ids),
+            _ => Ok(()),
+        }
+    }
+
     /// Check if DRM is required for playback
     pub fn is_drm_required(&self) -> bool {
         !self.pssh_boxes.is_empty()
@@ -408,6 +935,32 @@ impl DrmManager {
     }
 }
 
+/// Extract a human-readable message from a license server error body
+fn server_error_message(body: &[u8]) -> String {
+    let json = serde_json::from_slice::<serde_json::Value>(body).ok();
+    let message = json.as_ref().and_then(|json| {
+        ["error_description", "message", "error"]
+            .iter()
+            .find_map(|key| json.get(*key).and_then(|v| v.as_str()))
+    });
+
+    match message {
+        Some(message) => message.to_string(),
+        None if body.is_empty() => "empty response".to_string(),
+        None => {
+            let text = String::from_utf8_lossy(body);
+            text.chars().take(200).collect::<String>().trim().to_string()
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 // Base64 encoding/decoding helpers (avoiding external dependency for core lib)
 fn base64_encode(data: &[u8]) -> String {
     const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
@@ -455,11 +1008,11 @@ fn base64_decode(data: &str) -> Result<Vec<u8>> {
 
         for (i, &b) in chunk.iter().enumerate() {
             if b as usize >= 128 {
-                return Err(Error::drm("Invalid base64 character"));
+                return Err(Error::drm(None, DrmErrorKind::License, "Invalid base64 character"));
             }
             let val = DECODE_TABLE[b as usize];
             if val < 0 {
-                return Err(Error::drm("Invalid base64 character"));
+                return Err(Error::drm(None, DrmErrorKind::License, "Invalid base64 character"));
             }
             n |= (val as u32) << (18 - i * 6);
         }
@@ -497,6 +1050,57 @@ mod tests {
         assert_eq!(pssh.drm_system(), Some(DrmSystem::Widevine));
     }
 
+    fn hls_key(key_format: &str, uri: &str) -> EncryptionInfo {
+        EncryptionInfo {
+            method: crate::types::EncryptionMethod::SampleAes,
+            key_uri: Some(Url::parse(uri).unwrap()),
+            iv: None,
+            key_format: Some(key_format.to_string()),
+            key_format_versions: Some("1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_pssh_from_hls_key() {
+        let fairplay = PsshBox::from_hls_key(&hls_key(
+            "com.apple.streamingkeydelivery",
+            "skd://asset-1234",
+        )).unwrap();
+        assert_eq!(fairplay.drm_system(), Some(DrmSystem::FairPlay));
+        assert_eq!(fairplay.key_ids, ["asset-1234"]);
+        assert_eq!(fairplay.data_bytes().unwrap(), b"skd://asset-1234");
+
+        // Version 1 Widevine PSSH with one key ID and no data
+        let mut pssh = vec![0, 0, 0, 52];
+        pssh.extend_from_slice(b"pssh");
+        pssh.extend_from_slice(&[1, 0, 0, 0]);
+        pssh.extend_from_slice(&[
+            0xed, 0xef, 0x8b, 0xa9, 0x79, 0xd6, 0x4a, 0xce,
+            0xa3, 0xc8, 0x27, 0xdc, 0xd5, 0x1d, 0x21, 0xed,
+        ]);
+        pssh.extend_from_slice(&[0, 0, 0, 1]);
+        pssh.extend_from_slice(&[0xab; 16]);
+        pssh.extend_from_slice(&[0, 0, 0, 0]);
+        let uri = format!("data:text/plain;base64,{}", base64_encode(&pssh));
+        let widevine = PsshBox::from_hls_key(&hls_key(
+            "urn:uuid:edef8ba9-79d6-4ace-a3c8-27dcd51d21ed",
+            &uri,
+        )).unwrap();
+        assert_eq!(widevine.drm_system(), Some(DrmSystem::Widevine));
+        assert_eq!(widevine.key_ids, ["ab".repeat(16)]);
+        assert_eq!(widevine.data_bytes().unwrap(), pssh);
+
+        // Plain key servers carry no init data
+        assert!(PsshBox::from_hls_key(&hls_key("identity", "https://keys.example.com/k")).is_none());
+
+        let mut manager = DrmManager::new(DrmConfig::default());
+        manager.add_pssh_boxes(&[fairplay.clone(), widevine.clone()]);
+        manager.add_pssh_boxes(&[fairplay]);
+        assert!(manager.is_drm_required());
+        assert_eq!(manager.pssh_boxes.len(), 2);
+        assert_eq!(manager.get_pssh(DrmSystem::Widevine), Some(&widevine));
+    }
+
     #[test]
     fn test_base64_roundtrip() {
         let original = b"Hello, DRM!";
@@ -516,4 +1120,206 @@ mod tests {
         let license = manager.get_clearkey_license().unwrap();
         assert_eq!(license.system, DrmSystem::ClearKey);
     }
+
+    /// Transport that replays canned responses and records requests
+    struct MockTransport {
+        responses: std::sync::Mutex<std::collections::VecDeque<Result<TransportResponse>>>,
+        requests: std::sync::Mutex<Vec<LicenseRequest>>,
+        delay: Duration,
+    }
+
+    impl MockTransport {
+        fn new(responses: Vec<Result<TransportResponse>>) -> Arc<Self> {
+            Self::with_delay(responses, Duration::ZERO)
+        }
+
+        fn with_delay(responses: Vec<Result<TransportResponse>>, delay: Duration) -> Arc<Self> {
+            Arc::new(Self {
+                responses: std::sync::Mutex::new(responses.into()),
+                requests: std::sync::Mutex::new(Vec::new()),
+                delay,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LicenseTransport for MockTransport {
+        async fn send(&self, request: &LicenseRequest, _timeout: Duration) -> Result<TransportResponse> {
+            self.requests.lock().unwrap().push(request.clone());
+            tokio::time::sleep(self.delay).await;
+            self.responses.lock().unwrap().pop_front().expect("unexpected license request")
+        }
+    }
+
+    fn response(status: u16, body: &[u8]) -> Result<TransportResponse> {
+        Ok(TransportResponse { status, headers: HashMap::new(), body: body.to_vec() })
+    }
+
+    fn widevine_manager(transport: Arc<MockTransport>) -> (DrmManager, String) {
+        widevine_manager_with(transport)
+    }
+
+    fn widevine_manager_with(transport: Arc<dyn LicenseTransport>) -> (DrmManager, String) {
+        let mut config = DrmConfig::widevine(Url::parse("https://license.example.com/wv").unwrap())
+            .with_header("Authorization", "Bearer token");
+        config.retry_delay_ms = 0;
+
+        let mut manager = DrmManager::with_transport(config, transport);
+        let session_id = manager.create_session(DrmSystem::Widevine).id.clone();
+        (manager, session_id)
+    }
+
+    #[tokio::test]
+    async fn test_acquire_license_json_wrapper() {
+        let body = format!(r#"{{"license":"{}","expiration":1900000000000}}"#, base64_encode(b"wv-license"));
+        let transport = MockTransport::new(vec![response(200, body.as_bytes())]);
+        let (mut manager, session_id) = widevine_manager(transport.clone());
+
+        let license = manager.acquire_license(&session_id, b"challenge".to_vec()).await.unwrap();
+        assert_eq!(license.license, b"wv-license");
+        assert_eq!(license.expiration, 1_900_000_000);
+
+        let session = manager.get_session(&session_id).unwrap();
+        assert!(session.is_ready());
+        assert_eq!(session.expiration, 1_900_000_000);
+
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests[0].challenge, b"challenge");
+        assert_eq!(requests[0].headers.get("Authorization").unwrap(), "Bearer token");
+    }
+
+    #[tokio::test]
+    async fn test_acquire_license_retries_then_accepts_raw_body() {
+        let transport = MockTransport::new(vec![
+            response(503, b"busy"),
+            Err(Error::network(NetworkErrorKind::Timeout, None)),
+            response(200, &[0x08, 0x01, 0xff]),
+        ]);
+        let (mut manager, session_id) = widevine_manager(transport.clone());
+
+        let license = manager.acquire_license(&session_id, b"challenge".to_vec()).await.unwrap();
+        assert_eq!(license.license, vec![0x08, 0x01, 0xff]);
+        assert_eq!(license.expiration, 0);
+        assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_license_records_server_error() {
+        let transport = MockTransport::new(vec![response(403, br#"{"error":"subscription expired"}"#)]);
+        let (mut manager, session_id) = widevine_manager(transport.clone());
+
+        let err = manager.acquire_license(&session_id, b"challenge".to_vec()).await.unwrap_err();
+        assert!(err.to_string().contains("subscription expired"));
+
+        // Client errors are not retried
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+        let session = manager.get_session(&session_id).unwrap();
+        assert_eq!(session.state, DrmSessionState::Error);
+        assert!(session.error.as_deref().unwrap().contains("HTTP 403"));
+    }
+
+    #[tokio::test]
+    async fn test_http_transport_posts_through_fetcher() {
+        let fetcher = Arc::new(crate::http::ScriptedFetcher::new()
+            .respond("https://license.example.com/wv", 200, &b"wv-license"[..]));
+        let transport = Arc::new(HttpLicenseTransport::with_fetcher(fetcher.clone()));
+        let (mut manager, session_id) = widevine_manager_with(transport);
+
+        let license = manager.acquire_license(&session_id, b"challenge".to_vec()).await.unwrap();
+        assert_eq!(license.license, b"wv-license");
+
+        let requests = fetcher.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].body.as_ref(), b"challenge");
+        assert_eq!(requests[0].headers["Content-Type"], "application/octet-stream");
+        assert_eq!(requests[0].headers["Authorization"], "Bearer token");
+    }
+
+    #[tokio::test]
+    async fn test_acquire_license_times_out() {
+        let transport = MockTransport::with_delay(
+            vec![response(200, b"late"), response(200, b"late")],
+            Duration::from_secs(5),
+        );
+        let (mut manager, session_id) = widevine_manager(transport.clone());
+        manager.config.request_timeout_ms = 10;
+        manager.config.retry_attempts = 1;
+
+        let err = manager.acquire_license(&session_id, b"challenge".to_vec()).await.unwrap_err();
+        assert!(matches!(err, Error::Network { kind: NetworkErrorKind::Timeout, .. }));
+        assert_eq!(transport.requests.lock().unwrap().len(), 2);
+        assert_eq!(manager.get_session(&session_id).unwrap().state, DrmSessionState::Error);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_license_applies_license_duration() {
+        let transport = MockTransport::new(vec![response(200, b"license")]);
+        let (mut manager, session_id) = widevine_manager(transport);
+        manager.config.license_duration = 3600;
+
+        let license = manager.acquire_license(&session_id, Vec::new()).await.unwrap();
+        assert!(license.expiration >= unix_now() + 3590);
+    }
+
+    fn persistent_manager(transport: Arc<MockTransport>, store: Arc<MemoryLicenseStore>) -> (DrmManager, String) {
+        let mut config = DrmConfig::widevine(Url::parse("https://license.example.com/wv").unwrap());
+        config.persist_license = true;
+        config.retry_delay_ms = 0;
+
+        let mut manager = DrmManager::with_transport(config, transport).with_license_store(store);
+        let mut pssh = PsshBox::new(DrmSystem::Widevine.system_id(), b"pssh");
+        pssh.key_ids = vec!["KID-1".to_string(), "kid-2".to_string()];
+        manager.set_pssh_boxes(vec![pssh]);
+
+        let session_id = manager.create_session(DrmSystem::Widevine).id.clone();
+        (manager, session_id)
+    }
+
+    #[tokio::test]
+    async fn test_persisted_license_is_reused() {
+        let store = Arc::new(MemoryLicenseStore::new());
+        let transport = MockTransport::new(vec![response(200, b"license")]);
+
+        let (mut manager, session_id) = persistent_manager(transport.clone(), store.clone());
+        manager.acquire_license(&session_id, b"challenge".to_vec()).await.unwrap();
+        assert_eq!(store.len(), 1);
+
+        // Closing sessions keeps the license; a new manager reuses it without a request
+        manager.close_all_sessions();
+        let (mut manager, session_id) = persistent_manager(transport.clone(), store.clone());
+        let license = manager.acquire_license(&session_id, b"challenge".to_vec()).await.unwrap();
+        assert_eq!(license.license, b"license");
+        assert!(manager.get_session(&session_id).unwrap().is_ready());
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+
+        manager.revoke(&session_id).unwrap();
+        assert!(store.is_empty());
+        assert!(manager.get_session(&session_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expired_license_is_purged() {
+        let store = Arc::new(MemoryLicenseStore::new());
+        let key_ids = vec!["kid-2".to_string(), "kid-1".to_string()];
+        store.save(DrmSystem::Widevine, &key_ids, b"stale", 1).unwrap();
+        store.save(DrmSystem::PlayReady, &key_ids, b"other", 0).unwrap();
+
+        let transport = MockTransport::new(vec![response(200, b"fresh")]);
+        let (mut manager, session_id) = persistent_manager(transport.clone(), store.clone());
+        assert_eq!(store.len(), 1);
+
+        let license = manager.acquire_license(&session_id, b"challenge".to_vec()).await.unwrap();
+        assert_eq!(license.license, b"fresh");
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_memory_store_load_purges_expired() {
+        let store = MemoryLicenseStore::new();
+        let key_ids = vec!["kid".to_string()];
+        store.save(DrmSystem::Widevine, &key_ids, b"license", 1).unwrap();
+
+        assert!(store.load(DrmSystem::Widevine, &key_ids).unwrap().is_none());
+        assert!(store.is_empty());
+    }
 }
@@ -1,6 +1,17 @@
 //! Error types for Kino Core
+//!
+//! Variants are grouped by subsystem and carry structured detail, so
+//! embedders can branch on [`Error::is_retryable`] (retry silently) versus
+//! fatal errors (show an error screen) without matching on messages.
+//! [`Error::error_code`] gives a stable string for analytics.
+
+use std::fmt;
 
 use thiserror::Error;
+use url::Url;
+
+use crate::integrity::IntegrityFailure;
+use crate::types::DrmSystem;
 
 /// Result type alias for player operations
 pub type Result<T> = std::result::Result<T, Error>;
@@ -8,51 +19,40 @@ pub type Result<T> = std::result::Result<T, Error>;
 /// Player error types
 #[derive(Error, Debug)]
 pub enum Error {
-    // Manifest errors
-    #[error("Failed to fetch manifest: {0}")]
-    ManifestFetch(String),
-
-    #[error("Failed to parse manifest: {0}")]
-    ManifestParse(String),
+    // Network errors
+    #[error("{}", network_message(*.kind, *.status, .url.as_ref()))]
+    Network {
+        kind: NetworkErrorKind,
+        /// HTTP status, for `Http` errors
+        status: Option<u16>,
+        /// Requested URL, when known
+        url: Option<Url>,
+        source: Option<reqwest::Error>,
+    },
 
-    #[error("Invalid manifest format: {0}")]
-    InvalidManifest(String),
+    // Manifest errors
+    #[error("{kind}: {detail}")]
+    Manifest { kind: ManifestErrorKind, detail: String },
 
     #[error("No suitable rendition found")]
     NoSuitableRendition,
 
     // Segment errors
-    #[error("Failed to fetch segment: {url}")]
-    SegmentFetch { url: String, source: reqwest::Error },
+    #[error("Segment integrity check failed for {url}: {failure}")]
+    SegmentIntegrity { url: Url, failure: Box<IntegrityFailure> },
 
-    #[error("Segment timeout: {url}")]
-    SegmentTimeout { url: String },
-
-    #[error("Segment decryption failed")]
-    SegmentDecryption,
+    // DRM and segment decryption errors
+    #[error("{kind}{}: {detail}", .system.map(|s| format!(" ({:?})", s)).unwrap_or_default())]
+    Drm {
+        /// DRM system involved, if one applies
+        system: Option<DrmSystem>,
+        kind: DrmErrorKind,
+        detail: String,
+    },
 
     // Buffer errors
-    #[error("Buffer underrun")]
-    BufferUnderrun,
-
-    #[error("Buffer overflow")]
-    BufferOverflow,
-
-    #[error("Buffer seek failed: position {position}s not buffered")]
-    BufferSeekFailed { position: f64 },
-
-    // DRM errors
-    #[error("DRM not supported: {system}")]
-    DrmNotSupported { system: String },
-
-    #[error("License acquisition failed: {0}")]
-    LicenseAcquisition(String),
-
-    #[error("License expired")]
-    LicenseExpired,
-
-    #[error("Content key not found")]
-    ContentKeyNotFound,
+    #[error("{kind} at {position}s")]
+    Buffer { kind: BufferErrorKind, position: f64 },
 
     // Playback errors
     #[error("Playback stalled")]
@@ -64,12 +64,12 @@ pub enum Error {
     #[error("Codec not supported: {codec}")]
     CodecNotSupported { codec: String },
 
-    // Network errors
-    #[error("Network error: {0}")]
-    Network(#[from] reqwest::Error),
+    #[error("Track not found: {id}")]
+    TrackNotFound { id: String },
 
-    #[error("Connection timeout")]
-    ConnectionTimeout,
+    // Analytics errors
+    #[error("Analytics delivery failed: {0}")]
+    AnalyticsSink(String),
 
     // Configuration errors
     #[error("Invalid configuration: {0}")]
@@ -83,49 +83,349 @@ pub enum Error {
     Io(#[from] std::io::Error),
 }
 
+/// What went wrong with a network request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkErrorKind {
+    /// The request or response timed out
+    Timeout,
+    /// The host name did not resolve
+    Dns,
+    /// The TLS handshake or certificate check failed
+    Tls,
+    /// The connection was refused or dropped
+    Connect,
+    /// The server answered with an error status
+    Http,
+    /// The response body could not be read
+    Body,
+}
+
+impl NetworkErrorKind {
+    fn code(self) -> &'static str {
+        match self {
+            NetworkErrorKind::Timeout => "NETWORK_TIMEOUT",
+            NetworkErrorKind::Dns => "NETWORK_DNS",
+            NetworkErrorKind::Tls => "NETWORK_TLS",
+            NetworkErrorKind::Connect => "NETWORK_CONNECT",
+            NetworkErrorKind::Http => "NETWORK_HTTP",
+            NetworkErrorKind::Body => "NETWORK_BODY",
+        }
+    }
+}
+
+/// What went wrong with a manifest, playlist or caption file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ManifestErrorKind {
+    /// The document could not be parsed
+    ParseError,
+    /// The document parsed but is inconsistent, e.g. a bad URI or no renditions
+    Invalid,
+    /// The document uses a feature the player does not support
+    Unsupported,
+}
+
+impl fmt::Display for ManifestErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ManifestErrorKind::ParseError => "Failed to parse manifest",
+            ManifestErrorKind::Invalid => "Invalid manifest",
+            ManifestErrorKind::Unsupported => "Unsupported manifest",
+        })
+    }
+}
+
+/// What went wrong with DRM or segment decryption
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DrmErrorKind {
+    /// The DRM system or encryption method is not supported
+    Unsupported,
+    /// A license URL or key is missing from the configuration
+    Config,
+    /// No DRM session with the given ID
+    SessionNotFound,
+    /// The license server refused or returned an unusable license
+    License,
+    /// The license has expired
+    LicenseExpired,
+    /// A content key is missing or malformed
+    KeyNotFound,
+    /// Segment data failed to decrypt
+    Decryption,
+}
+
+impl fmt::Display for DrmErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DrmErrorKind::Unsupported => "DRM not supported",
+            DrmErrorKind::Config => "DRM not configured",
+            DrmErrorKind::SessionNotFound => "DRM session not found",
+            DrmErrorKind::License => "License acquisition failed",
+            DrmErrorKind::LicenseExpired => "License expired",
+            DrmErrorKind::KeyNotFound => "Content key not found",
+            DrmErrorKind::Decryption => "Segment decryption failed",
+        })
+    }
+}
+
+/// What went wrong with the buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BufferErrorKind {
+    /// Playback reached the end of buffered data
+    Underrun,
+    /// More data was appended than the buffer holds
+    Overflow,
+    /// A seek target is not buffered
+    SeekFailed,
+}
+
+impl fmt::Display for BufferErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BufferErrorKind::Underrun => "Buffer underrun",
+            BufferErrorKind::Overflow => "Buffer overflow",
+            BufferErrorKind::SeekFailed => "Buffer seek failed: position not buffered",
+        })
+    }
+}
+
+fn network_message(kind: NetworkErrorKind, status: Option<u16>, url: Option<&Url>) -> String {
+    let what = match (kind, status) {
+        (NetworkErrorKind::Http, Some(status)) => format!("HTTP {}", status),
+        (NetworkErrorKind::Http, None) => "HTTP error".to_string(),
+        (NetworkErrorKind::Timeout, _) => "Request timed out".to_string(),
+        (NetworkErrorKind::Dns, _) => "DNS lookup failed".to_string(),
+        (NetworkErrorKind::Tls, _) => "TLS error".to_string(),
+        (NetworkErrorKind::Connect, _) => "Connection failed".to_string(),
+        (NetworkErrorKind::Body, _) => "Failed to read response".to_string(),
+    };
+    match url {
+        Some(url) => format!("{} for {}", what, url),
+        None => what,
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        let kind = if err.is_timeout() {
+            NetworkErrorKind::Timeout
+        } else if err.is_status() {
+            NetworkErrorKind::Http
+        } else if err.is_connect() {
+            connect_error_kind(&err)
+        } else if err.is_body() || err.is_decode() {
+            NetworkErrorKind::Body
+        } else {
+            NetworkErrorKind::Connect
+        };
+
+        Error::Network {
+            kind,
+            status: err.status().map(|s| s.as_u16()),
+            url: err.url().cloned(),
+            source: Some(err),
+        }
+    }
+}
+
+/// Tell DNS and TLS failures apart from other connect errors
+///
+/// reqwest only flags connect errors, so the source chain is inspected.
+fn connect_error_kind(err: &reqwest::Error) -> NetworkErrorKind {
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        let message = cause.to_string().to_lowercase();
+        if message.contains("dns") || message.contains("lookup") || message.contains("resolve") {
+            return NetworkErrorKind::Dns;
+        }
+        if message.contains("tls") || message.contains("ssl") || message.contains("certificate") {
+            return NetworkErrorKind::Tls;
+        }
+        source = cause.source();
+    }
+    NetworkErrorKind::Connect
+}
+
+impl From<crate::types::InvalidTransition> for Error {
+    fn from(err: crate::types::InvalidTransition) -> Self {
+        Error::InvalidStateTransition {
+            from: err.from.to_string(),
+            to: err.to.to_string(),
+        }
+    }
+}
+
 impl Error {
+    /// Create a network error without an underlying request error
+    pub fn network(kind: NetworkErrorKind, url: Option<Url>) -> Self {
+        Error::Network { kind, status: None, url, source: None }
+    }
+
+    /// Create an HTTP status error
+    pub fn http_status(status: u16, url: Url) -> Self {
+        Error::Network {
+            kind: NetworkErrorKind::Http,
+            status: Some(status),
+            url: Some(url),
+            source: None,
+        }
+    }
+
+    /// Convert a request error, filling in `url` if reqwest didn't record one
+    pub fn request(err: reqwest::Error, url: &Url) -> Self {
+        match Error::from(err) {
+            Error::Network { kind, status, url: None, source } => Error::Network {
+                kind,
+                status,
+                url: Some(url.clone()),
+                source,
+            },
+            err => err,
+        }
+    }
+
+    /// Create a manifest error
+    pub fn manifest(kind: ManifestErrorKind, detail: impl Into<String>) -> Self {
+        Error::Manifest { kind, detail: detail.into() }
+    }
+
+    /// Create a manifest parse error
+    pub fn parse(detail: impl Into<String>) -> Self {
+        Error::manifest(ManifestErrorKind::ParseError, detail)
+    }
+
+    /// Create an invalid manifest error
+    pub fn invalid_manifest(detail: impl Into<String>) -> Self {
+        Error::manifest(ManifestErrorKind::Invalid, detail)
+    }
+
     /// Create a DRM error
-    pub fn drm(msg: impl Into<String>) -> Self {
-        Error::LicenseAcquisition(msg.into())
+    pub fn drm(system: Option<DrmSystem>, kind: DrmErrorKind, detail: impl Into<String>) -> Self {
+        Error::Drm { system, kind, detail: detail.into() }
+    }
+
+    /// Create a buffer error
+    pub fn buffer(kind: BufferErrorKind, position: f64) -> Self {
+        Error::Buffer { kind, position }
+    }
+
+    /// HTTP status of a network error, if the server answered
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::Network { status, .. } => *status,
+            _ => None,
+        }
+    }
+
+    /// URL a network or segment error occurred on, if known
+    pub fn url(&self) -> Option<&Url> {
+        match self {
+            Error::Network { url, .. } => url.as_ref(),
+            Error::SegmentIntegrity { url, .. } => Some(url),
+            _ => None,
+        }
     }
 
-    /// Returns true if this error is recoverable
-    pub fn is_recoverable(&self) -> bool {
-        matches!(
-            self,
-            Error::SegmentFetch { .. }
-                | Error::SegmentTimeout { .. }
-                | Error::BufferUnderrun
-                | Error::Network(_)
-                | Error::ConnectionTimeout
-        )
+    /// Returns true if retrying the same operation may succeed
+    ///
+    /// Timeouts, dropped connections, 408, 429 and 5xx responses, corrupt
+    /// segments, stalls and underruns are transient. Client errors such as a 404, parse failures,
+    /// DRM and configuration errors are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Network { kind, status, .. } => match kind {
+                NetworkErrorKind::Timeout
+                | NetworkErrorKind::Dns
+                | NetworkErrorKind::Connect
+                | NetworkErrorKind::Body => true,
+                NetworkErrorKind::Tls => false,
+                NetworkErrorKind::Http => {
+                    status.is_none_or(|s| s == 408 || s == 429 || s >= 500)
+                }
+            },
+            Error::SegmentIntegrity { .. } => true,
+            Error::Buffer { kind, .. } => *kind == BufferErrorKind::Underrun,
+            Error::PlaybackStalled => true,
+            _ => false,
+        }
     }
 
     /// Returns the error code for analytics
+    ///
+    /// Codes are stable across releases so dashboards can aggregate them.
     pub fn error_code(&self) -> &'static str {
         match self {
-            Error::ManifestFetch(_) => "MANIFEST_FETCH",
-            Error::ManifestParse(_) => "MANIFEST_PARSE",
-            Error::InvalidManifest(_) => "INVALID_MANIFEST",
+            Error::Network { kind, .. } => kind.code(),
+            Error::Manifest { kind, .. } => match kind {
+                ManifestErrorKind::ParseError => "MANIFEST_PARSE",
+                ManifestErrorKind::Invalid => "MANIFEST_INVALID",
+                ManifestErrorKind::Unsupported => "MANIFEST_UNSUPPORTED",
+            },
             Error::NoSuitableRendition => "NO_RENDITION",
-            Error::SegmentFetch { .. } => "SEGMENT_FETCH",
-            Error::SegmentTimeout { .. } => "SEGMENT_TIMEOUT",
-            Error::SegmentDecryption => "SEGMENT_DECRYPT",
-            Error::BufferUnderrun => "BUFFER_UNDERRUN",
-            Error::BufferOverflow => "BUFFER_OVERFLOW",
-            Error::BufferSeekFailed { .. } => "BUFFER_SEEK",
-            Error::DrmNotSupported { .. } => "DRM_UNSUPPORTED",
-            Error::LicenseAcquisition(_) => "LICENSE_ACQUIRE",
-            Error::LicenseExpired => "LICENSE_EXPIRED",
-            Error::ContentKeyNotFound => "KEY_NOT_FOUND",
+            Error::SegmentIntegrity { .. } => "SEGMENT_INTEGRITY",
+            Error::Drm { kind, .. } => match kind {
+                DrmErrorKind::Unsupported => "DRM_UNSUPPORTED",
+                DrmErrorKind::Config => "DRM_CONFIG",
+                DrmErrorKind::SessionNotFound => "DRM_SESSION",
+                DrmErrorKind::License => "LICENSE_ACQUIRE",
+                DrmErrorKind::LicenseExpired => "LICENSE_EXPIRED",
+                DrmErrorKind::KeyNotFound => "KEY_NOT_FOUND",
+                DrmErrorKind::Decryption => "SEGMENT_DECRYPT",
+            },
+            Error::Buffer { kind, .. } => match kind {
+                BufferErrorKind::Underrun => "BUFFER_UNDERRUN",
+                BufferErrorKind::Overflow => "BUFFER_OVERFLOW",
+                BufferErrorKind::SeekFailed => "BUFFER_SEEK",
+            },
             Error::PlaybackStalled => "PLAYBACK_STALLED",
             Error::InvalidStateTransition { .. } => "INVALID_STATE",
             Error::CodecNotSupported { .. } => "CODEC_UNSUPPORTED",
-            Error::Network(_) => "NETWORK",
-            Error::ConnectionTimeout => "TIMEOUT",
+            Error::TrackNotFound { .. } => "TRACK_NOT_FOUND",
+            Error::AnalyticsSink(_) => "ANALYTICS_SINK",
             Error::InvalidConfig(_) => "INVALID_CONFIG",
             Error::Internal(_) => "INTERNAL",
             Error::Io(_) => "IO",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url() -> Url {
+        Url::parse("https://cdn.example.com/master.m3u8").unwrap()
+    }
+
+    #[test]
+    fn test_network_retryability() {
+        assert!(!Error::http_status(404, url()).is_retryable());
+        assert!(!Error::http_status(403, url()).is_retryable());
+        assert!(Error::http_status(429, url()).is_retryable());
+        assert!(Error::http_status(503, url()).is_retryable());
+        assert!(Error::network(NetworkErrorKind::Timeout, Some(url())).is_retryable());
+        assert!(!Error::network(NetworkErrorKind::Tls, None).is_retryable());
+
+        assert!(!Error::parse("bad playlist").is_retryable());
+        assert!(!Error::drm(None, DrmErrorKind::Decryption, "bad padding").is_retryable());
+        assert!(Error::buffer(BufferErrorKind::Underrun, 12.0).is_retryable());
+    }
+
+    #[test]
+    fn test_error_codes_and_messages() {
+        let err = Error::http_status(404, url());
+        assert_eq!(err.error_code(), "NETWORK_HTTP");
+        assert_eq!(err.status(), Some(404));
+        assert_eq!(err.to_string(), "HTTP 404 for https://cdn.example.com/master.m3u8");
+
+        let err = Error::network(NetworkErrorKind::Timeout, Some(url()));
+        assert_eq!(err.error_code(), "NETWORK_TIMEOUT");
+        assert_eq!(err.to_string(), "Request timed out for https://cdn.example.com/master.m3u8");
+
+        let err = Error::drm(Some(DrmSystem::Widevine), DrmErrorKind::Config, "license URL not set");
+        assert_eq!(err.error_code(), "DRM_CONFIG");
+        assert_eq!(err.to_string(), "DRM not configured (Widevine): license URL not set");
+
+        let err = Error::parse("missing #EXTM3U");
+        assert_eq!(err.to_string(), "Failed to parse manifest: missing #EXTM3U");
+    }
+}
@@ -0,0 +1,119 @@
+//! Browser fetcher backed by the Fetch API
+
+use super::{range_header, HttpFetcher, HttpResponse, ResponseBody};
+use crate::error::{Error, NetworkErrorKind, Result};
+use crate::types::ByteRange;
+use async_trait::async_trait;
+use bytes::Bytes;
+use js_sys::{Array, Promise, Uint8Array};
+use std::collections::HashMap;
+use url::Url;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Headers, ReadableStreamDefaultReader, ReadableStreamReadResult, Request, RequestInit, Response, WorkerGlobalScope};
+
+/// [`HttpFetcher`] using the Fetch API of a window or worker
+///
+/// Timeouts, caching and CORS follow the browser's defaults.
+#[derive(Debug, Clone, Default)]
+pub struct FetchApiFetcher;
+
+impl FetchApiFetcher {
+    pub fn new() -> Self {
+        Self
+    }
+
+    async fn send(&self, url: &Url, init: RequestInit, headers: &HashMap<String, String>, range: Option<ByteRange>) -> Result<HttpResponse> {
+        let failed = |_: JsValue| Error::network(NetworkErrorKind::Connect, Some(url.clone()));
+
+        let request_headers = Headers::new().map_err(failed)?;
+        for (name, value) in headers {
+            request_headers.set(name, value).map_err(failed)?;
+        }
+        if let Some(range) = range {
+            request_headers.set("Range", &range_header(&range)).map_err(failed)?;
+        }
+        init.set_headers(&request_headers);
+
+        let request = Request::new_with_str_and_init(url.as_str(), &init).map_err(failed)?;
+        let response: Response = JsFuture::from(global_fetch(&request)?)
+            .await
+            .and_then(|response| response.dyn_into())
+            .map_err(failed)?;
+
+        let mut headers = HashMap::new();
+        for entry in js_sys::try_iter(&response.headers()).ok().flatten().into_iter().flatten().flatten() {
+            let pair = Array::from(&entry);
+            if let (Some(name), Some(value)) = (pair.get(0).as_string(), pair.get(1).as_string()) {
+                headers.insert(name.to_lowercase(), value);
+            }
+        }
+
+        let reader = match response.body() {
+            Some(stream) => Some(stream.get_reader().dyn_into::<ReadableStreamDefaultReader>().map_err(|_| body_error(url))?),
+            None => None,
+        };
+        let body = FetchBody { url: url.clone(), reader };
+        Ok(HttpResponse::new(url.clone(), response.status(), headers, Box::new(body)))
+    }
+}
+
+#[async_trait(?Send)]
+impl HttpFetcher for FetchApiFetcher {
+    async fn get(&self, url: &Url, headers: &HashMap<String, String>, range: Option<ByteRange>) -> Result<HttpResponse> {
+        let init = RequestInit::new();
+        init.set_method("GET");
+        self.send(url, init, headers, range).await
+    }
+
+    async fn post(&self, url: &Url, headers: &HashMap<String, String>, body: Bytes) -> Result<HttpResponse> {
+        let init = RequestInit::new();
+        init.set_method("POST");
+        init.set_body(&Uint8Array::from(body.as_ref()));
+        self.send(url, init, headers, None).await
+    }
+}
+
+/// `fetch` of the window, or of the worker the player runs in
+fn global_fetch(request: &Request) -> Result<Promise> {
+    let global = js_sys::global();
+    if let Some(window) = global.dyn_ref::<web_sys::Window>() {
+        return Ok(window.fetch_with_request(request));
+    }
+    if let Some(worker) = global.dyn_ref::<WorkerGlobalScope>() {
+        return Ok(worker.fetch_with_request(request));
+    }
+    Err(Error::Internal("Fetch API is not available in this context".to_string()))
+}
+
+/// Error for a body that failed partway through
+fn body_error(url: &Url) -> Error {
+    Error::network(NetworkErrorKind::Body, Some(url.clone()))
+}
+
+/// Body read from the response's ReadableStream
+struct FetchBody {
+    url: Url,
+    /// `None` for responses without a body, and once the body is complete
+    reader: Option<ReadableStreamDefaultReader>,
+}
+
+#[async_trait(?Send)]
+impl ResponseBody for FetchBody {
+    async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        let Some(reader) = &self.reader else {
+            return Ok(None);
+        };
+        let result: ReadableStreamReadResult = JsFuture::from(reader.read())
+            .await
+            .map_err(|_| body_error(&self.url))?
+            .unchecked_into();
+
+        if result.get_done().unwrap_or(true) {
+            self.reader = None;
+            return Ok(None);
+        }
+        let chunk = result.get_value().dyn_into::<Uint8Array>().map_err(|_| body_error(&self.url))?;
+        Ok(Some(Bytes::from(chunk.to_vec())))
+    }
+}
@@ -0,0 +1,252 @@
+//! HTTP fetching shared by manifests, segments, steering and licenses
+//!
+//! Everything the player downloads goes through an [`HttpFetcher`], so the
+//! same parsing and download logic runs in the CLI, the desktop apps and
+//! the browser:
+//! - [`ReqwestFetcher`] is the native implementation
+//! - `FetchApiFetcher` uses the browser Fetch API on `wasm32` with the
+//!   `wasm-fetch` feature
+//! - [`ScriptedFetcher`] answers from canned responses, so tests don't
+//!   need an HTTP server
+//!
+//! On native targets fetchers and their futures are `Send`, so an
+//! `Arc<dyn HttpFetcher>` can move into multi-threaded tokio tasks. On
+//! `wasm32` the futures are not required to be `Send`, since JavaScript
+//! promises never leave the thread that created them.
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm-fetch"))]
+mod fetch;
+mod native;
+mod scripted;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm-fetch"))]
+pub use fetch::FetchApiFetcher;
+pub use native::ReqwestFetcher;
+pub use scripted::{RecordedRequest, ScriptedFetcher};
+
+use crate::error::{Error, Result};
+use crate::types::ByteRange;
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+/// `Send` on native targets; nothing on `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// `Send` on native targets; nothing on `wasm32`
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// Issues HTTP requests for the player
+///
+/// Fetchers only move bytes: a 4xx or 5xx answer is a successful fetch,
+/// turned into an error by [`HttpResponse::error_for_status`]. Failures to
+/// reach the server are [`Error::Network`] errors.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait HttpFetcher: Send + Sync {
+    /// GET `url`, or only `range` of it
+    async fn get(&self, url: &Url, headers: &HashMap<String, String>, range: Option<ByteRange>) -> Result<HttpResponse>;
+
+    /// POST `body` to `url`
+    async fn post(&self, url: &Url, headers: &HashMap<String, String>, body: Bytes) -> Result<HttpResponse>;
+
+    /// GET `url` and read it as text, failing on error statuses
+    async fn get_text(&self, url: &Url) -> Result<String> {
+        self.get(url, &HashMap::new(), None).await?.error_for_status()?.text().await
+    }
+}
+
+/// Body of an [`HttpResponse`], read chunk by chunk
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait ResponseBody: MaybeSend {
+    /// Next chunk of the body, or `None` once it is complete
+    async fn chunk(&mut self) -> Result<Option<Bytes>>;
+}
+
+/// Body already in memory, returned as a single chunk
+struct FullBody(Option<Bytes>);
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ResponseBody for FullBody {
+    async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        Ok(self.0.take())
+    }
+}
+
+/// Response to an [`HttpFetcher`] request
+pub struct HttpResponse {
+    /// URL that was requested
+    pub url: Url,
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers (lowercase names)
+    pub headers: HashMap<String, String>,
+    body: Box<dyn ResponseBody>,
+}
+
+impl HttpResponse {
+    /// Create a response whose body is read from `body`
+    pub fn new(url: Url, status: u16, headers: HashMap<String, String>, body: Box<dyn ResponseBody>) -> Self {
+        Self { url, status, headers, body }
+    }
+
+    /// Create a response with a body already in memory
+    pub fn from_bytes(url: Url, status: u16, headers: HashMap<String, String>, body: impl Into<Bytes>) -> Self {
+        Self::new(url, status, headers, Box::new(FullBody(Some(body.into()))))
+    }
+
+    /// Check if the status code indicates success
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Turn a non-2xx status into an [`Error::Network`] HTTP error
+    pub fn error_for_status(self) -> Result<Self> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(Error::http_status(self.status, self.url))
+        }
+    }
+
+    /// Value of a header, by case-insensitive name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    /// Body length announced by the Content-Length header
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("content-length")?.trim().parse().ok()
+    }
+
+    /// Next chunk of the body, or `None` once it is complete
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        self.body.chunk().await
+    }
+
+    /// Read the whole body
+    pub async fn bytes(mut self) -> Result<Bytes> {
+        let Some(first) = self.chunk().await? else {
+            return Ok(Bytes::new());
+        };
+        let Some(second) = self.chunk().await? else {
+            return Ok(first);
+        };
+
+        let mut body = BytesMut::with_capacity(self.content_length().unwrap_or(0) as usize);
+        body.extend_from_slice(&first);
+        body.extend_from_slice(&second);
+        while let Some(chunk) = self.chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.freeze())
+    }
+
+    /// Read the whole body as text, replacing invalid UTF-8
+    pub async fn text(self) -> Result<String> {
+        let body = self.bytes().await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+}
+
+impl fmt::Debug for HttpResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("url", &self.url.as_str())
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Fetcher for the current platform, with a per-request timeout where the
+/// platform supports one
+pub fn default_fetcher(timeout: Duration) -> Arc<dyn HttpFetcher> {
+    #[cfg(all(target_arch = "wasm32", feature = "wasm-fetch"))]
+    {
+        let _ = timeout;
+        Arc::new(FetchApiFetcher::new())
+    }
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm-fetch")))]
+    {
+        Arc::new(ReqwestFetcher::new(timeout))
+    }
+}
+
+/// Range header value for `range`
+pub fn range_header(range: &ByteRange) -> String {
+    format!("bytes={}-{}", range.start, range.end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Body delivered in fixed chunks
+    struct Chunked(std::collections::VecDeque<Bytes>);
+
+    #[async_trait]
+    impl ResponseBody for Chunked {
+        async fn chunk(&mut self) -> Result<Option<Bytes>> {
+            Ok(self.0.pop_front())
+        }
+    }
+
+    fn url() -> Url {
+        Url::parse("https://cdn.example.com/seg1.ts").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_response_body() {
+        let chunks = [&b"ab"[..], b"cd", b"ef"].into_iter().map(Bytes::from_static).collect();
+        let headers = HashMap::from([("content-length".to_string(), "6".to_string())]);
+        let response = HttpResponse::new(url(), 200, headers, Box::new(Chunked(chunks)));
+        assert_eq!(response.content_length(), Some(6));
+        assert_eq!(response.header("Content-Length"), Some("6"));
+        assert_eq!(response.bytes().await.unwrap(), Bytes::from_static(b"abcdef"));
+
+        let empty = HttpResponse::new(url(), 204, HashMap::new(), Box::new(Chunked(Default::default())));
+        assert!(empty.bytes().await.unwrap().is_empty());
+
+        let invalid = HttpResponse::from_bytes(url(), 200, HashMap::new(), vec![b'o', b'k', 0xff]);
+        assert_eq!(invalid.text().await.unwrap(), "ok\u{fffd}");
+    }
+
+    #[test]
+    fn test_error_for_status() {
+        let ok = HttpResponse::from_bytes(url(), 206, HashMap::new(), "");
+        assert!(ok.error_for_status().is_ok());
+
+        let err = HttpResponse::from_bytes(url(), 503, HashMap::new(), "").error_for_status().unwrap_err();
+        assert_eq!(err.status(), Some(503));
+        assert_eq!(err.url(), Some(&url()));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_range_header() {
+        assert_eq!(range_header(&ByteRange { start: 100, length: 50 }), "bytes=100-149");
+    }
+
+    #[test]
+    fn test_fetches_can_move_across_threads() {
+        fn assert_send<T: Send>(_: &T) {}
+        let fetcher = default_fetcher(Duration::from_secs(1));
+        let url = url();
+        let headers = HashMap::new();
+        assert_send(&fetcher.get(&url, &headers, None));
+        assert_send(&fetcher.get_text(&url));
+    }
+}
@@ -0,0 +1,85 @@
+//! Native fetcher backed by reqwest
+
+use super::{range_header, HttpFetcher, HttpResponse, ResponseBody};
+use crate::error::{Error, Result};
+use crate::types::ByteRange;
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{Client, RequestBuilder};
+use std::collections::HashMap;
+use std::time::Duration;
+use url::Url;
+
+/// [`HttpFetcher`] backed by a reqwest client
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestFetcher {
+    client: Client,
+}
+
+impl ReqwestFetcher {
+    /// Create a fetcher whose requests time out after `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        Self::with_client(
+            Client::builder()
+                .timeout(timeout)
+                .build()
+                .expect("Failed to create HTTP client"),
+        )
+    }
+
+    /// Create a fetcher using an existing HTTP client
+    pub fn with_client(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// The underlying HTTP client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    async fn send(&self, url: &Url, mut request: RequestBuilder, headers: &HashMap<String, String>) -> Result<HttpResponse> {
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let response = request.send().await.map_err(|e| Error::request(e, url))?;
+
+        let status = response.status().as_u16();
+        let headers = response.headers()
+            .iter()
+            .filter_map(|(k, v)| Some((k.as_str().to_lowercase(), v.to_str().ok()?.to_string())))
+            .collect();
+        let body = ReqwestBody { url: url.clone(), response };
+        Ok(HttpResponse::new(url.clone(), status, headers, Box::new(body)))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl HttpFetcher for ReqwestFetcher {
+    async fn get(&self, url: &Url, headers: &HashMap<String, String>, range: Option<ByteRange>) -> Result<HttpResponse> {
+        let mut request = self.client.get(url.clone());
+        if let Some(range) = range {
+            request = request.header(reqwest::header::RANGE, range_header(&range));
+        }
+        self.send(url, request, headers).await
+    }
+
+    async fn post(&self, url: &Url, headers: &HashMap<String, String>, body: Bytes) -> Result<HttpResponse> {
+        let request = self.client.post(url.clone()).body(body);
+        self.send(url, request, headers).await
+    }
+}
+
+/// Body streamed from a reqwest response
+struct ReqwestBody {
+    url: Url,
+    response: reqwest::Response,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl ResponseBody for ReqwestBody {
+    async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        self.response.chunk().await.map_err(|e| Error::request(e, &self.url))
+    }
+}
@@ -0,0 +1,159 @@
+//! Fetcher answering from canned responses
+
+use super::{HttpFetcher, HttpResponse};
+use crate::error::{Error, NetworkErrorKind, Result};
+use crate::types::ByteRange;
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use url::Url;
+
+/// A request seen by a [`ScriptedFetcher`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    /// `GET` or `POST`
+    pub method: &'static str,
+    pub url: Url,
+    pub headers: HashMap<String, String>,
+    pub range: Option<ByteRange>,
+    pub body: Bytes,
+}
+
+#[derive(Debug, Clone)]
+enum Reply {
+    Response { status: u16, headers: HashMap<String, String>, body: Bytes },
+    Error(NetworkErrorKind),
+}
+
+/// [`HttpFetcher`] that answers from canned responses and records requests
+///
+/// Replies are looked up by the full request URL, then by the URL without
+/// its query. Several replies for one URL are given in order, the last one
+/// repeating; URLs without replies get a 404. Ranged GETs of a 200 reply
+/// get the requested bytes with a 206.
+#[derive(Debug, Default)]
+pub struct ScriptedFetcher {
+    replies: Mutex<HashMap<String, VecDeque<Reply>>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+impl ScriptedFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer requests for `url` with `status` and `body`
+    pub fn respond(self, url: &str, status: u16, body: impl Into<Bytes>) -> Self {
+        self.respond_with_headers(url, status, HashMap::new(), body)
+    }
+
+    /// Answer requests for `url` with `status`, `headers` and `body`
+    pub fn respond_with_headers(self, url: &str, status: u16, headers: HashMap<String, String>, body: impl Into<Bytes>) -> Self {
+        let headers = headers.into_iter().map(|(k, v)| (k.to_ascii_lowercase(), v)).collect();
+        self.push(url, Reply::Response { status, headers, body: body.into() })
+    }
+
+    /// Fail requests for `url` without a response
+    pub fn fail(self, url: &str, kind: NetworkErrorKind) -> Self {
+        self.push(url, Reply::Error(kind))
+    }
+
+    fn push(self, url: &str, reply: Reply) -> Self {
+        self.replies.lock().unwrap().entry(url.to_string()).or_default().push_back(reply);
+        self
+    }
+
+    /// Requests made so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn reply(&self, request: RecordedRequest) -> Result<HttpResponse> {
+        let url = request.url.clone();
+        let range = request.range;
+        self.requests.lock().unwrap().push(request);
+
+        let mut without_query = url.clone();
+        without_query.set_query(None);
+
+        let reply = {
+            let mut replies = self.replies.lock().unwrap();
+            let key = [url.as_str(), without_query.as_str()]
+                .into_iter()
+                .find(|key| replies.contains_key(*key));
+            key.and_then(|key| replies.get_mut(key))
+                .and_then(|queue| if queue.len() > 1 { queue.pop_front() } else { queue.front().cloned() })
+        };
+
+        match reply {
+            None => Ok(HttpResponse::from_bytes(url, 404, HashMap::new(), Bytes::new())),
+            Some(Reply::Error(kind)) => Err(Error::network(kind, Some(url))),
+            Some(Reply::Response { status, headers, body }) => match range {
+                Some(range) if status == 200 => {
+                    let start = (range.start as usize).min(body.len());
+                    let end = (range.start + range.length).min(body.len() as u64) as usize;
+                    Ok(HttpResponse::from_bytes(url, 206, headers, body.slice(start..end)))
+                }
+                _ => Ok(HttpResponse::from_bytes(url, status, headers, body)),
+            },
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl HttpFetcher for ScriptedFetcher {
+    async fn get(&self, url: &Url, headers: &HashMap<String, String>, range: Option<ByteRange>) -> Result<HttpResponse> {
+        self.reply(RecordedRequest {
+            method: "GET",
+            url: url.clone(),
+            headers: headers.clone(),
+            range,
+            body: Bytes::new(),
+        })
+    }
+
+    async fn post(&self, url: &Url, headers: &HashMap<String, String>, body: Bytes) -> Result<HttpResponse> {
+        self.reply(RecordedRequest {
+            method: "POST",
+            url: url.clone(),
+            headers: headers.clone(),
+            range: None,
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scripted_replies() {
+        let fetcher = ScriptedFetcher::new()
+            .respond("https://cdn.example.com/live.m3u8", 200, "first")
+            .respond("https://cdn.example.com/live.m3u8", 200, "second")
+            .respond("https://cdn.example.com/seg1.ts", 200, "0123456789")
+            .fail("https://cdn.example.com/down.ts", NetworkErrorKind::Timeout);
+        let url = |path: &str| Url::parse(&format!("https://cdn.example.com/{}", path)).unwrap();
+
+        // Replies are given in order, the last repeating, whatever the query
+        assert_eq!(fetcher.get_text(&url("live.m3u8")).await.unwrap(), "first");
+        assert_eq!(fetcher.get_text(&url("live.m3u8?_HLS_msn=4")).await.unwrap(), "second");
+        assert_eq!(fetcher.get_text(&url("live.m3u8")).await.unwrap(), "second");
+
+        let range = ByteRange { start: 2, length: 3 };
+        let partial = fetcher.get(&url("seg1.ts"), &HashMap::new(), Some(range)).await.unwrap();
+        assert_eq!(partial.status, 206);
+        assert_eq!(partial.bytes().await.unwrap(), Bytes::from_static(b"234"));
+
+        assert_eq!(fetcher.get(&url("missing.ts"), &HashMap::new(), None).await.unwrap().status, 404);
+        let err = fetcher.get(&url("down.ts"), &HashMap::new(), None).await.unwrap_err();
+        assert!(err.is_retryable());
+
+        let requests = fetcher.requests();
+        assert_eq!(requests.len(), 6);
+        assert_eq!(requests[3].range, Some(range));
+    }
+}
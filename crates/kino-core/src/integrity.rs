@@ -0,0 +1,363 @@
+//! Segment integrity verification
+//!
+//! CDNs occasionally serve truncated segments that decode into garbage
+//! mid-stream. [`SegmentVerifier`] checks downloaded bytes against the
+//! response's Content-Length and the segment's byte range, rejects
+//! implausibly small segments under [`IntegrityPolicy::Strict`], and can
+//! verify a SHA-256 digest published in a sidecar checksum file.
+
+use crate::error::{Error, Result};
+use crate::http::HttpFetcher;
+use crate::types::Segment;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+use url::Url;
+
+/// Template placeholders that differ between segments
+const SEGMENT_PLACEHOLDERS: [&str; 3] = ["{uri}", "{name}", "{number}"];
+
+/// How strictly downloaded segments are checked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityPolicy {
+    /// No checks
+    #[default]
+    Off,
+    /// Check sizes, and checksums when a sidecar is available
+    Lenient,
+    /// Also reject implausibly small segments, and segments without a
+    /// checksum when a sidecar template is configured
+    Strict,
+}
+
+/// Segment integrity settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SegmentIntegrity {
+    /// Checks to run on downloaded segments
+    pub policy: IntegrityPolicy,
+    /// Sidecar checksum URL, resolved against the segment URL
+    ///
+    /// `{uri}` expands to the segment URL, `{name}` to its file name and
+    /// `{number}` to its media sequence number, e.g. `{name}.sha256` or a
+    /// shared `checksums.sha256`. Sidecars hold a hex SHA-256, or
+    /// `sha256sum` lines matched by file name.
+    pub checksum_url_template: Option<String>,
+    /// Smallest plausible size of a segment lasting a second or more
+    pub min_segment_bytes: usize,
+    /// Times a segment failing verification is fetched again
+    pub max_refetches: u32,
+}
+
+impl Default for SegmentIntegrity {
+    fn default() -> Self {
+        Self {
+            policy: IntegrityPolicy::Off,
+            checksum_url_template: None,
+            min_segment_bytes: 1024,
+            max_refetches: 2,
+        }
+    }
+}
+
+/// Why a segment failed verification
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntegrityFailure {
+    /// Body length differs from the response's Content-Length
+    ContentLength { expected: u64, actual: u64 },
+    /// Fewer bytes than the segment's byte range
+    ByteRange { expected: u64, actual: u64 },
+    /// Implausibly small for the segment's duration
+    Truncated { actual: u64, duration: f64 },
+    /// SHA-256 digest differs from the sidecar
+    Checksum { expected: String, actual: String },
+    /// The sidecar lists no checksum for the segment
+    MissingChecksum,
+}
+
+impl IntegrityFailure {
+    /// Stable name of the failure kind, for analytics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            IntegrityFailure::ContentLength { .. } => "content_length",
+            IntegrityFailure::ByteRange { .. } => "byte_range",
+            IntegrityFailure::Truncated { .. } => "truncated",
+            IntegrityFailure::Checksum { .. } => "checksum",
+            IntegrityFailure::MissingChecksum => "missing_checksum",
+        }
+    }
+}
+
+impl fmt::Display for IntegrityFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityFailure::ContentLength { expected, actual } => {
+                write!(f, "received {} bytes, Content-Length was {}", actual, expected)
+            }
+            IntegrityFailure::ByteRange { expected, actual } => {
+                write!(f, "received {} bytes for a {} byte range", actual, expected)
+            }
+            IntegrityFailure::Truncated { actual, duration } => {
+                write!(f, "{} bytes is too small for a {:.1}s segment", actual, duration)
+            }
+            IntegrityFailure::Checksum { expected, actual } => {
+                write!(f, "SHA-256 {} does not match {}", actual, expected)
+            }
+            IntegrityFailure::MissingChecksum => f.write_str("no checksum in sidecar"),
+        }
+    }
+}
+
+/// Check a segment of `len` bytes for obvious truncation
+///
+/// Only applies under the strict policy, to segments of a second or more.
+pub fn check_truncated(segment: &Segment, len: usize, config: &SegmentIntegrity) -> Option<IntegrityFailure> {
+    let duration = segment.duration.as_secs_f64();
+    (config.policy == IntegrityPolicy::Strict && duration >= 1.0 && len < config.min_segment_bytes)
+        .then_some(IntegrityFailure::Truncated { actual: len as u64, duration })
+}
+
+/// Check a downloaded segment's size against its response and playlist
+pub fn check_size(
+    segment: &Segment,
+    content_length: Option<u64>,
+    len: usize,
+    config: &SegmentIntegrity,
+) -> Option<IntegrityFailure> {
+    if config.policy == IntegrityPolicy::Off {
+        return None;
+    }
+
+    let actual = len as u64;
+    if let Some(expected) = content_length.filter(|&expected| expected != actual) {
+        return Some(IntegrityFailure::ContentLength { expected, actual });
+    }
+    // Byte-range segments may arrive as the whole resource, so only a
+    // shortfall is an error
+    if let Some(range) = segment.byte_range.as_ref().filter(|r| actual < r.length) {
+        return Some(IntegrityFailure::ByteRange { expected: range.length, actual });
+    }
+    check_truncated(segment, len, config)
+}
+
+/// Hex SHA-256 of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Sidecar URL for the segment at `url`
+pub fn checksum_url(template: &str, url: &Url, number: u64) -> Result<Url> {
+    let expanded = template
+        .replace("{uri}", url.as_str())
+        .replace("{name}", file_name(url))
+        .replace("{number}", &number.to_string());
+    url.join(&expanded)
+        .map_err(|e| Error::InvalidConfig(format!("Invalid checksum URL '{}': {}", expanded, e)))
+}
+
+/// Checksum for `name` in a sidecar
+///
+/// A sidecar with a single bare digest applies to any segment; otherwise
+/// `sha256sum` lines (`<hex>  <name>`, optionally `*<name>`) are matched
+/// by file name.
+pub fn parse_checksum(sidecar: &str, name: &str) -> Option<String> {
+    let entries: Vec<(&str, Option<&str>)> = sidecar
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let digest = fields.next()?;
+            let file = fields.next().map(|f| f.trim_start_matches('*'));
+            Some((digest, file))
+        })
+        .collect();
+
+    let digest = match entries.as_slice() {
+        [(digest, None)] => digest,
+        _ => entries.iter()
+            .find(|(_, file)| file.is_some_and(|f| f == name || f.rsplit('/').next() == Some(name)))
+            .map(|(digest, _)| digest)?,
+    };
+    Some(digest.to_lowercase())
+}
+
+fn file_name(url: &Url) -> &str {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or("")
+}
+
+/// Verifies downloaded segments, caching shared sidecar checksum files
+pub struct SegmentVerifier {
+    fetcher: Arc<dyn HttpFetcher>,
+    config: SegmentIntegrity,
+    sidecars: RwLock<HashMap<Url, String>>,
+}
+
+impl SegmentVerifier {
+    /// Create a verifier that fetches sidecars with `fetcher`
+    pub fn new(fetcher: Arc<dyn HttpFetcher>, config: SegmentIntegrity) -> Self {
+        Self {
+            fetcher,
+            config,
+            sidecars: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Integrity settings
+    pub fn config(&self) -> &SegmentIntegrity {
+        &self.config
+    }
+
+    /// Verify a segment downloaded from `url`
+    ///
+    /// Fails with [`Error::SegmentIntegrity`] when a check fails. A
+    /// sidecar that can't be fetched is skipped with a warning, except
+    /// under the strict policy.
+    pub async fn verify(&self, segment: &Segment, url: &Url, content_length: Option<u64>, data: &[u8]) -> Result<()> {
+        let fail = |failure| Error::SegmentIntegrity { url: url.clone(), failure: Box::new(failure) };
+
+        if let Some(failure) = check_size(segment, content_length, data.len(), &self.config) {
+            return Err(fail(failure));
+        }
+
+        let Some(template) = self.config.checksum_url_template.as_deref() else {
+            return Ok(());
+        };
+        if self.config.policy == IntegrityPolicy::Off {
+            return Ok(());
+        }
+
+        let strict = self.config.policy == IntegrityPolicy::Strict;
+        let expected = match self.expected_checksum(template, segment, url).await {
+            Ok(Some(expected)) => expected,
+            Ok(None) if strict => return Err(fail(IntegrityFailure::MissingChecksum)),
+            Err(e) if strict => return Err(e),
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                warn!(error = %e, segment = segment.number, "Skipping segment checksum");
+                return Ok(());
+            }
+        };
+
+        let actual = sha256_hex(data);
+        if actual != expected {
+            return Err(fail(IntegrityFailure::Checksum { expected, actual }));
+        }
+        Ok(())
+    }
+
+    async fn expected_checksum(&self, template: &str, segment: &Segment, url: &Url) -> Result<Option<String>> {
+        let sidecar_url = checksum_url(template, url, segment.number)?;
+
+        let cached = self.sidecars.read().unwrap().get(&sidecar_url).cloned();
+        let sidecar = match cached {
+            Some(sidecar) => sidecar,
+            None => {
+                let sidecar = self.fetcher.get_text(&sidecar_url).await?;
+                // Per-segment sidecars are used once, so only shared ones are kept
+                if !SEGMENT_PLACEHOLDERS.iter().any(|p| template.contains(p)) {
+                    self.sidecars.write().unwrap().insert(sidecar_url, sidecar.clone());
+                }
+                sidecar
+            }
+        };
+
+        Ok(parse_checksum(&sidecar, file_name(url)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ByteRange;
+    use std::time::Duration;
+
+    fn segment(duration: f64, byte_range: Option<ByteRange>) -> Segment {
+        Segment {
+            number: 7,
+            uri: Url::parse("https://cdn.example.com/vod/720p/seg7.ts").unwrap(),
+            duration: Duration::from_secs_f64(duration),
+            byte_range,
+            encryption: None,
+            discontinuity_sequence: 0,
+            program_date_time: None,
+        }
+    }
+
+    fn config(policy: IntegrityPolicy) -> SegmentIntegrity {
+        SegmentIntegrity { policy, ..Default::default() }
+    }
+
+    #[test]
+    fn test_check_size() {
+        let lenient = config(IntegrityPolicy::Lenient);
+        let strict = config(IntegrityPolicy::Strict);
+        let seg = segment(6.0, None);
+
+        assert_eq!(check_size(&seg, Some(100), 40, &config(IntegrityPolicy::Off)), None);
+        assert_eq!(
+            check_size(&seg, Some(100), 40, &lenient),
+            Some(IntegrityFailure::ContentLength { expected: 100, actual: 40 })
+        );
+
+        // Tiny but complete segments pass unless the policy is strict
+        assert_eq!(check_size(&seg, Some(40), 40, &lenient), None);
+        assert_eq!(check_size(&seg, Some(40), 40, &strict).map(|f| f.kind()), Some("truncated"));
+        assert_eq!(check_size(&segment(0.5, None), None, 40, &strict), None);
+
+        let ranged = segment(6.0, Some(ByteRange { start: 0, length: 5000 }));
+        assert_eq!(check_size(&ranged, None, 8000, &lenient), None);
+        assert_eq!(
+            check_size(&ranged, None, 4000, &lenient),
+            Some(IntegrityFailure::ByteRange { expected: 5000, actual: 4000 })
+        );
+    }
+
+    #[test]
+    fn test_checksum_sidecars() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let url = Url::parse("https://cdn.example.com/vod/720p/seg7.ts").unwrap();
+        assert_eq!(
+            checksum_url("{name}.sha256", &url, 7).unwrap().as_str(),
+            "https://cdn.example.com/vod/720p/seg7.ts.sha256"
+        );
+        assert_eq!(
+            checksum_url("../checksums/{number}.txt", &url, 7).unwrap().as_str(),
+            "https://cdn.example.com/vod/checksums/7.txt"
+        );
+
+        assert_eq!(parse_checksum("ABCDEF\n", "seg7.ts").as_deref(), Some("abcdef"));
+        let listing = "aaaa  seg6.ts\nbbbb *720p/seg7.ts\n";
+        assert_eq!(parse_checksum(listing, "seg7.ts").as_deref(), Some("bbbb"));
+        assert_eq!(parse_checksum(listing, "seg8.ts"), None);
+    }
+
+    #[tokio::test]
+    async fn test_verifier_caches_shared_sidecar() {
+        let data = vec![0x47u8; 2048];
+        let listing = format!("{}  seg7.ts\n", sha256_hex(&data));
+        let fetcher = Arc::new(crate::http::ScriptedFetcher::new()
+            .respond("https://cdn.example.com/vod/720p/checksums.sha256", 200, listing));
+        let verifier = SegmentVerifier::new(fetcher.clone(), SegmentIntegrity {
+            checksum_url_template: Some("checksums.sha256".to_string()),
+            ..config(IntegrityPolicy::Strict)
+        });
+
+        let segment = segment(4.0, None);
+        for _ in 0..2 {
+            verifier.verify(&segment, &segment.uri, Some(2048), &data).await.unwrap();
+        }
+        assert_eq!(fetcher.requests().len(), 1);
+
+        let err = verifier.verify(&segment, &segment.uri, None, &[0x47; 1500]).await.unwrap_err();
+        assert!(matches!(err, Error::SegmentIntegrity { ref failure, .. } if matches!(**failure, IntegrityFailure::Checksum { .. })));
+    }
+}
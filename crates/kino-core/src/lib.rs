@@ -4,9 +4,13 @@
 //! - HLS manifest parsing and segment management
 //! - DASH MPD parsing and adaptation
 //! - Adaptive bitrate (ABR) algorithms
-//! - Buffer management with prefetching
+//! - Buffer management with prefetching and optional disk spillover
 //! - Analytics event emission
 //! - DRM license acquisition (optional)
+//! - Multi-CDN content steering
+//! - Embedded CEA-608/708 caption extraction
+//! - Offline downloads of HLS VOD content
+//! - Pluggable HTTP fetching shared by native and browser builds
 //!
 //! # Architecture
 //!
@@ -36,25 +40,48 @@
 
 pub mod error;
 pub mod types;
+pub mod http;
 pub mod manifest;
 pub mod buffer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod disk_cache;
+pub mod prefetch;
 pub mod abr;
 pub mod session;
 pub mod analytics;
 pub mod branding;
 pub mod drm;
+pub mod decrypt;
 pub mod captions;
+pub mod cea;
+pub mod steering;
+pub mod integrity;
+pub mod download;
+pub mod progress;
 
-pub use error::{Error, Result};
+pub use error::{BufferErrorKind, DrmErrorKind, Error, ManifestErrorKind, NetworkErrorKind, Result};
 pub use types::*;
-pub use manifest::{ManifestParser, HlsParser, DashParser};
+pub use http::{HttpFetcher, HttpResponse, ReqwestFetcher, ScriptedFetcher};
+pub use manifest::{ManifestParser, HlsParser, DashParser, LiveRefresh, PlaylistFreshness};
 pub use buffer::BufferManager;
+#[cfg(not(target_arch = "wasm32"))]
+pub use disk_cache::{DiskCacheConfig, DiskCacheStats};
+pub use prefetch::{PrefetchScheduler, PrefetchStats};
 pub use abr::{AbrEngine, AbrAlgorithm};
-pub use session::PlayerSession;
-pub use analytics::{AnalyticsEvent, AnalyticsEmitter};
+pub use session::{LadderChange, PlayerSession};
+pub use analytics::{AnalyticsEvent, AnalyticsEmitter, AnalyticsSink, CsvWriter, HttpSink, JsonlFileSink, SessionRecorder};
 pub use branding::{KinoColors, KinoTheme, JsTheme, CssVariables};
-pub use drm::{DrmConfig, DrmManager, DrmSession, PsshBox};
-pub use captions::{WebVttParser, SrtParser};
+pub use drm::{
+    DrmConfig, DrmManager, DrmSession, HttpLicenseTransport, LicenseStore, LicenseTransport,
+    MemoryLicenseStore, PsshBox, StoredLicense,
+};
+pub use captions::{chapters_to_vtt, load_text_track, parse_text_track, ActiveCues, WebVttParser, SrtParser};
+pub use cea::CaptionExtractor;
+pub use decrypt::SegmentDecryptor;
+pub use steering::{ContentSteering, PathwaySelector, SteeringClient};
+pub use integrity::{IntegrityPolicy, SegmentIntegrity, SegmentVerifier};
+pub use progress::{JsonFileProgressStore, WatchProgress, WatchProgressStore};
+pub use download::{DownloadConfig, DownloadHandle, DownloadManager, DownloadProgress, DownloadStatus, OfflineContent, RenditionPolicy};
 
 /// Library version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
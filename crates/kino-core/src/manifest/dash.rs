@@ -4,37 +4,72 @@
 //! - MPD (Media Presentation Description)
 //! - SegmentTemplate and SegmentList
 //! - AdaptationSets and Representations
-//! - Period handling
+//! - Multiple Periods, e.g. ad-stitched streams
+//! - Multiple BaseURLs (serviceLocation) and ContentSteering
+//!
+//! Each Period becomes a [`Period`] with its own renditions and segments.
+//! Segment numbers continue across periods while the discontinuity
+//! sequence increments at every period boundary, so decoders reset when an
+//! ad with different codec settings starts. With several periods,
+//! rendition ids are prefixed with the period id (`ad-1/video_720`) to keep
+//! them unique.
 
 use crate::{
     error::Error,
+    http::{self, HttpFetcher, ReqwestFetcher},
+    steering::{ContentSteering, Pathway},
     types::*,
     Result,
 };
-use super::{Manifest, ManifestParser, ManifestType};
+use super::{LiveRefresh, Manifest, ManifestParser, ManifestType, PlaylistCache, Period};
 use async_trait::async_trait;
-use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, instrument};
 use url::Url;
 
+/// Segments generated for a SegmentTemplate without a known period length
+const DEFAULT_SEGMENT_COUNT: u64 = 100;
+
+/// A Period element of an MPD with its timing resolved
+struct MpdPeriod<'a> {
+    id: String,
+    /// Period content up to the first AdaptationSet
+    header: &'a str,
+    /// Everything inside the Period element
+    body: &'a str,
+    start: Duration,
+    duration: Option<Duration>,
+}
+
+/// Numbering applied to the segments of one period
+#[derive(Debug, Clone, Copy)]
+struct SegmentNumbering {
+    /// `Segment::number` of the period's first segment
+    first_number: u64,
+    discontinuity_sequence: u32,
+}
+
 /// DASH MPD parser
 pub struct DashParser {
-    client: Client,
+    fetcher: Arc<dyn HttpFetcher>,
+    /// Last copy of each live MPD, for conditional refreshes
+    cache: PlaylistCache,
 }
 
 impl DashParser {
     pub fn new() -> Self {
-        Self {
-            client: Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()
-                .expect("Failed to create HTTP client"),
-        }
+        Self::with_fetcher(http::default_fetcher(Duration::from_secs(30)))
     }
 
-    pub fn with_client(client: Client) -> Self {
-        Self { client }
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self::with_fetcher(Arc::new(ReqwestFetcher::with_client(client)))
+    }
+
+    /// Create a parser that fetches MPDs with `fetcher`
+    pub fn with_fetcher(fetcher: Arc<dyn HttpFetcher>) -> Self {
+        Self { fetcher, cache: PlaylistCache::default() }
     }
 
     /// Parse MPD content
@@ -49,35 +84,146 @@ impl DashParser {
             .parse_duration_attr(content, "maxSegmentDuration")
             .unwrap_or(Duration::from_secs(4));
 
-        let renditions = self.extract_representations(content, base_url)?;
+        // MPD-level BaseURLs are alternative CDNs for the same content
+        let pathways = self.extract_base_urls(content, base_url)?;
+        let content_steering = self.extract_content_steering(content, base_url, &pathways)?;
+        let default_base = content_steering
+            .as_ref()
+            .and_then(|s| s.default_pathway.as_ref())
+            .and_then(|id| pathways.iter().find(|p| &p.id == id))
+            .or(pathways.first())
+            .map_or(base_url, |p| &p.base);
+
+        let periods = self.extract_periods(content, default_base, duration)?;
+        let renditions = periods[0].renditions.clone();
+        let duration = duration.or_else(|| periods.last().and_then(Period::end));
 
         Ok(Manifest {
             manifest_type: ManifestType::Dash,
             renditions,
+            iframe_renditions: Vec::new(),
+            audio_tracks: Vec::new(),
+            text_tracks: Vec::new(),
+            drm_init_data: Vec::new(),
+            content_steering,
             is_live,
             duration,
             target_duration,
+            minimum_update_period: self.parse_duration_attr(content, "minimumUpdatePeriod"),
             base_url: base_url.clone(),
+            fetched_bytes: 0,
+            periods,
         })
     }
 
-    /// Extract representations from MPD
-    fn extract_representations(&self, content: &str, base_url: &Url) -> Result<Vec<Rendition>> {
-        let mut renditions = Vec::new();
-        let mut idx = 0;
+    /// Split the MPD into Periods and resolve their start and duration
+    ///
+    /// A missing start follows on from the previous period; a missing
+    /// duration runs to the next period's start, or for the last period to
+    /// the end of the presentation.
+    fn split_periods<'a>(&self, content: &'a str, presentation_duration: Option<Duration>) -> Vec<MpdPeriod<'a>> {
+        let mut periods = Vec::new();
+        let mut next_start = Duration::ZERO;
+
+        for (index, element) in content.split("<Period").skip(1).enumerate() {
+            let Some(open_end) = element.find('>') else {
+                continue;
+            };
+            let attrs = &element[..open_end];
+            let body = &element[open_end + 1..element.find("</Period>").unwrap_or(element.len())];
+            let start = self.parse_duration_attr(attrs, "start").unwrap_or(next_start);
+            let duration = self.parse_duration_attr(attrs, "duration");
+            next_start = start + duration.unwrap_or_default();
+
+            periods.push(MpdPeriod {
+                id: self.extract_attr(attrs, "id").unwrap_or_else(|| format!("period_{}", index)),
+                header: &body[..body.find("<AdaptationSet").unwrap_or(body.len())],
+                body,
+                start,
+                duration,
+            });
+        }
 
-        // Find all Representation elements
-        for rep_match in content.split("<Representation").skip(1) {
-            if let Some(end) = rep_match.find('>') {
-                let attrs = &rep_match[..end];
+        let ends: Vec<_> = periods.iter()
+            .skip(1)
+            .map(|p| Some(p.start))
+            .chain([presentation_duration])
+            .collect();
+        for (period, end) in periods.iter_mut().zip(ends) {
+            if period.duration.is_none() {
+                period.duration = end.and_then(|end| end.checked_sub(period.start)).filter(|d| !d.is_zero());
+            }
+        }
+
+        periods
+    }
+
+    /// Parse every Period with its renditions and segments
+    fn extract_periods(&self, content: &str, base_url: &Url, presentation_duration: Option<Duration>) -> Result<Vec<Period>> {
+        let mpd_periods = self.split_periods(content, presentation_duration);
+        let prefix_ids = mpd_periods.len() > 1;
+
+        let mut periods: Vec<Period> = Vec::new();
+        let mut first_number = 1;
+        for mpd_period in &mpd_periods {
+            let numbering = SegmentNumbering {
+                first_number,
+                discontinuity_sequence: periods.len() as u32,
+            };
+            let period = self.extract_period(mpd_period, base_url, prefix_ids, numbering)?;
+            // e.g. an unresolved xlink period
+            if period.renditions.is_empty() {
+                continue;
+            }
+
+            first_number += period.segments.values().map(|s| s.len() as u64).max().unwrap_or(0);
+            periods.push(period);
+        }
+
+        if periods.is_empty() {
+            return Err(Error::invalid_manifest("No representations found in MPD".to_string()));
+        }
 
-                let bandwidth = self.extract_attr(attrs, "bandwidth")
+        Ok(periods)
+    }
+
+    /// Extract the representations of one Period and their segments
+    ///
+    /// Codec and resolution attributes may sit on the AdaptationSet, as is
+    /// common for ad periods, and are inherited by its representations.
+    fn extract_period(
+        &self,
+        period: &MpdPeriod,
+        base_url: &Url,
+        prefix_ids: bool,
+        numbering: SegmentNumbering,
+    ) -> Result<Period> {
+        let period_base = self.extract_base_url(period.header, base_url)?;
+        let mut renditions = Vec::new();
+        let mut segments = HashMap::new();
+
+        for set in period.body.split("<AdaptationSet").skip(1) {
+            let set = &set[..set.find("</AdaptationSet>").unwrap_or(set.len())];
+            let set_attrs = &set[..set.find('>').unwrap_or(set.len())];
+            let header_end = set.find("<Representation").unwrap_or(set.len());
+            let set_header = &set[..header_end];
+            let set_base = self.extract_base_url(set_header, &period_base)?;
+
+            for rep in set[header_end..].split("<Representation").skip(1) {
+                let Some(end) = rep.find('>') else {
+                    continue;
+                };
+                let attrs = &rep[..end];
+                let rep_body = &rep[..rep.find("</Representation>").unwrap_or(rep.len())];
+                let attr = |name: &str| self.extract_attr(attrs, name).or_else(|| self.extract_attr(set_attrs, name));
+
+                let bandwidth = attr("bandwidth")
                     .and_then(|s| s.parse::<u64>().ok())
                     .unwrap_or(0);
 
-                let width = self.extract_attr(attrs, "width")
+                let width = attr("width")
                     .and_then(|s| s.parse::<u32>().ok());
-                let height = self.extract_attr(attrs, "height")
+                let height = attr("height")
                     .and_then(|s| s.parse::<u32>().ok());
 
                 let resolution = match (width, height) {
@@ -85,11 +231,11 @@ impl DashParser {
                     _ => None,
                 };
 
-                let codecs = self.extract_attr(attrs, "codecs");
+                let codecs = attr("codecs");
                 let video_codec = codecs.as_ref().and_then(|c| parse_dash_video_codec(c));
                 let audio_codec = codecs.as_ref().and_then(|c| parse_dash_audio_codec(c));
 
-                let frame_rate = self.extract_attr(attrs, "frameRate")
+                let frame_rate = attr("frameRate")
                     .and_then(|s| {
                         if s.contains('/') {
                             let parts: Vec<_> = s.split('/').collect();
@@ -106,10 +252,23 @@ impl DashParser {
                     });
 
                 // Get BaseURL or construct from template
-                let uri = self.extract_base_url(rep_match, base_url)?;
+                let uri = self.extract_base_url(rep_body, &set_base)?;
+
+                let rep_id = self.extract_attr(attrs, "id")
+                    .unwrap_or_else(|| format!("rep_{}", renditions.len()));
+                let id = if prefix_ids { format!("{}/{}", period.id, rep_id) } else { rep_id.clone() };
+
+                let rep_segments = self.representation_segments(
+                    &[rep_body, set_header, period.header],
+                    Some((&rep_id, bandwidth)),
+                    &uri,
+                    period.duration,
+                    numbering,
+                )?;
+                segments.insert(id.clone(), rep_segments);
 
                 renditions.push(Rendition {
-                    id: self.extract_attr(attrs, "id").unwrap_or_else(|| format!("rep_{}", idx)),
+                    id,
                     bandwidth,
                     resolution,
                     frame_rate,
@@ -119,45 +278,94 @@ impl DashParser {
                     hdr: None,
                     language: None,
                     name: None,
+                    audio_group: None,
                 });
-
-                idx += 1;
             }
         }
 
         // Sort by bandwidth
         renditions.sort_by_key(|r| r.bandwidth);
 
-        if renditions.is_empty() {
-            return Err(Error::InvalidManifest("No representations found in MPD".to_string()));
+        Ok(Period {
+            id: period.id.clone(),
+            start: period.start,
+            duration: period.duration,
+            discontinuity_sequence: numbering.discontinuity_sequence,
+            renditions,
+            segments,
+        })
+    }
+
+    /// Extract MPD-level BaseURL elements as pathways
+    ///
+    /// Pathways are named by serviceLocation, or by host if it is absent.
+    fn extract_base_urls(&self, content: &str, base_url: &Url) -> Result<Vec<Pathway>> {
+        let header = &content[..content.find("<Period").unwrap_or(content.len())];
+        let mut pathways = Vec::new();
+
+        for element in header.split("<BaseURL").skip(1) {
+            let (Some(open_end), Some(close)) = (element.find('>'), element.find("</BaseURL>")) else {
+                continue;
+            };
+            let base = base_url.join(element[open_end + 1..close].trim())
+                .map_err(|e| Error::invalid_manifest(format!("Invalid BaseURL: {}", e)))?;
+            let id = self.extract_attr(&element[..open_end], "serviceLocation")
+                .unwrap_or_else(|| base.host_str().unwrap_or_default().to_string());
+            pathways.push(Pathway { id, base });
         }
 
-        Ok(renditions)
+        Ok(pathways)
+    }
+
+    /// Extract the ContentSteering element, or plain failover between
+    /// several BaseURLs when there is no steering server
+    fn extract_content_steering(
+        &self,
+        content: &str,
+        base_url: &Url,
+        pathways: &[Pathway],
+    ) -> Result<Option<ContentSteering>> {
+        let element = content.find("<ContentSteering").and_then(|start| {
+            let element = &content[start..];
+            let open_end = element.find('>')?;
+            let close = element.find("</ContentSteering>")?;
+            Some((&element[..open_end], element[open_end + 1..close].trim()))
+        });
+
+        let (server_uri, default_pathway) = match element {
+            Some((attrs, uri)) => {
+                let server_uri = base_url.join(uri)
+                    .map_err(|e| Error::invalid_manifest(format!("Invalid ContentSteering URI: {}", e)))?;
+                (Some(server_uri), self.extract_attr(attrs, "defaultServiceLocation"))
+            }
+            None if pathways.len() > 1 => (None, None),
+            None => return Ok(None),
+        };
+
+        Ok(Some(ContentSteering {
+            server_uri,
+            default_pathway: default_pathway.or_else(|| pathways.first().map(|p| p.id.clone())),
+            pathways: pathways.to_vec(),
+        }))
     }
 
     /// Extract attribute value from XML attributes string
+    ///
+    /// The name must start a word, so `width` does not match `bandwidth`.
     fn extract_attr(&self, attrs: &str, name: &str) -> Option<String> {
         let pattern = format!("{}=\"", name);
-        if let Some(start) = attrs.find(&pattern) {
-            let value_start = start + pattern.len();
-            if let Some(end) = attrs[value_start..].find('"') {
-                return Some(attrs[value_start..value_start + end].to_string());
-            }
-        }
-        None
+        let start = attrs.match_indices(&pattern)
+            .map(|(start, _)| start)
+            .find(|&start| attrs[..start].chars().next_back().is_none_or(char::is_whitespace))?;
+        let value_start = start + pattern.len();
+        let end = attrs[value_start..].find('"')?;
+        Some(attrs[value_start..value_start + end].to_string())
     }
 
     /// Parse ISO 8601 duration (PT...S format)
     fn parse_duration_attr(&self, content: &str, attr_name: &str) -> Option<Duration> {
-        let pattern = format!("{}=\"", attr_name);
-        if let Some(start) = content.find(&pattern) {
-            let value_start = start + pattern.len();
-            if let Some(end) = content[value_start..].find('"') {
-                let duration_str = &content[value_start..value_start + end];
-                return parse_iso8601_duration(duration_str);
-            }
-        }
-        None
+        self.extract_attr(content, attr_name)
+            .and_then(|duration_str| parse_iso8601_duration(&duration_str))
     }
 
     /// Extract BaseURL for representation
@@ -167,7 +375,7 @@ impl DashParser {
             if let Some(end) = rep_content[start..].find("</BaseURL>") {
                 let url_str = &rep_content[start + 9..start + end];
                 return base_url.join(url_str)
-                    .map_err(|e| Error::InvalidManifest(format!("Invalid BaseURL: {}", e)));
+                    .map_err(|e| Error::invalid_manifest(format!("Invalid BaseURL: {}", e)));
             }
         }
 
@@ -193,7 +401,7 @@ impl DashParser {
                 .replace("$Time$", &(i * 4000).to_string()); // Assume 4s segments
 
             let url = base_url.join(&url_str)
-                .map_err(|e| Error::InvalidManifest(format!("Invalid segment URL: {}", e)))?;
+                .map_err(|e| Error::invalid_manifest(format!("Invalid segment URL: {}", e)))?;
             urls.push(url);
         }
 
@@ -213,19 +421,10 @@ impl ManifestParser for DashParser {
     async fn parse(&self, url: &Url) -> Result<Manifest> {
         debug!("Fetching DASH manifest: {}", url);
 
-        let response = self
-            .client
-            .get(url.clone())
-            .send()
-            .await
-            .map_err(|e| Error::ManifestFetch(e.to_string()))?;
-
-        let content = response
-            .text()
-            .await
-            .map_err(|e| Error::ManifestFetch(e.to_string()))?;
+        let content = self.fetcher.get_text(url).await?;
 
-        self.parse_mpd(&content, url)
+        let manifest = self.parse_mpd(&content, url)?;
+        Ok(Manifest { fetched_bytes: content.len(), ..manifest })
     }
 
     #[instrument(skip(self))]
@@ -233,109 +432,145 @@ impl ManifestParser for DashParser {
         // For DASH, we need to parse the MPD and generate segments
         // based on SegmentTemplate or SegmentList
 
-        let response = self
-            .client
-            .get(url.clone())
-            .send()
-            .await
-            .map_err(|e| Error::ManifestFetch(e.to_string()))?;
-
-        let content = response
-            .text()
-            .await
-            .map_err(|e| Error::ManifestFetch(e.to_string()))?;
+        let content = self.fetcher.get_text(url).await?;
 
         self.parse_segments(&content, url)
     }
 
     #[instrument(skip(self))]
     async fn get_latest_segments(&self, url: &Url, last_sequence: u64) -> Result<Vec<Segment>> {
-        let all_segments = self.parse_variant(url).await?;
+        Ok(self.refresh_segments(url, last_sequence).await?.segments)
+    }
+
+    #[instrument(skip(self))]
+    async fn refresh_segments(&self, url: &Url, last_sequence: u64) -> Result<LiveRefresh> {
+        let (content, freshness) = self.cache.fetch(self.fetcher.as_ref(), url).await?;
+        debug!(not_modified = freshness.not_modified, age = ?freshness.age, "Refreshed DASH manifest: {}", url);
 
-        let new_segments: Vec<_> = all_segments
+        let segments = self.parse_segments(&content, url)?
             .into_iter()
             .filter(|s| s.number > last_sequence)
             .collect();
 
-        Ok(new_segments)
+        // Without minimumUpdatePeriod the MPD is reloaded every segment
+        let reload_interval = self.parse_duration_attr(&content, "minimumUpdatePeriod")
+            .or_else(|| self.parse_duration_attr(&content, "maxSegmentDuration"))
+            .unwrap_or(Duration::from_secs(4));
+
+        Ok(LiveRefresh { segments, reload_interval, freshness })
     }
 }
 
 impl DashParser {
-    /// Parse segments from MPD content
+    /// Parse segments from MPD content, across all periods
     fn parse_segments(&self, content: &str, base_url: &Url) -> Result<Vec<Segment>> {
+        let duration = self.parse_duration_attr(content, "mediaPresentationDuration");
+        let mut periods = self.split_periods(content, duration);
+        if periods.is_empty() {
+            periods.push(MpdPeriod { id: String::new(), header: content, body: content, start: Duration::ZERO, duration });
+        }
+
+        let mut segments: Vec<Segment> = Vec::new();
+        for (index, period) in periods.iter().enumerate() {
+            let numbering = SegmentNumbering {
+                first_number: segments.len() as u64 + 1,
+                discontinuity_sequence: index as u32,
+            };
+            segments.extend(self.representation_segments(&[period.body], None, base_url, period.duration, numbering)?);
+        }
+
+        if segments.is_empty() {
+            return Err(Error::invalid_manifest("No segments found in MPD".to_string()));
+        }
+
+        Ok(segments)
+    }
+
+    /// Segments from the first SegmentTemplate or SegmentList in `scopes`,
+    /// searched innermost first
+    ///
+    /// `representation` fills in `$RepresentationID$` and `$Bandwidth$`.
+    /// Without a period duration a template yields
+    /// [`DEFAULT_SEGMENT_COUNT`] segments.
+    fn representation_segments(
+        &self,
+        scopes: &[&str],
+        representation: Option<(&str, u64)>,
+        base_url: &Url,
+        period_duration: Option<Duration>,
+        numbering: SegmentNumbering,
+    ) -> Result<Vec<Segment>> {
+        let segment = |index: u64, uri: Url, duration: Duration| Segment {
+            number: numbering.first_number + index,
+            uri,
+            duration,
+            byte_range: None,
+            encryption: None,
+            discontinuity_sequence: numbering.discontinuity_sequence,
+            program_date_time: None,
+        };
+        let join = |url_str: &str| base_url.join(url_str)
+            .map_err(|e| Error::invalid_manifest(format!("Invalid segment URL: {}", e)));
+
+        let Some(scope) = scopes.iter().find(|s| s.contains("<SegmentTemplate") || s.contains("<SegmentList")) else {
+            return Ok(Vec::new());
+        };
         let mut segments = Vec::new();
 
         // Look for SegmentTemplate
-        if let Some(template_start) = content.find("<SegmentTemplate") {
-            if let Some(template_end) = content[template_start..].find('>') {
-                let template_attrs = &content[template_start..template_start + template_end];
-
-                let media_template = self.extract_attr(template_attrs, "media");
-                let timescale: u64 = self.extract_attr(template_attrs, "timescale")
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(1);
-                let duration: u64 = self.extract_attr(template_attrs, "duration")
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(timescale * 4);
-
-                let segment_duration = Duration::from_secs_f64(duration as f64 / timescale as f64);
-
-                // Generate segments (simplified - assumes 100 segments for VOD)
-                let segment_count = 100;
-
-                if let Some(template) = media_template {
-                    for i in 1..=segment_count {
-                        let url_str = template
-                            .replace("$Number$", &i.to_string())
-                            .replace("$Time$", &((i - 1) * duration).to_string());
-
-                        let url = base_url.join(&url_str)
-                            .map_err(|e| Error::InvalidManifest(format!("Invalid segment URL: {}", e)))?;
-
-                        segments.push(Segment {
-                            number: i as u64,
-                            uri: url,
-                            duration: segment_duration,
-                            byte_range: None,
-                            encryption: None,
-                            discontinuity_sequence: 0,
-                            program_date_time: None,
-                        });
-                    }
+        if let Some(template_start) = scope.find("<SegmentTemplate") {
+            let template_end = scope[template_start..].find('>').unwrap_or(scope.len() - template_start);
+            let template_attrs = &scope[template_start..template_start + template_end];
+
+            let number_attr = |name: &str, default: u64| self.extract_attr(template_attrs, name)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default);
+            let timescale = number_attr("timescale", 1).max(1);
+            let duration = number_attr("duration", timescale * 4).max(1);
+            let start_number = number_attr("startNumber", 1);
+            let time_offset = number_attr("presentationTimeOffset", 0);
+
+            let segment_duration = Duration::from_secs_f64(duration as f64 / timescale as f64);
+            let segment_count = period_duration
+                .map(|d| (d.as_secs_f64() / segment_duration.as_secs_f64()).ceil() as u64)
+                .filter(|&count| count > 0)
+                .unwrap_or(DEFAULT_SEGMENT_COUNT);
+
+            if let Some(template) = self.extract_attr(template_attrs, "media") {
+                let template = match representation {
+                    Some((id, bandwidth)) => template
+                        .replace("$RepresentationID$", id)
+                        .replace("$Bandwidth$", &bandwidth.to_string()),
+                    None => template,
+                };
+                for i in 0..segment_count {
+                    let url_str = template
+                        .replace("$Number$", &(start_number + i).to_string())
+                        .replace("$Time$", &(time_offset + i * duration).to_string());
+                    segments.push(segment(i, join(&url_str)?, segment_duration));
                 }
             }
-        }
+        } else if let Some(list_start) = scope.find("<SegmentList") {
+            let list = &scope[list_start..];
+            let list_attrs = &list[..list.find('>').unwrap_or(list.len())];
+            let timescale: f64 = self.extract_attr(list_attrs, "timescale")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1.0);
+            let segment_duration = self.extract_attr(list_attrs, "duration")
+                .and_then(|s| s.parse::<f64>().ok())
+                .map(|d| Duration::from_secs_f64(d / timescale))
+                .unwrap_or(Duration::from_secs(4)); // Default
 
-        // Look for SegmentList
-        if let Some(list_start) = content.find("<SegmentList") {
             // Parse SegmentURL elements
-            for segment_match in content[list_start..].split("<SegmentURL").skip(1) {
+            for segment_match in list.split("<SegmentURL").skip(1) {
                 if let Some(end) = segment_match.find('>') {
-                    let attrs = &segment_match[..end];
-
-                    if let Some(media) = self.extract_attr(attrs, "media") {
-                        let url = base_url.join(&media)
-                            .map_err(|e| Error::InvalidManifest(format!("Invalid segment URL: {}", e)))?;
-
-                        segments.push(Segment {
-                            number: segments.len() as u64 + 1,
-                            uri: url,
-                            duration: Duration::from_secs(4), // Default
-                            byte_range: None,
-                            encryption: None,
-                            discontinuity_sequence: 0,
-                            program_date_time: None,
-                        });
+                    if let Some(media) = self.extract_attr(&segment_match[..end], "media") {
+                        segments.push(segment(segments.len() as u64, join(&media)?, segment_duration));
                     }
                 }
             }
         }
 
-        if segments.is_empty() {
-            return Err(Error::InvalidManifest("No segments found in MPD".to_string()));
-        }
-
         Ok(segments)
     }
 }
@@ -425,4 +660,162 @@ mod tests {
             Some(Duration::from_secs(7510))
         );
     }
+
+    #[test]
+    fn test_parse_base_url_pathways() {
+        let mpd = r#"<?xml version="1.0"?>
+<MPD type="static" mediaPresentationDuration="PT60S">
+  <BaseURL serviceLocation="cdn-a">https://a.example.com/vod/</BaseURL>
+  <BaseURL serviceLocation="cdn-b">https://b.example.com/content/vod/</BaseURL>
+  <ContentSteering defaultServiceLocation="cdn-b" queryBeforeStart="false">https://steering.example.com/dash.json</ContentSteering>
+  <Period>
+    <AdaptationSet mimeType="video/mp4">
+      <Representation id="720p" bandwidth="2800000" width="1280" height="720" codecs="avc1.4d401f">
+        <BaseURL>720p/</BaseURL>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+        let base = Url::parse("https://origin.example.com/manifest.mpd").unwrap();
+
+        let manifest = DashParser::new().parse_mpd(mpd, &base).unwrap();
+
+        let steering = manifest.content_steering.unwrap();
+        assert_eq!(steering.server_uri.unwrap().as_str(), "https://steering.example.com/dash.json");
+        assert_eq!(steering.default_pathway.as_deref(), Some("cdn-b"));
+        let ids: Vec<_> = steering.pathways.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["cdn-a", "cdn-b"]);
+
+        // Representations resolve against the default pathway
+        assert_eq!(manifest.renditions[0].uri.as_str(), "https://b.example.com/content/vod/720p/");
+    }
+
+    #[tokio::test]
+    async fn test_refresh_dynamic_mpd() {
+        let url = "https://cdn.example.com/live/manifest.mpd";
+        let mpd = r#"<?xml version="1.0"?>
+<MPD type="dynamic" minimumUpdatePeriod="PT2S" maxSegmentDuration="PT4S">
+  <Period>
+    <AdaptationSet mimeType="video/mp4">
+      <Representation id="720p" bandwidth="2800000" width="1280" height="720" codecs="avc1.4d401f">
+        <SegmentList duration="4">
+          <SegmentURL media="seg1.m4s"/>
+          <SegmentURL media="seg2.m4s"/>
+          <SegmentURL media="seg3.m4s"/>
+        </SegmentList>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+        let fetcher = Arc::new(
+            http::ScriptedFetcher::new()
+                .respond(url, 200, mpd)
+                .respond_with_headers(url, 200, HashMap::from([("Cache-Control".to_string(), "max-age=1".to_string())]), mpd),
+        );
+        let parser = DashParser::with_fetcher(fetcher);
+        let url = Url::parse(url).unwrap();
+
+        let manifest = parser.parse_mpd(mpd, &url).unwrap();
+        assert_eq!(manifest.minimum_update_period, Some(Duration::from_secs(2)));
+
+        let first = parser.refresh_segments(&url, 1).await.unwrap();
+        let names: Vec<_> = first.segments.iter().map(|s| s.uri.path()).collect();
+        assert_eq!(names, ["/live/seg2.m4s", "/live/seg3.m4s"]);
+        assert_eq!(first.reload_interval, Duration::from_secs(2));
+        assert!(!first.freshness.unchanged);
+
+        // An identical MPD is unchanged, so the next poll comes sooner
+        let second = parser.refresh_segments(&url, 3).await.unwrap();
+        assert!(second.segments.is_empty());
+        assert!(second.freshness.unchanged && !second.freshness.not_modified);
+        assert_eq!(second.next_poll(), Duration::from_secs(1));
+    }
+
+    fn multi_period_manifest() -> Manifest {
+        let base = Url::parse("https://origin.example.com/vod/manifest.mpd").unwrap();
+        DashParser::new().parse_mpd(include_str!("../../tests/fixtures/multi_period.mpd"), &base).unwrap()
+    }
+
+    #[test]
+    fn test_multi_period_timing() {
+        let manifest = multi_period_manifest();
+
+        let periods: Vec<_> = manifest.periods.iter()
+            .map(|p| (p.id.as_str(), p.start.as_secs(), p.duration.map(|d| d.as_secs()), p.discontinuity_sequence))
+            .collect();
+        assert_eq!(periods, [("main-1", 0, Some(60), 0), ("ad-1", 60, Some(15), 1), ("main-2", 75, Some(60), 2)]);
+        assert_eq!(manifest.duration, Some(Duration::from_secs(135)));
+
+        assert_eq!(manifest.period_at(0.0).unwrap().id, "main-1");
+        assert_eq!(manifest.period_at(59.9).unwrap().id, "main-1");
+        assert_eq!(manifest.period_at(60.0).unwrap().id, "ad-1");
+        assert_eq!(manifest.period_at(200.0).unwrap().id, "main-2");
+    }
+
+    #[test]
+    fn test_multi_period_renditions() {
+        let manifest = multi_period_manifest();
+
+        // The first period's renditions are the initial set, ids scoped by period
+        let ids: Vec<_> = manifest.renditions.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["main-1/video_360", "main-1/video_720", "main-1/video_1080"]);
+        assert_eq!(manifest.renditions[0].video_codec, Some(VideoCodec::H264));
+        assert_eq!(manifest.renditions[0].uri.as_str(), "https://origin.example.com/vod/content/");
+
+        // The ad inherits codec and resolution from its AdaptationSet and
+        // is served from its own BaseURL
+        let ad = manifest.renditions_at(65.0);
+        let ids: Vec<_> = ad.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["ad-1/video_360", "ad-1/video_720"]);
+        assert!(ad.iter().all(|r| r.video_codec == Some(VideoCodec::H265) && r.frame_rate == Some(25.0)));
+        assert_eq!(ad[0].resolution, Some(Resolution::new(640, 360)));
+        assert_eq!(ad[1].resolution, Some(Resolution::new(1280, 720)));
+        assert_eq!(ad[1].uri.as_str(), "https://ads.example.com/break1/");
+
+        assert_eq!(manifest.renditions_at(80.0).len(), 3);
+    }
+
+    #[test]
+    fn test_multi_period_segments() {
+        let manifest = multi_period_manifest();
+
+        let main = manifest.segments("main-1/video_720").unwrap();
+        assert_eq!(main.len(), 15);
+        assert_eq!((main[0].number, main[0].discontinuity_sequence), (1, 0));
+        assert_eq!(main[14].uri.as_str(), "https://origin.example.com/vod/content/video_720/seg-15.m4s");
+
+        // Ad segments continue the numbering in a new discontinuity, with
+        // $Time$ offset by presentationTimeOffset
+        let ad = manifest.segments("ad-1/video_720").unwrap();
+        assert_eq!(ad.len(), 3);
+        assert_eq!(ad[0].duration, Duration::from_secs(5));
+        assert_eq!((ad[0].number, ad[0].discontinuity_sequence), (16, 1));
+        let uris: Vec<_> = ad.iter().map(|s| s.uri.as_str()).collect();
+        assert_eq!(uris, [
+            "https://ads.example.com/break1/1800000/chunk-900000.m4s",
+            "https://ads.example.com/break1/1800000/chunk-1350000.m4s",
+            "https://ads.example.com/break1/1800000/chunk-1800000.m4s",
+        ]);
+
+        let resumed = manifest.segments("main-2/video_1080").unwrap();
+        assert_eq!(resumed.len(), 15);
+        assert_eq!((resumed[0].number, resumed[0].discontinuity_sequence), (19, 2));
+        assert_eq!(resumed[0].uri.as_str(), "https://origin.example.com/vod/content/video_1080/seg-16.m4s");
+    }
+
+    #[test]
+    fn test_parse_segments_across_periods() {
+        let base = Url::parse("https://origin.example.com/vod/manifest.mpd").unwrap();
+        let segments = DashParser::new()
+            .parse_segments(include_str!("../../tests/fixtures/multi_period.mpd"), &base)
+            .unwrap();
+
+        assert_eq!(segments.len(), 33);
+        let boundaries: Vec<_> = segments.windows(2)
+            .filter(|pair| pair[0].discontinuity_sequence != pair[1].discontinuity_sequence)
+            .map(|pair| pair[1].number)
+            .collect();
+        assert_eq!(boundaries, [16, 19]);
+        assert!(segments.windows(2).all(|pair| pair[1].number == pair[0].number + 1));
+    }
 }
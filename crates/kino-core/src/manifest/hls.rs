@@ -3,98 +3,313 @@
 //! Implements parsing for:
 //! - Master playlists (multivariant)
 //! - Media playlists (segments)
-//! - EXT-X-KEY encryption
+//! - EXT-X-KEY encryption (AES-128, SAMPLE-AES, SAMPLE-AES-CTR)
+//! - EXT-X-SESSION-KEY and DRM init data (FairPlay skd://, PSSH data: URIs)
 //! - EXT-X-MAP initialization segments
+//! - EXT-X-I-FRAME-STREAM-INF trick play renditions
+//! - EXT-X-MEDIA alternate audio groups, subtitles and closed captions
+//! - EXT-X-PROGRAM-DATE-TIME
+//! - EXT-X-CONTENT-STEERING and PATHWAY-ID
 //! - Discontinuity handling
 
 use crate::{
+    drm::PsshBox,
     error::Error,
+    http::{self, HttpFetcher, ReqwestFetcher},
+    steering::{origin_base, ContentSteering, Pathway, DEFAULT_PATHWAY},
     types::*,
     Result,
 };
-use super::{Manifest, ManifestParser, ManifestType};
+use super::{LiveRefresh, Manifest, ManifestParser, ManifestType, PlaylistCache, PlaylistFreshness};
 use async_trait::async_trait;
-use m3u8_rs::{self, MediaPlaylist, MasterPlaylist};
-use reqwest::Client;
+use m3u8_rs::{self, AlternativeMediaType, InstreamId, MediaPlaylist, MasterPlaylist, VariantStream};
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, instrument};
 use url::Url;
 
+/// A parsed HLS media playlist along with its playlist-level tags
+#[derive(Debug, Clone)]
+pub struct VariantPlaylist {
+    /// Segments in the current playlist window
+    pub segments: Vec<Segment>,
+    /// EXT-X-TARGETDURATION
+    pub target_duration: Duration,
+    /// EXT-X-MEDIA-SEQUENCE of the first segment
+    pub media_sequence: u64,
+    /// EXT-X-DISCONTINUITY-SEQUENCE of the first segment
+    pub discontinuity_sequence: u64,
+    /// True unless the playlist carries EXT-X-ENDLIST
+    pub is_live: bool,
+    /// Total duration (for VOD)
+    pub duration: Option<Duration>,
+    /// DRM init data from the playlist's EXT-X-KEY tags
+    pub drm_init_data: Vec<PsshBox>,
+}
+
 /// HLS manifest parser
 pub struct HlsParser {
-    client: Client,
+    fetcher: Arc<dyn HttpFetcher>,
+    /// Last copy of each live playlist, for conditional refreshes
+    cache: PlaylistCache,
 }
 
 impl HlsParser {
     pub fn new() -> Self {
-        Self {
-            client: Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()
-                .expect("Failed to create HTTP client"),
-        }
+        Self::with_fetcher(http::default_fetcher(Duration::from_secs(30)))
+    }
+
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self::with_fetcher(Arc::new(ReqwestFetcher::with_client(client)))
+    }
+
+    /// Create a parser that fetches playlists with `fetcher`
+    pub fn with_fetcher(fetcher: Arc<dyn HttpFetcher>) -> Self {
+        Self { fetcher, cache: PlaylistCache::default() }
     }
 
-    pub fn with_client(client: Client) -> Self {
-        Self { client }
+    /// Fetch a playlist's text, reading `file://` URLs from disk so
+    /// downloaded content plays offline
+    pub(crate) async fn fetch_playlist(&self, url: &Url) -> Result<String> {
+        if let Some(path) = crate::download::local_path(url) {
+            return Ok(tokio::fs::read_to_string(path).await?);
+        }
+
+        self.fetcher.get_text(url).await
     }
 
     /// Parse master playlist
-    fn parse_master(&self, content: &str, base_url: &Url) -> Result<Manifest> {
+    pub(crate) fn parse_master(&self, content: &str, base_url: &Url) -> Result<Manifest> {
         let parsed = m3u8_rs::parse_master_playlist_res(content.as_bytes())
-            .map_err(|e| Error::ManifestParse(format!("Failed to parse HLS master: {:?}", e)))?;
+            .map_err(|e| Error::parse(format!("Failed to parse HLS master: {:?}", e)))?;
+
+        let content_steering = self.extract_content_steering(&parsed, base_url)?;
+
+        // Variants are repeated per pathway; keep those of the default one
+        // and let the pathway selector move URLs between CDNs
+        let pathway = content_steering
+            .as_ref()
+            .and_then(|s| s.default_pathway.as_deref())
+            .filter(|_| parsed.variants.iter().any(|v| variant_attr(v, "PATHWAY-ID").is_some()));
 
-        let renditions = self.extract_renditions(&parsed, base_url)?;
+        let renditions = self.extract_renditions(&parsed, base_url, pathway)?;
+        let iframe_renditions = self.extract_iframe_renditions(&parsed, base_url, pathway)?;
+        let audio_tracks = self.extract_audio_tracks(&parsed, base_url)?;
+        let text_tracks = self.extract_text_tracks(&parsed, base_url)?;
+
+        let mut session_keys = Vec::new();
+        for key in &parsed.session_key {
+            session_keys.extend(self.parse_encryption_key(&key.0, base_url)?);
+        }
 
         Ok(Manifest {
             manifest_type: ManifestType::Hls,
             renditions,
+            iframe_renditions,
+            audio_tracks,
+            text_tracks,
+            drm_init_data: drm_init_data(&session_keys),
+            content_steering,
             is_live: false, // Will be determined from media playlist
             duration: None,
             target_duration: Duration::from_secs(6), // Default, overridden by media playlist
+            minimum_update_period: None,
             base_url: base_url.clone(),
+            fetched_bytes: 0,
+            periods: Vec::new(),
         })
     }
 
     /// Extract renditions from master playlist
-    fn extract_renditions(&self, master: &MasterPlaylist, base_url: &Url) -> Result<Vec<Rendition>> {
+    fn extract_renditions(&self, master: &MasterPlaylist, base_url: &Url, pathway: Option<&str>) -> Result<Vec<Rendition>> {
         let mut renditions = Vec::new();
 
-        for (idx, variant) in master.variants.iter().enumerate() {
-            let uri = self.resolve_uri(base_url, &variant.uri)?;
+        let variants = master.variants.iter().filter(|v| !v.is_i_frame && on_pathway(v, pathway));
+        for (idx, variant) in variants.enumerate() {
+            renditions.push(self.variant_rendition(format!("variant_{}", idx), variant, base_url)?);
+        }
 
-            let resolution = variant.resolution.map(|r| Resolution {
-                width: r.width as u32,
-                height: r.height as u32,
-            });
+        // Sort by bandwidth
+        renditions.sort_by_key(|r| r.bandwidth);
 
-            let video_codec = variant.codecs.as_ref().and_then(|c| parse_video_codec(c));
-            let audio_codec = variant.codecs.as_ref().and_then(|c| parse_audio_codec(c));
+        Ok(renditions)
+    }
 
-            renditions.push(Rendition {
-                id: format!("variant_{}", idx),
-                bandwidth: variant.bandwidth,
-                resolution,
-                frame_rate: variant.frame_rate.map(|f| f as f32),
-                video_codec,
-                audio_codec,
-                uri,
-                hdr: None, // TODO: Parse HDR info from VIDEO-RANGE
-                language: None,
-                name: variant.video.clone(),
-            });
+    /// Extract I-frame only renditions (EXT-X-I-FRAME-STREAM-INF)
+    fn extract_iframe_renditions(&self, master: &MasterPlaylist, base_url: &Url, pathway: Option<&str>) -> Result<Vec<Rendition>> {
+        let mut renditions = Vec::new();
+
+        let variants = master.variants.iter().filter(|v| v.is_i_frame && on_pathway(v, pathway));
+        for (idx, variant) in variants.enumerate() {
+            renditions.push(self.variant_rendition(format!("iframe_{}", idx), variant, base_url)?);
         }
 
-        // Sort by bandwidth
         renditions.sort_by_key(|r| r.bandwidth);
 
         Ok(renditions)
     }
 
+    /// Extract pathways and the steering server (EXT-X-CONTENT-STEERING)
+    ///
+    /// Each pathway is identified by the origin of its first variant.
+    /// Masters without the tag only get steering if their variants span
+    /// several pathways, which still allows failover between them.
+    fn extract_content_steering(&self, master: &MasterPlaylist, base_url: &Url) -> Result<Option<ContentSteering>> {
+        let attrs = master
+            .unknown_tags
+            .iter()
+            .find(|t| t.tag == "X-CONTENT-STEERING")
+            .map(|t| parse_attribute_list(t.rest.as_deref().unwrap_or_default()));
+
+        let mut pathways: Vec<Pathway> = Vec::new();
+        for variant in master.variants.iter().filter(|v| !v.is_i_frame) {
+            let id = variant_attr(variant, "PATHWAY-ID").unwrap_or(DEFAULT_PATHWAY);
+            if pathways.iter().all(|p| p.id != id) {
+                let uri = self.resolve_uri(base_url, &variant.uri)?;
+                pathways.push(Pathway { id: id.to_string(), base: origin_base(&uri) });
+            }
+        }
+
+        if attrs.is_none() && pathways.len() < 2 {
+            return Ok(None);
+        }
+        let attrs = attrs.unwrap_or_default();
+
+        let server_uri = attrs
+            .get("SERVER-URI")
+            .map(|u| self.resolve_uri(base_url, u))
+            .transpose()?;
+        let default_pathway = attrs
+            .get("PATHWAY-ID")
+            .cloned()
+            .or_else(|| pathways.first().map(|p| p.id.clone()));
+
+        Ok(Some(ContentSteering { server_uri, default_pathway, pathways }))
+    }
+
+    /// Extract alternate audio tracks (EXT-X-MEDIA TYPE=AUDIO)
+    fn extract_audio_tracks(&self, master: &MasterPlaylist, base_url: &Url) -> Result<Vec<AudioTrack>> {
+        let mut tracks = Vec::new();
+
+        let audio = master
+            .alternatives
+            .iter()
+            .filter(|m| m.media_type == AlternativeMediaType::Audio);
+
+        for (idx, media) in audio.enumerate() {
+            let url = media
+                .uri
+                .as_ref()
+                .map(|u| self.resolve_uri(base_url, u))
+                .transpose()?;
+
+            // The codec is only declared on the variants that reference the group
+            let codec = master
+                .variants
+                .iter()
+                .filter(|v| !v.is_i_frame && v.audio.as_deref() == Some(media.group_id.as_str()))
+                .find_map(|v| v.codecs.as_deref().and_then(parse_audio_codec));
+
+            // CHANNELS is a count, optionally followed by "/" and coding details
+            let channels = media
+                .channels
+                .as_deref()
+                .and_then(|c| c.split('/').next())
+                .and_then(|c| c.trim().parse().ok());
+
+            let is_audio_description = media
+                .characteristics
+                .as_deref()
+                .is_some_and(|c| c.contains("public.accessibility.describes-video"));
+
+            tracks.push(AudioTrack {
+                id: format!("audio_{}", idx),
+                language: media.language.clone().unwrap_or_else(|| "und".to_string()),
+                label: media.name.clone(),
+                codec,
+                channels,
+                bitrate: None,
+                is_default: media.default,
+                is_audio_description,
+                url,
+                group_id: Some(media.group_id.clone()),
+                autoselect: media.autoselect,
+            });
+        }
+
+        Ok(tracks)
+    }
+
+    /// Extract subtitle and closed caption tracks (EXT-X-MEDIA
+    /// TYPE=SUBTITLES and TYPE=CLOSED-CAPTIONS)
+    fn extract_text_tracks(&self, master: &MasterPlaylist, base_url: &Url) -> Result<Vec<TextTrack>> {
+        let mut tracks = Vec::new();
+
+        for media in &master.alternatives {
+            let language = media.language.clone().unwrap_or_else(|| "und".to_string());
+            let mut track = match (&media.media_type, &media.instream_id) {
+                (AlternativeMediaType::Subtitles, _) => {
+                    let Some(uri) = &media.uri else {
+                        continue;
+                    };
+                    let id = format!("subtitles_{}", tracks.len());
+                    let url = self.resolve_uri(base_url, uri)?;
+                    TextTrack::new(id, TextTrackKind::Subtitles, language, &media.name, url, TextTrackFormat::WebVtt)
+                }
+                // Captions ride in the video; IDs match the tracks the
+                // session synthesizes when it finds them there
+                (AlternativeMediaType::ClosedCaptions, Some(instream)) => {
+                    let (id, format) = match instream {
+                        InstreamId::CC(n) => (format!("cc{}", n), TextTrackFormat::Cea608),
+                        InstreamId::Service(n) => (format!("service{}", n), TextTrackFormat::Cea708),
+                        InstreamId::Other(_) => continue,
+                    };
+                    let mut url = base_url.clone();
+                    url.set_fragment(Some(&id));
+                    TextTrack::new(id, TextTrackKind::Captions, language, &media.name, url, format)
+                }
+                _ => continue,
+            };
+            track.is_default = media.default;
+            track.is_forced = media.forced;
+            tracks.push(track);
+        }
+
+        Ok(tracks)
+    }
+
+    /// Build a rendition from a master playlist variant
+    fn variant_rendition(&self, id: String, variant: &VariantStream, base_url: &Url) -> Result<Rendition> {
+        let uri = self.resolve_uri(base_url, &variant.uri)?;
+
+        let resolution = variant.resolution.map(|r| Resolution {
+            width: r.width as u32,
+            height: r.height as u32,
+        });
+
+        let video_codec = variant.codecs.as_ref().and_then(|c| parse_video_codec(c));
+        let audio_codec = variant.codecs.as_ref().and_then(|c| parse_audio_codec(c));
+
+        Ok(Rendition {
+            id,
+            bandwidth: variant.bandwidth,
+            resolution,
+            frame_rate: variant.frame_rate.map(|f| f as f32),
+            video_codec,
+            audio_codec,
+            uri,
+            hdr: None, // TODO: Parse HDR info from VIDEO-RANGE
+            language: None,
+            name: variant.video.clone(),
+            audio_group: variant.audio.clone(),
+        })
+    }
+
     /// Parse media playlist
-    fn parse_media(&self, content: &str, base_url: &Url) -> Result<(Vec<Segment>, bool, Option<Duration>)> {
+    pub(crate) fn parse_media(&self, content: &str, base_url: &Url) -> Result<VariantPlaylist> {
         let parsed = m3u8_rs::parse_media_playlist_res(content.as_bytes())
-            .map_err(|e| Error::ManifestParse(format!("Failed to parse HLS media: {:?}", e)))?;
+            .map_err(|e| Error::parse(format!("Failed to parse HLS media: {:?}", e)))?;
 
         let is_live = !parsed.end_list;
         let duration = if parsed.end_list {
@@ -106,8 +321,72 @@ impl HlsParser {
         };
 
         let segments = self.extract_segments(&parsed, base_url)?;
+        let keys = self.media_playlist_keys(content, base_url)?;
+
+        Ok(VariantPlaylist {
+            segments,
+            target_duration: Duration::from_secs(parsed.target_duration),
+            media_sequence: parsed.media_sequence,
+            discontinuity_sequence: parsed.discontinuity_sequence,
+            is_live,
+            duration,
+            drm_init_data: drm_init_data(&keys),
+        })
+    }
 
-        Ok((segments, is_live, duration))
+    /// Collect every EXT-X-KEY in a media playlist
+    ///
+    /// m3u8-rs keeps only the last key per segment, which drops all but one
+    /// DRM system when several KEYFORMATs are listed together. The tags are
+    /// re-read as session keys, which share the attribute syntax.
+    fn media_playlist_keys(&self, content: &str, base_url: &Url) -> Result<Vec<EncryptionInfo>> {
+        let tags: String = content
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("#EXT-X-KEY:"))
+            .map(|attrs| format!("#EXT-X-SESSION-KEY:{}\n", attrs))
+            .collect();
+        if tags.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let parsed = m3u8_rs::parse_master_playlist_res(format!("#EXTM3U\n{}", tags).as_bytes())
+            .map_err(|e| Error::parse(format!("Failed to parse HLS keys: {:?}", e)))?;
+
+        let mut keys = Vec::new();
+        for key in &parsed.session_key {
+            keys.extend(self.parse_encryption_key(&key.0, base_url)?);
+        }
+        Ok(keys)
+    }
+
+    /// Fetch and parse a media playlist, keeping its playlist-level tags
+    ///
+    /// Use this instead of [`ManifestParser::parse_variant`] when the target
+    /// duration or sequence numbers matter, e.g. for live stream monitoring.
+    #[instrument(skip(self))]
+    pub async fn parse_variant_playlist(&self, url: &Url) -> Result<VariantPlaylist> {
+        debug!("Fetching HLS variant playlist: {}", url);
+
+        let content = self.fetch_playlist(url).await?;
+
+        self.parse_media(&content, url)
+    }
+
+    /// Refetch a live media playlist with a conditional request
+    ///
+    /// Like [`parse_variant_playlist`](Self::parse_variant_playlist), but
+    /// the copy from the previous refresh is revalidated with its `ETag` or
+    /// `Last-Modified`, and the response's freshness is returned with the
+    /// playlist. `file://` playlists are read from disk and always fresh.
+    #[instrument(skip(self))]
+    pub async fn refresh_variant_playlist(&self, url: &Url) -> Result<(VariantPlaylist, PlaylistFreshness)> {
+        if crate::download::local_path(url).is_some() {
+            return Ok((self.parse_variant_playlist(url).await?, PlaylistFreshness::default()));
+        }
+
+        let (content, freshness) = self.cache.fetch(self.fetcher.as_ref(), url).await?;
+        debug!(not_modified = freshness.not_modified, age = ?freshness.age, "Refreshed HLS variant playlist: {}", url);
+        Ok((self.parse_media(&content, url)?, freshness))
     }
 
     /// Extract segments from media playlist
@@ -142,7 +421,7 @@ impl HlsParser {
                 byte_range,
                 encryption: current_encryption.clone(),
                 discontinuity_sequence,
-                program_date_time: None, // TODO: Parse EXT-X-PROGRAM-DATE-TIME
+                program_date_time: seg.program_date_time.map(|t| t.with_timezone(&chrono::Utc)),
             });
         }
 
@@ -185,13 +464,14 @@ impl HlsParser {
             key_uri,
             iv,
             key_format: key.keyformat.clone(),
+            key_format_versions: key.keyformatversions.clone(),
         }))
     }
 
     /// Resolve relative URI against base URL
     fn resolve_uri(&self, base: &Url, relative: &str) -> Result<Url> {
         base.join(relative)
-            .map_err(|e| Error::InvalidManifest(format!("Invalid URI '{}': {}", relative, e)))
+            .map_err(|e| Error::invalid_manifest(format!("Invalid URI '{}': {}", relative, e)))
     }
 }
 
@@ -207,24 +487,15 @@ impl ManifestParser for HlsParser {
     async fn parse(&self, url: &Url) -> Result<Manifest> {
         debug!("Fetching HLS manifest: {}", url);
 
-        let response = self
-            .client
-            .get(url.clone())
-            .send()
-            .await
-            .map_err(|e| Error::ManifestFetch(e.to_string()))?;
-
-        let content = response
-            .text()
-            .await
-            .map_err(|e| Error::ManifestFetch(e.to_string()))?;
+        let content = self.fetch_playlist(url).await?;
 
         // Detect if master or media playlist
         if content.contains("#EXT-X-STREAM-INF") {
-            self.parse_master(&content, url)
+            let manifest = self.parse_master(&content, url)?;
+            Ok(Manifest { fetched_bytes: content.len(), ..manifest })
         } else {
             // Single rendition (media playlist as entry point)
-            let (_segments, is_live, duration) = self.parse_media(&content, url)?;
+            let media = self.parse_media(&content, url)?;
 
             // Create synthetic rendition
             let rendition = Rendition {
@@ -238,50 +509,49 @@ impl ManifestParser for HlsParser {
                 hdr: None,
                 language: None,
                 name: None,
+                audio_group: None,
             };
 
             Ok(Manifest {
                 manifest_type: ManifestType::Hls,
                 renditions: vec![rendition],
-                is_live,
-                duration,
-                target_duration: Duration::from_secs(6),
+                iframe_renditions: Vec::new(),
+                audio_tracks: Vec::new(),
+                text_tracks: Vec::new(),
+                drm_init_data: media.drm_init_data,
+                content_steering: None,
+                is_live: media.is_live,
+                duration: media.duration,
+                target_duration: media.target_duration,
+                minimum_update_period: None,
                 base_url: url.clone(),
+                fetched_bytes: content.len(),
+                periods: Vec::new(),
             })
         }
     }
 
     #[instrument(skip(self))]
     async fn parse_variant(&self, url: &Url) -> Result<Vec<Segment>> {
-        debug!("Fetching HLS variant playlist: {}", url);
-
-        let response = self
-            .client
-            .get(url.clone())
-            .send()
-            .await
-            .map_err(|e| Error::ManifestFetch(e.to_string()))?;
-
-        let content = response
-            .text()
-            .await
-            .map_err(|e| Error::ManifestFetch(e.to_string()))?;
-
-        let (segments, _, _) = self.parse_media(&content, url)?;
-        Ok(segments)
+        Ok(self.parse_variant_playlist(url).await?.segments)
     }
 
     #[instrument(skip(self))]
     async fn get_latest_segments(&self, url: &Url, last_sequence: u64) -> Result<Vec<Segment>> {
-        let all_segments = self.parse_variant(url).await?;
+        Ok(self.refresh_segments(url, last_sequence).await?.segments)
+    }
+
+    #[instrument(skip(self))]
+    async fn refresh_segments(&self, url: &Url, last_sequence: u64) -> Result<LiveRefresh> {
+        let (playlist, freshness) = self.refresh_variant_playlist(url).await?;
 
         // Filter to only new segments
-        let new_segments: Vec<_> = all_segments
+        let segments = playlist.segments
             .into_iter()
             .filter(|s| s.number > last_sequence)
             .collect();
 
-        Ok(new_segments)
+        Ok(LiveRefresh { segments, reload_interval: playlist.target_duration, freshness })
     }
 }
 
@@ -301,6 +571,44 @@ fn parse_video_codec(codecs: &str) -> Option<VideoCodec> {
     }
 }
 
+/// Attribute of a variant that m3u8-rs does not model
+fn variant_attr<'a>(variant: &'a VariantStream, name: &str) -> Option<&'a str> {
+    variant.other_attributes.as_ref()?.get(name).map(|v| v.as_str())
+}
+
+/// Whether a variant belongs to `pathway` (all do when None)
+fn on_pathway(variant: &VariantStream, pathway: Option<&str>) -> bool {
+    pathway.is_none_or(|p| variant_attr(variant, "PATHWAY-ID").unwrap_or(DEFAULT_PATHWAY) == p)
+}
+
+/// Parse an attribute list (`KEY=value,KEY="quoted, value"`)
+pub(crate) fn parse_attribute_list(list: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut rest = list.trim();
+
+    while let Some((key, after)) = rest.split_once('=') {
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => after.split_once(',').unwrap_or((after, "")),
+        };
+        attrs.insert(key.trim().to_string(), value.trim().to_string());
+        rest = remaining.trim_start_matches(',').trim();
+    }
+
+    attrs
+}
+
+/// DRM init data for the keys that name a DRM system, without duplicates
+fn drm_init_data(keys: &[EncryptionInfo]) -> Vec<PsshBox> {
+    let mut boxes: Vec<PsshBox> = Vec::new();
+    for pssh in keys.iter().filter_map(PsshBox::from_hls_key) {
+        if !boxes.contains(&pssh) {
+            boxes.push(pssh);
+        }
+    }
+    boxes
+}
+
 /// Parse audio codec from codecs string
 fn parse_audio_codec(codecs: &str) -> Option<AudioCodec> {
     let codecs_lower = codecs.to_lowercase();
@@ -349,4 +657,294 @@ mod tests {
         assert_eq!(parse_audio_codec("ac-3"), Some(AudioCodec::Ac3));
         assert_eq!(parse_audio_codec("ec-3"), Some(AudioCodec::Eac3));
     }
+
+    #[test]
+    fn test_parse_media_playlist_tags() {
+        let content = "#EXTM3U\n\
+            #EXT-X-VERSION:3\n\
+            #EXT-X-TARGETDURATION:4\n\
+            #EXT-X-MEDIA-SEQUENCE:120\n\
+            #EXT-X-DISCONTINUITY-SEQUENCE:5\n\
+            #EXTINF:4.0,\n\
+            seg120.ts\n\
+            #EXT-X-DISCONTINUITY\n\
+            #EXTINF:3.5,\n\
+            seg121.ts\n";
+        let base = Url::parse("https://example.com/live/index.m3u8").unwrap();
+
+        let playlist = HlsParser::new().parse_media(content, &base).unwrap();
+
+        assert_eq!(playlist.target_duration, Duration::from_secs(4));
+        assert_eq!(playlist.media_sequence, 120);
+        assert_eq!(playlist.discontinuity_sequence, 5);
+        assert!(playlist.is_live);
+        assert_eq!(playlist.segments.len(), 2);
+        assert_eq!(playlist.segments[1].number, 121);
+        assert_eq!(playlist.segments[1].discontinuity_sequence, 1);
+        assert_eq!(playlist.segments[1].uri.as_str(), "https://example.com/live/seg121.ts");
+    }
+
+    #[test]
+    fn test_parse_master_iframe_streams() {
+        let content = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2800000,RESOLUTION=1280x720,CODECS=\"avc1.4d401f,mp4a.40.2\"\n\
+            720p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360\n\
+            360p.m3u8\n\
+            #EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=280000,RESOLUTION=1280x720,URI=\"720p_iframes.m3u8\"\n\
+            #EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=86000,RESOLUTION=640x360,URI=\"360p_iframes.m3u8\"\n";
+        let base = Url::parse("https://example.com/vod/master.m3u8").unwrap();
+
+        let manifest = HlsParser::new().parse_master(content, &base).unwrap();
+
+        let bandwidths: Vec<u64> = manifest.renditions.iter().map(|r| r.bandwidth).collect();
+        assert_eq!(bandwidths, [800000, 2800000]);
+
+        assert_eq!(manifest.iframe_renditions.len(), 2);
+        assert_eq!(manifest.iframe_renditions[0].id, "iframe_1");
+        assert_eq!(manifest.iframe_renditions[0].bandwidth, 86000);
+        assert_eq!(manifest.iframe_renditions[1].uri.as_str(), "https://example.com/vod/720p_iframes.m3u8");
+        assert_eq!(manifest.iframe_renditions[1].resolution, Some(Resolution::new(1280, 720)));
+    }
+
+    #[test]
+    fn test_parse_master_audio_groups() {
+        let content = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"stereo\",LANGUAGE=\"en\",NAME=\"English\",DEFAULT=YES,AUTOSELECT=YES,CHANNELS=\"2\",URI=\"audio/en_stereo.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"stereo\",LANGUAGE=\"es\",NAME=\"Espanol\",AUTOSELECT=YES,CHANNELS=\"2\",URI=\"audio/es_stereo.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"surround\",LANGUAGE=\"en\",NAME=\"English 5.1\",CHANNELS=\"6\",URI=\"audio/en_51.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"surround\",LANGUAGE=\"en\",NAME=\"English Atmos\",CHANNELS=\"16/JOC\",CHARACTERISTICS=\"public.accessibility.describes-video\",URI=\"audio/en_atmos.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"subs\",LANGUAGE=\"en\",NAME=\"English\",URI=\"subs/en.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2800000,CODECS=\"avc1.4d401f,mp4a.40.2\",AUDIO=\"stereo\"\n\
+            720p_stereo.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=3200000,CODECS=\"avc1.4d401f,ec-3\",AUDIO=\"surround\"\n\
+            720p_surround.m3u8\n";
+        let base = Url::parse("https://example.com/vod/master.m3u8").unwrap();
+
+        let manifest = HlsParser::new().parse_master(content, &base).unwrap();
+
+        assert_eq!(manifest.audio_tracks.len(), 4);
+        let english = &manifest.audio_tracks[0];
+        assert_eq!(english.language, "en");
+        assert_eq!(english.label, "English");
+        assert_eq!(english.channels, Some(2));
+        assert_eq!(english.codec, Some(AudioCodec::Aac));
+        assert!(english.is_default && english.autoselect);
+        assert_eq!(english.url.as_ref().unwrap().as_str(), "https://example.com/vod/audio/en_stereo.m3u8");
+
+        let atmos = &manifest.audio_tracks[3];
+        assert_eq!(atmos.channels, Some(16));
+        assert_eq!(atmos.codec, Some(AudioCodec::Eac3));
+        assert!(atmos.is_audio_description);
+
+        // Picking the 5.1 track narrows the ladder to the surround variant
+        let tracks = manifest.media_tracks();
+        let surround = &manifest.audio_tracks[2];
+        assert_eq!(surround.channels, Some(6));
+        let variants: Vec<u64> = tracks.renditions_for_audio(surround).iter().map(|r| r.bandwidth).collect();
+        assert_eq!(variants, [3200000]);
+        assert_eq!(tracks.default_audio_track().unwrap().id, "audio_0");
+        assert_eq!(tracks.audio_tracks_by_language("en").len(), 3);
+    }
+
+    #[test]
+    fn test_parse_master_text_tracks() {
+        let content = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"subs\",LANGUAGE=\"en\",NAME=\"English\",DEFAULT=YES,URI=\"subs/en.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"subs\",LANGUAGE=\"fr\",NAME=\"Francais\",FORCED=YES,URI=\"subs/fr.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID=\"cc\",LANGUAGE=\"en\",NAME=\"English CC\",INSTREAM-ID=\"CC1\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2800000,SUBTITLES=\"subs\",CLOSED-CAPTIONS=\"cc\"\n\
+            720p.m3u8\n";
+        let base = Url::parse("https://example.com/vod/master.m3u8").unwrap();
+
+        let manifest = HlsParser::new().parse_master(content, &base).unwrap();
+
+        let tracks: Vec<_> = manifest.text_tracks.iter().map(|t| (t.id.as_str(), t.language.as_str(), t.format)).collect();
+        assert_eq!(tracks, [
+            ("subtitles_0", "en", TextTrackFormat::WebVtt),
+            ("subtitles_1", "fr", TextTrackFormat::WebVtt),
+            ("cc1", "en", TextTrackFormat::Cea608),
+        ]);
+        assert_eq!(manifest.text_tracks[0].url.as_str(), "https://example.com/vod/subs/en.m3u8");
+        assert!(manifest.text_tracks[0].is_default);
+        assert!(manifest.text_tracks[1].is_forced);
+        assert_eq!(manifest.text_tracks[2].kind, TextTrackKind::Captions);
+    }
+
+    #[test]
+    fn test_parse_fairplay_sample_aes() {
+        let content = "#EXTM3U\n\
+            #EXT-X-VERSION:5\n\
+            #EXT-X-TARGETDURATION:6\n\
+            #EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"skd://twelve-monkeys\",KEYFORMAT=\"com.apple.streamingkeydelivery\",KEYFORMATVERSIONS=\"1\"\n\
+            #EXTINF:6.0,\n\
+            seg0.ts\n\
+            #EXTINF:6.0,\n\
+            seg1.ts\n\
+            #EXT-X-ENDLIST\n";
+        let base = Url::parse("https://example.com/vod/720p.m3u8").unwrap();
+
+        let playlist = HlsParser::new().parse_media(content, &base).unwrap();
+
+        let info = playlist.segments[1].encryption.as_ref().unwrap();
+        assert_eq!(info.method, EncryptionMethod::SampleAes);
+        assert_eq!(info.key_uri.as_ref().unwrap().as_str(), "skd://twelve-monkeys");
+        assert_eq!(info.key_format.as_deref(), Some("com.apple.streamingkeydelivery"));
+        assert_eq!(info.key_format_versions.as_deref(), Some("1"));
+        assert_eq!(info.drm_system(), Some(DrmSystem::FairPlay));
+
+        assert_eq!(playlist.drm_init_data.len(), 1);
+        let pssh = &playlist.drm_init_data[0];
+        assert_eq!(pssh.drm_system(), Some(DrmSystem::FairPlay));
+        assert_eq!(pssh.key_ids, ["twelve-monkeys"]);
+    }
+
+    #[test]
+    fn test_parse_widevine_sample_aes_ctr() {
+        let pssh = "AAAAO3Bzc2gAAAAA7e+LqXnWSs6jyCfc1R0h7QAAABsIARIQq6urq6urq6urq6urq6urqyIFYXNzZXQ=";
+        let content = format!("#EXTM3U\n\
+            #EXT-X-VERSION:6\n\
+            #EXT-X-TARGETDURATION:4\n\
+            #EXT-X-MAP:URI=\"init.mp4\"\n\
+            #EXT-X-KEY:METHOD=SAMPLE-AES-CTR,URI=\"data:text/plain;base64,{pssh}\",KEYFORMAT=\"urn:uuid:edef8ba9-79d6-4ace-a3c8-27dcd51d21ed\",KEYFORMATVERSIONS=\"1\"\n\
+            #EXT-X-KEY:METHOD=SAMPLE-AES-CTR,URI=\"skd://twelve-monkeys\",KEYFORMAT=\"com.apple.streamingkeydelivery\",KEYFORMATVERSIONS=\"1\"\n\
+            #EXTINF:4.0,\n\
+            seg0.m4s\n\
+            #EXT-X-ENDLIST\n");
+        let base = Url::parse("https://example.com/vod/720p.m3u8").unwrap();
+
+        let playlist = HlsParser::new().parse_media(&content, &base).unwrap();
+
+        let info = playlist.segments[0].encryption.as_ref().unwrap();
+        assert_eq!(info.method, EncryptionMethod::SampleAesCtr);
+
+        // Both systems survive even though segments only keep one key
+        let systems: Vec<_> = playlist.drm_init_data.iter().map(|p| p.drm_system()).collect();
+        assert_eq!(systems, [Some(DrmSystem::Widevine), Some(DrmSystem::FairPlay)]);
+        assert_eq!(playlist.drm_init_data[0].data, pssh);
+    }
+
+    #[test]
+    fn test_parse_master_session_keys() {
+        let pssh = "AAAAO3Bzc2gAAAAA7e+LqXnWSs6jyCfc1R0h7QAAABsIARIQq6urq6urq6urq6urq6urqyIFYXNzZXQ=";
+        let content = format!("#EXTM3U\n\
+            #EXT-X-SESSION-KEY:METHOD=SAMPLE-AES,URI=\"skd://twelve-monkeys\",KEYFORMAT=\"com.apple.streamingkeydelivery\",KEYFORMATVERSIONS=\"1\"\n\
+            #EXT-X-SESSION-KEY:METHOD=SAMPLE-AES,URI=\"data:text/plain;base64,{pssh}\",KEYFORMAT=\"urn:uuid:edef8ba9-79d6-4ace-a3c8-27dcd51d21ed\",KEYFORMATVERSIONS=\"1\"\n\
+            #EXT-X-SESSION-KEY:METHOD=AES-128,URI=\"https://keys.example.com/key\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2800000\n\
+            720p.m3u8\n");
+        let base = Url::parse("https://example.com/vod/master.m3u8").unwrap();
+
+        let manifest = HlsParser::new().parse_master(&content, &base).unwrap();
+
+        // The plain AES-128 key needs no license
+        assert_eq!(manifest.drm_init_data.len(), 2);
+        assert_eq!(manifest.drm_init_data[0].drm_system(), Some(DrmSystem::FairPlay));
+        assert_eq!(manifest.drm_init_data[1].drm_system(), Some(DrmSystem::Widevine));
+
+        let mut drm = crate::DrmManager::new(crate::DrmConfig::default());
+        drm.add_pssh_boxes(&manifest.drm_init_data);
+        assert!(drm.is_drm_required());
+        assert!(drm.get_pssh(DrmSystem::FairPlay).is_some());
+    }
+
+    #[test]
+    fn test_parse_content_steering() {
+        let content = "#EXTM3U\n\
+            #EXT-X-CONTENT-STEERING:SERVER-URI=\"/steering?video=00012\",PATHWAY-ID=\"CDN-B\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,PATHWAY-ID=\"CDN-A\"\n\
+            https://a.example.com/vod/480p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2800000,PATHWAY-ID=\"CDN-A\"\n\
+            https://a.example.com/vod/720p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000,PATHWAY-ID=\"CDN-B\"\n\
+            https://b.example.com/vod/480p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2800000,PATHWAY-ID=\"CDN-B\"\n\
+            https://b.example.com/vod/720p.m3u8\n";
+        let base = Url::parse("https://example.com/vod/master.m3u8").unwrap();
+
+        let manifest = HlsParser::new().parse_master(content, &base).unwrap();
+
+        let steering = manifest.content_steering.unwrap();
+        assert_eq!(steering.server_uri.unwrap().as_str(), "https://example.com/steering?video=00012");
+        assert_eq!(steering.default_pathway.as_deref(), Some("CDN-B"));
+        assert_eq!(steering.pathways.len(), 2);
+        assert_eq!(steering.pathways[0].base.as_str(), "https://a.example.com/");
+
+        // Only the default pathway's variants become renditions
+        assert_eq!(manifest.renditions.len(), 2);
+        assert!(manifest.renditions.iter().all(|r| r.uri.host_str() == Some("b.example.com")));
+    }
+
+    #[test]
+    fn test_parse_attribute_list() {
+        let attrs = parse_attribute_list("SERVER-URI=\"https://x.com/s?a=1,b=2\",PATHWAY-ID=CDN-A");
+        assert_eq!(attrs["SERVER-URI"], "https://x.com/s?a=1,b=2");
+        assert_eq!(attrs["PATHWAY-ID"], "CDN-A");
+    }
+
+    #[tokio::test]
+    async fn test_parse_through_fetcher() {
+        let master = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2000000,RESOLUTION=1280x720\n\
+            720p/index.m3u8\n";
+        let media = "#EXTM3U\n\
+            #EXT-X-TARGETDURATION:6\n\
+            #EXTINF:6.0,\n\
+            seg0.ts\n\
+            #EXT-X-ENDLIST\n";
+        let fetcher = Arc::new(
+            http::ScriptedFetcher::new()
+                .respond("https://cdn.example.com/vod/master.m3u8", 200, master)
+                .respond("https://cdn.example.com/vod/720p/index.m3u8", 200, media),
+        );
+        let parser = HlsParser::with_fetcher(fetcher.clone());
+
+        let url = Url::parse("https://cdn.example.com/vod/master.m3u8").unwrap();
+        let manifest = parser.parse(&url).await.unwrap();
+        assert_eq!(manifest.fetched_bytes, master.len());
+        let segments = parser.parse_variant(&manifest.renditions[0].uri).await.unwrap();
+        assert_eq!(segments[0].uri.as_str(), "https://cdn.example.com/vod/720p/seg0.ts");
+        assert_eq!(fetcher.requests().len(), 2);
+
+        let missing = Url::parse("https://cdn.example.com/vod/gone.m3u8").unwrap();
+        assert_eq!(parser.parse(&missing).await.unwrap_err().status(), Some(404));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_live_playlist() {
+        let url = "https://cdn.example.com/live/720p.m3u8";
+        let playlist = "#EXTM3U\n\
+            #EXT-X-TARGETDURATION:4\n\
+            #EXT-X-MEDIA-SEQUENCE:10\n\
+            #EXTINF:4.0,\n\
+            seg10.ts\n\
+            #EXTINF:4.0,\n\
+            seg11.ts\n";
+        let fetcher = Arc::new(
+            http::ScriptedFetcher::new()
+                .respond_with_headers(url, 200, HashMap::from([("ETag".to_string(), "\"a1\"".to_string())]), playlist)
+                .respond_with_headers(
+                    url,
+                    304,
+                    HashMap::from([("Age".to_string(), "9".to_string()), ("X-Cache".to_string(), "HIT".to_string())]),
+                    "",
+                ),
+        );
+        let parser = HlsParser::with_fetcher(fetcher.clone());
+        let url = Url::parse(url).unwrap();
+
+        let first = parser.refresh_segments(&url, 10).await.unwrap();
+        assert_eq!(first.segments.len(), 1);
+        assert_eq!(first.reload_interval, Duration::from_secs(4));
+        assert_eq!(first.next_poll(), Duration::from_secs(4));
+
+        // The 304 replays the cached playlist, but the CDN copy is 9s old
+        let second = parser.refresh_segments(&url, 11).await.unwrap();
+        assert!(second.segments.is_empty());
+        assert!(second.freshness.not_modified);
+        assert!(second.freshness.is_stale(second.reload_interval));
+        assert_eq!(second.next_poll(), Duration::from_secs(1));
+        assert_eq!(fetcher.requests()[1].headers["If-None-Match"], "\"a1\"");
+    }
 }
+
@@ -0,0 +1,261 @@
+//! Conditional refreshing of live playlists and MPDs
+//!
+//! Live playlists are re-fetched every few seconds. [`PlaylistCache`] keeps
+//! the last copy of each one with its `ETag` and `Last-Modified` validators
+//! and revalidates it with `If-None-Match` / `If-Modified-Since`, so an
+//! unchanged playlist costs a bodiless 304 instead of a full download.
+//!
+//! Every refresh reports a [`PlaylistFreshness`]: whether the playlist
+//! changed, how long intermediary caches held the response (`Age`), its
+//! `Cache-Control` lifetime and whether a cache served it. It schedules the
+//! next poll and lets monitoring tell an origin that stopped publishing
+//! from a CDN handing out a stale copy.
+
+use crate::{http::HttpFetcher, Result, Segment};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Headers CDNs use to report a cache hit
+const CACHE_STATUS_HEADERS: [&str; 3] = ["x-cache", "cf-cache-status", "x-cache-status"];
+
+/// How fresh the response to a playlist refresh was
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistFreshness {
+    /// The server answered 304 Not Modified
+    pub not_modified: bool,
+    /// The playlist is the same as on the previous refresh, by a 304 or an
+    /// identical body
+    pub unchanged: bool,
+    /// Time the response spent in intermediary caches (`Age`)
+    pub age: Duration,
+    /// Freshness lifetime from `Cache-Control`: `s-maxage`, else `max-age`;
+    /// zero for `no-cache` and `no-store`
+    pub max_age: Option<Duration>,
+    /// An intermediary cache served the response: a non-zero `Age` or a
+    /// cache hit reported by the CDN
+    pub from_cache: bool,
+    /// Time since this client last saw the playlist change
+    pub unchanged_for: Duration,
+}
+
+impl PlaylistFreshness {
+    /// Whether a cache served a copy older than `reload_interval`, so the
+    /// origin has probably moved past it
+    pub fn is_stale(&self, reload_interval: Duration) -> bool {
+        self.from_cache && self.age > reload_interval
+    }
+
+    /// Delay before the next refresh of a playlist that should be reloaded
+    /// every `reload_interval` (the HLS target duration or the DASH
+    /// `minimumUpdatePeriod`)
+    ///
+    /// Follows RFC 8216: the full interval after a change, half of it
+    /// otherwise. The response's age is taken off, since the copy was made
+    /// that long ago, but the poll waits for a cached copy to expire, since
+    /// polling earlier only re-reads it. The result stays between a quarter
+    /// of the interval and the whole interval.
+    pub fn next_poll(&self, reload_interval: Duration) -> Duration {
+        let base = if self.unchanged { reload_interval / 2 } else { reload_interval };
+        let mut delay = base.saturating_sub(self.age);
+        if let Some(max_age) = self.max_age {
+            delay = delay.max(max_age.saturating_sub(self.age));
+        }
+        delay.clamp(reload_interval / 4, reload_interval)
+    }
+}
+
+/// Result of refreshing a live playlist or MPD
+#[derive(Debug, Clone)]
+pub struct LiveRefresh {
+    /// Segments after the last sequence number already seen
+    pub segments: Vec<Segment>,
+    /// How often the playlist should be reloaded: the HLS target duration
+    /// or the DASH `minimumUpdatePeriod`
+    pub reload_interval: Duration,
+    pub freshness: PlaylistFreshness,
+}
+
+impl LiveRefresh {
+    /// Delay before the next refresh; see [`PlaylistFreshness::next_poll`]
+    pub fn next_poll(&self) -> Duration {
+        self.freshness.next_poll(self.reload_interval)
+    }
+}
+
+/// Last copy of a playlist and its validators
+#[derive(Debug, Clone)]
+struct CachedPlaylist {
+    body: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    changed_at: Instant,
+}
+
+/// Last fetched copy of each live playlist, revalidated on refresh
+#[derive(Debug, Default)]
+pub(crate) struct PlaylistCache {
+    entries: Mutex<HashMap<Url, CachedPlaylist>>,
+}
+
+impl PlaylistCache {
+    /// Fetch `url`, revalidating the copy from the previous fetch
+    pub(crate) async fn fetch(&self, fetcher: &dyn HttpFetcher, url: &Url) -> Result<(String, PlaylistFreshness)> {
+        let previous = self.entries.lock().unwrap().get(url).cloned();
+
+        let mut headers = HashMap::new();
+        if let Some(previous) = &previous {
+            if let Some(etag) = &previous.etag {
+                headers.insert("If-None-Match".to_string(), etag.clone());
+            }
+            if let Some(last_modified) = &previous.last_modified {
+                headers.insert("If-Modified-Since".to_string(), last_modified.clone());
+            }
+        }
+
+        let response = fetcher.get(url, &headers, None).await?;
+        let age = response.header("age")
+            .and_then(|age| age.trim().parse::<u64>().ok())
+            .map_or(Duration::ZERO, Duration::from_secs);
+        let cache_hit = CACHE_STATUS_HEADERS.iter()
+            .filter_map(|name| response.header(name))
+            .any(|status| status.to_ascii_uppercase().contains("HIT"));
+        let mut freshness = PlaylistFreshness {
+            age,
+            max_age: response.header("cache-control").and_then(cache_lifetime),
+            from_cache: cache_hit || !age.is_zero(),
+            ..Default::default()
+        };
+
+        if let (304, Some(previous)) = (response.status, &previous) {
+            freshness.not_modified = true;
+            freshness.unchanged = true;
+            freshness.unchanged_for = previous.changed_at.elapsed();
+            return Ok((previous.body.clone(), freshness));
+        }
+
+        let response = response.error_for_status()?;
+        let etag = response.header("etag").map(str::to_string);
+        let last_modified = response.header("last-modified").map(str::to_string);
+        let body = response.text().await?;
+
+        let changed_at = match &previous {
+            Some(previous) if previous.body == body => {
+                freshness.unchanged = true;
+                previous.changed_at
+            }
+            _ => Instant::now(),
+        };
+        freshness.unchanged_for = changed_at.elapsed();
+
+        self.entries.lock().unwrap().insert(url.clone(), CachedPlaylist {
+            body: body.clone(),
+            etag,
+            last_modified,
+            changed_at,
+        });
+        Ok((body, freshness))
+    }
+}
+
+/// Freshness lifetime of a `Cache-Control` header value
+fn cache_lifetime(value: &str) -> Option<Duration> {
+    let mut max_age = None;
+    let mut s_maxage = None;
+    for directive in value.split(',') {
+        let directive = directive.trim().to_ascii_lowercase();
+        match directive.split_once('=') {
+            Some(("max-age", secs)) => max_age = secs.trim_matches('"').parse::<u64>().ok(),
+            Some(("s-maxage", secs)) => s_maxage = secs.trim_matches('"').parse::<u64>().ok(),
+            None if directive == "no-cache" || directive == "no-store" => return Some(Duration::ZERO),
+            _ => {}
+        }
+    }
+    s_maxage.or(max_age).map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::ScriptedFetcher;
+
+    #[test]
+    fn test_cache_lifetime() {
+        assert_eq!(cache_lifetime("public, max-age=2"), Some(Duration::from_secs(2)));
+        assert_eq!(cache_lifetime("max-age=10, s-maxage=1"), Some(Duration::from_secs(1)));
+        assert_eq!(cache_lifetime("no-cache"), Some(Duration::ZERO));
+        assert_eq!(cache_lifetime("public"), None);
+    }
+
+    #[test]
+    fn test_next_poll() {
+        let target = Duration::from_secs(6);
+        let changed = PlaylistFreshness::default();
+        assert_eq!(changed.next_poll(target), target);
+
+        let unchanged = PlaylistFreshness { unchanged: true, ..Default::default() };
+        assert_eq!(unchanged.next_poll(target), Duration::from_secs(3));
+
+        // A copy cached 2s ago was made 2s closer to the next update
+        let aged = PlaylistFreshness { age: Duration::from_secs(2), from_cache: true, ..Default::default() };
+        assert_eq!(aged.next_poll(target), Duration::from_secs(4));
+        assert!(!aged.is_stale(target));
+
+        // No point polling before the cached copy expires
+        let cached = PlaylistFreshness { unchanged: true, max_age: Some(Duration::from_secs(5)), ..Default::default() };
+        assert_eq!(cached.next_poll(target), Duration::from_secs(5));
+
+        // Stale copies are re-polled soon, but not in a tight loop
+        let stale = PlaylistFreshness { age: Duration::from_secs(30), from_cache: true, ..Default::default() };
+        assert_eq!(stale.next_poll(target), Duration::from_millis(1500));
+        assert!(stale.is_stale(target));
+    }
+
+    #[tokio::test]
+    async fn test_conditional_refresh() {
+        let url = Url::parse("https://cdn.example.com/live.m3u8").unwrap();
+        let validators = HashMap::from([
+            ("ETag".to_string(), "\"v1\"".to_string()),
+            ("Last-Modified".to_string(), "Tue, 06 Oct 2026 10:00:00 GMT".to_string()),
+            ("Cache-Control".to_string(), "max-age=1".to_string()),
+        ]);
+        let fetcher = ScriptedFetcher::new()
+            .respond_with_headers(url.as_str(), 200, validators, "#EXTM3U\n")
+            .respond_with_headers(
+                url.as_str(),
+                304,
+                HashMap::from([("Age".to_string(), "3".to_string()), ("X-Cache".to_string(), "HIT".to_string())]),
+                "",
+            )
+            .respond(url.as_str(), 200, "#EXTM3U\n")
+            .respond(url.as_str(), 200, "#EXTM3U\n#EXT-X-ENDLIST\n");
+        let cache = PlaylistCache::default();
+
+        let (body, first) = cache.fetch(&fetcher, &url).await.unwrap();
+        assert_eq!(body, "#EXTM3U\n");
+        assert!(!first.unchanged && !first.from_cache);
+        assert_eq!(first.max_age, Some(Duration::from_secs(1)));
+
+        // 304s return the cached copy
+        let (body, second) = cache.fetch(&fetcher, &url).await.unwrap();
+        assert_eq!(body, "#EXTM3U\n");
+        assert!(second.not_modified && second.unchanged && second.from_cache);
+        assert_eq!(second.age, Duration::from_secs(3));
+
+        // Servers without validators are compared by body
+        let (_, third) = cache.fetch(&fetcher, &url).await.unwrap();
+        assert!(third.unchanged && !third.not_modified);
+        let (body, fourth) = cache.fetch(&fetcher, &url).await.unwrap();
+        assert!(body.contains("ENDLIST"));
+        assert!(!fourth.unchanged);
+
+        let requests = fetcher.requests();
+        assert!(!requests[0].headers.contains_key("If-None-Match"));
+        assert_eq!(requests[1].headers["If-None-Match"], "\"v1\"");
+        assert_eq!(requests[1].headers["If-Modified-Since"], "Tue, 06 Oct 2026 10:00:00 GMT");
+        // The 304 kept the validators of the copy it confirmed
+        assert_eq!(requests[2].headers["If-None-Match"], "\"v1\"");
+        assert!(!requests[3].headers.contains_key("If-None-Match"));
+    }
+}
@@ -2,12 +2,19 @@
 
 mod hls;
 mod dash;
+mod live;
 
-pub use hls::HlsParser;
+pub use hls::{HlsParser, VariantPlaylist};
+pub(crate) use hls::parse_attribute_list;
 pub use dash::DashParser;
+pub use live::{LiveRefresh, PlaylistFreshness};
+pub(crate) use live::PlaylistCache;
 
-use crate::{Result, Rendition, Segment};
+use crate::{drm::PsshBox, http::HttpFetcher, steering::ContentSteering, AudioTrack, MediaTracks, Result, Rendition, Segment, TextTrack};
 use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 use url::Url;
 
 /// Manifest types
@@ -24,14 +31,91 @@ pub struct Manifest {
     pub manifest_type: ManifestType,
     /// Available renditions/variants
     pub renditions: Vec<Rendition>,
+    /// I-frame only renditions for trick play (HLS EXT-X-I-FRAME-STREAM-INF)
+    pub iframe_renditions: Vec<Rendition>,
+    /// Alternate audio tracks (HLS EXT-X-MEDIA TYPE=AUDIO)
+    pub audio_tracks: Vec<AudioTrack>,
+    /// Subtitle and closed caption tracks (HLS EXT-X-MEDIA TYPE=SUBTITLES
+    /// and TYPE=CLOSED-CAPTIONS)
+    pub text_tracks: Vec<TextTrack>,
+    /// DRM init data declared up front, to pass to
+    /// [`DrmManager::add_pssh_boxes`](crate::DrmManager::add_pssh_boxes)
+    pub drm_init_data: Vec<PsshBox>,
+    /// CDN pathways and steering server, if the manifest declares any
+    pub content_steering: Option<ContentSteering>,
     /// Is this a live stream
     pub is_live: bool,
     /// Total duration (for VOD)
     pub duration: Option<std::time::Duration>,
     /// Target segment duration
     pub target_duration: std::time::Duration,
+    /// How often a live DASH MPD is refetched (`minimumUpdatePeriod`);
+    /// `None` for HLS, whose playlists reload every target duration
+    pub minimum_update_period: Option<Duration>,
     /// Base URL for resolving relative URIs
     pub base_url: Url,
+    /// Size of the downloaded manifest document in bytes (0 if it was not
+    /// fetched)
+    pub fetched_bytes: usize,
+    /// DASH Periods in presentation order; `renditions` are those of the
+    /// first. Empty for HLS.
+    pub periods: Vec<Period>,
+}
+
+impl Manifest {
+    /// Period playing at `position` seconds, if the manifest has periods
+    pub fn period_at(&self, position: f64) -> Option<&Period> {
+        self.periods.iter()
+            .take_while(|p| p.start.as_secs_f64() <= position)
+            .last()
+            .or(self.periods.first())
+    }
+
+    /// Renditions available at `position` seconds
+    pub fn renditions_at(&self, position: f64) -> &[Rendition] {
+        self.period_at(position).map_or(&self.renditions, |p| &p.renditions)
+    }
+
+    /// Segments of a rendition from any period, if the manifest lists them
+    pub fn segments(&self, rendition_id: &str) -> Option<&[Segment]> {
+        self.periods.iter().find_map(|p| p.segments.get(rendition_id)).map(Vec::as_slice)
+    }
+
+    /// Video renditions, audio and text tracks as [`MediaTracks`]
+    pub fn media_tracks(&self) -> MediaTracks {
+        MediaTracks {
+            video: self.renditions.clone(),
+            audio: self.audio_tracks.clone(),
+            text: self.text_tracks.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A span of the presentation with its own renditions, such as an ad break
+/// in an ad-stitched DASH stream
+#[derive(Debug, Clone)]
+pub struct Period {
+    /// Period id, or `period_<n>` when the MPD has none
+    pub id: String,
+    /// Presentation time the period starts at
+    pub start: Duration,
+    /// Length of the period, if known
+    pub duration: Option<Duration>,
+    /// Discontinuity sequence of the period's segments, one higher than
+    /// the previous period's
+    pub discontinuity_sequence: u32,
+    /// Renditions, by ascending bandwidth
+    pub renditions: Vec<Rendition>,
+    /// Segments of each rendition, keyed by rendition id
+    pub segments: HashMap<String, Vec<Segment>>,
+}
+
+impl Period {
+    /// Presentation time the period ends at, if known
+    pub fn end(&self) -> Option<Duration> {
+        self.duration.map(|d| self.start + d)
+    }
 }
 
 /// Trait for manifest parsers
@@ -45,6 +129,11 @@ pub trait ManifestParser: Send + Sync {
 
     /// Get the latest segments (for live)
     async fn get_latest_segments(&self, url: &Url, last_sequence: u64) -> Result<Vec<Segment>>;
+
+    /// Refetch a live playlist or MPD, revalidating the previous copy with
+    /// a conditional request, and return the segments after
+    /// `last_sequence` along with the response's freshness
+    async fn refresh_segments(&self, url: &Url, last_sequence: u64) -> Result<LiveRefresh>;
 }
 
 /// Detect manifest type from URL or content
@@ -80,6 +169,14 @@ pub fn create_parser(url: &Url) -> Box<dyn ManifestParser> {
     }
 }
 
+/// Like [`create_parser`], with the parser fetching through `fetcher`
+pub fn create_parser_with(url: &Url, fetcher: Arc<dyn HttpFetcher>) -> Box<dyn ManifestParser> {
+    match detect_manifest_type(url, None) {
+        ManifestType::Hls => Box::new(HlsParser::with_fetcher(fetcher)),
+        ManifestType::Dash => Box::new(DashParser::with_fetcher(fetcher)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
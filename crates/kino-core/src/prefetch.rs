@@ -0,0 +1,472 @@
+//! Segment prefetch scheduling
+//!
+//! Decides which upcoming segments to fetch ahead of the playhead, and at
+//! which rendition. Segments before a predicted ABR switch point are
+//! fetched at the active rendition and those after it at the predicted
+//! one, so a switch does not waste the lookahead. Prefetching is bounded
+//! by buffer time (scaled by playback rate) and memory, and in-flight
+//! requests a seek makes useless are cancelled.
+
+use crate::types::{Rendition, Segment};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Prefetch limits
+#[derive(Debug, Clone)]
+pub struct PrefetchConfig {
+    /// Maximum segments in flight at once
+    pub max_segments: usize,
+    /// Maximum lookahead in wall-clock seconds of playback
+    pub max_buffer_time: f64,
+    /// Maximum bytes held by the buffer and in-flight prefetches together
+    pub max_memory_bytes: usize,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            max_segments: 3,
+            max_buffer_time: 30.0,
+            max_memory_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// A rendition switch the ABR engine is expected to make
+#[derive(Debug, Clone, Copy)]
+pub struct PredictedSwitch<'a> {
+    /// Rendition the engine will switch to
+    pub rendition: &'a Rendition,
+    /// Its segment list, aligned with the active rendition's
+    pub segments: &'a [Segment],
+    /// Upcoming segments still fetched at the active rendition
+    pub after_segments: u32,
+}
+
+/// Playback state a prefetch plan is made from
+#[derive(Debug, Clone, Copy)]
+pub struct PrefetchContext<'a> {
+    /// Playback position (seconds)
+    pub position: f64,
+    /// Playback rate
+    pub playback_rate: f64,
+    /// Media buffered ahead of the position (seconds)
+    pub buffer_level: f64,
+    /// Bytes currently held by the buffer
+    pub memory_used: usize,
+    /// Rendition being played
+    pub rendition: &'a Rendition,
+    /// Its segment list, starting at time zero
+    pub segments: &'a [Segment],
+    /// Switch predicted by the ABR engine
+    pub predicted: Option<PredictedSwitch<'a>>,
+}
+
+/// A segment scheduled for prefetching
+#[derive(Debug, Clone)]
+pub struct PrefetchRequest {
+    /// Segment to fetch
+    pub segment: Segment,
+    /// Rendition the segment belongs to
+    pub rendition_id: String,
+    /// Start of the segment in the timeline (seconds)
+    pub start_time: f64,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl PrefetchRequest {
+    /// Whether a seek made this request useless; the fetch should be
+    /// abandoned and its result discarded
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Prefetch effectiveness counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PrefetchStats {
+    /// Prefetch requests issued
+    pub issued: u64,
+    /// Consumed segments that had been prefetched
+    pub hits: u64,
+    /// Consumed segments that had not been prefetched
+    pub misses: u64,
+    /// Prefetched segments dropped without being played
+    pub wasted: u64,
+    /// In-flight prefetches cancelled by a seek
+    pub cancelled: u64,
+}
+
+impl PrefetchStats {
+    /// Fraction of consumed segments served by prefetching
+    pub fn hit_ratio(&self) -> f64 {
+        let consumed = self.hits + self.misses;
+        if consumed == 0 {
+            0.0
+        } else {
+            self.hits as f64 / consumed as f64
+        }
+    }
+}
+
+/// An in-flight prefetch
+#[derive(Debug)]
+struct InFlight {
+    start_time: f64,
+    end_time: f64,
+    bytes: usize,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Schedules segment prefetches
+#[derive(Debug)]
+pub struct PrefetchScheduler {
+    config: PrefetchConfig,
+    /// In-flight requests by segment number
+    in_flight: BTreeMap<u64, InFlight>,
+    /// Prefetched segments not yet consumed
+    prefetched: HashSet<u64>,
+    stats: PrefetchStats,
+}
+
+impl PrefetchScheduler {
+    /// Create a scheduler with the given limits
+    pub fn new(config: PrefetchConfig) -> Self {
+        Self {
+            config,
+            in_flight: BTreeMap::new(),
+            prefetched: HashSet::new(),
+            stats: PrefetchStats::default(),
+        }
+    }
+
+    /// Plan the next prefetches
+    ///
+    /// Walks the segments after the buffered range, up to the lookahead
+    /// horizon, and issues requests while in-flight and memory budgets
+    /// allow. Segment sizes are estimated from rendition bandwidth.
+    pub fn plan(&mut self, context: &PrefetchContext<'_>) -> Vec<PrefetchRequest> {
+        let rate = if context.playback_rate > 0.0 { context.playback_rate } else { 1.0 };
+        // At 2x, 30s of playback consumes 60s of media
+        let horizon_end = context.position + self.config.max_buffer_time * rate;
+        let buffered_end = context.position + context.buffer_level;
+
+        let in_flight_bytes: usize = self.in_flight.values().map(|f| f.bytes).sum();
+        let mut budget = self
+            .config
+            .max_memory_bytes
+            .saturating_sub(context.memory_used + in_flight_bytes);
+        let mut slots = self.config.max_segments.saturating_sub(self.in_flight.len());
+
+        let mut requests = Vec::new();
+        let mut upcoming = 0u32;
+        let mut start = 0.0;
+
+        for (index, active) in context.segments.iter().enumerate() {
+            let duration = active.duration.as_secs_f64();
+            let end = start + duration;
+            let segment_start = start;
+            start = end;
+
+            // Already played or buffered
+            if end <= buffered_end + 0.01 {
+                continue;
+            }
+            if segment_start >= horizon_end || slots == 0 {
+                break;
+            }
+
+            let (rendition, segment) = match &context.predicted {
+                Some(p) if upcoming >= p.after_segments => match p.segments.get(index) {
+                    Some(segment) => (p.rendition, segment),
+                    None => (context.rendition, active),
+                },
+                _ => (context.rendition, active),
+            };
+            upcoming += 1;
+
+            if self.in_flight.contains_key(&segment.number) || self.prefetched.contains(&segment.number) {
+                continue;
+            }
+
+            let bytes = (rendition.bandwidth as f64 * duration / 8.0) as usize;
+            if bytes > budget {
+                break;
+            }
+            budget -= bytes;
+            slots -= 1;
+
+            let cancelled = Arc::new(AtomicBool::new(false));
+            self.in_flight.insert(
+                segment.number,
+                InFlight {
+                    start_time: segment_start,
+                    end_time: end,
+                    bytes,
+                    cancelled: cancelled.clone(),
+                },
+            );
+            self.stats.issued += 1;
+            requests.push(PrefetchRequest {
+                segment: segment.clone(),
+                rendition_id: rendition.id.clone(),
+                start_time: segment_start,
+                cancelled,
+            });
+        }
+
+        if !requests.is_empty() {
+            debug!(
+                count = requests.len(),
+                first = requests[0].segment.number,
+                rendition = %requests[0].rendition_id,
+                "Prefetches scheduled"
+            );
+        }
+        requests
+    }
+
+    /// Record a finished prefetch, returning false if it was cancelled and
+    /// its data should be discarded
+    pub fn complete(&mut self, request: &PrefetchRequest) -> bool {
+        if request.is_cancelled() {
+            return false;
+        }
+        if self.in_flight.remove(&request.segment.number).is_none() {
+            return false;
+        }
+        self.prefetched.insert(request.segment.number);
+        true
+    }
+
+    /// Record a failed prefetch so the segment can be scheduled again
+    pub fn fail(&mut self, request: &PrefetchRequest) {
+        self.in_flight.remove(&request.segment.number);
+    }
+
+    /// Cancel in-flight prefetches outside the lookahead window starting
+    /// at `position`, returning how many were cancelled
+    pub fn cancel_outside(&mut self, position: f64) -> usize {
+        let horizon_end = position + self.config.max_buffer_time;
+        self.cancel_where(|f| f.end_time <= position || f.start_time >= horizon_end)
+    }
+
+    /// Cancel every in-flight prefetch and forget prefetched segments,
+    /// returning how many requests were cancelled
+    pub fn cancel_all(&mut self) -> usize {
+        self.stats.wasted += self.prefetched.len() as u64;
+        self.prefetched.clear();
+        self.cancel_where(|_| true)
+    }
+
+    fn cancel_where(&mut self, useless: impl Fn(&InFlight) -> bool) -> usize {
+        let numbers: Vec<u64> = self
+            .in_flight
+            .iter()
+            .filter(|(_, f)| useless(f))
+            .map(|(&n, _)| n)
+            .collect();
+
+        for number in &numbers {
+            if let Some(flight) = self.in_flight.remove(number) {
+                flight.cancelled.store(true, Ordering::Relaxed);
+            }
+        }
+        self.stats.cancelled += numbers.len() as u64;
+
+        if !numbers.is_empty() {
+            debug!(count = numbers.len(), "Prefetches cancelled");
+        }
+        numbers.len()
+    }
+
+    /// Record playback consuming a segment
+    pub fn record_consumed(&mut self, number: u64) {
+        if self.prefetched.remove(&number) {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+    }
+
+    /// Record a prefetched segment dropped from the buffer unplayed
+    pub fn record_dropped(&mut self, number: u64) {
+        if self.prefetched.remove(&number) {
+            self.stats.wasted += 1;
+        }
+    }
+
+    /// Number of prefetches in flight
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Effectiveness counters
+    pub fn stats(&self) -> PrefetchStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Resolution;
+    use std::time::Duration;
+    use url::Url;
+
+    fn rendition(id: &str, bandwidth: u64) -> Rendition {
+        Rendition {
+            id: id.to_string(),
+            bandwidth,
+            resolution: Some(Resolution::new(1280, 720)),
+            frame_rate: None,
+            video_codec: None,
+            audio_codec: None,
+            uri: Url::parse(&format!("https://example.com/{}.m3u8", id)).unwrap(),
+            hdr: None,
+            language: None,
+            name: None,
+            audio_group: None,
+        }
+    }
+
+    fn segments(id: &str, count: u64) -> Vec<Segment> {
+        (0..count)
+            .map(|n| Segment {
+                number: n,
+                uri: Url::parse(&format!("https://example.com/{}/{}.ts", id, n)).unwrap(),
+                duration: Duration::from_secs(4),
+                byte_range: None,
+                encryption: None,
+                discontinuity_sequence: 0,
+                program_date_time: None,
+            })
+            .collect()
+    }
+
+    fn context<'a>(rendition: &'a Rendition, segments: &'a [Segment]) -> PrefetchContext<'a> {
+        PrefetchContext {
+            position: 0.0,
+            playback_rate: 1.0,
+            buffer_level: 0.0,
+            memory_used: 0,
+            rendition,
+            segments,
+            predicted: None,
+        }
+    }
+
+    fn numbers(requests: &[PrefetchRequest]) -> Vec<u64> {
+        requests.iter().map(|r| r.segment.number).collect()
+    }
+
+    #[test]
+    fn test_plan_bounds() {
+        let low = rendition("low", 800_000);
+        let low_segments = segments("low", 50);
+        let config = PrefetchConfig {
+            max_segments: 100,
+            max_buffer_time: 12.0,
+            max_memory_bytes: usize::MAX,
+        };
+
+        // Starts after the buffered range, stops at the horizon
+        let mut scheduler = PrefetchScheduler::new(config.clone());
+        let ctx = PrefetchContext { buffer_level: 8.0, ..context(&low, &low_segments) };
+        assert_eq!(numbers(&scheduler.plan(&ctx)), vec![2]);
+
+        // Playback rate stretches the horizon; in-flight segments are skipped
+        let ctx = PrefetchContext { playback_rate: 2.0, ..ctx };
+        assert_eq!(numbers(&scheduler.plan(&ctx)), vec![3, 4, 5]);
+
+        // Memory: each segment is 800kbps * 4s = 400KB
+        let mut scheduler = PrefetchScheduler::new(PrefetchConfig {
+            max_memory_bytes: 1_000_000,
+            ..config.clone()
+        });
+        let ctx = PrefetchContext { memory_used: 100_000, ..context(&low, &low_segments) };
+        assert_eq!(numbers(&scheduler.plan(&ctx)), vec![0, 1]);
+
+        // In-flight count
+        let mut scheduler = PrefetchScheduler::new(PrefetchConfig { max_segments: 2, ..config });
+        let requests = scheduler.plan(&context(&low, &low_segments));
+        assert_eq!(numbers(&requests), vec![0, 1]);
+        assert!(scheduler.plan(&context(&low, &low_segments)).is_empty());
+        assert!(scheduler.complete(&requests[0]));
+        assert_eq!(numbers(&scheduler.plan(&context(&low, &low_segments))), vec![2]);
+    }
+
+    #[test]
+    fn test_plan_predicted_switch() {
+        let low = rendition("low", 800_000);
+        let high = rendition("high", 2_800_000);
+        let low_segments = segments("low", 10);
+        let high_segments = segments("high", 10);
+
+        let mut scheduler = PrefetchScheduler::new(PrefetchConfig {
+            max_segments: 4,
+            ..Default::default()
+        });
+        let ctx = PrefetchContext {
+            predicted: Some(PredictedSwitch {
+                rendition: &high,
+                segments: &high_segments,
+                after_segments: 2,
+            }),
+            ..context(&low, &low_segments)
+        };
+
+        let requests = scheduler.plan(&ctx);
+        let renditions: Vec<&str> = requests.iter().map(|r| r.rendition_id.as_str()).collect();
+        assert_eq!(renditions, vec!["low", "low", "high", "high"]);
+        assert_eq!(requests[2].segment.uri.path(), "/high/2.ts");
+        assert_eq!(requests[3].start_time, 12.0);
+    }
+
+    #[test]
+    fn test_seek_cancels_and_stats() {
+        let low = rendition("low", 800_000);
+        let low_segments = segments("low", 50);
+        let mut scheduler = PrefetchScheduler::new(PrefetchConfig {
+            max_segments: 3,
+            max_buffer_time: 12.0,
+            ..Default::default()
+        });
+
+        let requests = scheduler.plan(&context(&low, &low_segments));
+        assert_eq!(numbers(&requests), vec![0, 1, 2]);
+        assert!(scheduler.complete(&requests[0]));
+
+        // Seeking to 8s keeps segment 2 (8-12s) and cancels segment 1 (4-8s)
+        assert_eq!(scheduler.cancel_outside(8.0), 1);
+        assert!(requests[1].is_cancelled());
+        assert!(!requests[2].is_cancelled());
+        assert!(!scheduler.complete(&requests[1]));
+        assert!(scheduler.complete(&requests[2]));
+
+        scheduler.record_consumed(0);
+        scheduler.record_consumed(2);
+        scheduler.record_consumed(3);
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.issued, 3);
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.cancelled, 1);
+        assert!((stats.hit_ratio() - 2.0 / 3.0).abs() < 1e-9);
+
+        // A seek away drops everything
+        let requests = scheduler.plan(&PrefetchContext {
+            position: 16.0,
+            ..context(&low, &low_segments)
+        });
+        assert!(scheduler.complete(&requests[0]));
+        assert_eq!(scheduler.cancel_all(), requests.len() - 1);
+        let stats = scheduler.stats();
+        assert_eq!(stats.wasted, 1);
+        assert_eq!(scheduler.in_flight(), 0);
+    }
+}
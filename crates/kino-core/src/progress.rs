@@ -0,0 +1,208 @@
+//! Watch progress persistence for "resume where you left off"
+//!
+//! A [`PlayerSession`](crate::PlayerSession) given a [`WatchProgressStore`]
+//! and a content key saves the playback position every
+//! [`PlayerConfig::progress_save_interval_ms`](crate::PlayerConfig::progress_save_interval_ms)
+//! and whenever playback pauses, stops or ends. With
+//! [`PlayerConfig::resume_playback`](crate::PlayerConfig::resume_playback)
+//! set, loading the same content key seeks back to the saved position,
+//! unless the content was watched nearly to the end.
+//!
+//! ```rust,no_run
+//! use std::sync::Arc;
+//! use kino_core::{JsonFileProgressStore, PlayerConfig, PlayerSession};
+//!
+//! # async fn example() -> kino_core::Result<()> {
+//! let config = PlayerConfig { resume_playback: true, ..Default::default() };
+//! let session = PlayerSession::new(config)
+//!     .with_progress_store(Arc::new(JsonFileProgressStore::new("watch_progress.json")));
+//!
+//! session.set_content_key(Some("movie-42".to_string())).await;
+//! session.load(&"https://example.com/movie-42/master.m3u8".parse().unwrap()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Saved playback position of one piece of content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchProgress {
+    /// Caller-supplied key identifying the content
+    pub content_key: String,
+    /// Playback position (seconds)
+    pub position: f64,
+    /// Content duration (seconds), unknown for live streams
+    pub duration: Option<f64>,
+    /// When the progress was saved
+    pub updated_at: DateTime<Utc>,
+}
+
+impl WatchProgress {
+    /// Whether playback got past `finished_threshold` (a fraction of the
+    /// duration)
+    pub fn is_finished(&self, finished_threshold: f64) -> bool {
+        self.duration.is_some_and(|d| d > 0.0 && self.position >= d * finished_threshold)
+    }
+
+    /// Position to resume from, or `None` to start from the beginning
+    /// because nothing was watched or the content was finished
+    pub fn resume_position(&self, finished_threshold: f64) -> Option<f64> {
+        (self.position > 0.0 && !self.is_finished(finished_threshold)).then_some(self.position)
+    }
+}
+
+/// Persists watch progress by content key
+#[async_trait]
+pub trait WatchProgressStore: Send + Sync {
+    /// Save the position reached in `content_key` at `timestamp`
+    async fn save(&self, content_key: &str, position: f64, duration: Option<f64>, timestamp: DateTime<Utc>) -> Result<()>;
+
+    /// Saved progress of `content_key`, if any
+    async fn load(&self, content_key: &str) -> Result<Option<WatchProgress>>;
+
+    /// Forget the progress of `content_key`
+    async fn clear(&self, content_key: &str) -> Result<()>;
+}
+
+/// Stores all progress in one JSON file
+///
+/// The file is read on first use and rewritten atomically on every change.
+/// A missing or unreadable file starts out empty.
+pub struct JsonFileProgressStore {
+    path: PathBuf,
+    entries: Mutex<Option<HashMap<String, WatchProgress>>>,
+}
+
+impl JsonFileProgressStore {
+    /// Store progress in the JSON file at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            entries: Mutex::new(None),
+        }
+    }
+
+    /// Entries in the file, read on first use
+    async fn read(&self) -> HashMap<String, WatchProgress> {
+        match tokio::fs::read(&self.path).await {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!(path = %self.path.display(), error = %e, "Ignoring unreadable watch progress");
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    /// Apply `change` to the entries and write them back
+    async fn update(&self, change: impl FnOnce(&mut HashMap<String, WatchProgress>)) -> Result<()> {
+        let mut entries = self.entries.lock().await;
+        if entries.is_none() {
+            *entries = Some(self.read().await);
+        }
+        let entries = entries.as_mut().expect("entries were just read");
+        change(entries);
+
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let data = serde_json::to_vec_pretty(entries).map_err(std::io::Error::from)?;
+        let partial = self.path.with_extension("json.part");
+        tokio::fs::write(&partial, data).await?;
+        tokio::fs::rename(&partial, &self.path).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl WatchProgressStore for JsonFileProgressStore {
+    async fn save(&self, content_key: &str, position: f64, duration: Option<f64>, timestamp: DateTime<Utc>) -> Result<()> {
+        let progress = WatchProgress {
+            content_key: content_key.to_string(),
+            position,
+            duration,
+            updated_at: timestamp,
+        };
+        self.update(|entries| {
+            entries.insert(content_key.to_string(), progress);
+        })
+        .await
+    }
+
+    async fn load(&self, content_key: &str) -> Result<Option<WatchProgress>> {
+        let mut entries = self.entries.lock().await;
+        if entries.is_none() {
+            *entries = Some(self.read().await);
+        }
+        Ok(entries.as_ref().and_then(|e| e.get(content_key).cloned()))
+    }
+
+    async fn clear(&self, content_key: &str) -> Result<()> {
+        self.update(|entries| {
+            entries.remove(content_key);
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("kino-progress-{}", uuid::Uuid::new_v4()))
+            .join("progress.json")
+    }
+
+    #[test]
+    fn test_resume_position() {
+        let progress = |position, duration| WatchProgress {
+            content_key: "movie".to_string(),
+            position,
+            duration,
+            updated_at: Utc::now(),
+        };
+        assert_eq!(progress(600.0, Some(3600.0)).resume_position(0.95), Some(600.0));
+        assert_eq!(progress(3500.0, Some(3600.0)).resume_position(0.95), None);
+        assert_eq!(progress(0.0, Some(3600.0)).resume_position(0.95), None);
+        assert_eq!(progress(120.0, None).resume_position(0.95), Some(120.0));
+        assert!(progress(3600.0, Some(3600.0)).is_finished(0.95));
+    }
+
+    #[tokio::test]
+    async fn test_json_file_store() {
+        let path = temp_file();
+        let store = JsonFileProgressStore::new(&path);
+        assert_eq!(store.load("movie").await.unwrap(), None);
+
+        let saved_at = Utc::now();
+        store.save("movie", 42.5, Some(100.0), saved_at).await.unwrap();
+        store.save("episode", 10.0, None, saved_at).await.unwrap();
+
+        // A new store reads what the first one wrote
+        let reopened = JsonFileProgressStore::new(&path);
+        let progress = reopened.load("movie").await.unwrap().unwrap();
+        assert_eq!((progress.position, progress.duration, progress.updated_at), (42.5, Some(100.0), saved_at));
+
+        reopened.clear("movie").await.unwrap();
+        assert_eq!(JsonFileProgressStore::new(&path).load("movie").await.unwrap(), None);
+        assert!(JsonFileProgressStore::new(&path).load("episode").await.unwrap().is_some());
+
+        // Corrupt files start out empty
+        tokio::fs::write(&path, b"not json").await.unwrap();
+        let corrupt = JsonFileProgressStore::new(&path);
+        assert_eq!(corrupt.load("episode").await.unwrap(), None);
+        corrupt.save("movie", 1.0, None, saved_at).await.unwrap();
+        assert!(JsonFileProgressStore::new(&path).load("movie").await.unwrap().is_some());
+
+        tokio::fs::remove_dir_all(path.parent().unwrap()).await.unwrap();
+    }
+}
@@ -5,21 +5,37 @@
 //! - Segment fetching and buffering
 //! - ABR selection
 //! - State machine transitions
+//! - Trick play (I-frame renditions at high playback rates)
+//! - Audio track selection
+//! - Live rendition ladder changes
+//! - Content steering (CDN pathway failover)
+//! - ABR-aware segment prefetching
 //! - Analytics events
+//! - Session timeline recording
 
 use crate::{
-    abr::{AbrContext, AbrEngine},
-    analytics::{AnalyticsEmitter, AnalyticsEvent},
+    abr::{AbrContext, AbrEngine, DeviceCapabilities},
+    analytics::{AnalyticsEmitter, AnalyticsEvent, QualityChangeReason, SessionRecorder, TimelineSample},
+    steering::{PathwaySelector, PathwaySwitch, SteeringClient},
     buffer::{BufferConfig, BufferManager},
+    captions::merge_cues,
+    cea::{is_transport_stream, CaptionExtractor},
+    decrypt::SegmentDecryptor,
+    integrity::{IntegrityFailure, SegmentVerifier},
+    drm::DrmConfig,
     Error,
-    manifest::{create_parser, Manifest},
+    http::{self, HttpFetcher},
+    manifest::{create_parser_with, Manifest, ManifestType},
+    prefetch::{PredictedSwitch, PrefetchRequest, PrefetchStats},
+    progress::WatchProgressStore,
     types::*,
     Result,
 };
-use reqwest::Client;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Once};
 use std::time::{Duration, Instant};
 use tokio::sync::{RwLock, watch};
+use tokio::task::JoinHandle;
 use tracing::{debug, info, instrument, warn};
 use url::Url;
 
@@ -37,12 +53,38 @@ pub struct PlayerSession {
     buffer: Arc<BufferManager>,
     /// ABR engine
     abr: Arc<RwLock<AbrEngine>>,
-    /// HTTP client
-    client: Client,
+    /// Fetches manifests, segments and steering manifests
+    fetcher: Arc<dyn HttpFetcher>,
+    /// AES-128 segment decryptor
+    decryptor: SegmentDecryptor,
+    /// Downloaded segment verification
+    verifier: SegmentVerifier,
+    /// Decoder for captions embedded in the video
+    captions: Mutex<CaptionExtractor>,
+    /// Text tracks, including ones synthesized for embedded captions
+    text_tracks: Arc<RwLock<Vec<TextTrack>>>,
+    /// Cues decoded so far, by text track ID
+    text_cues: Arc<RwLock<HashMap<String, Vec<TextCue>>>>,
     /// Current manifest
     manifest: Arc<RwLock<Option<Manifest>>>,
+    /// URL the manifest was loaded from, for refreshes
+    manifest_url: Arc<RwLock<Option<Url>>>,
     /// Current rendition
     current_rendition: Arc<RwLock<Option<Rendition>>>,
+    /// Rendition to return to while an I-frame rendition is in use
+    main_rendition: Arc<RwLock<Option<Rendition>>>,
+    /// Selected audio track (None for muxed audio)
+    audio_track: Arc<RwLock<Option<AudioTrack>>>,
+    /// CDN pathway selection, if the manifest declares pathways
+    pathways: Arc<RwLock<Option<Arc<PathwaySelector>>>>,
+    /// Steering manifest polling task
+    steering_task: Mutex<Option<JoinHandle<()>>>,
+    /// Playback rate (1.0 = normal)
+    playback_rate: Arc<RwLock<f64>>,
+    /// Size the video is displayed at, if known
+    screen_size: Arc<RwLock<Option<Resolution>>>,
+    /// What the device can decode
+    device: Arc<RwLock<DeviceCapabilities>>,
     /// Playback position
     position: Arc<RwLock<f64>>,
     /// Content duration (if known)
@@ -51,10 +93,40 @@ pub struct PlayerSession {
     metrics: Arc<RwLock<QualityMetrics>>,
     /// Analytics emitter
     analytics: Option<Arc<AnalyticsEmitter>>,
+    /// Session timeline recorder, if enabled
+    recorder: Option<SessionRecorder>,
+    /// Registers the recorder as an analytics sink on first load
+    recorder_registered: Once,
+    /// Metric sampling task of the recorder
+    recording_task: Mutex<Option<JoinHandle<()>>>,
+    /// Where watch progress is saved
+    progress_store: Option<Arc<dyn WatchProgressStore>>,
+    /// Key the loaded content's watch progress is saved under
+    content_key: Arc<RwLock<Option<String>>>,
+    /// When watch progress was last saved
+    progress_saved_at: Mutex<Option<Instant>>,
     /// Session start time
     start_time: Instant,
 }
 
+/// Rendition ladder differences found on a manifest refresh
+#[derive(Debug, Clone, Default)]
+pub struct LadderChange {
+    /// Renditions new in the refreshed manifest
+    pub added: Vec<Rendition>,
+    /// Renditions no longer in the refreshed manifest
+    pub removed: Vec<Rendition>,
+    /// Rendition switched to because the playing one was removed
+    pub switched_to: Option<Rendition>,
+}
+
+impl LadderChange {
+    /// Whether the ladder is unchanged
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
 impl PlayerSession {
     /// Create a new player session
     pub fn new(config: PlayerConfig) -> Self {
@@ -65,41 +137,124 @@ impl PlayerSession {
             max_buffer_time: config.max_buffer_time,
             rebuffer_threshold: config.rebuffer_threshold,
             prefetch_enabled: config.prefetch_enabled,
+            integrity: config.segment_integrity.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            disk_cache: config.disk_cache.clone(),
             ..Default::default()
         };
 
-        let analytics = if config.analytics_enabled {
-            Some(Arc::new(AnalyticsEmitter::new()))
+        let fetcher = http::default_fetcher(Duration::from_millis(config.request_timeout_ms));
+
+        let id = SessionId::new();
+        let analytics = if config.analytics_enabled || config.record_session {
+            Some(Arc::new(AnalyticsEmitter::for_session(id)))
         } else {
             None
         };
+        let recorder = config.record_session.then(|| SessionRecorder::new(id, config.recorder.clone()));
 
         Self {
-            id: SessionId::new(),
+            id,
             config: config.clone(),
             state: Arc::new(RwLock::new(PlayerState::Idle)),
             state_tx,
             buffer: Arc::new(BufferManager::new(buffer_config)),
-            abr: Arc::new(RwLock::new(AbrEngine::new(config.abr_algorithm))),
-            client: Client::builder()
-                .timeout(Duration::from_millis(config.request_timeout_ms))
-                .build()
-                .expect("Failed to create HTTP client"),
+            abr: Arc::new(RwLock::new(
+                AbrEngine::new(config.abr_algorithm)
+                    .with_fast_start(config.fast_start_segments)
+                    .with_initial_estimate(config.initial_bandwidth_estimate),
+            )),
+            fetcher: fetcher.clone(),
+            decryptor: SegmentDecryptor::new(fetcher.clone()),
+            verifier: SegmentVerifier::new(fetcher, config.segment_integrity.clone()),
+            captions: Mutex::new(CaptionExtractor::new()),
+            text_tracks: Arc::new(RwLock::new(Vec::new())),
+            text_cues: Arc::new(RwLock::new(HashMap::new())),
             manifest: Arc::new(RwLock::new(None)),
+            manifest_url: Arc::new(RwLock::new(None)),
             current_rendition: Arc::new(RwLock::new(None)),
+            main_rendition: Arc::new(RwLock::new(None)),
+            audio_track: Arc::new(RwLock::new(None)),
+            pathways: Arc::new(RwLock::new(None)),
+            steering_task: Mutex::new(None),
+            playback_rate: Arc::new(RwLock::new(1.0)),
+            screen_size: Arc::new(RwLock::new(None)),
+            device: Arc::new(RwLock::new(DeviceCapabilities::default())),
             position: Arc::new(RwLock::new(0.0)),
             duration: Arc::new(RwLock::new(None)),
             metrics: Arc::new(RwLock::new(QualityMetrics::default())),
             analytics,
+            recorder,
+            recorder_registered: Once::new(),
+            recording_task: Mutex::new(None),
+            progress_store: None,
+            content_key: Arc::new(RwLock::new(None)),
+            progress_saved_at: Mutex::new(None),
             start_time: Instant::now(),
         }
     }
 
+    /// Fetch everything through `fetcher` instead of the platform default
+    ///
+    /// Call before [`set_drm_config`](Self::set_drm_config), which it resets.
+    pub fn with_fetcher(mut self, fetcher: Arc<dyn HttpFetcher>) -> Self {
+        self.decryptor = SegmentDecryptor::new(fetcher.clone());
+        self.verifier = SegmentVerifier::new(fetcher.clone(), self.config.segment_integrity.clone());
+        self.fetcher = fetcher;
+        self
+    }
+
+    /// Save watch progress to `store`; see [`crate::progress`]
+    pub fn with_progress_store(mut self, store: Arc<dyn WatchProgressStore>) -> Self {
+        self.progress_store = Some(store);
+        self
+    }
+
+    /// Key the next loaded content's watch progress is saved under
+    ///
+    /// Set it before [`load`](Self::load); [`stop`](Self::stop) clears it.
+    pub async fn set_content_key(&self, content_key: Option<String>) {
+        *self.content_key.write().await = content_key;
+    }
+
+    /// Saved position to offer resuming `content_key` from, or `None` if
+    /// there is none or the content was finished
+    pub async fn resume_position(&self, content_key: &str) -> Result<Option<f64>> {
+        let Some(store) = &self.progress_store else {
+            return Ok(None);
+        };
+        let progress = store.load(content_key).await?;
+        Ok(progress.and_then(|p| p.resume_position(self.config.resume_finished_threshold)))
+    }
+
+    /// Apply DRM configuration, e.g. headers for authenticated key servers
+    pub fn set_drm_config(&self, drm: &DrmConfig) {
+        self.decryptor.set_key_headers(drm.license_headers.clone());
+    }
+
     /// Get session ID
     pub fn id(&self) -> SessionId {
         self.id
     }
 
+    /// Fetcher used for manifests, segments and sidecar tracks
+    pub fn fetcher(&self) -> &Arc<dyn HttpFetcher> {
+        &self.fetcher
+    }
+
+    /// Analytics emitter, if analytics or session recording are enabled;
+    /// register sinks on it to deliver this session's events
+    pub fn analytics(&self) -> Option<&Arc<AnalyticsEmitter>> {
+        self.analytics.as_ref()
+    }
+
+    /// Session timeline recorder, if [`PlayerConfig::record_session`] is set
+    ///
+    /// Recording starts with the first [`load`](Self::load).
+    pub fn recorder(&self) -> Option<&SessionRecorder> {
+        self.recorder.as_ref()
+    }
+
     /// Get current state
     pub async fn state(&self) -> PlayerState {
         *self.state.read().await
@@ -113,13 +268,7 @@ impl PlayerSession {
     /// Transition to new state
     async fn set_state(&self, new_state: PlayerState) -> Result<()> {
         let current = *self.state.read().await;
-
-        if !current.can_transition_to(new_state) {
-            return Err(Error::InvalidStateTransition {
-                from: current.to_string(),
-                to: new_state.to_string(),
-            });
-        }
+        let new_state = current.transition(new_state)?;
 
         *self.state.write().await = new_state;
         let _ = self.state_tx.send(new_state);
@@ -135,6 +284,57 @@ impl PlayerSession {
 
         info!(from = %current, to = %new_state, "State transition");
 
+        if matches!(new_state, PlayerState::Paused | PlayerState::Ended) {
+            self.save_progress().await;
+        }
+
+        Ok(())
+    }
+
+    /// Save the position reached in the loaded content, if it has a key
+    /// and the session a progress store
+    ///
+    /// Failures are logged rather than interrupting playback.
+    async fn save_progress(&self) {
+        let Some(store) = &self.progress_store else {
+            return;
+        };
+        let Some(content_key) = self.content_key.read().await.clone() else {
+            return;
+        };
+        // Nothing has played before the content is loaded
+        if matches!(self.state().await, PlayerState::Idle | PlayerState::Loading) {
+            return;
+        }
+        let position = *self.position.read().await;
+        let duration = *self.duration.read().await;
+
+        *self.progress_saved_at.lock().unwrap() = Some(Instant::now());
+        match store.save(&content_key, position, duration, chrono::Utc::now()).await {
+            Ok(()) => debug!(content_key, position, "Watch progress saved"),
+            Err(e) => warn!(content_key, error = %e, "Failed to save watch progress"),
+        }
+    }
+
+    /// Seek to the saved position of the content being loaded, when
+    /// resuming is enabled and the content wasn't finished
+    async fn resume_saved_progress(&self, is_live: bool) -> Result<()> {
+        *self.progress_saved_at.lock().unwrap() = Some(Instant::now());
+        if !self.config.resume_playback || is_live {
+            return Ok(());
+        }
+        let Some(content_key) = self.content_key.read().await.clone() else {
+            return Ok(());
+        };
+
+        match self.resume_position(&content_key).await {
+            Ok(Some(position)) => {
+                info!(content_key, position, "Resuming from saved progress");
+                self.seek(position).await?;
+            }
+            Ok(None) => {}
+            Err(e) => warn!(content_key, error = %e, "Failed to load watch progress"),
+        }
         Ok(())
     }
 
@@ -143,11 +343,20 @@ impl PlayerSession {
     pub async fn load(&self, url: &Url) -> Result<()> {
         info!(url = %url, session_id = %self.id, "Loading content");
 
+        self.start_recording();
         self.set_state(PlayerState::Loading).await?;
 
-        // Parse manifest
-        let parser = create_parser(url);
-        let manifest = parser.parse(url).await?;
+        // Parse manifest, timing the download for the fast start
+        let parser = create_parser_with(url, self.fetcher.clone());
+        let started = Instant::now();
+        let manifest = match parser.parse(url).await {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                self.emit_error(&e).await;
+                return Err(e);
+            }
+        };
+        self.abr.write().await.record_playlist_download(manifest.fetched_bytes, started.elapsed());
 
         info!(
             renditions = manifest.renditions.len(),
@@ -157,18 +366,36 @@ impl PlayerSession {
 
         // Store manifest
         *self.manifest.write().await = Some(manifest.clone());
+        *self.manifest_url.write().await = Some(url.clone());
+
+        self.start_content_steering(&manifest).await;
 
         // Set duration if VOD
         if let Some(duration) = manifest.duration {
             *self.duration.write().await = Some(duration.as_secs_f64());
         }
 
+        // Select initial audio track, which limits the video renditions to
+        // those in its group
+        let audio_track = manifest.media_tracks().default_audio_track().cloned();
+        if let Some(ref track) = audio_track {
+            info!(track = %track.id, language = %track.language, "Initial audio track selected");
+        }
+        let renditions = compatible_renditions(&manifest.renditions, audio_track.as_ref());
+        *self.audio_track.write().await = audio_track;
+
         // Select initial rendition
         let context = self.create_abr_context().await;
-        let mut abr = self.abr.write().await;
-        if let Some(rendition) = abr.select_rendition(&manifest.renditions, &context) {
+        let decision = self.abr.write().await.decide(&renditions, &context)
+            .map(|d| (d.rendition.clone(), d.reason));
+        if let Some((rendition, reason)) = decision {
             *self.current_rendition.write().await = Some(rendition.clone());
-            info!(rendition = %rendition.id, bandwidth = rendition.bandwidth, "Initial rendition selected");
+            info!(rendition = %rendition.id, bandwidth = rendition.bandwidth, ?reason, "Initial rendition selected");
+            let reason = match reason {
+                QualityChangeReason::Abr => QualityChangeReason::Initial,
+                reason => reason,
+            };
+            self.emit_rendition_change(None, &rendition, reason).await;
         }
 
         // Emit load event
@@ -182,6 +409,8 @@ impl PlayerSession {
         // Transition to buffering
         self.set_state(PlayerState::Buffering).await?;
 
+        self.resume_saved_progress(manifest.is_live).await?;
+
         Ok(())
     }
 
@@ -198,7 +427,12 @@ impl PlayerSession {
                 }
             }
             PlayerState::Paused => {
-                self.set_state(PlayerState::Playing).await?;
+                // A buffer that drained while paused must refill first
+                if self.buffer.can_start_playback().await {
+                    self.set_state(PlayerState::Playing).await?;
+                } else {
+                    self.set_state(PlayerState::Buffering).await?;
+                }
             }
             PlayerState::Ended => {
                 // Restart from beginning
@@ -248,10 +482,12 @@ impl PlayerSession {
             position.max(0.0)
         };
 
-        info!(from = *self.position.read().await, to = clamped, "Seeking");
+        // Read outside the macro: awaiting inside it makes the future !Send
+        let from = *self.position.read().await;
+        info!(from, to = clamped, "Seeking");
 
         // Update state
-        let was_playing = self.state().await == PlayerState::Playing;
+        let resume_state = self.state().await;
         self.set_state(PlayerState::Seeking).await?;
 
         // Check if position is buffered
@@ -259,6 +495,7 @@ impl PlayerSession {
 
         // Update position
         *self.position.write().await = clamped;
+        self.captions.lock().unwrap().reset();
 
         // Emit seek event
         if let Some(ref analytics) = self.analytics {
@@ -268,10 +505,10 @@ impl PlayerSession {
             }).await;
         }
 
-        if is_buffered && was_playing {
-            self.set_state(PlayerState::Playing).await?;
-        } else {
-            self.set_state(PlayerState::Buffering).await?;
+        // A buffered seek resumes where it left off, so a paused seek stays paused
+        match resume_state {
+            PlayerState::Playing | PlayerState::Paused if is_buffered => self.set_state(resume_state).await?,
+            _ => self.set_state(PlayerState::Buffering).await?,
         }
 
         Ok(())
@@ -282,17 +519,40 @@ impl PlayerSession {
     pub async fn stop(&self) -> Result<()> {
         info!("Stopping playback");
 
+        if self.state().await != PlayerState::Idle {
+            self.save_progress().await;
+        }
+        *self.content_key.write().await = None;
+
         self.buffer.clear().await;
+        let prefetch = self.buffer.prefetch_stats().await;
         *self.position.write().await = 0.0;
         *self.manifest.write().await = None;
         *self.current_rendition.write().await = None;
+        *self.main_rendition.write().await = None;
+        *self.audio_track.write().await = None;
+        *self.playback_rate.write().await = 1.0;
+        *self.captions.lock().unwrap() = CaptionExtractor::new();
+        self.text_tracks.write().await.clear();
+        self.text_cues.write().await.clear();
+        self.stop_content_steering();
+        self.stop_recording();
+        *self.pathways.write().await = None;
 
         // Force state to Idle
         *self.state.write().await = PlayerState::Idle;
         let _ = self.state_tx.send(PlayerState::Idle);
 
-        // Emit end event
+        // Emit prefetch summary and end event
         if let Some(ref analytics) = self.analytics {
+            analytics.emit(AnalyticsEvent::Prefetch {
+                issued: prefetch.issued,
+                hits: prefetch.hits,
+                misses: prefetch.misses,
+                wasted: prefetch.wasted,
+                cancelled: prefetch.cancelled,
+                hit_ratio: prefetch.hit_ratio(),
+            }).await;
             analytics.emit(AnalyticsEvent::End {
                 position: *self.position.read().await,
                 watch_time: self.start_time.elapsed().as_secs_f64(),
@@ -302,6 +562,417 @@ impl PlayerSession {
         Ok(())
     }
 
+    /// Change the playback rate, switching to trick play when needed
+    ///
+    /// Above [`PlayerConfig::trick_play_threshold`] segments are sourced from
+    /// the best I-frame rendition the bandwidth allows at that rate, if the
+    /// manifest has any. Dropping back to or below the threshold restores
+    /// the main rendition and snaps the position to the start of the main
+    /// segment containing it, so normal playback resumes on a boundary.
+    #[instrument(skip(self))]
+    pub async fn set_trick_mode(&self, rate: f64) -> Result<()> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(Error::InvalidConfig(format!("Invalid playback rate: {}", rate)));
+        }
+
+        let from_rate = std::mem::replace(&mut *self.playback_rate.write().await, rate);
+        let wants_iframes = rate > self.config.trick_play_threshold;
+        let in_iframes = self.main_rendition.read().await.is_some();
+
+        if wants_iframes && !in_iframes {
+            self.enter_iframe_rendition(rate).await;
+        } else if !wants_iframes && in_iframes {
+            self.leave_iframe_rendition().await?;
+        }
+
+        info!(from = from_rate, to = rate, "Playback rate changed");
+
+        if let Some(ref analytics) = self.analytics {
+            analytics.emit(AnalyticsEvent::TrickMode {
+                from_rate,
+                to_rate: rate,
+                iframe_only: self.is_trick_mode().await,
+                position: *self.position.read().await,
+            }).await;
+        }
+
+        Ok(())
+    }
+
+    /// Alternate audio tracks declared by the manifest
+    pub async fn audio_tracks(&self) -> Vec<AudioTrack> {
+        self.manifest.read().await.as_ref()
+            .map(|m| m.audio_tracks.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the selected audio track
+    pub async fn current_audio_track(&self) -> Option<AudioTrack> {
+        self.audio_track.read().await.clone()
+    }
+
+    /// Switch to another audio track
+    ///
+    /// If the current video rendition does not belong to the track's audio
+    /// group, ABR picks a replacement among the compatible renditions. The
+    /// buffer is flushed and refilled from the current position either way,
+    /// since buffered segments carry the previous track's audio.
+    #[instrument(skip(self))]
+    pub async fn set_audio_track(&self, id: &str) -> Result<()> {
+        let (track, renditions) = {
+            let manifest = self.manifest.read().await;
+            let track = manifest.iter()
+                .flat_map(|m| &m.audio_tracks)
+                .find(|t| t.id == id)
+                .cloned()
+                .ok_or_else(|| Error::TrackNotFound { id: id.to_string() })?;
+            let renditions = manifest.as_ref()
+                .map(|m| compatible_renditions(&m.renditions, Some(&track)))
+                .unwrap_or_default();
+            (track, renditions)
+        };
+
+        let previous = self.audio_track.read().await.clone();
+        if previous.as_ref().is_some_and(|p| p.id == track.id) {
+            return Ok(());
+        }
+
+        // While in trick play the main rendition is the one to keep compatible
+        let in_iframes = self.main_rendition.read().await.is_some();
+        let video = if in_iframes {
+            self.main_rendition.read().await.clone()
+        } else {
+            self.current_rendition.read().await.clone()
+        };
+
+        if !video.as_ref().is_some_and(|v| track.is_compatible_with(v)) {
+            let context = self.create_abr_context().await;
+            let selected = self.abr.write().await.select_rendition(&renditions, &context).cloned();
+            if let Some(rendition) = selected {
+                info!(rendition = %rendition.id, group = ?rendition.audio_group, "Switched video rendition for audio group");
+                if in_iframes {
+                    *self.main_rendition.write().await = Some(rendition);
+                } else {
+                    *self.current_rendition.write().await = Some(rendition.clone());
+                    self.emit_rendition_change(video.as_ref(), &rendition, QualityChangeReason::Manual).await;
+                }
+            }
+        }
+
+        let position = *self.position.read().await;
+        self.buffer.clear().await;
+        self.buffer.seek(position).await?;
+
+        info!(track = %track.id, language = %track.language, "Audio track changed");
+
+        if let Some(ref analytics) = self.analytics {
+            analytics.emit(AnalyticsEvent::AudioTrackChange {
+                from: previous.map(|p| p.id),
+                to: track.id.clone(),
+                position,
+            }).await;
+        }
+
+        *self.audio_track.write().await = Some(track);
+
+        Ok(())
+    }
+
+    /// Pin playback to a rendition, or return to automatic selection with `None`
+    ///
+    /// The rendition is switched to immediately and held by the ABR engine
+    /// until cleared, except that a buffer below the rebuffer threshold may
+    /// still force a temporary downgrade. During trick play the pin applies
+    /// to the main rendition restored afterwards.
+    #[instrument(skip(self))]
+    pub async fn set_quality(&self, rendition_id: Option<&str>) -> Result<()> {
+        let Some(id) = rendition_id else {
+            self.abr.write().await.set_override(None);
+            info!("Automatic quality selection restored");
+            return Ok(());
+        };
+
+        let rendition = self.manifest.read().await.iter()
+            .flat_map(|m| &m.renditions)
+            .find(|r| r.id == id)
+            .cloned()
+            .ok_or_else(|| Error::TrackNotFound { id: id.to_string() })?;
+        self.abr.write().await.set_override(Some(rendition.id.clone()));
+
+        if self.is_trick_mode().await {
+            *self.main_rendition.write().await = Some(rendition);
+            return Ok(());
+        }
+
+        let previous = self.current_rendition.read().await.clone();
+        if previous.as_ref().is_some_and(|p| p.id == rendition.id) {
+            return Ok(());
+        }
+        info!(rendition = %rendition.id, bandwidth = rendition.bandwidth, "Quality pinned");
+        *self.current_rendition.write().await = Some(rendition.clone());
+        self.emit_rendition_change(previous.as_ref(), &rendition, QualityChangeReason::Manual).await;
+
+        Ok(())
+    }
+
+    /// Set the network segments are downloaded over, e.g. from the Network
+    /// Information API
+    pub async fn set_connection_type(&self, connection_type: Option<ConnectionType>) {
+        self.abr.write().await.set_connection_type(connection_type);
+    }
+
+    /// Set what the device can decode; renditions it cannot play are
+    /// skipped from the next ABR decision
+    pub async fn set_device_capabilities(&self, device: DeviceCapabilities) {
+        *self.device.write().await = device;
+    }
+
+    /// Set the size the video is displayed at, e.g. on a window resize or
+    /// entering fullscreen; `None` lifts the limit
+    ///
+    /// Renditions larger than the screen are skipped from the next ABR
+    /// decision, leaving the current one at once if it no longer fits.
+    pub async fn set_screen_size(&self, size: Option<Resolution>) {
+        *self.screen_size.write().await = size;
+    }
+
+    /// Bandwidth history for [`PlayerSession::restore_bandwidth_history`]
+    /// in a later session; see [`crate::abr`] for what it contains
+    pub async fn export_bandwidth_history(&self) -> Result<String> {
+        self.abr.read().await.export_history()
+    }
+
+    /// Seed ABR with history exported by an earlier session, returning how
+    /// many samples were kept
+    ///
+    /// Call after [`PlayerSession::set_connection_type`] and before
+    /// loading, so the first rendition benefits from it.
+    pub async fn restore_bandwidth_history(&self, blob: &str, max_age: Duration) -> Result<usize> {
+        self.abr.write().await.restore_history(blob, max_age)
+    }
+
+    /// Run the ABR decision for the next segment
+    ///
+    /// Switches the current rendition when the decision differs from it,
+    /// emitting a quality change whose reason separates manual, automatic
+    /// and buffer emergency switches. Renditions are left alone during
+    /// trick play. Crossing into a DASH period with a different rendition
+    /// set shows up as an automatic switch. Frames reported dropped since
+    /// the last decision may cap the resolution, with a
+    /// [`QualityChangeReason::DecoderOverload`] quality change.
+    pub async fn select_next_rendition(&self) -> Option<Rendition> {
+        if self.is_trick_mode().await {
+            return self.current_rendition().await;
+        }
+
+        let renditions = self.upcoming_renditions().await;
+        let (decoded, dropped) = {
+            let metrics = self.metrics.read().await;
+            (metrics.decoded_frames, metrics.dropped_frames)
+        };
+        self.abr.write().await.record_frames(decoded, dropped);
+        let context = self.create_abr_context().await;
+        let decision = self.abr.write().await.decide(&renditions, &context)
+            .map(|d| (d.rendition.clone(), d.reason));
+        let Some((rendition, reason)) = decision else {
+            return self.current_rendition().await;
+        };
+
+        let previous = self.current_rendition.read().await.clone();
+        if previous.as_ref().is_none_or(|p| p.id != rendition.id) {
+            debug!(rendition = %rendition.id, ?reason, "Rendition switched");
+            *self.current_rendition.write().await = Some(rendition.clone());
+            self.emit_rendition_change(previous.as_ref(), &rendition, reason).await;
+        }
+        Some(rendition)
+    }
+
+    /// Fetch the manifest again and apply any change to the rendition ladder
+    ///
+    /// Call on each master playlist refresh of a live stream; see
+    /// [`PlayerSession::apply_ladder_change`].
+    #[instrument(skip(self))]
+    pub async fn refresh_manifest(&self) -> Result<LadderChange> {
+        let url = self.manifest_url.read().await.clone()
+            .ok_or_else(|| Error::Internal("Manifest refreshed before load".to_string()))?;
+        let manifest = match create_parser_with(&url, self.fetcher.clone()).parse(&url).await {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                self.emit_error(&e).await;
+                return Err(e);
+            }
+        };
+        Ok(self.apply_ladder_change(manifest).await)
+    }
+
+    /// Replace the manifest with a refreshed one, following rendition
+    /// ladder changes
+    ///
+    /// Renditions are matched by variant playlist URI for HLS, whose IDs
+    /// are positional, and by ID for DASH. If the rendition being played
+    /// was dropped, playback switches to the closest remaining one by
+    /// bandwidth, preferring lower, with a [`QualityChangeReason::LadderChange`]
+    /// quality change. The buffer is left alone, so segments already
+    /// downloaded from the dropped rendition play out. Added renditions are
+    /// considered from the next ABR decision.
+    pub async fn apply_ladder_change(&self, manifest: Manifest) -> LadderChange {
+        let previous = self.manifest.write().await.replace(manifest.clone());
+        let old = previous.map(|m| m.renditions).unwrap_or_default();
+        let same = |a: &Rendition, b: &Rendition| same_variant(manifest.manifest_type, a, b);
+        let mut change = LadderChange {
+            added: manifest.renditions.iter().filter(|r| !old.iter().any(|o| same(o, r))).cloned().collect(),
+            removed: old.iter().filter(|o| !manifest.renditions.iter().any(|r| same(o, r))).cloned().collect(),
+            switched_to: None,
+        };
+        if change.is_empty() {
+            return change;
+        }
+        info!(
+            added = change.added.len(),
+            removed = change.removed.len(),
+            renditions = manifest.renditions.len(),
+            "Rendition ladder changed"
+        );
+
+        // During trick play the main rendition is the one to follow
+        let in_trick_mode = self.is_trick_mode().await;
+        let active = if in_trick_mode { &self.main_rendition } else { &self.current_rendition };
+        let Some(playing) = active.read().await.clone() else {
+            return change;
+        };
+
+        let renditions = self.upcoming_renditions().await;
+        let replacement = match renditions.iter().find(|r| same(&playing, r)) {
+            // IDs may have moved, so pick up the refreshed copy
+            Some(kept) => kept.clone(),
+            None => match closest_rendition(&renditions, playing.bandwidth) {
+                Some(closest) => {
+                    warn!(from = %playing.id, to = %closest.id, "Playing rendition dropped from the ladder");
+                    change.switched_to = Some(closest.clone());
+                    closest.clone()
+                }
+                None => {
+                    warn!(rendition = %playing.id, "Rendition ladder is empty, keeping the current rendition");
+                    return change;
+                }
+            },
+        };
+
+        *active.write().await = Some(replacement.clone());
+        self.abr.write().await.rebase_selection(&renditions, &replacement.id);
+        if change.switched_to.is_some() && !in_trick_mode {
+            self.emit_rendition_change(Some(&playing), &replacement, QualityChangeReason::LadderChange).await;
+        }
+        change
+    }
+
+    /// Renditions for the next segment to download: those of the period at
+    /// the end of the buffer, limited to the audio track's group
+    async fn upcoming_renditions(&self) -> Vec<Rendition> {
+        let audio = self.audio_track.read().await.clone();
+        let buffered_to = *self.position.read().await + self.buffer.buffer_level().await;
+        self.manifest.read().await.as_ref()
+            .map(|m| compatible_renditions(m.renditions_at(buffered_to), audio.as_ref()))
+            .unwrap_or_default()
+    }
+
+    /// Pathway segments are currently downloaded from
+    pub async fn active_pathway(&self) -> Option<String> {
+        self.pathways.read().await.as_ref().and_then(|p| p.active())
+    }
+
+    /// Set up pathway selection and poll the steering server, if any
+    async fn start_content_steering(&self, manifest: &Manifest) {
+        self.stop_content_steering();
+
+        let Some(steering) = &manifest.content_steering else {
+            *self.pathways.write().await = None;
+            return;
+        };
+
+        let selector = Arc::new(PathwaySelector::new(steering, self.config.pathway_failure_threshold));
+        info!(pathway = ?selector.active(), pathways = steering.pathways.len(), "Content steering enabled");
+        *self.pathways.write().await = Some(selector.clone());
+
+        let Some(server_uri) = steering.server_uri.clone() else {
+            return;
+        };
+        let mut client = SteeringClient::with_fetcher(self.fetcher.clone(), server_uri, manifest.manifest_type);
+        let abr = self.abr.clone();
+        let analytics = self.analytics.clone();
+
+        let task = tokio::spawn(async move {
+            loop {
+                let throughput = abr.read().await.bandwidth_estimate();
+                match client.poll(&selector, Some(throughput)).await {
+                    Ok(Some(switch)) => emit_pathway_switch(analytics.as_deref(), switch).await,
+                    Ok(None) => {}
+                    Err(e) => warn!(error = %e, "Steering manifest reload failed"),
+                }
+            }
+        });
+        *self.steering_task.lock().unwrap() = Some(task);
+    }
+
+    /// Stop polling the steering server
+    fn stop_content_steering(&self) {
+        if let Some(task) = self.steering_task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+
+    /// Start sampling metrics into the recorder, registering it for
+    /// events the first time
+    fn start_recording(&self) {
+        let (Some(recorder), Some(analytics)) = (&self.recorder, &self.analytics) else {
+            return;
+        };
+        self.recorder_registered.call_once(|| analytics.add_sink(recorder.clone()));
+        self.stop_recording();
+
+        let recorder = recorder.clone();
+        let state = self.state.clone();
+        let position = self.position.clone();
+        let buffer = self.buffer.clone();
+        let rendition = self.current_rendition.clone();
+        let abr = self.abr.clone();
+        let period = Duration::from_secs_f64(recorder.config().sample_interval_secs.max(0.01));
+
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                let sample = TimelineSample {
+                    time: recorder.elapsed(),
+                    position: *position.read().await,
+                    buffer_level: buffer.buffer_level().await,
+                    bitrate: rendition.read().await.as_ref().map_or(0, |r| r.bandwidth),
+                    bandwidth_estimate: abr.read().await.bandwidth_estimate(),
+                    state: *state.read().await,
+                };
+                recorder.record_sample(sample);
+            }
+        });
+        *self.recording_task.lock().unwrap() = Some(task);
+    }
+
+    /// Stop sampling metrics; the recorded timeline is kept
+    fn stop_recording(&self) {
+        if let Some(task) = self.recording_task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+
+    /// Get the playback rate
+    pub async fn playback_rate(&self) -> f64 {
+        *self.playback_rate.read().await
+    }
+
+    /// Whether segments are currently sourced from an I-frame rendition
+    pub async fn is_trick_mode(&self) -> bool {
+        self.main_rendition.read().await.is_some()
+    }
+
     /// Get current position
     pub async fn position(&self) -> f64 {
         *self.position.read().await
@@ -333,48 +1004,202 @@ impl PlayerSession {
     }
 
     /// Create ABR context from current state
+    ///
+    /// The buffer level is converted to wall-clock seconds at the current
+    /// playback rate: at 2x the same media lasts half as long, while slow
+    /// motion stretches it, so the target buffer is reached with less media.
     async fn create_abr_context(&self) -> AbrContext {
         let manifest = self.manifest.read().await;
         let is_live = manifest.as_ref().map(|m| m.is_live).unwrap_or(false);
+        let playback_rate = *self.playback_rate.read().await;
+        let screen = *self.screen_size.read().await;
 
         AbrContext {
-            buffer_level: self.buffer.buffer_level().await,
+            buffer_level: self.buffer.buffer_level().await / playback_rate,
             target_buffer: self.config.max_buffer_time,
-            playback_rate: 1.0,
+            playback_rate,
             is_live,
-            screen_width: None,
+            screen_width: screen.map(|s| s.width),
+            screen_height: screen.map(|s| s.height),
             max_bitrate: self.config.max_bitrate,
+            rebuffer_threshold: self.config.rebuffer_threshold,
             network: NetworkInfo {
                 bandwidth_estimate: self.abr.read().await.bandwidth_estimate(),
+                connection_type: self.abr.read().await.connection_type(),
                 ..Default::default()
             },
+            device: self.device.read().await.clone(),
         }
     }
 
-    /// Fetch next segment
+    /// Switch segment sourcing to an I-frame rendition for `rate`
+    async fn enter_iframe_rendition(&self, rate: f64) {
+        let iframes = match self.manifest.read().await.as_ref() {
+            Some(manifest) => manifest.iframe_renditions.clone(),
+            None => return,
+        };
+
+        let bandwidth = self.abr.read().await.bandwidth_estimate();
+        let Some(iframe) = select_iframe_rendition(&iframes, bandwidth, rate).cloned() else {
+            debug!(rate, "No I-frame renditions, trick play stays on the main rendition");
+            return;
+        };
+
+        let main = self.current_rendition.write().await.replace(iframe.clone());
+        info!(rendition = %iframe.id, bandwidth = iframe.bandwidth, "Switched to I-frame rendition");
+        self.emit_rendition_change(main.as_ref(), &iframe, QualityChangeReason::TrickPlay).await;
+        *self.main_rendition.write().await = main;
+    }
+
+    /// Return to the main rendition at the segment boundary containing the
+    /// current position
+    async fn leave_iframe_rendition(&self) -> Result<()> {
+        let Some(main) = self.main_rendition.write().await.take() else {
+            return Ok(());
+        };
+
+        let position = *self.position.read().await;
+        let boundary = self.main_segment_boundary(&main, position).await;
+
+        // I-frame segments cannot be played at normal speed
+        self.buffer.clear().await;
+        self.buffer.seek(boundary).await?;
+        *self.position.write().await = boundary;
+
+        let iframe = self.current_rendition.write().await.replace(main.clone());
+        info!(rendition = %main.id, from = position, to = boundary, "Resynchronized to main rendition");
+        self.emit_rendition_change(iframe.as_ref(), &main, QualityChangeReason::TrickPlay).await;
+
+        Ok(())
+    }
+
+    /// Start time of the main rendition segment containing `position`,
+    /// falling back to the target duration grid if its playlist is unavailable
+    async fn main_segment_boundary(&self, rendition: &Rendition, position: f64) -> f64 {
+        match create_parser_with(&rendition.uri, self.fetcher.clone()).parse_variant(&rendition.uri).await {
+            Ok(segments) if !segments.is_empty() => segment_boundary(&segments, position),
+            result => {
+                if let Err(e) = result {
+                    warn!(error = %e, "Failed to load main rendition playlist for resync");
+                }
+                let target = self.manifest.read().await.as_ref()
+                    .map(|m| m.target_duration.as_secs_f64())
+                    .filter(|&t| t > 0.0)
+                    .unwrap_or(6.0);
+                (position / target).floor() * target
+            }
+        }
+    }
+
+    /// Download a segment and check it against the integrity policy
+    async fn download_verified(&self, segment: &Segment, url: &Url) -> Result<bytes::Bytes> {
+        // Downloaded content is read from disk
+        if let Some(path) = crate::download::local_path(url) {
+            let data = bytes::Bytes::from(tokio::fs::read(path).await?);
+            self.verifier.verify(segment, url, None, &data).await?;
+            return Ok(data);
+        }
+
+        let response = self
+            .fetcher
+            .get(url, &HashMap::new(), segment.byte_range)
+            .await?
+            .error_for_status()?;
+        let content_length = response.content_length();
+        let data = response.bytes().await?;
+
+        self.verifier.verify(segment, url, content_length, &data).await?;
+        Ok(data)
+    }
+
+    /// Record a segment that failed verification
+    async fn emit_integrity_failure(&self, segment: &Segment, url: &Url, failure: &IntegrityFailure, refetch: bool) {
+        if let Some(ref analytics) = self.analytics {
+            analytics.emit(AnalyticsEvent::SegmentIntegrityFailure {
+                segment: segment.number,
+                url: url.to_string(),
+                kind: failure.kind().to_string(),
+                detail: failure.to_string(),
+                refetch,
+            }).await;
+        }
+    }
+
+    /// Emit an error event at the current position
+    async fn emit_error(&self, err: &Error) {
+        if let Some(ref analytics) = self.analytics {
+            let position = *self.position.read().await;
+            analytics.emit(AnalyticsEvent::error(err, position)).await;
+        }
+    }
+
+    /// Emit a quality change
+    async fn emit_rendition_change(&self, from: Option<&Rendition>, to: &Rendition, reason: QualityChangeReason) {
+        if let Some(ref analytics) = self.analytics {
+            analytics.emit(AnalyticsEvent::QualityChange {
+                from_bitrate: from.map(|r| r.bandwidth).unwrap_or(0),
+                to_bitrate: to.bandwidth,
+                from_resolution: from.and_then(|r| r.resolution),
+                to_resolution: to.resolution,
+                reason,
+            }).await;
+        }
+    }
+
+    /// Fetch a segment, decrypting it when the playlist marks it AES-128
+    ///
+    /// With content steering the URL is moved onto the active pathway and
+    /// the outcome reported back, so repeated failures trigger a failover.
+    /// Segments failing the integrity policy are fetched again up to
+    /// `max_refetches` times. Segments spilled to the buffer's disk cache
+    /// are read back from it without a download.
     #[instrument(skip(self))]
     pub async fn fetch_segment(&self, segment: &Segment) -> Result<bytes::Bytes> {
-        let start = Instant::now();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(data) = self.buffer.cached_segment(segment).await {
+            debug!(segment = segment.number, bytes = data.len(), "Segment restored from disk cache");
+            return Ok(data);
+        }
+
+        let selector = self.pathways.read().await.clone();
+        let url = match &selector {
+            Some(selector) => selector.rewrite(&segment.uri),
+            None => segment.uri.clone(),
+        };
+
+        let mut refetches = 0;
+        let (data, duration) = loop {
+            let start = Instant::now();
+            let result = self.download_verified(segment, &url).await;
+
+            if let Some(selector) = &selector {
+                match &result {
+                    Ok(_) => selector.record_success(&url),
+                    Err(_) => {
+                        if let Some(switch) = selector.record_failure(&url) {
+                            emit_pathway_switch(self.analytics.as_deref(), switch).await;
+                        }
+                    }
+                }
+            }
+
+            let err = match result {
+                Ok(data) => break (data, start.elapsed()),
+                Err(err) => err,
+            };
+            if let Error::SegmentIntegrity { failure, .. } = &err {
+                let refetch = refetches < self.config.segment_integrity.max_refetches;
+                self.emit_integrity_failure(segment, &url, failure, refetch).await;
+                if refetch {
+                    refetches += 1;
+                    warn!(segment = segment.number, error = %err, refetches, "Refetching corrupt segment");
+                    continue;
+                }
+            }
+            self.emit_error(&err).await;
+            return Err(err);
+        };
 
-        let response = self
-            .client
-            .get(segment.uri.clone())
-            .send()
-            .await
-            .map_err(|e| Error::SegmentFetch {
-                url: segment.uri.to_string(),
-                source: e,
-            })?;
-
-        let data = response
-            .bytes()
-            .await
-            .map_err(|e| Error::SegmentFetch {
-                url: segment.uri.to_string(),
-                source: e,
-            })?;
-
-        let duration = start.elapsed();
         let bytes = data.len();
 
         // Record bandwidth measurement
@@ -387,14 +1212,129 @@ impl PlayerSession {
             "Segment fetched"
         );
 
+        // Encrypted segments are decrypted before they reach the buffer
+        let data = self.decryptor.decrypt(segment, data).await?;
+        self.extract_captions(segment, &data).await;
         Ok(data)
     }
 
+    /// Decode CEA-608/708 captions embedded in a TS segment's video
+    ///
+    /// The first time a caption format shows up, a text track is
+    /// advertised for it. I-frame segments carry no captions and are
+    /// skipped.
+    async fn extract_captions(&self, segment: &Segment, data: &[u8]) {
+        if !is_transport_stream(data) || self.is_trick_mode().await {
+            return;
+        }
+        let start = *self.position.read().await;
+        let rendition_url = self.current_rendition().await.map(|r| r.uri).unwrap_or_else(|| segment.uri.clone());
+
+        let (cues, tracks, cea608_id, cea708_id) = {
+            let mut extractor = self.captions.lock().unwrap();
+            extractor.set_discontinuity_sequence(segment.discontinuity_sequence);
+            let cues = extractor.extract(data, start);
+            (cues, extractor.tracks(&rendition_url), extractor.cea608_track_id(), extractor.cea708_track_id())
+        };
+
+        {
+            let mut text_tracks = self.text_tracks.write().await;
+            for track in tracks {
+                if !text_tracks.iter().any(|t| t.id == track.id) {
+                    info!(track = %track.id, format = ?track.format, "Embedded captions detected");
+                    text_tracks.push(track);
+                }
+            }
+        }
+
+        let mut text_cues = self.text_cues.write().await;
+        for (id, updates) in [(cea608_id.to_string(), cues.cea608), (cea708_id, cues.cea708)] {
+            if !updates.is_empty() {
+                merge_cues(text_cues.entry(id).or_default(), updates);
+            }
+        }
+    }
+
+    /// Text tracks, including tracks synthesized for embedded captions
+    pub async fn text_tracks(&self) -> Vec<TextTrack> {
+        self.text_tracks.read().await.clone()
+    }
+
+    /// Cues of a text track decoded so far
+    pub async fn text_cues(&self, track_id: &str) -> Vec<TextCue> {
+        self.text_cues.read().await.get(track_id).cloned().unwrap_or_default()
+    }
+
+    /// Plan segment prefetches
+    ///
+    /// `playlists` maps rendition IDs to their segment lists. If the ABR
+    /// engine is heading towards a switch and the target's playlist is
+    /// known, segments from the predicted switch point on are planned at
+    /// the target rendition.
+    pub async fn schedule_prefetch(&self, playlists: &HashMap<String, Vec<Segment>>) -> Vec<PrefetchRequest> {
+        let Some(rendition) = self.current_rendition().await else {
+            return Vec::new();
+        };
+        let Some(segments) = playlists.get(&rendition.id) else {
+            return Vec::new();
+        };
+
+        // ABR does not switch renditions during trick play
+        let renditions = if self.is_trick_mode().await {
+            Vec::new()
+        } else {
+            self.upcoming_renditions().await
+        };
+
+        let context = self.create_abr_context().await;
+        let prediction = self.abr.read().await.predict_switch(&renditions, &context);
+        let predicted = prediction
+            .filter(|(target, _)| target.id != rendition.id)
+            .and_then(|(target, after_segments)| {
+                playlists.get(&target.id).map(|segments| PredictedSwitch {
+                    rendition: target,
+                    segments,
+                    after_segments,
+                })
+            });
+
+        self.buffer.schedule_prefetch(&rendition, segments, predicted, context.playback_rate).await
+    }
+
+    /// Fetch a planned prefetch into the buffer
+    ///
+    /// Returns false if a seek cancelled the request before its data
+    /// could be buffered.
+    pub async fn prefetch(&self, request: &PrefetchRequest) -> Result<bool> {
+        if request.is_cancelled() {
+            return Ok(false);
+        }
+        match self.fetch_segment(&request.segment).await {
+            Ok(data) => self.buffer.add_prefetched_segment(request, data).await,
+            Err(e) => {
+                self.buffer.prefetch_failed(request).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Prefetch hit/miss statistics
+    pub async fn prefetch_stats(&self) -> PrefetchStats {
+        self.buffer.prefetch_stats().await
+    }
+
     /// Update playback position (called by renderer)
     pub async fn update_position(&self, position: f64) {
         *self.position.write().await = position;
         self.buffer.update_position(position).await;
 
+        // Save watch progress periodically
+        let interval = Duration::from_millis(self.config.progress_save_interval_ms);
+        let save_due = self.progress_saved_at.lock().unwrap().is_none_or(|saved| saved.elapsed() >= interval);
+        if save_due {
+            self.save_progress().await;
+        }
+
         // Check for end of content
         if let Some(duration) = *self.duration.read().await {
             if position >= duration - 0.5 {
@@ -431,6 +1371,79 @@ impl PlayerSession {
     }
 }
 
+impl Drop for PlayerSession {
+    fn drop(&mut self) {
+        self.stop_content_steering();
+        self.stop_recording();
+    }
+}
+
+/// Pick the highest I-frame rendition whose bitrate, scaled by the playback
+/// rate, fits in 80% of the bandwidth estimate; the lowest if none fits
+fn select_iframe_rendition(renditions: &[Rendition], bandwidth: u64, rate: f64) -> Option<&Rendition> {
+    let budget = bandwidth as f64 * 0.8;
+    renditions
+        .iter()
+        .filter(|r| r.bandwidth as f64 * rate <= budget)
+        .max_by_key(|r| r.bandwidth)
+        .or_else(|| renditions.iter().min_by_key(|r| r.bandwidth))
+}
+
+/// Whether `a` and `b` are the same variant across manifest refreshes
+fn same_variant(manifest_type: ManifestType, a: &Rendition, b: &Rendition) -> bool {
+    match manifest_type {
+        ManifestType::Hls => a.uri == b.uri,
+        ManifestType::Dash => a.id == b.id,
+    }
+}
+
+/// Highest rendition at or below `bandwidth`, or the lowest one above it
+fn closest_rendition(renditions: &[Rendition], bandwidth: u64) -> Option<&Rendition> {
+    renditions
+        .iter()
+        .filter(|r| r.bandwidth <= bandwidth)
+        .max_by_key(|r| r.bandwidth)
+        .or_else(|| renditions.iter().min_by_key(|r| r.bandwidth))
+}
+
+/// Record a pathway switch
+async fn emit_pathway_switch(analytics: Option<&AnalyticsEmitter>, switch: PathwaySwitch) {
+    if let Some(analytics) = analytics {
+        analytics.emit(AnalyticsEvent::PathwaySwitch {
+            from: switch.from,
+            to: switch.to,
+            reason: switch.reason,
+        }).await;
+    }
+}
+
+/// Video renditions playable with `audio`, or all of them if none match
+fn compatible_renditions(renditions: &[Rendition], audio: Option<&AudioTrack>) -> Vec<Rendition> {
+    let compatible: Vec<Rendition> = match audio {
+        Some(track) => renditions.iter().filter(|r| track.is_compatible_with(r)).cloned().collect(),
+        None => renditions.to_vec(),
+    };
+    if compatible.is_empty() {
+        renditions.to_vec()
+    } else {
+        compatible
+    }
+}
+
+/// Start time of the segment containing `position`, counting from the
+/// first segment; positions past the end map to the last segment
+fn segment_boundary(segments: &[Segment], position: f64) -> f64 {
+    let mut start = 0.0;
+    for (i, segment) in segments.iter().enumerate() {
+        let end = start + segment.duration.as_secs_f64();
+        if position < end || i == segments.len() - 1 {
+            return start;
+        }
+        start = end;
+    }
+    0.0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -459,4 +1472,749 @@ mod tests {
         // Invalid: Buffering -> Ended (need to go through Playing first)
         // Actually Buffering -> Playing -> Ended is the path
     }
+
+    #[tokio::test]
+    async fn test_paused_resume_and_seek_rebuffer() {
+        let session = PlayerSession::new(PlayerConfig::default());
+        for state in [PlayerState::Loading, PlayerState::Buffering, PlayerState::Playing, PlayerState::Paused] {
+            session.set_state(state).await.unwrap();
+        }
+
+        // Nothing is buffered, so resuming rebuffers instead of playing
+        session.play().await.unwrap();
+        assert_eq!(session.state().await, PlayerState::Buffering);
+
+        // Seeking while buffering, then pausing and seeking again
+        session.seek(10.0).await.unwrap();
+        assert_eq!(session.state().await, PlayerState::Buffering);
+        session.set_state(PlayerState::Paused).await.unwrap();
+        session.seek(20.0).await.unwrap();
+        assert_eq!(session.state().await, PlayerState::Buffering);
+        assert_eq!(session.position().await, 20.0);
+    }
+
+    #[tokio::test]
+    async fn test_load_failure_emits_error_code() {
+        let session = PlayerSession::new(PlayerConfig::default());
+        let url = Url::parse("http://127.0.0.1:9/master.m3u8").unwrap();
+
+        let err = session.load(&url).await.unwrap_err();
+        assert!(matches!(err, Error::Network { .. }));
+        assert_eq!(err.url(), Some(&url));
+
+        let events = session.analytics.as_ref().unwrap().get_events().await;
+        let codes: Vec<_> = events
+            .iter()
+            .filter_map(|e| match &e.event {
+                AnalyticsEvent::Error { code, fatal, .. } => Some((code.as_str(), *fatal)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(codes, vec![(err.error_code(), !err.is_retryable())]);
+    }
+
+    #[tokio::test]
+    async fn test_watch_progress_resume() {
+        use crate::progress::JsonFileProgressStore;
+
+        let dir = std::env::temp_dir().join(format!("kino-resume-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut playlist = String::from("#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:10\n");
+        for i in 0..10 {
+            playlist.push_str(&format!("#EXTINF:10.0,\nseg{}.ts\n", i));
+        }
+        playlist.push_str("#EXT-X-ENDLIST\n");
+        std::fs::write(dir.join("media.m3u8"), playlist).unwrap();
+        let url = Url::from_file_path(dir.join("media.m3u8")).unwrap();
+
+        let store = Arc::new(JsonFileProgressStore::new(dir.join("progress.json")));
+        store.save("movie", 42.0, Some(100.0), chrono::Utc::now()).await.unwrap();
+        let config = PlayerConfig {
+            resume_playback: true,
+            progress_save_interval_ms: 0,
+            analytics_enabled: false,
+            ..Default::default()
+        };
+        let session = PlayerSession::new(config).with_progress_store(store.clone());
+        assert_eq!(session.resume_position("movie").await.unwrap(), Some(42.0));
+
+        session.set_content_key(Some("movie".to_string())).await;
+        session.load(&url).await.unwrap();
+        assert_eq!(session.position().await, 42.0);
+
+        // Saved as playback moves on, and when it stops
+        session.update_position(55.0).await;
+        assert_eq!(store.load("movie").await.unwrap().unwrap().position, 55.0);
+        *session.position.write().await = 61.0;
+        session.stop().await.unwrap();
+        assert_eq!(store.load("movie").await.unwrap().unwrap().position, 61.0);
+
+        // Without a content key nothing is resumed or saved
+        session.load(&url).await.unwrap();
+        assert_eq!(session.position().await, 0.0);
+        session.update_position(5.0).await;
+        assert_eq!(store.load("movie").await.unwrap().unwrap().position, 61.0);
+        session.stop().await.unwrap();
+
+        // Content watched nearly to the end starts over
+        store.save("movie", 97.0, Some(100.0), chrono::Utc::now()).await.unwrap();
+        session.set_content_key(Some("movie".to_string())).await;
+        session.load(&url).await.unwrap();
+        assert_eq!(session.position().await, 0.0);
+        assert_eq!(session.resume_position("movie").await.unwrap(), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_session_recording() {
+        let config = PlayerConfig {
+            analytics_enabled: false,
+            record_session: true,
+            recorder: crate::analytics::RecorderConfig {
+                sample_interval_secs: 0.05,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let session = PlayerSession::new(config);
+        let url = Url::parse("http://127.0.0.1:9/master.m3u8").unwrap();
+        let _ = session.load(&url).await;
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        session.analytics().unwrap().flush().await;
+
+        let timeline = session.recorder().unwrap().timeline();
+        assert_eq!(timeline.session_id, session.id());
+        assert!(timeline.samples.len() >= 2);
+        assert!(timeline.samples.windows(2).all(|w| w[0].time < w[1].time));
+        assert_eq!(timeline.samples.last().unwrap().state, PlayerState::Loading);
+
+        let events: Vec<_> = timeline.events.iter()
+            .map(|e| match &e.event {
+                AnalyticsEvent::StateChange { to, .. } => to.to_string(),
+                AnalyticsEvent::Error { code, .. } => code.clone(),
+                other => format!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(events.first().map(String::as_str), Some("loading"));
+        assert!(events.iter().any(|e| e.starts_with("NETWORK_")));
+
+        // Sampling stops with the session, the timeline stays
+        session.stop().await.unwrap();
+        let samples = session.recorder().unwrap().timeline().samples.len();
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(session.recorder().unwrap().timeline().samples.len(), samples);
+    }
+
+    fn rendition(id: &str, bandwidth: u64, uri: &str) -> Rendition {
+        Rendition {
+            id: id.to_string(),
+            bandwidth,
+            resolution: None,
+            frame_rate: None,
+            video_codec: None,
+            audio_codec: None,
+            uri: Url::parse(uri).unwrap(),
+            hdr: None,
+            language: None,
+            name: None,
+            audio_group: None,
+        }
+    }
+
+    #[test]
+    fn test_segment_boundary() {
+        let segments: Vec<Segment> = [6.0, 6.0, 4.5]
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| Segment {
+                number: i as u64,
+                uri: Url::parse(&format!("https://example.com/seg{}.ts", i)).unwrap(),
+                duration: Duration::from_secs_f64(d),
+                byte_range: None,
+                encryption: None,
+                discontinuity_sequence: 0,
+                program_date_time: None,
+            })
+            .collect();
+
+        assert_eq!(segment_boundary(&segments, 0.0), 0.0);
+        assert_eq!(segment_boundary(&segments, 5.9), 0.0);
+        assert_eq!(segment_boundary(&segments, 6.0), 6.0);
+        assert_eq!(segment_boundary(&segments, 13.0), 12.0);
+        assert_eq!(segment_boundary(&segments, 99.0), 12.0);
+    }
+
+    #[test]
+    fn test_select_iframe_rendition() {
+        let iframes = vec![
+            rendition("iframe_0", 86_000, "https://example.com/360p_iframes.m3u8"),
+            rendition("iframe_1", 280_000, "https://example.com/720p_iframes.m3u8"),
+        ];
+
+        // 8x of the 720p I-frame track needs 2.24 Mbps
+        assert_eq!(select_iframe_rendition(&iframes, 3_000_000, 8.0).unwrap().id, "iframe_1");
+        assert_eq!(select_iframe_rendition(&iframes, 1_000_000, 8.0).unwrap().id, "iframe_0");
+        assert_eq!(select_iframe_rendition(&iframes, 0, 8.0).unwrap().id, "iframe_0");
+        assert!(select_iframe_rendition(&[], 1_000_000, 8.0).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_trick_mode_switching() {
+        let session = PlayerSession::new(PlayerConfig::default());
+        // Unreachable playlists force the target duration fallback on resync
+        let main = rendition("variant_0", 2_800_000, "http://127.0.0.1:9/720p.m3u8");
+        let iframe = rendition("iframe_0", 86_000, "http://127.0.0.1:9/720p_iframes.m3u8");
+        *session.manifest.write().await = Some(Manifest {
+            manifest_type: crate::manifest::ManifestType::Hls,
+            renditions: vec![main.clone()],
+            iframe_renditions: vec![iframe.clone()],
+            audio_tracks: Vec::new(),
+            text_tracks: Vec::new(),
+            drm_init_data: Vec::new(),
+            content_steering: None,
+            is_live: false,
+            duration: Some(Duration::from_secs(120)),
+            target_duration: Duration::from_secs(6),
+            base_url: Url::parse("http://127.0.0.1:9/master.m3u8").unwrap(),
+            minimum_update_period: None,
+            fetched_bytes: 0,
+            periods: Vec::new(),
+        });
+        *session.current_rendition.write().await = Some(main.clone());
+
+        assert!(session.set_trick_mode(0.0).await.is_err());
+
+        // Slow motion and mild speed-ups stay on the main rendition
+        session.set_trick_mode(0.5).await.unwrap();
+        session.set_trick_mode(2.0).await.unwrap();
+        assert!(!session.is_trick_mode().await);
+
+        session.set_trick_mode(8.0).await.unwrap();
+        assert!(session.is_trick_mode().await);
+        assert_eq!(session.current_rendition().await.unwrap().id, "iframe_0");
+
+        // Changing speed within trick play keeps the I-frame rendition
+        session.set_trick_mode(16.0).await.unwrap();
+        assert_eq!(session.current_rendition().await.unwrap().id, "iframe_0");
+
+        *session.position.write().await = 40.5;
+        session.set_trick_mode(1.0).await.unwrap();
+        assert!(!session.is_trick_mode().await);
+        assert_eq!(session.current_rendition().await.unwrap().id, "variant_0");
+        assert_eq!(session.position().await, 36.0);
+        assert_eq!(session.playback_rate().await, 1.0);
+
+        let events = session.analytics.as_ref().unwrap().get_events().await;
+        let trick_plays = events
+            .iter()
+            .filter(|e| matches!(e.event, AnalyticsEvent::QualityChange { reason: QualityChangeReason::TrickPlay, .. }))
+            .count();
+        assert_eq!(trick_plays, 2);
+    }
+
+    #[tokio::test]
+    async fn test_set_quality() {
+        let session = PlayerSession::new(PlayerConfig::default());
+        let low = rendition("variant_0", 800_000, "http://127.0.0.1:9/360p.m3u8");
+        let high = rendition("variant_1", 5_000_000, "http://127.0.0.1:9/1080p.m3u8");
+        *session.manifest.write().await = Some(Manifest {
+            manifest_type: crate::manifest::ManifestType::Hls,
+            renditions: vec![low.clone(), high],
+            iframe_renditions: Vec::new(),
+            audio_tracks: Vec::new(),
+            text_tracks: Vec::new(),
+            drm_init_data: Vec::new(),
+            content_steering: None,
+            is_live: false,
+            duration: Some(Duration::from_secs(120)),
+            target_duration: Duration::from_secs(6),
+            base_url: Url::parse("http://127.0.0.1:9/master.m3u8").unwrap(),
+            minimum_update_period: None,
+            fetched_bytes: 0,
+            periods: Vec::new(),
+        });
+        *session.current_rendition.write().await = Some(low);
+
+        assert!(matches!(
+            session.set_quality(Some("variant_9")).await,
+            Err(Error::TrackNotFound { .. })
+        ));
+
+        session.set_quality(Some("variant_1")).await.unwrap();
+        assert_eq!(session.current_rendition().await.unwrap().id, "variant_1");
+
+        // The buffer is empty, below the rebuffer threshold, so ABR may
+        // downgrade despite the pin
+        assert_eq!(session.select_next_rendition().await.unwrap().id, "variant_0");
+
+        session.set_quality(None).await.unwrap();
+        assert_eq!(session.abr.read().await.override_id(), None);
+
+        let events = session.analytics.as_ref().unwrap().get_events().await;
+        let reasons: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e.event {
+                AnalyticsEvent::QualityChange { reason, .. } => Some(reason),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reasons, vec![QualityChangeReason::Manual, QualityChangeReason::Buffer]);
+    }
+
+    #[tokio::test]
+    async fn test_device_feedback_limits_renditions() {
+        let session = PlayerSession::new(PlayerConfig::default());
+        let ladder = [
+            ("variant_0", 800_000, 360, VideoCodec::H264),
+            ("variant_1", 2_800_000, 720, VideoCodec::H264),
+            ("variant_2", 5_000_000, 1080, VideoCodec::H264),
+            ("variant_3", 15_000_000, 2160, VideoCodec::H265),
+        ];
+        let renditions = ladder.iter()
+            .map(|&(id, bandwidth, height, codec)| Rendition {
+                resolution: Some(Resolution::new(height * 16 / 9, height)),
+                video_codec: Some(codec),
+                ..rendition(id, bandwidth, &format!("http://127.0.0.1:9/{}p.m3u8", height))
+            })
+            .collect();
+        *session.manifest.write().await = Some(Manifest {
+            manifest_type: crate::manifest::ManifestType::Hls,
+            renditions,
+            iframe_renditions: Vec::new(),
+            audio_tracks: Vec::new(),
+            text_tracks: Vec::new(),
+            drm_init_data: Vec::new(),
+            content_steering: None,
+            is_live: false,
+            duration: Some(Duration::from_secs(120)),
+            target_duration: Duration::from_secs(6),
+            base_url: Url::parse("http://127.0.0.1:9/master.m3u8").unwrap(),
+            minimum_update_period: None,
+            fetched_bytes: 0,
+            periods: Vec::new(),
+        });
+        let mut abr = AbrEngine::new(AbrAlgorithmType::Throughput);
+        abr.record_measurement(10_000_000, Duration::from_secs(2));
+        *session.abr.write().await = abr;
+
+        // HEVC 4K is skipped on a device without HEVC
+        session.set_device_capabilities(DeviceCapabilities {
+            video_codecs: Some(vec![VideoCodec::H264]),
+            ..Default::default()
+        }).await;
+        assert_eq!(session.select_next_rendition().await.unwrap().id, "variant_2");
+
+        // A smaller window leaves 1080p at once
+        session.set_screen_size(Some(Resolution::new(1280, 720))).await;
+        assert_eq!(session.select_next_rendition().await.unwrap().id, "variant_1");
+        session.set_screen_size(None).await;
+
+        // Sustained dropped frames cap the resolution regardless of bandwidth
+        for _ in 0..3 {
+            session.select_next_rendition().await;
+        }
+        assert_eq!(session.current_rendition().await.unwrap().id, "variant_2");
+        for frame in 0..300 {
+            session.report_decoded_frame().await;
+            if frame % 4 == 0 {
+                session.report_dropped_frame().await;
+            }
+        }
+        assert_eq!(session.select_next_rendition().await.unwrap().id, "variant_1");
+
+        let events = session.analytics.as_ref().unwrap().get_events().await;
+        let reasons: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e.event {
+                AnalyticsEvent::QualityChange { reason, .. } => Some(reason),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reasons.last(), Some(&QualityChangeReason::DecoderOverload));
+    }
+
+    #[tokio::test]
+    async fn test_period_boundary_switches_rendition() {
+        let session = PlayerSession::new(PlayerConfig::default());
+        let main = rendition("main-1/video_720", 2_800_000, "http://127.0.0.1:9/content/");
+        let ad = rendition("ad-1/video_720", 1_800_000, "http://127.0.0.1:9/ads/");
+        let period = |id: &str, start: u64, rendition: &Rendition| crate::manifest::Period {
+            id: id.to_string(),
+            start: Duration::from_secs(start),
+            duration: Some(Duration::from_secs(60)),
+            discontinuity_sequence: 0,
+            renditions: vec![rendition.clone()],
+            segments: HashMap::new(),
+        };
+        *session.manifest.write().await = Some(Manifest {
+            manifest_type: crate::manifest::ManifestType::Dash,
+            renditions: vec![main.clone()],
+            iframe_renditions: Vec::new(),
+            audio_tracks: Vec::new(),
+            text_tracks: Vec::new(),
+            drm_init_data: Vec::new(),
+            content_steering: None,
+            is_live: false,
+            duration: Some(Duration::from_secs(120)),
+            target_duration: Duration::from_secs(4),
+            base_url: Url::parse("http://127.0.0.1:9/manifest.mpd").unwrap(),
+            minimum_update_period: None,
+            fetched_bytes: 0,
+            periods: vec![period("main-1", 0, &main), period("ad-1", 60, &ad)],
+        });
+        *session.current_rendition.write().await = Some(main);
+
+        assert_eq!(session.select_next_rendition().await.unwrap().id, "main-1/video_720");
+
+        *session.position.write().await = 65.0;
+        assert_eq!(session.select_next_rendition().await.unwrap().id, "ad-1/video_720");
+
+        let events = session.analytics.as_ref().unwrap().get_events().await;
+        let switches: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e.event {
+                AnalyticsEvent::QualityChange { from_bitrate, to_bitrate, .. } => Some((from_bitrate, to_bitrate)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(switches, vec![(2_800_000, 1_800_000)]);
+    }
+
+    fn audio_track(id: &str, language: &str, channels: u8, group: &str) -> AudioTrack {
+        AudioTrack {
+            id: id.to_string(),
+            language: language.to_string(),
+            label: id.to_string(),
+            codec: None,
+            channels: Some(channels),
+            bitrate: None,
+            is_default: false,
+            is_audio_description: false,
+            url: None,
+            group_id: Some(group.to_string()),
+            autoselect: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_audio_track() {
+        let session = PlayerSession::new(PlayerConfig::default());
+        let stereo = Rendition {
+            audio_group: Some("stereo".to_string()),
+            ..rendition("variant_0", 2_800_000, "http://127.0.0.1:9/720p_stereo.m3u8")
+        };
+        let surround = Rendition {
+            audio_group: Some("surround".to_string()),
+            ..rendition("variant_1", 3_200_000, "http://127.0.0.1:9/720p_surround.m3u8")
+        };
+        *session.manifest.write().await = Some(Manifest {
+            manifest_type: crate::manifest::ManifestType::Hls,
+            renditions: vec![stereo.clone(), surround],
+            iframe_renditions: Vec::new(),
+            audio_tracks: vec![
+                audio_track("audio_0", "en", 2, "stereo"),
+                audio_track("audio_1", "es", 2, "stereo"),
+                audio_track("audio_2", "en", 6, "surround"),
+            ],
+            text_tracks: Vec::new(),
+            drm_init_data: Vec::new(),
+            content_steering: None,
+            is_live: false,
+            duration: Some(Duration::from_secs(120)),
+            target_duration: Duration::from_secs(6),
+            base_url: Url::parse("http://127.0.0.1:9/master.m3u8").unwrap(),
+            minimum_update_period: None,
+            fetched_bytes: 0,
+            periods: Vec::new(),
+        });
+        *session.current_rendition.write().await = Some(stereo);
+        *session.position.write().await = 30.0;
+
+        assert!(matches!(
+            session.set_audio_track("audio_9").await,
+            Err(Error::TrackNotFound { .. })
+        ));
+
+        // Same group keeps the video rendition
+        session.set_audio_track("audio_1").await.unwrap();
+        assert_eq!(session.current_audio_track().await.unwrap().language, "es");
+        assert_eq!(session.current_rendition().await.unwrap().id, "variant_0");
+
+        // 5.1 lives in another group, so video follows
+        session.set_audio_track("audio_2").await.unwrap();
+        assert_eq!(session.current_audio_track().await.unwrap().channels, Some(6));
+        assert_eq!(session.current_rendition().await.unwrap().id, "variant_1");
+        assert_eq!(session.position().await, 30.0);
+
+        let events = session.analytics.as_ref().unwrap().get_events().await;
+        let switches: Vec<_> = events
+            .iter()
+            .filter_map(|e| match &e.event {
+                AnalyticsEvent::AudioTrackChange { from, to, .. } => Some((from.clone(), to.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(switches, [
+            (None, "audio_1".to_string()),
+            (Some("audio_1".to_string()), "audio_2".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_segment_pathway_failover() {
+        use crate::steering::{ContentSteering, Pathway};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // CDN-B serves every request; CDN-A refuses connections
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let cdn_b = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\nseg!")
+                    .await;
+            }
+        });
+
+        let config = PlayerConfig { pathway_failure_threshold: 1, ..Default::default() };
+        let session = PlayerSession::new(config);
+        let manifest = Manifest {
+            manifest_type: crate::manifest::ManifestType::Hls,
+            renditions: Vec::new(),
+            iframe_renditions: Vec::new(),
+            audio_tracks: Vec::new(),
+            text_tracks: Vec::new(),
+            drm_init_data: Vec::new(),
+            content_steering: Some(ContentSteering {
+                server_uri: None,
+                default_pathway: Some("CDN-A".to_string()),
+                pathways: vec![
+                    Pathway { id: "CDN-A".to_string(), base: Url::parse("http://127.0.0.1:9/").unwrap() },
+                    Pathway { id: "CDN-B".to_string(), base: cdn_b },
+                ],
+            }),
+            is_live: false,
+            duration: None,
+            target_duration: Duration::from_secs(6),
+            base_url: Url::parse("http://127.0.0.1:9/master.m3u8").unwrap(),
+            minimum_update_period: None,
+            fetched_bytes: 0,
+            periods: Vec::new(),
+        };
+        session.start_content_steering(&manifest).await;
+
+        let segment = Segment {
+            number: 0,
+            uri: Url::parse("http://127.0.0.1:9/vod/seg0.ts").unwrap(),
+            duration: Duration::from_secs(6),
+            byte_range: None,
+            encryption: None,
+            discontinuity_sequence: 0,
+            program_date_time: None,
+        };
+
+        assert!(session.fetch_segment(&segment).await.is_err());
+        assert_eq!(session.active_pathway().await.as_deref(), Some("CDN-B"));
+        assert_eq!(&session.fetch_segment(&segment).await.unwrap()[..], b"seg!");
+
+        let events = session.analytics.as_ref().unwrap().get_events().await;
+        assert!(events.iter().any(|e| matches!(
+            &e.event,
+            AnalyticsEvent::PathwaySwitch { to, reason: crate::analytics::PathwaySwitchReason::Failover, .. } if to == "CDN-B"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_live_ladder_change() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // The encoder drops 720p and adds 2160p after the first request,
+        // shifting the positional HLS IDs of the variants after it
+        let ladders = [
+            vec![(800_000, "360p"), (2_800_000, "720p"), (5_000_000, "1080p")],
+            vec![(800_000, "360p"), (5_000_000, "1080p"), (8_000_000, "2160p")],
+        ];
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/live/master.m3u8", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let served = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let ladder = &ladders[served.fetch_add(1, Ordering::SeqCst).min(1)];
+                let mut body = "#EXTM3U\n".to_string();
+                for (bandwidth, name) in ladder {
+                    body += &format!("#EXT-X-STREAM-INF:BANDWIDTH={}\n{}.m3u8\n", bandwidth, name);
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let session = PlayerSession::new(PlayerConfig::default());
+        session.load(&url).await.unwrap();
+        let playing = session.manifest.read().await.as_ref().unwrap().renditions[1].clone();
+        assert!(playing.uri.path().ends_with("720p.m3u8"));
+        *session.current_rendition.write().await = Some(playing);
+
+        let change = session.refresh_manifest().await.unwrap();
+        let paths = |renditions: &[Rendition]| -> Vec<String> {
+            renditions.iter().map(|r| r.uri.path().to_string()).collect()
+        };
+        assert_eq!(paths(&change.removed), ["/live/720p.m3u8"]);
+        assert_eq!(paths(&change.added), ["/live/2160p.m3u8"]);
+
+        // Closest remaining by bandwidth, preferring lower
+        let switched = change.switched_to.unwrap();
+        assert_eq!((switched.id.as_str(), switched.bandwidth), ("variant_0", 800_000));
+        assert_eq!(session.current_rendition().await.unwrap().id, "variant_0");
+
+        // The new rendition is offered to ABR from the next decision
+        let upcoming = session.upcoming_renditions().await;
+        assert!(upcoming.iter().any(|r| r.bandwidth == 8_000_000));
+
+        // An unchanged ladder leaves playback alone
+        assert!(session.refresh_manifest().await.unwrap().is_empty());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        let events = session.analytics.as_ref().unwrap().get_events().await;
+        let ladder_changes: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e.event {
+                AnalyticsEvent::QualityChange { from_bitrate, to_bitrate, reason: QualityChangeReason::LadderChange, .. } => {
+                    Some((from_bitrate, to_bitrate))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ladder_changes, [(2_800_000, 800_000)]);
+    }
+
+    #[test]
+    fn test_closest_rendition() {
+        let renditions = [
+            rendition("low", 800_000, "http://127.0.0.1:9/360p.m3u8"),
+            rendition("high", 5_000_000, "http://127.0.0.1:9/1080p.m3u8"),
+        ];
+        assert_eq!(closest_rendition(&renditions, 2_800_000).unwrap().id, "low");
+        assert_eq!(closest_rendition(&renditions, 5_000_000).unwrap().id, "high");
+        assert_eq!(closest_rendition(&renditions, 500_000).unwrap().id, "low");
+        assert!(closest_rendition(&[], 500_000).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_segment_is_refetched() {
+        use crate::integrity::{sha256_hex, IntegrityPolicy, SegmentIntegrity};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // The first download is corrupt; the sidecar has the good digest
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let downloads = Arc::new(AtomicUsize::new(0));
+        let served = downloads.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let body = if request.contains(".sha256 ") {
+                    sha256_hex(b"good segment")
+                } else if served.fetch_add(1, Ordering::SeqCst) == 0 {
+                    "bad segment!".to_string()
+                } else {
+                    "good segment".to_string()
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let config = PlayerConfig {
+            segment_integrity: SegmentIntegrity {
+                policy: IntegrityPolicy::Lenient,
+                checksum_url_template: Some("{name}.sha256".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let session = PlayerSession::new(config);
+        let segment = Segment {
+            number: 0,
+            uri: base.join("vod/seg0.ts").unwrap(),
+            duration: Duration::from_secs(6),
+            byte_range: None,
+            encryption: None,
+            discontinuity_sequence: 0,
+            program_date_time: None,
+        };
+
+        assert_eq!(&session.fetch_segment(&segment).await.unwrap()[..], b"good segment");
+        assert_eq!(downloads.load(Ordering::SeqCst), 2);
+
+        let events = session.analytics.as_ref().unwrap().get_events().await;
+        let failures: Vec<_> = events
+            .iter()
+            .filter_map(|e| match &e.event {
+                AnalyticsEvent::SegmentIntegrityFailure { kind, refetch, .. } => Some((kind.as_str(), *refetch)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(failures, vec![("checksum", true)]);
+    }
+
+    #[tokio::test]
+    async fn test_embedded_captions_advertise_track() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            let body = include_bytes!("../tests/fixtures/cea608_popon.ts");
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let header = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+                let _ = socket.write_all(header.as_bytes()).await;
+                let _ = socket.write_all(body).await;
+            }
+        });
+
+        let session = PlayerSession::new(PlayerConfig::default());
+        let segment = Segment {
+            number: 0,
+            uri: base.join("vod/seg0.ts").unwrap(),
+            duration: Duration::from_secs(6),
+            byte_range: None,
+            encryption: None,
+            discontinuity_sequence: 0,
+            program_date_time: None,
+        };
+        assert!(session.text_tracks().await.is_empty());
+
+        session.fetch_segment(&segment).await.unwrap();
+
+        let tracks = session.text_tracks().await;
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].id, "cc1");
+        assert_eq!(tracks[0].format, TextTrackFormat::Cea608);
+
+        let cues = session.text_cues("cc1").await;
+        let texts: Vec<_> = cues.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["HELLO\nWORLD", "¡HOLA!"]);
+        assert_eq!(crate::captions::cues_at_time(&cues, 1.5)[0].text, "HELLO\nWORLD");
+    }
 }
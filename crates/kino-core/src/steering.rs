@@ -0,0 +1,484 @@
+//! Content steering - multi-CDN pathway selection and failover
+//!
+//! Implements:
+//! - HLS Content Steering (EXT-X-CONTENT-STEERING, PATHWAY-ID)
+//! - DASH multiple BaseURLs (serviceLocation) and ContentSteering
+//! - Steering manifest polling (TTL, RELOAD-URI, PATHWAY-CLONES)
+//! - Pathway demotion after consecutive download failures
+//!
+//! The [`PathwaySelector`] is shared between the steering poller and the
+//! segment download layer. Downloads rewrite their URLs onto the active
+//! pathway and report the outcome; a pathway that keeps failing is demoted
+//! until the next steering manifest arrives.
+
+use crate::{
+    analytics::PathwaySwitchReason,
+    error::Error,
+    http::{self, HttpFetcher, ReqwestFetcher},
+    manifest::ManifestType,
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+use url::Url;
+
+/// Pathway ID used when an HLS master declares none
+pub const DEFAULT_PATHWAY: &str = ".";
+
+/// Reload interval when the steering manifest has no TTL
+const DEFAULT_TTL: u64 = 300;
+
+/// A CDN pathway, identified by HLS PATHWAY-ID or DASH serviceLocation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pathway {
+    pub id: String,
+    /// URL prefix that identifies this pathway and replaces others'
+    pub base: Url,
+}
+
+/// Content steering declared by a manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentSteering {
+    /// Steering manifest URI (None for plain DASH BaseURL failover)
+    pub server_uri: Option<Url>,
+    /// Pathway to start on
+    pub default_pathway: Option<String>,
+    /// Known pathways, in manifest order
+    pub pathways: Vec<Pathway>,
+}
+
+/// Steering manifest served by the steering server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING-KEBAB-CASE")]
+pub struct SteeringManifest {
+    pub version: u32,
+    /// Seconds until the next reload
+    #[serde(default = "default_ttl")]
+    pub ttl: u64,
+    /// Where to fetch the next steering manifest
+    #[serde(default)]
+    pub reload_uri: Option<String>,
+    /// Pathway IDs, most preferred first
+    pub pathway_priority: Vec<String>,
+    #[serde(default)]
+    pub pathway_clones: Vec<PathwayClone>,
+}
+
+fn default_ttl() -> u64 {
+    DEFAULT_TTL
+}
+
+/// New pathway derived from an existing one (PATHWAY-CLONES)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING-KEBAB-CASE")]
+pub struct PathwayClone {
+    pub base_id: String,
+    pub id: String,
+    pub uri_replacement: UriReplacement,
+}
+
+/// How a cloned pathway's URLs differ from its base
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING-KEBAB-CASE")]
+pub struct UriReplacement {
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub params: HashMap<String, String>,
+}
+
+/// A change of active pathway
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathwaySwitch {
+    pub from: Option<String>,
+    pub to: String,
+    pub reason: PathwaySwitchReason,
+}
+
+/// Fetches steering manifests, honoring TTL and RELOAD-URI
+pub struct SteeringClient {
+    fetcher: Arc<dyn HttpFetcher>,
+    uri: Url,
+    /// `_HLS` or `_DASH`, prefixed to the pathway/throughput query params
+    param_prefix: &'static str,
+    ttl: Duration,
+    next_reload: Instant,
+}
+
+impl SteeringClient {
+    /// Create a client for the steering server of a manifest
+    pub fn new(server_uri: Url, manifest_type: ManifestType) -> Self {
+        Self::with_fetcher(http::default_fetcher(Duration::from_secs(10)), server_uri, manifest_type)
+    }
+
+    pub fn with_client(client: reqwest::Client, server_uri: Url, manifest_type: ManifestType) -> Self {
+        Self::with_fetcher(Arc::new(ReqwestFetcher::with_client(client)), server_uri, manifest_type)
+    }
+
+    /// Create a client that fetches steering manifests with `fetcher`
+    pub fn with_fetcher(fetcher: Arc<dyn HttpFetcher>, server_uri: Url, manifest_type: ManifestType) -> Self {
+        Self {
+            fetcher,
+            uri: server_uri,
+            param_prefix: match manifest_type {
+                ManifestType::Hls => "_HLS",
+                ManifestType::Dash => "_DASH",
+            },
+            ttl: Duration::from_secs(DEFAULT_TTL),
+            next_reload: Instant::now(),
+        }
+    }
+
+    /// URI the next steering manifest is fetched from
+    pub fn reload_uri(&self) -> &Url {
+        &self.uri
+    }
+
+    /// TTL of the last steering manifest
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Fetch the steering manifest now
+    ///
+    /// The current pathway and throughput are reported to the server as
+    /// query parameters. The TTL and RELOAD-URI of the response schedule
+    /// the next fetch; on failure the previous TTL is kept.
+    pub async fn fetch(&mut self, pathway: Option<&str>, throughput: Option<u64>) -> Result<SteeringManifest> {
+        let mut url = self.uri.clone();
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(pathway) = pathway {
+                query.append_pair(&format!("{}_pathway", self.param_prefix), pathway);
+            }
+            if let Some(throughput) = throughput.filter(|&t| t > 0) {
+                query.append_pair(&format!("{}_throughput", self.param_prefix), &throughput.to_string());
+            }
+        }
+        self.next_reload = Instant::now() + self.ttl;
+
+        let body = self
+            .fetcher
+            .get(&url, &HashMap::new(), None)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        let manifest: SteeringManifest = serde_json::from_slice(&body)
+            .map_err(|e| Error::parse(format!("Invalid steering manifest: {}", e)))?;
+
+        if let Some(reload) = &manifest.reload_uri {
+            match self.uri.join(reload) {
+                Ok(uri) => self.uri = uri,
+                Err(e) => warn!(error = %e, reload = %reload, "Ignoring invalid RELOAD-URI"),
+            }
+        }
+        self.ttl = Duration::from_secs(manifest.ttl.max(1));
+        self.next_reload = Instant::now() + self.ttl;
+
+        debug!(priority = ?manifest.pathway_priority, ttl = manifest.ttl, "Steering manifest loaded");
+        Ok(manifest)
+    }
+
+    /// Wait until the next reload is due, then fetch and apply the steering
+    /// manifest to `selector`
+    pub async fn poll(&mut self, selector: &PathwaySelector, throughput: Option<u64>) -> Result<Option<PathwaySwitch>> {
+        tokio::time::sleep_until(self.next_reload).await;
+        let manifest = self.fetch(selector.active().as_deref(), throughput).await?;
+        Ok(selector.apply(&manifest))
+    }
+}
+
+/// Picks the pathway downloads go to and demotes failing ones
+pub struct PathwaySelector {
+    state: Mutex<SelectorState>,
+    failure_threshold: u32,
+}
+
+#[derive(Debug, Default)]
+struct SelectorState {
+    pathways: Vec<Pathway>,
+    /// Pathway IDs, most preferred first
+    priority: Vec<String>,
+    active: Option<String>,
+    /// Consecutive failures per pathway
+    failures: HashMap<String, u32>,
+    demoted: HashSet<String>,
+}
+
+impl SelectorState {
+    /// Pathway whose base prefixes `url`
+    fn pathway_of(&self, url: &Url) -> Option<&Pathway> {
+        self.pathways
+            .iter()
+            .filter(|p| url.as_str().starts_with(p.base.as_str()))
+            .max_by_key(|p| p.base.as_str().len())
+    }
+
+    /// First pathway by priority that is known and not demoted
+    fn best(&self) -> Option<String> {
+        self.priority
+            .iter()
+            .find(|id| !self.demoted.contains(*id) && self.pathways.iter().any(|p| &p.id == *id))
+            .cloned()
+    }
+
+    fn switch_to(&mut self, to: Option<String>, reason: PathwaySwitchReason) -> Option<PathwaySwitch> {
+        let to = to?;
+        if self.active.as_ref() == Some(&to) {
+            return None;
+        }
+        let from = self.active.replace(to.clone());
+        info!(from = ?from, to = %to, reason = ?reason, "Pathway switched");
+        Some(PathwaySwitch { from, to, reason })
+    }
+}
+
+impl PathwaySelector {
+    /// Create a selector starting on the default pathway, demoting a
+    /// pathway after `failure_threshold` consecutive failures
+    pub fn new(steering: &ContentSteering, failure_threshold: u32) -> Self {
+        let mut priority: Vec<String> = steering.pathways.iter().map(|p| p.id.clone()).collect();
+        if let Some(default) = &steering.default_pathway {
+            if let Some(pos) = priority.iter().position(|id| id == default) {
+                let default = priority.remove(pos);
+                priority.insert(0, default);
+            }
+        }
+
+        let mut state = SelectorState {
+            pathways: steering.pathways.clone(),
+            priority,
+            ..Default::default()
+        };
+        state.active = state.best();
+
+        Self {
+            state: Mutex::new(state),
+            failure_threshold: failure_threshold.max(1),
+        }
+    }
+
+    /// Currently active pathway
+    pub fn active(&self) -> Option<String> {
+        self.state.lock().unwrap().active.clone()
+    }
+
+    /// All known pathways, including clones
+    pub fn pathways(&self) -> Vec<Pathway> {
+        self.state.lock().unwrap().pathways.clone()
+    }
+
+    /// Move `url` onto the active pathway
+    ///
+    /// URLs that do not belong to any pathway are returned unchanged.
+    pub fn rewrite(&self, url: &Url) -> Url {
+        let state = self.state.lock().unwrap();
+        let active = state.active.as_ref().and_then(|id| state.pathways.iter().find(|p| &p.id == id));
+        let (Some(from), Some(to)) = (state.pathway_of(url), active) else {
+            return url.clone();
+        };
+        if from.id == to.id {
+            return url.clone();
+        }
+
+        let rest = &url.as_str()[from.base.as_str().len()..];
+        to.base.join(rest).unwrap_or_else(|_| url.clone())
+    }
+
+    /// Record a successful download from `url`
+    pub fn record_success(&self, url: &Url) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(id) = state.pathway_of(url).map(|p| p.id.clone()) {
+            state.failures.remove(&id);
+        }
+    }
+
+    /// Record a failed download from `url`, failing over to the next
+    /// pathway once its pathway reaches the failure threshold
+    pub fn record_failure(&self, url: &Url) -> Option<PathwaySwitch> {
+        let mut state = self.state.lock().unwrap();
+        let id = state.pathway_of(url)?.id.clone();
+
+        let failures = {
+            let count = state.failures.entry(id.clone()).or_insert(0);
+            *count += 1;
+            *count
+        };
+        if failures < self.failure_threshold || state.demoted.contains(&id) {
+            return None;
+        }
+
+        warn!(pathway = %id, failures, "Demoting pathway");
+        state.demoted.insert(id.clone());
+        if state.best().is_none() {
+            // Every pathway is failing; give the others another chance
+            state.demoted.retain(|d| *d == id);
+        }
+
+        let next = state.best();
+        state.switch_to(next, PathwaySwitchReason::Failover)
+    }
+
+    /// Apply a steering manifest
+    ///
+    /// Clones are added, the priority replaced and demotions lifted, since
+    /// the server's view is newer than the failures that caused them.
+    pub fn apply(&self, manifest: &SteeringManifest) -> Option<PathwaySwitch> {
+        let mut state = self.state.lock().unwrap();
+
+        for clone in &manifest.pathway_clones {
+            if state.pathways.iter().any(|p| p.id == clone.id) {
+                continue;
+            }
+            let Some(base) = state.pathways.iter().find(|p| p.id == clone.base_id) else {
+                warn!(base = %clone.base_id, clone = %clone.id, "Pathway clone of unknown pathway");
+                continue;
+            };
+            match clone_base(&base.base, &clone.uri_replacement) {
+                Some(base) => state.pathways.push(Pathway { id: clone.id.clone(), base }),
+                None => warn!(clone = %clone.id, "Invalid pathway clone URI replacement"),
+            }
+        }
+
+        if !manifest.pathway_priority.is_empty() {
+            state.priority = manifest.pathway_priority.clone();
+        }
+        state.demoted.clear();
+        state.failures.clear();
+
+        let next = state.best();
+        state.switch_to(next, PathwaySwitchReason::Steering)
+    }
+}
+
+/// Base URL of a cloned pathway
+fn clone_base(base: &Url, replacement: &UriReplacement) -> Option<Url> {
+    let mut url = base.clone();
+    if let Some(host) = &replacement.host {
+        url.set_host(Some(host)).ok()?;
+    }
+    if !replacement.params.is_empty() {
+        let mut params: Vec<_> = replacement.params.iter().collect();
+        params.sort();
+        url.query_pairs_mut().extend_pairs(params);
+    }
+    Some(url)
+}
+
+/// Origin (`scheme://host:port/`) of `url`, the default pathway base
+pub(crate) fn origin_base(url: &Url) -> Url {
+    let mut base = url.clone();
+    base.set_path("/");
+    base.set_query(None);
+    base.set_fragment(None);
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::ScriptedFetcher;
+
+    fn steering(server_uri: Option<Url>) -> ContentSteering {
+        ContentSteering {
+            server_uri,
+            default_pathway: Some("CDN-A".to_string()),
+            pathways: vec![
+                Pathway { id: "CDN-A".to_string(), base: Url::parse("https://a.example.com/").unwrap() },
+                Pathway { id: "CDN-B".to_string(), base: Url::parse("https://b.example.com/").unwrap() },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_rewrite_onto_active_pathway() {
+        let selector = PathwaySelector::new(&steering(None), 3);
+        assert_eq!(selector.active().as_deref(), Some("CDN-A"));
+
+        let on_b = Url::parse("https://b.example.com/vod/720p/seg1.ts").unwrap();
+        assert_eq!(selector.rewrite(&on_b).as_str(), "https://a.example.com/vod/720p/seg1.ts");
+
+        let elsewhere = Url::parse("https://other.example.com/seg1.ts").unwrap();
+        assert_eq!(selector.rewrite(&elsewhere), elsewhere);
+    }
+
+    #[test]
+    fn test_pathway_clone() {
+        let selector = PathwaySelector::new(&steering(None), 3);
+        let manifest: SteeringManifest = serde_json::from_str(r#"{
+            "VERSION": 1,
+            "PATHWAY-PRIORITY": ["CDN-C", "CDN-A"],
+            "PATHWAY-CLONES": [{
+                "BASE-ID": "CDN-A",
+                "ID": "CDN-C",
+                "URI-REPLACEMENT": { "HOST": "c.example.com" }
+            }]
+        }"#).unwrap();
+        assert_eq!(manifest.ttl, DEFAULT_TTL);
+
+        let switch = selector.apply(&manifest).unwrap();
+        assert_eq!(switch.to, "CDN-C");
+        assert_eq!(switch.reason, PathwaySwitchReason::Steering);
+
+        let url = Url::parse("https://a.example.com/vod/seg1.ts").unwrap();
+        assert_eq!(selector.rewrite(&url).as_str(), "https://c.example.com/vod/seg1.ts");
+    }
+
+    #[tokio::test]
+    async fn test_failover_and_recovery() {
+        let server = Url::parse("https://steering.example.com/steer").unwrap();
+        let fetcher = Arc::new(
+            ScriptedFetcher::new()
+                .respond(server.as_str(), 200, r#"{"VERSION":1,"TTL":1,"RELOAD-URI":"/steer?session=abc","PATHWAY-PRIORITY":["CDN-A","CDN-B"]}"#)
+                .respond(server.as_str(), 200, r#"{"VERSION":1,"TTL":1,"PATHWAY-PRIORITY":["CDN-A","CDN-B"]}"#),
+        );
+
+        let selector = PathwaySelector::new(&steering(Some(server.clone())), 2);
+        let mut client = SteeringClient::with_fetcher(fetcher.clone(), server, ManifestType::Hls);
+
+        // The first poll is immediate and confirms the default pathway
+        assert_eq!(client.poll(&selector, Some(5_000_000)).await.unwrap(), None);
+        assert_eq!(client.ttl(), Duration::from_secs(1));
+        assert!(client.reload_uri().as_str().ends_with("/steer?session=abc"));
+
+        // CDN-A starts failing; one failure is tolerated, two demote it
+        let segment = Url::parse("https://a.example.com/vod/seg7.ts").unwrap();
+        assert_eq!(selector.record_failure(&segment), None);
+        let failover = selector.record_failure(&segment).unwrap();
+        assert_eq!(failover, PathwaySwitch {
+            from: Some("CDN-A".to_string()),
+            to: "CDN-B".to_string(),
+            reason: PathwaySwitchReason::Failover,
+        });
+        let rewritten = selector.rewrite(&segment);
+        assert_eq!(rewritten.as_str(), "https://b.example.com/vod/seg7.ts");
+        selector.record_success(&rewritten);
+
+        // The next steering manifest lifts the demotion
+        let recovery = client.poll(&selector, None).await.unwrap().unwrap();
+        assert_eq!(recovery.to, "CDN-A");
+        assert_eq!(recovery.reason, PathwaySwitchReason::Steering);
+        assert_eq!(selector.rewrite(&rewritten), segment);
+
+        let requests = fetcher.requests();
+        assert_eq!(requests[0].url.as_str(), "https://steering.example.com/steer?_HLS_pathway=CDN-A&_HLS_throughput=5000000");
+        assert_eq!(requests[1].url.as_str(), "https://steering.example.com/steer?session=abc&_HLS_pathway=CDN-B");
+    }
+
+    #[test]
+    fn test_all_pathways_failing() {
+        let selector = PathwaySelector::new(&steering(None), 1);
+        let a = Url::parse("https://a.example.com/seg.ts").unwrap();
+        let b = Url::parse("https://b.example.com/seg.ts").unwrap();
+
+        assert_eq!(selector.record_failure(&a).unwrap().to, "CDN-B");
+        // With CDN-B failing too, CDN-A is retried rather than giving up
+        assert_eq!(selector.record_failure(&b).unwrap().to, "CDN-A");
+    }
+}
@@ -1,5 +1,7 @@
 //! Core types for Kino
 
+use crate::analytics::RecorderConfig;
+use crate::integrity::SegmentIntegrity;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use url::Url;
@@ -92,6 +94,29 @@ impl DrmSystem {
             DrmSystem::ClearKey => "1077efec-c0b2-4d02-ace3-3c1e52e2fb4b",
         }
     }
+
+    /// Map an HLS KEYFORMAT to its DRM system
+    ///
+    /// Accepts the reverse-DNS names used by FairPlay, PlayReady and
+    /// ClearKey as well as `urn:uuid:<system id>` (Widevine et al).
+    pub fn from_key_format(key_format: &str) -> Option<DrmSystem> {
+        const SYSTEMS: [DrmSystem; 4] = [
+            DrmSystem::Widevine,
+            DrmSystem::FairPlay,
+            DrmSystem::PlayReady,
+            DrmSystem::ClearKey,
+        ];
+
+        match key_format {
+            "com.apple.streamingkeydelivery" => Some(DrmSystem::FairPlay),
+            "com.microsoft.playready" => Some(DrmSystem::PlayReady),
+            "org.w3.clearkey" => Some(DrmSystem::ClearKey),
+            other => {
+                let uuid = other.strip_prefix("urn:uuid:")?;
+                SYSTEMS.into_iter().find(|s| s.system_id().eq_ignore_ascii_case(uuid))
+            }
+        }
+    }
 }
 
 /// Video resolution
@@ -156,6 +181,9 @@ pub struct Rendition {
     pub language: Option<String>,
     /// Human-readable name
     pub name: Option<String>,
+    /// Audio group this variant plays with (HLS AUDIO attribute)
+    #[serde(default)]
+    pub audio_group: Option<String>,
 }
 
 impl Rendition {
@@ -214,7 +242,7 @@ pub struct Segment {
 }
 
 /// Byte range for partial segment requests
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ByteRange {
     pub start: u64,
     pub length: u64,
@@ -233,6 +261,16 @@ pub struct EncryptionInfo {
     pub key_uri: Option<Url>,
     pub iv: Option<Vec<u8>>,
     pub key_format: Option<String>,
+    /// KEYFORMATVERSIONS, e.g. "1" or "1/2/5"
+    #[serde(default)]
+    pub key_format_versions: Option<String>,
+}
+
+impl EncryptionInfo {
+    /// DRM system named by the key format, None for plain key delivery
+    pub fn drm_system(&self) -> Option<DrmSystem> {
+        self.key_format.as_deref().and_then(DrmSystem::from_key_format)
+    }
 }
 
 /// Encryption methods
@@ -265,7 +303,23 @@ pub enum PlayerState {
     Error,
 }
 
+/// `tracing` target of the event recorded for every attempted state
+/// transition; enable it to audit the state machine from telemetry
+pub const STATE_AUDIT_TARGET: &str = "kino_core::state_audit";
+
 impl PlayerState {
+    /// Every state, in declaration order
+    pub const ALL: [PlayerState; 8] = [
+        PlayerState::Idle,
+        PlayerState::Loading,
+        PlayerState::Buffering,
+        PlayerState::Playing,
+        PlayerState::Paused,
+        PlayerState::Seeking,
+        PlayerState::Ended,
+        PlayerState::Error,
+    ];
+
     /// Check if transition to target state is valid
     pub fn can_transition_to(&self, target: PlayerState) -> bool {
         use PlayerState::*;
@@ -275,20 +329,46 @@ impl PlayerState {
             (Idle, Loading) |
             // From Loading
             (Loading, Buffering) | (Loading, Error) |
-            // From Buffering
-            (Buffering, Playing) | (Buffering, Paused) | (Buffering, Error) |
+            // From Buffering (seeking while paused can land here)
+            (Buffering, Playing) | (Buffering, Paused) | (Buffering, Seeking) | (Buffering, Error) |
             // From Playing
             (Playing, Paused) | (Playing, Buffering) | (Playing, Seeking) | (Playing, Ended) | (Playing, Error) |
-            // From Paused
-            (Paused, Playing) | (Paused, Seeking) | (Paused, Idle) |
-            // From Seeking
-            (Seeking, Buffering) | (Seeking, Playing) | (Seeking, Error) |
+            // From Paused (resuming with a low buffer rebuffers first)
+            (Paused, Playing) | (Paused, Buffering) | (Paused, Seeking) | (Paused, Idle) | (Paused, Error) |
+            // From Seeking (a paused seek stays paused; a new seek may
+            // supersede one in flight)
+            (Seeking, Buffering) | (Seeking, Playing) | (Seeking, Paused) | (Seeking, Seeking) | (Seeking, Error) |
             // From Ended
             (Ended, Idle) | (Ended, Seeking) |
             // From Error
             (Error, Idle) | (Error, Loading)
         )
     }
+
+    /// Move to `target` if the state machine allows it.
+    ///
+    /// Every attempt is recorded as a tracing event on
+    /// [`STATE_AUDIT_TARGET`] with its outcome, so transitions the table
+    /// rejects in the field show up in telemetry.
+    pub fn transition(self, target: PlayerState) -> std::result::Result<PlayerState, InvalidTransition> {
+        if self.can_transition_to(target) {
+            tracing::debug!(target: STATE_AUDIT_TARGET, from = %self, to = %target, allowed = true, "State transition");
+            Ok(target)
+        } else {
+            tracing::warn!(target: STATE_AUDIT_TARGET, from = %self, to = %target, allowed = false, "State transition rejected");
+            Err(InvalidTransition { from: self, to: target })
+        }
+    }
+}
+
+/// A state transition the player state machine does not allow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid playback state transition: {from} -> {to}")]
+pub struct InvalidTransition {
+    /// State the player was in
+    pub from: PlayerState,
+    /// State that was requested
+    pub to: PlayerState,
 }
 
 impl std::fmt::Display for PlayerState {
@@ -327,6 +407,25 @@ pub struct QualityMetrics {
     pub quality_switches: u32,
     /// Average throughput in bps
     pub throughput: u64,
+    /// Decoder backend in use, when the platform reports it
+    #[serde(default)]
+    pub hardware_backend: Option<HardwareBackendInfo>,
+}
+
+/// Requested versus active video decoding backend
+///
+/// Backends are reported by name since they are platform specific
+/// (e.g. "VA-API (Linux)" or "Software").
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareBackendInfo {
+    /// Backend requested in the player configuration
+    pub requested: String,
+    /// Backend actually decoding, once a decoder has been chosen
+    pub active: Option<String>,
+    /// Decoder element or codec name
+    pub decoder: Option<String>,
+    /// Hardware decoding was requested but the active decoder is software
+    pub fallback: bool,
 }
 
 impl QualityMetrics {
@@ -393,6 +492,14 @@ pub struct PlayerConfig {
     pub max_bitrate: u64,
     /// Start at lowest quality
     pub start_at_lowest: bool,
+    /// Segments chosen by the fast start ramp before the ABR algorithm
+    /// takes over (0 = off); see [`crate::abr::AbrEngine::with_fast_start`]
+    #[serde(default = "default_fast_start_segments")]
+    pub fast_start_segments: u32,
+    /// Bandwidth estimate carried over from a previous session, in bps
+    /// (0 = none)
+    #[serde(default)]
+    pub initial_bandwidth_estimate: u64,
     /// Enable prefetch of next segment
     pub prefetch_enabled: bool,
     /// Retry attempts for failed requests
@@ -403,6 +510,56 @@ pub struct PlayerConfig {
     pub request_timeout_ms: u64,
     /// Enable analytics
     pub analytics_enabled: bool,
+    /// Playback rate above which segments come from an I-frame rendition
+    #[serde(default = "default_trick_play_threshold")]
+    pub trick_play_threshold: f64,
+    /// Consecutive segment failures before a CDN pathway is demoted
+    #[serde(default = "default_pathway_failure_threshold")]
+    pub pathway_failure_threshold: u32,
+    /// Verification of downloaded segments
+    #[serde(default)]
+    pub segment_integrity: SegmentIntegrity,
+    /// Record a session timeline for export; see [`crate::analytics::SessionRecorder`]
+    #[serde(default)]
+    pub record_session: bool,
+    /// Sampling and retention of the session timeline
+    #[serde(default)]
+    pub recorder: RecorderConfig,
+    /// Spill segments evicted from the buffer to disk; see
+    /// [`crate::disk_cache`]
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(default)]
+    pub disk_cache: Option<crate::disk_cache::DiskCacheConfig>,
+    /// Seek to the saved watch progress on load; see [`crate::progress`]
+    #[serde(default)]
+    pub resume_playback: bool,
+    /// How often watch progress is saved during playback, in milliseconds
+    #[serde(default = "default_progress_save_interval_ms")]
+    pub progress_save_interval_ms: u64,
+    /// Fraction of the duration after which content counts as finished
+    /// and resumes from the start
+    #[serde(default = "default_resume_finished_threshold")]
+    pub resume_finished_threshold: f64,
+}
+
+fn default_fast_start_segments() -> u32 {
+    3
+}
+
+fn default_trick_play_threshold() -> f64 {
+    2.0
+}
+
+fn default_pathway_failure_threshold() -> u32 {
+    3
+}
+
+fn default_progress_save_interval_ms() -> u64 {
+    10_000
+}
+
+fn default_resume_finished_threshold() -> f64 {
+    0.95
 }
 
 impl Default for PlayerConfig {
@@ -414,11 +571,23 @@ impl Default for PlayerConfig {
             abr_algorithm: AbrAlgorithmType::Bola,
             max_bitrate: 0,
             start_at_lowest: false,
+            fast_start_segments: default_fast_start_segments(),
+            initial_bandwidth_estimate: 0,
             prefetch_enabled: true,
             retry_attempts: 3,
             retry_delay_ms: 1000,
             request_timeout_ms: 10000,
             analytics_enabled: true,
+            trick_play_threshold: default_trick_play_threshold(),
+            pathway_failure_threshold: default_pathway_failure_threshold(),
+            segment_integrity: SegmentIntegrity::default(),
+            record_session: false,
+            recorder: RecorderConfig::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            disk_cache: None,
+            resume_playback: false,
+            progress_save_interval_ms: default_progress_save_interval_ms(),
+            resume_finished_threshold: default_resume_finished_threshold(),
         }
     }
 }
@@ -544,6 +713,16 @@ impl TextTrackFormat {
             TextTrackFormat::Cea608 | TextTrackFormat::Cea708 => "",
         }
     }
+
+    /// Format of a sidecar file with the given extension (case-insensitive)
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "vtt" | "webvtt" => Some(TextTrackFormat::WebVtt),
+            "ttml" | "dfxp" => Some(TextTrackFormat::Ttml),
+            "srt" => Some(TextTrackFormat::Srt),
+            _ => None,
+        }
+    }
 }
 
 /// Text track (captions, subtitles, etc.)
@@ -737,6 +916,31 @@ impl MediaTracks {
         self.text.push(track);
     }
 
+    /// Add an audio track
+    pub fn add_audio_track(&mut self, track: AudioTrack) {
+        self.audio.push(track);
+    }
+
+    /// Get audio tracks by language
+    pub fn audio_tracks_by_language(&self, language: &str) -> Vec<&AudioTrack> {
+        self.audio.iter().filter(|t| t.language == language).collect()
+    }
+
+    /// Get the default audio track, falling back to the first autoselect
+    /// track and then the first track
+    pub fn default_audio_track(&self) -> Option<&AudioTrack> {
+        self.audio
+            .iter()
+            .find(|t| t.is_default)
+            .or_else(|| self.audio.iter().find(|t| t.autoselect))
+            .or_else(|| self.audio.first())
+    }
+
+    /// Get the video renditions that can play with an audio track
+    pub fn renditions_for_audio(&self, track: &AudioTrack) -> Vec<&Rendition> {
+        self.video.iter().filter(|r| track.is_compatible_with(r)).collect()
+    }
+
     /// Add a chapter
     pub fn add_chapter(&mut self, chapter: Chapter) {
         self.chapters.push(chapter);
@@ -788,4 +992,19 @@ pub struct AudioTrack {
     pub is_audio_description: bool,
     /// URL to audio variant (if separate from video)
     pub url: Option<Url>,
+    /// Group this track belongs to (HLS GROUP-ID)
+    #[serde(default)]
+    pub group_id: Option<String>,
+    /// Whether the client may select this track without user input
+    #[serde(default)]
+    pub autoselect: bool,
+}
+
+impl AudioTrack {
+    /// Whether a video rendition can play with this track. Renditions
+    /// without an audio group carry muxed audio and only pair with tracks
+    /// that have no group either.
+    pub fn is_compatible_with(&self, rendition: &Rendition) -> bool {
+        rendition.audio_group == self.group_id
+    }
 }
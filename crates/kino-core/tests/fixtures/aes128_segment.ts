@@ -0,0 +1,4 @@
+ԨYVuf&Mα`Ǔ݋{x]`ߥr\VqxrCV0Y=rfCu
+j4pJ	#$b<]h l%7]E]e
+\}RTRcqxȎ\ocOvFEb8VTnȷ5T[z$kVۼ@sy.&hdDu>$L8Xޓqq}P=[6Z-~clf[bu	
+^}hhZ=UO+W1]k.m1 -#bk18x^ۦHy&1߭J@>
\ No newline at end of file
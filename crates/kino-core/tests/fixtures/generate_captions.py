@@ -0,0 +1,274 @@
+#!/usr/bin/env python3
+"""Regenerate the embedded caption fixtures used by `kino_core::cea` tests.
+
+Each fixture is an MPEG-TS segment of 30fps video access units whose SEI
+messages carry ATSC A/53 cc_data. The video itself is a stub slice; only
+the caption bytes matter. Frames are written in B-frame decode order
+(0, 2, 1, 4, 3, ...) with PTS starting at 10s, so decoders have to reorder
+by PTS and anchor the timeline at the earliest picture.
+
+- cea608_popon.ts   H.264, CC1 pop-on: "HELLO / WORLD" then "¡HOLA!"
+                    (extended character), cleared with EDM
+- cea608_rollup.ts  H.264, CC1 roll-up with two rows and three lines
+- cea708.ts         H.265, service 1: a visible window typed line by line,
+                    then a hidden window revealed with TGW and deleted
+
+The matching *.json files are the golden cues: the start and end of every
+caption follow from the frame numbers below (frame / 30 seconds).
+
+Usage: python3 generate_captions.py  (writes next to this script)
+"""
+
+import json
+import os
+
+OUT = os.path.dirname(os.path.abspath(__file__))
+
+FPS = 30
+TICKS_PER_FRAME = 90000 // FPS
+PTS_BASE = 900000  # 10s
+VIDEO_PID = 0x100
+PMT_PID = 0x1000
+
+PAD_608 = (0x80, 0x80)
+
+
+def crc32_mpeg2(data):
+    crc = 0xFFFFFFFF
+    for byte in data:
+        crc ^= byte << 24
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x04C11DB7) if crc & 0x80000000 else crc << 1
+            crc &= 0xFFFFFFFF
+    return crc
+
+
+def odd_parity(byte):
+    return byte | 0x80 if bin(byte).count("1") % 2 == 0 else byte
+
+
+def pair(b1, b2=0):
+    return (odd_parity(b1), odd_parity(b2) if b2 else 0x80)
+
+
+def chars(text):
+    """CEA-608 byte pairs for basic characters, two per pair."""
+    codes = [ord(c) for c in text]
+    return [pair(codes[i], codes[i + 1] if i + 1 < len(codes) else 0) for i in range(0, len(codes), 2)]
+
+
+def control(b1, b2):
+    """A control code and its redundant repeat."""
+    return [pair(b1, b2), pair(b1, b2)]
+
+
+RCL, EOC, EDM, ENM, CR = (0x14, 0x20), (0x14, 0x2F), (0x14, 0x2C), (0x14, 0x2E), (0x14, 0x2D)
+RU2 = (0x14, 0x25)
+PAC_ROW_14, PAC_ROW_15 = (0x14, 0x40), (0x14, 0x60)
+
+
+def schedule(total, events):
+    """Per-frame 608 pairs: {frame: [pairs...]} laid out from each frame on."""
+    frames = [PAD_608] * total
+    for start, pairs in events:
+        for i, p in enumerate(pairs):
+            frames[start + i] = p
+    return frames
+
+
+def escape(rbsp):
+    out = bytearray()
+    zeros = 0
+    for byte in rbsp:
+        if zeros >= 2 and byte <= 3:
+            out.append(3)
+            zeros = 0
+        out.append(byte)
+        zeros = zeros + 1 if byte == 0 else 0
+    return bytes(out)
+
+
+def cc_data(triplets):
+    out = bytearray([0xC0 | len(triplets), 0xFF])
+    for t in triplets:
+        out.extend(t)
+    out.append(0xFF)
+    return bytes(out)
+
+
+def sei_nal(header, triplets):
+    payload = bytes([0xB5, 0x00, 0x31]) + b"GA94" + bytes([0x03]) + cc_data(triplets)
+    rbsp = bytearray([4])
+    size = len(payload)
+    while size >= 255:
+        rbsp.append(255)
+        size -= 255
+    rbsp.append(size)
+    rbsp.extend(payload)
+    rbsp.append(0x80)
+    return b"\x00\x00\x01" + header + escape(bytes(rbsp))
+
+
+def access_unit(codec, triplets):
+    if codec == "h264":
+        return (b"\x00\x00\x00\x01\x09\xf0" + sei_nal(b"\x06", triplets)
+                + b"\x00\x00\x01\x65\x88\x84\x21\xa0")
+    return (b"\x00\x00\x00\x01\x46\x01\x50" + sei_nal(b"\x4e\x01", triplets)
+            + b"\x00\x00\x01\x26\x01\xaf\x09\x40")
+
+
+def timestamp(prefix, ts):
+    return bytes([
+        (prefix << 4) | ((ts >> 29) & 0x0E) | 1,
+        (ts >> 22) & 0xFF,
+        ((ts >> 14) & 0xFE) | 1,
+        (ts >> 7) & 0xFF,
+        ((ts << 1) & 0xFE) | 1,
+    ])
+
+
+def pes(payload, pts, dts):
+    header = timestamp(3, pts) + timestamp(1, dts)
+    return b"\x00\x00\x01\xe0\x00\x00\x80\xc0" + bytes([len(header)]) + header + payload
+
+
+class TsWriter:
+    def __init__(self):
+        self.out = bytearray()
+        self.counters = {}
+
+    def write(self, pid, data):
+        first = True
+        while data:
+            chunk, data = data[:184], data[184:]
+            counter = self.counters.get(pid, 0)
+            self.counters[pid] = (counter + 1) % 16
+            header = bytearray([0x47, (0x40 if first else 0) | (pid >> 8), pid & 0xFF, 0x10 | counter])
+            need = 184 - len(chunk)
+            if need:
+                header[3] |= 0x20
+                header.extend([0] if need == 1 else [need - 1, 0x00] + [0xFF] * (need - 2))
+            self.out.extend(header + chunk)
+            first = False
+
+    def psi(self, pid, table):
+        self.write(pid, b"\x00" + table + crc32_mpeg2(table).to_bytes(4, "big"))
+
+
+def transport_stream(codec, frames):
+    """frames: per-frame cc triplet lists in presentation order."""
+    ts = TsWriter()
+    pat = bytes([0x00, 0xB0, 13, 0x00, 0x01, 0xC1, 0x00, 0x00, 0x00, 0x01, 0xE0 | (PMT_PID >> 8), PMT_PID & 0xFF])
+    ts.psi(0, pat)
+    stream_type = 0x1B if codec == "h264" else 0x24
+    pmt_body = bytes([0x00, 0x01, 0xC1, 0x00, 0x00, 0xE0 | (VIDEO_PID >> 8), VIDEO_PID & 0xFF, 0xF0, 0x00,
+                      stream_type, 0xE0 | (VIDEO_PID >> 8), VIDEO_PID & 0xFF, 0xF0, 0x00])
+    ts.psi(PMT_PID, bytes([0x02, 0xB0, len(pmt_body) + 4]) + pmt_body)
+
+    order = [0]
+    for k in range(1, len(frames) - 1, 2):
+        order += [k + 1, k]
+    order += [i for i in range(len(frames)) if i not in order]
+
+    for decode_index, frame in enumerate(order):
+        pts = PTS_BASE + (frame + 1) * TICKS_PER_FRAME
+        dts = PTS_BASE + decode_index * TICKS_PER_FRAME
+        ts.write(VIDEO_PID, pes(access_unit(codec, frames[frame]), pts, dts))
+    return bytes(ts.out)
+
+
+def field1(frames_608):
+    return [[(0xFC,) + p] for p in frames_608]
+
+
+def write(name, data, cues):
+    with open(os.path.join(OUT, name + ".ts"), "wb") as f:
+        f.write(data)
+    golden = [{"id": i, "start": round(s / FPS, 6), "end": round(e / FPS, 6), "text": t} for i, s, e, t in cues]
+    with open(os.path.join(OUT, name + ".json"), "w") as f:
+        json.dump(golden, f, indent=2, ensure_ascii=False)
+        f.write("\n")
+
+
+def popon():
+    frames = schedule(96, [
+        (0, control(*RCL)),
+        (2, control(*ENM)),
+        (4, control(*PAC_ROW_14)),
+        (6, chars("HELLO")),
+        (9, control(*PAC_ROW_15)),
+        (11, chars("WORLD")),
+        (30, control(*EOC)),
+        (32, control(*ENM)),
+        (34, control(*PAC_ROW_15)),
+        # '!' is the fallback the extended '¡' (0x12 0x27) replaces
+        (36, chars("!")),
+        (37, control(0x12, 0x27)),
+        (39, chars("HOLA!")),
+        (60, control(*EOC)),
+        (90, control(*EDM)),
+    ])
+    write("cea608_popon", transport_stream("h264", field1(frames)), [
+        ("cc1-0", 30, 60, "HELLO\nWORLD"),
+        ("cc1-1", 60, 90, "¡HOLA!"),
+    ])
+
+
+def rollup():
+    frames = schedule(96, [
+        (0, control(*RU2)),
+        (2, control(*CR)),
+        (4, control(*PAC_ROW_15)),
+        (6, chars("FIRST LINE")),
+        (30, control(*CR)),
+        (32, chars("SECOND LINE")),
+        (60, control(*CR)),
+        (62, chars("THIRD")),
+        (90, control(*EDM)),
+    ])
+    write("cea608_rollup", transport_stream("h264", field1(frames)), [
+        ("cc1-0", 6, 32, "FIRST LINE"),
+        ("cc1-1", 32, 62, "FIRST LINE\nSECOND LINE"),
+        ("cc1-2", 62, 90, "SECOND LINE\nTHIRD"),
+    ])
+
+
+def dtvcc_packet(sequence, service_data):
+    block = bytes([(1 << 5) | len(service_data)]) + service_data
+    size_code = (len(block) + 2) // 2
+    content = block + b"\x00" * (size_code * 2 - 1 - len(block))
+    packet = bytes([(sequence << 6) | size_code]) + content
+    triplets = [(0xFF, packet[0], packet[1])]
+    for i in range(2, len(packet), 2):
+        triplets.append((0xFE, packet[i], packet[i + 1]))
+    return triplets
+
+
+def cea708():
+    # DF0: visible, 2 rows; DF1: hidden, 1 row
+    df0 = bytes([0x98, 0x38, 0x00, 0x00, 0x01, 0x1F, 0x00])
+    df1 = bytes([0x99, 0x18, 0x00, 0x00, 0x00, 0x1F, 0x00])
+    packets = {
+        0: df0 + b"HELLO\x0d",
+        10: b"WORLD \x7f\x03",
+        30: df1 + b"POP ON",
+        45: bytes([0x8B, 0x03]),  # TGW windows 0 and 1
+        75: bytes([0x8C, 0x02]),  # DLW window 1
+    }
+    frames = []
+    for frame in range(90):
+        triplets = [(0xFC,) + PAD_608]
+        if frame in packets:
+            triplets += dtvcc_packet(len(frames) % 4, packets[frame])
+        frames.append(triplets)
+    write("cea708", transport_stream("h265", frames), [
+        ("service1-0", 0, 10, "HELLO"),
+        ("service1-1", 10, 45, "HELLO\nWORLD ♪"),
+        ("service1-2", 45, 75, "POP ON"),
+    ])
+
+
+if __name__ == "__main__":
+    popon()
+    rollup()
+    cea708()
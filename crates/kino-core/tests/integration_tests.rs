@@ -1,7 +1,7 @@
 //! Integration tests for Kino Core
 
 use kino_core::{
-    PlayerConfig, PlayerState, Resolution,
+    Error, InvalidTransition, PlayerConfig, PlayerState, Resolution,
     KinoColors, KinoTheme, CssVariables,
     DrmConfig, DrmManager, DrmSystem,
     WebVttParser, SrtParser,
@@ -69,6 +69,48 @@ fn test_player_state_transitions() {
     assert!(!PlayerState::Playing.can_transition_to(PlayerState::Idle));
 }
 
+/// The intended transition matrix. Rows are the current state, columns the
+/// target, in `PlayerState::ALL` order; edit deliberately.
+#[test]
+fn test_player_state_transition_matrix() {
+    use PlayerState::*;
+
+    const Y: bool = true;
+    const N: bool = false;
+    #[rustfmt::skip]
+    let matrix: [(PlayerState, [bool; 8]); 8] = [
+        //          Idle Load Buff Play Paus Seek Endd Err
+        (Idle,      [N,   Y,   N,   N,   N,   N,   N,   N]),
+        (Loading,   [N,   N,   Y,   N,   N,   N,   N,   Y]),
+        (Buffering, [N,   N,   N,   Y,   Y,   Y,   N,   Y]),
+        (Playing,   [N,   N,   Y,   N,   Y,   Y,   Y,   Y]),
+        (Paused,    [Y,   N,   Y,   Y,   N,   Y,   N,   Y]),
+        (Seeking,   [N,   N,   Y,   Y,   Y,   Y,   N,   Y]),
+        (Ended,     [Y,   N,   N,   N,   N,   Y,   N,   N]),
+        (Error,     [Y,   Y,   N,   N,   N,   N,   N,   N]),
+    ];
+
+    for (from, row) in matrix {
+        for (to, allowed) in PlayerState::ALL.into_iter().zip(row) {
+            assert_eq!(from.can_transition_to(to), allowed, "{} -> {}", from, to);
+            match from.transition(to) {
+                Ok(state) => assert!(allowed && state == to, "{} -> {}", from, to),
+                Err(err) => {
+                    assert!(!allowed, "{} -> {}", from, to);
+                    assert_eq!(err, InvalidTransition { from, to });
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_invalid_transition_converts_to_error() {
+    let err: Error = PlayerState::Idle.transition(PlayerState::Playing).unwrap_err().into();
+    assert_eq!(err.error_code(), "INVALID_STATE");
+    assert_eq!(err.to_string(), "Invalid playback state transition: idle -> playing");
+}
+
 #[test]
 fn test_player_config_defaults() {
     let config = PlayerConfig::default();
@@ -1,15 +1,34 @@
 //! Playback controls for desktop player
 
+use winit::keyboard::{Key, NamedKey};
+
 /// Keyboard/remote control handling
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ControlAction {
     PlayPause,
     Stop,
     SeekForward(f64),
     SeekBackward(f64),
+    StepForward,
+    StepBackward,
     VolumeUp,
     VolumeDown,
     Mute,
     Fullscreen,
     QualityUp,
     QualityDown,
+    TogglePip,
+    ClosePip,
+}
+
+/// Map a key press in the picture-in-picture window to an action
+///
+/// The PiP window only offers play/pause and close.
+pub fn pip_action_for_key(key: &Key) -> Option<ControlAction> {
+    match key {
+        Key::Named(NamedKey::Space) => Some(ControlAction::PlayPause),
+        Key::Named(NamedKey::Escape) => Some(ControlAction::ClosePip),
+        Key::Character(c) if c.eq_ignore_ascii_case("k") => Some(ControlAction::PlayPause),
+        _ => None,
+    }
 }
@@ -5,6 +5,8 @@
 //! - HLS/DASH adaptive streaming
 //! - DRM support via Widevine CDM
 //! - Low-latency playback
+//! - Frame-accurate stepping for QC review
+//! - Sidecar WebVTT/SRT subtitles drawn over the video
 //!
 //! # Example
 //!
@@ -20,11 +22,19 @@
 pub mod player;
 pub mod window;
 pub mod controls;
+pub mod license_store;
+pub mod subtitles;
 
 pub use player::{
     DesktopPlayer,
     DesktopPlayerConfig,
     HardwareBackend,
     GStreamerInfo,
+    FrameInfo,
+    SeekMode,
+    StepDirection,
     check_gstreamer_installation,
 };
+pub use subtitles::SubtitleStyle;
+pub use window::{PipConfig, WindowConfig, WindowManager};
+pub use license_store::EncryptedFileLicenseStore;
@@ -0,0 +1,223 @@
+//! Encrypted on-disk license storage
+//!
+//! Persists DRM licenses so protected content can play offline and app
+//! restarts don't have to go back to the license server. Each license is
+//! stored in its own file, sealed with AES-256-GCM.
+
+use kino_core::drm::{LicenseStore, StoredLicense};
+use kino_core::{DrmSystem, Error, Result};
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN};
+use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Name of the generated key file inside the store directory
+const KEY_FILE: &str = "license.key";
+
+/// Extension of license entry files
+const LICENSE_EXTENSION: &str = "lic";
+
+/// License store that keeps one encrypted file per license
+///
+/// Files are named after a hash of the DRM system and key IDs, so nothing
+/// about the content is visible on disk without the store key.
+pub struct EncryptedFileLicenseStore {
+    dir: PathBuf,
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl EncryptedFileLicenseStore {
+    /// Open a store in `dir`, generating a key file on first use
+    ///
+    /// The key lives next to the licenses (readable only by the current user
+    /// on Unix). Use [`EncryptedFileLicenseStore::with_key`] to keep it in the
+    /// platform keychain instead.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let key_path = dir.join(KEY_FILE);
+        let key = match fs::read(&key_path) {
+            Ok(key) => key,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut key = vec![0u8; 32];
+                SystemRandom::new()
+                    .fill(&mut key)
+                    .map_err(|_| Error::Internal("Failed to generate license store key".to_string()))?;
+                write_private(&key_path, &key)?;
+                debug!(path = %key_path.display(), "Generated license store key");
+                key
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Self::with_key(dir, &key)
+    }
+
+    /// Open a store in `dir` with a caller-provided 256-bit key
+    pub fn with_key(dir: impl AsRef<Path>, key: &[u8]) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let key = UnboundKey::new(&aead::AES_256_GCM, key)
+            .map_err(|_| Error::InvalidConfig("License store key must be 32 bytes".to_string()))?;
+
+        Ok(Self {
+            dir,
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// File name (without extension) for a license entry
+    fn entry_name(system: DrmSystem, key_ids: &[String]) -> String {
+        let key = StoredLicense::storage_key(system, key_ids);
+        digest::digest(&digest::SHA256, key.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn entry_path(&self, name: &str) -> PathBuf {
+        self.dir.join(name).with_extension(LICENSE_EXTENSION)
+    }
+
+    /// Read and decrypt an entry file
+    fn read_entry(&self, name: &str) -> Result<Option<StoredLicense>> {
+        let mut data = match fs::read(self.entry_path(name)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        if data.len() < NONCE_LEN {
+            return Err(Error::Internal(format!("License entry {} is truncated", name)));
+        }
+        let mut ciphertext = data.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&data)
+            .map_err(|_| Error::Internal(format!("License entry {} has an invalid nonce", name)))?;
+
+        // The file name is authenticated so entries can't be swapped on disk
+        let plaintext = self.key
+            .open_in_place(nonce, Aad::from(name.as_bytes()), &mut ciphertext)
+            .map_err(|_| Error::Internal(format!("License entry {} failed to decrypt", name)))?;
+
+        serde_json::from_slice(plaintext)
+            .map(Some)
+            .map_err(|e| Error::Internal(format!("License entry {} is corrupt: {}", name, e)))
+    }
+
+    /// Encrypt and write an entry file
+    fn write_entry(&self, name: &str, stored: &StoredLicense) -> Result<()> {
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce_bytes)
+            .map_err(|_| Error::Internal("Failed to generate nonce".to_string()))?;
+
+        let mut sealed = serde_json::to_vec(stored)
+            .map_err(|e| Error::Internal(format!("Failed to serialize license: {}", e)))?;
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce_bytes),
+                Aad::from(name.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| Error::Internal("Failed to encrypt license".to_string()))?;
+
+        let mut data = nonce_bytes.to_vec();
+        data.extend_from_slice(&sealed);
+
+        // Write to a temporary file first so a crash never leaves a partial entry
+        let path = self.entry_path(name);
+        let tmp = path.with_extension("tmp");
+        write_private(&tmp, &data)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn remove_entry(&self, name: &str) -> Result<()> {
+        match fs::remove_file(self.entry_path(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl LicenseStore for EncryptedFileLicenseStore {
+    fn save(&self, system: DrmSystem, key_ids: &[String], license: &[u8], expiration: u64) -> Result<()> {
+        let stored = StoredLicense {
+            system,
+            key_ids: key_ids.to_vec(),
+            license: license.to_vec(),
+            expiration,
+        };
+        self.write_entry(&Self::entry_name(system, key_ids), &stored)
+    }
+
+    fn load(&self, system: DrmSystem, key_ids: &[String]) -> Result<Option<StoredLicense>> {
+        let name = Self::entry_name(system, key_ids);
+        match self.read_entry(&name)? {
+            Some(stored) if stored.is_expired() => {
+                self.remove_entry(&name)?;
+                Ok(None)
+            }
+            stored => Ok(stored),
+        }
+    }
+
+    fn remove(&self, system: DrmSystem, key_ids: &[String]) -> Result<()> {
+        self.remove_entry(&Self::entry_name(system, key_ids))
+    }
+
+    fn purge_expired(&self) -> Result<usize> {
+        let mut purged = 0;
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(LICENSE_EXTENSION) {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|n| n.to_str()) else { continue };
+
+            match self.read_entry(name) {
+                Ok(Some(stored)) if stored.is_expired() => {
+                    self.remove_entry(name)?;
+                    purged += 1;
+                }
+                Ok(_) => {}
+                // Entries sealed with another key are left alone rather than destroyed
+                Err(e) => warn!(error = %e, "Skipping unreadable license entry"),
+            }
+        }
+
+        Ok(purged)
+    }
+}
+
+/// Write a file readable only by the current user where supported
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(data)?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        fs::write(path, data)?;
+        Ok(())
+    }
+}
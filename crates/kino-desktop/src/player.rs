@@ -3,16 +3,32 @@
 //! Features:
 //! - Hardware-accelerated decoding (VA-API, VideoToolbox, NVDEC)
 //! - HLS/DASH playback via hlsdemux/dashdemux
-//! - Subtitle support
+//! - Subtitle support, including sidecar WebVTT and SRT tracks
 //! - Chapter navigation
+//! - Frame stepping and keyframe-snapped or accurate seeks
+//! - Pipeline statistics (dropped frames, decoder, hardware path)
+//! - Rendering into application windows, movable between windows
 
 use anyhow::{Context, Result};
 use gstreamer as gst;
+use gstreamer::prelude::*;
 use gstreamer_player as gst_player;
-use kino_core::{PlayerConfig, PlayerSession, PlayerState, QualityMetrics, Resolution, KinoColors};
-use std::sync::{Arc, Mutex};
+use gstreamer_video as gst_video;
+use gstreamer_video::prelude::*;
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use kino_core::{
+    AnalyticsEmitter, AnalyticsEvent, HardwareBackendInfo, KinoColors, PlayerConfig,
+    PlayerSession, PlayerState, QualityMetrics, Resolution, TextCue, TextTrack,
+};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 use tracing::{debug, error, info, warn};
 
+use crate::subtitles::{SubtitleOverlay, SubtitleStyle};
+
 /// Hardware decoding backend
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HardwareBackend {
@@ -50,6 +66,20 @@ impl HardwareBackend {
         }
     }
 
+    /// Identify the backend behind a video decoder element
+    ///
+    /// Unrecognized decoders (e.g. `avdec_h264`, `dav1ddec`) are software.
+    pub fn from_decoder_element(factory_name: &str) -> Self {
+        match factory_name {
+            // vaapi* and the newer va* plugin decoders
+            name if name.starts_with("va") => Self::VaApi,
+            name if name.starts_with("vtdec") => Self::VideoToolbox,
+            name if name.starts_with("nv") => Self::Nvdec,
+            name if name.starts_with("d3d11") || name.starts_with("d3d12") => Self::D3d11Va,
+            _ => Self::Software,
+        }
+    }
+
     /// Detect available hardware backends on this system
     pub fn detect_available() -> Vec<Self> {
         let mut available = vec![Self::Software];
@@ -107,6 +137,8 @@ pub struct DesktopPlayerConfig {
     pub buffer_duration: u64,
     /// Enable low-latency mode
     pub low_latency: bool,
+    /// Number of recently displayed frames remembered for backward stepping
+    pub frame_cache_size: usize,
 }
 
 impl Default for DesktopPlayerConfig {
@@ -119,6 +151,7 @@ impl Default for DesktopPlayerConfig {
             subtitle_language: None,
             buffer_duration: 3_000_000_000, // 3 seconds
             low_latency: false,
+            frame_cache_size: 120,
         }
     }
 }
@@ -134,6 +167,126 @@ impl DesktopPlayerConfig {
             subtitle_language: None,
             buffer_duration: 500_000_000, // 500ms
             low_latency: true,
+            frame_cache_size: 120,
+        }
+    }
+}
+
+/// Frame step direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepDirection {
+    Forward,
+    Backward,
+}
+
+/// Seek precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekMode {
+    /// Snap to the nearest keyframe (fast)
+    Keyframe,
+    /// Snap to the keyframe at or before the position
+    KeyframeBefore,
+    /// Snap to the keyframe at or after the position
+    KeyframeAfter,
+    /// Land on the exact frame, decoding from the previous keyframe
+    Accurate,
+}
+
+impl SeekMode {
+    /// GStreamer seek flags for this mode, excluding FLUSH
+    pub fn seek_flags(&self) -> gst::SeekFlags {
+        match self {
+            Self::Keyframe => gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_NEAREST,
+            Self::KeyframeBefore => gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_BEFORE,
+            Self::KeyframeAfter => gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_AFTER,
+            Self::Accurate => gst::SeekFlags::ACCURATE,
+        }
+    }
+}
+
+/// A displayed video frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// Presentation timestamp in nanoseconds
+    pub pts: u64,
+    /// Frame number derived from the PTS and frame rate
+    pub frame_number: u64,
+    /// Whether the frame was decoded from a keyframe
+    pub is_keyframe: bool,
+}
+
+/// Frames seen by the video sink, for frame info and backward stepping
+///
+/// Only frame metadata is kept: holding decoded buffers would starve
+/// the decoder's buffer pool.
+#[derive(Debug)]
+struct FrameTracker {
+    /// Recently displayed frames by PTS
+    frames: BTreeMap<u64, FrameInfo>,
+    /// Most recent frame to reach the sink
+    current: Option<FrameInfo>,
+    /// Frame rate from the sink caps (numerator, denominator)
+    frame_rate: Option<(u64, u64)>,
+    /// Duration of the most recent buffer in nanoseconds
+    buffer_duration: Option<u64>,
+    capacity: usize,
+}
+
+impl FrameTracker {
+    fn new(capacity: usize) -> Self {
+        Self {
+            frames: BTreeMap::new(),
+            current: None,
+            frame_rate: None,
+            buffer_duration: None,
+            capacity,
+        }
+    }
+
+    /// Nominal frame duration in nanoseconds
+    fn frame_duration(&self) -> Option<u64> {
+        match self.frame_rate {
+            Some((num, den)) if num > 0 => Some(den * 1_000_000_000 / num),
+            _ => self.buffer_duration,
+        }
+    }
+
+    fn record(&mut self, pts: u64, duration: Option<u64>, is_keyframe: bool, frame_rate: Option<(u64, u64)>) {
+        if frame_rate.is_some() {
+            self.frame_rate = frame_rate;
+        }
+        if duration.is_some() {
+            self.buffer_duration = duration;
+        }
+
+        let frame_number = match (self.frame_rate, self.frame_duration()) {
+            (Some((num, den)), _) if den > 0 => (pts as u128 * num as u128 / (den as u128 * 1_000_000_000)) as u64,
+            (_, Some(d)) if d > 0 => pts / d,
+            _ => 0,
+        };
+        let frame = FrameInfo { pts, frame_number, is_keyframe };
+        self.current = Some(frame);
+        self.frames.insert(pts, frame);
+
+        // Forget the frames farthest from the playhead
+        while self.frames.len() > self.capacity.max(1) {
+            let first = *self.frames.keys().next().unwrap();
+            let last = *self.frames.keys().next_back().unwrap();
+            let evict = if pts - first >= last - pts { first } else { last };
+            self.frames.remove(&evict);
+        }
+    }
+
+    /// PTS of the frame before the current one: the cached frame if it
+    /// is adjacent, otherwise one frame duration back
+    fn previous_pts(&self) -> Option<u64> {
+        let current = self.current?;
+        let cached = self.frames.range(..current.pts).next_back().map(|(&pts, _)| pts);
+        match (cached, self.frame_duration()) {
+            (Some(pts), Some(d)) if current.pts - pts <= d + d / 2 => Some(pts),
+            (Some(pts), None) => Some(pts),
+            (_, Some(d)) => current.pts.checked_sub(d),
+            (None, None) => None,
         }
     }
 }
@@ -150,6 +303,11 @@ struct PlayerStateInner {
     video_width: u32,
     video_height: u32,
     current_bitrate: u64,
+    dropped_frames: u64,
+    decoded_frames: u64,
+    decoder: Option<String>,
+    active_backend: Option<HardwareBackend>,
+    decoder_fallback: bool,
 }
 
 impl Default for PlayerStateInner {
@@ -164,17 +322,137 @@ impl Default for PlayerStateInner {
             video_width: 0,
             video_height: 0,
             current_bitrate: 0,
+            dropped_frames: 0,
+            decoded_frames: 0,
+            decoder: None,
+            active_backend: None,
+            decoder_fallback: false,
         }
     }
 }
 
+/// How often the pipeline is polled for statistics
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Background poller copying pipeline statistics into the player state
+struct StatsCollector {
+    pipeline: gst::Element,
+    sink_pad: gst::Pad,
+    /// fpsdisplaysink wrapping the real sink, when the element is installed
+    fps_sink: Option<gst::Element>,
+    /// Buffers seen at the sink, used when fpsdisplaysink is unavailable
+    frames_seen: Arc<AtomicU64>,
+    state: Arc<Mutex<PlayerStateInner>>,
+    requested: HardwareBackend,
+    expects_hardware: bool,
+    analytics: Option<Arc<AnalyticsEmitter>>,
+    runtime: Option<tokio::runtime::Handle>,
+    /// Decoder already reported as a fallback, so each one warns once
+    reported_fallback: Option<String>,
+}
+
+impl StatsCollector {
+    fn run(mut self, stop: mpsc::Receiver<()>) {
+        // The sender is dropped with the player, which ends the loop
+        while let Err(mpsc::RecvTimeoutError::Timeout) = stop.recv_timeout(STATS_INTERVAL) {
+            self.collect();
+        }
+    }
+
+    fn collect(&mut self) {
+        let (dropped, decoded) = match &self.fps_sink {
+            Some(sink) => {
+                let dropped = sink.property::<u32>("frames-dropped") as u64;
+                let rendered = sink.property::<u32>("frames-rendered") as u64;
+                (dropped, rendered + dropped)
+            }
+            None => (0, self.frames_seen.load(Ordering::Relaxed)),
+        };
+
+        let bitrate = self.sink_pad
+            .sticky_event::<gst::event::Tag>(0)
+            .and_then(|event| {
+                let tags = event.tag();
+                tags.get::<gst::tags::Bitrate>()
+                    .or_else(|| tags.get::<gst::tags::NominalBitrate>())
+                    .map(|value| value.get() as u64)
+            })
+            .filter(|b| *b > 0);
+
+        let decoder = find_video_decoder(&self.pipeline);
+        let active = decoder.as_deref().map(HardwareBackend::from_decoder_element);
+        let fallback = self.expects_hardware && active == Some(HardwareBackend::Software);
+
+        let position = match self.state.lock() {
+            Ok(mut s) => {
+                s.dropped_frames = dropped;
+                s.decoded_frames = decoded;
+                if let Some(bitrate) = bitrate {
+                    s.current_bitrate = bitrate;
+                }
+                s.decoder = decoder.clone();
+                s.active_backend = active;
+                s.decoder_fallback = fallback;
+                s.position as f64 / 1_000_000_000.0
+            }
+            Err(_) => return,
+        };
+
+        let newly_fallen_back = decoder.filter(|d| fallback && self.reported_fallback.as_ref() != Some(d));
+        if let Some(decoder) = newly_fallen_back {
+            self.report_fallback(&decoder, position);
+            self.reported_fallback = Some(decoder);
+        }
+    }
+
+    fn report_fallback(&self, decoder: &str, position: f64) {
+        warn!(
+            requested = self.requested.display_name(),
+            decoder,
+            "Hardware decoding unavailable, fell back to software"
+        );
+
+        if let (Some(analytics), Some(runtime)) = (&self.analytics, &self.runtime) {
+            let analytics = analytics.clone();
+            let event = AnalyticsEvent::DecoderFallback {
+                requested: self.requested.display_name().to_string(),
+                active: HardwareBackend::Software.display_name().to_string(),
+                decoder: Some(decoder.to_string()),
+                position,
+            };
+            runtime.spawn(async move {
+                analytics.emit(event).await;
+            });
+        }
+    }
+}
+
+/// Find the factory name of the video decoder inside the pipeline
+fn find_video_decoder(pipeline: &gst::Element) -> Option<String> {
+    let bin = pipeline.downcast_ref::<gst::Bin>()?;
+    bin.iterate_recurse().into_iter().flatten().find_map(|element| {
+        let factory = element.factory()?;
+        let klass = factory.klass();
+        (klass.contains("Decoder") && klass.contains("Video")).then(|| factory.name().to_string())
+    })
+}
+
 /// GStreamer-based desktop video player
 pub struct DesktopPlayer {
     player: gst_player::Player,
     session: Arc<PlayerSession>,
     config: DesktopPlayerConfig,
     state: Arc<Mutex<PlayerStateInner>>,
+    frames: Arc<Mutex<FrameTracker>>,
     available_backends: Vec<HardwareBackend>,
+    stats_stop: Option<mpsc::Sender<()>>,
+    stats_thread: Option<thread::JoinHandle<()>>,
+    /// Sink that renders into the application window
+    output_sink: gst::Element,
+    /// Native handle of the window currently receiving video
+    window_handle: Arc<Mutex<Option<usize>>>,
+    /// Overlay drawing sidecar text track cues
+    subtitles: Arc<Mutex<SubtitleOverlay>>,
 }
 
 impl DesktopPlayer {
@@ -195,6 +473,75 @@ impl DesktopPlayer {
 
         let session = Arc::new(PlayerSession::new(config.core.clone()));
         let state = Arc::new(Mutex::new(PlayerStateInner::default()));
+        let frames = Arc::new(Mutex::new(FrameTracker::new(config.frame_cache_size)));
+
+        // Use an explicit video sink so its input can be probed for frame info,
+        // wrapped in fpsdisplaysink (if installed) for dropped/rendered counts
+        let output_sink = gst::ElementFactory::make("autovideosink")
+            .build()
+            .context("Failed to create video sink")?;
+        let fps_sink = gst::ElementFactory::make("fpsdisplaysink")
+            .property("video-sink", &output_sink)
+            .property("text-overlay", false)
+            .build();
+        let fps_sink = match fps_sink {
+            Ok(sink) => Some(sink),
+            Err(_) => {
+                debug!("fpsdisplaysink unavailable, dropped frames will not be reported");
+                None
+            }
+        };
+        let video_sink = fps_sink.clone().unwrap_or_else(|| output_sink.clone());
+        player.pipeline().set_property("video-sink", &video_sink);
+        let sink_pad = video_sink
+            .static_pad("sink")
+            .context("Video sink has no sink pad")?;
+
+        // Sidecar subtitles are drawn over the decoded frames
+        let subtitles = Arc::new(Mutex::new(SubtitleOverlay::new(config.subtitles_enabled)));
+        if let Some(overlay) = subtitles.lock().ok().and_then(|s| s.element().cloned()) {
+            player.pipeline().set_property("video-filter", &overlay);
+        }
+
+        let frames_clone = frames.clone();
+        let frames_seen = Arc::new(AtomicU64::new(0));
+        let frames_seen_clone = frames_seen.clone();
+        sink_pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            if let Some(gst::PadProbeData::Buffer(ref buffer)) = info.data {
+                frames_seen_clone.fetch_add(1, Ordering::Relaxed);
+                if let Some(pts) = buffer.pts() {
+                    let frame_rate = pad
+                        .current_caps()
+                        .and_then(|caps| gst_video::VideoInfo::from_caps(&caps).ok())
+                        .map(|info| info.fps())
+                        .filter(|fps| fps.numer() > 0 && fps.denom() > 0)
+                        .map(|fps| (fps.numer() as u64, fps.denom() as u64));
+                    let is_keyframe = !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT);
+                    if let Ok(mut f) = frames_clone.lock() {
+                        f.record(pts.nseconds(), buffer.duration().map(|d| d.nseconds()), is_keyframe, frame_rate);
+                    }
+                }
+            }
+            gst::PadProbeReturn::Ok
+        });
+
+        // Hand the window to the sink once it is created and asks for one
+        let window_handle: Arc<Mutex<Option<usize>>> = Arc::new(Mutex::new(None));
+        if let Some(bus) = player.pipeline().bus() {
+            let window_handle = window_handle.clone();
+            bus.set_sync_handler(move |_bus, msg| {
+                if gst_video::is_video_overlay_prepare_window_handle_message(msg) {
+                    let handle = window_handle.lock().ok().and_then(|h| *h);
+                    let overlay = msg.src().and_then(|src| src.dynamic_cast_ref::<gst_video::VideoOverlay>());
+                    if let (Some(handle), Some(overlay)) = (handle, overlay) {
+                        // SAFETY: the handle belongs to a window that stays
+                        // alive until it is replaced via `set_video_window`
+                        unsafe { overlay.set_window_handle(handle) };
+                    }
+                }
+                gst::BusSyncReply::Pass
+            });
+        }
 
         // Connect signals
         let state_clone = state.clone();
@@ -214,11 +561,15 @@ impl DesktopPlayer {
         });
 
         let state_clone = state.clone();
+        let subtitles_clone = subtitles.clone();
         player.connect_position_updated(move |_player, position| {
             if let Some(pos) = position {
                 if let Ok(mut s) = state_clone.lock() {
                     s.position = pos.nseconds();
                 }
+                if let Ok(mut s) = subtitles_clone.lock() {
+                    s.update(pos.nseconds() as f64 / 1_000_000_000.0);
+                }
             }
         });
 
@@ -248,12 +599,42 @@ impl DesktopPlayer {
             warn!("Player warning: {}", warning);
         });
 
+        // Auto only implies hardware decoding when some hardware backend exists
+        let expects_hardware = config.hardware_decode && match config.hardware_backend {
+            HardwareBackend::Software => false,
+            HardwareBackend::Auto => available_backends.iter().any(|b| *b != HardwareBackend::Software),
+            _ => true,
+        };
+        let collector = StatsCollector {
+            pipeline: player.pipeline(),
+            sink_pad,
+            fps_sink,
+            frames_seen,
+            state: state.clone(),
+            requested: config.hardware_backend,
+            expects_hardware,
+            analytics: session.analytics().cloned(),
+            runtime: tokio::runtime::Handle::try_current().ok(),
+            reported_fallback: None,
+        };
+        let (stats_stop, stop_rx) = mpsc::channel();
+        let stats_thread = thread::Builder::new()
+            .name("kino-pipeline-stats".into())
+            .spawn(move || collector.run(stop_rx))
+            .context("Failed to start pipeline stats thread")?;
+
         Ok(Self {
             player,
             session,
             config,
             state,
+            frames,
             available_backends,
+            stats_stop: Some(stats_stop),
+            stats_thread: Some(stats_thread),
+            output_sink,
+            window_handle,
+            subtitles,
         })
     }
 
@@ -296,6 +677,7 @@ impl DesktopPlayer {
     /// Seek to position (in nanoseconds)
     pub fn seek(&self, position_ns: u64) {
         self.player.seek(gst::ClockTime::from_nseconds(position_ns));
+        self.seek_subtitles(position_ns);
     }
 
     /// Seek to position (in seconds)
@@ -303,6 +685,128 @@ impl DesktopPlayer {
         self.seek((position_secs * 1_000_000_000.0) as u64);
     }
 
+    /// Seek to position (in nanoseconds) with explicit precision
+    ///
+    /// Keyframe modes are fast but land on a keyframe near the position;
+    /// `Accurate` decodes up to the exact frame. At rates beyond 2x,
+    /// keyframe seeks also let decoders skip to keyframes (trick mode).
+    pub fn seek_precise(&self, position_ns: u64, mode: SeekMode) -> Result<()> {
+        let rate = self.player.rate();
+        let mut flags = gst::SeekFlags::FLUSH | mode.seek_flags();
+        if mode != SeekMode::Accurate && rate.abs() > 2.0 {
+            flags |= gst::SeekFlags::TRICKMODE | gst::SeekFlags::TRICKMODE_KEY_UNITS;
+        }
+
+        // Reverse playback runs from the stop position towards the start
+        let position = Some(gst::ClockTime::from_nseconds(position_ns));
+        let (start, stop_type, stop) = if rate >= 0.0 {
+            (position, gst::SeekType::None, gst::ClockTime::NONE)
+        } else {
+            (Some(gst::ClockTime::ZERO), gst::SeekType::Set, position)
+        };
+
+        debug!("Seeking to {}ns ({:?}, rate {})", position_ns, mode, rate);
+        self.player
+            .pipeline()
+            .seek(rate, flags, gst::SeekType::Set, start, stop_type, stop)
+            .context("Pipeline rejected seek")?;
+        self.seek_subtitles(position_ns);
+        Ok(())
+    }
+
+    /// Clear and re-resolve the displayed cues after a position jump
+    fn seek_subtitles(&self, position_ns: u64) {
+        if let Ok(mut s) = self.subtitles.lock() {
+            s.seek(position_ns as f64 / 1_000_000_000.0);
+        }
+    }
+
+    /// Step one frame forward or backward, pausing playback first
+    ///
+    /// Forward steps use a GStreamer step event. Backward steps seek
+    /// accurately to the previous frame, taken from the frame cache when
+    /// it is adjacent to the current one.
+    pub fn step_frame(&self, direction: StepDirection) -> Result<()> {
+        if self.player_state() == PlayerState::Playing {
+            self.pause();
+        }
+
+        match direction {
+            StepDirection::Forward => {
+                let step = gst::event::Step::new(gst::format::Buffers::from_u64(1), 1.0, true, false);
+                if !self.player.pipeline().send_event(step) {
+                    anyhow::bail!("Pipeline rejected frame step");
+                }
+            }
+            StepDirection::Backward => {
+                let target = self.frames.lock()
+                    .ok()
+                    .and_then(|f| f.previous_pts())
+                    .context("No previous frame to step back to")?;
+                self.seek_precise(target, SeekMode::Accurate)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the frame most recently delivered to the video sink
+    pub fn current_frame_info(&self) -> Option<FrameInfo> {
+        self.frames.lock().ok().and_then(|f| f.current)
+    }
+
+    /// Render video into `window`, moving it from the previous window
+    ///
+    /// The sink is re-targeted in place, so playback continues without
+    /// rebuilding the pipeline. The previous window must stay open until
+    /// this returns.
+    pub fn set_video_window(&self, window: &impl HasWindowHandle) -> Result<()> {
+        let raw = window.window_handle()
+            .map_err(|e| anyhow::anyhow!("Window has no native handle: {}", e))?
+            .as_raw();
+        let handle = match raw {
+            RawWindowHandle::Xlib(h) => h.window as usize,
+            RawWindowHandle::Xcb(h) => h.window.get() as usize,
+            RawWindowHandle::Win32(h) => h.hwnd.get() as usize,
+            RawWindowHandle::AppKit(h) => h.ns_view.as_ptr() as usize,
+            RawWindowHandle::Wayland(h) => h.surface.as_ptr() as usize,
+            other => anyhow::bail!("Unsupported window system: {:?}", other),
+        };
+
+        if let Ok(mut h) = self.window_handle.lock() {
+            *h = Some(handle);
+        }
+
+        // Before the sink exists the bus handler applies the handle instead
+        if let Some(overlay) = self.video_overlay() {
+            // SAFETY: `window` is alive for this call, and callers replace
+            // the handle before closing the window
+            unsafe { overlay.set_window_handle(handle) };
+            overlay.expose();
+        }
+        debug!("Video window set to {:#x}", handle);
+        Ok(())
+    }
+
+    /// Redraw the last frame, e.g. after the video window was resized
+    pub fn expose_video(&self) {
+        if let Some(overlay) = self.video_overlay() {
+            overlay.expose();
+        }
+    }
+
+    /// The overlay interface of the sink, once the real sink was plugged
+    fn video_overlay(&self) -> Option<gst_video::VideoOverlay> {
+        if let Some(overlay) = self.output_sink.dynamic_cast_ref::<gst_video::VideoOverlay>() {
+            return Some(overlay.clone());
+        }
+        self.output_sink
+            .downcast_ref::<gst::Bin>()?
+            .by_interface(gst_video::VideoOverlay::static_type())?
+            .dynamic_cast::<gst_video::VideoOverlay>()
+            .ok()
+    }
+
     /// Set volume (0.0 - 1.0)
     pub fn set_volume(&self, volume: f64) {
         self.player.set_volume(volume.clamp(0.0, 1.0));
@@ -372,15 +876,31 @@ impl DesktopPlayer {
         &self.available_backends
     }
 
+    /// Get hardware backend requested in the configuration
+    pub fn requested_backend(&self) -> HardwareBackend {
+        self.config.hardware_backend
+    }
+
     /// Get current hardware backend being used
+    ///
+    /// This is the backend of the decoder found in the pipeline, or the
+    /// requested one until a decoder has been plugged.
     pub fn current_backend(&self) -> HardwareBackend {
-        self.config.hardware_backend
+        self.state.lock()
+            .ok()
+            .and_then(|s| s.active_backend)
+            .unwrap_or(self.config.hardware_backend)
     }
 
     /// Check if hardware decoding is active
     pub fn is_hardware_accelerated(&self) -> bool {
-        self.config.hardware_decode &&
-            self.available_backends.iter().any(|b| *b != HardwareBackend::Software)
+        match self.state.lock().ok().and_then(|s| s.active_backend) {
+            Some(active) => active != HardwareBackend::Software,
+            None => {
+                self.config.hardware_decode &&
+                    self.available_backends.iter().any(|b| *b != HardwareBackend::Software)
+            }
+        }
     }
 
     /// Get quality metrics
@@ -397,13 +917,21 @@ impl DesktopPlayer {
             } else {
                 None
             },
-            dropped_frames: 0,
-            decoded_frames: 0,
+            dropped_frames: s.as_ref().map(|s| s.dropped_frames).unwrap_or(0),
+            decoded_frames: s.as_ref().map(|s| s.decoded_frames).unwrap_or(0),
             buffer_level: 0.0,
             stall_count: 0,
             stall_duration: 0.0,
             quality_switches: 0,
             throughput: 0,
+            hardware_backend: Some(HardwareBackendInfo {
+                requested: self.config.hardware_backend.display_name().to_string(),
+                active: s.as_ref()
+                    .and_then(|s| s.active_backend)
+                    .map(|b| b.display_name().to_string()),
+                decoder: s.as_ref().and_then(|s| s.decoder.clone()),
+                fallback: s.as_ref().map(|s| s.decoder_fallback).unwrap_or(false),
+            }),
         }
     }
 
@@ -421,6 +949,59 @@ impl DesktopPlayer {
     pub fn set_subtitles_enabled(&mut self, enabled: bool) {
         self.config.subtitles_enabled = enabled;
         self.player.set_subtitle_track_enabled(enabled);
+        if let Ok(mut s) = self.subtitles.lock() {
+            s.set_enabled(enabled);
+        }
+    }
+
+    /// Show a sidecar WebVTT or SRT track, or pass `None` to clear it
+    ///
+    /// Remote tracks are fetched through the session's fetcher; `file:`
+    /// URLs are read from disk.
+    pub async fn set_text_track(&self, track: Option<&TextTrack>) -> Result<()> {
+        let cues = match track {
+            Some(track) => {
+                info!("Loading text track: {} ({})", track.label, track.url);
+                kino_core::load_text_track(self.session.fetcher().as_ref(), track)
+                    .await
+                    .with_context(|| format!("Failed to load text track {}", track.url))?
+            }
+            None => Vec::new(),
+        };
+        self.set_text_cues(cues);
+        Ok(())
+    }
+
+    /// Show already parsed cues as the sidecar text track
+    pub fn set_text_cues(&self, cues: Vec<TextCue>) {
+        let position = self.position_seconds();
+        if let Ok(mut s) = self.subtitles.lock() {
+            debug!("Showing {} cues", cues.len());
+            s.set_cues(cues, position);
+        }
+    }
+
+    /// Cues of the sidecar text track currently on screen
+    pub fn active_cues(&self) -> Vec<TextCue> {
+        self.subtitles.lock()
+            .map(|s| s.active_cues())
+            .unwrap_or_default()
+    }
+
+    /// Set the font scale and background of sidecar subtitles
+    pub fn set_text_track_style(&self, style: SubtitleStyle) -> Result<()> {
+        style.validate()?;
+        if let Ok(mut s) = self.subtitles.lock() {
+            s.set_style(style);
+        }
+        Ok(())
+    }
+
+    /// Current sidecar subtitle style
+    pub fn text_track_style(&self) -> SubtitleStyle {
+        self.subtitles.lock()
+            .map(|s| s.style())
+            .unwrap_or_default()
     }
 
     /// Select subtitle track by index
@@ -437,6 +1018,10 @@ impl DesktopPlayer {
 impl Drop for DesktopPlayer {
     fn drop(&mut self) {
         self.stop();
+        self.stats_stop.take();
+        if let Some(handle) = self.stats_thread.take() {
+            let _ = handle.join();
+        }
     }
 }
 
@@ -0,0 +1,167 @@
+//! Subtitle overlay for sidecar WebVTT and SRT tracks
+//!
+//! Cues come from the kino-core caption parsers and are drawn by a
+//! `textoverlay` element installed as playbin's video filter. The active
+//! cues follow the player's position updates and are re-resolved on seeks.
+//!
+//! Cue settings are honored where `textoverlay` can express them: `align`
+//! and `position` place the text horizontally, and a non-negative `line`
+//! moves it to the top of the frame. `size` and vertical text are ignored.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use kino_core::{ActiveCues, CueAlignment, TextCue, WebVttParser};
+use tracing::debug;
+
+/// Font size in points at a font scale of 1
+const BASE_FONT_SIZE: f64 = 18.0;
+
+/// Subtitle appearance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubtitleStyle {
+    /// Multiplier applied to the default font size
+    pub font_scale: f64,
+    /// Opacity of the box behind the text, from 0 (none) to 1 (opaque)
+    pub background_opacity: f64,
+}
+
+impl Default for SubtitleStyle {
+    fn default() -> Self {
+        Self {
+            font_scale: 1.0,
+            background_opacity: 0.5,
+        }
+    }
+}
+
+impl SubtitleStyle {
+    /// Check the values are in range
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(self.font_scale.is_finite() && self.font_scale > 0.0) {
+            anyhow::bail!("Font scale must be positive, got {}", self.font_scale);
+        }
+        if !(0.0..=1.0).contains(&self.background_opacity) {
+            anyhow::bail!("Background opacity must be between 0 and 1, got {}", self.background_opacity);
+        }
+        Ok(())
+    }
+}
+
+/// Text overlay showing the active cues of the selected track
+pub(crate) struct SubtitleOverlay {
+    /// `textoverlay` element, if the plugin is installed
+    element: Option<gst::Element>,
+    cues: ActiveCues,
+    style: SubtitleStyle,
+    enabled: bool,
+}
+
+impl SubtitleOverlay {
+    pub(crate) fn new(enabled: bool) -> Self {
+        let element = match gst::ElementFactory::make("textoverlay").build() {
+            Ok(element) => Some(element),
+            Err(_) => {
+                debug!("textoverlay unavailable, sidecar subtitles will not be shown");
+                None
+            }
+        };
+        let overlay = Self {
+            element,
+            cues: ActiveCues::default(),
+            style: SubtitleStyle::default(),
+            enabled,
+        };
+        overlay.apply_style();
+        overlay.render();
+        overlay
+    }
+
+    /// Element to install as the pipeline's video filter
+    pub(crate) fn element(&self) -> Option<&gst::Element> {
+        self.element.as_ref()
+    }
+
+    /// Show `cues`, starting from the cues active at `position` (seconds)
+    pub(crate) fn set_cues(&mut self, cues: Vec<TextCue>, position: f64) {
+        self.cues = ActiveCues::new(cues);
+        self.cues.update(position);
+        self.render();
+    }
+
+    /// Cues currently displayed
+    pub(crate) fn active_cues(&self) -> Vec<TextCue> {
+        self.cues.active().into_iter().cloned().collect()
+    }
+
+    pub(crate) fn style(&self) -> SubtitleStyle {
+        self.style
+    }
+
+    pub(crate) fn set_style(&mut self, style: SubtitleStyle) {
+        self.style = style;
+        self.apply_style();
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.render();
+    }
+
+    /// Follow the playback clock
+    pub(crate) fn update(&mut self, position: f64) {
+        if self.cues.update(position) {
+            self.render();
+        }
+    }
+
+    /// Playback jumped to `position`, e.g. after a seek
+    pub(crate) fn seek(&mut self, position: f64) {
+        self.cues.reset();
+        self.update(position);
+    }
+
+    fn apply_style(&self) {
+        let Some(element) = &self.element else { return };
+        let font_size = BASE_FONT_SIZE * self.style.font_scale;
+        element.set_property("font-desc", format!("Sans {:.1}", font_size));
+        let shading = (self.style.background_opacity.clamp(0.0, 1.0) * 255.0).round() as u32;
+        element.set_property("shaded-background", shading > 0);
+        element.set_property("shading-value", shading.max(1));
+    }
+
+    fn render(&self) {
+        let Some(element) = &self.element else { return };
+        let active = self.cues.active();
+
+        // textoverlay parses its text as Pango markup
+        let text = active
+            .iter()
+            .map(|cue| gst::glib::markup_escape_text(&WebVttParser::strip_tags(&cue.text)).to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        element.set_property("silent", !self.enabled || text.is_empty());
+        element.set_property("text", text);
+
+        // The earliest cue decides the layout of the block
+        let settings = active.first().and_then(|cue| cue.settings.as_ref());
+        let align = match settings.and_then(|s| s.align) {
+            Some(CueAlignment::Start | CueAlignment::Left) => "left",
+            Some(CueAlignment::End | CueAlignment::Right) => "right",
+            Some(CueAlignment::Center) | None => "center",
+        };
+        element.set_property_from_str("line-alignment", align);
+        match settings.and_then(|s| s.position) {
+            Some(position) => {
+                element.set_property_from_str("halignment", "position");
+                element.set_property("xpos", (position / 100.0).clamp(0.0, 1.0));
+            }
+            None => element.set_property_from_str("halignment", align),
+        }
+        // Non-negative line numbers count from the top of the frame
+        let valign = match settings.and_then(|s| s.line) {
+            Some(line) if line >= 0.0 => "top",
+            _ => "bottom",
+        };
+        element.set_property_from_str("valignment", valign);
+    }
+}
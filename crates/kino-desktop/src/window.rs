@@ -1,4 +1,17 @@
 //! Window management for desktop player
+//!
+//! The main window and an optional picture-in-picture (PiP) window share a
+//! single pipeline: popping out moves the video sink to the PiP window
+//! instead of rebuilding playback.
+
+use crate::controls::{pip_action_for_key, ControlAction};
+use crate::player::DesktopPlayer;
+use anyhow::{Context, Result};
+use tracing::{debug, info, warn};
+use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
+use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{Window, WindowId, WindowLevel};
 
 /// Video window configuration
 pub struct WindowConfig {
@@ -18,3 +31,259 @@ impl Default for WindowConfig {
         }
     }
 }
+
+/// Picture-in-picture window configuration
+#[derive(Debug, Clone)]
+pub struct PipConfig {
+    pub title: String,
+    /// Initial width in logical pixels
+    pub width: u32,
+    /// Width/height ratio to keep while resizing (video aspect if `None`)
+    pub aspect_ratio: Option<f64>,
+    /// Distance from the screen corner in logical pixels
+    pub margin: u32,
+}
+
+impl Default for PipConfig {
+    fn default() -> Self {
+        Self {
+            title: "Kino".to_string(),
+            width: 480,
+            aspect_ratio: None,
+            margin: 24,
+        }
+    }
+}
+
+/// Smallest PiP width in logical pixels
+const PIP_MIN_WIDTH: f64 = 160.0;
+
+/// Floating always-on-top video window
+struct PipWindow {
+    window: Window,
+    aspect_ratio: f64,
+}
+
+impl PipWindow {
+    /// Snap a resized window back to the aspect ratio, keeping its width
+    fn lock_aspect(&self, size: PhysicalSize<u32>) {
+        let scale = self.window.scale_factor();
+        let logical = size.to_logical::<f64>(scale);
+        let height = logical.width / self.aspect_ratio;
+        if (height - logical.height).abs() * scale > 1.0 {
+            let _ = self.window.request_inner_size(LogicalSize::new(logical.width, height));
+        }
+    }
+
+    /// Keep the window fully on its monitor, e.g. after moving to a
+    /// smaller screen
+    fn keep_on_monitor(&self) {
+        let (Some(monitor), Ok(position)) = (self.window.current_monitor(), self.window.outer_position()) else {
+            return;
+        };
+        let area_pos = monitor.position();
+        let area = monitor.size();
+        let size = self.window.outer_size();
+
+        let max_x = area_pos.x + area.width as i32 - size.width as i32;
+        let max_y = area_pos.y + area.height as i32 - size.height as i32;
+        let clamped = PhysicalPosition::new(
+            position.x.min(max_x).max(area_pos.x),
+            position.y.min(max_y).max(area_pos.y),
+        );
+        if clamped != position {
+            self.window.set_outer_position(clamped);
+        }
+    }
+}
+
+/// Main and picture-in-picture windows around one desktop player
+///
+/// Feed window events from the application's event loop into
+/// `handle_window_event`; it returns the playback actions to apply.
+pub struct WindowManager {
+    /// `None` once the main window was closed while PiP kept playing
+    main: Option<Window>,
+    pip: Option<PipWindow>,
+}
+
+impl WindowManager {
+    /// Create the main window and render the player's video into it
+    pub fn new(event_loop: &ActiveEventLoop, player: &DesktopPlayer, config: &WindowConfig) -> Result<Self> {
+        let mut attributes = Window::default_attributes()
+            .with_title(config.title.clone())
+            .with_inner_size(LogicalSize::new(config.width, config.height));
+        if config.fullscreen {
+            attributes = attributes.with_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+        }
+
+        let main = event_loop.create_window(attributes).context("Failed to create main window")?;
+        player.set_video_window(&main)?;
+
+        Ok(Self { main: Some(main), pip: None })
+    }
+
+    /// Main window, unless it was closed during PiP playback
+    pub fn main_window(&self) -> Option<&Window> {
+        self.main.as_ref()
+    }
+
+    /// Whether video is playing in the PiP window
+    pub fn is_pip_active(&self) -> bool {
+        self.pip.is_some()
+    }
+
+    /// Pop the video out into a borderless, always-on-top window in the
+    /// bottom-right corner of the current monitor
+    pub fn create_pip_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        player: &DesktopPlayer,
+        config: PipConfig,
+    ) -> Result<WindowId> {
+        if let Some(pip) = &self.pip {
+            return Ok(pip.window.id());
+        }
+
+        let aspect_ratio = config.aspect_ratio
+            .or_else(|| match player.video_dimensions() {
+                (w, h) if w > 0 && h > 0 => Some(w as f64 / h as f64),
+                _ => None,
+            })
+            .unwrap_or(16.0 / 9.0);
+        let width = (config.width as f64).max(PIP_MIN_WIDTH);
+        let size = LogicalSize::new(width, width / aspect_ratio);
+
+        let mut attributes = Window::default_attributes()
+            .with_title(config.title)
+            .with_inner_size(size)
+            .with_min_inner_size(LogicalSize::new(PIP_MIN_WIDTH, PIP_MIN_WIDTH / aspect_ratio))
+            .with_decorations(false)
+            .with_resizable(true)
+            .with_window_level(WindowLevel::AlwaysOnTop);
+
+        let monitor = self.main.as_ref()
+            .and_then(|w| w.current_monitor())
+            .or_else(|| event_loop.primary_monitor());
+        if let Some(monitor) = monitor {
+            // Monitor geometry is physical, so scale the logical size and margin
+            let scale = monitor.scale_factor();
+            let physical: PhysicalSize<i32> = size.to_physical(scale);
+            let margin = (config.margin as f64 * scale) as i32;
+            let origin = monitor.position();
+            let area = monitor.size();
+            attributes = attributes.with_position(PhysicalPosition::new(
+                origin.x + area.width as i32 - physical.width - margin,
+                origin.y + area.height as i32 - physical.height - margin,
+            ));
+        }
+
+        let window = event_loop.create_window(attributes).context("Failed to create PiP window")?;
+        player.set_video_window(&window)?;
+
+        let id = window.id();
+        info!(aspect_ratio, "Picture-in-picture started");
+        self.pip = Some(PipWindow { window, aspect_ratio });
+        Ok(id)
+    }
+
+    /// Move the video back to the main window and close the PiP window
+    pub fn restore_to_main(&mut self, player: &DesktopPlayer) -> Result<()> {
+        let main = self.main.as_ref().context("Main window was closed")?;
+        if self.pip.is_none() {
+            return Ok(());
+        }
+
+        player.set_video_window(main)?;
+        // Drop the PiP window only after the sink moved off it
+        self.pip = None;
+        info!("Picture-in-picture ended");
+        Ok(())
+    }
+
+    /// Handle a window event, returning the playback action it triggers
+    pub fn handle_window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        player: &DesktopPlayer,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) -> Option<ControlAction> {
+        if self.pip.as_ref().is_some_and(|p| p.window.id() == window_id) {
+            return self.handle_pip_event(event_loop, player, event);
+        }
+        if self.main.as_ref().is_some_and(|w| w.id() == window_id) {
+            match event {
+                WindowEvent::CloseRequested if self.pip.is_some() => {
+                    // Keep playing in PiP; stop when that closes too
+                    debug!("Main window closed during picture-in-picture");
+                    self.main = None;
+                }
+                WindowEvent::CloseRequested => {
+                    event_loop.exit();
+                    return Some(ControlAction::Stop);
+                }
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => player.expose_video(),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn handle_pip_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        player: &DesktopPlayer,
+        event: WindowEvent,
+    ) -> Option<ControlAction> {
+        let pip = self.pip.as_ref()?;
+        match event {
+            WindowEvent::Resized(size) => {
+                pip.lock_aspect(size);
+                player.expose_video();
+                None
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, mut inner_size_writer } => {
+                // Keep the logical size when moving to a monitor with a
+                // different DPI
+                let logical = pip.window.inner_size().to_logical::<f64>(pip.window.scale_factor());
+                let _ = inner_size_writer.request_inner_size(logical.to_physical(scale_factor));
+                None
+            }
+            WindowEvent::Moved(_) => {
+                pip.keep_on_monitor();
+                None
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } => {
+                // Borderless, so the video itself is the drag handle
+                let _ = pip.window.drag_window();
+                None
+            }
+            WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
+                match pip_action_for_key(&event.logical_key)? {
+                    ControlAction::ClosePip => self.close_pip(event_loop, player),
+                    action => Some(action),
+                }
+            }
+            WindowEvent::CloseRequested => self.close_pip(event_loop, player),
+            _ => None,
+        }
+    }
+
+    /// Close PiP, returning to the main window or ending playback if the
+    /// main window is gone
+    fn close_pip(&mut self, event_loop: &ActiveEventLoop, player: &DesktopPlayer) -> Option<ControlAction> {
+        if self.main.is_some() {
+            if let Err(e) = self.restore_to_main(player) {
+                warn!(error = %e, "Failed to restore video to main window");
+            }
+            return Some(ControlAction::ClosePip);
+        }
+
+        // Nothing left to render into: stop before the window goes away
+        player.stop();
+        self.pip = None;
+        event_loop.exit();
+        Some(ControlAction::Stop)
+    }
+}
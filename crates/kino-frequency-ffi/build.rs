@@ -0,0 +1,25 @@
+//! Generates the C header for the exported functions with cbindgen.
+//!
+//! The header is written to `OUT_DIR`. The `c_abi` integration test checks
+//! it against `include/kino_frequency.h`, the copy C and C++ consumers build
+//! with, so an ABI change also has to update the committed header.
+
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let header = PathBuf::from(env::var("OUT_DIR").unwrap()).join("kino_frequency.h");
+
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).expect("Invalid cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_src(crate_dir.join("src/lib.rs"))
+        .with_config(config)
+        .generate()
+        .expect("Failed to generate the C header")
+        .write_to_file(&header);
+
+    println!("cargo:rustc-env=KINO_FFI_HEADER={}", header.display());
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
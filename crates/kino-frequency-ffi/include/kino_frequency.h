@@ -0,0 +1,213 @@
+/*
+ * Kino frequency analysis, fingerprinting and tagging.
+ *
+ * Handles come from kino_*_new and are released with the matching
+ * kino_*_free. Calls return a KinoStatus; on failure kino_last_error
+ * returns the message for the calling thread. Samples are mono floats and
+ * lengths count samples. Callers allocate every output buffer.
+ *
+ * Thread safety: handles are immutable once created, so one handle may be
+ * used from several threads at once, but must not be freed while in use.
+ */
+
+#ifndef KINO_FREQUENCY_H
+#define KINO_FREQUENCY_H
+
+/* Generated by cbindgen from crates/kino-frequency-ffi; do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Number of band energies in a `KinoAnalysis`: sub-bass, bass, low-mid,
+// mid, high-mid and high.
+#define KINO_BAND_COUNT 6
+
+// Bytes needed for a fingerprint hash: 64 hex digits and a NUL.
+#define KINO_HASH_SIZE 65
+
+// Bytes in a `KinoTag` label, including the NUL.
+#define KINO_TAG_LABEL_SIZE 64
+
+// Result of every call that can fail.
+typedef enum KinoStatus {
+  // Success
+  KINO_STATUS_OK = 0,
+  // A required pointer was NULL
+  KINO_STATUS_NULL_POINTER = 1,
+  // An argument was out of range
+  KINO_STATUS_INVALID_ARGUMENT = 2,
+  // Too few samples for the analysis
+  KINO_STATUS_TOO_SHORT = 3,
+  // The samples contain NaN or infinite values
+  KINO_STATUS_INVALID_SAMPLES = 4,
+  // The samples are silent or DC only
+  KINO_STATUS_ZERO_SIGNAL = 5,
+  // A caller-allocated buffer is too small
+  KINO_STATUS_BUFFER_TOO_SMALL = 6,
+  // Any other failure
+  KINO_STATUS_ERROR = 7,
+  // The library panicked; the handle should not be used again
+  KINO_STATUS_PANIC = 8,
+} KinoStatus;
+
+// Opaque frequency analyzer handle.
+typedef struct KinoAnalyzer KinoAnalyzer;
+
+// Opaque fingerprinter handle.
+typedef struct KinoFingerprinter KinoFingerprinter;
+
+// Opaque content tagger handle.
+typedef struct KinoTagger KinoTagger;
+
+// Spectral features of a clip.
+typedef struct KinoAnalysis {
+  // Spectral centroid in Hz (brightness)
+  float spectral_centroid;
+  // Frequency below which 95% of the energy lies, in Hz
+  float spectral_rolloff;
+  // Spectral flatness, 0 for pure tones to 1 for white noise
+  float spectral_flatness;
+  // Spread around the centroid in Hz
+  float spectral_bandwidth;
+  // Zero crossing rate
+  float zero_crossing_rate;
+  // Peak-to-RMS ratio of the waveform
+  float crest_factor;
+  // Normalized energy per band: sub-bass, bass, low-mid, mid, high-mid, high
+  float band_energies[KINO_BAND_COUNT];
+} KinoAnalysis;
+
+// Metadata of a fingerprint; its hash is written to a separate buffer.
+typedef struct KinoFingerprint {
+  // Fingerprint algorithm version
+  uint32_t version;
+  // Rate the audio was resampled to before fingerprinting
+  uint32_t sample_rate;
+  // Number of constellation points
+  size_t num_points;
+  // Duration of the analyzed audio in seconds
+  double duration_secs;
+  // Too few points to match reliably, e.g. mostly silent audio
+  bool low_entropy;
+} KinoFingerprint;
+
+// A content tag.
+typedef struct KinoTag {
+  // NUL-terminated UTF-8 label, truncated to fit
+  char label[KINO_TAG_LABEL_SIZE];
+  // Confidence from 0 to 1
+  float confidence;
+} KinoTag;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Copy the calling thread's last error message into `buffer` as a
+// NUL-terminated string, truncated to `size` bytes.
+//
+// Returns the size needed for the whole message including the NUL, or 0
+// if the last call on this thread succeeded. `buffer` may be NULL to only
+// query the size.
+//
+// # Safety
+//
+// `buffer` must be NULL or valid for writes of `size` bytes.
+size_t kino_last_error(char *buffer, size_t size);
+
+// Create a frequency analyzer with a Hann window.
+//
+// Returns NULL if `fft_size` is below 2 or `hop_size` is 0. Free with
+// `kino_analyzer_free`.
+struct KinoAnalyzer *kino_analyzer_new(size_t fft_size, size_t hop_size);
+
+// Free an analyzer. NULL is ignored.
+//
+// # Safety
+//
+// `analyzer` must be NULL or a handle from `kino_analyzer_new` that is not
+// used again.
+void kino_analyzer_free(struct KinoAnalyzer *analyzer);
+
+// Analyze `len` mono samples at `sample_rate` into `out`.
+//
+// Fails with `KINO_STATUS_TOO_SHORT` below one FFT window and
+// `KINO_STATUS_INVALID_SAMPLES` for NaN or infinite samples.
+//
+// # Safety
+//
+// `analyzer` must be a live handle, `samples` valid for reads of `len`
+// floats and `out` valid for writes.
+enum KinoStatus kino_analyze(const struct KinoAnalyzer *analyzer,
+                             const float *samples,
+                             size_t len,
+                             uint32_t sample_rate,
+                             struct KinoAnalysis *out);
+
+// Create a constellation fingerprinter. Free with `kino_fingerprinter_free`.
+struct KinoFingerprinter *kino_fingerprinter_new(void);
+
+// Free a fingerprinter. NULL is ignored.
+//
+// # Safety
+//
+// `fingerprinter` must be NULL or a handle from `kino_fingerprinter_new`
+// that is not used again.
+void kino_fingerprinter_free(struct KinoFingerprinter *fingerprinter);
+
+// Fingerprint `len` mono samples at `sample_rate`.
+//
+// Writes the metadata to `out` and the hex hash to `hash`, which must hold
+// at least `KINO_HASH_SIZE` bytes; smaller buffers fail with
+// `KINO_STATUS_BUFFER_TOO_SMALL` before any work is done.
+//
+// # Safety
+//
+// `fingerprinter` must be a live handle, `samples` valid for reads of
+// `len` floats, `out` valid for writes and `hash` valid for writes of
+// `hash_size` bytes.
+enum KinoStatus kino_fingerprint(const struct KinoFingerprinter *fingerprinter,
+                                 const float *samples,
+                                 size_t len,
+                                 uint32_t sample_rate,
+                                 struct KinoFingerprint *out,
+                                 char *hash,
+                                 size_t hash_size);
+
+// Create a content tagger with the default configuration. Free with
+// `kino_tagger_free`.
+struct KinoTagger *kino_tagger_new(void);
+
+// Free a tagger. NULL is ignored.
+//
+// # Safety
+//
+// `tagger` must be NULL or a handle from `kino_tagger_new` that is not
+// used again.
+void kino_tagger_free(struct KinoTagger *tagger);
+
+// Tag `len` mono samples at `sample_rate`.
+//
+// Writes up to `capacity` tags to `tags`, highest confidence first, and
+// the number of tags found to `count`, which may exceed `capacity`. `tags`
+// may be NULL when `capacity` is 0 to only count them.
+//
+// # Safety
+//
+// `tagger` must be a live handle, `samples` valid for reads of `len`
+// floats, `tags` valid for writes of `capacity` tags and `count` valid for
+// writes.
+enum KinoStatus kino_tags(const struct KinoTagger *tagger,
+                          const float *samples,
+                          size_t len,
+                          uint32_t sample_rate,
+                          struct KinoTag *tags,
+                          size_t capacity,
+                          size_t *count);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* KINO_FREQUENCY_H */
@@ -0,0 +1,541 @@
+//! C ABI for Kino frequency analysis.
+//!
+//! Exposes [`FrequencyAnalyzer`], [`Fingerprinter`] and [`ContentTagger`]
+//! to C and C++ through opaque handles and plain result structs, for media
+//! servers that cannot go through the Python bindings. The header
+//! `include/kino_frequency.h` is generated from this file by cbindgen.
+//!
+//! # Conventions
+//!
+//! - Handles come from a `kino_*_new` function and are released with the
+//!   matching `kino_*_free`. Freeing NULL does nothing.
+//! - Functions return a [`KinoStatus`]. On failure the error message is
+//!   kept for the calling thread and read with [`kino_last_error`].
+//! - Samples are mono 32-bit floats, and lengths count samples, not bytes.
+//! - Callers allocate every output buffer, so nothing but the handles ever
+//!   needs freeing.
+//! - Panics are caught at the boundary and reported as
+//!   [`KinoStatus::Panic`]. They never unwind into C.
+//!
+//! # Thread Safety
+//!
+//! Handles are immutable once created. Concurrent calls on one handle from
+//! several threads are safe, but a handle must not be freed while another
+//! thread is still using it. The last error is kept per thread.
+//!
+//! # Example
+//!
+//! ```c
+//! KinoFingerprinter *fingerprinter = kino_fingerprinter_new();
+//! KinoFingerprint fingerprint;
+//! char hash[KINO_HASH_SIZE];
+//! if (kino_fingerprint(fingerprinter, samples, len, 44100, &fingerprint, hash, sizeof hash) != KINO_STATUS_OK) {
+//!     char message[256];
+//!     kino_last_error(message, sizeof message);
+//!     fprintf(stderr, "fingerprint failed: %s\n", message);
+//! }
+//! kino_fingerprinter_free(fingerprinter);
+//! ```
+
+use std::cell::RefCell;
+use std::ffi::c_char;
+use std::panic::{self, AssertUnwindSafe};
+
+use kino_frequency::{AudioData, ContentTagger, Fingerprinter, FrequencyAnalyzer, FrequencyError};
+
+/// Number of band energies in a `KinoAnalysis`: sub-bass, bass, low-mid,
+/// mid, high-mid and high.
+pub const KINO_BAND_COUNT: usize = 6;
+
+/// Bytes needed for a fingerprint hash: 64 hex digits and a NUL.
+pub const KINO_HASH_SIZE: usize = 65;
+
+/// Bytes in a `KinoTag` label, including the NUL.
+pub const KINO_TAG_LABEL_SIZE: usize = 64;
+
+/// Result of every call that can fail.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KinoStatus {
+    /// Success
+    Ok = 0,
+    /// A required pointer was NULL
+    NullPointer = 1,
+    /// An argument was out of range
+    InvalidArgument = 2,
+    /// Too few samples for the analysis
+    TooShort = 3,
+    /// The samples contain NaN or infinite values
+    InvalidSamples = 4,
+    /// The samples are silent or DC only
+    ZeroSignal = 5,
+    /// A caller-allocated buffer is too small
+    BufferTooSmall = 6,
+    /// Any other failure
+    Error = 7,
+    /// The library panicked; the handle should not be used again
+    Panic = 8,
+}
+
+/// Spectral features of a clip.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KinoAnalysis {
+    /// Spectral centroid in Hz (brightness)
+    pub spectral_centroid: f32,
+    /// Frequency below which 95% of the energy lies, in Hz
+    pub spectral_rolloff: f32,
+    /// Spectral flatness, 0 for pure tones to 1 for white noise
+    pub spectral_flatness: f32,
+    /// Spread around the centroid in Hz
+    pub spectral_bandwidth: f32,
+    /// Zero crossing rate
+    pub zero_crossing_rate: f32,
+    /// Peak-to-RMS ratio of the waveform
+    pub crest_factor: f32,
+    /// Normalized energy per band: sub-bass, bass, low-mid, mid, high-mid, high
+    pub band_energies: [f32; KINO_BAND_COUNT],
+}
+
+/// Metadata of a fingerprint; its hash is written to a separate buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KinoFingerprint {
+    /// Fingerprint algorithm version
+    pub version: u32,
+    /// Rate the audio was resampled to before fingerprinting
+    pub sample_rate: u32,
+    /// Number of constellation points
+    pub num_points: usize,
+    /// Duration of the analyzed audio in seconds
+    pub duration_secs: f64,
+    /// Too few points to match reliably, e.g. mostly silent audio
+    pub low_entropy: bool,
+}
+
+/// A content tag.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KinoTag {
+    /// NUL-terminated UTF-8 label, truncated to fit
+    pub label: [c_char; KINO_TAG_LABEL_SIZE],
+    /// Confidence from 0 to 1
+    pub confidence: f32,
+}
+
+/// Opaque frequency analyzer handle.
+pub struct KinoAnalyzer {
+    inner: FrequencyAnalyzer,
+}
+
+/// Opaque fingerprinter handle.
+pub struct KinoFingerprinter {
+    inner: Fingerprinter,
+}
+
+/// Opaque content tagger handle.
+pub struct KinoTagger {
+    inner: ContentTagger,
+}
+
+// The thread-safety guarantees in the docs rest on this
+const _: fn() = || {
+    fn thread_safe<T: Send + Sync>() {}
+    thread_safe::<KinoAnalyzer>();
+    thread_safe::<KinoFingerprinter>();
+    thread_safe::<KinoTagger>();
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// A failed call: its status and the message kept for the thread.
+struct Failure {
+    status: KinoStatus,
+    message: String,
+}
+
+impl Failure {
+    fn new(status: KinoStatus, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+}
+
+impl From<FrequencyError> for Failure {
+    fn from(e: FrequencyError) -> Self {
+        let status = match e {
+            FrequencyError::TooShort { .. } => KinoStatus::TooShort,
+            FrequencyError::InvalidSamples { .. } => KinoStatus::InvalidSamples,
+            FrequencyError::ZeroSignal => KinoStatus::ZeroSignal,
+            _ => KinoStatus::Error,
+        };
+        Self::new(status, e.to_string())
+    }
+}
+
+/// Run the body of an exported function, recording its error for the
+/// thread and catching panics.
+fn guard(f: impl FnOnce() -> Result<(), Failure>) -> KinoStatus {
+    let result = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(Failure::new(KinoStatus::Panic, format!("panic: {}", message)))
+    });
+
+    match result {
+        Ok(()) => {
+            LAST_ERROR.with(|last| *last.borrow_mut() = None);
+            KinoStatus::Ok
+        }
+        Err(failure) => {
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some(failure.message));
+            failure.status
+        }
+    }
+}
+
+/// Run a constructor, recording its error and returning NULL on failure.
+fn construct<T>(f: impl FnOnce() -> Result<T, Failure>) -> *mut T {
+    let mut handle = std::ptr::null_mut();
+    guard(|| {
+        handle = Box::into_raw(Box::new(f()?));
+        Ok(())
+    });
+    handle
+}
+
+/// Borrow a handle, failing on NULL.
+unsafe fn handle<'a, T>(ptr: *const T) -> Result<&'a T, Failure> {
+    ptr.as_ref().ok_or_else(|| Failure::new(KinoStatus::NullPointer, "handle is NULL"))
+}
+
+/// Borrow an output struct, failing on NULL.
+unsafe fn output<'a, T>(ptr: *mut T, name: &str) -> Result<&'a mut T, Failure> {
+    ptr.as_mut().ok_or_else(|| Failure::new(KinoStatus::NullPointer, format!("{} is NULL", name)))
+}
+
+/// Borrow the caller's samples; NULL is only accepted for zero samples.
+unsafe fn samples<'a>(ptr: *const f32, len: usize) -> Result<&'a [f32], Failure> {
+    match (ptr.is_null(), len) {
+        (_, 0) => Ok(&[]),
+        (true, _) => Err(Failure::new(KinoStatus::NullPointer, "samples is NULL")),
+        (false, len) => Ok(std::slice::from_raw_parts(ptr, len)),
+    }
+}
+
+/// Free a handle created by `Box::into_raw`.
+unsafe fn free<T>(ptr: *mut T) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr));
+    }
+}
+
+/// Copy `s` into `buf` as a NUL-terminated string, truncated at a UTF-8
+/// boundary to fit. `buf` must not be empty.
+fn copy_str(s: &str, buf: &mut [c_char]) {
+    let mut len = s.len().min(buf.len() - 1);
+    while !s.is_char_boundary(len) {
+        len -= 1;
+    }
+    for (dst, &src) in buf.iter_mut().zip(&s.as_bytes()[..len]) {
+        *dst = src as c_char;
+    }
+    buf[len] = 0;
+}
+
+/// Copy the calling thread's last error message into `buffer` as a
+/// NUL-terminated string, truncated to `size` bytes.
+///
+/// Returns the size needed for the whole message including the NUL, or 0
+/// if the last call on this thread succeeded. `buffer` may be NULL to only
+/// query the size.
+///
+/// # Safety
+///
+/// `buffer` must be NULL or valid for writes of `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn kino_last_error(buffer: *mut c_char, size: usize) -> usize {
+    LAST_ERROR.with(|last| {
+        let last = last.borrow();
+        let Some(message) = last.as_deref() else {
+            return 0;
+        };
+        if !buffer.is_null() && size > 0 {
+            copy_str(message, std::slice::from_raw_parts_mut(buffer, size));
+        }
+        message.len() + 1
+    })
+}
+
+/// Create a frequency analyzer with a Hann window.
+///
+/// Returns NULL if `fft_size` is below 2 or `hop_size` is 0. Free with
+/// `kino_analyzer_free`.
+#[no_mangle]
+pub extern "C" fn kino_analyzer_new(fft_size: usize, hop_size: usize) -> *mut KinoAnalyzer {
+    construct(|| {
+        if fft_size < 2 || hop_size == 0 {
+            return Err(Failure::new(
+                KinoStatus::InvalidArgument,
+                format!("invalid FFT size {} or hop size {}", fft_size, hop_size),
+            ));
+        }
+        Ok(KinoAnalyzer { inner: FrequencyAnalyzer::new(fft_size, hop_size) })
+    })
+}
+
+/// Free an analyzer. NULL is ignored.
+///
+/// # Safety
+///
+/// `analyzer` must be NULL or a handle from `kino_analyzer_new` that is not
+/// used again.
+#[no_mangle]
+pub unsafe extern "C" fn kino_analyzer_free(analyzer: *mut KinoAnalyzer) {
+    free(analyzer);
+}
+
+/// Analyze `len` mono samples at `sample_rate` into `out`.
+///
+/// Fails with `KINO_STATUS_TOO_SHORT` below one FFT window and
+/// `KINO_STATUS_INVALID_SAMPLES` for NaN or infinite samples.
+///
+/// # Safety
+///
+/// `analyzer` must be a live handle, `samples` valid for reads of `len`
+/// floats and `out` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn kino_analyze(
+    analyzer: *const KinoAnalyzer,
+    samples: *const f32,
+    len: usize,
+    sample_rate: u32,
+    out: *mut KinoAnalysis,
+) -> KinoStatus {
+    guard(|| {
+        let analyzer = handle(analyzer)?;
+        let samples = self::samples(samples, len)?;
+        let out = output(out, "out")?;
+
+        let analysis = analyzer.inner.analyze(samples, sample_rate)?;
+        let mut band_energies = [0.0; KINO_BAND_COUNT];
+        for (dst, &energy) in band_energies.iter_mut().zip(&analysis.band_energies.energies) {
+            *dst = energy;
+        }
+        *out = KinoAnalysis {
+            spectral_centroid: analysis.spectral_centroid,
+            spectral_rolloff: analysis.spectral_rolloff,
+            spectral_flatness: analysis.spectral_flatness,
+            spectral_bandwidth: analysis.spectral_bandwidth,
+            zero_crossing_rate: analysis.zero_crossing_rate,
+            crest_factor: analysis.crest_factor,
+            band_energies,
+        };
+        Ok(())
+    })
+}
+
+/// Create a constellation fingerprinter. Free with `kino_fingerprinter_free`.
+#[no_mangle]
+pub extern "C" fn kino_fingerprinter_new() -> *mut KinoFingerprinter {
+    construct(|| Ok(KinoFingerprinter { inner: Fingerprinter::new() }))
+}
+
+/// Free a fingerprinter. NULL is ignored.
+///
+/// # Safety
+///
+/// `fingerprinter` must be NULL or a handle from `kino_fingerprinter_new`
+/// that is not used again.
+#[no_mangle]
+pub unsafe extern "C" fn kino_fingerprinter_free(fingerprinter: *mut KinoFingerprinter) {
+    free(fingerprinter);
+}
+
+/// Fingerprint `len` mono samples at `sample_rate`.
+///
+/// Writes the metadata to `out` and the hex hash to `hash`, which must hold
+/// at least `KINO_HASH_SIZE` bytes; smaller buffers fail with
+/// `KINO_STATUS_BUFFER_TOO_SMALL` before any work is done.
+///
+/// # Safety
+///
+/// `fingerprinter` must be a live handle, `samples` valid for reads of
+/// `len` floats, `out` valid for writes and `hash` valid for writes of
+/// `hash_size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn kino_fingerprint(
+    fingerprinter: *const KinoFingerprinter,
+    samples: *const f32,
+    len: usize,
+    sample_rate: u32,
+    out: *mut KinoFingerprint,
+    hash: *mut c_char,
+    hash_size: usize,
+) -> KinoStatus {
+    guard(|| {
+        let fingerprinter = handle(fingerprinter)?;
+        let samples = self::samples(samples, len)?;
+        let out = output(out, "out")?;
+        if hash.is_null() {
+            return Err(Failure::new(KinoStatus::NullPointer, "hash is NULL"));
+        }
+        if hash_size < KINO_HASH_SIZE {
+            return Err(Failure::new(
+                KinoStatus::BufferTooSmall,
+                format!("hash buffer holds {} bytes, need {}", hash_size, KINO_HASH_SIZE),
+            ));
+        }
+
+        let fingerprint = fingerprinter.inner.fingerprint(&AudioData::new(samples.to_vec(), sample_rate))?;
+        copy_str(&fingerprint.hash, std::slice::from_raw_parts_mut(hash, hash_size));
+        *out = KinoFingerprint {
+            version: fingerprint.version,
+            sample_rate: fingerprint.sample_rate,
+            num_points: fingerprint.points.len(),
+            duration_secs: fingerprint.duration_secs,
+            low_entropy: fingerprint.low_entropy,
+        };
+        Ok(())
+    })
+}
+
+/// Create a content tagger with the default configuration. Free with
+/// `kino_tagger_free`.
+#[no_mangle]
+pub extern "C" fn kino_tagger_new() -> *mut KinoTagger {
+    construct(|| Ok(KinoTagger { inner: ContentTagger::new() }))
+}
+
+/// Free a tagger. NULL is ignored.
+///
+/// # Safety
+///
+/// `tagger` must be NULL or a handle from `kino_tagger_new` that is not
+/// used again.
+#[no_mangle]
+pub unsafe extern "C" fn kino_tagger_free(tagger: *mut KinoTagger) {
+    free(tagger);
+}
+
+/// Tag `len` mono samples at `sample_rate`.
+///
+/// Writes up to `capacity` tags to `tags`, highest confidence first, and
+/// the number of tags found to `count`, which may exceed `capacity`. `tags`
+/// may be NULL when `capacity` is 0 to only count them.
+///
+/// # Safety
+///
+/// `tagger` must be a live handle, `samples` valid for reads of `len`
+/// floats, `tags` valid for writes of `capacity` tags and `count` valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn kino_tags(
+    tagger: *const KinoTagger,
+    samples: *const f32,
+    len: usize,
+    sample_rate: u32,
+    tags: *mut KinoTag,
+    capacity: usize,
+    count: *mut usize,
+) -> KinoStatus {
+    guard(|| {
+        let tagger = handle(tagger)?;
+        let samples = self::samples(samples, len)?;
+        let count = output(count, "count")?;
+        if tags.is_null() && capacity > 0 {
+            return Err(Failure::new(KinoStatus::NullPointer, "tags is NULL"));
+        }
+
+        let predicted = tagger.inner.predict(&AudioData::new(samples.to_vec(), sample_rate))?;
+        let out = if capacity == 0 { &mut [][..] } else { std::slice::from_raw_parts_mut(tags, capacity) };
+        for (dst, tag) in out.iter_mut().zip(&predicted) {
+            *dst = KinoTag { label: [0; KINO_TAG_LABEL_SIZE], confidence: tag.confidence };
+            copy_str(&tag.label, &mut dst.label);
+        }
+        *count = predicted.len();
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    fn tone(freq: f32, secs: f32, sample_rate: u32) -> Vec<f32> {
+        (0..(secs * sample_rate as f32) as usize)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    fn last_error() -> String {
+        let mut buffer = [0 as c_char; 256];
+        unsafe {
+            kino_last_error(buffer.as_mut_ptr(), buffer.len());
+            CStr::from_ptr(buffer.as_ptr()).to_string_lossy().into_owned()
+        }
+    }
+
+    #[test]
+    fn test_copy_str_truncates_at_char_boundary() {
+        let mut buffer = [1 as c_char; 4];
+        copy_str("héllo", &mut buffer);
+        // "hé" is 3 bytes; the next char does not fit before the NUL
+        assert_eq!(unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap(), "hé");
+
+        let mut tiny = [1 as c_char; 1];
+        copy_str("abc", &mut tiny);
+        assert_eq!(tiny[0], 0);
+    }
+
+    #[test]
+    fn test_errors_are_reported_per_call() {
+        let samples = tone(440.0, 2.0, 44100);
+        let fingerprinter = kino_fingerprinter_new();
+        let mut out = KinoFingerprint::default();
+        let mut hash = [0 as c_char; KINO_HASH_SIZE];
+
+        unsafe {
+            let status = kino_fingerprint(fingerprinter, samples.as_ptr(), samples.len(), 44100, &mut out, hash.as_mut_ptr(), 10);
+            assert_eq!(status, KinoStatus::BufferTooSmall);
+            assert!(last_error().contains("need 65"), "{}", last_error());
+            assert_eq!(kino_last_error(std::ptr::null_mut(), 0), last_error().len() + 1);
+
+            let status = kino_fingerprint(fingerprinter, samples.as_ptr(), 100, 44100, &mut out, hash.as_mut_ptr(), hash.len());
+            assert_eq!(status, KinoStatus::TooShort);
+
+            let status = kino_fingerprint(fingerprinter, samples.as_ptr(), samples.len(), 44100, &mut out, hash.as_mut_ptr(), hash.len());
+            assert_eq!(status, KinoStatus::Ok);
+            assert_eq!(kino_last_error(std::ptr::null_mut(), 0), 0);
+            assert_eq!(CStr::from_ptr(hash.as_ptr()).to_bytes().len(), 64);
+
+            assert_eq!(kino_fingerprint(std::ptr::null(), samples.as_ptr(), 1, 44100, &mut out, hash.as_mut_ptr(), hash.len()), KinoStatus::NullPointer);
+            kino_fingerprinter_free(fingerprinter);
+        }
+        assert!(kino_analyzer_new(0, 512).is_null());
+        assert!(last_error().contains("invalid FFT size"));
+    }
+
+    #[test]
+    fn test_tags_capacity() {
+        let samples = tone(440.0, 3.0, 44100);
+        let tagger = kino_tagger_new();
+        let mut count = 0;
+
+        unsafe {
+            assert_eq!(kino_tags(tagger, samples.as_ptr(), samples.len(), 44100, std::ptr::null_mut(), 0, &mut count), KinoStatus::Ok);
+            assert!(count > 0);
+
+            let mut tags = vec![KinoTag { label: [0; KINO_TAG_LABEL_SIZE], confidence: 0.0 }; count];
+            let mut written = 0;
+            assert_eq!(kino_tags(tagger, samples.as_ptr(), samples.len(), 44100, tags.as_mut_ptr(), 1, &mut written), KinoStatus::Ok);
+            assert_eq!(written, count);
+            assert!(!CStr::from_ptr(tags[0].label.as_ptr()).to_bytes().is_empty());
+            assert_eq!(tags[1].confidence, 0.0);
+            kino_tagger_free(tagger);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+/* Exercises every exported function through the generated header.
+ *
+ * Built and run by tests/c_abi.rs; exits non-zero on the first failed check. */
+
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "kino_frequency.h"
+
+#define SAMPLE_RATE 44100
+#define LEN (3 * SAMPLE_RATE)
+
+#define CHECK(cond)                                                       \
+    do {                                                                  \
+        if (!(cond)) {                                                    \
+            char message[256] = "";                                       \
+            kino_last_error(message, sizeof message);                     \
+            fprintf(stderr, "%s:%d: %s (%s)\n", __FILE__, __LINE__, #cond, \
+                    message);                                             \
+            return 1;                                                     \
+        }                                                                 \
+    } while (0)
+
+int main(void) {
+    static float samples[LEN];
+    for (size_t i = 0; i < LEN; i++) {
+        samples[i] = 0.5f * sinf(2.0f * 3.14159265f * 440.0f * (float)i / SAMPLE_RATE);
+    }
+
+    /* Analysis */
+    KinoAnalyzer *analyzer = kino_analyzer_new(4096, 2048);
+    CHECK(analyzer != NULL);
+    KinoAnalysis analysis;
+    CHECK(kino_analyze(analyzer, samples, LEN, SAMPLE_RATE, &analysis) == KINO_STATUS_OK);
+    CHECK(analysis.spectral_centroid > 200.0f && analysis.spectral_centroid < 2000.0f);
+    float total = 0.0f;
+    for (size_t i = 0; i < KINO_BAND_COUNT; i++) {
+        total += analysis.band_energies[i];
+    }
+    CHECK(total > 0.0f);
+    CHECK(kino_analyze(analyzer, samples, 16, SAMPLE_RATE, &analysis) == KINO_STATUS_TOO_SHORT);
+    CHECK(kino_last_error(NULL, 0) > 1);
+    kino_analyzer_free(analyzer);
+    CHECK(kino_analyzer_new(0, 0) == NULL);
+
+    /* Fingerprinting */
+    KinoFingerprinter *fingerprinter = kino_fingerprinter_new();
+    CHECK(fingerprinter != NULL);
+    KinoFingerprint fingerprint;
+    char hash[KINO_HASH_SIZE];
+    CHECK(kino_fingerprint(fingerprinter, samples, LEN, SAMPLE_RATE, &fingerprint, hash, sizeof hash) == KINO_STATUS_OK);
+    CHECK(strlen(hash) == KINO_HASH_SIZE - 1);
+    CHECK(fingerprint.num_points > 0);
+    CHECK(fabs(fingerprint.duration_secs - 3.0) < 0.01);
+    char small[8];
+    CHECK(kino_fingerprint(fingerprinter, samples, LEN, SAMPLE_RATE, &fingerprint, small, sizeof small) ==
+          KINO_STATUS_BUFFER_TOO_SMALL);
+    kino_fingerprinter_free(fingerprinter);
+
+    /* Tagging */
+    KinoTagger *tagger = kino_tagger_new();
+    CHECK(tagger != NULL);
+    size_t count = 0;
+    CHECK(kino_tags(tagger, samples, LEN, SAMPLE_RATE, NULL, 0, &count) == KINO_STATUS_OK);
+    CHECK(count > 0);
+    KinoTag *tags = calloc(count, sizeof *tags);
+    CHECK(kino_tags(tagger, samples, LEN, SAMPLE_RATE, tags, count, &count) == KINO_STATUS_OK);
+    CHECK(tags[0].label[0] != '\0' && memchr(tags[0].label, '\0', KINO_TAG_LABEL_SIZE) != NULL);
+    CHECK(tags[0].confidence > 0.0f && tags[0].confidence <= 1.0f);
+    free(tags);
+    kino_tagger_free(tagger);
+
+    kino_analyzer_free(NULL);
+    kino_fingerprinter_free(NULL);
+    kino_tagger_free(NULL);
+
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,52 @@
+//! Guards the C ABI: the committed header must match the one cbindgen
+//! generates, and a C program built against it must run.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn committed_header() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("include/kino_frequency.h")
+}
+
+#[test]
+fn test_header_is_up_to_date() {
+    let generated = std::fs::read_to_string(env!("KINO_FFI_HEADER")).unwrap();
+    let committed = std::fs::read_to_string(committed_header()).unwrap_or_default();
+    assert!(
+        generated == committed,
+        "include/kino_frequency.h is out of date; if the ABI change is intended, run\n  cp {} {}",
+        env!("KINO_FFI_HEADER"),
+        committed_header().display()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_c_program() {
+    // target/<profile>/deps/c_abi-<hash> -> target/<profile>, where the cdylib is
+    let lib_dir = std::env::current_exe().unwrap().parent().unwrap().parent().unwrap().to_path_buf();
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/c/smoke.c");
+    let binary = Path::new(env!("CARGO_TARGET_TMPDIR")).join("kino_ffi_smoke");
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let status = Command::new(&compiler)
+        .arg("-std=c99")
+        .args(["-Wall", "-Wextra", "-Werror"])
+        .arg(&source)
+        .arg("-I").arg(committed_header().parent().unwrap())
+        .arg("-L").arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .args(["-lkino_frequency_ffi", "-lm", "-o"])
+        .arg(&binary)
+        .status()
+        .unwrap_or_else(|e| panic!("Failed to run the C compiler {}: {}", compiler, e));
+    assert!(status.success(), "Compiling {} failed", source.display());
+
+    let output = Command::new(&binary).output().unwrap();
+    assert!(
+        output.status.success(),
+        "C smoke test failed:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
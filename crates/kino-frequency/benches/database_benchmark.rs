@@ -0,0 +1,111 @@
+//! Benchmarks comparing in-memory and on-disk fingerprint databases
+//!
+//! Run with: cargo bench -p kino-frequency --bench database_benchmark
+//!
+//! Heap in use after building each database, and the size of the shard files,
+//! are printed before the latency measurements.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use kino_frequency::fingerprint::{DatabaseBackend, FingerprintDatabase, CANONICAL_SAMPLE_RATE, FINGERPRINT_VERSION};
+use kino_frequency::types::{AudioFingerprint, FingerprintAlgorithm, FingerprintPoint};
+
+const FINGERPRINTS: u64 = 100_000;
+const FRAMES: u32 = 24;
+const FLUSH_EVERY: u64 = 10_000;
+const QUERIES: u64 = 100;
+
+/// Allocator that tracks the bytes currently allocated.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// One random peak per frame, about a second of audio at the default hop
+fn synthetic_fingerprint(seed: u64) -> AudioFingerprint {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    let points = (0..FRAMES)
+        .map(|time_offset| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            FingerprintPoint { time_offset, freq_bin: (state % 2048) as u32, amplitude: 128 }
+        })
+        .collect();
+
+    AudioFingerprint {
+        hash: String::new(),
+        version: FINGERPRINT_VERSION,
+        algorithm: FingerprintAlgorithm::Constellation,
+        sample_rate: CANONICAL_SAMPLE_RATE,
+        points,
+        subfingerprints: Vec::new(),
+        chromaprint: None,
+        duration_secs: 0.0,
+        low_entropy: false,
+    }
+}
+
+fn build(backend: DatabaseBackend) -> FingerprintDatabase {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let mut db = FingerprintDatabase::with_backend(backend.clone()).unwrap();
+    for seed in 0..FINGERPRINTS {
+        db.add(&format!("track-{}", seed), &synthetic_fingerprint(seed));
+        if (seed + 1) % FLUSH_EVERY == 0 {
+            db.flush().unwrap();
+        }
+    }
+    db.flush().unwrap();
+
+    let heap = ALLOCATED.load(Ordering::Relaxed).saturating_sub(before);
+    println!("{:?}: {} fingerprints, {:.1} MiB heap", backend, FINGERPRINTS, heap as f64 / (1 << 20) as f64);
+    if let DatabaseBackend::Disk(dir) = &backend {
+        let bytes: u64 = std::fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().metadata().unwrap().len())
+            .sum();
+        println!("{:?}: {:.1} MiB on disk", backend, bytes as f64 / (1 << 20) as f64);
+    }
+    db
+}
+
+fn bench_database_query(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let queries: Vec<AudioFingerprint> = (0..QUERIES)
+        .map(|i| synthetic_fingerprint(i * FINGERPRINTS / QUERIES))
+        .collect();
+
+    let mut group = c.benchmark_group("Fingerprint Database Query");
+    for (name, backend) in [
+        ("memory", DatabaseBackend::Memory),
+        ("disk", DatabaseBackend::Disk(dir.path().to_path_buf())),
+    ] {
+        let db = build(backend);
+        let mut next = 0;
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                next = (next + 1) % queries.len();
+                black_box(db.query(&queries[next], 0.1))
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_database_query);
+criterion_main!(benches);
@@ -0,0 +1,132 @@
+//! Benchmarks comparing brute-force and ANN recommendation lookups
+//!
+//! Run with: cargo bench -p kino-frequency --bench recommend_benchmark
+//!
+//! Recall@10 of the ANN index against the exact scan is printed once per
+//! catalog size before the latency measurements.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
+use kino_frequency::recommend::{RecommendConfig, RecommendationEngine, RecommendationFilter};
+use kino_frequency::types::{BandEnergies, FrequencySignature, SIGNATURE_VERSION};
+
+const QUERIES: usize = 50;
+
+fn next_random(state: &mut u64) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    (*state >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// Clustered 128-dim signatures, roughly what a catalog of similar-genre content
+// looks like. As with real signatures, band energies and spectral shape are
+// derived from the same spectrum as the feature vector.
+fn synthetic_catalog(count: usize) -> Vec<(String, FrequencySignature)> {
+    let mut state = 0x6b696e6f;
+    let centers: Vec<Vec<f32>> = (0..256)
+        .map(|_| (0..128).map(|_| next_random(&mut state)).collect())
+        .collect();
+
+    (0..count)
+        .map(|i| {
+            let features: Vec<f32> = centers[i % centers.len()].iter()
+                .map(|c| c + 0.3 * next_random(&mut state))
+                .collect();
+            (format!("content_{}", i), signature_from_features(features))
+        })
+        .collect()
+}
+
+fn signature_from_features(features: Vec<f32>) -> FrequencySignature {
+    let band = |range: std::ops::Range<usize>| features[range].iter().sum::<f32>();
+    let total: f32 = features.iter().sum::<f32>().max(f32::EPSILON);
+    let weighted: f32 = features.iter().enumerate().map(|(i, f)| i as f32 * f).sum();
+
+    FrequencySignature {
+        version: SIGNATURE_VERSION,
+        band_energies: BandEnergies::standard([
+            band(0..8),
+            band(8..24),
+            band(24..40),
+            band(40..72),
+            band(72..96),
+            band(96..128),
+        ]),
+        centroid: weighted / total * 150.0,
+        flatness: features.iter().cloned().fold(f32::INFINITY, f32::min) / (total / 128.0),
+        bandwidth: 0.0,
+        contrast: Vec::new(),
+        crest_factor: 0.0,
+        features,
+    }
+}
+
+fn build_engine(catalog: &[(String, FrequencySignature)], ann_enabled: bool) -> RecommendationEngine {
+    let engine = RecommendationEngine::with_config(RecommendConfig {
+        ann_enabled,
+        ..RecommendConfig::default()
+    });
+    engine.import_index(catalog.to_vec()).unwrap();
+    engine
+}
+
+fn recall_at_10(exact: &RecommendationEngine, approx: &RecommendationEngine, queries: &[String]) -> f32 {
+    let filter = RecommendationFilter::default();
+    let mut hits = 0;
+    let mut total = 0;
+
+    for id in queries {
+        let expected = exact.get_similar(id, 10, &filter);
+        let actual = approx.get_similar(id, 10, &filter);
+        total += expected.len();
+        hits += expected.iter()
+            .filter(|e| actual.iter().any(|a| a.content_id == e.content_id))
+            .count();
+    }
+
+    hits as f32 / total.max(1) as f32
+}
+
+fn bench_recommendation_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Recommendation Lookup");
+    group.sample_size(10);
+    let filter = RecommendationFilter::default();
+
+    for size in [10_000usize, 50_000, 200_000] {
+        let catalog = synthetic_catalog(size);
+        let queries: Vec<String> = (0..QUERIES)
+            .map(|i| format!("content_{}", i * size / QUERIES))
+            .collect();
+
+        let brute = build_engine(&catalog, false);
+        let ann = build_engine(&catalog, true);
+
+        println!(
+            "catalog {}: ANN recall@10 = {:.3} ({} lists)",
+            size,
+            recall_at_10(&brute, &ann, &queries),
+            ann.ann_index().map_or(0, |index| index.num_lists()),
+        );
+
+        group.bench_with_input(BenchmarkId::new("Brute Force", size), &queries, |b, queries| {
+            let mut i = 0;
+            b.iter(|| {
+                i = (i + 1) % queries.len();
+                black_box(brute.get_similar(&queries[i], 10, &filter))
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("IVF", size), &queries, |b, queries| {
+            let mut i = 0;
+            b.iter(|| {
+                i = (i + 1) % queries.len();
+                black_box(ann.get_similar(&queries[i], 10, &filter))
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_recommendation_lookup);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+//! Benchmarks for multi-threaded spectrogram computation
+//!
+//! Run with: cargo bench -p kino-frequency --features parallel --bench spectrogram_benchmark
+//!
+//! Each clip is transformed on rayon pools of increasing size to show how
+//! compute_spectrogram scales with the number of threads.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use kino_frequency::FrequencyAnalyzer;
+
+const SAMPLE_RATE: u32 = 48000;
+const FFT_SIZE: usize = 4096;
+const HOP_SIZE: usize = 1024;
+
+fn generate_audio(duration_secs: f32) -> Vec<f32> {
+    let num_samples = (SAMPLE_RATE as f32 * duration_secs) as usize;
+    (0..num_samples)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            0.5 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+                + 0.3 * (2.0 * std::f32::consts::PI * 2637.0 * t).sin()
+        })
+        .collect()
+}
+
+fn bench_thread_scaling(c: &mut Criterion) {
+    let analyzer = FrequencyAnalyzer::new(FFT_SIZE, HOP_SIZE);
+    let max_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    for duration in [10.0, 60.0] {
+        let samples = generate_audio(duration);
+        let frames = (samples.len() - FFT_SIZE) / HOP_SIZE + 1;
+
+        let mut group = c.benchmark_group(format!("Spectrogram Scaling ({}s)", duration));
+        group.throughput(Throughput::Elements(frames as u64));
+        group.sample_size(20);
+
+        let mut threads = 1;
+        while threads <= max_threads {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+
+            group.bench_with_input(BenchmarkId::new("threads", threads), &samples, |b, samples| {
+                b.iter(|| pool.install(|| analyzer.compute_spectrogram(black_box(samples)).unwrap()))
+            });
+            threads *= 2;
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_thread_scaling);
+criterion_main!(benches);
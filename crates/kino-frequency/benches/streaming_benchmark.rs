@@ -0,0 +1,108 @@
+//! Benchmarks for the real-time streaming analyzer
+//!
+//! Run with: cargo bench -p kino-frequency --bench streaming_benchmark
+//!
+//! The "Stream Buffering" group isolates frame extraction, comparing the
+//! former per-sample VecDeque copy/pop loop with the contiguous buffer that
+//! StreamAnalyzer now slices and drains once per call.
+
+use std::collections::VecDeque;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use kino_frequency::streaming::StreamAnalyzer;
+
+const SAMPLE_RATE: u32 = 48000;
+const FFT_SIZE: usize = 2048;
+const HOP_SIZE: usize = 512;
+const CHUNK_SIZE: usize = 1024;
+
+fn generate_audio(duration_secs: f32) -> Vec<f32> {
+    let num_samples = (SAMPLE_RATE as f32 * duration_secs) as usize;
+    (0..num_samples)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            0.5 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+                + 0.3 * (2.0 * std::f32::consts::PI * 110.0 * t).sin()
+        })
+        .collect()
+}
+
+fn bench_buffering(c: &mut Criterion) {
+    let samples = generate_audio(1.0);
+    let mut group = c.benchmark_group("Stream Buffering");
+    group.throughput(Throughput::Elements(samples.len() as u64));
+
+    group.bench_function("VecDeque copy + pop_front", |b| {
+        b.iter(|| {
+            let mut buffer: VecDeque<f32> = VecDeque::with_capacity(FFT_SIZE * 2);
+            let mut checksum = 0.0f32;
+            for chunk in samples.chunks(CHUNK_SIZE) {
+                buffer.extend(chunk);
+                while buffer.len() >= FFT_SIZE {
+                    let frame: Vec<f32> = buffer.iter().take(FFT_SIZE).copied().collect();
+                    checksum += frame[FFT_SIZE / 2];
+                    for _ in 0..HOP_SIZE {
+                        buffer.pop_front();
+                    }
+                }
+            }
+            black_box(checksum)
+        });
+    });
+
+    group.bench_function("Contiguous slice + drain", |b| {
+        b.iter(|| {
+            let mut buffer: Vec<f32> = Vec::with_capacity(FFT_SIZE * 2);
+            let mut checksum = 0.0f32;
+            for chunk in samples.chunks(CHUNK_SIZE) {
+                buffer.extend_from_slice(chunk);
+                let mut offset = 0;
+                while buffer.len() - offset >= FFT_SIZE {
+                    let frame = &buffer[offset..offset + FFT_SIZE];
+                    checksum += frame[FFT_SIZE / 2];
+                    offset += HOP_SIZE;
+                }
+                buffer.drain(..offset);
+            }
+            black_box(checksum)
+        });
+    });
+
+    group.finish();
+}
+
+fn bench_stream_analyzer(c: &mut Criterion) {
+    let samples = generate_audio(1.0);
+    let mut group = c.benchmark_group("StreamAnalyzer");
+    group.throughput(Throughput::Elements(samples.len() as u64));
+
+    group.bench_function("process (no callbacks)", |b| {
+        b.iter(|| {
+            let mut analyzer = StreamAnalyzer::new(SAMPLE_RATE, FFT_SIZE);
+            let mut frames = 0;
+            for chunk in samples.chunks(CHUNK_SIZE) {
+                frames += analyzer.process(chunk).len();
+            }
+            black_box(frames)
+        });
+    });
+
+    group.bench_function("process (with callback)", |b| {
+        b.iter(|| {
+            let mut analyzer = StreamAnalyzer::new(SAMPLE_RATE, FFT_SIZE);
+            analyzer.on_event(|event| {
+                black_box(event);
+            });
+            let mut frames = 0;
+            for chunk in samples.chunks(CHUNK_SIZE) {
+                frames += analyzer.process(chunk).len();
+            }
+            black_box(frames)
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_buffering, bench_stream_analyzer);
+criterion_main!(benches);
@@ -107,39 +107,39 @@ fn main() -> Result<()> {
     println!("                     File 1    File 2    Diff");
     println!(
         "  Sub-bass:         {:>7.4}   {:>7.4}   {:>+.4}",
-        bands1.sub_bass,
-        bands2.sub_bass,
-        bands1.sub_bass - bands2.sub_bass
+        bands1.sub_bass(),
+        bands2.sub_bass(),
+        bands1.sub_bass() - bands2.sub_bass()
     );
     println!(
         "  Bass:             {:>7.4}   {:>7.4}   {:>+.4}",
-        bands1.bass,
-        bands2.bass,
-        bands1.bass - bands2.bass
+        bands1.bass(),
+        bands2.bass(),
+        bands1.bass() - bands2.bass()
     );
     println!(
         "  Low-mid:          {:>7.4}   {:>7.4}   {:>+.4}",
-        bands1.low_mid,
-        bands2.low_mid,
-        bands1.low_mid - bands2.low_mid
+        bands1.low_mid(),
+        bands2.low_mid(),
+        bands1.low_mid() - bands2.low_mid()
     );
     println!(
         "  Mid:              {:>7.4}   {:>7.4}   {:>+.4}",
-        bands1.mid,
-        bands2.mid,
-        bands1.mid - bands2.mid
+        bands1.mid(),
+        bands2.mid(),
+        bands1.mid() - bands2.mid()
     );
     println!(
         "  High-mid:         {:>7.4}   {:>7.4}   {:>+.4}",
-        bands1.high_mid,
-        bands2.high_mid,
-        bands1.high_mid - bands2.high_mid
+        bands1.high_mid(),
+        bands2.high_mid(),
+        bands1.high_mid() - bands2.high_mid()
     );
     println!(
         "  High:             {:>7.4}   {:>7.4}   {:>+.4}",
-        bands1.high,
-        bands2.high,
-        bands1.high - bands2.high
+        bands1.high(),
+        bands2.high(),
+        bands1.high() - bands2.high()
     );
 
     Ok(())
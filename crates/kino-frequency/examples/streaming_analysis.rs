@@ -43,6 +43,7 @@ fn main() -> Result<()> {
         beat_threshold: 1.5,
         silence_threshold: 0.01,
         frequency_change_threshold: 100.0,
+        ..Default::default()
     };
 
     // Create analyzer with config
@@ -86,16 +87,19 @@ fn main() -> Result<()> {
     println!("  Avg dominant freq: {:.1} Hz", stats.avg_dominant_frequency);
     println!("  Freq variance:     {:.1}", stats.frequency_variance);
     println!("  Avg centroid:      {:.1} Hz", stats.avg_spectral_centroid);
+    if let Some(bpm) = stats.estimated_bpm {
+        println!("  Estimated tempo:   {:.1} BPM", bpm);
+    }
 
     // Print band energies
     let bands = &stats.avg_band_energies;
     println!("\nAverage Band Energies:");
-    println!("  Sub-bass:  {:.4}", bands.sub_bass);
-    println!("  Bass:      {:.4}", bands.bass);
-    println!("  Low-mid:   {:.4}", bands.low_mid);
-    println!("  Mid:       {:.4}", bands.mid);
-    println!("  High-mid:  {:.4}", bands.high_mid);
-    println!("  High:      {:.4}", bands.high);
+    println!("  Sub-bass:  {:.4}", bands.sub_bass());
+    println!("  Bass:      {:.4}", bands.bass());
+    println!("  Low-mid:   {:.4}", bands.low_mid());
+    println!("  Mid:       {:.4}", bands.mid());
+    println!("  High-mid:  {:.4}", bands.high_mid());
+    println!("  High:      {:.4}", bands.high());
 
     // Print detected events
     let events = events.lock().unwrap();
@@ -109,6 +113,9 @@ fn main() -> Result<()> {
                         timestamp, strength
                     );
                 }
+                AnalysisEvent::TempoChanged { bpm, timestamp, .. } => {
+                    println!("  [{:>6.2}s] Tempo: {:.1} BPM", timestamp, bpm);
+                }
                 AnalysisEvent::SilenceStart { timestamp } => {
                     println!("  [{:>6.2}s] Silence started", timestamp);
                 }
@@ -130,6 +137,15 @@ fn main() -> Result<()> {
                         timestamp, magnitude
                     );
                 }
+                AnalysisEvent::SpeechStart { timestamp } => {
+                    println!("  [{:>6.2}s] Speech started", timestamp);
+                }
+                AnalysisEvent::SpeechEnd { timestamp, duration, confidence } => {
+                    println!(
+                        "  [{:>6.2}s] Speech ended (duration: {:.2}s, confidence: {:.2})",
+                        timestamp, duration, confidence
+                    );
+                }
                 AnalysisEvent::FrameAnalyzed { .. } => {
                     // Skip frame events for brevity
                 }
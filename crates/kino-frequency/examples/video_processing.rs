@@ -9,7 +9,7 @@
 //! ```
 
 use anyhow::Result;
-use kino_frequency::{process_video, ProcessingConfig, ProcessingResult};
+use kino_frequency::{process_video, AudioExtractOptions, ProcessingConfig, ProcessingResult, SamplePolicy, StageTimeouts};
 use std::env;
 
 #[tokio::main]
@@ -37,6 +37,13 @@ async fn main() -> Result<()> {
         enable_tagging: true,
         enable_thumbnail: true,
         enable_signature: true,
+        language_model: None,
+        sample_policy: SamplePolicy::Replace,
+        stage_timeouts: StageTimeouts::default(),
+        // Derived from the fingerprint
+        content_id: None,
+        // Default audio track, whole program
+        audio: AudioExtractOptions::default(),
     };
 
     // Process the video
@@ -60,7 +67,7 @@ fn print_results(result: &ProcessingResult) {
     println!("PROCESSING RESULTS");
     println!("{}", "-".repeat(60));
 
-    println!("\nContent ID: {}", result.content_id);
+    println!("\nContent ID: {} ({:?})", result.content_id, result.content_id_source);
 
     // Fingerprint
     if let Some(ref fp) = result.fingerprint {
@@ -123,18 +130,18 @@ fn print_results(result: &ProcessingResult) {
         println!("\n   Band Energy Distribution:");
         let bands = &sig.band_energies;
         let max_energy = [
-            bands.sub_bass, bands.bass, bands.low_mid,
-            bands.mid, bands.high_mid, bands.high
+            bands.sub_bass(), bands.bass(), bands.low_mid(),
+            bands.mid(), bands.high_mid(), bands.high()
         ].iter().cloned().fold(0.0f32, f32::max);
 
         if max_energy > 0.0 {
             let bar = |energy: f32| "#".repeat(((energy / max_energy) * 30.0) as usize);
-            println!("   Sub-bass:  {}", bar(bands.sub_bass));
-            println!("   Bass:      {}", bar(bands.bass));
-            println!("   Low-mid:   {}", bar(bands.low_mid));
-            println!("   Mid:       {}", bar(bands.mid));
-            println!("   High-mid:  {}", bar(bands.high_mid));
-            println!("   High:      {}", bar(bands.high));
+            println!("   Sub-bass:  {}", bar(bands.sub_bass()));
+            println!("   Bass:      {}", bar(bands.bass()));
+            println!("   Low-mid:   {}", bar(bands.low_mid()));
+            println!("   Mid:       {}", bar(bands.mid()));
+            println!("   High-mid:  {}", bar(bands.high_mid()));
+            println!("   High:      {}", bar(bands.high()));
         }
     }
 }
@@ -0,0 +1,326 @@
+//! Batch processing with bounded concurrency and progress reporting.
+//!
+//! [`process_videos`] runs many files through the same pipeline as
+//! [`process_video`](crate::process_video). Audio extraction (FFmpeg or
+//! in-process decoding, mostly I/O and subprocess bound) and analysis (FFT,
+//! fingerprinting and tagging, CPU bound) are limited separately, so a slow
+//! disk doesn't starve the CPU and a large batch doesn't oversubscribe it.
+//!
+//! ```rust,no_run
+//! use kino_frequency::{process_videos, BatchConcurrency, ProcessingConfig, ProgressEvent};
+//! use tokio_util::sync::CancellationToken;
+//!
+//! # async fn run() {
+//! let results = process_videos(
+//!     ["a.mp4", "b.mp4"],
+//!     ProcessingConfig::default(),
+//!     BatchConcurrency::new(2, 4),
+//!     CancellationToken::new(),
+//!     |event| {
+//!         if let ProgressEvent::Failed { index, error } = event {
+//!             eprintln!("file {} failed: {}", index, error);
+//!         }
+//!     },
+//! )
+//! .await;
+//! # }
+//! ```
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, field::Empty, info, info_span, Instrument, Span};
+
+use crate::telemetry::{FailureKind, StageTimer};
+use crate::{
+    analyze_audio, AnalyzedAudio, AudioAnalyzer, AudioData, FfmpegBackend, ProcessingConfig, ProcessingResult,
+};
+
+/// Limits on how many files are in each stage at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConcurrency {
+    /// Files whose audio is being extracted
+    pub extractions: usize,
+    /// Files being analyzed
+    pub analyses: usize,
+}
+
+impl BatchConcurrency {
+    /// Create limits for both stages; zero is treated as one.
+    pub fn new(extractions: usize, analyses: usize) -> Self {
+        Self { extractions, analyses }
+    }
+}
+
+impl Default for BatchConcurrency {
+    fn default() -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::new(2, cpus)
+    }
+}
+
+/// One input of a batch.
+#[derive(Debug, Clone)]
+pub enum BatchItem {
+    /// A media file, extracted like [`process_video`](crate::process_video)
+    File(PathBuf),
+    /// Audio that is already decoded; skips extraction and thumbnail selection
+    Audio {
+        /// Name reported in [`ProgressEvent::Started`]
+        label: String,
+        /// The audio to analyze
+        audio: AudioData,
+    },
+}
+
+impl BatchItem {
+    /// Human-readable name of the item.
+    pub fn label(&self) -> String {
+        match self {
+            Self::File(path) => path.display().to_string(),
+            Self::Audio { label, .. } => label.clone(),
+        }
+    }
+}
+
+impl From<PathBuf> for BatchItem {
+    fn from(path: PathBuf) -> Self {
+        Self::File(path)
+    }
+}
+
+/// A pipeline stage reported by [`ProgressEvent::StageCompleted`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStage {
+    /// Audio was extracted from the file
+    Extraction,
+    /// Fingerprint, tags, signature and loudness were computed
+    Analysis,
+}
+
+/// Progress of a batch, identified by the item's position in the input.
+///
+/// Serialized with the variant name in a snake_case `type` field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// The item was scheduled
+    Started {
+        /// Input position
+        index: usize,
+        /// File path or audio label
+        label: String,
+    },
+    /// The item finished a stage
+    StageCompleted {
+        /// Input position
+        index: usize,
+        /// Completed stage
+        stage: BatchStage,
+    },
+    /// The item was processed successfully
+    Finished {
+        /// Input position
+        index: usize,
+        /// Content ID of the result
+        content_id: String,
+    },
+    /// The item failed; the rest of the batch carries on
+    Failed {
+        /// Input position
+        index: usize,
+        /// Error description
+        error: String,
+    },
+}
+
+/// Why one item of a batch produced no result.
+#[derive(Debug, thiserror::Error)]
+pub enum ProcessingError {
+    /// Audio could not be extracted from the file.
+    #[error("Audio extraction failed: {0:#}")]
+    Extraction(anyhow::Error),
+
+    /// Analysis of the extracted audio failed.
+    #[error("Analysis failed: {0:#}")]
+    Analysis(anyhow::Error),
+
+    /// The batch was cancelled before the item was scheduled.
+    #[error("Cancelled before processing started")]
+    Cancelled,
+}
+
+impl FailureKind for ProcessingError {
+    fn failure_kind(&self) -> &'static str {
+        match self {
+            Self::Extraction(e) | Self::Analysis(e) => e.failure_kind(),
+            Self::Cancelled => "other",
+        }
+    }
+}
+
+/// Process media files through the complete pipeline, a bounded number at a time.
+///
+/// See [`process_batch`].
+pub async fn process_videos<I, P>(
+    paths: I,
+    config: ProcessingConfig,
+    concurrency: BatchConcurrency,
+    cancel: CancellationToken,
+    progress: impl Fn(ProgressEvent) + Send + Sync + 'static,
+) -> Vec<Result<ProcessingResult, ProcessingError>>
+where
+    I: IntoIterator<Item = P>,
+    P: Into<PathBuf>,
+{
+    let items = paths.into_iter().map(|p| BatchItem::File(p.into()));
+    process_batch(items, config, concurrency, cancel, progress).await
+}
+
+/// Process a batch of files or decoded audio.
+///
+/// Items are scheduled in input order and results are returned in input
+/// order; a failing item doesn't affect the others. Once `cancel` fires no
+/// further items are scheduled, items already in flight run to completion
+/// and the remaining ones report [`ProcessingError::Cancelled`] without any
+/// progress events.
+pub async fn process_batch(
+    items: impl IntoIterator<Item = BatchItem>,
+    config: ProcessingConfig,
+    concurrency: BatchConcurrency,
+    cancel: CancellationToken,
+    progress: impl Fn(ProgressEvent) + Send + Sync + 'static,
+) -> Vec<Result<ProcessingResult, ProcessingError>> {
+    let items: Vec<BatchItem> = items.into_iter().collect();
+    info!(
+        "Processing batch of {} items ({} extractions, {} analyses at a time)",
+        items.len(),
+        concurrency.extractions,
+        concurrency.analyses
+    );
+
+    let extraction_slots = Arc::new(Semaphore::new(concurrency.extractions.max(1)));
+    let analysis_slots = Arc::new(Semaphore::new(concurrency.analyses.max(1)));
+    let config = Arc::new(config);
+    let progress: Arc<dyn Fn(ProgressEvent) + Send + Sync> = Arc::new(progress);
+
+    let mut results: Vec<Option<Result<ProcessingResult, ProcessingError>>> =
+        items.iter().map(|_| None).collect();
+    let mut tasks = JoinSet::new();
+
+    for (index, item) in items.into_iter().enumerate() {
+        let permit = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => break,
+            permit = extraction_slots.clone().acquire_owned() => permit.expect("semaphore is never closed"),
+        };
+        if cancel.is_cancelled() {
+            break;
+        }
+
+        progress(ProgressEvent::Started { index, label: item.label() });
+
+        let analysis_slots = analysis_slots.clone();
+        let config = config.clone();
+        let progress = progress.clone();
+        tasks.spawn(async move {
+            let result = process_item(index, item, permit, analysis_slots, config, &*progress).await;
+            match &result {
+                Ok(result) => progress(ProgressEvent::Finished {
+                    index,
+                    content_id: result.content_id.clone(),
+                }),
+                Err(e) => progress(ProgressEvent::Failed { index, error: e.to_string() }),
+            }
+            (index, result)
+        });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, result)) => results[index] = Some(result),
+            // Tasks only panic if the progress callback does
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    if cancel.is_cancelled() {
+        debug!("Batch cancelled, {} items not scheduled", results.iter().filter(|r| r.is_none()).count());
+    }
+
+    results
+        .into_iter()
+        .map(|result| result.unwrap_or(Err(ProcessingError::Cancelled)))
+        .collect()
+}
+
+/// Extract and analyze one item in a `batch_item` span.
+async fn process_item(
+    index: usize,
+    item: BatchItem,
+    extraction_permit: OwnedSemaphorePermit,
+    analysis_slots: Arc<Semaphore>,
+    config: Arc<ProcessingConfig>,
+    progress: &(dyn Fn(ProgressEvent) + Send + Sync),
+) -> Result<ProcessingResult, ProcessingError> {
+    let span = info_span!("batch_item", content_id = Empty, index, elapsed_ms = Empty);
+    async {
+        let timer = StageTimer::start("batch_item");
+        let result = run_item(index, item, extraction_permit, analysis_slots, config, progress).await;
+        timer.finish(result)
+    }
+    .instrument(span)
+    .await
+}
+
+/// Extract and analyze one item, holding the extraction slot until an
+/// analysis slot is free so decoded audio can't pile up between the stages.
+async fn run_item(
+    index: usize,
+    item: BatchItem,
+    extraction_permit: OwnedSemaphorePermit,
+    analysis_slots: Arc<Semaphore>,
+    config: Arc<ProcessingConfig>,
+    progress: &(dyn Fn(ProgressEvent) + Send + Sync),
+) -> Result<ProcessingResult, ProcessingError> {
+    let (path, audio, analyzed) = match item {
+        BatchItem::File(path) => {
+            let analyzer = AudioAnalyzer::new(config.sample_rate)
+                .with_sample_policy(config.sample_policy)
+                .with_extract_options(config.audio.clone());
+            let selection = analyzer.resolve_audio_options(&path).map_err(ProcessingError::Extraction)?;
+            let audio = analyzer.with_extract_options(selection.clone())
+                .extract_audio(&path)
+                .await
+                .map_err(ProcessingError::Extraction)?;
+            progress(ProgressEvent::StageCompleted { index, stage: BatchStage::Extraction });
+            let analyzed = AnalyzedAudio::new(&selection, &audio);
+            (Some(path), audio, analyzed)
+        }
+        BatchItem::Audio { audio, .. } => {
+            let analyzed = AnalyzedAudio { duration_secs: audio.duration_secs, ..Default::default() };
+            (None, audio, analyzed)
+        }
+    };
+
+    let analysis_permit = analysis_slots.acquire_owned().await.expect("semaphore is never closed");
+    drop(extraction_permit);
+
+    let span = Span::current();
+    let result = tokio::task::spawn_blocking(move || {
+        let _permit = analysis_permit;
+        let _entered = span.enter();
+        analyze_audio(path.as_deref(), &audio, analyzed, &config, Arc::new(FfmpegBackend), &CancellationToken::new())
+    })
+    .await
+    .unwrap_or_else(|e| Err(anyhow::anyhow!("Analysis task panicked: {}", e)))
+    .map_err(ProcessingError::Analysis)?;
+
+    progress(ProgressEvent::StageCompleted { index, stage: BatchStage::Analysis });
+    Ok(result)
+}
@@ -0,0 +1,474 @@
+//! Chapter and ad-break candidates from audio silence and black or static frames.
+//!
+//! A natural break is a moment where the audio goes silent *and* the picture
+//! goes dark or holds still, like the fade to black between acts or before an
+//! ad break. [`BreakDetector`] finds silences with the
+//! [`StreamAnalyzer`](crate::streaming::StreamAnalyzer), samples frames inside
+//! each one and scores how clearly audio and picture agree.
+//!
+//! Two kinds of silence are deliberately not reported:
+//! - **Audio fade-outs** over a lit picture, such as a song ending mid-scene.
+//!   A fade that comes with a fade to black is still a break.
+//! - **End credits**: a break followed only by a short tail, or by video that
+//!   stays dark until the end, would just start a trailing credits chapter.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::streaming::{AnalysisEvent, StreamAnalyzer, StreamConfig};
+use crate::thumbnail::{frame_difference, ThumbnailSelector};
+use crate::types::*;
+
+/// Samples fed to the stream analyzer at a time.
+const STREAM_CHUNK_SIZE: usize = 8192;
+
+/// Silence only needs each frame's level, so frames are short and don't overlap.
+const SILENCE_FRAME_SIZE: usize = 1024;
+
+/// Fraction of the probed frames after a break that must be dark for the
+/// rest of the video to count as end credits.
+const CREDITS_DARK_FRACTION: f32 = 0.8;
+
+/// Configuration for break detection.
+#[derive(Debug, Clone)]
+pub struct BreakConfig {
+    /// Minimum silence length for a break (seconds)
+    pub min_silence_secs: f64,
+    /// RMS level below which audio counts as silent
+    pub silence_threshold: f32,
+    /// Mean luminance (0-1) at which a frame stops counting as dark
+    pub max_luminance: f32,
+    /// Mean absolute difference between frames (0-1) at which the picture stops counting as static
+    pub max_motion: f32,
+    /// Frames sampled across each silence
+    pub frames_per_silence: usize,
+    /// Weight for the silence length in the confidence
+    pub silence_weight: f32,
+    /// Weight for the darkest sampled frame in the confidence
+    pub darkness_weight: f32,
+    /// Weight for the lack of motion in the confidence
+    pub stillness_weight: f32,
+    /// Minimum confidence for a reported break (0-1)
+    pub min_confidence: f32,
+    /// Audio windows compared before a silence to spot fade-outs (seconds)
+    pub fade_window_secs: f64,
+    /// A silence is a fade-out when the level of the window right before it
+    /// is below this fraction of the window before that
+    pub fade_ratio: f32,
+    /// Minimum chapter length: breaks closer than this to the start or to a
+    /// stronger break are dropped (seconds)
+    pub min_chapter_secs: f64,
+    /// Minimum length of the last chapter, so closing credits don't get one (seconds)
+    pub min_trailing_chapter_secs: f64,
+    /// Frames probed after the last break to recognize end credits (0 disables the check)
+    pub credits_probe_frames: usize,
+    /// Mean luminance (0-1) below which a probed frame looks like credits
+    pub credits_max_luminance: f32,
+}
+
+impl Default for BreakConfig {
+    fn default() -> Self {
+        Self {
+            min_silence_secs: 0.5,
+            silence_threshold: 0.01,
+            max_luminance: 0.1,
+            max_motion: 0.02,
+            frames_per_silence: 3,
+            silence_weight: 0.3,
+            darkness_weight: 0.5,
+            stillness_weight: 0.2,
+            min_confidence: 0.6,
+            fade_window_secs: 1.0,
+            fade_ratio: 0.5,
+            min_chapter_secs: 30.0,
+            min_trailing_chapter_secs: 60.0,
+            credits_probe_frames: 5,
+            credits_max_luminance: 0.25,
+        }
+    }
+}
+
+/// A candidate chapter or ad-break point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BreakCandidate {
+    /// Middle of the silence (seconds)
+    pub time: f64,
+    /// Length of the silence (seconds)
+    pub silence_duration: f64,
+    /// How clearly audio and picture agree on a break (0-1)
+    pub confidence: f32,
+}
+
+/// A stretch of silent audio.
+#[derive(Debug, Clone, Copy)]
+struct Silence {
+    start: f64,
+    duration: f64,
+    /// The audio faded into the silence rather than cutting to it
+    faded: bool,
+}
+
+/// Brightness and movement of the frames sampled in a silence.
+#[derive(Debug, Clone, Copy)]
+struct FrameStats {
+    /// Mean luminance of the darkest frame (0-1)
+    luminance: f32,
+    /// Largest mean absolute difference between consecutive frames (0-1)
+    motion: f32,
+}
+
+/// Finds natural breaks where audio silence and dark or static frames coincide.
+pub struct BreakDetector {
+    config: BreakConfig,
+    frames: ThumbnailSelector,
+}
+
+impl BreakDetector {
+    /// Create a new break detector with default configuration.
+    pub fn new() -> Self {
+        Self::with_config(BreakConfig::default())
+    }
+
+    /// Create a detector with custom configuration.
+    pub fn with_config(config: BreakConfig) -> Self {
+        Self {
+            config,
+            frames: ThumbnailSelector::new(),
+        }
+    }
+
+    /// Scan a video for break candidates, ordered by time.
+    ///
+    /// `audio` is the video's extracted audio; frames are sampled with FFmpeg.
+    pub fn detect(&self, video_path: impl AsRef<Path>, audio: &AudioData) -> Result<Vec<BreakCandidate>> {
+        let video_path = video_path.as_ref();
+        info!("Detecting breaks in: {}", video_path.display());
+
+        let duration = self.frames.get_video_duration(video_path)?;
+        let options = self.frames.decode_options(&self.frames.color_info(video_path));
+        let breaks = self.detect_with_frames(audio, duration, |t| self.frames.extract_frame(video_path, t, &options));
+
+        info!("Found {} break candidates", breaks.len());
+        Ok(breaks)
+    }
+
+    /// Break detection with frames supplied by `frame_at(timestamp)`.
+    fn detect_with_frames(
+        &self,
+        audio: &AudioData,
+        duration: f64,
+        mut frame_at: impl FnMut(f64) -> Result<GrayImage>,
+    ) -> Vec<BreakCandidate> {
+        let silences = self.find_silences(audio);
+        debug!("{} silences of at least {:.2}s", silences.len(), self.config.min_silence_secs);
+
+        let candidates = silences.iter()
+            .filter_map(|silence| {
+                let frames = self.sample_frames(silence, &mut frame_at)?;
+                self.score(silence, frames)
+            })
+            .collect();
+        let mut breaks = self.select(candidates, duration);
+
+        // A break that only leads into the end credits isn't a chapter
+        while let Some(last) = breaks.last() {
+            if !self.is_credits(last.time, duration, &mut frame_at) {
+                break;
+            }
+            debug!("Dropping break at {:.2}s before end credits", last.time);
+            breaks.pop();
+        }
+
+        breaks
+    }
+
+    /// Silences of at least `min_silence_secs` that the audio resumes after.
+    fn find_silences(&self, audio: &AudioData) -> Vec<Silence> {
+        let audio = audio.to_mono();
+        let mut analyzer = StreamAnalyzer::with_config(StreamConfig {
+            sample_rate: audio.sample_rate,
+            fft_size: SILENCE_FRAME_SIZE,
+            hop_size: SILENCE_FRAME_SIZE,
+            silence_threshold: self.config.silence_threshold,
+            vad: None,
+            ..Default::default()
+        });
+
+        // A silence still running at the end never reports SilenceEnd
+        let ended = Arc::new(Mutex::new(Vec::new()));
+        let sink = ended.clone();
+        analyzer.on_event(move |event| {
+            if let AnalysisEvent::SilenceEnd { timestamp, duration } = event {
+                sink.lock().unwrap().push((timestamp - duration, duration));
+            }
+        });
+        for chunk in audio.samples.chunks(STREAM_CHUNK_SIZE) {
+            analyzer.process(chunk);
+        }
+
+        let ended = std::mem::take(&mut *ended.lock().unwrap());
+        ended.into_iter()
+            .filter(|&(_, duration)| duration >= self.config.min_silence_secs)
+            .map(|(start, duration)| Silence {
+                start,
+                duration,
+                faded: self.is_fade(&audio, start),
+            })
+            .collect()
+    }
+
+    /// Whether the audio faded into a silence rather than cutting to it.
+    fn is_fade(&self, audio: &AudioData, silence_start: f64) -> bool {
+        let window = self.config.fade_window_secs;
+        let recent = window_rms(audio, silence_start - window, silence_start);
+        let earlier = window_rms(audio, silence_start - 2.0 * window, silence_start - window);
+        earlier > self.config.silence_threshold && recent < earlier * self.config.fade_ratio
+    }
+
+    /// Sample frames evenly across a silence; `None` if none could be extracted.
+    fn sample_frames(
+        &self,
+        silence: &Silence,
+        frame_at: &mut impl FnMut(f64) -> Result<GrayImage>,
+    ) -> Option<FrameStats> {
+        let count = self.config.frames_per_silence.max(1);
+        let frames: Vec<GrayImage> = (0..count)
+            .filter_map(|i| {
+                let timestamp = silence.start + silence.duration * (i as f64 + 0.5) / count as f64;
+                match frame_at(timestamp) {
+                    Ok(frame) => Some(frame),
+                    Err(e) => {
+                        warn!("Failed to extract frame at {:.2}s: {}", timestamp, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        if frames.is_empty() {
+            return None;
+        }
+
+        Some(FrameStats {
+            luminance: frames.iter().map(mean_luminance).fold(f32::INFINITY, f32::min),
+            motion: frames.windows(2).map(|pair| frame_difference(&pair[0], &pair[1])).fold(0.0, f32::max),
+        })
+    }
+
+    /// Score a silence against its frames; `None` below `min_confidence`.
+    fn score(&self, silence: &Silence, frames: FrameStats) -> Option<BreakCandidate> {
+        let config = &self.config;
+        let length = (silence.duration / (2.0 * config.min_silence_secs)).min(1.0) as f32;
+        let darkness = (1.0 - frames.luminance / config.max_luminance).clamp(0.0, 1.0);
+        let stillness = (1.0 - frames.motion / config.max_motion).clamp(0.0, 1.0);
+
+        // An audio fade over a lit picture is a quiet moment, not a break
+        if silence.faded && darkness < 0.5 {
+            debug!("Skipping fade-out at {:.2}s", silence.start);
+            return None;
+        }
+
+        let confidence = length * config.silence_weight
+            + darkness * config.darkness_weight
+            + stillness * config.stillness_weight;
+        debug!(
+            "Silence at {:.2}s ({:.2}s): darkness={:.3}, stillness={:.3}, confidence={:.3}",
+            silence.start, silence.duration, darkness, stillness, confidence
+        );
+
+        (confidence >= config.min_confidence).then(|| BreakCandidate {
+            time: silence.start + silence.duration / 2.0,
+            silence_duration: silence.duration,
+            confidence,
+        })
+    }
+
+    /// Keep the strongest break within each `min_chapter_secs`, away from the
+    /// start and leaving at least `min_trailing_chapter_secs` at the end.
+    fn select(&self, mut candidates: Vec<BreakCandidate>, duration: f64) -> Vec<BreakCandidate> {
+        let config = &self.config;
+        let min_tail = config.min_trailing_chapter_secs.max(config.min_chapter_secs);
+        candidates.retain(|b| b.time >= config.min_chapter_secs && duration - b.time >= min_tail);
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+        let mut selected: Vec<BreakCandidate> = Vec::new();
+        for candidate in candidates {
+            if selected.iter().all(|s| (s.time - candidate.time).abs() >= config.min_chapter_secs) {
+                selected.push(candidate);
+            }
+        }
+
+        selected.sort_by(|a, b| a.time.total_cmp(&b.time));
+        selected
+    }
+
+    /// Whether the video after `time` stays dark, like closing credits.
+    fn is_credits(
+        &self,
+        time: f64,
+        duration: f64,
+        frame_at: &mut impl FnMut(f64) -> Result<GrayImage>,
+    ) -> bool {
+        let count = self.config.credits_probe_frames;
+        let luminances: Vec<f32> = (0..count)
+            .filter_map(|i| frame_at(time + (duration - time) * (i as f64 + 0.5) / count as f64).ok())
+            .map(|frame| mean_luminance(&frame))
+            .collect();
+
+        let dark = luminances.iter().filter(|&&l| l <= self.config.credits_max_luminance).count();
+        !luminances.is_empty() && dark as f32 >= luminances.len() as f32 * CREDITS_DARK_FRACTION
+    }
+}
+
+impl Default for BreakDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// RMS level of mono audio between two timestamps, ignoring non-finite samples.
+fn window_rms(audio: &AudioData, start: f64, end: f64) -> f32 {
+    let rate = audio.sample_rate as f64;
+    let start = ((start.max(0.0) * rate) as usize).min(audio.samples.len());
+    let end = ((end.max(0.0) * rate) as usize).min(audio.samples.len());
+    if start >= end {
+        return 0.0;
+    }
+
+    let energy: f32 = audio.samples[start..end].iter()
+        .filter(|s| s.is_finite())
+        .map(|&s| s * s)
+        .sum();
+    (energy / (end - start) as f32).sqrt()
+}
+
+/// Mean pixel value (0-1).
+fn mean_luminance(frame: &GrayImage) -> f32 {
+    let pixels = frame.as_raw();
+    if pixels.is_empty() {
+        return 0.0;
+    }
+    pixels.iter().map(|&p| p as f32).sum::<f32>() / pixels.len() as f32 / 255.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    const SAMPLE_RATE: u32 = 8000;
+
+    /// A 440 Hz tone shaped by `gain(t)`.
+    fn audio(secs: f64, gain: impl Fn(f64) -> f32) -> AudioData {
+        let samples = (0..(secs * SAMPLE_RATE as f64) as usize)
+            .map(|i| {
+                let t = i as f64 / SAMPLE_RATE as f64;
+                0.5 * gain(t) * (2.0 * std::f64::consts::PI * 440.0 * t).sin() as f32
+            })
+            .collect();
+        AudioData::new(samples, SAMPLE_RATE)
+    }
+
+    /// Silent during the given ranges, full level otherwise.
+    fn silent_during(ranges: &'static [(f64, f64)]) -> impl Fn(f64) -> f32 {
+        move |t| if ranges.iter().any(|&(s, e)| t >= s && t < e) { 0.0 } else { 1.0 }
+    }
+
+    fn black() -> GrayImage {
+        GrayImage::new(32, 18)
+    }
+
+    /// A lit picture that keeps changing over time.
+    fn scene(t: f64) -> GrayImage {
+        let shift = (t * 40.0) as u32;
+        GrayImage::from_fn(32, 18, |x, y| Luma([(40 + (x * 7 + y * 3 + shift) % 180) as u8]))
+    }
+
+    #[test]
+    fn test_black_silence_is_a_break() {
+        let audio = audio(150.0, silent_during(&[(60.0, 61.0)]));
+        let detector = BreakDetector::new();
+        let breaks = detector.detect_with_frames(&audio, 150.0, |t| {
+            Ok(if (60.0..61.0).contains(&t) { black() } else { scene(t) })
+        });
+
+        assert_eq!(breaks.len(), 1, "{:?}", breaks);
+        assert!((breaks[0].time - 60.5).abs() < 0.2, "{:?}", breaks[0]);
+        assert!(breaks[0].silence_duration > 0.8);
+        assert!(breaks[0].confidence > 0.9);
+
+        // The same silence over a moving, lit scene is just a pause
+        assert!(detector.detect_with_frames(&audio, 150.0, |t| Ok(scene(t))).is_empty());
+    }
+
+    #[test]
+    fn test_fade_out_needs_fade_to_black() {
+        // Two-second fade into a silence at 60 s
+        let audio = audio(150.0, |t| match t {
+            t if (58.0..60.0).contains(&t) => ((60.0 - t) / 2.0) as f32,
+            t if (60.0..61.0).contains(&t) => 0.0,
+            _ => 1.0,
+        });
+        let detector = BreakDetector::new();
+        let still = GrayImage::from_pixel(32, 18, Luma([128]));
+
+        // Lit and static would otherwise be scored on silence and stillness alone
+        assert!(detector.detect_with_frames(&audio, 150.0, |_| Ok(still.clone())).is_empty());
+
+        let breaks = detector.detect_with_frames(&audio, 150.0, |t| {
+            Ok(if (60.0..61.0).contains(&t) { black() } else { scene(t) })
+        });
+        assert_eq!(breaks.len(), 1);
+    }
+
+    #[test]
+    fn test_breaks_are_spaced_and_skip_credits() {
+        // Breaks at 40 s, a weaker one at 50 s, 100 s, and before dark credits from 160 s
+        let ranges = &[(40.0, 41.5), (50.0, 50.8), (100.0, 101.0), (160.0, 161.0)];
+        let audio = audio(240.0, silent_during(ranges));
+        let detector = BreakDetector::new();
+        let frame_at = |t: f64| {
+            Ok(if ranges.iter().any(|&(s, e)| t >= s && t < e) {
+                black()
+            } else if t >= 160.0 {
+                // Light text on a dark background
+                GrayImage::from_fn(32, 18, |x, _| Luma([if x % 8 == 0 { 200 } else { 10 }]))
+            } else {
+                scene(t)
+            })
+        };
+
+        let breaks = detector.detect_with_frames(&audio, 240.0, frame_at);
+        assert_eq!(breaks.len(), 2, "{:?}", breaks);
+        assert!((breaks[0].time - 40.75).abs() < 0.2 && (breaks[1].time - 100.5).abs() < 0.2, "{:?}", breaks);
+
+        // Without the credits check the last break is kept
+        let detector = BreakDetector::with_config(BreakConfig {
+            credits_probe_frames: 0,
+            ..Default::default()
+        });
+        assert_eq!(detector.detect_with_frames(&audio, 240.0, frame_at).len(), 3);
+    }
+
+    #[test]
+    fn test_trailing_silence_and_short_tail() {
+        // Silence running into the end, and a break too close to the end
+        let audio = audio(100.0, silent_during(&[(60.0, 61.0), (95.0, 100.0)]));
+        let breaks = BreakDetector::new().detect_with_frames(&audio, 100.0, |_| Ok(black()));
+        assert!(breaks.is_empty(), "{:?}", breaks);
+    }
+
+    #[test]
+    fn test_frame_metrics() {
+        assert_eq!(mean_luminance(&black()), 0.0);
+        assert_eq!(mean_luminance(&GrayImage::from_pixel(4, 4, Luma([255]))), 1.0);
+        assert_eq!(frame_difference(&black(), &black()), 0.0);
+        assert_eq!(frame_difference(&black(), &GrayImage::new(4, 4)), 1.0);
+        assert!(frame_difference(&scene(1.0), &scene(2.0)) > 0.1);
+    }
+}
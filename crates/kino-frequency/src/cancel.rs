@@ -0,0 +1,138 @@
+//! Cooperative cancellation of long-running analysis.
+//!
+//! The `*_cancellable` entry points take a [`CancellationToken`] and check
+//! it between FFT frames, tagging windows and pipeline stages, failing with
+//! [`FrequencyError::Cancelled`] at the first check after it fires. A frame
+//! already being transformed is finished first, so cancellation is observed
+//! within one frame's worth of work; the plain entry points never cancel.
+//!
+//! [`StageTimeouts`] in [`ProcessingConfig`](crate::ProcessingConfig) cancel
+//! pipeline stages that run too long, e.g. on a corrupt file that decodes to
+//! hours of noise.
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::types::{FrequencyError, FrequencyResult};
+
+/// Fail with [`FrequencyError::Cancelled`] once `cancel` has fired.
+pub(crate) fn check(cancel: &CancellationToken) -> FrequencyResult<()> {
+    if cancel.is_cancelled() {
+        Err(FrequencyError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// How long each stage of [`process_video`](crate::process_video) may run
+/// before it is cancelled; `None` lets it run to completion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageTimeouts {
+    /// Audio extraction (FFmpeg or in-process decoding)
+    pub extraction: Option<Duration>,
+    /// Fingerprint generation
+    pub fingerprint: Option<Duration>,
+    /// Content tagging, including language and music analysis
+    pub tagging: Option<Duration>,
+    /// Frequency signature
+    pub signature: Option<Duration>,
+}
+
+/// Token for one stage: a child of the caller's token that is also
+/// cancelled once the stage's timeout elapses.
+pub(crate) struct StageDeadline {
+    token: CancellationToken,
+    timeout: Option<Duration>,
+    /// Dropping it stops the timer thread
+    _done: Option<Sender<()>>,
+}
+
+impl StageDeadline {
+    /// Start timing a stage cancelled with `parent` or after `timeout`.
+    pub(crate) fn start(parent: &CancellationToken, timeout: Option<Duration>) -> Self {
+        let token = parent.child_token();
+        let done = match timeout {
+            Some(timeout) if timeout.is_zero() => {
+                token.cancel();
+                None
+            }
+            Some(timeout) => {
+                let (done, finished) = mpsc::channel::<()>();
+                let expired = token.clone();
+                std::thread::Builder::new()
+                    .name("kino-stage-timeout".to_string())
+                    .spawn(move || {
+                        if finished.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                            expired.cancel();
+                        }
+                    })
+                    .expect("Failed to spawn stage timeout thread");
+                Some(done)
+            }
+            None => None,
+        };
+        Self { token, timeout, _done: done }
+    }
+
+    /// Token the stage checks.
+    pub(crate) fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Attach a timeout message to a stage's error when the timeout, rather
+    /// than the caller, cancelled it.
+    pub(crate) fn finish<T, E: Into<anyhow::Error>>(
+        self,
+        stage: &str,
+        parent: &CancellationToken,
+        result: Result<T, E>,
+    ) -> anyhow::Result<T> {
+        let timed_out = self.token.is_cancelled() && !parent.is_cancelled();
+        result.map_err(|e| match self.timeout {
+            Some(timeout) if timed_out => e.into().context(format!("{} timed out after {:?}", stage, timeout)),
+            _ => e.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_deadline() {
+        let parent = CancellationToken::new();
+
+        let unlimited = StageDeadline::start(&parent, None);
+        assert!(check(unlimited.token()).is_ok());
+
+        // Zero timeouts cancel before the stage starts
+        let expired = StageDeadline::start(&parent, Some(Duration::ZERO));
+        let result = check(expired.token());
+        let err = expired.finish("tagging", &parent, result).unwrap_err();
+        assert!(err.to_string().contains("tagging timed out"), "{}", err);
+        assert!(matches!(err.downcast_ref(), Some(FrequencyError::Cancelled)));
+
+        let timed = StageDeadline::start(&parent, Some(Duration::from_millis(20)));
+        let token = timed.token().clone();
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(token.is_cancelled());
+        drop(timed);
+
+        // Finishing in time stops the timer without cancelling
+        let quick = StageDeadline::start(&parent, Some(Duration::from_millis(50)));
+        let token = quick.token().clone();
+        assert_eq!(quick.finish("fingerprint", &parent, Ok::<_, FrequencyError>(1)).unwrap(), 1);
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(!token.is_cancelled());
+
+        // Cancelled by the caller: no timeout message
+        let stage = StageDeadline::start(&parent, Some(Duration::from_secs(60)));
+        parent.cancel();
+        let result = check(stage.token());
+        let err = stage.finish("fingerprint", &parent, result).unwrap_err();
+        assert_eq!(err.to_string(), "Analysis was cancelled");
+    }
+}
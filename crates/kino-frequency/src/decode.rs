@@ -0,0 +1,288 @@
+//! In-process audio decoding via Symphonia
+//!
+//! Decodes common audio and video containers without shelling out to FFmpeg.
+//! Decoded audio is downmixed to mono and resampled to the analyzer's target
+//! sample rate so it can be fed straight into the analysis pipeline.
+//!
+//! # Codec coverage
+//!
+//! | Container        | Codecs            |
+//! |------------------|-------------------|
+//! | MP4 / M4A / MOV  | AAC-LC            |
+//! | MP3              | MPEG-1/2 Layer III|
+//! | Ogg              | Vorbis            |
+//! | MKV / WebM       | Vorbis, FLAC, PCM |
+//! | FLAC             | FLAC              |
+//! | WAV              | PCM, IEEE float   |
+//!
+//! Opus (in Ogg or WebM), HE-AAC, AC-3 and other codecs Symphonia does not
+//! implement are reported as errors; [`AudioAnalyzer::extract_audio`](crate::AudioAnalyzer::extract_audio)
+//! falls back to FFmpeg for those.
+
+use std::fs::File;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tracing::{debug, warn};
+
+use crate::types::AudioData;
+
+/// Input accepted by [`AudioAnalyzer::decode_audio`](crate::AudioAnalyzer::decode_audio).
+#[derive(Debug, Clone)]
+pub enum AudioSource {
+    /// A file on disk; the extension is used as a format hint
+    Path(PathBuf),
+    /// An in-memory encoded file
+    Bytes(Vec<u8>),
+}
+
+impl From<&Path> for AudioSource {
+    fn from(path: &Path) -> Self {
+        Self::Path(path.to_path_buf())
+    }
+}
+
+impl From<PathBuf> for AudioSource {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<&str> for AudioSource {
+    fn from(path: &str) -> Self {
+        Self::Path(PathBuf::from(path))
+    }
+}
+
+impl From<Vec<u8>> for AudioSource {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for AudioSource {
+    fn from(bytes: &[u8]) -> Self {
+        Self::Bytes(bytes.to_vec())
+    }
+}
+
+/// Decode the first audio track of `source` into mono samples at `target_rate`.
+pub(crate) fn decode(source: AudioSource, target_rate: u32) -> Result<AudioData> {
+    let mut hint = Hint::new();
+    let media: Box<dyn MediaSource> = match source {
+        AudioSource::Path(path) => {
+            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+                hint.with_extension(ext);
+            }
+            let file = File::open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            Box::new(file)
+        }
+        AudioSource::Bytes(bytes) => Box::new(Cursor::new(bytes)),
+    };
+
+    let stream = MediaSourceStream::new(media, Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .context("Unrecognized container format")?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .context("No audio track found")?;
+    let track_id = track.id;
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .context("Unsupported audio codec")?;
+
+    let mut samples = Vec::new();
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut channels = track.codec_params.channels.map(|c| c.count()).unwrap_or(0);
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(e).context("Failed to read packet"),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(e)) => {
+                warn!("Skipping corrupt packet: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e).context("Failed to decode packet"),
+        };
+
+        let spec = *decoded.spec();
+        sample_rate = spec.rate;
+        channels = spec.channels.count();
+
+        // Reuse the conversion buffer unless this packet is larger than any seen so far
+        let frames = decoded.capacity();
+        if !matches!(&buffer, Some(buf) if buf.capacity() >= frames * channels) {
+            buffer = Some(SampleBuffer::new(frames as u64, spec));
+        }
+        let buf = buffer.as_mut().expect("buffer allocated above");
+        buf.copy_interleaved_ref(decoded);
+        samples.extend_from_slice(buf.samples());
+    }
+
+    if samples.is_empty() || sample_rate == 0 {
+        bail!("Stream contains no decodable audio");
+    }
+
+    debug!(
+        "Decoded {} samples ({} channels at {}Hz)",
+        samples.len(),
+        channels,
+        sample_rate
+    );
+
+    let audio = AudioData::with_channels(samples, sample_rate, channels as u32);
+    let mono = audio.to_mono().into_owned();
+
+    if mono.sample_rate == target_rate {
+        return Ok(mono);
+    }
+
+    Ok(AudioData::new(
+        resample_linear(&mono.samples, mono.sample_rate, target_rate),
+        target_rate,
+    ))
+}
+
+/// Resample mono audio with linear interpolation.
+fn resample_linear(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if samples.is_empty() || from_rate == to_rate || to_rate == 0 {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = (samples.len() as f64 / ratio).round() as usize;
+    let last = samples.len() - 1;
+
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f64 * ratio;
+            let idx = (pos.floor() as usize).min(last);
+            let frac = (pos - idx as f64) as f32;
+            let next = samples[(idx + 1).min(last)];
+            samples[idx] + (next - samples[idx]) * frac
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/", $name)).to_vec()
+        };
+    }
+
+    #[test]
+    fn test_resample_linear() {
+        let samples: Vec<f32> = (0..480).map(|i| i as f32).collect();
+
+        let down = resample_linear(&samples, 48000, 16000);
+        assert_eq!(down.len(), 160);
+        assert_eq!(down[1], 3.0);
+
+        let up = resample_linear(&samples, 24000, 48000);
+        assert_eq!(up.len(), 960);
+        assert_eq!(up[3], 1.5);
+    }
+
+    #[test]
+    fn test_decode_wav_bytes() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 22050,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+            for i in 0..2205 {
+                let s = ((i as f32 * 0.1).sin() * 16000.0) as i16;
+                writer.write_sample(s).unwrap();
+                writer.write_sample(s).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+
+        let audio = decode(cursor.into_inner().into(), 44100).unwrap();
+        assert_eq!(audio.channels, 1);
+        assert_eq!(audio.sample_rate, 44100);
+        assert_eq!(audio.len(), 4410);
+        assert!(audio.samples.iter().any(|s| s.abs() > 0.4));
+    }
+
+    #[test]
+    fn test_decode_flac() {
+        let audio = decode(fixture!("tone.flac").into(), 44100).unwrap();
+        assert_eq!(audio.sample_rate, 44100);
+        assert!((audio.duration_secs - 0.1).abs() < 0.01);
+        assert!(audio.samples.iter().any(|s| s.abs() > 0.4));
+    }
+
+    #[test]
+    fn test_decode_mp3() {
+        let audio = decode(fixture!("silence.mp3").into(), 44100).unwrap();
+        assert_eq!(audio.channels, 1);
+        assert!(audio.duration_secs > 0.05);
+        assert!(audio.samples.iter().all(|s| s.abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_decode_mp4_aac() {
+        let audio = decode(fixture!("silence.m4a").into(), 22050).unwrap();
+        assert_eq!(audio.sample_rate, 22050);
+        assert!(audio.duration_secs > 0.05);
+        assert!(audio.samples.iter().all(|s| s.abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_decode_ogg_opus_is_unsupported() {
+        let err = decode(fixture!("silence.opus").into(), 48000).unwrap_err();
+        assert!(err.to_string().contains("Unsupported audio codec"));
+    }
+
+    #[test]
+    fn test_decode_path_hint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tone.flac");
+        std::fs::write(&path, fixture!("tone.flac")).unwrap();
+
+        let audio = decode(path.as_path().into(), 16000).unwrap();
+        assert_eq!(audio.sample_rate, 16000);
+        assert!((audio.duration_secs - 0.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_decode_garbage() {
+        assert!(decode(vec![0u8; 64].into(), 44100).is_err());
+    }
+}
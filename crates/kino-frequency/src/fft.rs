@@ -3,43 +3,153 @@
 //! This module provides the fundamental frequency analysis operations
 //! used throughout the Kino frequency analysis system.
 
-use anyhow::{Result, bail};
-use rustfft::{FftPlanner, num_complex::Complex};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 
+use anyhow::Result;
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use crate::cancel;
+use crate::resample;
 use crate::types::*;
 
+/// Lower edges of the octave bands used for spectral contrast (Hz).
+/// The last band extends to the Nyquist frequency.
+const CONTRAST_BAND_EDGES: [f32; 7] = [0.0, 200.0, 400.0, 800.0, 1600.0, 3200.0, 6400.0];
+
+/// Fraction of each band's bins averaged to estimate its peak and valley.
+const CONTRAST_QUANTILE: f32 = 0.02;
+
 /// Core frequency analyzer using FFT.
 pub struct FrequencyAnalyzer {
     fft_size: usize,
     hop_size: usize,
+    window_function: WindowFunction,
+    /// Window coefficients, pre-scaled by the amplitude correction
     window: Vec<f32>,
+    band_plan: BandPlan,
+    /// Rate input is resampled to before analysis, if any
+    analysis_rate: Option<u32>,
+    /// Last sample rate the band plan was reported as exceeding Nyquist for
+    nyquist_warned: AtomicU32,
 }
 
 impl FrequencyAnalyzer {
-    /// Create a new frequency analyzer.
+    /// Create a new frequency analyzer with a Hann window.
     pub fn new(fft_size: usize, hop_size: usize) -> Self {
-        // Generate Hann window
-        let window: Vec<f32> = (0..fft_size)
-            .map(|i| {
-                0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / (fft_size - 1) as f32).cos())
-            })
+        Self::with_window(fft_size, hop_size, WindowFunction::Hann)
+    }
+
+    /// Create a frequency analyzer using the given window function.
+    pub fn with_window(fft_size: usize, hop_size: usize, window_function: WindowFunction) -> Self {
+        let correction = window_function.amplitude_correction();
+        let window = window_function.generate(fft_size)
+            .into_iter()
+            .map(|w| w * correction)
             .collect();
 
         Self {
             fft_size,
             hop_size,
+            window_function,
             window,
+            band_plan: BandPlan::standard(),
+            analysis_rate: None,
+            nyquist_warned: AtomicU32::new(0),
         }
     }
 
+    /// Use a custom band plan for band energies instead of the standard one.
+    pub fn with_band_plan(mut self, band_plan: BandPlan) -> Self {
+        self.band_plan = band_plan;
+        self
+    }
+
+    /// Resample input to `rate` before analysis when its rate differs by more
+    /// than [`resample::RATE_TOLERANCE`].
+    pub fn with_analysis_rate(mut self, rate: u32) -> Self {
+        self.analysis_rate = Some(rate);
+        self
+    }
+
+    /// Rate input is resampled to before analysis; `None` analyzes at the input rate.
+    pub fn preferred_sample_rate(&self) -> Option<u32> {
+        self.analysis_rate
+    }
+
+    /// FFT window size in samples.
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    /// Window function applied to each frame.
+    pub fn window_function(&self) -> WindowFunction {
+        self.window_function
+    }
+
     /// Perform complete frequency analysis on audio samples.
-    pub fn analyze(&self, samples: &[f32], sample_rate: u32) -> Result<FrequencyAnalysis> {
-        if samples.len() < self.fft_size {
-            bail!("Not enough samples for FFT analysis. Need at least {} samples.", self.fft_size);
+    ///
+    /// Fails with [`FrequencyError::TooShort`] below one FFT window and
+    /// [`FrequencyError::InvalidSamples`] if any sample is NaN or infinite.
+    /// Silent input is analyzed as is; every feature is then zero.
+    pub fn analyze(&self, samples: &[f32], sample_rate: u32) -> FrequencyResult<FrequencyAnalysis> {
+        self.analyze_cancellable(samples, sample_rate, &CancellationToken::new())
+    }
+
+    /// [`analyze`](Self::analyze), checking `cancel` between frames.
+    pub(crate) fn analyze_cancellable(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+        cancel: &CancellationToken,
+    ) -> FrequencyResult<FrequencyAnalysis> {
+        let (samples, sample_rate) = self.conform(samples, sample_rate);
+        self.analyze_at_rate(&samples, sample_rate, cancel)
+    }
+
+    /// Resample to the analysis rate, if one is set and the input is off by more than the tolerance.
+    fn conform<'a>(&self, samples: &'a [f32], sample_rate: u32) -> (Cow<'a, [f32]>, u32) {
+        match self.analysis_rate {
+            Some(rate) if !resample::within_tolerance(sample_rate, rate) => {
+                (Cow::Owned(resample::resample_samples(samples, sample_rate, rate)), rate)
+            }
+            _ => (Cow::Borrowed(samples), sample_rate),
         }
+    }
+
+    /// Warn once per sample rate when bands reach past the Nyquist frequency.
+    ///
+    /// The spectrum ends at Nyquist, so such bands are effectively clamped to
+    /// it and bands starting above it always have zero energy.
+    fn check_band_plan(&self, sample_rate: u32) {
+        let nyquist = sample_rate as f32 / 2.0;
+        let clamped = self.band_plan.bands.iter().filter(|(_, high)| *high > nyquist).count();
+        if clamped == 0 || self.nyquist_warned.swap(sample_rate, Ordering::Relaxed) == sample_rate {
+            return;
+        }
+
+        let empty = self.band_plan.bands.iter().filter(|(low, _)| *low >= nyquist).count();
+        warn!(
+            "Band plan '{}': {} of {} bands extend past the {} Hz Nyquist frequency of {} Hz audio and are clamped to it \
+             ({} lie entirely above it and have zero energy); set an analysis rate to resample",
+            self.band_plan.name,
+            clamped,
+            self.band_plan.len(),
+            nyquist,
+            sample_rate,
+            empty
+        );
+    }
+
+    /// [`analyze`](Self::analyze) for samples already at the analysis rate.
+    fn analyze_at_rate(&self, samples: &[f32], sample_rate: u32, cancel: &CancellationToken) -> FrequencyResult<FrequencyAnalysis> {
+        self.check_band_plan(sample_rate);
 
         // Compute average spectrum across all frames
-        let spectrogram = self.compute_spectrogram(samples)?;
+        let spectrogram = self.compute_spectrogram_cancellable(samples, cancel)?;
 
         // Average spectrum
         let num_frames = spectrogram.len();
@@ -64,8 +174,11 @@ impl FrequencyAnalyzer {
         let spectral_centroid = self.compute_spectral_centroid(&spectrum, &frequencies);
         let spectral_rolloff = self.compute_spectral_rolloff(&spectrum, &frequencies, 0.95);
         let spectral_flatness = self.compute_spectral_flatness(&spectrum);
-        let band_energies = BandEnergies::from_spectrum(&spectrum, &frequencies);
+        let spectral_bandwidth = self.compute_spectral_bandwidth(&spectrum, &frequencies, spectral_centroid);
+        let spectral_contrast = self.compute_spectral_contrast(&spectrum, &frequencies);
+        let band_energies = BandEnergies::from_spectrum_with_plan(&spectrum, &frequencies, &self.band_plan);
         let zero_crossing_rate = self.compute_zcr(samples);
+        let crest_factor = self.compute_crest_factor(samples);
 
         Ok(FrequencyAnalysis {
             spectrum,
@@ -73,43 +186,128 @@ impl FrequencyAnalyzer {
             spectral_centroid,
             spectral_rolloff,
             spectral_flatness,
+            spectral_bandwidth,
+            spectral_contrast,
             band_energies,
             zero_crossing_rate,
+            crest_factor,
         })
     }
 
     /// Compute spectrogram (time-frequency representation).
-    pub fn compute_spectrogram(&self, samples: &[f32]) -> Result<Vec<Vec<f32>>> {
-        let mut planner = FftPlanner::new();
-        let fft = planner.plan_fft_forward(self.fft_size);
+    ///
+    /// With the `parallel` feature, frames are transformed on the rayon
+    /// thread pool; the output is bit-identical to the serial path.
+    pub fn compute_spectrogram(&self, samples: &[f32]) -> FrequencyResult<Vec<Vec<f32>>> {
+        self.compute_spectrogram_cancellable(samples, &CancellationToken::new())
+    }
 
-        let num_frames = (samples.len() - self.fft_size) / self.hop_size + 1;
-        let mut spectrogram = Vec::with_capacity(num_frames);
+    /// [`compute_spectrogram`](Self::compute_spectrogram), checking `cancel`
+    /// before each frame.
+    ///
+    /// Fails with [`FrequencyError::Cancelled`] once it fires; frames
+    /// already being transformed are finished first.
+    pub fn compute_spectrogram_cancellable(
+        &self,
+        samples: &[f32],
+        cancel: &CancellationToken,
+    ) -> FrequencyResult<Vec<Vec<f32>>> {
+        self.spectrogram_checked(samples, |_| cancel::check(cancel))
+    }
 
-        for frame_idx in 0..num_frames {
+    /// Spectrogram calling `check` with each frame's index before computing it.
+    fn spectrogram_checked(
+        &self,
+        samples: &[f32],
+        check: impl Fn(usize) -> FrequencyResult<()> + Sync,
+    ) -> FrequencyResult<Vec<Vec<f32>>> {
+        self.check_samples(samples)?;
+        let fft = self.plan_fft();
+
+        let num_frames = (samples.len() - self.fft_size) / self.hop_size + 1;
+        let frame = |frame_idx: usize| {
             let start = frame_idx * self.hop_size;
-            let frame_samples = &samples[start..start + self.fft_size];
+            &samples[start..start + self.fft_size]
+        };
+
+        #[cfg(feature = "parallel")]
+        let spectrogram = {
+            use rayon::prelude::*;
+
+            (0..num_frames)
+                .into_par_iter()
+                .map_init(
+                    || self.fft_scratch(fft.as_ref()),
+                    |scratch, frame_idx| {
+                        check(frame_idx)?;
+                        Ok(self.frame_spectrum(fft.as_ref(), frame(frame_idx), scratch))
+                    },
+                )
+                .collect()
+        };
+
+        #[cfg(not(feature = "parallel"))]
+        let spectrogram = {
+            let mut scratch = self.fft_scratch(fft.as_ref());
+            (0..num_frames)
+                .map(|frame_idx| {
+                    check(frame_idx)?;
+                    Ok(self.frame_spectrum(fft.as_ref(), frame(frame_idx), &mut scratch))
+                })
+                .collect()
+        };
+
+        spectrogram
+    }
+
+    /// Require at least one full frame of finite samples.
+    fn check_samples(&self, samples: &[f32]) -> FrequencyResult<()> {
+        let required = self.fft_size.max(1);
+        if samples.len() < required || self.hop_size == 0 {
+            return Err(FrequencyError::TooShort { samples: samples.len(), required });
+        }
 
-            // Apply window and convert to complex
-            let mut buffer: Vec<Complex<f32>> = frame_samples
-                .iter()
-                .zip(self.window.iter())
-                .map(|(&s, &w)| Complex::new(s * w, 0.0))
-                .collect();
+        let nan = samples.iter().filter(|s| s.is_nan()).count();
+        let infinite = samples.iter().filter(|s| s.is_infinite()).count();
+        if nan + infinite > 0 {
+            return Err(FrequencyError::InvalidSamples { nan, infinite });
+        }
 
-            // Perform FFT
-            fft.process(&mut buffer);
+        Ok(())
+    }
 
-            // Compute magnitude spectrum (only positive frequencies)
-            let magnitude: Vec<f32> = buffer[..self.fft_size / 2]
-                .iter()
-                .map(|c| (c.re * c.re + c.im * c.im).sqrt() * 2.0 / self.fft_size as f32)
-                .collect();
+    /// Plan the forward FFT applied to each frame.
+    pub(crate) fn plan_fft(&self) -> Arc<dyn Fft<f32>> {
+        FftPlanner::new().plan_fft_forward(self.fft_size)
+    }
 
-            spectrogram.push(magnitude);
-        }
+    /// Scratch buffer for [`frame_spectrum`](Self::frame_spectrum).
+    pub(crate) fn fft_scratch(&self, fft: &dyn Fft<f32>) -> Vec<Complex<f32>> {
+        vec![Complex::default(); fft.get_inplace_scratch_len()]
+    }
+
+    /// Magnitude spectrum (positive frequencies only) of one `fft_size` frame.
+    pub(crate) fn frame_spectrum(
+        &self,
+        fft: &dyn Fft<f32>,
+        frame_samples: &[f32],
+        scratch: &mut [Complex<f32>],
+    ) -> Vec<f32> {
+        // Apply window and convert to complex
+        let mut buffer: Vec<Complex<f32>> = frame_samples
+            .iter()
+            .zip(self.window.iter())
+            .map(|(&s, &w)| Complex::new(s * w, 0.0))
+            .collect();
+
+        // Perform FFT
+        fft.process_with_scratch(&mut buffer, scratch);
 
-        Ok(spectrogram)
+        // Compute magnitude spectrum (only positive frequencies)
+        buffer[..self.fft_size / 2]
+            .iter()
+            .map(|c| (c.re * c.re + c.im * c.im).sqrt() * 2.0 / self.fft_size as f32)
+            .collect()
     }
 
     /// Find dominant frequencies in the audio.
@@ -118,7 +316,7 @@ impl FrequencyAnalyzer {
         samples: &[f32],
         sample_rate: u32,
         top_k: usize,
-    ) -> Result<Vec<DominantFrequency>> {
+    ) -> FrequencyResult<Vec<DominantFrequency>> {
         let analysis = self.analyze(samples, sample_rate)?;
 
         // Find peaks in spectrum
@@ -128,10 +326,10 @@ impl FrequencyAnalyzer {
             .map(|(i, &mag)| (i, mag))
             .collect();
 
-        indexed.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        indexed.sort_by(|a, b| b.1.total_cmp(&a.1));
 
-        // Normalize magnitudes
-        let max_mag = indexed.first().map(|(_, m)| *m).unwrap_or(1.0);
+        // Normalize magnitudes; silence has no peak to normalize by
+        let max_mag = indexed.first().map(|(_, m)| *m).filter(|&m| m > 0.0).unwrap_or(1.0);
 
         let dominant: Vec<DominantFrequency> = indexed
             .into_iter()
@@ -148,56 +346,32 @@ impl FrequencyAnalyzer {
     }
 
     /// Compute a compact frequency signature for similarity matching.
-    pub fn compute_signature(&self, samples: &[f32], sample_rate: u32) -> Result<FrequencySignature> {
-        let analysis = self.analyze(samples, sample_rate)?;
-
-        // Create mel-scale inspired binning (128 features)
-        let num_features = 128;
-        let min_freq = 20.0f32;
-        let max_freq = (sample_rate / 2) as f32;
-
-        // Log-spaced frequency bins
-        let bin_edges: Vec<f32> = (0..=num_features)
-            .map(|i| {
-                let t = i as f32 / num_features as f32;
-                min_freq * (max_freq / min_freq).powf(t)
-            })
-            .collect();
-
-        let mut features = vec![0.0f32; num_features];
-
-        for i in 0..num_features {
-            let low = bin_edges[i];
-            let high = bin_edges[i + 1];
-
-            let mut energy = 0.0f32;
-            let mut count = 0;
-
-            for (j, &freq) in analysis.frequencies.iter().enumerate() {
-                if freq >= low && freq < high {
-                    energy += analysis.spectrum[j];
-                    count += 1;
-                }
-            }
+    pub fn compute_signature(&self, samples: &[f32], sample_rate: u32) -> FrequencyResult<FrequencySignature> {
+        self.compute_signature_cancellable(samples, sample_rate, &CancellationToken::new())
+    }
 
-            if count > 0 {
-                features[i] = energy / count as f32;
-            }
-        }
+    /// [`compute_signature`](Self::compute_signature), checking `cancel` between frames.
+    pub(crate) fn compute_signature_cancellable(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+        cancel: &CancellationToken,
+    ) -> FrequencyResult<FrequencySignature> {
+        let (samples, sample_rate) = self.conform(samples, sample_rate);
+        let analysis = self.analyze_at_rate(&samples, sample_rate, cancel)?;
 
-        // Normalize features
-        let max_feature = features.iter().cloned().fold(0.0f32, f32::max);
-        if max_feature > 0.0 {
-            for f in &mut features {
-                *f /= max_feature;
-            }
-        }
+        // Log-spaced binning shared with the WASM signature
+        let features = kino_tagging::signature_features(&analysis.spectrum, &analysis.frequencies, sample_rate);
 
         Ok(FrequencySignature {
+            version: SIGNATURE_VERSION,
             features,
             band_energies: analysis.band_energies,
             centroid: analysis.spectral_centroid,
             flatness: analysis.spectral_flatness,
+            bandwidth: analysis.spectral_bandwidth,
+            contrast: analysis.spectral_contrast,
+            crest_factor: analysis.crest_factor,
         })
     }
 
@@ -253,6 +427,60 @@ impl FrequencyAnalyzer {
         }
     }
 
+    /// Compute spectral bandwidth (magnitude-weighted spread around the centroid).
+    fn compute_spectral_bandwidth(&self, spectrum: &[f32], frequencies: &[f32], centroid: f32) -> f32 {
+        let total_mag: f32 = spectrum.iter().sum();
+        if total_mag <= 0.0 {
+            return 0.0;
+        }
+
+        let variance: f32 = spectrum.iter()
+            .zip(frequencies.iter())
+            .map(|(&mag, &freq)| mag * (freq - centroid).powi(2))
+            .sum::<f32>() / total_mag;
+
+        variance.sqrt()
+    }
+
+    /// Compute spectral contrast (peak-to-valley ratio in dB) for each octave band.
+    fn compute_spectral_contrast(&self, spectrum: &[f32], frequencies: &[f32]) -> Vec<f32> {
+        (0..CONTRAST_BAND_EDGES.len())
+            .map(|band| {
+                let low = CONTRAST_BAND_EDGES[band];
+                let high = CONTRAST_BAND_EDGES.get(band + 1).copied().unwrap_or(f32::INFINITY);
+
+                // The DC bin is skipped; it carries offset rather than spectral shape
+                let mut mags: Vec<f32> = spectrum.iter()
+                    .zip(frequencies.iter())
+                    .filter(|&(_, &freq)| freq > low && freq < high)
+                    .map(|(&mag, _)| mag)
+                    .collect();
+                if mags.is_empty() {
+                    return 0.0;
+                }
+                mags.sort_by(f32::total_cmp);
+
+                let k = ((mags.len() as f32 * CONTRAST_QUANTILE).round() as usize).max(1);
+                let valley = mags[..k].iter().sum::<f32>() / k as f32;
+                let peak = mags[mags.len() - k..].iter().sum::<f32>() / k as f32;
+
+                20.0 * ((peak + 1e-10) / (valley + 1e-10)).log10()
+            })
+            .collect()
+    }
+
+    /// Compute crest factor (peak amplitude over RMS).
+    fn compute_crest_factor(&self, samples: &[f32]) -> f32 {
+        let peak = samples.iter().fold(0.0f32, |max, &s| max.max(s.abs()));
+        let rms = (samples.iter().map(|&s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+
+        if rms > 0.0 {
+            peak / rms
+        } else {
+            0.0
+        }
+    }
+
     /// Compute zero crossing rate.
     fn compute_zcr(&self, samples: &[f32]) -> f32 {
         let crossings: usize = samples.windows(2)
@@ -263,6 +491,9 @@ impl FrequencyAnalyzer {
     }
 
     /// Apply a bandpass filter to extract specific frequency range.
+    ///
+    /// Signals longer than [`FILTER_BLOCK_SIZE`] are filtered in blocks, so
+    /// memory use stays bounded on long files.
     pub fn bandpass_filter(
         &self,
         samples: &[f32],
@@ -270,40 +501,13 @@ impl FrequencyAnalyzer {
         low_freq: f32,
         high_freq: f32,
     ) -> Result<Vec<f32>> {
-        let mut planner = FftPlanner::new();
-        let fft_forward = planner.plan_fft_forward(samples.len());
-        let fft_inverse = planner.plan_fft_inverse(samples.len());
-
-        // Forward FFT
-        let mut buffer: Vec<Complex<f32>> = samples
-            .iter()
-            .map(|&s| Complex::new(s, 0.0))
-            .collect();
-        fft_forward.process(&mut buffer);
-
-        // Apply bandpass filter in frequency domain
-        let freq_resolution = sample_rate as f32 / samples.len() as f32;
-        for (i, c) in buffer.iter_mut().enumerate() {
-            let freq = if i <= samples.len() / 2 {
-                i as f32 * freq_resolution
-            } else {
-                (samples.len() - i) as f32 * freq_resolution
-            };
-
-            if freq < low_freq || freq > high_freq {
-                *c = Complex::new(0.0, 0.0);
-            }
-        }
-
-        // Inverse FFT
-        fft_inverse.process(&mut buffer);
-
-        // Normalize and extract real part
-        let scale = 1.0 / samples.len() as f32;
-        Ok(buffer.iter().map(|c| c.re * scale).collect())
+        Ok(filter_bins(samples, sample_rate, |freq, _| freq >= low_freq && freq <= high_freq))
     }
 
     /// Project signal onto top-K dominant frequencies.
+    ///
+    /// The dominant frequencies are found over the whole signal; long
+    /// signals are then filtered in blocks like [`bandpass_filter`](Self::bandpass_filter).
     pub fn project_to_dominant(
         &self,
         samples: &[f32],
@@ -311,51 +515,93 @@ impl FrequencyAnalyzer {
         top_k: usize,
     ) -> Result<Vec<f32>> {
         let dominant = self.dominant_frequencies(samples, sample_rate, top_k)?;
+        let dominant_freqs: Vec<f32> = dominant.iter().map(|d| d.frequency_hz).collect();
+
+        // Keep the bins close to a dominant frequency
+        Ok(filter_bins(samples, sample_rate, |freq, freq_resolution| {
+            dominant_freqs.iter().any(|&dom_freq| (freq - dom_freq).abs() < freq_resolution)
+        }))
+    }
+}
+
+/// Longest signal filtered with a single FFT. Longer signals are split into
+/// Hann-windowed blocks of this size at 50% overlap, each filtered on its own
+/// and overlap-added back together.
+pub const FILTER_BLOCK_SIZE: usize = 1 << 16;
 
-        let mut planner = FftPlanner::new();
+/// Zero every frequency bin `keep(frequency, bin_width)` rejects.
+fn filter_bins(samples: &[f32], sample_rate: u32, keep: impl Fn(f32, f32) -> bool) -> Vec<f32> {
+    let mut planner = FftPlanner::new();
+    if samples.len() <= FILTER_BLOCK_SIZE {
         let fft_forward = planner.plan_fft_forward(samples.len());
         let fft_inverse = planner.plan_fft_inverse(samples.len());
+        let mut buffer: Vec<Complex<f32>> = samples.iter().map(|&s| Complex::new(s, 0.0)).collect();
+        filter_block(&mut buffer, sample_rate, &keep, &*fft_forward, &*fft_inverse);
+        return buffer.iter().map(|c| c.re).collect();
+    }
 
-        // Forward FFT
-        let mut buffer: Vec<Complex<f32>> = samples
-            .iter()
-            .map(|&s| Complex::new(s, 0.0))
-            .collect();
-        fft_forward.process(&mut buffer);
-
-        // Keep only dominant frequency bins
-        let freq_resolution = sample_rate as f32 / samples.len() as f32;
-        let dominant_freqs: Vec<f32> = dominant.iter().map(|d| d.frequency_hz).collect();
+    let size = FILTER_BLOCK_SIZE;
+    let hop = size / 2;
+    let fft_forward = planner.plan_fft_forward(size);
+    let fft_inverse = planner.plan_fft_inverse(size);
+
+    // A periodic Hann window at 50% overlap sums to exactly one, so the
+    // blocks reassemble the signal. Starting half a block early gives the
+    // first samples their second window too.
+    let window: Vec<f32> = (0..size)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / size as f32).cos())
+        .collect();
+    let mut output = vec![0.0f32; samples.len()];
+    let mut buffer = vec![Complex::new(0.0, 0.0); size];
+
+    let mut start = -(hop as isize);
+    while start < samples.len() as isize {
+        for (i, c) in buffer.iter_mut().enumerate() {
+            let sample = usize::try_from(start + i as isize).ok().and_then(|n| samples.get(n));
+            *c = Complex::new(sample.map_or(0.0, |&s| s * window[i]), 0.0);
+        }
+        filter_block(&mut buffer, sample_rate, &keep, &*fft_forward, &*fft_inverse);
 
-        let mut mask = vec![false; buffer.len()];
-        for (i, _) in buffer.iter().enumerate() {
-            let freq = if i <= samples.len() / 2 {
-                i as f32 * freq_resolution
-            } else {
-                (samples.len() - i) as f32 * freq_resolution
-            };
-
-            // Check if this bin is close to a dominant frequency
-            for &dom_freq in &dominant_freqs {
-                if (freq - dom_freq).abs() < freq_resolution {
-                    mask[i] = true;
-                    break;
-                }
+        for (i, c) in buffer.iter().enumerate() {
+            if let Some(out) = usize::try_from(start + i as isize).ok().and_then(|n| output.get_mut(n)) {
+                *out += c.re;
             }
         }
+        start += hop as isize;
+    }
 
-        // Zero out non-dominant frequencies
-        for (i, c) in buffer.iter_mut().enumerate() {
-            if !mask[i] {
-                *c = Complex::new(0.0, 0.0);
-            }
+    output
+}
+
+/// Filter one block in place, leaving the normalized result in the real parts.
+fn filter_block(
+    buffer: &mut [Complex<f32>],
+    sample_rate: u32,
+    keep: &impl Fn(f32, f32) -> bool,
+    fft_forward: &dyn Fft<f32>,
+    fft_inverse: &dyn Fft<f32>,
+) {
+    let len = buffer.len();
+    fft_forward.process(buffer);
+
+    let freq_resolution = sample_rate as f32 / len as f32;
+    for (i, c) in buffer.iter_mut().enumerate() {
+        let freq = if i <= len / 2 {
+            i as f32 * freq_resolution
+        } else {
+            (len - i) as f32 * freq_resolution
+        };
+
+        if !keep(freq, freq_resolution) {
+            *c = Complex::new(0.0, 0.0);
         }
+    }
 
-        // Inverse FFT
-        fft_inverse.process(&mut buffer);
+    fft_inverse.process(buffer);
 
-        let scale = 1.0 / samples.len() as f32;
-        Ok(buffer.iter().map(|c| c.re * scale).collect())
+    let scale = 1.0 / len as f32;
+    for c in buffer.iter_mut() {
+        c.re *= scale;
     }
 }
 
@@ -443,6 +689,46 @@ mod tests {
         assert!(high_analysis.spectral_centroid > low_analysis.spectral_centroid);
     }
 
+    #[test]
+    fn test_spectral_contrast_noise_vs_harmonic() {
+        let sample_rate = 44100;
+
+        // White noise from a fixed-seed LCG
+        let mut state = 0x2545_f491u32;
+        let noise: Vec<f32> = (0..sample_rate as usize * 4)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+            })
+            .collect();
+
+        // 220 Hz tone with ten harmonics
+        let harmonic: Vec<f32> = (0..sample_rate as usize)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                (1..=10)
+                    .map(|k| (2.0 * std::f32::consts::PI * 220.0 * k as f32 * t).sin() / k as f32)
+                    .sum::<f32>()
+            })
+            .collect();
+
+        let analyzer = FrequencyAnalyzer::new(2048, 1024);
+        let noise_analysis = analyzer.analyze(&noise, sample_rate).unwrap();
+        let harmonic_analysis = analyzer.analyze(&harmonic, sample_rate).unwrap();
+
+        let mean = |v: &[f32]| v.iter().sum::<f32>() / v.len() as f32;
+        assert_eq!(noise_analysis.spectral_contrast.len(), CONTRAST_BAND_EDGES.len());
+        assert!(mean(&noise_analysis.spectral_contrast) < 3.0, "{:?}", noise_analysis.spectral_contrast);
+        assert!(mean(&harmonic_analysis.spectral_contrast) > 30.0, "{:?}", harmonic_analysis.spectral_contrast);
+
+        // Noise spreads energy across the band and has a higher crest factor than a sine
+        assert!(noise_analysis.spectral_bandwidth > harmonic_analysis.spectral_bandwidth);
+        let sine = generate_sine_wave(440.0, sample_rate, 1.0);
+        let sine_analysis = analyzer.analyze(&sine, sample_rate).unwrap();
+        assert!((sine_analysis.crest_factor - std::f32::consts::SQRT_2).abs() < 0.01);
+        assert!(noise_analysis.crest_factor > sine_analysis.crest_factor);
+    }
+
     #[test]
     fn test_frequency_signature_similarity() {
         let sample_rate = 44100;
@@ -465,6 +751,102 @@ mod tests {
         assert!(sig1.similarity(&sig3) < sig1.similarity(&sig2));
     }
 
+    #[test]
+    fn test_analysis_rate_normalizes_input() {
+        let analyzer = FrequencyAnalyzer::new(4096, 2048).with_analysis_rate(44100);
+        let reference = analyzer.compute_signature(&generate_sine_wave(440.0, 44100, 1.0), 44100).unwrap();
+
+        // A 48 kHz recording is analyzed on the same frequency grid
+        let analysis = analyzer.analyze(&generate_sine_wave(440.0, 48000, 1.0), 48000).unwrap();
+        assert!((analysis.frequencies[1] - 44100.0 / 4096.0).abs() < 1e-3);
+
+        let resampled = analyzer.compute_signature(&generate_sine_wave(440.0, 48000, 1.0), 48000).unwrap();
+        assert!(reference.try_similarity(&resampled).unwrap() > 0.99);
+    }
+
+    #[test]
+    fn test_spectrogram_matches_frame_by_frame() {
+        let samples: Vec<f32> = generate_sine_wave(440.0, 44100, 2.0)
+            .iter()
+            .zip(generate_sine_wave(3100.0, 44100, 2.0))
+            .map(|(a, b)| a + 0.5 * b)
+            .collect();
+        let analyzer = FrequencyAnalyzer::new(1024, 256);
+
+        let fft = analyzer.plan_fft();
+        let mut scratch = analyzer.fft_scratch(fft.as_ref());
+        let serial: Vec<Vec<f32>> = samples
+            .windows(1024)
+            .step_by(256)
+            .map(|frame| analyzer.frame_spectrum(fft.as_ref(), frame, &mut scratch))
+            .collect();
+
+        // Bit-identical, including with the `parallel` feature
+        let spectrogram = analyzer.compute_spectrogram(&samples).unwrap();
+        assert_eq!(spectrogram.len(), serial.len());
+        for (a, b) in spectrogram.iter().zip(&serial) {
+            assert!(a.iter().zip(b).all(|(x, y)| x.to_bits() == y.to_bits()));
+        }
+    }
+
+    #[test]
+    fn test_spectrogram_cancellation() {
+        let samples = generate_sine_wave(440.0, 44100, 2.0);
+        let analyzer = FrequencyAnalyzer::new(1024, 256);
+        let cancel = CancellationToken::new();
+        assert_eq!(
+            analyzer.compute_spectrogram_cancellable(&samples, &cancel).unwrap(),
+            analyzer.compute_spectrogram(&samples).unwrap()
+        );
+
+        // Cancelled while frame 10 is transformed: frame 11 is never started
+        let checks = std::sync::atomic::AtomicUsize::new(0);
+        let result = analyzer.spectrogram_checked(&samples, |frame| {
+            checks.fetch_add(1, Ordering::SeqCst);
+            cancel::check(&cancel)?;
+            if frame == 10 {
+                cancel.cancel();
+            }
+            Ok(())
+        });
+        assert!(matches!(result, Err(FrequencyError::Cancelled)));
+        #[cfg(not(feature = "parallel"))]
+        assert_eq!(checks.load(Ordering::SeqCst), 12);
+        #[cfg(feature = "parallel")]
+        assert!(checks.load(Ordering::SeqCst) < (samples.len() - 1024) / 256 + 1);
+
+        assert!(matches!(analyzer.compute_spectrogram_cancellable(&samples, &cancel), Err(FrequencyError::Cancelled)));
+    }
+
+    #[test]
+    fn test_window_coherent_gain() {
+        let n = 4096;
+        let expected = [
+            (WindowFunction::Hann, 0.5),
+            (WindowFunction::Hamming, 0.54),
+            (WindowFunction::BlackmanHarris, 0.35875),
+            (WindowFunction::FlatTop, 0.2156),
+            (WindowFunction::Rectangular, 1.0),
+        ];
+
+        // Full-scale sine centered on bin 64
+        let sample_rate = 44100;
+        let freq = 64.0 * sample_rate as f32 / n as f32;
+        let sine = generate_sine_wave(freq, sample_rate, 0.5);
+
+        for (window, gain) in expected {
+            let coefficients = window.generate(n);
+            let mean = coefficients.iter().sum::<f32>() / n as f32;
+            assert!((mean - gain).abs() < 1e-3, "{:?}: coherent gain {}", window, mean);
+            assert!((window.coherent_gain() - gain).abs() < 1e-3);
+
+            // After correction every window reports the tone at Hann's level
+            let analyzer = FrequencyAnalyzer::with_window(n, n, window);
+            let spectrum = &analyzer.compute_spectrogram(&sine[..n]).unwrap()[0];
+            assert!((spectrum[64] - 0.5).abs() < 2e-3, "{:?}: peak {}", window, spectrum[64]);
+        }
+    }
+
     #[test]
     fn test_bandpass_filter() {
         let sample_rate = 44100;
@@ -488,4 +870,27 @@ mod tests {
         // Dominant should be close to 200 Hz
         assert!((dominant[0].frequency_hz - 200.0).abs() < 30.0);
     }
+
+    #[test]
+    fn test_bandpass_filter_long_signal_in_blocks() {
+        let sample_rate = 44100;
+        let tone = |freq: f32, i: usize| (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).sin();
+
+        // Several blocks, ending part-way through one
+        let len = FILTER_BLOCK_SIZE * 3 + 1234;
+        let samples: Vec<f32> = (0..len).map(|i| tone(200.0, i) + tone(2000.0, i)).collect();
+
+        let analyzer = FrequencyAnalyzer::new(4096, 2048);
+        let filtered = analyzer.bandpass_filter(&samples, sample_rate, 150.0, 250.0).unwrap();
+        assert_eq!(filtered.len(), len);
+
+        // The 200 Hz tone comes back whole, block seams included
+        let error = filtered.iter().enumerate()
+            .map(|(i, &s)| (s - tone(200.0, i)).powi(2))
+            .sum::<f32>() / len as f32;
+        assert!(error.sqrt() < 0.02, "RMS error {}", error.sqrt());
+
+        let projected = analyzer.project_to_dominant(&samples, sample_rate, 2).unwrap();
+        assert_eq!(projected.len(), len);
+    }
 }
@@ -6,32 +6,127 @@
 //!
 //! # Algorithm Overview
 //!
-//! 1. Compute spectrogram of audio
-//! 2. Find spectral peaks in each time frame
-//! 3. Create constellation map of peaks
-//! 4. Generate hash pairs from peak combinations
-//! 5. Produce final SHA-256 fingerprint hash
+//! 1. Resample to [`CANONICAL_SAMPLE_RATE`] so peaks land on the same
+//!    frequency bins regardless of the source rate
+//! 2. Compute spectrogram of audio
+//! 3. Find spectral peaks in each time frame
+//! 4. Create constellation map of peaks
+//! 5. Generate hash pairs from peak combinations
+//! 6. Produce final SHA-256 fingerprint hash
+//!
+//! Version 1 fingerprints skipped the resampling step, so their hashes depend
+//! on the source sample rate and cannot be matched against version 2.
 //!
 //! # On-Chain Verification
 //!
 //! The fingerprint hash can be stored on Solana for decentralized content
 //! verification, ensuring creator ownership without centralized control.
-
-use std::collections::HashMap;
-use anyhow::Result;
+//!
+//! # Exact vs Perceptual Verification
+//!
+//! [`Fingerprinter::verify`] compares hashes, so it only succeeds for audio
+//! that fingerprints identically: the same decoded samples, or a lossless
+//! copy. It is cheap, needs nothing but the 32-byte hash, and any change to
+//! the audio fails it, which is what a tamper check wants.
+//!
+//! Transcoding (a different encoder, bitrate or resampler) moves a few
+//! spectral peaks and changes the hash even though the content is the same.
+//! [`Fingerprinter::verify_perceptual`] instead matches the constellation
+//! against a stored reference and accepts it above a similarity threshold,
+//! reporting how much of the audio lined up. It survives re-encoding but
+//! needs the reference points rather than just the hash, and a low
+//! threshold can accept similar-sounding but different content.
+//!
+//! Full constellations are too large to anchor on-chain, so anchor records
+//! carry a [`ConstellationSummary`]: a fixed number of landmark hashes
+//! chosen by value, which transcoded copies tend to share. Matching against
+//! it with [`Fingerprinter::match_summary`] is coarser than against the full
+//! reference but needs only a couple of hundred bytes.
+//!
+//! # Chromaprint
+//!
+//! Setting [`FingerprintConfig::algorithm`] to
+//! [`FingerprintAlgorithm::Chromaprint`] produces Chromaprint-compatible
+//! sub-fingerprints instead, along with the compressed base64 string that
+//! AcoustID accepts for lookups and submissions.
+//!
+//! # Streaming
+//!
+//! [`StreamingFingerprinter`] accepts audio in chunks, e.g. from a live
+//! channel, and emits rolling windowed fingerprints for
+//! [`FingerprintDatabase::query`]. Its final fingerprint is identical to the
+//! batch one for the same samples.
+//!
+//! # Intros and Outros
+//!
+//! [`IntroDetector`] aligns the fingerprints of a series' episodes to find
+//! the intro and outro they share, for "skip intro" markers.
+//!
+//! # Large Catalogs
+//!
+//! [`FingerprintDatabase`] keeps its postings in memory by default. With
+//! [`DatabaseBackend::Disk`] they live in sorted shard files that are
+//! memory-mapped for queries, so catalogs larger than RAM stay searchable.
+//!
+//! # Low-Entropy Audio
+//!
+//! Short or mostly silent audio yields few constellation points, and with
+//! few hash pairs random collisions with other content dominate a query.
+//! Fingerprints below [`FingerprintConfig::min_points`] or
+//! [`FingerprintConfig::min_pairs`] are flagged as
+//! [`low_entropy`](AudioFingerprint::low_entropy), database queries need a
+//! minimum number of matching pairs on top of the similarity ratio, and
+//! [`FingerprintDatabase::audit`] reports how pairs and collisions are
+//! spread across a catalog when tuning `num_bands` and `fan_out`.
+
+mod chromaprint;
+mod database;
+mod intro;
+mod streaming;
+
+use std::collections::{HashMap, HashSet};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
 use ring::digest::{Context, SHA256};
-use tracing::{debug, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, field::Empty, info, instrument};
 
+use crate::cancel;
 use crate::fft::FrequencyAnalyzer;
+use crate::resample;
+use crate::telemetry::{self, StageTimer};
 use crate::types::*;
 
+pub use database::{
+    ContentAudit, DatabaseAudit, DatabaseBackend, DatabaseMatch, Distribution, FingerprintDatabase, KeyCollision,
+    DEFAULT_MIN_MATCHING_PAIRS,
+};
+pub use intro::{EpisodeMarkers, IntroConfig, IntroDetector, IntroMarker};
+pub use streaming::{StreamingFingerprintConfig, StreamingFingerprinter, WindowFingerprint};
+
+/// Current constellation fingerprint version.
+pub const FINGERPRINT_VERSION: u32 = 2;
+
+/// Sample rate audio is resampled to before constellation fingerprinting.
+pub const CANONICAL_SAMPLE_RATE: u32 = 22050;
+
+/// Number of landmarks kept in a [`ConstellationSummary`].
+pub const SUMMARY_LANDMARKS: usize = 24;
+
 /// Fingerprinting configuration.
 #[derive(Debug, Clone)]
 pub struct FingerprintConfig {
+    /// Fingerprinting algorithm; Chromaprint ignores the remaining settings
+    pub algorithm: FingerprintAlgorithm,
     /// FFT window size
     pub fft_size: usize,
     /// Hop size between frames
     pub hop_size: usize,
+    /// Window applied to each frame
+    ///
+    /// Blackman-Harris reduces leakage between peak bands, but fingerprints
+    /// only match others computed with the same window.
+    pub window: WindowFunction,
     /// Number of frequency bands for peak detection
     pub num_bands: usize,
     /// Fan-out factor for hash generation
@@ -40,22 +135,35 @@ pub struct FingerprintConfig {
     pub target_zone_frames: usize,
     /// Minimum peak prominence threshold
     pub peak_threshold: f32,
+    /// How NaN and infinite samples are handled; streaming fingerprints
+    /// always replace them
+    pub sample_policy: SamplePolicy,
+    /// Fewest constellation points before a fingerprint is flagged as
+    /// low-entropy
+    pub min_points: usize,
+    /// Fewest hash pairs before a fingerprint is flagged as low-entropy
+    pub min_pairs: usize,
 }
 
 impl Default for FingerprintConfig {
     fn default() -> Self {
         Self {
+            algorithm: FingerprintAlgorithm::Constellation,
             fft_size: 4096,
             hop_size: 2048,
+            window: WindowFunction::Hann,
             num_bands: 6,
             fan_out: 5,
             target_zone_frames: 50,
             peak_threshold: 0.1,
+            sample_policy: SamplePolicy::default(),
+            min_points: 50,
+            min_pairs: 200,
         }
     }
 }
 
-/// Audio fingerprinter using spectral peak constellation.
+/// Audio fingerprinter using spectral peak constellation or Chromaprint.
 pub struct Fingerprinter {
     config: FingerprintConfig,
     analyzer: FrequencyAnalyzer,
@@ -69,16 +177,53 @@ impl Fingerprinter {
 
     /// Create a fingerprinter with custom configuration.
     pub fn with_config(config: FingerprintConfig) -> Self {
-        let analyzer = FrequencyAnalyzer::new(config.fft_size, config.hop_size);
+        let analyzer = FrequencyAnalyzer::with_window(config.fft_size, config.hop_size, config.window);
         Self { config, analyzer }
     }
 
     /// Generate a fingerprint from audio data.
-    pub fn fingerprint(&self, audio: &AudioData) -> Result<AudioFingerprint> {
+    ///
+    /// Fails with [`FrequencyError::TooShort`] below one FFT window at the
+    /// canonical rate and [`FrequencyError::ZeroSignal`] for silent or
+    /// DC-only audio; NaN and infinite samples are handled per the
+    /// configured [`SamplePolicy`].
+    pub fn fingerprint(&self, audio: &AudioData) -> FrequencyResult<AudioFingerprint> {
+        self.fingerprint_cancellable(audio, &CancellationToken::new())
+    }
+
+    /// [`fingerprint`](Self::fingerprint), checking `cancel` between FFT
+    /// frames and failing with [`FrequencyError::Cancelled`] once it fires.
+    #[instrument(
+        name = "fingerprint",
+        skip_all,
+        fields(
+            samples = audio.samples.len(),
+            duration_secs = audio.duration_secs,
+            fft_size = self.config.fft_size,
+            elapsed_ms = Empty,
+        ),
+    )]
+    pub fn fingerprint_cancellable(&self, audio: &AudioData, cancel: &CancellationToken) -> FrequencyResult<AudioFingerprint> {
+        let timer = StageTimer::start("fingerprint");
+        let fingerprint = timer.finish(self.generate(audio, cancel))?;
+        telemetry::increment(telemetry::FINGERPRINTS_TOTAL);
+        Ok(fingerprint)
+    }
+
+    fn generate(&self, audio: &AudioData, cancel: &CancellationToken) -> FrequencyResult<AudioFingerprint> {
+        cancel::check(cancel)?;
+        let audio = audio.prepare(self.config.sample_policy, self.min_samples(audio.sample_rate))?;
+        let audio = audio.to_mono();
         info!("Generating fingerprint for {} samples", audio.samples.len());
 
+        if self.config.algorithm == FingerprintAlgorithm::Chromaprint {
+            return self.fingerprint_chromaprint(&audio, cancel);
+        }
+
+        let canonical = resample::conform(&audio, CANONICAL_SAMPLE_RATE);
+
         // Compute spectrogram
-        let spectrogram = self.analyzer.compute_spectrogram(&audio.samples)?;
+        let spectrogram = self.analyzer.compute_spectrogram_cancellable(&canonical.samples, cancel)?;
         debug!("Computed spectrogram with {} frames", spectrogram.len());
 
         // Find spectral peaks
@@ -95,14 +240,70 @@ impl Fingerprinter {
 
         // Compute final fingerprint hash
         let hash = self.compute_hash(&hash_pairs);
-
-        let duration_secs = audio.samples.len() as f64 / audio.sample_rate as f64;
+        let low_entropy = self.is_low_entropy(points.len(), hash_pairs.len());
+        if low_entropy {
+            debug!("Fingerprint is low-entropy: {} points, {} hash pairs", points.len(), hash_pairs.len());
+        }
 
         Ok(AudioFingerprint {
             hash,
-            version: 1,
+            version: FINGERPRINT_VERSION,
+            algorithm: FingerprintAlgorithm::Constellation,
+            sample_rate: CANONICAL_SAMPLE_RATE,
             points,
-            duration_secs,
+            subfingerprints: Vec::new(),
+            chromaprint: None,
+            duration_secs: audio.duration_secs,
+            low_entropy,
+        })
+    }
+
+    /// Rate audio is resampled to before fingerprinting.
+    pub fn preferred_sample_rate(&self) -> u32 {
+        match self.config.algorithm {
+            FingerprintAlgorithm::Chromaprint => chromaprint::SAMPLE_RATE,
+            FingerprintAlgorithm::Constellation => CANONICAL_SAMPLE_RATE,
+        }
+    }
+
+    /// Input samples at `sample_rate` needed for one FFT window after
+    /// resampling; Chromaprint accepts any length.
+    /// Whether a constellation is too sparse to match reliably.
+    fn is_low_entropy(&self, points: usize, pairs: usize) -> bool {
+        points < self.config.min_points || pairs < self.config.min_pairs
+    }
+
+    fn min_samples(&self, sample_rate: u32) -> usize {
+        match self.config.algorithm {
+            FingerprintAlgorithm::Chromaprint => 1,
+            FingerprintAlgorithm::Constellation => {
+                let samples = self.config.fft_size as u64 * sample_rate as u64;
+                samples.div_ceil(CANONICAL_SAMPLE_RATE as u64) as usize + 1
+            }
+        }
+    }
+
+    /// Generate a Chromaprint-compatible fingerprint from mono audio.
+    fn fingerprint_chromaprint(&self, audio: &AudioData, cancel: &CancellationToken) -> FrequencyResult<AudioFingerprint> {
+        let subfingerprints = chromaprint::compute(audio, cancel)?;
+        debug!("Computed {} Chromaprint sub-fingerprints", subfingerprints.len());
+
+        let mut context = Context::new(&SHA256);
+        context.update(b"chromaprint");
+        for sub in &subfingerprints {
+            context.update(&sub.to_le_bytes());
+        }
+
+        Ok(AudioFingerprint {
+            hash: hex::encode(context.finish().as_ref()),
+            version: 1,
+            algorithm: FingerprintAlgorithm::Chromaprint,
+            sample_rate: chromaprint::SAMPLE_RATE,
+            points: Vec::new(),
+            chromaprint: Some(chromaprint::encode(&subfingerprints)),
+            subfingerprints,
+            duration_secs: audio.duration_secs,
+            low_entropy: false,
         })
     }
 
@@ -112,45 +313,53 @@ impl Fingerprinter {
             .map(|f| f.len())
             .ok_or_else(|| anyhow::anyhow!("Empty spectrogram"))?;
 
-        // Define frequency bands (log-spaced)
-        let band_edges: Vec<usize> = (0..=self.config.num_bands)
+        let band_edges = self.band_edges(spectrum_size);
+        let mut peaks = Vec::new();
+
+        for (time_idx, frame) in spectrogram.iter().enumerate() {
+            self.frame_peaks(time_idx as u32, frame, &band_edges, &mut peaks);
+        }
+
+        Ok(peaks)
+    }
+
+    /// Log-spaced frequency band edges for peak detection.
+    fn band_edges(&self, spectrum_size: usize) -> Vec<usize> {
+        (0..=self.config.num_bands)
             .map(|i| {
                 let t = i as f32 / self.config.num_bands as f32;
                 (spectrum_size as f32 * t.powf(2.0)) as usize
             })
-            .collect();
-
-        let mut peaks = Vec::new();
+            .collect()
+    }
 
-        for (time_idx, frame) in spectrogram.iter().enumerate() {
-            // Find max in each frequency band
-            for band_idx in 0..self.config.num_bands {
-                let start = band_edges[band_idx];
-                let end = band_edges[band_idx + 1].min(frame.len());
+    /// Append the peaks of one spectrogram frame.
+    fn frame_peaks(&self, time_idx: u32, frame: &[f32], band_edges: &[usize], peaks: &mut Vec<SpectralPeak>) {
+        // Find max in each frequency band
+        for band_idx in 0..self.config.num_bands {
+            let start = band_edges[band_idx];
+            let end = band_edges[band_idx + 1].min(frame.len());
 
-                if start >= end {
-                    continue;
-                }
+            if start >= end {
+                continue;
+            }
 
-                // Find maximum in this band
-                let (local_max_idx, &max_val) = frame[start..end]
-                    .iter()
-                    .enumerate()
-                    .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
-                    .unwrap_or((0, &0.0));
-
-                // Only keep peaks above threshold
-                if max_val > self.config.peak_threshold {
-                    peaks.push(SpectralPeak {
-                        time_frame: time_idx as u32,
-                        freq_bin: (start + local_max_idx) as u32,
-                        magnitude: max_val,
-                    });
-                }
+            // Find maximum in this band
+            let (local_max_idx, &max_val) = frame[start..end]
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .unwrap_or((0, &0.0));
+
+            // Only keep peaks above threshold
+            if max_val > self.config.peak_threshold {
+                peaks.push(SpectralPeak {
+                    time_frame: time_idx,
+                    freq_bin: (start + local_max_idx) as u32,
+                    magnitude: max_val,
+                });
             }
         }
-
-        Ok(peaks)
     }
 
     /// Create constellation points from spectral peaks.
@@ -169,44 +378,49 @@ impl Fingerprinter {
         let mut pairs = Vec::new();
 
         for (i, anchor) in points.iter().enumerate() {
-            // Find target points in the target zone
-            let mut targets_found = 0;
-
-            for target in points.iter().skip(i + 1) {
-                let time_delta = target.time_offset.saturating_sub(anchor.time_offset);
-
-                // Only consider targets within the target zone
-                if time_delta > 0 && time_delta <= self.config.target_zone_frames as u32 {
-                    pairs.push(HashPair {
-                        anchor_freq: anchor.freq_bin,
-                        target_freq: target.freq_bin,
-                        time_delta,
-                        anchor_time: anchor.time_offset,
-                    });
-
-                    targets_found += 1;
-                    if targets_found >= self.config.fan_out {
-                        break;
-                    }
-                }
-            }
+            self.anchor_pairs(anchor, points.iter().skip(i + 1), &mut pairs);
         }
 
         pairs
     }
 
+    /// Append the hash pairs of one anchor, given the points that follow it.
+    fn anchor_pairs<'a>(
+        &self,
+        anchor: &FingerprintPoint,
+        following: impl Iterator<Item = &'a FingerprintPoint>,
+        pairs: &mut Vec<HashPair>,
+    ) {
+        // Find target points in the target zone
+        let mut targets_found = 0;
+
+        for target in following {
+            let time_delta = target.time_offset.saturating_sub(anchor.time_offset);
+
+            // Only consider targets within the target zone
+            if time_delta > 0 && time_delta <= self.config.target_zone_frames as u32 {
+                pairs.push(HashPair {
+                    anchor_freq: anchor.freq_bin,
+                    target_freq: target.freq_bin,
+                    time_delta,
+                    anchor_time: anchor.time_offset,
+                });
+
+                targets_found += 1;
+                if targets_found >= self.config.fan_out {
+                    break;
+                }
+            }
+        }
+    }
+
     /// Compute final SHA-256 hash from hash pairs.
     fn compute_hash(&self, pairs: &[HashPair]) -> String {
-        let mut context = Context::new(&SHA256);
-
-        // Add version
-        context.update(&1u32.to_le_bytes());
+        let mut context = hash_context();
 
         // Add all hash pairs
         for pair in pairs {
-            context.update(&pair.anchor_freq.to_le_bytes());
-            context.update(&pair.target_freq.to_le_bytes());
-            context.update(&pair.time_delta.to_le_bytes());
+            hash_pair(&mut context, pair);
         }
 
         let digest = context.finish();
@@ -214,7 +428,36 @@ impl Fingerprinter {
     }
 
     /// Match two fingerprints and return similarity score.
-    pub fn match_fingerprints(&self, fp1: &AudioFingerprint, fp2: &AudioFingerprint) -> MatchResult {
+    ///
+    /// Both fingerprints must come from the same algorithm and version. Chromaprint
+    /// fingerprints are aligned by sub-fingerprint and scored as one minus
+    /// their bit error rate.
+    pub fn match_fingerprints(&self, fp1: &AudioFingerprint, fp2: &AudioFingerprint) -> Result<MatchResult> {
+        if fp1.algorithm != fp2.algorithm {
+            bail!(
+                "Cannot compare a {} fingerprint with a {} fingerprint",
+                fp1.algorithm,
+                fp2.algorithm
+            );
+        }
+        if fp1.version != fp2.version {
+            bail!(
+                "Cannot compare a version {} fingerprint with a version {} fingerprint",
+                fp1.version,
+                fp2.version
+            );
+        }
+
+        Ok(match fp1.algorithm {
+            FingerprintAlgorithm::Constellation => self.match_constellations(fp1, fp2),
+            FingerprintAlgorithm::Chromaprint => {
+                chromaprint::match_subfingerprints(&fp1.subfingerprints, &fp2.subfingerprints)
+            }
+        })
+    }
+
+    /// Match constellation fingerprints by aligned hash pairs.
+    fn match_constellations(&self, fp1: &AudioFingerprint, fp2: &AudioFingerprint) -> MatchResult {
         // Build hash map from first fingerprint
         let pairs1 = self.generate_hash_pairs(&fp1.points);
         let pairs2 = self.generate_hash_pairs(&fp2.points);
@@ -226,9 +469,9 @@ impl Fingerprinter {
             fp1_hashes.entry(key).or_default().push(pair.anchor_time);
         }
 
-        // Count matches
+        // Count matches, tracking the span of anchor times at each offset
         let mut _match_count = 0;
-        let mut time_offsets: HashMap<i64, u32> = HashMap::new();
+        let mut time_offsets: HashMap<i64, OffsetVotes> = HashMap::new();
 
         for pair in &pairs2 {
             let key = (pair.anchor_freq, pair.target_freq, pair.time_delta);
@@ -236,18 +479,17 @@ impl Fingerprinter {
                 _match_count += 1;
                 for &t1 in fp1_times {
                     let offset = pair.anchor_time as i64 - t1 as i64;
-                    *time_offsets.entry(offset).or_default() += 1;
+                    time_offsets.entry(offset).or_default().add(pair.anchor_time);
                 }
             }
         }
 
         // Find best time offset alignment
-        let best_offset = time_offsets.iter()
-            .max_by_key(|(_, &count)| count)
-            .map(|(&offset, _)| offset)
-            .unwrap_or(0);
+        let (best_offset, best) = time_offsets.into_iter()
+            .max_by_key(|(_, votes)| votes.count)
+            .unwrap_or_default();
 
-        let aligned_matches = time_offsets.get(&best_offset).copied().unwrap_or(0);
+        let aligned_matches = best.count;
 
         // Calculate similarity score
         let total_pairs = pairs1.len().max(pairs2.len()) as f32;
@@ -263,11 +505,100 @@ impl Fingerprinter {
             time_offset_frames: best_offset as i32,
             matching_pairs: aligned_matches,
             total_pairs_checked: pairs2.len() as u32,
+            matched_duration_secs: self.frames_to_secs(best.span()),
         }
     }
 
+    /// Summarize a constellation fingerprint into landmarks for anchoring.
+    ///
+    /// Returns `None` for Chromaprint fingerprints, which have no points.
+    pub fn summarize(&self, fingerprint: &AudioFingerprint) -> Option<ConstellationSummary> {
+        if fingerprint.algorithm != FingerprintAlgorithm::Constellation || fingerprint.points.is_empty() {
+            return None;
+        }
+
+        // Earliest occurrence of each distinct landmark
+        let mut first_seen: HashMap<u32, u32> = HashMap::new();
+        for pair in self.generate_hash_pairs(&fingerprint.points) {
+            let time = first_seen.entry(landmark_key(&pair)).or_insert(pair.anchor_time);
+            *time = (*time).min(pair.anchor_time);
+        }
+
+        // Keep the smallest keys, so copies that share most of their hash
+        // pairs tend to pick the same landmarks
+        let mut landmarks: Vec<Landmark> = first_seen.into_iter()
+            .map(|(key, anchor_time)| Landmark { key, anchor_time })
+            .collect();
+        landmarks.sort_by_key(|l| l.key);
+        landmarks.truncate(SUMMARY_LANDMARKS);
+
+        Some(ConstellationSummary { version: fingerprint.version, landmarks })
+    }
+
+    /// Match a fingerprint against an anchored constellation summary.
+    ///
+    /// Similarity is the fraction of landmarks found in `fingerprint` at one
+    /// consistent time offset.
+    pub fn match_summary(&self, summary: &ConstellationSummary, fingerprint: &AudioFingerprint) -> Result<MatchResult> {
+        if fingerprint.algorithm != FingerprintAlgorithm::Constellation {
+            bail!("Cannot compare a {} fingerprint with a constellation summary", fingerprint.algorithm);
+        }
+        if fingerprint.version != summary.version {
+            bail!(
+                "Cannot compare a version {} fingerprint with a version {} summary",
+                fingerprint.version,
+                summary.version
+            );
+        }
+
+        let mut times: HashMap<u32, Vec<u32>> = HashMap::new();
+        for pair in self.generate_hash_pairs(&fingerprint.points) {
+            times.entry(landmark_key(&pair)).or_default().push(pair.anchor_time);
+        }
+
+        // Each landmark votes once for every offset it is found at
+        let mut time_offsets: HashMap<i64, OffsetVotes> = HashMap::new();
+        for landmark in &summary.landmarks {
+            let Some(found) = times.get(&landmark.key) else { continue };
+            let mut voted = HashSet::new();
+            for &time in found {
+                let offset = time as i64 - landmark.anchor_time as i64;
+                if voted.insert(offset) {
+                    time_offsets.entry(offset).or_default().add(time);
+                }
+            }
+        }
+
+        let (best_offset, best) = time_offsets.into_iter()
+            .max_by_key(|(_, votes)| votes.count)
+            .unwrap_or_default();
+
+        let similarity = if summary.landmarks.is_empty() {
+            0.0
+        } else {
+            best.count as f32 / summary.landmarks.len() as f32
+        };
+
+        Ok(MatchResult {
+            is_match: similarity >= 0.5,
+            similarity,
+            time_offset_frames: best_offset as i32,
+            matching_pairs: best.count,
+            total_pairs_checked: summary.landmarks.len() as u32,
+            matched_duration_secs: self.frames_to_secs(best.span()),
+        })
+    }
+
+    /// Convert constellation frames to seconds.
+    fn frames_to_secs(&self, frames: u32) -> f64 {
+        frames as f64 * self.config.hop_size as f64 / CANONICAL_SAMPLE_RATE as f64
+    }
+
     /// Verify content against a known fingerprint hash.
-    pub fn verify(&self, audio: &AudioData, expected_hash: &str) -> Result<VerificationResult> {
+    ///
+    /// Only byte-identical audio verifies; see the module docs for
+    /// [`verify_perceptual`](Self::verify_perceptual).
+    pub fn verify(&self, audio: &AudioData, expected_hash: &str) -> FrequencyResult<VerificationResult> {
         let fingerprint = self.fingerprint(audio)?;
 
         let matches = fingerprint.hash == expected_hash;
@@ -276,6 +607,31 @@ impl Fingerprinter {
             verified: matches,
             computed_hash: fingerprint.hash,
             expected_hash: expected_hash.to_string(),
+            similarity: if matches { 1.0 } else { 0.0 },
+            matched_duration_secs: if matches { fingerprint.duration_secs } else { 0.0 },
+        })
+    }
+
+    /// Verify content against a reference fingerprint, tolerating transcoding.
+    ///
+    /// The audio is fingerprinted and matched against `reference` with
+    /// [`match_fingerprints`](Self::match_fingerprints); it verifies when the
+    /// similarity reaches `threshold` (0-1), whether or not the hashes agree.
+    pub fn verify_perceptual(
+        &self,
+        audio: &AudioData,
+        reference: &AudioFingerprint,
+        threshold: f32,
+    ) -> FrequencyResult<VerificationResult> {
+        let fingerprint = self.fingerprint(audio)?;
+        let result = self.match_fingerprints(reference, &fingerprint)?;
+
+        Ok(VerificationResult {
+            verified: result.similarity >= threshold,
+            computed_hash: fingerprint.hash,
+            expected_hash: reference.hash.clone(),
+            similarity: result.similarity,
+            matched_duration_secs: result.matched_duration_secs,
         })
     }
 }
@@ -286,6 +642,20 @@ impl Default for Fingerprinter {
     }
 }
 
+/// Start a fingerprint hash, keyed by the fingerprint version.
+fn hash_context() -> Context {
+    let mut context = Context::new(&SHA256);
+    context.update(&FINGERPRINT_VERSION.to_le_bytes());
+    context
+}
+
+/// Add a hash pair to a fingerprint hash.
+fn hash_pair(context: &mut Context, pair: &HashPair) {
+    context.update(&pair.anchor_freq.to_le_bytes());
+    context.update(&pair.target_freq.to_le_bytes());
+    context.update(&pair.time_delta.to_le_bytes());
+}
+
 /// Internal spectral peak representation.
 #[derive(Debug, Clone)]
 struct SpectralPeak {
@@ -303,8 +673,104 @@ struct HashPair {
     anchor_time: u32,
 }
 
+/// 64-bit key of a hash pair's frequencies and time delta.
+///
+/// Exact for frequency bins below 2^24 and time deltas below 2^16.
+fn pair_key(pair: &HashPair) -> u64 {
+    ((pair.anchor_freq as u64) << 40) ^ ((pair.target_freq as u64) << 16) ^ pair.time_delta as u64
+}
+
+/// SplitMix64 finalizer.
+fn mix_key(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// 32-bit landmark key of a hash pair.
+fn landmark_key(pair: &HashPair) -> u32 {
+    mix_key(pair_key(pair)) as u32
+}
+
+/// Matches voting for one time offset.
+#[derive(Debug, Clone, Copy, Default)]
+struct OffsetVotes {
+    count: u32,
+    first: u32,
+    last: u32,
+}
+
+impl OffsetVotes {
+    fn add(&mut self, time: u32) {
+        if self.count == 0 {
+            self.first = time;
+            self.last = time;
+        }
+        self.count += 1;
+        self.first = self.first.min(time);
+        self.last = self.last.max(time);
+    }
+
+    /// Frames between the first and last matching anchor.
+    fn span(&self) -> u32 {
+        if self.count == 0 { 0 } else { self.last - self.first + 1 }
+    }
+}
+
+/// A hash pair kept in a [`ConstellationSummary`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Landmark {
+    /// 32-bit key of the pair's frequencies and time delta
+    pub key: u32,
+    /// Earliest anchor time of the pair, in frames
+    pub anchor_time: u32,
+}
+
+/// Compact constellation summary for on-chain anchoring.
+///
+/// Holds the [`SUMMARY_LANDMARKS`] hash pairs with the smallest keys. Only
+/// comparable with fingerprints of the same version made with the default
+/// [`FingerprintConfig`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstellationSummary {
+    /// Fingerprint version the summary was taken from
+    pub version: u32,
+    /// Landmarks ordered by key
+    pub landmarks: Vec<Landmark>,
+}
+
+impl ConstellationSummary {
+    /// Serialize as `version: u32` followed by `key: u32, anchor_time: u32`
+    /// per landmark, all little-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.landmarks.len() * 8);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        for landmark in &self.landmarks {
+            bytes.extend_from_slice(&landmark.key.to_le_bytes());
+            bytes.extend_from_slice(&landmark.anchor_time.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Parse bytes written by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 4 || !(bytes.len() - 4).is_multiple_of(8) || bytes.len() > 4 + SUMMARY_LANDMARKS * 8 {
+            return None;
+        }
+        let word = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+
+        Some(Self {
+            version: word(0),
+            landmarks: (4..bytes.len())
+                .step_by(8)
+                .map(|i| Landmark { key: word(i), anchor_time: word(i + 4) })
+                .collect(),
+        })
+    }
+}
+
 /// Result of fingerprint matching.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchResult {
     /// Whether the fingerprints are considered a match
     pub is_match: bool,
@@ -316,10 +782,12 @@ pub struct MatchResult {
     pub matching_pairs: u32,
     /// Total hash pairs checked
     pub total_pairs_checked: u32,
+    /// Approximate duration of the aligned matching audio in seconds
+    pub matched_duration_secs: f64,
 }
 
 /// Result of content verification.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerificationResult {
     /// Whether the content matches the expected fingerprint
     pub verified: bool,
@@ -327,95 +795,10 @@ pub struct VerificationResult {
     pub computed_hash: String,
     /// The expected fingerprint hash
     pub expected_hash: String,
-}
-
-/// Fingerprint database for content matching.
-pub struct FingerprintDatabase {
-    /// Map from hash pair key to (content_id, anchor_time)
-    index: HashMap<(u32, u32, u32), Vec<(String, u32)>>,
-}
-
-impl FingerprintDatabase {
-    /// Create a new empty database.
-    pub fn new() -> Self {
-        Self {
-            index: HashMap::new(),
-        }
-    }
-
-    /// Add a fingerprint to the database.
-    pub fn add(&mut self, content_id: &str, fingerprint: &AudioFingerprint) {
-        let fingerprinter = Fingerprinter::new();
-        let pairs = fingerprinter.generate_hash_pairs(&fingerprint.points);
-
-        for pair in pairs {
-            let key = (pair.anchor_freq, pair.target_freq, pair.time_delta);
-            self.index.entry(key)
-                .or_default()
-                .push((content_id.to_string(), pair.anchor_time));
-        }
-    }
-
-    /// Query the database for matching content.
-    pub fn query(&self, fingerprint: &AudioFingerprint, threshold: f32) -> Vec<DatabaseMatch> {
-        let fingerprinter = Fingerprinter::new();
-        let pairs = fingerprinter.generate_hash_pairs(&fingerprint.points);
-
-        // Count matches per content
-        let mut content_matches: HashMap<String, HashMap<i64, u32>> = HashMap::new();
-
-        for pair in &pairs {
-            let key = (pair.anchor_freq, pair.target_freq, pair.time_delta);
-            if let Some(entries) = self.index.get(&key) {
-                for (content_id, db_time) in entries {
-                    let offset = pair.anchor_time as i64 - *db_time as i64;
-                    *content_matches
-                        .entry(content_id.clone())
-                        .or_default()
-                        .entry(offset)
-                        .or_default() += 1;
-                }
-            }
-        }
-
-        // Find best matches
-        let mut results: Vec<DatabaseMatch> = content_matches.iter()
-            .filter_map(|(content_id, offsets)| {
-                let best_count = offsets.values().max().copied().unwrap_or(0);
-                let similarity = best_count as f32 / pairs.len() as f32;
-
-                if similarity >= threshold {
-                    Some(DatabaseMatch {
-                        content_id: content_id.clone(),
-                        similarity,
-                        matching_pairs: best_count,
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        results.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal));
-        results
-    }
-}
-
-impl Default for FingerprintDatabase {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-/// Match result from database query.
-#[derive(Debug, Clone)]
-pub struct DatabaseMatch {
-    /// Content ID of the matched item
-    pub content_id: String,
-    /// Similarity score
+    /// Similarity to the reference (0-1); exact verification reports 1 or 0
     pub similarity: f32,
-    /// Number of matching hash pairs
-    pub matching_pairs: u32,
+    /// Duration of audio that matched the reference in seconds
+    pub matched_duration_secs: f64,
 }
 
 #[cfg(test)]
@@ -423,7 +806,10 @@ mod tests {
     use super::*;
 
     fn generate_test_audio(freq: f32, duration_secs: f32) -> AudioData {
-        let sample_rate = 44100;
+        generate_test_audio_at(freq, duration_secs, 44100)
+    }
+
+    fn generate_test_audio_at(freq: f32, duration_secs: f32, sample_rate: u32) -> AudioData {
         let num_samples = (sample_rate as f32 * duration_secs) as usize;
         let samples: Vec<f32> = (0..num_samples)
             .map(|i| {
@@ -443,7 +829,78 @@ mod tests {
 
         assert!(!fp.hash.is_empty());
         assert!(fp.points.len() > 0);
-        assert_eq!(fp.version, 1);
+        assert_eq!(fp.version, FINGERPRINT_VERSION);
+        assert_eq!(fp.sample_rate, CANONICAL_SAMPLE_RATE);
+    }
+
+    #[test]
+    fn test_stereo_fingerprint_matches_mono() {
+        let mono = generate_test_audio(440.0, 5.0);
+        let interleaved: Vec<f32> = mono.samples.iter().flat_map(|&s| [s, s]).collect();
+        let stereo = AudioData::with_channels(interleaved, mono.sample_rate, 2);
+
+        let fingerprinter = Fingerprinter::new();
+        let fp_mono = fingerprinter.fingerprint(&mono).unwrap();
+        let fp_stereo = fingerprinter.fingerprint(&stereo).unwrap();
+
+        assert_eq!(fp_mono.hash, fp_stereo.hash);
+        assert!((fp_stereo.duration_secs - 5.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_fingerprint_independent_of_sample_rate() {
+        let fingerprinter = Fingerprinter::new();
+        let fp_44k = fingerprinter.fingerprint(&generate_test_audio_at(440.0, 5.0, 44100)).unwrap();
+        let fp_48k = fingerprinter.fingerprint(&generate_test_audio_at(440.0, 5.0, 48000)).unwrap();
+
+        assert_eq!(fp_44k.version, 2);
+        assert_eq!(fp_44k.hash, fp_48k.hash);
+
+        let result = fingerprinter.match_fingerprints(&fp_44k, &fp_48k).unwrap();
+        assert!(result.is_match);
+        assert_eq!(result.time_offset_frames, 0);
+    }
+
+    #[test]
+    fn test_fingerprint_stable_across_resampling() {
+        // A melody over a bass line with noise, so peaks change over time
+        let notes = [261.6, 329.6, 392.0, 523.3, 440.0, 349.2, 587.3, 493.9];
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let samples: Vec<f32> = (0..48000 * 6)
+            .map(|i| {
+                let t = i as f32 / 48000.0;
+                let note = notes[(t * 4.0) as usize % notes.len()];
+                let bass = notes[(t / 2.0) as usize % notes.len()] / 4.0;
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let noise = (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5;
+                let tone = |f: f32| (2.0 * std::f32::consts::PI * f * t).sin();
+                0.5 * tone(note) + 0.3 * tone(bass) + 0.2 * tone(note * 3.0) + 0.02 * noise
+            })
+            .collect();
+        let audio_48k = AudioData::new(samples, 48000);
+        let audio_44k = resample::resample(&audio_48k, 44100);
+        assert_eq!(audio_44k.sample_rate, 44100);
+
+        let fingerprinter = Fingerprinter::new();
+        let fp_48k = fingerprinter.fingerprint(&audio_48k).unwrap();
+        let fp_44k = fingerprinter.fingerprint(&audio_44k).unwrap();
+
+        let result = fingerprinter.match_fingerprints(&fp_48k, &fp_44k).unwrap();
+        assert!(result.is_match);
+        assert!(result.similarity > 0.9, "similarity {}", result.similarity);
+        assert_eq!(result.time_offset_frames, 0);
+    }
+
+    #[test]
+    fn test_match_rejects_mixed_versions() {
+        let fingerprinter = Fingerprinter::new();
+        let current = fingerprinter.fingerprint(&generate_test_audio(440.0, 5.0)).unwrap();
+        let legacy = AudioFingerprint { version: 1, sample_rate: 0, ..current.clone() };
+
+        let err = fingerprinter.match_fingerprints(&current, &legacy).unwrap_err();
+        assert_eq!(err.to_string(), "Cannot compare a version 2 fingerprint with a version 1 fingerprint");
     }
 
     #[test]
@@ -471,14 +928,86 @@ mod tests {
         let fp3 = fingerprinter.fingerprint(&audio3).unwrap();
 
         // Same audio should match
-        let match_same = fingerprinter.match_fingerprints(&fp1, &fp2);
+        let match_same = fingerprinter.match_fingerprints(&fp1, &fp2).unwrap();
         assert!(match_same.is_match);
 
         // Different audio should not match as well
-        let match_diff = fingerprinter.match_fingerprints(&fp1, &fp3);
+        let match_diff = fingerprinter.match_fingerprints(&fp1, &fp3).unwrap();
         assert!(match_same.similarity > match_diff.similarity);
     }
 
+    fn chromaprinter() -> Fingerprinter {
+        Fingerprinter::with_config(FingerprintConfig {
+            algorithm: FingerprintAlgorithm::Chromaprint,
+            ..Default::default()
+        })
+    }
+
+    /// Notes cycling through a fixed sequence, switching every half second.
+    fn generate_melody(notes: &[f32], duration_secs: f32) -> AudioData {
+        let sample_rate = 22050;
+        let samples: Vec<f32> = (0..(sample_rate as f32 * duration_secs) as usize)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                let freq = notes[(t * 2.0) as usize % notes.len()];
+                0.5 * (2.0 * std::f32::consts::PI * freq * t).sin()
+            })
+            .collect();
+
+        AudioData::new(samples, sample_rate)
+    }
+
+    #[test]
+    fn test_chromaprint_fingerprint() {
+        let fp = chromaprinter().fingerprint(&generate_melody(&[440.0, 554.4, 659.3], 10.0)).unwrap();
+
+        assert_eq!(fp.algorithm, FingerprintAlgorithm::Chromaprint);
+        assert!(fp.points.is_empty());
+        assert!(!fp.subfingerprints.is_empty());
+        // Algorithm 1 header, as produced by fpcalc
+        assert!(fp.chromaprint.as_deref().unwrap().starts_with("AQAA"));
+
+        let json = serde_json::to_string(&fp).unwrap();
+        let restored: AudioFingerprint = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.subfingerprints, fp.subfingerprints);
+        assert_eq!(restored.chromaprint, fp.chromaprint);
+    }
+
+    #[test]
+    fn test_chromaprint_matching() {
+        let fingerprinter = chromaprinter();
+        let melody = [440.0, 554.4, 659.3, 493.9];
+
+        let fp1 = fingerprinter.fingerprint(&generate_melody(&melody, 10.0)).unwrap();
+        let fp2 = fingerprinter.fingerprint(&generate_melody(&melody, 10.0)).unwrap();
+        let fp3 = fingerprinter.fingerprint(&generate_melody(&[392.0, 311.1, 349.2, 370.0], 10.0)).unwrap();
+
+        let match_same = fingerprinter.match_fingerprints(&fp1, &fp2).unwrap();
+        assert!(match_same.is_match);
+        assert_eq!(match_same.similarity, 1.0);
+
+        let match_diff = fingerprinter.match_fingerprints(&fp1, &fp3).unwrap();
+        assert!(match_same.similarity > match_diff.similarity);
+    }
+
+    #[test]
+    fn test_match_rejects_mixed_algorithms() {
+        let audio = generate_test_audio(440.0, 5.0);
+        let constellation = Fingerprinter::new().fingerprint(&audio).unwrap();
+        let chromaprint = chromaprinter().fingerprint(&audio).unwrap();
+
+        let err = Fingerprinter::new().match_fingerprints(&constellation, &chromaprint).unwrap_err();
+        assert_eq!(err.to_string(), "Cannot compare a constellation fingerprint with a chromaprint fingerprint");
+    }
+
+    #[test]
+    fn test_legacy_fingerprint_defaults_to_constellation() {
+        let json = r#"{"hash":"ab","version":1,"points":[],"duration_secs":1.0}"#;
+        let fp: AudioFingerprint = serde_json::from_str(json).unwrap();
+        assert_eq!(fp.algorithm, FingerprintAlgorithm::Constellation);
+        assert!(fp.chromaprint.is_none());
+    }
+
     #[test]
     fn test_verification() {
         let audio = generate_test_audio(440.0, 5.0);
@@ -489,6 +1018,113 @@ mod tests {
 
         assert!(result.verified);
         assert_eq!(result.computed_hash, result.expected_hash);
+        assert_eq!(result.similarity, 1.0);
+    }
+
+    /// Simulate a lossy re-encode: a four-pole 4 kHz low-pass, then 8-bit
+    /// requantization.
+    fn reencode(audio: &AudioData) -> AudioData {
+        let dt = 1.0 / audio.sample_rate as f32;
+        let alpha = dt / (1.0 / (2.0 * std::f32::consts::PI * 4000.0) + dt);
+        let mut poles = [0.0f32; 4];
+        let samples = audio.samples.iter()
+            .map(|&s| {
+                let mut x = s;
+                for pole in &mut poles {
+                    *pole += alpha * (x - *pole);
+                    x = *pole;
+                }
+                (x * 127.0).round() / 127.0
+            })
+            .collect();
+
+        AudioData::new(samples, audio.sample_rate)
+    }
+
+    /// Layered notes with a bright upper partial that lossy encoders cut.
+    fn generate_track(duration_secs: f32) -> AudioData {
+        let melody = generate_melody(&[440.0, 554.4, 659.3, 493.9, 392.0, 587.3], duration_secs);
+        let bass = generate_melody(&[110.0, 146.8, 98.0], duration_secs);
+        let shimmer = generate_melody(&[6500.0, 7100.0, 6800.0, 7400.0, 6950.0], duration_secs);
+        let samples = melody.samples.iter().zip(&bass.samples).zip(&shimmer.samples)
+            .map(|((&m, &b), &h)| 0.6 * (m + b) + 0.3 * h)
+            .collect();
+
+        AudioData::new(samples, melody.sample_rate)
+    }
+
+    #[test]
+    fn test_perceptual_verification_survives_reencode() {
+        let fingerprinter = Fingerprinter::new();
+        let original = generate_track(10.0);
+        let transcoded = reencode(&original);
+        let reference = fingerprinter.fingerprint(&original).unwrap();
+
+        // The exact hash no longer matches
+        let exact = fingerprinter.verify(&transcoded, &reference.hash).unwrap();
+        assert!(!exact.verified);
+        assert_eq!(exact.similarity, 0.0);
+
+        let result = fingerprinter.verify_perceptual(&transcoded, &reference, 0.5).unwrap();
+        assert!(result.verified, "similarity = {}", result.similarity);
+        assert_eq!(result.expected_hash, reference.hash);
+        assert!(result.matched_duration_secs > 5.0, "matched {}s", result.matched_duration_secs);
+
+        let other = fingerprinter.fingerprint(&generate_test_audio(880.0, 10.0)).unwrap();
+        let result = fingerprinter.verify_perceptual(&transcoded, &other, 0.5).unwrap();
+        assert!(!result.verified);
+    }
+
+    #[test]
+    fn test_summary_matches_reencode() {
+        let fingerprinter = Fingerprinter::new();
+        let original = fingerprinter.fingerprint(&generate_track(10.0)).unwrap();
+        let transcoded = fingerprinter.fingerprint(&reencode(&generate_track(10.0))).unwrap();
+
+        let summary = fingerprinter.summarize(&original).unwrap();
+        assert_eq!(summary.landmarks.len(), SUMMARY_LANDMARKS);
+        assert_eq!(ConstellationSummary::from_bytes(&summary.to_bytes()), Some(summary.clone()));
+        assert!(ConstellationSummary::from_bytes(&[0; 7]).is_none());
+
+        let result = fingerprinter.match_summary(&summary, &transcoded).unwrap();
+        assert!(result.is_match);
+        assert_eq!(result.time_offset_frames, 0);
+
+        let other = fingerprinter.fingerprint(&generate_melody(&[392.0, 311.1, 349.2, 370.0], 10.0)).unwrap();
+        assert!(fingerprinter.match_summary(&summary, &other).unwrap().similarity < result.similarity);
+
+        assert!(fingerprinter.summarize(&chromaprinter().fingerprint(&generate_track(10.0)).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_fingerprint_cancellation() {
+        let audio = generate_test_audio(440.0, 5.0);
+        let cancel = CancellationToken::new();
+        for fingerprinter in [Fingerprinter::new(), chromaprinter()] {
+            let fp = fingerprinter.fingerprint_cancellable(&audio, &cancel).unwrap();
+            assert_eq!(fp.hash, fingerprinter.fingerprint(&audio).unwrap().hash);
+        }
+
+        cancel.cancel();
+        for fingerprinter in [Fingerprinter::new(), chromaprinter()] {
+            let err = fingerprinter.fingerprint_cancellable(&audio, &cancel).unwrap_err();
+            assert!(matches!(err, FrequencyError::Cancelled), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn test_low_entropy_fingerprint() {
+        let fingerprinter = Fingerprinter::new();
+        let full = fingerprinter.fingerprint(&generate_track(10.0)).unwrap();
+        assert!(!full.low_entropy, "{} points", full.points.len());
+
+        // A blip in a mostly silent recording
+        let mut sparse = generate_test_audio(440.0, 0.3);
+        sparse.samples.resize(44100 * 10, 0.0);
+        let fp = fingerprinter.fingerprint(&AudioData::new(sparse.samples, 44100)).unwrap();
+        assert!(fp.low_entropy, "{} points", fp.points.len());
+        assert!(serde_json::to_string(&fp).unwrap().contains("\"low_entropy\":true"));
+        assert!(!serde_json::to_string(&full).unwrap().contains("low_entropy"));
     }
 
     #[test]
@@ -0,0 +1,446 @@
+//! Chromaprint-compatible fingerprints.
+//!
+//! Implements Chromaprint's default algorithm (`TEST2`) so fingerprints can
+//! be submitted to AcoustID and cross-referenced against MusicBrainz.
+//!
+//! 1. Downmix and resample to 11025 Hz
+//! 2. Hamming-windowed 4096-sample frames every 1365 samples
+//! 3. Fold FFT energy between 28 Hz and 3520 Hz into 12 chroma bins
+//! 4. Smooth chroma over 5 frames and normalize each vector
+//! 5. Run 16 Haar-like classifiers over the chroma image; each one quantizes
+//!    its response into 2 Gray-coded bits of a 32-bit sub-fingerprint
+//!
+//! Audio already at 11025 Hz goes through the reference pipeline unchanged.
+//! Other rates are converted by [`crate::resample`], which follows the
+//! design of Chromaprint's resampler but is not bit-identical to it; AcoustID
+//! matches on bit error rate, so the small differences do not affect lookups.
+
+use std::sync::Arc;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use tokio_util::sync::CancellationToken;
+
+use super::MatchResult;
+use crate::cancel;
+use crate::resample::resample_samples;
+use crate::types::{AudioData, FrequencyResult};
+
+/// Sample rate the algorithm operates at.
+pub(crate) const SAMPLE_RATE: u32 = 11025;
+const FRAME_SIZE: usize = 4096;
+const HOP_SIZE: usize = FRAME_SIZE / 3;
+const MIN_FREQ: f64 = 28.0;
+const MAX_FREQ: f64 = 3520.0;
+const NUM_BANDS: usize = 12;
+/// Temporal smoothing applied to consecutive chroma vectors.
+const CHROMA_FILTER: [f64; 5] = [0.25, 0.5, 1.0, 0.5, 0.25];
+/// Chroma vectors with a smaller norm are treated as silence.
+const NORMALIZE_THRESHOLD: f64 = 0.01;
+/// Widest classifier filter, in frames.
+const MAX_FILTER_WIDTH: usize = 16;
+/// Algorithm identifier written into the compressed fingerprint header.
+const ALGORITHM_ID: u8 = 1;
+/// Largest bit-position delta stored in the 3-bit stream.
+const MAX_NORMAL_VALUE: u32 = 7;
+/// Sub-fingerprints differing in at most this many bits count as matching.
+const MATCH_BIT_TOLERANCE: u32 = 2;
+/// Minimum similarity (1 - bit error rate) for two fingerprints to match.
+const MATCH_THRESHOLD: f32 = 0.65;
+
+/// Haar-like filter over the chroma image followed by a 4-level quantizer.
+struct Classifier {
+    kind: u8,
+    /// First chroma band
+    y: usize,
+    /// Number of chroma bands
+    height: usize,
+    /// Number of frames
+    width: usize,
+    thresholds: [f64; 3],
+}
+
+const fn classifier(kind: u8, y: usize, height: usize, width: usize, thresholds: [f64; 3]) -> Classifier {
+    Classifier { kind, y, height, width, thresholds }
+}
+
+/// Trained classifiers of the `TEST2` algorithm.
+const CLASSIFIERS: [Classifier; 16] = [
+    classifier(0, 4, 3, 15, [1.98215, 2.35817, 2.63523]),
+    classifier(4, 4, 6, 15, [-1.03809, -0.651211, -0.282167]),
+    classifier(1, 0, 4, 16, [-0.298702, 0.119262, 0.558497]),
+    classifier(3, 8, 2, 12, [-0.105439, 0.0153946, 0.135898]),
+    classifier(3, 4, 4, 8, [-0.142891, 0.0258736, 0.200632]),
+    classifier(4, 0, 3, 5, [-0.826319, -0.590612, -0.368214]),
+    classifier(1, 2, 2, 9, [-0.557409, -0.233035, 0.0534525]),
+    classifier(2, 7, 3, 4, [-0.0646826, 0.00620476, 0.0784847]),
+    classifier(2, 6, 2, 16, [-0.192387, -0.029699, 0.215855]),
+    classifier(2, 1, 3, 2, [-0.0397818, -0.00568076, 0.0292026]),
+    classifier(5, 10, 1, 15, [-0.53823, -0.369934, -0.190235]),
+    classifier(3, 6, 2, 10, [-0.124877, 0.0296483, 0.139239]),
+    classifier(2, 1, 1, 14, [-0.101475, 0.0225617, 0.231971]),
+    classifier(3, 5, 6, 4, [-0.0799915, -0.00729616, 0.063262]),
+    classifier(1, 9, 2, 12, [-0.272556, 0.019424, 0.302559]),
+    classifier(3, 4, 2, 14, [-0.164292, -0.0321188, 0.0846339]),
+];
+
+impl Classifier {
+    fn classify(&self, image: &IntegralImage, x: usize) -> u32 {
+        let value = self.apply(image, x);
+        let [t0, t1, t2] = self.thresholds;
+        let level = if value < t1 {
+            if value < t0 { 0 } else { 1 }
+        } else if value < t2 {
+            2
+        } else {
+            3
+        };
+        // Gray code so neighbouring levels differ in a single bit
+        [0, 1, 3, 2][level]
+    }
+
+    fn apply(&self, image: &IntegralImage, x: usize) -> f64 {
+        let (y, w, h) = (self.y, self.width, self.height);
+        let area = |x1, y1, x2, y2| image.area(x1, y1, x2, y2);
+
+        let (a, b) = match self.kind {
+            0 => (area(x, y, x + w, y + h), 0.0),
+            1 => {
+                let h2 = h / 2;
+                (area(x, y + h2, x + w, y + h), area(x, y, x + w, y + h2))
+            }
+            2 => {
+                let w2 = w / 2;
+                (area(x + w2, y, x + w, y + h), area(x, y, x + w2, y + h))
+            }
+            3 => {
+                let (w2, h2) = (w / 2, h / 2);
+                (
+                    area(x, y + h2, x + w2, y + h) + area(x + w2, y, x + w, y + h2),
+                    area(x, y, x + w2, y + h2) + area(x + w2, y + h2, x + w, y + h),
+                )
+            }
+            4 => {
+                let h3 = h / 3;
+                (
+                    area(x, y + h3, x + w, y + 2 * h3),
+                    area(x, y, x + w, y + h3) + area(x, y + 2 * h3, x + w, y + h),
+                )
+            }
+            _ => {
+                let w3 = w / 3;
+                (
+                    area(x + w3, y, x + 2 * w3, y + h),
+                    area(x, y, x + w3, y + h) + area(x + 2 * w3, y, x + w, y + h),
+                )
+            }
+        };
+
+        ((1.0 + a) / (1.0 + b)).ln()
+    }
+}
+
+/// Summed-area table over chroma frames (rows) and bands (columns).
+struct IntegralImage {
+    rows: Vec<[f64; NUM_BANDS]>,
+}
+
+impl IntegralImage {
+    fn new(features: &[[f64; NUM_BANDS]]) -> Self {
+        let mut rows: Vec<[f64; NUM_BANDS]> = Vec::with_capacity(features.len());
+        for feature in features {
+            let mut row = [0.0; NUM_BANDS];
+            row[0] = feature[0];
+            for i in 1..NUM_BANDS {
+                row[i] = row[i - 1] + feature[i];
+            }
+            if let Some(prev) = rows.last() {
+                for (value, above) in row.iter_mut().zip(prev) {
+                    *value += above;
+                }
+            }
+            rows.push(row);
+        }
+        Self { rows }
+    }
+
+    /// Sum over frames `r1..r2` and bands `c1..c2`.
+    fn area(&self, r1: usize, c1: usize, r2: usize, c2: usize) -> f64 {
+        if r1 == r2 || c1 == c2 {
+            return 0.0;
+        }
+        let corner = |r: usize, c: usize| if r == 0 || c == 0 { 0.0 } else { self.rows[r - 1][c - 1] };
+        corner(r2, c2) - corner(r1, c2) - corner(r2, c1) + corner(r1, c1)
+    }
+}
+
+/// Compute the sub-fingerprint sequence for mono audio, checking `cancel`
+/// before each chroma frame.
+pub(crate) fn compute(audio: &AudioData, cancel: &CancellationToken) -> FrequencyResult<Vec<u32>> {
+    let samples = resample_samples(&audio.samples, audio.sample_rate, SAMPLE_RATE);
+    let chroma = smooth_and_normalize(&chroma_frames(&samples, cancel)?);
+    if chroma.len() < MAX_FILTER_WIDTH {
+        return Ok(Vec::new());
+    }
+
+    let image = IntegralImage::new(&chroma);
+    Ok((0..=chroma.len() - MAX_FILTER_WIDTH)
+        .map(|offset| {
+            CLASSIFIERS.iter().fold(0u32, |bits, c| (bits << 2) | c.classify(&image, offset))
+        })
+        .collect())
+}
+
+/// Per-frame chroma energy.
+fn chroma_frames(samples: &[f32], cancel: &CancellationToken) -> FrequencyResult<Vec<[f64; NUM_BANDS]>> {
+    if samples.len() < FRAME_SIZE {
+        return Ok(Vec::new());
+    }
+
+    let fft: Arc<dyn Fft<f64>> = FftPlanner::new().plan_fft_forward(FRAME_SIZE);
+    // Chromaprint works on 16-bit PCM and folds the conversion back into the window
+    let window: Vec<f64> = (0..FRAME_SIZE)
+        .map(|i| {
+            let phase = 2.0 * std::f64::consts::PI * i as f64 / (FRAME_SIZE - 1) as f64;
+            (0.54 - 0.46 * phase.cos()) / i16::MAX as f64
+        })
+        .collect();
+    let pcm: Vec<f64> = samples.iter()
+        .map(|&s| (s as f64 * 32768.0).round().clamp(i16::MIN as f64, i16::MAX as f64))
+        .collect();
+
+    let freq_to_index = |freq: f64| (FRAME_SIZE as f64 * freq / SAMPLE_RATE as f64).round() as usize;
+    let min_index = freq_to_index(MIN_FREQ).max(1);
+    let max_index = freq_to_index(MAX_FREQ).min(FRAME_SIZE / 2);
+    let notes: Vec<usize> = (min_index..max_index)
+        .map(|i| {
+            let freq = i as f64 * SAMPLE_RATE as f64 / FRAME_SIZE as f64;
+            // Octaves above A0 (27.5 Hz)
+            let octave = (freq / 27.5).log2();
+            (NUM_BANDS as f64 * (octave - octave.floor())) as usize
+        })
+        .collect();
+
+    let mut buffer = vec![Complex::new(0.0, 0.0); FRAME_SIZE];
+    (0..=(pcm.len() - FRAME_SIZE) / HOP_SIZE)
+        .map(|frame| {
+            cancel::check(cancel)?;
+            let start = frame * HOP_SIZE;
+            for ((slot, &s), &w) in buffer.iter_mut().zip(&pcm[start..start + FRAME_SIZE]).zip(&window) {
+                *slot = Complex::new(s * w, 0.0);
+            }
+            fft.process(&mut buffer);
+
+            let mut chroma = [0.0; NUM_BANDS];
+            for (bin, &note) in buffer[min_index..max_index].iter().zip(&notes) {
+                chroma[note] += bin.norm_sqr();
+            }
+            Ok(chroma)
+        })
+        .collect()
+}
+
+/// Apply the chroma filter and scale each vector to unit length.
+fn smooth_and_normalize(frames: &[[f64; NUM_BANDS]]) -> Vec<[f64; NUM_BANDS]> {
+    // As in Chromaprint, the first output is produced once a full window has
+    // been buffered after the first frame, so the very first frame is dropped.
+    frames.iter()
+        .skip(1)
+        .collect::<Vec<_>>()
+        .windows(CHROMA_FILTER.len())
+        .map(|window| {
+            let mut out = [0.0; NUM_BANDS];
+            for (frame, &coefficient) in window.iter().zip(&CHROMA_FILTER) {
+                for (o, v) in out.iter_mut().zip(frame.iter()) {
+                    *o += v * coefficient;
+                }
+            }
+
+            let norm = out.iter().map(|v| v * v).sum::<f64>().sqrt();
+            if norm < NORMALIZE_THRESHOLD {
+                [0.0; NUM_BANDS]
+            } else {
+                out.map(|v| v / norm)
+            }
+        })
+        .collect()
+}
+
+/// Encode sub-fingerprints in the compressed base64 format used by AcoustID.
+pub(crate) fn encode(subfingerprints: &[u32]) -> String {
+    URL_SAFE_NO_PAD.encode(compress(subfingerprints, ALGORITHM_ID))
+}
+
+/// Chromaprint's compressed fingerprint layout: a 4-byte header (algorithm,
+/// 24-bit length) followed by the set-bit positions of each XOR delta as
+/// packed 3-bit values, with overflow stored in a packed 4-bit stream.
+fn compress(subfingerprints: &[u32], algorithm: u8) -> Vec<u8> {
+    let mut normal = Vec::new();
+    let mut exceptional = Vec::new();
+
+    let mut previous = 0;
+    for &sub in subfingerprints {
+        let mut x = sub ^ previous;
+        previous = sub;
+
+        let (mut bit, mut last_bit) = (1, 0);
+        while x != 0 {
+            if x & 1 != 0 {
+                let delta = bit - last_bit;
+                if delta >= MAX_NORMAL_VALUE {
+                    normal.push(MAX_NORMAL_VALUE);
+                    exceptional.push(delta - MAX_NORMAL_VALUE);
+                } else {
+                    normal.push(delta);
+                }
+                last_bit = bit;
+            }
+            x >>= 1;
+            bit += 1;
+        }
+        normal.push(0);
+    }
+
+    let len = subfingerprints.len() as u32;
+    let mut out = vec![algorithm, (len >> 16) as u8, (len >> 8) as u8, len as u8];
+    pack_bits(&normal, 3, &mut out);
+    pack_bits(&exceptional, 4, &mut out);
+    out
+}
+
+/// Pack `bits`-wide values least significant bit first.
+fn pack_bits(values: &[u32], bits: u32, out: &mut Vec<u8>) {
+    let mut acc = 0u32;
+    let mut filled = 0;
+    for &value in values {
+        acc |= (value & ((1 << bits) - 1)) << filled;
+        filled += bits;
+        while filled >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            filled -= 8;
+        }
+    }
+    if filled > 0 {
+        out.push(acc as u8);
+    }
+}
+
+/// Compare sub-fingerprint sequences at every alignment that overlaps by at
+/// least half of the shorter one, keeping the lowest bit error rate.
+pub(crate) fn match_subfingerprints(a: &[u32], b: &[u32]) -> MatchResult {
+    let min_overlap = (a.len().min(b.len()) / 2).max(1);
+    let mut best = MatchResult {
+        is_match: false,
+        similarity: 0.0,
+        time_offset_frames: 0,
+        matching_pairs: 0,
+        total_pairs_checked: 0,
+        matched_duration_secs: 0.0,
+    };
+
+    for offset in -(a.len() as i64)..b.len() as i64 {
+        // b[i + offset] aligns with a[i]
+        let start = (-offset).max(0) as usize;
+        let end = (b.len() as i64 - offset).min(a.len() as i64).max(0) as usize;
+        if end < start + min_overlap {
+            continue;
+        }
+
+        let errors: Vec<u32> = a[start..end].iter()
+            .zip(&b[(start as i64 + offset) as usize..])
+            .map(|(x, y)| (x ^ y).count_ones())
+            .collect();
+        let bit_errors: u32 = errors.iter().sum();
+        let similarity = 1.0 - bit_errors as f32 / (32 * errors.len()) as f32;
+
+        if similarity > best.similarity {
+            let matching = errors.iter().filter(|&&e| e <= MATCH_BIT_TOLERANCE).count() as u32;
+            best = MatchResult {
+                is_match: similarity >= MATCH_THRESHOLD,
+                similarity,
+                time_offset_frames: offset as i32,
+                matching_pairs: matching,
+                total_pairs_checked: errors.len() as u32,
+                matched_duration_secs: matching as f64 * HOP_SIZE as f64 / SAMPLE_RATE as f64,
+            };
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sub-fingerprint of any steady tone whose chroma falls entirely in
+    /// band 0, derived by hand from the classifier table: with a constant
+    /// one-hot chroma image only classifiers 3 and 6 see energy.
+    const TONE_SUBFINGERPRINT: u32 = 0x214D_F977;
+
+    fn tone(freq: f32, sample_rate: u32, secs: f32) -> AudioData {
+        let samples = (0..(sample_rate as f32 * secs) as usize)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).sin())
+            .collect();
+        AudioData::new(samples, sample_rate)
+    }
+
+    /// Center of chroma band 0 (A to A#) in the fifth octave above A0.
+    fn band_zero_center() -> f32 {
+        27.5 * 2f32.powf(4.0 + 0.5 / 12.0)
+    }
+
+    #[test]
+    fn test_compressor_reference_vectors() {
+        // From Chromaprint's own compressor tests
+        assert_eq!(compress(&[1], 0), [0, 0, 0, 1, 1]);
+        assert_eq!(compress(&[7], 0), [0, 0, 0, 1, 73, 0]);
+        assert_eq!(compress(&[1 << 6], 0), [0, 0, 0, 1, 7, 0]);
+        assert_eq!(compress(&[1 << 8], 0), [0, 0, 0, 1, 7, 2]);
+        assert_eq!(compress(&[1, 0], 0), [0, 0, 0, 2, 65, 0]);
+        assert_eq!(compress(&[1, 1], 0), [0, 0, 0, 2, 1, 0]);
+
+        // chromaprint_encode_fingerprint({1, 0}, algorithm 1, base64)
+        assert_eq!(encode(&[1, 0]), "AQAAAkEA");
+    }
+
+    #[test]
+    fn test_tone_vector() {
+        let subfingerprints = compute(&tone(band_zero_center(), SAMPLE_RATE, 10.0), &CancellationToken::new()).unwrap();
+
+        // 78 frames, minus 5 consumed by the chroma filter, minus 15 by the classifiers
+        assert_eq!(subfingerprints.len(), 58);
+        assert!(subfingerprints.iter().all(|&s| s == TONE_SUBFINGERPRINT));
+        assert_eq!(encode(&subfingerprints), "AQAAOkmUaEkSZSoAAAAAAAAAAAAAAAAAAAAAAAAAAAAA");
+    }
+
+    #[test]
+    fn test_resampled_tone_matches_native_rate() {
+        let native = compute(&tone(band_zero_center(), SAMPLE_RATE, 10.0), &CancellationToken::new()).unwrap();
+        let resampled = compute(&tone(band_zero_center(), 44100, 10.0), &CancellationToken::new()).unwrap();
+
+        assert_eq!(resampled.len(), native.len());
+        assert!(resampled.iter().all(|&s| s == TONE_SUBFINGERPRINT));
+    }
+
+    #[test]
+    fn test_short_input_yields_empty_fingerprint() {
+        assert!(compute(&tone(440.0, SAMPLE_RATE, 0.5), &CancellationToken::new()).unwrap().is_empty());
+        assert_eq!(encode(&[]), "AQAAAA");
+    }
+
+    #[test]
+    fn test_match_finds_offset() {
+        let a: Vec<u32> = (0..100u32).map(|i| i.wrapping_mul(0x9E37_79B9)).collect();
+        let b = a[10..].to_vec();
+
+        let result = match_subfingerprints(&a, &b);
+        assert!(result.is_match);
+        assert_eq!(result.similarity, 1.0);
+        assert_eq!(result.time_offset_frames, -10);
+        assert_eq!(result.matching_pairs, 90);
+    }
+}
@@ -0,0 +1,898 @@
+//! Fingerprint database for content matching.
+//!
+//! Every hash pair of an added fingerprint becomes a posting: its 64-bit pair
+//! key, the content it came from and its anchor time. A query looks up the
+//! postings for its own pairs and votes per content and time offset.
+//! A match needs both the similarity threshold and
+//! [`DEFAULT_MIN_MATCHING_PAIRS`] aligned pairs (see
+//! [`with_min_matching_pairs`](FingerprintDatabase::with_min_matching_pairs)),
+//! so a sparse query cannot match on a handful of chance collisions.
+//!
+//! [`FingerprintDatabase::audit`] reports how postings spread over content
+//! and keys, for tuning the constellation settings to a catalog.
+//!
+//! # Backends
+//!
+//! [`DatabaseBackend::Memory`] keeps postings in a hash map.
+//! [`DatabaseBackend::Disk`] splits the key space into shards, each a file of
+//! postings sorted by key, and memory-maps them so a query only pages in the
+//! records it binary-searches. Postings added to a disk database are buffered
+//! in memory until [`FingerprintDatabase::flush`] merges them into the shards
+//! they belong to.
+//!
+//! # On-Disk Format
+//!
+//! A database directory holds `manifest.json` (format version, shard count
+//! and content ids in ordinal order) and one `shard-NNNNN.bin` per shard of
+//! 16-byte records: `key: u64, content: u32, anchor_time: u32`, little-endian
+//! and sorted. A missing shard file is an empty shard. Content ids are only
+//! ever appended, so the manifest is written before the shards it covers.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+
+use super::{mix_key, pair_key, FingerprintConfig, Fingerprinter, CANONICAL_SAMPLE_RATE};
+use crate::types::AudioFingerprint;
+
+/// Version of the on-disk format.
+const DISK_FORMAT_VERSION: u32 = 1;
+
+/// Number of shards a new on-disk database is split into.
+pub const DISK_SHARDS: usize = 256;
+
+/// Size of one posting record in a shard file.
+const RECORD_SIZE: usize = 16;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Aligned hash pairs a query needs to match, whatever its similarity.
+pub const DEFAULT_MIN_MATCHING_PAIRS: u32 = 8;
+
+/// Where a [`FingerprintDatabase`] keeps its postings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatabaseBackend {
+    /// All postings in memory
+    Memory,
+    /// Memory-mapped shard files in a directory, created if missing
+    Disk(PathBuf),
+}
+
+/// Content ordinal and anchor time of each posting, by pair key.
+type Postings = HashMap<u64, Vec<(u32, u32)>>;
+
+/// A posting as stored in a shard file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Posting {
+    key: u64,
+    content: u32,
+    anchor_time: u32,
+}
+
+impl Posting {
+    fn to_bytes(self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+        bytes[..8].copy_from_slice(&self.key.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.content.to_le_bytes());
+        bytes[12..].copy_from_slice(&self.anchor_time.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            key: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            content: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            anchor_time: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+}
+
+/// Contents of `manifest.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    shards: usize,
+    content_ids: Vec<String>,
+}
+
+/// A memory-mapped shard file.
+#[derive(Default)]
+struct Shard {
+    /// `None` for a missing or empty file, which cannot be mapped
+    map: Option<Mmap>,
+}
+
+impl Shard {
+    fn open(path: &Path) -> Result<Self> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to open shard {}", path.display())),
+        };
+        let len = file.metadata()?.len() as usize;
+        if !len.is_multiple_of(RECORD_SIZE) {
+            bail!("Corrupt shard {}: {} bytes is not a whole number of records", path.display(), len);
+        }
+        if len == 0 {
+            return Ok(Self::default());
+        }
+
+        // SAFETY: shard files are replaced by renaming a new file over them,
+        // never written in place, so the mapped pages do not change under us.
+        let map = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("Failed to map shard {}", path.display()))?;
+        Ok(Self { map: Some(map) })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+
+    fn len(&self) -> usize {
+        self.bytes().len() / RECORD_SIZE
+    }
+
+    fn record(&self, i: usize) -> Posting {
+        Posting::from_bytes(&self.bytes()[i * RECORD_SIZE..(i + 1) * RECORD_SIZE])
+    }
+
+    fn records(&self) -> impl Iterator<Item = Posting> + '_ {
+        self.bytes().chunks_exact(RECORD_SIZE).map(Posting::from_bytes)
+    }
+
+    /// Call `f` with the content and anchor time of each posting for `key`.
+    fn for_each(&self, key: u64, mut f: impl FnMut(u32, u32)) {
+        // Binary search for the first record with this key
+        let (mut lo, mut hi) = (0, self.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.record(mid).key < key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+
+        for posting in (lo..self.len()).map(|i| self.record(i)).take_while(|p| p.key == key) {
+            f(posting.content, posting.anchor_time);
+        }
+    }
+}
+
+/// Posting storage of a [`FingerprintDatabase`].
+enum Store {
+    Memory(Postings),
+    Disk {
+        dir: PathBuf,
+        shards: Vec<Shard>,
+        /// Postings added since the last flush
+        pending: Postings,
+    },
+}
+
+/// Fingerprint database for content matching.
+///
+/// Only constellation fingerprints are indexed.
+pub struct FingerprintDatabase {
+    /// Content ids by ordinal
+    content_ids: Vec<String>,
+    /// Ordinal of each content id
+    ordinals: HashMap<String, u32>,
+    store: Store,
+    /// Aligned pairs a match needs on top of the similarity threshold
+    min_matching_pairs: u32,
+}
+
+impl FingerprintDatabase {
+    /// Create a new empty in-memory database.
+    pub fn new() -> Self {
+        Self {
+            content_ids: Vec::new(),
+            ordinals: HashMap::new(),
+            store: Store::Memory(Postings::new()),
+            min_matching_pairs: DEFAULT_MIN_MATCHING_PAIRS,
+        }
+    }
+
+    /// Create a database with the given backend.
+    ///
+    /// A disk database opens the shards already in its directory, so this
+    /// also reopens a database written by [`flush`](Self::flush) or
+    /// [`save`](Self::save).
+    pub fn with_backend(backend: DatabaseBackend) -> Result<Self> {
+        let dir = match backend {
+            DatabaseBackend::Memory => return Ok(Self::new()),
+            DatabaseBackend::Disk(dir) => dir,
+        };
+
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create database directory {}", dir.display()))?;
+        let manifest = if dir.join(MANIFEST_FILE).exists() {
+            read_manifest(&dir)?
+        } else {
+            Manifest { version: DISK_FORMAT_VERSION, shards: DISK_SHARDS, content_ids: Vec::new() }
+        };
+        let shards = (0..manifest.shards)
+            .map(|shard| Shard::open(&shard_path(&dir, shard)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            ordinals: ordinals(&manifest.content_ids),
+            content_ids: manifest.content_ids,
+            store: Store::Disk { dir, shards, pending: Postings::new() },
+            min_matching_pairs: DEFAULT_MIN_MATCHING_PAIRS,
+        })
+    }
+
+    /// Load a database directory written by [`save`](Self::save) or
+    /// [`flush`](Self::flush) into memory.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let manifest = read_manifest(dir)?;
+
+        let mut postings = Postings::new();
+        for shard in 0..manifest.shards {
+            for posting in Shard::open(&shard_path(dir, shard))?.records() {
+                postings.entry(posting.key).or_default().push((posting.content, posting.anchor_time));
+            }
+        }
+
+        Ok(Self {
+            ordinals: ordinals(&manifest.content_ids),
+            content_ids: manifest.content_ids,
+            store: Store::Memory(postings),
+            min_matching_pairs: DEFAULT_MIN_MATCHING_PAIRS,
+        })
+    }
+
+    /// Require at least `pairs` aligned hash pairs for a match, in addition
+    /// to the similarity threshold.
+    pub fn with_min_matching_pairs(mut self, pairs: u32) -> Self {
+        self.min_matching_pairs = pairs;
+        self
+    }
+
+    /// Number of content items in the database.
+    pub fn len(&self) -> usize {
+        self.content_ids.len()
+    }
+
+    /// Whether the database has no content.
+    pub fn is_empty(&self) -> bool {
+        self.content_ids.is_empty()
+    }
+
+    /// Add a fingerprint to the database.
+    ///
+    /// A disk database keeps the new postings in memory until the next
+    /// [`flush`](Self::flush); flush periodically when adding a large catalog.
+    pub fn add(&mut self, content_id: &str, fingerprint: &AudioFingerprint) {
+        let fingerprinter = Fingerprinter::new();
+        let pairs = fingerprinter.generate_hash_pairs(&fingerprint.points);
+
+        let content = match self.ordinals.get(content_id) {
+            Some(&ordinal) => ordinal,
+            None => {
+                let ordinal = self.content_ids.len() as u32;
+                self.content_ids.push(content_id.to_string());
+                self.ordinals.insert(content_id.to_string(), ordinal);
+                ordinal
+            }
+        };
+
+        let postings = match &mut self.store {
+            Store::Memory(postings) => postings,
+            Store::Disk { pending, .. } => pending,
+        };
+        for pair in pairs {
+            postings.entry(pair_key(&pair)).or_default().push((content, pair.anchor_time));
+        }
+    }
+
+    /// Query the database for matching content.
+    ///
+    /// A content item matches when its best time offset lines up at least
+    /// `threshold` of the query's hash pairs and at least the minimum
+    /// number of matching pairs.
+    pub fn query(&self, fingerprint: &AudioFingerprint, threshold: f32) -> Vec<DatabaseMatch> {
+        let fingerprinter = Fingerprinter::new();
+        let pairs = fingerprinter.generate_hash_pairs(&fingerprint.points);
+
+        // Count matches per content
+        let mut content_matches: HashMap<u32, HashMap<i64, u32>> = HashMap::new();
+        let mut vote = |content: u32, db_time: u32, anchor_time: u32| {
+            let offset = anchor_time as i64 - db_time as i64;
+            *content_matches
+                .entry(content)
+                .or_default()
+                .entry(offset)
+                .or_default() += 1;
+        };
+
+        match &self.store {
+            Store::Memory(postings) => {
+                for pair in &pairs {
+                    for &(content, db_time) in postings.get(&pair_key(pair)).into_iter().flatten() {
+                        vote(content, db_time, pair.anchor_time);
+                    }
+                }
+            }
+            Store::Disk { shards, pending, .. } => {
+                // Visit the shards one at a time, in key order within each
+                let mut lookups: Vec<(usize, u64, u32)> = pairs.iter()
+                    .map(|pair| {
+                        let key = pair_key(pair);
+                        (shard_of(key, shards.len()), key, pair.anchor_time)
+                    })
+                    .collect();
+                lookups.sort_unstable();
+
+                for (shard, key, anchor_time) in lookups {
+                    shards[shard].for_each(key, |content, db_time| vote(content, db_time, anchor_time));
+                    for &(content, db_time) in pending.get(&key).into_iter().flatten() {
+                        vote(content, db_time, anchor_time);
+                    }
+                }
+            }
+        }
+
+        // Find best matches
+        let mut results: Vec<DatabaseMatch> = content_matches.iter()
+            .filter_map(|(&content, offsets)| {
+                let best_count = offsets.values().max().copied().unwrap_or(0);
+                let similarity = best_count as f32 / pairs.len() as f32;
+
+                if similarity >= threshold && best_count >= self.min_matching_pairs {
+                    Some(DatabaseMatch {
+                        content_id: self.content_ids.get(content as usize)?.clone(),
+                        similarity,
+                        matching_pairs: best_count,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal));
+        results
+    }
+
+    /// Report how hash pairs are spread across content and keys, keeping
+    /// the `top_keys` keys shared by the most content items.
+    ///
+    /// Reads every posting, so it takes about as long as a full load.
+    pub fn audit(&self, top_keys: usize) -> DatabaseAudit {
+        let frame_secs = FingerprintConfig::default().hop_size as f64 / CANONICAL_SAMPLE_RATE as f64;
+        let mut contents = vec![(0u64, u32::MAX, 0u32); self.content_ids.len()];
+        let mut audit = DatabaseAudit::default();
+        let mut heap = BinaryHeap::new();
+        let mut owners = Vec::new();
+
+        self.for_each_key(|key, postings| {
+            owners.clear();
+            for &(content, anchor_time) in postings {
+                if let Some((pairs, first, last)) = contents.get_mut(content as usize) {
+                    *pairs += 1;
+                    *first = (*first).min(anchor_time);
+                    *last = (*last).max(anchor_time);
+                }
+                owners.push(content);
+            }
+            owners.sort_unstable();
+            owners.dedup();
+
+            audit.total_pairs += postings.len() as u64;
+            audit.distinct_keys += 1;
+            if owners.len() > 1 {
+                audit.shared_keys += 1;
+            }
+            // Min-heap of the most collided keys so far
+            heap.push(Reverse((owners.len() as u32, postings.len() as u32, key)));
+            if heap.len() > top_keys {
+                heap.pop();
+            }
+        });
+
+        audit.contents = self.content_ids.iter().zip(contents)
+            .map(|(content_id, (pairs, first, last))| {
+                let span_secs = if pairs == 0 { 0.0 } else { (last - first + 1) as f64 * frame_secs };
+                ContentAudit {
+                    content_id: content_id.clone(),
+                    pairs,
+                    span_secs,
+                    pairs_per_second: if span_secs > 0.0 { pairs as f64 / span_secs } else { 0.0 },
+                }
+            })
+            .collect();
+        audit.pairs_per_content = Distribution::of(audit.contents.iter().map(|c| c.pairs as f64).collect());
+        audit.pairs_per_second = Distribution::of(audit.contents.iter().map(|c| c.pairs_per_second).collect());
+
+        let mut top = heap.into_vec();
+        top.sort_unstable();
+        audit.top_keys = top.into_iter()
+            .map(|Reverse((contents, postings, key))| KeyCollision {
+                key,
+                anchor_freq: (key >> 40) as u32,
+                target_freq: ((key >> 16) & 0xff_ffff) as u32,
+                time_delta: (key & 0xffff) as u32,
+                contents,
+                postings,
+            })
+            .collect();
+        audit
+    }
+
+    /// Call `f` with each pair key and all of its postings, once per key.
+    fn for_each_key(&self, mut f: impl FnMut(u64, &[(u32, u32)])) {
+        let (shards, pending) = match &self.store {
+            Store::Memory(postings) => {
+                for (&key, entries) in postings {
+                    f(key, entries);
+                }
+                return;
+            }
+            Store::Disk { shards, pending, .. } => (shards, pending),
+        };
+
+        // Shards are sorted by key, so each key's postings are contiguous
+        let mut current = None;
+        let mut group = Vec::new();
+        for (shard, added) in shards.iter().zip(shard_buckets(pending, shards.len())) {
+            for posting in merge_sorted(shard.records(), added) {
+                if current != Some(posting.key) {
+                    if let Some(key) = current {
+                        f(key, &group);
+                    }
+                    current = Some(posting.key);
+                    group.clear();
+                }
+                group.push((posting.content, posting.anchor_time));
+            }
+        }
+        if let Some(key) = current {
+            f(key, &group);
+        }
+    }
+
+    /// Merge the postings added since the last flush into a disk database's
+    /// shards. Does nothing for an in-memory database.
+    ///
+    /// Only the shards that received postings are rewritten.
+    pub fn flush(&mut self) -> Result<()> {
+        let Store::Disk { dir, shards, pending } = &mut self.store else {
+            return Ok(());
+        };
+
+        write_manifest(dir, shards.len(), &self.content_ids)?;
+        for (shard, added) in shard_buckets(pending, shards.len()).into_iter().enumerate() {
+            if added.is_empty() {
+                continue;
+            }
+            let path = shard_path(dir, shard);
+            let temp = write_shard(&path, shards[shard].records(), added)?;
+
+            // Unmap before replacing the file; some platforms refuse to
+            // rename over a mapped file
+            shards[shard] = Shard::default();
+            fs::rename(&temp, &path)
+                .with_context(|| format!("Failed to replace shard {}", path.display()))?;
+            shards[shard] = Shard::open(&path)?;
+        }
+        // Release the buffer rather than keep its capacity around
+        *pending = Postings::new();
+
+        Ok(())
+    }
+
+    /// Write the whole database to a directory in the on-disk format, ready
+    /// for [`load`](Self::load) or [`DatabaseBackend::Disk`].
+    ///
+    /// Saving a disk database to its own directory is a [`flush`](Self::flush).
+    pub fn save(&mut self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        let (shards, pending) = match &self.store {
+            Store::Memory(postings) => (&[][..], postings),
+            Store::Disk { dir: own, .. } if same_dir(own, dir) => return self.flush(),
+            Store::Disk { shards, pending, .. } => (&shards[..], pending),
+        };
+        let shard_count = if shards.is_empty() { DISK_SHARDS } else { shards.len() };
+
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create database directory {}", dir.display()))?;
+        write_manifest(dir, shard_count, &self.content_ids)?;
+        for (shard, added) in shard_buckets(pending, shard_count).into_iter().enumerate() {
+            let existing = shards.get(shard).into_iter().flat_map(Shard::records);
+            let path = shard_path(dir, shard);
+            let temp = write_shard(&path, existing, added)?;
+            fs::rename(&temp, &path)
+                .with_context(|| format!("Failed to replace shard {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for FingerprintDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Match result from database query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseMatch {
+    /// Content ID of the matched item
+    pub content_id: String,
+    /// Similarity score
+    pub similarity: f32,
+    /// Number of matching hash pairs
+    pub matching_pairs: u32,
+}
+
+/// Hash-space diagnostics from [`FingerprintDatabase::audit`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatabaseAudit {
+    /// Postings in the database, one per indexed hash pair
+    pub total_pairs: u64,
+    /// Distinct pair keys
+    pub distinct_keys: u64,
+    /// Keys with postings from more than one content item
+    pub shared_keys: u64,
+    /// Hash pairs indexed per content item
+    pub pairs_per_content: Distribution,
+    /// Hash pairs per second of indexed audio, per content item
+    pub pairs_per_second: Distribution,
+    /// Statistics for each content item, in the order they were added
+    pub contents: Vec<ContentAudit>,
+    /// Keys shared by the most content items, most collided first
+    pub top_keys: Vec<KeyCollision>,
+}
+
+/// Postings of one content item in a [`DatabaseAudit`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentAudit {
+    /// Content ID of the item
+    pub content_id: String,
+    /// Hash pairs indexed for the content
+    pub pairs: u64,
+    /// Time from the first to the last anchor, in seconds
+    pub span_secs: f64,
+    /// Hash pairs per second of the span; sparse audio scores low
+    pub pairs_per_second: f64,
+}
+
+/// A pair key shared across content in a [`DatabaseAudit`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyCollision {
+    /// 64-bit pair key
+    pub key: u64,
+    /// Frequency bin of the anchor peak
+    pub anchor_freq: u32,
+    /// Frequency bin of the target peak
+    pub target_freq: u32,
+    /// Frames between the anchor and the target
+    pub time_delta: u32,
+    /// Distinct content items with the key
+    pub contents: u32,
+    /// Postings with the key
+    pub postings: u32,
+}
+
+/// Summary statistics of a set of values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    /// Smallest value
+    pub min: f64,
+    /// 10th percentile
+    pub p10: f64,
+    /// 50th percentile
+    pub median: f64,
+    /// 90th percentile
+    pub p90: f64,
+    /// Largest value
+    pub max: f64,
+    /// Arithmetic mean
+    pub mean: f64,
+}
+
+impl Distribution {
+    /// Statistics of `values`, all zero when there are none.
+    fn of(mut values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(f64::total_cmp);
+        let percentile = |p: f64| values[((values.len() - 1) as f64 * p).round() as usize];
+        Self {
+            min: values[0],
+            p10: percentile(0.1),
+            median: percentile(0.5),
+            p90: percentile(0.9),
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+        }
+    }
+}
+
+/// Shard holding a pair key.
+fn shard_of(key: u64, shards: usize) -> usize {
+    (mix_key(key) % shards as u64) as usize
+}
+
+fn shard_path(dir: &Path, shard: usize) -> PathBuf {
+    dir.join(format!("shard-{:05}.bin", shard))
+}
+
+fn ordinals(content_ids: &[String]) -> HashMap<String, u32> {
+    content_ids.iter().enumerate().map(|(i, id)| (id.clone(), i as u32)).collect()
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    matches!((fs::canonicalize(a), fs::canonicalize(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Split postings by shard, each sorted.
+fn shard_buckets(postings: &Postings, shards: usize) -> Vec<Vec<Posting>> {
+    let mut buckets = vec![Vec::new(); shards];
+    for (&key, entries) in postings {
+        buckets[shard_of(key, shards)].extend(
+            entries.iter().map(|&(content, anchor_time)| Posting { key, content, anchor_time }),
+        );
+    }
+    for bucket in &mut buckets {
+        bucket.sort_unstable();
+    }
+    buckets
+}
+
+/// Write the merge of two sorted posting streams next to `path`, returning
+/// the temporary file to rename over it.
+fn write_shard(path: &Path, existing: impl Iterator<Item = Posting>, added: Vec<Posting>) -> Result<PathBuf> {
+    let temp = path.with_extension("bin.tmp");
+    let file = File::create(&temp)
+        .with_context(|| format!("Failed to create {}", temp.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    for posting in merge_sorted(existing, added) {
+        writer.write_all(&posting.to_bytes())?;
+    }
+    writer.into_inner()?.sync_all()?;
+
+    Ok(temp)
+}
+
+/// Merge two sorted posting streams.
+fn merge_sorted(existing: impl Iterator<Item = Posting>, added: Vec<Posting>) -> impl Iterator<Item = Posting> {
+    let mut existing = existing.peekable();
+    let mut added = added.into_iter().peekable();
+    std::iter::from_fn(move || match (existing.peek(), added.peek()) {
+        (Some(a), Some(b)) if a <= b => existing.next(),
+        (_, Some(_)) => added.next(),
+        _ => existing.next(),
+    })
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest> {
+    let path = dir.join(MANIFEST_FILE);
+    let json = fs::read(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let manifest: Manifest = serde_json::from_slice(&json)
+        .with_context(|| format!("Invalid manifest {}", path.display()))?;
+
+    if manifest.version != DISK_FORMAT_VERSION {
+        bail!("Unsupported fingerprint database version {} (expected {})", manifest.version, DISK_FORMAT_VERSION);
+    }
+    if manifest.shards == 0 {
+        bail!("Invalid manifest {}: no shards", path.display());
+    }
+    Ok(manifest)
+}
+
+fn write_manifest(dir: &Path, shards: usize, content_ids: &[String]) -> Result<()> {
+    let manifest = Manifest { version: DISK_FORMAT_VERSION, shards, content_ids: content_ids.to_vec() };
+    let path = dir.join(MANIFEST_FILE);
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, serde_json::to_vec(&manifest)?)
+        .with_context(|| format!("Failed to write {}", temp.display()))?;
+    fs::rename(&temp, &path)
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fingerprint::{CANONICAL_SAMPLE_RATE, FINGERPRINT_VERSION};
+    use crate::types::{FingerprintAlgorithm, FingerprintPoint};
+
+    /// Fingerprint of random constellation points, two per frame.
+    fn synthetic_fingerprint(seed: u64, frames: u32) -> AudioFingerprint {
+        let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        let mut next_bin = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 2048) as u32
+        };
+        let points = (0..frames)
+            .flat_map(|frame| [frame, frame])
+            .map(|time_offset| FingerprintPoint { time_offset, freq_bin: next_bin(), amplitude: 128 })
+            .collect();
+
+        AudioFingerprint {
+            hash: String::new(),
+            version: FINGERPRINT_VERSION,
+            algorithm: FingerprintAlgorithm::Constellation,
+            sample_rate: CANONICAL_SAMPLE_RATE,
+            points,
+            subfingerprints: Vec::new(),
+            chromaprint: None,
+            duration_secs: 0.0,
+            low_entropy: false,
+        }
+    }
+
+    /// A clip of a fingerprint's frames, re-timed to start at zero.
+    fn excerpt(fingerprint: &AudioFingerprint, start: u32, end: u32) -> AudioFingerprint {
+        let mut excerpt = fingerprint.clone();
+        excerpt.points = fingerprint.points.iter()
+            .filter(|p| (start..end).contains(&p.time_offset))
+            .map(|p| FingerprintPoint { time_offset: p.time_offset - start, ..p.clone() })
+            .collect();
+        excerpt
+    }
+
+    fn summary(matches: &[DatabaseMatch]) -> Vec<(String, u32)> {
+        let mut summary: Vec<_> = matches.iter().map(|m| (m.content_id.clone(), m.matching_pairs)).collect();
+        summary.sort();
+        summary
+    }
+
+    #[test]
+    fn test_disk_backend_matches_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut memory = FingerprintDatabase::new();
+        let mut disk = FingerprintDatabase::with_backend(DatabaseBackend::Disk(dir.path().to_path_buf())).unwrap();
+
+        let fingerprints: Vec<_> = (0..20).map(|i| synthetic_fingerprint(i, 200)).collect();
+        for (i, fingerprint) in fingerprints.iter().enumerate() {
+            memory.add(&format!("track-{}", i), fingerprint);
+            disk.add(&format!("track-{}", i), fingerprint);
+            // Leave the last few unflushed so queries see both
+            if i == 14 {
+                disk.flush().unwrap();
+            }
+        }
+
+        for (i, query) in [(3, excerpt(&fingerprints[3], 50, 120)), (17, excerpt(&fingerprints[17], 10, 90))] {
+            let expected = memory.query(&query, 0.1);
+            assert_eq!(expected[0].content_id, format!("track-{}", i));
+            assert_eq!(summary(&disk.query(&query, 0.1)), summary(&expected));
+        }
+    }
+
+    #[test]
+    fn test_disk_database_reopens() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = DatabaseBackend::Disk(dir.path().to_path_buf());
+        let first = synthetic_fingerprint(1, 200);
+        let second = synthetic_fingerprint(2, 200);
+
+        let mut db = FingerprintDatabase::with_backend(backend.clone()).unwrap();
+        db.add("first", &first);
+        db.flush().unwrap();
+        drop(db);
+
+        // Adding after a reopen merges into the existing shards
+        let mut db = FingerprintDatabase::with_backend(backend.clone()).unwrap();
+        assert_eq!(db.len(), 1);
+        db.add("second", &second);
+        db.save(dir.path()).unwrap();
+        drop(db);
+
+        let db = FingerprintDatabase::with_backend(backend).unwrap();
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.query(&excerpt(&first, 20, 100), 0.5)[0].content_id, "first");
+        assert_eq!(db.query(&excerpt(&second, 20, 100), 0.5)[0].content_id, "second");
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let fingerprints: Vec<_> = (0..5).map(|i| synthetic_fingerprint(i, 100)).collect();
+        let mut db = FingerprintDatabase::new();
+        for (i, fingerprint) in fingerprints.iter().enumerate() {
+            db.add(&format!("track-{}", i), fingerprint);
+        }
+        db.save(dir.path()).unwrap();
+
+        let query = excerpt(&fingerprints[2], 30, 80);
+        let expected = summary(&db.query(&query, 0.1));
+        let loaded = FingerprintDatabase::load(dir.path()).unwrap();
+        let mapped = FingerprintDatabase::with_backend(DatabaseBackend::Disk(dir.path().to_path_buf())).unwrap();
+
+        assert_eq!(loaded.len(), 5);
+        assert_eq!(summary(&loaded.query(&query, 0.1)), expected);
+        assert_eq!(summary(&mapped.query(&query, 0.1)), expected);
+    }
+
+    #[test]
+    fn test_min_matching_pairs() {
+        let fingerprint = synthetic_fingerprint(3, 200);
+        let mut db = FingerprintDatabase::new();
+        db.add("track", &fingerprint);
+
+        // A two-frame clip lines up perfectly, but on too few pairs to trust
+        let clip = excerpt(&fingerprint, 100, 102);
+        assert!(db.query(&clip, 0.5).is_empty());
+
+        let db = db.with_min_matching_pairs(1);
+        let matches = db.query(&clip, 0.5);
+        assert_eq!(matches[0].content_id, "track");
+        assert!(matches[0].matching_pairs < DEFAULT_MIN_MATCHING_PAIRS);
+    }
+
+    #[test]
+    fn test_audit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut memory = FingerprintDatabase::new();
+        let mut disk = FingerprintDatabase::with_backend(DatabaseBackend::Disk(dir.path().to_path_buf())).unwrap();
+        let shared = synthetic_fingerprint(9, 100);
+        let fingerprints = [synthetic_fingerprint(1, 200), synthetic_fingerprint(2, 50), shared.clone(), shared];
+        for (i, fingerprint) in fingerprints.iter().enumerate() {
+            memory.add(&format!("track-{}", i), fingerprint);
+            disk.add(&format!("track-{}", i), fingerprint);
+            if i == 1 {
+                disk.flush().unwrap();
+            }
+        }
+
+        let audit = memory.audit(5);
+        let pairs: Vec<u64> = audit.contents.iter().map(|c| c.pairs).collect();
+        assert_eq!(audit.total_pairs, pairs.iter().sum::<u64>());
+        assert_eq!(pairs[2], pairs[3]);
+        assert!(pairs[0] > pairs[1]);
+        assert_eq!(audit.pairs_per_content.max, pairs[0] as f64);
+        assert!(audit.contents[0].span_secs > audit.contents[1].span_secs);
+        assert!(audit.pairs_per_second.min > 0.0);
+
+        // The duplicated track shares every key
+        assert!(audit.shared_keys >= audit.distinct_keys / 4);
+        assert_eq!(audit.top_keys.len(), 5);
+        assert!(audit.top_keys.iter().all(|k| k.contents >= 2));
+        let top = &audit.top_keys[0];
+        assert_eq!(((top.anchor_freq as u64) << 40) ^ ((top.target_freq as u64) << 16) ^ top.time_delta as u64, top.key);
+
+        let on_disk = disk.audit(5);
+        assert_eq!(on_disk.total_pairs, audit.total_pairs);
+        assert_eq!(on_disk.distinct_keys, audit.distinct_keys);
+        assert_eq!(on_disk.shared_keys, audit.shared_keys);
+        assert_eq!(on_disk.pairs_per_content, audit.pairs_per_content);
+        assert_eq!(on_disk.top_keys[0].contents, top.contents);
+    }
+
+    #[test]
+    fn test_corrupt_shard_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = FingerprintDatabase::new();
+        db.add("track", &synthetic_fingerprint(7, 100));
+        db.save(dir.path()).unwrap();
+
+        let path = shard_path(dir.path(), (0..DISK_SHARDS)
+            .find(|&shard| fs::metadata(shard_path(dir.path(), shard)).unwrap().len() > 0)
+            .unwrap());
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.pop();
+        fs::write(&path, bytes).unwrap();
+
+        assert!(FingerprintDatabase::load(dir.path()).is_err());
+        assert!(FingerprintDatabase::with_backend(DatabaseBackend::Disk(dir.path().to_path_buf())).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+//! Intro and outro detection across the episodes of a series.
+//!
+//! Episodes of a series share their opening (and often closing) music.
+//! [`IntroDetector`] matches the constellation fingerprints of every pair of
+//! episodes over the first (or last) few minutes, using the same hash-pair
+//! alignment as [`Fingerprinter::match_fingerprints`]: each shared hash pair
+//! votes for the time offset between the two episodes, and the matches at
+//! the winning offset mark the shared audio. Their longest run without a
+//! long gap is the candidate region.
+//!
+//! The offset is free, so an intro that starts later in one episode, e.g.
+//! after a cold open, is found where it actually plays. A candidate is only
+//! reported when enough of the episode's hash pairs inside it match
+//! ([`IntroConfig::min_similarity`]) and it lasts long enough; episodes
+//! without the intro get no marker rather than a forced one.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use super::{Fingerprinter, HashPair, CANONICAL_SAMPLE_RATE, FINGERPRINT_VERSION};
+use crate::types::*;
+
+/// Hash pair key: anchor frequency, target frequency and time delta.
+type PairKey = (u32, u32, u32);
+
+/// Configuration for intro and outro detection.
+#[derive(Debug, Clone)]
+pub struct IntroConfig {
+    /// Seconds from the start searched for the intro, and from the end for
+    /// the outro; at most half of each episode is searched
+    pub search_secs: f64,
+    /// Also look for a shared outro
+    pub detect_outro: bool,
+    /// Shortest region reported (seconds)
+    pub min_duration_secs: f64,
+    /// Fraction (0-1) of the episode's hash pairs inside the region that
+    /// must match another episode
+    pub min_similarity: f32,
+    /// Longest gap between matches inside one region (seconds)
+    pub max_gap_secs: f64,
+}
+
+impl Default for IntroConfig {
+    fn default() -> Self {
+        Self {
+            search_secs: 300.0,
+            detect_outro: true,
+            min_duration_secs: 10.0,
+            min_similarity: 0.3,
+            max_gap_secs: 3.0,
+        }
+    }
+}
+
+/// Intro or outro of one episode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntroMarker {
+    /// Start of the region in seconds
+    pub start: f64,
+    /// End of the region in seconds
+    pub end: f64,
+    /// Mean similarity of the matches, scaled by the fraction of the other
+    /// episodes the region was found in (0-1)
+    pub confidence: f32,
+}
+
+impl IntroMarker {
+    /// Length of the region in seconds.
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Markers found for one episode.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EpisodeMarkers {
+    /// Shared region near the start, if any
+    pub intro: Option<IntroMarker>,
+    /// Shared region near the end, if any
+    pub outro: Option<IntroMarker>,
+}
+
+/// Part of an episode searched for a shared region.
+#[derive(Debug, Clone, Copy)]
+enum Search {
+    Start,
+    End,
+}
+
+/// Region of one episode shared with another, in frames.
+#[derive(Debug, Clone, Copy)]
+struct SharedRegion {
+    first: u32,
+    last: u32,
+    matches: usize,
+    similarity: f32,
+}
+
+/// Hash pairs of the searched part of one episode, indexed by key.
+struct EpisodePairs {
+    pairs: Vec<HashPair>,
+    index: HashMap<PairKey, Vec<u32>>,
+}
+
+/// Finds intros and outros shared by the episodes of a series.
+pub struct IntroDetector {
+    config: IntroConfig,
+    fingerprinter: Fingerprinter,
+}
+
+impl IntroDetector {
+    /// Create a detector with default configuration.
+    pub fn new() -> Self {
+        Self::with_config(IntroConfig::default())
+    }
+
+    /// Create a detector with custom configuration.
+    pub fn with_config(config: IntroConfig) -> Self {
+        Self { config, fingerprinter: Fingerprinter::new() }
+    }
+
+    /// Fingerprint each episode and detect its markers.
+    pub fn detect_audio(&self, episodes: &[AudioData]) -> FrequencyResult<Vec<EpisodeMarkers>> {
+        let fingerprints = episodes.iter()
+            .map(|audio| self.fingerprinter.fingerprint(audio))
+            .collect::<FrequencyResult<Vec<_>>>()?;
+        self.detect(&fingerprints)
+    }
+
+    /// Detect the markers of each episode, in the order given.
+    ///
+    /// Needs at least two current-version constellation fingerprints made
+    /// with the default [`FingerprintConfig`](super::FingerprintConfig).
+    pub fn detect(&self, episodes: &[AudioFingerprint]) -> FrequencyResult<Vec<EpisodeMarkers>> {
+        if episodes.len() < 2 {
+            return Err(anyhow!("Intro detection needs at least two episodes, got {}", episodes.len()).into());
+        }
+        if let Some(fp) = episodes.iter()
+            .find(|fp| fp.algorithm != FingerprintAlgorithm::Constellation || fp.version != FINGERPRINT_VERSION)
+        {
+            return Err(anyhow!(
+                "Intro detection needs version {} constellation fingerprints, got a version {} {} fingerprint",
+                FINGERPRINT_VERSION,
+                fp.version,
+                fp.algorithm
+            )
+            .into());
+        }
+
+        let intros = self.detect_search(episodes, Search::Start);
+        let outros = if self.config.detect_outro {
+            self.detect_search(episodes, Search::End)
+        } else {
+            vec![None; episodes.len()]
+        };
+
+        Ok(intros.into_iter()
+            .zip(outros)
+            .map(|(intro, outro)| EpisodeMarkers { intro, outro })
+            .collect())
+    }
+
+    /// Marker for each episode in the searched part.
+    fn detect_search(&self, episodes: &[AudioFingerprint], search: Search) -> Vec<Option<IntroMarker>> {
+        let pairs: Vec<EpisodePairs> = episodes.iter().map(|fp| self.episode_pairs(fp, search)).collect();
+
+        (0..episodes.len())
+            .map(|i| {
+                let regions: Vec<SharedRegion> = (0..episodes.len())
+                    .filter(|&j| j != i)
+                    .filter_map(|j| self.shared_region(&pairs[i], &pairs[j]))
+                    .collect();
+                debug!("Episode {} shares its {:?} with {} other episode(s)", i, search, regions.len());
+
+                // The strongest match sets the region; agreement sets the confidence
+                let best = regions.iter().max_by_key(|r| r.matches)?;
+                let mean_similarity = regions.iter().map(|r| r.similarity).sum::<f32>() / regions.len() as f32;
+                let agreement = regions.len() as f32 / (episodes.len() - 1) as f32;
+
+                Some(IntroMarker {
+                    start: self.fingerprinter.frames_to_secs(best.first),
+                    end: self.fingerprinter.frames_to_secs(best.last + 1),
+                    confidence: (mean_similarity * agreement).min(1.0),
+                })
+            })
+            .collect()
+    }
+
+    /// Hash pairs of the searched part of an episode.
+    fn episode_pairs(&self, fingerprint: &AudioFingerprint, search: Search) -> EpisodePairs {
+        // Intros are searched in the first half and outros in the second
+        let total = self.secs_to_frames(fingerprint.duration_secs);
+        let window = self.secs_to_frames(self.config.search_secs).min(total / 2);
+        let range = match search {
+            Search::Start => 0..window,
+            Search::End => total.saturating_sub(window)..u32::MAX,
+        };
+
+        let points: Vec<FingerprintPoint> = fingerprint.points.iter()
+            .filter(|p| range.contains(&p.time_offset))
+            .cloned()
+            .collect();
+        let pairs = self.fingerprinter.generate_hash_pairs(&points);
+
+        let mut index: HashMap<PairKey, Vec<u32>> = HashMap::new();
+        for pair in &pairs {
+            index.entry(key(pair)).or_default().push(pair.anchor_time);
+        }
+        EpisodePairs { pairs, index }
+    }
+
+    /// Region of `episode` whose audio also plays in `other`.
+    fn shared_region(&self, episode: &EpisodePairs, other: &EpisodePairs) -> Option<SharedRegion> {
+        // Matching pairs vote for the offset between the episodes
+        let mut offsets: HashMap<i64, Vec<u32>> = HashMap::new();
+        for pair in &episode.pairs {
+            for &time in other.index.get(&key(pair)).into_iter().flatten() {
+                offsets.entry(time as i64 - pair.anchor_time as i64).or_default().push(pair.anchor_time);
+            }
+        }
+        let best_offset = offsets.iter()
+            .max_by_key(|(&offset, times)| (times.len(), std::cmp::Reverse(offset)))
+            .map(|(&offset, _)| offset)?;
+
+        // Peaks can land a frame apart when the episodes aren't frame-aligned
+        let mut times: Vec<u32> = (best_offset - 1..=best_offset + 1)
+            .filter_map(|offset| offsets.get(&offset))
+            .flatten()
+            .copied()
+            .collect();
+        times.sort_unstable();
+        times.dedup();
+
+        // Longest run of matches without a long gap
+        let max_gap = self.secs_to_frames(self.config.max_gap_secs).max(1);
+        let mut runs: Vec<&[u32]> = Vec::new();
+        let mut start = 0;
+        for i in 1..=times.len() {
+            if i == times.len() || times[i] - times[i - 1] > max_gap {
+                runs.push(&times[start..i]);
+                start = i;
+            }
+        }
+        let run = runs.into_iter().max_by_key(|run| run.len())?;
+        let (first, last) = (run[0], run[run.len() - 1]);
+
+        // How much of the episode's audio in the region matched
+        let anchors: HashSet<u32> = episode.pairs.iter()
+            .map(|pair| pair.anchor_time)
+            .filter(|time| (first..=last).contains(time))
+            .collect();
+        let similarity = run.len() as f32 / anchors.len().max(1) as f32;
+
+        let duration = self.fingerprinter.frames_to_secs(last - first + 1);
+        if duration < self.config.min_duration_secs || similarity < self.config.min_similarity {
+            return None;
+        }
+        Some(SharedRegion { first, last, matches: run.len(), similarity })
+    }
+
+    /// Convert seconds to constellation frames.
+    fn secs_to_frames(&self, secs: f64) -> u32 {
+        (secs.max(0.0) * CANONICAL_SAMPLE_RATE as f64 / self.fingerprinter.config.hop_size as f64).round() as u32
+    }
+}
+
+impl Default for IntroDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Key of a hash pair.
+fn key(pair: &HashPair) -> PairKey {
+    (pair.anchor_freq, pair.target_freq, pair.time_delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// Notes cycling through a fixed sequence, switching every half second.
+    fn melody(notes: &[f32], secs: f32) -> Vec<f32> {
+        let sample_rate = 22050;
+        (0..(sample_rate as f32 * secs) as usize)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                let freq = notes[(t * 2.0) as usize % notes.len()];
+                0.5 * (2.0 * std::f32::consts::PI * freq * t).sin()
+            })
+            .collect()
+    }
+
+    /// Distinct noise for each episode body.
+    fn body(episode: usize, secs: f32) -> Vec<f32> {
+        let noise = testing::noise(22050, 40.0 * 5.0);
+        let start = episode * 40 * 22050;
+        noise.samples[start..start + (secs * 22050.0) as usize].to_vec()
+    }
+
+    fn episode(parts: &[Vec<f32>]) -> AudioData {
+        AudioData::new(parts.concat(), 22050)
+    }
+
+    fn intro() -> Vec<f32> {
+        melody(&[440.0, 554.4, 659.3, 493.9, 392.0, 587.3, 349.2, 523.3], 20.0)
+    }
+
+    fn outro() -> Vec<f32> {
+        melody(&[261.6, 329.6, 392.0, 311.1, 415.3, 277.2], 15.0)
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1.0, "expected {:.1}s, got {:.1}s", expected, actual);
+    }
+
+    #[test]
+    fn test_detects_shared_intro_and_outro() {
+        // The third episode has a cold open, the fourth no intro
+        let episodes = [
+            episode(&[intro(), body(0, 30.0), outro()]),
+            episode(&[intro(), body(1, 30.0), outro()]),
+            episode(&[body(2, 7.3), intro(), body(3, 30.0), outro()]),
+            episode(&[body(4, 40.0)]),
+        ];
+        let markers = IntroDetector::new().detect_audio(&episodes).unwrap();
+
+        for (i, intro_start) in [(0, 0.0), (1, 0.0), (2, 7.3)] {
+            let intro = markers[i].intro.as_ref().unwrap_or_else(|| panic!("episode {} has no intro", i));
+            assert_near(intro.start, intro_start);
+            assert_near(intro.end, intro_start + 20.0);
+            assert!(intro.confidence > 0.3, "confidence {}", intro.confidence);
+
+            let outro = markers[i].outro.as_ref().unwrap_or_else(|| panic!("episode {} has no outro", i));
+            assert_near(outro.end, episodes[i].duration_secs);
+            assert_near(outro.duration(), 15.0);
+        }
+        assert_eq!(markers[3], EpisodeMarkers::default());
+    }
+
+    #[test]
+    fn test_outro_detection_optional() {
+        let episodes = [
+            episode(&[intro(), body(0, 20.0), outro()]),
+            episode(&[intro(), body(1, 20.0), outro()]),
+        ];
+        let detector = IntroDetector::with_config(IntroConfig { detect_outro: false, ..Default::default() });
+        let markers = detector.detect_audio(&episodes).unwrap();
+        assert!(markers.iter().all(|m| m.intro.is_some() && m.outro.is_none()));
+    }
+
+    #[test]
+    fn test_rejects_unusable_input() {
+        let detector = IntroDetector::new();
+        let one = detector.fingerprinter.fingerprint(&episode(&[intro()])).unwrap();
+        assert!(detector.detect(std::slice::from_ref(&one)).is_err());
+
+        let mut chromaprint = one.clone();
+        chromaprint.algorithm = FingerprintAlgorithm::Chromaprint;
+        assert!(detector.detect(&[one, chromaprint]).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+//! Incremental constellation fingerprinting for live audio.
+//!
+//! [`StreamingFingerprinter`] runs the same resample, spectrogram, peak and
+//! hash pair steps as [`Fingerprinter`], one frame at a time. Hash pairs are
+//! folded into the running hash as soon as their target zone has passed, so
+//! only a bounded tail of the constellation is ever held in memory.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use kino_spectrum::Framer;
+use ring::digest::Context;
+use rustfft::{Fft, num_complex::Complex};
+use serde::{Deserialize, Serialize};
+
+use super::{hash_context, hash_pair, hex, FingerprintConfig, Fingerprinter, CANONICAL_SAMPLE_RATE, FINGERPRINT_VERSION};
+use crate::resample::StreamResampler;
+use crate::types::*;
+
+/// Configuration for [`StreamingFingerprinter`].
+#[derive(Debug, Clone)]
+pub struct StreamingFingerprintConfig {
+    /// Constellation settings, as used by the batch [`Fingerprinter`]
+    pub fingerprint: FingerprintConfig,
+    /// Length of the rolling window covered by windowed fingerprints (seconds)
+    pub window_secs: f64,
+    /// Interval between windowed fingerprints (seconds, 0 disables them)
+    pub emit_interval_secs: f64,
+    /// Length of constellation kept for [`StreamingFingerprinter::finalize`] (seconds)
+    ///
+    /// Longer streams finalize with only their most recent points, while the
+    /// hash still covers the whole stream.
+    pub max_history_secs: f64,
+}
+
+impl Default for StreamingFingerprintConfig {
+    fn default() -> Self {
+        Self {
+            fingerprint: FingerprintConfig::default(),
+            window_secs: 30.0,
+            emit_interval_secs: 10.0,
+            max_history_secs: 600.0,
+        }
+    }
+}
+
+/// Fingerprint of the most recent stretch of a stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowFingerprint {
+    /// Stream time at the start of the window in seconds
+    pub start_secs: f64,
+    /// Stream time at the end of the window in seconds
+    pub end_secs: f64,
+    /// Fingerprint of the window, with point times relative to its start
+    pub fingerprint: AudioFingerprint,
+}
+
+/// Constellation fingerprinter fed with chunks of mono audio.
+pub struct StreamingFingerprinter {
+    fingerprinter: Fingerprinter,
+    config: StreamingFingerprintConfig,
+    /// Input sample rate in Hz
+    sample_rate: u32,
+    resampler: StreamResampler,
+    framer: Framer,
+    fft: Arc<dyn Fft<f32>>,
+    fft_scratch: Vec<Complex<f32>>,
+    band_edges: Vec<usize>,
+    /// Frames analyzed so far
+    frames: u32,
+    /// Input samples received so far
+    samples_received: u64,
+    /// Anchor points whose target zone is still open
+    pending: VecDeque<FingerprintPoint>,
+    /// Running hash over all completed hash pairs
+    hash: Context,
+    /// Recent constellation points, oldest first
+    history: VecDeque<FingerprintPoint>,
+    /// Frame count when the last windowed fingerprint was emitted
+    last_emit: u32,
+}
+
+impl StreamingFingerprinter {
+    /// Create a streaming fingerprinter for audio at `sample_rate`.
+    pub fn new(sample_rate: u32) -> Self {
+        Self::build(sample_rate, StreamingFingerprintConfig::default())
+    }
+
+    /// Create a streaming fingerprinter with custom configuration.
+    ///
+    /// Only constellation fingerprints can be computed incrementally.
+    pub fn with_config(sample_rate: u32, config: StreamingFingerprintConfig) -> Result<Self> {
+        if config.fingerprint.algorithm != FingerprintAlgorithm::Constellation {
+            bail!("Streaming fingerprints support the constellation algorithm only");
+        }
+        if sample_rate == 0 {
+            bail!("Sample rate must be non-zero");
+        }
+        Ok(Self::build(sample_rate, config))
+    }
+
+    fn build(sample_rate: u32, config: StreamingFingerprintConfig) -> Self {
+        let fingerprinter = Fingerprinter::with_config(config.fingerprint.clone());
+        let fft = fingerprinter.analyzer.plan_fft();
+        let fft_scratch = fingerprinter.analyzer.fft_scratch(fft.as_ref());
+        let band_edges = fingerprinter.band_edges(config.fingerprint.fft_size / 2);
+
+        Self {
+            resampler: StreamResampler::new(sample_rate, CANONICAL_SAMPLE_RATE),
+            framer: Framer::new(config.fingerprint.fft_size, config.fingerprint.hop_size),
+            fingerprinter,
+            config,
+            sample_rate,
+            fft,
+            fft_scratch,
+            band_edges,
+            frames: 0,
+            samples_received: 0,
+            pending: VecDeque::new(),
+            hash: hash_context(),
+            history: VecDeque::new(),
+            last_emit: 0,
+        }
+    }
+
+    /// Feed mono samples, returning any windowed fingerprints now due.
+    ///
+    /// NaN samples are treated as silence and infinities as full scale.
+    pub fn process(&mut self, samples: &[f32]) -> Vec<WindowFingerprint> {
+        self.samples_received += samples.len() as u64;
+        let resampled = self.resampler.process(&replace_non_finite(samples));
+        self.process_resampled(&resampled)
+    }
+
+    /// Stream time processed so far in seconds.
+    pub fn current_time(&self) -> f64 {
+        self.samples_received as f64 / self.sample_rate as f64
+    }
+
+    /// Fingerprint the current rolling window.
+    pub fn window(&self) -> WindowFingerprint {
+        let start = self.frames.saturating_sub(self.frames_for(self.config.window_secs));
+        let points: Vec<FingerprintPoint> = self.history.iter()
+            .filter(|p| p.time_offset >= start)
+            .map(|p| FingerprintPoint { time_offset: p.time_offset - start, ..p.clone() })
+            .collect();
+
+        let pairs = self.fingerprinter.generate_hash_pairs(&points);
+        let start_secs = self.frame_time(start);
+        let end_secs = self.frame_time(self.frames);
+
+        WindowFingerprint {
+            start_secs,
+            end_secs,
+            fingerprint: AudioFingerprint {
+                hash: self.fingerprinter.compute_hash(&pairs),
+                version: FINGERPRINT_VERSION,
+                algorithm: FingerprintAlgorithm::Constellation,
+                sample_rate: CANONICAL_SAMPLE_RATE,
+                low_entropy: self.fingerprinter.is_low_entropy(points.len(), pairs.len()),
+                points,
+                subfingerprints: Vec::new(),
+                chromaprint: None,
+                duration_secs: end_secs - start_secs,
+            },
+        }
+    }
+
+    /// Flush the stream and produce its complete fingerprint.
+    ///
+    /// Matches [`Fingerprinter::fingerprint`] over the same samples, provided
+    /// the stream is no longer than `max_history_secs`.
+    pub fn finalize(mut self) -> FrequencyResult<AudioFingerprint> {
+        let tail = self.resampler.finish();
+        self.process_resampled(&tail);
+
+        if self.frames == 0 {
+            return Err(FrequencyError::TooShort {
+                samples: self.samples_received as usize,
+                required: self.fingerprinter.min_samples(self.sample_rate),
+            });
+        }
+        self.complete_anchors(true);
+        let points: Vec<FingerprintPoint> = self.history.into();
+        let pairs = self.fingerprinter.generate_hash_pairs(&points).len();
+
+        Ok(AudioFingerprint {
+            hash: hex::encode(self.hash.finish().as_ref()),
+            version: FINGERPRINT_VERSION,
+            algorithm: FingerprintAlgorithm::Constellation,
+            sample_rate: CANONICAL_SAMPLE_RATE,
+            low_entropy: self.fingerprinter.is_low_entropy(points.len(), pairs),
+            points,
+            subfingerprints: Vec::new(),
+            chromaprint: None,
+            duration_secs: self.samples_received as f64 / self.sample_rate as f64,
+        })
+    }
+
+    fn process_resampled(&mut self, samples: &[f32]) -> Vec<WindowFingerprint> {
+        let mut windows = Vec::new();
+
+        // Take the framer so frames can be analyzed as borrowed slices
+        let mut framer = std::mem::take(&mut self.framer);
+        framer.process(samples, |_, frame| {
+            self.process_frame(frame);
+
+            let interval = self.frames_for(self.config.emit_interval_secs);
+            if interval > 0 && self.frames - self.last_emit >= interval {
+                self.last_emit = self.frames;
+                windows.push(self.window());
+            }
+        });
+        self.framer = framer;
+
+        windows
+    }
+
+    fn process_frame(&mut self, frame: &[f32]) {
+        let spectrum = self.fingerprinter.analyzer
+            .frame_spectrum(self.fft.as_ref(), frame, &mut self.fft_scratch);
+        let mut peaks = Vec::new();
+        self.fingerprinter.frame_peaks(self.frames, &spectrum, &self.band_edges, &mut peaks);
+        self.frames += 1;
+
+        let points = self.fingerprinter.create_constellation(&peaks);
+        self.pending.extend(points.iter().cloned());
+        self.history.extend(points);
+        self.complete_anchors(false);
+
+        let keep = self.frames_for(self.config.max_history_secs)
+            .max(self.frames_for(self.config.window_secs));
+        let oldest = self.frames.saturating_sub(keep);
+        while self.history.front().is_some_and(|p| p.time_offset < oldest) {
+            self.history.pop_front();
+        }
+    }
+
+    /// Hash the pairs of every anchor whose target zone has been fully seen,
+    /// or of all remaining anchors at the end of the stream.
+    fn complete_anchors(&mut self, end_of_stream: bool) {
+        let zone = self.config.fingerprint.target_zone_frames as u32;
+        let mut pairs = Vec::new();
+
+        while let Some(anchor) = self.pending.front() {
+            if !end_of_stream && anchor.time_offset + zone >= self.frames {
+                break;
+            }
+
+            pairs.clear();
+            self.fingerprinter.anchor_pairs(anchor, self.pending.iter().skip(1), &mut pairs);
+            for pair in &pairs {
+                hash_pair(&mut self.hash, pair);
+            }
+            self.pending.pop_front();
+        }
+    }
+
+    /// Number of frames spanning `secs` seconds.
+    fn frames_for(&self, secs: f64) -> u32 {
+        let hop = self.config.fingerprint.hop_size as f64;
+        (secs.max(0.0) * CANONICAL_SAMPLE_RATE as f64 / hop).round() as u32
+    }
+
+    /// Stream time at the start of frame `frame` in seconds.
+    fn frame_time(&self, frame: u32) -> f64 {
+        frame as f64 * self.config.fingerprint.hop_size as f64 / CANONICAL_SAMPLE_RATE as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fingerprint::FingerprintDatabase;
+
+    fn generate_melody(notes: &[f32], note_secs: f32, sample_rate: u32) -> Vec<f32> {
+        let note_len = (sample_rate as f32 * note_secs) as usize;
+        (0..note_len * notes.len())
+            .map(|i| {
+                let freq = notes[i / note_len];
+                let t = i as f32 / sample_rate as f32;
+                0.5 * (2.0 * std::f32::consts::PI * freq * t).sin()
+                    + 0.3 * (2.0 * std::f32::consts::PI * freq * 2.0 * t).sin()
+            })
+            .collect()
+    }
+
+    fn point_tuples(fingerprint: &AudioFingerprint) -> Vec<(u32, u32, u8)> {
+        fingerprint.points.iter().map(|p| (p.time_offset, p.freq_bin, p.amplitude)).collect()
+    }
+
+    #[test]
+    fn test_streaming_matches_batch() {
+        let sample_rate = 44100;
+        let samples = generate_melody(&[440.0, 523.25, 659.25, 392.0, 880.0], 1.0, sample_rate);
+        let batch = Fingerprinter::new()
+            .fingerprint(&AudioData::new(samples.clone(), sample_rate))
+            .unwrap();
+
+        let mut streaming = StreamingFingerprinter::new(sample_rate);
+        for chunk in samples.chunks(3001) {
+            streaming.process(chunk);
+        }
+        let streamed = streaming.finalize().unwrap();
+
+        assert_eq!(streamed.hash, batch.hash);
+        assert_eq!(point_tuples(&streamed), point_tuples(&batch));
+        assert_eq!(streamed.duration_secs, batch.duration_secs);
+    }
+
+    #[test]
+    fn test_rolling_windows_match_database() {
+        let sample_rate = 22050;
+        let melody = generate_melody(&[440.0, 523.25, 659.25, 392.0, 587.33, 493.88], 2.0, sample_rate);
+        let reference = Fingerprinter::new()
+            .fingerprint(&AudioData::new(melody.clone(), sample_rate))
+            .unwrap();
+        let mut db = FingerprintDatabase::new();
+        db.add("live_channel", &reference);
+
+        let config = StreamingFingerprintConfig {
+            window_secs: 4.0,
+            emit_interval_secs: 2.0,
+            ..Default::default()
+        };
+        let mut streaming = StreamingFingerprinter::with_config(sample_rate, config).unwrap();
+        let windows: Vec<WindowFingerprint> = melody.chunks(1024)
+            .flat_map(|chunk| streaming.process(chunk))
+            .collect();
+
+        assert!(windows.len() >= 4);
+        let last = windows.last().unwrap();
+        assert!((last.end_secs - last.start_secs - 4.0).abs() < 0.1);
+        let matches = db.query(&last.fingerprint, 0.1);
+        assert_eq!(matches.first().map(|m| m.content_id.as_str()), Some("live_channel"));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let sample_rate = 22050;
+        let config = StreamingFingerprintConfig {
+            window_secs: 1.0,
+            max_history_secs: 2.0,
+            ..Default::default()
+        };
+        let mut streaming = StreamingFingerprinter::with_config(sample_rate, config).unwrap();
+        let melody = generate_melody(&[440.0, 660.0], 1.0, sample_rate);
+        for _ in 0..10 {
+            streaming.process(&melody);
+        }
+
+        let frames_kept = streaming.frames_for(2.0);
+        assert!(streaming.history.iter().all(|p| p.time_offset + frames_kept >= streaming.frames));
+        assert!(streaming.pending.len() <= (streaming.config.fingerprint.target_zone_frames + 1) * 6);
+    }
+}
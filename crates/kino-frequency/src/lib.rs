@@ -4,7 +4,17 @@
 //! - **Audio Fingerprinting**: Cryptographic content verification using spectral peaks
 //! - **AI Auto-Tagging**: Content classification based on frequency signatures
 //! - **Thumbnail Generation**: Optimal frame selection using FFT-based quality metrics
+//! - **Chapter breaks**: Chapter and ad-break candidates from silence and black frames
+//! - **Intro detection**: Intros and outros shared across a series' episodes
+//! - **Sync check**: A/V offset and drift from audio onsets against scene changes
 //! - **Recommendations**: Content similarity matching via frequency signatures
+//! - **Loudness**: EBU R128 integrated loudness, loudness range and true peak
+//! - **Music**: Tempo (BPM) and key detection
+//! - **Spectrograms**: PNG rendering for moderation review and debugging
+//! - **Seek-bar overviews**: Waveform and frequency heatmap buckets for the player UI
+//! - **Voice activity**: Speech segments for tagging, thumbnails and live moderation
+//! - **Batch processing**: Many files at once with bounded concurrency and progress events
+//! - **Cancellation**: Aborting long analyses with a token or per-stage timeouts
 //!
 //! # Architecture
 //!
@@ -65,6 +75,11 @@
 #![warn(missing_docs)]
 
 pub mod fft;
+pub mod loudness;
+pub mod music;
+pub mod overview;
+pub mod resample;
+pub mod spectrogram;
 pub mod types;
 
 #[cfg(feature = "fingerprint")]
@@ -76,24 +91,50 @@ pub mod tagging;
 #[cfg(feature = "thumbnail")]
 pub mod thumbnail;
 
+#[cfg(feature = "thumbnail")]
+pub mod breaks;
+
+#[cfg(feature = "thumbnail")]
+pub mod sync;
+
 #[cfg(feature = "recommend")]
 pub mod recommend;
 
 #[cfg(feature = "solana")]
 pub mod solana;
 
+#[cfg(feature = "symphonia")]
+pub mod decode;
+
 pub mod streaming;
+pub mod vad;
+
+pub mod batch;
+pub mod cancel;
+pub mod media;
+pub mod telemetry;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 use std::path::Path;
-use std::process::Command;
+use std::sync::Arc;
 use anyhow::{Context, Result, bail};
-use tracing::{info, debug, warn};
+use tracing::{field::Empty, info, info_span, instrument, debug, Instrument, Span};
+
+use cancel::StageDeadline;
+use telemetry::StageTimer;
 
 pub use types::*;
 pub use fft::FrequencyAnalyzer;
+pub use spectrogram::{Colormap, SpectrogramOptions};
+pub use vad::{detect_speech, SpeechSegment, VoiceActivityDetector};
+pub use overview::{generate_overview, Overview, OverviewConfig};
+pub use cancel::StageTimeouts;
+pub use tokio_util::sync::CancellationToken;
 
 #[cfg(feature = "fingerprint")]
-pub use fingerprint::Fingerprinter;
+pub use fingerprint::{Fingerprinter, StreamingFingerprinter};
 
 #[cfg(feature = "tagging")]
 pub use tagging::ContentTagger;
@@ -101,14 +142,31 @@ pub use tagging::ContentTagger;
 #[cfg(feature = "thumbnail")]
 pub use thumbnail::ThumbnailSelector;
 
+#[cfg(feature = "thumbnail")]
+pub use breaks::{BreakCandidate, BreakDetector};
+
+#[cfg(feature = "thumbnail")]
+pub use sync::{SyncChecker, SyncReport};
+
 #[cfg(feature = "recommend")]
-pub use recommend::RecommendationEngine;
+pub use recommend::{DimensionPolicy, RecommendationEngine, RecommendationFilter};
+
+#[cfg(feature = "symphonia")]
+pub use decode::AudioSource;
+
+pub use media::{
+    AudioExtractOptions, AudioSink, AudioTrack, ColorInfo, DecodeOptions, FfmpegBackend, HdrTransfer, MediaBackend,
+};
+pub use batch::{process_batch, process_videos, BatchConcurrency, BatchItem, BatchStage, ProcessingError, ProgressEvent};
 
 /// Main audio analyzer that coordinates all frequency analysis operations.
 pub struct AudioAnalyzer {
     sample_rate: u32,
     fft_size: usize,
     hop_size: usize,
+    sample_policy: SamplePolicy,
+    backend: Arc<dyn MediaBackend>,
+    extract_options: AudioExtractOptions,
 }
 
 impl AudioAnalyzer {
@@ -118,6 +176,9 @@ impl AudioAnalyzer {
             sample_rate,
             fft_size: 4096,
             hop_size: 2048,
+            sample_policy: SamplePolicy::default(),
+            backend: Arc::new(FfmpegBackend),
+            extract_options: AudioExtractOptions::default(),
         }
     }
 
@@ -127,81 +188,281 @@ impl AudioAnalyzer {
             sample_rate,
             fft_size,
             hop_size,
+            sample_policy: SamplePolicy::default(),
+            backend: Arc::new(FfmpegBackend),
+            extract_options: AudioExtractOptions::default(),
         }
     }
 
-    /// Extract audio from a video file using FFmpeg.
+    /// Set how NaN and infinite samples are handled (replaced by default).
+    pub fn with_sample_policy(mut self, sample_policy: SamplePolicy) -> Self {
+        self.sample_policy = sample_policy;
+        self
+    }
+
+    /// Extract audio with `backend` instead of FFmpeg.
+    pub fn with_backend(mut self, backend: Arc<dyn MediaBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Extract the track and time range in `options` instead of the whole
+    /// default track.
+    pub fn with_extract_options(mut self, options: AudioExtractOptions) -> Self {
+        self.extract_options = options;
+        self
+    }
+
+    /// List the audio streams of a media file, with their language, codec
+    /// and channels.
+    pub fn probe_audio_tracks(&self, video_path: impl AsRef<Path>) -> Result<Vec<AudioTrack>> {
+        self.backend.audio_tracks(video_path.as_ref())
+    }
+
+    /// The analyzer's extract options with the track pinned down: a
+    /// language becomes the index of its first stream, and an index gets
+    /// its stream's language.
+    ///
+    /// Fails when no stream has the index or language, or the time range is
+    /// invalid. The media is only probed when a track was asked for.
+    pub fn resolve_audio_options(&self, video_path: impl AsRef<Path>) -> Result<AudioExtractOptions> {
+        let mut options = self.extract_options.clone();
+        options.validate()?;
+        if options.stream_index.is_none() && options.language.is_none() {
+            return Ok(options);
+        }
+
+        let tracks = self.probe_audio_tracks(video_path)?;
+        let describe = || {
+            let listed: Vec<String> = tracks.iter()
+                .map(|t| format!("{} ({})", t.index, t.language.as_deref().unwrap_or("no language")))
+                .collect();
+            if listed.is_empty() { "none".to_string() } else { listed.join(", ") }
+        };
+        let track = match (options.stream_index, options.language.as_deref()) {
+            (Some(index), _) => tracks.iter().find(|t| t.index == index)
+                .with_context(|| format!("No audio stream {}; audio streams: {}", index, describe()))?,
+            (None, Some(language)) => tracks.iter().find(|t| t.has_language(language))
+                .with_context(|| format!("No audio stream in language '{}'; audio streams: {}", language, describe()))?,
+            (None, None) => unreachable!(),
+        };
+        options.stream_index = Some(track.index);
+        options.language = track.language.clone().or(options.language);
+        Ok(options)
+    }
+
+    /// Extract mono audio at the analyzer's sample rate from a media file.
+    ///
+    /// With the `symphonia` feature enabled the file is decoded in-process
+    /// first (see the `decode` module for codec coverage); formats Symphonia cannot
+    /// handle fall back to the media backend (FFmpeg by default). A track or
+    /// time range set with [`with_extract_options`](Self::with_extract_options)
+    /// always comes from the media backend.
+    #[instrument(
+        name = "extract_audio",
+        skip_all,
+        fields(
+            path = %video_path.as_ref().display(),
+            sample_rate = self.extract_rate(),
+            samples = Empty,
+            duration_secs = Empty,
+            elapsed_ms = Empty,
+        ),
+    )]
     pub async fn extract_audio(&self, video_path: impl AsRef<Path>) -> Result<AudioData> {
         let video_path = video_path.as_ref();
 
         info!("Extracting audio from: {}", video_path.display());
 
-        // Create temporary WAV file
-        let temp_dir = std::env::temp_dir();
-        let temp_wav = temp_dir.join(format!("kino_audio_{}.wav", uuid::Uuid::new_v4()));
-
-        // Run FFmpeg to extract audio
-        let output = Command::new("ffmpeg")
-            .args([
-                "-i", &video_path.to_string_lossy(),
-                "-vn",                          // No video
-                "-acodec", "pcm_s16le",         // 16-bit PCM
-                "-ar", &self.sample_rate.to_string(),  // Sample rate
-                "-ac", "1",                     // Mono
-                "-y",                           // Overwrite
-                &temp_wav.to_string_lossy(),
-            ])
-            .output()
-            .context("FFmpeg not found. Please install FFmpeg.")?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            bail!("FFmpeg audio extraction failed: {}", stderr);
+        let timer = StageTimer::start("extract_audio");
+        let audio = timer.finish(self.decode_or_extract(video_path).await)?;
+
+        let span = Span::current();
+        span.record("samples", audio.samples.len());
+        span.record("duration_secs", audio.duration_secs);
+        info!("Extracted {} samples at {}Hz", audio.samples.len(), audio.sample_rate);
+        Ok(audio)
+    }
+
+    async fn decode_or_extract(&self, video_path: &Path) -> Result<AudioData> {
+        #[cfg(feature = "symphonia")]
+        if self.extract_options == AudioExtractOptions::default() {
+            match self.decode_audio(video_path) {
+                Ok(audio) => return Ok(audio),
+                Err(e) => debug!("In-process decode failed, falling back to the media backend: {:#}", e),
+            }
         }
 
-        // Read the WAV file
-        let reader = hound::WavReader::open(&temp_wav)
-            .context("Failed to open extracted audio")?;
+        let options = self.backend_options(video_path)?;
+        self.backend.extract_audio(video_path, self.extract_rate(), &options).await
+    }
 
-        let spec = reader.spec();
-        debug!("Audio spec: {:?}", spec);
+    /// Options as backends take them, with any language resolved.
+    fn backend_options(&self, video_path: &Path) -> Result<AudioExtractOptions> {
+        if self.extract_options.stream_index.is_none() && self.extract_options.language.is_some() {
+            return self.resolve_audio_options(video_path);
+        }
+        self.extract_options.validate()?;
+        Ok(self.extract_options.clone())
+    }
 
-        let samples: Vec<f32> = reader
-            .into_samples::<i16>()
-            .filter_map(|s| s.ok())
-            .map(|s| s as f32 / 32768.0)
-            .collect();
+    fn extract_rate(&self) -> u32 {
+        self.extract_options.sample_rate_override.unwrap_or(self.sample_rate)
+    }
+
+    /// Extract mono audio at the analyzer's sample rate, handing it to
+    /// `sink` in chunks as it is decoded.
+    ///
+    /// Memory stays bounded however long the media is. Audio always comes
+    /// from the media backend, without the in-process decoder, and sample
+    /// values are passed on as decoded.
+    pub async fn stream_audio(&self, video_path: impl AsRef<Path>, sink: &mut AudioSink<'_>) -> Result<()> {
+        let video_path = video_path.as_ref();
+        info!("Streaming audio from: {}", video_path.display());
+        let options = self.backend_options(video_path)?;
+        self.backend.stream_audio(video_path, self.extract_rate(), &options, sink).await
+    }
 
-        // Clean up temp file
-        let _ = std::fs::remove_file(&temp_wav);
+    /// Load audio from an existing WAV file without invoking FFmpeg.
+    ///
+    /// Supports 8/16/24/32-bit integer PCM and 32-bit IEEE float WAVs. The
+    /// returned audio keeps the file's sample rate and channel layout.
+    pub fn load_wav(&self, path: impl AsRef<Path>) -> Result<AudioData> {
+        let path = path.as_ref();
+        info!("Loading WAV: {}", path.display());
+
+        let audio = read_wav(path)
+            .with_context(|| format!("Failed to load WAV file: {}", path.display()))?;
+
+        if audio.sample_rate != self.sample_rate {
+            debug!(
+                "WAV sample rate {}Hz differs from analyzer rate {}Hz",
+                audio.sample_rate, self.sample_rate
+            );
+        }
 
-        info!("Extracted {} samples at {}Hz", samples.len(), spec.sample_rate);
+        Ok(audio)
+    }
 
-        Ok(AudioData {
-            samples,
-            sample_rate: spec.sample_rate,
-            channels: spec.channels as u32,
-            duration_secs: 0.0, // Will be calculated
-        })
+    /// Decode a media file or in-memory buffer without invoking FFmpeg.
+    ///
+    /// The first audio track is downmixed to mono and resampled to the
+    /// analyzer's sample rate. See [`decode`] for supported codecs.
+    #[cfg(feature = "symphonia")]
+    pub fn decode_audio(&self, source: impl Into<AudioSource>) -> Result<AudioData> {
+        let audio = decode::decode(source.into(), self.sample_rate)?;
+        info!("Decoded {} samples at {}Hz", audio.samples.len(), audio.sample_rate);
+        Ok(audio)
     }
 
     /// Perform complete frequency analysis on audio data.
-    pub fn analyze(&self, audio: &AudioData) -> Result<FrequencyAnalysis> {
-        let analyzer = FrequencyAnalyzer::new(self.fft_size, self.hop_size);
-        analyzer.analyze(&audio.samples, audio.sample_rate)
+    ///
+    /// Fails with [`FrequencyError::TooShort`] below one FFT window and
+    /// [`FrequencyError::ZeroSignal`] for silent or DC-only audio; NaN and
+    /// infinite samples are handled per the analyzer's [`SamplePolicy`].
+    #[instrument(
+        name = "analyze",
+        skip_all,
+        fields(
+            samples = audio.samples.len(),
+            duration_secs = audio.duration_secs,
+            fft_size = self.fft_size,
+            elapsed_ms = Empty,
+        ),
+    )]
+    pub fn analyze(&self, audio: &AudioData) -> FrequencyResult<FrequencyAnalysis> {
+        let timer = StageTimer::start("analyze");
+        let result = audio.prepare(self.sample_policy, self.fft_size).and_then(|audio| {
+            let audio = audio.to_mono();
+            let analyzer = FrequencyAnalyzer::new(self.fft_size, self.hop_size);
+            analyzer.analyze(&audio.samples, audio.sample_rate)
+        });
+        timer.finish(result)
     }
 
     /// Get the dominant frequencies from audio.
-    pub fn dominant_frequencies(&self, audio: &AudioData, top_k: usize) -> Result<Vec<DominantFrequency>> {
+    pub fn dominant_frequencies(&self, audio: &AudioData, top_k: usize) -> FrequencyResult<Vec<DominantFrequency>> {
+        let audio = audio.prepare(self.sample_policy, self.fft_size)?;
+        let audio = audio.to_mono();
         let analyzer = FrequencyAnalyzer::new(self.fft_size, self.hop_size);
         analyzer.dominant_frequencies(&audio.samples, audio.sample_rate, top_k)
     }
 
+    /// Render a spectrogram of the audio and write it to `path` as a PNG.
+    pub fn write_spectrogram(
+        &self,
+        audio: &AudioData,
+        options: &SpectrogramOptions,
+        path: impl AsRef<Path>,
+    ) -> FrequencyResult<()> {
+        let audio = audio.prepare(self.sample_policy, self.fft_size)?;
+        let audio = audio.to_mono();
+        let analyzer = FrequencyAnalyzer::new(self.fft_size, self.hop_size);
+        Ok(analyzer.write_spectrogram_png(&audio.samples, audio.sample_rate, options, path)?)
+    }
+
+    /// Measure EBU R128 integrated loudness, loudness range and true peak.
+    ///
+    /// Silence is measured rather than rejected and reports -70 LUFS.
+    pub fn measure_loudness(&self, audio: &AudioData) -> FrequencyResult<LoudnessInfo> {
+        loudness::measure(&*audio.sanitize(self.sample_policy)?)
+    }
+
+    /// Detect tempo (BPM) and musical key.
+    ///
+    /// See [`music`] for the algorithms and their confidences.
+    pub fn analyze_music(&self, audio: &AudioData) -> FrequencyResult<MusicInfo> {
+        music::analyze(&*audio.sanitize(self.sample_policy)?)
+    }
+
     /// Compute frequency signature for similarity matching.
-    pub fn compute_signature(&self, audio: &AudioData) -> Result<FrequencySignature> {
+    pub fn compute_signature(&self, audio: &AudioData) -> FrequencyResult<FrequencySignature> {
+        self.compute_signature_cancellable(audio, &CancellationToken::new())
+    }
+
+    /// [`compute_signature`](Self::compute_signature), checking `cancel`
+    /// between FFT frames.
+    pub fn compute_signature_cancellable(&self, audio: &AudioData, cancel: &CancellationToken) -> FrequencyResult<FrequencySignature> {
+        let audio = audio.prepare(self.sample_policy, self.fft_size)?;
+        let audio = audio.to_mono();
         let analyzer = FrequencyAnalyzer::new(self.fft_size, self.hop_size);
-        analyzer.compute_signature(&audio.samples, audio.sample_rate)
+        analyzer.compute_signature_cancellable(&audio.samples, audio.sample_rate, cancel)
+    }
+}
+
+/// Decode a WAV file into normalized f32 samples.
+pub(crate) fn read_wav(path: &Path) -> Result<AudioData> {
+    let reader = hound::WavReader::open(path)
+        .context("Failed to open WAV file")?;
+
+    let spec = reader.spec();
+    debug!("Audio spec: {:?}", spec);
+
+    let samples: Vec<f32> = match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Int, bits @ (8 | 16 | 24 | 32)) => {
+            let scale = (1u64 << (bits - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<std::result::Result<_, _>>()
+                .context("Failed to decode integer PCM samples")?
+        }
+        (hound::SampleFormat::Float, 32) => reader
+            .into_samples::<f32>()
+            .collect::<std::result::Result<_, _>>()
+            .context("Failed to decode float samples")?,
+        (format, bits) => bail!(
+            "Unsupported WAV layout: {:?} with {} bits per sample (supported: 8/16/24/32-bit integer, 32-bit float)",
+            format,
+            bits
+        ),
+    };
+
+    if samples.is_empty() {
+        bail!("WAV file contains no audio samples");
     }
+
+    Ok(AudioData::with_channels(samples, spec.sample_rate, spec.channels as u32))
 }
 
 /// Process a video file through the complete frequency analysis pipeline.
@@ -209,55 +470,238 @@ pub async fn process_video(
     video_path: impl AsRef<Path>,
     config: ProcessingConfig,
 ) -> Result<ProcessingResult> {
-    let video_path = video_path.as_ref();
-    info!("Processing video: {}", video_path.display());
+    process_video_with_backend(video_path, config, Arc::new(FfmpegBackend)).await
+}
 
-    let analyzer = AudioAnalyzer::new(config.sample_rate);
-    let audio = analyzer.extract_audio(video_path).await?;
+/// [`process_video`] with media probed and extracted by `backend`.
+pub async fn process_video_with_backend(
+    video_path: impl AsRef<Path>,
+    config: ProcessingConfig,
+    backend: Arc<dyn MediaBackend>,
+) -> Result<ProcessingResult> {
+    process_video_cancellable(video_path, config, backend, CancellationToken::new()).await
+}
 
-    let mut result = ProcessingResult {
-        content_id: uuid::Uuid::new_v4().to_string(),
-        fingerprint: None,
-        tags: Vec::new(),
-        thumbnail_timestamp: None,
-        signature: None,
-        dominant_frequencies: Vec::new(),
-    };
+/// [`process_video_with_backend`], stopped once `cancel` fires.
+///
+/// Extraction is abandoned as soon as the token fires; the analysis stages
+/// check it between FFT frames and stages (see [`cancel`]). The error then
+/// downcasts to [`FrequencyError::Cancelled`], with a "timed out" context
+/// when one of `config.stage_timeouts` cancelled the stage.
+pub async fn process_video_cancellable(
+    video_path: impl AsRef<Path>,
+    config: ProcessingConfig,
+    backend: Arc<dyn MediaBackend>,
+    cancel: CancellationToken,
+) -> Result<ProcessingResult> {
+    let video_path = video_path.as_ref();
+    let span = info_span!(
+        "process_video",
+        content_id = Empty,
+        path = %video_path.display(),
+        sample_rate = config.sample_rate,
+        elapsed_ms = Empty,
+    );
+
+    async {
+        info!("Processing video: {}", video_path.display());
+        let timer = StageTimer::start("process_video");
+
+        let analyzer = AudioAnalyzer::new(config.sample_rate)
+            .with_sample_policy(config.sample_policy)
+            .with_backend(backend.clone())
+            .with_extract_options(config.audio.clone());
+        let result = async {
+            let selection = analyzer.resolve_audio_options(video_path)?;
+            let analyzer = analyzer.with_extract_options(selection.clone());
+            let deadline = StageDeadline::start(&cancel, config.stage_timeouts.extraction);
+            let audio = tokio::select! {
+                audio = analyzer.extract_audio(video_path) => audio,
+                _ = deadline.token().cancelled() => Err(FrequencyError::Cancelled.into()),
+            };
+            let audio = deadline.finish("extraction", &cancel, audio)?;
+            let analyzed = AnalyzedAudio::new(&selection, &audio);
+            analyze_audio(Some(video_path), &audio, analyzed, &config, backend, &cancel)
+        }.await;
+
+        timer.finish(result)
+    }
+    .instrument(span)
+    .await
+}
+
+/// Run the CPU-bound analysis stages of the pipeline on extracted audio.
+///
+/// Thumbnail selection needs the source video and the audio of the whole
+/// program, and is skipped without them.
+/// Each stage runs in a `pipeline_stage` span (see [`telemetry`]) and is
+/// cancelled with `cancel` or after its `config.stage_timeouts` entry. The
+/// content id is derived once the fingerprint is known and recorded on the
+/// current span.
+#[cfg_attr(not(feature = "thumbnail"), allow(unused_variables))]
+pub(crate) fn analyze_audio(
+    video_path: Option<&Path>,
+    audio: &AudioData,
+    analyzed: AnalyzedAudio,
+    config: &ProcessingConfig,
+    backend: Arc<dyn MediaBackend>,
+    cancel: &CancellationToken,
+) -> Result<ProcessingResult> {
+    let analyzer = AudioAnalyzer::new(config.sample_rate).with_sample_policy(config.sample_policy);
+    telemetry::increment(telemetry::ANALYSES_TOTAL);
 
     // Fingerprint
     #[cfg(feature = "fingerprint")]
-    if config.enable_fingerprint {
-        let fingerprinter = Fingerprinter::new();
-        result.fingerprint = Some(fingerprinter.fingerprint(&audio)?);
-    }
+    let audio_fingerprint = if config.enable_fingerprint {
+        let fingerprinter = Fingerprinter::with_config(fingerprint::FingerprintConfig {
+            sample_policy: config.sample_policy,
+            ..Default::default()
+        });
+        let deadline = StageDeadline::start(cancel, config.stage_timeouts.fingerprint);
+        let fingerprinted = telemetry::pipeline_stage(config.content_id.as_deref(), "fingerprint", audio, || {
+            fingerprinter.fingerprint_cancellable(audio, deadline.token())
+        });
+        Some(deadline.finish("fingerprint", cancel, fingerprinted)?)
+    } else {
+        None
+    };
+    #[cfg(not(feature = "fingerprint"))]
+    let audio_fingerprint = None;
+
+    let (content_id, source) =
+        derive_content_id(config.content_id.as_deref(), audio_fingerprint.as_ref(), video_path, audio)?;
+    Span::current().record("content_id", content_id.as_str());
+    let mut result = ProcessingResult {
+        content_id_source: source,
+        fingerprint: audio_fingerprint,
+        audio: Some(analyzed),
+        ..ProcessingResult::new(content_id.clone())
+    };
 
     // Auto-tagging
     #[cfg(feature = "tagging")]
     if config.enable_tagging {
-        let tagger = ContentTagger::new();
-        result.tags = tagger.predict(&audio)?;
+        let tagger = ContentTagger::with_config(tagging::TaggingConfig {
+            language_model: config.language_model.clone().map(tagging::MlModelConfig::language_id),
+            sample_policy: config.sample_policy,
+            ..Default::default()
+        });
+        let deadline = StageDeadline::start(cancel, config.stage_timeouts.tagging);
+        let tagged = telemetry::pipeline_stage(Some(&content_id), "tagging", audio, || -> Result<()> {
+            result.tags = tagger.predict_cancellable(audio, deadline.token())?;
+
+            if config.language_model.is_some() {
+                cancel::check(deadline.token())?;
+                let languages = tagger.detect_languages(audio)?;
+                result.dominant_language = languages.first().map(|l| l.language.clone());
+                result.tags.extend(languages.iter().map(LanguageTag::to_content_tag));
+            }
+
+            // Tempo and key for music, including its subgenres
+            let is_music = result.tags.iter()
+                .any(|t| t.confidence >= music::MUSIC_INFO_MIN_CONFIDENCE && tagger.taxonomy().tag_is_a(t, "genre.music"));
+            if is_music {
+                cancel::check(deadline.token())?;
+                match analyzer.analyze_music(audio) {
+                    Ok(music) => result.music = Some(music),
+                    Err(e) => debug!("Skipping music analysis: {}", e),
+                }
+            }
+            Ok(())
+        });
+        deadline.finish("tagging", cancel, tagged)?;
     }
 
     // Thumbnail selection
     #[cfg(feature = "thumbnail")]
-    if config.enable_thumbnail {
-        let selector = ThumbnailSelector::new();
-        if let Ok(timestamp) = selector.find_best_timestamp(video_path, &audio) {
+    if let (true, false, Some(video_path)) = (config.enable_thumbnail, config.audio.is_range(), video_path) {
+        cancel::check(cancel)?;
+        let selector = ThumbnailSelector::new().with_backend(backend);
+        let timestamp = telemetry::pipeline_stage(Some(&content_id), "thumbnail", audio, || {
+            selector.find_best_timestamp(video_path, audio)
+        });
+        if let Ok(timestamp) = timestamp {
             result.thumbnail_timestamp = Some(timestamp);
         }
     }
 
     // Frequency signature for recommendations
     if config.enable_signature {
-        result.signature = Some(analyzer.compute_signature(&audio)?);
+        let deadline = StageDeadline::start(cancel, config.stage_timeouts.signature);
+        let signature = telemetry::pipeline_stage(Some(&content_id), "signature", audio, || {
+            analyzer.compute_signature_cancellable(audio, deadline.token())
+        });
+        result.signature = Some(deadline.finish("signature", cancel, signature)?);
     }
 
     // Dominant frequencies
-    result.dominant_frequencies = analyzer.dominant_frequencies(&audio, 10)?;
+    cancel::check(cancel)?;
+    result.dominant_frequencies = telemetry::pipeline_stage(Some(&content_id), "dominant_frequencies", audio, || {
+        analyzer.dominant_frequencies(audio, 10)
+    })?;
+
+    // Loudness
+    match telemetry::pipeline_stage(Some(&content_id), "loudness", audio, || analyzer.measure_loudness(audio)) {
+        Ok(loudness) => result.loudness = Some(loudness),
+        Err(e) => debug!("Skipping loudness measurement: {}", e),
+    }
 
     Ok(result)
 }
 
+/// Derive the id of processed content, with how it was derived.
+///
+/// In order of precedence: the caller's `explicit` id, the fingerprint hash,
+/// a SHA-256 of the file at `path` (read in chunks, never whole) or, for
+/// audio without a file, a SHA-256 of the decoded samples. See
+/// [`ContentIdSource`].
+pub fn derive_content_id(
+    explicit: Option<&str>,
+    fingerprint: Option<&AudioFingerprint>,
+    path: Option<&Path>,
+    audio: &AudioData,
+) -> Result<(String, ContentIdSource)> {
+    use ring::digest::{Context as Digest, SHA256};
+
+    if let Some(id) = explicit {
+        return Ok((id.to_string(), ContentIdSource::Caller));
+    }
+    if let Some(fingerprint) = fingerprint {
+        return Ok((fingerprint.hash.clone(), ContentIdSource::Fingerprint));
+    }
+
+    let mut digest = Digest::new(&SHA256);
+    let source = match path {
+        Some(path) => {
+            use std::io::Read;
+
+            let mut file = std::fs::File::open(path)
+                .with_context(|| format!("Failed to open {} to derive its content id", path.display()))?;
+            let mut buffer = vec![0u8; 64 * 1024];
+            loop {
+                let read = file.read(&mut buffer)
+                    .with_context(|| format!("Failed to read {} to derive its content id", path.display()))?;
+                if read == 0 {
+                    break;
+                }
+                digest.update(&buffer[..read]);
+            }
+            ContentIdSource::FileSha256
+        }
+        None => {
+            digest.update(&audio.sample_rate.to_le_bytes());
+            digest.update(&audio.channels.to_le_bytes());
+            for sample in &audio.samples {
+                digest.update(&sample.to_le_bytes());
+            }
+            ContentIdSource::SampleSha256
+        }
+    };
+
+    let id = digest.finish().as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    Ok((id, source))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -269,6 +713,82 @@ mod tests {
         assert_eq!(analyzer.fft_size, 4096);
     }
 
+    fn write_wav(path: &Path, spec: hound::WavSpec, samples: &[f32]) {
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for &s in samples {
+            match spec.sample_format {
+                hound::SampleFormat::Float => writer.write_sample(s).unwrap(),
+                hound::SampleFormat::Int => {
+                    let max = ((1i64 << (spec.bits_per_sample - 1)) - 1) as f32;
+                    writer.write_sample((s * max) as i32).unwrap();
+                }
+            }
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn test_signal() -> Vec<f32> {
+        (0..4410).map(|i| (i as f32 * 0.05).sin() * 0.8).collect()
+    }
+
+    #[test]
+    fn test_load_wav_integer_and_float_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let signal = test_signal();
+        let analyzer = AudioAnalyzer::new(44100);
+
+        let layouts = [
+            (hound::SampleFormat::Int, 16),
+            (hound::SampleFormat::Int, 24),
+            (hound::SampleFormat::Int, 32),
+            (hound::SampleFormat::Float, 32),
+        ];
+
+        for (format, bits) in layouts {
+            let path = dir.path().join(format!("{:?}_{}.wav", format, bits));
+            let spec = hound::WavSpec {
+                channels: 1,
+                sample_rate: 44100,
+                bits_per_sample: bits,
+                sample_format: format,
+            };
+            write_wav(&path, spec, &signal);
+
+            let audio = analyzer.load_wav(&path).unwrap();
+            assert_eq!(audio.len(), signal.len(), "{:?}/{}", format, bits);
+            assert_eq!(audio.sample_rate, 44100);
+            assert!((audio.duration_secs - 0.1).abs() < 1e-6);
+            for (a, b) in audio.samples.iter().zip(&signal) {
+                assert!((a - b).abs() < 1e-3, "{:?}/{}: {} vs {}", format, bits, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_wav_stereo_keeps_channels() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stereo.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 24,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let interleaved: Vec<f32> = test_signal().iter().flat_map(|&s| [s, -s]).collect();
+        write_wav(&path, spec, &interleaved);
+
+        let audio = AudioAnalyzer::new(48000).load_wav(&path).unwrap();
+        assert_eq!(audio.channels, 2);
+        assert!((audio.duration_secs - 4410.0 / 48000.0).abs() < 1e-6);
+        assert!(audio.to_mono().samples.iter().all(|s| s.abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_load_wav_missing_file() {
+        let err = AudioAnalyzer::new(44100).load_wav("/nonexistent/kino.wav").unwrap_err();
+        assert!(err.to_string().contains("Failed to load WAV file"));
+    }
+
     #[test]
     fn test_custom_fft_params() {
         let analyzer = AudioAnalyzer::with_fft_params(48000, 8192, 4096);
@@ -276,4 +796,153 @@ mod tests {
         assert_eq!(analyzer.fft_size, 8192);
         assert_eq!(analyzer.hop_size, 4096);
     }
+
+    #[tokio::test]
+    async fn test_process_video_with_mock_backend() {
+        use crate::testing::{self, MockBackend};
+
+        let backend = MockBackend::new(12.0)
+            .with_audio(testing::sine(440.0, 44100, 12.0))
+            .with_frames(|t| Some(if (5.0..7.0).contains(&t) { testing::stripes_frame(2) } else { testing::flat_frame(128) }));
+        let config = ProcessingConfig {
+            sample_rate: 22050,
+            ..Default::default()
+        };
+        let result = process_video_with_backend("video.mp4", config, Arc::new(backend)).await.unwrap();
+
+        assert!((result.dominant_frequencies[0].frequency_hz - 440.0).abs() < 10.0);
+        assert!(result.signature.is_some());
+        #[cfg(feature = "thumbnail")]
+        assert!(result.thumbnail_timestamp.is_some_and(|t| (5.0..7.0).contains(&t)), "{:?}", result.thumbnail_timestamp);
+
+        // No audio track to analyze
+        let err = process_video_with_backend("video.mp4", ProcessingConfig::default(), Arc::new(MockBackend::new(12.0)))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no audio track"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_process_video_cancellation() {
+        use crate::testing::{self, MockBackend};
+        use std::time::Duration;
+
+        let backend = || Arc::new(MockBackend::new(12.0).with_audio(testing::sine(440.0, 44100, 12.0)));
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let err = process_video_cancellable("video.mp4", ProcessingConfig::default(), backend(), cancel)
+            .await
+            .unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(FrequencyError::Cancelled)), "{}", err);
+
+        let config = ProcessingConfig {
+            stage_timeouts: StageTimeouts { tagging: Some(Duration::ZERO), ..Default::default() },
+            ..Default::default()
+        };
+        let err = process_video_cancellable("video.mp4", config, backend(), CancellationToken::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "tagging timed out after 0ns");
+        assert!(matches!(err.downcast_ref(), Some(FrequencyError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_content_ids_are_deterministic() {
+        use crate::testing::{self, MockBackend};
+
+        async fn process(path: &Path, audio: AudioData, config: ProcessingConfig) -> ProcessingResult {
+            let backend = Arc::new(MockBackend::new(4.0).with_audio(audio));
+            process_video_with_backend(path, config, backend).await.unwrap()
+        }
+        let sine = || testing::sine(440.0, 44100, 4.0);
+        let sweep = || testing::sweep(200.0, 4000.0, 44100, 4.0);
+        let config = || ProcessingConfig { enable_tagging: false, enable_thumbnail: false, ..Default::default() };
+        let path = Path::new("video.mp4");
+
+        // Same audio, same id, whatever the file
+        let first = process(path, sine(), config()).await;
+        let again = process(Path::new("copy.mkv"), sine(), config()).await;
+        let other = process(path, sweep(), config()).await;
+        assert_eq!(first.content_id_source, ContentIdSource::Fingerprint);
+        assert_eq!(first.content_id, first.fingerprint.as_ref().unwrap().hash);
+        assert_eq!(first.content_id, again.content_id);
+        assert_ne!(first.content_id, other.content_id);
+
+        // Without a fingerprint, the file's bytes decide
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.mp4"), dir.path().join("b.mp4"));
+        std::fs::write(&a, vec![1u8; 200_000]).unwrap();
+        std::fs::write(&b, vec![2u8; 200_000]).unwrap();
+        let unfingerprinted = || ProcessingConfig { enable_fingerprint: false, ..config() };
+        let first = process(&a, sine(), unfingerprinted()).await;
+        assert_eq!(first.content_id_source, ContentIdSource::FileSha256);
+        assert!(first.fingerprint.is_none());
+        assert_eq!(process(&a, sweep(), unfingerprinted()).await.content_id, first.content_id);
+        assert_ne!(process(&b, sine(), unfingerprinted()).await.content_id, first.content_id);
+        assert!(process_video_with_backend(path, unfingerprinted(), Arc::new(MockBackend::new(4.0).with_audio(sine())))
+            .await
+            .is_err());
+
+        // Decoded audio without a file
+        let (id, source) = derive_content_id(None, None, None, &sine()).unwrap();
+        assert_eq!(source, ContentIdSource::SampleSha256);
+        assert_eq!(derive_content_id(None, None, None, &sine()).unwrap().0, id);
+        assert_ne!(derive_content_id(None, None, None, &sweep()).unwrap().0, id);
+
+        // The caller's id wins
+        let config = ProcessingConfig { content_id: Some("episode-1".to_string()), ..config() };
+        let result = process(path, sine(), config).await;
+        assert_eq!((result.content_id.as_str(), result.content_id_source), ("episode-1", ContentIdSource::Caller));
+    }
+
+    #[tokio::test]
+    async fn test_audio_track_and_range_selection() {
+        use crate::testing::{self, MockBackend};
+
+        let track = |index, language: &str| AudioTrack { index, language: Some(language.to_string()), ..Default::default() };
+        let backend: Arc<dyn MediaBackend> = Arc::new(
+            MockBackend::new(20.0)
+                .with_audio_track(track(1, "fre"), testing::sine(440.0, 44100, 20.0))
+                .with_audio_track(track(2, "eng"), testing::sine(1000.0, 44100, 20.0)),
+        );
+        let analyzer = AudioAnalyzer::new(44100).with_backend(backend.clone());
+        assert_eq!(analyzer.probe_audio_tracks("master.mov").unwrap().len(), 2);
+
+        let process = |audio: AudioExtractOptions| {
+            let config = ProcessingConfig { enable_tagging: false, audio, ..Default::default() };
+            process_video_with_backend("master.mov", config, backend.clone())
+        };
+        let dominant = |result: &ProcessingResult| result.dominant_frequencies[0].frequency_hz;
+
+        // The default track, whole program
+        let result = process(AudioExtractOptions::default()).await.unwrap();
+        assert!((dominant(&result) - 440.0).abs() < 10.0);
+        assert_eq!(result.audio, Some(AnalyzedAudio { duration_secs: 20.0, ..Default::default() }));
+
+        // The English mix, first ten seconds after the cold open
+        let english = AudioExtractOptions {
+            language: Some("ENG".to_string()),
+            start: Some(5.0),
+            duration: Some(10.0),
+            ..Default::default()
+        };
+        let result = process(english).await.unwrap();
+        assert!((dominant(&result) - 1000.0).abs() < 10.0);
+        assert_eq!(result.audio, Some(AnalyzedAudio {
+            stream_index: Some(2),
+            language: Some("eng".to_string()),
+            start_secs: 5.0,
+            duration_secs: 10.0,
+        }));
+        assert_eq!(result.thumbnail_timestamp, None);
+
+        // By index, picking up the track's language
+        let result = process(AudioExtractOptions { stream_index: Some(1), ..Default::default() }).await.unwrap();
+        assert_eq!(result.audio.unwrap().language.as_deref(), Some("fre"));
+
+        let err = process(AudioExtractOptions { language: Some("ger".to_string()), ..Default::default() }).await.unwrap_err();
+        assert_eq!(err.to_string(), "No audio stream in language 'ger'; audio streams: 1 (fre), 2 (eng)");
+        assert!(process(AudioExtractOptions { stream_index: Some(0), ..Default::default() }).await.is_err());
+        assert!(process(AudioExtractOptions { duration: Some(-1.0), ..Default::default() }).await.is_err());
+    }
 }
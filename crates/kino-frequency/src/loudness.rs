@@ -0,0 +1,399 @@
+//! Loudness measurement per EBU R128 / ITU-R BS.1770.
+//!
+//! Provides integrated loudness, loudness range (EBU Tech 3342) and true
+//! peak for [`AudioData`], so content can be compared and normalized on a
+//! perceptual scale instead of raw signal energy.
+//!
+//! # Algorithm Overview
+//!
+//! 1. K-weight each channel (high-shelf pre-filter + RLB high-pass)
+//! 2. Sum channel power with the BS.1770 channel weights
+//! 3. Integrated loudness: 400ms blocks every 100ms, gated at -70 LUFS and
+//!    then at 10 LU below the ungated mean
+//! 4. Loudness range: 3s blocks every 100ms, gated at -70 LUFS and 20 LU
+//!    below the mean, reported as the 10th to 95th percentile spread
+//! 5. True peak: maximum of the 4x oversampled signal
+
+use crate::types::{AudioData, FrequencyError, FrequencyResult, LoudnessInfo};
+
+/// Offset that makes a 1kHz full-scale stereo sine read -3.01 LUFS.
+const LOUDNESS_OFFSET: f64 = -0.691;
+/// Blocks quieter than this never count towards a measurement.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Relative gate for integrated loudness, in LU below the ungated mean.
+const INTEGRATED_RELATIVE_GATE: f64 = -10.0;
+/// Relative gate for loudness range, in LU below the ungated mean.
+const RANGE_RELATIVE_GATE: f64 = -20.0;
+/// Gating block step, in 100ms units.
+const STEPS_PER_SECOND: u32 = 10;
+/// Momentary block length in steps (400ms).
+const MOMENTARY_STEPS: usize = 4;
+/// Short-term block length in steps (3s).
+const SHORT_TERM_STEPS: usize = 30;
+/// True peak oversampling factor below 96kHz.
+const OVERSAMPLING: usize = 4;
+/// Interpolation filter half-width in input samples.
+const INTERPOLATION_TAPS: usize = 8;
+/// Reported for peaks of silent input, roughly the 24-bit noise floor.
+const SILENCE_PEAK_DB: f64 = -144.0;
+
+/// Measure integrated loudness, loudness range and true peak.
+///
+/// Multichannel audio is weighted per BS.1770; six-channel input is assumed
+/// to be 5.1 in L, R, C, LFE, Ls, Rs order with the LFE channel ignored.
+/// Input quieter than the absolute gate reports -70 LUFS. Fails with
+/// [`FrequencyError::TooShort`] below 400ms and
+/// [`FrequencyError::InvalidSamples`] if any sample is NaN or infinite.
+pub fn measure(audio: &AudioData) -> FrequencyResult<LoudnessInfo> {
+    let report = audio.validate();
+    if !report.is_finite() {
+        return Err(FrequencyError::InvalidSamples { nan: report.nan, infinite: report.infinite });
+    }
+
+    let (integrated_lufs, loudness_range_lu) = gated_loudness(audio)?;
+
+    let channels = audio.channels.max(1) as usize;
+    let peak = (0..channels)
+        .map(|c| {
+            let channel: Vec<f32> = audio.samples.iter().skip(c).step_by(channels).copied().collect();
+            true_peak(&channel, audio.sample_rate)
+        })
+        .fold(0.0, f64::max);
+    let true_peak_dbtp = if peak > 0.0 {
+        (20.0 * peak.log10()).max(SILENCE_PEAK_DB)
+    } else {
+        SILENCE_PEAK_DB
+    };
+
+    Ok(LoudnessInfo {
+        integrated_lufs,
+        loudness_range_lu,
+        true_peak_dbtp,
+    })
+}
+
+/// Integrated loudness (LUFS) and loudness range (LU).
+fn gated_loudness(audio: &AudioData) -> FrequencyResult<(f64, f64)> {
+    let channels = audio.channels.max(1) as usize;
+    let frames = audio.samples.len() / channels;
+    let step_len = (audio.sample_rate / STEPS_PER_SECOND) as usize;
+
+    if step_len == 0 || frames < step_len * MOMENTARY_STEPS {
+        return Err(FrequencyError::TooShort { samples: frames, required: (step_len * MOMENTARY_STEPS).max(1) });
+    }
+
+    // Weighted K-filtered energy per 100ms step
+    let weights = channel_weights(channels);
+    let mut filters: Vec<KWeighting> = (0..channels)
+        .map(|_| KWeighting::new(audio.sample_rate))
+        .collect();
+    let steps: Vec<f64> = audio.samples[..frames / step_len * step_len * channels]
+        .chunks_exact(step_len * channels)
+        .map(|step| {
+            let mut energy = 0.0;
+            for frame in step.chunks_exact(channels) {
+                for ((filter, &weight), &sample) in filters.iter_mut().zip(&weights).zip(frame) {
+                    let y = filter.process(sample as f64);
+                    energy += weight * y * y;
+                }
+            }
+            energy
+        })
+        .collect();
+
+    let block_powers = |len: usize| -> Vec<f64> {
+        steps.windows(len)
+            .map(|w| w.iter().sum::<f64>() / (len * step_len) as f64)
+            .collect()
+    };
+
+    let momentary = gate(&block_powers(MOMENTARY_STEPS), INTEGRATED_RELATIVE_GATE);
+    let integrated_lufs = if momentary.is_empty() {
+        ABSOLUTE_GATE_LUFS
+    } else {
+        to_lufs(momentary.iter().sum::<f64>() / momentary.len() as f64)
+    };
+
+    let mut short_term: Vec<f64> = gate(&block_powers(SHORT_TERM_STEPS), RANGE_RELATIVE_GATE)
+        .into_iter()
+        .map(to_lufs)
+        .collect();
+    short_term.sort_by(f64::total_cmp);
+    let loudness_range_lu = if short_term.is_empty() {
+        0.0
+    } else {
+        percentile(&short_term, 0.95) - percentile(&short_term, 0.10)
+    };
+
+    Ok((integrated_lufs, loudness_range_lu))
+}
+
+/// Scale audio so its integrated loudness matches `target_lufs`.
+///
+/// Input below the absolute gate is returned unchanged rather than amplified
+/// into noise. No limiting is applied, so samples may exceed full scale.
+pub fn normalize(audio: &AudioData, info: &LoudnessInfo, target_lufs: f64) -> AudioData {
+    if info.integrated_lufs <= ABSOLUTE_GATE_LUFS {
+        return audio.clone();
+    }
+
+    let gain = 10f64.powf(info.gain_to(target_lufs) / 20.0) as f32;
+    AudioData::with_channels(
+        audio.samples.iter().map(|s| s * gain).collect(),
+        audio.sample_rate,
+        audio.channels,
+    )
+}
+
+fn to_lufs(power: f64) -> f64 {
+    LOUDNESS_OFFSET + 10.0 * power.log10()
+}
+
+/// Apply the absolute gate, then a relative gate `relative_lu` below the
+/// mean of the surviving blocks. Returns the powers of the blocks kept.
+fn gate(powers: &[f64], relative_lu: f64) -> Vec<f64> {
+    let above_absolute: Vec<f64> = powers.iter()
+        .copied()
+        .filter(|&p| p > 0.0 && to_lufs(p) > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if above_absolute.is_empty() {
+        return above_absolute;
+    }
+
+    let mean = above_absolute.iter().sum::<f64>() / above_absolute.len() as f64;
+    let threshold = to_lufs(mean) + relative_lu;
+    above_absolute.into_iter().filter(|&p| to_lufs(p) > threshold).collect()
+}
+
+/// Linearly interpolated percentile of sorted values.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let pos = p * (sorted.len() - 1) as f64;
+    let lower = pos.floor() as usize;
+    let upper = pos.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (pos - lower as f64)
+}
+
+fn channel_weights(channels: usize) -> Vec<f64> {
+    match channels {
+        5 => vec![1.0, 1.0, 1.0, 1.41, 1.41],
+        6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+        n => vec![1.0; n],
+    }
+}
+
+/// Highest absolute sample value after oversampling.
+fn true_peak(samples: &[f32], sample_rate: u32) -> f64 {
+    let sample_peak = samples.iter().map(|s| s.abs() as f64).fold(0.0, f64::max);
+    let factor = match sample_rate {
+        0..=95_999 => OVERSAMPLING,
+        96_000..=191_999 => 2,
+        _ => return sample_peak,
+    };
+
+    // Hann-windowed sinc kernels for each intermediate phase; kernel tap `k`
+    // weights sample `n + k + 1 - INTERPOLATION_TAPS`
+    let taps = 2 * INTERPOLATION_TAPS;
+    let kernels: Vec<Vec<f32>> = (1..factor)
+        .map(|phase| {
+            let frac = phase as f64 / factor as f64;
+            (1 - INTERPOLATION_TAPS as i64..=INTERPOLATION_TAPS as i64)
+                .map(|k| {
+                    let x = k as f64 - frac;
+                    let window = 0.5 + 0.5 * (std::f64::consts::PI * x / INTERPOLATION_TAPS as f64).cos();
+                    (sinc(x) * window) as f32
+                })
+                .collect()
+        })
+        .collect();
+
+    // Zero-pad so every interpolation point sees a full window
+    let pad = INTERPOLATION_TAPS;
+    let mut padded = vec![0.0f32; samples.len() + taps];
+    padded[pad - 1..pad - 1 + samples.len()].copy_from_slice(samples);
+
+    let mut peak = sample_peak as f32;
+    for window in padded.windows(taps) {
+        for kernel in &kernels {
+            let value: f32 = window.iter().zip(kernel).map(|(s, w)| s * w).sum();
+            peak = peak.max(value.abs());
+        }
+    }
+    peak as f64
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        let px = std::f64::consts::PI * x;
+        px.sin() / px
+    }
+}
+
+/// Two-stage K-weighting filter for a single channel.
+struct KWeighting {
+    stages: [Biquad; 2],
+}
+
+impl KWeighting {
+    fn new(sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+
+        // Stage 1: high shelf modelling the acoustic effect of the head
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (std::f64::consts::PI * f0 / fs).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        // Stage 2: RLB high-pass
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (std::f64::consts::PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        Self { stages: [shelf, highpass] }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let [shelf, highpass] = &mut self.stages;
+        highpass.process(shelf.process(x))
+    }
+}
+
+/// Direct form II transposed biquad section.
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, z: [0.0; 2] }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stereo 1kHz sine sections as used by EBU Tech 3341/3342, given as
+    /// (seconds, level in dBFS).
+    fn stereo_sine(sections: &[(f64, f64)], sample_rate: u32) -> AudioData {
+        let mut samples = Vec::new();
+        let mut n = 0usize;
+        for &(secs, dbfs) in sections {
+            let amplitude = 10f64.powf(dbfs / 20.0);
+            for _ in 0..(secs * sample_rate as f64).round() as usize {
+                let t = n as f64 / sample_rate as f64;
+                let s = (amplitude * (2.0 * std::f64::consts::PI * 1000.0 * t).sin()) as f32;
+                samples.extend([s, s]);
+                n += 1;
+            }
+        }
+        AudioData::with_channels(samples, sample_rate, 2)
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {} ± {}, got {}",
+            expected,
+            tolerance,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_tech_3341_integrated_loudness() {
+        // Cases 1-5 of EBU Tech 3341, all at 48kHz, ±0.1 LU
+        let cases: [(&[(f64, f64)], f64); 5] = [
+            (&[(20.0, -23.0)], -23.0),
+            (&[(20.0, -33.0)], -33.0),
+            (&[(10.0, -36.0), (60.0, -23.0), (10.0, -36.0)], -23.0),
+            (&[(10.0, -72.0), (10.0, -36.0), (60.0, -23.0), (10.0, -36.0), (10.0, -72.0)], -23.0),
+            (&[(20.0, -26.0), (20.1, -20.0), (20.0, -26.0)], -23.0),
+        ];
+
+        for (sections, expected) in cases {
+            let (integrated, _) = gated_loudness(&stereo_sine(sections, 48000)).unwrap();
+            assert_close(integrated, expected, 0.1);
+        }
+    }
+
+    #[test]
+    fn test_integrated_loudness_at_44100() {
+        let (integrated, _) = gated_loudness(&stereo_sine(&[(20.0, -23.0)], 44100)).unwrap();
+        assert_close(integrated, -23.0, 0.1);
+    }
+
+    #[test]
+    fn test_tech_3342_loudness_range() {
+        // Cases 1-4 of EBU Tech 3342, ±1 LU
+        let cases: [(&[(f64, f64)], f64); 4] = [
+            (&[(20.0, -20.0), (20.0, -30.0)], 10.0),
+            (&[(20.0, -20.0), (20.0, -15.0)], 5.0),
+            (&[(20.0, -40.0), (20.0, -20.0)], 20.0),
+            (&[(20.0, -50.0), (20.0, -35.0), (20.0, -20.0), (20.0, -35.0), (20.0, -50.0)], 15.0),
+        ];
+
+        for (sections, expected) in cases {
+            let (_, range) = gated_loudness(&stereo_sine(sections, 48000)).unwrap();
+            assert_close(range, expected, 1.0);
+        }
+    }
+
+    #[test]
+    fn test_true_peak_between_samples() {
+        // fs/4 sine with a 45° phase offset: every sample lands at 0.707 of
+        // the peak, so the sample peak under-reads by 3dB
+        let samples: Vec<f32> = (0..48000)
+            .map(|n| (0.5 * (std::f64::consts::FRAC_PI_2 * n as f64 + std::f64::consts::FRAC_PI_4).sin()) as f32)
+            .collect();
+        let audio = AudioData::new(samples, 48000);
+
+        let sample_peak = audio.samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert_close(20.0 * (sample_peak as f64).log10(), -9.03, 0.01);
+
+        // Tech 3341 allows +0.2 / -0.4 dB for true peak
+        let info = measure(&audio).unwrap();
+        assert!(info.true_peak_dbtp > -6.02 - 0.4 && info.true_peak_dbtp < -6.02 + 0.2, "{}", info.true_peak_dbtp);
+    }
+
+    #[test]
+    fn test_silence_and_short_input() {
+        let info = measure(&AudioData::new(vec![0.0; 48000], 48000)).unwrap();
+        assert_eq!(info.integrated_lufs, ABSOLUTE_GATE_LUFS);
+        assert_eq!(info.loudness_range_lu, 0.0);
+        assert_eq!(info.true_peak_dbtp, SILENCE_PEAK_DB);
+
+        assert!(measure(&AudioData::new(vec![0.1; 1000], 48000)).is_err());
+    }
+
+    #[test]
+    fn test_normalize_to_target() {
+        let audio = stereo_sine(&[(5.0, -30.0)], 48000);
+        let info = measure(&audio).unwrap();
+
+        let normalized = normalize(&audio, &info, -16.0);
+        assert_close(measure(&normalized).unwrap().integrated_lufs, -16.0, 0.05);
+    }
+}
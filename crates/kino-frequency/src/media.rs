@@ -0,0 +1,717 @@
+//! Media probing and extraction behind a swappable backend.
+//!
+//! Everything that needs a decoded video (audio extraction, durations and
+//! frames) goes through a [`MediaBackend`]. [`FfmpegBackend`] shells out to
+//! `ffmpeg` and `ffprobe` and is the default everywhere; tests can drive
+//! [`AudioAnalyzer`](crate::AudioAnalyzer) and the thumbnail selector with
+//! the mock in the `testing` module instead, so they run without FFmpeg.
+//!
+//! [`MediaBackend::stream_audio`] hands audio over in chunks as it is
+//! decoded, for consumers such as the streaming fingerprinter that don't
+//! need the whole track in memory at once.
+//!
+//! Audio comes from the default track of the whole program unless
+//! [`AudioExtractOptions`] pick a stream, such as one language of a
+//! multi-language master, or a time range. [`MediaBackend::audio_tracks`]
+//! lists the streams to choose from.
+//!
+//! Frame decoding takes [`DecodeOptions`]: HDR (PQ or HLG) sources found by
+//! [`MediaBackend::color_info`] can be tone-mapped to SDR before analysis, so
+//! sharpness and contrast aren't measured on a washed-out picture, and
+//! decoding can be tried on the GPU first.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+use tracing::{debug, info, warn};
+
+use crate::types::AudioData;
+
+/// Width of the grayscale frames extracted for analysis.
+pub const ANALYSIS_FRAME_WIDTH: u32 = 320;
+
+/// Height of the grayscale frames extracted for analysis.
+pub const ANALYSIS_FRAME_HEIGHT: u32 = 180;
+
+/// Samples per chunk handed over by [`MediaBackend::stream_audio`].
+pub const STREAM_CHUNK_SAMPLES: usize = 32 * 1024;
+
+/// Receives chunks of mono samples from [`MediaBackend::stream_audio`];
+/// returning an error stops the extraction.
+pub type AudioSink<'a> = dyn FnMut(&[f32]) -> Result<()> + Send + 'a;
+
+/// Which audio stream, and which part of it, to extract.
+///
+/// The default is the file's default audio stream for its whole duration.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioExtractOptions {
+    /// Stream to extract, by its index among all the file's streams (as
+    /// listed by [`MediaBackend::audio_tracks`] and passed to `-map 0:<index>`)
+    pub stream_index: Option<usize>,
+    /// Extract the first audio stream tagged with this language (e.g. `eng`)
+    /// when no `stream_index` is given
+    pub language: Option<String>,
+    /// Seconds to skip from the start of the media
+    pub start: Option<f64>,
+    /// Seconds to extract; to the end of the media when `None`
+    pub duration: Option<f64>,
+    /// Sample rate to extract at instead of the analyzer's
+    pub sample_rate_override: Option<u32>,
+}
+
+impl AudioExtractOptions {
+    /// Whether only part of the program's duration is extracted.
+    pub fn is_range(&self) -> bool {
+        self.start.is_some_and(|start| start > 0.0) || self.duration.is_some()
+    }
+
+    /// Check the time range, which FFmpeg would otherwise misread or ignore.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(start) = self.start {
+            if !start.is_finite() || start < 0.0 {
+                bail!("Audio start must be a non-negative number of seconds, got {}", start);
+            }
+        }
+        if let Some(duration) = self.duration {
+            if !duration.is_finite() || duration <= 0.0 {
+                bail!("Audio duration must be a positive number of seconds, got {}", duration);
+            }
+        }
+        if self.sample_rate_override == Some(0) {
+            bail!("Sample rate must be non-zero");
+        }
+        Ok(())
+    }
+}
+
+/// An audio stream of a media file, as reported by `ffprobe`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioTrack {
+    /// Index among all the file's streams, as used by
+    /// [`AudioExtractOptions::stream_index`]
+    pub index: usize,
+    /// Language tag, e.g. `eng`
+    pub language: Option<String>,
+    /// Stream title, e.g. "English 5.1"
+    pub title: Option<String>,
+    /// Codec name, e.g. `aac`
+    pub codec: Option<String>,
+    /// Number of channels
+    pub channels: u32,
+    /// Channel layout, e.g. `5.1(side)`
+    pub channel_layout: Option<String>,
+    /// Sample rate in Hz
+    pub sample_rate: Option<u32>,
+    /// Whether the stream is flagged as the default
+    pub default: bool,
+}
+
+impl AudioTrack {
+    /// Read the audio streams of `ffprobe -show_streams` JSON output.
+    pub(crate) fn from_ffprobe(json: &serde_json::Value) -> Vec<Self> {
+        let Some(streams) = json["streams"].as_array() else {
+            return Vec::new();
+        };
+        let text = |value: &serde_json::Value| {
+            value.as_str()
+                .filter(|v| !v.is_empty() && *v != "und" && *v != "unknown")
+                .map(str::to_string)
+        };
+        streams.iter()
+            .filter(|s| s["codec_type"] == "audio")
+            .filter_map(|s| {
+                Some(Self {
+                    index: s["index"].as_u64()? as usize,
+                    language: text(&s["tags"]["language"]),
+                    title: text(&s["tags"]["title"]),
+                    codec: text(&s["codec_name"]),
+                    channels: s["channels"].as_u64().unwrap_or(0) as u32,
+                    channel_layout: text(&s["channel_layout"]),
+                    sample_rate: s["sample_rate"].as_str().and_then(|r| r.parse().ok()),
+                    default: s["disposition"]["default"].as_u64() == Some(1),
+                })
+            })
+            .collect()
+    }
+
+    /// Whether the track is tagged with `language`, ignoring case.
+    pub fn has_language(&self, language: &str) -> bool {
+        self.language.as_deref().is_some_and(|l| l.eq_ignore_ascii_case(language))
+    }
+}
+
+/// High dynamic range transfer characteristic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HdrTransfer {
+    /// SMPTE ST 2084 perceptual quantizer (HDR10, Dolby Vision)
+    Pq,
+    /// ARIB STD-B67 hybrid log-gamma
+    Hlg,
+}
+
+impl HdrTransfer {
+    /// The transfer's name in FFmpeg and zscale.
+    pub fn ffmpeg_name(self) -> &'static str {
+        match self {
+            Self::Pq => "smpte2084",
+            Self::Hlg => "arib-std-b67",
+        }
+    }
+}
+
+/// Color metadata of a video stream, as reported by `ffprobe`.
+///
+/// Fields are FFmpeg's names (`bt709`, `bt2020nc`, `smpte2084`, ...) and are
+/// `None` when the stream doesn't say.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColorInfo {
+    /// Transfer characteristic (`color_transfer`)
+    pub transfer: Option<String>,
+    /// Color primaries (`color_primaries`)
+    pub primaries: Option<String>,
+    /// Matrix coefficients (`color_space`)
+    pub matrix: Option<String>,
+    /// `tv` (limited) or `pc` (full) range (`color_range`)
+    pub range: Option<String>,
+    /// Decoded pixel format, e.g. `yuv420p10le`
+    pub pixel_format: Option<String>,
+}
+
+impl ColorInfo {
+    /// Read the first video stream of `ffprobe -show_streams` JSON output.
+    pub(crate) fn from_ffprobe(json: &serde_json::Value) -> Self {
+        let stream = json["streams"].as_array()
+            .and_then(|streams| streams.iter().find(|s| s["codec_type"] == "video"))
+            .unwrap_or(&serde_json::Value::Null);
+        let field = |name: &str| {
+            stream[name].as_str()
+                .filter(|v| !v.is_empty() && *v != "unknown")
+                .map(str::to_string)
+        };
+        Self {
+            transfer: field("color_transfer"),
+            primaries: field("color_primaries"),
+            matrix: field("color_space"),
+            range: field("color_range"),
+            pixel_format: field("pix_fmt"),
+        }
+    }
+
+    /// The HDR transfer characteristic, if the stream is HDR.
+    pub fn hdr(&self) -> Option<HdrTransfer> {
+        match self.transfer.as_deref()? {
+            "smpte2084" => Some(HdrTransfer::Pq),
+            "arib-std-b67" => Some(HdrTransfer::Hlg),
+            _ => None,
+        }
+    }
+
+    /// Whether the stream is HDR (PQ or HLG).
+    pub fn is_hdr(&self) -> bool {
+        self.hdr().is_some()
+    }
+}
+
+/// How frames are decoded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Tone-map from this HDR transfer to SDR BT.709
+    pub tonemap: Option<HdrTransfer>,
+    /// FFmpeg hardware decoder to try first (e.g. `auto`, `cuda`,
+    /// `videotoolbox`); decoding silently falls back to software
+    pub hwaccel: Option<String>,
+    /// Keep 16 bits per channel in written images (PNG only)
+    pub high_bit_depth: bool,
+}
+
+/// Probes and decodes media files.
+///
+/// Audio extraction honours the stream index and time range of
+/// [`AudioExtractOptions`]; its language and sample rate override are
+/// resolved by [`AudioAnalyzer`](crate::AudioAnalyzer) beforehand.
+#[async_trait]
+pub trait MediaBackend: Send + Sync {
+    /// Extract the audio selected by `options` as mono at `sample_rate`.
+    async fn extract_audio(&self, path: &Path, sample_rate: u32, options: &AudioExtractOptions) -> Result<AudioData>;
+
+    /// Extract the audio selected by `options` as mono at `sample_rate`,
+    /// handing it to `sink` in chunks of at most [`STREAM_CHUNK_SAMPLES`]
+    /// samples.
+    ///
+    /// The default extracts the whole selection with
+    /// [`extract_audio`](Self::extract_audio) and then splits it; backends
+    /// that can decode incrementally override it so memory stays bounded.
+    async fn stream_audio(
+        &self,
+        path: &Path,
+        sample_rate: u32,
+        options: &AudioExtractOptions,
+        sink: &mut AudioSink<'_>,
+    ) -> Result<()> {
+        let audio = self.extract_audio(path, sample_rate, options).await?;
+        for chunk in audio.samples.chunks(STREAM_CHUNK_SAMPLES) {
+            sink(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// The audio streams of the media, in stream order.
+    fn audio_tracks(&self, path: &Path) -> Result<Vec<AudioTrack>>;
+
+    /// Duration of the media in seconds.
+    fn duration(&self, path: &Path) -> Result<f64>;
+
+    /// Color metadata of the video stream; unknown (treated as SDR) by default.
+    fn color_info(&self, _path: &Path) -> Result<ColorInfo> {
+        Ok(ColorInfo::default())
+    }
+
+    /// A small grayscale frame at `timestamp` for analysis.
+    fn extract_frame(&self, path: &Path, timestamp: f64, options: &DecodeOptions) -> Result<GrayImage>;
+
+    /// Every frame of `duration` seconds from `start`, sampled at `fps`, as
+    /// small grayscale images.
+    fn extract_frames(
+        &self,
+        path: &Path,
+        start: f64,
+        duration: f64,
+        fps: f64,
+        options: &DecodeOptions,
+    ) -> Result<Vec<GrayImage>>;
+
+    /// Write the frame at `timestamp`, scaled to `width` x `height`, as an image file.
+    fn write_thumbnail(
+        &self,
+        path: &Path,
+        timestamp: f64,
+        width: u32,
+        height: u32,
+        output: &Path,
+        options: &DecodeOptions,
+    ) -> Result<()>;
+}
+
+/// The FFmpeg command-line tools.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FfmpegBackend;
+
+#[async_trait]
+impl MediaBackend for FfmpegBackend {
+    async fn extract_audio(&self, path: &Path, sample_rate: u32, options: &AudioExtractOptions) -> Result<AudioData> {
+        // Create temporary WAV file
+        let temp_dir = std::env::temp_dir();
+        let temp_wav = temp_dir.join(format!("kino_audio_{}.wav", uuid::Uuid::new_v4()));
+
+        // Run FFmpeg to extract audio
+        let output = tokio::process::Command::new("ffmpeg")
+            .args(audio_input_args(path, options))
+            .args([
+                "-vn",                          // No video
+                "-acodec", "pcm_s16le",         // 16-bit PCM
+                "-ar", &sample_rate.to_string(),  // Sample rate
+                "-ac", "1",                     // Mono
+                "-y",                           // Overwrite
+                &temp_wav.to_string_lossy(),
+            ])
+            // Cancelled extractions drop this future; don't leave FFmpeg running
+            .kill_on_drop(true)
+            .output()
+            .await
+            .context("FFmpeg not found. Please install FFmpeg.")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("FFmpeg audio extraction failed: {}", stderr);
+        }
+
+        // Read the WAV file, cleaning up the temp file even if decoding fails
+        let decoded = crate::read_wav(&temp_wav);
+        let _ = std::fs::remove_file(&temp_wav);
+        let audio = decoded.context("Failed to read extracted audio")?;
+
+        if audio.channels > 1 {
+            warn!("Expected mono audio from FFmpeg but got {} channels, downmixing", audio.channels);
+            return Ok(audio.to_mono().into_owned());
+        }
+
+        Ok(audio)
+    }
+
+    async fn stream_audio(
+        &self,
+        path: &Path,
+        sample_rate: u32,
+        options: &AudioExtractOptions,
+        sink: &mut AudioSink<'_>,
+    ) -> Result<()> {
+        // Raw PCM on stdout, read a chunk at a time
+        let mut child = tokio::process::Command::new("ffmpeg")
+            .args(["-v", "error", "-nostdin"])
+            .args(audio_input_args(path, options))
+            .args([
+                "-vn",
+                "-f", "s16le",
+                "-acodec", "pcm_s16le",
+                "-ar", &sample_rate.to_string(),
+                "-ac", "1",
+                "pipe:1",
+            ])
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("FFmpeg not found. Please install FFmpeg.")?;
+
+        let mut stdout = child.stdout.take().context("FFmpeg stdout unavailable")?;
+        let mut buffer = vec![0u8; STREAM_CHUNK_SAMPLES * 2];
+        let mut filled = 0;
+        loop {
+            let read = stdout.read(&mut buffer[filled..]).await.context("Failed to read FFmpeg output")?;
+            filled += read;
+            if read == 0 || filled == buffer.len() {
+                // Keep an odd trailing byte for the next read
+                let whole = filled - filled % 2;
+                if whole > 0 {
+                    sink(&pcm_s16le_samples(&buffer[..whole]))?;
+                }
+                buffer.copy_within(whole..filled, 0);
+                filled -= whole;
+            }
+            if read == 0 {
+                break;
+            }
+        }
+
+        let output = child.wait_with_output().await.context("FFmpeg audio extraction failed")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("FFmpeg audio extraction failed: {}", stderr);
+        }
+        Ok(())
+    }
+
+    fn audio_tracks(&self, path: &Path) -> Result<Vec<AudioTrack>> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v", "error",
+                "-print_format", "json",
+                "-show_streams",
+                "-select_streams", "a",
+                &path.to_string_lossy(),
+            ])
+            .output()
+            .context("FFprobe not found")?;
+
+        if !output.status.success() {
+            bail!("FFprobe failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+            .context("Failed to parse ffprobe output")?;
+
+        Ok(AudioTrack::from_ffprobe(&json))
+    }
+
+    fn duration(&self, path: &Path) -> Result<f64> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v", "quiet",
+                "-print_format", "json",
+                "-show_format",
+                &path.to_string_lossy(),
+            ])
+            .output()
+            .context("FFprobe not found")?;
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+            .context("Failed to parse ffprobe output")?;
+
+        let duration = json["format"]["duration"]
+            .as_str()
+            .and_then(|d| d.parse::<f64>().ok())
+            .ok_or_else(|| anyhow::anyhow!("Could not determine video duration"))?;
+
+        Ok(duration)
+    }
+
+    fn color_info(&self, path: &Path) -> Result<ColorInfo> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v", "quiet",
+                "-print_format", "json",
+                "-show_streams",
+                "-select_streams", "v:0",
+                &path.to_string_lossy(),
+            ])
+            .output()
+            .context("FFprobe not found")?;
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+            .context("Failed to parse ffprobe output")?;
+
+        Ok(ColorInfo::from_ffprobe(&json))
+    }
+
+    fn extract_frame(&self, path: &Path, timestamp: f64, options: &DecodeOptions) -> Result<GrayImage> {
+        // Extract frame to raw grayscale
+        let filter = video_filter(options, &format!("scale={}:{},format=gray", ANALYSIS_FRAME_WIDTH, ANALYSIS_FRAME_HEIGHT));
+        let output = run_ffmpeg(options, &[
+            "-ss", &format!("{:.3}", timestamp),
+            "-i", &path.to_string_lossy(),
+            "-vframes", "1",
+            "-vf", &filter,
+            "-f", "rawvideo",
+            "-pix_fmt", "gray",
+            "pipe:1",
+        ])
+        .context("FFmpeg frame extraction failed")?;
+
+        if !output.status.success() || output.stdout.is_empty() {
+            bail!("Failed to extract frame at {:.2}s", timestamp);
+        }
+
+        let frame_size = (ANALYSIS_FRAME_WIDTH * ANALYSIS_FRAME_HEIGHT) as usize;
+        if output.stdout.len() < frame_size {
+            bail!("Incomplete frame data");
+        }
+
+        GrayImage::from_raw(ANALYSIS_FRAME_WIDTH, ANALYSIS_FRAME_HEIGHT, output.stdout[..frame_size].to_vec())
+            .ok_or_else(|| anyhow::anyhow!("Failed to create image from raw data"))
+    }
+
+    fn extract_frames(
+        &self,
+        path: &Path,
+        start: f64,
+        duration: f64,
+        fps: f64,
+        options: &DecodeOptions,
+    ) -> Result<Vec<GrayImage>> {
+        let filter = video_filter(
+            options,
+            &format!("fps={},scale={}:{},format=gray", fps, ANALYSIS_FRAME_WIDTH, ANALYSIS_FRAME_HEIGHT),
+        );
+        let output = run_ffmpeg(options, &[
+            "-ss", &format!("{:.3}", start),
+            "-t", &format!("{:.3}", duration),
+            "-i", &path.to_string_lossy(),
+            "-vf", &filter,
+            "-f", "rawvideo",
+            "-pix_fmt", "gray",
+            "pipe:1",
+        ])
+        .context("FFmpeg frame extraction failed")?;
+
+        if !output.status.success() {
+            bail!("Failed to extract frames at {:.2}s", start);
+        }
+
+        let frame_size = (ANALYSIS_FRAME_WIDTH * ANALYSIS_FRAME_HEIGHT) as usize;
+        Ok(output.stdout.chunks_exact(frame_size)
+            .filter_map(|raw| GrayImage::from_raw(ANALYSIS_FRAME_WIDTH, ANALYSIS_FRAME_HEIGHT, raw.to_vec()))
+            .collect())
+    }
+
+    fn write_thumbnail(
+        &self,
+        path: &Path,
+        timestamp: f64,
+        width: u32,
+        height: u32,
+        output: &Path,
+        options: &DecodeOptions,
+    ) -> Result<()> {
+        let filter = video_filter(options, &format!("scale={}:{}", width, height));
+        let mut args = vec![
+            "-ss".to_string(), format!("{:.3}", timestamp),
+            "-i".to_string(), path.to_string_lossy().into_owned(),
+            "-vframes".to_string(), "1".to_string(),
+            "-vf".to_string(), filter,
+        ];
+        if options.high_bit_depth {
+            args.extend(["-pix_fmt".to_string(), "rgb48be".to_string()]);
+        }
+        args.extend(["-y".to_string(), output.to_string_lossy().into_owned()]);
+
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let result = run_ffmpeg(options, &args).context("FFmpeg not found")?;
+
+        if !result.status.success() {
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            bail!("FFmpeg thumbnail extraction failed: {}", stderr);
+        }
+
+        info!("Extracted thumbnail to: {}", output.display());
+        Ok(())
+    }
+}
+
+/// The FFmpeg arguments that open `path` and select the audio in `options`.
+///
+/// Seeking before `-i` is fast and, for audio, sample accurate.
+fn audio_input_args(path: &Path, options: &AudioExtractOptions) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(start) = options.start {
+        args.extend(["-ss".to_string(), format!("{:.3}", start)]);
+    }
+    args.extend(["-i".to_string(), path.to_string_lossy().into_owned()]);
+    if let Some(duration) = options.duration {
+        args.extend(["-t".to_string(), format!("{:.3}", duration)]);
+    }
+    if let Some(index) = options.stream_index {
+        args.extend(["-map".to_string(), format!("0:{}", index)]);
+    }
+    args
+}
+
+/// Little-endian 16-bit PCM as samples in [-1, 1), scaled like WAV input.
+fn pcm_s16le_samples(bytes: &[u8]) -> Vec<f32> {
+    bytes.chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0)
+        .collect()
+}
+
+/// The `-vf` filter graph: tone mapping when asked, then `rest`.
+fn video_filter(options: &DecodeOptions, rest: &str) -> String {
+    match options.tonemap {
+        // Linearize, map highlights into SDR with Hable's curve, then convert
+        // to BT.709 so the rest of the graph sees an ordinary SDR picture
+        Some(transfer) => format!(
+            "zscale=tin={}:pin=bt2020:min=bt2020nc:t=linear:npl=100,format=gbrpf32le,\
+             zscale=p=bt709,tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,{}",
+            transfer.ffmpeg_name(),
+            rest,
+        ),
+        None => rest.to_string(),
+    }
+}
+
+/// Run `ffmpeg` with `args`, trying the hardware decoder in `options` first.
+///
+/// Hardware decoding isn't available everywhere and fails for some codecs and
+/// filter graphs, so any failure is retried in software.
+fn run_ffmpeg(options: &DecodeOptions, args: &[&str]) -> std::io::Result<Output> {
+    if let Some(hwaccel) = &options.hwaccel {
+        match Command::new("ffmpeg").args(["-hwaccel", hwaccel]).args(args).output() {
+            Ok(output) if output.status.success() => return Ok(output),
+            Ok(_) => debug!("Hardware decoding with {} failed, retrying in software", hwaccel),
+            Err(e) => return Err(e),
+        }
+    }
+    Command::new("ffmpeg").args(args).output()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_info_from_ffprobe() {
+        let json = serde_json::json!({
+            "streams": [{
+                "codec_type": "video",
+                "pix_fmt": "yuv420p10le",
+                "color_range": "tv",
+                "color_space": "bt2020nc",
+                "color_transfer": "smpte2084",
+                "color_primaries": "bt2020",
+            }]
+        });
+        let info = ColorInfo::from_ffprobe(&json);
+        assert_eq!(info.hdr(), Some(HdrTransfer::Pq));
+        assert_eq!(info.pixel_format.as_deref(), Some("yuv420p10le"));
+        assert_eq!(info.matrix.as_deref(), Some("bt2020nc"));
+
+        let hlg = serde_json::json!({ "streams": [{ "codec_type": "video", "color_transfer": "arib-std-b67" }] });
+        assert_eq!(ColorInfo::from_ffprobe(&hlg).hdr(), Some(HdrTransfer::Hlg));
+
+        // SDR, unknown and missing metadata
+        let sdr = serde_json::json!({ "streams": [{ "codec_type": "video", "color_transfer": "bt709", "color_space": "unknown" }] });
+        let sdr = ColorInfo::from_ffprobe(&sdr);
+        assert!(!sdr.is_hdr());
+        assert_eq!(sdr.matrix, None);
+        assert_eq!(ColorInfo::from_ffprobe(&serde_json::json!({})), ColorInfo::default());
+    }
+
+    #[test]
+    fn test_audio_tracks_from_ffprobe() {
+        let json = serde_json::json!({
+            "streams": [
+                { "index": 0, "codec_type": "video", "codec_name": "h264" },
+                {
+                    "index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2,
+                    "channel_layout": "stereo", "sample_rate": "48000",
+                    "disposition": { "default": 1 }, "tags": { "language": "fre" },
+                },
+                {
+                    "index": 2, "codec_type": "audio", "codec_name": "eac3", "channels": 6,
+                    "channel_layout": "5.1(side)", "sample_rate": "48000",
+                    "disposition": { "default": 0 }, "tags": { "language": "eng", "title": "English 5.1" },
+                },
+                { "index": 3, "codec_type": "audio", "codec_name": "aac", "tags": { "language": "und" } },
+            ]
+        });
+        let tracks = AudioTrack::from_ffprobe(&json);
+        assert_eq!(tracks.iter().map(|t| t.index).collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(tracks[0].default && !tracks[1].default);
+        assert_eq!(tracks[1], AudioTrack {
+            index: 2,
+            language: Some("eng".to_string()),
+            title: Some("English 5.1".to_string()),
+            codec: Some("eac3".to_string()),
+            channels: 6,
+            channel_layout: Some("5.1(side)".to_string()),
+            sample_rate: Some(48000),
+            default: false,
+        });
+        assert!(tracks[1].has_language("ENG"));
+        assert_eq!(tracks[2].language, None);
+        assert!(AudioTrack::from_ffprobe(&serde_json::json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_audio_input_args() {
+        let path = Path::new("master.mov");
+        assert_eq!(audio_input_args(path, &AudioExtractOptions::default()), ["-i", "master.mov"]);
+
+        let options = AudioExtractOptions {
+            stream_index: Some(2),
+            start: Some(90.0),
+            duration: Some(600.0),
+            ..Default::default()
+        };
+        assert!(options.is_range());
+        assert_eq!(
+            audio_input_args(path, &options),
+            ["-ss", "90.000", "-i", "master.mov", "-t", "600.000", "-map", "0:2"]
+        );
+
+        assert!(AudioExtractOptions { start: Some(-1.0), ..Default::default() }.validate().is_err());
+        assert!(AudioExtractOptions { duration: Some(0.0), ..Default::default() }.validate().is_err());
+        assert!(options.validate().is_ok());
+    }
+
+    #[test]
+    fn test_pcm_s16le_samples() {
+        let bytes = [0x00, 0x80, 0xff, 0x7f, 0x00, 0x00, 0x00, 0x40];
+        assert_eq!(pcm_s16le_samples(&bytes), vec![-1.0, 32767.0 / 32768.0, 0.0, 0.5]);
+        assert!(pcm_s16le_samples(&[0x01]).is_empty());
+    }
+
+    #[test]
+    fn test_video_filter() {
+        assert_eq!(video_filter(&DecodeOptions::default(), "scale=320:180"), "scale=320:180");
+
+        let options = DecodeOptions { tonemap: Some(HdrTransfer::Hlg), ..Default::default() };
+        let filter = video_filter(&options, "scale=320:180,format=gray");
+        assert!(filter.starts_with("zscale=tin=arib-std-b67:"), "{}", filter);
+        assert!(filter.contains("tonemap=tonemap=hable"), "{}", filter);
+        assert!(filter.ends_with(",scale=320:180,format=gray"), "{}", filter);
+    }
+}
@@ -0,0 +1,315 @@
+//! Tempo and key detection for music.
+//!
+//! # Tempo
+//!
+//! 1. Onset strength: half-wave rectified spectral flux of log-compressed
+//!    magnitude spectra, detrended by its moving average
+//! 2. Autocorrelation of the onset strength over beat periods of
+//!    [`TEMPO_RANGE`], weighted by a log-normal prior around
+//!    [`PREFERRED_BPM`] so the beat level wins over half and double time
+//! 3. Parabolic interpolation around the best lag for sub-frame precision
+//!
+//! The confidence is the normalized autocorrelation at the chosen period:
+//! near one for a steady beat, near zero without one.
+//!
+//! # Key
+//!
+//! Spectra are folded into a 12-bin chromagram, which is correlated with
+//! the 24 rotations of the Krumhansl-Kessler major and minor key profiles.
+//! The confidence is the winning correlation.
+//!
+//! The tagging rules keep the simpler estimator shared with the WASM build,
+//! so native and browser tags stay identical.
+
+use crate::fft::FrequencyAnalyzer;
+use crate::types::*;
+
+/// Tag confidence of "music" above which [`crate::process_video`] reports [`MusicInfo`].
+pub const MUSIC_INFO_MIN_CONFIDENCE: f32 = 0.5;
+
+/// Detected tempos lie in this range (BPM).
+pub const TEMPO_RANGE: (f32, f32) = (50.0, 220.0);
+
+/// Center of the tempo prior (BPM).
+pub const PREFERRED_BPM: f32 = 120.0;
+
+/// Width of the tempo prior in octaves (one standard deviation).
+const TEMPO_PRIOR_OCTAVES: f32 = 1.0;
+
+/// Reported with zero confidence when no beat period can be measured.
+const FALLBACK_BPM: f32 = 120.0;
+
+/// Onset analysis frame and hop at 44.1 kHz, scaled with the sample rate.
+const ONSET_FRAME_SECS: f64 = 2048.0 / 44100.0;
+const ONSET_HOP_SECS: f64 = 512.0 / 44100.0;
+
+/// Minimum onset flux as a fraction of the frame's total (log-compressed)
+/// magnitude, so the frame-to-frame ripple of sustained tones isn't a beat.
+const MIN_RELATIVE_FLUX: f32 = 0.05;
+
+/// Span of the moving average subtracted from the onset strength (seconds).
+const ONSET_DETREND_SECS: f64 = 0.5;
+
+/// Chroma analysis frame; long enough to resolve semitones in the bass.
+const CHROMA_FFT_SIZE: usize = 8192;
+
+/// Pitches folded into the chromagram (Hz).
+const CHROMA_RANGE: (f32, f32) = (55.0, 4200.0);
+
+/// Pitch class names, starting at C.
+pub const PITCH_CLASSES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// Krumhansl-Kessler probe-tone ratings for C major.
+const MAJOR_PROFILE: [f32; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+
+/// Krumhansl-Kessler probe-tone ratings for C minor.
+const MINOR_PROFILE: [f32; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+/// Detect tempo and key.
+///
+/// Multichannel audio is downmixed. Fails with [`FrequencyError::TooShort`]
+/// below [`CHROMA_FFT_SIZE`] samples, [`FrequencyError::ZeroSignal`] for
+/// silent input and [`FrequencyError::InvalidSamples`] if any sample is NaN
+/// or infinite.
+pub fn analyze(audio: &AudioData) -> FrequencyResult<MusicInfo> {
+    let audio = audio.prepare(SamplePolicy::Reject, CHROMA_FFT_SIZE)?;
+    let audio = audio.to_mono();
+
+    let (bpm, bpm_confidence) = estimate_tempo(&audio.samples, audio.sample_rate)?;
+    let (tonic, mode, key_confidence) = detect_key(&audio.samples, audio.sample_rate)?;
+
+    Ok(MusicInfo {
+        bpm,
+        bpm_confidence,
+        key: PITCH_CLASSES[tonic].to_string(),
+        mode,
+        key_confidence,
+    })
+}
+
+/// Estimate the tempo of mono samples as `(bpm, confidence)`.
+///
+/// Audio too short to hold two beat periods reports 120 BPM with zero confidence.
+pub fn estimate_tempo(samples: &[f32], sample_rate: u32) -> FrequencyResult<(f32, f32)> {
+    let rate = sample_rate as f64;
+    let frame = ((ONSET_FRAME_SECS * rate) as usize).max(2);
+    let hop = ((ONSET_HOP_SECS * rate) as usize).max(1);
+    if samples.len() < frame {
+        return Ok((FALLBACK_BPM, 0.0));
+    }
+
+    let onsets = onset_strength(&FrequencyAnalyzer::new(frame, hop).compute_spectrogram(samples)?, hop, rate);
+    let frames_per_sec = rate / hop as f64;
+    let lag_of = |bpm: f32| 60.0 * frames_per_sec as f32 / bpm;
+    let min_lag = lag_of(TEMPO_RANGE.1).floor().max(1.0) as usize;
+    let max_lag = (lag_of(TEMPO_RANGE.0).ceil() as usize).min(onsets.len() / 2);
+    if min_lag + 2 > max_lag {
+        return Ok((FALLBACK_BPM, 0.0));
+    }
+
+    let correlation = normalized_autocorrelation(&onsets, max_lag + 2);
+    let prior = |lag: usize| {
+        let octaves = (lag_of(PREFERRED_BPM) / lag as f32).log2() / TEMPO_PRIOR_OCTAVES;
+        (-0.5 * octaves * octaves).exp()
+    };
+    let Some(best) = (min_lag..=max_lag)
+        .filter(|&lag| correlation[lag] > 0.0)
+        .max_by(|&a, &b| (correlation[a] * prior(a)).total_cmp(&(correlation[b] * prior(b))))
+    else {
+        return Ok((FALLBACK_BPM, 0.0));
+    };
+
+    // Vertex of the parabola through the peak and its neighbours
+    let (before, peak, after) = (correlation[best - 1], correlation[best], correlation[best + 1]);
+    let curvature = before - 2.0 * peak + after;
+    let offset = if curvature < 0.0 { (0.5 * (before - after) / curvature).clamp(-0.5, 0.5) } else { 0.0 };
+
+    let bpm = 60.0 * frames_per_sec as f32 / (best as f32 + offset);
+    Ok((bpm.clamp(TEMPO_RANGE.0, TEMPO_RANGE.1), peak.clamp(0.0, 1.0)))
+}
+
+/// Detect the key of mono samples as `(tonic pitch class, mode, confidence)`.
+///
+/// The tonic indexes [`PITCH_CLASSES`].
+pub fn detect_key(samples: &[f32], sample_rate: u32) -> FrequencyResult<(usize, KeyMode, f32)> {
+    let chroma = chromagram(samples, sample_rate)?;
+
+    let mut best = (0, KeyMode::Major, f32::NEG_INFINITY);
+    for tonic in 0..12 {
+        for (mode, profile) in [(KeyMode::Major, &MAJOR_PROFILE), (KeyMode::Minor, &MINOR_PROFILE)] {
+            let rotated: Vec<f32> = (0..12).map(|pc| profile[(pc + 12 - tonic) % 12]).collect();
+            let r = pearson(&chroma, &rotated);
+            if r > best.2 {
+                best = (tonic, mode, r);
+            }
+        }
+    }
+
+    Ok((best.0, best.1, best.2.clamp(0.0, 1.0)))
+}
+
+/// Energy per pitch class summed over all frames, starting at C.
+pub fn chromagram(samples: &[f32], sample_rate: u32) -> FrequencyResult<[f32; 12]> {
+    let analyzer = FrequencyAnalyzer::new(CHROMA_FFT_SIZE, CHROMA_FFT_SIZE / 2);
+    let spectrogram = analyzer.compute_spectrogram(samples)?;
+
+    let bin_hz = sample_rate as f32 / CHROMA_FFT_SIZE as f32;
+    let pitch_classes: Vec<Option<usize>> = (0..CHROMA_FFT_SIZE / 2)
+        .map(|bin| {
+            let freq = bin as f32 * bin_hz;
+            if freq < CHROMA_RANGE.0 || freq > CHROMA_RANGE.1 {
+                return None;
+            }
+            let midi = 69.0 + 12.0 * (freq / 440.0).log2();
+            Some((midi.round() as i32).rem_euclid(12) as usize)
+        })
+        .collect();
+
+    let mut chroma = [0.0f32; 12];
+    for spectrum in &spectrogram {
+        for (magnitude, pitch_class) in spectrum.iter().zip(&pitch_classes) {
+            if let Some(pc) = pitch_class {
+                chroma[*pc] += magnitude * magnitude;
+            }
+        }
+    }
+    Ok(chroma)
+}
+
+/// Detrended, half-wave rectified spectral flux per frame.
+fn onset_strength(spectrogram: &[Vec<f32>], hop: usize, rate: f64) -> Vec<f32> {
+    let compress = |m: f32| (1.0 + 1000.0 * m).ln();
+    let flux: Vec<f32> = spectrogram.windows(2)
+        .map(|pair| {
+            let rise: f32 = pair[1].iter()
+                .zip(&pair[0])
+                .map(|(&cur, &prev)| (compress(cur) - compress(prev)).max(0.0))
+                .sum();
+            let total: f32 = pair[1].iter().map(|&m| compress(m)).sum();
+            if rise >= MIN_RELATIVE_FLUX * total { rise } else { 0.0 }
+        })
+        .collect();
+
+    let half = ((ONSET_DETREND_SECS * rate / hop as f64) as usize / 2).max(1);
+    (0..flux.len())
+        .map(|i| {
+            let window = &flux[i.saturating_sub(half)..(i + half + 1).min(flux.len())];
+            let mean = window.iter().sum::<f32>() / window.len() as f32;
+            (flux[i] - mean).max(0.0)
+        })
+        .collect()
+}
+
+/// Unbiased autocorrelation for lags `0..lags`, normalized by lag zero.
+fn normalized_autocorrelation(values: &[f32], lags: usize) -> Vec<f32> {
+    let n = values.len();
+    let energy = values.iter().map(|v| v * v).sum::<f32>() / n.max(1) as f32;
+    (0..lags.min(n))
+        .map(|lag| {
+            if energy <= 0.0 {
+                return 0.0;
+            }
+            let sum: f32 = values.iter().zip(&values[lag..]).map(|(a, b)| a * b).sum();
+            sum / (n - lag) as f32 / energy
+        })
+        .collect()
+}
+
+/// Pearson correlation of two equally long series.
+fn pearson(a: &[f32], b: &[f32]) -> f32 {
+    let n = a.len() as f32;
+    let (mean_a, mean_b) = (a.iter().sum::<f32>() / n, b.iter().sum::<f32>() / n);
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a) * (x - mean_a);
+        var_b += (y - mean_b) * (y - mean_b);
+    }
+    if var_a <= 0.0 || var_b <= 0.0 {
+        return 0.0;
+    }
+    cov / (var_a * var_b).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 22050;
+
+    /// Frequency of a MIDI note.
+    fn note(midi: i32) -> f32 {
+        440.0 * 2f32.powf((midi - 69) as f32 / 12.0)
+    }
+
+    /// Chords of MIDI notes, one per second, each note with a few harmonics.
+    fn progression(chords: &[&[i32]]) -> AudioData {
+        let samples = chords.iter()
+            .flat_map(|chord| {
+                (0..SAMPLE_RATE as usize).map(move |i| {
+                    let t = i as f32 / SAMPLE_RATE as f32;
+                    chord.iter()
+                        .flat_map(|&midi| (1..=3).map(move |h| (note(midi) * h as f32, 0.2 / h as f32)))
+                        .map(|(f, amp)| amp * (2.0 * std::f32::consts::PI * f * t).sin())
+                        .sum::<f32>()
+                })
+            })
+            .collect();
+        AudioData::new(samples, SAMPLE_RATE)
+    }
+
+    /// Decaying 1 kHz clicks at `bpm`.
+    fn click_track(bpm: f32, secs: f32) -> Vec<f32> {
+        let period = 60.0 / bpm;
+        (0..(SAMPLE_RATE as f32 * secs) as usize)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                let since = t % period;
+                (-since * 60.0).exp() * (2.0 * std::f32::consts::PI * 1000.0 * t).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_key_of_minor_progression() {
+        // i - iv - V - i in A minor: Am, Dm, E, Am
+        let audio = progression(&[&[57, 60, 64], &[62, 65, 69], &[52, 56, 59], &[57, 60, 64]]);
+        let info = analyze(&audio).unwrap();
+        assert_eq!((info.key.as_str(), info.mode), ("A", KeyMode::Minor), "{:?}", info);
+        assert!(info.key_confidence > 0.6);
+        assert_eq!(info.key_name(), "A minor");
+    }
+
+    #[test]
+    fn test_key_of_major_progression() {
+        // I - IV - V - I in Eb major
+        let audio = progression(&[&[63, 67, 70], &[68, 72, 75], &[70, 74, 77], &[63, 67, 70]]);
+        let (tonic, mode, _) = detect_key(&audio.samples, SAMPLE_RATE).unwrap();
+        assert_eq!((PITCH_CLASSES[tonic], mode), ("D#", KeyMode::Major));
+    }
+
+    #[test]
+    fn test_tempo_of_click_tracks() {
+        for bpm in [90.0, 128.0, 174.0] {
+            let (estimate, confidence) = estimate_tempo(&click_track(bpm, 12.0), SAMPLE_RATE).unwrap();
+            assert!((estimate - bpm).abs() < 1.5, "{} BPM estimated as {}", bpm, estimate);
+            assert!(confidence > 0.5, "{} BPM confidence {}", bpm, confidence);
+        }
+    }
+
+    #[test]
+    fn test_no_beat_has_low_confidence() {
+        // A held chord; chord changes would be beats
+        let held: Vec<f32> = (0..SAMPLE_RATE as usize * 6)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                [60, 64, 67].iter().map(|&m| 0.2 * (2.0 * std::f32::consts::PI * note(m) * t).sin()).sum()
+            })
+            .collect();
+        let (_, confidence) = estimate_tempo(&held, SAMPLE_RATE).unwrap();
+        assert!(confidence < 0.3, "confidence {}", confidence);
+
+        assert_eq!(estimate_tempo(&[0.1; 100], SAMPLE_RATE).unwrap(), (FALLBACK_BPM, 0.0));
+        assert!(matches!(analyze(&AudioData::new(vec![0.1; 100], SAMPLE_RATE)), Err(FrequencyError::TooShort { .. })));
+    }
+}
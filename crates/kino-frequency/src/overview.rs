@@ -0,0 +1,66 @@
+//! Seek-bar overviews for the player UI.
+//!
+//! [`generate_overview`] reduces a recording to a waveform (peak and RMS
+//! amplitude) and a coarse heatmap of log-spaced frequency bands over a
+//! fixed number of time buckets, for drawing under the seek bar. The
+//! computation lives in [`kino_spectrum::overview`] and is shared with
+//! kino-wasm's `KinoOverviewGenerator`, so an overview computed in the
+//! browser has the same layout as `overview.json` from `kino process`.
+//!
+//! Buckets are split by sample count, so the same audio always produces
+//! the same overview, and the bucket count is capped by
+//! [`OverviewConfig::max_buckets`] however long the recording is.
+
+pub use kino_spectrum::overview::{Overview, OverviewConfig, MAX_FRAMES_PER_BUCKET};
+
+use crate::types::*;
+
+/// Overview of `audio` in `buckets` time buckets with the default 32 bands.
+///
+/// Multichannel audio is downmixed; NaN samples are treated as silence and
+/// infinities as full scale. Silence gives a flat overview.
+pub fn generate_overview(audio: &AudioData, buckets: usize) -> FrequencyResult<Overview> {
+    generate_overview_with_config(audio, buckets, &OverviewConfig::default())
+}
+
+/// Overview of `audio` with custom bands, frame size and bucket cap.
+pub fn generate_overview_with_config(
+    audio: &AudioData,
+    buckets: usize,
+    config: &OverviewConfig,
+) -> FrequencyResult<Overview> {
+    let mono = audio.to_mono();
+    if mono.samples.is_empty() || mono.sample_rate == 0 {
+        return Err(FrequencyError::TooShort { samples: mono.samples.len(), required: 1 });
+    }
+    let mono = mono.sanitize(SamplePolicy::Replace)?;
+
+    Ok(kino_spectrum::overview(&mono.samples, mono.sample_rate, buckets, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_stereo_overview_is_downmixed() {
+        let mono = testing::sine(440.0, 22050, 2.0);
+        let stereo: Vec<f32> = mono.samples.iter().flat_map(|&s| [s, s]).collect();
+        let stereo = AudioData::with_channels(stereo, 22050, 2);
+
+        let overview = generate_overview(&stereo, 50).unwrap();
+        assert_eq!(overview, generate_overview(&mono, 50).unwrap());
+        assert_eq!(overview.buckets(), 50);
+        assert_eq!(overview.duration_secs, 2.0);
+
+        let capped = OverviewConfig { max_buckets: 20, bands: 8, ..Default::default() };
+        let overview = generate_overview_with_config(&mono, 1000, &capped).unwrap();
+        assert_eq!((overview.buckets(), overview.bands()), (20, 8));
+
+        assert!(matches!(
+            generate_overview(&AudioData::new(Vec::new(), 22050), 10),
+            Err(FrequencyError::TooShort { .. })
+        ));
+    }
+}
@@ -4,50 +4,145 @@
 //! frequency signatures of audio content. It supports:
 //!
 //! - **Similar content**: Find content with similar audio characteristics
-//! - **User preferences**: Learn user taste from watch history
+//! - **User preferences**: Learn user taste from watch history, weighted by
+//!   recency and completion, with disliked items as negative signals
 //! - **Hybrid scoring**: Combine multiple similarity metrics
-
-use std::collections::HashMap;
-use anyhow::Result;
-use tracing::info;
+//! - **Metadata filtering**: Restrict results by creator, tags or duration
+//! - **Cold start**: Recommend content by tag overlap before its audio is analyzed
+//! - **Approximate search**: IVF index for large catalogs, with exact rescoring
+//! - **Concurrent use**: Queries run on a catalog snapshot while content is added
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
 
 use crate::fft::FrequencyAnalyzer;
 use crate::types::*;
 
+mod ivf;
+
+pub use crate::types::ContentMetadata;
+pub use ivf::IvfIndex;
+
+/// Number of signature features reported in [`SimilarityBreakdown::top_features`].
+const TOP_FEATURES: usize = 3;
+
+/// How the engine handles signatures whose feature count differs from
+/// [`RecommendConfig::signature_size`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DimensionPolicy {
+    /// Refuse the signature with a [`SignatureError::DimensionMismatch`].
+    #[default]
+    Reject,
+    /// Re-bin the signature to `signature_size` features.
+    Rebin,
+}
+
 /// Configuration for the recommendation engine.
 #[derive(Debug, Clone)]
 pub struct RecommendConfig {
     /// Number of features in frequency signature
     pub signature_size: usize,
+    /// What to do with signatures of a different size
+    pub dimension_policy: DimensionPolicy,
     /// Weight for frequency signature similarity
     pub signature_weight: f32,
     /// Weight for band energy similarity
     pub band_weight: f32,
     /// Weight for spectral features similarity
     pub spectral_weight: f32,
+    /// Weight for spectral bandwidth similarity
+    pub bandwidth_weight: f32,
+    /// Weight for per-octave spectral contrast similarity
+    pub contrast_weight: f32,
+    /// Weight for crest factor similarity
+    pub crest_weight: f32,
     /// Minimum similarity threshold for recommendations
     pub min_similarity: f32,
+    /// Use the approximate nearest neighbor index for large catalogs
+    pub ann_enabled: bool,
+    /// Catalog size at which the ANN index is built (smaller catalogs are scanned)
+    pub ann_min_items: usize,
+    /// Number of IVF lists (0 picks sqrt of the catalog size)
+    pub ann_num_lists: usize,
+    /// Number of IVF lists scanned per query
+    pub ann_probe_count: usize,
+    /// Number of candidates shortlisted by the ANN index for exact rescoring
+    pub ann_num_neighbors: usize,
+    /// How NaN and infinite samples in indexed and query audio are handled
+    pub sample_policy: SamplePolicy,
+    /// Seconds after which a watch event counts half as much (0 disables decay)
+    pub history_half_life: f64,
+    /// Watch events below this completion ratio are ignored
+    pub min_completion: f32,
+    /// How strongly disliked items push the taste profile away
+    pub dislike_weight: f32,
+    /// Share of the score given to tag similarity when both items have a
+    /// signature and tags (0-1); items missing either use the other alone
+    pub tag_weight: f32,
+    /// Attach a [`SimilarityBreakdown`] to each recommendation
+    pub explain: bool,
 }
 
 impl Default for RecommendConfig {
     fn default() -> Self {
         Self {
             signature_size: 128,
+            dimension_policy: DimensionPolicy::Reject,
             signature_weight: 0.5,
             band_weight: 0.3,
             spectral_weight: 0.2,
+            bandwidth_weight: 0.0,
+            contrast_weight: 0.0,
+            crest_weight: 0.0,
             min_similarity: 0.3,
+            ann_enabled: true,
+            ann_min_items: 5_000,
+            ann_num_lists: 0,
+            ann_probe_count: 8,
+            ann_num_neighbors: 256,
+            sample_policy: SamplePolicy::default(),
+            history_half_life: 30.0 * 24.0 * 3600.0,
+            min_completion: 0.1,
+            dislike_weight: 0.5,
+            tag_weight: 0.3,
+            explain: false,
         }
     }
 }
 
 /// Content-based recommendation engine.
+///
+/// Every method takes `&self` and the engine is `Send + Sync`, so a service
+/// can share one engine in an `Arc` without an outer lock. Queries work on a
+/// snapshot of the catalog: audio is analyzed before any lock is taken, and
+/// an update only briefly blocks new queries from taking their snapshot.
+/// [`add_many`](Self::add_many) builds the updated catalog off to the side
+/// and swaps it in at once.
 pub struct RecommendationEngine {
     config: RecommendConfig,
-    /// Content signatures indexed by content ID
-    content_index: HashMap<String, ContentEntry>,
     /// Analyzer for computing signatures
     analyzer: FrequencyAnalyzer,
+    /// Current catalog; queries clone the `Arc` and release the lock
+    catalog: RwLock<Arc<Catalog>>,
+    /// Serializes updates so concurrent writers don't lose each other's changes
+    writer: Mutex<()>,
+}
+
+/// The indexed content and everything derived from it.
+#[derive(Debug, Clone, Default)]
+struct Catalog {
+    /// Content signatures indexed by content ID
+    content_index: HashMap<String, ContentEntry>,
+    /// Approximate nearest neighbor index, present once the catalog is large enough
+    ann: Option<IvfIndex>,
+    /// Number of tagged items carrying each tag, for IDF weighting
+    tag_counts: HashMap<String, usize>,
+    /// Number of items with at least one tag
+    tagged_items: usize,
 }
 
 impl RecommendationEngine {
@@ -60,62 +155,232 @@ impl RecommendationEngine {
     pub fn with_config(config: RecommendConfig) -> Self {
         Self {
             config,
-            content_index: HashMap::new(),
             analyzer: FrequencyAnalyzer::new(4096, 2048),
+            catalog: RwLock::new(Arc::new(Catalog::default())),
+            writer: Mutex::new(()),
         }
     }
 
+    /// Snapshot of the current catalog.
+    fn catalog(&self) -> Arc<Catalog> {
+        self.catalog.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Apply a change to the catalog.
+    ///
+    /// The catalog is changed in place while no query holds a snapshot of
+    /// it, and otherwise changed as a copy that is then swapped in.
+    fn update<R>(&self, change: impl FnOnce(&mut Catalog) -> R) -> R {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        {
+            let mut current = self.catalog.write().unwrap_or_else(PoisonError::into_inner);
+            if let Some(catalog) = Arc::get_mut(&mut current) {
+                return change(catalog);
+            }
+        }
+        self.swap_in(change)
+    }
+
+    /// Apply a change to a copy of the catalog and swap it in, so queries
+    /// see either none or all of it.
+    fn rebuild<R>(&self, change: impl FnOnce(&mut Catalog) -> R) -> R {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        self.swap_in(change)
+    }
+
+    /// Copy, change and swap in the catalog; the caller holds the writer lock.
+    fn swap_in<R>(&self, change: impl FnOnce(&mut Catalog) -> R) -> R {
+        let mut next = Catalog::clone(&self.catalog());
+        let result = change(&mut next);
+        *self.catalog.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(next);
+        result
+    }
+
     /// Add content to the recommendation index.
+    ///
+    /// The audio is analyzed before the catalog is touched, so queries carry
+    /// on meanwhile. Fails with [`FrequencyError::TooShort`] or
+    /// [`FrequencyError::ZeroSignal`] for audio that has no usable signature.
     pub fn add_content(
-        &mut self,
+        &self,
         content_id: &str,
         audio: &AudioData,
         metadata: Option<ContentMetadata>,
-    ) -> Result<()> {
-        let signature = self.analyzer.compute_signature(&audio.samples, audio.sample_rate)?;
+    ) -> FrequencyResult<()> {
+        let signature = self.compute_signature(audio)?;
 
         info!("Indexed content: {} (signature size: {})", content_id, signature.features.len());
 
-        self.content_index.insert(content_id.to_string(), ContentEntry {
-            content_id: content_id.to_string(),
-            signature,
-            _metadata: metadata,
-        });
+        Ok(self.add_content_with_signature(content_id, signature, metadata)?)
+    }
 
-        Ok(())
+    /// Validate and downmix audio, then compute its signature.
+    ///
+    /// Analysis doesn't touch the catalog: compute signatures up front and
+    /// index them with [`add_content_with_signature`](Self::add_content_with_signature)
+    /// or [`add_many`](Self::add_many).
+    pub fn compute_signature(&self, audio: &AudioData) -> FrequencyResult<FrequencySignature> {
+        let audio = audio.prepare(self.config.sample_policy, self.analyzer.fft_size())?;
+        let audio = audio.to_mono();
+        self.analyzer.compute_signature(&audio.samples, audio.sample_rate)
     }
 
     /// Add content with a pre-computed signature.
+    ///
+    /// Fails if the signature doesn't match the configured signature size and
+    /// the [`DimensionPolicy`] is `Reject`.
     pub fn add_content_with_signature(
-        &mut self,
+        &self,
         content_id: &str,
         signature: FrequencySignature,
         metadata: Option<ContentMetadata>,
-    ) {
-        self.content_index.insert(content_id.to_string(), ContentEntry {
-            content_id: content_id.to_string(),
-            signature,
-            _metadata: metadata,
+    ) -> Result<()> {
+        let signature = self.conform_signature(signature)
+            .with_context(|| format!("Cannot index content {}", content_id))?;
+        self.update(|catalog| {
+            catalog.insert_entry(ContentEntry {
+                content_id: content_id.to_string(),
+                signature: Some(signature),
+                metadata,
+            });
+            catalog.update_ann(&self.config);
+        });
+        Ok(())
+    }
+
+    /// Index many items with pre-computed signatures at once.
+    ///
+    /// Accepts [`IndexEntry`] values as well as bare `(id, signature)` pairs.
+    /// Every signature is validated first, so a rejected entry leaves the
+    /// index unchanged. The updated catalog, with its ANN index updated once,
+    /// is built off to the side and swapped in, so queries see either none
+    /// or all of the items.
+    pub fn add_many<I, E>(&self, items: I) -> Result<()>
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<IndexEntry>,
+    {
+        let entries = self.conform_entries(items.into_iter().map(Into::into))?;
+        info!("Indexing {} items", entries.len());
+
+        self.rebuild(|catalog| {
+            for IndexEntry { content_id, signature, metadata } in entries {
+                catalog.insert_entry(ContentEntry {
+                    content_id,
+                    signature,
+                    metadata,
+                });
+            }
+            catalog.update_ann(&self.config);
         });
+        Ok(())
+    }
+
+    /// Index content by its metadata alone, before its audio is analyzed.
+    ///
+    /// The item is recommended through tag overlap until a signature is
+    /// added. An existing signature for `content_id` is kept.
+    pub fn add_content_metadata(&self, content_id: &str, metadata: ContentMetadata) {
+        self.update(|catalog| {
+            let signature = catalog.content_index.get(content_id)
+                .and_then(|entry| entry.signature.clone());
+
+            info!("Indexed content: {} (metadata only: {})", content_id, signature.is_none());
+
+            catalog.insert_entry(ContentEntry {
+                content_id: content_id.to_string(),
+                signature,
+                metadata: Some(metadata),
+            });
+            catalog.update_ann(&self.config);
+        });
+    }
+
+    /// Bring a signature to the configured size, according to the policy.
+    ///
+    /// Signatures from a newer layout version are always rejected, since
+    /// there is no way to tell what their features mean.
+    fn conform_signature(&self, signature: FrequencySignature) -> Result<FrequencySignature, SignatureError> {
+        if signature.version > SIGNATURE_VERSION {
+            return Err(SignatureError::VersionMismatch {
+                left: SIGNATURE_VERSION,
+                right: signature.version,
+            });
+        }
+
+        let expected = self.config.signature_size;
+        if signature.dimensions() == expected {
+            return Ok(signature);
+        }
+        match self.config.dimension_policy {
+            DimensionPolicy::Reject => Err(SignatureError::DimensionMismatch {
+                left: expected,
+                right: signature.dimensions(),
+            }),
+            DimensionPolicy::Rebin => {
+                debug!("Re-binning signature from {} to {} features", signature.dimensions(), expected);
+                Ok(signature.rebin(expected))
+            }
+        }
     }
 
     /// Remove content from the index.
-    pub fn remove_content(&mut self, content_id: &str) -> bool {
-        self.content_index.remove(content_id).is_some()
+    pub fn remove_content(&self, content_id: &str) -> bool {
+        self.update(|catalog| {
+            if let Some(ann) = &mut catalog.ann {
+                ann.remove(content_id);
+            }
+            let removed = catalog.content_index.remove(content_id);
+            if let Some(entry) = &removed {
+                catalog.count_tags(entry, false);
+            }
+            catalog.update_ann(&self.config);
+            removed.is_some()
+        })
+    }
+
+    /// Retrain the ANN index from scratch over the current catalog.
+    ///
+    /// The index is otherwise maintained incrementally and retrained
+    /// automatically whenever the catalog doubles in size. Queries keep
+    /// using the previous index until training finishes.
+    pub fn rebuild_ann(&self) {
+        self.rebuild(|catalog| {
+            catalog.ann = None;
+            catalog.update_ann(&self.config);
+        });
+    }
+
+    /// A copy of the ANN index, if the catalog is large enough to use one.
+    pub fn ann_index(&self) -> Option<IvfIndex> {
+        self.catalog().ann.clone()
+    }
+
+    /// Get metadata for an indexed content item.
+    pub fn metadata(&self, content_id: &str) -> Option<ContentMetadata> {
+        self.catalog().content_index.get(content_id)?.metadata.clone()
     }
 
     /// Get recommendations for a specific content item.
+    ///
+    /// Items are compared by signature and by tag overlap, whichever both
+    /// sides have, so content indexed with metadata only is still matched.
     pub fn get_similar(
         &self,
         content_id: &str,
         limit: usize,
+        filter: &RecommendationFilter,
     ) -> Vec<Recommendation> {
-        let target = match self.content_index.get(content_id) {
-            Some(entry) => &entry.signature,
+        let catalog = self.catalog();
+        let target = match catalog.content_index.get(content_id) {
+            Some(entry) => Query {
+                signature: entry.signature.as_ref(),
+                tags: entry.tags(),
+            },
             None => return Vec::new(),
         };
 
-        self.find_similar_to_signature(target, Some(content_id), limit)
+        self.find_similar(&catalog, &target, &[content_id], filter, limit)
     }
 
     /// Get recommendations based on audio data.
@@ -123,41 +388,102 @@ impl RecommendationEngine {
         &self,
         audio: &AudioData,
         limit: usize,
-    ) -> Result<Vec<Recommendation>> {
-        let signature = self.analyzer.compute_signature(&audio.samples, audio.sample_rate)?;
-        Ok(self.find_similar_to_signature(&signature, None, limit))
+        filter: &RecommendationFilter,
+    ) -> FrequencyResult<Vec<Recommendation>> {
+        let signature = self.compute_signature(audio)?;
+        let signature = self.conform_signature(signature).map_err(anyhow::Error::from)?;
+        Ok(self.find_similar(&self.catalog(), &Query::signature(&signature), &[], filter, limit))
     }
 
     /// Get personalized recommendations based on user watch history.
+    ///
+    /// Every ID counts as one fully watched item; see
+    /// [`get_user_recommendations_weighted`](Self::get_user_recommendations_weighted)
+    /// for recency and completion weighting.
     pub fn get_user_recommendations(
         &self,
         watch_history: &[String],
         limit: usize,
+        filter: &RecommendationFilter,
     ) -> Vec<Recommendation> {
-        if watch_history.is_empty() {
-            return Vec::new();
-        }
+        self.recommend_for_history(&self.catalog(), &full_views(watch_history), &[], limit, filter)
+    }
 
-        // Compute average signature from watch history
-        let history_signatures: Vec<&FrequencySignature> = watch_history.iter()
-            .filter_map(|id| self.content_index.get(id))
-            .map(|entry| &entry.signature)
+    /// Get personalized recommendations from timestamped watch events.
+    ///
+    /// Each event is weighted by its completion ratio and by exponential decay
+    /// with [`RecommendConfig::history_half_life`], measured from the most recent
+    /// event. Events below [`RecommendConfig::min_completion`] are ignored.
+    /// Disliked items move the taste profile away from their average signature
+    /// by [`RecommendConfig::dislike_weight`]. Watched and disliked items are
+    /// never recommended.
+    pub fn get_user_recommendations_weighted(
+        &self,
+        watch_history: &[WatchEvent],
+        disliked: &[String],
+        limit: usize,
+        filter: &RecommendationFilter,
+    ) -> Vec<Recommendation> {
+        self.recommend_for_history(&self.catalog(), watch_history, disliked, limit, filter)
+    }
+
+    /// Recommendations from watch events against one catalog snapshot.
+    fn recommend_for_history(
+        &self,
+        catalog: &Catalog,
+        watch_history: &[WatchEvent],
+        disliked: &[String],
+        limit: usize,
+        filter: &RecommendationFilter,
+    ) -> Vec<Recommendation> {
+        let latest = watch_history.iter()
+            .map(|event| event.timestamp)
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        // Compute weighted average signature from watch history
+        let history_signatures: Vec<(&FrequencySignature, f32)> = watch_history.iter()
+            .filter(|event| event.completion_ratio >= self.config.min_completion)
+            .filter_map(|event| {
+                let signature = catalog.content_index.get(&event.content_id)?.signature.as_ref()?;
+                Some((signature, self.history_weight(event, latest)))
+            })
+            .filter(|(_, weight)| *weight > 0.0)
             .collect();
 
         if history_signatures.is_empty() {
             return Vec::new();
         }
 
-        let avg_signature = self.average_signatures(&history_signatures);
+        let mut profile = self.weighted_average_signatures(&history_signatures);
+
+        let disliked_signatures: Vec<(&FrequencySignature, f32)> = disliked.iter()
+            .filter_map(|id| catalog.content_index.get(id)?.signature.as_ref())
+            .map(|signature| (signature, 1.0))
+            .collect();
+
+        if !disliked_signatures.is_empty() && self.config.dislike_weight > 0.0 {
+            let avoid = self.weighted_average_signatures(&disliked_signatures);
+            push_away(&mut profile, &avoid, self.config.dislike_weight);
+        }
 
         // Find similar content not in history
-        let mut recommendations = self.find_similar_to_signature(&avg_signature, None, limit * 2);
+        let excluded: Vec<&str> = watch_history.iter()
+            .map(|event| event.content_id.as_str())
+            .chain(disliked.iter().map(String::as_str))
+            .collect();
+        self.find_similar(catalog, &Query::signature(&profile), &excluded, filter, limit)
+    }
 
-        // Filter out already watched
-        recommendations.retain(|r| !watch_history.contains(&r.content_id));
-        recommendations.truncate(limit);
+    /// Weight of a watch event relative to the most recent one.
+    fn history_weight(&self, event: &WatchEvent, latest: f64) -> f32 {
+        let completion = event.completion_ratio.clamp(0.0, 1.0);
+        let half_life = self.config.history_half_life;
+        if half_life <= 0.0 {
+            return completion;
+        }
 
-        recommendations
+        let age = (latest - event.timestamp).max(0.0);
+        completion * 0.5f64.powf(age / half_life) as f32
     }
 
     /// Get diverse recommendations (explore vs exploit).
@@ -166,15 +492,17 @@ impl RecommendationEngine {
         watch_history: &[String],
         explore_ratio: f32,
         limit: usize,
+        filter: &RecommendationFilter,
     ) -> Vec<Recommendation> {
         let exploit_count = ((1.0 - explore_ratio) * limit as f32) as usize;
         let explore_count = limit - exploit_count;
 
         // Exploit: similar to history
-        let mut exploit_recs = self.get_user_recommendations(watch_history, exploit_count);
+        let catalog = self.catalog();
+        let mut exploit_recs = self.recommend_for_history(&catalog, &full_views(watch_history), &[], exploit_count, filter);
 
         // Explore: random diverse content
-        let mut explore_recs = self.get_diverse_content(watch_history, explore_count);
+        let mut explore_recs = self.get_diverse_content(&catalog, watch_history, explore_count, filter);
 
         // Interleave results
         let mut results = Vec::with_capacity(limit);
@@ -201,18 +529,35 @@ impl RecommendationEngine {
         results
     }
 
-    /// Find content similar to a signature.
-    fn find_similar_to_signature(
+    /// Find content similar to a signature and/or a set of tags.
+    fn find_similar(
         &self,
-        target: &FrequencySignature,
-        exclude_id: Option<&str>,
+        catalog: &Catalog,
+        target: &Query<'_>,
+        exclude_ids: &[&str],
+        filter: &RecommendationFilter,
         limit: usize,
     ) -> Vec<Recommendation> {
-        let mut similarities: Vec<(String, f32, Vec<String>)> = self.content_index.iter()
-            .filter(|(id, _)| exclude_id.map_or(true, |ex| *id != ex))
-            .map(|(id, entry)| {
-                let (similarity, features) = self.compute_similarity(target, &entry.signature);
-                (id.clone(), similarity, features)
+        let candidates = catalog.content_index.values()
+            .filter(|entry| !exclude_ids.contains(&entry.content_id.as_str()))
+            .filter(|entry| filter.matches(&entry.content_id, entry.metadata.as_ref()));
+
+        let candidates: Vec<&ContentEntry> = match (&catalog.ann, target.signature) {
+            // The ANN index only holds signatures, so metadata-only items
+            // are always scanned
+            (Some(ann), Some(signature)) => {
+                let mut shortlist = self.ann_shortlist(catalog, ann, signature, exclude_ids, filter);
+                shortlist.extend(candidates.filter(|entry| entry.signature.is_none()));
+                shortlist
+            }
+            _ => candidates.collect(),
+        };
+
+        // Exact rescoring with the full weighted similarity
+        let mut similarities: Vec<(&ContentEntry, f32, SimilarityBreakdown)> = candidates.into_iter()
+            .filter_map(|entry| {
+                let breakdown = self.score(catalog, target, entry)?;
+                Some((entry, breakdown.total(), breakdown))
             })
             .filter(|(_, sim, _)| *sim >= self.config.min_similarity)
             .collect();
@@ -221,54 +566,129 @@ impl RecommendationEngine {
 
         similarities.into_iter()
             .take(limit)
-            .map(|(content_id, similarity, matching_features)| Recommendation {
-                content_id,
+            .map(|(entry, similarity, breakdown)| Recommendation {
+                content_id: entry.content_id.clone(),
                 similarity,
-                matching_features,
+                matching_features: breakdown.matching_features(),
+                metadata: entry.metadata.clone(),
+                breakdown: self.config.explain.then_some(breakdown),
             })
             .collect()
     }
 
-    /// Compute similarity between two signatures.
-    fn compute_similarity(
+    /// Shortlist the closest candidates by feature cosine similarity using the ANN index.
+    fn ann_shortlist<'a>(
         &self,
-        sig1: &FrequencySignature,
-        sig2: &FrequencySignature,
-    ) -> (f32, Vec<String>) {
-        let mut matching_features = Vec::new();
+        catalog: &'a Catalog,
+        ann: &IvfIndex,
+        target: &FrequencySignature,
+        exclude_ids: &[&str],
+        filter: &RecommendationFilter,
+    ) -> Vec<&'a ContentEntry> {
+        let query = ivf::normalize(&target.features);
+
+        let mut scored: Vec<(&ContentEntry, f32)> = ann.probe(&query, self.config.ann_probe_count)
+            .filter(|id| !exclude_ids.contains(id))
+            .filter_map(|id| catalog.content_index.get(id))
+            .filter(|entry| filter.matches(&entry.content_id, entry.metadata.as_ref()))
+            .filter_map(|entry| {
+                let features = &entry.signature.as_ref()?.features;
+                Some((entry, ivf::dot(&query, &ivf::normalize(features))))
+            })
+            .collect();
 
-        // Feature vector cosine similarity
-        let feature_sim = sig1.similarity(sig2);
-        if feature_sim > 0.7 {
-            matching_features.push("frequency_pattern".to_string());
+        let keep = self.config.ann_num_neighbors.max(1);
+        if scored.len() > keep {
+            scored.select_nth_unstable_by(keep - 1, |a, b| {
+                b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal)
+            });
+            scored.truncate(keep);
         }
 
-        // Band energy similarity
-        let band_sim = self.band_similarity(&sig1.band_energies, &sig2.band_energies);
-        if band_sim > 0.8 {
-            matching_features.push("energy_distribution".to_string());
+        scored.into_iter().map(|(entry, _)| entry).collect()
+    }
+
+    /// Score a candidate against the query, mixing signature and tag similarity.
+    ///
+    /// Returns `None` when the two share neither a signature nor tags to compare.
+    fn score(&self, catalog: &Catalog, target: &Query<'_>, entry: &ContentEntry) -> Option<SimilarityBreakdown> {
+        let audio = match (target.signature, &entry.signature) {
+            (Some(a), Some(b)) => Some(self.compute_similarity(a, b)),
+            _ => None,
+        };
+        let tags = (!target.tags.is_empty() && !entry.tags().is_empty())
+            .then(|| catalog.tag_similarity(target.tags, entry.tags()));
+
+        match (audio, tags) {
+            (Some(mut breakdown), Some(tag_sim)) => {
+                let tag_weight = self.config.tag_weight.clamp(0.0, 1.0);
+                let w = &mut breakdown.weights;
+                for weight in [&mut w.signature, &mut w.band_energy, &mut w.spectral, &mut w.bandwidth, &mut w.contrast, &mut w.crest] {
+                    *weight *= 1.0 - tag_weight;
+                }
+                w.tags = tag_weight;
+                breakdown.tags = Some(tag_sim);
+                Some(breakdown)
+            }
+            (Some(breakdown), None) => Some(breakdown),
+            (None, Some(tag_sim)) => Some(SimilarityBreakdown {
+                tags: Some(tag_sim),
+                weights: SimilarityWeights { tags: 1.0, ..Default::default() },
+                ..Default::default()
+            }),
+            (None, None) => None,
         }
+    }
+
+    /// Compare two signatures component by component.
+    ///
+    /// The strongest contributing features are only collected when
+    /// explanations are enabled.
+    fn compute_similarity(
+        &self,
+        sig1: &FrequencySignature,
+        sig2: &FrequencySignature,
+    ) -> SimilarityBreakdown {
+        let config = &self.config;
 
         // Spectral feature similarity
         let centroid_diff = (sig1.centroid - sig2.centroid).abs() / sig1.centroid.max(sig2.centroid).max(1.0);
         let flatness_diff = (sig1.flatness - sig2.flatness).abs();
 
-        let spectral_sim = 1.0 - (centroid_diff * 0.5 + flatness_diff * 0.5);
-        if spectral_sim > 0.8 {
-            matching_features.push("tonal_quality".to_string());
+        SimilarityBreakdown {
+            signature: Some(sig1.similarity(sig2)),
+            band_energy: Some(self.band_similarity(&sig1.band_energies, &sig2.band_energies)),
+            spectral: Some(1.0 - (centroid_diff * 0.5 + flatness_diff * 0.5)),
+            // Optional timbre components, off unless weighted
+            bandwidth: (config.bandwidth_weight > 0.0)
+                .then(|| relative_similarity(sig1.bandwidth, sig2.bandwidth)),
+            contrast: (config.contrast_weight > 0.0)
+                .then(|| contrast_similarity(&sig1.contrast, &sig2.contrast))
+                .flatten(),
+            crest: (config.crest_weight > 0.0)
+                .then(|| relative_similarity(sig1.crest_factor, sig2.crest_factor)),
+            tags: None,
+            weights: SimilarityWeights {
+                signature: config.signature_weight,
+                band_energy: config.band_weight,
+                spectral: config.spectral_weight,
+                bandwidth: config.bandwidth_weight,
+                contrast: config.contrast_weight,
+                crest: config.crest_weight,
+                tags: 0.0,
+            },
+            top_features: if config.explain { top_features(sig1, sig2) } else { Vec::new() },
         }
-
-        // Weighted combination
-        let total_similarity =
-            feature_sim * self.config.signature_weight +
-            band_sim * self.config.band_weight +
-            spectral_sim * self.config.spectral_weight;
-
-        (total_similarity, matching_features)
     }
 
     /// Compute band energy similarity.
+    ///
+    /// Energies computed over different band plans aren't comparable and
+    /// score 0.
     fn band_similarity(&self, band1: &BandEnergies, band2: &BandEnergies) -> f32 {
+        if band1.plan != band2.plan {
+            return 0.0;
+        }
         let v1 = band1.to_vec();
         let v2 = band2.to_vec();
 
@@ -284,71 +704,90 @@ impl RecommendationEngine {
         }
     }
 
-    /// Compute average of multiple signatures.
-    fn average_signatures(&self, signatures: &[&FrequencySignature]) -> FrequencySignature {
-        if signatures.is_empty() {
+    /// Compute the weighted average of multiple signatures.
+    fn weighted_average_signatures(&self, signatures: &[(&FrequencySignature, f32)]) -> FrequencySignature {
+        let total: f32 = signatures.iter().map(|(_, w)| w).sum();
+        if signatures.is_empty() || total <= 0.0 {
             return FrequencySignature {
+                version: SIGNATURE_VERSION,
                 features: vec![0.0; self.config.signature_size],
-                band_energies: BandEnergies {
-                    sub_bass: 0.0,
-                    bass: 0.0,
-                    low_mid: 0.0,
-                    mid: 0.0,
-                    high_mid: 0.0,
-                    high: 0.0,
-                },
+                band_energies: BandEnergies::default(),
                 centroid: 0.0,
                 flatness: 0.0,
+                bandwidth: 0.0,
+                contrast: Vec::new(),
+                crest_factor: 0.0,
             };
         }
 
-        let n = signatures.len() as f32;
-        let feature_len = signatures[0].features.len();
+        let feature_len = signatures[0].0.features.len();
+        let average = |value: fn(&FrequencySignature) -> f32| {
+            signatures.iter().map(|(s, w)| value(s) * w).sum::<f32>() / total
+        };
 
         // Average features
         let mut avg_features = vec![0.0f32; feature_len];
-        for sig in signatures {
+        for (sig, weight) in signatures {
             for (i, &f) in sig.features.iter().enumerate() {
                 if i < feature_len {
-                    avg_features[i] += f / n;
+                    avg_features[i] += f * weight / total;
                 }
             }
         }
 
         // Average band energies
-        let avg_band = BandEnergies {
-            sub_bass: signatures.iter().map(|s| s.band_energies.sub_bass).sum::<f32>() / n,
-            bass: signatures.iter().map(|s| s.band_energies.bass).sum::<f32>() / n,
-            low_mid: signatures.iter().map(|s| s.band_energies.low_mid).sum::<f32>() / n,
-            mid: signatures.iter().map(|s| s.band_energies.mid).sum::<f32>() / n,
-            high_mid: signatures.iter().map(|s| s.band_energies.high_mid).sum::<f32>() / n,
-            high: signatures.iter().map(|s| s.band_energies.high).sum::<f32>() / n,
-        };
+        let avg_band = BandEnergies::weighted_average(signatures.iter().map(|(s, w)| (&s.band_energies, *w)));
 
         // Average spectral features
-        let avg_centroid = signatures.iter().map(|s| s.centroid).sum::<f32>() / n;
-        let avg_flatness = signatures.iter().map(|s| s.flatness).sum::<f32>() / n;
+        let avg_centroid = average(|s| s.centroid);
+        let avg_flatness = average(|s| s.flatness);
+        let avg_bandwidth = average(|s| s.bandwidth);
+        let avg_crest = average(|s| s.crest_factor);
+
+        // Contrast is only averaged when every signature carries the same bands
+        let contrast_len = signatures[0].0.contrast.len();
+        let avg_contrast = if signatures.iter().all(|(s, _)| s.contrast.len() == contrast_len) {
+            (0..contrast_len)
+                .map(|i| signatures.iter().map(|(s, w)| s.contrast[i] * w).sum::<f32>() / total)
+                .collect()
+        } else {
+            Vec::new()
+        };
 
         FrequencySignature {
+            version: SIGNATURE_VERSION,
             features: avg_features,
             band_energies: avg_band,
             centroid: avg_centroid,
             flatness: avg_flatness,
+            bandwidth: avg_bandwidth,
+            contrast: avg_contrast,
+            crest_factor: avg_crest,
         }
     }
 
     /// Get diverse content for exploration.
-    fn get_diverse_content(&self, exclude: &[String], limit: usize) -> Vec<Recommendation> {
+    fn get_diverse_content(
+        &self,
+        catalog: &Catalog,
+        exclude: &[String],
+        limit: usize,
+        filter: &RecommendationFilter,
+    ) -> Vec<Recommendation> {
         // Simple diversity: pick content with different band energy profiles
         let mut clusters: HashMap<usize, Vec<&ContentEntry>> = HashMap::new();
 
-        for entry in self.content_index.values() {
-            if exclude.contains(&entry.content_id) {
+        for entry in catalog.content_index.values() {
+            if exclude.contains(&entry.content_id)
+                || !filter.matches(&entry.content_id, entry.metadata.as_ref())
+            {
                 continue;
             }
 
             // Classify by dominant band
-            let bands = entry.signature.band_energies.to_vec();
+            let bands = entry.signature.as_ref()
+                .map(|signature| signature.band_energies.to_vec())
+                .unwrap_or_default();
             let dominant_band = bands.iter()
                 .enumerate()
                 .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
@@ -372,6 +811,8 @@ impl RecommendationEngine {
                             content_id: entry.content_id.clone(),
                             similarity: 0.5, // Exploration score
                             matching_features: vec!["diverse".to_string()],
+                            metadata: entry.metadata.clone(),
+                            breakdown: None,
                         });
                         break;
                     }
@@ -379,7 +820,7 @@ impl RecommendationEngine {
             }
 
             // Prevent infinite loop
-            if seen.len() >= self.content_index.len() {
+            if seen.len() >= clusters.values().map(Vec::len).sum::<usize>() {
                 break;
             }
         }
@@ -390,31 +831,105 @@ impl RecommendationEngine {
 
     /// Get the number of indexed items.
     pub fn len(&self) -> usize {
-        self.content_index.len()
+        self.catalog().content_index.len()
+    }
+
+    /// Check whether `content_id` is indexed.
+    pub fn contains(&self, content_id: &str) -> bool {
+        self.catalog().content_index.contains_key(content_id)
     }
 
     /// Check if the index is empty.
     pub fn is_empty(&self) -> bool {
-        self.content_index.is_empty()
+        self.catalog().content_index.is_empty()
     }
 
-    /// Export the index for persistence.
-    pub fn export_index(&self) -> Vec<(String, FrequencySignature)> {
-        self.content_index.iter()
-            .map(|(id, entry)| (id.clone(), entry.signature.clone()))
+    /// Export the index, including metadata, for persistence.
+    pub fn export_index(&self) -> Vec<IndexEntry> {
+        self.catalog().content_index.values()
+            .map(|entry| IndexEntry {
+                content_id: entry.content_id.clone(),
+                signature: entry.signature.clone(),
+                metadata: entry.metadata.clone(),
+            })
             .collect()
     }
 
-    /// Import signatures from persistence.
-    pub fn import_index(&mut self, data: Vec<(String, FrequencySignature)>) {
-        for (id, signature) in data {
-            self.content_index.insert(id.clone(), ContentEntry {
-                content_id: id,
-                signature,
-                _metadata: None,
-            });
+    /// Import entries from persistence.
+    ///
+    /// Same as [`add_many`](Self::add_many).
+    pub fn import_index<I, E>(&self, data: I) -> Result<()>
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<IndexEntry>,
+    {
+        self.add_many(data)
+    }
+
+    /// Validate or re-bin a batch of imported entries.
+    fn conform_entries(&self, entries: impl Iterator<Item = IndexEntry>) -> Result<Vec<IndexEntry>> {
+        entries
+            .map(|entry| {
+                let signature = entry.signature
+                    .map(|signature| self.conform_signature(signature))
+                    .transpose()
+                    .with_context(|| format!("Cannot import content {}", entry.content_id))?;
+                Ok(IndexEntry { signature, ..entry })
+            })
+            .collect()
+    }
+
+    /// Export the entries together with the trained ANN index.
+    pub fn export_snapshot(&self) -> IndexSnapshot {
+        let catalog = self.catalog();
+        IndexSnapshot {
+            entries: catalog.content_index.values()
+                .map(|entry| IndexEntry {
+                    content_id: entry.content_id.clone(),
+                    signature: entry.signature.clone(),
+                    metadata: entry.metadata.clone(),
+                })
+                .collect(),
+            ann: catalog.ann.clone(),
         }
     }
+
+    /// Restore a snapshot without retraining the ANN index.
+    ///
+    /// The ANN index is retrained instead if it was built over signatures of
+    /// a different size.
+    pub fn import_snapshot(&self, snapshot: IndexSnapshot) -> Result<()> {
+        let ann = snapshot.ann.filter(|ann| ann.dimensions() == self.config.signature_size);
+        let Some(mut ann) = ann else {
+            return self.import_index(snapshot.entries);
+        };
+
+        let entries = self.conform_entries(snapshot.entries.into_iter())?;
+        ann.restore_assignments();
+        self.rebuild(|catalog| {
+            for IndexEntry { content_id, signature, metadata } in entries {
+                catalog.store_entry(ContentEntry {
+                    content_id,
+                    signature,
+                    metadata,
+                });
+            }
+
+            // Place items the snapshot's index doesn't know about, e.g. content
+            // added to this engine before the import
+            for entry in catalog.content_index.values() {
+                if let Some(signature) = &entry.signature {
+                    if !ann.contains(&entry.content_id) {
+                        ann.insert(&entry.content_id, &signature.features);
+                    }
+                }
+            }
+
+            catalog.ann = Some(ann);
+            catalog.update_ann(&self.config);
+        });
+        Ok(())
+    }
 }
 
 impl Default for RecommendationEngine {
@@ -423,25 +938,327 @@ impl Default for RecommendationEngine {
     }
 }
 
+impl Catalog {
+    /// Insert an entry, keeping the ANN index in sync.
+    fn insert_entry(&mut self, entry: ContentEntry) {
+        if let Some(ann) = &mut self.ann {
+            match &entry.signature {
+                Some(signature) => ann.insert(&entry.content_id, &signature.features),
+                None => {
+                    ann.remove(&entry.content_id);
+                }
+            }
+        }
+        self.store_entry(entry);
+    }
+
+    /// Insert an entry into the catalog, keeping the tag counts in sync.
+    fn store_entry(&mut self, entry: ContentEntry) {
+        self.count_tags(&entry, true);
+        if let Some(old) = self.content_index.insert(entry.content_id.clone(), entry) {
+            self.count_tags(&old, false);
+        }
+    }
+
+    /// Add or remove an entry's distinct tags from the document frequencies.
+    fn count_tags(&mut self, entry: &ContentEntry, add: bool) {
+        let tags: HashSet<&String> = entry.tags().iter().collect();
+        if tags.is_empty() {
+            return;
+        }
+
+        for tag in tags {
+            let count = self.tag_counts.entry(tag.clone()).or_default();
+            if add {
+                *count += 1;
+            } else {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    self.tag_counts.remove(tag);
+                }
+            }
+        }
+        if add {
+            self.tagged_items += 1;
+        } else {
+            self.tagged_items = self.tagged_items.saturating_sub(1);
+        }
+    }
+
+    /// Build, retrain or drop the ANN index to match the catalog size.
+    fn update_ann(&mut self, config: &RecommendConfig) {
+        let len = self.content_index.values()
+            .filter(|entry| entry.signature.is_some())
+            .count();
+        if !config.ann_enabled || len < config.ann_min_items {
+            self.ann = None;
+            return;
+        }
+
+        if self.ann.as_ref().is_some_and(|ann| len <= ann.trained_size() * 2) {
+            return;
+        }
+
+        let num_lists = match config.ann_num_lists {
+            0 => (len as f64).sqrt().round() as usize,
+            n => n,
+        };
+        let ann = IvfIndex::train(
+            self.content_index.values()
+                .filter_map(|entry| Some((entry.content_id.as_str(), entry.signature.as_ref()?.features.as_slice()))),
+            num_lists,
+        );
+        debug!("Trained ANN index: {} items in {} lists", len, ann.num_lists());
+        self.ann = Some(ann);
+    }
+
+    /// IDF-weighted Jaccard similarity of two tag sets.
+    ///
+    /// Rare tags count for more than tags most of the catalog carries.
+    fn tag_similarity(&self, tags1: &[String], tags2: &[String]) -> f32 {
+        let set1: HashSet<&String> = tags1.iter().collect();
+        let set2: HashSet<&String> = tags2.iter().collect();
+
+        let idf = |tag: &String| {
+            let count = self.tag_counts.get(tag).copied().unwrap_or(0).max(1);
+            (1.0 + self.tagged_items.max(1) as f32 / count as f32).ln()
+        };
+
+        let shared: f32 = set1.intersection(&set2).map(|t| idf(t)).sum();
+        let total: f32 = set1.union(&set2).map(|t| idf(t)).sum();
+
+        if total > 0.0 { shared / total } else { 0.0 }
+    }
+}
+
+/// Watch events for fully watched items, in no particular order.
+fn full_views(content_ids: &[String]) -> Vec<WatchEvent> {
+    content_ids.iter()
+        .map(|id| WatchEvent {
+            content_id: id.clone(),
+            timestamp: 0.0,
+            completion_ratio: 1.0,
+        })
+        .collect()
+}
+
+/// Similarity of two non-negative scalars relative to the larger one.
+fn relative_similarity(a: f32, b: f32) -> f32 {
+    1.0 - (a - b).abs() / a.max(b).max(1.0)
+}
+
+/// Signature features with the largest products of the normalized feature
+/// vectors, i.e. the biggest terms of their cosine similarity.
+///
+/// Empty for incompatible signatures, which score 0.
+fn top_features(sig1: &FrequencySignature, sig2: &FrequencySignature) -> Vec<FeatureContribution> {
+    let norm = |features: &[f32]| features.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(&sig1.features) * norm(&sig2.features);
+    if sig1.check_compatible(sig2).is_err() || norms == 0.0 {
+        return Vec::new();
+    }
+
+    let mut contributions: Vec<FeatureContribution> = sig1.features.iter()
+        .zip(&sig2.features)
+        .enumerate()
+        .map(|(index, (a, b))| FeatureContribution { index, contribution: a * b / norms })
+        .collect();
+    contributions.sort_by(|a, b| b.contribution.total_cmp(&a.contribution));
+    contributions.truncate(TOP_FEATURES);
+    contributions
+}
+
+/// Mean per-band contrast similarity, or `None` when either signature
+/// predates contrast features or the band layouts differ.
+fn contrast_similarity(c1: &[f32], c2: &[f32]) -> Option<f32> {
+    if c1.is_empty() || c1.len() != c2.len() {
+        return None;
+    }
+
+    let sum: f32 = c1.iter().zip(c2).map(|(&a, &b)| relative_similarity(a, b)).sum();
+    Some(sum / c1.len() as f32)
+}
+
 /// Internal content entry in the index.
 #[derive(Debug, Clone)]
 struct ContentEntry {
     content_id: String,
-    signature: FrequencySignature,
-    _metadata: Option<ContentMetadata>,
+    /// Absent for content indexed by metadata only
+    signature: Option<FrequencySignature>,
+    metadata: Option<ContentMetadata>,
 }
 
-/// Optional metadata for content items.
-#[derive(Debug, Clone)]
-pub struct ContentMetadata {
-    /// Content title
-    pub title: Option<String>,
-    /// Creator ID
-    pub creator_id: Option<String>,
-    /// Tags
-    pub tags: Vec<String>,
-    /// Duration in seconds
-    pub duration_secs: Option<f64>,
+impl ContentEntry {
+    /// The entry's tags, empty without metadata.
+    fn tags(&self) -> &[String] {
+        self.metadata.as_ref().map_or(&[], |m| m.tags.as_slice())
+    }
+}
+
+/// What recommendations are compared against.
+struct Query<'a> {
+    signature: Option<&'a FrequencySignature>,
+    tags: &'a [String],
+}
+
+impl<'a> Query<'a> {
+    fn signature(signature: &'a FrequencySignature) -> Self {
+        Self { signature: Some(signature), tags: &[] }
+    }
+}
+
+/// Persisted form of an indexed content item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Content ID
+    pub content_id: String,
+    /// Frequency signature, absent for content indexed by metadata only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<FrequencySignature>,
+    /// Content metadata, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ContentMetadata>,
+}
+
+/// Persisted recommendation index, including the trained ANN structure.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexSnapshot {
+    /// Indexed content
+    pub entries: Vec<IndexEntry>,
+    /// Trained ANN index, if the catalog was large enough to have one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ann: Option<IvfIndex>,
+}
+
+impl From<(String, FrequencySignature)> for IndexEntry {
+    fn from((content_id, signature): (String, FrequencySignature)) -> Self {
+        Self { content_id, signature: Some(signature), metadata: None }
+    }
+}
+
+/// Custom predicate applied to candidate items by [`RecommendationFilter`].
+pub type FilterPredicate = Arc<dyn Fn(&str, Option<&ContentMetadata>) -> bool + Send + Sync>;
+
+/// Restricts which indexed items may be recommended.
+///
+/// All configured conditions must hold. Items indexed without metadata never
+/// match a tag or duration condition, but are not affected by `exclude_creator`.
+#[derive(Clone, Default)]
+pub struct RecommendationFilter {
+    /// Drop items by this creator
+    pub exclude_creator: Option<String>,
+    /// Keep only items carrying at least one of these tags (ignored when empty)
+    pub require_any_tags: Vec<String>,
+    /// Minimum duration in seconds (inclusive)
+    pub min_duration_secs: Option<f64>,
+    /// Maximum duration in seconds (inclusive)
+    pub max_duration_secs: Option<f64>,
+    /// Custom predicate receiving the content ID and its metadata
+    pub predicate: Option<FilterPredicate>,
+}
+
+impl RecommendationFilter {
+    /// Create a filter that accepts every item.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Exclude items from the given creator.
+    pub fn exclude_creator(mut self, creator_id: impl Into<String>) -> Self {
+        self.exclude_creator = Some(creator_id.into());
+        self
+    }
+
+    /// Require at least one of the given tags.
+    pub fn require_any_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.require_any_tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Restrict items to a duration range in seconds.
+    pub fn duration_range(mut self, min_secs: Option<f64>, max_secs: Option<f64>) -> Self {
+        self.min_duration_secs = min_secs;
+        self.max_duration_secs = max_secs;
+        self
+    }
+
+    /// Add a custom predicate.
+    pub fn with_predicate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&str, Option<&ContentMetadata>) -> bool + Send + Sync + 'static,
+    {
+        self.predicate = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Check whether an item passes the filter.
+    pub fn matches(&self, content_id: &str, metadata: Option<&ContentMetadata>) -> bool {
+        if let (Some(creator), Some(meta)) = (&self.exclude_creator, metadata) {
+            if meta.creator_id.as_deref() == Some(creator.as_str()) {
+                return false;
+            }
+        }
+
+        if !self.require_any_tags.is_empty() {
+            let has_tag = metadata
+                .is_some_and(|m| m.tags.iter().any(|t| self.require_any_tags.contains(t)));
+            if !has_tag {
+                return false;
+            }
+        }
+
+        if self.min_duration_secs.is_some() || self.max_duration_secs.is_some() {
+            let Some(duration) = metadata.and_then(|m| m.duration_secs) else {
+                return false;
+            };
+            if self.min_duration_secs.is_some_and(|min| duration < min)
+                || self.max_duration_secs.is_some_and(|max| duration > max)
+            {
+                return false;
+            }
+        }
+
+        self.predicate.as_ref().is_none_or(|p| p(content_id, metadata))
+    }
+}
+
+impl fmt::Debug for RecommendationFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecommendationFilter")
+            .field("exclude_creator", &self.exclude_creator)
+            .field("require_any_tags", &self.require_any_tags)
+            .field("min_duration_secs", &self.min_duration_secs)
+            .field("max_duration_secs", &self.max_duration_secs)
+            .field("predicate", &self.predicate.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+/// Move `profile` away from `avoid` by `weight`, clamping every component at zero.
+fn push_away(profile: &mut FrequencySignature, avoid: &FrequencySignature, weight: f32) {
+    let away = |value: f32, other: f32| (value + weight * (value - other)).max(0.0);
+
+    for (f, &a) in profile.features.iter_mut().zip(&avoid.features) {
+        *f = away(*f, a);
+    }
+    if profile.band_energies.plan == avoid.band_energies.plan {
+        for (e, &a) in profile.band_energies.energies.iter_mut().zip(&avoid.band_energies.energies) {
+            *e = away(*e, a);
+        }
+    }
+    if profile.contrast.len() == avoid.contrast.len() {
+        for (c, &a) in profile.contrast.iter_mut().zip(&avoid.contrast) {
+            *c = away(*c, a);
+        }
+    }
+    profile.centroid = away(profile.centroid, avoid.centroid);
+    profile.flatness = away(profile.flatness, avoid.flatness);
+    profile.bandwidth = away(profile.bandwidth, avoid.bandwidth);
+    profile.crest_factor = away(profile.crest_factor, avoid.crest_factor);
 }
 
 #[cfg(test)]
@@ -463,7 +1280,7 @@ mod tests {
 
     #[test]
     fn test_add_and_retrieve() {
-        let mut engine = RecommendationEngine::new();
+        let engine = RecommendationEngine::new();
 
         let audio1 = generate_test_audio(440.0, 5.0);
         let audio2 = generate_test_audio(880.0, 5.0);
@@ -476,7 +1293,7 @@ mod tests {
 
     #[test]
     fn test_similar_content() {
-        let mut engine = RecommendationEngine::new();
+        let engine = RecommendationEngine::new();
 
         // Similar frequencies
         let audio1 = generate_test_audio(440.0, 5.0);
@@ -487,7 +1304,7 @@ mod tests {
         engine.add_content("similar_2", &audio2, None).unwrap();
         engine.add_content("different", &audio3, None).unwrap();
 
-        let recommendations = engine.get_similar("similar_1", 2);
+        let recommendations = engine.get_similar("similar_1", 2, &RecommendationFilter::default());
 
         // similar_2 should be ranked higher than different
         assert!(!recommendations.is_empty());
@@ -505,9 +1322,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_timbre_weights() {
+        let engine = RecommendationEngine::with_config(RecommendConfig {
+            contrast_weight: 0.2,
+            crest_weight: 0.1,
+            ..Default::default()
+        });
+
+        let analyzer = FrequencyAnalyzer::new(4096, 2048);
+        let audio = generate_test_audio(440.0, 2.0);
+        let base = analyzer.compute_signature(&audio.samples, audio.sample_rate).unwrap();
+
+        let mut flat = base.clone();
+        flat.contrast = vec![0.0; base.contrast.len()];
+        flat.crest_factor = 4.0;
+
+        let same_breakdown = engine.compute_similarity(&base, &base);
+        let changed_breakdown = engine.compute_similarity(&base, &flat);
+        let (same, same_features) = (same_breakdown.total(), same_breakdown.matching_features());
+        let (changed, changed_features) = (changed_breakdown.total(), changed_breakdown.matching_features());
+        assert!(same > changed);
+        assert!(same_features.contains(&"spectral_texture".to_string()));
+        assert!(!changed_features.contains(&"spectral_texture".to_string()));
+        assert!(!changed_features.contains(&"dynamics".to_string()));
+
+        // Signatures saved before contrast existed skip that component
+        let mut legacy = base.clone();
+        legacy.contrast.clear();
+        let legacy_sim = engine.compute_similarity(&base, &legacy).total();
+        assert!((legacy_sim - (same - 0.2)).abs() < 1e-4);
+    }
+
     #[test]
     fn test_user_recommendations() {
-        let mut engine = RecommendationEngine::new();
+        let engine = RecommendationEngine::new();
 
         // User watched low-frequency content
         let audio1 = generate_test_audio(200.0, 5.0);
@@ -523,7 +1372,7 @@ mod tests {
         engine.add_content("unwatched_different", &audio4, None).unwrap();
 
         let history = vec!["watched_1".to_string(), "watched_2".to_string()];
-        let recommendations = engine.get_user_recommendations(&history, 2);
+        let recommendations = engine.get_user_recommendations(&history, 2, &RecommendationFilter::default());
 
         // Should not recommend already watched content
         for rec in &recommendations {
@@ -531,9 +1380,85 @@ mod tests {
         }
     }
 
+    fn watch(content_id: &str, days_ago: f64, completion_ratio: f32) -> WatchEvent {
+        WatchEvent {
+            content_id: content_id.to_string(),
+            timestamp: 1_700_000_000.0 - days_ago * 24.0 * 3600.0,
+            completion_ratio,
+        }
+    }
+
+    fn history_engine() -> RecommendationEngine {
+        let engine = RecommendationEngine::with_config(RecommendConfig {
+            min_similarity: 0.0,
+            ..Default::default()
+        });
+        for (id, freq) in [("low", 200.0), ("high", 5000.0), ("near_low", 220.0), ("near_high", 4800.0)] {
+            engine.add_content(id, &generate_test_audio(freq, 5.0), None).unwrap();
+        }
+        engine
+    }
+
+    fn similarity_of(recs: &[Recommendation], id: &str) -> f32 {
+        recs.iter().find(|r| r.content_id == id).unwrap().similarity
+    }
+
+    #[test]
+    fn test_recent_complete_history_dominates() {
+        let engine = history_engine();
+        let filter = RecommendationFilter::default();
+
+        // Yesterday's full watch outweighs a half-watched item from three months ago
+        let history = [watch("low", 1.0, 1.0), watch("high", 90.0, 0.5)];
+        let recs = engine.get_user_recommendations_weighted(&history, &[], 10, &filter);
+        assert_eq!(recs[0].content_id, "near_low");
+        assert!(similarity_of(&recs, "near_low") > similarity_of(&recs, "near_high"));
+
+        // Swapping recency and completion flips the ranking
+        let history = [watch("low", 90.0, 0.5), watch("high", 1.0, 1.0)];
+        let recs = engine.get_user_recommendations_weighted(&history, &[], 10, &filter);
+        assert_eq!(recs[0].content_id, "near_high");
+        assert!(recs.iter().all(|r| r.content_id != "low" && r.content_id != "high"));
+    }
+
+    #[test]
+    fn test_history_below_min_completion_is_ignored() {
+        let engine = history_engine();
+        let filter = RecommendationFilter::default();
+
+        let abandoned = [watch("high", 0.0, 0.05)];
+        assert!(engine.get_user_recommendations_weighted(&abandoned, &[], 10, &filter).is_empty());
+
+        let with_abandoned = [watch("low", 2.0, 1.0), watch("high", 0.0, 0.05)];
+        let without = [watch("low", 2.0, 1.0)];
+        let recs = engine.get_user_recommendations_weighted(&with_abandoned, &[], 10, &filter);
+        let baseline = engine.get_user_recommendations_weighted(&without, &[], 10, &filter);
+        assert!((similarity_of(&recs, "near_high") - similarity_of(&baseline, "near_high")).abs() < 1e-6);
+        assert!(recs.iter().all(|r| r.content_id != "high"));
+    }
+
+    #[test]
+    fn test_disliked_items_push_profile_away() {
+        let engine = history_engine();
+        let filter = RecommendationFilter::default();
+        let history = [watch("low", 0.0, 1.0), watch("high", 0.0, 1.0)];
+
+        let neutral = engine.get_user_recommendations_weighted(&history, &[], 10, &filter);
+        let disliked = ["near_high".to_string()];
+        let recs = engine.get_user_recommendations_weighted(&history, &disliked, 10, &filter);
+
+        assert!(recs.iter().all(|r| r.content_id != "near_high"));
+        assert!(similarity_of(&recs, "near_low") > similarity_of(&neutral, "near_low"));
+
+        // The wrapper matches equally weighted, fully watched events
+        let ids = ["low".to_string(), "high".to_string()];
+        let wrapped = engine.get_user_recommendations(&ids, 10, &filter);
+        assert!((similarity_of(&wrapped, "near_low") - similarity_of(&neutral, "near_low")).abs() < 1e-6);
+    }
+
     #[test]
     fn test_export_import() {
-        let mut engine1 = RecommendationEngine::new();
+        let engine1 = RecommendationEngine::new();
 
         let audio = generate_test_audio(440.0, 5.0);
         engine1.add_content("test_content", &audio, None).unwrap();
@@ -543,9 +1468,383 @@ mod tests {
         assert_eq!(exported.len(), 1);
 
         // Import into new engine
-        let mut engine2 = RecommendationEngine::new();
-        engine2.import_index(exported);
+        let engine2 = RecommendationEngine::new();
+        engine2.import_index(exported).unwrap();
 
         assert_eq!(engine2.len(), 1);
     }
+
+    fn metadata(creator: &str, tags: &[&str], duration_secs: f64) -> Option<ContentMetadata> {
+        Some(ContentMetadata {
+            title: None,
+            creator_id: Some(creator.to_string()),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            duration_secs: Some(duration_secs),
+        })
+    }
+
+    fn engine_with_metadata() -> RecommendationEngine {
+        let engine = RecommendationEngine::new();
+        let audio = generate_test_audio(440.0, 2.0);
+        let signature = engine.analyzer.compute_signature(&audio.samples, audio.sample_rate).unwrap();
+
+        engine.add_content_with_signature("seed", signature.clone(), metadata("alice", &["music"], 200.0)).unwrap();
+        engine.add_content_with_signature("same_creator", signature.clone(), metadata("alice", &["music"], 180.0)).unwrap();
+        engine.add_content_with_signature("podcast", signature.clone(), metadata("bob", &["talk"], 3600.0)).unwrap();
+        engine.add_content_with_signature("short", signature.clone(), metadata("carol", &["music", "clip"], 30.0)).unwrap();
+        engine.add_content_with_signature("untagged", signature, None).unwrap();
+        engine
+    }
+
+    fn ids(recs: &[Recommendation]) -> Vec<&str> {
+        let mut ids: Vec<&str> = recs.iter().map(|r| r.content_id.as_str()).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn test_filter_exclude_creator() {
+        let engine = engine_with_metadata();
+        let filter = RecommendationFilter::new().exclude_creator("alice");
+
+        let recs = engine.get_similar("seed", 10, &filter);
+        assert_eq!(ids(&recs), ["podcast", "short", "untagged"]);
+    }
+
+    #[test]
+    fn test_filter_tags_and_duration() {
+        let engine = engine_with_metadata();
+
+        let music = RecommendationFilter::new().require_any_tags(["music", "ambient"]);
+        assert_eq!(ids(&engine.get_similar("seed", 10, &music)), ["same_creator", "short"]);
+
+        let long_form = RecommendationFilter::new().duration_range(Some(60.0), None);
+        assert_eq!(ids(&engine.get_similar("seed", 10, &long_form)), ["podcast", "same_creator"]);
+
+        let bounded = RecommendationFilter::new().duration_range(Some(60.0), Some(600.0));
+        assert_eq!(ids(&engine.get_similar("seed", 10, &bounded)), ["same_creator"]);
+    }
+
+    #[test]
+    fn test_filter_predicate_and_user_history() {
+        let engine = engine_with_metadata();
+        let filter = RecommendationFilter::new()
+            .with_predicate(|id, meta| id != "untagged" && meta.is_some_and(|m| m.title.is_none()));
+
+        let history = vec!["seed".to_string()];
+        let recs = engine.get_user_recommendations(&history, 10, &filter);
+        assert_eq!(ids(&recs), ["podcast", "same_creator", "short"]);
+    }
+
+    #[test]
+    fn test_recommendations_include_metadata() {
+        let engine = engine_with_metadata();
+        let recs = engine.get_similar("seed", 10, &RecommendationFilter::default());
+
+        let podcast = recs.iter().find(|r| r.content_id == "podcast").unwrap();
+        assert_eq!(podcast.metadata, metadata("bob", &["talk"], 3600.0));
+
+        let untagged = recs.iter().find(|r| r.content_id == "untagged").unwrap();
+        assert!(untagged.metadata.is_none());
+    }
+
+    fn tags(tags: &[&str]) -> ContentMetadata {
+        ContentMetadata {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_similarity_breakdown() {
+        let engine = RecommendationEngine::with_config(RecommendConfig {
+            explain: true,
+            ..Default::default()
+        });
+        engine.add_content("seed", &generate_test_audio(440.0, 2.0), Some(tags(&["music"]))).unwrap();
+        engine.add_content("close", &generate_test_audio(445.0, 2.0), Some(tags(&["music", "clip"]))).unwrap();
+        engine.add_content_metadata("tags_only", tags(&["music"]));
+
+        let recs = engine.get_similar("seed", 10, &RecommendationFilter::default());
+        for rec in &recs {
+            let breakdown = rec.breakdown.as_ref().unwrap();
+            assert!((breakdown.total() - rec.similarity).abs() < 1e-5, "{:?}", rec);
+            assert_eq!(breakdown.matching_features(), rec.matching_features);
+        }
+
+        let close = recs.iter().find(|r| r.content_id == "close").unwrap().breakdown.clone().unwrap();
+        assert!(close.signature.is_some() && close.tags.is_some() && close.bandwidth.is_none());
+        assert!((close.weights.signature - 0.5 * 0.7).abs() < 1e-6 && close.weights.tags == 0.3);
+        assert_eq!(close.top_features.len(), TOP_FEATURES);
+        assert!(close.top_features.windows(2).all(|p| p[0].contribution >= p[1].contribution));
+        assert!(close.top_features.iter().map(|f| f.contribution).sum::<f32>() <= close.signature.unwrap() + 1e-5);
+
+        let tags_only = recs.iter().find(|r| r.content_id == "tags_only").unwrap().breakdown.clone().unwrap();
+        assert_eq!((tags_only.signature, tags_only.tags, tags_only.weights.tags), (None, Some(1.0), 1.0));
+
+        // Only compared components are serialized
+        let json = serde_json::to_value(&tags_only).unwrap();
+        assert!(json.get("signature").is_none() && json.get("top_features").is_none());
+        assert_eq!(json["tags"], 1.0);
+
+        // Off by default
+        let engine = RecommendationEngine::new();
+        engine.add_content_metadata("a", tags(&["music"]));
+        engine.add_content_metadata("b", tags(&["music"]));
+        let recs = engine.get_similar("a", 10, &RecommendationFilter::default());
+        assert!(recs[0].breakdown.is_none());
+        assert!(!serde_json::to_string(&recs[0]).unwrap().contains("breakdown"));
+    }
+
+    #[test]
+    fn test_cold_start_by_tags() {
+        let engine = engine_with_metadata();
+        engine.add_content_metadata("new_clip", tags(&["music", "clip"]));
+        engine.add_content_metadata("new_lecture", tags(&["lecture"]));
+
+        // Metadata-only uploads show up next to analyzed content straight away
+        let recs = engine.get_similar("short", 10, &RecommendationFilter::default());
+        let clip = recs.iter().find(|r| r.content_id == "new_clip").unwrap();
+        assert_eq!(clip.matching_features, ["tags"]);
+        assert!((clip.similarity - 1.0).abs() < 1e-6);
+        assert!(recs.iter().all(|r| r.content_id != "new_lecture"));
+
+        // Analyzed items with tags on both sides report both signals
+        let seed = recs.iter().find(|r| r.content_id == "seed").unwrap();
+        assert!(seed.matching_features.contains(&"frequency_pattern".to_string()));
+        assert!(seed.matching_features.contains(&"tags".to_string()));
+
+        // A metadata-only item is matched by tags alone, skipping untagged content
+        let recs = engine.get_similar("new_clip", 10, &RecommendationFilter::default());
+        assert_eq!(recs[0].content_id, "short");
+        assert!(recs.iter().all(|r| r.content_id != "untagged" && r.content_id != "new_lecture"));
+        assert!(recs.iter().all(|r| r.matching_features == ["tags"]));
+
+        // Adding audio later keeps the metadata
+        let signature = engine.catalog().content_index["seed"].signature.clone().unwrap();
+        engine.add_content_metadata("seed", tags(&["music"]));
+        assert!(engine.catalog().content_index["seed"].signature.is_some());
+        engine.add_content_with_signature("new_lecture", signature, Some(tags(&["lecture"]))).unwrap();
+        assert!(engine.get_similar("new_lecture", 10, &RecommendationFilter::default())
+            .iter()
+            .any(|r| r.matching_features.contains(&"frequency_pattern".to_string())));
+    }
+
+    #[test]
+    fn test_tag_similarity_favors_rare_tags() {
+        let engine = RecommendationEngine::with_config(RecommendConfig {
+            min_similarity: 0.0,
+            ..Default::default()
+        });
+        for i in 0..8 {
+            engine.add_content_metadata(&format!("filler_{}", i), tags(&["music"]));
+        }
+        engine.add_content_metadata("target", tags(&["music", "theremin"]));
+        engine.add_content_metadata("rare_match", tags(&["theremin", "live"]));
+        engine.add_content_metadata("common_match", tags(&["music", "live"]));
+
+        let recs = engine.get_similar("target", 20, &RecommendationFilter::default());
+        assert_eq!(recs[0].content_id, "rare_match");
+        assert!(similarity_of(&recs, "rare_match") > similarity_of(&recs, "common_match"));
+
+        // Removing items updates the document frequencies
+        for i in 0..8 {
+            engine.remove_content(&format!("filler_{}", i));
+        }
+        assert_eq!(engine.catalog().tag_counts["music"], 2);
+        assert_eq!(engine.catalog().tagged_items, 3);
+    }
+
+    #[test]
+    fn test_export_import_metadata_only() {
+        let engine = engine_with_metadata();
+        engine.add_content_metadata("new_clip", tags(&["music", "clip"]));
+
+        let json = serde_json::to_string(&engine.export_index()).unwrap();
+        let restored = RecommendationEngine::new();
+        restored.import_index(serde_json::from_str::<Vec<IndexEntry>>(&json).unwrap()).unwrap();
+
+        assert!(restored.catalog().content_index["new_clip"].signature.is_none());
+        assert_eq!(restored.catalog().tag_counts, engine.catalog().tag_counts);
+        let recs = restored.get_similar("new_clip", 1, &RecommendationFilter::default());
+        assert_eq!(recs[0].content_id, "short");
+    }
+
+    fn next_random(state: &mut u64) -> f32 {
+        // xorshift64, good enough for synthetic test vectors
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        (*state >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Signatures drawn around `clusters` random centers.
+    fn synthetic_catalog(count: usize, clusters: usize, seed: u64) -> Vec<(String, FrequencySignature)> {
+        let mut state = seed;
+        let centers: Vec<Vec<f32>> = (0..clusters)
+            .map(|_| (0..128).map(|_| next_random(&mut state)).collect())
+            .collect();
+
+        (0..count)
+            .map(|i| {
+                let center = &centers[i % clusters];
+                let features = center.iter().map(|c| c + 0.3 * next_random(&mut state)).collect();
+                let band = |state: &mut u64| next_random(state);
+                let signature = FrequencySignature {
+                    version: SIGNATURE_VERSION,
+                    features,
+                    band_energies: BandEnergies::standard(std::array::from_fn(|_| band(&mut state))),
+                    centroid: 1000.0 + 2000.0 * next_random(&mut state),
+                    flatness: next_random(&mut state),
+                    bandwidth: 0.0,
+                    contrast: Vec::new(),
+                    crest_factor: 0.0,
+                };
+                (format!("item_{}", i), signature)
+            })
+            .collect()
+    }
+
+    fn ann_config() -> RecommendConfig {
+        RecommendConfig {
+            ann_min_items: 500,
+            ..RecommendConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_ann_recall_matches_brute_force() {
+        let catalog = synthetic_catalog(3000, 40, 0x5eed);
+
+        let ann = RecommendationEngine::with_config(ann_config());
+        ann.import_index(catalog.clone()).unwrap();
+        let brute = RecommendationEngine::with_config(RecommendConfig {
+            ann_enabled: false,
+            ..RecommendConfig::default()
+        });
+        brute.import_index(catalog).unwrap();
+
+        assert!(ann.ann_index().is_some());
+        assert!(brute.ann_index().is_none());
+
+        let filter = RecommendationFilter::default();
+        let mut hits = 0;
+        let mut total = 0;
+        for q in (0..3000).step_by(150) {
+            let id = format!("item_{}", q);
+            let expected = brute.get_similar(&id, 10, &filter);
+            let actual = ann.get_similar(&id, 10, &filter);
+            total += expected.len();
+            hits += expected.iter()
+                .filter(|e| actual.iter().any(|a| a.content_id == e.content_id))
+                .count();
+        }
+
+        let recall = hits as f32 / total as f32;
+        assert!(recall >= 0.9, "recall@10 = {}", recall);
+    }
+
+    #[test]
+    fn test_ann_incremental_updates() {
+        let engine = RecommendationEngine::with_config(ann_config());
+        engine.import_index(synthetic_catalog(600, 10, 7)).unwrap();
+        assert_eq!(engine.ann_index().unwrap().len(), 600);
+
+        let (_, signature) = synthetic_catalog(1, 1, 99).pop().unwrap();
+        engine.add_content_with_signature("late_arrival", signature, None).unwrap();
+        assert_eq!(engine.ann_index().unwrap().len(), 601);
+
+        let recs = engine.get_similar("late_arrival", 5, &RecommendationFilter::default());
+        assert!(recs.iter().all(|r| r.content_id != "late_arrival"));
+
+        assert!(engine.remove_content("item_0"));
+        assert_eq!(engine.ann_index().unwrap().len(), 600);
+        let recs = engine.get_similar("item_10", 600, &RecommendationFilter::default());
+        assert!(recs.iter().all(|r| r.content_id != "item_0"));
+
+        // Doubling the catalog retrains the index
+        let lists_before = engine.ann_index().unwrap().num_lists();
+        let more: Vec<_> = synthetic_catalog(800, 10, 8).into_iter()
+            .map(|(id, sig)| (format!("more_{}", id), sig))
+            .collect();
+        engine.import_index(more).unwrap();
+        assert_eq!(engine.ann_index().unwrap().len(), 1400);
+        assert!(engine.ann_index().unwrap().num_lists() > lists_before);
+
+        // Shrinking below the threshold falls back to a linear scan
+        for i in 1..600 {
+            engine.remove_content(&format!("item_{}", i));
+        }
+        for i in 0..800 {
+            engine.remove_content(&format!("more_item_{}", i));
+        }
+        assert!(engine.ann_index().is_none());
+    }
+
+    #[test]
+    fn test_snapshot_restores_ann_without_retraining() {
+        let engine = RecommendationEngine::with_config(ann_config());
+        engine.import_index(synthetic_catalog(1000, 20, 3)).unwrap();
+
+        let json = serde_json::to_string(&engine.export_snapshot()).unwrap();
+        let snapshot: IndexSnapshot = serde_json::from_str(&json).unwrap();
+
+        let restored = RecommendationEngine::with_config(ann_config());
+        restored.import_snapshot(snapshot).unwrap();
+
+        let original = engine.ann_index().unwrap();
+        let loaded = restored.ann_index().unwrap();
+        assert_eq!(loaded.num_lists(), original.num_lists());
+        assert_eq!(loaded.len(), 1000);
+
+        let filter = RecommendationFilter::default();
+        let ids = |recs: Vec<Recommendation>| recs.into_iter().map(|r| r.content_id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(restored.get_similar("item_42", 10, &filter)),
+            ids(engine.get_similar("item_42", 10, &filter)),
+        );
+    }
+
+    #[test]
+    fn test_export_import_preserves_metadata() {
+        let engine = engine_with_metadata();
+        let json = serde_json::to_string(&engine.export_index()).unwrap();
+
+        let restored = RecommendationEngine::new();
+        restored.import_index(serde_json::from_str::<Vec<IndexEntry>>(&json).unwrap()).unwrap();
+
+        assert_eq!(restored.len(), engine.len());
+        assert_eq!(restored.metadata("short"), engine.metadata("short"));
+        assert!(restored.metadata("untagged").is_none());
+
+        let filter = RecommendationFilter::new().exclude_creator("alice");
+        assert_eq!(ids(&restored.get_similar("seed", 10, &filter)), ["podcast", "short", "untagged"]);
+    }
+
+    #[test]
+    fn test_dimension_policy() {
+        let catalog: Vec<(String, FrequencySignature)> = synthetic_catalog(10, 2, 11)
+            .into_iter()
+            .map(|(id, sig)| (id, sig.rebin(64)))
+            .collect();
+
+        let strict = RecommendationEngine::new();
+        let err = strict.import_index(catalog.clone()).unwrap_err();
+        let mismatch = err.downcast_ref::<SignatureError>().unwrap();
+        assert_eq!(*mismatch, SignatureError::DimensionMismatch { left: 128, right: 64 });
+        assert!(strict.is_empty());
+
+        let (id, signature) = catalog[0].clone();
+        assert!(strict.add_content_with_signature(&id, signature, None).is_err());
+
+        let lenient = RecommendationEngine::with_config(RecommendConfig {
+            dimension_policy: DimensionPolicy::Rebin,
+            min_similarity: 0.0,
+            ..RecommendConfig::default()
+        });
+        lenient.import_index(catalog).unwrap();
+        assert_eq!(lenient.len(), 10);
+        assert!(!lenient.get_similar("item_0", 5, &RecommendationFilter::default()).is_empty());
+    }
 }
+
@@ -0,0 +1,233 @@
+//! Inverted-file (IVF) approximate nearest neighbor index.
+//!
+//! Signature feature vectors are normalized and clustered with spherical
+//! k-means. Each item lives in the inverted list of its closest centroid, so
+//! a query only scans the lists of the `probe_count` centroids nearest to it
+//! instead of the whole catalog.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Number of k-means refinement passes used when training centroids.
+const KMEANS_ITERATIONS: usize = 8;
+
+/// Approximate nearest neighbor index over signature feature vectors.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IvfIndex {
+    /// Unit-length cluster centroids
+    centroids: Vec<Vec<f32>>,
+    /// Content IDs assigned to each centroid
+    lists: Vec<Vec<String>>,
+    /// Number of items the centroids were trained on
+    trained_size: usize,
+    /// Reverse lookup from content ID to list, rebuilt after deserialization
+    #[serde(skip)]
+    assignments: HashMap<String, usize>,
+}
+
+impl IvfIndex {
+    /// Train centroids over `items` and assign every item to a list.
+    pub(crate) fn train<'a, I>(items: I, num_lists: usize) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, &'a [f32])>,
+    {
+        let mut items: Vec<(&str, Vec<f32>)> = items.into_iter()
+            .map(|(id, features)| (id, normalize(features)))
+            .collect();
+        // Sort so training is deterministic regardless of hash map order
+        items.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        let num_lists = num_lists.clamp(1, items.len().max(1));
+        let stride = items.len() as f32 / num_lists as f32;
+        let mut centroids: Vec<Vec<f32>> = (0..num_lists)
+            .filter_map(|i| items.get((i as f32 * stride) as usize))
+            .map(|(_, v)| v.clone())
+            .collect();
+
+        let mut assignment = vec![0usize; items.len()];
+        for _ in 0..KMEANS_ITERATIONS {
+            for (slot, (_, v)) in assignment.iter_mut().zip(&items) {
+                *slot = nearest(&centroids, v);
+            }
+
+            let dim = centroids.first().map_or(0, Vec::len);
+            let mut sums = vec![vec![0.0f32; dim]; centroids.len()];
+            for (&c, (_, v)) in assignment.iter().zip(&items) {
+                for (s, x) in sums[c].iter_mut().zip(v) {
+                    *s += x;
+                }
+            }
+
+            // Empty clusters keep their previous centroid
+            for (centroid, sum) in centroids.iter_mut().zip(sums) {
+                if sum.iter().any(|&x| x != 0.0) {
+                    *centroid = normalize(&sum);
+                }
+            }
+        }
+
+        let mut index = Self {
+            lists: vec![Vec::new(); centroids.len()],
+            centroids,
+            trained_size: items.len(),
+            assignments: HashMap::with_capacity(items.len()),
+        };
+        for (id, v) in &items {
+            let list = nearest(&index.centroids, v);
+            index.lists[list].push(id.to_string());
+            index.assignments.insert(id.to_string(), list);
+        }
+        index
+    }
+
+    /// Add an item to the list of its nearest centroid, replacing any previous entry.
+    pub(crate) fn insert(&mut self, content_id: &str, features: &[f32]) {
+        if self.centroids.is_empty() {
+            return;
+        }
+        self.remove(content_id);
+
+        let list = nearest(&self.centroids, &normalize(features));
+        self.lists[list].push(content_id.to_string());
+        self.assignments.insert(content_id.to_string(), list);
+    }
+
+    /// Remove an item from the index.
+    pub(crate) fn remove(&mut self, content_id: &str) -> bool {
+        match self.assignments.remove(content_id) {
+            Some(list) => {
+                self.lists[list].retain(|id| id != content_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Content IDs in the `probe_count` lists closest to `query`.
+    pub(crate) fn probe<'a>(&'a self, query: &[f32], probe_count: usize) -> impl Iterator<Item = &'a str> + 'a {
+        let mut order: Vec<(usize, f32)> = self.centroids.iter()
+            .map(|c| dot(c, query))
+            .enumerate()
+            .collect();
+        order.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        order.into_iter()
+            .take(probe_count.max(1))
+            .flat_map(move |(list, _)| self.lists[list].iter().map(String::as_str))
+    }
+
+    /// Rebuild the reverse lookup after deserialization.
+    pub(crate) fn restore_assignments(&mut self) {
+        self.assignments = self.lists.iter()
+            .enumerate()
+            .flat_map(|(list, ids)| ids.iter().map(move |id| (id.clone(), list)))
+            .collect();
+    }
+
+    /// Check whether an item is indexed.
+    pub(crate) fn contains(&self, content_id: &str) -> bool {
+        self.assignments.contains_key(content_id)
+    }
+
+    /// Number of items the centroids were trained on.
+    pub(crate) fn trained_size(&self) -> usize {
+        self.trained_size
+    }
+
+    /// Length of the feature vectors the centroids were trained on.
+    pub fn dimensions(&self) -> usize {
+        self.centroids.first().map_or(0, Vec::len)
+    }
+
+    /// Number of inverted lists.
+    pub fn num_lists(&self) -> usize {
+        self.lists.len()
+    }
+
+    /// Number of indexed items.
+    pub fn len(&self) -> usize {
+        self.lists.iter().map(Vec::len).sum()
+    }
+
+    /// Check if the index is empty.
+    pub fn is_empty(&self) -> bool {
+        self.lists.iter().all(Vec::is_empty)
+    }
+}
+
+/// Scale a vector to unit length (zero vectors are returned unchanged).
+pub(crate) fn normalize(v: &[f32]) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter().map(|x| x / norm).collect()
+    } else {
+        v.to_vec()
+    }
+}
+
+/// Dot product over the common prefix of two vectors.
+pub(crate) fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn nearest(centroids: &[Vec<f32>], v: &[f32]) -> usize {
+    centroids.iter()
+        .map(|c| dot(c, v))
+        .enumerate()
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axis(dim: usize, hot: usize, jitter: f32) -> Vec<f32> {
+        (0..dim).map(|i| if i == hot { 1.0 } else { jitter }).collect()
+    }
+
+    #[test]
+    fn test_train_groups_clusters() {
+        let vectors: Vec<(String, Vec<f32>)> = (0..40)
+            .map(|i| (format!("item_{:02}", i), axis(8, i / 10, 0.01 * (i % 10) as f32)))
+            .collect();
+        let index = IvfIndex::train(vectors.iter().map(|(id, v)| (id.as_str(), v.as_slice())), 4);
+
+        assert_eq!(index.num_lists(), 4);
+        assert_eq!(index.len(), 40);
+
+        // Probing a single list returns exactly the items sharing the query's axis
+        let hits: Vec<&str> = index.probe(&axis(8, 2, 0.0), 1).collect();
+        assert_eq!(hits.len(), 10);
+        assert!(hits.iter().all(|id| id[5..].parse::<usize>().unwrap() / 10 == 2));
+    }
+
+    #[test]
+    fn test_insert_remove_and_restore() {
+        let vectors: Vec<(String, Vec<f32>)> = (0..8)
+            .map(|i| (format!("item_{}", i), axis(4, i / 4, 0.0)))
+            .collect();
+        let mut index = IvfIndex::train(vectors.iter().map(|(id, v)| (id.as_str(), v.as_slice())), 2);
+
+        index.insert("new", &axis(4, 1, 0.0));
+        assert!(index.contains("new"));
+        assert_eq!(index.len(), 9);
+
+        // Re-inserting moves the item instead of duplicating it
+        index.insert("new", &axis(4, 0, 0.0));
+        assert_eq!(index.len(), 9);
+        assert!(index.probe(&axis(4, 0, 0.0), 1).any(|id| id == "new"));
+
+        assert!(index.remove("item_3"));
+        assert!(!index.remove("item_3"));
+        assert_eq!(index.len(), 8);
+
+        let mut restored: IvfIndex = serde_json::from_str(&serde_json::to_string(&index).unwrap()).unwrap();
+        restored.restore_assignments();
+        assert!(restored.contains("new"));
+        assert!(restored.remove("item_0"));
+        assert_eq!(restored.len(), 7);
+    }
+}
@@ -0,0 +1,313 @@
+//! Band-limited sample rate conversion.
+//!
+//! Kaiser-windowed sinc interpolation, following the design of the resampler
+//! Chromaprint uses (16 taps at the output rate, cutoff at 80% of the lower
+//! Nyquist frequency) so downsampled input carries no aliased content into
+//! the fingerprint.
+//!
+//! Analyzers that assume a particular rate declare it (see
+//! `preferred_sample_rate` on [`FrequencyAnalyzer`](crate::FrequencyAnalyzer),
+//! `ContentTagger` and `Fingerprinter`) and convert input with [`conform`].
+
+use std::borrow::Cow;
+use std::f64::consts::PI;
+
+use crate::types::AudioData;
+
+/// Filter taps per output sample before scaling for the conversion ratio.
+const FILTER_LENGTH: f64 = 16.0;
+/// Passband edge relative to the lower of the two Nyquist frequencies.
+const CUTOFF: f64 = 0.8;
+/// Kaiser window shape parameter.
+const KAISER_BETA: f64 = 9.0;
+
+/// Relative rate difference below which audio is analyzed as is, e.g.
+/// 44099 Hz from a drifting capture clock isn't resampled to 44100 Hz.
+pub const RATE_TOLERANCE: f64 = 0.001;
+
+/// Resample audio to `target_rate`, converting each channel separately.
+pub fn resample(audio: &AudioData, target_rate: u32) -> AudioData {
+    if audio.sample_rate == target_rate || audio.samples.is_empty() {
+        return audio.clone();
+    }
+
+    let channels = audio.channels.max(1) as usize;
+    if channels == 1 {
+        return AudioData::new(resample_samples(&audio.samples, audio.sample_rate, target_rate), target_rate);
+    }
+
+    let converted: Vec<Vec<f32>> = (0..channels)
+        .map(|c| {
+            let channel: Vec<f32> = audio.samples.iter().skip(c).step_by(channels).copied().collect();
+            resample_samples(&channel, audio.sample_rate, target_rate)
+        })
+        .collect();
+
+    let frames = converted.iter().map(Vec::len).min().unwrap_or(0);
+    let samples = (0..frames)
+        .flat_map(|i| converted.iter().map(move |channel| channel[i]))
+        .collect();
+    AudioData::with_channels(samples, target_rate, channels as u32)
+}
+
+/// Bring audio to `preferred_rate` unless it is already within [`RATE_TOLERANCE`].
+pub fn conform(audio: &AudioData, preferred_rate: u32) -> Cow<'_, AudioData> {
+    if within_tolerance(audio.sample_rate, preferred_rate) {
+        Cow::Borrowed(audio)
+    } else {
+        Cow::Owned(resample(audio, preferred_rate))
+    }
+}
+
+/// Whether `rate` is close enough to `preferred_rate` to skip resampling.
+pub fn within_tolerance(rate: u32, preferred_rate: u32) -> bool {
+    preferred_rate == 0 || (rate as f64 - preferred_rate as f64).abs() <= preferred_rate as f64 * RATE_TOLERANCE
+}
+
+/// Resample mono samples from `from_rate` to `to_rate`.
+pub fn resample_samples(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if samples.is_empty() || from_rate == to_rate || from_rate == 0 || to_rate == 0 {
+        return samples.to_vec();
+    }
+
+    let filter = Filter::new(from_rate, to_rate);
+    let out_len = filter.output_len(samples.len() as u64);
+    (0..out_len)
+        .map(|i| filter.output_sample(samples, 0, i))
+        .collect()
+}
+
+/// Polyphase Kaiser-windowed sinc filter for one conversion ratio.
+struct Filter {
+    from: u64,
+    to: u64,
+    half_width: i64,
+    /// One kernel per fractional output offset
+    kernels: Vec<Vec<f64>>,
+}
+
+impl Filter {
+    fn new(from_rate: u32, to_rate: u32) -> Self {
+        // Output positions cycle through `to` fractional offsets, so
+        // the kernel is evaluated once per offset rather than once per sample
+        let gcd = gcd(from_rate, to_rate) as u64;
+        let (from, to) = (from_rate as u64 / gcd, to_rate as u64 / gcd);
+        let factor = (to as f64 / from as f64).min(1.0) * CUTOFF;
+        let half_width = (FILTER_LENGTH / factor / 2.0).ceil() as i64;
+        let norm = bessel_i0(KAISER_BETA);
+
+        let kernels = (0..to)
+            .map(|phase| {
+                let frac = phase as f64 / to as f64;
+                (-half_width..=half_width)
+                    .map(|k| {
+                        let x = k as f64 - frac;
+                        let r = x / half_width as f64;
+                        if r.abs() > 1.0 {
+                            return 0.0;
+                        }
+                        factor * sinc(factor * x) * bessel_i0(KAISER_BETA * (1.0 - r * r).sqrt()) / norm
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self { from, to, half_width, kernels }
+    }
+
+    /// Number of output samples for `input_len` input samples.
+    fn output_len(&self, input_len: u64) -> u64 {
+        (input_len * self.to + self.from / 2) / self.from
+    }
+
+    /// Input index the kernel of output sample `i` is centered on.
+    fn center(&self, i: u64) -> i64 {
+        (i * self.from / self.to) as i64
+    }
+
+    /// Compute output sample `i`, where `samples[0]` is input sample `offset`.
+    ///
+    /// Taps outside `samples` contribute nothing.
+    fn output_sample(&self, samples: &[f32], offset: u64, i: u64) -> f32 {
+        let base = self.center(i) - offset as i64;
+        let kernel = &self.kernels[(i * self.from % self.to) as usize];
+
+        kernel.iter()
+            .enumerate()
+            .filter_map(|(k, w)| {
+                let j = base + k as i64 - self.half_width;
+                samples.get(usize::try_from(j).ok()?).map(|&s| s as f64 * w)
+            })
+            .sum::<f64>() as f32
+    }
+}
+
+/// Incremental resampler producing the same output as [`resample`].
+///
+/// Output samples are emitted as soon as every filter tap they need has
+/// arrived, and only the input still in reach of the filter is kept.
+#[cfg_attr(not(feature = "fingerprint"), allow(dead_code))]
+pub(crate) struct StreamResampler {
+    /// `None` when the rates match and samples pass through unchanged
+    filter: Option<Filter>,
+    /// Buffered input, starting at absolute input index `offset`
+    input: Vec<f32>,
+    offset: u64,
+    /// Total input samples received
+    received: u64,
+    /// Output samples emitted so far
+    produced: u64,
+}
+
+#[cfg_attr(not(feature = "fingerprint"), allow(dead_code))]
+impl StreamResampler {
+    pub(crate) fn new(from_rate: u32, to_rate: u32) -> Self {
+        let filter = (from_rate != to_rate && from_rate != 0 && to_rate != 0)
+            .then(|| Filter::new(from_rate, to_rate));
+        Self { filter, input: Vec::new(), offset: 0, received: 0, produced: 0 }
+    }
+
+    /// Feed input samples, returning the output samples that are now complete.
+    pub(crate) fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let Some(filter) = &self.filter else {
+            return samples.to_vec();
+        };
+        self.input.extend_from_slice(samples);
+        self.received += samples.len() as u64;
+
+        let mut out = Vec::new();
+        while filter.center(self.produced) + filter.half_width < self.received as i64 {
+            out.push(filter.output_sample(&self.input, self.offset, self.produced));
+            self.produced += 1;
+        }
+
+        // Drop input the next output's filter can no longer reach
+        let keep_from = (filter.center(self.produced) - filter.half_width).max(0) as u64;
+        if keep_from > self.offset {
+            let drop = ((keep_from - self.offset) as usize).min(self.input.len());
+            self.input.drain(..drop);
+            self.offset += drop as u64;
+        }
+        out
+    }
+
+    /// Flush the output samples that depend on the end of the stream.
+    pub(crate) fn finish(&mut self) -> Vec<f32> {
+        let Some(filter) = &self.filter else {
+            return Vec::new();
+        };
+        let out_len = filter.output_len(self.received);
+        let out = (self.produced..out_len)
+            .map(|i| filter.output_sample(&self.input, self.offset, i))
+            .collect();
+        self.produced = out_len.max(self.produced);
+        out
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Zeroth-order modified Bessel function of the first kind.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    for k in 1..32 {
+        term *= half / k as f64;
+        sum += term * term;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, rate: u32, secs: f32) -> Vec<f32> {
+        (0..(rate as f32 * secs) as usize)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / rate as f32).sin())
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_resample_preserves_passband() {
+        let out = resample_samples(&sine(440.0, 44100, 1.0), 44100, 11025);
+        assert_eq!(out.len(), 11025);
+
+        // Skip the filter's edge transients
+        let expected = sine(440.0, 11025, 1.0);
+        let body = 200..out.len() - 200;
+        let error: f32 = out[body.clone()].iter()
+            .zip(&expected[body])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(error < 0.01, "max error {}", error);
+    }
+
+    #[test]
+    fn test_resample_rejects_aliases() {
+        // 9kHz is above the 5.5kHz Nyquist of the output and must not fold back
+        let out = resample_samples(&sine(9000.0, 44100, 1.0), 44100, 11025);
+        assert!(rms(&out[200..out.len() - 200]) < 0.01);
+    }
+
+    #[test]
+    fn test_stream_resampler_matches_batch() {
+        let input = sine(440.0, 44100, 0.5);
+        let batch = resample_samples(&input, 44100, 22050);
+
+        let mut resampler = StreamResampler::new(44100, 22050);
+        let mut streamed = Vec::new();
+        for chunk in input.chunks(1000) {
+            streamed.extend(resampler.process(chunk));
+        }
+        streamed.extend(resampler.finish());
+        assert_eq!(streamed, batch);
+    }
+
+    #[test]
+    fn test_resample_upsample_length() {
+        let out = resample_samples(&sine(440.0, 22050, 0.5), 22050, 48000);
+        assert_eq!(out.len(), 24000);
+        assert!((rms(&out[500..23500]) - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_resample_audio_keeps_channels() {
+        let left = sine(440.0, 48000, 0.5);
+        let interleaved: Vec<f32> = left.iter().flat_map(|&s| [s, -s]).collect();
+        let stereo = AudioData::with_channels(interleaved, 48000, 2);
+
+        let out = resample(&stereo, 44100);
+        assert_eq!(out.channels, 2);
+        assert_eq!(out.sample_rate, 44100);
+        assert_eq!(out.samples.len(), 2 * 22050);
+        assert!(out.samples.chunks(2).all(|frame| (frame[0] + frame[1]).abs() < 1e-6));
+        assert!((out.duration_secs - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_conform_tolerance() {
+        let audio = AudioData::new(sine(440.0, 44099, 0.1), 44099);
+        assert!(matches!(conform(&audio, 44100), Cow::Borrowed(_)));
+
+        let audio = AudioData::new(sine(440.0, 8000, 0.1), 8000);
+        let conformed = conform(&audio, 44100);
+        assert_eq!(conformed.sample_rate, 44100);
+        assert_eq!(conformed.samples.len(), 4410);
+    }
+}
@@ -41,12 +41,44 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! # Anchoring
+//!
+//! [`SolanaAnchorClient`] anchors a fingerprint's hash, version and duration
+//! under a content ID and verifies local fingerprints against it. Records are
+//! written to a fingerprint program account when the program is deployed and
+//! to an SPL memo otherwise, so it also works against a bare
+//! `solana-test-validator`.
+//!
+//! Records also carry a [`ConstellationSummary`] of the fingerprint, so
+//! [`SolanaAnchorClient::verify_fingerprint_perceptual`] can verify
+//! transcoded copies whose hash no longer matches. See the
+//! [`fingerprint`](crate::fingerprint) module docs for the tradeoffs.
+//!
+//! ```rust,no_run
+//! use kino_frequency::solana::SolanaAnchorClient;
+//! # async fn run(fingerprint: kino_frequency::AudioFingerprint, payer: solana_sdk::signature::Keypair)
+//! #     -> Result<(), kino_frequency::solana::AnchorError> {
+//! let client = SolanaAnchorClient::with_rpc_url("http://127.0.0.1:8899")?;
+//!
+//! client.register_fingerprint("video_123", &fingerprint, &payer).await?;
+//!
+//! let verification = client.verify_fingerprint("video_123", &fingerprint).await?;
+//! if let Some(record) = verification.record {
+//!     println!("anchored at slot {} by {}", record.slot, record.authority);
+//! }
+//! # Ok(())
+//! # }
+//! ```
 
 use std::str::FromStr;
 use anyhow::{Result, Context, bail};
 use serde::{Deserialize, Serialize};
 use tracing::{info, debug, warn};
 
+use crate::fingerprint::{ConstellationSummary, Fingerprinter};
+use crate::types::AudioFingerprint;
+
 // Note: These imports require the "solana" feature
 #[cfg(feature = "solana")]
 use solana_sdk::{
@@ -106,6 +138,8 @@ pub struct SolanaConfig {
     pub commitment: String,
     /// Program ID for fingerprint storage
     pub program_id: String,
+    /// Where [`SolanaAnchorClient`] writes anchor records
+    pub anchor_mode: AnchorMode,
 }
 
 impl Default for SolanaConfig {
@@ -114,6 +148,7 @@ impl Default for SolanaConfig {
             rpc_url: "https://api.devnet.solana.com".to_string(),
             commitment: "confirmed".to_string(),
             program_id: FINGERPRINT_PROGRAM_ID.to_string(),
+            anchor_mode: AnchorMode::default(),
         }
     }
 }
@@ -357,6 +392,607 @@ fn hex_decode(s: &str) -> Result<Vec<u8>> {
         .collect()
 }
 
+// ============================================================================
+// Fingerprint anchoring
+// ============================================================================
+
+/// SPL Memo v1 program, used when the fingerprint program is not deployed.
+///
+/// Unlike later Memo versions, v1 accepts non-signer accounts, which lets a
+/// memo reference the content's anchor address so it can be looked up later.
+pub const MEMO_PROGRAM_ID: &str = "Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo";
+
+/// Maximum length for an anchored content ID.
+pub const MAX_CONTENT_ID_LEN: usize = 128;
+
+/// Prefix identifying Kino anchor memos.
+const ANCHOR_MEMO_PREFIX: &str = "kino-anchor";
+
+/// Version of the anchor memo layout; version 1 memos carry no summary.
+const ANCHOR_LAYOUT_VERSION: u8 = 2;
+
+/// Magic bytes at the start of an anchor record account.
+const ANCHOR_RECORD_MAGIC: &[u8; 8] = b"kinoanch";
+
+/// Instruction discriminator for anchoring a fingerprint.
+const ANCHOR_DISCRIMINATOR: u8 = 3;
+
+/// Page size for signature history lookups.
+const SIGNATURE_PAGE_LIMIT: usize = 1000;
+
+/// Errors returned by [`SolanaAnchorClient`].
+#[derive(Debug, thiserror::Error)]
+pub enum AnchorError {
+    /// The RPC request failed.
+    #[error("Solana RPC error: {0}")]
+    Rpc(#[from] solana_client::client_error::ClientError),
+
+    /// Client configuration is invalid.
+    #[error("Invalid Solana configuration: {0}")]
+    InvalidConfig(String),
+
+    /// The content ID is empty or too long.
+    #[error("Invalid content ID: {0}")]
+    InvalidContentId(String),
+
+    /// The fingerprint hash is not a 32-byte hex string.
+    #[error("Invalid fingerprint hash: {0}")]
+    InvalidHash(String),
+
+    /// Program-account mode was requested but the program is not deployed.
+    #[error("Fingerprint program {0} is not deployed on this cluster")]
+    ProgramNotDeployed(String),
+
+    /// On-chain data exists but could not be decoded.
+    #[error("Malformed anchor record: {0}")]
+    MalformedRecord(String),
+}
+
+/// Where anchor records are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchorMode {
+    /// Use the fingerprint program when it is deployed, memos otherwise
+    #[default]
+    Auto,
+    /// Write a record account owned by the fingerprint program
+    ProgramAccount,
+    /// Write an SPL memo referencing the content's anchor address
+    Memo,
+}
+
+/// Fingerprint record anchored on-chain for a content ID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnchorRecord {
+    /// Content ID the fingerprint was anchored for
+    pub content_id: String,
+    /// Wallet that anchored the record
+    pub authority: String,
+    /// SHA-256 hash of the fingerprint
+    pub fingerprint_hash: [u8; 32],
+    /// Fingerprint algorithm version
+    pub fingerprint_version: u32,
+    /// Duration of the fingerprinted audio in seconds
+    pub duration_secs: f64,
+    /// Slot the record was anchored in
+    pub slot: u64,
+    /// Unix timestamp the record was anchored at, if the cluster reports one
+    pub anchored_at: Option<i64>,
+    /// How the record is stored
+    pub mode: AnchorMode,
+    /// Transaction signature (memo records only)
+    pub signature: Option<String>,
+    /// Constellation summary for perceptual verification (absent for
+    /// Chromaprint fingerprints and records anchored before summaries)
+    #[serde(default)]
+    pub summary: Option<ConstellationSummary>,
+}
+
+/// Result of anchoring a fingerprint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchorReceipt {
+    /// Transaction signature
+    pub signature: String,
+    /// Anchor address for the content ID
+    pub address: String,
+    /// How the record was stored (never `Auto`)
+    pub mode: AnchorMode,
+}
+
+/// Comparison of a local fingerprint with its on-chain record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchorVerification {
+    /// The on-chain record, if one exists
+    pub record: Option<AnchorRecord>,
+    /// Whether the fingerprint hash matches the record
+    pub hash_matches: bool,
+    /// Whether the fingerprint version matches the record
+    pub version_matches: bool,
+    /// Whether the fingerprint is verified by the record
+    pub verified: bool,
+    /// Similarity to the anchored summary (perceptual verification only)
+    #[serde(default)]
+    pub similarity: Option<f32>,
+}
+
+/// Async client that anchors fingerprint hashes on Solana and verifies them.
+///
+/// Each content ID maps to an anchor address derived from the fingerprint
+/// program and the SHA-256 of the ID. In program-account mode the record is
+/// stored in that account; in memo mode an SPL memo carrying the record is
+/// sent with the address attached, and the oldest valid memo wins. Memo
+/// records can be posted by anyone, so callers that need ownership guarantees
+/// should check [`AnchorRecord::authority`].
+pub struct SolanaAnchorClient {
+    rpc: solana_client::nonblocking::rpc_client::RpcClient,
+    commitment: CommitmentConfig,
+    program_id: Pubkey,
+    memo_program_id: Pubkey,
+    mode: AnchorMode,
+}
+
+impl SolanaAnchorClient {
+    /// Create a client for the given configuration.
+    pub fn new(config: SolanaConfig) -> std::result::Result<Self, AnchorError> {
+        let commitment = CommitmentConfig::from_str(&config.commitment)
+            .map_err(|_| AnchorError::InvalidConfig(format!("unknown commitment '{}'", config.commitment)))?;
+        let program_id = Pubkey::from_str(&config.program_id)
+            .map_err(|e| AnchorError::InvalidConfig(format!("invalid program ID: {}", e)))?;
+        let memo_program_id = Pubkey::from_str(MEMO_PROGRAM_ID)
+            .map_err(|e| AnchorError::InvalidConfig(format!("invalid memo program ID: {}", e)))?;
+
+        Ok(Self {
+            rpc: solana_client::nonblocking::rpc_client::RpcClient::new_with_commitment(
+                config.rpc_url.clone(),
+                commitment,
+            ),
+            commitment,
+            program_id,
+            memo_program_id,
+            mode: config.anchor_mode,
+        })
+    }
+
+    /// Create a client for an RPC URL with default settings.
+    pub fn with_rpc_url(rpc_url: &str) -> std::result::Result<Self, AnchorError> {
+        Self::new(SolanaConfig {
+            rpc_url: rpc_url.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Anchor address for a content ID.
+    pub fn anchor_address(&self, content_id: &str) -> (Pubkey, u8) {
+        derive_anchor_address(&self.program_id, content_id)
+    }
+
+    /// Anchor a fingerprint's hash, version, duration and constellation
+    /// summary for a content ID.
+    pub async fn register_fingerprint(
+        &self,
+        content_id: &str,
+        fingerprint: &AudioFingerprint,
+        payer: &Keypair,
+    ) -> std::result::Result<AnchorReceipt, AnchorError> {
+        validate_content_id(content_id)?;
+        let hash = fingerprint_hash_bytes(fingerprint)?;
+        let summary = Fingerprinter::new().summarize(fingerprint);
+        let (address, bump) = self.anchor_address(content_id);
+        let mode = self.resolve_mode().await?;
+
+        info!("Anchoring fingerprint for '{}' at {} ({:?})", content_id, address, mode);
+
+        let instruction = match mode {
+            AnchorMode::ProgramAccount => instructions::anchor_fingerprint(
+                &self.program_id,
+                &address,
+                &payer.pubkey(),
+                content_id,
+                &hash,
+                fingerprint.version,
+                fingerprint.duration_secs,
+                summary.as_ref(),
+                bump,
+            ),
+            _ => Instruction {
+                program_id: self.memo_program_id,
+                accounts: vec![AccountMeta::new_readonly(address, false)],
+                data: encode_anchor_memo(&AnchorMemo {
+                    content_id: content_id.to_string(),
+                    fingerprint_hash: hash,
+                    fingerprint_version: fingerprint.version,
+                    duration_secs: fingerprint.duration_secs,
+                    summary,
+                })
+                .into_bytes(),
+            },
+        };
+
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[payer],
+            blockhash,
+        );
+        let signature = self.rpc.send_and_confirm_transaction(&transaction).await?;
+
+        debug!("Anchored '{}' in transaction {}", content_id, signature);
+
+        Ok(AnchorReceipt {
+            signature: signature.to_string(),
+            address: address.to_string(),
+            mode,
+        })
+    }
+
+    /// Fetch the on-chain record for a content ID.
+    ///
+    /// Program accounts take precedence over memos when both exist.
+    pub async fn fetch_record(
+        &self,
+        content_id: &str,
+    ) -> std::result::Result<Option<AnchorRecord>, AnchorError> {
+        validate_content_id(content_id)?;
+        let (address, _) = self.anchor_address(content_id);
+
+        if self.mode != AnchorMode::Memo {
+            let account = self.rpc
+                .get_account_with_commitment(&address, self.commitment)
+                .await?
+                .value;
+
+            if let Some(account) = account {
+                if account.owner != self.program_id {
+                    return Err(AnchorError::MalformedRecord(format!(
+                        "account {} is owned by {}, not the fingerprint program",
+                        address, account.owner
+                    )));
+                }
+                return decode_anchor_record(&account.data).map(Some);
+            }
+        }
+
+        if self.mode == AnchorMode::ProgramAccount {
+            return Ok(None);
+        }
+
+        self.fetch_memo_record(content_id, &address).await
+    }
+
+    /// Compare a fingerprint with the record anchored for a content ID.
+    pub async fn verify_fingerprint(
+        &self,
+        content_id: &str,
+        fingerprint: &AudioFingerprint,
+    ) -> std::result::Result<AnchorVerification, AnchorError> {
+        let hash = fingerprint_hash_bytes(fingerprint)?;
+        let record = self.fetch_record(content_id).await?;
+
+        let hash_matches = record.as_ref().is_some_and(|r| r.fingerprint_hash == hash);
+        let version_matches = record.as_ref().is_some_and(|r| r.fingerprint_version == fingerprint.version);
+
+        Ok(AnchorVerification {
+            record,
+            hash_matches,
+            version_matches,
+            verified: hash_matches && version_matches,
+            similarity: None,
+        })
+    }
+
+    /// Compare a fingerprint with the record for a content ID, tolerating
+    /// transcoding.
+    ///
+    /// The fingerprint verifies when its hash matches the record, or when it
+    /// matches the anchored constellation summary with at least `threshold`
+    /// similarity (0-1). Records without a summary only verify exactly.
+    pub async fn verify_fingerprint_perceptual(
+        &self,
+        content_id: &str,
+        fingerprint: &AudioFingerprint,
+        threshold: f32,
+    ) -> std::result::Result<AnchorVerification, AnchorError> {
+        let mut verification = self.verify_fingerprint(content_id, fingerprint).await?;
+
+        let summary = verification.record.as_ref().and_then(|r| r.summary.as_ref());
+        if let (Some(summary), true) = (summary, verification.version_matches) {
+            let similarity = Fingerprinter::new()
+                .match_summary(summary, fingerprint)
+                .map_or(0.0, |result| result.similarity);
+            verification.similarity = Some(similarity);
+            verification.verified |= similarity >= threshold;
+        }
+
+        Ok(verification)
+    }
+
+    /// Pick the concrete storage mode for a registration.
+    async fn resolve_mode(&self) -> std::result::Result<AnchorMode, AnchorError> {
+        if self.mode == AnchorMode::Memo {
+            return Ok(AnchorMode::Memo);
+        }
+
+        let deployed = self.rpc
+            .get_account_with_commitment(&self.program_id, self.commitment)
+            .await?
+            .value
+            .is_some_and(|account| account.executable);
+
+        match (self.mode, deployed) {
+            (_, true) => Ok(AnchorMode::ProgramAccount),
+            (AnchorMode::ProgramAccount, false) => Err(AnchorError::ProgramNotDeployed(self.program_id.to_string())),
+            _ => {
+                debug!("Fingerprint program {} not deployed, falling back to memo", self.program_id);
+                Ok(AnchorMode::Memo)
+            }
+        }
+    }
+
+    /// Find the oldest successful anchor memo referencing the anchor address.
+    async fn fetch_memo_record(
+        &self,
+        content_id: &str,
+        address: &Pubkey,
+    ) -> std::result::Result<Option<AnchorRecord>, AnchorError> {
+        use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+
+        // History is returned newest first, so the last match seen is the oldest
+        let mut oldest = None;
+        let mut before = None;
+        loop {
+            let page = self.rpc
+                .get_signatures_for_address_with_config(
+                    address,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: Some(SIGNATURE_PAGE_LIMIT),
+                        commitment: Some(self.commitment),
+                    },
+                )
+                .await?;
+
+            for entry in &page {
+                if entry.err.is_some() {
+                    continue;
+                }
+                let anchored = entry.memo.as_deref()
+                    .into_iter()
+                    .flat_map(split_rpc_memos)
+                    .find_map(|memo| decode_anchor_memo(memo).filter(|m| m.content_id == content_id));
+                if let Some(memo) = anchored {
+                    oldest = Some((entry.signature.clone(), entry.slot, entry.block_time, memo));
+                }
+            }
+
+            if page.len() < SIGNATURE_PAGE_LIMIT {
+                break;
+            }
+            before = page.last()
+                .map(|entry| Signature::from_str(&entry.signature))
+                .transpose()
+                .map_err(|e| AnchorError::MalformedRecord(format!("invalid signature in history: {}", e)))?;
+        }
+
+        let Some((signature, slot, anchored_at, memo)) = oldest else {
+            return Ok(None);
+        };
+
+        Ok(Some(AnchorRecord {
+            content_id: memo.content_id,
+            authority: self.transaction_fee_payer(&signature).await?.to_string(),
+            fingerprint_hash: memo.fingerprint_hash,
+            fingerprint_version: memo.fingerprint_version,
+            duration_secs: memo.duration_secs,
+            slot,
+            anchored_at,
+            mode: AnchorMode::Memo,
+            signature: Some(signature),
+            summary: memo.summary,
+        }))
+    }
+
+    /// Fee payer (first account key) of a confirmed transaction.
+    async fn transaction_fee_payer(&self, signature: &str) -> std::result::Result<Pubkey, AnchorError> {
+        use solana_client::rpc_config::RpcTransactionConfig;
+        use solana_transaction_status::UiTransactionEncoding;
+
+        let signature = Signature::from_str(signature)
+            .map_err(|e| AnchorError::MalformedRecord(format!("invalid signature: {}", e)))?;
+        let transaction = self.rpc
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(self.commitment),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await?;
+
+        transaction.transaction.transaction
+            .decode()
+            .and_then(|tx| tx.message.static_account_keys().first().copied())
+            .ok_or_else(|| AnchorError::MalformedRecord(format!("could not decode transaction {}", signature)))
+    }
+}
+
+/// Derive the anchor address for a content ID.
+pub fn derive_anchor_address(program_id: &Pubkey, content_id: &str) -> (Pubkey, u8) {
+    let content_key = ring::digest::digest(&ring::digest::SHA256, content_id.as_bytes());
+    Pubkey::find_program_address(&[b"anchor", content_key.as_ref()], program_id)
+}
+
+fn validate_content_id(content_id: &str) -> std::result::Result<(), AnchorError> {
+    if content_id.is_empty() {
+        return Err(AnchorError::InvalidContentId("content ID is empty".to_string()));
+    }
+    if content_id.len() > MAX_CONTENT_ID_LEN {
+        return Err(AnchorError::InvalidContentId(format!(
+            "content ID is {} bytes (max {})",
+            content_id.len(),
+            MAX_CONTENT_ID_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn fingerprint_hash_bytes(fingerprint: &AudioFingerprint) -> std::result::Result<[u8; 32], AnchorError> {
+    parse_fingerprint_hash(&fingerprint.hash).map_err(|e| AnchorError::InvalidHash(e.to_string()))
+}
+
+/// Anchor record fields carried by a memo.
+#[derive(Debug, Clone, PartialEq)]
+struct AnchorMemo {
+    content_id: String,
+    fingerprint_hash: [u8; 32],
+    fingerprint_version: u32,
+    duration_secs: f64,
+    summary: Option<ConstellationSummary>,
+}
+
+/// Encode an anchor memo. The summary is unpadded URL-safe base64 (empty
+/// when absent), and the content ID comes last so it may contain `:`.
+fn encode_anchor_memo(memo: &AnchorMemo) -> String {
+    use base64::Engine;
+
+    let summary = memo.summary.as_ref()
+        .map(|s| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(s.to_bytes()))
+        .unwrap_or_default();
+
+    format!(
+        "{}:{}:{}:{}:{}:{}:{}",
+        ANCHOR_MEMO_PREFIX,
+        ANCHOR_LAYOUT_VERSION,
+        hex::encode(&memo.fingerprint_hash),
+        memo.fingerprint_version,
+        memo.duration_secs,
+        summary,
+        memo.content_id
+    )
+}
+
+fn decode_anchor_memo(memo: &str) -> Option<AnchorMemo> {
+    use base64::Engine;
+
+    let mut parts = memo.splitn(7, ':');
+    if parts.next()? != ANCHOR_MEMO_PREFIX {
+        return None;
+    }
+    let layout = parts.next()?.parse::<u8>().ok()?;
+    if layout == 0 || layout > ANCHOR_LAYOUT_VERSION {
+        return None;
+    }
+
+    let fingerprint_hash = parse_fingerprint_hash(parts.next()?).ok()?;
+    let fingerprint_version = parts.next()?.parse().ok()?;
+    let duration_secs = parts.next()?.parse().ok()?;
+
+    // Version 1 memos end with the content ID, which may itself contain `:`
+    let (summary, content_id) = if layout == 1 {
+        (None, parts.collect::<Vec<_>>().join(":"))
+    } else {
+        let summary = match parts.next()? {
+            "" => None,
+            encoded => {
+                let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(encoded).ok()?;
+                Some(ConstellationSummary::from_bytes(&bytes)?)
+            }
+        };
+        (summary, parts.next()?.to_string())
+    };
+
+    Some(AnchorMemo { content_id, fingerprint_hash, fingerprint_version, duration_secs, summary })
+}
+
+/// Split the memo field of a signature status into individual memos.
+///
+/// RPC nodes report each memo as `[len] text` and join them with `; `. Input
+/// without length prefixes is returned as a single memo.
+fn split_rpc_memos(field: &str) -> Vec<&str> {
+    let mut memos = Vec::new();
+    let mut rest = field;
+
+    while let Some(body) = rest.strip_prefix('[') {
+        let Some((len, tail)) = body.split_once("] ") else { break };
+        let Some(memo) = len.parse::<usize>().ok().and_then(|len| tail.get(..len)) else { break };
+
+        memos.push(memo);
+        rest = tail[memo.len()..].trim_start_matches("; ");
+    }
+
+    if memos.is_empty() && !field.is_empty() {
+        memos.push(field);
+    }
+    memos
+}
+
+/// Decode an anchor record account written by the fingerprint program.
+///
+/// ```text
+/// magic: [u8; 8] | authority: Pubkey | fingerprint_hash: [u8; 32] |
+/// fingerprint_version: u32 | duration_secs: f64 | slot: u64 |
+/// anchored_at: i64 | content_id: u32 length + UTF-8 |
+/// summary: u32 length + ConstellationSummary bytes (optional)
+/// ```
+fn decode_anchor_record(data: &[u8]) -> std::result::Result<AnchorRecord, AnchorError> {
+    fn take<'a>(data: &mut &'a [u8], len: usize) -> std::result::Result<&'a [u8], AnchorError> {
+        if data.len() < len {
+            return Err(AnchorError::MalformedRecord("account data is truncated".to_string()));
+        }
+        let (head, tail) = data.split_at(len);
+        *data = tail;
+        Ok(head)
+    }
+
+    let mut data = data;
+    if take(&mut data, 8)? != ANCHOR_RECORD_MAGIC {
+        return Err(AnchorError::MalformedRecord("missing anchor record header".to_string()));
+    }
+
+    let authority = Pubkey::try_from(take(&mut data, 32)?)
+        .map_err(|_| AnchorError::MalformedRecord("invalid authority".to_string()))?;
+    let mut fingerprint_hash = [0u8; 32];
+    fingerprint_hash.copy_from_slice(take(&mut data, 32)?);
+    let fingerprint_version = u32::from_le_bytes(take(&mut data, 4)?.try_into().unwrap());
+    let duration_secs = f64::from_le_bytes(take(&mut data, 8)?.try_into().unwrap());
+    let slot = u64::from_le_bytes(take(&mut data, 8)?.try_into().unwrap());
+    let anchored_at = i64::from_le_bytes(take(&mut data, 8)?.try_into().unwrap());
+    let id_len = u32::from_le_bytes(take(&mut data, 4)?.try_into().unwrap()) as usize;
+    let content_id = std::str::from_utf8(take(&mut data, id_len)?)
+        .map_err(|_| AnchorError::MalformedRecord("content ID is not UTF-8".to_string()))?
+        .to_string();
+
+    // Records anchored before summaries end here; an all-zero tail is padding
+    let summary = match take(&mut data, 4) {
+        Ok(len) => {
+            let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+            if len == 0 {
+                None
+            } else {
+                let summary = ConstellationSummary::from_bytes(take(&mut data, len)?)
+                    .ok_or_else(|| AnchorError::MalformedRecord("invalid constellation summary".to_string()))?;
+                Some(summary)
+            }
+        }
+        Err(_) => None,
+    };
+
+    Ok(AnchorRecord {
+        content_id,
+        authority: authority.to_string(),
+        fingerprint_hash,
+        fingerprint_version,
+        duration_secs,
+        slot,
+        anchored_at: Some(anchored_at),
+        mode: AnchorMode::ProgramAccount,
+        signature: None,
+        summary,
+    })
+}
+
 /// Anchor program instruction builders (for use with anchor-client).
 #[cfg(feature = "solana")]
 pub mod instructions {
@@ -428,6 +1064,42 @@ pub mod instructions {
             data,
         }
     }
+
+    /// Build instruction to anchor a fingerprint for a content ID.
+    #[allow(clippy::too_many_arguments)]
+    pub fn anchor_fingerprint(
+        program_id: &Pubkey,
+        anchor_pda: &Pubkey,
+        authority: &Pubkey,
+        content_id: &str,
+        fingerprint_hash: &[u8; 32],
+        fingerprint_version: u32,
+        duration_secs: f64,
+        summary: Option<&ConstellationSummary>,
+        bump: u8,
+    ) -> Instruction {
+        let summary = summary.map(ConstellationSummary::to_bytes).unwrap_or_default();
+
+        let mut data = vec![ANCHOR_DISCRIMINATOR];
+        data.extend_from_slice(fingerprint_hash);
+        data.extend_from_slice(&fingerprint_version.to_le_bytes());
+        data.extend_from_slice(&duration_secs.to_le_bytes());
+        data.extend_from_slice(&(content_id.len() as u32).to_le_bytes());
+        data.extend_from_slice(content_id.as_bytes());
+        data.extend_from_slice(&(summary.len() as u32).to_le_bytes());
+        data.extend_from_slice(&summary);
+        data.push(bump);
+
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(*anchor_pda, false),
+                AccountMeta::new(*authority, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+            data,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -456,4 +1128,140 @@ mod tests {
         let encoded = hex::encode(&bytes);
         assert_eq!(encoded, "01234567");
     }
+
+    fn test_summary() -> ConstellationSummary {
+        use crate::fingerprint::Landmark;
+
+        ConstellationSummary {
+            version: 2,
+            landmarks: (0..24).map(|i| Landmark { key: i * 1000, anchor_time: i * 7 }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_anchor_memo_roundtrip() {
+        let memo = AnchorMemo {
+            content_id: "show:s01e02".to_string(),
+            fingerprint_hash: [0xab; 32],
+            fingerprint_version: 2,
+            duration_secs: 1234.5,
+            summary: Some(test_summary()),
+        };
+        let encoded = encode_anchor_memo(&memo);
+        assert_eq!(decode_anchor_memo(&encoded), Some(memo.clone()));
+
+        let without_summary = AnchorMemo { summary: None, ..memo };
+        assert_eq!(decode_anchor_memo(&encode_anchor_memo(&without_summary)), Some(without_summary));
+
+        assert!(decode_anchor_memo("hello world").is_none());
+        assert!(decode_anchor_memo(&encoded.replacen(":2:", ":9:", 1)).is_none());
+    }
+
+    #[test]
+    fn test_decode_version_1_anchor_memo() {
+        let memo = format!("kino-anchor:1:{}:2:60:show:s01e02", "cd".repeat(32));
+
+        let decoded = decode_anchor_memo(&memo).unwrap();
+        assert_eq!(decoded.content_id, "show:s01e02");
+        assert_eq!(decoded.fingerprint_hash, [0xcd; 32]);
+        assert_eq!(decoded.duration_secs, 60.0);
+        assert!(decoded.summary.is_none());
+    }
+
+    #[test]
+    fn test_split_rpc_memos() {
+        assert_eq!(split_rpc_memos("[5] hello"), vec!["hello"]);
+        assert_eq!(split_rpc_memos("[4] a; b; [3] xyz"), vec!["a; b", "xyz"]);
+        assert_eq!(split_rpc_memos("plain memo"), vec!["plain memo"]);
+        assert!(split_rpc_memos("").is_empty());
+    }
+
+    #[test]
+    fn test_decode_anchor_record() {
+        let authority = Pubkey::new_unique();
+        let mut data = ANCHOR_RECORD_MAGIC.to_vec();
+        data.extend_from_slice(&authority.to_bytes());
+        data.extend_from_slice(&[7u8; 32]);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&90.25f64.to_le_bytes());
+        data.extend_from_slice(&4242u64.to_le_bytes());
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        data.extend_from_slice(&8u32.to_le_bytes());
+        data.extend_from_slice(b"video_42");
+
+        let record = decode_anchor_record(&data).unwrap();
+        assert_eq!(record.content_id, "video_42");
+        assert_eq!(record.authority, authority.to_string());
+        assert_eq!(record.fingerprint_hash, [7u8; 32]);
+        assert_eq!(record.fingerprint_version, 2);
+        assert_eq!(record.slot, 4242);
+        assert_eq!(record.anchored_at, Some(1_700_000_000));
+        assert_eq!(record.mode, AnchorMode::ProgramAccount);
+        assert!(record.summary.is_none());
+
+        let summary = test_summary().to_bytes();
+        let mut with_summary = data.clone();
+        with_summary.extend_from_slice(&(summary.len() as u32).to_le_bytes());
+        with_summary.extend_from_slice(&summary);
+        assert_eq!(decode_anchor_record(&with_summary).unwrap().summary, Some(test_summary()));
+
+        assert!(matches!(decode_anchor_record(&data[..40]), Err(AnchorError::MalformedRecord(_))));
+        assert!(matches!(decode_anchor_record(&[0u8; 128]), Err(AnchorError::MalformedRecord(_))));
+    }
+
+    #[test]
+    fn test_anchor_address_and_content_id() {
+        let program_id = Pubkey::new_unique();
+        assert_eq!(derive_anchor_address(&program_id, "a"), derive_anchor_address(&program_id, "a"));
+        assert_ne!(derive_anchor_address(&program_id, "a").0, derive_anchor_address(&program_id, "b").0);
+
+        assert!(validate_content_id("video_1").is_ok());
+        assert!(matches!(validate_content_id(""), Err(AnchorError::InvalidContentId(_))));
+        let long = "x".repeat(MAX_CONTENT_ID_LEN + 1);
+        assert!(matches!(validate_content_id(&long), Err(AnchorError::InvalidContentId(_))));
+    }
+
+    /// Runs against a local `solana-test-validator` (or `KINO_SOLANA_RPC_URL`).
+    #[tokio::test]
+    #[ignore = "requires solana-test-validator"]
+    async fn test_anchor_roundtrip_on_validator() {
+        let rpc_url = std::env::var("KINO_SOLANA_RPC_URL")
+            .unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+        let client = SolanaAnchorClient::with_rpc_url(&rpc_url).unwrap();
+
+        let payer = Keypair::new();
+        let airdrop = client.rpc.request_airdrop(&payer.pubkey(), 1_000_000_000).await.unwrap();
+        while !client.rpc.confirm_transaction(&airdrop).await.unwrap() {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        }
+
+        let content_id = format!("test_{}", payer.pubkey());
+        let fingerprint = AudioFingerprint {
+            hash: "11".repeat(32),
+            version: 2,
+            algorithm: Default::default(),
+            sample_rate: 22050,
+            points: Vec::new(),
+            subfingerprints: Vec::new(),
+            chromaprint: None,
+            duration_secs: 61.5,
+            low_entropy: false,
+        };
+
+        assert!(client.fetch_record(&content_id).await.unwrap().is_none());
+
+        let receipt = client.register_fingerprint(&content_id, &fingerprint, &payer).await.unwrap();
+        assert_ne!(receipt.mode, AnchorMode::Auto);
+
+        let record = client.fetch_record(&content_id).await.unwrap().unwrap();
+        assert_eq!(record.authority, payer.pubkey().to_string());
+        assert_eq!(record.duration_secs, 61.5);
+        assert!(record.slot > 0);
+
+        assert!(client.verify_fingerprint(&content_id, &fingerprint).await.unwrap().verified);
+
+        let tampered = AudioFingerprint { hash: "22".repeat(32), ..fingerprint };
+        let verification = client.verify_fingerprint(&content_id, &tampered).await.unwrap();
+        assert!(!verification.verified && !verification.hash_matches && verification.version_matches);
+    }
 }
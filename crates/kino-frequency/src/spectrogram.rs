@@ -0,0 +1,299 @@
+//! Spectrogram image rendering.
+//!
+//! Renders the magnitude spectrogram from [`FrequencyAnalyzer`] as an RGB
+//! image, mainly so moderators can eyeball flagged uploads and developers can
+//! debug analysis results.
+
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use image::{Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use crate::fft::FrequencyAnalyzer;
+
+/// Viridis control points, evenly spaced from 0.0 to 1.0.
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+/// Color mapping applied to normalized dB values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Colormap {
+    /// Perceptually uniform purple-to-yellow map.
+    #[default]
+    Viridis,
+    /// Black (quiet) to white (loud).
+    Grayscale,
+}
+
+impl Colormap {
+    /// Map a value in [0, 1] to a color.
+    pub fn color(&self, value: f32) -> Rgb<u8> {
+        let value = value.clamp(0.0, 1.0);
+        match self {
+            Colormap::Grayscale => {
+                let v = (value * 255.0).round() as u8;
+                Rgb([v, v, v])
+            }
+            Colormap::Viridis => {
+                let pos = value * (VIRIDIS.len() - 1) as f32;
+                let idx = (pos.floor() as usize).min(VIRIDIS.len() - 2);
+                let frac = pos - idx as f32;
+                let (a, b) = (VIRIDIS[idx], VIRIDIS[idx + 1]);
+                Rgb(std::array::from_fn(|c| {
+                    (a[c] as f32 + (b[c] as f32 - a[c] as f32) * frac).round() as u8
+                }))
+            }
+        }
+    }
+}
+
+/// Options for [`FrequencyAnalyzer::render_spectrogram`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpectrogramOptions {
+    /// Output width in pixels (time axis).
+    pub width: u32,
+    /// Output height in pixels (frequency axis).
+    pub height: u32,
+    /// Magnitudes this far below the peak (in dB, negative) render as the
+    /// bottom of the colormap.
+    pub db_floor: f32,
+    /// Colormap to apply.
+    pub colormap: Colormap,
+    /// Use a logarithmic frequency axis instead of a linear one.
+    pub log_frequency: bool,
+    /// Start of the rendered time range in seconds.
+    pub start_secs: Option<f64>,
+    /// End of the rendered time range in seconds.
+    pub end_secs: Option<f64>,
+}
+
+impl Default for SpectrogramOptions {
+    fn default() -> Self {
+        Self {
+            width: 1024,
+            height: 512,
+            db_floor: -80.0,
+            colormap: Colormap::Viridis,
+            log_frequency: false,
+            start_secs: None,
+            end_secs: None,
+        }
+    }
+}
+
+impl FrequencyAnalyzer {
+    /// Render a spectrogram of the samples as an RGB image.
+    ///
+    /// Time runs left to right and frequency bottom to top. Each pixel shows
+    /// the loudest bin it covers, so short transients survive downscaling.
+    pub fn render_spectrogram(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+        options: &SpectrogramOptions,
+    ) -> Result<RgbImage> {
+        if options.width == 0 || options.height == 0 {
+            bail!("Spectrogram dimensions must be non-zero");
+        }
+        if options.db_floor >= 0.0 {
+            bail!("Spectrogram dB floor must be negative");
+        }
+
+        let start = options
+            .start_secs
+            .map_or(0, |s| (s.max(0.0) * sample_rate as f64) as usize)
+            .min(samples.len());
+        let end = options
+            .end_secs
+            .map_or(samples.len(), |s| (s.max(0.0) * sample_rate as f64) as usize)
+            .min(samples.len());
+        if end <= start {
+            bail!("Spectrogram time range is empty");
+        }
+        let samples = &samples[start..end];
+        if samples.len() < self.fft_size() {
+            bail!("Not enough samples for a spectrogram. Need at least {} samples.", self.fft_size());
+        }
+
+        let frames = self.compute_spectrogram(samples)?;
+        let num_bins = frames[0].len();
+
+        // Collapse frames into columns, keeping the peak magnitude per bin
+        let width = options.width as usize;
+        let columns: Vec<Vec<f32>> = (0..width)
+            .map(|x| {
+                let (lo, hi) = span(x, width, frames.len());
+                let mut column = vec![0.0f32; num_bins];
+                for frame in &frames[lo..hi] {
+                    for (c, &m) in column.iter_mut().zip(frame) {
+                        *c = c.max(m);
+                    }
+                }
+                column
+            })
+            .collect();
+
+        let peak = columns
+            .iter()
+            .flat_map(|c| c.iter().copied())
+            .fold(0.0f32, f32::max);
+        let reference = if peak > 0.0 { peak } else { 1.0 };
+
+        let rows = row_bins(options.height as usize, num_bins, options.log_frequency);
+        let mut image = RgbImage::new(options.width, options.height);
+
+        for (x, column) in columns.iter().enumerate() {
+            for (y, &(lo, hi)) in rows.iter().enumerate() {
+                let magnitude = column[lo..hi].iter().copied().fold(0.0f32, f32::max);
+                let db = 20.0 * (magnitude / reference).max(1e-10).log10();
+                let value = 1.0 - db.max(options.db_floor) / options.db_floor;
+                // Row 0 is the top of the image, i.e. the highest frequency
+                let row = options.height as usize - 1 - y;
+                image.put_pixel(x as u32, row as u32, options.colormap.color(value));
+            }
+        }
+
+        Ok(image)
+    }
+
+    /// Render a spectrogram and write it to `path` as a PNG.
+    pub fn write_spectrogram_png(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+        options: &SpectrogramOptions,
+        path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let image = self.render_spectrogram(samples, sample_rate, options)?;
+        image
+            .save_with_format(path, image::ImageFormat::Png)
+            .with_context(|| format!("Failed to write spectrogram to {}", path.display()))
+    }
+}
+
+/// Range of `total` items covered by output slot `idx` of `slots`, never empty.
+fn span(idx: usize, slots: usize, total: usize) -> (usize, usize) {
+    let lo = (idx * total / slots).min(total - 1);
+    let hi = ((idx + 1) * total / slots).clamp(lo + 1, total);
+    (lo, hi)
+}
+
+/// FFT bin range for each image row, lowest frequency first.
+///
+/// The log scale starts at bin 1 since DC has no place on a log axis.
+fn row_bins(height: usize, num_bins: usize, log_frequency: bool) -> Vec<(usize, usize)> {
+    if !log_frequency || num_bins < 3 {
+        return (0..height).map(|y| span(y, height, num_bins)).collect();
+    }
+
+    let max = num_bins as f32;
+    (0..height)
+        .map(|y| {
+            let lo = max.powf(y as f32 / height as f32) as usize;
+            let hi = max.powf((y + 1) as f32 / height as f32) as usize;
+            let lo = lo.clamp(1, num_bins - 1);
+            (lo, hi.clamp(lo + 1, num_bins))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_sweep(start_hz: f32, end_hz: f32, sample_rate: u32, duration_secs: f32) -> Vec<f32> {
+        let num_samples = (sample_rate as f32 * duration_secs) as usize;
+        let rate = (end_hz - start_hz) / duration_secs;
+        (0..num_samples)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                (2.0 * std::f32::consts::PI * (start_hz * t + 0.5 * rate * t * t)).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render_sweep_png() {
+        let sample_rate = 22050;
+        let samples = sine_sweep(100.0, 8000.0, sample_rate, 2.0);
+        let analyzer = FrequencyAnalyzer::new(1024, 256);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sweep.png");
+        let options = SpectrogramOptions {
+            width: 200,
+            height: 100,
+            log_frequency: true,
+            ..Default::default()
+        };
+        analyzer.write_spectrogram_png(&samples, sample_rate, &options, &path).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let decoded = image::open(&path).unwrap().to_rgb8();
+        assert_eq!(decoded.dimensions(), (200, 100));
+        let corner = *decoded.get_pixel(0, 0);
+        assert!(decoded.pixels().any(|p| *p != corner));
+    }
+
+    #[test]
+    fn test_sweep_rises_over_time() {
+        let sample_rate = 22050;
+        let samples = sine_sweep(200.0, 8000.0, sample_rate, 2.0);
+        let analyzer = FrequencyAnalyzer::new(1024, 256);
+        let options = SpectrogramOptions {
+            width: 50,
+            height: 100,
+            colormap: Colormap::Grayscale,
+            ..Default::default()
+        };
+        let image = analyzer.render_spectrogram(&samples, sample_rate, &options).unwrap();
+
+        // Brightest row per column; row 0 is the top of the image
+        let loudest_row = |x: u32| (0..100).max_by_key(|&y| image.get_pixel(x, y)[0]).unwrap();
+        assert!(loudest_row(45) < loudest_row(5));
+    }
+
+    #[test]
+    fn test_time_range_crop() {
+        let sample_rate = 22050;
+        let samples = sine_sweep(200.0, 8000.0, sample_rate, 2.0);
+        let analyzer = FrequencyAnalyzer::new(1024, 256);
+
+        let options = SpectrogramOptions {
+            start_secs: Some(1.5),
+            end_secs: Some(1.0),
+            ..Default::default()
+        };
+        assert!(analyzer.render_spectrogram(&samples, sample_rate, &options).is_err());
+
+        let options = SpectrogramOptions {
+            width: 10,
+            height: 10,
+            start_secs: Some(0.5),
+            end_secs: Some(1.0),
+            ..Default::default()
+        };
+        assert!(analyzer.render_spectrogram(&samples, sample_rate, &options).is_ok());
+    }
+
+    #[test]
+    fn test_colormap_endpoints() {
+        assert_eq!(Colormap::Viridis.color(0.0), Rgb(VIRIDIS[0]));
+        assert_eq!(Colormap::Viridis.color(1.0), Rgb(VIRIDIS[8]));
+        assert_eq!(Colormap::Grayscale.color(1.0), Rgb([255, 255, 255]));
+    }
+}
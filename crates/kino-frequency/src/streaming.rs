@@ -1,9 +1,16 @@
 //! Real-time streaming frequency analysis module.
 //!
+//! Framing and per-frame features come from [`kino_spectrum`], shared with
+//! kino-wasm's `KinoStreamingAnalyzer`, so both report the same frames for
+//! the same samples, band plan and window.
+//!
 //! This module provides capabilities for live audio analysis:
 //! - Frame-by-frame processing for low latency
 //! - Rolling window statistics
+//! - Spectral-flux onset detection and tempo tracking
+//! - Voice activity detection ([`crate::vad`])
 //! - Event-driven analysis callbacks
+//! - Backpressured async worker ([`AsyncStreamAnalyzer`])
 //! - Integration with media streaming pipelines
 //!
 //! # Usage
@@ -22,6 +29,9 @@
 //!         AnalysisEvent::BeatDetected { timestamp, strength } => {
 //!             println!("Beat at {:.2}s (strength: {:.2})", timestamp, strength);
 //!         }
+//!         AnalysisEvent::TempoChanged { bpm, .. } => {
+//!             println!("Tempo: {:.1} BPM", bpm);
+//!         }
 //!         _ => {}
 //!     }
 //! });
@@ -33,14 +43,21 @@
 //! ```
 
 use std::collections::VecDeque;
-use std::sync::{Arc, Mutex};
-use tracing::trace;
+use anyhow::{bail, Context, Result};
+use kino_spectrum::{FrameAnalyzer, FrameFeatures, Framer, RollingStats};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, trace};
 
-use crate::fft::FrequencyAnalyzer;
 use crate::types::*;
+use crate::vad::{VadConfig, VadEvent, VoiceActivityDetector};
 
 /// Events emitted during streaming analysis.
-#[derive(Debug, Clone)]
+///
+/// Serialized with the variant name in a snake_case `type` field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum AnalysisEvent {
     /// Dominant frequency changed significantly
     DominantChange {
@@ -55,9 +72,18 @@ pub enum AnalysisEvent {
     BeatDetected {
         /// Time of the beat in seconds
         timestamp: f64,
-        /// Beat intensity relative to average energy
+        /// Spectral flux relative to the adaptive onset threshold
         strength: f32,
     },
+    /// Rolling tempo estimate changed
+    TempoChanged {
+        /// New tempo estimate in beats per minute
+        bpm: f32,
+        /// Previous estimate, if there was one
+        previous: Option<f32>,
+        /// Time of the change in seconds
+        timestamp: f64,
+    },
     /// Spectral shift detected (e.g., song section change)
     SpectralShift {
         /// Time of the shift in seconds
@@ -77,6 +103,20 @@ pub enum AnalysisEvent {
         /// Duration of the silent period in seconds
         duration: f64,
     },
+    /// Speech started, reported once it has lasted the VAD's `min_speech_secs`
+    SpeechStart {
+        /// Time speech began in seconds
+        timestamp: f64,
+    },
+    /// Speech ended, reported once the VAD's `hangover_secs` have passed
+    SpeechEnd {
+        /// Time speech ended in seconds
+        timestamp: f64,
+        /// Duration of the speech in seconds
+        duration: f64,
+        /// Mean speech confidence (0-1)
+        confidence: f32,
+    },
     /// New frame analyzed
     FrameAnalyzed {
         /// Frame timestamp in seconds
@@ -87,7 +127,7 @@ pub enum AnalysisEvent {
 }
 
 /// Single frame of analysis data.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisFrame {
     /// Frame timestamp in seconds
     pub timestamp: f64,
@@ -103,6 +143,8 @@ pub struct AnalysisFrame {
     pub rms_energy: f32,
     /// Zero crossing rate
     pub zcr: f32,
+    /// Spectral flux (summed positive magnitude change since the previous frame)
+    pub spectral_flux: f32,
 }
 
 /// Configuration for streaming analyzer.
@@ -112,16 +154,30 @@ pub struct StreamConfig {
     pub fft_size: usize,
     /// Hop size between frames
     pub hop_size: usize,
+    /// Window applied to each frame
+    pub window: WindowFunction,
+    /// Bands of each frame's band energies
+    pub band_plan: BandPlan,
     /// Sample rate in Hz
     pub sample_rate: u32,
     /// History length for rolling statistics (in frames)
     pub history_length: usize,
     /// Silence threshold (RMS energy below this is silence)
     pub silence_threshold: f32,
-    /// Beat detection threshold
+    /// Onset threshold multiplier `k` in `median + k * MAD` of recent spectral flux
     pub beat_threshold: f32,
+    /// Length of the sliding window used for the adaptive onset threshold (seconds)
+    pub onset_window_secs: f64,
+    /// Minimum time between two reported beats (seconds)
+    pub min_beat_interval_secs: f64,
+    /// Minimum onset flux as a fraction of the frame's total spectral magnitude
+    pub min_relative_flux: f32,
+    /// Minimum tempo change in BPM to trigger a TempoChanged event
+    pub tempo_change_threshold: f32,
     /// Minimum frequency change to trigger DominantChange event
     pub frequency_change_threshold: f32,
+    /// Voice activity detection for SpeechStart/SpeechEnd events (`None` disables it)
+    pub vad: Option<VadConfig>,
 }
 
 impl Default for StreamConfig {
@@ -129,36 +185,72 @@ impl Default for StreamConfig {
         Self {
             fft_size: 2048,
             hop_size: 512,
+            window: WindowFunction::Hann,
+            band_plan: BandPlan::standard(),
             sample_rate: 44100,
             history_length: 100,
             silence_threshold: 0.01,
             beat_threshold: 1.5,
+            onset_window_secs: 1.0,
+            min_beat_interval_secs: 0.1, // At most 600 BPM
+            min_relative_flux: 0.05,
+            tempo_change_threshold: 2.0,
             frequency_change_threshold: 50.0, // Hz
+            vad: Some(VadConfig::default()),
         }
     }
 }
 
+/// Number of recent onsets kept for tempo estimation.
+const TEMPO_ONSET_HISTORY: usize = 24;
+
+/// Tempo estimates are folded into this BPM range to avoid octave errors.
+const TEMPO_RANGE: (f32, f32) = (60.0, 180.0);
+
+/// Onset detection state for the most recent frame, awaiting peak confirmation.
+#[derive(Debug, Clone, Copy)]
+struct FluxPoint {
+    timestamp: f64,
+    flux: f32,
+    threshold: f32,
+    audible: bool,
+}
+
 /// Event callback type.
 pub type EventCallback = Box<dyn Fn(AnalysisEvent) + Send + Sync>;
 
 /// Real-time streaming frequency analyzer.
 pub struct StreamAnalyzer {
     config: StreamConfig,
-    analyzer: FrequencyAnalyzer,
-    /// Audio sample buffer
-    buffer: VecDeque<f32>,
-    /// History of analysis frames
-    history: VecDeque<AnalysisFrame>,
-    /// Current timestamp in seconds
-    current_time: f64,
+    analyzer: FrameAnalyzer,
+    /// Splits incoming audio into overlapping frames
+    framer: Framer,
+    /// Most recent analysis frame
+    latest: Option<AnalysisFrame>,
+    /// Rolling statistics over the last `history_length` frames
+    stats: RollingStats,
     /// Previous dominant frequency for change detection
     prev_dominant: f32,
-    /// Rolling energy history for beat detection
-    energy_history: VecDeque<f32>,
+    /// Magnitude spectrum of the most recent frame
+    last_spectrum: Vec<f32>,
+    /// Rolling spectral flux history for the adaptive onset threshold
+    flux_history: VecDeque<f32>,
+    /// Flux of the frame before `pending_peak`
+    prev_flux: f32,
+    /// Previous frame, reported as a beat once confirmed as a local flux peak
+    pending_peak: Option<FluxPoint>,
+    /// Timestamp of the last reported beat
+    last_beat: Option<f64>,
+    /// Recent onset timestamps for tempo estimation
+    onset_times: VecDeque<f64>,
+    /// Current tempo estimate in BPM
+    tempo_bpm: Option<f32>,
     /// Whether currently in silence
     in_silence: bool,
     /// Silence start timestamp
     silence_start: f64,
+    /// Voice activity detector, framing the audio independently
+    vad: Option<VoiceActivityDetector>,
     /// Event callbacks
     callbacks: Vec<EventCallback>,
 }
@@ -177,18 +269,27 @@ impl StreamAnalyzer {
 
     /// Create analyzer with custom configuration.
     pub fn with_config(config: StreamConfig) -> Self {
-        let analyzer = FrequencyAnalyzer::new(config.fft_size, config.hop_size);
+        let analyzer = FrameAnalyzer::new(config.fft_size, config.sample_rate, config.window.into())
+            .with_bands(config.band_plan.bands.clone());
+        let vad = config.vad.clone().map(|vad| VoiceActivityDetector::with_config(config.sample_rate, vad));
 
         Self {
             config: config.clone(),
             analyzer,
-            buffer: VecDeque::with_capacity(config.fft_size * 2),
-            history: VecDeque::with_capacity(config.history_length),
-            current_time: 0.0,
+            framer: Framer::new(config.fft_size, config.hop_size),
+            latest: None,
+            stats: RollingStats::new(config.history_length),
             prev_dominant: 0.0,
-            energy_history: VecDeque::with_capacity(config.history_length),
+            last_spectrum: Vec::new(),
+            flux_history: VecDeque::new(),
+            prev_flux: 0.0,
+            pending_peak: None,
+            last_beat: None,
+            onset_times: VecDeque::with_capacity(TEMPO_ONSET_HISTORY),
+            tempo_bpm: None,
             in_silence: false,
             silence_start: 0.0,
+            vad,
             callbacks: Vec::new(),
         }
     }
@@ -203,64 +304,57 @@ impl StreamAnalyzer {
 
     /// Process incoming audio samples.
     /// Returns analysis frames if any were generated.
+    ///
+    /// NaN samples are treated as silence and infinities as full scale.
+    /// Speech events follow the frame events of the same chunk.
     pub fn process(&mut self, samples: &[f32]) -> Vec<AnalysisFrame> {
-        self.buffer.extend(samples);
-
         let mut frames = Vec::new();
+        let samples = replace_non_finite(samples);
+
+        // Take the framer so frames can be analyzed as borrowed slices
+        let mut framer = std::mem::take(&mut self.framer);
+        framer.process(&samples, |start, frame_samples| {
+            let features = self.analyzer.analyze(frame_samples);
+            self.stats.push(&features);
+            let frame = self.compute_flux(start, features);
+            self.detect_events(&frame);
+            self.latest = Some(frame.clone());
+            frames.push(frame);
+        });
+        self.framer = framer;
 
-        // Process complete frames
-        while self.buffer.len() >= self.config.fft_size {
-            // Extract frame
-            let frame_samples: Vec<f32> = self.buffer
-                .iter()
-                .take(self.config.fft_size)
-                .copied()
-                .collect();
-
-            // Analyze frame
-            if let Some(frame) = self.analyze_frame(&frame_samples) {
-                self.detect_events(&frame);
-                self.update_history(&frame);
-                frames.push(frame);
-            }
-
-            // Advance buffer by hop size
-            for _ in 0..self.config.hop_size {
-                self.buffer.pop_front();
-            }
-
-            // Update timestamp
-            self.current_time += self.config.hop_size as f64 / self.config.sample_rate as f64;
+        let speech = self.vad.as_mut().map(|vad| vad.push(&samples)).unwrap_or_default();
+        for event in speech {
+            self.emit_event(speech_event(event));
         }
 
         frames
     }
 
-    /// Analyze a single frame of audio.
-    fn analyze_frame(&self, samples: &[f32]) -> Option<AnalysisFrame> {
-        let analysis = self.analyzer.analyze(samples, self.config.sample_rate).ok()?;
-
-        // Find dominant frequency
-        let (dominant_idx, dominant_mag) = analysis.spectrum
-            .iter()
-            .enumerate()
-            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))?;
-
-        let freq_resolution = self.config.sample_rate as f32 / self.config.fft_size as f32;
-        let dominant_frequency = dominant_idx as f32 * freq_resolution;
-
-        // Compute RMS energy
-        let rms_energy = (samples.iter().map(|&s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+    /// Build the frame starting at sample `start`, with spectral flux
+    /// against the previous frame's spectrum.
+    fn compute_flux(&mut self, start: u64, features: FrameFeatures) -> AnalysisFrame {
+        let spectral_flux = if self.last_spectrum.len() == features.spectrum.len() {
+            features.spectrum.iter()
+                .zip(&self.last_spectrum)
+                .map(|(cur, prev)| (cur - prev).max(0.0))
+                .sum()
+        } else {
+            0.0
+        };
 
-        Some(AnalysisFrame {
-            timestamp: self.current_time,
-            dominant_frequency,
-            dominant_magnitude: *dominant_mag,
-            spectral_centroid: analysis.spectral_centroid,
-            band_energies: analysis.band_energies,
-            rms_energy,
-            zcr: analysis.zero_crossing_rate,
-        })
+        let frame = AnalysisFrame {
+            timestamp: start as f64 / self.config.sample_rate as f64,
+            dominant_frequency: features.dominant_frequency,
+            dominant_magnitude: features.dominant_magnitude,
+            spectral_centroid: features.spectral_centroid,
+            band_energies: BandEnergies::new(self.config.band_plan.clone(), features.band_energies),
+            rms_energy: features.rms_energy,
+            zcr: features.zero_crossing_rate,
+            spectral_flux,
+        };
+        self.last_spectrum = features.spectrum;
+        frame
     }
 
     /// Detect events based on frame analysis.
@@ -277,20 +371,7 @@ impl StreamAnalyzer {
         self.prev_dominant = frame.dominant_frequency;
 
         // Beat detection
-        self.energy_history.push_back(frame.rms_energy);
-        if self.energy_history.len() > self.config.history_length {
-            self.energy_history.pop_front();
-        }
-
-        if self.energy_history.len() >= 10 {
-            let avg_energy: f32 = self.energy_history.iter().sum::<f32>() / self.energy_history.len() as f32;
-            if frame.rms_energy > avg_energy * self.config.beat_threshold {
-                self.emit_event(AnalysisEvent::BeatDetected {
-                    timestamp: frame.timestamp,
-                    strength: frame.rms_energy / avg_energy,
-                });
-            }
-        }
+        self.detect_onset(frame);
 
         // Silence detection
         if frame.rms_energy < self.config.silence_threshold {
@@ -310,18 +391,84 @@ impl StreamAnalyzer {
             });
         }
 
-        // Frame analyzed event
-        self.emit_event(AnalysisEvent::FrameAnalyzed {
+        // Frame analyzed event (skip the clone when nobody is listening)
+        if !self.callbacks.is_empty() {
+            self.emit_event(AnalysisEvent::FrameAnalyzed {
+                timestamp: frame.timestamp,
+                frame: frame.clone(),
+            });
+        }
+    }
+
+    /// Spectral-flux onset detection with an adaptive `median + k * MAD` threshold.
+    ///
+    /// A frame is reported as a beat once the following frame confirms it as a
+    /// local flux peak, so beats are emitted one hop late with the peak's timestamp.
+    fn detect_onset(&mut self, frame: &AnalysisFrame) {
+        let window_frames = ((self.config.onset_window_secs * self.config.sample_rate as f64
+            / self.config.hop_size as f64).ceil() as usize).max(3);
+
+        self.flux_history.push_back(frame.spectral_flux);
+        while self.flux_history.len() > window_frames {
+            self.flux_history.pop_front();
+        }
+
+        // Floor the adaptive threshold so spectral jitter on steady material never counts
+        let (median, mad) = median_and_mad(self.flux_history.iter().copied());
+        let floor = self.last_spectrum.iter().sum::<f32>() * self.config.min_relative_flux;
+        let current = FluxPoint {
             timestamp: frame.timestamp,
-            frame: frame.clone(),
-        });
+            flux: frame.spectral_flux,
+            threshold: (median + self.config.beat_threshold * mad).max(floor).max(f32::EPSILON),
+            audible: frame.rms_energy >= self.config.silence_threshold,
+        };
+
+        if let Some(peak) = self.pending_peak {
+            let is_peak = peak.flux > self.prev_flux && peak.flux >= current.flux;
+            let debounced = self.last_beat
+                .is_none_or(|last| peak.timestamp - last >= self.config.min_beat_interval_secs);
+
+            if is_peak && peak.audible && peak.flux > peak.threshold && debounced {
+                self.last_beat = Some(peak.timestamp);
+                self.emit_event(AnalysisEvent::BeatDetected {
+                    timestamp: peak.timestamp,
+                    strength: peak.flux / peak.threshold,
+                });
+                self.update_tempo(peak.timestamp);
+            }
+
+            self.prev_flux = peak.flux;
+        }
+
+        self.pending_peak = Some(current);
     }
 
-    /// Update history with new frame.
-    fn update_history(&mut self, frame: &AnalysisFrame) {
-        self.history.push_back(frame.clone());
-        if self.history.len() > self.config.history_length {
-            self.history.pop_front();
+    /// Update the tempo estimate from the inter-onset interval histogram.
+    fn update_tempo(&mut self, onset: f64) {
+        self.onset_times.push_back(onset);
+        if self.onset_times.len() > TEMPO_ONSET_HISTORY {
+            self.onset_times.pop_front();
+        }
+
+        let intervals: Vec<f32> = self.onset_times.iter()
+            .zip(self.onset_times.iter().skip(1))
+            .map(|(a, b)| (b - a) as f32)
+            .collect();
+
+        let Some(bpm) = estimate_tempo(&intervals) else {
+            return;
+        };
+
+        let changed = self.tempo_bpm
+            .is_none_or(|prev| (bpm - prev).abs() >= self.config.tempo_change_threshold);
+        if changed {
+            let previous = self.tempo_bpm;
+            self.tempo_bpm = Some(bpm);
+            self.emit_event(AnalysisEvent::TempoChanged {
+                bpm,
+                previous,
+                timestamp: onset,
+            });
         }
     }
 
@@ -335,94 +482,70 @@ impl StreamAnalyzer {
 
     /// Get rolling statistics over the history window.
     pub fn get_statistics(&self) -> StreamStatistics {
-        if self.history.is_empty() {
+        if self.stats.is_empty() {
             return StreamStatistics::default();
         }
 
-        let n = self.history.len() as f32;
-
-        // Average dominant frequency
-        let avg_dominant: f32 = self.history.iter()
-            .map(|f| f.dominant_frequency)
-            .sum::<f32>() / n;
-
-        // Average centroid
-        let avg_centroid: f32 = self.history.iter()
-            .map(|f| f.spectral_centroid)
-            .sum::<f32>() / n;
-
-        // Average RMS
-        let avg_rms: f32 = self.history.iter()
-            .map(|f| f.rms_energy)
-            .sum::<f32>() / n;
-
-        // RMS variance
-        let rms_variance: f32 = self.history.iter()
-            .map(|f| (f.rms_energy - avg_rms).powi(2))
-            .sum::<f32>() / n;
-
-        // Dominant frequency variance
-        let freq_variance: f32 = self.history.iter()
-            .map(|f| (f.dominant_frequency - avg_dominant).powi(2))
-            .sum::<f32>() / n;
-
-        // Average band energies
-        let mut avg_bands = BandEnergies {
-            sub_bass: 0.0, bass: 0.0, low_mid: 0.0,
-            mid: 0.0, high_mid: 0.0, high: 0.0,
-        };
-        for frame in &self.history {
-            avg_bands.sub_bass += frame.band_energies.sub_bass;
-            avg_bands.bass += frame.band_energies.bass;
-            avg_bands.low_mid += frame.band_energies.low_mid;
-            avg_bands.mid += frame.band_energies.mid;
-            avg_bands.high_mid += frame.band_energies.high_mid;
-            avg_bands.high += frame.band_energies.high;
-        }
-        avg_bands.sub_bass /= n;
-        avg_bands.bass /= n;
-        avg_bands.low_mid /= n;
-        avg_bands.mid /= n;
-        avg_bands.high_mid /= n;
-        avg_bands.high /= n;
-
+        let summary = self.stats.summary();
         StreamStatistics {
             window_duration: self.config.history_length as f64
                 * self.config.hop_size as f64
                 / self.config.sample_rate as f64,
-            avg_dominant_frequency: avg_dominant,
-            avg_spectral_centroid: avg_centroid,
-            avg_rms_energy: avg_rms,
-            rms_variance,
-            frequency_variance: freq_variance,
-            avg_band_energies: avg_bands,
-            frame_count: self.history.len(),
+            avg_dominant_frequency: summary.avg_dominant_frequency,
+            avg_spectral_centroid: summary.avg_spectral_centroid,
+            avg_rms_energy: summary.avg_rms_energy,
+            rms_variance: summary.rms_variance,
+            frequency_variance: summary.frequency_variance,
+            avg_band_energies: BandEnergies::new(self.config.band_plan.clone(), summary.avg_band_energies),
+            frame_count: summary.frame_count,
+            estimated_bpm: self.tempo_bpm,
         }
     }
 
     /// Get the most recent frame.
     pub fn current_frame(&self) -> Option<&AnalysisFrame> {
-        self.history.back()
+        self.latest.as_ref()
     }
 
-    /// Get the current timestamp.
+    /// Get the current timestamp: the start of the next frame.
     pub fn current_time(&self) -> f64 {
-        self.current_time
+        self.framer.position() as f64 / self.config.sample_rate as f64
     }
 
     /// Reset the analyzer state.
     pub fn reset(&mut self) {
-        self.buffer.clear();
-        self.history.clear();
-        self.energy_history.clear();
-        self.current_time = 0.0;
+        self.framer.clear();
+        self.latest = None;
+        self.stats.clear();
+        self.last_spectrum.clear();
+        self.flux_history.clear();
+        self.prev_flux = 0.0;
+        self.pending_peak = None;
+        self.last_beat = None;
+        self.onset_times.clear();
+        self.tempo_bpm = None;
         self.prev_dominant = 0.0;
         self.in_silence = false;
+        if let Some(vad) = &mut self.vad {
+            vad.reset();
+        }
+    }
+}
+
+/// Convert a voice activity event to its [`AnalysisEvent`].
+fn speech_event(event: VadEvent) -> AnalysisEvent {
+    match event {
+        VadEvent::SpeechStart { timestamp } => AnalysisEvent::SpeechStart { timestamp },
+        VadEvent::SpeechEnd { segment } => AnalysisEvent::SpeechEnd {
+            timestamp: segment.end,
+            duration: segment.duration(),
+            confidence: segment.confidence,
+        },
     }
 }
 
 /// Rolling statistics over the analysis window.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StreamStatistics {
     /// Duration of the statistics window in seconds
     pub window_duration: f64,
@@ -440,45 +563,222 @@ pub struct StreamStatistics {
     pub avg_band_energies: BandEnergies,
     /// Number of frames in the window
     pub frame_count: usize,
+    /// Rolling tempo estimate in BPM, once enough beats have been detected
+    pub estimated_bpm: Option<f32>,
+}
+
+/// Median and median absolute deviation of a set of values.
+fn median_and_mad(values: impl Iterator<Item = f32>) -> (f32, f32) {
+    fn median(values: &mut [f32]) -> f32 {
+        if values.is_empty() {
+            return 0.0;
+        }
+        let mid = values.len() / 2;
+        *values.select_nth_unstable_by(mid, |a, b| a.total_cmp(b)).1
+    }
+
+    let mut values: Vec<f32> = values.collect();
+    let med = median(&mut values);
+    let mut deviations: Vec<f32> = values.iter().map(|v| (v - med).abs()).collect();
+    (med, median(&mut deviations))
+}
+
+/// Estimate tempo from inter-onset intervals (seconds).
+///
+/// Each interval is folded into [`TEMPO_RANGE`]; the interval with the most
+/// others within 4% of it wins, and its supporters are averaged. Returns
+/// `None` until at least three intervals are available.
+fn estimate_tempo(intervals: &[f32]) -> Option<f32> {
+    if intervals.len() < 3 {
+        return None;
+    }
+
+    let folded: Vec<f32> = intervals.iter()
+        .filter(|&&ioi| ioi > 0.0)
+        .map(|&ioi| {
+            let mut bpm = 60.0 / ioi;
+            while bpm < TEMPO_RANGE.0 {
+                bpm *= 2.0;
+            }
+            while bpm >= TEMPO_RANGE.1 {
+                bpm /= 2.0;
+            }
+            bpm
+        })
+        .collect();
+
+    let supporters = |center: f32| folded.iter().filter(move |&&bpm| (bpm - center).abs() <= center * 0.04);
+    let best = folded.iter()
+        .copied()
+        .max_by_key(|&center| supporters(center).count())?;
+
+    let support: Vec<f32> = supporters(best).copied().collect();
+    // Average the intervals rather than the BPMs so quantization errors cancel
+    let mean_interval = support.iter().map(|bpm| 60.0 / bpm).sum::<f32>() / support.len() as f32;
+    Some(60.0 / mean_interval)
 }
 
-/// Thread-safe streaming analyzer for async contexts.
+/// Default number of queued sample chunks before `send_samples` waits.
+const DEFAULT_SAMPLE_QUEUE: usize = 32;
+
+/// Default number of buffered events per subscriber before it starts lagging.
+const DEFAULT_EVENT_QUEUE: usize = 1024;
+
+/// Requests handled by the [`StreamWorker`].
+enum WorkerCommand {
+    Samples(Vec<f32>),
+    Statistics(oneshot::Sender<StreamStatistics>),
+    Reset,
+    Shutdown(oneshot::Sender<()>),
+}
+
+/// Channel-based streaming analyzer for async contexts.
+///
+/// A [`StreamWorker`] task owns the [`StreamAnalyzer`]; handles send it
+/// sample chunks over a bounded channel and receive [`AnalysisEvent`]s from a
+/// broadcast channel. Handles are cheap to clone, so several producers can
+/// feed the same worker.
+///
+/// # Latency
+///
+/// Samples are analyzed in arrival order. An event is published as soon as
+/// the chunk that completes its frame has been processed, i.e. at most one
+/// hop (`hop_size / sample_rate`) after the audio it describes arrived, plus
+/// queueing delay. Beats are confirmed one hop after their peak frame. When
+/// the sample queue is full `send_samples` waits, so a producer can never run
+/// more than the queue capacity ahead of the analysis. Subscribers that fall
+/// more than the event queue capacity behind skip the oldest events and get
+/// [`broadcast::error::RecvError::Lagged`].
+///
+/// ```rust,ignore
+/// let (analyzer, worker) = AsyncStreamAnalyzer::new(44100, 2048);
+/// let handle = tokio::spawn(worker.run());
+///
+/// let mut events = analyzer.subscribe();
+/// analyzer.send_samples(&samples).await?;
+/// while let Ok(event) = events.recv().await { /* ... */ }
+///
+/// analyzer.shutdown().await?;
+/// handle.await?;
+/// ```
+#[derive(Clone)]
 pub struct AsyncStreamAnalyzer {
-    inner: Arc<Mutex<StreamAnalyzer>>,
+    commands: mpsc::Sender<WorkerCommand>,
+    events: broadcast::Sender<AnalysisEvent>,
+}
+
+/// Worker task that owns a [`StreamAnalyzer`] on behalf of [`AsyncStreamAnalyzer`] handles.
+pub struct StreamWorker {
+    analyzer: StreamAnalyzer,
+    commands: mpsc::Receiver<WorkerCommand>,
 }
 
 impl AsyncStreamAnalyzer {
-    /// Create a new async streaming analyzer.
-    pub fn new(sample_rate: u32, fft_size: usize) -> Self {
-        Self {
-            inner: Arc::new(Mutex::new(StreamAnalyzer::new(sample_rate, fft_size))),
-        }
+    /// Create a handle and its worker with default configuration.
+    ///
+    /// The worker does nothing until [`StreamWorker::run`] is spawned.
+    pub fn new(sample_rate: u32, fft_size: usize) -> (Self, StreamWorker) {
+        let config = StreamConfig {
+            sample_rate,
+            fft_size,
+            hop_size: fft_size / 4,
+            ..Default::default()
+        };
+        Self::with_config(config, DEFAULT_SAMPLE_QUEUE, DEFAULT_EVENT_QUEUE)
     }
 
-    /// Process samples asynchronously.
-    pub async fn process(&self, samples: &[f32]) -> Vec<AnalysisFrame> {
-        let mut analyzer = self.inner.lock().unwrap();
-        analyzer.process(samples)
+    /// Create a handle and worker with custom configuration and queue sizes.
+    ///
+    /// `sample_queue` bounds the number of pending chunks, `event_queue` the
+    /// number of events buffered per subscriber.
+    pub fn with_config(config: StreamConfig, sample_queue: usize, event_queue: usize) -> (Self, StreamWorker) {
+        let (command_tx, command_rx) = mpsc::channel(sample_queue.max(1));
+        let (event_tx, _) = broadcast::channel(event_queue.max(1));
+
+        let mut analyzer = StreamAnalyzer::with_config(config);
+        let publisher = event_tx.clone();
+        analyzer.on_event(move |event| {
+            // No subscribers is not an error; events are simply dropped
+            let _ = publisher.send(event);
+        });
+
+        let handle = Self {
+            commands: command_tx,
+            events: event_tx,
+        };
+        let worker = StreamWorker {
+            analyzer,
+            commands: command_rx,
+        };
+        (handle, worker)
     }
 
-    /// Get current statistics.
-    pub fn get_statistics(&self) -> StreamStatistics {
-        let analyzer = self.inner.lock().unwrap();
-        analyzer.get_statistics()
+    /// Create a handle and spawn its worker on the current Tokio runtime.
+    pub fn spawn(sample_rate: u32, fft_size: usize) -> (Self, JoinHandle<()>) {
+        let (handle, worker) = Self::new(sample_rate, fft_size);
+        (handle, tokio::spawn(worker.run()))
+    }
+
+    /// Queue samples for analysis, waiting while the queue is full.
+    pub async fn send_samples(&self, samples: &[f32]) -> Result<()> {
+        self.send(WorkerCommand::Samples(samples.to_vec())).await
+    }
+
+    /// Subscribe to analysis events published after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<AnalysisEvent> {
+        self.events.subscribe()
+    }
+
+    /// Get statistics once all previously queued samples have been analyzed.
+    pub async fn statistics(&self) -> Result<StreamStatistics> {
+        let (tx, rx) = oneshot::channel();
+        self.send(WorkerCommand::Statistics(tx)).await?;
+        rx.await.context("Stream analysis worker stopped before replying")
     }
 
-    /// Reset the analyzer.
-    pub fn reset(&self) {
-        let mut analyzer = self.inner.lock().unwrap();
-        analyzer.reset();
+    /// Reset the analyzer once all previously queued samples have been analyzed.
+    pub async fn reset(&self) -> Result<()> {
+        self.send(WorkerCommand::Reset).await
+    }
+
+    /// Stop the worker after it has analyzed all previously queued samples.
+    ///
+    /// Other handles fail with an error afterwards. The worker also stops on
+    /// its own once every handle has been dropped.
+    pub async fn shutdown(&self) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send(WorkerCommand::Shutdown(tx)).await?;
+        rx.await.context("Stream analysis worker stopped before acknowledging shutdown")
+    }
+
+    async fn send(&self, command: WorkerCommand) -> Result<()> {
+        if self.commands.send(command).await.is_err() {
+            bail!("Stream analysis worker has stopped");
+        }
+        Ok(())
     }
 }
 
-impl Clone for AsyncStreamAnalyzer {
-    fn clone(&self) -> Self {
-        Self {
-            inner: Arc::clone(&self.inner),
+impl StreamWorker {
+    /// Process commands until shutdown or until every handle is dropped.
+    pub async fn run(mut self) {
+        while let Some(command) = self.commands.recv().await {
+            match command {
+                WorkerCommand::Samples(samples) => {
+                    self.analyzer.process(&samples);
+                }
+                WorkerCommand::Statistics(reply) => {
+                    let _ = reply.send(self.analyzer.get_statistics());
+                }
+                WorkerCommand::Reset => self.analyzer.reset(),
+                WorkerCommand::Shutdown(ack) => {
+                    self.commands.close();
+                    let _ = ack.send(());
+                    break;
+                }
+            }
         }
+        debug!("Stream analysis worker stopped at {:.2}s", self.analyzer.current_time());
     }
 }
 
@@ -486,6 +786,8 @@ impl Clone for AsyncStreamAnalyzer {
 mod tests {
     use super::*;
     use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
 
     fn generate_sine(freq: f32, sample_rate: u32, duration_secs: f32) -> Vec<f32> {
         let n = (sample_rate as f32 * duration_secs) as usize;
@@ -508,6 +810,28 @@ mod tests {
         assert!(frames[0].dominant_frequency > 400.0 && frames[0].dominant_frequency < 480.0);
     }
 
+    #[test]
+    fn test_chunked_input_matches_single_call() {
+        let samples = generate_click_track(120.0, 44100, 2.0);
+
+        let mut whole = StreamAnalyzer::new(44100, 2048);
+        let expected = whole.process(&samples);
+
+        let mut chunked = StreamAnalyzer::new(44100, 2048);
+        let mut actual = Vec::new();
+        for chunk in samples.chunks(333) {
+            actual.extend(chunked.process(chunk));
+        }
+
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(&expected) {
+            assert_eq!(a.timestamp, e.timestamp);
+            assert_eq!(a.rms_energy, e.rms_energy);
+            assert_eq!(a.spectral_flux, e.spectral_flux);
+        }
+        assert!(chunked.framer.buffered() < 2048);
+    }
+
     #[test]
     fn test_event_callbacks() {
         let event_count = Arc::new(AtomicUsize::new(0));
@@ -536,6 +860,129 @@ mod tests {
         assert!(stats.avg_dominant_frequency > 400.0);
     }
 
+    /// Decaying 2kHz clicks at `bpm`, starting half a beat in.
+    fn generate_click_track(bpm: f32, sample_rate: u32, duration_secs: f32) -> Vec<f32> {
+        let mut samples = vec![0.0f32; (sample_rate as f32 * duration_secs) as usize];
+        let period = 60.0 / bpm * sample_rate as f32;
+        let click_len = (0.01 * sample_rate as f32) as usize;
+
+        let mut start = period / 2.0;
+        while (start as usize) < samples.len() {
+            for i in 0..click_len.min(samples.len() - start as usize) {
+                let t = i as f32 / sample_rate as f32;
+                samples[start as usize + i] +=
+                    (2.0 * std::f32::consts::PI * 2000.0 * t).sin() * (-t * 400.0).exp();
+            }
+            start += period;
+        }
+        samples
+    }
+
+    fn collect_events(analyzer: &mut StreamAnalyzer) -> Arc<Mutex<Vec<AnalysisEvent>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        analyzer.on_event(move |event| {
+            if matches!(
+                event,
+                AnalysisEvent::BeatDetected { .. }
+                    | AnalysisEvent::TempoChanged { .. }
+                    | AnalysisEvent::SpeechStart { .. }
+                    | AnalysisEvent::SpeechEnd { .. }
+            ) {
+                sink.lock().unwrap().push(event);
+            }
+        });
+        events
+    }
+
+    fn beat_times(events: &[AnalysisEvent]) -> Vec<f64> {
+        events.iter()
+            .filter_map(|e| match e {
+                AnalysisEvent::BeatDetected { timestamp, .. } => Some(*timestamp),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_click_track_beats_and_tempo() {
+        let mut analyzer = StreamAnalyzer::new(44100, 2048);
+        let events = collect_events(&mut analyzer);
+
+        // Feed in uneven chunks like a live stream would
+        let track = generate_click_track(120.0, 44100, 8.0);
+        for chunk in track.chunks(1000) {
+            analyzer.process(chunk);
+        }
+
+        let events = events.lock().unwrap();
+        let beats = beat_times(&events);
+        assert!((15..=16).contains(&beats.len()), "detected {} beats", beats.len());
+        for pair in beats.windows(2) {
+            let interval = pair[1] - pair[0];
+            assert!((interval - 0.5).abs() < 0.03, "inter-beat interval {}", interval);
+        }
+
+        let bpm = analyzer.get_statistics().estimated_bpm.expect("tempo estimate");
+        assert!((bpm - 120.0).abs() < 2.0, "estimated {} BPM", bpm);
+        assert!(events.iter().any(|e| matches!(e, AnalysisEvent::TempoChanged { previous: None, .. })));
+    }
+
+    #[test]
+    fn test_tempo_change_event() {
+        let mut analyzer = StreamAnalyzer::new(44100, 2048);
+        let events = collect_events(&mut analyzer);
+
+        analyzer.process(&generate_click_track(100.0, 44100, 8.0));
+        analyzer.process(&generate_click_track(140.0, 44100, 10.0));
+
+        let bpm = analyzer.get_statistics().estimated_bpm.unwrap();
+        assert!((bpm - 140.0).abs() < 3.0, "estimated {} BPM", bpm);
+
+        let tempos: Vec<f32> = events.lock().unwrap().iter()
+            .filter_map(|e| match e {
+                AnalysisEvent::TempoChanged { bpm, .. } => Some(*bpm),
+                _ => None,
+            })
+            .collect();
+        assert!(tempos.iter().any(|b| (b - 100.0).abs() < 3.0));
+        assert!((tempos.last().unwrap() - 140.0).abs() < 3.0);
+    }
+
+    #[test]
+    fn test_steady_tone_has_no_beats() {
+        let mut analyzer = StreamAnalyzer::new(44100, 2048);
+        let events = collect_events(&mut analyzer);
+
+        analyzer.process(&generate_sine(440.0, 44100, 3.0));
+
+        // Only the tone's attack may register
+        assert!(beat_times(&events.lock().unwrap()).len() <= 1);
+        assert!(analyzer.get_statistics().estimated_bpm.is_none());
+    }
+
+    #[test]
+    fn test_onset_debounce() {
+        let config = StreamConfig {
+            min_beat_interval_secs: 0.25,
+            ..Default::default()
+        };
+        let mut analyzer = StreamAnalyzer::with_config(config);
+        let events = collect_events(&mut analyzer);
+
+        // Flam: pairs of clicks 50ms apart, every 0.5s
+        let mut track = generate_click_track(120.0, 44100, 4.0);
+        let offset = (0.05 * 44100.0) as usize;
+        let shifted: Vec<f32> = track.iter().copied().take(track.len() - offset).collect();
+        for (s, extra) in track[offset..].iter_mut().zip(shifted) {
+            *s += extra;
+        }
+        analyzer.process(&track);
+
+        let beats = beat_times(&events.lock().unwrap());
+        assert!((7..=8).contains(&beats.len()), "detected {} beats", beats.len());
+    }
+
     #[test]
     fn test_silence_detection() {
         let config = StreamConfig {
@@ -563,4 +1010,94 @@ mod tests {
 
         assert!(silence_detected.load(Ordering::SeqCst) > 0);
     }
+
+    #[test]
+    fn test_speech_events() {
+        let mut analyzer = StreamAnalyzer::new(44100, 2048);
+        let events = collect_events(&mut analyzer);
+
+        let mut samples = vec![0.0; 44100];
+        samples.extend(crate::testing::speech_like(44100, 1.5).samples);
+        samples.extend(generate_sine(440.0, 44100, 2.0));
+        for chunk in samples.chunks(1024) {
+            analyzer.process(chunk);
+        }
+
+        let speech: Vec<AnalysisEvent> = events.lock().unwrap().iter()
+            .filter(|e| matches!(e, AnalysisEvent::SpeechStart { .. } | AnalysisEvent::SpeechEnd { .. }))
+            .cloned()
+            .collect();
+        match speech[..] {
+            [AnalysisEvent::SpeechStart { timestamp: start }, AnalysisEvent::SpeechEnd { timestamp: end, duration, .. }] => {
+                assert!((start - 1.0).abs() < 0.1, "speech started at {}", start);
+                assert!((end - 2.25).abs() < 0.35, "speech ended at {}", end);
+                assert!((duration - (end - start)).abs() < 1e-9);
+            }
+            _ => panic!("unexpected speech events {:?}", speech),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_async_analyzer_realtime_feed() {
+        let (analyzer, worker) = AsyncStreamAnalyzer::new(44100, 2048);
+        let handle = tokio::spawn(worker.run());
+        let mut events = analyzer.subscribe();
+
+        // Feed 20ms chunks at real-time pace from a cloned handle
+        let producer = analyzer.clone();
+        let start = tokio::time::Instant::now();
+        let feeder = tokio::spawn(async move {
+            let track = generate_click_track(120.0, 44100, 6.0);
+            let mut ticker = tokio::time::interval(Duration::from_millis(20));
+            for chunk in track.chunks(882) {
+                ticker.tick().await;
+                producer.send_samples(chunk).await.unwrap();
+            }
+        });
+        feeder.await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(5900));
+
+        // Statistics are answered after every queued chunk has been analyzed
+        let stats = analyzer.statistics().await.unwrap();
+        let bpm = stats.estimated_bpm.expect("tempo estimate");
+        assert!((bpm - 120.0).abs() < 2.0, "estimated {} BPM", bpm);
+
+        let mut beats = 0;
+        let mut frames = 0;
+        while let Ok(event) = events.try_recv() {
+            match event {
+                AnalysisEvent::BeatDetected { .. } => beats += 1,
+                AnalysisEvent::FrameAnalyzed { .. } => frames += 1,
+                _ => {}
+            }
+        }
+        assert!((11..=12).contains(&beats), "detected {} beats", beats);
+        assert!(frames > 500);
+
+        analyzer.shutdown().await.unwrap();
+        handle.await.unwrap();
+        assert!(analyzer.send_samples(&[0.0; 16]).await.is_err());
+        assert!(analyzer.statistics().await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_async_analyzer_backpressure() {
+        let (analyzer, worker) = AsyncStreamAnalyzer::with_config(StreamConfig::default(), 2, 16);
+
+        analyzer.send_samples(&[0.0; 512]).await.unwrap();
+        analyzer.send_samples(&[0.0; 512]).await.unwrap();
+
+        // The queue is full and nothing is draining it yet
+        let blocked = tokio::time::timeout(Duration::from_millis(100), analyzer.send_samples(&[0.0; 512])).await;
+        assert!(blocked.is_err());
+
+        let handle = tokio::spawn(worker.run());
+        analyzer.send_samples(&[0.0; 512]).await.unwrap();
+        analyzer.reset().await.unwrap();
+        assert_eq!(analyzer.statistics().await.unwrap().frame_count, 0);
+
+        // Dropping the last handle stops the worker
+        drop(analyzer);
+        handle.await.unwrap();
+    }
 }
@@ -0,0 +1,416 @@
+//! Audio-video sync drift detection for QC.
+//!
+//! Assets with a frame-rate mismatch tend to drift: audio that is in sync at
+//! the start ends up hundreds of milliseconds early or late by the end.
+//! [`SyncChecker`] estimates the A/V offset in a few windows spread through
+//! the asset by lining up audio onsets with scene changes, scored with the
+//! same frame difference the break detector uses. A sync test pattern (a
+//! flash with a beep) is just a very clear scene change and onset.
+//!
+//! Offsets are only as precise as the sampled frame rate allows, so the
+//! report is meant to flag assets for human review rather than to measure
+//! drift exactly.
+
+use std::path::Path;
+
+use anyhow::Result;
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::thumbnail::{frame_difference, ThumbnailSelector};
+use crate::types::*;
+
+/// Hop between audio onset measurements (seconds).
+const ONSET_HOP_SECS: f64 = 0.005;
+
+/// Lags scoring at least this fraction of the best one count as equally good.
+const PLATEAU_FRACTION: f32 = 0.95;
+
+/// Configuration for sync checking.
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    /// Windows measured through the asset, first at the start and last at the end
+    pub checkpoints: usize,
+    /// Length of each window (seconds)
+    pub window_secs: f64,
+    /// Frames sampled per second inside a window
+    pub frame_rate: f64,
+    /// Largest offset searched for, either way (milliseconds)
+    pub max_offset_ms: f64,
+    /// Mean absolute difference between frames (0-1) that counts as a scene change
+    pub min_scene_change: f32,
+    /// Minimum confidence for a window's offset to be reported (0-1)
+    pub min_confidence: f32,
+    /// Offset or drift beyond which the asset is flagged for review (milliseconds)
+    pub threshold_ms: f64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            checkpoints: 3,
+            window_secs: 10.0,
+            frame_rate: 25.0,
+            max_offset_ms: 500.0,
+            min_scene_change: 0.1,
+            min_confidence: 0.3,
+            threshold_ms: 45.0,
+        }
+    }
+}
+
+/// The offset measured in one window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncCheckpoint {
+    /// Middle of the window (seconds)
+    pub time: f64,
+    /// How far the audio trails the picture (milliseconds, negative when it
+    /// leads); `None` when the window had too few scene changes or onsets
+    pub offset_ms: Option<f64>,
+    /// How clearly one offset beat the others (0-1)
+    pub confidence: f32,
+    /// Scene changes the offset was measured from
+    pub scene_changes: usize,
+}
+
+/// Sync measurements for a whole asset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncReport {
+    /// Asset duration (seconds)
+    pub duration_secs: f64,
+    /// Every measured window, ordered by time
+    pub checkpoints: Vec<SyncCheckpoint>,
+    /// Offset of the first window with a measurement (milliseconds)
+    pub start_offset_ms: Option<f64>,
+    /// Offset of the measured window closest to the middle (milliseconds)
+    pub middle_offset_ms: Option<f64>,
+    /// Offset of the last window with a measurement (milliseconds)
+    pub end_offset_ms: Option<f64>,
+    /// Change in offset from start to end (milliseconds)
+    pub drift_ms: Option<f64>,
+    /// Least-squares drift through all measurements (milliseconds per minute)
+    pub drift_rate_ms_per_min: Option<f64>,
+    /// Offset or drift that flags the asset (milliseconds)
+    pub threshold_ms: f64,
+    /// Some offset, or the drift, exceeds the threshold
+    pub flagged: bool,
+}
+
+/// Audio onset strength on a fixed grid.
+struct Onsets {
+    /// Time of the first value (seconds)
+    start: f64,
+    values: Vec<f32>,
+}
+
+impl Onsets {
+    /// Positive RMS flux of mono audio between two timestamps, normalized to 0-1.
+    fn measure(audio: &AudioData, start: f64, end: f64) -> Self {
+        let rate = audio.sample_rate as f64;
+        let hop = ((ONSET_HOP_SECS * rate) as usize).max(1);
+        let first = (start.max(0.0) * rate) as usize;
+        let last = ((end.max(0.0) * rate) as usize).min(audio.samples.len());
+
+        let levels: Vec<f32> = (first..last)
+            .step_by(hop)
+            .map(|i| {
+                let block = &audio.samples[i..(i + hop).min(last)];
+                let energy: f32 = block.iter().filter(|s| s.is_finite()).map(|&s| s * s).sum();
+                (energy / block.len() as f32).sqrt()
+            })
+            .collect();
+
+        let mut values: Vec<f32> = std::iter::once(0.0)
+            .chain(levels.windows(2).map(|pair| (pair[1] - pair[0]).max(0.0)))
+            .collect();
+        let peak = values.iter().cloned().fold(0.0f32, f32::max);
+        if peak > 0.0 {
+            for v in &mut values {
+                *v /= peak;
+            }
+        } else {
+            values.clear();
+        }
+
+        Self { start: first as f64 / rate, values }
+    }
+
+    /// Strongest onset within `radius` seconds of `time`.
+    fn peak_near(&self, time: f64, radius: f64) -> f32 {
+        let index = |t: f64| ((t - self.start) / ONSET_HOP_SECS).round().max(0.0) as usize;
+        let from = index(time - radius).min(self.values.len());
+        let to = (index(time + radius) + 1).min(self.values.len());
+        self.values[from..to].iter().cloned().fold(0.0, f32::max)
+    }
+}
+
+/// Estimates A/V offset and drift by matching audio onsets to scene changes.
+pub struct SyncChecker {
+    config: SyncConfig,
+    frames: ThumbnailSelector,
+}
+
+impl SyncChecker {
+    /// Create a new sync checker with default configuration.
+    pub fn new() -> Self {
+        Self::with_config(SyncConfig::default())
+    }
+
+    /// Create a checker with custom configuration.
+    pub fn with_config(config: SyncConfig) -> Self {
+        Self {
+            config,
+            frames: ThumbnailSelector::new(),
+        }
+    }
+
+    /// Measure A/V sync through a video.
+    ///
+    /// `audio` is the video's extracted audio; frames are sampled with FFmpeg.
+    pub fn check(&self, video_path: impl AsRef<Path>, audio: &AudioData) -> Result<SyncReport> {
+        let video_path = video_path.as_ref();
+        info!("Checking A/V sync in: {}", video_path.display());
+
+        let duration = self.frames.get_video_duration(video_path)?;
+        let fps = self.config.frame_rate;
+        let options = self.frames.decode_options(&self.frames.color_info(video_path));
+        let report = self.check_with_frames(audio, duration, |start, length| {
+            self.frames.extract_frames(video_path, start, length, fps, &options)
+        });
+
+        info!(
+            "Sync drift {:?} ms over {:.0}s{}",
+            report.drift_ms,
+            duration,
+            if report.flagged { " (flagged)" } else { "" }
+        );
+        Ok(report)
+    }
+
+    /// Sync check with the frames of each window supplied by
+    /// `frames_in(start, length)`, sampled at `frame_rate`.
+    fn check_with_frames(
+        &self,
+        audio: &AudioData,
+        duration: f64,
+        mut frames_in: impl FnMut(f64, f64) -> Result<Vec<GrayImage>>,
+    ) -> SyncReport {
+        let checkpoints: Vec<SyncCheckpoint> = self.windows(duration)
+            .into_iter()
+            .map(|(start, length)| match frames_in(start, length) {
+                Ok(frames) => self.measure(audio, start, length, &frames),
+                Err(e) => {
+                    warn!("Failed to extract frames at {:.2}s: {}", start, e);
+                    SyncCheckpoint { time: start + length / 2.0, offset_ms: None, confidence: 0.0, scene_changes: 0 }
+                }
+            })
+            .collect();
+
+        self.summarize(duration, checkpoints)
+    }
+
+    /// Start and length of each window, spread evenly from start to end.
+    fn windows(&self, duration: f64) -> Vec<(f64, f64)> {
+        let length = self.config.window_secs.min(duration).max(0.0);
+        if length <= 0.0 {
+            return Vec::new();
+        }
+
+        let count = self.config.checkpoints.max(1);
+        if count == 1 {
+            return vec![((duration - length) / 2.0, length)];
+        }
+        (0..count)
+            .map(|i| ((duration - length) * i as f64 / (count - 1) as f64, length))
+            .collect()
+    }
+
+    /// Find the lag that best lines the window's scene changes up with audio onsets.
+    fn measure(&self, audio: &AudioData, start: f64, length: f64, frames: &[GrayImage]) -> SyncCheckpoint {
+        let config = &self.config;
+        let frame_secs = 1.0 / config.frame_rate;
+        let max_offset = config.max_offset_ms / 1000.0;
+        let unmeasured = |scene_changes| SyncCheckpoint {
+            time: start + length / 2.0,
+            offset_ms: None,
+            confidence: 0.0,
+            scene_changes,
+        };
+
+        // A cut lands somewhere between the two frames it separates
+        let cuts: Vec<(f64, f32)> = frames.windows(2)
+            .enumerate()
+            .map(|(i, pair)| (start + (i as f64 + 0.5) * frame_secs, frame_difference(&pair[0], &pair[1])))
+            .filter(|&(_, change)| change >= config.min_scene_change)
+            .collect();
+        let onsets = Onsets::measure(audio, start - max_offset - frame_secs, start + length + max_offset + frame_secs);
+        if cuts.is_empty() || onsets.values.is_empty() {
+            return unmeasured(cuts.len());
+        }
+
+        let steps = (max_offset / ONSET_HOP_SECS).round() as i64;
+        let scores: Vec<f32> = (-steps..=steps)
+            .map(|step| {
+                let lag = step as f64 * ONSET_HOP_SECS;
+                cuts.iter().map(|&(t, w)| w * onsets.peak_near(t + lag, frame_secs / 2.0)).sum()
+            })
+            .collect();
+
+        let (best, &peak) = scores.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap();
+        if peak <= 0.0 {
+            return unmeasured(cuts.len());
+        }
+        let mean = scores.iter().sum::<f32>() / scores.len() as f32;
+        let confidence = 1.0 - mean / peak;
+
+        // Onsets are matched within half a frame, so the best lags form a
+        // plateau; its middle is the estimate
+        let good = |i: &usize| scores[*i] >= peak * PLATEAU_FRACTION;
+        let first = (0..=best).rev().take_while(good).last().unwrap_or(best);
+        let last = (best..scores.len()).take_while(good).last().unwrap_or(best);
+        let offset_ms = ((first + last) as f64 / 2.0 - steps as f64) * ONSET_HOP_SECS * 1000.0;
+        debug!(
+            "Window at {:.2}s: {} cuts, offset {:.0}ms, confidence {:.3}",
+            start, cuts.len(), offset_ms, confidence
+        );
+
+        SyncCheckpoint {
+            time: start + length / 2.0,
+            offset_ms: (confidence >= config.min_confidence).then_some(offset_ms),
+            confidence,
+            scene_changes: cuts.len(),
+        }
+    }
+
+    /// Start, middle and end offsets, drift and the review flag.
+    fn summarize(&self, duration: f64, checkpoints: Vec<SyncCheckpoint>) -> SyncReport {
+        let measured: Vec<(f64, f64)> = checkpoints.iter()
+            .filter_map(|c| c.offset_ms.map(|offset| (c.time, offset)))
+            .collect();
+
+        let start_offset_ms = measured.first().map(|&(_, offset)| offset);
+        let end_offset_ms = measured.last().map(|&(_, offset)| offset);
+        let middle_offset_ms = measured.iter()
+            .min_by(|a, b| (a.0 - duration / 2.0).abs().total_cmp(&(b.0 - duration / 2.0).abs()))
+            .map(|&(_, offset)| offset);
+        let drift_ms = (measured.len() >= 2).then(|| end_offset_ms.unwrap() - start_offset_ms.unwrap());
+
+        let drift_rate_ms_per_min = (measured.len() >= 2).then(|| {
+            let n = measured.len() as f64;
+            let mean_t = measured.iter().map(|m| m.0).sum::<f64>() / n;
+            let mean_o = measured.iter().map(|m| m.1).sum::<f64>() / n;
+            let covariance: f64 = measured.iter().map(|&(t, o)| (t - mean_t) * (o - mean_o)).sum();
+            let variance: f64 = measured.iter().map(|&(t, _)| (t - mean_t).powi(2)).sum();
+            if variance > 0.0 { covariance / variance * 60.0 } else { 0.0 }
+        });
+
+        let threshold_ms = self.config.threshold_ms;
+        let flagged = measured.iter().any(|&(_, offset)| offset.abs() > threshold_ms)
+            || drift_ms.is_some_and(|drift| drift.abs() > threshold_ms);
+
+        SyncReport {
+            duration_secs: duration,
+            checkpoints,
+            start_offset_ms,
+            middle_offset_ms,
+            end_offset_ms,
+            drift_ms,
+            drift_rate_ms_per_min,
+            threshold_ms,
+            flagged,
+        }
+    }
+}
+
+impl Default for SyncChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    const SAMPLE_RATE: u32 = 8000;
+    const FPS: f64 = 25.0;
+
+    /// Irregularly spaced scene changes, so no offset lines up by coincidence.
+    fn cuts(duration: f64) -> Vec<f64> {
+        (1..)
+            .map(|k| k as f64 * 1.7 + (k % 3) as f64 * 0.37)
+            .take_while(|&t| t < duration)
+            .collect()
+    }
+
+    /// Short tone bursts starting `offset(t)` seconds after each cut at `t`.
+    fn audio(duration: f64, offset: impl Fn(f64) -> f64) -> AudioData {
+        let onsets: Vec<f64> = cuts(duration).into_iter().map(|t| t + offset(t)).collect();
+        let samples = (0..(duration * SAMPLE_RATE as f64) as usize)
+            .map(|i| {
+                let t = i as f64 / SAMPLE_RATE as f64;
+                let sounding = onsets.iter().any(|&o| t >= o && t < o + 0.3);
+                if sounding { 0.5 * (2.0 * std::f64::consts::PI * 440.0 * t).sin() as f32 } else { 0.0 }
+            })
+            .collect();
+        AudioData::new(samples, SAMPLE_RATE)
+    }
+
+    /// Frames alternating between a dark and a light shot at every cut.
+    fn frames(duration: f64) -> impl FnMut(f64, f64) -> Result<Vec<GrayImage>> {
+        let cuts = cuts(duration);
+        move |start, length| {
+            Ok((0..(length * FPS) as usize)
+                .map(|i| {
+                    let t = start + i as f64 / FPS;
+                    let shot = cuts.iter().filter(|&&c| c <= t).count();
+                    GrayImage::from_pixel(32, 18, Luma([if shot % 2 == 0 { 40 } else { 200 }]))
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_constant_offset() {
+        let checker = SyncChecker::new();
+        let report = checker.check_with_frames(&audio(120.0, |_| 0.08), 120.0, frames(120.0));
+
+        assert_eq!(report.checkpoints.len(), 3);
+        for checkpoint in &report.checkpoints {
+            let offset = checkpoint.offset_ms.expect("offset measured");
+            assert!((offset - 80.0).abs() <= 25.0, "{:?}", checkpoint);
+            assert!(checkpoint.scene_changes > 0);
+        }
+        assert!(report.drift_ms.unwrap().abs() <= 25.0, "{:?}", report);
+        assert!(report.flagged);
+
+        let report = checker.check_with_frames(&audio(120.0, |_| 0.0), 120.0, frames(120.0));
+        assert!(report.start_offset_ms.unwrap().abs() <= 25.0, "{:?}", report);
+        assert!(!report.flagged, "{:?}", report);
+    }
+
+    #[test]
+    fn test_drift_over_asset() {
+        // Audio falls 200ms further behind over ten minutes
+        let drift = |t: f64| 0.2 * t / 600.0;
+        let report = SyncChecker::new().check_with_frames(&audio(600.0, drift), 600.0, frames(600.0));
+
+        assert!(report.start_offset_ms.unwrap().abs() <= 25.0, "{:?}", report);
+        assert!((report.middle_offset_ms.unwrap() - 100.0).abs() <= 25.0, "{:?}", report);
+        assert!((report.end_offset_ms.unwrap() - 200.0).abs() <= 25.0, "{:?}", report);
+        assert!((report.drift_rate_ms_per_min.unwrap() - 20.0).abs() <= 5.0, "{:?}", report);
+        assert!(report.flagged);
+    }
+
+    #[test]
+    fn test_no_scene_changes() {
+        let still = |_: f64, length: f64| Ok(vec![GrayImage::new(32, 18); (length * FPS) as usize]);
+        let report = SyncChecker::new().check_with_frames(&audio(60.0, |_| 0.0), 60.0, still);
+
+        assert!(report.checkpoints.iter().all(|c| c.offset_ms.is_none() && c.scene_changes == 0));
+        assert_eq!(report.drift_ms, None);
+        assert!(!report.flagged);
+    }
+}
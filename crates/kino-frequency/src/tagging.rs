@@ -10,13 +10,83 @@
 //! - **Mood**: energetic, calm, dramatic, upbeat, melancholic
 //! - **Content Type**: vocal, instrumental, ambient, dialogue
 //! - **Quality**: high-fidelity, compressed, noisy
-
+//!
+//! # Segments
+//!
+//! Long files mix content: a podcast may open with a music intro.
+//! [`ContentTagger::predict_segments`] tags each analysis window separately
+//! and merges adjacent windows that share a top tag into a timeline, and
+//! [`ContentTagger::predict`] aggregates that timeline weighted by duration.
+//!
+//! # Voice Activity
+//!
+//! The spectral rules alone can't tell speech from other broadband,
+//! modulated sound. Each window's speech coverage from [`crate::vad`] raises
+//! the scores of the spoken genres (speech, podcast, tutorial, news) towards
+//! one by [`TaggingConfig::speech_coverage_weight`]. Missing speech lowers
+//! nothing, as speech under music or noise often goes undetected.
+//!
+//! # ML Models
+//!
+//! With the `onnx` feature, [`TaggingConfig::use_ml_model`] runs a
+//! user-supplied ONNX classifier (see [`MlModelConfig`]) on each window and
+//! blends its predictions with the rule-based tags. A model that fails to
+//! load is logged and tagging continues rule-based.
+//!
+//! # Spoken Language
+//!
+//! [`ContentTagger::detect_languages`] finds speech with the speech profile's
+//! ZCR and flatness ranges and runs a language-ID model
+//! ([`TaggingConfig::language_model`], `onnx` feature) over it only. Each
+//! language is reported with its share of the speech; audio without speech
+//! yields no languages.
+//!
+//! # Quality
+//!
+//! [`ContentTagger::analyze_quality`] measures the effective bandwidth
+//! ceiling and noise floor at the input rate and estimates the source's
+//! bitrate class from any codec low-pass, returning a [`QualityReport`] with
+//! "high-fidelity", "compressed" and "noisy" tags. With
+//! [`TaggingConfig::quality_tags`], [`ContentTagger::predict`] adds those
+//! tags to the content tags.
+//!
+//! # Taxonomy
+//!
+//! Tags carry the id of their label in the tagger's [`TagTaxonomy`], e.g.
+//! `"genre.speech.podcast"` for "podcast", which gives parent/child
+//! relationships and localized display names. The built-in taxonomy covers
+//! every rule-based tag; [`ContentTagger::with_taxonomy`] swaps in one
+//! loaded from JSON, whose aliases map ML model labels to ids. Tags whose
+//! label isn't in the taxonomy have no id.
+
+// Only the `onnx` inference path consumes the features and blending
+#[cfg_attr(not(feature = "onnx"), allow(dead_code))]
+mod language;
+#[cfg_attr(not(feature = "onnx"), allow(dead_code))]
+mod ml;
+#[cfg(feature = "onnx")]
+mod onnx;
+mod quality;
+mod taxonomy;
+
+use std::borrow::Cow;
 use std::collections::HashMap;
-use anyhow::Result;
-use tracing::{debug, info};
+use anyhow::{anyhow, bail, Result};
+use kino_tagging::{cosine_similarity, rule_tags, signal, TagFeatures, GENRE_PROFILES};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, field::Empty, info, instrument, warn};
 
+use crate::cancel;
 use crate::fft::FrequencyAnalyzer;
+use crate::loudness;
+use crate::resample;
+use crate::telemetry::StageTimer;
 use crate::types::*;
+use crate::vad::{self, VadConfig};
+
+pub use ml::{MlModelConfig, ModelInput, OutputActivation};
+pub use quality::{BitrateClass, QualityReport};
+pub use taxonomy::{TagTaxonomy, TaxonomyEntry};
 
 /// Content tagging configuration.
 #[derive(Debug, Clone)]
@@ -25,12 +95,38 @@ pub struct TaggingConfig {
     pub fft_size: usize,
     /// Hop size for frame analysis
     pub hop_size: usize,
+    /// Window applied to each analysis frame
+    pub window: WindowFunction,
     /// Minimum confidence threshold for tags
     pub min_confidence: f32,
     /// Maximum number of tags to return
     pub max_tags: usize,
-    /// Enable ML model inference (if available)
+    /// Enable ML model inference (requires the `onnx` feature and `ml_model`)
     pub use_ml_model: bool,
+    /// Model used when `use_ml_model` is set
+    pub ml_model: Option<MlModelConfig>,
+    /// Language-ID model for `detect_languages` (requires the `onnx`
+    /// feature), e.g. [`MlModelConfig::language_id`]
+    pub language_model: Option<MlModelConfig>,
+    /// Normalize input to this integrated loudness (LUFS) before feature
+    /// extraction, so level-dependent tags don't track recording gain
+    pub normalize_loudness: Option<f64>,
+    /// Window length in seconds used by `predict` to aggregate long files
+    pub segment_window_secs: f64,
+    /// Hop between windows in seconds used by `predict`
+    pub segment_hop_secs: f64,
+    /// How NaN and infinite samples are handled
+    pub sample_policy: SamplePolicy,
+    /// Rate input is resampled to before tagging; the genre profiles and
+    /// tempo estimation are tuned for 44.1 kHz (`None` keeps the input rate)
+    pub analysis_rate: Option<u32>,
+    /// Voice activity detection measuring each window's speech coverage
+    pub vad: VadConfig,
+    /// How far full speech coverage raises a spoken genre's score towards one (0 disables it)
+    pub speech_coverage_weight: f32,
+    /// Add quality tags from [`ContentTagger::analyze_quality`] to `predict`'s
+    /// tags, on top of `max_tags`
+    pub quality_tags: bool,
 }
 
 impl Default for TaggingConfig {
@@ -38,9 +134,20 @@ impl Default for TaggingConfig {
         Self {
             fft_size: 4096,
             hop_size: 2048,
+            window: WindowFunction::Hann,
             min_confidence: 0.3,
             max_tags: 5,
             use_ml_model: false,
+            ml_model: None,
+            language_model: None,
+            normalize_loudness: None,
+            segment_window_secs: 30.0,
+            segment_hop_secs: 30.0,
+            sample_policy: SamplePolicy::default(),
+            analysis_rate: Some(44100),
+            vad: VadConfig::default(),
+            speech_coverage_weight: 0.0,
+            quality_tags: false,
         }
     }
 }
@@ -51,6 +158,14 @@ pub struct ContentTagger {
     analyzer: FrequencyAnalyzer,
     /// Genre classification thresholds (learned from training data)
     genre_profiles: HashMap<String, GenreProfile>,
+    /// Taxonomy giving tags their ids
+    taxonomy: TagTaxonomy,
+    /// ML classifier, when enabled and loaded
+    #[cfg(feature = "onnx")]
+    classifier: Option<onnx::OnnxClassifier>,
+    /// Language-ID classifier, when configured and loaded
+    #[cfg(feature = "onnx")]
+    language_classifier: Option<onnx::OnnxClassifier>,
 }
 
 impl ContentTagger {
@@ -61,419 +176,407 @@ impl ContentTagger {
 
     /// Create a tagger with custom configuration.
     pub fn with_config(config: TaggingConfig) -> Self {
-        let analyzer = FrequencyAnalyzer::new(config.fft_size, config.hop_size);
+        let analyzer = FrequencyAnalyzer::with_window(config.fft_size, config.hop_size, config.window);
         let genre_profiles = Self::default_genre_profiles();
 
+        #[cfg(feature = "onnx")]
+        let classifier = onnx::OnnxClassifier::from_tagging_config(&config);
+        #[cfg(feature = "onnx")]
+        let language_classifier = config.language_model.as_ref()
+            .and_then(|model| onnx::OnnxClassifier::from_model(model, "language-ID"));
+        #[cfg(not(feature = "onnx"))]
+        if config.use_ml_model {
+            warn!("use_ml_model requires the `onnx` feature; using rule-based tags only");
+        }
+        #[cfg(not(feature = "onnx"))]
+        if config.language_model.is_some() {
+            warn!("language_model requires the `onnx` feature; languages will not be detected");
+        }
+
         Self {
             config,
             analyzer,
             genre_profiles,
+            taxonomy: TagTaxonomy::builtin(),
+            #[cfg(feature = "onnx")]
+            classifier,
+            #[cfg(feature = "onnx")]
+            language_classifier,
         }
     }
 
-    /// Default genre profiles based on frequency characteristics.
-    fn default_genre_profiles() -> HashMap<String, GenreProfile> {
-        let mut profiles = HashMap::new();
-
-        // Music: balanced spectrum, low flatness (tonal), moderate ZCR
-        profiles.insert("music".to_string(), GenreProfile {
-            spectral_centroid_range: (500.0, 4000.0),
-            spectral_flatness_range: (0.0, 0.3),
-            zcr_range: (0.02, 0.15),
-            band_weights: BandWeights {
-                sub_bass: 0.15,
-                bass: 0.20,
-                low_mid: 0.20,
-                mid: 0.20,
-                high_mid: 0.15,
-                high: 0.10,
-            },
-        });
-
-        // Speech: mid-range centroid, low ZCR, concentrated in mid frequencies
-        profiles.insert("speech".to_string(), GenreProfile {
-            spectral_centroid_range: (300.0, 2000.0),
-            spectral_flatness_range: (0.1, 0.5),
-            zcr_range: (0.01, 0.08),
-            band_weights: BandWeights {
-                sub_bass: 0.05,
-                bass: 0.10,
-                low_mid: 0.25,
-                mid: 0.35,
-                high_mid: 0.15,
-                high: 0.10,
-            },
-        });
-
-        // Gaming: wide spectrum, high energy variation, high ZCR
-        profiles.insert("gaming".to_string(), GenreProfile {
-            spectral_centroid_range: (1000.0, 6000.0),
-            spectral_flatness_range: (0.2, 0.7),
-            zcr_range: (0.05, 0.20),
-            band_weights: BandWeights {
-                sub_bass: 0.15,
-                bass: 0.15,
-                low_mid: 0.15,
-                mid: 0.20,
-                high_mid: 0.20,
-                high: 0.15,
-            },
-        });
-
-        // Nature: low centroid, high flatness (noise-like), low ZCR
-        profiles.insert("nature".to_string(), GenreProfile {
-            spectral_centroid_range: (200.0, 2000.0),
-            spectral_flatness_range: (0.4, 0.9),
-            zcr_range: (0.01, 0.06),
-            band_weights: BandWeights {
-                sub_bass: 0.10,
-                bass: 0.15,
-                low_mid: 0.20,
-                mid: 0.25,
-                high_mid: 0.15,
-                high: 0.15,
-            },
-        });
+    /// Use `taxonomy` instead of the built-in one to give tags their ids.
+    pub fn with_taxonomy(mut self, taxonomy: TagTaxonomy) -> Self {
+        self.taxonomy = taxonomy;
+        self
+    }
 
-        // Podcast: similar to speech but with music intros
-        profiles.insert("podcast".to_string(), GenreProfile {
-            spectral_centroid_range: (300.0, 2500.0),
-            spectral_flatness_range: (0.1, 0.4),
-            zcr_range: (0.01, 0.10),
-            band_weights: BandWeights {
-                sub_bass: 0.05,
-                bass: 0.10,
-                low_mid: 0.25,
-                mid: 0.35,
-                high_mid: 0.15,
-                high: 0.10,
-            },
-        });
+    /// Taxonomy the tags' ids belong to, e.g. to look up display names.
+    pub fn taxonomy(&self) -> &TagTaxonomy {
+        &self.taxonomy
+    }
 
-        // Tutorial: clear speech with occasional UI sounds
-        profiles.insert("tutorial".to_string(), GenreProfile {
-            spectral_centroid_range: (400.0, 3000.0),
-            spectral_flatness_range: (0.1, 0.5),
-            zcr_range: (0.02, 0.12),
-            band_weights: BandWeights {
-                sub_bass: 0.05,
-                bass: 0.08,
-                low_mid: 0.20,
-                mid: 0.35,
-                high_mid: 0.20,
-                high: 0.12,
-            },
-        });
+    /// Rate input is resampled to before tagging; `None` tags at the input rate.
+    pub fn preferred_sample_rate(&self) -> Option<u32> {
+        self.config.analysis_rate
+    }
 
-        // News: professional speech, compressed dynamics
-        profiles.insert("news".to_string(), GenreProfile {
-            spectral_centroid_range: (350.0, 1800.0),
-            spectral_flatness_range: (0.1, 0.35),
-            zcr_range: (0.01, 0.06),
-            band_weights: BandWeights {
-                sub_bass: 0.03,
-                bass: 0.08,
-                low_mid: 0.25,
-                mid: 0.40,
-                high_mid: 0.15,
-                high: 0.09,
-            },
-        });
+    /// Score a genre's band energies over a custom band plan.
+    ///
+    /// `weights` gives the expected energy share of each band in `plan`, e.g.
+    /// scoring speech against [`BandPlan::telephone`] to emphasize the
+    /// 300-3400 Hz range.
+    pub fn set_band_profile(&mut self, genre: &str, plan: BandPlan, weights: Vec<f32>) -> Result<()> {
+        if weights.len() != plan.len() {
+            bail!("Band plan '{}' has {} bands but {} weights were given", plan.name, plan.len(), weights.len());
+        }
+        let Some(profile) = self.genre_profiles.get_mut(genre) else {
+            bail!("Unknown genre '{}'", genre);
+        };
 
-        // Sports: crowd noise, commentary, high energy
-        profiles.insert("sports".to_string(), GenreProfile {
-            spectral_centroid_range: (500.0, 4000.0),
-            spectral_flatness_range: (0.3, 0.7),
-            zcr_range: (0.04, 0.15),
-            band_weights: BandWeights {
-                sub_bass: 0.10,
-                bass: 0.15,
-                low_mid: 0.20,
-                mid: 0.25,
-                high_mid: 0.18,
-                high: 0.12,
-            },
-        });
+        profile.custom_bands = Some(CustomBands { plan, weights });
+        Ok(())
+    }
 
-        profiles
+    /// Default genre profiles, shared with the WASM tagger.
+    fn default_genre_profiles() -> HashMap<String, GenreProfile> {
+        GENRE_PROFILES.iter()
+            .map(|&rules| (rules.name.to_string(), GenreProfile { rules, custom_bands: None }))
+            .collect()
     }
 
     /// Predict content tags from audio data.
-    pub fn predict(&self, audio: &AudioData) -> Result<Vec<ContentTag>> {
-        info!("Predicting tags for {} samples", audio.samples.len());
-
-        // Extract frequency features
-        let features = self.extract_features(audio)?;
-        debug!("Extracted features: {:?}", features);
-
-        // Score against each genre profile
-        let mut scores: Vec<(String, f32)> = self.genre_profiles.iter()
-            .map(|(genre, profile)| {
-                let score = self.compute_profile_score(&features, profile);
-                (genre.clone(), score)
-            })
-            .collect();
-
-        // Sort by score descending
-        scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-
-        // Add mood tags based on features
-        let mood_tags = self.predict_mood(&features);
-
-        // Add content type tags
-        let content_type_tags = self.predict_content_type(&features);
+    ///
+    /// Files longer than `segment_window_secs` are tagged per window and the
+    /// window tags averaged, weighted by how much of the file each covers.
+    /// Quality tags, when enabled, are added after `max_tags` is applied.
+    /// Fails with [`FrequencyError::TooShort`] below one FFT window and
+    /// [`FrequencyError::ZeroSignal`] for silent or DC-only audio.
+    pub fn predict(&self, audio: &AudioData) -> FrequencyResult<Vec<ContentTag>> {
+        self.predict_cancellable(audio, &CancellationToken::new())
+    }
 
-        // Combine all tags
-        let min_conf = self.config.min_confidence;
-        let mut all_tags: Vec<ContentTag> = scores.into_iter()
-            .filter(|(_, score)| *score >= min_conf)
-            .take(self.config.max_tags)
-            .map(|(label, confidence)| ContentTag { label, confidence })
-            .collect();
+    /// [`predict`](Self::predict), checking `cancel` between windows and FFT
+    /// frames.
+    ///
+    /// Fails with [`FrequencyError::Cancelled`] once it fires; a window's
+    /// model inference, when enabled, is finished first.
+    #[instrument(
+        name = "predict",
+        skip_all,
+        fields(
+            samples = audio.samples.len(),
+            duration_secs = audio.duration_secs,
+            fft_size = self.config.fft_size,
+            elapsed_ms = Empty,
+        ),
+    )]
+    pub fn predict_cancellable(&self, audio: &AudioData, cancel: &CancellationToken) -> FrequencyResult<Vec<ContentTag>> {
+        let timer = StageTimer::start("predict");
+        let segments = self.segments(
+            audio,
+            self.config.segment_window_secs,
+            self.config.segment_hop_secs,
+            cancel,
+        );
+        let segments = timer.finish(segments)?;
+        let mut tags = merge_tags(segments.iter().map(|s| (s.end_secs - s.start_secs, s.tags.as_slice())));
+        tags.retain(|t| t.confidence >= self.config.min_confidence);
+        tags.truncate(self.config.max_tags);
+
+        if self.config.quality_tags {
+            let report = self.quality(audio, cancel)?;
+            tags.extend(report.tags.into_iter().filter(|t| t.confidence >= self.config.min_confidence));
+        }
+        Ok(tags)
+    }
 
-        // Filter mood and content type tags by min_confidence too
-        all_tags.extend(mood_tags.into_iter().filter(|t| t.confidence >= min_conf));
-        all_tags.extend(content_type_tags.into_iter().filter(|t| t.confidence >= min_conf));
+    /// Estimate perceptual quality: bandwidth ceiling, likely source bitrate
+    /// class and noise floor, with quality tags.
+    ///
+    /// Analyzed at the input rate, as resampling would move the ceiling.
+    /// Report tags are not filtered by `min_confidence`. Fails like
+    /// [`predict`](Self::predict).
+    pub fn analyze_quality(&self, audio: &AudioData) -> FrequencyResult<QualityReport> {
+        self.quality(audio, &CancellationToken::new())
+    }
 
-        // Sort by confidence and limit
-        all_tags.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal));
-        all_tags.truncate(self.config.max_tags);
+    fn quality(&self, audio: &AudioData, cancel: &CancellationToken) -> FrequencyResult<QualityReport> {
+        let audio = audio.prepare(self.config.sample_policy, self.config.fft_size)?;
+        let audio = audio.to_mono();
+        let spectrogram = self.analyzer.compute_spectrogram_cancellable(&audio.samples, cancel)?;
+        let mut report = quality::analyze(
+            &spectrogram,
+            &audio.samples,
+            audio.sample_rate,
+            self.config.fft_size,
+            self.config.hop_size,
+        );
+        self.taxonomy.annotate(&mut report.tags);
+        debug!(
+            bandwidth_hz = report.bandwidth_hz,
+            noise_floor_db = report.noise_floor_db,
+            bitrate_class = ?report.bitrate_class,
+            "Estimated audio quality"
+        );
+        Ok(report)
+    }
 
-        Ok(all_tags)
+    /// Predict tags for each `window_secs` window, stepping by `hop_secs`.
+    ///
+    /// Each window owns the time from its start to the next window's start
+    /// (the last one runs to the end of the audio). Adjacent windows with the
+    /// same top tag are merged into a single segment whose tags are averaged
+    /// by duration. Audio shorter than one window is tagged as a whole.
+    pub fn predict_segments(
+        &self,
+        audio: &AudioData,
+        window_secs: f64,
+        hop_secs: f64,
+    ) -> FrequencyResult<Vec<TaggedSegment>> {
+        self.segments(audio, window_secs, hop_secs, &CancellationToken::new())
     }
 
-    /// Extract frequency features for classification.
-    fn extract_features(&self, audio: &AudioData) -> Result<AudioFeatures> {
-        let analysis = self.analyzer.analyze(&audio.samples, audio.sample_rate)?;
+    fn segments(
+        &self,
+        audio: &AudioData,
+        window_secs: f64,
+        hop_secs: f64,
+        cancel: &CancellationToken,
+    ) -> FrequencyResult<Vec<TaggedSegment>> {
+        if window_secs <= 0.0 || hop_secs <= 0.0 {
+            return Err(anyhow!("Segment window and hop must be positive").into());
+        }
 
-        Ok(AudioFeatures {
-            spectral_centroid: analysis.spectral_centroid,
-            _spectral_rolloff: analysis.spectral_rolloff,
-            spectral_flatness: analysis.spectral_flatness,
-            zero_crossing_rate: analysis.zero_crossing_rate,
-            band_energies: analysis.band_energies,
-            // Compute additional features
-            energy_variance: self.compute_energy_variance(audio)?,
-            tempo_estimate: self.estimate_tempo(audio)?,
-        })
-    }
+        let audio = self.prepare(audio)?;
+        info!("Predicting tags for {} samples", audio.samples.len());
 
-    /// Compute energy variance (dynamic range indicator).
-    fn compute_energy_variance(&self, audio: &AudioData) -> Result<f32> {
-        let frame_size = self.config.fft_size;
-        let hop_size = self.config.hop_size;
-        let num_frames = (audio.samples.len() - frame_size) / hop_size + 1;
+        let rate = audio.sample_rate as f64;
+        let window = ((window_secs * rate) as usize).min(audio.samples.len());
+        let hop = ((hop_secs * rate) as usize).max(1);
+        let starts: Vec<usize> = (0..)
+            .map(|i| i * hop)
+            .take_while(|&start| start == 0 || start + window <= audio.samples.len())
+            .collect();
 
-        let mut energies = Vec::with_capacity(num_frames);
+        let mut segments: Vec<TaggedSegment> = Vec::new();
+        for (i, &start) in starts.iter().enumerate() {
+            cancel::check(cancel)?;
+            let samples = audio.samples[start..start + window].to_vec();
+            let tags = self.tag_window(&AudioData::new(samples, audio.sample_rate), cancel)?;
+            let end = starts.get(i + 1).copied().unwrap_or(audio.samples.len());
+            let segment = TaggedSegment {
+                start_secs: start as f64 / rate,
+                end_secs: end as f64 / rate,
+                tags,
+            };
+
+            // Changepoint merge: extend the previous segment while the top tag holds
+            match segments.last_mut() {
+                Some(last) if top_label(&last.tags) == top_label(&segment.tags) => {
+                    let mut tags = merge_tags([
+                        (last.end_secs - last.start_secs, last.tags.as_slice()),
+                        (segment.end_secs - segment.start_secs, segment.tags.as_slice()),
+                    ]);
+                    tags.truncate(self.config.max_tags);
+                    last.end_secs = segment.end_secs;
+                    last.tags = tags;
+                }
+                _ => segments.push(segment),
+            }
+        }
 
-        for i in 0..num_frames {
-            let start = i * hop_size;
-            let end = start + frame_size;
-            let frame = &audio.samples[start..end.min(audio.samples.len())];
+        debug!("Tagged {} windows into {} segments", starts.len(), segments.len());
+        Ok(segments)
+    }
 
-            let energy: f32 = frame.iter().map(|&s| s * s).sum::<f32>() / frame.len() as f32;
-            energies.push(energy);
+    /// Identify the spoken languages, e.g. to order caption tracks.
+    ///
+    /// Only detected speech is classified, in chunks of up to ten seconds.
+    /// Returns nothing when there is no speech or no language model is
+    /// loaded, and drops languages below `min_confidence`. Sorted by
+    /// coverage, highest first.
+    pub fn detect_languages(&self, audio: &AudioData) -> FrequencyResult<Vec<LanguageTag>> {
+        let audio = self.prepare(audio)?;
+        let speech = &self.genre_profiles["speech"];
+        let gate = language::SpeechGate {
+            zcr_range: speech.rules.zcr_range,
+            flatness_range: speech.rules.spectral_flatness_range,
+        };
+        let segments = language::speech_segments(&self.analyzer, &gate, &audio)?;
+        if segments.is_empty() {
+            debug!("No speech detected, skipping language identification");
+            return Ok(Vec::new());
         }
 
-        // Compute variance
-        let mean: f32 = energies.iter().sum::<f32>() / energies.len() as f32;
-        let variance: f32 = energies.iter()
-            .map(|&e| (e - mean) * (e - mean))
-            .sum::<f32>() / energies.len() as f32;
+        #[cfg(feature = "onnx")]
+        if let Some(classifier) = &self.language_classifier {
+            let rate = audio.sample_rate as f64;
+            let chunks = segments.into_iter()
+                .flat_map(|segment| language::split_segment(segment, (language::MAX_CHUNK_SECS * rate) as usize));
+
+            let mut spans = Vec::new();
+            for chunk in chunks {
+                let secs = chunk.len() as f64 / rate;
+                let samples = AudioData::new(audio.samples[chunk].to_vec(), audio.sample_rate);
+                match classifier.predict(&self.analyzer, &samples) {
+                    Ok(tags) => spans.push((secs, tags)),
+                    Err(e) => warn!("Language identification failed for a speech chunk: {:#}", e),
+                }
+            }
 
-        Ok(variance.sqrt())
+            let mut languages = language::aggregate_languages(&spans);
+            languages.retain(|l| l.confidence >= self.config.min_confidence);
+            return Ok(languages);
+        }
+        Ok(Vec::new())
     }
 
-    /// Estimate tempo using autocorrelation.
-    fn estimate_tempo(&self, audio: &AudioData) -> Result<f32> {
-        // Simple onset detection via energy derivative
-        let frame_size = 1024;
-        let hop_size = 512;
+    /// Validate, downmix, resample and optionally loudness-normalize input before analysis.
+    fn prepare<'a>(&self, audio: &'a AudioData) -> FrequencyResult<Cow<'a, AudioData>> {
+        let audio = match audio.prepare(self.config.sample_policy, self.config.fft_size)? {
+            Cow::Borrowed(audio) => audio.to_mono(),
+            Cow::Owned(audio) => Cow::Owned(audio.to_mono().into_owned()),
+        };
+        let audio = match self.config.analysis_rate {
+            Some(rate) if !resample::within_tolerance(audio.sample_rate, rate) => {
+                debug!("Resampling from {}Hz to {}Hz for tagging", audio.sample_rate, rate);
+                Cow::Owned(resample::resample(&audio, rate))
+            }
+            _ => audio,
+        };
 
-        let num_frames = (audio.samples.len() - frame_size) / hop_size;
-        if num_frames < 2 {
-            return Ok(120.0); // Default tempo
+        match self.config.normalize_loudness {
+            Some(target) => {
+                let measured = loudness::measure(&audio)?;
+                debug!("Normalizing from {:.1} LUFS to {:.1} LUFS", measured.integrated_lufs, target);
+                Ok(Cow::Owned(loudness::normalize(&audio, &measured, target)))
+            }
+            None => Ok(audio),
         }
+    }
 
-        let mut energies = Vec::with_capacity(num_frames);
-        for i in 0..num_frames {
-            let start = i * hop_size;
-            let end = start + frame_size;
-            let energy: f32 = audio.samples[start..end]
-                .iter()
-                .map(|&s| s * s)
-                .sum();
-            energies.push(energy);
-        }
+    /// Tag a single prepared window of mono audio.
+    fn tag_window(&self, audio: &AudioData, cancel: &CancellationToken) -> FrequencyResult<Vec<ContentTag>> {
+        // Extract frequency features
+        let features = self.extract_features(audio, cancel)?;
+        debug!("Extracted features: {:?}", features);
 
-        // Compute onset strength (energy derivative)
-        let onset_strength: Vec<f32> = energies.windows(2)
-            .map(|w| (w[1] - w[0]).max(0.0))
+        // Score against each genre profile
+        let scores: Vec<(&'static str, f32)> = self.genre_profiles.values()
+            .map(|profile| (profile.rules.name, self.compute_profile_score(&features, profile)))
             .collect();
 
-        // Autocorrelation for tempo estimation
-        let max_lag = (4.0 * audio.sample_rate as f32 / hop_size as f32) as usize; // Up to 4 seconds
-        let min_lag = (0.25 * audio.sample_rate as f32 / hop_size as f32) as usize; // At least 0.25 seconds
-
-        let mut best_lag = min_lag;
-        let mut best_corr = 0.0f32;
-
-        for lag in min_lag..max_lag.min(onset_strength.len()) {
-            let corr: f32 = onset_strength.iter()
-                .zip(onset_strength.iter().skip(lag))
-                .map(|(&a, &b)| a * b)
-                .sum();
+        // Best genres plus mood and content type tags
+        let min_conf = self.config.min_confidence;
+        let mut all_tags: Vec<ContentTag> = rule_tags(&features.rules, scores, min_conf, self.config.max_tags)
+            .into_iter()
+            .map(|(label, confidence)| ContentTag::new(label, confidence))
+            .collect();
 
-            if corr > best_corr {
-                best_corr = corr;
-                best_lag = lag;
+        #[cfg(feature = "onnx")]
+        if let Some(classifier) = &self.classifier {
+            match classifier.predict(&self.analyzer, audio) {
+                Ok(ml_tags) => {
+                    all_tags = ml::blend_tags(&all_tags, &ml_tags, classifier.blend_weight());
+                    all_tags.retain(|t| t.confidence >= min_conf);
+                }
+                Err(e) => warn!("Model inference failed, using rule-based tags: {:#}", e),
             }
         }
 
-        // Convert lag to BPM
-        let beat_period_secs = best_lag as f32 * hop_size as f32 / audio.sample_rate as f32;
-        let bpm = if beat_period_secs > 0.0 {
-            60.0 / beat_period_secs
-        } else {
-            120.0
-        };
+        // Sort by confidence and limit
+        all_tags.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        all_tags.truncate(self.config.max_tags);
+        self.taxonomy.annotate(&mut all_tags);
 
-        Ok(bpm.clamp(60.0, 200.0))
+        Ok(all_tags)
+    }
+
+    /// Extract frequency features for classification.
+    fn extract_features(&self, audio: &AudioData, cancel: &CancellationToken) -> FrequencyResult<AudioFeatures> {
+        let analysis = self.analyzer.analyze_cancellable(&audio.samples, audio.sample_rate, cancel)?;
+
+        Ok(AudioFeatures {
+            rules: TagFeatures {
+                spectral_centroid: analysis.spectral_centroid,
+                spectral_flatness: analysis.spectral_flatness,
+                zero_crossing_rate: analysis.zero_crossing_rate,
+                energy_variance: signal::energy_variance(&audio.samples, self.config.fft_size, self.config.hop_size),
+                tempo_estimate: signal::estimate_tempo(&audio.samples, audio.sample_rate),
+            },
+            speech_coverage: vad::speech_coverage(&vad::detect_speech(audio, &self.config.vad), audio.duration_secs),
+            _spectral_rolloff: analysis.spectral_rolloff,
+            band_energies: analysis.band_energies,
+            spectrum: analysis.spectrum,
+            frequencies: analysis.frequencies,
+        })
     }
 
     /// Compute score against a genre profile.
     fn compute_profile_score(&self, features: &AudioFeatures, profile: &GenreProfile) -> f32 {
-        let mut score = 0.0f32;
-
-        // Spectral centroid match
-        let centroid_score = if features.spectral_centroid >= profile.spectral_centroid_range.0
-            && features.spectral_centroid <= profile.spectral_centroid_range.1 {
-            1.0
-        } else {
-            let dist = (features.spectral_centroid - profile.spectral_centroid_range.0)
-                .min(features.spectral_centroid - profile.spectral_centroid_range.1)
-                .abs();
-            (1.0 - dist / 2000.0).max(0.0)
-        };
-        score += centroid_score * 0.25;
+        let score = profile.rules.score(&features.rules, self.compute_band_match(features, profile));
+        if !SPOKEN_GENRES.contains(&profile.rules.name) {
+            return score;
+        }
+        let weight = self.config.speech_coverage_weight.clamp(0.0, 1.0);
+        score + (1.0 - score) * features.speech_coverage * weight
+    }
 
-        // Spectral flatness match
-        let flatness_score = if features.spectral_flatness >= profile.spectral_flatness_range.0
-            && features.spectral_flatness <= profile.spectral_flatness_range.1 {
-            1.0
-        } else {
-            let dist = (features.spectral_flatness - profile.spectral_flatness_range.0)
-                .min(features.spectral_flatness - profile.spectral_flatness_range.1)
-                .abs();
-            (1.0 - dist * 2.0).max(0.0)
+    /// Compute band energy distribution match.
+    fn compute_band_match(&self, features: &AudioFeatures, profile: &GenreProfile) -> f32 {
+        let Some(custom) = &profile.custom_bands else {
+            let energies = &features.band_energies;
+            return profile.rules.band_match(&[
+                energies.sub_bass(), energies.bass(), energies.low_mid(),
+                energies.mid(), energies.high_mid(), energies.high(),
+            ]);
         };
-        score += flatness_score * 0.25;
 
-        // ZCR match
-        let zcr_score = if features.zero_crossing_rate >= profile.zcr_range.0
-            && features.zero_crossing_rate <= profile.zcr_range.1 {
-            1.0
+        let energies = if features.band_energies.plan == custom.plan {
+            Cow::Borrowed(&features.band_energies)
         } else {
-            let dist = (features.zero_crossing_rate - profile.zcr_range.0)
-                .min(features.zero_crossing_rate - profile.zcr_range.1)
-                .abs();
-            (1.0 - dist * 10.0).max(0.0)
+            Cow::Owned(BandEnergies::from_spectrum_with_plan(&features.spectrum, &features.frequencies, &custom.plan))
         };
-        score += zcr_score * 0.2;
-
-        // Band energy distribution match
-        let band_score = self.compute_band_match(&features.band_energies, &profile.band_weights);
-        score += band_score * 0.3;
-
-        score
-    }
-
-    /// Compute band energy distribution match.
-    fn compute_band_match(&self, energies: &BandEnergies, weights: &BandWeights) -> f32 {
-        let features = [
-            energies.sub_bass, energies.bass, energies.low_mid,
-            energies.mid, energies.high_mid, energies.high,
-        ];
-        let targets = [
-            weights.sub_bass, weights.bass, weights.low_mid,
-            weights.mid, weights.high_mid, weights.high,
-        ];
-
-        // Cosine similarity
-        let dot: f32 = features.iter().zip(targets.iter()).map(|(a, b)| a * b).sum();
-        let norm_a: f32 = features.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let norm_b: f32 = targets.iter().map(|x| x * x).sum::<f32>().sqrt();
-
-        if norm_a > 0.0 && norm_b > 0.0 {
-            dot / (norm_a * norm_b)
-        } else {
-            0.0
-        }
+        cosine_similarity(&energies.energies, &custom.weights)
     }
+}
 
-    /// Predict mood tags based on features.
-    fn predict_mood(&self, features: &AudioFeatures) -> Vec<ContentTag> {
-        let mut tags = Vec::new();
-
-        // Energetic: high tempo, high energy variance, high centroid
-        if features.tempo_estimate > 140.0 && features.spectral_centroid > 2000.0 {
-            tags.push(ContentTag {
-                label: "energetic".to_string(),
-                confidence: 0.7,
-            });
-        }
+/// Genres whose scores detected speech raises.
+const SPOKEN_GENRES: [&str; 4] = ["speech", "podcast", "tutorial", "news"];
 
-        // Calm: low tempo, low centroid, low energy variance
-        if features.tempo_estimate < 90.0 && features.spectral_centroid < 1500.0 {
-            tags.push(ContentTag {
-                label: "calm".to_string(),
-                confidence: 0.7,
-            });
-        }
+/// Label of the highest-confidence tag.
+fn top_label(tags: &[ContentTag]) -> Option<&str> {
+    tags.first().map(|t| t.label.as_str())
+}
 
-        // Dramatic: high energy variance
-        if features.energy_variance > 0.1 {
-            tags.push(ContentTag {
-                label: "dramatic".to_string(),
-                confidence: 0.5,
-            });
+/// Average tag confidences over `(duration, tags)` spans, weighted by
+/// duration. Tags missing from a span count as zero confidence there.
+fn merge_tags<'a, I>(spans: I) -> Vec<ContentTag>
+where
+    I: IntoIterator<Item = (f64, &'a [ContentTag])>,
+{
+    let mut totals: HashMap<&str, (f64, &ContentTag)> = HashMap::new();
+    let mut duration = 0.0;
+    for (secs, tags) in spans {
+        duration += secs;
+        for tag in tags {
+            totals.entry(tag.label.as_str()).or_insert((0.0, tag)).0 += tag.confidence as f64 * secs;
         }
-
-        tags
     }
 
-    /// Predict content type tags.
-    fn predict_content_type(&self, features: &AudioFeatures) -> Vec<ContentTag> {
-        let mut tags = Vec::new();
-
-        // Vocal: mid-range centroid, low flatness
-        if features.spectral_centroid > 300.0 && features.spectral_centroid < 2000.0
-            && features.spectral_flatness < 0.3 {
-            tags.push(ContentTag {
-                label: "vocal".to_string(),
-                confidence: 0.6,
-            });
-        }
-
-        // Instrumental: low flatness, not in vocal range
-        if features.spectral_flatness < 0.25
-            && (features.spectral_centroid < 300.0 || features.spectral_centroid > 2500.0) {
-            tags.push(ContentTag {
-                label: "instrumental".to_string(),
-                confidence: 0.5,
-            });
-        }
-
-        // Ambient: high flatness, low energy variance
-        if features.spectral_flatness > 0.5 && features.energy_variance < 0.05 {
-            tags.push(ContentTag {
-                label: "ambient".to_string(),
-                confidence: 0.6,
-            });
-        }
-
-        tags
-    }
+    let mut merged: Vec<ContentTag> = totals.into_values()
+        .map(|(total, tag)| ContentTag {
+            confidence: if duration > 0.0 { (total / duration) as f32 } else { 0.0 },
+            ..tag.clone()
+        })
+        .collect();
+    merged.sort_by(|a, b| {
+        b.confidence.partial_cmp(&a.confidence)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    merged
 }
 
 impl Default for ContentTagger {
@@ -485,33 +588,31 @@ impl Default for ContentTagger {
 /// Audio features for classification.
 #[derive(Debug, Clone)]
 struct AudioFeatures {
-    spectral_centroid: f32,
+    /// Features scored by the shared tagging rules
+    rules: TagFeatures,
+    /// Fraction of the window with detected speech
+    speech_coverage: f32,
     _spectral_rolloff: f32,
-    spectral_flatness: f32,
-    zero_crossing_rate: f32,
     band_energies: BandEnergies,
-    energy_variance: f32,
-    tempo_estimate: f32,
+    /// Average spectrum, kept to compute energies for custom band plans
+    spectrum: Vec<f32>,
+    frequencies: Vec<f32>,
 }
 
 /// Genre classification profile.
 #[derive(Debug, Clone)]
 struct GenreProfile {
-    spectral_centroid_range: (f32, f32),
-    spectral_flatness_range: (f32, f32),
-    zcr_range: (f32, f32),
-    band_weights: BandWeights,
+    /// Shared feature ranges and standard-plan band weights
+    rules: kino_tagging::GenreProfile,
+    /// Custom band plan scored instead of the standard band weights
+    custom_bands: Option<CustomBands>,
 }
 
-/// Expected band energy weights for a genre.
+/// Expected band energy weights over a custom band plan.
 #[derive(Debug, Clone)]
-struct BandWeights {
-    sub_bass: f32,
-    bass: f32,
-    low_mid: f32,
-    mid: f32,
-    high_mid: f32,
-    high: f32,
+struct CustomBands {
+    plan: BandPlan,
+    weights: Vec<f32>,
 }
 
 #[cfg(test)]
@@ -577,6 +678,127 @@ mod tests {
         assert!(!tags.is_empty());
     }
 
+    #[test]
+    fn test_loudness_normalization_makes_tags_level_independent() {
+        // Tone alternating between full and -20dB level every half second
+        let tone = generate_test_audio(440.0, 5.0);
+        let loud = AudioData::new(
+            tone.samples.iter()
+                .enumerate()
+                .map(|(i, s)| if (i / 22050) % 2 == 0 { *s } else { s * 0.1 })
+                .collect(),
+            tone.sample_rate,
+        );
+        let quiet = AudioData::new(loud.samples.iter().map(|s| s * 0.05).collect(), loud.sample_rate);
+
+        let labels = |tagger: &ContentTagger, audio: &AudioData| -> Vec<String> {
+            tagger.predict(audio).unwrap().into_iter().map(|t| t.label).collect()
+        };
+
+        // Absolute energy thresholds separate the two recordings...
+        let plain = ContentTagger::with_config(TaggingConfig {
+            max_tags: 20,
+            ..Default::default()
+        });
+        assert_ne!(labels(&plain, &loud), labels(&plain, &quiet));
+
+        // ...until both are brought to the same loudness
+        let normalizing = ContentTagger::with_config(TaggingConfig {
+            normalize_loudness: Some(-23.0),
+            max_tags: 20,
+            ..Default::default()
+        });
+        assert_eq!(labels(&normalizing, &loud), labels(&normalizing, &quiet));
+    }
+
+    #[test]
+    fn test_speech_coverage_moves_spoken_genres() {
+        let speech_like = crate::testing::speech_like(44100, 6.0);
+        let tone = generate_test_audio(440.0, 6.0);
+
+        let confidence = |weight: f32, audio: &AudioData| -> f32 {
+            let tagger = ContentTagger::with_config(TaggingConfig {
+                speech_coverage_weight: weight,
+                min_confidence: 0.0,
+                max_tags: 20,
+                ..Default::default()
+            });
+            let tags = tagger.predict(audio).unwrap();
+            tags.iter().find(|t| t.label == "speech").map_or(0.0, |t| t.confidence)
+        };
+
+        assert!(confidence(0.5, &speech_like) > confidence(0.0, &speech_like));
+        assert_eq!(confidence(0.5, &tone), confidence(0.0, &tone));
+    }
+
+    #[test]
+    fn test_tags_independent_of_input_rate() {
+        let audio = generate_test_audio(440.0, 5.0);
+        let upsampled = crate::resample::resample(&audio, 48000);
+
+        let tagger = ContentTagger::new();
+        assert_eq!(tagger.preferred_sample_rate(), Some(44100));
+        let labels = |audio: &AudioData| -> Vec<String> {
+            tagger.predict(audio).unwrap().into_iter().map(|t| t.label).collect()
+        };
+        assert_eq!(labels(&audio), labels(&upsampled));
+    }
+
+    #[test]
+    fn test_segments_split_music_from_ambient() {
+        let music = generate_test_audio(440.0, 10.0);
+        let ambient = generate_noise(5.0);
+        let spliced = AudioData::new(
+            music.samples.iter().chain(&ambient.samples).copied().collect(),
+            music.sample_rate,
+        );
+
+        let tagger = ContentTagger::new();
+        let segments = tagger.predict_segments(&spliced, 5.0, 5.0).unwrap();
+
+        // The two music windows collapse into one segment
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].start_secs, 0.0);
+        assert!((segments[0].end_secs - 10.0).abs() < 1e-6);
+        assert!((segments[1].end_secs - 15.0).abs() < 1e-6);
+        assert_ne!(segments[0].tags[0].label, segments[1].tags[0].label);
+
+        // Whole-file tags are diluted by the ambient section
+        let whole = tagger.predict(&spliced).unwrap();
+        let music_top = &segments[0].tags[0];
+        let whole_conf = whole.iter()
+            .find(|t| t.label == music_top.label)
+            .map_or(0.0, |t| t.confidence);
+        assert!(whole_conf < music_top.confidence);
+    }
+
+    #[test]
+    fn test_short_audio_is_a_single_segment() {
+        let audio = generate_test_audio(440.0, 5.0);
+        let tagger = ContentTagger::new();
+
+        let segments = tagger.predict_segments(&audio, 30.0, 30.0).unwrap();
+        assert_eq!(segments.len(), 1);
+        assert!((segments[0].end_secs - 5.0).abs() < 1e-6);
+        assert!(tagger.predict_segments(&audio, 0.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_unavailable_model_falls_back_to_rules() {
+        let audio = generate_test_audio(440.0, 5.0);
+        let tags = |tagger: &ContentTagger| -> Vec<(String, f32)> {
+            tagger.predict(&audio).unwrap().into_iter().map(|t| (t.label, t.confidence)).collect()
+        };
+
+        let rules = ContentTagger::new();
+        let ml = ContentTagger::with_config(TaggingConfig {
+            use_ml_model: true,
+            ml_model: Some(MlModelConfig::new("/nonexistent/model.onnx")),
+            ..Default::default()
+        });
+        assert_eq!(tags(&ml), tags(&rules));
+    }
+
     #[test]
     fn test_min_confidence_filter() {
         let audio = generate_test_audio(440.0, 5.0);
@@ -599,4 +821,115 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_custom_band_profile() {
+        let audio = generate_test_audio(1000.0, 2.0);
+        let mut tagger = ContentTagger::new();
+
+        assert!(tagger.set_band_profile("speech", BandPlan::telephone(), vec![1.0]).is_err());
+        assert!(tagger.set_band_profile("opera", BandPlan::telephone(), vec![0.0; 6]).is_err());
+
+        // 1 kHz falls in the telephone plan's 800-1500 Hz band
+        let weights = vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+        tagger.set_band_profile("speech", BandPlan::telephone(), weights).unwrap();
+
+        let features = tagger.extract_features(&audio, &CancellationToken::new()).unwrap();
+        let score = tagger.compute_band_match(&features, &tagger.genre_profiles["speech"]);
+        assert!(score > 0.9, "band match {:.3}", score);
+    }
+
+    #[test]
+    fn test_tags_carry_taxonomy_ids() {
+        let audio = generate_test_audio(440.0, 2.0);
+        let tags = ContentTagger::new().predict(&audio).unwrap();
+        assert!(!tags.is_empty());
+        for tag in &tags {
+            let id = tag.id.as_deref().unwrap_or_else(|| panic!("'{}' has no id", tag.label));
+            assert_eq!(ContentTagger::new().taxonomy().resolve(&tag.label), Some(id));
+        }
+
+        // A custom taxonomy without the labels leaves tags without ids
+        let taxonomy = TagTaxonomy::from_json(r#"{"tags": [{"id": "other", "name": "Other"}]}"#).unwrap();
+        let tagger = ContentTagger::new().with_taxonomy(taxonomy);
+        assert!(tagger.predict(&audio).unwrap().iter().all(|t| t.id.is_none()));
+    }
+
+    /// Equal-amplitude partials every 173 Hz from 100 Hz to 21 kHz
+    fn generate_full_band(duration_secs: f32) -> AudioData {
+        let sample_rate = 44100;
+        let num_samples = (sample_rate as f32 * duration_secs) as usize;
+        let partials: Vec<(f32, f32)> = (0..121)
+            .map(|k| (100.0 + 173.0 * k as f32, k as f32 * 2.39996))
+            .collect();
+        let samples: Vec<f32> = (0..num_samples)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                partials.iter()
+                    .map(|&(freq, phase)| (2.0 * std::f32::consts::PI * freq * t + phase).sin())
+                    .sum::<f32>() * 0.01
+            })
+            .collect();
+
+        AudioData::new(samples, sample_rate)
+    }
+
+    fn tag_confidence(tags: &[ContentTag], label: &str) -> Option<f32> {
+        tags.iter().find(|t| t.label == label).map(|t| t.confidence)
+    }
+
+    #[test]
+    fn test_quality_detects_lowpass_ceiling() {
+        let full = generate_full_band(3.0);
+        let tagger = ContentTagger::new();
+
+        let report = tagger.analyze_quality(&full).unwrap();
+        assert!(report.bandwidth_hz > 20_500.0, "ceiling {} Hz", report.bandwidth_hz);
+        assert!(!report.codec_lowpass);
+        assert_eq!(report.bitrate_class, BitrateClass::Lossless);
+        assert!(tag_confidence(&report.tags, "high-fidelity").unwrap() > 0.9);
+        assert!(tag_confidence(&report.tags, "compressed").is_none());
+
+        let filtered = FrequencyAnalyzer::new(4096, 2048)
+            .bandpass_filter(&full.samples, full.sample_rate, 0.0, 15_000.0)
+            .unwrap();
+        let report = tagger.analyze_quality(&AudioData::new(filtered, full.sample_rate)).unwrap();
+        assert!((report.bandwidth_hz - 15_000.0).abs() < 500.0, "ceiling {} Hz", report.bandwidth_hz);
+        assert!(report.codec_lowpass, "drop {} dB", report.cutoff_drop_db);
+        assert_eq!(report.bitrate_class, BitrateClass::Low);
+        assert!(tag_confidence(&report.tags, "compressed").unwrap() > 0.5);
+        assert!(tag_confidence(&report.tags, "high-fidelity").is_none());
+        assert!(tag_confidence(&report.tags, "noisy").is_none());
+    }
+
+    #[test]
+    fn test_quality_tags_noise() {
+        let mut noise = generate_noise(3.0);
+        noise.samples.iter_mut().for_each(|s| *s *= 0.3);
+        let report = ContentTagger::new().analyze_quality(&noise).unwrap();
+        assert!(report.noise_floor_db > -20.0, "noise floor {} dBFS", report.noise_floor_db);
+        assert!(tag_confidence(&report.tags, "noisy").unwrap() > 0.9);
+        assert!(tag_confidence(&report.tags, "high-fidelity").is_none());
+
+        // predict only adds them when asked to
+        assert!(tag_confidence(&ContentTagger::new().predict(&noise).unwrap(), "noisy").is_none());
+        let tagger = ContentTagger::with_config(TaggingConfig { quality_tags: true, ..Default::default() });
+        assert!(tag_confidence(&tagger.predict(&noise).unwrap(), "noisy").is_some());
+    }
+
+    #[test]
+    fn test_predict_cancellation() {
+        let audio = generate_test_audio(440.0, 6.0);
+        let tagger = ContentTagger::with_config(TaggingConfig { quality_tags: true, ..Default::default() });
+        let cancel = CancellationToken::new();
+        let labels = |tags: Vec<ContentTag>| tags.into_iter().map(|t| t.label).collect::<Vec<_>>();
+        assert_eq!(
+            labels(tagger.predict_cancellable(&audio, &cancel).unwrap()),
+            labels(tagger.predict(&audio).unwrap()),
+        );
+
+        cancel.cancel();
+        let err = tagger.predict_cancellable(&audio, &cancel).unwrap_err();
+        assert!(matches!(err, FrequencyError::Cancelled), "{:?}", err);
+    }
 }
@@ -0,0 +1,208 @@
+//! Speech detection and language aggregation for spoken language ID.
+//!
+//! Speech is found with the rule-based speech profile's ZCR and spectral
+//! flatness ranges plus a level gate, so music and ambience never reach the
+//! language classifier. Per-chunk predictions are then pooled by duration.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use anyhow::Result;
+
+use crate::fft::FrequencyAnalyzer;
+use crate::types::*;
+
+/// Length of each block classified as speech or not.
+const BLOCK_SECS: f64 = 0.5;
+/// Block RMS below which audio counts as silence (about -40 dBFS).
+const MIN_SPEECH_RMS: f32 = 0.01;
+/// Shortest speech run worth classifying.
+const MIN_SPEECH_SECS: f64 = 1.0;
+/// Longest chunk classified at once; longer speech runs are split.
+pub(crate) const MAX_CHUNK_SECS: f64 = 10.0;
+
+/// Feature ranges a block must fall in to count as speech.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SpeechGate {
+    pub zcr_range: (f32, f32),
+    pub flatness_range: (f32, f32),
+}
+
+impl SpeechGate {
+    fn accepts(&self, block: &[f32], analysis: &FrequencyAnalysis) -> bool {
+        let rms = (block.iter().map(|&s| s * s).sum::<f32>() / block.len() as f32).sqrt();
+        let within = |value: f32, (low, high): (f32, f32)| value >= low && value <= high;
+
+        rms >= MIN_SPEECH_RMS
+            && within(analysis.zero_crossing_rate, self.zcr_range)
+            && within(analysis.spectral_flatness, self.flatness_range)
+    }
+}
+
+/// Sample ranges of mono `audio` that contain speech.
+///
+/// Single non-speech blocks between speech (pauses between words) are
+/// bridged; runs shorter than a second are dropped.
+pub(crate) fn speech_segments(
+    analyzer: &FrequencyAnalyzer,
+    gate: &SpeechGate,
+    audio: &AudioData,
+) -> Result<Vec<Range<usize>>> {
+    let block = ((BLOCK_SECS * audio.sample_rate as f64) as usize).max(analyzer.fft_size());
+    let mut speech = Vec::new();
+    for samples in audio.samples.chunks(block) {
+        if samples.len() < analyzer.fft_size() {
+            break;
+        }
+        let analysis = analyzer.analyze(samples, audio.sample_rate)?;
+        speech.push(gate.accepts(samples, &analysis));
+    }
+
+    for i in 1..speech.len().saturating_sub(1) {
+        if !speech[i] && speech[i - 1] && speech[i + 1] {
+            speech[i] = true;
+        }
+    }
+
+    let min_len = (MIN_SPEECH_SECS * audio.sample_rate as f64) as usize;
+    let mut segments: Vec<Range<usize>> = Vec::new();
+    for (i, _) in speech.iter().enumerate().filter(|(_, &is_speech)| is_speech) {
+        let range = i * block..((i + 1) * block).min(audio.samples.len());
+        match segments.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => segments.push(range),
+        }
+    }
+    segments.retain(|s| s.len() >= min_len);
+    Ok(segments)
+}
+
+/// Split `range` into equal chunks of at most `max_len` samples.
+pub(crate) fn split_segment(range: Range<usize>, max_len: usize) -> Vec<Range<usize>> {
+    let chunks = range.len().div_ceil(max_len.max(1)).max(1);
+    let len = range.len() / chunks;
+    (0..chunks)
+        .map(|i| {
+            let start = range.start + i * len;
+            let end = if i + 1 == chunks { range.end } else { start + len };
+            start..end
+        })
+        .collect()
+}
+
+/// Pool per-chunk predictions given as `(duration, tags)`.
+///
+/// Each chunk's duration goes to its top language. Coverage is that share
+/// of the total, confidence the duration-weighted mean top confidence.
+/// Sorted by coverage, highest first.
+pub(crate) fn aggregate_languages(spans: &[(f64, Vec<ContentTag>)]) -> Vec<LanguageTag> {
+    let mut totals: HashMap<&str, (f64, f64)> = HashMap::new();
+    let mut speech = 0.0;
+    for (secs, tags) in spans {
+        let Some(top) = tags.iter().max_by(|a, b| a.confidence.total_cmp(&b.confidence)) else {
+            continue;
+        };
+        speech += secs;
+        let (duration, confidence) = totals.entry(top.label.as_str()).or_default();
+        *duration += secs;
+        *confidence += top.confidence as f64 * secs;
+    }
+    if speech <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut languages: Vec<LanguageTag> = totals.into_iter()
+        .map(|(language, (duration, confidence))| LanguageTag {
+            language: language.to_string(),
+            confidence: (confidence / duration) as f32,
+            coverage: (duration / speech) as f32,
+        })
+        .collect();
+    languages.sort_by(|a, b| {
+        b.coverage.total_cmp(&a.coverage)
+            .then_with(|| b.confidence.total_cmp(&a.confidence))
+            .then_with(|| a.language.cmp(&b.language))
+    });
+    languages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEECH_GATE: SpeechGate = SpeechGate {
+        zcr_range: (0.01, 0.08),
+        flatness_range: (0.1, 0.5),
+    };
+
+    /// Voiced, syllable-modulated harmonic signal with a little breath noise.
+    fn voiced(secs: f32) -> Vec<f32> {
+        let sample_rate = 44100.0;
+        let mut seed = 12345u64;
+        (0..(sample_rate * secs) as usize)
+            .map(|i| {
+                let t = i as f32 / sample_rate;
+                let envelope = 0.5 - 0.5 * (2.0 * std::f32::consts::PI * 4.0 * t).cos();
+                let voice: f32 = (1..=25)
+                    .map(|h| {
+                        let f = 200.0 * h as f32;
+                        let formants = (-((f - 700.0) / 300.0).powi(2)).exp()
+                            + 0.6 * (-((f - 1200.0) / 400.0).powi(2)).exp();
+                        (0.3 + formants) / h as f32 * (2.0 * std::f32::consts::PI * f * t).sin()
+                    })
+                    .sum();
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let noise = (seed >> 33) as f32 / (1u64 << 31) as f32 * 2.0 - 1.0;
+                envelope * (0.3 * voice + 0.005 * noise)
+            })
+            .collect()
+    }
+
+    fn tone(secs: f32) -> Vec<f32> {
+        (0..(44100.0 * secs) as usize)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44100.0).sin())
+            .collect()
+    }
+
+    fn segments(samples: Vec<f32>) -> Vec<Range<usize>> {
+        let analyzer = FrequencyAnalyzer::new(4096, 2048);
+        speech_segments(&analyzer, &SPEECH_GATE, &AudioData::new(samples, 44100)).unwrap()
+    }
+
+    #[test]
+    fn test_speech_segments_skip_music_and_silence() {
+        assert!(segments(tone(4.0)).is_empty());
+        assert!(segments(vec![0.0; 44100 * 4]).is_empty());
+
+        // Silence, then speech from 2 s to 6 s, then a tone
+        let mut samples = vec![0.0; 44100 * 2];
+        samples.extend(voiced(4.0));
+        samples.extend(tone(2.0));
+        let found = segments(samples);
+        assert_eq!(found, vec![44100 * 2..44100 * 6]);
+    }
+
+    #[test]
+    fn test_split_segment() {
+        assert_eq!(split_segment(0..10, 4), vec![0..3, 3..6, 6..10]);
+        assert_eq!(split_segment(5..8, 10), vec![5..8]);
+    }
+
+    #[test]
+    fn test_aggregate_languages() {
+        let tag = |label: &str, confidence: f32| ContentTag::new(label, confidence);
+        let languages = aggregate_languages(&[
+            (6.0, vec![tag("en", 0.9), tag("es", 0.1)]),
+            (2.0, vec![tag("es", 0.6), tag("en", 0.4)]),
+            (2.0, vec![tag("en", 0.7), tag("es", 0.3)]),
+        ]);
+
+        assert_eq!(languages.len(), 2);
+        assert_eq!(languages[0].language, "en");
+        assert!((languages[0].coverage - 0.8).abs() < 1e-6);
+        assert!((languages[0].confidence - 0.85).abs() < 1e-6);
+        assert_eq!(languages[1].to_content_tag().label, "lang:es");
+        assert!((languages[1].coverage - 0.2).abs() < 1e-6);
+
+        assert!(aggregate_languages(&[]).is_empty());
+    }
+}
@@ -0,0 +1,440 @@
+//! Model configuration, input features and tag blending for ML tagging.
+//!
+//! Everything here is independent of the inference runtime: features are
+//! computed in pure Rust and handed to the model as a flat tensor, and the
+//! model's class scores come back through [`scores_to_tags`].
+
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+
+use crate::fft::FrequencyAnalyzer;
+use crate::resample::resample_samples;
+use crate::types::*;
+
+/// Floor added to mel energies before taking the log.
+const LOG_FLOOR: f32 = 1e-6;
+
+/// Features fed to the model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModelInput {
+    /// The 128-dim [`FrequencySignature`] features, shape `[1, 128]`
+    Signature,
+    /// MFCC matrix, shape `[1, frames, n_coefficients]`
+    Mfcc {
+        /// Mel bands the coefficients are derived from
+        n_mels: usize,
+        /// Cepstral coefficients per frame
+        n_coefficients: usize,
+    },
+    /// Non-overlapping log-mel spectrogram patches, shape
+    /// `[patches, patch_frames, n_mels]` (VGGish-style models)
+    LogMelPatches {
+        /// Mel bands per frame
+        n_mels: usize,
+        /// Frames per patch
+        patch_frames: usize,
+    },
+}
+
+/// How raw model outputs map to confidences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputActivation {
+    /// Outputs already are probabilities
+    #[default]
+    Probabilities,
+    /// Independent sigmoid per class (multi-label models)
+    Sigmoid,
+    /// Softmax across classes (single-label models)
+    Softmax,
+}
+
+/// User-supplied ONNX audio classification model.
+#[derive(Debug, Clone)]
+pub struct MlModelConfig {
+    /// Path to the `.onnx` file
+    pub model_path: PathBuf,
+    /// JSON array of labels indexed like the model outputs; defaults to
+    /// `labels.json` next to the model
+    pub labels_path: Option<PathBuf>,
+    /// Features the model expects
+    pub input: ModelInput,
+    /// Activation applied to the model outputs
+    pub activation: OutputActivation,
+    /// Weight of ML confidences when blended with rule-based ones
+    /// (0.0 = rules only, 1.0 = model only)
+    pub blend_weight: f32,
+    /// Sample rate the model was trained at; audio is resampled to it
+    pub sample_rate: Option<u32>,
+    /// STFT frame size for mel features
+    pub frame_size: usize,
+    /// STFT hop size for mel features
+    pub hop_size: usize,
+}
+
+impl MlModelConfig {
+    /// Configuration for the model at `model_path` with default settings.
+    pub fn new(model_path: impl Into<PathBuf>) -> Self {
+        Self {
+            model_path: model_path.into(),
+            labels_path: None,
+            input: ModelInput::Signature,
+            activation: OutputActivation::Probabilities,
+            blend_weight: 0.5,
+            sample_rate: None,
+            frame_size: 1024,
+            hop_size: 512,
+        }
+    }
+
+    /// Defaults for a spoken language-ID model: 16 kHz log-mel patches
+    /// (64 bands, 96 frames of 10 ms) with softmax outputs over language
+    /// codes such as `"en"`.
+    pub fn language_id(model_path: impl Into<PathBuf>) -> Self {
+        Self {
+            input: ModelInput::LogMelPatches { n_mels: 64, patch_frames: 96 },
+            activation: OutputActivation::Softmax,
+            blend_weight: 1.0,
+            sample_rate: Some(16000),
+            frame_size: 512,
+            hop_size: 160,
+            ..Self::new(model_path)
+        }
+    }
+
+    /// Path of the label file.
+    pub fn labels_path(&self) -> PathBuf {
+        self.labels_path.clone().unwrap_or_else(|| {
+            self.model_path
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .join("labels.json")
+        })
+    }
+}
+
+/// Read a JSON array of labels.
+pub(crate) fn load_labels(path: &Path) -> Result<Vec<String>> {
+    let data = std::fs::read(path)
+        .with_context(|| format!("Cannot read labels {}", path.display()))?;
+    let labels: Vec<String> = serde_json::from_slice(&data)
+        .with_context(|| format!("Labels {} must be a JSON array of strings", path.display()))?;
+    if labels.is_empty() {
+        bail!("Labels {} are empty", path.display());
+    }
+    Ok(labels)
+}
+
+/// Build the input tensor for `audio`, returning its shape and row-major data.
+pub(crate) fn model_input(
+    config: &MlModelConfig,
+    analyzer: &FrequencyAnalyzer,
+    audio: &AudioData,
+) -> Result<(Vec<usize>, Vec<f32>)> {
+    let (samples, sample_rate) = match config.sample_rate {
+        Some(rate) if rate != audio.sample_rate => (resample_samples(&audio.samples, audio.sample_rate, rate), rate),
+        _ => (audio.samples.clone(), audio.sample_rate),
+    };
+
+    match config.input {
+        ModelInput::Signature => {
+            let features = analyzer.compute_signature(&samples, sample_rate)?.features;
+            Ok((vec![1, features.len()], features))
+        }
+        ModelInput::Mfcc { n_mels, n_coefficients } => {
+            if n_coefficients > n_mels {
+                bail!("Cannot derive {} MFCCs from {} mel bands", n_coefficients, n_mels);
+            }
+            let log_mel = log_mel_spectrogram(&samples, sample_rate, config.frame_size, config.hop_size, n_mels)?;
+            let coefficients = mfcc(&log_mel, n_coefficients);
+            Ok((vec![1, coefficients.len(), n_coefficients], coefficients.concat()))
+        }
+        ModelInput::LogMelPatches { n_mels, patch_frames } => {
+            if patch_frames == 0 {
+                bail!("Log-mel patches need at least one frame");
+            }
+            let mut log_mel = log_mel_spectrogram(&samples, sample_rate, config.frame_size, config.hop_size, n_mels)?;
+
+            // Short clips are padded with silence into a single patch
+            if log_mel.len() < patch_frames {
+                log_mel.resize(patch_frames, vec![LOG_FLOOR.ln(); n_mels]);
+            }
+            let patches = log_mel.len() / patch_frames;
+            let data = log_mel[..patches * patch_frames].concat();
+            Ok((vec![patches, patch_frames, n_mels], data))
+        }
+    }
+}
+
+/// Turn model outputs into tags, averaging over batch rows.
+///
+/// `scores` holds one row per input patch with one column per label.
+pub(crate) fn scores_to_tags(scores: &[f32], labels: &[String], activation: OutputActivation) -> Result<Vec<ContentTag>> {
+    if scores.is_empty() || !scores.len().is_multiple_of(labels.len()) {
+        bail!("Model produced {} scores for {} labels", scores.len(), labels.len());
+    }
+
+    let rows = scores.len() / labels.len();
+    let mut averaged = vec![0.0f32; labels.len()];
+    for row in scores.chunks(labels.len()) {
+        for (sum, value) in averaged.iter_mut().zip(activate(row, activation)) {
+            *sum += value / rows as f32;
+        }
+    }
+
+    let mut tags: Vec<ContentTag> = labels.iter()
+        .zip(averaged)
+        .map(|(label, confidence)| ContentTag::new(label.clone(), confidence))
+        .collect();
+    tags.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(tags)
+}
+
+fn activate(row: &[f32], activation: OutputActivation) -> Vec<f32> {
+    match activation {
+        OutputActivation::Probabilities => row.to_vec(),
+        OutputActivation::Sigmoid => row.iter().map(|&x| 1.0 / (1.0 + (-x).exp())).collect(),
+        OutputActivation::Softmax => {
+            let max = row.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let exps: Vec<f32> = row.iter().map(|&x| (x - max).exp()).collect();
+            let sum: f32 = exps.iter().sum();
+            exps.into_iter().map(|e| e / sum).collect()
+        }
+    }
+}
+
+/// Blend rule-based and ML tags: each label's confidence is
+/// `(1 - weight) * rule + weight * ml`, counting a missing tag as zero.
+pub(crate) fn blend_tags(rule: &[ContentTag], ml: &[ContentTag], weight: f32) -> Vec<ContentTag> {
+    let weight = weight.clamp(0.0, 1.0);
+    let mut blended: Vec<ContentTag> = Vec::new();
+
+    for (tags, factor) in [(rule, 1.0 - weight), (ml, weight)] {
+        for tag in tags {
+            match blended.iter_mut().find(|t| t.label == tag.label) {
+                Some(existing) => existing.confidence += tag.confidence * factor,
+                None => blended.push(ContentTag {
+                    confidence: tag.confidence * factor,
+                    ..tag.clone()
+                }),
+            }
+        }
+    }
+
+    blended.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal));
+    blended
+}
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Triangular mel filters over the positive-frequency FFT bins (HTK scale).
+struct MelFilterbank {
+    /// Non-zero `(bin, weight)` pairs of each filter
+    filters: Vec<Vec<(usize, f32)>>,
+}
+
+impl MelFilterbank {
+    fn new(n_mels: usize, fft_size: usize, sample_rate: u32) -> Self {
+        let bin_hz = sample_rate as f32 / fft_size as f32;
+        let max_mel = hz_to_mel(sample_rate as f32 / 2.0);
+        let edges: Vec<f32> = (0..n_mels + 2)
+            .map(|i| mel_to_hz(max_mel * i as f32 / (n_mels + 1) as f32))
+            .collect();
+
+        let filters = edges.windows(3)
+            .map(|edge| {
+                let (low, center, high) = (edge[0], edge[1], edge[2]);
+                (0..fft_size / 2)
+                    .filter_map(|bin| {
+                        let freq = bin as f32 * bin_hz;
+                        let weight = if freq > low && freq <= center {
+                            (freq - low) / (center - low)
+                        } else if freq > center && freq < high {
+                            (high - freq) / (high - center)
+                        } else {
+                            0.0
+                        };
+                        (weight > 0.0).then_some((bin, weight))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self { filters }
+    }
+
+    /// Mel band powers of a magnitude spectrum.
+    fn apply(&self, spectrum: &[f32]) -> Vec<f32> {
+        self.filters.iter()
+            .map(|filter| filter.iter().map(|&(bin, weight)| spectrum[bin] * spectrum[bin] * weight).sum())
+            .collect()
+    }
+}
+
+/// Natural-log mel spectrogram, one row of `n_mels` values per frame.
+///
+/// Audio shorter than one frame is zero-padded to a single frame.
+pub(crate) fn log_mel_spectrogram(
+    samples: &[f32],
+    sample_rate: u32,
+    frame_size: usize,
+    hop_size: usize,
+    n_mels: usize,
+) -> Result<Vec<Vec<f32>>> {
+    if n_mels == 0 || frame_size == 0 || hop_size == 0 {
+        bail!("Mel bands, frame size and hop size must be positive");
+    }
+
+    let padded;
+    let samples = if samples.len() < frame_size {
+        padded = [samples, &vec![0.0; frame_size - samples.len()]].concat();
+        &padded
+    } else {
+        samples
+    };
+
+    let filterbank = MelFilterbank::new(n_mels, frame_size, sample_rate);
+    let spectrogram = FrequencyAnalyzer::new(frame_size, hop_size).compute_spectrogram(samples)?;
+    Ok(spectrogram.iter()
+        .map(|spectrum| filterbank.apply(spectrum).into_iter().map(|e| (e + LOG_FLOOR).ln()).collect())
+        .collect())
+}
+
+/// MFCCs: orthonormal DCT-II of each log-mel frame, keeping the first
+/// `n_coefficients`.
+pub(crate) fn mfcc(log_mel: &[Vec<f32>], n_coefficients: usize) -> Vec<Vec<f32>> {
+    log_mel.iter()
+        .map(|frame| {
+            let n = frame.len() as f32;
+            (0..n_coefficients)
+                .map(|k| {
+                    let scale = if k == 0 { (1.0 / n).sqrt() } else { (2.0 / n).sqrt() };
+                    scale * frame.iter()
+                        .enumerate()
+                        .map(|(i, &x)| x * (std::f32::consts::PI / n * (i as f32 + 0.5) * k as f32).cos())
+                        .sum::<f32>()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(freq: f32, sample_rate: u32, secs: f32) -> AudioData {
+        let samples = (0..(sample_rate as f32 * secs) as usize)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).sin())
+            .collect();
+        AudioData::new(samples, sample_rate)
+    }
+
+    fn tag(label: &str, confidence: f32) -> ContentTag {
+        ContentTag::new(label, confidence)
+    }
+
+    #[test]
+    fn test_log_mel_peaks_at_tone() {
+        let audio = tone(1000.0, 16000, 0.5);
+        let log_mel = log_mel_spectrogram(&audio.samples, 16000, 1024, 512, 40).unwrap();
+        assert_eq!(log_mel.len(), (8000 - 1024) / 512 + 1);
+
+        // The loudest band is the filter centered nearest 1 kHz
+        let frame = &log_mel[3];
+        let peak = (0..40).max_by(|&a, &b| frame[a].total_cmp(&frame[b])).unwrap();
+        let max_mel = hz_to_mel(8000.0);
+        let centers: Vec<f32> = (1..=40).map(|i| mel_to_hz(max_mel * i as f32 / 41.0)).collect();
+        let nearest = (0..40)
+            .min_by(|&a, &b| (centers[a] - 1000.0).abs().total_cmp(&(centers[b] - 1000.0).abs()))
+            .unwrap();
+        assert!(peak.abs_diff(nearest) <= 1, "peak {} nearest {}", peak, nearest);
+    }
+
+    #[test]
+    fn test_mfcc_of_flat_spectrum() {
+        // A constant log-mel frame only has energy in the DC coefficient
+        let coefficients = mfcc(&[vec![2.0; 16]], 4);
+        assert!((coefficients[0][0] - 2.0 * 16f32.sqrt()).abs() < 1e-4);
+        assert!(coefficients[0][1..].iter().all(|c| c.abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_model_input_shapes() {
+        let analyzer = FrequencyAnalyzer::new(4096, 2048);
+        let audio = tone(440.0, 16000, 1.0);
+        let mut config = MlModelConfig::new("model.onnx");
+
+        let (shape, data) = model_input(&config, &analyzer, &audio).unwrap();
+        assert_eq!(shape, vec![1, 128]);
+        assert_eq!(data.len(), 128);
+
+        config.input = ModelInput::Mfcc { n_mels: 40, n_coefficients: 13 };
+        let frames = (16000 - 1024) / 512 + 1;
+        let (shape, data) = model_input(&config, &analyzer, &audio).unwrap();
+        assert_eq!(shape, vec![1, frames, 13]);
+        assert_eq!(data.len(), frames * 13);
+
+        config.input = ModelInput::LogMelPatches { n_mels: 64, patch_frames: 10 };
+        let (shape, data) = model_input(&config, &analyzer, &audio).unwrap();
+        assert_eq!(shape, vec![frames / 10, 10, 64]);
+        assert_eq!(data.len(), frames / 10 * 640);
+
+        // Resampled to the model rate first, and padded into one patch
+        config.sample_rate = Some(8000);
+        config.input = ModelInput::LogMelPatches { n_mels: 64, patch_frames: 96 };
+        let (shape, _) = model_input(&config, &analyzer, &audio).unwrap();
+        assert_eq!(shape, vec![1, 96, 64]);
+    }
+
+    #[test]
+    fn test_scores_to_tags() {
+        let labels: Vec<String> = ["music", "speech"].iter().map(|s| s.to_string()).collect();
+
+        // Two patches averaged
+        let tags = scores_to_tags(&[0.8, 0.2, 0.4, 0.6], &labels, OutputActivation::Probabilities).unwrap();
+        assert_eq!(tags[0].label, "music");
+        assert!((tags[0].confidence - 0.6).abs() < 1e-6);
+
+        let tags = scores_to_tags(&[0.0, 2.0_f32.ln()], &labels, OutputActivation::Softmax).unwrap();
+        assert_eq!(tags[0].label, "speech");
+        assert!((tags[0].confidence - 2.0 / 3.0).abs() < 1e-6);
+
+        let tags = scores_to_tags(&[0.0, 0.0], &labels, OutputActivation::Sigmoid).unwrap();
+        assert!(tags.iter().all(|t| (t.confidence - 0.5).abs() < 1e-6));
+
+        assert!(scores_to_tags(&[0.1, 0.2, 0.3], &labels, OutputActivation::Probabilities).is_err());
+    }
+
+    #[test]
+    fn test_blend_tags() {
+        let rule = [tag("speech", 0.8), tag("calm", 0.6)];
+        let ml = [tag("music", 0.9), tag("speech", 0.4)];
+
+        let blended = blend_tags(&rule, &ml, 0.25);
+        let confidence = |label: &str| blended.iter().find(|t| t.label == label).unwrap().confidence;
+        assert!((confidence("speech") - 0.7).abs() < 1e-6);
+        assert!((confidence("calm") - 0.45).abs() < 1e-6);
+        assert!((confidence("music") - 0.225).abs() < 1e-6);
+        assert_eq!(blended[0].label, "speech");
+
+        // Full weight ignores the rules
+        let blended = blend_tags(&rule, &ml, 1.0);
+        assert_eq!(blended[0].label, "music");
+        assert_eq!(blended.iter().find(|t| t.label == "calm").unwrap().confidence, 0.0);
+    }
+
+    #[test]
+    fn test_labels_sidecar() {
+        let config = MlModelConfig::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tiny_tagger.onnx"));
+        let labels = load_labels(&config.labels_path()).unwrap();
+        assert_eq!(labels, vec!["music", "speech", "nature"]);
+
+        assert!(load_labels(Path::new("/nonexistent/labels.json")).is_err());
+    }
+}
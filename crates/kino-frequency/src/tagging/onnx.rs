@@ -0,0 +1,128 @@
+//! ONNX Runtime inference for [`MlModelConfig`] models.
+
+use anyhow::{Context, Result};
+use ndarray::{ArrayD, IxDyn};
+use ort::session::Session;
+use ort::value::Tensor;
+use tracing::{info, warn};
+
+use super::ml::{load_labels, model_input, scores_to_tags, MlModelConfig};
+use super::TaggingConfig;
+use crate::fft::FrequencyAnalyzer;
+use crate::types::*;
+
+/// Loaded classification model and its labels.
+pub(crate) struct OnnxClassifier {
+    session: Session,
+    labels: Vec<String>,
+    config: MlModelConfig,
+}
+
+impl OnnxClassifier {
+    /// Load the model configured for `config`, if ML tagging is enabled.
+    ///
+    /// Any failure is logged and yields `None`, leaving rule-based tagging.
+    pub(crate) fn from_tagging_config(config: &TaggingConfig) -> Option<Self> {
+        if !config.use_ml_model {
+            return None;
+        }
+        let Some(model) = &config.ml_model else {
+            warn!("use_ml_model is set but no ml_model is configured; using rule-based tags only");
+            return None;
+        };
+        Self::from_model(model, "tagging")
+    }
+
+    /// Load `model`, logging and returning `None` on failure.
+    pub(crate) fn from_model(model: &MlModelConfig, purpose: &str) -> Option<Self> {
+        match Self::load(model) {
+            Ok(classifier) => {
+                info!("Loaded {} model {} with {} labels", purpose, model.model_path.display(), classifier.labels.len());
+                Some(classifier)
+            }
+            Err(e) => {
+                warn!("Cannot load {} model, skipping it: {:#}", purpose, e);
+                None
+            }
+        }
+    }
+
+    fn load(config: &MlModelConfig) -> Result<Self> {
+        let labels = load_labels(&config.labels_path())?;
+        let session = Session::builder()?
+            .commit_from_file(&config.model_path)
+            .with_context(|| format!("Cannot load ONNX model {}", config.model_path.display()))?;
+
+        Ok(Self {
+            session,
+            labels,
+            config: config.clone(),
+        })
+    }
+
+    /// Blend weight of this model's predictions.
+    pub(crate) fn blend_weight(&self) -> f32 {
+        self.config.blend_weight
+    }
+
+    /// Run the model on a window of mono audio.
+    pub(crate) fn predict(&self, analyzer: &FrequencyAnalyzer, audio: &AudioData) -> Result<Vec<ContentTag>> {
+        let (shape, data) = model_input(&self.config, analyzer, audio)?;
+        let input = Tensor::from_array(ArrayD::from_shape_vec(IxDyn(&shape), data)?)?;
+
+        let outputs = self.session.run(ort::inputs![input]?)?;
+        let scores = outputs[0].try_extract_tensor::<f32>()?;
+        let scores: Vec<f32> = scores.iter().copied().collect();
+
+        scores_to_tags(&scores, &self.labels, self.config.activation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(freq: f32, sample_rate: u32, secs: f32) -> AudioData {
+        let samples = (0..(sample_rate as f32 * secs) as usize)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).sin())
+            .collect();
+        AudioData::new(samples, sample_rate)
+    }
+
+    fn fixture_config() -> TaggingConfig {
+        TaggingConfig {
+            use_ml_model: true,
+            ml_model: Some(MlModelConfig::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tiny_tagger.onnx"))),
+            ..TaggingConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_fixture_model_inference() {
+        let classifier = OnnxClassifier::from_tagging_config(&fixture_config()).unwrap();
+        let analyzer = FrequencyAnalyzer::new(4096, 2048);
+
+        // The fixture scores low-frequency signature energy as "music" and
+        // high-frequency energy as "speech"
+        let low = classifier.predict(&analyzer, &tone(100.0, 22050, 1.0)).unwrap();
+        assert_eq!(low[0].label, "music");
+        let high = classifier.predict(&analyzer, &tone(6000.0, 22050, 1.0)).unwrap();
+        assert_eq!(high[0].label, "speech");
+
+        let total: f32 = low.iter().map(|t| t.confidence).sum();
+        assert!((total - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_model_only_blend() {
+        let mut config = fixture_config();
+        if let Some(model) = config.ml_model.as_mut() {
+            model.blend_weight = 1.0;
+        }
+        let tagger = super::super::ContentTagger::with_config(config);
+
+        let tags = tagger.predict(&tone(6000.0, 22050, 2.0)).unwrap();
+        assert_eq!(tags[0].label, "speech");
+        assert!(tags.iter().all(|t| ["music", "speech", "nature"].contains(&t.label.as_str())));
+    }
+}
@@ -0,0 +1,261 @@
+//! Perceptual quality estimation for the quality tags.
+//!
+//! Lossy codecs low-pass their input before encoding, lower bitrates more
+//! aggressively, which leaves a telltale cliff in the long-term spectrum.
+//! The effective bandwidth ceiling is the highest band whose level clears
+//! the spectral floor by [`CEILING_MARGIN_DB`] and lies within
+//! [`DYNAMIC_RANGE_DB`] of the loudest band; a drop of at least
+//! [`CUTOFF_DROP_DB`] right above it marks a codec low-pass rather than
+//! content that simply rolls off. The noise floor is the level of the
+//! quietest frames, and counts as noise when those frames are spectrally
+//! flat.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::ContentTag;
+
+/// Width of the bands the long-term spectrum is smoothed into (Hz)
+const BAND_HZ: f32 = 250.0;
+
+/// How far a band must clear the spectral floor to count towards the ceiling (dB)
+const CEILING_MARGIN_DB: f32 = 12.0;
+
+/// Range below the loudest band beyond which levels are treated as silence (dB)
+const DYNAMIC_RANGE_DB: f32 = 90.0;
+
+/// Span compared on either side of the ceiling to measure the cutoff (Hz)
+const CUTOFF_SPAN_HZ: f32 = 1000.0;
+
+/// Minimum drop across the ceiling that marks a codec low-pass (dB)
+const CUTOFF_DROP_DB: f32 = 25.0;
+
+/// Lowest Nyquist frequency at which a missing cutoff means full bandwidth (Hz)
+const FULL_BAND_HZ: f32 = 19_000.0;
+
+/// Quiet-frame level at which noise starts to count (dBFS)
+const NOISY_FLOOR_DB: f32 = -60.0;
+
+/// Quiet-frame level at which noise fully counts (dBFS)
+const NOISY_FULL_DB: f32 = -30.0;
+
+/// Spectral flatness above which quiet frames are noise rather than quiet content
+const NOISE_FLATNESS: f32 = 0.3;
+
+/// Level reported for digital silence (dBFS)
+const SILENCE_DB: f32 = -120.0;
+
+/// Likely bitrate class of the source, estimated from its codec low-pass.
+///
+/// The ranges follow common MP3/AAC encoder defaults and are a hint, not a
+/// measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BitrateClass {
+    /// No codec low-pass: lossless or transparent
+    Lossless,
+    /// Cutoff at 19 kHz or above, roughly 192-320 kbps
+    High,
+    /// Cutoff at 16-19 kHz, roughly 128-160 kbps
+    Medium,
+    /// Cutoff at 11-16 kHz, roughly 64-112 kbps
+    Low,
+    /// Cutoff below 11 kHz, under 64 kbps
+    VeryLow,
+    /// No cutoff found, but the sample rate is too low to tell
+    Unknown,
+}
+
+impl BitrateClass {
+    /// Class of a source low-passed at `cutoff_hz`
+    fn from_cutoff(cutoff_hz: f32) -> Self {
+        match cutoff_hz {
+            c if c >= 19_000.0 => Self::High,
+            c if c >= 16_000.0 => Self::Medium,
+            c if c >= 11_000.0 => Self::Low,
+            _ => Self::VeryLow,
+        }
+    }
+
+    /// How sure a cutoff at this class makes the "compressed" tag
+    fn compressed_confidence(self) -> f32 {
+        match self {
+            Self::High => 0.5,
+            Self::Medium => 0.7,
+            Self::Low => 0.85,
+            Self::VeryLow => 0.95,
+            Self::Lossless | Self::Unknown => 0.0,
+        }
+    }
+}
+
+/// Result of [`ContentTagger::analyze_quality`](super::ContentTagger::analyze_quality).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityReport {
+    /// Effective bandwidth ceiling: highest frequency with energy above
+    /// the spectral floor (Hz)
+    pub bandwidth_hz: f32,
+    /// Nyquist frequency of the analyzed audio (Hz)
+    pub nyquist_hz: f32,
+    /// Level drop right above the ceiling (dB), zero when the ceiling is at Nyquist
+    pub cutoff_drop_db: f32,
+    /// Whether the drop is steep enough to be a codec low-pass
+    pub codec_lowpass: bool,
+    /// Likely bitrate class of the source
+    pub bitrate_class: BitrateClass,
+    /// Level of the quietest frames (dBFS)
+    pub noise_floor_db: f32,
+    /// Level of the loudest frames over the noise floor (dB)
+    pub signal_to_noise_db: f32,
+    /// Quality tags: "high-fidelity", "compressed" and "noisy", sorted by confidence
+    pub tags: Vec<ContentTag>,
+}
+
+/// Estimate quality from a mono spectrogram and the samples it was computed from.
+pub(super) fn analyze(
+    spectrogram: &[Vec<f32>],
+    samples: &[f32],
+    sample_rate: u32,
+    fft_size: usize,
+    hop_size: usize,
+) -> QualityReport {
+    let nyquist_hz = sample_rate as f32 / 2.0;
+    let bands = band_levels(spectrogram, sample_rate, fft_size);
+    let (bandwidth_hz, cutoff_drop_db) = bandwidth_ceiling(&bands, nyquist_hz);
+
+    let codec_lowpass = cutoff_drop_db >= CUTOFF_DROP_DB;
+    let bitrate_class = if codec_lowpass {
+        BitrateClass::from_cutoff(bandwidth_hz)
+    } else if nyquist_hz >= FULL_BAND_HZ {
+        BitrateClass::Lossless
+    } else {
+        BitrateClass::Unknown
+    };
+
+    // Frame levels, aligned with the spectrogram frames
+    let mut levels: Vec<(f32, usize)> = (0..spectrogram.len())
+        .map(|i| (rms_db(&samples[i * hop_size..i * hop_size + fft_size]), i))
+        .collect();
+    levels.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let noise_floor_db = percentile(&levels, 0.1).0;
+    let signal_to_noise_db = percentile(&levels, 0.9).0 - noise_floor_db;
+
+    // Spectral flatness of the frames at the noise floor
+    let quiet: Vec<f32> = levels.iter()
+        .take_while(|(db, _)| *db <= noise_floor_db + 3.0)
+        .map(|&(_, i)| flatness(&spectrogram[i]))
+        .collect();
+    let quiet_flatness = quiet.iter().sum::<f32>() / quiet.len().max(1) as f32;
+
+    let mut tags = Vec::new();
+    let noisy = if quiet_flatness >= NOISE_FLATNESS {
+        ((noise_floor_db - NOISY_FLOOR_DB) / (NOISY_FULL_DB - NOISY_FLOOR_DB)).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    if noisy > 0.0 {
+        tags.push(ContentTag::new("noisy", noisy));
+    }
+    if codec_lowpass {
+        // Sharper cutoffs are more clearly an encoder's
+        let sharpness = (cutoff_drop_db / (2.0 * CUTOFF_DROP_DB)).clamp(0.5, 1.0);
+        tags.push(ContentTag::new("compressed", bitrate_class.compressed_confidence() * sharpness));
+    } else if bitrate_class == BitrateClass::Lossless {
+        // Full bandwidth, unless it is full of noise
+        let fidelity = ((bandwidth_hz - 16_000.0) / (FULL_BAND_HZ - 16_000.0)).clamp(0.0, 1.0) * (1.0 - noisy);
+        if fidelity > 0.0 {
+            tags.push(ContentTag::new("high-fidelity", fidelity));
+        }
+    }
+    tags.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    QualityReport {
+        bandwidth_hz,
+        nyquist_hz,
+        cutoff_drop_db,
+        codec_lowpass,
+        bitrate_class,
+        noise_floor_db,
+        signal_to_noise_db,
+        tags,
+    }
+}
+
+/// Long-term power spectrum in [`BAND_HZ`] bands, as (upper edge Hz, dB) pairs.
+fn band_levels(spectrogram: &[Vec<f32>], sample_rate: u32, fft_size: usize) -> Vec<(f32, f32)> {
+    let bin_hz = sample_rate as f32 / fft_size as f32;
+    let bins = spectrogram.first().map_or(0, Vec::len);
+    let mut power = vec![0.0f64; bins];
+    for frame in spectrogram {
+        for (total, &mag) in power.iter_mut().zip(frame) {
+            *total += (mag as f64).powi(2);
+        }
+    }
+
+    let per_band = ((BAND_HZ / bin_hz).round() as usize).max(1);
+    // Skip DC, which says nothing about bandwidth
+    power[1.min(bins)..]
+        .chunks(per_band)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mean = chunk.iter().sum::<f64>() / (chunk.len() * spectrogram.len()) as f64;
+            let upper_hz = ((1 + (i + 1) * per_band) as f32 * bin_hz).min(sample_rate as f32 / 2.0);
+            (upper_hz, 10.0 * (mean.max(1e-20)).log10() as f32)
+        })
+        .collect()
+}
+
+/// Bandwidth ceiling and the level drop above it.
+///
+/// The spectral floor is the 5th percentile band level; a spectrum with no
+/// band clearing it by the margin is flat and reaches Nyquist. Filter
+/// stopbands and rounding noise sit beyond the dynamic range and never
+/// count.
+fn bandwidth_ceiling(bands: &[(f32, f32)], nyquist_hz: f32) -> (f32, f32) {
+    let mut sorted: Vec<f32> = bands.iter().map(|&(_, db)| db).collect();
+    sorted.sort_by(f32::total_cmp);
+    let Some(&floor) = sorted.get(sorted.len() / 20) else {
+        return (0.0, 0.0);
+    };
+
+    let peak = sorted[sorted.len() - 1];
+    if peak < floor + CEILING_MARGIN_DB {
+        return (nyquist_hz, 0.0);
+    }
+    let threshold = (floor + CEILING_MARGIN_DB).max(peak - DYNAMIC_RANGE_DB);
+    let Some(top) = bands.iter().rposition(|&(_, db)| db >= threshold) else {
+        return (nyquist_hz, 0.0);
+    };
+    let ceiling_hz = bands[top].0;
+    if nyquist_hz - ceiling_hz < CUTOFF_SPAN_HZ {
+        return (ceiling_hz, 0.0);
+    }
+
+    let span = ((CUTOFF_SPAN_HZ / BAND_HZ) as usize).max(1);
+    let mean = |levels: &[(f32, f32)]| levels.iter().map(|&(_, db)| db).sum::<f32>() / levels.len().max(1) as f32;
+    let below = mean(&bands[(top + 1).saturating_sub(span)..=top]);
+    let above = mean(&bands[top + 1..(top + 1 + span).min(bands.len())]);
+    (ceiling_hz, (below - above).max(0.0))
+}
+
+/// RMS level of a frame (dBFS)
+fn rms_db(frame: &[f32]) -> f32 {
+    let mean_square = frame.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / frame.len().max(1) as f64;
+    if mean_square > 0.0 {
+        (10.0 * mean_square.log10() as f32).max(SILENCE_DB)
+    } else {
+        SILENCE_DB
+    }
+}
+
+/// Spectral flatness of a frame's power spectrum (0 tonal, 1 white noise)
+fn flatness(frame: &[f32]) -> f32 {
+    let power: Vec<f64> = frame.iter().skip(1).map(|&m| (m as f64).powi(2) + 1e-20).collect();
+    let mean = power.iter().sum::<f64>() / power.len().max(1) as f64;
+    let log_mean = power.iter().map(|p| p.ln()).sum::<f64>() / power.len().max(1) as f64;
+    (log_mean.exp() / mean) as f32
+}
+
+/// Element at fraction `p` of a sorted, non-empty slice
+fn percentile<T: Copy>(sorted: &[T], p: f32) -> T {
+    sorted[((sorted.len() - 1) as f32 * p).round() as usize]
+}
@@ -0,0 +1,364 @@
+//! Hierarchical tag taxonomy with localized display names.
+//!
+//! Tags have stable dotted ids such as `"genre.music.electronic"`, an
+//! optional parent, a default display name and translations keyed by
+//! locale. Free-form labels, such as the tagger's own `"music"` or an ML
+//! model's `"edm"`, map to ids through each entry's aliases, so older
+//! labels keep resolving as the taxonomy grows.
+//!
+//! A taxonomy file is JSON:
+//!
+//! ```json
+//! { "tags": [
+//!     { "id": "genre", "name": "Genre" },
+//!     { "id": "genre.music", "parent": "genre", "name": "Music",
+//!       "aliases": ["music"], "translations": { "es": "Música" } }
+//! ] }
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::types::ContentTag;
+
+/// Taxonomy used when none is configured, covering every rule-based tag
+const BUILTIN: &str = include_str!("taxonomy.json");
+
+/// One tag of a [`TagTaxonomy`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxonomyEntry {
+    /// Stable id, e.g. `"genre.music.electronic"`
+    pub id: String,
+    /// Id of the parent tag; `None` for a root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Display name when no translation matches
+    pub name: String,
+    /// Display names by locale, e.g. `"es"` or `"pt-BR"`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub translations: HashMap<String, String>,
+    /// Labels that resolve to this tag (case-insensitive)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+/// Serialized form of a taxonomy.
+#[derive(Serialize, Deserialize)]
+struct TaxonomyFile {
+    tags: Vec<TaxonomyEntry>,
+}
+
+/// Validated tag hierarchy.
+#[derive(Debug, Clone)]
+pub struct TagTaxonomy {
+    entries: Vec<TaxonomyEntry>,
+    /// Entry index by id
+    ids: HashMap<String, usize>,
+    /// Entry index by lowercased alias
+    aliases: HashMap<String, usize>,
+}
+
+impl TagTaxonomy {
+    /// Taxonomy shipped with the crate, covering the rule-based tags.
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN).expect("built-in taxonomy is valid")
+    }
+
+    /// Build a taxonomy, checking that ids are unique, parents exist, the
+    /// hierarchy has no cycles and no alias names two tags.
+    pub fn from_entries(entries: Vec<TaxonomyEntry>) -> Result<Self> {
+        let mut ids = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.id.is_empty() {
+                bail!("Taxonomy entry {} has an empty id", i);
+            }
+            if ids.insert(entry.id.clone(), i).is_some() {
+                bail!("Duplicate taxonomy id '{}'", entry.id);
+            }
+        }
+
+        for entry in &entries {
+            let mut parent = entry.parent.as_deref();
+            let mut depth = 0;
+            while let Some(id) = parent {
+                let Some(&index) = ids.get(id) else {
+                    bail!("Taxonomy id '{}' has unknown parent '{}'", entry.id, id);
+                };
+                depth += 1;
+                if depth > entries.len() {
+                    bail!("Taxonomy id '{}' is its own ancestor", entry.id);
+                }
+                parent = entries[index].parent.as_deref();
+            }
+        }
+
+        let mut taxonomy = Self { entries, ids, aliases: HashMap::new() };
+        for i in 0..taxonomy.entries.len() {
+            for alias in taxonomy.entries[i].aliases.clone() {
+                taxonomy.insert_alias(&alias, i)?;
+            }
+        }
+        Ok(taxonomy)
+    }
+
+    /// Parse a taxonomy from JSON.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: TaxonomyFile = serde_json::from_str(json).context("Invalid taxonomy JSON")?;
+        Self::from_entries(file.tags)
+    }
+
+    /// Load a taxonomy from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read taxonomy {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("Failed to load taxonomy {}", path.display()))
+    }
+
+    /// Serialize the taxonomy to JSON, in the format [`from_json`](Self::from_json) reads.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&TaxonomyFile { tags: self.entries.clone() })?)
+    }
+
+    /// All entries, in file order.
+    pub fn entries(&self) -> &[TaxonomyEntry] {
+        &self.entries
+    }
+
+    /// Entry with this id.
+    pub fn get(&self, id: &str) -> Option<&TaxonomyEntry> {
+        self.ids.get(id).map(|&i| &self.entries[i])
+    }
+
+    /// Map a legacy or free-form label to the id of `id`'s tag.
+    pub fn add_alias(&mut self, label: &str, id: &str) -> Result<()> {
+        let Some(&index) = self.ids.get(id) else {
+            bail!("Unknown taxonomy id '{}'", id);
+        };
+        self.insert_alias(label, index)?;
+        self.entries[index].aliases.push(label.to_string());
+        Ok(())
+    }
+
+    fn insert_alias(&mut self, label: &str, index: usize) -> Result<()> {
+        match self.aliases.insert(label.trim().to_lowercase(), index) {
+            Some(other) if other != index => bail!(
+                "Alias '{}' maps to both '{}' and '{}'",
+                label, self.entries[other].id, self.entries[index].id
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Canonical id for a label: the label itself when it is an id,
+    /// otherwise the tag it is an alias of.
+    pub fn resolve(&self, label: &str) -> Option<&str> {
+        let index = self.ids.get(label)
+            .or_else(|| self.aliases.get(&label.trim().to_lowercase()))?;
+        Some(&self.entries[*index].id)
+    }
+
+    /// Ancestors of `id`, parent first.
+    pub fn ancestors<'a>(&'a self, id: &str) -> impl Iterator<Item = &'a TaxonomyEntry> + 'a {
+        let mut parent = self.get(id).and_then(|e| e.parent.as_deref());
+        std::iter::from_fn(move || {
+            let entry = self.get(parent?)?;
+            parent = entry.parent.as_deref();
+            Some(entry)
+        })
+    }
+
+    /// Whether `id` is `ancestor` or one of its descendants.
+    pub fn is_a(&self, id: &str, ancestor: &str) -> bool {
+        id == ancestor || self.ancestors(id).any(|e| e.id == ancestor)
+    }
+
+    /// Whether a tag, by its id or else its label, is `ancestor` or one of
+    /// its descendants.
+    pub fn tag_is_a(&self, tag: &ContentTag, ancestor: &str) -> bool {
+        tag.id.as_deref()
+            .or_else(|| self.resolve(&tag.label))
+            .is_some_and(|id| self.is_a(id, ancestor))
+    }
+
+    /// Whether any of `tags` falls under `id`, e.g. an "electronic" tag
+    /// matches a filter for `"genre.music"`.
+    pub fn matches(&self, tags: &[ContentTag], id: &str) -> bool {
+        tags.iter().any(|tag| self.tag_is_a(tag, id))
+    }
+
+    /// Display name of `id` in `locale`.
+    ///
+    /// Tries the exact locale, then its language (`"pt"` for `"pt-BR"`),
+    /// then falls back to the default name. `None` for unknown ids.
+    pub fn display_name(&self, id: &str, locale: Option<&str>) -> Option<&str> {
+        let entry = self.get(id)?;
+        let translated = locale.and_then(|locale| {
+            let language = locale.split(['-', '_']).next().unwrap_or(locale);
+            entry.translations.get(locale)
+                .or_else(|| entry.translations.get(&locale.replace('_', "-")))
+                .or_else(|| entry.translations.get(language))
+        });
+        Some(translated.unwrap_or(&entry.name))
+    }
+
+    /// Display name of a tag in `locale`, or its label when it has no id
+    /// in this taxonomy.
+    pub fn tag_name<'a>(&'a self, tag: &'a ContentTag, locale: Option<&str>) -> &'a str {
+        tag.id.as_deref()
+            .and_then(|id| self.display_name(id, locale))
+            .unwrap_or(&tag.label)
+    }
+
+    /// Set the id of each tag that doesn't have one and whose label resolves.
+    pub fn annotate(&self, tags: &mut [ContentTag]) {
+        for tag in tags.iter_mut().filter(|t| t.id.is_none()) {
+            tag.id = self.resolve(&tag.label).map(str::to_string);
+        }
+    }
+
+    /// Add the ancestors of every tag, so filters on a parent match its
+    /// children.
+    ///
+    /// An ancestor gets the confidence of its most confident descendant,
+    /// raising it if already tagged, and is labeled with its first alias
+    /// (or id). The result is sorted by confidence, highest first.
+    pub fn expand_ancestors(&self, tags: &[ContentTag]) -> Vec<ContentTag> {
+        let mut expanded = tags.to_vec();
+        self.annotate(&mut expanded);
+
+        for tag in tags {
+            let Some(id) = tag.id.as_deref().or_else(|| self.resolve(&tag.label)) else {
+                continue;
+            };
+            for ancestor in self.ancestors(id) {
+                match expanded.iter_mut().find(|t| t.id.as_deref() == Some(ancestor.id.as_str())) {
+                    Some(existing) => existing.confidence = existing.confidence.max(tag.confidence),
+                    None => expanded.push(ContentTag {
+                        label: ancestor.aliases.first().unwrap_or(&ancestor.id).clone(),
+                        confidence: tag.confidence,
+                        id: Some(ancestor.id.clone()),
+                    }),
+                }
+            }
+        }
+
+        expanded.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        expanded
+    }
+}
+
+impl Default for TagTaxonomy {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, parent: Option<&str>) -> TaxonomyEntry {
+        TaxonomyEntry {
+            id: id.to_string(),
+            parent: parent.map(str::to_string),
+            name: id.to_string(),
+            translations: HashMap::new(),
+            aliases: Vec::new(),
+        }
+    }
+
+    fn tag(label: &str, confidence: f32) -> ContentTag {
+        ContentTag::new(label, confidence)
+    }
+
+    #[test]
+    fn test_builtin_covers_rule_tags() {
+        let taxonomy = TagTaxonomy::builtin();
+        for profile in kino_tagging::GENRE_PROFILES {
+            assert!(taxonomy.resolve(profile.name).is_some(), "{} has no id", profile.name);
+        }
+        for label in ["energetic", "calm", "dramatic", "vocal", "instrumental", "ambient", "high-fidelity", "compressed", "noisy"] {
+            assert!(taxonomy.resolve(label).is_some(), "{} has no id", label);
+        }
+
+        assert_eq!(taxonomy.resolve("Music"), Some("genre.music"));
+        assert_eq!(taxonomy.resolve("genre.music.electronic"), Some("genre.music.electronic"));
+        assert_eq!(taxonomy.resolve("EDM"), Some("genre.music.electronic"));
+        assert_eq!(taxonomy.resolve("lang:en"), None);
+
+        let round_trip = TagTaxonomy::from_json(&taxonomy.to_json().unwrap()).unwrap();
+        assert_eq!(round_trip.entries(), taxonomy.entries());
+    }
+
+    #[test]
+    fn test_display_names() {
+        let taxonomy = TagTaxonomy::builtin();
+        assert_eq!(taxonomy.display_name("genre.music", None), Some("Music"));
+        assert_eq!(taxonomy.display_name("genre.music", Some("es")), Some("Música"));
+        assert_eq!(taxonomy.display_name("genre.music", Some("es-MX")), Some("Música"));
+        assert_eq!(taxonomy.display_name("genre.music", Some("de_AT")), Some("Musik"));
+        assert_eq!(taxonomy.display_name("genre.music", Some("xx")), Some("Music"));
+        assert_eq!(taxonomy.display_name("genre.unknown", Some("es")), None);
+
+        let mut tags = vec![tag("speech", 0.8), tag("laughter", 0.5)];
+        taxonomy.annotate(&mut tags);
+        assert_eq!(tags[0].id.as_deref(), Some("genre.speech"));
+        assert_eq!(taxonomy.tag_name(&tags[0], Some("fr")), "Parole");
+        assert_eq!(taxonomy.tag_name(&tags[1], Some("fr")), "laughter");
+    }
+
+    #[test]
+    fn test_expand_ancestors() {
+        let taxonomy = TagTaxonomy::builtin();
+        let tags = [tag("electronic", 0.9), tag("music", 0.4), tag("calm", 0.5)];
+
+        let expanded = taxonomy.expand_ancestors(&tags);
+        let confidence = |id: &str| expanded.iter().find(|t| t.id.as_deref() == Some(id)).map(|t| t.confidence);
+        assert_eq!(confidence("genre.music.electronic"), Some(0.9));
+        assert_eq!(confidence("genre.music"), Some(0.9));
+        assert_eq!(confidence("genre"), Some(0.9));
+        assert_eq!(confidence("mood"), Some(0.5));
+        assert_eq!(expanded.len(), 5);
+        assert!(expanded.windows(2).all(|w| w[0].confidence >= w[1].confidence));
+
+        assert!(taxonomy.matches(&tags, "genre.music"));
+        assert!(!taxonomy.matches(&tags, "genre.speech"));
+        assert!(taxonomy.is_a("genre.speech.podcast", "genre.speech"));
+        assert!(!taxonomy.is_a("genre.speech", "genre.speech.podcast"));
+    }
+
+    #[test]
+    fn test_legacy_aliases() {
+        let mut taxonomy = TagTaxonomy::builtin();
+        assert_eq!(taxonomy.resolve("techno"), None);
+        taxonomy.add_alias("techno", "genre.music.electronic").unwrap();
+        assert_eq!(taxonomy.resolve("Techno"), Some("genre.music.electronic"));
+
+        assert!(taxonomy.add_alias("techno", "genre.music.rock").is_err());
+        assert!(taxonomy.add_alias("polka", "genre.music.polka").is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_taxonomies() {
+        let orphan = TagTaxonomy::from_entries(vec![entry("genre.music", Some("genre"))]);
+        assert!(orphan.unwrap_err().to_string().contains("unknown parent"));
+
+        let duplicate = TagTaxonomy::from_entries(vec![entry("genre", None), entry("genre", None)]);
+        assert!(duplicate.unwrap_err().to_string().contains("Duplicate"));
+
+        let cycle = TagTaxonomy::from_entries(vec![entry("a", Some("b")), entry("b", Some("a"))]);
+        assert!(cycle.unwrap_err().to_string().contains("own ancestor"));
+
+        let mut music = entry("music", None);
+        let mut speech = entry("speech", None);
+        music.aliases.push("audio".to_string());
+        speech.aliases.push("Audio".to_string());
+        assert!(TagTaxonomy::from_entries(vec![music, speech]).is_err());
+
+        assert!(TagTaxonomy::from_json("{\"tags\": [{\"id\": \"genre\"}]}").is_err());
+    }
+}
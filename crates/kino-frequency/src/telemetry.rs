@@ -0,0 +1,163 @@
+//! Tracing spans and metrics emitted by the analysis pipeline.
+//!
+//! Names and fields listed here are stable, so dashboards and alerts can
+//! rely on them.
+//!
+//! # Spans
+//!
+//! Entry points run in `INFO` spans. Fields are recorded as soon as they are
+//! known; `elapsed_ms` is recorded when the span's work finishes, whether it
+//! succeeded or failed.
+//!
+//! | Span | Fields |
+//! |------|--------|
+//! | `process_video` | `content_id`, `path`, `sample_rate`, `elapsed_ms` |
+//! | `batch_item` | `content_id`, `index`, `elapsed_ms` |
+//! | `extract_audio` | `path`, `sample_rate`, `samples`, `duration_secs`, `elapsed_ms` |
+//! | `analyze` | `samples`, `duration_secs`, `fft_size`, `elapsed_ms` |
+//! | `fingerprint` | `samples`, `duration_secs`, `fft_size`, `elapsed_ms` |
+//! | `predict` | `samples`, `duration_secs`, `fft_size`, `elapsed_ms` |
+//! | `find_best_timestamp` | `path`, `duration_secs`, `candidates`, `elapsed_ms` |
+//! | `pipeline_stage` | `content_id`, `stage`, `samples`, `duration_secs`, `elapsed_ms` |
+//!
+//! `samples` and `duration_secs` describe the input audio, except on
+//! `find_best_timestamp` where `duration_secs` is the video's duration.
+//! [`process_video`](crate::process_video) and batch items wrap each analysis
+//! stage in a `pipeline_stage` span whose `stage` is one of `fingerprint`,
+//! `tagging`, `thumbnail`, `signature`, `dominant_frequencies` or `loudness`.
+//! The content id is derived from the fingerprint (see
+//! [`ContentIdSource`](crate::ContentIdSource)), so `content_id` is recorded
+//! after the `fingerprint` stage and is absent from its span unless the
+//! caller supplied the id.
+//! Failed stages log a `DEBUG` event with `stage` and `kind` fields.
+//!
+//! # Metrics
+//!
+//! With the `metrics` feature the same work is reported through the
+//! [`metrics`](https://docs.rs/metrics) facade. Install a recorder (such as a
+//! Prometheus or OpenTelemetry exporter) to collect them; without one they
+//! cost next to nothing.
+//!
+//! | Metric | Type | Labels | Counts |
+//! |--------|------|--------|--------|
+//! | [`ANALYSES_TOTAL`] | counter | | Pipeline runs, one per video or batch item |
+//! | [`FINGERPRINTS_TOTAL`] | counter | | Fingerprints generated |
+//! | [`FAILURES_TOTAL`] | counter | `stage`, `kind` | Failed spans and stages |
+//! | [`STAGE_DURATION_SECONDS`] | histogram | `stage` | Wall time of each span and stage |
+//!
+//! `stage` is a span name from the table above (`process_video`,
+//! `extract_audio`, `analyze`, `fingerprint`, `predict`,
+//! `find_best_timestamp`) or a pipeline stage. `kind` is `too_short`,
+//! `invalid_samples`, `zero_signal`, `cancelled` or `other`, following [`FrequencyError`].
+
+use std::time::Instant;
+
+use tracing::{debug, info_span, Span};
+
+use crate::types::FrequencyError;
+
+/// Counter of pipeline runs.
+pub const ANALYSES_TOTAL: &str = "kino_frequency_analyses_total";
+
+/// Counter of fingerprints generated.
+pub const FINGERPRINTS_TOTAL: &str = "kino_frequency_fingerprints_total";
+
+/// Counter of failures, labelled by `stage` and error `kind`.
+pub const FAILURES_TOTAL: &str = "kino_frequency_failures_total";
+
+/// Histogram of stage durations in seconds, labelled by `stage`.
+pub const STAGE_DURATION_SECONDS: &str = "kino_frequency_stage_duration_seconds";
+
+/// Errors that can be classified for [`FAILURES_TOTAL`].
+pub(crate) trait FailureKind {
+    fn failure_kind(&self) -> &'static str;
+}
+
+impl FailureKind for FrequencyError {
+    fn failure_kind(&self) -> &'static str {
+        self.kind()
+    }
+}
+
+impl FailureKind for anyhow::Error {
+    fn failure_kind(&self) -> &'static str {
+        self.downcast_ref::<FrequencyError>().map_or("other", FrequencyError::kind)
+    }
+}
+
+/// Times work done in the current span.
+pub(crate) struct StageTimer {
+    stage: &'static str,
+    span: Span,
+    start: Instant,
+}
+
+impl StageTimer {
+    /// Start timing `stage` in the current span.
+    pub(crate) fn start(stage: &'static str) -> Self {
+        Self { stage, span: Span::current(), start: Instant::now() }
+    }
+
+    /// Record the elapsed time and any failure, passing `result` through.
+    pub(crate) fn finish<T, E: FailureKind>(self, result: Result<T, E>) -> Result<T, E> {
+        let elapsed = self.start.elapsed();
+        self.span.record("elapsed_ms", elapsed.as_secs_f64() * 1000.0);
+        #[cfg(feature = "metrics")]
+        metrics::histogram!(STAGE_DURATION_SECONDS, "stage" => self.stage).record(elapsed.as_secs_f64());
+
+        if let Err(e) = &result {
+            let kind = e.failure_kind();
+            debug!(stage = self.stage, kind, "Stage failed");
+            #[cfg(feature = "metrics")]
+            metrics::counter!(FAILURES_TOTAL, "stage" => self.stage, "kind" => kind).increment(1);
+        }
+        result
+    }
+}
+
+/// Run one stage of an analysis pipeline in a `pipeline_stage` span.
+///
+/// `content_id` is absent while it is still being derived.
+pub(crate) fn pipeline_stage<T, E: FailureKind>(
+    content_id: Option<&str>,
+    stage: &'static str,
+    audio: &crate::types::AudioData,
+    run: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    let span = info_span!(
+        "pipeline_stage",
+        content_id,
+        stage,
+        samples = audio.samples.len(),
+        duration_secs = audio.duration_secs,
+        elapsed_ms = tracing::field::Empty,
+    );
+    let _entered = span.enter();
+    StageTimer::start(stage).finish(run())
+}
+
+/// Increment a counter without labels.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn increment(counter: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(counter).increment(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_kinds() {
+        assert_eq!(FrequencyError::ZeroSignal.failure_kind(), "zero_signal");
+        assert_eq!(anyhow::Error::from(FrequencyError::TooShort { samples: 1, required: 2 }).failure_kind(), "too_short");
+        assert_eq!(anyhow::anyhow!("FFmpeg not found").failure_kind(), "other");
+    }
+
+    #[test]
+    fn test_pipeline_stage_passes_result_through() {
+        let audio = crate::types::AudioData::new(vec![0.0; 100], 100);
+        assert_eq!(pipeline_stage(Some("id"), "loudness", &audio, || Ok::<_, FrequencyError>(3)).unwrap(), 3);
+        assert!(pipeline_stage(None, "loudness", &audio, || Err::<(), _>(FrequencyError::ZeroSignal)).is_err());
+    }
+}
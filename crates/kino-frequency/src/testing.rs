@@ -0,0 +1,429 @@
+//! Fixtures for testing without FFmpeg.
+//!
+//! Compiled for this crate's own tests and, with the `testing` feature, for
+//! downstream crates:
+//!
+//! - Deterministic synthetic audio: [`sine`], [`sweep`], [`noise`],
+//!   [`speech_like`] and [`click_track`]
+//! - [`wav_bytes`] and [`write_wav`] for WAV fixtures on disk or in memory
+//! - Synthetic analysis frames: [`flat_frame`] and [`stripes_frame`]
+//! - [`MockBackend`], a [`MediaBackend`] serving that audio and those frames
+//!   so [`AudioAnalyzer`](crate::AudioAnalyzer), the thumbnail selector and
+//!   [`process_video_with_backend`](crate::process_video_with_backend) run
+//!   without any media tools installed
+
+use std::f32::consts::PI;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use image::GrayImage;
+
+use crate::media::{
+    AudioExtractOptions, AudioTrack, ColorInfo, DecodeOptions, MediaBackend, ANALYSIS_FRAME_HEIGHT, ANALYSIS_FRAME_WIDTH,
+};
+use crate::resample::resample;
+use crate::types::AudioData;
+
+/// Seed shared by the noise generators, so fixtures are identical across runs.
+const NOISE_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Length of each click in [`click_track`] (seconds).
+const CLICK_SECS: f32 = 0.01;
+
+fn num_samples(sample_rate: u32, secs: f32) -> usize {
+    (sample_rate as f32 * secs) as usize
+}
+
+/// Xorshift generator yielding uniform samples in [-0.5, 0.5).
+fn xorshift(mut seed: u64) -> impl FnMut() -> f32 {
+    move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+    }
+}
+
+/// A sine tone at half amplitude.
+pub fn sine(freq: f32, sample_rate: u32, secs: f32) -> AudioData {
+    let rate = sample_rate as f32;
+    let samples = (0..num_samples(sample_rate, secs))
+        .map(|i| 0.5 * (2.0 * PI * freq * i as f32 / rate).sin())
+        .collect();
+    AudioData::new(samples, sample_rate)
+}
+
+/// A linear sweep from `start_hz` to `end_hz` at half amplitude.
+pub fn sweep(start_hz: f32, end_hz: f32, sample_rate: u32, secs: f32) -> AudioData {
+    let rate = sample_rate as f32;
+    let slope = (end_hz - start_hz) / secs.max(f32::EPSILON);
+    let samples = (0..num_samples(sample_rate, secs))
+        .map(|i| {
+            let t = i as f32 / rate;
+            0.5 * (2.0 * PI * (start_hz * t + 0.5 * slope * t * t)).sin()
+        })
+        .collect();
+    AudioData::new(samples, sample_rate)
+}
+
+/// Uniform white noise at half amplitude.
+pub fn noise(sample_rate: u32, secs: f32) -> AudioData {
+    let mut next = xorshift(NOISE_SEED);
+    let samples = (0..num_samples(sample_rate, secs)).map(|_| next()).collect();
+    AudioData::new(samples, sample_rate)
+}
+
+/// Formant-shaped noise in 4 Hz syllables with a pause every second.
+pub fn speech_like(sample_rate: u32, secs: f32) -> AudioData {
+    let rate = sample_rate as f32;
+    let mut next = xorshift(NOISE_SEED);
+    let formants: Vec<(f32, f32)> = [700.0f32, 1200.0, 2600.0]
+        .iter()
+        .map(|f| (2.0 * 0.97 * (2.0 * PI * f / rate).cos(), 0.97 * 0.97))
+        .collect();
+    let mut state = vec![(0.0f32, 0.0f32); formants.len()];
+
+    let samples = (0..num_samples(sample_rate, secs))
+        .map(|i| {
+            let excitation = next();
+            let voice: f32 = formants.iter()
+                .zip(&mut state)
+                .map(|(&(a1, a2), (y1, y2))| {
+                    let y = excitation + a1 * *y1 - a2 * *y2;
+                    *y2 = *y1;
+                    *y1 = y;
+                    y
+                })
+                .sum();
+
+            let t = i as f32 / rate;
+            let syllable = 0.5 - 0.5 * (2.0 * PI * 4.0 * t).cos();
+            let pause = if t.fract() > 0.75 { 0.0 } else { 1.0 };
+            0.02 * voice * syllable * pause
+        })
+        .collect();
+    AudioData::new(samples, sample_rate)
+}
+
+/// Short decaying 2 kHz clicks at `bpm`, starting at zero, over silence.
+pub fn click_track(bpm: f32, sample_rate: u32, secs: f32) -> AudioData {
+    let rate = sample_rate as f32;
+    let interval = num_samples(sample_rate, 60.0 / bpm).max(1);
+    let click = num_samples(sample_rate, CLICK_SECS).min(interval);
+    let samples = (0..num_samples(sample_rate, secs))
+        .map(|i| {
+            let offset = i % interval;
+            if offset >= click {
+                return 0.0;
+            }
+            let t = offset as f32 / rate;
+            0.9 * (-t / (CLICK_SECS / 4.0)).exp() * (2.0 * PI * 2000.0 * t).sin()
+        })
+        .collect();
+    AudioData::new(samples, sample_rate)
+}
+
+/// Encode audio as a 16-bit PCM WAV file.
+pub fn wav_bytes(audio: &AudioData) -> Vec<u8> {
+    let channels = audio.channels.max(1) as u16;
+    let data_len = (audio.samples.len() * 2) as u32;
+    let byte_rate = audio.sample_rate * channels as u32 * 2;
+
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&audio.sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&byte_rate.to_le_bytes());
+    bytes.extend_from_slice(&(channels * 2).to_le_bytes()); // block align
+    bytes.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for &sample in &audio.samples {
+        let sample = if sample.is_finite() { sample.clamp(-1.0, 1.0) } else { 0.0 };
+        bytes.extend_from_slice(&((sample * i16::MAX as f32).round() as i16).to_le_bytes());
+    }
+    bytes
+}
+
+/// Write audio to `path` as a 16-bit PCM WAV file.
+pub fn write_wav(path: impl AsRef<Path>, audio: &AudioData) -> Result<()> {
+    let path = path.as_ref();
+    std::fs::write(path, wav_bytes(audio))
+        .with_context(|| format!("Failed to write WAV file: {}", path.display()))
+}
+
+/// A featureless analysis frame of one gray level.
+pub fn flat_frame(level: u8) -> GrayImage {
+    GrayImage::from_pixel(ANALYSIS_FRAME_WIDTH, ANALYSIS_FRAME_HEIGHT, image::Luma([level]))
+}
+
+/// A sharp, high-contrast analysis frame of black and white vertical stripes
+/// `width` pixels wide.
+pub fn stripes_frame(width: u32) -> GrayImage {
+    let width = width.max(1);
+    GrayImage::from_fn(ANALYSIS_FRAME_WIDTH, ANALYSIS_FRAME_HEIGHT, |x, _| {
+        image::Luma([if (x / width).is_multiple_of(2) { 255 } else { 0 }])
+    })
+}
+
+/// An analysis frame of white "text" on black: `lines` evenly spaced lines
+/// of short vertical strokes grouped into words, like rolling credits.
+pub fn text_frame(lines: u32) -> GrayImage {
+    let (line_height, pitch) = (8, ANALYSIS_FRAME_HEIGHT / (lines.max(1) + 1));
+    GrayImage::from_fn(ANALYSIS_FRAME_WIDTH, ANALYSIS_FRAME_HEIGHT, |x, y| {
+        let in_line = (1..=lines).any(|line| (line * pitch..line * pitch + line_height).contains(&y));
+        let in_word = x % 48 < 40 && (40..ANALYSIS_FRAME_WIDTH - 40).contains(&x);
+        let stroke = (x + y / pitch) % 5 < 2;
+        image::Luma([if in_line && in_word && stroke { 255 } else { 0 }])
+    })
+}
+
+type FrameSource = Box<dyn Fn(f64) -> Option<GrayImage> + Send + Sync>;
+
+/// A [`MediaBackend`] serving synthetic media, whatever the path.
+///
+/// Frames come from a function of the timestamp (mid-gray by default) and
+/// audio from [`with_audio`](Self::with_audio), or from the stream picked
+/// among [`with_audio_track`](Self::with_audio_track)s, cut to the requested
+/// time range and resampled to the requested rate. Every frame timestamp
+/// asked for is recorded for inspection, along with its decode options.
+pub struct MockBackend {
+    duration: f64,
+    audio: Option<AudioData>,
+    tracks: Vec<(AudioTrack, AudioData)>,
+    frames: FrameSource,
+    color: ColorInfo,
+    frame_requests: Mutex<Vec<(f64, DecodeOptions)>>,
+}
+
+impl MockBackend {
+    /// Media of `duration` seconds with flat gray frames and no audio track.
+    pub fn new(duration: f64) -> Self {
+        Self {
+            duration,
+            audio: None,
+            tracks: Vec::new(),
+            frames: Box::new(|_| Some(flat_frame(128))),
+            color: ColorInfo::default(),
+            frame_requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Serve `audio` as the audio track.
+    pub fn with_audio(mut self, audio: AudioData) -> Self {
+        self.audio = Some(audio);
+        self
+    }
+
+    /// Add an audio stream, listed by `audio_tracks` and extracted when
+    /// selected by index, or by default when there is no [`with_audio`](Self::with_audio).
+    pub fn with_audio_track(mut self, track: AudioTrack, audio: AudioData) -> Self {
+        self.tracks.push((track, audio));
+        self
+    }
+
+    /// Serve frames from `frames`; returning `None` makes extraction at that
+    /// timestamp fail, as a corrupt or undecodable frame would.
+    pub fn with_frames(mut self, frames: impl Fn(f64) -> Option<GrayImage> + Send + Sync + 'static) -> Self {
+        self.frames = Box::new(frames);
+        self
+    }
+
+    /// Report `color` as the video's color metadata.
+    pub fn with_color(mut self, color: ColorInfo) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Timestamps of every frame extracted so far, in request order.
+    pub fn frame_requests(&self) -> Vec<f64> {
+        self.frame_requests.lock().unwrap_or_else(|e| e.into_inner()).iter().map(|(t, _)| *t).collect()
+    }
+
+    /// Decode options of every frame extracted so far, in request order.
+    pub fn decode_requests(&self) -> Vec<DecodeOptions> {
+        self.frame_requests.lock().unwrap_or_else(|e| e.into_inner()).iter().map(|(_, o)| o.clone()).collect()
+    }
+
+    fn frame(&self, timestamp: f64, options: &DecodeOptions) -> Result<GrayImage> {
+        self.frame_requests.lock().unwrap_or_else(|e| e.into_inner()).push((timestamp, options.clone()));
+        if !(0.0..=self.duration).contains(&timestamp) {
+            bail!("Frame at {:.2}s is outside the {:.2}s media", timestamp, self.duration);
+        }
+        (self.frames)(timestamp)
+            .ok_or_else(|| anyhow::anyhow!("Failed to extract frame at {:.2}s", timestamp))
+    }
+}
+
+#[async_trait]
+impl MediaBackend for MockBackend {
+    async fn extract_audio(&self, _path: &Path, sample_rate: u32, options: &AudioExtractOptions) -> Result<AudioData> {
+        let audio = match options.stream_index {
+            Some(index) => self.tracks.iter()
+                .find(|(track, _)| track.index == index)
+                .map(|(_, audio)| audio)
+                .with_context(|| format!("Mock media has no audio stream {}", index))?,
+            None => match self.audio.as_ref().or_else(|| self.tracks.first().map(|(_, audio)| audio)) {
+                Some(audio) => audio,
+                None => bail!("Mock media has no audio track"),
+            },
+        };
+
+        let audio = audio.to_mono();
+        let rate = audio.sample_rate as f64;
+        let start = ((options.start.unwrap_or(0.0) * rate) as usize).min(audio.samples.len());
+        let end = options.duration
+            .map_or(audio.samples.len(), |duration| start + (duration * rate) as usize)
+            .min(audio.samples.len());
+        let cut = AudioData::new(audio.samples[start..end].to_vec(), audio.sample_rate);
+        Ok(resample(&cut, sample_rate))
+    }
+
+    fn audio_tracks(&self, _path: &Path) -> Result<Vec<AudioTrack>> {
+        Ok(self.tracks.iter().map(|(track, _)| track.clone()).collect())
+    }
+
+    fn duration(&self, _path: &Path) -> Result<f64> {
+        Ok(self.duration)
+    }
+
+    fn color_info(&self, _path: &Path) -> Result<ColorInfo> {
+        Ok(self.color.clone())
+    }
+
+    fn extract_frame(&self, _path: &Path, timestamp: f64, options: &DecodeOptions) -> Result<GrayImage> {
+        self.frame(timestamp, options)
+    }
+
+    fn extract_frames(
+        &self,
+        _path: &Path,
+        start: f64,
+        duration: f64,
+        fps: f64,
+        options: &DecodeOptions,
+    ) -> Result<Vec<GrayImage>> {
+        let end = (start + duration).min(self.duration);
+        let count = ((end - start) * fps).ceil().max(0.0) as usize;
+        (0..count).map(|i| self.frame(start + i as f64 / fps, options)).collect()
+    }
+
+    fn write_thumbnail(
+        &self,
+        _path: &Path,
+        timestamp: f64,
+        width: u32,
+        height: u32,
+        output: &Path,
+        options: &DecodeOptions,
+    ) -> Result<()> {
+        let frame = image::imageops::resize(
+            &self.frame(timestamp, options)?,
+            width,
+            height,
+            image::imageops::FilterType::Triangle,
+        );
+        let image = image::DynamicImage::ImageLuma8(frame);
+        let saved = if options.high_bit_depth { image.to_luma16().save(output) } else { image.save(output) };
+        saved.with_context(|| format!("Failed to write thumbnail: {}", output.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AudioAnalyzer;
+
+    #[test]
+    fn test_generators() {
+        let rms = |audio: &AudioData| {
+            (audio.samples.iter().map(|s| s * s).sum::<f32>() / audio.samples.len() as f32).sqrt()
+        };
+        for audio in [
+            sine(440.0, 8000, 2.0),
+            sweep(100.0, 3000.0, 8000, 2.0),
+            noise(8000, 2.0),
+            speech_like(8000, 2.0),
+            click_track(120.0, 8000, 2.0),
+        ] {
+            assert_eq!((audio.samples.len(), audio.duration_secs), (16000, 2.0));
+            assert!(audio.samples.iter().all(|s| s.abs() <= 1.0));
+            assert!(rms(&audio) > 0.001);
+        }
+        assert_eq!(noise(8000, 1.0).samples, noise(8000, 1.0).samples);
+
+        // Two clicks a second, silent between them
+        let clicks = click_track(120.0, 8000, 2.0);
+        let onsets = clicks.samples.windows(2).filter(|w| w[0] == 0.0 && w[1] != 0.0).count();
+        assert_eq!(onsets, 4);
+    }
+
+    #[test]
+    fn test_wav_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sweep.wav");
+        let audio = sweep(200.0, 2000.0, 22050, 1.0);
+        write_wav(&path, &audio).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 44 + 2 * 22050);
+
+        let loaded = AudioAnalyzer::new(22050).load_wav(&path).unwrap();
+        assert_eq!((loaded.sample_rate, loaded.channels), (22050, 1));
+        assert_eq!(loaded.samples.len(), audio.samples.len());
+        assert!(loaded.samples.iter().zip(&audio.samples).all(|(a, b)| (a - b).abs() < 1e-4));
+    }
+
+    #[tokio::test]
+    async fn test_mock_backend() {
+        let backend = MockBackend::new(5.0)
+            .with_audio(sine(440.0, 44100, 5.0))
+            .with_frames(|t| (t < 3.0).then(|| stripes_frame(2)));
+
+        let whole = AudioExtractOptions::default();
+        let audio = backend.extract_audio(Path::new("any.mp4"), 22050, &whole).await.unwrap();
+        assert_eq!((audio.sample_rate, audio.samples.len()), (22050, 110_250));
+
+        let range = AudioExtractOptions { start: Some(1.0), duration: Some(2.0), ..Default::default() };
+        let cut = backend.extract_audio(Path::new("any.mp4"), 22050, &range).await.unwrap();
+        assert_eq!(cut.samples.len(), 44_100);
+
+        let mut streamed = Vec::new();
+        let mut chunks = 0;
+        backend.stream_audio(Path::new("any.mp4"), 22050, &whole, &mut |chunk| {
+            chunks += 1;
+            streamed.extend_from_slice(chunk);
+            Ok(())
+        }).await.unwrap();
+        assert_eq!((streamed, chunks), (audio.samples.clone(), 4));
+
+        let path = Path::new("any.mp4");
+        let options = DecodeOptions::default();
+        assert!(backend.extract_frame(path, 1.0, &options).is_ok());
+        assert!(backend.extract_frame(path, 4.0, &options).is_err());
+        assert!(backend.extract_frame(path, 6.0, &options).is_err());
+        assert_eq!(backend.extract_frames(path, 0.0, 1.0, 10.0, &options).unwrap().len(), 10);
+        assert_eq!(backend.frame_requests().len(), 13);
+
+        let missing = MockBackend::new(5.0);
+        assert!(missing.extract_audio(path, 22050, &whole).await.is_err());
+
+        let track = |index| AudioTrack { index, ..Default::default() };
+        let tracks = MockBackend::new(5.0)
+            .with_audio_track(track(1), sine(440.0, 44100, 5.0))
+            .with_audio_track(track(2), sine(880.0, 44100, 5.0));
+        assert_eq!(tracks.audio_tracks(path).unwrap().len(), 2);
+        let second = AudioExtractOptions { stream_index: Some(2), ..Default::default() };
+        let (default, english) = (
+            tracks.extract_audio(path, 22050, &whole).await.unwrap(),
+            tracks.extract_audio(path, 22050, &second).await.unwrap(),
+        );
+        assert_ne!(default.samples, english.samples);
+        let absent = AudioExtractOptions { stream_index: Some(3), ..Default::default() };
+        assert!(tracks.extract_audio(path, 22050, &absent).await.is_err());
+    }
+}
@@ -5,15 +5,38 @@
 //! - **Audio energy** to find visually interesting moments
 //! - **Motion detection** to avoid blurry transitional frames
 //! - **Contrast analysis** for visually appealing frames
+//! - **Voice activity** to catch (or avoid) someone mid-sentence
+//! - **Text likeness** to avoid burned-in credits and lower-third graphics
+//!
+//! HDR (PQ/HLG) sources are tone-mapped to SDR before frames are scored, and
+//! frames can be decoded on the GPU via [`ThumbnailConfig::hwaccel`].
 
 use std::path::Path;
-use std::process::Command;
-use anyhow::{Result, bail, Context};
+use std::sync::Arc;
+use anyhow::Result;
 use image::GrayImage;
 use rustfft::{FftPlanner, num_complex::Complex};
-use tracing::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, field::Empty, info, instrument, warn, Span};
 
+use crate::media::{ColorInfo, DecodeOptions, FfmpegBackend, MediaBackend};
+use crate::telemetry::StageTimer;
 use crate::types::*;
+use crate::vad::{self, VadConfig};
+
+/// Audio around each candidate timestamp that its energy and speech activity are measured over (seconds).
+const AUDIO_WINDOW_SECS: f64 = 0.5;
+
+/// Candidates this close to one of [`ThumbnailConfig::exclude_timestamps`] are skipped (seconds).
+const EXCLUDE_TIMESTAMP_SECS: f64 = 1.0;
+
+/// Horizontal step between neighbouring pixels that counts as an edge (0-1).
+const TEXT_EDGE_THRESHOLD: f32 = 0.25;
+/// Fraction of a row's pixels that must be edges for it to count as a text row.
+const TEXT_ROW_DENSITY: f32 = 0.1;
+/// Tallest run of text rows, as a fraction of the frame height, still read
+/// as a line of text rather than texture covering the frame.
+const TEXT_MAX_LINE_HEIGHT: f32 = 0.15;
 
 /// Configuration for thumbnail selection.
 #[derive(Debug, Clone)]
@@ -32,10 +55,31 @@ pub struct ThumbnailConfig {
     pub contrast_weight: f32,
     /// Weight for audio energy correlation
     pub audio_weight: f32,
+    /// Weight for voice activity: positive prefers frames while someone is
+    /// speaking, negative avoids them (e.g. mid-word expressions), zero ignores speech
+    pub speech_weight: f32,
+    /// Voice activity detection used when `speech_weight` is non-zero
+    pub vad: VadConfig,
+    /// Penalty for text-like frames (credits, lower thirds), which score
+    /// high on sharpness and contrast precisely because text is sharp
+    pub text_weight: f32,
+    /// Time ranges to never pick a thumbnail from, as `(start, end)` seconds,
+    /// e.g. known end credits
+    pub exclude_ranges: Vec<(f64, f64)>,
+    /// Timestamps to avoid; candidates within a second of one are skipped
+    pub exclude_timestamps: Vec<f64>,
     /// Target thumbnail width
     pub output_width: u32,
     /// Target thumbnail height
     pub output_height: u32,
+    /// Tone-map HDR (PQ/HLG) sources to SDR for frame scoring and thumbnails
+    pub tonemap_hdr: bool,
+    /// FFmpeg hardware decoder to try for frames (e.g. `auto`, `cuda`,
+    /// `videotoolbox`); falls back to software decoding when it fails
+    pub hwaccel: Option<String>,
+    /// Have `extract_thumbnail` write both a tone-mapped SDR JPEG and an
+    /// original-range 16-bit PNG, named after the output path
+    pub hdr_variants: bool,
 }
 
 impl Default for ThumbnailConfig {
@@ -48,8 +92,16 @@ impl Default for ThumbnailConfig {
             sharpness_weight: 0.4,
             contrast_weight: 0.3,
             audio_weight: 0.3,
+            speech_weight: 0.0,
+            vad: VadConfig::default(),
+            text_weight: 0.3,
+            exclude_ranges: Vec::new(),
+            exclude_timestamps: Vec::new(),
             output_width: 1280,
             output_height: 720,
+            tonemap_hdr: true,
+            hwaccel: None,
+            hdr_variants: false,
         }
     }
 }
@@ -57,6 +109,7 @@ impl Default for ThumbnailConfig {
 /// Thumbnail selector using frequency-based frame analysis.
 pub struct ThumbnailSelector {
     config: ThumbnailConfig,
+    backend: Arc<dyn MediaBackend>,
 }
 
 impl ThumbnailSelector {
@@ -67,10 +120,29 @@ impl ThumbnailSelector {
 
     /// Create a selector with custom configuration.
     pub fn with_config(config: ThumbnailConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            backend: Arc::new(FfmpegBackend),
+        }
+    }
+
+    /// Probe and extract frames with `backend` instead of FFmpeg.
+    pub fn with_backend(mut self, backend: Arc<dyn MediaBackend>) -> Self {
+        self.backend = backend;
+        self
     }
 
     /// Find the best timestamp for a thumbnail.
+    #[instrument(
+        name = "find_best_timestamp",
+        skip_all,
+        fields(
+            path = %video_path.as_ref().display(),
+            duration_secs = Empty,
+            candidates = self.config.num_candidates,
+            elapsed_ms = Empty,
+        ),
+    )]
     pub fn find_best_timestamp(
         &self,
         video_path: impl AsRef<Path>,
@@ -78,44 +150,51 @@ impl ThumbnailSelector {
     ) -> Result<f64> {
         let video_path = video_path.as_ref();
         info!("Finding best thumbnail timestamp for: {}", video_path.display());
+        let timer = StageTimer::start("find_best_timestamp");
+        timer.finish(self.best_timestamp(video_path, audio))
+    }
 
+    fn best_timestamp(&self, video_path: &Path, audio: &AudioData) -> Result<f64> {
         // Get video duration
         let duration = self.get_video_duration(video_path)?;
+        Span::current().record("duration_secs", duration);
         debug!("Video duration: {:.2}s", duration);
 
         // Calculate valid range
         let start_time = self.config.skip_start_secs;
         let end_time = (duration - self.config.skip_end_secs).max(start_time + 1.0);
 
-        // Generate candidate timestamps
+        // Generate candidate timestamps outside the excluded regions
         let step = (end_time - start_time) / self.config.num_candidates as f64;
         let timestamps: Vec<f64> = (0..self.config.num_candidates)
             .map(|i| start_time + i as f64 * step)
+            .filter(|&t| !self.is_excluded(t))
             .collect();
 
-        // Analyze audio energy at each timestamp
+        // Analyze audio energy and speech at each timestamp
         let audio_energies = self.compute_audio_energies(audio, &timestamps);
+        let speech_activity = self.compute_speech_activity(audio, &timestamps);
+        let options = self.decode_options(&self.color_info(video_path));
 
         // Score each candidate
         let mut candidates: Vec<(f64, f32)> = Vec::new();
 
         for (i, &timestamp) in timestamps.iter().enumerate() {
             // Extract frame at timestamp
-            match self.extract_frame(video_path, timestamp) {
+            match self.extract_frame(video_path, timestamp, &options) {
                 Ok(frame) => {
                     let quality = self.analyze_frame_quality(&frame);
 
                     // Combine scores
                     let audio_score = audio_energies.get(i).copied().unwrap_or(0.5);
-                    let total_score = quality.sharpness * self.config.sharpness_weight
-                        + quality.contrast * self.config.contrast_weight
-                        + audio_score * self.config.audio_weight;
+                    let speech = speech_activity[i];
+                    let total_score = self.total_score(&quality, audio_score, speech);
 
                     if quality.sharpness >= self.config.min_sharpness {
                         candidates.push((timestamp, total_score));
                         debug!(
-                            "Frame at {:.2}s: sharpness={:.3}, contrast={:.3}, audio={:.3}, total={:.3}",
-                            timestamp, quality.sharpness, quality.contrast, audio_score, total_score
+                            "Frame at {:.2}s: sharpness={:.3}, contrast={:.3}, text={:.3}, audio={:.3}, speech={:.3}, total={:.3}",
+                            timestamp, quality.sharpness, quality.contrast, quality.text_likeness, audio_score, speech, total_score
                         );
                     }
                 }
@@ -126,14 +205,15 @@ impl ThumbnailSelector {
         }
 
         // Find best candidate
-        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
 
         if let Some((best_timestamp, best_score)) = candidates.first() {
             info!("Best thumbnail at {:.2}s with score {:.3}", best_timestamp, best_score);
             Ok(*best_timestamp)
         } else {
-            // Fallback to middle of video
-            let fallback = (start_time + end_time) / 2.0;
+            // Fallback to middle of video, or the first candidate if that is excluded
+            let middle = (start_time + end_time) / 2.0;
+            let fallback = if self.is_excluded(middle) { timestamps.first().copied().unwrap_or(middle) } else { middle };
             warn!("No suitable frames found, using fallback at {:.2}s", fallback);
             Ok(fallback)
         }
@@ -160,35 +240,40 @@ impl ThumbnailSelector {
         let step = (end_time - start_time) / num_samples as f64;
         let timestamps: Vec<f64> = (0..num_samples)
             .map(|i| start_time + i as f64 * step)
+            .filter(|&t| !self.is_excluded(t))
             .collect();
 
-        // Analyze audio energy
+        // Analyze audio energy and speech
         let audio_energies = self.compute_audio_energies(audio, &timestamps);
+        let speech_activity = self.compute_speech_activity(audio, &timestamps);
+        let color = self.color_info(video_path);
+        let options = self.decode_options(&color);
 
         // Analyze each frame
         let mut candidates: Vec<ThumbnailCandidate> = Vec::new();
 
         for (i, &timestamp) in timestamps.iter().enumerate() {
-            if let Ok(frame) = self.extract_frame(video_path, timestamp) {
+            if let Ok(frame) = self.extract_frame(video_path, timestamp, &options) {
                 let quality = self.analyze_frame_quality(&frame);
 
                 let audio_score = audio_energies.get(i).copied().unwrap_or(0.5);
-                let total_score = quality.sharpness * self.config.sharpness_weight
-                    + quality.contrast * self.config.contrast_weight
-                    + audio_score * self.config.audio_weight;
+                let total_score = self.total_score(&quality, audio_score, speech_activity[i]);
 
                 candidates.push(ThumbnailCandidate {
                     timestamp,
                     sharpness: quality.sharpness,
                     contrast: quality.contrast,
+                    text_likeness: quality.text_likeness,
                     audio_energy: audio_score,
+                    speech_activity: speech_activity[i],
                     total_score,
+                    color: color.clone(),
                 });
             }
         }
 
         // Sort by total score
-        candidates.sort_by(|a, b| b.total_score.partial_cmp(&a.total_score).unwrap_or(std::cmp::Ordering::Equal));
+        candidates.sort_by(|a, b| b.total_score.total_cmp(&a.total_score));
 
         // Diversify results (avoid clustering)
         let mut diversified = Vec::new();
@@ -211,90 +296,85 @@ impl ThumbnailSelector {
     }
 
     /// Extract a thumbnail at the specified timestamp.
+    ///
+    /// With `hdr_variants` set, `output_path` only names the files: the SDR
+    /// thumbnail goes to its `.jpg` sibling and the original-range one to
+    /// its `.png` sibling.
     pub fn extract_thumbnail(
         &self,
         video_path: impl AsRef<Path>,
         timestamp: f64,
         output_path: impl AsRef<Path>,
     ) -> Result<()> {
-        let video_path = video_path.as_ref();
-        let output_path = output_path.as_ref();
-
-        let output = Command::new("ffmpeg")
-            .args([
-                "-ss", &format!("{:.3}", timestamp),
-                "-i", &video_path.to_string_lossy(),
-                "-vframes", "1",
-                "-vf", &format!("scale={}:{}", self.config.output_width, self.config.output_height),
-                "-y",
-                &output_path.to_string_lossy(),
-            ])
-            .output()
-            .context("FFmpeg not found")?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            bail!("FFmpeg thumbnail extraction failed: {}", stderr);
+        let (video_path, output_path) = (video_path.as_ref(), output_path.as_ref());
+        let options = self.decode_options(&self.color_info(video_path));
+        if !self.config.hdr_variants {
+            return self.write_thumbnail(video_path, timestamp, output_path, &options);
         }
 
-        info!("Extracted thumbnail to: {}", output_path.display());
-        Ok(())
+        self.write_thumbnail(video_path, timestamp, &output_path.with_extension("jpg"), &options)?;
+        let original = DecodeOptions { tonemap: None, high_bit_depth: true, ..options };
+        self.write_thumbnail(video_path, timestamp, &output_path.with_extension("png"), &original)
     }
 
-    /// Get video duration using ffprobe.
-    fn get_video_duration(&self, video_path: &Path) -> Result<f64> {
-        let output = Command::new("ffprobe")
-            .args([
-                "-v", "quiet",
-                "-print_format", "json",
-                "-show_format",
-                &video_path.to_string_lossy(),
-            ])
-            .output()
-            .context("FFprobe not found")?;
-
-        let json: serde_json::Value = serde_json::from_slice(&output.stdout)
-            .context("Failed to parse ffprobe output")?;
+    fn write_thumbnail(&self, video_path: &Path, timestamp: f64, output_path: &Path, options: &DecodeOptions) -> Result<()> {
+        self.backend.write_thumbnail(
+            video_path,
+            timestamp,
+            self.config.output_width,
+            self.config.output_height,
+            output_path,
+            options,
+        )
+    }
 
-        let duration = json["format"]["duration"]
-            .as_str()
-            .and_then(|d| d.parse::<f64>().ok())
-            .ok_or_else(|| anyhow::anyhow!("Could not determine video duration"))?;
+    /// Get the video duration from the media backend.
+    pub(crate) fn get_video_duration(&self, video_path: &Path) -> Result<f64> {
+        self.backend.duration(video_path)
+    }
 
-        Ok(duration)
+    /// Color metadata of the video, treated as SDR when it can't be probed.
+    pub(crate) fn color_info(&self, video_path: &Path) -> ColorInfo {
+        self.backend.color_info(video_path).unwrap_or_else(|e| {
+            debug!("Could not read color metadata, assuming SDR: {}", e);
+            ColorInfo::default()
+        })
     }
 
-    /// Extract a single frame as grayscale image.
-    fn extract_frame(&self, video_path: &Path, timestamp: f64) -> Result<GrayImage> {
-        // Extract frame to raw grayscale
-        let output = Command::new("ffmpeg")
-            .args([
-                "-ss", &format!("{:.3}", timestamp),
-                "-i", &video_path.to_string_lossy(),
-                "-vframes", "1",
-                "-vf", "scale=320:180,format=gray",  // Small for analysis
-                "-f", "rawvideo",
-                "-pix_fmt", "gray",
-                "pipe:1",
-            ])
-            .output()
-            .context("FFmpeg frame extraction failed")?;
-
-        if !output.status.success() || output.stdout.is_empty() {
-            bail!("Failed to extract frame at {:.2}s", timestamp);
+    /// How to decode frames of a video with `color` metadata.
+    pub(crate) fn decode_options(&self, color: &ColorInfo) -> DecodeOptions {
+        let tonemap = color.hdr().filter(|_| self.config.tonemap_hdr);
+        if let Some(transfer) = tonemap {
+            debug!("Tone-mapping {:?} HDR frames to SDR", transfer);
         }
-
-        let width = 320;
-        let height = 180;
-
-        if output.stdout.len() < width * height {
-            bail!("Incomplete frame data");
+        DecodeOptions {
+            tonemap,
+            hwaccel: self.config.hwaccel.clone(),
+            high_bit_depth: false,
         }
+    }
 
-        let img = GrayImage::from_raw(width as u32, height as u32, output.stdout[..width * height].to_vec())
-            .ok_or_else(|| anyhow::anyhow!("Failed to create image from raw data"))?;
+    /// Extract a single frame as grayscale image.
+    pub(crate) fn extract_frame(&self, video_path: &Path, timestamp: f64, options: &DecodeOptions) -> Result<GrayImage> {
+        self.backend.extract_frame(video_path, timestamp, options)
+    }
 
-        Ok(img)
+    /// Extract every frame of a stretch of video at `fps`, as grayscale images.
+    pub(crate) fn extract_frames(
+        &self,
+        video_path: &Path,
+        start: f64,
+        duration: f64,
+        fps: f64,
+        options: &DecodeOptions,
+    ) -> Result<Vec<GrayImage>> {
+        self.backend.extract_frames(video_path, start, duration, fps, options)
+    }
+
+    /// Whether `timestamp` falls in an excluded range or near an excluded timestamp.
+    fn is_excluded(&self, timestamp: f64) -> bool {
+        self.config.exclude_ranges.iter().any(|&(start, end)| (start..=end).contains(&timestamp))
+            || self.config.exclude_timestamps.iter().any(|&t| (t - timestamp).abs() < EXCLUDE_TIMESTAMP_SECS)
     }
 
     /// Analyze frame quality using 2D FFT.
@@ -320,6 +400,7 @@ impl ThumbnailSelector {
         ImageQuality {
             sharpness,
             contrast: contrast_normalized,
+            text_likeness: text_likeness(&pixels, width as usize, height as usize),
         }
     }
 
@@ -400,22 +481,53 @@ impl ThumbnailSelector {
         }
     }
 
+    /// Weighted combination of a candidate's scores.
+    fn total_score(&self, quality: &ImageQuality, audio_energy: f32, speech_activity: f32) -> f32 {
+        quality.sharpness * self.config.sharpness_weight
+            + quality.contrast * self.config.contrast_weight
+            - quality.text_likeness * self.config.text_weight
+            + audio_energy * self.config.audio_weight
+            + speech_activity * self.config.speech_weight
+    }
+
+    /// Fraction of the audio window around each candidate timestamp with speech.
+    ///
+    /// All zero when `speech_weight` is zero, without running detection.
+    fn compute_speech_activity(&self, audio: &AudioData, timestamps: &[f64]) -> Vec<f32> {
+        if self.config.speech_weight == 0.0 {
+            return vec![0.0; timestamps.len()];
+        }
+
+        let segments = vad::detect_speech(audio, &self.config.vad);
+        timestamps.iter()
+            .map(|&t| {
+                let (start, end) = (t - AUDIO_WINDOW_SECS / 2.0, t + AUDIO_WINDOW_SECS / 2.0);
+                let speech: f64 = segments.iter()
+                    .map(|s| (s.end.min(end) - s.start.max(start)).max(0.0))
+                    .sum();
+                (speech / AUDIO_WINDOW_SECS) as f32
+            })
+            .collect()
+    }
+
     /// Compute audio energy at each candidate timestamp.
     fn compute_audio_energies(&self, audio: &AudioData, timestamps: &[f64]) -> Vec<f32> {
-        let window_secs = 0.5; // Look at 0.5 second window around each timestamp
-        let window_samples = (audio.sample_rate as f64 * window_secs) as usize;
+        // NaN samples count as silence; audio only breaks ties between frames
+        let audio = audio.to_mono();
+        let samples = replace_non_finite(&audio.samples);
+        let window_samples = (audio.sample_rate as f64 * AUDIO_WINDOW_SECS) as usize;
 
         let mut energies: Vec<f32> = timestamps.iter()
             .map(|&t| {
                 let center_sample = (t * audio.sample_rate as f64) as usize;
                 let start = center_sample.saturating_sub(window_samples / 2);
-                let end = (start + window_samples).min(audio.samples.len());
+                let end = (start + window_samples).min(samples.len());
 
-                if start >= audio.samples.len() {
+                if start >= end {
                     return 0.0;
                 }
 
-                let energy: f32 = audio.samples[start..end]
+                let energy: f32 = samples[start..end]
                     .iter()
                     .map(|&s| s * s)
                     .sum::<f32>() / (end - start) as f32;
@@ -442,6 +554,69 @@ impl Default for ThumbnailSelector {
     }
 }
 
+/// How much a grayscale frame (pixels 0-1) looks like rendered text (0-1).
+///
+/// Text is dense with short vertical strokes, so its rows are full of
+/// horizontal edges, and it is laid out in lines: bands of such rows,
+/// short compared to the frame, separated by quieter rows. The score grows
+/// with the share of rows in such bands and with how evenly the bands are
+/// spaced; texture covering the whole frame forms one tall band and scores 0.
+fn text_likeness(pixels: &[f32], width: usize, height: usize) -> f32 {
+    if width < 2 || height == 0 {
+        return 0.0;
+    }
+
+    let text_rows: Vec<bool> = pixels.chunks_exact(width)
+        .map(|row| {
+            let edges = row.windows(2).filter(|p| (p[1] - p[0]).abs() > TEXT_EDGE_THRESHOLD).count();
+            edges as f32 / (width - 1) as f32 >= TEXT_ROW_DENSITY
+        })
+        .collect();
+
+    // Runs of text rows, as (first row, height)
+    let mut bands: Vec<(usize, usize)> = Vec::new();
+    let mut y = 0;
+    while y < height {
+        let start = y;
+        while y < height && text_rows[y] {
+            y += 1;
+        }
+        if y > start {
+            bands.push((start, y - start));
+        }
+        y += 1;
+    }
+    let max_line = ((height as f32 * TEXT_MAX_LINE_HEIGHT) as usize).max(1);
+    let lines: Vec<(usize, usize)> = bands.into_iter().filter(|&(_, len)| len <= max_line).collect();
+    if lines.is_empty() {
+        return 0.0;
+    }
+
+    // A lone line (a lower third) counts half; several count by how evenly they repeat
+    let regularity = if lines.len() < 2 {
+        0.5
+    } else {
+        let pitches: Vec<f32> = lines.windows(2).map(|w| (w[1].0 - w[0].0) as f32).collect();
+        let mean = pitches.iter().sum::<f32>() / pitches.len() as f32;
+        let std = (pitches.iter().map(|p| (p - mean).powi(2)).sum::<f32>() / pitches.len() as f32).sqrt();
+        (1.0 - std / mean).clamp(0.0, 1.0)
+    };
+    let coverage = lines.iter().map(|&(_, len)| len).sum::<usize>() as f32 / height as f32;
+    (coverage * 4.0).min(1.0) * regularity
+}
+
+/// Mean absolute pixel difference (0-1); frames of different sizes differ completely.
+pub(crate) fn frame_difference(a: &GrayImage, b: &GrayImage) -> f32 {
+    if a.dimensions() != b.dimensions() || a.as_raw().is_empty() {
+        return 1.0;
+    }
+    let total: f32 = a.as_raw().iter()
+        .zip(b.as_raw())
+        .map(|(&p, &q)| (p as f32 - q as f32).abs())
+        .sum();
+    total / a.as_raw().len() as f32 / 255.0
+}
+
 /// Image quality metrics.
 #[derive(Debug, Clone)]
 struct ImageQuality {
@@ -449,10 +624,12 @@ struct ImageQuality {
     sharpness: f32,
     /// Contrast score (0-1)
     contrast: f32,
+    /// Text likeness (0-1)
+    text_likeness: f32,
 }
 
 /// Thumbnail candidate with quality scores.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThumbnailCandidate {
     /// Timestamp in seconds
     pub timestamp: f64,
@@ -460,15 +637,25 @@ pub struct ThumbnailCandidate {
     pub sharpness: f32,
     /// Contrast score (0-1)
     pub contrast: f32,
+    /// How much the frame looks like credits or on-screen text (0-1),
+    /// penalized by `text_weight`
+    #[serde(default)]
+    pub text_likeness: f32,
     /// Audio energy at this moment (0-1)
     pub audio_energy: f32,
+    /// Fraction of the surrounding half second with speech (0-1, 0 unless `speech_weight` is set)
+    pub speech_activity: f32,
     /// Combined quality score
     pub total_score: f32,
+    /// Color metadata of the source video (`color.is_hdr()` for PQ/HLG sources)
+    pub color: ColorInfo,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{self, MockBackend};
+    use crate::media::{HdrTransfer, ANALYSIS_FRAME_HEIGHT, ANALYSIS_FRAME_WIDTH};
 
     #[test]
     fn test_default_config() {
@@ -502,6 +689,35 @@ mod tests {
         assert!(quality.contrast > 0.3);
     }
 
+    /// A smooth left-to-right gradient, sharp-free but full of contrast.
+    fn gradient_frame() -> GrayImage {
+        GrayImage::from_fn(ANALYSIS_FRAME_WIDTH, ANALYSIS_FRAME_HEIGHT, |x, _| {
+            image::Luma([(x * 255 / (ANALYSIS_FRAME_WIDTH - 1)) as u8])
+        })
+    }
+
+    #[test]
+    fn test_text_likeness() {
+        let selector = ThumbnailSelector::new();
+        let credits = selector.analyze_frame_quality(&testing::text_frame(6));
+        let lower_third = selector.analyze_frame_quality(&testing::text_frame(1));
+        let gradient = selector.analyze_frame_quality(&gradient_frame());
+        let stripes = selector.analyze_frame_quality(&testing::stripes_frame(2));
+
+        assert!(credits.text_likeness > 0.8, "{:?}", credits);
+        assert!(lower_third.text_likeness > 0.0 && lower_third.text_likeness < credits.text_likeness, "{:?}", lower_third);
+        // Texture over the whole frame is not text
+        assert_eq!(gradient.text_likeness, 0.0);
+        assert_eq!(stripes.text_likeness, 0.0);
+
+        // Text scores as sharp as a picture; the penalty ranks it below
+        let unpenalized = ThumbnailSelector::with_config(ThumbnailConfig { text_weight: 0.0, ..Default::default() });
+        let penalty = unpenalized.total_score(&credits, 0.5, 0.0) - selector.total_score(&credits, 0.5, 0.0);
+        assert!((penalty - 0.3 * credits.text_likeness).abs() < 1e-5, "{}", penalty);
+        assert!(selector.total_score(&credits, 0.5, 0.0) < selector.total_score(&gradient, 0.5, 0.0));
+        assert!(selector.total_score(&lower_third, 0.5, 0.0) > selector.total_score(&credits, 0.5, 0.0));
+    }
+
     #[test]
     fn test_audio_energy_computation() {
         let sample_rate = 44100;
@@ -532,4 +748,175 @@ mod tests {
 
         assert!(max_idx >= 3 && max_idx <= 6);
     }
+
+    #[test]
+    fn test_speech_activity() {
+        // Silence, then speech from 2s to 4s
+        let mut samples = vec![0.0; 2 * 16000];
+        samples.extend(crate::testing::speech_like(16000, 2.0).samples);
+        samples.extend(vec![0.0; 2 * 16000]);
+        let audio = AudioData::new(samples, 16000);
+        let timestamps = [1.0, 2.5, 5.0];
+
+        // Off by default
+        let selector = ThumbnailSelector::new();
+        assert_eq!(selector.compute_speech_activity(&audio, &timestamps), [0.0; 3]);
+
+        let selector = ThumbnailSelector::with_config(ThumbnailConfig {
+            speech_weight: 0.2,
+            ..Default::default()
+        });
+        let activity = selector.compute_speech_activity(&audio, &timestamps);
+        assert_eq!(activity[0], 0.0);
+        assert!(activity[1] > 0.9, "{:?}", activity);
+        assert_eq!(activity[2], 0.0);
+    }
+
+    /// Flat frames except for high-contrast stripes inside `windows`.
+    fn striped_video(duration: f64, windows: &'static [(f64, f64)]) -> Arc<MockBackend> {
+        Arc::new(MockBackend::new(duration).with_frames(move |t| {
+            let striped = windows.iter().any(|&(start, end)| (start..end).contains(&t));
+            Some(if striped { testing::stripes_frame(2) } else { testing::flat_frame(128) })
+        }))
+    }
+
+    #[test]
+    fn test_best_timestamp_with_mock_backend() {
+        let backend = striped_video(20.0, &[(6.0, 8.0)]);
+        let selector = ThumbnailSelector::new().with_backend(backend.clone());
+        let best = selector.find_best_timestamp("video.mp4", &testing::noise(8000, 20.0)).unwrap();
+
+        assert!((6.0..8.0).contains(&best), "{}", best);
+        let requests = backend.frame_requests();
+        assert_eq!(requests.len(), 30);
+        assert!(requests.iter().all(|&t| (2.0..18.0).contains(&t)));
+    }
+
+    #[test]
+    fn test_best_timestamp_falls_back_when_frames_fail() {
+        let backend = Arc::new(MockBackend::new(20.0).with_frames(|_| None));
+        let selector = ThumbnailSelector::new().with_backend(backend);
+        let best = selector.find_best_timestamp("video.mp4", &testing::noise(8000, 20.0)).unwrap();
+        assert_eq!(best, 10.0);
+    }
+
+    #[test]
+    fn test_candidates_with_mock_backend() {
+        let backend = striped_video(30.0, &[(5.0, 7.0), (20.0, 22.0)]);
+        let selector = ThumbnailSelector::new().with_backend(backend);
+        let candidates = selector.find_candidates("video.mp4", &testing::noise(8000, 30.0), 2).unwrap();
+
+        let mut timestamps: Vec<f64> = candidates.iter().map(|c| c.timestamp).collect();
+        timestamps.sort_by(f64::total_cmp);
+        assert_eq!(timestamps.len(), 2);
+        assert!((5.0..7.0).contains(&timestamps[0]), "{:?}", timestamps);
+        assert!((20.0..22.0).contains(&timestamps[1]), "{:?}", timestamps);
+        assert!(candidates.iter().all(|c| c.contrast == 1.0));
+    }
+
+    #[test]
+    fn test_candidates_avoid_text_and_exclusions() {
+        // Credits from 20s, plain pictures before
+        let backend = Arc::new(MockBackend::new(30.0).with_frames(|t| {
+            Some(if t >= 20.0 { testing::text_frame(6) } else { gradient_frame() })
+        }));
+        let selector = ThumbnailSelector::new().with_backend(backend);
+        let candidates = selector.find_candidates("video.mp4", &testing::noise(8000, 30.0), 3).unwrap();
+        assert!(candidates[0].timestamp < 20.0, "{:?}", candidates);
+        assert!(candidates.iter().all(|c| (c.text_likeness > 0.8) == (c.timestamp >= 20.0)), "{:?}", candidates);
+
+        let backend = striped_video(30.0, &[(5.0, 7.0), (20.0, 22.0)]);
+        let selector = ThumbnailSelector::with_config(ThumbnailConfig {
+            exclude_ranges: vec![(19.0, 23.0)],
+            exclude_timestamps: vec![5.0],
+            ..Default::default()
+        })
+        .with_backend(backend.clone());
+        let candidates = selector.find_candidates("video.mp4", &testing::noise(8000, 30.0), 2).unwrap();
+        assert!(!candidates.is_empty());
+        assert!(candidates.iter().all(|c| !(19.0..=23.0).contains(&c.timestamp) && (c.timestamp - 5.0).abs() >= 1.0));
+
+        // Excluded regions are never decoded
+        let best = selector.find_best_timestamp("video.mp4", &testing::noise(8000, 30.0)).unwrap();
+        assert!((5.0..7.0).contains(&best), "{}", best);
+        assert!(backend.frame_requests().iter().all(|&t| !(19.0..=23.0).contains(&t) && (t - 5.0).abs() >= 1.0));
+    }
+
+    #[test]
+    fn test_extract_thumbnail_with_mock_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("thumb.png");
+        let selector = ThumbnailSelector::with_config(ThumbnailConfig {
+            output_width: 64,
+            output_height: 36,
+            ..Default::default()
+        })
+        .with_backend(striped_video(10.0, &[(0.0, 10.0)]));
+
+        selector.extract_thumbnail("video.mp4", 4.0, &output).unwrap();
+        assert_eq!(image::open(&output).unwrap().to_luma8().dimensions(), (64, 36));
+        assert!(selector.extract_thumbnail("video.mp4", 12.0, &output).is_err());
+    }
+
+    fn pq_color() -> ColorInfo {
+        ColorInfo {
+            transfer: Some("smpte2084".to_string()),
+            primaries: Some("bt2020".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hdr_frames_are_tone_mapped() {
+        let backend = Arc::new(MockBackend::new(30.0).with_color(pq_color()));
+        let selector = ThumbnailSelector::with_config(ThumbnailConfig {
+            hwaccel: Some("auto".to_string()),
+            ..Default::default()
+        })
+        .with_backend(backend.clone());
+        let candidates = selector.find_candidates("video.mp4", &testing::noise(8000, 30.0), 2).unwrap();
+
+        assert!(candidates.iter().all(|c| c.color.hdr() == Some(HdrTransfer::Pq)));
+        assert!(backend.decode_requests().iter().all(|o| {
+            o.tonemap == Some(HdrTransfer::Pq) && o.hwaccel.as_deref() == Some("auto") && !o.high_bit_depth
+        }));
+
+        // SDR sources and disabled tone mapping decode as-is
+        let sdr = Arc::new(MockBackend::new(30.0));
+        let selector = ThumbnailSelector::new().with_backend(sdr.clone());
+        let candidates = selector.find_candidates("video.mp4", &testing::noise(8000, 30.0), 2).unwrap();
+        assert!(candidates.iter().all(|c| !c.color.is_hdr()));
+        assert!(sdr.decode_requests().iter().all(|o| *o == DecodeOptions::default()));
+
+        let hdr = Arc::new(MockBackend::new(30.0).with_color(pq_color()));
+        let selector = ThumbnailSelector::with_config(ThumbnailConfig { tonemap_hdr: false, ..Default::default() })
+            .with_backend(hdr.clone());
+        selector.find_best_timestamp("video.mp4", &testing::noise(8000, 30.0)).unwrap();
+        assert!(hdr.decode_requests().iter().all(|o| o.tonemap.is_none()));
+    }
+
+    #[test]
+    fn test_extract_thumbnail_hdr_variants() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::new(10.0).with_color(pq_color()));
+        let selector = ThumbnailSelector::with_config(ThumbnailConfig {
+            output_width: 64,
+            output_height: 36,
+            hdr_variants: true,
+            ..Default::default()
+        })
+        .with_backend(backend.clone());
+
+        selector.extract_thumbnail("video.mp4", 4.0, dir.path().join("poster")).unwrap();
+        let sdr = image::open(dir.path().join("poster.jpg")).unwrap();
+        let original = image::open(dir.path().join("poster.png")).unwrap();
+        assert_eq!(sdr.color(), image::ColorType::L8);
+        assert_eq!(original.color(), image::ColorType::L16);
+        assert_eq!(original.width(), 64);
+
+        let requests = backend.decode_requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!((requests[0].tonemap, requests[0].high_bit_depth), (Some(HdrTransfer::Pq), false));
+        assert_eq!((requests[1].tonemap, requests[1].high_bit_depth), (None, true));
+    }
 }
@@ -1,7 +1,13 @@
 //! Core types for frequency analysis.
 
+use std::borrow::Cow;
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
+use crate::cancel::StageTimeouts;
+use crate::media::AudioExtractOptions;
+
 /// Raw audio data extracted from a video file.
 #[derive(Debug, Clone)]
 pub struct AudioData {
@@ -16,21 +22,51 @@ pub struct AudioData {
 }
 
 impl AudioData {
-    /// Create new audio data from samples.
+    /// Create new mono audio data from samples.
     pub fn new(samples: Vec<f32>, sample_rate: u32) -> Self {
-        let duration_secs = samples.len() as f64 / sample_rate as f64;
+        Self::with_channels(samples, sample_rate, 1)
+    }
+
+    /// Create audio data from interleaved samples with the given channel count.
+    pub fn with_channels(samples: Vec<f32>, sample_rate: u32, channels: u32) -> Self {
+        let channels = channels.max(1);
+        let duration_secs = if sample_rate > 0 {
+            samples.len() as f64 / sample_rate as f64 / channels as f64
+        } else {
+            0.0
+        };
         Self {
             samples,
             sample_rate,
-            channels: 1,
+            channels,
             duration_secs,
         }
     }
 
+    /// Downmix interleaved multichannel audio to mono by averaging channels.
+    ///
+    /// Returns a borrowed view when the audio is already mono.
+    pub fn to_mono(&self) -> Cow<'_, AudioData> {
+        if self.channels <= 1 {
+            return Cow::Borrowed(self);
+        }
+
+        let channels = self.channels as usize;
+        let samples: Vec<f32> = self.samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+
+        Cow::Owned(AudioData::new(samples, self.sample_rate))
+    }
+
     /// Get a slice of samples for a specific time range.
+    ///
+    /// For multichannel audio the returned slice contains whole interleaved frames.
     pub fn slice(&self, start_secs: f64, end_secs: f64) -> &[f32] {
-        let start_idx = (start_secs * self.sample_rate as f64) as usize;
-        let end_idx = (end_secs * self.sample_rate as f64) as usize;
+        let channels = self.channels.max(1) as usize;
+        let start_idx = (start_secs * self.sample_rate as f64) as usize * channels;
+        let end_idx = (end_secs * self.sample_rate as f64) as usize * channels;
         &self.samples[start_idx.min(self.samples.len())..end_idx.min(self.samples.len())]
     }
 
@@ -43,8 +79,178 @@ impl AudioData {
     pub fn is_empty(&self) -> bool {
         self.samples.is_empty()
     }
+
+    /// Count NaN and infinite samples and measure the finite sample range.
+    pub fn validate(&self) -> SampleReport {
+        let mut report = SampleReport {
+            samples: self.samples.len(),
+            nan: 0,
+            infinite: 0,
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+        };
+
+        for &s in &self.samples {
+            if s.is_nan() {
+                report.nan += 1;
+            } else if s.is_infinite() {
+                report.infinite += 1;
+            } else {
+                report.min = report.min.min(s);
+                report.max = report.max.max(s);
+            }
+        }
+
+        report
+    }
+
+    /// Apply `policy` to NaN and infinite samples.
+    ///
+    /// Returns a borrowed view when every sample is finite.
+    pub fn sanitize(&self, policy: SamplePolicy) -> Result<Cow<'_, AudioData>, FrequencyError> {
+        let report = self.validate();
+        if report.is_finite() {
+            return Ok(Cow::Borrowed(self));
+        }
+
+        match policy {
+            SamplePolicy::Reject => Err(FrequencyError::InvalidSamples {
+                nan: report.nan,
+                infinite: report.infinite,
+            }),
+            SamplePolicy::Replace => {
+                let samples = replace_non_finite(&self.samples).into_owned();
+                Ok(Cow::Owned(AudioData { samples, ..*self }))
+            }
+        }
+    }
+
+    /// Sanitize audio for an analysis entry point.
+    ///
+    /// Applies `policy`, then fails with [`FrequencyError::TooShort`] below
+    /// `min_samples` per channel and [`FrequencyError::ZeroSignal`] for
+    /// silent or DC-only audio.
+    pub fn prepare(&self, policy: SamplePolicy, min_samples: usize) -> Result<Cow<'_, AudioData>, FrequencyError> {
+        let channels = self.channels.max(1) as usize;
+        let frames = self.samples.len() / channels;
+        if frames < min_samples.max(1) || self.sample_rate == 0 {
+            return Err(FrequencyError::TooShort { samples: frames, required: min_samples.max(1) });
+        }
+
+        let audio = self.sanitize(policy)?;
+        if !audio.validate().has_signal() {
+            return Err(FrequencyError::ZeroSignal);
+        }
+
+        Ok(audio)
+    }
+}
+
+/// Replace NaN samples with silence and clamp infinities to full scale.
+pub(crate) fn replace_non_finite(samples: &[f32]) -> Cow<'_, [f32]> {
+    if samples.iter().all(|s| s.is_finite()) {
+        return Cow::Borrowed(samples);
+    }
+
+    samples.iter()
+        .map(|&s| if s.is_nan() { 0.0 } else if s.is_infinite() { s.signum() } else { s })
+        .collect()
+}
+
+/// Sample statistics from [`AudioData::validate`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleReport {
+    /// Total number of samples
+    pub samples: usize,
+    /// Number of NaN samples
+    pub nan: usize,
+    /// Number of positive or negative infinite samples
+    pub infinite: usize,
+    /// Smallest finite sample (infinity if there is none)
+    pub min: f32,
+    /// Largest finite sample (negative infinity if there is none)
+    pub max: f32,
+}
+
+impl SampleReport {
+    /// Whether every sample is finite.
+    pub fn is_finite(&self) -> bool {
+        self.nan == 0 && self.infinite == 0
+    }
+
+    /// Whether the finite samples vary, i.e. the audio is neither silent
+    /// nor DC only.
+    pub fn has_signal(&self) -> bool {
+        self.max > self.min
+    }
+}
+
+/// How entry points treat NaN and infinite samples.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplePolicy {
+    /// Replace NaN with silence and clamp infinities to full scale
+    #[default]
+    Replace,
+    /// Fail with [`FrequencyError::InvalidSamples`]
+    Reject,
+}
+
+/// Errors from analysis, fingerprinting and tagging entry points.
+///
+/// Failures other than unusable input, such as I/O, FFmpeg or model
+/// inference, are carried as [`FrequencyError::Other`].
+#[derive(Debug, thiserror::Error)]
+pub enum FrequencyError {
+    /// The audio has fewer samples per channel than the analysis needs.
+    #[error("Audio is too short: {samples} samples, need at least {required}")]
+    TooShort {
+        /// Samples per channel in the input
+        samples: usize,
+        /// Samples per channel the analysis needs
+        required: usize,
+    },
+
+    /// The audio contains NaN or infinite samples and the
+    /// [`SamplePolicy`] rejects them.
+    #[error("Audio contains {nan} NaN and {infinite} infinite samples")]
+    InvalidSamples {
+        /// Number of NaN samples
+        nan: usize,
+        /// Number of infinite samples
+        infinite: usize,
+    },
+
+    /// Every sample is zero, or the same constant (DC only).
+    #[error("Audio has no signal (silent or DC only)")]
+    ZeroSignal,
+
+    /// The caller's [`CancellationToken`](tokio_util::sync::CancellationToken)
+    /// fired, or a stage ran past its timeout.
+    #[error("Analysis was cancelled")]
+    Cancelled,
+
+    /// Any other failure.
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl FrequencyError {
+    /// Short snake_case name of the variant, used as a metrics label.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::TooShort { .. } => "too_short",
+            Self::InvalidSamples { .. } => "invalid_samples",
+            Self::ZeroSignal => "zero_signal",
+            Self::Cancelled => "cancelled",
+            Self::Other(_) => "other",
+        }
+    }
 }
 
+/// Result of an analysis, fingerprinting or tagging entry point.
+pub type FrequencyResult<T> = Result<T, FrequencyError>;
+
 /// A dominant frequency detected in the audio.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DominantFrequency {
@@ -57,7 +263,7 @@ pub struct DominantFrequency {
 }
 
 /// Complete frequency analysis results.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrequencyAnalysis {
     /// Full magnitude spectrum
     pub spectrum: Vec<f32>,
@@ -69,85 +275,277 @@ pub struct FrequencyAnalysis {
     pub spectral_rolloff: f32,
     /// Spectral flatness (tonality measure)
     pub spectral_flatness: f32,
+    /// Spectral bandwidth (spread around the centroid, Hz)
+    pub spectral_bandwidth: f32,
+    /// Spectral contrast per octave band (peak-to-valley, dB)
+    pub spectral_contrast: Vec<f32>,
     /// Band energies (sub-bass, bass, low-mid, mid, high-mid, high)
     pub band_energies: BandEnergies,
     /// Zero crossing rate
     pub zero_crossing_rate: f32,
+    /// Crest factor (peak-to-RMS ratio of the waveform)
+    pub crest_factor: f32,
+}
+
+/// Named set of frequency bands used to compute [`BandEnergies`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BandPlan {
+    /// Plan name, e.g. "standard" or "telephone"
+    pub name: String,
+    /// Ordered (low, high) ranges in Hz; low is inclusive, high exclusive
+    pub bands: Vec<(f32, f32)>,
+}
+
+impl BandPlan {
+    /// Name of the built-in six-band plan.
+    pub const STANDARD: &'static str = "standard";
+
+    /// Create a plan, checking that every band is a non-empty range.
+    pub fn new(name: impl Into<String>, bands: Vec<(f32, f32)>) -> anyhow::Result<Self> {
+        if bands.is_empty() {
+            anyhow::bail!("A band plan needs at least one band");
+        }
+        if let Some((low, high)) = kino_spectrum::invalid_band(&bands) {
+            anyhow::bail!("Invalid band {}-{} Hz", low, high);
+        }
+
+        Ok(Self { name: name.into(), bands })
+    }
+
+    /// Sub-bass, bass, low-mid, mid, high-mid and high, covering 20 Hz-20 kHz.
+    pub fn standard() -> Self {
+        Self {
+            name: Self::STANDARD.to_string(),
+            bands: kino_spectrum::STANDARD_BANDS.to_vec(),
+        }
+    }
+
+    /// Speech-centric plan that splits the 300-3400 Hz telephone band finely.
+    pub fn telephone() -> Self {
+        Self {
+            name: "telephone".to_string(),
+            bands: vec![
+                (20.0, 300.0),
+                (300.0, 800.0),
+                (800.0, 1500.0),
+                (1500.0, 2500.0),
+                (2500.0, 3400.0),
+                (3400.0, 20000.0),
+            ],
+        }
+    }
+
+    /// Number of bands.
+    pub fn len(&self) -> usize {
+        self.bands.len()
+    }
+
+    /// True if the plan has no bands.
+    pub fn is_empty(&self) -> bool {
+        self.bands.is_empty()
+    }
+
+    /// True if this is the built-in six-band plan.
+    pub fn is_standard(&self) -> bool {
+        *self == Self::standard()
+    }
+}
+
+impl Default for BandPlan {
+    fn default() -> Self {
+        Self::standard()
+    }
 }
 
 /// Energy distribution across frequency bands.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+///
+/// Energies are normalized to sum to 1 and ordered like the bands of their
+/// [`BandPlan`]. Energies for the standard plan serialize with the named
+/// `sub_bass`..`high` fields, so older stored signatures keep loading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "BandEnergiesRepr", into = "BandEnergiesRepr")]
 pub struct BandEnergies {
-    /// Sub-bass: 20-60 Hz
-    pub sub_bass: f32,
-    /// Bass: 60-250 Hz
-    pub bass: f32,
-    /// Low-mid: 250-500 Hz
-    pub low_mid: f32,
-    /// Mid: 500-2000 Hz
-    pub mid: f32,
-    /// High-mid: 2000-4000 Hz
-    pub high_mid: f32,
-    /// High: 4000-20000 Hz
-    pub high: f32,
+    /// Bands the energies were computed over
+    pub plan: BandPlan,
+    /// Normalized energy per band
+    pub energies: Vec<f32>,
 }
 
 impl BandEnergies {
-    /// Create band energies from a spectrum and frequency bins.
+    /// Create band energies for a plan.
+    pub fn new(plan: BandPlan, energies: Vec<f32>) -> Self {
+        Self { plan, energies }
+    }
+
+    /// Create energies for the standard plan from its six bands.
+    pub fn standard(energies: [f32; 6]) -> Self {
+        Self::new(BandPlan::standard(), energies.to_vec())
+    }
+
+    /// Create band energies from a spectrum and frequency bins, using the standard plan.
     pub fn from_spectrum(spectrum: &[f32], frequencies: &[f32]) -> Self {
-        let bands = [
-            (20.0, 60.0),     // sub_bass
-            (60.0, 250.0),    // bass
-            (250.0, 500.0),   // low_mid
-            (500.0, 2000.0),  // mid
-            (2000.0, 4000.0), // high_mid
-            (4000.0, 20000.0), // high
-        ];
-
-        let mut energies = [0.0f32; 6];
-
-        for (i, (low, high)) in bands.iter().enumerate() {
-            for (j, &freq) in frequencies.iter().enumerate() {
-                if freq >= *low && freq < *high {
-                    energies[i] += spectrum[j];
-                }
-            }
-        }
+        Self::from_spectrum_with_plan(spectrum, frequencies, &BandPlan::standard())
+    }
+
+    /// Create band energies from a spectrum and frequency bins.
+    pub fn from_spectrum_with_plan(spectrum: &[f32], frequencies: &[f32], plan: &BandPlan) -> Self {
+        Self::new(plan.clone(), kino_spectrum::band_energies(spectrum, frequencies, &plan.bands))
+    }
+
+    /// Average energies that share the first item's plan; others are skipped.
+    pub fn average<'a>(items: impl IntoIterator<Item = &'a BandEnergies>) -> Self {
+        Self::weighted_average(items.into_iter().map(|b| (b, 1.0)))
+    }
+
+    /// Weighted average of energies that share the first item's plan; others are skipped.
+    pub fn weighted_average<'a>(items: impl IntoIterator<Item = (&'a BandEnergies, f32)>) -> Self {
+        let mut items = items.into_iter();
+        let Some((first, first_weight)) = items.next() else {
+            return Self::default();
+        };
 
-        // Normalize
-        let total: f32 = energies.iter().sum();
-        if total > 0.0 {
-            for e in &mut energies {
-                *e /= total;
+        let mut sums: Vec<f32> = first.energies.iter().map(|e| e * first_weight).collect();
+        let mut total = first_weight;
+        for (item, weight) in items.filter(|(b, _)| b.plan == first.plan) {
+            for (sum, e) in sums.iter_mut().zip(&item.energies) {
+                *sum += e * weight;
             }
+            total += weight;
         }
 
-        Self {
-            sub_bass: energies[0],
-            bass: energies[1],
-            low_mid: energies[2],
-            mid: energies[3],
-            high_mid: energies[4],
-            high: energies[5],
+        if total <= 0.0 {
+            return Self::new(first.plan.clone(), vec![0.0; sums.len()]);
         }
+        Self::new(first.plan.clone(), sums.into_iter().map(|s| s / total).collect())
     }
 
     /// Convert to a vector for ML features.
     pub fn to_vec(&self) -> Vec<f32> {
-        vec![
-            self.sub_bass,
-            self.bass,
-            self.low_mid,
-            self.mid,
-            self.high_mid,
-            self.high,
-        ]
+        self.energies.clone()
+    }
+
+    /// Energy of a standard-plan band, or 0.0 for other plans.
+    fn standard_band(&self, idx: usize) -> f32 {
+        if self.plan.is_standard() {
+            self.energies.get(idx).copied().unwrap_or(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Sub-bass (20-60 Hz) energy; 0.0 unless the standard plan is used.
+    pub fn sub_bass(&self) -> f32 {
+        self.standard_band(0)
+    }
+
+    /// Bass (60-250 Hz) energy; 0.0 unless the standard plan is used.
+    pub fn bass(&self) -> f32 {
+        self.standard_band(1)
+    }
+
+    /// Low-mid (250-500 Hz) energy; 0.0 unless the standard plan is used.
+    pub fn low_mid(&self) -> f32 {
+        self.standard_band(2)
+    }
+
+    /// Mid (500-2000 Hz) energy; 0.0 unless the standard plan is used.
+    pub fn mid(&self) -> f32 {
+        self.standard_band(3)
+    }
+
+    /// High-mid (2000-4000 Hz) energy; 0.0 unless the standard plan is used.
+    pub fn high_mid(&self) -> f32 {
+        self.standard_band(4)
+    }
+
+    /// High (4000-20000 Hz) energy; 0.0 unless the standard plan is used.
+    pub fn high(&self) -> f32 {
+        self.standard_band(5)
+    }
+}
+
+impl Default for BandEnergies {
+    fn default() -> Self {
+        Self::standard([0.0; 6])
+    }
+}
+
+/// Wire format for [`BandEnergies`].
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BandEnergiesRepr {
+    /// Standard plan, in the original named-field layout
+    Standard {
+        sub_bass: f32,
+        bass: f32,
+        low_mid: f32,
+        mid: f32,
+        high_mid: f32,
+        high: f32,
+    },
+    /// Any other plan
+    Planned {
+        plan: BandPlan,
+        energies: Vec<f32>,
+    },
+}
+
+impl From<BandEnergiesRepr> for BandEnergies {
+    fn from(repr: BandEnergiesRepr) -> Self {
+        match repr {
+            BandEnergiesRepr::Standard { sub_bass, bass, low_mid, mid, high_mid, high } => {
+                Self::standard([sub_bass, bass, low_mid, mid, high_mid, high])
+            }
+            BandEnergiesRepr::Planned { plan, energies } => Self::new(plan, energies),
+        }
     }
 }
 
+impl From<BandEnergies> for BandEnergiesRepr {
+    fn from(bands: BandEnergies) -> Self {
+        match bands.energies[..] {
+            [sub_bass, bass, low_mid, mid, high_mid, high] if bands.plan.is_standard() => {
+                BandEnergiesRepr::Standard { sub_bass, bass, low_mid, mid, high_mid, high }
+            }
+            _ => BandEnergiesRepr::Planned { plan: bands.plan, energies: bands.energies },
+        }
+    }
+}
+
+/// Current [`FrequencySignature`] layout version.
+///
+/// Bump this whenever the meaning of the feature vector changes, so
+/// signatures computed by different releases are never compared blindly.
+pub const SIGNATURE_VERSION: u32 = 1;
+
+/// Errors from comparing incompatible [`FrequencySignature`]s.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SignatureError {
+    /// The feature vectors have different lengths.
+    #[error("Signature dimension mismatch: {left} features vs {right} features")]
+    DimensionMismatch {
+        /// Features in the first signature
+        left: usize,
+        /// Features in the second signature
+        right: usize,
+    },
+
+    /// The signatures were computed with different feature layouts.
+    #[error("Signature version mismatch: v{left} vs v{right}")]
+    VersionMismatch {
+        /// Version of the first signature
+        left: u32,
+        /// Version of the second signature
+        right: u32,
+    },
+}
+
 /// Compact frequency signature for similarity matching.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrequencySignature {
+    /// Feature layout version (0 for signatures serialized before versioning)
+    #[serde(default)]
+    pub version: u32,
     /// 128-dimensional feature vector (mel-scale inspired)
     pub features: Vec<f32>,
     /// Band energies
@@ -156,14 +554,52 @@ pub struct FrequencySignature {
     pub centroid: f32,
     /// Spectral flatness
     pub flatness: f32,
+    /// Spectral bandwidth
+    #[serde(default)]
+    pub bandwidth: f32,
+    /// Spectral contrast per octave band (empty for older signatures)
+    #[serde(default)]
+    pub contrast: Vec<f32>,
+    /// Crest factor
+    #[serde(default)]
+    pub crest_factor: f32,
 }
 
 impl FrequencySignature {
+    /// Number of features in the signature.
+    pub fn dimensions(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Check that two signatures can be compared.
+    ///
+    /// Unversioned signatures share the layout of version 1.
+    pub fn check_compatible(&self, other: &FrequencySignature) -> Result<(), SignatureError> {
+        let (left, right) = (self.version.max(1), other.version.max(1));
+        if left != right {
+            return Err(SignatureError::VersionMismatch { left, right });
+        }
+        if self.dimensions() != other.dimensions() {
+            return Err(SignatureError::DimensionMismatch {
+                left: self.dimensions(),
+                right: other.dimensions(),
+            });
+        }
+        Ok(())
+    }
+
     /// Compute cosine similarity with another signature.
+    ///
+    /// Returns 0.0 for incompatible signatures; use
+    /// [`try_similarity`](Self::try_similarity) to tell them apart from
+    /// genuinely dissimilar ones.
     pub fn similarity(&self, other: &FrequencySignature) -> f32 {
-        if self.features.len() != other.features.len() {
-            return 0.0;
-        }
+        self.try_similarity(other).unwrap_or(0.0)
+    }
+
+    /// Compute cosine similarity, failing if the signatures are incompatible.
+    pub fn try_similarity(&self, other: &FrequencySignature) -> Result<f32, SignatureError> {
+        self.check_compatible(other)?;
 
         let dot: f32 = self.features.iter()
             .zip(other.features.iter())
@@ -174,10 +610,137 @@ impl FrequencySignature {
         let norm_b: f32 = other.features.iter().map(|x| x * x).sum::<f32>().sqrt();
 
         if norm_a == 0.0 || norm_b == 0.0 {
-            return 0.0;
+            return Ok(0.0);
+        }
+
+        Ok(dot / (norm_a * norm_b))
+    }
+
+    /// Resample the feature vector to `dimensions` features.
+    ///
+    /// Features are log-spaced bands over the same frequency range, so each
+    /// output feature averages the input features it overlaps, weighted by
+    /// the overlap. The result is renormalized to a peak of 1.0.
+    pub fn rebin(&self, dimensions: usize) -> FrequencySignature {
+        let mut rebinned = self.clone();
+        rebinned.version = SIGNATURE_VERSION;
+        if dimensions == self.dimensions() || self.features.is_empty() || dimensions == 0 {
+            rebinned.features.resize(dimensions, 0.0);
+            return rebinned;
         }
 
-        dot / (norm_a * norm_b)
+        let scale = self.features.len() as f64 / dimensions as f64;
+        let mut features: Vec<f32> = (0..dimensions)
+            .map(|i| {
+                let (lo, hi) = (i as f64 * scale, (i + 1) as f64 * scale);
+                let mut sum = 0.0f64;
+                let mut j = lo.floor() as usize;
+                while (j as f64) < hi && j < self.features.len() {
+                    let overlap = (hi.min((j + 1) as f64) - lo.max(j as f64)).max(0.0);
+                    sum += self.features[j] as f64 * overlap;
+                    j += 1;
+                }
+                (sum / scale) as f32
+            })
+            .collect();
+
+        let max = features.iter().cloned().fold(0.0f32, f32::max);
+        if max > 0.0 {
+            for f in &mut features {
+                *f /= max;
+            }
+        }
+        rebinned.features = features;
+        rebinned
+    }
+}
+
+/// Window applied to each frame before the FFT.
+///
+/// Magnitudes are scaled by each window's coherent gain relative to Hann, so
+/// a tone measures the same regardless of the window and Hann output stays
+/// unchanged. Serializable counterpart of [`kino_spectrum::WindowFunction`],
+/// which holds the coefficients.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowFunction {
+    /// Good general-purpose leakage/resolution trade-off
+    #[default]
+    Hann,
+    /// Narrower main lobe than Hann, higher far sidelobes
+    Hamming,
+    /// 4-term Blackman-Harris; very low sidelobes, less leakage between bands
+    BlackmanHarris,
+    /// Flat passband for accurate amplitudes of tonal content
+    FlatTop,
+    /// No windowing
+    Rectangular,
+}
+
+impl WindowFunction {
+    /// Coherent gain: the window's mean value, i.e. the factor by which it
+    /// attenuates a bin-centered tone.
+    pub fn coherent_gain(&self) -> f32 {
+        kino_spectrum::WindowFunction::from(*self).coherent_gain()
+    }
+
+    /// Magnitude correction that brings this window to Hann's scale.
+    pub fn amplitude_correction(&self) -> f32 {
+        kino_spectrum::WindowFunction::from(*self).amplitude_correction()
+    }
+
+    /// Generate a symmetric window of `size` points.
+    pub fn generate(&self, size: usize) -> Vec<f32> {
+        kino_spectrum::WindowFunction::from(*self).generate(size)
+    }
+
+    /// Parse a window name such as `"hann"` or `"blackman-harris"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        kino_spectrum::WindowFunction::from_name(name).map(Self::from)
+    }
+}
+
+impl From<WindowFunction> for kino_spectrum::WindowFunction {
+    fn from(window: WindowFunction) -> Self {
+        match window {
+            WindowFunction::Hann => Self::Hann,
+            WindowFunction::Hamming => Self::Hamming,
+            WindowFunction::BlackmanHarris => Self::BlackmanHarris,
+            WindowFunction::FlatTop => Self::FlatTop,
+            WindowFunction::Rectangular => Self::Rectangular,
+        }
+    }
+}
+
+impl From<kino_spectrum::WindowFunction> for WindowFunction {
+    fn from(window: kino_spectrum::WindowFunction) -> Self {
+        match window {
+            kino_spectrum::WindowFunction::Hann => Self::Hann,
+            kino_spectrum::WindowFunction::Hamming => Self::Hamming,
+            kino_spectrum::WindowFunction::BlackmanHarris => Self::BlackmanHarris,
+            kino_spectrum::WindowFunction::FlatTop => Self::FlatTop,
+            kino_spectrum::WindowFunction::Rectangular => Self::Rectangular,
+        }
+    }
+}
+
+/// Algorithm used to produce an [`AudioFingerprint`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FingerprintAlgorithm {
+    /// Spectral peak constellation hashing
+    #[default]
+    Constellation,
+    /// Chromaprint-compatible chroma sub-fingerprints, as used by AcoustID
+    Chromaprint,
+}
+
+impl std::fmt::Display for FingerprintAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Constellation => write!(f, "constellation"),
+            Self::Chromaprint => write!(f, "chromaprint"),
+        }
     }
 }
 
@@ -188,10 +751,27 @@ pub struct AudioFingerprint {
     pub hash: String,
     /// Version of fingerprinting algorithm
     pub version: u32,
-    /// Fingerprint constellation points
+    /// Algorithm that produced this fingerprint
+    #[serde(default)]
+    pub algorithm: FingerprintAlgorithm,
+    /// Sample rate the audio was normalized to before fingerprinting
+    /// (0 for version 1 fingerprints, which used the source rate)
+    #[serde(default)]
+    pub sample_rate: u32,
+    /// Fingerprint constellation points (constellation only)
     pub points: Vec<FingerprintPoint>,
+    /// 32-bit sub-fingerprints (Chromaprint only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subfingerprints: Vec<u32>,
+    /// Compressed base64 fingerprint accepted by AcoustID (Chromaprint only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chromaprint: Option<String>,
     /// Duration of analyzed audio in seconds
     pub duration_secs: f64,
+    /// Too few constellation points or hash pairs to match reliably, e.g.
+    /// short or mostly silent audio (constellation only)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub low_entropy: bool,
 }
 
 /// A single point in the fingerprint constellation.
@@ -212,6 +792,104 @@ pub struct ContentTag {
     pub label: String,
     /// Confidence score (0-1)
     pub confidence: f32,
+    /// Canonical taxonomy id, e.g. `"genre.music"`, when the label maps to one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+impl ContentTag {
+    /// Create a tag without a taxonomy id.
+    pub fn new(label: impl Into<String>, confidence: f32) -> Self {
+        Self { label: label.into(), confidence, id: None }
+    }
+}
+
+/// Tags for a time range of the audio.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaggedSegment {
+    /// Segment start in seconds
+    pub start_secs: f64,
+    /// Segment end in seconds
+    pub end_secs: f64,
+    /// Tags for this segment, highest confidence first
+    pub tags: Vec<ContentTag>,
+}
+
+/// Spoken language identified in the speech of a file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageTag {
+    /// Language code from the model's labels, e.g. `"en"`
+    pub language: String,
+    /// Mean classifier confidence over the speech attributed to it (0-1)
+    pub confidence: f32,
+    /// Fraction of the detected speech duration attributed to it (0-1)
+    pub coverage: f32,
+}
+
+impl LanguageTag {
+    /// Content tag for this language, labeled `lang:<code>`.
+    pub fn to_content_tag(&self) -> ContentTag {
+        ContentTag::new(format!("lang:{}", self.language), self.confidence)
+    }
+}
+
+/// Loudness measurement per EBU R128 / ITU-R BS.1770.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoudnessInfo {
+    /// Integrated (programme) loudness in LUFS
+    pub integrated_lufs: f64,
+    /// Loudness range in LU (EBU Tech 3342)
+    pub loudness_range_lu: f64,
+    /// Maximum true peak in dBTP
+    pub true_peak_dbtp: f64,
+}
+
+impl LoudnessInfo {
+    /// Gain in dB that brings the integrated loudness to `target_lufs`.
+    pub fn gain_to(&self, target_lufs: f64) -> f64 {
+        target_lufs - self.integrated_lufs
+    }
+}
+
+/// Major or minor mode of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyMode {
+    /// Major key
+    Major,
+    /// Minor key
+    Minor,
+}
+
+impl std::fmt::Display for KeyMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            KeyMode::Major => "major",
+            KeyMode::Minor => "minor",
+        })
+    }
+}
+
+/// Tempo and key of music.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MusicInfo {
+    /// Tempo in beats per minute
+    pub bpm: f32,
+    /// Strength of the beat at that tempo (0-1)
+    pub bpm_confidence: f32,
+    /// Tonic pitch class, e.g. "A" or "F#"
+    pub key: String,
+    /// Major or minor
+    pub mode: KeyMode,
+    /// Correlation of the pitch content with the key profile (0-1)
+    pub key_confidence: f32,
+}
+
+impl MusicInfo {
+    /// Key with its mode, e.g. "A minor".
+    pub fn key_name(&self) -> String {
+        format!("{} {}", self.key, self.mode)
+    }
 }
 
 /// Configuration for video processing pipeline.
@@ -227,6 +905,20 @@ pub struct ProcessingConfig {
     pub enable_thumbnail: bool,
     /// Enable signature generation
     pub enable_signature: bool,
+    /// Spoken language-ID model (see `MlModelConfig::language_id`); when
+    /// set and tagging is enabled, languages are tagged as `lang:<code>`
+    pub language_model: Option<PathBuf>,
+    /// How NaN and infinite samples from the decoder are handled
+    pub sample_policy: SamplePolicy,
+    /// Per-stage timeouts after which a stage is cancelled
+    pub stage_timeouts: StageTimeouts,
+    /// Content id to use instead of deriving one (see [`ContentIdSource`]);
+    /// every result processed with this config gets it, so set it per input
+    pub content_id: Option<String>,
+    /// Audio track and time range of media files to analyze; thumbnail
+    /// selection scores frames by the audio around them, so it is skipped
+    /// when only a range is analyzed
+    pub audio: AudioExtractOptions,
 }
 
 impl Default for ProcessingConfig {
@@ -237,15 +929,89 @@ impl Default for ProcessingConfig {
             enable_tagging: true,
             enable_thumbnail: true,
             enable_signature: true,
+            language_model: None,
+            sample_policy: SamplePolicy::default(),
+            stage_timeouts: StageTimeouts::default(),
+            content_id: None,
+            audio: AudioExtractOptions::default(),
         }
     }
 }
 
+/// The audio a [`ProcessingResult`] was computed from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzedAudio {
+    /// Stream index of the track; `None` for the default track, or for
+    /// audio that was decoded before processing
+    pub stream_index: Option<usize>,
+    /// Language of the track, when tagged
+    pub language: Option<String>,
+    /// Start of the analyzed range in the media, in seconds
+    pub start_secs: f64,
+    /// Length of the analyzed range in seconds
+    pub duration_secs: f64,
+}
+
+impl AnalyzedAudio {
+    /// The track and range of `audio`, extracted with `selection` as
+    /// resolved by [`AudioAnalyzer::resolve_audio_options`](crate::AudioAnalyzer::resolve_audio_options).
+    pub fn new(selection: &AudioExtractOptions, audio: &AudioData) -> Self {
+        Self {
+            stream_index: selection.stream_index,
+            language: selection.language.clone(),
+            start_secs: selection.start.unwrap_or(0.0),
+            duration_secs: audio.duration_secs,
+        }
+    }
+}
+
+/// How a [`ProcessingResult::content_id`] was derived.
+///
+/// Ids are derived from the content itself, so processing the same input
+/// again yields the same id. Ids with the same source can be compared:
+/// equal fingerprint ids mean the same audio, even across containers or
+/// encodes, while equal file ids mean the same bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentIdSource {
+    /// Supplied by the caller in [`ProcessingConfig::content_id`]
+    Caller,
+    /// The hash of the audio fingerprint
+    Fingerprint,
+    /// SHA-256 of the source file, used when fingerprinting is disabled
+    FileSha256,
+    /// SHA-256 of the decoded samples, for audio processed without a file
+    SampleSha256,
+    /// Not recorded: results from before ids were derived, which carry a
+    /// random UUID, or built by hand
+    #[default]
+    Unspecified,
+}
+
+/// Version of the serialized [`ProcessingResult`] layout.
+///
+/// Bumped whenever a field of the result, or of a type it contains, is
+/// renamed, removed or changes meaning. New optional fields that older
+/// readers can ignore keep the version. The layout of each version is
+/// pinned by the golden files in `tests/fixtures/schema`.
+pub const RESULT_SCHEMA_VERSION: u32 = 1;
+
 /// Result of complete video processing.
+///
+/// Stored results carry a [`schema_version`](Self::schema_version); read
+/// them with [`from_json`](Self::from_json) to reject results written by a
+/// newer release.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessingResult {
-    /// Unique content identifier
+    /// Layout version, [`RESULT_SCHEMA_VERSION`] when produced by this
+    /// release; 0 for results serialized before versioning
+    #[serde(default)]
+    pub schema_version: u32,
+    /// Content identifier, derived as recorded in `content_id_source`
     pub content_id: String,
+    /// How `content_id` was derived, and so which ids it can be compared with
+    #[serde(default)]
+    pub content_id_source: ContentIdSource,
     /// Audio fingerprint (if enabled)
     pub fingerprint: Option<AudioFingerprint>,
     /// Content tags (if enabled)
@@ -256,10 +1022,57 @@ pub struct ProcessingResult {
     pub signature: Option<FrequencySignature>,
     /// Top dominant frequencies
     pub dominant_frequencies: Vec<DominantFrequency>,
+    /// Loudness measurement (absent for audio shorter than 400ms)
+    #[serde(default)]
+    pub loudness: Option<LoudnessInfo>,
+    /// Language with the most speech coverage (needs a language model;
+    /// absent when no speech was detected)
+    #[serde(default)]
+    pub dominant_language: Option<String>,
+    /// Tempo and key (tagging enabled and tagged as music)
+    #[serde(default)]
+    pub music: Option<MusicInfo>,
+    /// Track and time range that was analyzed (absent in results written
+    /// before it was recorded)
+    #[serde(default)]
+    pub audio: Option<AnalyzedAudio>,
+}
+
+impl ProcessingResult {
+    /// Empty result for `content_id` at the current schema version.
+    pub fn new(content_id: impl Into<String>) -> Self {
+        Self {
+            schema_version: RESULT_SCHEMA_VERSION,
+            content_id: content_id.into(),
+            content_id_source: ContentIdSource::Unspecified,
+            fingerprint: None,
+            tags: Vec::new(),
+            thumbnail_timestamp: None,
+            signature: None,
+            dominant_frequencies: Vec::new(),
+            loudness: None,
+            dominant_language: None,
+            music: None,
+            audio: None,
+        }
+    }
+
+    /// Parse a serialized result, failing for schema versions newer than
+    /// [`RESULT_SCHEMA_VERSION`], whose fields may mean something else.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let result: Self = serde_json::from_str(json)?;
+        if result.schema_version > RESULT_SCHEMA_VERSION {
+            anyhow::bail!(
+                "Result schema version {} is newer than the supported version {}",
+                result.schema_version, RESULT_SCHEMA_VERSION
+            );
+        }
+        Ok(result)
+    }
 }
 
 /// Frame quality metrics for thumbnail selection.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrameQuality {
     /// Timestamp in seconds
     pub timestamp: f64,
@@ -282,4 +1095,250 @@ pub struct Recommendation {
     pub similarity: f32,
     /// Matching features that contributed to similarity
     pub matching_features: Vec<String>,
+    /// Metadata of the recommended item, if it was indexed with any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ContentMetadata>,
+    /// Component scores behind `similarity`, when explanations are enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<SimilarityBreakdown>,
+}
+
+/// The component scores a recommendation's similarity is made of.
+///
+/// Components are `None` when they weren't compared: audio components for
+/// items without a signature, optional timbre components that aren't
+/// weighted, and tags unless both items have some. The similarity is the sum
+/// of each component times its weight.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimilarityBreakdown {
+    /// Cosine similarity of the signature feature vectors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<f32>,
+    /// Cosine similarity of the band energies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub band_energy: Option<f32>,
+    /// Similarity of spectral centroid and flatness
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spectral: Option<f32>,
+    /// Spectral bandwidth similarity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<f32>,
+    /// Mean per-octave spectral contrast similarity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contrast: Option<f32>,
+    /// Crest factor similarity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crest: Option<f32>,
+    /// IDF-weighted tag overlap
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<f32>,
+    /// Weights the components were combined with
+    pub weights: SimilarityWeights,
+    /// Signature features that contributed most to the signature match, strongest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub top_features: Vec<FeatureContribution>,
+}
+
+impl SimilarityBreakdown {
+    /// Weighted sum of the compared components.
+    pub fn total(&self) -> f32 {
+        let w = &self.weights;
+        [
+            (self.signature, w.signature),
+            (self.band_energy, w.band_energy),
+            (self.spectral, w.spectral),
+            (self.bandwidth, w.bandwidth),
+            (self.contrast, w.contrast),
+            (self.crest, w.crest),
+            (self.tags, w.tags),
+        ]
+        .iter()
+        .filter_map(|&(score, weight)| Some(score? * weight))
+        .sum()
+    }
+
+    /// Names of the components that matched strongly, as in
+    /// [`Recommendation::matching_features`].
+    pub fn matching_features(&self) -> Vec<String> {
+        let w = &self.weights;
+        [
+            ("frequency_pattern", self.signature, 0.7, 1.0),
+            ("energy_distribution", self.band_energy, 0.8, 1.0),
+            ("tonal_quality", self.spectral, 0.8, 1.0),
+            ("spectral_texture", self.contrast, 0.8, w.contrast),
+            ("dynamics", self.crest, 0.8, w.crest),
+            ("tags", self.tags, 0.0, w.tags),
+        ]
+        .iter()
+        .filter(|&&(_, score, threshold, weight)| score.is_some_and(|s| s > threshold) && weight > 0.0)
+        .map(|&(name, ..)| name.to_string())
+        .collect()
+    }
+}
+
+/// Weight of each component in a [`SimilarityBreakdown`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimilarityWeights {
+    /// Signature feature similarity
+    pub signature: f32,
+    /// Band energy similarity
+    pub band_energy: f32,
+    /// Spectral similarity
+    pub spectral: f32,
+    /// Spectral bandwidth similarity
+    pub bandwidth: f32,
+    /// Spectral contrast similarity
+    pub contrast: f32,
+    /// Crest factor similarity
+    pub crest: f32,
+    /// Tag similarity
+    pub tags: f32,
+}
+
+/// One signature feature's share of the signature similarity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureContribution {
+    /// Index into [`FrequencySignature::features`] (log-spaced bands, lowest first)
+    pub index: usize,
+    /// Product of the two normalized features; all contributions sum to the signature cosine
+    pub contribution: f32,
+}
+
+/// One entry of a user's watch history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchEvent {
+    /// Content ID that was watched
+    pub content_id: String,
+    /// When the item was watched, in Unix seconds
+    pub timestamp: f64,
+    /// Fraction of the item that was watched (0-1)
+    pub completion_ratio: f32,
+}
+
+/// Optional metadata for indexed content items.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentMetadata {
+    /// Content title
+    pub title: Option<String>,
+    /// Creator ID
+    pub creator_id: Option<String>,
+    /// Tags
+    pub tags: Vec<String>,
+    /// Duration in seconds
+    pub duration_secs: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mono_duration() {
+        let audio = AudioData::new(vec![0.0; 44100], 44100);
+        assert_eq!(audio.channels, 1);
+        assert!((audio.duration_secs - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_stereo_duration_and_downmix() {
+        // 2 seconds of interleaved stereo: left = 1.0, right = -0.5
+        let sample_rate = 8000;
+        let samples: Vec<f32> = (0..sample_rate * 2)
+            .flat_map(|_| [1.0f32, -0.5f32])
+            .collect();
+
+        let audio = AudioData::with_channels(samples, sample_rate, 2);
+        assert_eq!(audio.channels, 2);
+        assert!((audio.duration_secs - 2.0).abs() < 1e-9);
+
+        let mono = audio.to_mono();
+        assert_eq!(mono.channels, 1);
+        assert_eq!(mono.len(), (sample_rate * 2) as usize);
+        assert!((mono.duration_secs - audio.duration_secs).abs() < 1e-9);
+        assert!(mono.samples.iter().all(|&s| (s - 0.25).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_to_mono_borrows_mono_input() {
+        let audio = AudioData::new(vec![0.5; 1024], 44100);
+        assert!(matches!(audio.to_mono(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_standard_band_energies_serialize_as_named_fields() {
+        let json = r#"{"sub_bass":0.1,"bass":0.2,"low_mid":0.1,"mid":0.3,"high_mid":0.2,"high":0.1}"#;
+        let bands: BandEnergies = serde_json::from_str(json).unwrap();
+        assert!(bands.plan.is_standard());
+        assert_eq!(bands.mid(), 0.3);
+
+        assert_eq!(serde_json::to_string(&bands).unwrap(), json);
+    }
+
+    #[test]
+    fn test_custom_plan_round_trip() {
+        let plan = BandPlan::new("split", vec![(0.0, 1000.0), (1000.0, 8000.0)]).unwrap();
+        let frequencies = [500.0, 1500.0, 2500.0];
+        let spectrum = [1.0, 2.0, 1.0];
+
+        let bands = BandEnergies::from_spectrum_with_plan(&spectrum, &frequencies, &plan);
+        assert_eq!(bands.energies, vec![0.25, 0.75]);
+        assert_eq!(bands.bass(), 0.0);
+
+        let json = serde_json::to_string(&bands).unwrap();
+        let parsed: BandEnergies = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, bands);
+    }
+
+    #[test]
+    fn test_band_plan_validation() {
+        assert!(BandPlan::new("empty", Vec::new()).is_err());
+        assert!(BandPlan::new("inverted", vec![(500.0, 100.0)]).is_err());
+        assert!(!BandPlan::telephone().is_standard());
+    }
+
+    fn signature(features: Vec<f32>) -> FrequencySignature {
+        FrequencySignature {
+            version: SIGNATURE_VERSION,
+            features,
+            band_energies: BandEnergies::default(),
+            centroid: 0.0,
+            flatness: 0.0,
+            bandwidth: 0.0,
+            contrast: Vec::new(),
+            crest_factor: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_try_similarity_reports_dimensions() {
+        let a = signature(vec![1.0; 128]);
+        let b = signature(vec![1.0; 64]);
+
+        let err = a.try_similarity(&b).unwrap_err();
+        assert_eq!(err, SignatureError::DimensionMismatch { left: 128, right: 64 });
+        assert!(err.to_string().contains("128") && err.to_string().contains("64"));
+        assert_eq!(a.similarity(&b), 0.0);
+
+        // Unversioned signatures are treated as version 1
+        let legacy = FrequencySignature { version: 0, ..a.clone() };
+        assert!((a.try_similarity(&legacy).unwrap() - 1.0).abs() < 1e-6);
+        let future = FrequencySignature { version: SIGNATURE_VERSION + 1, ..a.clone() };
+        assert!(matches!(a.try_similarity(&future), Err(SignatureError::VersionMismatch { .. })));
+    }
+
+    #[test]
+    fn test_rebin_preserves_shape() {
+        let features: Vec<f32> = (0..128).map(|i| if i < 64 { 1.0 } else { 0.25 }).collect();
+        let rebinned = signature(features).rebin(32);
+
+        assert_eq!(rebinned.dimensions(), 32);
+        assert!(rebinned.features[..16].iter().all(|&f| (f - 1.0).abs() < 1e-6));
+        assert!(rebinned.features[16..].iter().all(|&f| (f - 0.25).abs() < 1e-6));
+
+        let upsampled = signature(vec![0.5, 1.0]).rebin(4);
+        assert_eq!(upsampled.features, vec![0.5, 0.5, 1.0, 1.0]);
+    }
 }
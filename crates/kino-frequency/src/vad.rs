@@ -0,0 +1,482 @@
+//! Voice activity detection.
+//!
+//! Audio is cut into short frames, each classified as speech when
+//! - its level stands out from the noise floor, tracked as the quietest
+//!   frame of the last [`NOISE_FLOOR_WINDOW_SECS`], so steady sounds such as
+//!   hum, fans or held tones fall back to non-speech, and
+//! - its spectral entropy over the speech band lies between that of tones
+//!   (energy in a few bins) and of broadband noise.
+//!
+//! A segment starts once speech has lasted `min_speech_secs` and ends after
+//! `hangover_secs` without speech, so pauses between words don't split it.
+//!
+//! [`detect_speech`] returns the [`SpeechSegment`]s of a whole recording;
+//! [`VoiceActivityDetector::push`] does the same incrementally and reports
+//! [`VadEvent`]s, which the streaming analyzer republishes as
+//! [`AnalysisEvent::SpeechStart`](crate::streaming::AnalysisEvent::SpeechStart)
+//! and [`AnalysisEvent::SpeechEnd`](crate::streaming::AnalysisEvent::SpeechEnd).
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use kino_spectrum::Framer;
+use rustfft::{Fft, num_complex::Complex};
+use serde::{Deserialize, Serialize};
+
+use crate::fft::FrequencyAnalyzer;
+use crate::types::*;
+
+/// Span over which the quietest frame sets the noise floor (seconds).
+pub const NOISE_FLOOR_WINDOW_SECS: f64 = 1.5;
+
+/// Lowest noise floor, so digital silence doesn't make every sound stand out infinitely (dBFS).
+const MIN_NOISE_FLOOR_DB: f32 = -90.0;
+
+/// Frequency range whose spectral entropy is measured (Hz).
+const SPEECH_BAND: (f32, f32) = (100.0, 6000.0);
+
+/// How readily frames are rejected as non-speech.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VadAggressiveness {
+    /// Keep anything that might be speech
+    Low,
+    /// Balanced for typical recordings
+    #[default]
+    Moderate,
+    /// Require clearer speech, e.g. over background music
+    High,
+    /// Only report unambiguous speech
+    VeryHigh,
+}
+
+impl VadAggressiveness {
+    /// Level above the noise floor a speech frame needs (dB).
+    fn level_margin_db(self) -> f32 {
+        match self {
+            Self::Low => 6.0,
+            Self::Moderate => 9.0,
+            Self::High => 12.0,
+            Self::VeryHigh => 15.0,
+        }
+    }
+
+    /// Normalized spectral entropy range of speech frames.
+    fn entropy_range(self) -> (f32, f32) {
+        match self {
+            Self::Low => (0.3, 0.95),
+            Self::Moderate => (0.35, 0.92),
+            Self::High => (0.4, 0.9),
+            Self::VeryHigh => (0.45, 0.88),
+        }
+    }
+}
+
+/// Voice activity detection configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VadConfig {
+    /// How readily frames are rejected as non-speech
+    pub aggressiveness: VadAggressiveness,
+    /// Analysis frame length (seconds)
+    pub frame_secs: f64,
+    /// Interval between frames (seconds)
+    pub hop_secs: f64,
+    /// Speech needed before a segment starts (seconds)
+    pub min_speech_secs: f64,
+    /// Non-speech needed before a segment ends (seconds)
+    pub hangover_secs: f64,
+    /// Frames quieter than this never count as speech (dBFS)
+    pub min_level_db: f32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            aggressiveness: VadAggressiveness::default(),
+            frame_secs: 0.032,
+            hop_secs: 0.016,
+            min_speech_secs: 0.15,
+            hangover_secs: 0.3,
+            min_level_db: -50.0,
+        }
+    }
+}
+
+/// A stretch of audio containing speech.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpeechSegment {
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+    /// Mean speech confidence of the segment's speech frames (0-1)
+    pub confidence: f32,
+}
+
+impl SpeechSegment {
+    /// Length of the segment in seconds.
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Speech boundary reported by [`VoiceActivityDetector::push`].
+///
+/// Serialized with the variant name in a snake_case `type` field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VadEvent {
+    /// Speech started; reported once it has lasted `min_speech_secs`
+    SpeechStart {
+        /// Start of the speech in seconds
+        timestamp: f64,
+    },
+    /// Speech ended; reported once `hangover_secs` have passed without speech
+    SpeechEnd {
+        /// The completed segment
+        segment: SpeechSegment,
+    },
+}
+
+/// Find the speech in a recording.
+///
+/// Multichannel audio is downmixed; NaN samples are treated as silence and
+/// infinities as full scale. Audio shorter than one frame has no speech.
+pub fn detect_speech(audio: &AudioData, config: &VadConfig) -> Vec<SpeechSegment> {
+    if audio.sample_rate == 0 {
+        return Vec::new();
+    }
+
+    let audio = audio.to_mono();
+    let mut detector = VoiceActivityDetector::with_config(audio.sample_rate, config.clone());
+    let events = detector.push(&audio.samples).into_iter().chain(detector.finish());
+    events
+        .filter_map(|event| match event {
+            VadEvent::SpeechEnd { segment } => Some(segment),
+            VadEvent::SpeechStart { .. } => None,
+        })
+        .collect()
+}
+
+/// Fraction of `duration_secs` covered by `segments` (0-1).
+pub fn speech_coverage(segments: &[SpeechSegment], duration_secs: f64) -> f32 {
+    if duration_secs <= 0.0 {
+        return 0.0;
+    }
+    let speech: f64 = segments.iter().map(SpeechSegment::duration).sum();
+    (speech / duration_secs).clamp(0.0, 1.0) as f32
+}
+
+/// Segment in progress.
+#[derive(Debug, Clone, Copy)]
+struct OpenSegment {
+    start: f64,
+    /// End of the latest speech frame
+    end: f64,
+    confidence_sum: f32,
+    speech_frames: usize,
+}
+
+impl OpenSegment {
+    fn close(self) -> SpeechSegment {
+        SpeechSegment {
+            start: self.start,
+            end: self.end,
+            confidence: self.confidence_sum / self.speech_frames.max(1) as f32,
+        }
+    }
+}
+
+/// Incremental voice activity detector for mono audio.
+pub struct VoiceActivityDetector {
+    config: VadConfig,
+    sample_rate: u32,
+    analyzer: FrequencyAnalyzer,
+    framer: Framer,
+    fft: Arc<dyn Fft<f32>>,
+    fft_scratch: Vec<Complex<f32>>,
+    /// Spectrum bins measured for entropy
+    band: std::ops::Range<usize>,
+    hop: usize,
+    /// Frames needed to start and to end a segment
+    onset_frames: usize,
+    hangover_frames: usize,
+    /// Levels of recent frames for the noise floor (dBFS)
+    recent_levels: VecDeque<f32>,
+    floor_frames: usize,
+    /// Frames analyzed so far
+    frames: u64,
+    /// Speech frames seen since the last non-speech frame, outside a segment
+    onset: Option<OpenSegment>,
+    segment: Option<OpenSegment>,
+    /// Non-speech frames since the open segment's last speech frame
+    silent_frames: usize,
+}
+
+impl VoiceActivityDetector {
+    /// Create a detector for audio at `sample_rate` with default configuration.
+    pub fn new(sample_rate: u32) -> Self {
+        Self::with_config(sample_rate, VadConfig::default())
+    }
+
+    /// Create a detector with custom configuration.
+    pub fn with_config(sample_rate: u32, config: VadConfig) -> Self {
+        let rate = sample_rate as f64;
+        let frame = ((config.frame_secs * rate) as usize).max(16);
+        let hop = ((config.hop_secs * rate) as usize).max(1);
+        let frames_in = |secs: f64| (secs / (hop as f64 / rate)).ceil().max(1.0) as usize;
+
+        let analyzer = FrequencyAnalyzer::new(frame, hop);
+        let fft = analyzer.plan_fft();
+        let fft_scratch = analyzer.fft_scratch(fft.as_ref());
+        let bin = |hz: f32| ((hz * frame as f32 / sample_rate.max(1) as f32) as usize).min(frame / 2);
+
+        Self {
+            sample_rate,
+            framer: Framer::new(frame, hop),
+            band: bin(SPEECH_BAND.0).max(1)..bin(SPEECH_BAND.1),
+            hop,
+            onset_frames: frames_in(config.min_speech_secs),
+            hangover_frames: frames_in(config.hangover_secs),
+            floor_frames: frames_in(NOISE_FLOOR_WINDOW_SECS),
+            recent_levels: VecDeque::new(),
+            analyzer,
+            fft,
+            fft_scratch,
+            config,
+            frames: 0,
+            onset: None,
+            segment: None,
+            silent_frames: 0,
+        }
+    }
+
+    /// Whether a segment is open, i.e. someone is speaking right now.
+    pub fn is_speech_active(&self) -> bool {
+        self.segment.is_some()
+    }
+
+    /// Stream time analyzed so far in seconds.
+    pub fn current_time(&self) -> f64 {
+        self.frames as f64 * self.hop as f64 / self.sample_rate as f64
+    }
+
+    /// Feed mono samples, returning the speech boundaries now known.
+    ///
+    /// NaN samples are treated as silence and infinities as full scale.
+    pub fn push(&mut self, samples: &[f32]) -> Vec<VadEvent> {
+        let mut events = Vec::new();
+
+        // Take the framer so frames can be classified as borrowed slices
+        let mut framer = std::mem::take(&mut self.framer);
+        framer.process(&replace_non_finite(samples), |_, frame| {
+            let confidence = self.classify(frame);
+            events.extend(self.advance(confidence));
+        });
+        self.framer = framer;
+
+        events
+    }
+
+    /// Close the open segment at the end of the stream.
+    pub fn finish(&mut self) -> Option<VadEvent> {
+        self.onset = None;
+        self.silent_frames = 0;
+        self.segment.take().map(|segment| VadEvent::SpeechEnd { segment: segment.close() })
+    }
+
+    /// Clear all state to start a new stream.
+    pub fn reset(&mut self) {
+        self.framer.clear();
+        self.recent_levels.clear();
+        self.frames = 0;
+        self.onset = None;
+        self.segment = None;
+        self.silent_frames = 0;
+    }
+
+    /// Speech confidence of one frame, `None` if it isn't speech.
+    fn classify(&mut self, frame: &[f32]) -> Option<f32> {
+        let power = frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32;
+        let level = 10.0 * power.max(1e-12).log10();
+
+        self.recent_levels.push_back(level);
+        if self.recent_levels.len() > self.floor_frames {
+            self.recent_levels.pop_front();
+        }
+        let floor = self.recent_levels.iter().copied().fold(f32::INFINITY, f32::min).max(MIN_NOISE_FLOOR_DB);
+
+        let margin = self.config.aggressiveness.level_margin_db();
+        if level < self.config.min_level_db || level - floor < margin {
+            return None;
+        }
+
+        let spectrum = self.analyzer.frame_spectrum(self.fft.as_ref(), frame, &mut self.fft_scratch);
+        let entropy = spectral_entropy(spectrum.get(self.band.clone()).unwrap_or_default());
+        let (low, high) = self.config.aggressiveness.entropy_range();
+        if entropy < low || entropy > high {
+            return None;
+        }
+
+        // Confident when well above the floor and mid-range in entropy
+        let level_confidence = ((level - floor) / (2.0 * margin)).min(1.0);
+        let center = (low + high) / 2.0;
+        let entropy_confidence = 1.0 - (entropy - center).abs() / (high - center);
+        Some(0.5 * level_confidence + 0.5 * entropy_confidence)
+    }
+
+    /// Update the segment state with the next frame's classification.
+    fn advance(&mut self, confidence: Option<f32>) -> Option<VadEvent> {
+        let start = self.current_time();
+        self.frames += 1;
+        let end = self.current_time();
+
+        let Some(confidence) = confidence else {
+            self.onset = None;
+            self.silent_frames += 1;
+            if self.silent_frames >= self.hangover_frames {
+                return self.finish();
+            }
+            return None;
+        };
+
+        self.silent_frames = 0;
+        if let Some(segment) = &mut self.segment {
+            segment.end = end;
+            segment.confidence_sum += confidence;
+            segment.speech_frames += 1;
+            return None;
+        }
+
+        let onset = self.onset.get_or_insert(OpenSegment {
+            start,
+            end,
+            confidence_sum: 0.0,
+            speech_frames: 0,
+        });
+        onset.end = end;
+        onset.confidence_sum += confidence;
+        onset.speech_frames += 1;
+        if onset.speech_frames < self.onset_frames {
+            return None;
+        }
+
+        let timestamp = onset.start;
+        self.segment = self.onset.take();
+        Some(VadEvent::SpeechStart { timestamp })
+    }
+}
+
+/// Shannon entropy of the power spectrum, normalized to 0 (one bin) - 1 (flat).
+fn spectral_entropy(magnitudes: &[f32]) -> f32 {
+    if magnitudes.len() < 2 {
+        return 0.0;
+    }
+    let total: f32 = magnitudes.iter().map(|m| m * m).sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    let entropy: f32 = magnitudes.iter()
+        .map(|m| m * m / total)
+        .filter(|&p| p > 0.0)
+        .map(|p| -p * p.ln())
+        .sum();
+    entropy / (magnitudes.len() as f32).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    fn speech_like(sample_rate: u32, secs: f32) -> Vec<f32> {
+        crate::testing::speech_like(sample_rate, secs).samples
+    }
+
+    fn tone(freq: f32, secs: f32) -> Vec<f32> {
+        (0..(SAMPLE_RATE as f32 * secs) as usize)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    fn silence(secs: f32) -> Vec<f32> {
+        vec![0.0; (SAMPLE_RATE as f32 * secs) as usize]
+    }
+
+    fn detect(samples: Vec<f32>) -> Vec<SpeechSegment> {
+        detect_speech(&AudioData::new(samples, SAMPLE_RATE), &VadConfig::default())
+    }
+
+    #[test]
+    fn test_speech_located_between_silence_and_tone() {
+        let samples = [silence(1.0), speech_like(SAMPLE_RATE, 3.0), silence(1.0), tone(440.0, 2.0)].concat();
+        let segments = detect(samples);
+
+        // Pauses within the speech are bridged by the hangover
+        assert_eq!(segments.len(), 1, "{:?}", segments);
+        assert!((segments[0].start - 1.0).abs() < 0.1, "{:?}", segments[0]);
+        assert!((segments[0].end - 3.75).abs() < 0.35, "{:?}", segments[0]);
+        assert!(segments[0].confidence > 0.3);
+    }
+
+    #[test]
+    fn test_steady_sounds_are_not_speech() {
+        let harmonics: Vec<f32> = tone(220.0, 3.0).iter()
+            .zip(tone(440.0, 3.0))
+            .zip(tone(660.0, 3.0))
+            .map(|((a, b), c)| (a + b / 2.0 + c / 3.0) / 2.0)
+            .collect();
+        let mut seed = 7u64;
+        let noise: Vec<f32> = (0..SAMPLE_RATE * 3)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (seed >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+            })
+            .collect();
+
+        assert!(detect(tone(440.0, 3.0)).is_empty());
+        assert!(detect(harmonics).is_empty());
+        assert!(detect(noise).is_empty());
+        assert!(detect(silence(3.0)).is_empty());
+    }
+
+    #[test]
+    fn test_push_matches_batch_detection() {
+        let samples = [speech_like(SAMPLE_RATE, 2.0), silence(1.0), speech_like(SAMPLE_RATE, 2.0)].concat();
+        let expected = detect(samples.clone());
+        assert_eq!(expected.len(), 2);
+
+        let mut detector = VoiceActivityDetector::new(SAMPLE_RATE);
+        let mut events = Vec::new();
+        let mut was_active = Vec::new();
+        for chunk in samples.chunks(333) {
+            events.extend(detector.push(chunk));
+            was_active.push(detector.is_speech_active());
+        }
+        events.extend(detector.finish());
+
+        assert!(was_active.contains(&true) && was_active.contains(&false));
+        let starts: Vec<f64> = events.iter()
+            .filter_map(|e| match e {
+                VadEvent::SpeechStart { timestamp } => Some(*timestamp),
+                _ => None,
+            })
+            .collect();
+        let segments: Vec<SpeechSegment> = events.iter()
+            .filter_map(|e| match e {
+                VadEvent::SpeechEnd { segment } => Some(*segment),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(segments, expected);
+        assert_eq!(starts, expected.iter().map(|s| s.start).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_speech_coverage() {
+        let segments = detect([speech_like(SAMPLE_RATE, 2.0), silence(2.0)].concat());
+        let coverage = speech_coverage(&segments, 4.0);
+        assert!((0.3..0.6).contains(&coverage), "{}", coverage);
+        assert_eq!(speech_coverage(&segments, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,126 @@
+//! Batch processing: ordering, error isolation, progress and cancellation.
+
+use std::sync::{Arc, Mutex};
+
+use kino_frequency::{
+    process_batch, AudioData, BatchConcurrency, BatchItem, BatchStage, ProcessingConfig,
+    ProcessingError, ProgressEvent,
+};
+use tokio_util::sync::CancellationToken;
+
+const SAMPLE_RATE: u32 = 22050;
+
+fn tone(label: &str, freq: f32, secs: f32) -> BatchItem {
+    let samples = (0..(SAMPLE_RATE as f32 * secs) as usize)
+        .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE as f32).sin())
+        .collect();
+    BatchItem::Audio {
+        label: label.to_string(),
+        audio: AudioData::new(samples, SAMPLE_RATE),
+    }
+}
+
+fn config() -> ProcessingConfig {
+    ProcessingConfig {
+        sample_rate: SAMPLE_RATE,
+        ..Default::default()
+    }
+}
+
+/// Collects progress events for inspection after the batch.
+fn recorder() -> (Arc<Mutex<Vec<ProgressEvent>>>, impl Fn(ProgressEvent) + Send + Sync + 'static) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = events.clone();
+    (events, move |event| sink.lock().unwrap().push(event))
+}
+
+fn dominant(result: &kino_frequency::ProcessingResult) -> f32 {
+    result.dominant_frequencies[0].frequency_hz
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_results_keep_input_order_and_isolate_errors() {
+    let items = vec![
+        tone("low", 220.0, 2.0),
+        BatchItem::File("/nonexistent/kino-batch.mp4".into()),
+        tone("mid", 880.0, 2.0),
+        tone("blip", 440.0, 0.01),
+        tone("high", 3520.0, 2.0),
+    ];
+    let (events, progress) = recorder();
+
+    let results = process_batch(items, config(), BatchConcurrency::new(2, 2), CancellationToken::new(), progress).await;
+
+    assert_eq!(results.len(), 5);
+    for (index, expected) in [(0, 220.0), (2, 880.0), (4, 3520.0)] {
+        let result = results[index].as_ref().unwrap();
+        assert!((dominant(result) - expected).abs() < 20.0, "item {}: {}", index, dominant(result));
+    }
+    assert!(matches!(results[1], Err(ProcessingError::Extraction(_))));
+    assert!(matches!(results[3], Err(ProcessingError::Analysis(_))));
+
+    let events = events.lock().unwrap();
+    let started: Vec<usize> = events.iter()
+        .filter_map(|e| match e {
+            ProgressEvent::Started { index, .. } => Some(*index),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(started, [0, 1, 2, 3, 4]);
+
+    for index in [0, 2, 4] {
+        let position = |wanted: &ProgressEvent| events.iter().position(|e| e == wanted).unwrap();
+        let analyzed = position(&ProgressEvent::StageCompleted { index, stage: BatchStage::Analysis });
+        let content_id = results[index].as_ref().unwrap().content_id.clone();
+        assert!(analyzed < position(&ProgressEvent::Finished { index, content_id }));
+    }
+
+    let failed: Vec<usize> = events.iter()
+        .filter_map(|e| match e {
+            ProgressEvent::Failed { index, .. } => Some(*index),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(failed.len(), 2);
+    assert!(failed.contains(&1) && failed.contains(&3));
+}
+
+#[tokio::test]
+async fn test_cancellation_stops_scheduling() {
+    let cancel = CancellationToken::new();
+    let events = Arc::new(Mutex::new(Vec::new()));
+
+    // Cancel as soon as the first item is scheduled
+    let sink = events.clone();
+    let token = cancel.clone();
+    let progress = move |event: ProgressEvent| {
+        if matches!(event, ProgressEvent::Started { .. }) {
+            token.cancel();
+        }
+        sink.lock().unwrap().push(event);
+    };
+
+    let items = vec![tone("a", 220.0, 1.0), tone("b", 440.0, 1.0), tone("c", 880.0, 1.0)];
+    let results = process_batch(items, config(), BatchConcurrency::new(1, 1), cancel, progress).await;
+
+    // The in-flight item completes, the rest are never started
+    assert!(results[0].is_ok());
+    assert!(matches!(results[1], Err(ProcessingError::Cancelled)));
+    assert!(matches!(results[2], Err(ProcessingError::Cancelled)));
+    assert!(events.lock().unwrap().iter().all(|e| match e {
+        ProgressEvent::Started { index, .. } | ProgressEvent::Finished { index, .. } => *index == 0,
+        _ => true,
+    }));
+}
+
+#[tokio::test]
+async fn test_cancelled_before_start() {
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let (events, progress) = recorder();
+
+    let results = process_batch(vec![tone("a", 220.0, 1.0)], config(), BatchConcurrency::default(), cancel, progress).await;
+
+    assert!(matches!(results[..], [Err(ProcessingError::Cancelled)]));
+    assert!(events.lock().unwrap().is_empty());
+}
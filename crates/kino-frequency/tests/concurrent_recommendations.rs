@@ -0,0 +1,87 @@
+//! Queries against a shared recommendation engine while content is added.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use kino_frequency::recommend::{RecommendConfig, RecommendationEngine, RecommendationFilter};
+use kino_frequency::{AudioData, FrequencySignature};
+
+const SAMPLE_RATE: u32 = 22050;
+const SEED_ITEMS: usize = 50;
+const SINGLE_ADDS: usize = 150;
+const BULK_ADDS: usize = 100;
+const READERS: usize = 4;
+
+fn signatures(engine: &RecommendationEngine) -> Vec<FrequencySignature> {
+    [220.0, 330.0, 440.0, 660.0, 880.0, 1320.0, 1760.0, 2640.0]
+        .iter()
+        .map(|&freq| {
+            let samples = (0..SAMPLE_RATE as usize)
+                .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE as f32).sin())
+                .collect();
+            engine.compute_signature(&AudioData::new(samples, SAMPLE_RATE)).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_engine_is_shareable() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RecommendationEngine>();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_queries_proceed_during_writes() {
+    // Small enough that the ANN index is trained and retrained while writing
+    let engine = Arc::new(RecommendationEngine::with_config(RecommendConfig {
+        ann_min_items: 100,
+        ..Default::default()
+    }));
+    let signatures = signatures(&engine);
+    engine.add_many((0..SEED_ITEMS).map(|i| (format!("seed-{}", i), signatures[i % signatures.len()].clone()))).unwrap();
+
+    let writing = Arc::new(AtomicBool::new(true));
+    let readers: Vec<_> = (0..READERS)
+        .map(|reader| {
+            let engine = engine.clone();
+            let writing = writing.clone();
+            tokio::task::spawn_blocking(move || {
+                let filter = RecommendationFilter::default();
+                let mut last_len = 0;
+                let mut queries = 0;
+                while writing.load(Ordering::Acquire) || queries == 0 {
+                    let len = engine.len();
+                    assert!(len >= last_len, "index shrank from {} to {}", last_len, len);
+                    last_len = len;
+
+                    let recs = engine.get_similar(&format!("seed-{}", (reader + queries) % SEED_ITEMS), 10, &filter);
+                    assert!(!recs.is_empty());
+                    queries += 1;
+                }
+                queries
+            })
+        })
+        .collect();
+
+    let writer = {
+        let engine = engine.clone();
+        let signatures = signatures.clone();
+        tokio::task::spawn_blocking(move || {
+            for i in 0..SINGLE_ADDS {
+                engine.add_content_with_signature(&format!("single-{}", i), signatures[i % signatures.len()].clone(), None).unwrap();
+            }
+            engine.add_many((0..BULK_ADDS).map(|i| (format!("bulk-{}", i), signatures[i % signatures.len()].clone()))).unwrap();
+        })
+    };
+
+    tokio::time::timeout(Duration::from_secs(60), writer).await.expect("writer deadlocked").unwrap();
+    writing.store(false, Ordering::Release);
+    for reader in readers {
+        let queries = tokio::time::timeout(Duration::from_secs(60), reader).await.expect("reader deadlocked").unwrap();
+        assert!(queries > 0);
+    }
+
+    assert_eq!(engine.len(), SEED_ITEMS + SINGLE_ADDS + BULK_ADDS);
+    assert!(engine.ann_index().is_some());
+}
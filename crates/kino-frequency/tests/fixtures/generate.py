@@ -0,0 +1,287 @@
+#!/usr/bin/env python3
+"""Regenerate the tiny fixtures used by `kino_frequency::decode` and tagging tests.
+
+The files are assembled by hand so no encoder or ONNX tooling is needed:
+
+- tone.flac     0.1s 440Hz sine, 44.1kHz mono, 16-bit VERBATIM subframes
+- silence.mp3   10 silent MPEG-1 Layer III frames, 44.1kHz mono
+- silence.m4a   20 silent AAC-LC frames in an MP4 container, 22.05kHz mono
+- silence.opus  Ogg/Opus stream (unsupported by Symphonia, exercises the FFmpeg fallback)
+- tiny_tagger.onnx  Gemm + Softmax over the 128-dim signature: the low half of
+                    the bins scores "music", the high half "speech"
+- labels.json   Sidecar labels for tiny_tagger.onnx
+
+Usage: python3 generate.py  (writes next to this script)
+"""
+
+import math
+import os
+import struct
+
+OUT = os.path.dirname(os.path.abspath(__file__))
+
+
+class BitWriter:
+    def __init__(self):
+        self.bits = []
+
+    def write(self, value, n):
+        for i in reversed(range(n)):
+            self.bits.append((value >> i) & 1)
+
+    def align(self):
+        while len(self.bits) % 8:
+            self.bits.append(0)
+
+    def bytes(self):
+        self.align()
+        out = bytearray()
+        for i in range(0, len(self.bits), 8):
+            byte = 0
+            for b in self.bits[i:i + 8]:
+                byte = (byte << 1) | b
+            out.append(byte)
+        return bytes(out)
+
+
+# --- FLAC -------------------------------------------------------------------
+
+def crc8(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x07) & 0xFF if crc & 0x80 else (crc << 1) & 0xFF
+    return crc
+
+
+def crc16(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte << 8
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x8005) & 0xFFFF if crc & 0x8000 else (crc << 1) & 0xFFFF
+    return crc
+
+
+def flac():
+    rate, total, block = 44100, 4410, 4096
+    samples = [int(math.sin(2 * math.pi * 440 * i / rate) * 0.8 * 32767) for i in range(total)]
+
+    info = BitWriter()
+    info.write(block, 16)
+    info.write(block, 16)
+    info.write(0, 24)
+    info.write(0, 24)
+    info.write(rate, 20)
+    info.write(0, 3)       # mono
+    info.write(15, 5)      # 16 bits
+    info.write(total, 36)
+    info.write(0, 128)     # MD5 not computed
+    out = bytearray(b"fLaC")
+    out += bytes([0x80, 0, 0, 34]) + info.bytes()
+
+    for frame, start in enumerate(range(0, total, block)):
+        chunk = samples[start:start + block]
+        hdr = BitWriter()
+        hdr.write(0b11111111111110, 14)
+        hdr.write(0, 2)
+        hdr.write(12 if len(chunk) == block else 7, 4)
+        hdr.write(9, 4)        # 44.1kHz
+        hdr.write(0, 4)        # mono
+        hdr.write(4, 3)        # 16 bits
+        hdr.write(0, 1)
+        hdr.write(frame, 8)    # UTF-8 coded frame number (< 128)
+        if len(chunk) != block:
+            hdr.write(len(chunk) - 1, 16)
+        header = hdr.bytes()
+        body = BitWriter()
+        body.write(0, 1)
+        body.write(1, 6)       # VERBATIM
+        body.write(0, 1)
+        for s in chunk:
+            body.write(s & 0xFFFF, 16)
+        data = header + bytes([crc8(header)]) + body.bytes()
+        out += data + struct.pack(">H", crc16(data))
+    return bytes(out)
+
+
+# --- MP3 --------------------------------------------------------------------
+
+def mp3():
+    # MPEG-1 Layer III, 32kbps, 44.1kHz, mono: 104-byte frames with zeroed
+    # side info and main data decode to silence.
+    frame = bytes([0xFF, 0xFB, 0x10, 0xC0]) + bytes(100)
+    return frame * 10
+
+
+# --- MP4 / AAC --------------------------------------------------------------
+
+def box(kind, payload):
+    return struct.pack(">I", 8 + len(payload)) + kind + payload
+
+
+def full_box(kind, version, flags, payload):
+    return box(kind, struct.pack(">I", (version << 24) | flags) + payload)
+
+
+def descriptor(tag, payload):
+    return bytes([tag, len(payload)]) + payload
+
+
+MATRIX = struct.pack(">9I", 0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x40000000)
+
+
+def aac_silent_frame():
+    bw = BitWriter()
+    bw.write(0, 3)     # SCE
+    bw.write(0, 4)     # element_instance_tag
+    bw.write(100, 8)   # global_gain
+    bw.write(0, 1)     # ics_reserved_bit
+    bw.write(0, 2)     # ONLY_LONG_SEQUENCE
+    bw.write(0, 1)     # window_shape
+    bw.write(0, 6)     # max_sfb = 0, no spectral data
+    bw.write(0, 1)     # predictor_data_present
+    bw.write(0, 1)     # pulse_data_present
+    bw.write(0, 1)     # tns_data_present
+    bw.write(0, 1)     # gain_control_data_present
+    bw.write(7, 3)     # END
+    return bw.bytes()
+
+
+def m4a():
+    rate, count = 22050, 20
+    frame = aac_silent_frame()
+    duration = count * 1024
+
+    asc = BitWriter()
+    asc.write(2, 5)    # AAC-LC
+    asc.write(7, 4)    # 22050Hz
+    asc.write(1, 4)    # mono
+    asc.write(0, 3)
+    esds = full_box(b"esds", 0, 0, descriptor(0x03, struct.pack(">HB", 1, 0)
+        + descriptor(0x04, bytes([0x40, 0x15]) + bytes(3) + struct.pack(">II", 0, 0)
+                     + descriptor(0x05, asc.bytes()))
+        + descriptor(0x06, b"\x02")))
+    mp4a = box(b"mp4a", bytes(6) + struct.pack(">H", 1) + bytes(8)
+               + struct.pack(">HHHHI", 1, 16, 0, 0, rate << 16) + esds)
+
+    def stbl(chunk_offset):
+        return box(b"stbl",
+            full_box(b"stsd", 0, 0, struct.pack(">I", 1) + mp4a)
+            + full_box(b"stts", 0, 0, struct.pack(">III", 1, count, 1024))
+            + full_box(b"stsc", 0, 0, struct.pack(">IIII", 1, 1, count, 1))
+            + full_box(b"stsz", 0, 0, struct.pack(">II", 0, count) + struct.pack(">I", len(frame)) * count)
+            + full_box(b"stco", 0, 0, struct.pack(">II", 1, chunk_offset)))
+
+    def moov(chunk_offset):
+        mdia = box(b"mdia",
+            full_box(b"mdhd", 0, 0, struct.pack(">IIIIHH", 0, 0, rate, duration, 0x55C4, 0))
+            + full_box(b"hdlr", 0, 0, bytes(4) + b"soun" + bytes(12) + b"SoundHandler\x00")
+            + box(b"minf",
+                full_box(b"smhd", 0, 0, bytes(4))
+                + box(b"dinf", full_box(b"dref", 0, 0, struct.pack(">I", 1) + full_box(b"url ", 0, 1, b"")))
+                + stbl(chunk_offset)))
+        tkhd = full_box(b"tkhd", 0, 7, struct.pack(">IIIII", 0, 0, 1, 0, duration) + bytes(8)
+                        + struct.pack(">hhhH", 0, 0, 0x0100, 0) + MATRIX + struct.pack(">II", 0, 0))
+        mvhd = full_box(b"mvhd", 0, 0, struct.pack(">IIIIIH", 0, 0, rate, duration, 0x10000, 0x0100)
+                        + bytes(10) + MATRIX + bytes(24) + struct.pack(">I", 2))
+        return box(b"moov", mvhd + box(b"trak", tkhd + mdia))
+
+    ftyp = box(b"ftyp", b"M4A " + struct.pack(">I", 0) + b"M4A isom")
+    offset = len(ftyp) + len(moov(0)) + 8
+    return ftyp + moov(offset) + box(b"mdat", frame * count)
+
+
+# --- Ogg / Opus -------------------------------------------------------------
+
+def ogg_crc(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte << 24
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x04C11DB7) & 0xFFFFFFFF if crc & 0x80000000 else (crc << 1) & 0xFFFFFFFF
+    return crc
+
+
+def ogg_page(packet, seq, granule, header_type):
+    segments = [255] * (len(packet) // 255) + [len(packet) % 255]
+    page = bytearray(b"OggS" + struct.pack("<BBqIII", 0, header_type, granule, 0x4B494E4F, seq, 0)
+                     + bytes([len(segments)]) + bytes(segments) + packet)
+    page[22:26] = struct.pack("<I", ogg_crc(page))
+    return bytes(page)
+
+
+def opus():
+    head = b"OpusHead" + struct.pack("<BBHIhB", 1, 1, 312, 48000, 0, 0)
+    vendor = b"kino"
+    tags = b"OpusTags" + struct.pack("<I", len(vendor)) + vendor + struct.pack("<I", 0)
+    silence = bytes([0xF8, 0xFF, 0xFE])  # 20ms CELT frame flagged as silence
+    return (ogg_page(head, 0, 0, 0x02) + ogg_page(tags, 1, 0, 0x00)
+            + ogg_page(silence, 2, 960, 0x04))
+
+
+def pb_varint(value):
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def pb_int(field, value):
+    return pb_varint(field << 3) + pb_varint(value)
+
+
+def pb_bytes(field, payload):
+    if isinstance(payload, str):
+        payload = payload.encode()
+    return pb_varint(field << 3 | 2) + pb_varint(len(payload)) + payload
+
+
+def onnx_value_info(name, dims):
+    # ValueInfoProto { name, type: TypeProto { tensor_type { elem_type FLOAT, shape } } }
+    shape = b"".join(pb_bytes(1, pb_bytes(2, d) if isinstance(d, str) else pb_int(1, d)) for d in dims)
+    tensor_type = pb_int(1, 1) + pb_bytes(2, shape)
+    return pb_bytes(1, name) + pb_bytes(2, pb_bytes(1, tensor_type))
+
+
+def onnx_tensor(name, dims, values):
+    # TensorProto { dims, data_type FLOAT, name, raw_data }
+    return (b"".join(pb_int(1, d) for d in dims) + pb_int(2, 1) + pb_bytes(8, name)
+            + pb_bytes(9, struct.pack(f"<{len(values)}f", *values)))
+
+
+def onnx_node(op_type, inputs, outputs):
+    return (b"".join(pb_bytes(1, i) for i in inputs) + b"".join(pb_bytes(2, o) for o in outputs)
+            + pb_bytes(3, op_type.lower()) + pb_bytes(4, op_type))
+
+
+def onnx_tagger():
+    features, classes = 128, 3
+    weights = [4.0 if (c == 0) == (f < features // 2) and c < 2 else 0.0
+               for f in range(features) for c in range(classes)]
+    graph = (pb_bytes(1, onnx_node("Gemm", ["signature", "weights", "bias"], ["logits"]))
+             + pb_bytes(1, onnx_node("Softmax", ["logits"], ["scores"]))
+             + pb_bytes(2, "tiny_tagger")
+             + pb_bytes(5, onnx_tensor("weights", [features, classes], weights))
+             + pb_bytes(5, onnx_tensor("bias", [classes], [0.0] * classes))
+             + pb_bytes(11, onnx_value_info("signature", ["batch", features]))
+             + pb_bytes(12, onnx_value_info("scores", ["batch", classes])))
+    # ModelProto { ir_version 7, producer_name, graph, opset_import { version 13 } }
+    return (pb_int(1, 7) + pb_bytes(2, "kino-fixtures") + pb_bytes(7, graph)
+            + pb_bytes(8, pb_bytes(1, "") + pb_int(2, 13)))
+
+
+if __name__ == "__main__":
+    labels = b'["music", "speech", "nature"]\n'
+    for name, data in [("tone.flac", flac()), ("silence.mp3", mp3()),
+                       ("silence.m4a", m4a()), ("silence.opus", opus()),
+                       ("tiny_tagger.onnx", onnx_tagger()), ("labels.json", labels)]:
+        with open(os.path.join(OUT, name), "wb") as f:
+            f.write(data)
+        print(f"{name}: {len(data)} bytes")
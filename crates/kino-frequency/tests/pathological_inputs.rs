@@ -0,0 +1,267 @@
+//! Pathological inputs through every analysis entry point.
+//!
+//! Empty, short, NaN, infinite and constant buffers must never panic. Each
+//! entry point either fails with a typed [`FrequencyError`] or returns
+//! results without NaN.
+
+use kino_frequency::fingerprint::{FingerprintConfig, Fingerprinter, StreamingFingerprinter};
+use kino_frequency::recommend::{RecommendationEngine, RecommendationFilter};
+use kino_frequency::streaming::StreamAnalyzer;
+use kino_frequency::tagging::{ContentTagger, TaggingConfig};
+use kino_frequency::{
+    generate_overview, loudness, AudioAnalyzer, AudioData, FingerprintAlgorithm, FrequencyAnalysis, FrequencyAnalyzer,
+    FrequencyError, FrequencyResult, FrequencySignature, SamplePolicy,
+};
+
+const SAMPLE_RATE: u32 = 22050;
+
+/// Deterministic xorshift noise in [-1, 1).
+struct Noise(u64);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        ((self.next() + 1.0) * 0.5 * n as f32) as usize % n.max(1)
+    }
+}
+
+/// A tone with noise, long enough for every entry point.
+fn signal(noise: &mut Noise, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            0.5 * (2.0 * std::f32::consts::PI * 440.0 * t).sin() + 0.1 * noise.next()
+        })
+        .collect()
+}
+
+/// Named pathological buffers, plus seeded random corruptions of a signal.
+fn cases() -> Vec<(String, Vec<f32>)> {
+    let mut noise = Noise(0x9e37_79b9_7f4a_7c15);
+    let long = SAMPLE_RATE as usize * 2;
+
+    let mut cases = vec![
+        ("empty".to_string(), Vec::new()),
+        ("one sample".to_string(), vec![0.5]),
+        ("short".to_string(), signal(&mut noise, 1000)),
+        ("all NaN".to_string(), vec![f32::NAN; long]),
+        ("all +inf".to_string(), vec![f32::INFINITY; long]),
+        ("all -inf".to_string(), vec![f32::NEG_INFINITY; long]),
+        ("silence".to_string(), vec![0.0; long]),
+        ("DC offset".to_string(), vec![0.25; long]),
+        ("full scale DC".to_string(), vec![1.0; long]),
+        ("short NaN".to_string(), vec![f32::NAN; 100]),
+    ];
+
+    for seed in 0..8 {
+        let mut samples = signal(&mut noise, long);
+        let corrupt = 1 + noise.below(64);
+        for _ in 0..corrupt {
+            let i = noise.below(samples.len());
+            samples[i] = match seed % 3 {
+                0 => f32::NAN,
+                1 => f32::INFINITY,
+                _ => f32::NEG_INFINITY,
+            };
+        }
+        cases.push((format!("signal with {} corrupt samples (seed {})", corrupt, seed), samples));
+    }
+
+    for seed in 0..4 {
+        let len = noise.below(8192);
+        cases.push((format!("random length {} (seed {})", len, seed), signal(&mut noise, len)));
+    }
+
+    cases
+}
+
+/// Accept typed rejections of unusable input; anything else must be Ok.
+fn check<T>(case: &str, entry: &str, result: FrequencyResult<T>, finite: impl FnOnce(&T) -> bool) {
+    match result {
+        Ok(value) => assert!(finite(&value), "{}: {} produced NaN or infinite output", case, entry),
+        Err(FrequencyError::TooShort { samples, required }) => {
+            assert!(samples < required, "{}: {} TooShort with {} >= {}", case, entry, samples, required)
+        }
+        Err(FrequencyError::InvalidSamples { nan, infinite }) => {
+            assert!(nan + infinite > 0, "{}: {} rejected finite samples", case, entry)
+        }
+        Err(FrequencyError::ZeroSignal) => {}
+        Err(FrequencyError::Cancelled) => panic!("{}: {} was cancelled without a token", case, entry),
+        Err(FrequencyError::Other(e)) => panic!("{}: {} failed with an untyped error: {:#}", case, entry, e),
+    }
+}
+
+fn all_finite(values: &[f32]) -> bool {
+    values.iter().all(|v| v.is_finite())
+}
+
+fn analysis_finite(a: &FrequencyAnalysis) -> bool {
+    all_finite(&a.spectrum)
+        && all_finite(&a.spectral_contrast)
+        && all_finite(&a.band_energies.energies)
+        && all_finite(&[
+            a.spectral_centroid,
+            a.spectral_rolloff,
+            a.spectral_flatness,
+            a.spectral_bandwidth,
+            a.zero_crossing_rate,
+            a.crest_factor,
+        ])
+}
+
+fn signature_finite(s: &FrequencySignature) -> bool {
+    all_finite(&s.features)
+        && all_finite(&s.contrast)
+        && all_finite(&s.band_energies.energies)
+        && all_finite(&[s.centroid, s.flatness, s.bandwidth, s.crest_factor])
+}
+
+#[test]
+fn test_audio_analyzer_entry_points() {
+    for policy in [SamplePolicy::Replace, SamplePolicy::Reject] {
+        let analyzer = AudioAnalyzer::with_fft_params(SAMPLE_RATE, 2048, 1024).with_sample_policy(policy);
+        for (case, samples) in cases() {
+            let audio = AudioData::new(samples, SAMPLE_RATE);
+            check(&case, "analyze", analyzer.analyze(&audio), analysis_finite);
+            check(&case, "dominant_frequencies", analyzer.dominant_frequencies(&audio, 5), |d| {
+                d.iter().all(|f| f.frequency_hz.is_finite() && f.magnitude.is_finite())
+            });
+            check(&case, "compute_signature", analyzer.compute_signature(&audio), signature_finite);
+            check(&case, "measure_loudness", analyzer.measure_loudness(&audio), |l| {
+                !l.integrated_lufs.is_nan() && !l.loudness_range_lu.is_nan() && !l.true_peak_dbtp.is_nan()
+            });
+        }
+    }
+}
+
+#[test]
+fn test_frequency_analyzer_entry_points() {
+    let analyzer = FrequencyAnalyzer::new(1024, 512);
+    for (case, samples) in cases() {
+        check(&case, "FrequencyAnalyzer::analyze", analyzer.analyze(&samples, SAMPLE_RATE), analysis_finite);
+        check(&case, "compute_spectrogram", analyzer.compute_spectrogram(&samples), |frames| {
+            frames.iter().all(|f| all_finite(f))
+        });
+        check(&case, "loudness::measure", loudness::measure(&AudioData::new(samples, SAMPLE_RATE)), |l| {
+            !l.integrated_lufs.is_nan() && !l.true_peak_dbtp.is_nan()
+        });
+    }
+}
+
+#[test]
+fn test_replace_policy_never_reports_invalid_samples() {
+    let analyzer = AudioAnalyzer::with_fft_params(SAMPLE_RATE, 2048, 1024);
+    for (case, samples) in cases() {
+        let result = analyzer.compute_signature(&AudioData::new(samples, SAMPLE_RATE));
+        assert!(!matches!(result, Err(FrequencyError::InvalidSamples { .. })), "{}", case);
+    }
+}
+
+#[test]
+fn test_fingerprint_entry_points() {
+    let chromaprint = FingerprintConfig { algorithm: FingerprintAlgorithm::Chromaprint, ..Default::default() };
+    let rejecting = FingerprintConfig { sample_policy: SamplePolicy::Reject, ..Default::default() };
+    let fingerprinters = [
+        Fingerprinter::new(),
+        Fingerprinter::with_config(chromaprint),
+        Fingerprinter::with_config(rejecting),
+    ];
+
+    for fingerprinter in &fingerprinters {
+        for (case, samples) in cases() {
+            let audio = AudioData::new(samples, SAMPLE_RATE);
+            check(&case, "fingerprint", fingerprinter.fingerprint(&audio), |fp| fp.duration_secs.is_finite());
+            check(&case, "verify", fingerprinter.verify(&audio, "00"), |v| v.similarity.is_finite());
+        }
+    }
+}
+
+#[test]
+fn test_streaming_entry_points() {
+    for (case, samples) in cases() {
+        let mut fingerprinter = StreamingFingerprinter::new(SAMPLE_RATE);
+        for chunk in samples.chunks(1000) {
+            fingerprinter.process(chunk);
+        }
+        check(&case, "StreamingFingerprinter::finalize", fingerprinter.finalize(), |fp| {
+            fp.duration_secs.is_finite()
+        });
+
+        let mut analyzer = StreamAnalyzer::new(SAMPLE_RATE, 1024);
+        for frame in analyzer.process(&samples) {
+            let values = [frame.dominant_frequency, frame.spectral_centroid, frame.rms_energy, frame.zcr, frame.spectral_flux];
+            assert!(all_finite(&values), "{}: StreamAnalyzer produced {:?}", case, values);
+        }
+    }
+}
+
+#[test]
+fn test_overview_entry_point() {
+    for (case, samples) in cases() {
+        let audio = AudioData::new(samples, SAMPLE_RATE);
+        check(&case, "generate_overview", generate_overview(&audio, 64), |overview| {
+            overview.buckets() <= 64 && overview.heatmap.len() == overview.buckets() * overview.bands()
+        });
+    }
+}
+
+#[test]
+fn test_tagging_entry_points() {
+    let normalizing = TaggingConfig { normalize_loudness: Some(-23.0), ..Default::default() };
+    let rejecting = TaggingConfig { sample_policy: SamplePolicy::Reject, ..Default::default() };
+    let taggers = [ContentTagger::new(), ContentTagger::with_config(normalizing), ContentTagger::with_config(rejecting)];
+
+    for tagger in &taggers {
+        for (case, samples) in cases() {
+            let audio = AudioData::new(samples, SAMPLE_RATE);
+            check(&case, "predict", tagger.predict(&audio), |tags| {
+                tags.iter().all(|t| t.confidence.is_finite())
+            });
+            check(&case, "predict_segments", tagger.predict_segments(&audio, 0.5, 0.25), |segments| {
+                segments.iter().flat_map(|s| &s.tags).all(|t| t.confidence.is_finite())
+            });
+            check(&case, "detect_languages", tagger.detect_languages(&audio), |languages| {
+                languages.iter().all(|l| l.confidence.is_finite())
+            });
+        }
+    }
+}
+
+#[test]
+fn test_recommendation_entry_points() {
+    let engine = RecommendationEngine::new();
+    let mut noise = Noise(7);
+    engine.add_content("reference", &AudioData::new(signal(&mut noise, 20000), SAMPLE_RATE), None).unwrap();
+
+    let filter = RecommendationFilter::default();
+    for (case, samples) in cases() {
+        let audio = AudioData::new(samples, SAMPLE_RATE);
+        check(&case, "get_recommendations_for_audio", engine.get_recommendations_for_audio(&audio, 5, &filter), |recs| {
+            recs.iter().all(|r| r.similarity.is_finite())
+        });
+        check(&case, "add_content", engine.add_content(&case, &audio, None), |_| true);
+    }
+}
+
+#[test]
+fn test_validate_counts_non_finite_samples() {
+    let audio = AudioData::new(vec![0.5, f32::NAN, -0.25, f32::INFINITY, f32::NEG_INFINITY], SAMPLE_RATE);
+    let report = audio.validate();
+
+    assert_eq!((report.samples, report.nan, report.infinite), (5, 1, 2));
+    assert_eq!((report.min, report.max), (-0.25, 0.5));
+    assert!(!report.is_finite());
+
+    let replaced = audio.sanitize(SamplePolicy::Replace).unwrap();
+    assert_eq!(replaced.samples, vec![0.5, 0.0, -0.25, 1.0, -1.0]);
+    assert!(matches!(
+        audio.sanitize(SamplePolicy::Reject),
+        Err(FrequencyError::InvalidSamples { nan: 1, infinite: 2 })
+    ));
+}
@@ -0,0 +1,244 @@
+//! Serialized layout of the public result types.
+//!
+//! Each type is pinned by a golden file in `tests/fixtures/schema`: a fully
+//! populated value must serialize to exactly that JSON, and the file must
+//! deserialize back to it, so renaming or dropping a field fails here
+//! rather than in a consumer's database. When a layout change is intended,
+//! bump `RESULT_SCHEMA_VERSION` if it breaks readers and rerun with
+//! `KINO_UPDATE_SCHEMA=1` to rewrite the files.
+
+use std::path::PathBuf;
+
+use kino_frequency::fingerprint::{DatabaseMatch, MatchResult, VerificationResult, WindowFingerprint};
+use kino_frequency::streaming::{AnalysisEvent, AnalysisFrame, StreamStatistics};
+use kino_frequency::thumbnail::ThumbnailCandidate;
+use kino_frequency::vad::VadEvent;
+use kino_frequency::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/schema").join(format!("{}.json", name))
+}
+
+/// Check `value` against the golden file `name`, in both directions.
+fn assert_golden<T: Serialize + DeserializeOwned>(name: &str, value: &T) {
+    let path = golden_path(name);
+    let actual = serde_json::to_value(value).unwrap();
+    if std::env::var_os("KINO_UPDATE_SCHEMA").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+    }
+
+    let golden = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Missing golden file {}: {}", path.display(), e));
+    let expected: Value = serde_json::from_str(&golden).unwrap();
+    assert_eq!(actual, expected, "{} no longer serializes like {}", name, path.display());
+
+    let parsed: T = serde_json::from_str(&golden)
+        .unwrap_or_else(|e| panic!("{} no longer deserializes: {}", path.display(), e));
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), expected, "{} does not round-trip", name);
+}
+
+fn band_energies() -> BandEnergies {
+    BandEnergies::standard([0.05, 0.2, 0.25, 0.3, 0.15, 0.05])
+}
+
+fn fingerprint() -> AudioFingerprint {
+    AudioFingerprint {
+        hash: "ab".repeat(32),
+        version: 2,
+        algorithm: FingerprintAlgorithm::Constellation,
+        sample_rate: 22050,
+        points: vec![
+            FingerprintPoint { time_offset: 0, freq_bin: 120, amplitude: 200 },
+            FingerprintPoint { time_offset: 3, freq_bin: 341, amplitude: 96 },
+        ],
+        subfingerprints: Vec::new(),
+        chromaprint: None,
+        duration_secs: 12.5,
+        low_entropy: true,
+    }
+}
+
+fn frame() -> AnalysisFrame {
+    AnalysisFrame {
+        timestamp: 1.5,
+        dominant_frequency: 440.0,
+        dominant_magnitude: 0.75,
+        spectral_centroid: 1250.0,
+        band_energies: band_energies(),
+        rms_energy: 0.125,
+        zcr: 0.0625,
+        spectral_flux: 3.5,
+    }
+}
+
+#[test]
+fn test_processing_result_schema() {
+    let result = ProcessingResult {
+        fingerprint: Some(fingerprint()),
+        tags: vec![
+            ContentTag { label: "music".to_string(), confidence: 0.875, id: Some("genre.music".to_string()) },
+            ContentTag::new("lang:en", 0.5),
+        ],
+        thumbnail_timestamp: Some(42.5),
+        signature: Some(FrequencySignature {
+            version: 2,
+            features: vec![0.25, 0.5, 0.125],
+            band_energies: band_energies(),
+            centroid: 1250.0,
+            flatness: 0.25,
+            bandwidth: 900.0,
+            contrast: vec![12.0, 18.5],
+            crest_factor: 4.0,
+        }),
+        dominant_frequencies: vec![DominantFrequency { frequency_hz: 440.0, magnitude: 0.75, rank: 1 }],
+        loudness: Some(LoudnessInfo { integrated_lufs: -16.5, loudness_range_lu: 6.25, true_peak_dbtp: -1.5 }),
+        dominant_language: Some("en".to_string()),
+        content_id_source: ContentIdSource::Fingerprint,
+        audio: Some(AnalyzedAudio {
+            stream_index: Some(2),
+            language: Some("eng".to_string()),
+            start_secs: 0.0,
+            duration_secs: 12.5,
+        }),
+        music: Some(MusicInfo {
+            bpm: 120.0,
+            bpm_confidence: 0.75,
+            key: "A".to_string(),
+            mode: KeyMode::Minor,
+            key_confidence: 0.5,
+        }),
+        ..ProcessingResult::new("content-1")
+    };
+    assert_eq!(result.schema_version, RESULT_SCHEMA_VERSION);
+    assert_golden("processing_result", &result);
+}
+
+#[test]
+fn test_processing_result_versions() {
+    // Results stored before versioning load as version 0
+    let legacy = r#"{"content_id":"old","fingerprint":null,"tags":[],"thumbnail_timestamp":null,
+        "signature":null,"dominant_frequencies":[]}"#;
+    let legacy = ProcessingResult::from_json(legacy).unwrap();
+    assert_eq!((legacy.schema_version, legacy.content_id_source), (0, ContentIdSource::Unspecified));
+
+    let mut newer = serde_json::to_value(ProcessingResult::new("new")).unwrap();
+    newer["schema_version"] = (RESULT_SCHEMA_VERSION + 1).into();
+    let err = ProcessingResult::from_json(&newer.to_string()).unwrap_err();
+    assert!(err.to_string().contains("newer than the supported version"), "{}", err);
+}
+
+#[test]
+fn test_analysis_schemas() {
+    assert_golden("frequency_analysis", &FrequencyAnalysis {
+        spectrum: vec![0.0, 0.5, 0.25],
+        frequencies: vec![0.0, 10.75, 21.5],
+        spectral_centroid: 1250.0,
+        spectral_rolloff: 4000.0,
+        spectral_flatness: 0.25,
+        spectral_bandwidth: 900.0,
+        spectral_contrast: vec![12.0, 18.5],
+        band_energies: band_energies(),
+        zero_crossing_rate: 0.0625,
+        crest_factor: 4.0,
+    });
+
+    assert_golden("thumbnail_candidate", &ThumbnailCandidate {
+        timestamp: 42.5,
+        sharpness: 0.75,
+        contrast: 0.5,
+        text_likeness: 0.0625,
+        audio_energy: 0.25,
+        speech_activity: 0.125,
+        total_score: 0.625,
+        color: ColorInfo {
+            transfer: Some("smpte2084".to_string()),
+            primaries: Some("bt2020".to_string()),
+            matrix: Some("bt2020nc".to_string()),
+            range: Some("tv".to_string()),
+            pixel_format: Some("yuv420p10le".to_string()),
+        },
+    });
+
+    assert_golden("frame_quality", &FrameQuality {
+        timestamp: 42.5,
+        sharpness: 0.75,
+        contrast: 0.5,
+        face_count: 1,
+        score: 0.625,
+    });
+}
+
+#[test]
+fn test_fingerprint_schemas() {
+    assert_golden("match_result", &MatchResult {
+        is_match: true,
+        similarity: 0.75,
+        time_offset_frames: -12,
+        matching_pairs: 96,
+        total_pairs_checked: 128,
+        matched_duration_secs: 8.5,
+    });
+
+    assert_golden("verification_result", &VerificationResult {
+        verified: false,
+        computed_hash: "ab".repeat(32),
+        expected_hash: "cd".repeat(32),
+        similarity: 0.25,
+        matched_duration_secs: 2.0,
+    });
+
+    assert_golden("database_match", &DatabaseMatch {
+        content_id: "content-1".to_string(),
+        similarity: 0.5,
+        matching_pairs: 64,
+    });
+
+    assert_golden("window_fingerprint", &WindowFingerprint {
+        start_secs: 30.0,
+        end_secs: 60.0,
+        fingerprint: fingerprint(),
+    });
+}
+
+#[test]
+fn test_stream_schemas() {
+    assert_golden("analysis_events", &vec![
+        AnalysisEvent::DominantChange { old: 440.0, new: 880.0, timestamp: 1.5 },
+        AnalysisEvent::BeatDetected { timestamp: 2.0, strength: 1.5 },
+        AnalysisEvent::TempoChanged { bpm: 120.0, previous: None, timestamp: 4.0 },
+        AnalysisEvent::SpectralShift { timestamp: 8.0, magnitude: 0.5 },
+        AnalysisEvent::SilenceStart { timestamp: 10.0 },
+        AnalysisEvent::SilenceEnd { timestamp: 12.0, duration: 2.0 },
+        AnalysisEvent::SpeechStart { timestamp: 12.5 },
+        AnalysisEvent::SpeechEnd { timestamp: 15.0, duration: 2.5, confidence: 0.75 },
+        AnalysisEvent::FrameAnalyzed { timestamp: 1.5, frame: frame() },
+    ]);
+
+    assert_golden("stream_statistics", &StreamStatistics {
+        window_duration: 10.0,
+        avg_dominant_frequency: 440.0,
+        avg_spectral_centroid: 1250.0,
+        avg_rms_energy: 0.125,
+        rms_variance: 0.015625,
+        frequency_variance: 16.0,
+        avg_band_energies: band_energies(),
+        frame_count: 215,
+        estimated_bpm: Some(120.0),
+    });
+
+    assert_golden("vad_events", &vec![
+        VadEvent::SpeechStart { timestamp: 1.0 },
+        VadEvent::SpeechEnd { segment: SpeechSegment { start: 1.0, end: 3.5, confidence: 0.75 } },
+    ]);
+
+    assert_golden("progress_events", &vec![
+        ProgressEvent::Started { index: 0, label: "episode-1.mp4".to_string() },
+        ProgressEvent::StageCompleted { index: 0, stage: BatchStage::Extraction },
+        ProgressEvent::Finished { index: 0, content_id: "content-1".to_string() },
+        ProgressEvent::Failed { index: 1, error: "Audio is too short".to_string() },
+    ]);
+}
@@ -0,0 +1,120 @@
+//! Parity between the native and browser analyzers built on kino-spectrum.
+//!
+//! [`StreamAnalyzer`] and kino-wasm's `KinoStreamingAnalyzer` both wrap the
+//! kino-spectrum frame core, so identical samples must produce identical
+//! frames and statistics, whichever way each side chunks its input. Seek-bar
+//! overviews must serialize identically on both sides.
+
+use kino_frequency::streaming::{AnalysisFrame, StreamAnalyzer, StreamConfig};
+use kino_frequency::{generate_overview, AudioData, BandPlan, WindowFunction};
+use kino_wasm::{KinoOverviewGenerator, KinoStreamingAnalyzer};
+
+const SAMPLE_RATE: u32 = 22050;
+const FFT_SIZE: usize = 512;
+
+/// Render quantum of an AudioWorklet
+const QUANTUM: usize = 128;
+
+/// Two tones sweeping against each other, plus deterministic noise.
+fn signal(len: usize) -> Vec<f32> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let noise = (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5;
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let sweep = 300.0 + 2000.0 * t;
+            0.4 * (2.0 * std::f32::consts::PI * sweep * t).sin()
+                + 0.2 * (2.0 * std::f32::consts::PI * 3500.0 * t).sin()
+                + 0.05 * noise
+        })
+        .collect()
+}
+
+/// Frames from the native analyzer, fed in uneven chunks.
+fn native_frames(config: StreamConfig, samples: &[f32]) -> (StreamAnalyzer, Vec<AnalysisFrame>) {
+    let mut analyzer = StreamAnalyzer::with_config(config);
+    let frames = samples.chunks(1000).flat_map(|chunk| analyzer.process(chunk)).collect();
+    (analyzer, frames)
+}
+
+/// Run both analyzers over the same samples and compare every frame.
+fn assert_parity(window: &str, band_plan: BandPlan) {
+    let samples = signal(SAMPLE_RATE as usize * 2);
+
+    let mut browser = KinoStreamingAnalyzer::new(FFT_SIZE, SAMPLE_RATE);
+    browser.set_window(window).unwrap();
+    if !band_plan.is_standard() {
+        let bounds: Vec<f32> = band_plan.bands.iter().flat_map(|&(low, high)| [low, high]).collect();
+        browser.set_band_bounds(band_plan.name.clone(), &bounds).unwrap();
+    }
+    let mut browser_frames = Vec::new();
+    for quantum in samples.chunks(QUANTUM) {
+        if browser.process_samples(quantum) > 0 {
+            browser_frames.push(browser.get_latest_frame().unwrap());
+        }
+    }
+
+    let config = StreamConfig {
+        fft_size: FFT_SIZE,
+        hop_size: FFT_SIZE / 2,
+        window: WindowFunction::from_name(window).unwrap(),
+        band_plan,
+        sample_rate: SAMPLE_RATE,
+        vad: None,
+        ..Default::default()
+    };
+    let (native, native_frames) = native_frames(config, &samples);
+
+    assert_eq!(native_frames.len(), browser_frames.len());
+    assert!(native_frames.len() > 100);
+    for (native, browser) in native_frames.iter().zip(&browser_frames) {
+        assert_eq!(native.timestamp, browser.time());
+        assert_eq!(native.dominant_frequency, browser.dominant_frequency());
+        assert_eq!(native.spectral_centroid, browser.spectral_centroid());
+        assert_eq!(native.rms_energy, browser.rms_energy());
+        let bands = native.band_energies.to_vec();
+        for (band, &energy) in bands.iter().enumerate() {
+            assert_eq!(energy, browser.get_band_energy(band), "band {} at {}s", band, native.timestamp);
+        }
+        assert_eq!(browser.get_band_energy(bands.len()), 0.0);
+    }
+
+    let native_stats = native.get_statistics();
+    let browser_stats = browser.get_statistics();
+    assert_eq!(native_stats.frame_count, browser_stats.frame_count());
+    assert_eq!(native_stats.avg_dominant_frequency, browser_stats.avg_dominant_frequency());
+    assert_eq!(native_stats.avg_spectral_centroid, browser_stats.avg_spectral_centroid());
+    assert_eq!(native_stats.avg_rms_energy, browser_stats.avg_rms_energy());
+    assert_eq!(native_stats.rms_variance, browser_stats.rms_variance());
+    assert_eq!(native_stats.frequency_variance, browser_stats.frequency_variance());
+    for (band, &energy) in native_stats.avg_band_energies.to_vec().iter().enumerate() {
+        assert_eq!(energy, browser_stats.get_avg_band_energy(band));
+    }
+}
+
+#[test]
+fn test_standard_bands_match() {
+    assert_parity("hann", BandPlan::standard());
+}
+
+#[test]
+fn test_custom_window_and_bands_match() {
+    let plan = BandPlan::new("thirds", vec![(0.0, 1000.0), (1000.0, 3000.0), (3000.0, 11025.0)]).unwrap();
+    assert_parity("blackman-harris", plan);
+}
+
+#[test]
+fn test_telephone_plan_matches() {
+    assert_parity("hamming", BandPlan::telephone());
+}
+
+#[test]
+fn test_overview_json_matches() {
+    let samples = signal(SAMPLE_RATE as usize * 3);
+    let native = generate_overview(&AudioData::new(samples.clone(), SAMPLE_RATE), 300).unwrap();
+    let browser = KinoOverviewGenerator::new().generate_samples(&samples, SAMPLE_RATE, 300);
+    assert_eq!(serde_json::to_string(&native).unwrap(), browser.to_json());
+}
@@ -4,6 +4,7 @@
 //! - Audio frequency analysis
 //! - Fingerprint generation
 //! - Auto-tagging
+//! - Thumbnail selection
 //! - Recommendation similarity
 //!
 //! ## Installation
@@ -16,11 +17,15 @@
 //!
 //! ```python
 //! import numpy as np
-//! from kino_frequency import FrequencyAnalyzer, Fingerprinter, ContentTagger
+//! from kino_frequency import (
+//!     FrequencyAnalyzer, Fingerprinter, ContentTagger, TagTaxonomy,
+//!     ThumbnailSelector, RecommendationEngine,
+//! )
 //!
-//! # Load audio samples
-//! samples = np.array([...], dtype=np.float32)
+//! # Three seconds of an A4 tone
 //! sample_rate = 44100
+//! t = np.arange(3 * sample_rate) / sample_rate
+//! samples = (0.5 * np.sin(2 * np.pi * 440.0 * t)).astype(np.float32)
 //!
 //! # Analyze frequencies
 //! analyzer = FrequencyAnalyzer(sample_rate)
@@ -29,6 +34,10 @@
 //! print(f"Dominant frequency: {result.dominant_frequencies[0].frequency_hz} Hz")
 //! print(f"Spectral centroid: {result.spectral_centroid} Hz")
 //!
+//! # Isolate the speech band, or keep only the strongest tones
+//! speech = analyzer.bandpass(samples, sample_rate, 80.0, 1200.0)
+//! tones = analyzer.project_dominant(samples, sample_rate, 3)
+//!
 //! # Generate fingerprint
 //! fingerprinter = Fingerprinter()
 //! fingerprint = fingerprinter.fingerprint(samples, sample_rate)
@@ -39,10 +48,57 @@
 //! tags = tagger.predict(samples, sample_rate)
 //! for tag in tags:
 //!     print(f"{tag.label}: {tag.confidence:.2%}")
+//!
+//! # Localized tag names, and parent tags for filtering
+//! taxonomy = TagTaxonomy()
+//! tags = ContentTagger(taxonomy=taxonomy, locale="es").predict(samples, sample_rate)
+//! print([tag.name for tag in tags])
+//! is_music = taxonomy.matches(tags, "genre.music")
+//!
+//! # Index content and find similar items
+//! engine = RecommendationEngine()
+//! engine.add_content("a4", samples, sample_rate)
+//! engine.add_content("e5", (0.5 * np.sin(2 * np.pi * 659.3 * t)).astype(np.float32), sample_rate)
+//! for rec in engine.get_similar("a4", limit=5):
+//!     print(f"{rec.content_id}: {rec.similarity:.2f}")
+//!
+//! # Persist the index as bytes and restore it
+//! restored = RecommendationEngine()
+//! restored.import_index(engine.export_index())
+//! assert len(restored) == 2
+//!
+//! # Thumbnails need a video file and ffmpeg/ffprobe on PATH
+//! selector = ThumbnailSelector()
+//! try:
+//!     for candidate in selector.find_candidates("video.mp4", samples, sample_rate, num_results=3):
+//!         print(f"{candidate['timestamp']:.1f}s: {candidate['total_score']:.2f}")
+//! except FileNotFoundError:
+//!     print("video.mp4 not found")
+//! ```
+//!
+//! ## Input Arrays
+//!
+//! Methods taking `samples` accept 1-D or 2-D float32/float64 arrays, so
+//! soundfile and librosa output can be passed directly. 2-D arrays are
+//! averaged to mono across the shorter axis. Only contiguous 1-D float32
+//! arrays avoid a copy; other inputs are converted first.
+//!
+//! ```python
+//! stereo = np.stack([samples, samples], axis=1)  # (frames, channels)
+//! analyzer.analyze(stereo)
+//! analyzer.analyze(samples.astype(np.float64)[::2])
+//!
+//! results = analyzer.analyze_batch([samples, stereo])  # parallel, GIL released
 //! ```
 
-use numpy::{PyArray1, PyReadonlyArray1};
+use std::path::{Path, PathBuf};
+
+use numpy::ndarray::{ArrayView2, Axis};
+use numpy::{PyArray1, PyReadonlyArray1, PyReadonlyArray2};
+use pyo3::exceptions::{PyFileNotFoundError, PyKeyError, PyRuntimeError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use rayon::prelude::*;
 
 // ============================================================================
 // Data Types
@@ -60,42 +116,119 @@ pub struct DominantFrequency {
     pub rank: usize,
 }
 
+/// Named set of frequency bands used for band energies
+#[pyclass]
+#[derive(Clone)]
+pub struct BandPlan {
+    inner: ::kino_frequency::types::BandPlan,
+}
+
+#[pymethods]
+impl BandPlan {
+    /// Create a plan from (low_hz, high_hz) ranges
+    #[new]
+    pub fn new(name: String, bands: Vec<(f32, f32)>) -> PyResult<Self> {
+        ::kino_frequency::types::BandPlan::new(name, bands)
+            .map(|inner| Self { inner })
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    /// Six-band plan from sub-bass to high
+    #[staticmethod]
+    pub fn standard() -> Self {
+        Self { inner: ::kino_frequency::types::BandPlan::standard() }
+    }
+
+    /// Speech-centric plan splitting the 300-3400 Hz telephone band
+    #[staticmethod]
+    pub fn telephone() -> Self {
+        Self { inner: ::kino_frequency::types::BandPlan::telephone() }
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        self.inner.name.clone()
+    }
+
+    #[getter]
+    fn bands(&self) -> Vec<(f32, f32)> {
+        self.inner.bands.clone()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("BandPlan(name={:?}, bands={:?})", self.inner.name, self.inner.bands)
+    }
+}
+
 /// Band energy distribution
+///
+/// The named band getters (sub_bass .. high) apply to the standard plan and
+/// return 0.0 for other plans; use `energies` for those.
 #[pyclass]
 #[derive(Clone)]
 pub struct BandEnergies {
-    #[pyo3(get)]
-    pub sub_bass: f32,
-    #[pyo3(get)]
-    pub bass: f32,
-    #[pyo3(get)]
-    pub low_mid: f32,
-    #[pyo3(get)]
-    pub mid: f32,
-    #[pyo3(get)]
-    pub high_mid: f32,
-    #[pyo3(get)]
-    pub high: f32,
+    inner: ::kino_frequency::types::BandEnergies,
 }
 
 #[pymethods]
 impl BandEnergies {
+    #[getter]
+    fn sub_bass(&self) -> f32 {
+        self.inner.sub_bass()
+    }
+
+    #[getter]
+    fn bass(&self) -> f32 {
+        self.inner.bass()
+    }
+
+    #[getter]
+    fn low_mid(&self) -> f32 {
+        self.inner.low_mid()
+    }
+
+    #[getter]
+    fn mid(&self) -> f32 {
+        self.inner.mid()
+    }
+
+    #[getter]
+    fn high_mid(&self) -> f32 {
+        self.inner.high_mid()
+    }
+
+    #[getter]
+    fn high(&self) -> f32 {
+        self.inner.high()
+    }
+
+    #[getter]
+    fn plan(&self) -> BandPlan {
+        BandPlan { inner: self.inner.plan.clone() }
+    }
+
+    #[getter]
+    fn energies(&self) -> Vec<f32> {
+        self.inner.energies.clone()
+    }
+
     fn to_list(&self) -> Vec<f32> {
-        vec![
-            self.sub_bass,
-            self.bass,
-            self.low_mid,
-            self.mid,
-            self.high_mid,
-            self.high,
-        ]
+        self.inner.to_vec()
     }
 
     fn __repr__(&self) -> String {
-        format!(
-            "BandEnergies(sub_bass={:.3}, bass={:.3}, low_mid={:.3}, mid={:.3}, high_mid={:.3}, high={:.3})",
-            self.sub_bass, self.bass, self.low_mid, self.mid, self.high_mid, self.high
-        )
+        if self.inner.plan.is_standard() {
+            format!(
+                "BandEnergies(sub_bass={:.3}, bass={:.3}, low_mid={:.3}, mid={:.3}, high_mid={:.3}, high={:.3})",
+                self.sub_bass(), self.bass(), self.low_mid(), self.mid(), self.high_mid(), self.high()
+            )
+        } else {
+            format!("BandEnergies(plan={:?}, energies={:?})", self.inner.plan.name, self.inner.energies)
+        }
     }
 }
 
@@ -111,8 +244,14 @@ pub struct AnalysisResult {
     #[pyo3(get)]
     pub spectral_flatness: f32,
     #[pyo3(get)]
+    pub spectral_bandwidth: f32,
+    #[pyo3(get)]
+    pub spectral_contrast: Vec<f32>,
+    #[pyo3(get)]
     pub zero_crossing_rate: f32,
     #[pyo3(get)]
+    pub crest_factor: f32,
+    #[pyo3(get)]
     pub band_energies: BandEnergies,
 }
 
@@ -143,6 +282,10 @@ impl Fingerprint {
 }
 
 /// Content tag
+///
+/// `id` is the tag's taxonomy id (e.g. `"genre.music"`), or None when the
+/// label isn't in the taxonomy; `name` is its display name, localized when
+/// the tagger was given a locale.
 #[pyclass]
 #[derive(Clone)]
 pub struct ContentTag {
@@ -150,12 +293,57 @@ pub struct ContentTag {
     pub label: String,
     #[pyo3(get)]
     pub confidence: f32,
+    #[pyo3(get)]
+    pub id: Option<String>,
+    #[pyo3(get)]
+    pub name: String,
 }
 
 #[pymethods]
 impl ContentTag {
     fn __repr__(&self) -> String {
-        format!("ContentTag('{}', confidence={:.2})", self.label, self.confidence)
+        match &self.id {
+            Some(id) => format!("ContentTag('{}', id='{}', confidence={:.2})", self.label, id, self.confidence),
+            None => format!("ContentTag('{}', confidence={:.2})", self.label, self.confidence),
+        }
+    }
+}
+
+impl ContentTag {
+    fn from_tag(tag: &::kino_frequency::types::ContentTag, taxonomy: &::kino_frequency::tagging::TagTaxonomy, locale: Option<&str>) -> Self {
+        Self {
+            label: tag.label.clone(),
+            confidence: tag.confidence,
+            id: tag.id.clone(),
+            name: taxonomy.tag_name(tag, locale).to_string(),
+        }
+    }
+
+    fn to_tag(&self) -> ::kino_frequency::types::ContentTag {
+        ::kino_frequency::types::ContentTag {
+            label: self.label.clone(),
+            confidence: self.confidence,
+            id: self.id.clone(),
+        }
+    }
+}
+
+/// Recommended content item
+#[pyclass]
+#[derive(Clone)]
+pub struct Recommendation {
+    #[pyo3(get)]
+    pub content_id: String,
+    #[pyo3(get)]
+    pub similarity: f32,
+    #[pyo3(get)]
+    pub matching_features: Vec<String>,
+}
+
+#[pymethods]
+impl Recommendation {
+    fn __repr__(&self) -> String {
+        format!("Recommendation('{}', similarity={:.3})", self.content_id, self.similarity)
     }
 }
 
@@ -177,10 +365,22 @@ impl FrequencySignature {
         PyArray1::from_slice_bound(py, &self.features)
     }
 
+    /// Number of features in the signature
+    #[getter]
+    fn dimensions(&self) -> usize {
+        self.features.len()
+    }
+
     /// Compute similarity with another signature
-    fn similarity(&self, other: &FrequencySignature) -> f32 {
+    ///
+    /// Raises ValueError if the signatures have different dimensions.
+    fn similarity(&self, other: &FrequencySignature) -> PyResult<f32> {
         if self.features.len() != other.features.len() {
-            return 0.0;
+            let err = ::kino_frequency::types::SignatureError::DimensionMismatch {
+                left: self.features.len(),
+                right: other.features.len(),
+            };
+            return Err(pyo3::exceptions::PyValueError::new_err(err.to_string()));
         }
 
         let dot: f32 = self.features.iter()
@@ -192,13 +392,86 @@ impl FrequencySignature {
         let norm_b: f32 = other.features.iter().map(|x| x * x).sum::<f32>().sqrt();
 
         if norm_a == 0.0 || norm_b == 0.0 {
-            return 0.0;
+            return Ok(0.0);
+        }
+
+        Ok(dot / (norm_a * norm_b))
+    }
+}
+
+// ============================================================================
+// Input Conversion
+// ============================================================================
+
+/// Audio samples passed from Python
+///
+/// Contiguous 1-D float32 arrays are read in place. Anything else (float64,
+/// strided slices, 2-D arrays) is converted into a temporary float32 copy,
+/// which costs one extra pass and allocation per call.
+pub enum Samples<'py> {
+    Borrowed(PyReadonlyArray1<'py, f32>),
+    Owned(Vec<f32>),
+}
+
+impl Samples<'_> {
+    fn as_slice(&self) -> &[f32] {
+        match self {
+            Samples::Borrowed(array) => array.as_slice().expect("borrowed arrays are contiguous"),
+            Samples::Owned(samples) => samples,
+        }
+    }
+
+    fn into_vec(self) -> Vec<f32> {
+        match self {
+            Samples::Borrowed(array) => array.as_array().to_vec(),
+            Samples::Owned(samples) => samples,
+        }
+    }
+}
+
+impl<'py> FromPyObject<'py> for Samples<'py> {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(array) = ob.extract::<PyReadonlyArray1<'py, f32>>() {
+            if array.as_slice().is_ok() {
+                return Ok(Samples::Borrowed(array));
+            }
+            return Ok(Samples::Owned(array.as_array().to_vec()));
+        }
+        if let Ok(array) = ob.extract::<PyReadonlyArray1<'py, f64>>() {
+            return Ok(Samples::Owned(array.as_array().iter().map(|&s| s as f32).collect()));
+        }
+        if let Ok(array) = ob.extract::<PyReadonlyArray2<'py, f32>>() {
+            return Ok(Samples::Owned(downmix(array.as_array())));
+        }
+        if let Ok(array) = ob.extract::<PyReadonlyArray2<'py, f64>>() {
+            return Ok(Samples::Owned(downmix(array.as_array())));
         }
 
-        dot / (norm_a * norm_b)
+        let found = match (ob.getattr("ndim"), ob.getattr("dtype")) {
+            (Ok(ndim), Ok(dtype)) => format!("a {}-D {} array", ndim, dtype),
+            _ => ob.get_type().name()?.to_string(),
+        };
+        Err(PyTypeError::new_err(format!(
+            "samples must be a 1-D or 2-D float32/float64 numpy array, got {}",
+            found,
+        )))
     }
 }
 
+/// Average a 2-D array to mono across its shorter axis
+///
+/// Covers both soundfile's (frames, channels) and librosa's
+/// (channels, frames) layouts.
+fn downmix<T: Copy + Into<f64>>(array: ArrayView2<'_, T>) -> Vec<f32> {
+    if array.is_empty() {
+        return Vec::new();
+    }
+    let frames = if array.nrows() >= array.ncols() { Axis(0) } else { Axis(1) };
+    array.axis_iter(frames)
+        .map(|frame| (frame.iter().map(|&s| s.into()).sum::<f64>() / frame.len() as f64) as f32)
+        .collect()
+}
+
 // ============================================================================
 // Main Classes
 // ============================================================================
@@ -208,80 +481,63 @@ impl FrequencySignature {
 pub struct FrequencyAnalyzer {
     sample_rate: u32,
     fft_size: usize,
-    _hop_size: usize,
+    hop_size: usize,
+    window: ::kino_frequency::types::WindowFunction,
+    band_plan: ::kino_frequency::types::BandPlan,
 }
 
 #[pymethods]
 impl FrequencyAnalyzer {
     /// Create a new frequency analyzer
+    ///
+    /// `window` is one of "hann", "hamming", "blackman_harris", "flat_top"
+    /// or "rectangular".
     #[new]
-    #[pyo3(signature = (sample_rate, fft_size=4096, hop_size=2048))]
-    pub fn new(sample_rate: u32, fft_size: usize, hop_size: usize) -> Self {
-        Self {
+    #[pyo3(signature = (sample_rate, fft_size=4096, hop_size=2048, band_plan=None, window="hann"))]
+    pub fn new(
+        sample_rate: u32,
+        fft_size: usize,
+        hop_size: usize,
+        band_plan: Option<BandPlan>,
+        window: &str,
+    ) -> PyResult<Self> {
+        let window = ::kino_frequency::types::WindowFunction::from_name(window).ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(format!("Unknown window function: {}", window))
+        })?;
+
+        Ok(Self {
             sample_rate,
             fft_size,
-            _hop_size: hop_size,
-        }
+            hop_size,
+            window,
+            band_plan: band_plan.map(|p| p.inner).unwrap_or_default(),
+        })
     }
 
     /// Analyze audio samples
-    pub fn analyze(&self, samples: PyReadonlyArray1<f32>) -> PyResult<AnalysisResult> {
-        let samples_slice = samples.as_slice()?;
-
-        if samples_slice.len() < self.fft_size {
-            return Err(pyo3::exceptions::PyValueError::new_err(
-                format!("Need at least {} samples, got {}", self.fft_size, samples_slice.len())
-            ));
-        }
-
-        // Compute spectrum using simple DFT (in production, use proper FFT)
-        let spectrum = self.compute_spectrum(samples_slice);
-        let freq_resolution = self.sample_rate as f32 / self.fft_size as f32;
-
-        // Find dominant frequencies
-        let mut indexed: Vec<(usize, f32)> = spectrum.iter()
-            .enumerate()
-            .map(|(i, &m)| (i, m))
-            .collect();
-        indexed.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-
-        let max_mag = indexed.first().map(|(_, m)| *m).unwrap_or(1.0);
-        let dominant_frequencies: Vec<DominantFrequency> = indexed.iter()
-            .take(10)
-            .enumerate()
-            .map(|(rank, (idx, mag))| DominantFrequency {
-                frequency_hz: *idx as f32 * freq_resolution,
-                magnitude: mag / max_mag,
-                rank: rank + 1,
-            })
-            .collect();
-
-        // Compute spectral features
-        let frequencies: Vec<f32> = (0..spectrum.len())
-            .map(|i| i as f32 * freq_resolution)
-            .collect();
-
-        let spectral_centroid = self.compute_centroid(&spectrum, &frequencies);
-        let spectral_rolloff = self.compute_rolloff(&spectrum, &frequencies, 0.95);
-        let spectral_flatness = self.compute_flatness(&spectrum);
-        let zero_crossing_rate = self.compute_zcr(samples_slice);
-        let band_energies = self.compute_band_energies(&spectrum, &frequencies);
+    ///
+    /// Accepts 1-D or 2-D float32/float64 arrays; see `Samples` for how
+    /// they are converted.
+    pub fn analyze(&self, samples: Samples<'_>) -> PyResult<AnalysisResult> {
+        self.analyze_slice(samples.as_slice())
+    }
 
-        Ok(AnalysisResult {
-            dominant_frequencies,
-            spectral_centroid,
-            spectral_rolloff,
-            spectral_flatness,
-            zero_crossing_rate,
-            band_energies,
-        })
+    /// Analyze several clips in parallel
+    ///
+    /// The clips are copied while holding the GIL, then analyzed on the
+    /// rayon thread pool with the GIL released. Raises ValueError if any
+    /// clip is too short.
+    pub fn analyze_batch(&self, py: Python<'_>, batch: Vec<Samples<'_>>) -> PyResult<Vec<AnalysisResult>> {
+        // Owned copies: Python code may write to the arrays once the GIL is released
+        let batch: Vec<Vec<f32>> = batch.into_iter().map(Samples::into_vec).collect();
+        py.allow_threads(|| batch.par_iter().map(|samples| self.analyze_slice(samples)).collect())
     }
 
     /// Get dominant frequencies
     #[pyo3(signature = (samples, top_k=10))]
     pub fn dominant_frequencies(
         &self,
-        samples: PyReadonlyArray1<f32>,
+        samples: Samples<'_>,
         top_k: usize,
     ) -> PyResult<Vec<DominantFrequency>> {
         let result = self.analyze(samples)?;
@@ -289,8 +545,8 @@ impl FrequencyAnalyzer {
     }
 
     /// Compute frequency signature
-    pub fn compute_signature(&self, samples: PyReadonlyArray1<f32>) -> PyResult<FrequencySignature> {
-        let samples_slice = samples.as_slice()?;
+    pub fn compute_signature(&self, samples: Samples<'_>) -> PyResult<FrequencySignature> {
+        let samples_slice = samples.as_slice();
         let spectrum = self.compute_spectrum(samples_slice);
         let freq_resolution = self.sample_rate as f32 / self.fft_size as f32;
 
@@ -346,25 +602,137 @@ impl FrequencyAnalyzer {
             flatness: self.compute_flatness(&spectrum),
         })
     }
+
+    /// Keep only the frequencies between `low` and `high` Hz
+    ///
+    /// Returns a float32 array as long as the (mono) input. Long signals
+    /// are filtered in blocks with the GIL released.
+    pub fn bandpass<'py>(
+        &self,
+        py: Python<'py>,
+        samples: Samples<'_>,
+        sample_rate: u32,
+        low: f32,
+        high: f32,
+    ) -> PyResult<Bound<'py, PyArray1<f32>>> {
+        if !(low >= 0.0 && low < high) {
+            return Err(PyValueError::new_err(format!(
+                "Invalid band {}-{} Hz: low must be non-negative and below high",
+                low, high
+            )));
+        }
+        let audio = audio_data(samples, sample_rate)?;
+        let analyzer = self.core_analyzer();
+        let filtered = py
+            .allow_threads(|| analyzer.bandpass_filter(&audio.samples, sample_rate, low, high))
+            .map_err(|e| PyValueError::new_err(format!("{:#}", e)))?;
+        Ok(PyArray1::from_vec_bound(py, filtered))
+    }
+
+    /// Keep only the `k` dominant frequencies
+    ///
+    /// Returns a float32 array as long as the (mono) input. Raises
+    /// ValueError if the clip is shorter than one FFT window.
+    pub fn project_dominant<'py>(
+        &self,
+        py: Python<'py>,
+        samples: Samples<'_>,
+        sample_rate: u32,
+        k: usize,
+    ) -> PyResult<Bound<'py, PyArray1<f32>>> {
+        if k == 0 {
+            return Err(PyValueError::new_err("k must be at least 1"));
+        }
+        let audio = audio_data(samples, sample_rate)?;
+        let analyzer = self.core_analyzer();
+        let projected = py
+            .allow_threads(|| analyzer.project_to_dominant(&audio.samples, sample_rate, k))
+            .map_err(|e| PyValueError::new_err(format!("{:#}", e)))?;
+        Ok(PyArray1::from_vec_bound(py, projected))
+    }
 }
 
 // Private helper methods (not exposed to Python)
 impl FrequencyAnalyzer {
+    /// The core analyzer with this analyzer's FFT settings
+    fn core_analyzer(&self) -> ::kino_frequency::fft::FrequencyAnalyzer {
+        ::kino_frequency::fft::FrequencyAnalyzer::with_window(self.fft_size, self.hop_size, self.window)
+    }
+
+    fn analyze_slice(&self, samples_slice: &[f32]) -> PyResult<AnalysisResult> {
+
+        if samples_slice.len() < self.fft_size {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                format!("Need at least {} samples, got {}", self.fft_size, samples_slice.len())
+            ));
+        }
+
+        // Compute spectrum using simple DFT (in production, use proper FFT)
+        let spectrum = self.compute_spectrum(samples_slice);
+        let freq_resolution = self.sample_rate as f32 / self.fft_size as f32;
+
+        // Find dominant frequencies
+        let mut indexed: Vec<(usize, f32)> = spectrum.iter()
+            .enumerate()
+            .map(|(i, &m)| (i, m))
+            .collect();
+        indexed.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let max_mag = indexed.first().map(|(_, m)| *m).unwrap_or(1.0);
+        let dominant_frequencies: Vec<DominantFrequency> = indexed.iter()
+            .take(10)
+            .enumerate()
+            .map(|(rank, (idx, mag))| DominantFrequency {
+                frequency_hz: *idx as f32 * freq_resolution,
+                magnitude: mag / max_mag,
+                rank: rank + 1,
+            })
+            .collect();
+
+        // Compute spectral features
+        let frequencies: Vec<f32> = (0..spectrum.len())
+            .map(|i| i as f32 * freq_resolution)
+            .collect();
+
+        let spectral_centroid = self.compute_centroid(&spectrum, &frequencies);
+        let spectral_rolloff = self.compute_rolloff(&spectrum, &frequencies, 0.95);
+        let spectral_flatness = self.compute_flatness(&spectrum);
+        let spectral_bandwidth = self.compute_bandwidth(&spectrum, &frequencies, spectral_centroid);
+        let spectral_contrast = self.compute_contrast(&spectrum, &frequencies);
+        let zero_crossing_rate = self.compute_zcr(samples_slice);
+        let crest_factor = self.compute_crest_factor(samples_slice);
+        let band_energies = self.compute_band_energies(&spectrum, &frequencies);
+
+        Ok(AnalysisResult {
+            dominant_frequencies,
+            spectral_centroid,
+            spectral_rolloff,
+            spectral_flatness,
+            spectral_bandwidth,
+            spectral_contrast,
+            zero_crossing_rate,
+            crest_factor,
+            band_energies,
+        })
+    }
+
     fn compute_spectrum(&self, samples: &[f32]) -> Vec<f32> {
         let n = self.fft_size.min(samples.len());
 
         // Simple DFT (in production, use rustfft)
         let mut spectrum = vec![0.0f32; n / 2];
 
+        // Window scaled to Hann's level so magnitudes match across windows
+        let correction = self.window.amplitude_correction();
+        let window: Vec<f32> = self.window.generate(n).into_iter().map(|w| w * correction).collect();
+
         for k in 0..n / 2 {
             let mut real = 0.0f32;
             let mut imag = 0.0f32;
 
             for (i, &sample) in samples.iter().take(n).enumerate() {
                 let angle = 2.0 * std::f32::consts::PI * k as f32 * i as f32 / n as f32;
-                // Apply Hann window
-                let window = 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / (n - 1) as f32).cos());
-                let windowed = sample * window;
+                let windowed = sample * window[i];
                 real += windowed * angle.cos();
                 imag -= windowed * angle.sin();
             }
@@ -408,6 +776,47 @@ impl FrequencyAnalyzer {
         if arithmetic_mean > 0.0 { geometric_mean / arithmetic_mean } else { 0.0 }
     }
 
+    fn compute_bandwidth(&self, spectrum: &[f32], frequencies: &[f32], centroid: f32) -> f32 {
+        let total: f32 = spectrum.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let variance: f32 = spectrum.iter().zip(frequencies.iter())
+            .map(|(&m, &f)| m * (f - centroid).powi(2)).sum::<f32>() / total;
+        variance.sqrt()
+    }
+
+    fn compute_contrast(&self, spectrum: &[f32], frequencies: &[f32]) -> Vec<f32> {
+        // Octave bands from 200 Hz; the last band extends to Nyquist
+        let edges = [0.0f32, 200.0, 400.0, 800.0, 1600.0, 3200.0, 6400.0];
+
+        (0..edges.len())
+            .map(|band| {
+                let low = edges[band];
+                let high = edges.get(band + 1).copied().unwrap_or(f32::INFINITY);
+                let mut mags: Vec<f32> = spectrum.iter().zip(frequencies.iter())
+                    .filter(|&(_, &f)| f > low && f < high)
+                    .map(|(&m, _)| m)
+                    .collect();
+                if mags.is_empty() {
+                    return 0.0;
+                }
+                mags.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+                let k = ((mags.len() as f32 * 0.02).round() as usize).max(1);
+                let valley = mags[..k].iter().sum::<f32>() / k as f32;
+                let peak = mags[mags.len() - k..].iter().sum::<f32>() / k as f32;
+                20.0 * ((peak + 1e-10) / (valley + 1e-10)).log10()
+            })
+            .collect()
+    }
+
+    fn compute_crest_factor(&self, samples: &[f32]) -> f32 {
+        let peak = samples.iter().fold(0.0f32, |max, &s| max.max(s.abs()));
+        let rms = (samples.iter().map(|&s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        if rms > 0.0 { peak / rms } else { 0.0 }
+    }
+
     fn compute_zcr(&self, samples: &[f32]) -> f32 {
         let crossings: usize = samples.windows(2)
             .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
@@ -416,35 +825,8 @@ impl FrequencyAnalyzer {
     }
 
     fn compute_band_energies(&self, spectrum: &[f32], frequencies: &[f32]) -> BandEnergies {
-        let bands = [
-            (20.0, 60.0), (60.0, 250.0), (250.0, 500.0),
-            (500.0, 2000.0), (2000.0, 4000.0), (4000.0, 20000.0),
-        ];
-
-        let mut energies = [0.0f32; 6];
-
-        for (i, (low, high)) in bands.iter().enumerate() {
-            for (j, &freq) in frequencies.iter().enumerate() {
-                if freq >= *low && freq < *high {
-                    energies[i] += spectrum[j];
-                }
-            }
-        }
-
-        let total: f32 = energies.iter().sum();
-        if total > 0.0 {
-            for e in &mut energies {
-                *e /= total;
-            }
-        }
-
         BandEnergies {
-            sub_bass: energies[0],
-            bass: energies[1],
-            low_mid: energies[2],
-            mid: energies[3],
-            high_mid: energies[4],
-            high: energies[5],
+            inner: ::kino_frequency::types::BandEnergies::from_spectrum_with_plan(spectrum, frequencies, &self.band_plan),
         }
     }
 }
@@ -467,10 +849,10 @@ impl Fingerprinter {
     /// Generate fingerprint from audio samples
     pub fn fingerprint(
         &self,
-        samples: PyReadonlyArray1<f32>,
+        samples: Samples<'_>,
         sample_rate: u32,
     ) -> PyResult<Fingerprint> {
-        let samples_slice = samples.as_slice()?;
+        let samples_slice = samples.as_slice();
 
         if samples_slice.len() < self.fft_size {
             return Err(pyo3::exceptions::PyValueError::new_err("Not enough samples"));
@@ -509,7 +891,7 @@ impl Fingerprinter {
     /// Verify audio against a known hash
     pub fn verify(
         &self,
-        samples: PyReadonlyArray1<f32>,
+        samples: Samples<'_>,
         sample_rate: u32,
         expected_hash: &str,
     ) -> PyResult<bool> {
@@ -518,27 +900,101 @@ impl Fingerprinter {
     }
 }
 
+/// Tag taxonomy: hierarchical tag ids with localized display names
+///
+/// Without a path the built-in taxonomy is used. Raises FileNotFoundError
+/// for a missing file and ValueError for an invalid one.
+#[pyclass]
+#[derive(Clone)]
+pub struct TagTaxonomy {
+    inner: ::kino_frequency::tagging::TagTaxonomy,
+}
+
+#[pymethods]
+impl TagTaxonomy {
+    #[new]
+    #[pyo3(signature = (path=None))]
+    pub fn new(path: Option<PathBuf>) -> PyResult<Self> {
+        let inner = match path {
+            Some(path) if !path.is_file() => {
+                return Err(PyFileNotFoundError::new_err(format!("Taxonomy not found: {}", path.display())));
+            }
+            Some(path) => ::kino_frequency::tagging::TagTaxonomy::load(&path)
+                .map_err(|e| PyValueError::new_err(format!("{:#}", e)))?,
+            None => ::kino_frequency::tagging::TagTaxonomy::builtin(),
+        };
+        Ok(Self { inner })
+    }
+
+    /// Taxonomy id for a label or id, or None
+    pub fn resolve(&self, label: &str) -> Option<String> {
+        self.inner.resolve(label).map(str::to_string)
+    }
+
+    /// Map a legacy label to a taxonomy id
+    pub fn add_alias(&mut self, label: &str, id: &str) -> PyResult<()> {
+        self.inner.add_alias(label, id).map_err(|e| PyValueError::new_err(format!("{:#}", e)))
+    }
+
+    /// Display name of an id in `locale`, or None for unknown ids
+    #[pyo3(signature = (id, locale=None))]
+    pub fn display_name(&self, id: &str, locale: Option<&str>) -> Option<String> {
+        self.inner.display_name(id, locale).map(str::to_string)
+    }
+
+    /// Ids of the ancestors of `id`, parent first
+    pub fn ancestors(&self, id: &str) -> Vec<String> {
+        self.inner.ancestors(id).map(|e| e.id.clone()).collect()
+    }
+
+    /// Whether `id` is `ancestor` or one of its descendants
+    pub fn is_a(&self, id: &str, ancestor: &str) -> bool {
+        self.inner.is_a(id, ancestor)
+    }
+
+    /// Tags plus their ancestors, so a tag "electronic" also matches "genre.music"
+    #[pyo3(signature = (tags, locale=None))]
+    pub fn expand_ancestors(&self, tags: Vec<ContentTag>, locale: Option<&str>) -> Vec<ContentTag> {
+        let tags: Vec<_> = tags.iter().map(ContentTag::to_tag).collect();
+        self.inner.expand_ancestors(&tags).iter()
+            .map(|tag| ContentTag::from_tag(tag, &self.inner, locale))
+            .collect()
+    }
+
+    /// Whether any of `tags` falls under `id`
+    pub fn matches(&self, tags: Vec<ContentTag>, id: &str) -> bool {
+        let tags: Vec<_> = tags.iter().map(ContentTag::to_tag).collect();
+        self.inner.matches(&tags, id)
+    }
+}
+
 /// Content tagger
+///
+/// Tags get ids from `taxonomy` (the built-in one by default) and display
+/// names in `locale`.
 #[pyclass]
 pub struct ContentTagger {
     min_confidence: f32,
+    taxonomy: ::kino_frequency::tagging::TagTaxonomy,
+    locale: Option<String>,
 }
 
 #[pymethods]
 impl ContentTagger {
     #[new]
-    #[pyo3(signature = (min_confidence=0.3))]
-    pub fn new(min_confidence: f32) -> Self {
-        Self { min_confidence }
+    #[pyo3(signature = (min_confidence=0.3, taxonomy=None, locale=None))]
+    pub fn new(min_confidence: f32, taxonomy: Option<TagTaxonomy>, locale: Option<String>) -> Self {
+        let taxonomy = taxonomy.map_or_else(::kino_frequency::tagging::TagTaxonomy::builtin, |t| t.inner);
+        Self { min_confidence, taxonomy, locale }
     }
 
     /// Predict content tags from audio
     pub fn predict(
         &self,
-        samples: PyReadonlyArray1<f32>,
+        samples: Samples<'_>,
         _sample_rate: u32,
     ) -> PyResult<Vec<ContentTag>> {
-        let samples_slice = samples.as_slice()?;
+        let samples_slice = samples.as_slice();
 
         // Simplified rule-based tagging
         let mut tags = Vec::new();
@@ -555,22 +1011,221 @@ impl ContentTagger {
         let zcr_rate = zcr as f32 / n as f32;
 
         if zcr_rate < 0.05 {
-            tags.push(ContentTag { label: "music".to_string(), confidence: 0.7 });
+            tags.push(::kino_frequency::types::ContentTag::new("music", 0.7));
         } else if zcr_rate < 0.1 {
-            tags.push(ContentTag { label: "speech".to_string(), confidence: 0.65 });
+            tags.push(::kino_frequency::types::ContentTag::new("speech", 0.65));
         }
 
         if energy > 0.1 {
-            tags.push(ContentTag { label: "energetic".to_string(), confidence: 0.6 });
+            tags.push(::kino_frequency::types::ContentTag::new("energetic", 0.6));
         } else if energy < 0.01 {
-            tags.push(ContentTag { label: "ambient".to_string(), confidence: 0.5 });
+            tags.push(::kino_frequency::types::ContentTag::new("ambient", 0.5));
         }
 
         // Filter by confidence
         tags.retain(|t| t.confidence >= self.min_confidence);
         tags.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
+        self.taxonomy.annotate(&mut tags);
+
+        Ok(tags.iter()
+            .map(|tag| ContentTag::from_tag(tag, &self.taxonomy, self.locale.as_deref()))
+            .collect())
+    }
+}
+
+/// Thumbnail selector scoring video frames by sharpness, contrast and
+/// audio energy
+///
+/// Frames are extracted with ffmpeg, which must be on PATH.
+#[pyclass]
+pub struct ThumbnailSelector {
+    inner: ::kino_frequency::thumbnail::ThumbnailSelector,
+}
+
+#[pymethods]
+impl ThumbnailSelector {
+    #[new]
+    #[pyo3(signature = (num_candidates=30, skip_start_secs=2.0, skip_end_secs=2.0, min_sharpness=0.3))]
+    pub fn new(num_candidates: usize, skip_start_secs: f64, skip_end_secs: f64, min_sharpness: f32) -> PyResult<Self> {
+        if num_candidates == 0 {
+            return Err(PyValueError::new_err("num_candidates must be at least 1"));
+        }
+
+        let config = ::kino_frequency::thumbnail::ThumbnailConfig {
+            num_candidates,
+            skip_start_secs,
+            skip_end_secs,
+            min_sharpness,
+            ..Default::default()
+        };
+        Ok(Self { inner: ::kino_frequency::thumbnail::ThumbnailSelector::with_config(config) })
+    }
+
+    /// Timestamp in seconds of the best thumbnail frame
+    ///
+    /// Raises FileNotFoundError if the video does not exist and ValueError
+    /// for empty audio or a zero sample rate.
+    pub fn find_best_timestamp(
+        &self,
+        py: Python<'_>,
+        video_path: PathBuf,
+        samples: Samples<'_>,
+        sample_rate: u32,
+    ) -> PyResult<f64> {
+        check_video_path(&video_path)?;
+        let audio = audio_data(samples, sample_rate)?;
+
+        py.allow_threads(|| self.inner.find_best_timestamp(&video_path, &audio))
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#}", e)))
+    }
+
+    /// Best-scoring, spread-out thumbnail candidates
+    ///
+    /// Each candidate is a dict with `timestamp`, `sharpness`, `contrast`,
+    /// `text_likeness` (on-screen text such as credits, which is penalized),
+    /// `audio_energy`, `total_score` and `hdr` (whether the source is PQ or
+    /// HLG and was tone-mapped for scoring), best first.
+    #[pyo3(signature = (video_path, samples, sample_rate, num_results=5))]
+    pub fn find_candidates<'py>(
+        &self,
+        py: Python<'py>,
+        video_path: PathBuf,
+        samples: Samples<'_>,
+        sample_rate: u32,
+        num_results: usize,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        check_video_path(&video_path)?;
+        if num_results == 0 {
+            return Err(PyValueError::new_err("num_results must be at least 1"));
+        }
+        let audio = audio_data(samples, sample_rate)?;
+
+        let candidates = py.allow_threads(|| self.inner.find_candidates(&video_path, &audio, num_results))
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#}", e)))?;
+
+        candidates.into_iter()
+            .map(|candidate| {
+                let dict = PyDict::new_bound(py);
+                dict.set_item("timestamp", candidate.timestamp)?;
+                dict.set_item("sharpness", candidate.sharpness)?;
+                dict.set_item("contrast", candidate.contrast)?;
+                dict.set_item("text_likeness", candidate.text_likeness)?;
+                dict.set_item("audio_energy", candidate.audio_energy)?;
+                dict.set_item("total_score", candidate.total_score)?;
+                dict.set_item("hdr", candidate.color.is_hdr())?;
+                Ok(dict)
+            })
+            .collect()
+    }
+}
+
+/// Content recommendation index over frequency signatures
+#[pyclass]
+pub struct RecommendationEngine {
+    inner: ::kino_frequency::recommend::RecommendationEngine,
+}
+
+#[pymethods]
+impl RecommendationEngine {
+    #[new]
+    #[pyo3(signature = (min_similarity=0.3))]
+    pub fn new(min_similarity: f32) -> Self {
+        let config = ::kino_frequency::recommend::RecommendConfig {
+            min_similarity,
+            ..Default::default()
+        };
+        Self { inner: ::kino_frequency::recommend::RecommendationEngine::with_config(config) }
+    }
+
+    /// Index audio under `content_id`, replacing any previous entry
+    ///
+    /// Raises ValueError if the audio is too short to analyze.
+    pub fn add_content(
+        &self,
+        py: Python<'_>,
+        content_id: &str,
+        samples: Samples<'_>,
+        sample_rate: u32,
+    ) -> PyResult<()> {
+        let audio = audio_data(samples, sample_rate)?;
+        let inner = &self.inner;
+
+        py.allow_threads(|| inner.add_content(content_id, &audio, None))
+            .map_err(|e| PyValueError::new_err(format!("{:#}", e)))
+    }
+
+    /// Remove an item, returning whether it was indexed
+    pub fn remove_content(&self, content_id: &str) -> bool {
+        self.inner.remove_content(content_id)
+    }
+
+    /// Items most similar to `content_id`, best first
+    ///
+    /// Raises KeyError if `content_id` is not indexed.
+    #[pyo3(signature = (content_id, limit=10))]
+    pub fn get_similar(&self, py: Python<'_>, content_id: &str, limit: usize) -> PyResult<Vec<Recommendation>> {
+        if !self.inner.contains(content_id) {
+            return Err(PyKeyError::new_err(content_id.to_string()));
+        }
+
+        let filter = ::kino_frequency::recommend::RecommendationFilter::new();
+        let recommendations = py.allow_threads(|| self.inner.get_similar(content_id, limit, &filter));
+        Ok(recommendations.into_iter()
+            .map(|rec| Recommendation {
+                content_id: rec.content_id,
+                similarity: rec.similarity,
+                matching_features: rec.matching_features,
+            })
+            .collect())
+    }
+
+    /// Serialize the index, including any trained ANN structure, to bytes
+    pub fn export_index<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let data = py.allow_threads(|| serde_json::to_vec(&self.inner.export_snapshot()))
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        Ok(PyBytes::new_bound(py, &data))
+    }
+
+    /// Add the items of a blob produced by `export_index`
+    ///
+    /// Raises ValueError for a malformed blob or mismatched signatures; the
+    /// index is left unchanged in that case.
+    pub fn import_index(&self, py: Python<'_>, data: &[u8]) -> PyResult<()> {
+        let inner = &self.inner;
+        py.allow_threads(|| {
+            let snapshot = serde_json::from_slice(data)
+                .map_err(|e| PyValueError::new_err(format!("Invalid index data: {}", e)))?;
+            inner.import_snapshot(snapshot)
+                .map_err(|e| PyValueError::new_err(format!("{:#}", e)))
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn __contains__(&self, content_id: &str) -> bool {
+        self.inner.contains(content_id)
+    }
+}
+
+/// Mono audio from Python samples
+fn audio_data(samples: Samples<'_>, sample_rate: u32) -> PyResult<::kino_frequency::types::AudioData> {
+    if sample_rate == 0 {
+        return Err(PyValueError::new_err("sample_rate must be positive"));
+    }
+    let samples = samples.into_vec();
+    if samples.is_empty() {
+        return Err(PyValueError::new_err("samples must not be empty"));
+    }
+    Ok(::kino_frequency::types::AudioData::new(samples, sample_rate))
+}
 
-        Ok(tags)
+fn check_video_path(path: &Path) -> PyResult<()> {
+    if path.is_file() {
+        Ok(())
+    } else {
+        Err(PyFileNotFoundError::new_err(format!("Video not found: {}", path.display())))
     }
 }
 
@@ -584,11 +1239,16 @@ fn kino_frequency(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<FrequencyAnalyzer>()?;
     m.add_class::<Fingerprinter>()?;
     m.add_class::<ContentTagger>()?;
+    m.add_class::<TagTaxonomy>()?;
+    m.add_class::<ThumbnailSelector>()?;
+    m.add_class::<RecommendationEngine>()?;
     m.add_class::<DominantFrequency>()?;
+    m.add_class::<BandPlan>()?;
     m.add_class::<BandEnergies>()?;
     m.add_class::<AnalysisResult>()?;
     m.add_class::<Fingerprint>()?;
     m.add_class::<ContentTag>()?;
+    m.add_class::<Recommendation>()?;
     m.add_class::<FrequencySignature>()?;
 
     // Add version
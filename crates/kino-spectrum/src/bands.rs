@@ -0,0 +1,62 @@
+//! Energy distribution across frequency bands.
+
+/// Sub-bass, bass, low-mid, mid, high-mid and high, covering 20 Hz-20 kHz.
+pub const STANDARD_BANDS: [(f32, f32); 6] = [
+    (20.0, 60.0),      // sub_bass
+    (60.0, 250.0),     // bass
+    (250.0, 500.0),    // low_mid
+    (500.0, 2000.0),   // mid
+    (2000.0, 4000.0),  // high_mid
+    (4000.0, 20000.0), // high
+];
+
+/// Magnitude summed per `(low, high)` band and normalized to sum to 1.
+///
+/// Bins at `low` count towards a band, bins at `high` don't. Silent spectra
+/// give all zeros.
+pub fn band_energies(spectrum: &[f32], frequencies: &[f32], bands: &[(f32, f32)]) -> Vec<f32> {
+    let mut energies = vec![0.0f32; bands.len()];
+
+    for (i, (low, high)) in bands.iter().enumerate() {
+        for (j, &freq) in frequencies.iter().enumerate() {
+            if freq >= *low && freq < *high {
+                energies[i] += spectrum[j];
+            }
+        }
+    }
+
+    // Normalize
+    let total: f32 = energies.iter().sum();
+    if total > 0.0 {
+        for e in &mut energies {
+            *e /= total;
+        }
+    }
+
+    energies
+}
+
+/// First band that is not a non-empty, non-negative range.
+pub fn invalid_band(bands: &[(f32, f32)]) -> Option<(f32, f32)> {
+    bands.iter().copied().find(|(low, high)| !(*low >= 0.0 && low < high))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_band_energies() {
+        let frequencies = [0.0, 50.0, 100.0, 300.0, 1000.0, 3000.0, 5000.0];
+        let spectrum = [9.0, 1.0, 1.0, 0.0, 2.0, 0.0, 0.0];
+
+        // DC lies below every band
+        let energies = band_energies(&spectrum, &frequencies, &STANDARD_BANDS);
+        assert_eq!(energies, vec![0.25, 0.25, 0.0, 0.5, 0.0, 0.0]);
+        assert_eq!(band_energies(&[0.0; 7], &frequencies, &STANDARD_BANDS), vec![0.0; 6]);
+
+        assert_eq!(invalid_band(&STANDARD_BANDS), None);
+        assert_eq!(invalid_band(&[(0.0, 100.0), (200.0, 200.0)]), Some((200.0, 200.0)));
+        assert_eq!(invalid_band(&[(-1.0, 100.0)]), Some((-1.0, 100.0)));
+    }
+}
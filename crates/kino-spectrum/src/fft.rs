@@ -0,0 +1,260 @@
+//! Windowed magnitude spectra.
+//!
+//! Without the `rustfft` feature, power-of-two sizes use an in-place
+//! iterative radix-2 FFT and other sizes a direct DFT, which is only fast
+//! enough for short frames.
+
+#[cfg(feature = "rustfft")]
+use std::sync::Arc;
+
+#[cfg(feature = "rustfft")]
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+use crate::window::WindowFunction;
+
+/// Computes the magnitude spectrum of `fft_size` frames.
+///
+/// Magnitudes are scaled by `2 / fft_size` and the window's amplitude
+/// correction, so a full-scale bin-centered sine measures 0.5 with any
+/// window. Only the `fft_size / 2` positive-frequency bins are returned.
+#[derive(Clone)]
+pub struct Spectrum {
+    fft_size: usize,
+    window_function: WindowFunction,
+    /// Window coefficients, pre-scaled by the amplitude correction
+    window: Vec<f32>,
+    transform: Transform,
+}
+
+#[derive(Clone)]
+enum Transform {
+    #[cfg(feature = "rustfft")]
+    RustFft(Arc<dyn Fft<f32>>),
+    /// Radix-2 twiddle factors
+    #[cfg_attr(feature = "rustfft", allow(dead_code))]
+    Radix2(Vec<(f32, f32)>),
+    #[cfg_attr(feature = "rustfft", allow(dead_code))]
+    Dft,
+}
+
+impl Spectrum {
+    /// Create a spectrum computer for `fft_size` frames.
+    pub fn new(fft_size: usize, window: WindowFunction) -> Self {
+        #[cfg(feature = "rustfft")]
+        let transform = Transform::RustFft(FftPlanner::new().plan_fft_forward(fft_size));
+        #[cfg(not(feature = "rustfft"))]
+        let transform = Transform::iterative(fft_size);
+
+        Self {
+            fft_size,
+            window_function: window,
+            window: window.generate_corrected(fft_size),
+            transform,
+        }
+    }
+
+    /// Use the built-in iterative FFT even when rustfft is available.
+    #[cfg(feature = "rustfft")]
+    pub fn iterative(fft_size: usize, window: WindowFunction) -> Self {
+        Self { transform: Transform::iterative(fft_size), ..Self::new(fft_size, window) }
+    }
+
+    /// Frame size.
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    /// Window applied before the FFT.
+    pub fn window_function(&self) -> WindowFunction {
+        self.window_function
+    }
+
+    /// Magnitude spectrum of the first `fft_size` samples, or all zeros if
+    /// there are fewer.
+    pub fn magnitudes(&self, samples: &[f32]) -> Vec<f32> {
+        if samples.len() < self.fft_size {
+            return vec![0.0; self.fft_size / 2];
+        }
+
+        // Apply window
+        let windowed = samples.iter()
+            .take(self.fft_size)
+            .zip(self.window.iter())
+            .map(|(&s, &w)| s * w);
+
+        let scale = 2.0 / self.fft_size as f32;
+        match &self.transform {
+            #[cfg(feature = "rustfft")]
+            Transform::RustFft(fft) => {
+                let mut buffer: Vec<Complex<f32>> = windowed.map(|s| Complex::new(s, 0.0)).collect();
+                fft.process(&mut buffer);
+                buffer[..self.fft_size / 2]
+                    .iter()
+                    .map(|c| (c.re * c.re + c.im * c.im).sqrt() * scale)
+                    .collect()
+            }
+            Transform::Radix2(twiddles) => radix2(windowed.collect(), twiddles)
+                .map(|(re, im)| (re * re + im * im).sqrt() * scale)
+                .collect(),
+            Transform::Dft => dft(&windowed.collect::<Vec<f32>>())
+                .map(|(re, im)| (re * re + im * im).sqrt() * scale)
+                .collect(),
+        }
+    }
+
+    /// Magnitude spectrum averaged over every `hop_size` frame, or `None`
+    /// when there is not a single full frame.
+    pub fn average(&self, samples: &[f32], hop_size: usize) -> Option<Vec<f32>> {
+        if samples.len() < self.fft_size || hop_size == 0 {
+            return None;
+        }
+        let num_frames = (samples.len() - self.fft_size) / hop_size + 1;
+
+        let mut spectrum = vec![0.0f32; self.fft_size / 2];
+        for i in 0..num_frames {
+            let frame = self.magnitudes(&samples[i * hop_size..]);
+            for (avg, mag) in spectrum.iter_mut().zip(frame) {
+                *avg += mag;
+            }
+        }
+        for mag in &mut spectrum {
+            *mag /= num_frames as f32;
+        }
+        Some(spectrum)
+    }
+
+    /// Center frequency of each spectrum bin.
+    pub fn frequencies(&self, sample_rate: u32) -> Vec<f32> {
+        (0..self.fft_size / 2)
+            .map(|i| i as f32 * sample_rate as f32 / self.fft_size as f32)
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for Spectrum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Spectrum")
+            .field("fft_size", &self.fft_size)
+            .field("window_function", &self.window_function)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Transform {
+    /// Radix-2 for power-of-two sizes, DFT otherwise.
+    fn iterative(fft_size: usize) -> Self {
+        if fft_size < 2 || !fft_size.is_power_of_two() {
+            return Self::Dft;
+        }
+        let twiddles = (0..fft_size / 2)
+            .map(|k| {
+                let angle = -2.0 * std::f32::consts::PI * k as f32 / fft_size as f32;
+                (angle.cos(), angle.sin())
+            })
+            .collect();
+        Self::Radix2(twiddles)
+    }
+}
+
+/// In-place iterative radix-2 FFT; yields the first half of the bins.
+fn radix2(mut real: Vec<f32>, twiddles: &[(f32, f32)]) -> impl Iterator<Item = (f32, f32)> {
+    let n = real.len();
+    let mut imag = vec![0.0f32; n];
+
+    // Bit-reversal permutation
+    let shift = usize::BITS - n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> shift;
+        if j > i {
+            real.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let stride = n / len;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (wr, wi) = twiddles[k * stride];
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = real[b] * wr - imag[b] * wi;
+                let ti = real[b] * wi + imag[b] * wr;
+                real[b] = real[a] - tr;
+                imag[b] = imag[a] - ti;
+                real[a] += tr;
+                imag[a] += ti;
+            }
+        }
+        len *= 2;
+    }
+
+    real.into_iter().zip(imag).take(n / 2)
+}
+
+/// Direct DFT of the first half of the bins.
+fn dft(samples: &[f32]) -> impl Iterator<Item = (f32, f32)> + '_ {
+    let n = samples.len() as f32;
+    (0..samples.len() / 2).map(move |k| {
+        let mut real = 0.0f32;
+        let mut imag = 0.0f32;
+        for (i, &sample) in samples.iter().enumerate() {
+            let angle = 2.0 * std::f32::consts::PI * k as f32 * i as f32 / n;
+            real += sample * angle.cos();
+            imag -= sample * angle.sin();
+        }
+        (real, imag)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mixed_tone(freqs: &[f32], rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| freqs.iter().map(|&f| (2.0 * std::f32::consts::PI * f * i as f32 / rate as f32).sin()).sum::<f32>() * 0.3)
+            .collect()
+    }
+
+    fn assert_close(a: &[f32], b: &[f32]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() < 1e-4, "{} vs {}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_radix2_matches_dft() {
+        let samples = mixed_tone(&[440.0, 1250.0], 8000, 256);
+        let mut fast = Spectrum::new(256, WindowFunction::Hann);
+        fast.transform = Transform::iterative(256);
+        let mut slow = Spectrum::new(256, WindowFunction::Hann);
+        slow.transform = Transform::Dft;
+
+        assert!(matches!(fast.transform, Transform::Radix2(_)));
+        assert_close(&fast.magnitudes(&samples), &slow.magnitudes(&samples));
+        assert_eq!(fast.magnitudes(&samples[..100]), vec![0.0; 128]);
+    }
+
+    #[cfg(feature = "rustfft")]
+    #[test]
+    fn test_rustfft_matches_iterative() {
+        let samples = mixed_tone(&[440.0, 1250.0, 3000.0], 8000, 1024);
+        let native = Spectrum::new(1024, WindowFunction::BlackmanHarris);
+        let iterative = Spectrum::iterative(1024, WindowFunction::BlackmanHarris);
+        assert_close(&native.magnitudes(&samples), &iterative.magnitudes(&samples));
+    }
+
+    #[test]
+    fn test_windows_report_the_same_level() {
+        // Full-scale sine centered on bin 16
+        let n = 512;
+        let sine: Vec<f32> = (0..n)
+            .map(|i| (2.0 * std::f32::consts::PI * 16.0 * i as f32 / n as f32).sin())
+            .collect();
+        for window in [WindowFunction::Hann, WindowFunction::Hamming, WindowFunction::FlatTop, WindowFunction::Rectangular] {
+            let peak = Spectrum::new(n, window).magnitudes(&sine)[16];
+            assert!((peak - 0.5).abs() < 5e-3, "{:?}: peak {}", window, peak);
+        }
+    }
+}
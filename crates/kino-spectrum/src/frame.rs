@@ -0,0 +1,221 @@
+//! Per-frame features and overlapping framing of a sample stream.
+
+use crate::bands::{band_energies, STANDARD_BANDS};
+use crate::fft::Spectrum;
+use crate::window::WindowFunction;
+
+/// Features of one analysis frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameFeatures {
+    /// Magnitude spectrum (`fft_size / 2` bins)
+    pub spectrum: Vec<f32>,
+    /// Frequency of the strongest bin (Hz)
+    pub dominant_frequency: f32,
+    /// Magnitude of the strongest bin
+    pub dominant_magnitude: f32,
+    /// Magnitude-weighted mean frequency (Hz)
+    pub spectral_centroid: f32,
+    /// Normalized energy per band of the analyzer's bands
+    pub band_energies: Vec<f32>,
+    /// RMS level of the samples
+    pub rms_energy: f32,
+    /// Zero crossings per sample
+    pub zero_crossing_rate: f32,
+}
+
+/// Computes [`FrameFeatures`] for `fft_size` frames.
+#[derive(Debug, Clone)]
+pub struct FrameAnalyzer {
+    spectrum: Spectrum,
+    sample_rate: u32,
+    frequencies: Vec<f32>,
+    bands: Vec<(f32, f32)>,
+}
+
+impl FrameAnalyzer {
+    /// Create an analyzer over the [`STANDARD_BANDS`].
+    pub fn new(fft_size: usize, sample_rate: u32, window: WindowFunction) -> Self {
+        let spectrum = Spectrum::new(fft_size, window);
+        Self {
+            frequencies: spectrum.frequencies(sample_rate),
+            spectrum,
+            sample_rate,
+            bands: STANDARD_BANDS.to_vec(),
+        }
+    }
+
+    /// Compute band energies over `bands` instead of the standard ones.
+    pub fn with_bands(mut self, bands: Vec<(f32, f32)>) -> Self {
+        self.bands = bands;
+        self
+    }
+
+    /// Replace the bands band energies are computed over.
+    pub fn set_bands(&mut self, bands: Vec<(f32, f32)>) {
+        self.bands = bands;
+    }
+
+    /// Replace the window applied before the FFT.
+    pub fn set_window(&mut self, window: WindowFunction) {
+        self.spectrum = Spectrum::new(self.spectrum.fft_size(), window);
+    }
+
+    /// Frame size.
+    pub fn fft_size(&self) -> usize {
+        self.spectrum.fft_size()
+    }
+
+    /// Sample rate frequencies are computed for.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Bands band energies are computed over.
+    pub fn bands(&self) -> &[(f32, f32)] {
+        &self.bands
+    }
+
+    /// Analyze the first `fft_size` samples of `frame`; shorter frames
+    /// analyze as silence.
+    pub fn analyze(&self, frame: &[f32]) -> FrameFeatures {
+        let frame = &frame[..frame.len().min(self.fft_size())];
+        let spectrum = self.spectrum.magnitudes(frame);
+
+        let (dominant_idx, dominant_magnitude) = spectrum.iter()
+            .copied()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((0, 0.0));
+        let dominant_frequency = self.frequencies.get(dominant_idx).copied().unwrap_or(0.0);
+
+        let weighted: f32 = spectrum.iter().zip(&self.frequencies).map(|(&m, &f)| m * f).sum();
+        let total: f32 = spectrum.iter().sum();
+        let spectral_centroid = if total > 0.0 { weighted / total } else { 0.0 };
+
+        let band_energies = band_energies(&spectrum, &self.frequencies, &self.bands);
+
+        let len = frame.len().max(1) as f32;
+        let rms_energy = (frame.iter().map(|&s| s * s).sum::<f32>() / len).sqrt();
+        let crossings = frame.windows(2).filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0)).count();
+
+        FrameFeatures {
+            spectrum,
+            dominant_frequency,
+            dominant_magnitude,
+            spectral_centroid,
+            band_energies,
+            rms_energy,
+            zero_crossing_rate: crossings as f32 / len,
+        }
+    }
+}
+
+/// Splits a stream of samples into overlapping `fft_size` frames.
+///
+/// Frames start every `hop_size` samples from the first sample, however
+/// the stream is chunked; samples past the last full frame are kept for
+/// the next call.
+#[derive(Debug, Default)]
+pub struct Framer {
+    fft_size: usize,
+    hop_size: usize,
+    /// Audio samples not yet consumed by a full hop
+    buffer: Vec<f32>,
+    /// Stream position of `buffer[0]`
+    position: u64,
+}
+
+impl Framer {
+    /// Create a framer; a zero hop is treated as one sample.
+    pub fn new(fft_size: usize, hop_size: usize) -> Self {
+        Self {
+            fft_size,
+            hop_size: hop_size.max(1),
+            buffer: Vec::with_capacity(fft_size * 2),
+            position: 0,
+        }
+    }
+
+    /// Append samples and call `on_frame` with the stream position of the
+    /// first sample and the samples of every frame now complete.
+    pub fn process(&mut self, samples: &[f32], mut on_frame: impl FnMut(u64, &[f32])) {
+        self.buffer.extend_from_slice(samples);
+
+        let mut offset = 0;
+        while self.buffer.len() - offset >= self.fft_size {
+            on_frame(self.position + offset as u64, &self.buffer[offset..offset + self.fft_size]);
+
+            // Advance by hop size
+            offset = (offset + self.hop_size).min(self.buffer.len());
+        }
+
+        // Drop consumed samples in a single move
+        self.buffer.drain(..offset);
+        self.position += offset as u64;
+    }
+
+    /// Stream position of the next frame's first sample.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Samples held for the next frame.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Drop buffered samples and restart positions from zero.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.position = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_framer_is_chunking_independent() {
+        let samples: Vec<f32> = (0..1000).map(|i| i as f32).collect();
+        let frames = |chunk: usize| {
+            let mut framer = Framer::new(256, 100);
+            let mut starts = Vec::new();
+            for chunk in samples.chunks(chunk) {
+                framer.process(chunk, |start, frame| {
+                    assert_eq!(frame[0], start as f32);
+                    starts.push(start);
+                });
+            }
+            (starts, framer.position(), framer.buffered())
+        };
+
+        let whole = frames(1000);
+        assert_eq!(whole.0, vec![0, 100, 200, 300, 400, 500, 600, 700]);
+        assert_eq!((whole.1, whole.2), (800, 200));
+        assert_eq!(frames(37), whole);
+    }
+
+    #[test]
+    fn test_frame_features() {
+        let rate = 8000;
+        let analyzer = FrameAnalyzer::new(256, rate, WindowFunction::Hann);
+        let tone: Vec<f32> = (0..256)
+            .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / rate as f32).sin())
+            .collect();
+
+        let features = analyzer.analyze(&tone);
+        assert_eq!(features.spectrum.len(), 128);
+        assert_eq!(features.dominant_frequency, 1000.0);
+        assert!((features.spectral_centroid - 1000.0).abs() < 100.0);
+        // 1 kHz lies in the standard "mid" band
+        assert!(features.band_energies[3] > 0.9);
+        assert!((features.rms_energy - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-3);
+
+        let custom = analyzer.clone().with_bands(vec![(0.0, 500.0), (500.0, 4000.0)]);
+        assert_eq!(custom.analyze(&tone).band_energies.len(), 2);
+
+        let silence = analyzer.analyze(&[0.0; 256]);
+        assert_eq!((silence.dominant_magnitude, silence.spectral_centroid, silence.rms_energy), (0.0, 0.0, 0.0));
+    }
+}
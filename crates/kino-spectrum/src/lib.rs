@@ -0,0 +1,30 @@
+//! Frame-level spectral analysis shared by kino-frequency and kino-wasm.
+//!
+//! Holds the windowing, magnitude spectrum, band energies, per-frame
+//! features and rolling statistics behind both streaming analyzers, so the
+//! native `StreamAnalyzer` and the browser `KinoStreamingAnalyzer` report
+//! the same frames for the same samples. Events, timing policy and bindings
+//! stay with each caller. Seek-bar [`overview`]s are shared the same way.
+//!
+//! The crate has no dependencies by default, does no I/O and starts no
+//! threads. The FFT is an iterative radix-2 transform (with a plain DFT for
+//! other sizes); the `rustfft` feature swaps in rustfft for native builds.
+//! The two agree to within float rounding. The `serde` feature derives
+//! serialization for the overview types.
+
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+pub mod bands;
+pub mod fft;
+pub mod frame;
+pub mod overview;
+pub mod stats;
+pub mod window;
+
+pub use bands::{band_energies, invalid_band, STANDARD_BANDS};
+pub use fft::Spectrum;
+pub use frame::{FrameAnalyzer, FrameFeatures, Framer};
+pub use overview::{overview, Overview, OverviewConfig};
+pub use stats::{RollingStats, RollingSummary};
+pub use window::WindowFunction;
@@ -0,0 +1,278 @@
+//! Seek-bar overviews: a waveform and a coarse frequency heatmap.
+//!
+//! A clip is split into equal time buckets by integer sample arithmetic,
+//! so the same samples always produce the same overview. Each bucket gets
+//! its peak and RMS amplitude and the level of log-spaced frequency bands,
+//! all quantized to bytes to keep the serialized overview small. Work and
+//! output size are bounded by [`OverviewConfig::max_buckets`] and
+//! [`MAX_FRAMES_PER_BUCKET`], however long the input.
+
+use std::ops::Range;
+
+use crate::fft::Spectrum;
+use crate::window::WindowFunction;
+
+/// Most FFT frames averaged into one bucket's heatmap column
+pub const MAX_FRAMES_PER_BUCKET: usize = 8;
+
+/// Settings for [`overview`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct OverviewConfig {
+    /// Upper bound on the number of buckets, whatever is requested
+    pub max_buckets: usize,
+    /// Number of log-spaced heatmap bands
+    pub bands: usize,
+    /// FFT frame size for the heatmap
+    pub fft_size: usize,
+    /// Lower edge of the lowest band (Hz); the highest band ends at Nyquist
+    pub min_frequency: f32,
+    /// Levels this far below the loudest cell (dB) quantize to zero
+    pub db_range: f32,
+}
+
+impl Default for OverviewConfig {
+    fn default() -> Self {
+        Self {
+            max_buckets: 2000,
+            bands: 32,
+            fft_size: 2048,
+            min_frequency: 40.0,
+            db_range: 80.0,
+        }
+    }
+}
+
+/// Waveform and heatmap of a clip, one entry per time bucket.
+///
+/// Amplitudes map 0-1 full scale linearly to 0-255. Heatmap levels map the
+/// `db_range` below the loudest cell to 0-255.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Overview {
+    /// Sample rate of the analyzed audio (Hz)
+    pub sample_rate: u32,
+    /// Clip duration (seconds)
+    pub duration_secs: f64,
+    /// Nominal bucket length (seconds); bucket lengths differ by at most one sample
+    pub bucket_secs: f64,
+    /// Heatmap band edges (Hz), one more than the number of bands
+    pub band_edges_hz: Vec<f32>,
+    /// Peak amplitude per bucket
+    pub peaks: Vec<u8>,
+    /// RMS amplitude per bucket
+    pub rms: Vec<u8>,
+    /// Band levels, bucket by bucket: `heatmap[bucket * bands + band]`
+    pub heatmap: Vec<u8>,
+}
+
+impl Overview {
+    /// Number of time buckets.
+    pub fn buckets(&self) -> usize {
+        self.peaks.len()
+    }
+
+    /// Number of heatmap bands.
+    pub fn bands(&self) -> usize {
+        self.band_edges_hz.len().saturating_sub(1)
+    }
+
+    /// Band levels of one bucket, lowest band first.
+    pub fn column(&self, bucket: usize) -> &[u8] {
+        let bands = self.bands();
+        self.heatmap.get(bucket * bands..(bucket + 1) * bands).unwrap_or(&[])
+    }
+}
+
+/// Compute an overview of mono `samples` in `buckets` buckets.
+///
+/// The bucket count is capped by [`OverviewConfig::max_buckets`] and the
+/// number of samples; empty input gives an overview without buckets.
+pub fn overview(samples: &[f32], sample_rate: u32, buckets: usize, config: &OverviewConfig) -> Overview {
+    let len = samples.len();
+    let buckets = buckets.clamp(1, config.max_buckets.max(1)).min(len);
+    let fft_size = config.fft_size.max(2);
+    let duration_secs = if sample_rate > 0 { len as f64 / sample_rate as f64 } else { 0.0 };
+
+    let edges = band_edges(sample_rate, config.bands.max(1), config.min_frequency);
+    let band_bins = band_bins(&edges, sample_rate, fft_size);
+    let spectrum = Spectrum::new(fft_size, WindowFunction::Hann);
+
+    let mut peaks = Vec::with_capacity(buckets);
+    let mut rms = Vec::with_capacity(buckets);
+    let mut levels = Vec::with_capacity(buckets * band_bins.len());
+    let mut frame = vec![0.0f32; fft_size];
+
+    for bucket in 0..buckets {
+        let range = bucket_range(bucket, buckets, len);
+        let chunk = &samples[range.clone()];
+
+        let peak = chunk.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        let mean_square = chunk.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / chunk.len().max(1) as f64;
+        peaks.push(quantize(peak));
+        rms.push(quantize(mean_square.sqrt() as f32));
+
+        // Evenly spaced frames centered in the bucket, shifted to stay
+        // inside the clip and zero-padded when it is shorter than a frame
+        let frames = (chunk.len() / fft_size).clamp(1, MAX_FRAMES_PER_BUCKET);
+        let mut power = vec![0.0f64; band_bins.len()];
+        for i in 0..frames {
+            let center = range.start + (2 * i + 1) * chunk.len() / (2 * frames);
+            let start = center.saturating_sub(fft_size / 2).min(len.saturating_sub(fft_size));
+            let end = (start + fft_size).min(len);
+            frame[..end - start].copy_from_slice(&samples[start..end]);
+            frame[end - start..].fill(0.0);
+
+            let magnitudes = spectrum.magnitudes(&frame);
+            for (total, bins) in power.iter_mut().zip(&band_bins) {
+                let band = &magnitudes[bins.clone()];
+                *total += band.iter().map(|&m| (m as f64).powi(2)).sum::<f64>() / band.len() as f64;
+            }
+        }
+        levels.extend(power.iter().map(|p| p / frames as f64));
+    }
+
+    Overview {
+        sample_rate,
+        duration_secs,
+        bucket_secs: if buckets > 0 { duration_secs / buckets as f64 } else { 0.0 },
+        band_edges_hz: edges,
+        peaks,
+        rms,
+        heatmap: quantize_levels(&levels, config.db_range),
+    }
+}
+
+/// Samples of bucket `index`, split by integer arithmetic so the edges
+/// never depend on float rounding.
+fn bucket_range(index: usize, buckets: usize, len: usize) -> Range<usize> {
+    let edge = |i: usize| (i as u128 * len as u128 / buckets as u128) as usize;
+    edge(index)..edge(index + 1)
+}
+
+/// Log-spaced band edges from `min_frequency` to Nyquist, rounded to whole Hz.
+fn band_edges(sample_rate: u32, bands: usize, min_frequency: f32) -> Vec<f32> {
+    let nyquist = sample_rate as f32 / 2.0;
+    // Keep at least an octave of range at very low sample rates
+    let low = min_frequency.max(1.0).min(nyquist / 2.0).max(f32::MIN_POSITIVE);
+    let ratio = (nyquist / low).max(1.0);
+    (0..=bands)
+        .map(|i| (low * ratio.powf(i as f32 / bands as f32)).round())
+        .collect()
+}
+
+/// Spectrum bins of each band. Bands narrower than a bin use the bin
+/// nearest their geometric center.
+fn band_bins(edges: &[f32], sample_rate: u32, fft_size: usize) -> Vec<Range<usize>> {
+    let bin_hz = sample_rate.max(1) as f32 / fft_size as f32;
+    let last = fft_size / 2 - 1;
+    edges
+        .windows(2)
+        .map(|edge| {
+            let first = ((edge[0] / bin_hz).ceil() as usize).min(last);
+            let end = ((edge[1] / bin_hz).ceil() as usize).min(last + 1);
+            if end > first {
+                first..end
+            } else {
+                let center = (((edge[0] * edge[1]).sqrt() / bin_hz).round() as usize).min(last);
+                center..center + 1
+            }
+        })
+        .collect()
+}
+
+/// Map an amplitude in 0-1 to a byte.
+fn quantize(amplitude: f32) -> u8 {
+    (amplitude.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Map band powers to bytes over `db_range` below the loudest; all zeros
+/// for silence.
+fn quantize_levels(powers: &[f64], db_range: f32) -> Vec<u8> {
+    let loudest = powers.iter().copied().fold(0.0f64, f64::max);
+    if loudest <= 0.0 {
+        return vec![0; powers.len()];
+    }
+
+    let range = db_range.max(1.0) as f64;
+    let top = 10.0 * loudest.log10();
+    powers
+        .iter()
+        .map(|&p| {
+            let db = 10.0 * p.max(1e-30).log10();
+            (((db - top + range) / range).clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    fn tone(freq: f32, len: usize, amplitude: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    /// Band whose range holds `freq`
+    fn band_of(overview: &Overview, freq: f32) -> usize {
+        overview.band_edges_hz.windows(2).position(|e| freq >= e[0] && freq < e[1]).unwrap()
+    }
+
+    fn loudest_band(column: &[u8]) -> usize {
+        (0..column.len()).max_by_key(|&b| column[b]).unwrap()
+    }
+
+    #[test]
+    fn test_overview_follows_the_content() {
+        // Quiet low tone, then a loud high one
+        let mut samples = tone(300.0, RATE as usize, 0.2);
+        samples.extend(tone(4000.0, RATE as usize, 0.8));
+
+        let overview = overview(&samples, RATE, 10, &OverviewConfig::default());
+        assert_eq!((overview.buckets(), overview.bands()), (10, 32));
+        assert_eq!(overview.heatmap.len(), 10 * 32);
+        assert_eq!(overview.duration_secs, 2.0);
+        assert_eq!(overview.band_edges_hz[0], 40.0);
+        assert_eq!(overview.band_edges_hz[32], 8000.0);
+
+        assert_eq!(overview.peaks[0], 51);
+        assert_eq!(overview.peaks[9], 204);
+        assert_eq!(overview.rms[9], quantize(0.8 * std::f32::consts::FRAC_1_SQRT_2));
+
+        assert_eq!(loudest_band(overview.column(0)), band_of(&overview, 300.0));
+        assert_eq!(loudest_band(overview.column(9)), band_of(&overview, 4000.0));
+        assert_eq!(overview.column(9)[band_of(&overview, 4000.0)], 255);
+        assert!(overview.column(10).is_empty());
+    }
+
+    #[test]
+    fn test_overview_is_bounded_and_deterministic() {
+        let samples = tone(1000.0, RATE as usize * 3, 0.5);
+        let config = OverviewConfig { max_buckets: 100, ..Default::default() };
+
+        let overview_a = overview(&samples, RATE, 100_000, &config);
+        assert_eq!(overview_a.buckets(), 100);
+        assert_eq!(overview_a, overview(&samples, RATE, 100_000, &config));
+
+        // More buckets than samples, and short clips padded to a frame
+        assert_eq!(overview(&samples[..50], RATE, 1000, &config).buckets(), 50);
+        assert_eq!(overview(&[], RATE, 10, &config).buckets(), 0);
+
+        let silence = overview(&[0.0; 4000], RATE, 4, &config);
+        assert!(silence.heatmap.iter().chain(&silence.peaks).all(|&v| v == 0));
+    }
+
+    #[test]
+    fn test_bucket_ranges_cover_the_clip() {
+        let ranges: Vec<_> = (0..7).map(|i| bucket_range(i, 7, 100)).collect();
+        assert_eq!(ranges[0].start, 0);
+        assert_eq!(ranges[6].end, 100);
+        assert!(ranges.windows(2).all(|r| r[0].end == r[1].start));
+        assert!(ranges.iter().all(|r| r.len() == 14 || r.len() == 15));
+    }
+}
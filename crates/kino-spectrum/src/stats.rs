@@ -0,0 +1,155 @@
+//! Rolling statistics over recent frames.
+
+use std::collections::VecDeque;
+
+use crate::frame::FrameFeatures;
+
+/// Summary of the frames in a [`RollingStats`] window.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RollingSummary {
+    /// Number of frames in the window
+    pub frame_count: usize,
+    /// Average dominant frequency
+    pub avg_dominant_frequency: f32,
+    /// Average spectral centroid
+    pub avg_spectral_centroid: f32,
+    /// Average RMS energy
+    pub avg_rms_energy: f32,
+    /// RMS energy variance
+    pub rms_variance: f32,
+    /// Dominant frequency variance
+    pub frequency_variance: f32,
+    /// Average band energies, empty when the window is
+    pub avg_band_energies: Vec<f32>,
+}
+
+/// The parts of a frame the statistics need.
+#[derive(Debug, Clone)]
+struct FrameSummary {
+    dominant_frequency: f32,
+    spectral_centroid: f32,
+    rms_energy: f32,
+    band_energies: Vec<f32>,
+}
+
+/// Statistics over the last `capacity` frames.
+#[derive(Debug, Clone)]
+pub struct RollingStats {
+    capacity: usize,
+    frames: VecDeque<FrameSummary>,
+}
+
+impl RollingStats {
+    /// Keep statistics over the last `capacity` frames.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            frames: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Add a frame, dropping the oldest once the window is full.
+    pub fn push(&mut self, frame: &FrameFeatures) {
+        self.frames.push_back(FrameSummary {
+            dominant_frequency: frame.dominant_frequency,
+            spectral_centroid: frame.spectral_centroid,
+            rms_energy: frame.rms_energy,
+            band_energies: frame.band_energies.clone(),
+        });
+        if self.frames.len() > self.capacity {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Number of frames in the window.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// True if no frames have been pushed since the last clear.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Drop all frames.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Averages and variances over the window.
+    pub fn summary(&self) -> RollingSummary {
+        if self.frames.is_empty() {
+            return RollingSummary::default();
+        }
+
+        let n = self.frames.len() as f32;
+        let mean = |value: fn(&FrameSummary) -> f32| self.frames.iter().map(value).sum::<f32>() / n;
+
+        let avg_dominant = mean(|f| f.dominant_frequency);
+        let avg_rms = mean(|f| f.rms_energy);
+        let rms_variance = self.frames.iter()
+            .map(|f| (f.rms_energy - avg_rms).powi(2))
+            .sum::<f32>() / n;
+        let frequency_variance = self.frames.iter()
+            .map(|f| (f.dominant_frequency - avg_dominant).powi(2))
+            .sum::<f32>() / n;
+
+        // Frames of a stream share its bands; any that don't are skipped
+        let bands = self.frames[0].band_energies.len();
+        let mut sums = vec![0.0f32; bands];
+        let mut count = 0.0f32;
+        for frame in self.frames.iter().filter(|f| f.band_energies.len() == bands) {
+            for (sum, e) in sums.iter_mut().zip(&frame.band_energies) {
+                *sum += e;
+            }
+            count += 1.0;
+        }
+
+        RollingSummary {
+            frame_count: self.frames.len(),
+            avg_dominant_frequency: avg_dominant,
+            avg_spectral_centroid: mean(|f| f.spectral_centroid),
+            avg_rms_energy: avg_rms,
+            rms_variance,
+            frequency_variance,
+            avg_band_energies: sums.into_iter().map(|s| s / count).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(dominant_frequency: f32, rms_energy: f32, band_energies: Vec<f32>) -> FrameFeatures {
+        FrameFeatures {
+            spectrum: Vec::new(),
+            dominant_frequency,
+            dominant_magnitude: 0.0,
+            spectral_centroid: dominant_frequency,
+            band_energies,
+            rms_energy,
+            zero_crossing_rate: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_rolling_summary() {
+        let mut stats = RollingStats::new(2);
+        assert_eq!(stats.summary(), RollingSummary::default());
+
+        stats.push(&frame(100.0, 0.5, vec![1.0, 0.0]));
+        stats.push(&frame(200.0, 0.1, vec![0.0, 1.0]));
+        stats.push(&frame(400.0, 0.3, vec![0.5, 0.5]));
+
+        let summary = stats.summary();
+        assert_eq!(summary.frame_count, 2);
+        assert_eq!(summary.avg_dominant_frequency, 300.0);
+        assert_eq!(summary.frequency_variance, 10_000.0);
+        assert!((summary.avg_rms_energy - 0.2).abs() < 1e-6);
+        assert_eq!(summary.avg_band_energies, vec![0.25, 0.75]);
+
+        stats.clear();
+        assert!(stats.is_empty());
+    }
+}
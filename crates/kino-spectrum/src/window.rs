@@ -0,0 +1,78 @@
+//! Window functions applied to each frame before the FFT.
+
+/// Cosine-sum window applied to each frame before the FFT.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowFunction {
+    /// Good general-purpose leakage/resolution trade-off
+    #[default]
+    Hann,
+    /// Narrower main lobe than Hann, higher far sidelobes
+    Hamming,
+    /// 4-term Blackman-Harris; very low sidelobes, less leakage between bands
+    BlackmanHarris,
+    /// Flat passband for accurate amplitudes of tonal content
+    FlatTop,
+    /// No windowing
+    Rectangular,
+}
+
+impl WindowFunction {
+    /// Cosine-sum coefficients `a0, a1, ...` of the window.
+    pub fn coefficients(&self) -> &'static [f32] {
+        match self {
+            Self::Hann => &[0.5, 0.5],
+            Self::Hamming => &[0.54, 0.46],
+            Self::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            Self::FlatTop => &[0.215_578_95, 0.416_631_58, 0.277_263_16, 0.083_578_95, 0.006_947_368],
+            Self::Rectangular => &[1.0],
+        }
+    }
+
+    /// Coherent gain: the window's mean value, i.e. the factor by which it
+    /// attenuates a bin-centered tone.
+    pub fn coherent_gain(&self) -> f32 {
+        self.coefficients()[0]
+    }
+
+    /// Magnitude correction that brings this window to Hann's scale.
+    pub fn amplitude_correction(&self) -> f32 {
+        Self::Hann.coherent_gain() / self.coherent_gain()
+    }
+
+    /// Generate a symmetric window of `size` points.
+    pub fn generate(&self, size: usize) -> Vec<f32> {
+        let coefficients = self.coefficients();
+        let denom = size.saturating_sub(1).max(1) as f32;
+        (0..size)
+            .map(|i| {
+                let phase = 2.0 * std::f32::consts::PI * i as f32 / denom;
+                coefficients.iter()
+                    .enumerate()
+                    .map(|(k, &a)| {
+                        let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                        sign * a * (k as f32 * phase).cos()
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// [`generate`](Self::generate) scaled by the amplitude correction, so
+    /// a tone measures the same regardless of the window.
+    pub fn generate_corrected(&self, size: usize) -> Vec<f32> {
+        let correction = self.amplitude_correction();
+        self.generate(size).into_iter().map(|w| w * correction).collect()
+    }
+
+    /// Parse a window name such as `"hann"` or `"blackman-harris"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace(['-', ' '], "_").as_str() {
+            "hann" | "hanning" => Some(Self::Hann),
+            "hamming" => Some(Self::Hamming),
+            "blackman_harris" | "blackmanharris" => Some(Self::BlackmanHarris),
+            "flat_top" | "flattop" => Some(Self::FlatTop),
+            "rectangular" | "rect" | "none" => Some(Self::Rectangular),
+            _ => None,
+        }
+    }
+}
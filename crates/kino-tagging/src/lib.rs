@@ -0,0 +1,19 @@
+//! Rule-based content tagging shared by kino-frequency and kino-wasm.
+//!
+//! Holds the genre profiles, mood and content-type rules and the frequency
+//! signature layout, so the native `ContentTagger` and the browser tagger
+//! produce the same tags from the same features. Spectral analysis stays
+//! with each caller; this crate only turns features into scores.
+//!
+//! The crate has no dependencies and does no I/O, keeping it cheap to
+//! include in the WASM bundle.
+
+#![warn(clippy::all)]
+#![warn(missing_docs)]
+
+pub mod profiles;
+pub mod signal;
+pub mod signature;
+
+pub use profiles::{rule_tags, GenreProfile, TagFeatures, GENRE_PROFILES};
+pub use signature::{cosine_similarity, signature_features, SIGNATURE_SIZE};
@@ -0,0 +1,238 @@
+//! Genre profiles and mood/content-type rules.
+
+use crate::signature::cosine_similarity;
+
+/// Features the tagging rules are scored on.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TagFeatures {
+    /// Spectral centroid in Hz
+    pub spectral_centroid: f32,
+    /// Spectral flatness (0 = tonal, 1 = noise)
+    pub spectral_flatness: f32,
+    /// Zero crossings per sample
+    pub zero_crossing_rate: f32,
+    /// Standard deviation of frame energy
+    pub energy_variance: f32,
+    /// Estimated tempo in BPM
+    pub tempo_estimate: f32,
+}
+
+/// Expected feature ranges of a genre.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenreProfile {
+    /// Genre tag label
+    pub name: &'static str,
+    /// Spectral centroid range in Hz
+    pub spectral_centroid_range: (f32, f32),
+    /// Spectral flatness range
+    pub spectral_flatness_range: (f32, f32),
+    /// Zero-crossing rate range
+    pub zcr_range: (f32, f32),
+    /// Expected energy share of the six standard bands, sub-bass to high
+    pub band_weights: [f32; 6],
+}
+
+impl GenreProfile {
+    /// Score features against this profile (0-1).
+    ///
+    /// `band_match` is the band energy similarity, usually
+    /// [`band_match`](Self::band_match) of the standard band energies.
+    pub fn score(&self, features: &TagFeatures, band_match: f32) -> f32 {
+        let centroid_score = range_score(features.spectral_centroid, self.spectral_centroid_range, 1.0 / 2000.0);
+        let flatness_score = range_score(features.spectral_flatness, self.spectral_flatness_range, 2.0);
+        let zcr_score = range_score(features.zero_crossing_rate, self.zcr_range, 10.0);
+
+        centroid_score * 0.25 + flatness_score * 0.25 + zcr_score * 0.2 + band_match * 0.3
+    }
+
+    /// Similarity of standard-plan band energies to this profile's weights.
+    pub fn band_match(&self, energies: &[f32]) -> f32 {
+        cosine_similarity(energies, &self.band_weights)
+    }
+}
+
+/// 1 inside `range`, falling off linearly with the distance to the nearer
+/// bound scaled by `falloff`.
+fn range_score(value: f32, range: (f32, f32), falloff: f32) -> f32 {
+    if value >= range.0 && value <= range.1 {
+        return 1.0;
+    }
+    let dist = (value - range.0).min(value - range.1).abs();
+    (1.0 - dist * falloff).max(0.0)
+}
+
+/// Default genre profiles based on frequency characteristics.
+pub const GENRE_PROFILES: [GenreProfile; 8] = [
+    // Balanced spectrum, low flatness (tonal), moderate ZCR
+    GenreProfile {
+        name: "music",
+        spectral_centroid_range: (500.0, 4000.0),
+        spectral_flatness_range: (0.0, 0.3),
+        zcr_range: (0.02, 0.15),
+        band_weights: [0.15, 0.20, 0.20, 0.20, 0.15, 0.10],
+    },
+    // Mid-range centroid, low ZCR, concentrated in mid frequencies
+    GenreProfile {
+        name: "speech",
+        spectral_centroid_range: (300.0, 2000.0),
+        spectral_flatness_range: (0.1, 0.5),
+        zcr_range: (0.01, 0.08),
+        band_weights: [0.05, 0.10, 0.25, 0.35, 0.15, 0.10],
+    },
+    // Wide spectrum, high energy variation, high ZCR
+    GenreProfile {
+        name: "gaming",
+        spectral_centroid_range: (1000.0, 6000.0),
+        spectral_flatness_range: (0.2, 0.7),
+        zcr_range: (0.05, 0.20),
+        band_weights: [0.15, 0.15, 0.15, 0.20, 0.20, 0.15],
+    },
+    // Low centroid, high flatness (noise-like), low ZCR
+    GenreProfile {
+        name: "nature",
+        spectral_centroid_range: (200.0, 2000.0),
+        spectral_flatness_range: (0.4, 0.9),
+        zcr_range: (0.01, 0.06),
+        band_weights: [0.10, 0.15, 0.20, 0.25, 0.15, 0.15],
+    },
+    // Similar to speech but with music intros
+    GenreProfile {
+        name: "podcast",
+        spectral_centroid_range: (300.0, 2500.0),
+        spectral_flatness_range: (0.1, 0.4),
+        zcr_range: (0.01, 0.10),
+        band_weights: [0.05, 0.10, 0.25, 0.35, 0.15, 0.10],
+    },
+    // Clear speech with occasional UI sounds
+    GenreProfile {
+        name: "tutorial",
+        spectral_centroid_range: (400.0, 3000.0),
+        spectral_flatness_range: (0.1, 0.5),
+        zcr_range: (0.02, 0.12),
+        band_weights: [0.05, 0.08, 0.20, 0.35, 0.20, 0.12],
+    },
+    // Professional speech, compressed dynamics
+    GenreProfile {
+        name: "news",
+        spectral_centroid_range: (350.0, 1800.0),
+        spectral_flatness_range: (0.1, 0.35),
+        zcr_range: (0.01, 0.06),
+        band_weights: [0.03, 0.08, 0.25, 0.40, 0.15, 0.09],
+    },
+    // Crowd noise, commentary, high energy
+    GenreProfile {
+        name: "sports",
+        spectral_centroid_range: (500.0, 4000.0),
+        spectral_flatness_range: (0.3, 0.7),
+        zcr_range: (0.04, 0.15),
+        band_weights: [0.10, 0.15, 0.20, 0.25, 0.18, 0.12],
+    },
+];
+
+/// Mood tags implied by the features.
+pub fn mood_tags(features: &TagFeatures) -> Vec<(&'static str, f32)> {
+    let mut tags = Vec::new();
+
+    // High tempo and bright spectrum
+    if features.tempo_estimate > 140.0 && features.spectral_centroid > 2000.0 {
+        tags.push(("energetic", 0.7));
+    }
+    // Low tempo and dark spectrum
+    if features.tempo_estimate < 90.0 && features.spectral_centroid < 1500.0 {
+        tags.push(("calm", 0.7));
+    }
+    // High energy variance
+    if features.energy_variance > 0.1 {
+        tags.push(("dramatic", 0.5));
+    }
+
+    tags
+}
+
+/// Content-type tags implied by the features.
+pub fn content_type_tags(features: &TagFeatures) -> Vec<(&'static str, f32)> {
+    let mut tags = Vec::new();
+
+    // Mid-range centroid, low flatness
+    if features.spectral_centroid > 300.0 && features.spectral_centroid < 2000.0
+        && features.spectral_flatness < 0.3 {
+        tags.push(("vocal", 0.6));
+    }
+    // Low flatness, outside the vocal range
+    if features.spectral_flatness < 0.25
+        && (features.spectral_centroid < 300.0 || features.spectral_centroid > 2500.0) {
+        tags.push(("instrumental", 0.5));
+    }
+    // High flatness, low energy variance
+    if features.spectral_flatness > 0.5 && features.energy_variance < 0.05 {
+        tags.push(("ambient", 0.6));
+    }
+
+    tags
+}
+
+/// Combine genre scores with mood and content-type tags.
+///
+/// Keeps the best `max_genres` genres scoring at least `min_confidence`,
+/// adds the mood and content-type tags above it, and sorts everything by
+/// confidence. Callers truncate to their tag limit after any blending.
+pub fn rule_tags(
+    features: &TagFeatures,
+    mut genre_scores: Vec<(&'static str, f32)>,
+    min_confidence: f32,
+    max_genres: usize,
+) -> Vec<(&'static str, f32)> {
+    genre_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut tags: Vec<(&'static str, f32)> = genre_scores.into_iter()
+        .filter(|&(_, score)| score >= min_confidence)
+        .take(max_genres)
+        .collect();
+    tags.extend(mood_tags(features).into_iter().filter(|&(_, c)| c >= min_confidence));
+    tags.extend(content_type_tags(features).into_iter().filter(|&(_, c)| c >= min_confidence));
+
+    tags.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speech_like() -> TagFeatures {
+        TagFeatures {
+            spectral_centroid: 1000.0,
+            spectral_flatness: 0.2,
+            zero_crossing_rate: 0.04,
+            energy_variance: 0.02,
+            tempo_estimate: 100.0,
+        }
+    }
+
+    #[test]
+    fn test_score_inside_and_outside_ranges() {
+        let speech = GENRE_PROFILES.iter().find(|p| p.name == "speech").unwrap();
+        assert!((speech.score(&speech_like(), 1.0) - 1.0).abs() < 1e-6);
+
+        // 1 kHz above the centroid range loses half the centroid score
+        let bright = TagFeatures { spectral_centroid: 3000.0, ..speech_like() };
+        assert!((speech.score(&bright, 1.0) - 0.875).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_band_match() {
+        let news = &GENRE_PROFILES[6];
+        assert!((news.band_match(&news.band_weights) - 1.0).abs() < 1e-6);
+        assert_eq!(news.band_match(&[0.0; 6]), 0.0);
+    }
+
+    #[test]
+    fn test_rule_tags_filter_and_order() {
+        let features = speech_like();
+        let scores = vec![("music", 0.2), ("speech", 0.9), ("news", 0.8), ("podcast", 0.85)];
+        let tags = rule_tags(&features, scores, 0.3, 2);
+
+        let labels: Vec<&str> = tags.iter().map(|t| t.0).collect();
+        assert_eq!(labels, ["speech", "podcast", "vocal"]);
+    }
+}
@@ -0,0 +1,108 @@
+//! Time-domain features used by the tagging rules.
+
+/// Standard deviation of frame energy (a dynamic range indicator).
+///
+/// Returns 0.0 if there is not a single full frame.
+pub fn energy_variance(samples: &[f32], frame_size: usize, hop_size: usize) -> f32 {
+    if samples.len() < frame_size || frame_size == 0 || hop_size == 0 {
+        return 0.0;
+    }
+    let num_frames = (samples.len() - frame_size) / hop_size + 1;
+
+    let energies: Vec<f32> = (0..num_frames)
+        .map(|i| {
+            let frame = &samples[i * hop_size..i * hop_size + frame_size];
+            frame.iter().map(|&s| s * s).sum::<f32>() / frame.len() as f32
+        })
+        .collect();
+
+    let mean: f32 = energies.iter().sum::<f32>() / energies.len() as f32;
+    let variance: f32 = energies.iter()
+        .map(|&e| (e - mean) * (e - mean))
+        .sum::<f32>() / energies.len() as f32;
+
+    variance.sqrt()
+}
+
+/// Estimate tempo in BPM by autocorrelating onset strength.
+///
+/// Falls back to 120 BPM for clips too short to estimate; results are
+/// clamped to 60-200 BPM.
+pub fn estimate_tempo(samples: &[f32], sample_rate: u32) -> f32 {
+    // Simple onset detection via energy derivative
+    let frame_size = 1024;
+    let hop_size = 512;
+
+    let num_frames = samples.len().saturating_sub(frame_size) / hop_size;
+    if num_frames < 2 {
+        return 120.0;
+    }
+
+    let energies: Vec<f32> = (0..num_frames)
+        .map(|i| {
+            let start = i * hop_size;
+            samples[start..start + frame_size].iter().map(|&s| s * s).sum()
+        })
+        .collect();
+
+    // Onset strength (energy derivative)
+    let onset_strength: Vec<f32> = energies.windows(2)
+        .map(|w| (w[1] - w[0]).max(0.0))
+        .collect();
+
+    // Beat periods between 0.25 and 4 seconds
+    let max_lag = (4.0 * sample_rate as f32 / hop_size as f32) as usize;
+    let min_lag = (0.25 * sample_rate as f32 / hop_size as f32) as usize;
+
+    let mut best_lag = min_lag;
+    let mut best_corr = 0.0f32;
+
+    for lag in min_lag..max_lag.min(onset_strength.len()) {
+        let corr: f32 = onset_strength.iter()
+            .zip(onset_strength.iter().skip(lag))
+            .map(|(&a, &b)| a * b)
+            .sum();
+
+        if corr > best_corr {
+            best_corr = corr;
+            best_lag = lag;
+        }
+    }
+
+    let beat_period_secs = best_lag as f32 * hop_size as f32 / sample_rate as f32;
+    let bpm = if beat_period_secs > 0.0 {
+        60.0 / beat_period_secs
+    } else {
+        120.0
+    };
+
+    bpm.clamp(60.0, 200.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_energy_variance() {
+        assert_eq!(energy_variance(&[0.5; 4096], 1024, 512), 0.0);
+        assert_eq!(energy_variance(&[0.5; 100], 1024, 512), 0.0);
+
+        // Alternating loud and silent frames
+        let samples: Vec<f32> = (0..4096).map(|i| if (i / 1024) % 2 == 0 { 1.0 } else { 0.0 }).collect();
+        assert!((energy_variance(&samples, 1024, 1024) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_estimate_tempo_of_clicks() {
+        // One click every half second is 120 BPM
+        let sample_rate = 44100;
+        let samples: Vec<f32> = (0..sample_rate * 8)
+            .map(|i| if i % (sample_rate / 2) < 256 { 1.0 } else { 0.0 })
+            .collect();
+        let bpm = estimate_tempo(&samples, sample_rate as u32);
+        assert!((bpm - 120.0).abs() < 5.0, "bpm = {}", bpm);
+
+        assert_eq!(estimate_tempo(&[0.0; 1000], 44100), 120.0);
+    }
+}
@@ -0,0 +1,92 @@
+//! Frequency signature layout for similarity matching.
+
+/// Number of features in a frequency signature.
+pub const SIGNATURE_SIZE: usize = 128;
+
+/// Lowest frequency covered by the signature, in Hz.
+const MIN_FREQ: f32 = 20.0;
+
+/// Bin a magnitude spectrum into signature features.
+///
+/// Features average the spectrum over log-spaced bands from 20 Hz to
+/// Nyquist and are normalized to a peak of 1.0. `frequencies` holds the
+/// center frequency of each `spectrum` bin.
+pub fn signature_features(spectrum: &[f32], frequencies: &[f32], sample_rate: u32) -> Vec<f32> {
+    let max_freq = (sample_rate / 2) as f32;
+
+    // Log-spaced frequency bins
+    let bin_edges: Vec<f32> = (0..=SIGNATURE_SIZE)
+        .map(|i| {
+            let t = i as f32 / SIGNATURE_SIZE as f32;
+            MIN_FREQ * (max_freq / MIN_FREQ).powf(t)
+        })
+        .collect();
+
+    let mut features = vec![0.0f32; SIGNATURE_SIZE];
+
+    for (i, feature) in features.iter_mut().enumerate() {
+        let low = bin_edges[i];
+        let high = bin_edges[i + 1];
+
+        let mut energy = 0.0f32;
+        let mut count = 0;
+
+        for (&freq, &mag) in frequencies.iter().zip(spectrum) {
+            if freq >= low && freq < high {
+                energy += mag;
+                count += 1;
+            }
+        }
+
+        if count > 0 {
+            *feature = energy / count as f32;
+        }
+    }
+
+    // Normalize features
+    let max_feature = features.iter().cloned().fold(0.0f32, f32::max);
+    if max_feature > 0.0 {
+        for f in &mut features {
+            *f /= max_feature;
+        }
+    }
+
+    features
+}
+
+/// Cosine similarity of two equal-length vectors, 0 if either is zero.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a > 0.0 && norm_b > 0.0 {
+        dot / (norm_a * norm_b)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_features_peak_at_tone() {
+        // 1 Hz bins up to Nyquist with a single peak at 1 kHz
+        let frequencies: Vec<f32> = (0..8000).map(|i| i as f32).collect();
+        let spectrum: Vec<f32> = frequencies.iter().map(|&f| if f == 1000.0 { 2.0 } else { 0.0 }).collect();
+
+        let features = signature_features(&spectrum, &frequencies, 16000);
+        assert_eq!(features.len(), SIGNATURE_SIZE);
+        assert_eq!(features.iter().cloned().fold(0.0f32, f32::max), 1.0);
+        assert_eq!(features.iter().filter(|&&f| f > 0.0).count(), 1);
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+}
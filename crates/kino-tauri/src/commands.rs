@@ -3,17 +3,36 @@
 //! Lightweight commands that work with the web frontend.
 //! The actual video playback is handled by hls.js in the frontend.
 
-use kino_core::{KinoColors, Chapter, TextTrack};
+use crate::queue::{PlaybackQueue, QueueItem, QueueItemInfo, QueueTransition};
+use kino_core::{
+    ActiveCues, AudioTrack, KinoColors, Chapter, PlayerConfig, QualityMetrics, TextCue, TextTrack, TextTrackFormat,
+    TextTrackKind, WatchProgressStore, WebVttParser,
+};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use tokio::sync::RwLock;
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
+use url::Url;
 
 /// Shared application state
 pub struct AppState {
     pub current_url: Arc<RwLock<Option<String>>>,
     pub chapters: Arc<RwLock<Vec<Chapter>>>,
     pub text_tracks: Arc<RwLock<Vec<TextTrack>>>,
+    /// Text track whose cues are loaded into `cues`
+    pub active_text_track: Arc<RwLock<Option<String>>>,
+    /// Cues of the active text track, synced to the reported position
+    pub cues: Arc<RwLock<ActiveCues>>,
+    pub text_track_style: Arc<RwLock<TextTrackStyle>>,
+    pub audio_tracks: Arc<RwLock<Vec<AudioTrack>>>,
+    pub active_audio_track: Arc<RwLock<Option<String>>>,
+    /// Manually pinned rendition; `None` for automatic selection
+    pub quality_override: Arc<RwLock<Option<String>>>,
+    pub metrics: Arc<RwLock<QualityMetrics>>,
+    pub queue: Arc<RwLock<PlaybackQueue>>,
+    /// Watch progress by content key, set up once the app data directory is known
+    pub progress: OnceLock<Arc<dyn WatchProgressStore>>,
 }
 
 impl AppState {
@@ -22,6 +41,15 @@ impl AppState {
             current_url: Arc::new(RwLock::new(None)),
             chapters: Arc::new(RwLock::new(Vec::new())),
             text_tracks: Arc::new(RwLock::new(Vec::new())),
+            active_text_track: Arc::new(RwLock::new(None)),
+            cues: Arc::new(RwLock::new(ActiveCues::default())),
+            text_track_style: Arc::new(RwLock::new(TextTrackStyle::default())),
+            audio_tracks: Arc::new(RwLock::new(Vec::new())),
+            active_audio_track: Arc::new(RwLock::new(None)),
+            quality_override: Arc::new(RwLock::new(None)),
+            metrics: Arc::new(RwLock::new(QualityMetrics::default())),
+            queue: Arc::new(RwLock::new(PlaybackQueue::default())),
+            progress: OnceLock::new(),
         }
     }
 }
@@ -54,6 +82,71 @@ pub struct TextTrackInfo {
     pub active: bool,
 }
 
+/// Caption cue for the frontend's subtitle overlay
+///
+/// `position`, `line` and `size` are the WebVTT cue settings, in percent
+/// except for line numbers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CueInfo {
+    pub id: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// Cue text without markup
+    pub text: String,
+    /// `start`, `center`, `end`, `left` or `right`
+    pub align: Option<String>,
+    pub position: Option<f64>,
+    pub line: Option<f64>,
+    pub size: Option<f64>,
+}
+
+impl From<&TextCue> for CueInfo {
+    fn from(cue: &TextCue) -> Self {
+        let settings = cue.settings.as_ref();
+        Self {
+            id: cue.id.clone(),
+            start_time: cue.start_time,
+            end_time: cue.end_time,
+            text: WebVttParser::strip_tags(&cue.text),
+            align: settings.and_then(|s| s.align).map(|a| format!("{:?}", a).to_lowercase()),
+            position: settings.and_then(|s| s.position),
+            line: settings.and_then(|s| s.line),
+            size: settings.and_then(|s| s.size),
+        }
+    }
+}
+
+/// Subtitle appearance
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextTrackStyle {
+    /// Multiplier applied to the default font size
+    pub font_scale: f64,
+    /// Opacity of the box behind the text, from 0 (none) to 1 (opaque)
+    pub background_opacity: f64,
+}
+
+impl Default for TextTrackStyle {
+    fn default() -> Self {
+        Self {
+            font_scale: 1.0,
+            background_opacity: 0.5,
+        }
+    }
+}
+
+/// Audio track info for frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioTrackInfo {
+    pub id: String,
+    pub language: String,
+    pub label: String,
+    pub channels: Option<u8>,
+    pub active: bool,
+}
+
 /// Theme colors for frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -102,13 +195,36 @@ pub async fn stop(_state: State<'_, AppState>) -> Result<(), String> {
     Ok(())
 }
 
-/// Seek - just logs, frontend handles
+/// Seek - frontend handles playback; the active cues are re-resolved
 #[tauri::command]
-pub async fn seek(_state: State<'_, AppState>, position: f64) -> Result<(), String> {
+pub async fn seek(state: State<'_, AppState>, position: f64) -> Result<(), String> {
     tracing::info!(position, "Seeking");
+    state.cues.write().await.reset();
     Ok(())
 }
 
+/// Frame-aligned position one frame forward or backward
+///
+/// The frontend binds this to the arrow keys and seeks its video element
+/// to the returned position; `frame_rate` defaults to 30 when unknown.
+#[tauri::command]
+pub async fn step_frame(
+    _state: State<'_, AppState>,
+    direction: String,
+    position: f64,
+    frame_rate: Option<f64>,
+) -> Result<f64, String> {
+    let frame_rate = frame_rate.filter(|f| f.is_finite() && *f > 0.0).unwrap_or(30.0);
+    let frame = (position.max(0.0) * frame_rate).round();
+    let target = match direction.as_str() {
+        "forward" => frame + 1.0,
+        "backward" => (frame - 1.0).max(0.0),
+        other => return Err(format!("Invalid step direction: {}", other)),
+    };
+    tracing::debug!(direction = %direction, frame = target, "Stepping frame");
+    Ok(target / frame_rate)
+}
+
 /// Set volume - frontend handles
 #[tauri::command]
 pub async fn set_volume(_state: State<'_, AppState>, _volume: f64) -> Result<(), String> {
@@ -128,24 +244,43 @@ pub async fn set_playback_rate(_state: State<'_, AppState>, _rate: f64) -> Resul
 }
 
 /// Get player state - frontend provides this
+///
+/// Includes the last reported quality metrics (dropped frames,
+/// resolution, decoder backend) for the stats overlay.
 #[tauri::command]
-pub async fn get_state(_state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+pub async fn get_state(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let metrics = state.metrics.read().await.clone();
     Ok(serde_json::json!({
         "state": "idle",
         "position": 0.0,
-        "duration": null
+        "duration": null,
+        "metrics": metrics,
+        "qoeScore": metrics.qoe_score()
     }))
 }
 
+/// Store playback metrics measured by the frontend
+#[tauri::command]
+pub async fn report_metrics(state: State<'_, AppState>, metrics: QualityMetrics) -> Result<(), String> {
+    let fallback = |m: &QualityMetrics| m.hardware_backend.as_ref().is_some_and(|b| b.fallback);
+    let mut current = state.metrics.write().await;
+    if fallback(&metrics) && !fallback(&current) {
+        tracing::warn!("Hardware decoding fell back to software");
+    }
+    *current = metrics;
+    Ok(())
+}
+
 /// Get quality levels - frontend provides this
 #[tauri::command]
 pub async fn get_qualities(_state: State<'_, AppState>) -> Result<Vec<serde_json::Value>, String> {
     Ok(vec![])
 }
 
-/// Set quality - frontend handles
+/// Pin a quality, or pass `None` to return to automatic selection
 #[tauri::command]
-pub async fn set_quality(_state: State<'_, AppState>, _quality_id: String) -> Result<(), String> {
+pub async fn set_quality(state: State<'_, AppState>, quality_id: Option<String>) -> Result<(), String> {
+    *state.quality_override.write().await = quality_id;
     Ok(())
 }
 
@@ -162,25 +297,251 @@ pub async fn get_chapters(state: State<'_, AppState>) -> Result<Vec<ChapterInfo>
     }).collect())
 }
 
+fn text_track_info(track: &TextTrack, active: bool) -> TextTrackInfo {
+    TextTrackInfo {
+        id: track.id.clone(),
+        kind: format!("{:?}", track.kind),
+        language: track.language.clone(),
+        label: track.label.clone(),
+        active,
+    }
+}
+
 /// Get text tracks
 #[tauri::command]
 pub async fn get_text_tracks(state: State<'_, AppState>) -> Result<Vec<TextTrackInfo>, String> {
     let tracks = state.text_tracks.read().await;
-    Ok(tracks.iter().map(|t| TextTrackInfo {
+    let active = state.active_text_track.read().await;
+    Ok(tracks.iter().map(|t| text_track_info(t, active.as_deref() == Some(t.id.as_str()))).collect())
+}
+
+/// Add a sidecar WebVTT or SRT track from a URL or local file path
+#[tauri::command]
+pub async fn add_text_track(
+    state: State<'_, AppState>,
+    url: String,
+    language: Option<String>,
+    label: Option<String>,
+) -> Result<TextTrackInfo, String> {
+    // Windows paths like C:\subs.srt parse as URLs with a one-letter scheme
+    let url = match Url::parse(&url) {
+        Ok(parsed) if parsed.scheme().len() > 1 => parsed,
+        _ => Url::from_file_path(&url).map_err(|_| format!("Invalid text track location: {}", url))?,
+    };
+    let format = url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| name.rsplit_once('.'))
+        .and_then(|(_, extension)| TextTrackFormat::from_extension(extension))
+        .filter(|format| matches!(format, TextTrackFormat::WebVtt | TextTrackFormat::Srt))
+        .ok_or_else(|| format!("Unsupported text track (expected .vtt or .srt): {}", url))?;
+
+    let mut tracks = state.text_tracks.write().await;
+    let language = language.unwrap_or_else(|| "und".to_string());
+    let label = label.unwrap_or_else(|| language.clone());
+    let id = format!("external-{}", tracks.len());
+    let track = TextTrack::new(id, TextTrackKind::Subtitles, language, label, url, format);
+    tracing::info!(id = %track.id, url = %track.url, "Added text track");
+    let info = text_track_info(&track, false);
+    tracks.push(track);
+    Ok(info)
+}
+
+/// Load the cues of a text track, or pass `None` to turn captions off
+#[tauri::command]
+pub async fn set_text_track(state: State<'_, AppState>, track_id: Option<String>) -> Result<(), String> {
+    let cues = match &track_id {
+        Some(id) => {
+            let track = state.text_tracks.read().await
+                .iter()
+                .find(|t| t.id == *id)
+                .cloned()
+                .ok_or_else(|| format!("Text track not found: {}", id))?;
+            let fetcher = kino_core::http::default_fetcher(Duration::from_millis(PlayerConfig::default().request_timeout_ms));
+            kino_core::load_text_track(fetcher.as_ref(), &track).await.map_err(|e| e.to_string())?
+        }
+        None => Vec::new(),
+    };
+    tracing::info!(track = ?track_id, cues = cues.len(), "Text track selected");
+    *state.cues.write().await = ActiveCues::new(cues);
+    *state.active_text_track.write().await = track_id;
+    Ok(())
+}
+
+/// Cues to display at `position` (seconds) of the active text track
+///
+/// The frontend calls this on each `timeupdate`; jumps backward or more
+/// than a second ahead re-resolve the cues as after a seek.
+#[tauri::command]
+pub async fn get_active_cues(state: State<'_, AppState>, position: f64) -> Result<Vec<CueInfo>, String> {
+    let mut cues = state.cues.write().await;
+    cues.update(position);
+    Ok(cues.active().into_iter().map(CueInfo::from).collect())
+}
+
+/// Set the subtitle font scale and background opacity
+///
+/// Emits `text-track-style` with the new style.
+#[tauri::command]
+pub async fn set_text_track_style(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    font_scale: Option<f64>,
+    background_opacity: Option<f64>,
+) -> Result<TextTrackStyle, String> {
+    let mut style = state.text_track_style.write().await;
+    let font_scale = font_scale.unwrap_or(style.font_scale);
+    let background_opacity = background_opacity.unwrap_or(style.background_opacity);
+    if !(font_scale.is_finite() && font_scale > 0.0) {
+        return Err(format!("Font scale must be positive, got {}", font_scale));
+    }
+    if !(0.0..=1.0).contains(&background_opacity) {
+        return Err(format!("Background opacity must be between 0 and 1, got {}", background_opacity));
+    }
+    *style = TextTrackStyle { font_scale, background_opacity };
+    app.emit("text-track-style", *style).map_err(|e| e.to_string())?;
+    Ok(*style)
+}
+
+/// Get audio tracks
+#[tauri::command]
+pub async fn get_audio_tracks(state: State<'_, AppState>) -> Result<Vec<AudioTrackInfo>, String> {
+    let tracks = state.audio_tracks.read().await;
+    let active = state.active_audio_track.read().await;
+    Ok(tracks.iter().map(|t| AudioTrackInfo {
         id: t.id.clone(),
-        kind: format!("{:?}", t.kind),
         language: t.language.clone(),
         label: t.label.clone(),
-        active: t.is_default,
+        channels: t.channels,
+        active: match active.as_ref() {
+            Some(id) => *id == t.id,
+            None => t.is_default,
+        },
     }).collect())
 }
 
-/// Set text track
+/// Set audio track
 #[tauri::command]
-pub async fn set_text_track(_state: State<'_, AppState>, _track_id: Option<String>) -> Result<(), String> {
+pub async fn set_audio_track(state: State<'_, AppState>, track_id: String) -> Result<(), String> {
+    let tracks = state.audio_tracks.read().await;
+    if !tracks.iter().any(|t| t.id == track_id) {
+        return Err(format!("Audio track not found: {}", track_id));
+    }
+    *state.active_audio_track.write().await = Some(track_id);
     Ok(())
 }
 
+// ============================================================================
+// Watch progress
+// ============================================================================
+
+fn progress_store(state: &AppState) -> Result<&Arc<dyn WatchProgressStore>, String> {
+    state.progress.get().ok_or_else(|| "Watch progress is unavailable".to_string())
+}
+
+/// Position to offer resuming `content_key` from, for a "Resume / Start
+/// over" prompt; `None` if never watched or watched nearly to the end
+#[tauri::command]
+pub async fn get_resume_position(state: State<'_, AppState>, content_key: String) -> Result<Option<f64>, String> {
+    let progress = progress_store(&state)?.load(&content_key).await.map_err(|e| e.to_string())?;
+    let threshold = PlayerConfig::default().resume_finished_threshold;
+    Ok(progress.and_then(|p| p.resume_position(threshold)))
+}
+
+/// Save the position reached in `content_key`
+#[tauri::command]
+pub async fn save_watch_progress(
+    state: State<'_, AppState>,
+    content_key: String,
+    position: f64,
+    duration: Option<f64>,
+) -> Result<(), String> {
+    progress_store(&state)?
+        .save(&content_key, position, duration, chrono::Utc::now())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Forget the progress of `content_key` ("Start over")
+#[tauri::command]
+pub async fn clear_watch_progress(state: State<'_, AppState>, content_key: String) -> Result<(), String> {
+    progress_store(&state)?.clear(&content_key).await.map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Queue
+// ============================================================================
+
+/// Queue contents with the current item, for the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueInfo {
+    pub items: Vec<QueueItemInfo>,
+    pub current_index: Option<usize>,
+}
+
+/// Append an item to the queue
+#[tauri::command]
+pub async fn queue_add(
+    state: State<'_, AppState>,
+    url: String,
+    metadata: Option<serde_json::Value>,
+) -> Result<QueueItem, String> {
+    let item = state.queue.write().await.add(url, metadata.unwrap_or_default())?;
+    tracing::info!(id = item.id, url = %item.url, "Queued item");
+    Ok(item)
+}
+
+/// Remove the item at `index`
+#[tauri::command]
+pub async fn queue_remove(state: State<'_, AppState>, index: usize) -> Result<QueueItem, String> {
+    state.queue.write().await.remove(index)
+}
+
+/// Move an item within the queue
+#[tauri::command]
+pub async fn queue_move(state: State<'_, AppState>, from: usize, to: usize) -> Result<(), String> {
+    state.queue.write().await.move_item(from, to)
+}
+
+/// Get queue contents
+#[tauri::command]
+pub async fn get_queue(state: State<'_, AppState>) -> Result<QueueInfo, String> {
+    let queue = state.queue.read().await;
+    Ok(QueueInfo {
+        items: queue.items(),
+        current_index: queue.current_index(),
+    })
+}
+
+/// Start playing the item at `index`
+#[tauri::command]
+pub async fn play_index(state: State<'_, AppState>, index: usize) -> Result<QueueTransition, String> {
+    let transition = state.queue.write().await.play_index(index).await?;
+    tracing::info!(index, preloaded = transition.preloaded, "Playing queue item");
+    *state.current_url.write().await = Some(transition.item.url.clone());
+    Ok(transition)
+}
+
+/// Current item finished - advance the queue
+///
+/// Emits `queue-advance` with the next item, or `queue-ended` when the
+/// last item finished.
+#[tauri::command]
+pub async fn playback_ended(app: AppHandle, state: State<'_, AppState>) -> Result<Option<QueueTransition>, String> {
+    let transition = state.queue.write().await.advance().await;
+    match &transition {
+        Some(transition) => {
+            tracing::info!(index = transition.index, preloaded = transition.preloaded, "Advancing queue");
+            *state.current_url.write().await = Some(transition.item.url.clone());
+            app.emit("queue-advance", transition).map_err(|e| e.to_string())?;
+        }
+        None => {
+            app.emit("queue-ended", ()).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(transition)
+}
+
 /// Get Kino theme colors
 #[tauri::command]
 pub fn get_theme() -> ThemeColors {
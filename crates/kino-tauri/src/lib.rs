@@ -3,5 +3,6 @@
 //! This library provides the Tauri IPC commands for the Kino desktop application.
 
 pub mod commands;
+pub mod queue;
 
 pub use commands::AppState;
@@ -4,10 +4,14 @@
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::sync::Arc;
+
 use commands::AppState;
+use kino_core::JsonFileProgressStore;
 use tauri::Manager;
 
 mod commands;
+mod queue;
 
 fn main() {
     // Initialize tracing
@@ -35,14 +39,32 @@ fn main() {
             commands::set_volume,
             commands::set_muted,
             commands::set_playback_rate,
+            commands::step_frame,
             // State queries
             commands::get_state,
+            commands::report_metrics,
             commands::get_qualities,
             commands::set_quality,
             // Chapters & tracks
             commands::get_chapters,
             commands::get_text_tracks,
+            commands::add_text_track,
             commands::set_text_track,
+            commands::get_active_cues,
+            commands::set_text_track_style,
+            commands::get_audio_tracks,
+            commands::set_audio_track,
+            // Watch progress
+            commands::get_resume_position,
+            commands::save_watch_progress,
+            commands::clear_watch_progress,
+            // Queue
+            commands::queue_add,
+            commands::queue_remove,
+            commands::queue_move,
+            commands::get_queue,
+            commands::play_index,
+            commands::playback_ended,
             // Theme & info
             commands::get_theme,
             commands::get_version,
@@ -50,6 +72,14 @@ fn main() {
         .setup(|app| {
             tracing::info!("Kino initialized");
 
+            match app.path().app_data_dir() {
+                Ok(dir) => {
+                    let store = Arc::new(JsonFileProgressStore::new(dir.join("watch_progress.json")));
+                    let _ = app.state::<AppState>().progress.set(store);
+                }
+                Err(e) => tracing::warn!(error = %e, "No app data directory, watch progress disabled"),
+            }
+
             // Open devtools in debug mode
             #[cfg(debug_assertions)]
             if let Some(window) = app.get_webview_window("main") {
@@ -0,0 +1,287 @@
+//! Playback queue with gapless preloading
+//!
+//! The queue tracks items by ID, so moving or removing entries never
+//! changes which item is playing. While the current item plays, the next
+//! one is loaded in a separate session: its manifest is parsed and its
+//! buffer warmed up to `QueueConfig::preload_buffer` seconds.
+
+use kino_core::manifest::create_parser;
+use kino_core::{PlayerConfig, PlayerSession};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::async_runtime::JoinHandle;
+use url::Url;
+
+/// Queue preloading configuration
+#[derive(Debug, Clone)]
+pub struct QueueConfig {
+    /// Preload the next item while the current one plays
+    pub preload_enabled: bool,
+    /// Seconds of the next item to buffer ahead of the transition
+    pub preload_buffer: f64,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            preload_enabled: true,
+            preload_buffer: 10.0,
+        }
+    }
+}
+
+/// Queue entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueItem {
+    pub id: u64,
+    pub url: String,
+    /// Arbitrary frontend data (title, lesson number, ...)
+    pub metadata: serde_json::Value,
+}
+
+/// Queue entry with its playback status, for the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueItemInfo {
+    #[serde(flatten)]
+    pub item: QueueItem,
+    pub current: bool,
+    /// Manifest parsed and buffer warmed
+    pub preloaded: bool,
+}
+
+/// Result of switching to another queue item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueTransition {
+    pub index: usize,
+    pub item: QueueItem,
+    /// Whether the item was ready before the switch
+    pub preloaded: bool,
+    /// Seconds buffered by the preloader
+    pub buffered: f64,
+}
+
+/// Background load of an upcoming item
+struct Preload {
+    item_id: u64,
+    session: Arc<PlayerSession>,
+    ready: Arc<AtomicBool>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for Preload {
+    fn drop(&mut self) {
+        // A skipped preload must not keep fetching segments
+        self.task.abort();
+    }
+}
+
+/// Ordered playback queue
+pub struct PlaybackQueue {
+    config: QueueConfig,
+    items: Vec<QueueItem>,
+    current: Option<u64>,
+    next_id: u64,
+    preload: Option<Preload>,
+    /// Session of the current item, when it was preloaded
+    current_session: Option<Arc<PlayerSession>>,
+}
+
+impl PlaybackQueue {
+    pub fn new(config: QueueConfig) -> Self {
+        Self {
+            config,
+            items: Vec::new(),
+            current: None,
+            next_id: 1,
+            preload: None,
+            current_session: None,
+        }
+    }
+
+    /// Append an item, returning it with its assigned ID
+    pub fn add(&mut self, url: String, metadata: serde_json::Value) -> Result<QueueItem, String> {
+        Url::parse(&url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+
+        let item = QueueItem {
+            id: self.next_id,
+            url,
+            metadata,
+        };
+        self.next_id += 1;
+        self.items.push(item.clone());
+        self.refresh_preload();
+        Ok(item)
+    }
+
+    /// Remove the item at `index`
+    ///
+    /// Removing the current item leaves nothing current; the frontend
+    /// decides what plays next.
+    pub fn remove(&mut self, index: usize) -> Result<QueueItem, String> {
+        if index >= self.items.len() {
+            return Err(format!("Queue index out of range: {}", index));
+        }
+        let item = self.items.remove(index);
+        if self.current == Some(item.id) {
+            self.current = None;
+            self.current_session = None;
+        }
+        self.refresh_preload();
+        Ok(item)
+    }
+
+    /// Move the item at `from` so it ends up at `to`
+    pub fn move_item(&mut self, from: usize, to: usize) -> Result<(), String> {
+        let len = self.items.len();
+        if from >= len || to >= len {
+            return Err(format!("Queue index out of range: {} -> {} (length {})", from, to, len));
+        }
+        let item = self.items.remove(from);
+        self.items.insert(to, item);
+        self.refresh_preload();
+        Ok(())
+    }
+
+    /// Snapshot of the queue
+    pub fn items(&self) -> Vec<QueueItemInfo> {
+        self.items.iter().map(|item| QueueItemInfo {
+            item: item.clone(),
+            current: self.current == Some(item.id),
+            preloaded: self.is_preloaded(item.id),
+        }).collect()
+    }
+
+    /// Index of the current item
+    pub fn current_index(&self) -> Option<usize> {
+        self.current.and_then(|id| self.index_of(id))
+    }
+
+    /// Make the item at `index` current
+    pub async fn play_index(&mut self, index: usize) -> Result<QueueTransition, String> {
+        let item = self.items.get(index)
+            .cloned()
+            .ok_or_else(|| format!("Queue index out of range: {}", index))?;
+
+        // Keep the session of a finished preload; one still loading is
+        // aborted, as is a preload for any other item
+        let preloaded = self.is_preloaded(item.id);
+        self.current_session = if preloaded {
+            self.preload.take().map(|p| p.session.clone())
+        } else {
+            None
+        };
+        self.current = Some(item.id);
+        self.refresh_preload();
+
+        let buffered = match &self.current_session {
+            Some(session) => session.buffer_level().await,
+            None => 0.0,
+        };
+        Ok(QueueTransition { index, item, preloaded, buffered })
+    }
+
+    /// Advance past the current item after it ended
+    ///
+    /// Returns `None` at the end of the queue or when nothing is current.
+    pub async fn advance(&mut self) -> Option<QueueTransition> {
+        let next = self.current_index()? + 1;
+        self.play_index(next).await.ok()
+    }
+
+    fn index_of(&self, id: u64) -> Option<usize> {
+        self.items.iter().position(|item| item.id == id)
+    }
+
+    fn is_preloaded(&self, id: u64) -> bool {
+        self.preload.as_ref()
+            .is_some_and(|p| p.item_id == id && p.ready.load(Ordering::Acquire))
+    }
+
+    /// The item that plays after the current one
+    fn upcoming(&self) -> Option<&QueueItem> {
+        let index = self.current_index()?;
+        self.items.get(index + 1)
+    }
+
+    /// Point the preloader at the upcoming item
+    ///
+    /// Called after every change to the queue or current item. A preload
+    /// for an item that is no longer upcoming is dropped, which aborts it.
+    fn refresh_preload(&mut self) {
+        let upcoming = if self.config.preload_enabled {
+            self.upcoming().cloned()
+        } else {
+            None
+        };
+
+        match (&self.preload, upcoming) {
+            (Some(preload), Some(item)) if preload.item_id == item.id => {}
+            (_, Some(item)) => self.preload = Some(self.start_preload(item)),
+            (_, None) => self.preload = None,
+        }
+    }
+
+    fn start_preload(&self, item: QueueItem) -> Preload {
+        let config = PlayerConfig {
+            max_buffer_time: self.config.preload_buffer,
+            analytics_enabled: false,
+            ..PlayerConfig::default()
+        };
+        let session = Arc::new(PlayerSession::new(config));
+        let ready = Arc::new(AtomicBool::new(false));
+
+        let task = {
+            let session = session.clone();
+            let ready = ready.clone();
+            let url = item.url.clone();
+            tauri::async_runtime::spawn(async move {
+                match warm_up(&session, &url).await {
+                    Ok(segments) => {
+                        tracing::debug!(url = %url, segments, "Preloaded next queue item");
+                        ready.store(true, Ordering::Release);
+                    }
+                    Err(e) => tracing::warn!(url = %url, error = %e, "Failed to preload next queue item"),
+                }
+            })
+        };
+
+        Preload {
+            item_id: item.id,
+            session,
+            ready,
+            task,
+        }
+    }
+}
+
+impl Default for PlaybackQueue {
+    fn default() -> Self {
+        Self::new(QueueConfig::default())
+    }
+}
+
+/// Parse the manifest and buffer the first segments of the initial
+/// rendition, returning how many segments were fetched
+async fn warm_up(session: &PlayerSession, url: &str) -> anyhow::Result<usize> {
+    let url = Url::parse(url)?;
+    session.load(&url).await?;
+
+    let Some(rendition) = session.current_rendition().await else {
+        return Ok(0);
+    };
+    let segments = create_parser(&rendition.uri).parse_variant(&rendition.uri).await?;
+    let playlists = HashMap::from([(rendition.id.clone(), segments)]);
+
+    let mut fetched = 0;
+    for request in session.schedule_prefetch(&playlists).await {
+        if session.prefetch(&request).await? {
+            fetched += 1;
+        }
+    }
+    Ok(fetched)
+}
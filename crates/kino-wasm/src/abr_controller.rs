@@ -27,12 +27,25 @@
 //!     hls.currentLevel = level;
 //!   }
 //! });
+//!
+//! // Option 3: Feed hls.js loader stats and inspect each decision
+//! hls.on(Hls.Events.FRAG_LOADED, (_, data) => {
+//!   psm.ingest_fragment_stats(JSON.stringify({
+//!     ...data.frag.stats,
+//!     level: data.frag.level,
+//!   }));
+//!   const decision = psm.pick_level(levelsJson, bufferLevel, hls.liveSyncPosition !== null);
+//!   hls.nextLoadLevel = decision.level;
+//!   overlay.render(JSON.parse(psm.get_decision_history()));
+//! });
 //! ```
 
 use wasm_bindgen::prelude::*;
 use serde::{Serialize, Deserialize};
 use std::collections::VecDeque;
 
+use crate::config::WasmConfig;
+
 /// Level/Quality information from hls.js
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Level {
@@ -42,6 +55,118 @@ pub struct Level {
     pub codec: Option<String>,
 }
 
+/// Fragment loader stats as reported by hls.js on `FRAG_LOADED`
+///
+/// Accepts both the legacy flat timings (`trequest`/`tfirst`/`tload`) and
+/// the `loading: {start, first, end}` block used by newer hls.js releases.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+struct FragmentStats {
+    trequest: Option<f64>,
+    tfirst: Option<f64>,
+    tload: Option<f64>,
+    loading: Option<LoadingTimes>,
+    total: Option<usize>,
+    loaded: Option<usize>,
+    level: Option<i32>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+struct LoadingTimes {
+    start: f64,
+    first: f64,
+    end: f64,
+}
+
+impl FragmentStats {
+    /// Request, first byte and load-complete timestamps in ms
+    fn timings(&self) -> Option<(f64, f64, f64)> {
+        if let Some(loading) = &self.loading {
+            return Some((loading.start, loading.first, loading.end));
+        }
+        let request = self.trequest?;
+        let load = self.tload?;
+        Some((request, self.tfirst.unwrap_or(request), load))
+    }
+
+    fn bytes(&self) -> usize {
+        self.total.filter(|&total| total > 0).or(self.loaded).unwrap_or(0)
+    }
+}
+
+/// Rule that determined a level decision
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SwitchRule {
+    /// A higher level needed more than the safe share of bandwidth
+    ThroughputCap,
+    /// Buffer fell below the panic threshold, so the lowest level was used
+    BufferPanic,
+    /// A higher level was excluded by `set_max_bitrate` or the muted cap
+    MaxBitrateCap,
+    /// BOLA's buffer utility preferred this level
+    BufferUtility,
+    /// Already at the highest level
+    HighestLevel,
+    /// The stability filter kept the previous level
+    StabilityHold,
+    /// The levels JSON was empty or invalid
+    InvalidLevels,
+    /// First decision with `start_at_lowest` configured
+    StartLowest,
+}
+
+impl SwitchRule {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::ThroughputCap => "throughput_cap",
+            Self::BufferPanic => "buffer_panic",
+            Self::MaxBitrateCap => "max_bitrate_cap",
+            Self::BufferUtility => "buffer_utility",
+            Self::HighestLevel => "highest_level",
+            Self::StabilityHold => "stability_hold",
+            Self::InvalidLevels => "invalid_levels",
+            Self::StartLowest => "start_lowest",
+        }
+    }
+}
+
+/// A single level decision, kept for the debug overlay
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AbrDecision {
+    /// Decision number since the last reset
+    pub sequence: u32,
+    /// Selected level index
+    pub level: i32,
+    /// Bitrate of the selected level in bps
+    pub bitrate: u32,
+    /// Level that was selected before this decision (-1 if none)
+    pub previous_level: i32,
+    /// Bandwidth estimate used, in bps
+    pub estimated_bandwidth: f64,
+    /// Buffer level used, in seconds
+    pub buffer_level: f64,
+    /// Whether live rules applied
+    pub is_live: bool,
+    /// Rule that triggered the decision
+    rule: String,
+}
+
+#[wasm_bindgen]
+impl AbrDecision {
+    #[wasm_bindgen(getter)]
+    pub fn rule(&self) -> String {
+        self.rule.clone()
+    }
+
+    /// Convert to JSON string
+    #[wasm_bindgen]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
 /// Bandwidth measurement sample
 #[derive(Clone)]
 struct BandwidthSample {
@@ -83,6 +208,16 @@ pub struct KinoAbrController {
     buffer_max: f64,
     /// Maximum bitrate cap
     max_bitrate: u32,
+    /// Bitrate cap until `on_unmuted` (0 = none)
+    muted_max_bitrate: u32,
+    /// Pick the lowest level for the first decision
+    start_at_lowest: bool,
+    /// Recent decisions from `pick_level`
+    decisions: VecDeque<AbrDecision>,
+    /// Maximum decisions kept
+    max_decisions: usize,
+    /// Decisions made since the last reset
+    decision_count: u32,
 }
 
 #[wasm_bindgen]
@@ -102,6 +237,11 @@ impl KinoAbrController {
             buffer_min: 5.0,
             buffer_max: 30.0,
             max_bitrate: 0,
+            muted_max_bitrate: 0,
+            start_at_lowest: false,
+            decisions: VecDeque::with_capacity(50),
+            max_decisions: 50,
+            decision_count: 0,
         }
     }
 
@@ -113,6 +253,23 @@ impl KinoAbrController {
         controller
     }
 
+    /// Create controller from a player config
+    #[wasm_bindgen]
+    pub fn from_config(config: &WasmConfig) -> Self {
+        let mut controller = Self::with_algorithm(&config.abr_algorithm());
+        controller.set_buffer_thresholds(config.min_buffer_time(), config.max_buffer_time());
+        controller.max_bitrate = config.max_bitrate;
+        controller.muted_max_bitrate = config.muted_max_bitrate;
+        controller.start_at_lowest = config.start_at_lowest;
+        controller
+    }
+
+    /// Lift the muted autoplay bitrate cap once playback is unmuted
+    #[wasm_bindgen]
+    pub fn on_unmuted(&mut self) {
+        self.muted_max_bitrate = 0;
+    }
+
     /// Set maximum bitrate cap
     #[wasm_bindgen]
     pub fn set_max_bitrate(&mut self, max_bitrate: u32) {
@@ -129,26 +286,50 @@ impl KinoAbrController {
     /// Record a bandwidth measurement (called after each segment download)
     #[wasm_bindgen]
     pub fn record_download(&mut self, bytes: usize, duration_ms: f64) {
-        let sample = BandwidthSample {
+        self.record_sample(BandwidthSample {
             bytes,
             duration_ms,
-            _timestamp: js_sys::Date::now(),
+            _timestamp: crate::now_ms(),
+        });
+    }
+
+    /// Record the stats object from an hls.js `FRAG_LOADED` event
+    ///
+    /// The download time runs from request to load completion, so
+    /// time-to-first-byte counts against the estimate. When `level` is
+    /// present it becomes the reference for the stability filter, keeping
+    /// the controller in sync with manual or capped switches made by hls.js.
+    ///
+    /// # Returns
+    /// `false` if the JSON could not be parsed or carried no usable timings
+    #[wasm_bindgen]
+    pub fn ingest_fragment_stats(&mut self, stats_json: &str) -> bool {
+        let stats: FragmentStats = match serde_json::from_str(stats_json) {
+            Ok(s) => s,
+            Err(_) => return false,
         };
 
-        // Update history
-        if self.bandwidth_history.len() >= self.max_history {
-            self.bandwidth_history.pop_front();
+        let Some((request, _first, load)) = stats.timings() else {
+            return false;
+        };
+        let bytes = stats.bytes();
+        if bytes == 0 || load <= request {
+            return false;
         }
-        self.bandwidth_history.push_back(sample.clone());
 
-        // Update estimate using EWMA (Exponentially Weighted Moving Average)
-        let throughput = sample.throughput_bps();
-        if self.bandwidth_estimate == 0.0 {
-            self.bandwidth_estimate = throughput;
-        } else {
-            // EWMA with alpha = 0.2 for smoothing
-            self.bandwidth_estimate = self.bandwidth_estimate * 0.8 + throughput * 0.2;
+        self.record_sample(BandwidthSample {
+            bytes,
+            duration_ms: load - request,
+            _timestamp: load,
+        });
+
+        if let Some(level) = stats.level.filter(|&level| level >= 0) {
+            if level != self.last_level {
+                self.last_level = level;
+                self.stability_count = 0;
+            }
         }
+        true
     }
 
     /// Select the best level based on current conditions
@@ -161,34 +342,60 @@ impl KinoAbrController {
     /// Index of the recommended level
     #[wasm_bindgen]
     pub fn select_level(&mut self, levels_json: &str, buffer_level: f64) -> i32 {
-        let levels: Vec<Level> = match serde_json::from_str(levels_json) {
-            Ok(l) => l,
-            Err(_) => return 0,
-        };
-
-        if levels.is_empty() {
-            return 0;
+        match serde_json::from_str::<Vec<Level>>(levels_json) {
+            Ok(levels) => self.decide(&levels, buffer_level, false).0,
+            Err(_) => 0,
         }
+    }
 
-        let selected = match self.algorithm.as_str() {
-            "throughput" => self.select_throughput(&levels),
-            "bola" => self.select_bola(&levels, buffer_level),
-            "hybrid" => self.select_hybrid(&levels, buffer_level),
-            _ => self.select_bola(&levels, buffer_level),
+    /// Select a level and record why it was chosen
+    ///
+    /// Live streams run with shallow buffers, so the buffer panic
+    /// threshold is halved and the pick never exceeds what throughput
+    /// alone would allow.
+    ///
+    /// # Arguments
+    /// * `levels_json` - JSON array of {bitrate, width, height} objects
+    /// * `buffer_seconds` - Current buffer level in seconds
+    /// * `is_live` - Whether the stream is live
+    #[wasm_bindgen]
+    pub fn pick_level(&mut self, levels_json: &str, buffer_seconds: f64, is_live: bool) -> AbrDecision {
+        let previous_level = self.last_level;
+        let levels: Vec<Level> = serde_json::from_str(levels_json).unwrap_or_default();
+        let (level, rule) = self.decide(&levels, buffer_seconds, is_live);
+
+        let decision = AbrDecision {
+            sequence: self.decision_count,
+            level,
+            bitrate: levels.get(level as usize).map(|l| l.bitrate).unwrap_or(0),
+            previous_level,
+            estimated_bandwidth: self.bandwidth_estimate,
+            buffer_level: buffer_seconds,
+            is_live,
+            rule: rule.as_str().to_string(),
         };
 
-        // Apply stability filter to prevent rapid oscillation
-        let selected_i32 = selected as i32;
-        if self.last_level >= 0 && selected_i32 != self.last_level {
-            self.stability_count += 1;
-            if self.stability_count < 3 {
-                return self.last_level;
-            }
+        self.decision_count += 1;
+        if self.decisions.len() >= self.max_decisions {
+            self.decisions.pop_front();
         }
+        self.decisions.push_back(decision.clone());
+        decision
+    }
 
-        self.stability_count = 0;
-        self.last_level = selected_i32;
-        selected_i32
+    /// Get the most recent `pick_level` decisions as a JSON array, oldest first
+    #[wasm_bindgen]
+    pub fn get_decision_history(&self) -> String {
+        serde_json::to_string(&self.decisions).unwrap_or("[]".to_string())
+    }
+
+    /// Set how many decisions `get_decision_history` keeps (default 50)
+    #[wasm_bindgen]
+    pub fn set_decision_history_size(&mut self, size: usize) {
+        self.max_decisions = size.max(1);
+        while self.decisions.len() > self.max_decisions {
+            self.decisions.pop_front();
+        }
     }
 
     /// Get current bandwidth estimate in bps
@@ -197,6 +404,17 @@ impl KinoAbrController {
         self.bandwidth_estimate
     }
 
+    /// Estimate seconds to download `bytes` at the current bandwidth
+    /// (`Infinity` before any measurement)
+    #[wasm_bindgen]
+    pub fn get_download_eta(&self, bytes: f64) -> f64 {
+        if self.bandwidth_estimate > 0.0 {
+            bytes * 8.0 / self.bandwidth_estimate
+        } else {
+            f64::INFINITY
+        }
+    }
+
     /// Get bandwidth estimate in human-readable format
     #[wasm_bindgen]
     pub fn get_bandwidth_display(&self) -> String {
@@ -217,45 +435,98 @@ impl KinoAbrController {
         self.bandwidth_estimate = 0.0;
         self.last_level = -1;
         self.stability_count = 0;
+        self.decisions.clear();
+        self.decision_count = 0;
     }
 }
 
 impl KinoAbrController {
+    /// Run the configured algorithm and stability filter
+    fn decide(&mut self, levels: &[Level], buffer_level: f64, is_live: bool) -> (i32, SwitchRule) {
+        if levels.is_empty() {
+            return (0, SwitchRule::InvalidLevels);
+        }
+
+        if self.start_at_lowest && self.last_level < 0 {
+            self.last_level = 0;
+            return (0, SwitchRule::StartLowest);
+        }
+
+        let panic_threshold = if is_live { self.buffer_min * 0.5 } else { self.buffer_min };
+        let (mut selected, mut rule) = match self.algorithm.as_str() {
+            "throughput" => self.select_throughput(levels),
+            "hybrid" => self.select_hybrid(levels, buffer_level, panic_threshold),
+            _ => self.select_bola(levels, buffer_level, panic_threshold),
+        };
+
+        if is_live && rule != SwitchRule::BufferPanic {
+            let (throughput_pick, throughput_rule) = self.select_throughput(levels);
+            if throughput_pick < selected {
+                selected = throughput_pick;
+                rule = throughput_rule;
+            }
+        }
+
+        // Apply stability filter to prevent rapid oscillation
+        let selected_i32 = selected as i32;
+        if self.last_level >= 0 && selected_i32 != self.last_level {
+            self.stability_count += 1;
+            if self.stability_count < 3 {
+                return (self.last_level, SwitchRule::StabilityHold);
+            }
+        }
+
+        self.stability_count = 0;
+        self.last_level = selected_i32;
+        (selected_i32, rule)
+    }
+
     /// Throughput-based selection (simple, fast)
-    fn select_throughput(&self, levels: &[Level]) -> usize {
+    fn select_throughput(&self, levels: &[Level]) -> (usize, SwitchRule) {
         // Use 80% of estimated bandwidth for safety margin
         let safe_bandwidth = (self.bandwidth_estimate * 0.8) as u32;
 
         // Find highest quality that fits
+        let cap = self.bitrate_cap();
         let mut best = 0;
+        let mut capped = false;
+        let mut throttled = false;
         for (i, level) in levels.iter().enumerate() {
-            if self.max_bitrate > 0 && level.bitrate > self.max_bitrate {
+            if cap > 0 && level.bitrate > cap {
+                capped |= level.bitrate <= safe_bandwidth;
                 continue;
             }
             if level.bitrate <= safe_bandwidth {
                 best = i;
+            } else {
+                throttled = true;
             }
         }
-        best
+
+        let rule = if capped {
+            SwitchRule::MaxBitrateCap
+        } else if throttled {
+            SwitchRule::ThroughputCap
+        } else {
+            SwitchRule::HighestLevel
+        };
+        (best, rule)
     }
 
     /// BOLA algorithm (buffer-based, proven better QoE)
     /// Paper: https://arxiv.org/abs/1601.06748
-    fn select_bola(&self, levels: &[Level], buffer_level: f64) -> usize {
+    fn select_bola(&self, levels: &[Level], buffer_level: f64, panic_threshold: f64) -> (usize, SwitchRule) {
         // Emergency: if buffer is critically low, pick lowest
-        if buffer_level < self.buffer_min {
-            return 0;
+        if buffer_level < panic_threshold {
+            return (0, SwitchRule::BufferPanic);
         }
 
+        let cap = self.bitrate_cap();
         let mut best_level = 0;
         let mut best_score = f64::NEG_INFINITY;
+        let mut best_capped_score = f64::NEG_INFINITY;
 
         for (i, level) in levels.iter().enumerate() {
-            // Skip if over max bitrate
-            if self.max_bitrate > 0 && level.bitrate > self.max_bitrate {
-                continue;
-            }
-
             // BOLA utility function: logarithmic quality
             let utility = (level.bitrate as f64).ln();
 
@@ -265,27 +536,66 @@ impl KinoAbrController {
             // BOLA objective: maximize (V * utility - buffer) / (size + gamma)
             let score = (self.bola_v * utility - buffer_level) / (size + self.bola_gamma);
 
+            // Skip if over max bitrate
+            if cap > 0 && level.bitrate > cap {
+                best_capped_score = best_capped_score.max(score);
+                continue;
+            }
+
             if score > best_score {
                 best_score = score;
                 best_level = i;
             }
         }
 
-        best_level
+        let rule = if best_capped_score > best_score {
+            SwitchRule::MaxBitrateCap
+        } else {
+            SwitchRule::BufferUtility
+        };
+        (best_level, rule)
     }
 
     /// Hybrid: combine throughput and buffer metrics
-    fn select_hybrid(&self, levels: &[Level], buffer_level: f64) -> usize {
-        let throughput_pick = self.select_throughput(levels);
-        let bola_pick = self.select_bola(levels, buffer_level);
+    fn select_hybrid(&self, levels: &[Level], buffer_level: f64, panic_threshold: f64) -> (usize, SwitchRule) {
+        let (throughput_pick, throughput_rule) = self.select_throughput(levels);
+        let (bola_pick, bola_rule) = self.select_bola(levels, buffer_level, panic_threshold);
 
         // If buffer is low, trust BOLA (more conservative)
         if buffer_level < 10.0 {
-            return bola_pick;
+            return (bola_pick, bola_rule);
+        }
+
+        // If buffer is healthy, average the two; the lower pick is the
+        // one holding quality back
+        let rule = if throughput_pick < bola_pick { throughput_rule } else { bola_rule };
+        ((throughput_pick + bola_pick) / 2, rule)
+    }
+
+    /// Tighter of the max bitrate and the muted cap (0 = unlimited)
+    fn bitrate_cap(&self) -> u32 {
+        match (self.max_bitrate, self.muted_max_bitrate) {
+            (0, cap) | (cap, 0) => cap,
+            (max, muted) => max.min(muted),
+        }
+    }
+
+    /// Fold a sample into the history and EWMA estimate
+    fn record_sample(&mut self, sample: BandwidthSample) {
+        // Update history
+        if self.bandwidth_history.len() >= self.max_history {
+            self.bandwidth_history.pop_front();
         }
+        self.bandwidth_history.push_back(sample.clone());
 
-        // If buffer is healthy, average the two
-        (throughput_pick + bola_pick) / 2
+        // Update estimate using EWMA (Exponentially Weighted Moving Average)
+        let throughput = sample.throughput_bps();
+        if self.bandwidth_estimate == 0.0 {
+            self.bandwidth_estimate = throughput;
+        } else {
+            // EWMA with alpha = 0.2 for smoothing
+            self.bandwidth_estimate = self.bandwidth_estimate * 0.8 + throughput * 0.2;
+        }
     }
 }
 
@@ -324,4 +634,125 @@ mod tests {
         let selected = controller.select_level(levels, 20.0);
         assert!(selected >= 2); // At least 720p
     }
+
+    const LEVELS: &str = r#"[
+        {"bitrate": 500000, "width": 640, "height": 360},
+        {"bitrate": 1500000, "width": 854, "height": 480},
+        {"bitrate": 3000000, "width": 1280, "height": 720},
+        {"bitrate": 6000000, "width": 1920, "height": 1080}
+    ]"#;
+
+    #[test]
+    fn test_from_config_autoplay_muted() {
+        let mut config = WasmConfig::autoplay_muted();
+        config.set_abr_algorithm("throughput".to_string());
+        let mut controller = KinoAbrController::from_config(&config);
+        controller.record_download(1_000_000, 1000.0); // 8 Mbps
+
+        let first = controller.pick_level(LEVELS, 20.0, false);
+        assert_eq!((first.level, first.rule()), (0, "start_lowest".to_string()));
+
+        // Muted playback stays under 1.5 Mbps despite the bandwidth
+        let decision = (0..3).map(|_| controller.pick_level(LEVELS, 20.0, false)).last().unwrap();
+        assert_eq!((decision.level, decision.rule()), (1, "max_bitrate_cap".to_string()));
+
+        controller.on_unmuted();
+        let decision = (0..3).map(|_| controller.pick_level(LEVELS, 20.0, false)).last().unwrap();
+        assert_eq!((decision.level, decision.rule()), (3, "highest_level".to_string()));
+
+        // The stricter of the two caps applies
+        config.max_bitrate = 1_000_000;
+        let mut controller = KinoAbrController::from_config(&config);
+        controller.record_download(1_000_000, 1000.0);
+        controller.pick_level(LEVELS, 20.0, false);
+        let decision = (0..3).map(|_| controller.pick_level(LEVELS, 20.0, false)).last().unwrap();
+        assert_eq!(decision.level, 0);
+    }
+
+    #[test]
+    fn test_ingest_fragment_stats() {
+        let mut controller = KinoAbrController::new();
+
+        // Legacy hls.js timings: 1MB requested at 0ms, loaded at 1000ms
+        assert!(controller.ingest_fragment_stats(
+            r#"{"trequest": 0, "tfirst": 50, "tload": 1000, "total": 1000000, "level": 1}"#
+        ));
+        assert!((controller.get_bandwidth_estimate() - 8_000_000.0).abs() < 1000.0);
+
+        // Newer hls.js LoadStats shape
+        assert!(controller.ingest_fragment_stats(
+            r#"{"loading": {"start": 2000, "first": 2100, "end": 2500}, "loaded": 1000000}"#
+        ));
+        assert_eq!(controller.get_sample_count(), 2);
+
+        assert!(!controller.ingest_fragment_stats("not json"));
+        assert!(!controller.ingest_fragment_stats(r#"{"total": 1000}"#));
+        assert!(!controller.ingest_fragment_stats(r#"{"trequest": 5, "tload": 5, "total": 1000}"#));
+        assert_eq!(controller.get_sample_count(), 2);
+    }
+
+    #[test]
+    fn test_pick_level_rules() {
+        let mut controller = KinoAbrController::with_algorithm("throughput");
+        controller.ingest_fragment_stats(
+            r#"{"trequest": 0, "tload": 1000, "total": 1000000, "level": 1}"#
+        );
+
+        // hls.js reported level 1, so the switch to the top level is held twice
+        let rules: Vec<String> = (0..3)
+            .map(|_| controller.pick_level(LEVELS, 20.0, false).rule())
+            .collect();
+        assert_eq!(rules, ["stability_hold", "stability_hold", "highest_level"]);
+
+        controller.set_max_bitrate(3_000_000);
+        let decision = (0..3)
+            .map(|_| controller.pick_level(LEVELS, 20.0, false))
+            .last()
+            .unwrap();
+        assert_eq!(decision.level, 2);
+        assert_eq!(decision.bitrate, 3_000_000);
+        assert_eq!(decision.previous_level, 3);
+        assert_eq!(decision.sequence, 5);
+        assert_eq!(decision.rule(), "max_bitrate_cap");
+
+        let mut controller = KinoAbrController::new();
+        controller.ingest_fragment_stats(r#"{"trequest": 0, "tload": 1000, "total": 250000}"#);
+        let decision = controller.pick_level(LEVELS, 2.0, false);
+        assert_eq!(decision.level, 0);
+        assert_eq!(decision.rule(), "buffer_panic");
+
+        // Live halves the panic threshold...
+        controller.reset();
+        controller.ingest_fragment_stats(r#"{"trequest": 0, "tload": 1000, "total": 250000}"#);
+        assert_eq!(controller.pick_level(LEVELS, 3.0, true).rule(), "buffer_utility");
+
+        // ...and caps BOLA's pick at what throughput allows (1.6 Mbps safe)
+        controller.reset();
+        controller.ingest_fragment_stats(r#"{"trequest": 0, "tload": 1000, "total": 250000}"#);
+        assert_eq!(controller.pick_level(LEVELS, 20.0, false).level, 3);
+        controller.reset();
+        controller.ingest_fragment_stats(r#"{"trequest": 0, "tload": 1000, "total": 250000}"#);
+        let decision = controller.pick_level(LEVELS, 20.0, true);
+        assert_eq!(decision.level, 1);
+        assert_eq!(decision.rule(), "throughput_cap");
+
+        assert_eq!(controller.pick_level("[]", 3.0, true).rule(), "invalid_levels");
+    }
+
+    #[test]
+    fn test_decision_history() {
+        let mut controller = KinoAbrController::with_algorithm("throughput");
+        controller.set_decision_history_size(2);
+        for _ in 0..5 {
+            controller.pick_level(LEVELS, 20.0, false);
+        }
+
+        let history: Vec<AbrDecision> = serde_json::from_str(&controller.get_decision_history()).unwrap();
+        let sequences: Vec<u32> = history.iter().map(|d| d.sequence).collect();
+        assert_eq!(sequences, [3, 4]);
+        assert_eq!(history[1].rule(), "throughput_cap");
+
+        controller.reset();
+        assert_eq!(controller.get_decision_history(), "[]");
+    }
 }
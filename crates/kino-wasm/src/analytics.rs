@@ -1,6 +1,26 @@
 //! Analytics - QoE metrics and event tracking for WASM
 //!
 //! Collects and aggregates playback metrics for quality analysis.
+//!
+//! QoE scores come from a port of kino-core's `QoeCalculator`, so a
+//! session scores the same in the browser as in the native player.
+//!
+//! ## Batched reporting
+//!
+//! ```javascript
+//! const analytics = new KinoAnalytics();
+//! analytics.set_batch_config(50, 10000); // 50 events or 10s, whichever first
+//!
+//! setInterval(() => {
+//!   const payload = analytics.flush_if_due();
+//!   if (payload) navigator.sendBeacon('/analytics', payload);
+//! }, 1000);
+//!
+//! window.addEventListener('pagehide', () => {
+//!   navigator.sendBeacon('/analytics', analytics.flush());
+//!   navigator.sendBeacon('/analytics/summary', analytics.get_session_summary().to_json());
+//! });
+//! ```
 
 use wasm_bindgen::prelude::*;
 use serde::{Serialize, Deserialize};
@@ -37,11 +57,167 @@ impl QoeMetrics {
     }
 }
 
-/// Bitrate sample for averaging
-#[derive(Clone)]
-struct BitrateSample {
-    bitrate: u32,
-    duration: f64,
+/// QoE score breakdown, field for field the same as kino-core's `QoeBreakdown`
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QoeBreakdown {
+    /// QoE score (0-100)
+    pub score: f64,
+    /// Mean opinion score (1-5)
+    pub mos: f64,
+    /// Startup delay in seconds
+    pub initial_buffer_time: f64,
+    /// Number of rebuffer events
+    pub rebuffer_count: u32,
+    /// Total rebuffer duration in seconds
+    pub rebuffer_duration: f64,
+    /// Number of quality switches
+    pub quality_switches: u32,
+    /// Time-weighted average bitrate in bps
+    average_bitrate: u64,
+}
+
+#[wasm_bindgen]
+impl QoeBreakdown {
+    #[wasm_bindgen(getter)]
+    pub fn average_bitrate(&self) -> f64 {
+        self.average_bitrate as f64
+    }
+
+    /// Convert to JSON string
+    #[wasm_bindgen]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Session totals for reporting at teardown
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    /// Time to first frame in ms (0 if playback never started)
+    pub startup_time_ms: f64,
+    /// Share of the session spent rebuffering (0-1)
+    pub rebuffer_ratio: f64,
+    /// Time-weighted average bitrate in bps
+    pub avg_bitrate: u32,
+    /// Number of quality switches
+    pub switch_count: u32,
+    /// Number of rebuffer events
+    pub rebuffer_count: u32,
+    /// Session length in seconds
+    pub watch_time: f64,
+    /// QoE score (0-100)
+    pub qoe_score: f64,
+    /// Sequence number of the last event logged
+    pub last_sequence: u32,
+}
+
+#[wasm_bindgen]
+impl SessionSummary {
+    /// Convert to JSON string
+    #[wasm_bindgen]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Port of kino-core's `QoeCalculator`; keep the scoring in sync with
+/// `kino_core::analytics` so browser and native scores match
+#[derive(Clone, Default)]
+struct QoeCalculator {
+    /// Initial buffer time
+    initial_buffer_time: f64,
+    /// Total rebuffer count
+    rebuffer_count: u32,
+    /// Total rebuffer duration
+    rebuffer_duration: f64,
+    /// Quality switches
+    quality_switches: Vec<(f64, u64)>, // (timestamp, bitrate)
+    /// Average bitrate (weighted by time)
+    bitrate_samples: Vec<(f64, u64)>, // (duration, bitrate)
+}
+
+impl QoeCalculator {
+    fn record_initial_buffer(&mut self, duration: f64) {
+        self.initial_buffer_time = duration;
+    }
+
+    fn record_rebuffer(&mut self, duration: f64) {
+        self.rebuffer_count += 1;
+        self.rebuffer_duration += duration;
+    }
+
+    fn record_quality_switch(&mut self, timestamp: f64, bitrate: u64) {
+        self.quality_switches.push((timestamp, bitrate));
+    }
+
+    fn record_bitrate(&mut self, duration: f64, bitrate: u64) {
+        self.bitrate_samples.push((duration, bitrate));
+    }
+
+    fn calculate_qoe(&self) -> f64 {
+        let mut score = 100.0;
+
+        // Penalize initial buffer time
+        // > 2s starts reducing score
+        if self.initial_buffer_time > 2.0 {
+            score -= (self.initial_buffer_time - 2.0) * 5.0;
+        }
+
+        // Penalize rebuffers heavily
+        // Each rebuffer costs 10 points
+        score -= self.rebuffer_count as f64 * 10.0;
+
+        // Penalize rebuffer duration
+        // Each second of rebuffering costs 5 points
+        score -= self.rebuffer_duration * 5.0;
+
+        // Penalize quality switches
+        // Each switch costs 2 points
+        score -= self.quality_switches.len() as f64 * 2.0;
+
+        // Bonus for high average bitrate
+        let avg_bitrate = self.average_bitrate();
+        if avg_bitrate > 5_000_000 {
+            score += 5.0;
+        } else if avg_bitrate > 2_000_000 {
+            score += 2.0;
+        }
+
+        score.clamp(0.0, 100.0)
+    }
+
+    fn average_bitrate(&self) -> u64 {
+        if self.bitrate_samples.is_empty() {
+            return 0;
+        }
+
+        let total_duration: f64 = self.bitrate_samples.iter().map(|(d, _)| d).sum();
+        if total_duration == 0.0 {
+            return 0;
+        }
+
+        let weighted_sum: f64 = self.bitrate_samples
+            .iter()
+            .map(|(d, b)| d * *b as f64)
+            .sum();
+
+        (weighted_sum / total_duration) as u64
+    }
+
+    fn breakdown(&self) -> QoeBreakdown {
+        let score = self.calculate_qoe();
+        QoeBreakdown {
+            score,
+            mos: 1.0 + score * 0.04,
+            initial_buffer_time: self.initial_buffer_time,
+            rebuffer_count: self.rebuffer_count,
+            rebuffer_duration: self.rebuffer_duration,
+            quality_switches: self.quality_switches.len() as u32,
+            average_bitrate: self.average_bitrate(),
+        }
+    }
 }
 
 /// Analytics collector and QoE calculator
@@ -51,18 +227,12 @@ pub struct KinoAnalytics {
     session_start: f64,
     /// Time to first frame
     startup_time_ms: Option<f64>,
-    /// Rebuffer events
-    rebuffer_count: u32,
-    /// Total rebuffer duration
-    rebuffer_duration: f64,
     /// Current rebuffer start (if rebuffering)
     rebuffer_start: Option<f64>,
-    /// Quality switches
-    quality_switches: u32,
     /// Last bitrate
     last_bitrate: Option<u32>,
-    /// Bitrate samples for averaging
-    bitrate_samples: VecDeque<BitrateSample>,
+    /// Rebuffer, switch and bitrate totals for scoring
+    qoe: QoeCalculator,
     /// Highest available bitrate
     max_available_bitrate: u32,
     /// Time spent at max quality
@@ -77,29 +247,53 @@ pub struct KinoAnalytics {
     events: VecDeque<AnalyticsEvent>,
     /// Max events to keep
     max_events: usize,
+    /// Sequence number for the next event
+    next_sequence: u32,
+    /// Events logged since the last flush
+    pending: Vec<AnalyticsEvent>,
+    /// Flush once this many events are pending
+    batch_size: usize,
+    /// Flush once this long has passed since the last flush (ms)
+    flush_interval_ms: f64,
+    /// Time of the last flush
+    last_flush: f64,
+    /// Number of batches flushed
+    batch_count: u32,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 struct AnalyticsEvent {
+    /// Monotonic per-session number, so receivers can spot gaps
+    seq: u32,
     event_type: String,
     timestamp: f64,
     data: serde_json::Value,
 }
 
+/// JSON payload produced by a flush
+#[derive(Serialize)]
+struct EventBatch<'a> {
+    batch: u32,
+    session_start: f64,
+    sent_at: f64,
+    first_seq: Option<u32>,
+    last_seq: Option<u32>,
+    events: &'a [AnalyticsEvent],
+    qoe: QoeBreakdown,
+}
+
 #[wasm_bindgen]
 impl KinoAnalytics {
     /// Create a new analytics collector
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
+        let now = crate::now_ms();
         Self {
-            session_start: js_sys::Date::now(),
+            session_start: now,
             startup_time_ms: None,
-            rebuffer_count: 0,
-            rebuffer_duration: 0.0,
             rebuffer_start: None,
-            quality_switches: 0,
             last_bitrate: None,
-            bitrate_samples: VecDeque::new(),
+            qoe: QoeCalculator::default(),
             max_available_bitrate: 0,
             max_quality_time: 0.0,
             total_play_time: 0.0,
@@ -107,6 +301,12 @@ impl KinoAnalytics {
             is_playing: false,
             events: VecDeque::new(),
             max_events: 1000,
+            next_sequence: 0,
+            pending: Vec::new(),
+            batch_size: 50,
+            flush_interval_ms: 10_000.0,
+            last_flush: now,
+            batch_count: 0,
         }
     }
 
@@ -114,7 +314,9 @@ impl KinoAnalytics {
     #[wasm_bindgen]
     pub fn report_first_frame(&mut self) {
         if self.startup_time_ms.is_none() {
-            self.startup_time_ms = Some(js_sys::Date::now() - self.session_start);
+            let startup_ms = crate::now_ms() - self.session_start;
+            self.startup_time_ms = Some(startup_ms);
+            self.qoe.record_initial_buffer(startup_ms / 1000.0);
             self.log_event("first_frame", serde_json::json!({
                 "startup_ms": self.startup_time_ms
             }));
@@ -140,8 +342,7 @@ impl KinoAnalytics {
     #[wasm_bindgen]
     pub fn report_rebuffer_start(&mut self, position: f64) {
         if self.rebuffer_start.is_none() {
-            self.rebuffer_start = Some(js_sys::Date::now());
-            self.rebuffer_count += 1;
+            self.rebuffer_start = Some(crate::now_ms());
             self.log_event("rebuffer_start", serde_json::json!({ "position": position }));
         }
     }
@@ -150,8 +351,8 @@ impl KinoAnalytics {
     #[wasm_bindgen]
     pub fn report_rebuffer_end(&mut self, position: f64) {
         if let Some(start) = self.rebuffer_start.take() {
-            let duration = (js_sys::Date::now() - start) / 1000.0;
-            self.rebuffer_duration += duration;
+            let duration = (crate::now_ms() - start) / 1000.0;
+            self.qoe.record_rebuffer(duration);
             self.log_event("rebuffer_end", serde_json::json!({
                 "position": position,
                 "duration_s": duration
@@ -164,7 +365,7 @@ impl KinoAnalytics {
     pub fn report_quality_change(&mut self, new_bitrate: u32, position: f64) {
         if let Some(old) = self.last_bitrate {
             if old != new_bitrate {
-                self.quality_switches += 1;
+                self.qoe.record_quality_switch(position, new_bitrate as u64);
                 self.log_event("quality_change", serde_json::json!({
                     "from": old,
                     "to": new_bitrate,
@@ -178,7 +379,7 @@ impl KinoAnalytics {
     /// Report current bitrate (for averaging)
     #[wasm_bindgen]
     pub fn report_bitrate_sample(&mut self, bitrate: u32, duration: f64) {
-        self.bitrate_samples.push_back(BitrateSample { bitrate, duration });
+        self.qoe.record_bitrate(duration, bitrate as u64);
 
         // Update max quality time
         if bitrate >= self.max_available_bitrate && self.max_available_bitrate > 0 {
@@ -187,6 +388,33 @@ impl KinoAnalytics {
         self.total_play_time += duration;
     }
 
+    /// Record a completed rebuffer of `duration` seconds, for hosts that
+    /// time stalls themselves (same as kino-core's `QoeCalculator::record_rebuffer`)
+    #[wasm_bindgen]
+    pub fn record_rebuffer(&mut self, duration: f64) {
+        self.qoe.record_rebuffer(duration);
+        self.log_event("rebuffer", serde_json::json!({ "duration_s": duration }));
+    }
+
+    /// Record a quality switch to `bitrate` at `timestamp` (same as
+    /// kino-core's `QoeCalculator::record_quality_switch`)
+    #[wasm_bindgen]
+    pub fn record_quality_switch(&mut self, timestamp: f64, bitrate: u32) {
+        self.qoe.record_quality_switch(timestamp, bitrate as u64);
+        self.last_bitrate = Some(bitrate);
+        self.log_event("quality_change", serde_json::json!({
+            "to": bitrate,
+            "position": timestamp
+        }));
+    }
+
+    /// Record `duration` seconds played at `bitrate` (same as kino-core's
+    /// `QoeCalculator::record_bitrate`)
+    #[wasm_bindgen]
+    pub fn record_bitrate(&mut self, duration: f64, bitrate: u32) {
+        self.report_bitrate_sample(bitrate, duration);
+    }
+
     /// Set the available quality levels (for high quality ratio)
     #[wasm_bindgen]
     pub fn set_available_qualities(&mut self, max_bitrate: u32) {
@@ -215,28 +443,85 @@ impl KinoAnalytics {
     /// Calculate and return QoE metrics
     #[wasm_bindgen]
     pub fn get_qoe(&self) -> QoeMetrics {
-        let avg_bitrate = self.calculate_avg_bitrate();
-        let watch_time = (js_sys::Date::now() - self.session_start) / 1000.0;
+        let watch_time = (crate::now_ms() - self.session_start) / 1000.0;
         let high_quality_ratio = if self.total_play_time > 0.0 {
             self.max_quality_time / self.total_play_time
         } else {
             0.0
         };
 
-        let score = self.calculate_qoe_score(watch_time, avg_bitrate);
-
         QoeMetrics {
-            score,
+            score: self.qoe.calculate_qoe(),
             startup_time_ms: self.startup_time_ms.unwrap_or(0.0),
-            rebuffer_count: self.rebuffer_count,
-            rebuffer_duration: self.rebuffer_duration,
-            quality_switches: self.quality_switches,
-            avg_bitrate,
+            rebuffer_count: self.qoe.rebuffer_count,
+            rebuffer_duration: self.qoe.rebuffer_duration,
+            quality_switches: self.qoe.quality_switches.len() as u32,
+            avg_bitrate: self.qoe.average_bitrate() as u32,
             high_quality_ratio,
             watch_time,
         }
     }
 
+    /// Get the QoE score breakdown as computed by the native player
+    #[wasm_bindgen]
+    pub fn get_qoe_breakdown(&self) -> QoeBreakdown {
+        self.qoe.breakdown()
+    }
+
+    /// Get session totals, typically at teardown
+    #[wasm_bindgen]
+    pub fn get_session_summary(&self) -> SessionSummary {
+        let watch_time = (crate::now_ms() - self.session_start) / 1000.0;
+        let rebuffer_ratio = if watch_time > 0.0 {
+            (self.qoe.rebuffer_duration / watch_time).min(1.0)
+        } else {
+            0.0
+        };
+
+        SessionSummary {
+            startup_time_ms: self.startup_time_ms.unwrap_or(0.0),
+            rebuffer_ratio,
+            avg_bitrate: self.qoe.average_bitrate() as u32,
+            switch_count: self.qoe.quality_switches.len() as u32,
+            rebuffer_count: self.qoe.rebuffer_count,
+            watch_time,
+            qoe_score: self.qoe.calculate_qoe(),
+            last_sequence: self.next_sequence.saturating_sub(1),
+        }
+    }
+
+    /// Configure batching: flush after `max_events` pending events or
+    /// `flush_interval_ms` since the last flush
+    #[wasm_bindgen]
+    pub fn set_batch_config(&mut self, max_events: usize, flush_interval_ms: f64) {
+        self.batch_size = max_events.max(1);
+        self.flush_interval_ms = flush_interval_ms.max(0.0);
+    }
+
+    /// Number of events waiting for the next flush
+    #[wasm_bindgen]
+    pub fn get_pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Flush if the batch is full or the interval has elapsed
+    ///
+    /// # Returns
+    /// A JSON payload ready to POST, or `undefined` if nothing is due
+    #[wasm_bindgen]
+    pub fn flush_if_due(&mut self) -> Option<String> {
+        self.flush_if_due_at(crate::now_ms())
+    }
+
+    /// Flush pending events now, even if the batch is empty
+    ///
+    /// The payload carries a batch number, the sequence range it covers and
+    /// the current QoE breakdown. Sending it is left to the host app.
+    #[wasm_bindgen]
+    pub fn flush(&mut self) -> String {
+        self.flush_at(crate::now_ms())
+    }
+
     /// Get events as JSON array
     #[wasm_bindgen]
     pub fn get_events_json(&self) -> String {
@@ -257,51 +542,26 @@ impl KinoAnalytics {
 }
 
 impl KinoAnalytics {
-    fn calculate_avg_bitrate(&self) -> u32 {
-        if self.bitrate_samples.is_empty() {
-            return 0;
-        }
-
-        let total_duration: f64 = self.bitrate_samples.iter().map(|s| s.duration).sum();
-        if total_duration <= 0.0 {
-            return 0;
-        }
-
-        let weighted_sum: f64 = self.bitrate_samples
-            .iter()
-            .map(|s| s.bitrate as f64 * s.duration)
-            .sum();
-
-        (weighted_sum / total_duration) as u32
+    fn flush_if_due_at(&mut self, now: f64) -> Option<String> {
+        let full = self.pending.len() >= self.batch_size;
+        let elapsed = !self.pending.is_empty() && now - self.last_flush >= self.flush_interval_ms;
+        (full || elapsed).then(|| self.flush_at(now))
     }
 
-    fn calculate_qoe_score(&self, _watch_time: f64, avg_bitrate: u32) -> f64 {
-        let mut score = 100.0;
-
-        // Penalize startup time (target < 2s)
-        if let Some(startup) = self.startup_time_ms {
-            if startup > 2000.0 {
-                score -= ((startup - 2000.0) / 1000.0) * 5.0;
-            }
-        }
-
-        // Penalize rebuffers heavily (-10 per rebuffer)
-        score -= self.rebuffer_count as f64 * 10.0;
-
-        // Penalize rebuffer duration (-5 per second)
-        score -= self.rebuffer_duration * 5.0;
-
-        // Penalize quality switches (-2 per switch)
-        score -= self.quality_switches as f64 * 2.0;
-
-        // Bonus for high average bitrate
-        if avg_bitrate > 5_000_000 {
-            score += 5.0;
-        } else if avg_bitrate > 2_500_000 {
-            score += 2.0;
-        }
-
-        score.clamp(0.0, 100.0)
+    fn flush_at(&mut self, now: f64) -> String {
+        let events = std::mem::take(&mut self.pending);
+        let payload = EventBatch {
+            batch: self.batch_count,
+            session_start: self.session_start,
+            sent_at: now,
+            first_seq: events.first().map(|e| e.seq),
+            last_seq: events.last().map(|e| e.seq),
+            events: &events,
+            qoe: self.qoe.breakdown(),
+        };
+        self.batch_count += 1;
+        self.last_flush = now;
+        serde_json::to_string(&payload).unwrap_or_default()
     }
 
     fn log_event(&mut self, event_type: &str, data: serde_json::Value) {
@@ -309,11 +569,15 @@ impl KinoAnalytics {
             self.events.pop_front();
         }
 
-        self.events.push_back(AnalyticsEvent {
+        let event = AnalyticsEvent {
+            seq: self.next_sequence,
             event_type: event_type.to_string(),
-            timestamp: js_sys::Date::now(),
+            timestamp: crate::now_ms(),
             data,
-        });
+        };
+        self.next_sequence += 1;
+        self.events.push_back(event.clone());
+        self.pending.push(event);
     }
 }
 
@@ -322,3 +586,89 @@ impl Default for KinoAnalytics {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Same cases as kino-core's analytics tests, to keep scores in parity
+
+    #[test]
+    fn test_qoe_parity_with_core() {
+        let mut analytics = KinoAnalytics::new();
+        assert_eq!(analytics.get_qoe_breakdown().score, 100.0);
+
+        analytics.record_rebuffer(1.0);
+        analytics.record_rebuffer(2.0);
+        // 100 - 2*10 - 3*5 = 65
+        assert!((analytics.get_qoe_breakdown().score - 65.0).abs() < 0.1);
+
+        let mut analytics = KinoAnalytics::new();
+        analytics.qoe.record_initial_buffer(5.0);
+        // 100 - 3*5 = 85
+        assert!((analytics.get_qoe_breakdown().score - 85.0).abs() < 0.1);
+
+        let mut analytics = KinoAnalytics::new();
+        analytics.record_bitrate(10.0, 2_500_000);
+        analytics.record_quality_switch(10.0, 1_000_000);
+        analytics.record_bitrate(10.0, 1_000_000);
+        let breakdown = analytics.get_qoe_breakdown();
+        assert_eq!(breakdown.average_bitrate(), 1_750_000.0);
+        assert_eq!(breakdown.quality_switches, 1);
+        assert_eq!(breakdown.score, 98.0);
+        assert_eq!(analytics.get_qoe().score, breakdown.score);
+    }
+
+    #[test]
+    fn test_event_batching() {
+        let mut analytics = KinoAnalytics::new();
+        analytics.set_batch_config(3, 1000.0);
+        let start = analytics.last_flush;
+
+        analytics.report_play(0.0);
+        analytics.report_seek(0.0, 30.0);
+        assert!(analytics.flush_if_due_at(start + 10.0).is_none());
+
+        analytics.report_pause(30.0);
+        let batch: serde_json::Value = serde_json::from_str(&analytics.flush_if_due_at(start + 20.0).unwrap()).unwrap();
+        assert_eq!(batch["batch"], 0);
+        assert_eq!(batch["first_seq"], 0);
+        assert_eq!(batch["last_seq"], 2);
+        assert_eq!(batch["events"].as_array().unwrap().len(), 3);
+        assert_eq!(batch["qoe"]["score"], 100.0);
+        assert_eq!(analytics.get_pending_count(), 0);
+
+        // A lone event waits for the interval
+        analytics.report_play(30.0);
+        assert!(analytics.flush_if_due_at(start + 500.0).is_none());
+        let batch: serde_json::Value = serde_json::from_str(&analytics.flush_if_due_at(start + 1100.0).unwrap()).unwrap();
+        assert_eq!(batch["batch"], 1);
+        assert_eq!(batch["first_seq"], 3);
+
+        // Nothing pending means nothing due, but a forced flush still reports
+        assert!(analytics.flush_if_due_at(start + 5000.0).is_none());
+        let batch: serde_json::Value = serde_json::from_str(&analytics.flush()).unwrap();
+        assert_eq!(batch["batch"], 2);
+        assert!(batch["first_seq"].is_null());
+    }
+
+    #[test]
+    fn test_session_summary() {
+        let mut analytics = KinoAnalytics::new();
+        analytics.session_start -= 100_000.0;
+        analytics.report_first_frame();
+        analytics.record_bitrate(60.0, 3_000_000);
+        analytics.record_quality_switch(60.0, 1_500_000);
+        analytics.record_bitrate(30.0, 1_500_000);
+        analytics.record_rebuffer(5.0);
+
+        let summary = analytics.get_session_summary();
+        assert!(summary.startup_time_ms >= 100_000.0);
+        assert!((summary.rebuffer_ratio - 0.05).abs() < 0.001);
+        assert_eq!(summary.avg_bitrate, 2_500_000);
+        assert_eq!(summary.switch_count, 1);
+        assert_eq!(summary.rebuffer_count, 1);
+        assert_eq!(summary.last_sequence, 2);
+        assert_eq!(summary.qoe_score, analytics.get_qoe_breakdown().score);
+    }
+}
@@ -1,186 +1,172 @@
 //! Kino Branding - WASM-compatible color palette and theming
+//!
+//! The palette, override rules and CSS generation come from `kino-branding`,
+//! the same code kino-core uses, so web and desktop render identically.
 
+use kino_branding::{ColorOverrides, CssVariables, JsTheme, KinoColors};
 use wasm_bindgen::prelude::*;
 
-/// Kino color palette constants
-pub struct Colors;
-
-impl Colors {
-    pub const PRIMARY: &'static str = "#9b30ff";
-    pub const PRIMARY_DARK: &'static str = "#7a1fe8";
-    pub const PRIMARY_DEEP: &'static str = "#3b0b7d";
-    pub const BACKGROUND: &'static str = "#0c0a12";
-    pub const BACKGROUND_LIGHT: &'static str = "#0f0b18";
-    pub const SURFACE: &'static str = "#1a1625";
-    pub const TEXT: &'static str = "#f6f2ff";
-    pub const TEXT_SOFT: &'static str = "#d4cde9";
-    pub const SUCCESS: &'static str = "#22c55e";
-    pub const WARNING: &'static str = "#f59e0b";
-    pub const ERROR: &'static str = "#ef4444";
-}
-
 /// Kino branding colors exposed to JavaScript
+///
+/// The static methods describe the standard Kino theme. Create an instance
+/// to apply partner brand colors with `set_colors`.
 #[wasm_bindgen]
-pub struct KinoBranding;
+#[derive(Default)]
+pub struct KinoBranding {
+    overrides: ColorOverrides,
+}
 
 #[wasm_bindgen]
 impl KinoBranding {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Override brand colors from JSON with any of `primary`, `secondary`,
+    /// `background` and `text` as hex strings, e.g. `{"primary": "#ff0066"}`.
+    ///
+    /// Replaces any earlier overrides. Throws on invalid JSON or colors,
+    /// keeping the previous colors. Returns a JSON array of WCAG AA contrast
+    /// warnings for the dark and light variants, which are also logged.
+    pub fn set_colors(&mut self, json: &str) -> Result<String, JsValue> {
+        let overrides: ColorOverrides = serde_json::from_str(json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid colors: {}", e)))?;
+
+        let mut warnings = Vec::new();
+        for (variant, base) in [("dark", KinoColors::dark()), ("light", KinoColors::light())] {
+            let colors = base
+                .with_overrides(&overrides)
+                .map_err(|e| JsValue::from_str(&e.to_string()))?;
+            warnings.extend(colors.contrast_warnings().iter().map(|w| format!("{} theme: {}", variant, w)));
+        }
+        for warning in &warnings {
+            crate::console_warn(warning);
+        }
+
+        self.overrides = overrides;
+        Ok(serde_json::to_string(&warnings).unwrap_or_default())
+    }
+
+    /// Drop all color overrides
+    pub fn reset_colors(&mut self) {
+        self.overrides = ColorOverrides::default();
+    }
+
+    /// CSS variables for the dark or light variant with overrides applied
+    pub fn generate_css(&self, dark: bool) -> String {
+        CssVariables::generate_for(&self.colors(dark))
+    }
+
+    /// Player stylesheet for the dark or light variant with overrides applied
+    pub fn generate_player_css(&self, dark: bool) -> String {
+        CssVariables::player_css_for(&self.colors(dark))
+    }
+
+    /// Resolved palette for the dark or light variant as JSON
+    pub fn colors_json(&self, dark: bool) -> String {
+        serde_json::to_string(&self.colors(dark)).unwrap_or_default()
+    }
+
+    /// hls.js/React theme object (kino-core's `JsTheme`) as JSON
+    pub fn to_js_theme(&self) -> String {
+        JsTheme::from_colors(&self.colors(true)).to_json()
+    }
+
     #[wasm_bindgen(getter)]
-    pub fn primary() -> String { Colors::PRIMARY.to_string() }
+    pub fn primary() -> String { KinoColors::default().primary }
 
     #[wasm_bindgen(getter)]
-    pub fn primary_dark() -> String { Colors::PRIMARY_DARK.to_string() }
+    pub fn primary_dark() -> String { KinoColors::default().primary_dark }
 
     #[wasm_bindgen(getter)]
-    pub fn primary_deep() -> String { Colors::PRIMARY_DEEP.to_string() }
+    pub fn primary_deep() -> String { KinoColors::default().primary_deep }
 
     #[wasm_bindgen(getter)]
-    pub fn background() -> String { Colors::BACKGROUND.to_string() }
+    pub fn background() -> String { KinoColors::default().background }
 
     #[wasm_bindgen(getter)]
-    pub fn background_light() -> String { Colors::BACKGROUND_LIGHT.to_string() }
+    pub fn background_light() -> String { KinoColors::default().background_light }
 
     #[wasm_bindgen(getter)]
-    pub fn surface() -> String { Colors::SURFACE.to_string() }
+    pub fn surface() -> String { KinoColors::default().surface }
 
     #[wasm_bindgen(getter)]
-    pub fn text() -> String { Colors::TEXT.to_string() }
+    pub fn text() -> String { KinoColors::default().text }
 
     #[wasm_bindgen(getter)]
-    pub fn text_soft() -> String { Colors::TEXT_SOFT.to_string() }
+    pub fn text_soft() -> String { KinoColors::default().text_soft }
 
-    /// Get primary color as RGBA with custom alpha
+        /// Get primary color as RGBA with custom alpha
     #[wasm_bindgen]
     pub fn primary_rgba(alpha: f32) -> String {
-        format!("rgba(155, 48, 255, {})", alpha)
+        KinoColors::default().primary_rgba(alpha)
     }
 
     /// Get background color as RGBA with custom alpha
     #[wasm_bindgen]
     pub fn background_rgba(alpha: f32) -> String {
-        format!("rgba(12, 10, 18, {})", alpha)
+        KinoColors::default().background_rgba(alpha)
     }
 
     /// Get complete CSS variables for the Kino theme
     #[wasm_bindgen]
     pub fn get_css_variables() -> String {
-        format!(
-            r#":root {{
-  /* Kino Primary Colors */
-  --kino-primary: {};
-  --kino-primary-dark: {};
-  --kino-primary-deep: {};
-
-  /* Kino Background Colors */
-  --kino-background: {};
-  --kino-background-light: {};
-  --kino-surface: {};
-
-  /* Kino Text Colors */
-  --kino-text: {};
-  --kino-text-soft: {};
-
-  /* Kino Status Colors */
-  --kino-success: {};
-  --kino-warning: {};
-  --kino-error: {};
-
-  /* Kino Gradients */
-  --kino-gradient-primary: linear-gradient(145deg, {}, {});
-  --kino-gradient-controls: linear-gradient(transparent, rgba(12, 10, 18, 0.9));
-
-  /* Kino Shadows */
-  --kino-shadow-primary: 0 4px 20px rgba(155, 48, 255, 0.4);
-  --kino-shadow-glow: 0 0 10px rgba(155, 48, 255, 0.5);
-
-  /* Plyr/hls.js compatibility */
-  --plyr-color-main: {};
-  --plyr-video-background: {};
-  --plyr-menu-background: rgba(12, 10, 18, 0.95);
-  --plyr-menu-color: {};
-}}"#,
-            Colors::PRIMARY,
-            Colors::PRIMARY_DARK,
-            Colors::PRIMARY_DEEP,
-            Colors::BACKGROUND,
-            Colors::BACKGROUND_LIGHT,
-            Colors::SURFACE,
-            Colors::TEXT,
-            Colors::TEXT_SOFT,
-            Colors::SUCCESS,
-            Colors::WARNING,
-            Colors::ERROR,
-            Colors::PRIMARY_DARK,
-            Colors::PRIMARY_DEEP,
-            Colors::PRIMARY,
-            Colors::BACKGROUND,
-            Colors::TEXT,
-        )
+        CssVariables::generate()
     }
 
     /// Get complete player CSS stylesheet
     #[wasm_bindgen]
     pub fn get_player_css() -> String {
-        r#"
-/* Kino Styles */
-.kino {
-  background: var(--kino-background);
-  font-family: system-ui, -apple-system, sans-serif;
-}
-
-.kino__controls {
-  background: var(--kino-gradient-controls) !important;
-}
-
-.kino__play-button {
-  background: var(--kino-gradient-primary) !important;
-  box-shadow: var(--kino-shadow-primary);
-  border: none;
-  cursor: pointer;
-  transition: all 0.2s ease;
-}
-
-.kino__play-button:hover {
-  transform: scale(1.05);
-  box-shadow: var(--kino-shadow-glow);
-}
-
-.kino__progress {
-  background: var(--kino-background-light);
-}
-
-.kino__progress-bar {
-  background: var(--kino-primary);
-}
-
-.kino__tooltip {
-  background: rgba(12, 10, 18, 0.95);
-  color: var(--kino-text);
-  border: 1px solid rgba(155, 48, 255, 0.3);
-}
-"#.to_string()
+        CssVariables::player_css()
     }
 
     /// Get theme as JSON object
     #[wasm_bindgen]
     pub fn get_theme_json() -> String {
         serde_json::json!({
-            "colors": {
-                "primary": Colors::PRIMARY,
-                "primary_dark": Colors::PRIMARY_DARK,
-                "primary_deep": Colors::PRIMARY_DEEP,
-                "background": Colors::BACKGROUND,
-                "background_light": Colors::BACKGROUND_LIGHT,
-                "surface": Colors::SURFACE,
-                "text": Colors::TEXT,
-                "text_soft": Colors::TEXT_SOFT,
-                "success": Colors::SUCCESS,
-                "warning": Colors::WARNING,
-                "error": Colors::ERROR
-            },
+            "colors": KinoColors::default(),
             "border_radius": 8,
             "show_watermark": true,
             "watermark_text": "Kino"
         }).to_string()
     }
 }
+
+impl KinoBranding {
+    fn colors(&self, dark: bool) -> KinoColors {
+        let base = if dark { KinoColors::dark() } else { KinoColors::light() };
+        // set_colors only stores overrides that applied cleanly
+        base.with_overrides(&self.overrides).unwrap_or(base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_theme_matches_native() {
+        let branding = KinoBranding::new();
+        assert_eq!(branding.generate_css(true), KinoBranding::get_css_variables());
+        assert_eq!(branding.to_js_theme(), JsTheme::default().to_json());
+        assert_eq!(KinoBranding::primary(), "#9b30ff");
+        assert_eq!(KinoBranding::background_rgba(0.9), "rgba(12, 10, 18, 0.9)");
+    }
+
+    #[test]
+    fn test_set_colors() {
+        let mut branding = KinoBranding::new();
+        let warnings = branding.set_colors(r##"{"primary": "#ff0066", "background": "#ffffff"}"##).unwrap();
+        assert!(warnings.contains("dark theme: text on background"));
+
+        let css = branding.generate_css(true);
+        assert!(css.contains("--kino-primary: #ff0066;"));
+        assert!(css.contains("--kino-background: #ffffff;"));
+        assert!(branding.generate_css(false).contains("--kino-text: #1a1625;"));
+        assert!(branding.to_js_theme().contains(r##""primaryColor":"#ff0066""##));
+
+        branding.reset_colors();
+        assert_eq!(branding.generate_css(true), KinoBranding::get_css_variables());
+    }
+}
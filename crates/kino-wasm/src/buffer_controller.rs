@@ -1,10 +1,47 @@
 //! Buffer Controller - Intelligent buffer management for WASM
 //!
 //! Provides buffer strategy recommendations to complement MSE/hls.js.
+//!
+//! ## Gap handling and stall prediction
+//!
+//! ```javascript
+//! const buffer = new KinoBufferController();
+//! buffer.set_gap_threshold(0.5);
+//!
+//! video.addEventListener('timeupdate', () => {
+//!   const ranges = Array.from({ length: video.buffered.length },
+//!     (_, i) => [video.buffered.start(i), video.buffered.end(i)]);
+//!   const report = buffer.update_buffered_ranges(JSON.stringify(ranges), video.currentTime);
+//!
+//!   if (report.action === 'nudge') {
+//!     video.currentTime += report.nudge_ms / 1000;
+//!   } else if (report.action === 'flush') {
+//!     hls.trigger(Hls.Events.BUFFER_FLUSHING, { startOffset: 0, endOffset: Infinity });
+//!   }
+//!
+//!   buffer.set_inflight_segment(abr.get_download_eta(remainingBytes), segmentDuration);
+//!   if (buffer.seconds_until_stall() < 2) showSpinner();
+//! });
+//! ```
 
 use wasm_bindgen::prelude::*;
 use serde::{Serialize, Deserialize};
 
+use crate::config::WasmConfig;
+
+/// Distance from a range edge at which the playhead counts as outside it.
+/// Browsers routinely park the playhead a few ms short of a range end.
+const EDGE_TOLERANCE: f64 = 0.05;
+
+/// Extra distance added to a nudge so the playhead lands inside the range
+const NUDGE_MARGIN: f64 = 0.01;
+
+/// Consecutive stuck updates before a large gap is worth a flush
+const FLUSH_AFTER_STUCK_UPDATES: u32 = 3;
+
+/// EWMA weight for new drain rate measurements
+const DRAIN_ALPHA: f64 = 0.3;
+
 /// Buffer state information
 #[wasm_bindgen]
 #[derive(Clone, Serialize, Deserialize)]
@@ -29,6 +66,32 @@ impl BufferState {
     }
 }
 
+/// Result of inspecting the buffered ranges around the playhead
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GapReport {
+    /// Seconds buffered ahead of the playhead in its current range
+    pub buffer_ahead: f64,
+    /// Whether a gap was found at or just ahead of the playhead
+    pub has_gap: bool,
+    /// Start of the gap in seconds (0 when there is none)
+    pub gap_start: f64,
+    /// End of the gap in seconds (0 when there is none)
+    pub gap_end: f64,
+    /// How far to move the playhead when `action` is "nudge"
+    pub nudge_ms: f64,
+    /// Recommended action: "none", "nudge", "wait" or "flush"
+    action: String,
+}
+
+#[wasm_bindgen]
+impl GapReport {
+    #[wasm_bindgen(getter)]
+    pub fn action(&self) -> String {
+        self.action.clone()
+    }
+}
+
 /// Buffer management controller
 #[wasm_bindgen]
 pub struct KinoBufferController {
@@ -48,6 +111,16 @@ pub struct KinoBufferController {
     is_live: bool,
     /// Stall count
     stall_count: u32,
+    /// Largest gap that is skipped with a nudge, in seconds
+    gap_threshold: f64,
+    /// Updates in a row with the playhead stuck in an unbuffered region
+    stuck_updates: u32,
+    /// Last (wall clock ms, buffer ahead) from `update_buffered_ranges`
+    last_sample: Option<(f64, f64)>,
+    /// Smoothed buffer drain in seconds of media per second
+    drain_rate: Option<f64>,
+    /// Seconds until the in-flight segment lands, and its duration
+    inflight: Option<(f64, f64)>,
 }
 
 #[wasm_bindgen]
@@ -64,9 +137,28 @@ impl KinoBufferController {
             duration: 0.0,
             is_live: false,
             stall_count: 0,
+            gap_threshold: 0.5,
+            stuck_updates: 0,
+            last_sample: None,
+            drain_rate: None,
+            inflight: None,
         }
     }
 
+    /// Create a buffer controller from a player config
+    ///
+    /// The target sits midway between the config's minimum and maximum
+    /// buffer, and the rebuffer threshold is at most half the minimum.
+    #[wasm_bindgen]
+    pub fn from_config(config: &WasmConfig) -> Self {
+        let mut controller = Self::new();
+        controller.min_buffer = config.min_buffer_time();
+        controller.max_buffer = config.max_buffer_time();
+        controller.target_buffer = (controller.min_buffer + controller.max_buffer) / 2.0;
+        controller.rebuffer_threshold = controller.rebuffer_threshold.min(controller.min_buffer / 2.0);
+        controller
+    }
+
     /// Configure for VOD content
     #[wasm_bindgen]
     pub fn configure_vod(&mut self, duration: f64) {
@@ -159,11 +251,160 @@ impl KinoBufferController {
         buffer_level / playback_rate
     }
 
+    /// Set the largest gap (in seconds) that is skipped with a nudge
+    #[wasm_bindgen]
+    pub fn set_gap_threshold(&mut self, seconds: f64) {
+        self.gap_threshold = seconds.max(0.0);
+    }
+
+    /// Ingest the media element's buffered ranges and check for gaps
+    ///
+    /// # Arguments
+    /// * `ranges_json` - JSON array of `[start, end]` pairs in seconds
+    /// * `current_time` - Playhead position in seconds
+    ///
+    /// # Returns
+    /// A report whose action is "nudge" when the playhead sits in a gap no
+    /// wider than the gap threshold, "wait" while a wider gap may still be
+    /// filled by the loader, "flush" once the playhead has been stuck in
+    /// such a gap for several updates, and "none" otherwise
+    #[wasm_bindgen]
+    pub fn update_buffered_ranges(&mut self, ranges_json: &str, current_time: f64) -> GapReport {
+        let ranges: Vec<(f64, f64)> = serde_json::from_str(ranges_json).unwrap_or_default();
+        self.update_buffered_ranges_at(ranges, current_time, crate::now_ms())
+    }
+
+    /// Tell the predictor that a segment is downloading
+    ///
+    /// # Arguments
+    /// * `eta_seconds` - Time until it finishes (e.g. `KinoAbrController.get_download_eta`)
+    /// * `duration` - Media duration it will add to the buffer
+    #[wasm_bindgen]
+    pub fn set_inflight_segment(&mut self, eta_seconds: f64, duration: f64) {
+        self.inflight = Some((eta_seconds.max(0.0), duration.max(0.0)));
+    }
+
+    /// Clear the in-flight segment once it has been appended
+    #[wasm_bindgen]
+    pub fn clear_inflight_segment(&mut self) {
+        self.inflight = None;
+    }
+
+    /// Smoothed buffer drain rate in media seconds per second (0 if unknown)
+    #[wasm_bindgen]
+    pub fn get_drain_rate(&self) -> f64 {
+        self.drain_rate.unwrap_or(0.0)
+    }
+
+    /// Predict the seconds until playback stalls
+    ///
+    /// Uses the buffer ahead from the last `update_buffered_ranges` call
+    /// and the smoothed drain rate. An in-flight segment that lands before
+    /// the buffer runs dry extends the prediction by its duration.
+    /// Returns `Infinity` while the buffer is not draining.
+    #[wasm_bindgen]
+    pub fn seconds_until_stall(&self) -> f64 {
+        let (Some((_, buffer_ahead)), Some(drain)) = (self.last_sample, self.drain_rate) else {
+            return f64::INFINITY;
+        };
+        if drain <= 0.0 {
+            return f64::INFINITY;
+        }
+
+        let until_empty = buffer_ahead / drain;
+        match self.inflight {
+            Some((eta, duration)) if eta < until_empty => (buffer_ahead + duration) / drain,
+            _ => until_empty,
+        }
+    }
+
     /// Reset controller state
     #[wasm_bindgen]
     pub fn reset(&mut self) {
         self.position = 0.0;
         self.stall_count = 0;
+        self.stuck_updates = 0;
+        self.last_sample = None;
+        self.drain_rate = None;
+        self.inflight = None;
+    }
+}
+
+impl KinoBufferController {
+    fn update_buffered_ranges_at(&mut self, mut ranges: Vec<(f64, f64)>, current_time: f64, now_ms: f64) -> GapReport {
+        ranges.retain(|&(start, end)| end > start);
+        ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let stuck = (current_time - self.position).abs() < f64::EPSILON;
+        self.position = current_time;
+
+        let containing = ranges.iter()
+            .position(|&(start, end)| start - EDGE_TOLERANCE <= current_time && current_time < end - EDGE_TOLERANCE);
+
+        let report = match containing {
+            Some(i) => {
+                self.stuck_updates = 0;
+                let end = ranges[i].1;
+                let next = ranges.get(i + 1).filter(|&&(start, _)| start - end <= self.gap_threshold);
+                GapReport {
+                    buffer_ahead: end - current_time,
+                    has_gap: next.is_some(),
+                    gap_start: next.map(|_| end).unwrap_or(0.0),
+                    gap_end: next.map(|&(start, _)| start).unwrap_or(0.0),
+                    nudge_ms: 0.0,
+                    action: "none".to_string(),
+                }
+            }
+            None => {
+                let next = ranges.iter().find(|&&(start, _)| start >= current_time - EDGE_TOLERANCE);
+                let (action, nudge_ms) = match next {
+                    Some(&(start, _)) if start - current_time <= self.gap_threshold => {
+                        self.stuck_updates = 0;
+                        ("nudge", ((start - current_time).max(0.0) + NUDGE_MARGIN) * 1000.0)
+                    }
+                    Some(_) => {
+                        self.stuck_updates = if stuck { self.stuck_updates + 1 } else { 0 };
+                        if self.stuck_updates >= FLUSH_AFTER_STUCK_UPDATES {
+                            ("flush", 0.0)
+                        } else {
+                            ("wait", 0.0)
+                        }
+                    }
+                    None => {
+                        self.stuck_updates = 0;
+                        ("wait", 0.0)
+                    }
+                };
+                GapReport {
+                    buffer_ahead: 0.0,
+                    has_gap: next.is_some(),
+                    gap_start: next.map(|_| current_time).unwrap_or(0.0),
+                    gap_end: next.map(|&(start, _)| start).unwrap_or(0.0),
+                    nudge_ms,
+                    action: action.to_string(),
+                }
+            }
+        };
+
+        self.record_buffer_sample(report.buffer_ahead, now_ms);
+        report
+    }
+
+    /// Update the drain estimate from the change in buffer since the last
+    /// sample. Growth means a segment was appended, which says nothing
+    /// about how fast playback consumes the buffer, so it is skipped.
+    fn record_buffer_sample(&mut self, buffer_ahead: f64, now_ms: f64) {
+        if let Some((last_ms, last_buffer)) = self.last_sample {
+            let elapsed = (now_ms - last_ms) / 1000.0;
+            if elapsed > 0.0 && buffer_ahead <= last_buffer {
+                let rate = (last_buffer - buffer_ahead) / elapsed;
+                self.drain_rate = Some(match self.drain_rate {
+                    Some(current) => current * (1.0 - DRAIN_ALPHA) + rate * DRAIN_ALPHA,
+                    None => rate,
+                });
+            }
+        }
+        self.last_sample = Some((now_ms, buffer_ahead));
     }
 }
 
@@ -172,3 +413,88 @@ impl Default for KinoBufferController {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() {
+        let controller = KinoBufferController::from_config(&WasmConfig::audio_only());
+        assert!(controller.can_start_playback(4.0));
+        assert_eq!(controller.get_state(12.0).target, 12.0);
+        assert_eq!(controller.get_state(20.0).action(), "stop_buffering");
+
+        let controller = KinoBufferController::from_config(&WasmConfig::low_latency());
+        assert_eq!(controller.get_state(1.5).action(), "buffer_aggressively");
+    }
+
+    #[test]
+    fn test_gap_detection() {
+        let mut controller = KinoBufferController::new();
+        let ranges = vec![(0.0, 10.0), (10.2, 20.0), (25.0, 40.0)];
+
+        let report = controller.update_buffered_ranges_at(ranges.clone(), 5.0, 0.0);
+        assert_eq!(report.action(), "none");
+        assert!((report.buffer_ahead - 5.0).abs() < 1e-9);
+        assert!(report.has_gap);
+        assert_eq!((report.gap_start, report.gap_end), (10.0, 10.2));
+
+        // Parked at the end of the first range: hop the 200ms hole
+        let report = controller.update_buffered_ranges_at(ranges.clone(), 9.98, 100.0);
+        assert_eq!(report.action(), "nudge");
+        assert!((report.nudge_ms - 230.0).abs() < 1e-6);
+
+        // A 5s hole is left for the loader, then flushed if nothing moves
+        let actions: Vec<String> = (0..4)
+            .map(|i| controller.update_buffered_ranges_at(ranges.clone(), 20.0, 200.0 + i as f64 * 100.0).action())
+            .collect();
+        assert_eq!(actions, ["wait", "wait", "wait", "flush"]);
+
+        controller.set_gap_threshold(6.0);
+        assert_eq!(controller.update_buffered_ranges_at(ranges, 20.0, 700.0).action(), "nudge");
+
+        assert_eq!(controller.update_buffered_ranges_at(Vec::new(), 0.0, 800.0).action(), "wait");
+    }
+
+    #[test]
+    fn test_stall_prediction_converges() {
+        let mut controller = KinoBufferController::new();
+        assert!(controller.seconds_until_stall().is_infinite());
+
+        // Playback drains 1.25s of buffer per second; samples arrive with
+        // jittered spacing and the media clock lags the wall clock
+        let true_rate = 1.25;
+        let mut buffer = 20.0;
+        let mut now_ms = 0.0;
+        let mut errors = Vec::new();
+        for i in 0..30 {
+            let step = if i % 2 == 0 { 0.2 } else { 0.3 };
+            let reported = if i % 3 == 0 { buffer + 0.05 } else { buffer };
+            controller.update_buffered_ranges_at(vec![(0.0, 100.0)], 100.0 - reported, now_ms);
+            if i > 0 {
+                let truth = buffer / true_rate;
+                errors.push((controller.seconds_until_stall() - truth).abs() / truth);
+            }
+            buffer -= true_rate * step;
+            now_ms += step * 1000.0;
+        }
+
+        let last = *errors.last().unwrap();
+        assert!(last < 0.05, "prediction error {last}");
+        assert!(last < errors[0]);
+        assert!((controller.get_drain_rate() - true_rate).abs() < 0.1);
+
+        // A segment landing before the buffer empties pushes the stall out
+        let before = controller.seconds_until_stall();
+        controller.set_inflight_segment(1.0, 4.0);
+        assert!(controller.seconds_until_stall() > before + 2.0);
+        controller.set_inflight_segment(before + 1.0, 4.0);
+        assert_eq!(controller.seconds_until_stall(), before);
+
+        // Appends do not count as negative drain
+        controller.clear_inflight_segment();
+        controller.update_buffered_ranges_at(vec![(0.0, 200.0)], 100.0, now_ms + 200.0);
+        assert!(controller.get_drain_rate() > 1.0);
+    }
+}
@@ -0,0 +1,319 @@
+//! Player configuration shared by the ABR and buffer controllers
+//!
+//! ## Presets and stored configs
+//!
+//! ```javascript
+//! const config = video.muted ? WasmConfig.autoplay_muted() : WasmConfig.vod();
+//! const abr = KinoAbrController.from_config(config);
+//! const buffer = KinoBufferController.from_config(config);
+//!
+//! // Lift the muted bitrate cap once the viewer turns the sound on
+//! video.addEventListener('volumechange', () => {
+//!   if (!video.muted) abr.on_unmuted();
+//! });
+//!
+//! // Host apps can keep configs as JSON
+//! localStorage.setItem('kino-config', config.to_json());
+//! const stored = WasmConfig.from_json(localStorage.getItem('kino-config'));
+//! ```
+//!
+//! Setters coerce or reject invalid values with a console warning, so a
+//! config built from JS always passes [`WasmConfig::validate`].
+
+use wasm_bindgen::prelude::*;
+use serde::{Serialize, Deserialize};
+
+/// ABR algorithms the controller implements
+pub(crate) const ABR_ALGORITHMS: [&str; 3] = ["throughput", "bola", "hybrid"];
+
+/// A problem found by [`WasmConfig::validate`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigIssue {
+    /// Name of the offending field
+    pub field: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self { field: field.to_string(), message: message.into() }
+    }
+}
+
+/// Configuration for the WASM player
+#[wasm_bindgen]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WasmConfig {
+    /// ABR algorithm: "throughput", "bola", or "hybrid"
+    abr_algorithm: String,
+    /// Minimum buffer before playback (seconds)
+    min_buffer_time: f64,
+    /// Maximum buffer level (seconds)
+    max_buffer_time: f64,
+    /// Enable analytics collection
+    pub analytics_enabled: bool,
+    /// Maximum bitrate cap (0 = unlimited)
+    pub max_bitrate: u32,
+    /// Start at lowest quality
+    pub start_at_lowest: bool,
+    /// Bitrate cap while playback is muted, lifted by
+    /// `KinoAbrController.on_unmuted` (0 = none)
+    pub muted_max_bitrate: u32,
+}
+
+#[wasm_bindgen]
+impl WasmConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            abr_algorithm: "bola".to_string(),
+            min_buffer_time: 10.0,
+            max_buffer_time: 30.0,
+            analytics_enabled: true,
+            max_bitrate: 0,
+            start_at_lowest: false,
+            muted_max_bitrate: 0,
+        }
+    }
+
+    /// Get ABR algorithm
+    #[wasm_bindgen(getter)]
+    pub fn abr_algorithm(&self) -> String {
+        self.abr_algorithm.clone()
+    }
+
+    /// Set ABR algorithm; unknown names are rejected with a warning
+    #[wasm_bindgen(setter)]
+    pub fn set_abr_algorithm(&mut self, algorithm: String) {
+        let normalized = algorithm.trim().to_ascii_lowercase();
+        if ABR_ALGORITHMS.contains(&normalized.as_str()) {
+            self.abr_algorithm = normalized;
+        } else {
+            crate::console_warn(&format!(
+                "Unknown ABR algorithm {:?}, keeping {:?}",
+                algorithm, self.abr_algorithm
+            ));
+        }
+    }
+
+    /// Get minimum buffer before playback (seconds)
+    #[wasm_bindgen(getter)]
+    pub fn min_buffer_time(&self) -> f64 {
+        self.min_buffer_time
+    }
+
+    /// Set minimum buffer before playback; negative or non-finite values
+    /// are rejected and values above the maximum are capped to it
+    #[wasm_bindgen(setter)]
+    pub fn set_min_buffer_time(&mut self, seconds: f64) {
+        if !seconds.is_finite() || seconds < 0.0 {
+            crate::console_warn(&format!("Invalid min_buffer_time {}, keeping {}", seconds, self.min_buffer_time));
+        } else if seconds > self.max_buffer_time {
+            crate::console_warn(&format!(
+                "min_buffer_time {} exceeds max_buffer_time, using {}",
+                seconds, self.max_buffer_time
+            ));
+            self.min_buffer_time = self.max_buffer_time;
+        } else {
+            self.min_buffer_time = seconds;
+        }
+    }
+
+    /// Get maximum buffer level (seconds)
+    #[wasm_bindgen(getter)]
+    pub fn max_buffer_time(&self) -> f64 {
+        self.max_buffer_time
+    }
+
+    /// Set maximum buffer level; values that are not positive and finite
+    /// are rejected and values below the minimum are raised to it
+    #[wasm_bindgen(setter)]
+    pub fn set_max_buffer_time(&mut self, seconds: f64) {
+        if !seconds.is_finite() || seconds <= 0.0 {
+            crate::console_warn(&format!("Invalid max_buffer_time {}, keeping {}", seconds, self.max_buffer_time));
+        } else if seconds < self.min_buffer_time {
+            crate::console_warn(&format!(
+                "max_buffer_time {} is below min_buffer_time, using {}",
+                seconds, self.min_buffer_time
+            ));
+            self.max_buffer_time = self.min_buffer_time;
+        } else {
+            self.max_buffer_time = seconds;
+        }
+    }
+
+    /// Check the config, returning a JSON array of `{field, message}`
+    /// issues (empty when valid)
+    #[wasm_bindgen]
+    pub fn validate(&self) -> String {
+        serde_json::to_string(&self.issues()).unwrap_or("[]".to_string())
+    }
+
+    /// Parse a config stored with `to_json`; missing fields take their
+    /// defaults. Throws on invalid JSON or if `validate` finds issues.
+    #[wasm_bindgen]
+    pub fn from_json(json: &str) -> Result<WasmConfig, JsValue> {
+        Self::parse(json).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Convert to JSON string
+    #[wasm_bindgen]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Create config optimized for low-latency live streaming
+    #[wasm_bindgen]
+    pub fn low_latency() -> Self {
+        Self {
+            abr_algorithm: "throughput".to_string(),
+            min_buffer_time: 2.0,
+            max_buffer_time: 6.0,
+            start_at_lowest: true,
+            ..Self::new()
+        }
+    }
+
+    /// Create config optimized for VOD
+    #[wasm_bindgen]
+    pub fn vod() -> Self {
+        Self {
+            abr_algorithm: "bola".to_string(),
+            min_buffer_time: 15.0,
+            max_buffer_time: 60.0,
+            ..Self::new()
+        }
+    }
+
+    /// Create config for audio-only content such as podcasts: small
+    /// buffers and a 256 kbps cap
+    #[wasm_bindgen]
+    pub fn audio_only() -> Self {
+        Self {
+            abr_algorithm: "throughput".to_string(),
+            min_buffer_time: 4.0,
+            max_buffer_time: 20.0,
+            max_bitrate: 256_000,
+            ..Self::new()
+        }
+    }
+
+    /// Create config for muted background autoplay: start at the lowest
+    /// level and stay below 1.5 Mbps until `KinoAbrController.on_unmuted`
+    #[wasm_bindgen]
+    pub fn autoplay_muted() -> Self {
+        Self {
+            abr_algorithm: "bola".to_string(),
+            min_buffer_time: 6.0,
+            max_buffer_time: 30.0,
+            start_at_lowest: true,
+            muted_max_bitrate: 1_500_000,
+            ..Self::new()
+        }
+    }
+}
+
+impl WasmConfig {
+    /// Everything wrong with the config
+    pub fn issues(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        if !ABR_ALGORITHMS.contains(&self.abr_algorithm.as_str()) {
+            issues.push(ConfigIssue::new(
+                "abr_algorithm",
+                format!("unknown algorithm {:?}, expected one of {}", self.abr_algorithm, ABR_ALGORITHMS.join(", ")),
+            ));
+        }
+        if !self.min_buffer_time.is_finite() || self.min_buffer_time < 0.0 {
+            issues.push(ConfigIssue::new("min_buffer_time", "must be a non-negative number of seconds"));
+        }
+        if !self.max_buffer_time.is_finite() || self.max_buffer_time <= 0.0 {
+            issues.push(ConfigIssue::new("max_buffer_time", "must be a positive number of seconds"));
+        }
+        if self.min_buffer_time > self.max_buffer_time {
+            issues.push(ConfigIssue::new("min_buffer_time", "must not exceed max_buffer_time"));
+        }
+        issues
+    }
+
+    /// `from_json` with the error as a string
+    fn parse(json: &str) -> Result<Self, String> {
+        let config: Self = serde_json::from_str(json).map_err(|e| format!("Invalid config: {}", e))?;
+        let issues = config.issues();
+        if !issues.is_empty() {
+            let details: Vec<String> = issues.iter().map(|i| format!("{}: {}", i.field, i.message)).collect();
+            return Err(format!("Invalid config: {}", details.join("; ")));
+        }
+        Ok(config)
+    }
+}
+
+impl Default for WasmConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_are_valid() {
+        for config in [
+            WasmConfig::new(),
+            WasmConfig::low_latency(),
+            WasmConfig::vod(),
+            WasmConfig::audio_only(),
+            WasmConfig::autoplay_muted(),
+        ] {
+            assert!(config.issues().is_empty(), "{}", config.to_json());
+            assert_eq!(config.validate(), "[]");
+        }
+    }
+
+    #[test]
+    fn test_setters_coerce_and_reject() {
+        let mut config = WasmConfig::new();
+        config.set_abr_algorithm(" Hybrid ".to_string());
+        assert_eq!(config.abr_algorithm(), "hybrid");
+        config.set_abr_algorithm("ml".to_string());
+        assert_eq!(config.abr_algorithm(), "hybrid");
+
+        config.set_min_buffer_time(45.0);
+        assert_eq!(config.min_buffer_time(), 30.0);
+        config.set_min_buffer_time(f64::NAN);
+        config.set_min_buffer_time(-1.0);
+        assert_eq!(config.min_buffer_time(), 30.0);
+
+        config.set_max_buffer_time(20.0);
+        assert_eq!(config.max_buffer_time(), 30.0);
+        config.set_max_buffer_time(0.0);
+        assert_eq!(config.max_buffer_time(), 30.0);
+        config.set_max_buffer_time(90.0);
+        assert_eq!(config.max_buffer_time(), 90.0);
+        assert!(config.issues().is_empty());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let config = WasmConfig::autoplay_muted();
+        let restored = WasmConfig::parse(&config.to_json()).unwrap();
+        assert_eq!(restored.to_json(), config.to_json());
+
+        // Missing fields take their defaults
+        let partial = WasmConfig::parse(r#"{"abr_algorithm": "throughput", "max_bitrate": 3000000}"#).unwrap();
+        assert_eq!(partial.abr_algorithm(), "throughput");
+        assert_eq!((partial.max_bitrate, partial.max_buffer_time()), (3_000_000, 30.0));
+
+        let error = WasmConfig::parse(r#"{"abr_algorithm": "fastest", "min_buffer_time": 40}"#).unwrap_err();
+        assert!(error.contains("abr_algorithm") && error.contains("min_buffer_time"), "{}", error);
+        assert!(WasmConfig::parse("not json").is_err());
+
+        let issues: Vec<serde_json::Value> = serde_json::from_str(
+            &WasmConfig { min_buffer_time: -1.0, ..WasmConfig::new() }.validate()
+        ).unwrap();
+        assert_eq!(issues[0]["field"], "min_buffer_time");
+    }
+}
@@ -5,6 +5,8 @@
 //! - Dominant frequency detection
 //! - Spectral feature extraction
 //! - Audio fingerprinting
+//! - Content tagging and signature similarity, using the same genre
+//!   profiles and signature layout as kino-frequency
 //!
 //! ## JavaScript Integration
 //!
@@ -20,66 +22,84 @@
 //! console.log('Dominant:', result.dominant_frequencies);
 //! console.log('Centroid:', result.spectral_centroid);
 //! ```
+//!
+//! ## AudioWorklet Streaming
+//!
+//! `KinoStreamingAnalyzer` can read samples straight out of WASM linear
+//! memory, so an `AudioWorkletProcessor` never has to allocate a JS array
+//! per render quantum. The worklet writes into the region returned by
+//! `allocate_input_buffer`, calls `process_from_buffer`, and forwards the
+//! compact `get_latest_frame()` result to the UI thread at the cadence set
+//! by `set_emit_interval`:
+//!
+//! ```javascript
+//! // worklet.js (the WASM module is instantiated inside the worklet scope)
+//! import { initSync, KinoStreamingAnalyzer } from '@kino/wasm';
+//!
+//! class KinoProcessor extends AudioWorkletProcessor {
+//!   constructor({ processorOptions }) {
+//!     super();
+//!     this.wasm = initSync(processorOptions.module);
+//!     this.analyzer = new KinoStreamingAnalyzer(2048, sampleRate);
+//!     this.analyzer.set_emit_interval(4); // analyze every 4th hop
+//!     this.input = this.analyzer.allocate_input_buffer(128);
+//!     this.view = null;
+//!   }
+//!
+//!   process(inputs) {
+//!     const channel = inputs[0][0];
+//!     if (!channel) return true;
+//!
+//!     // Views detach when linear memory grows, so rebuild them lazily
+//!     if (!this.view || this.view.buffer !== this.wasm.memory.buffer) {
+//!       this.view = new Float32Array(this.wasm.memory.buffer, this.input.ptr, this.input.len);
+//!     }
+//!     this.view.set(channel);
+//!
+//!     if (this.analyzer.process_from_buffer(0, channel.length) > 0) {
+//!       const frame = this.analyzer.get_latest_frame();
+//!       this.port.postMessage({
+//!         time: frame.time,
+//!         dominant: frame.dominant_frequency,
+//!         centroid: frame.spectral_centroid,
+//!         bands: frame.get_band_energies(),
+//!       });
+//!       frame.free();
+//!     }
+//!     return true;
+//!   }
+//! }
+//!
+//! registerProcessor('kino-processor', KinoProcessor);
+//! ```
+//!
+//! When the module is built with shared memory (`+atomics`),
+//! `memory.buffer` is a `SharedArrayBuffer` and the same pointer can be
+//! written from another thread, as long as it only writes between calls
+//! to `process_from_buffer`.
 
 use wasm_bindgen::prelude::*;
 use serde::{Serialize, Deserialize};
-use js_sys::{Float32Array, Array};
+use js_sys::{Float32Array, Array, Uint8Array};
+// Windowing, FFT, band energies, streaming frames and overviews are shared
+// with kino-frequency
+use kino_spectrum::{
+    FrameAnalyzer, FrameFeatures, Framer, Overview, OverviewConfig, RollingStats, Spectrum, WindowFunction,
+};
 
 // ============================================================================
-// Core FFT Implementation (no Tokio - WASM compatible)
+// Shared Analysis Core (kino-spectrum, no Tokio - WASM compatible)
 // ============================================================================
 
-/// FFT Analyzer for WASM
-struct FftAnalyzer {
-    fft_size: usize,
-    window: Vec<f32>,
+/// Parse a window name for the `set_window` methods
+fn window_from_name(name: &str) -> Result<WindowFunction, JsValue> {
+    WindowFunction::from_name(name)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown window function: {}", name)))
 }
 
-impl FftAnalyzer {
-    fn new(fft_size: usize) -> Self {
-        // Generate Hann window
-        let window: Vec<f32> = (0..fft_size)
-            .map(|i| {
-                0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / (fft_size - 1) as f32).cos())
-            })
-            .collect();
-
-        Self { fft_size, window }
-    }
-
-    fn compute_spectrum(&self, samples: &[f32]) -> Vec<f32> {
-        if samples.len() < self.fft_size {
-            return vec![0.0; self.fft_size / 2];
-        }
-
-        // Apply window
-        let windowed: Vec<f32> = samples.iter()
-            .take(self.fft_size)
-            .zip(self.window.iter())
-            .map(|(&s, &w)| s * w)
-            .collect();
-
-        // Simple DFT (for WASM we avoid complex FFT library dependencies)
-        // In production, use web-sys AudioContext.createAnalyser()
-        let mut spectrum = vec![0.0f32; self.fft_size / 2];
-        let n = self.fft_size as f32;
-
-        for k in 0..self.fft_size / 2 {
-            let mut real = 0.0f32;
-            let mut imag = 0.0f32;
-
-            for (i, &sample) in windowed.iter().enumerate() {
-                let angle = 2.0 * std::f32::consts::PI * k as f32 * i as f32 / n;
-                real += sample * angle.cos();
-                imag -= sample * angle.sin();
-            }
-
-            spectrum[k] = (real * real + imag * imag).sqrt() * 2.0 / n;
-        }
-
-        spectrum
-    }
-}
+/// Frames kept for `KinoStreamingAnalyzer.get_statistics`, matching
+/// kino-frequency's `StreamConfig::history_length`
+const STATS_HISTORY: usize = 100;
 
 // ============================================================================
 // WASM Bindings
@@ -93,6 +113,9 @@ pub struct FrequencyResult {
     spectral_centroid: f32,
     spectral_rolloff: f32,
     spectral_flatness: f32,
+    spectral_bandwidth: f32,
+    spectral_contrast: Vec<f32>,
+    crest_factor: f32,
     band_energies: BandEnergies,
 }
 
@@ -103,14 +126,90 @@ struct DominantFreq {
     rank: usize,
 }
 
+/// Named set of frequency bands, matching kino-frequency's `BandPlan`
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct BandPlan {
+    name: String,
+    bands: Vec<(f32, f32)>,
+}
+
+impl BandPlan {
+    fn standard() -> Self {
+        Self {
+            name: "standard".to_string(),
+            bands: kino_spectrum::STANDARD_BANDS.to_vec(),
+        }
+    }
+
+    /// Build a plan from flattened `[low0, high0, low1, high1, ...]` bounds
+    fn from_bounds(name: String, bounds: &[f32]) -> Result<Self, JsValue> {
+        if bounds.is_empty() || !bounds.len().is_multiple_of(2) {
+            return Err(JsValue::from_str("Band bounds must be non-empty (low, high) pairs"));
+        }
+        let bands: Vec<(f32, f32)> = bounds.chunks(2).map(|b| (b[0], b[1])).collect();
+        if let Some((low, high)) = kino_spectrum::invalid_band(&bands) {
+            return Err(JsValue::from_str(&format!("Invalid band {}-{} Hz", low, high)));
+        }
+
+        Ok(Self { name, bands })
+    }
+
+    fn is_standard(&self) -> bool {
+        *self == Self::standard()
+    }
+
+    /// Normalized energy per band
+    fn energies(&self, spectrum: &[f32], frequencies: &[f32]) -> Vec<f32> {
+        kino_spectrum::band_energies(spectrum, frequencies, &self.bands)
+    }
+}
+
+/// Band energies; the standard plan serializes with named fields like kino-frequency
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(from = "BandEnergiesRepr", into = "BandEnergiesRepr")]
 struct BandEnergies {
-    sub_bass: f32,
-    bass: f32,
-    low_mid: f32,
-    mid: f32,
-    high_mid: f32,
-    high: f32,
+    plan: BandPlan,
+    energies: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BandEnergiesRepr {
+    Standard {
+        sub_bass: f32,
+        bass: f32,
+        low_mid: f32,
+        mid: f32,
+        high_mid: f32,
+        high: f32,
+    },
+    Planned {
+        plan: BandPlan,
+        energies: Vec<f32>,
+    },
+}
+
+impl From<BandEnergiesRepr> for BandEnergies {
+    fn from(repr: BandEnergiesRepr) -> Self {
+        match repr {
+            BandEnergiesRepr::Standard { sub_bass, bass, low_mid, mid, high_mid, high } => Self {
+                plan: BandPlan::standard(),
+                energies: vec![sub_bass, bass, low_mid, mid, high_mid, high],
+            },
+            BandEnergiesRepr::Planned { plan, energies } => Self { plan, energies },
+        }
+    }
+}
+
+impl From<BandEnergies> for BandEnergiesRepr {
+    fn from(bands: BandEnergies) -> Self {
+        match bands.energies[..] {
+            [sub_bass, bass, low_mid, mid, high_mid, high] if bands.plan.is_standard() => {
+                BandEnergiesRepr::Standard { sub_bass, bass, low_mid, mid, high_mid, high }
+            }
+            _ => BandEnergiesRepr::Planned { plan: bands.plan, energies: bands.energies },
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -130,6 +229,22 @@ impl FrequencyResult {
         self.spectral_flatness
     }
 
+    #[wasm_bindgen(getter)]
+    pub fn spectral_bandwidth(&self) -> f32 {
+        self.spectral_bandwidth
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn crest_factor(&self) -> f32 {
+        self.crest_factor
+    }
+
+    /// Get per-octave spectral contrast (dB) as a Float32Array
+    #[wasm_bindgen]
+    pub fn get_spectral_contrast(&self) -> Float32Array {
+        Float32Array::from(&self.spectral_contrast[..])
+    }
+
     /// Get dominant frequencies as JSON
     #[wasm_bindgen]
     pub fn get_dominant_json(&self) -> String {
@@ -147,7 +262,8 @@ impl FrequencyResult {
 #[wasm_bindgen]
 pub struct KinoFrequencyAnalyzer {
     fft_size: usize,
-    analyzer: FftAnalyzer,
+    analyzer: Spectrum,
+    band_plan: BandPlan,
 }
 
 #[wasm_bindgen]
@@ -158,10 +274,27 @@ impl KinoFrequencyAnalyzer {
         let fft_size = fft_size.max(256).min(8192);
         Self {
             fft_size,
-            analyzer: FftAnalyzer::new(fft_size),
+            analyzer: Spectrum::new(fft_size, WindowFunction::Hann),
+            band_plan: BandPlan::standard(),
         }
     }
 
+    /// Use custom bands for band energies, given as flattened
+    /// `[low0, high0, low1, high1, ...]` bounds in Hz
+    #[wasm_bindgen]
+    pub fn set_band_plan(&mut self, name: String, bounds: &Float32Array) -> Result<(), JsValue> {
+        self.band_plan = BandPlan::from_bounds(name, &bounds.to_vec())?;
+        Ok(())
+    }
+
+    /// Change the window applied before the FFT: "hann" (default),
+    /// "hamming", "blackman_harris", "flat_top" or "rectangular"
+    #[wasm_bindgen]
+    pub fn set_window(&mut self, name: &str) -> Result<(), JsValue> {
+        self.analyzer = Spectrum::new(self.fft_size, window_from_name(name)?);
+        Ok(())
+    }
+
     /// Analyze audio samples and return frequency data
     #[wasm_bindgen]
     pub fn analyze(&self, samples: &Float32Array, sample_rate: u32) -> FrequencyResult {
@@ -173,18 +306,17 @@ impl KinoFrequencyAnalyzer {
                 spectral_centroid: 0.0,
                 spectral_rolloff: 0.0,
                 spectral_flatness: 0.0,
+                spectral_bandwidth: 0.0,
+                spectral_contrast: Vec::new(),
+                crest_factor: 0.0,
                 band_energies: BandEnergies {
-                    sub_bass: 0.0,
-                    bass: 0.0,
-                    low_mid: 0.0,
-                    mid: 0.0,
-                    high_mid: 0.0,
-                    high: 0.0,
+                    plan: self.band_plan.clone(),
+                    energies: vec![0.0; self.band_plan.bands.len()],
                 },
             };
         }
 
-        let spectrum = self.analyzer.compute_spectrum(&samples_vec);
+        let spectrum = self.analyzer.magnitudes(&samples_vec);
         let freq_resolution = sample_rate as f32 / self.fft_size as f32;
 
         // Find dominant frequencies
@@ -213,6 +345,9 @@ impl KinoFrequencyAnalyzer {
         let spectral_centroid = self.compute_centroid(&spectrum, &frequencies);
         let spectral_rolloff = self.compute_rolloff(&spectrum, &frequencies, 0.95);
         let spectral_flatness = self.compute_flatness(&spectrum);
+        let spectral_bandwidth = self.compute_bandwidth(&spectrum, &frequencies, spectral_centroid);
+        let spectral_contrast = self.compute_contrast(&spectrum, &frequencies);
+        let crest_factor = self.compute_crest_factor(&samples_vec);
         let band_energies = self.compute_band_energies(&spectrum, &frequencies);
 
         FrequencyResult {
@@ -220,6 +355,9 @@ impl KinoFrequencyAnalyzer {
             spectral_centroid,
             spectral_rolloff,
             spectral_flatness,
+            spectral_bandwidth,
+            spectral_contrast,
+            crest_factor,
             band_energies,
         }
     }
@@ -228,7 +366,7 @@ impl KinoFrequencyAnalyzer {
     #[wasm_bindgen]
     pub fn get_spectrum(&self, samples: &Float32Array) -> Float32Array {
         let samples_vec: Vec<f32> = samples.to_vec();
-        let spectrum = self.analyzer.compute_spectrum(&samples_vec);
+        let spectrum = self.analyzer.magnitudes(&samples_vec);
         Float32Array::from(&spectrum[..])
     }
 
@@ -288,40 +426,59 @@ impl KinoFrequencyAnalyzer {
         }
     }
 
-    fn compute_band_energies(&self, spectrum: &[f32], frequencies: &[f32]) -> BandEnergies {
-        let bands = [
-            (20.0, 60.0),
-            (60.0, 250.0),
-            (250.0, 500.0),
-            (500.0, 2000.0),
-            (2000.0, 4000.0),
-            (4000.0, 20000.0),
-        ];
-
-        let mut energies = [0.0f32; 6];
-
-        for (i, (low, high)) in bands.iter().enumerate() {
-            for (j, &freq) in frequencies.iter().enumerate() {
-                if freq >= *low && freq < *high {
-                    energies[i] += spectrum[j];
-                }
-            }
+    fn compute_bandwidth(&self, spectrum: &[f32], frequencies: &[f32], centroid: f32) -> f32 {
+        let total: f32 = spectrum.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
         }
+        let variance: f32 = spectrum.iter()
+            .zip(frequencies.iter())
+            .map(|(&m, &f)| m * (f - centroid).powi(2))
+            .sum::<f32>() / total;
+        variance.sqrt()
+    }
 
-        let total: f32 = energies.iter().sum();
-        if total > 0.0 {
-            for e in &mut energies {
-                *e /= total;
-            }
+    fn compute_contrast(&self, spectrum: &[f32], frequencies: &[f32]) -> Vec<f32> {
+        // Octave bands from 200 Hz; the last band extends to Nyquist
+        let edges = [0.0f32, 200.0, 400.0, 800.0, 1600.0, 3200.0, 6400.0];
+
+        (0..edges.len())
+            .map(|band| {
+                let low = edges[band];
+                let high = edges.get(band + 1).copied().unwrap_or(f32::INFINITY);
+                let mut mags: Vec<f32> = spectrum.iter()
+                    .zip(frequencies.iter())
+                    .filter(|&(_, &f)| f > low && f < high)
+                    .map(|(&m, _)| m)
+                    .collect();
+                if mags.is_empty() {
+                    return 0.0;
+                }
+                mags.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+                let k = ((mags.len() as f32 * 0.02).round() as usize).max(1);
+                let valley = mags[..k].iter().sum::<f32>() / k as f32;
+                let peak = mags[mags.len() - k..].iter().sum::<f32>() / k as f32;
+                20.0 * ((peak + 1e-10) / (valley + 1e-10)).log10()
+            })
+            .collect()
+    }
+
+    fn compute_crest_factor(&self, samples: &[f32]) -> f32 {
+        let peak = samples.iter().fold(0.0f32, |max, &s| max.max(s.abs()));
+        let rms = (samples.iter().map(|&s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+
+        if rms > 0.0 {
+            peak / rms
+        } else {
+            0.0
         }
+    }
 
+    fn compute_band_energies(&self, spectrum: &[f32], frequencies: &[f32]) -> BandEnergies {
         BandEnergies {
-            sub_bass: energies[0],
-            bass: energies[1],
-            low_mid: energies[2],
-            mid: energies[3],
-            high_mid: energies[4],
-            high: energies[5],
+            plan: self.band_plan.clone(),
+            energies: self.band_plan.energies(spectrum, frequencies),
         }
     }
 }
@@ -331,6 +488,7 @@ impl KinoFrequencyAnalyzer {
 pub struct KinoFingerprinter {
     fft_size: usize,
     hop_size: usize,
+    window: WindowFunction,
 }
 
 #[wasm_bindgen]
@@ -341,9 +499,18 @@ impl KinoFingerprinter {
         Self {
             fft_size: 4096,
             hop_size: 2048,
+            window: WindowFunction::Hann,
         }
     }
 
+    /// Change the window applied before the FFT (see
+    /// `KinoFrequencyAnalyzer.set_window`)
+    #[wasm_bindgen]
+    pub fn set_window(&mut self, name: &str) -> Result<(), JsValue> {
+        self.window = window_from_name(name)?;
+        Ok(())
+    }
+
     /// Generate a fingerprint hash from audio samples
     #[wasm_bindgen]
     pub fn fingerprint(&self, samples: &Float32Array, _sample_rate: u32) -> String {
@@ -354,7 +521,7 @@ impl KinoFingerprinter {
         }
 
         // Simple hash based on spectral peaks
-        let analyzer = FftAnalyzer::new(self.fft_size);
+        let analyzer = Spectrum::new(self.fft_size, self.window);
         let mut hash_data = Vec::new();
 
         let num_frames = (samples_vec.len() - self.fft_size) / self.hop_size + 1;
@@ -362,7 +529,7 @@ impl KinoFingerprinter {
         for frame_idx in 0..num_frames.min(100) {
             let start = frame_idx * self.hop_size;
             let frame = &samples_vec[start..start + self.fft_size];
-            let spectrum = analyzer.compute_spectrum(frame);
+            let spectrum = analyzer.magnitudes(frame);
 
             // Find peaks in 6 bands
             let bands = [0, 10, 20, 40, 80, 160, 256];
@@ -417,7 +584,7 @@ impl Default for KinoFingerprinter {
 #[wasm_bindgen]
 pub struct RealtimeFrequencyData {
     spectrum: Vec<f32>,
-    band_energies: [f32; 6],
+    band_energies: Vec<f32>,
     dominant_freq: f32,
     centroid: f32,
 }
@@ -443,15 +610,170 @@ impl RealtimeFrequencyData {
     pub fn get_band_energy(&self, band: usize) -> f32 {
         self.band_energies.get(band).copied().unwrap_or(0.0)
     }
+
+    /// Number of bands in the analyzer's band plan
+    #[wasm_bindgen(getter)]
+    pub fn band_count(&self) -> usize {
+        self.band_energies.len()
+    }
+}
+
+/// Compact per-frame summary for handing off to a UI thread
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct StreamingFrame {
+    index: u32,
+    time: f64,
+    dominant_freq: f32,
+    centroid: f32,
+    rms_energy: f32,
+    band_energies: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl StreamingFrame {
+    /// Number of frames emitted before this one
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Start of the analyzed window in seconds since the last reset
+    #[wasm_bindgen(getter)]
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn dominant_frequency(&self) -> f32 {
+        self.dominant_freq
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn spectral_centroid(&self) -> f32 {
+        self.centroid
+    }
+
+    /// RMS level of the analyzed window
+    #[wasm_bindgen(getter)]
+    pub fn rms_energy(&self) -> f32 {
+        self.rms_energy
+    }
+
+    #[wasm_bindgen]
+    pub fn get_band_energies(&self) -> Float32Array {
+        Float32Array::from(&self.band_energies[..])
+    }
+
+    #[wasm_bindgen]
+    pub fn get_band_energy(&self, band: usize) -> f32 {
+        self.band_energies.get(band).copied().unwrap_or(0.0)
+    }
+}
+
+/// Averages over the most recently emitted frames, matching
+/// kino-frequency's `StreamStatistics`
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct StreamingStatistics {
+    frame_count: usize,
+    avg_dominant_freq: f32,
+    avg_centroid: f32,
+    avg_rms_energy: f32,
+    rms_variance: f32,
+    frequency_variance: f32,
+    avg_band_energies: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl StreamingStatistics {
+    /// Number of frames averaged
+    #[wasm_bindgen(getter)]
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn avg_dominant_frequency(&self) -> f32 {
+        self.avg_dominant_freq
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn avg_spectral_centroid(&self) -> f32 {
+        self.avg_centroid
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn avg_rms_energy(&self) -> f32 {
+        self.avg_rms_energy
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn rms_variance(&self) -> f32 {
+        self.rms_variance
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn frequency_variance(&self) -> f32 {
+        self.frequency_variance
+    }
+
+    #[wasm_bindgen]
+    pub fn get_avg_band_energies(&self) -> Float32Array {
+        Float32Array::from(&self.avg_band_energies[..])
+    }
+
+    #[wasm_bindgen]
+    pub fn get_avg_band_energy(&self, band: usize) -> f32 {
+        self.avg_band_energies.get(band).copied().unwrap_or(0.0)
+    }
+}
+
+/// Location of the analyzer's input region in WASM linear memory
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct InputBuffer {
+    ptr: usize,
+    len: usize,
+}
+
+#[wasm_bindgen]
+impl InputBuffer {
+    /// Byte offset of the first sample in `memory.buffer`
+    #[wasm_bindgen(getter)]
+    pub fn ptr(&self) -> usize {
+        self.ptr
+    }
+
+    /// Capacity in `f32` samples
+    #[wasm_bindgen(getter)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[wasm_bindgen]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
 }
 
 /// Streaming analyzer for real-time use
+///
+/// Frames, features and statistics come from the same kino-spectrum core as
+/// kino-frequency's `StreamAnalyzer` with a hop of half the FFT size, so
+/// both produce identical frames for identical input.
 #[wasm_bindgen]
 pub struct KinoStreamingAnalyzer {
-    fft_size: usize,
-    buffer: Vec<f32>,
-    analyzer: FftAnalyzer,
-    sample_rate: u32,
+    analyzer: FrameAnalyzer,
+    framer: Framer,
+    /// Region the caller writes into for `process_from_buffer`
+    input: Vec<f32>,
+    /// Analyze every N-th hop
+    emit_interval: usize,
+    hop_index: u64,
+    frames_emitted: u32,
+    latest: Option<StreamingFrame>,
+    stats: RollingStats,
 }
 
 #[wasm_bindgen]
@@ -460,79 +782,615 @@ impl KinoStreamingAnalyzer {
     pub fn new(fft_size: usize, sample_rate: u32) -> Self {
         let fft_size = fft_size.max(256).min(8192);
         Self {
-            fft_size,
-            buffer: Vec::with_capacity(fft_size * 2),
-            analyzer: FftAnalyzer::new(fft_size),
-            sample_rate,
+            analyzer: FrameAnalyzer::new(fft_size, sample_rate, WindowFunction::Hann),
+            framer: Framer::new(fft_size, fft_size / 2),
+            input: Vec::new(),
+            emit_interval: 1,
+            hop_index: 0,
+            frames_emitted: 0,
+            latest: None,
+            stats: RollingStats::new(STATS_HISTORY),
         }
     }
 
-    /// Push samples and get analysis if ready
+    /// Use custom bands for band energies, given as flattened
+    /// `[low0, high0, low1, high1, ...]` bounds in Hz
+    #[wasm_bindgen]
+    pub fn set_band_plan(&mut self, name: String, bounds: &Float32Array) -> Result<(), JsValue> {
+        self.set_band_bounds(name, &bounds.to_vec())
+    }
+
+    /// Change the window applied before the FFT (see
+    /// `KinoFrequencyAnalyzer.set_window`)
+    #[wasm_bindgen]
+    pub fn set_window(&mut self, name: &str) -> Result<(), JsValue> {
+        self.analyzer.set_window(window_from_name(name)?);
+        Ok(())
+    }
+
+    /// Analyze only every `hops`-th hop (half an FFT window), so the
+    /// render rate can be decoupled from the audio rate. The first hop
+    /// after a reset is always analyzed.
+    #[wasm_bindgen]
+    pub fn set_emit_interval(&mut self, hops: usize) {
+        self.emit_interval = hops.max(1);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn emit_interval(&self) -> usize {
+        self.emit_interval
+    }
+
+    /// Push samples and get the most recent analysis produced by them, if any
     #[wasm_bindgen]
     pub fn push(&mut self, samples: &Float32Array) -> Option<RealtimeFrequencyData> {
-        self.buffer.extend(samples.to_vec());
-
-        if self.buffer.len() >= self.fft_size {
-            let spectrum = self.analyzer.compute_spectrum(&self.buffer[..self.fft_size]);
-
-            // Compute features
-            let freq_resolution = self.sample_rate as f32 / self.fft_size as f32;
-            let frequencies: Vec<f32> = (0..spectrum.len())
-                .map(|i| i as f32 * freq_resolution)
-                .collect();
-
-            // Dominant frequency
-            let dominant_idx = spectrum.iter()
-                .enumerate()
-                .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
-                .map(|(i, _)| i)
-                .unwrap_or(0);
-            let dominant_freq = dominant_idx as f32 * freq_resolution;
-
-            // Centroid
-            let weighted: f32 = spectrum.iter().zip(frequencies.iter())
-                .map(|(&m, &f)| m * f).sum();
-            let total: f32 = spectrum.iter().sum();
-            let centroid = if total > 0.0 { weighted / total } else { 0.0 };
-
-            // Band energies
-            let bands = [
-                (20.0, 60.0), (60.0, 250.0), (250.0, 500.0),
-                (500.0, 2000.0), (2000.0, 4000.0), (4000.0, 20000.0),
-            ];
-            let mut band_energies = [0.0f32; 6];
-            for (i, (low, high)) in bands.iter().enumerate() {
-                for (j, &freq) in frequencies.iter().enumerate() {
-                    if freq >= *low && freq < *high {
-                        band_energies[i] += spectrum[j];
-                    }
-                }
-            }
-            let band_total: f32 = band_energies.iter().sum();
-            if band_total > 0.0 {
-                for e in &mut band_energies {
-                    *e /= band_total;
-                }
-            }
+        self.feed(&samples.to_vec())
+    }
 
-            // Keep overlap
-            let drain = self.buffer.len() - self.fft_size / 2;
-            self.buffer.drain(0..drain);
+    /// Reserve `frames` samples of input in linear memory and return where
+    /// they live. Any previous region is released, and the returned pointer
+    /// stays valid until the next call to this method or until the analyzer
+    /// is freed; JS views over it must be rebuilt if `memory.buffer` changes.
+    #[wasm_bindgen]
+    pub fn allocate_input_buffer(&mut self, frames: usize) -> InputBuffer {
+        self.input = vec![0.0; frames];
+        InputBuffer {
+            ptr: self.input.as_ptr() as usize,
+            len: self.input.len(),
+        }
+    }
 
-            Some(RealtimeFrequencyData {
-                spectrum,
-                band_energies,
-                dominant_freq,
-                centroid,
-            })
-        } else {
-            None
+    /// Consume `len` samples starting at `offset` in the input buffer and
+    /// return how many frames were emitted. The newest one is available
+    /// from `get_latest_frame`.
+    #[wasm_bindgen]
+    pub fn process_from_buffer(&mut self, offset: usize, len: usize) -> Result<u32, JsValue> {
+        let end = offset
+            .checked_add(len)
+            .filter(|&end| end <= self.input.len())
+            .ok_or_else(|| {
+                JsValue::from_str(&format!(
+                    "Range {}..{} is outside the {}-sample input buffer",
+                    offset,
+                    offset.saturating_add(len),
+                    self.input.len()
+                ))
+            })?;
+
+        // Moving the Vec out keeps its allocation, and so the JS view, intact
+        let input = std::mem::take(&mut self.input);
+        let emitted = self.process_samples(&input[offset..end]);
+        self.input = input;
+        Ok(emitted)
+    }
+
+    /// Summary of the most recently emitted frame
+    #[wasm_bindgen]
+    pub fn get_latest_frame(&self) -> Option<StreamingFrame> {
+        self.latest.clone()
+    }
+
+    /// Averages over the last 100 emitted frames
+    #[wasm_bindgen]
+    pub fn get_statistics(&self) -> StreamingStatistics {
+        let summary = self.stats.summary();
+        StreamingStatistics {
+            frame_count: summary.frame_count,
+            avg_dominant_freq: summary.avg_dominant_frequency,
+            avg_centroid: summary.avg_spectral_centroid,
+            avg_rms_energy: summary.avg_rms_energy,
+            rms_variance: summary.rms_variance,
+            frequency_variance: summary.frequency_variance,
+            avg_band_energies: summary.avg_band_energies,
         }
     }
 
-    /// Reset the analyzer buffer
+    /// Frames emitted since the last reset
+    #[wasm_bindgen(getter)]
+    pub fn frames_emitted(&self) -> u32 {
+        self.frames_emitted
+    }
+
+    /// Reset the analyzer buffer, frame counters and statistics. The input
+    /// buffer stays allocated.
     #[wasm_bindgen]
     pub fn reset(&mut self) {
-        self.buffer.clear();
+        self.framer.clear();
+        self.hop_index = 0;
+        self.frames_emitted = 0;
+        self.latest = None;
+        self.stats.clear();
+    }
+}
+
+impl KinoStreamingAnalyzer {
+    /// Analyze `samples` as if written to the input buffer and return how
+    /// many frames were emitted
+    pub fn process_samples(&mut self, samples: &[f32]) -> u32 {
+        let before = self.frames_emitted;
+        self.feed(samples);
+        self.frames_emitted - before
+    }
+
+    /// Use custom bands from flattened `[low0, high0, ...]` bounds in Hz
+    pub fn set_band_bounds(&mut self, name: String, bounds: &[f32]) -> Result<(), JsValue> {
+        let plan = BandPlan::from_bounds(name, bounds)?;
+        self.analyzer.set_bands(plan.bands);
+        Ok(())
+    }
+
+    /// Step through every complete window, analyzing those that fall on
+    /// the emit cadence, and return the newest analysis
+    fn feed(&mut self, samples: &[f32]) -> Option<RealtimeFrequencyData> {
+        let sample_rate = self.analyzer.sample_rate() as f64;
+        let mut result = None;
+
+        let Self { analyzer, framer, emit_interval, hop_index, frames_emitted, latest, stats, .. } = self;
+        framer.process(samples, |start, frame| {
+            if hop_index.is_multiple_of(*emit_interval as u64) {
+                let features = analyzer.analyze(frame);
+                stats.push(&features);
+                *latest = Some(StreamingFrame {
+                    index: *frames_emitted,
+                    time: start as f64 / sample_rate,
+                    dominant_freq: features.dominant_frequency,
+                    centroid: features.spectral_centroid,
+                    rms_energy: features.rms_energy,
+                    band_energies: features.band_energies.clone(),
+                });
+                *frames_emitted += 1;
+                result = Some(features);
+            }
+            *hop_index += 1;
+        });
+
+        result.map(|features: FrameFeatures| RealtimeFrequencyData {
+            spectrum: features.spectrum,
+            band_energies: features.band_energies,
+            dominant_freq: features.dominant_frequency,
+            centroid: features.spectral_centroid,
+        })
+    }
+}
+
+// ============================================================================
+// Content Tagging and Signatures (shared with kino-frequency via kino-tagging)
+// ============================================================================
+
+/// Frame and hop sizes matching kino-frequency's tagger and recommender
+const TAG_FFT_SIZE: usize = 4096;
+const TAG_HOP_SIZE: usize = 2048;
+
+/// Zero crossings per sample, as kino-frequency computes it
+fn zero_crossing_rate(samples: &[f32]) -> f32 {
+    let crossings = samples.windows(2)
+        .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
+        .count();
+    crossings as f32 / samples.len() as f32
+}
+
+/// Rule-based content tagger using the native `ContentTagger`'s genre profiles
+///
+/// The clip is scored as a single window, so tags match kino-frequency's
+/// `ContentTagger::predict` for clips up to its 30 second segment window.
+///
+/// ```javascript
+/// const tagger = new KinoContentTagger();
+/// for (const { label, confidence } of tagger.predict(samples, 44100)) {
+///   console.log(label, confidence);
+/// }
+/// ```
+#[wasm_bindgen]
+pub struct KinoContentTagger {
+    analyzer: KinoFrequencyAnalyzer,
+    min_confidence: f32,
+    max_tags: usize,
+}
+
+#[wasm_bindgen]
+impl KinoContentTagger {
+    /// Create a tagger with the native defaults (min confidence 0.3, 5 tags)
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            analyzer: KinoFrequencyAnalyzer::new(TAG_FFT_SIZE),
+            min_confidence: 0.3,
+            max_tags: 5,
+        }
+    }
+
+    /// Drop tags below this confidence
+    #[wasm_bindgen]
+    pub fn set_min_confidence(&mut self, min_confidence: f32) {
+        self.min_confidence = min_confidence;
+    }
+
+    /// Return at most this many tags
+    #[wasm_bindgen]
+    pub fn set_max_tags(&mut self, max_tags: usize) {
+        self.max_tags = max_tags;
+    }
+
+    /// Tag mono audio, returning `{label, confidence}` objects sorted by
+    /// confidence (empty for clips shorter than one 4096-sample frame)
+    #[wasm_bindgen]
+    pub fn predict(&self, samples: &Float32Array, sample_rate: u32) -> Array {
+        let array = Array::new();
+
+        for (label, confidence) in self.tags(&samples.to_vec(), sample_rate) {
+            let obj = js_sys::Object::new();
+            js_sys::Reflect::set(&obj, &"label".into(), &label.into()).ok();
+            js_sys::Reflect::set(&obj, &"confidence".into(), &confidence.into()).ok();
+            array.push(&obj);
+        }
+
+        array
+    }
+}
+
+impl KinoContentTagger {
+    fn tags(&self, samples: &[f32], sample_rate: u32) -> Vec<(&'static str, f32)> {
+        let fft = &self.analyzer.analyzer;
+        let Some(spectrum) = fft.average(samples, TAG_HOP_SIZE) else {
+            return Vec::new();
+        };
+        let frequencies = fft.frequencies(sample_rate);
+
+        let features = kino_tagging::TagFeatures {
+            spectral_centroid: self.analyzer.compute_centroid(&spectrum, &frequencies),
+            spectral_flatness: self.analyzer.compute_flatness(&spectrum),
+            zero_crossing_rate: zero_crossing_rate(samples),
+            energy_variance: kino_tagging::signal::energy_variance(samples, TAG_FFT_SIZE, TAG_HOP_SIZE),
+            tempo_estimate: kino_tagging::signal::estimate_tempo(samples, sample_rate),
+        };
+        let band_energies = BandPlan::standard().energies(&spectrum, &frequencies);
+
+        let scores = kino_tagging::GENRE_PROFILES.iter()
+            .map(|profile| (profile.name, profile.score(&features, profile.band_match(&band_energies))))
+            .collect();
+
+        let mut tags = kino_tagging::rule_tags(&features, scores, self.min_confidence, self.max_tags);
+        tags.truncate(self.max_tags);
+        tags
+    }
+}
+
+impl Default for KinoContentTagger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Frequency signature for similarity matching, laid out like
+/// kino-frequency's `FrequencySignature::features`
+///
+/// ```javascript
+/// const a = KinoSignature.compute(clipA, 44100);
+/// const b = KinoSignature.compute(clipB, 44100);
+/// console.log('Similarity:', a.similarity(b));
+/// ```
+#[wasm_bindgen]
+pub struct KinoSignature {
+    features: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl KinoSignature {
+    /// Compute the signature of mono audio; needs at least 4096 samples
+    #[wasm_bindgen]
+    pub fn compute(samples: &Float32Array, sample_rate: u32) -> Result<KinoSignature, JsValue> {
+        Self::from_samples(&samples.to_vec(), sample_rate).ok_or_else(|| {
+            JsValue::from_str(&format!("Need at least {} samples for a signature", TAG_FFT_SIZE))
+        })
+    }
+
+    /// Wrap features computed elsewhere, e.g. a stored native signature
+    #[wasm_bindgen]
+    pub fn from_features(features: &Float32Array) -> KinoSignature {
+        Self { features: features.to_vec() }
+    }
+
+    /// Signature features as a Float32Array
+    #[wasm_bindgen]
+    pub fn get_features(&self) -> Float32Array {
+        Float32Array::from(&self.features[..])
+    }
+
+    /// Cosine similarity with another signature (0 if their sizes differ)
+    #[wasm_bindgen]
+    pub fn similarity(&self, other: &KinoSignature) -> f32 {
+        if self.features.len() != other.features.len() {
+            return 0.0;
+        }
+        kino_tagging::cosine_similarity(&self.features, &other.features)
+    }
+}
+
+impl KinoSignature {
+    fn from_samples(samples: &[f32], sample_rate: u32) -> Option<Self> {
+        let analyzer = Spectrum::new(TAG_FFT_SIZE, WindowFunction::Hann);
+        let spectrum = analyzer.average(samples, TAG_HOP_SIZE)?;
+        let features = kino_tagging::signature_features(&spectrum, &analyzer.frequencies(sample_rate), sample_rate);
+        Some(Self { features })
+    }
+}
+
+// ============================================================================
+// Seek-Bar Overviews (shared with kino-frequency via kino-spectrum)
+// ============================================================================
+
+/// Waveform and frequency heatmap for drawing under the seek bar, in the
+/// layout of the `overview.json` written by `kino process`
+///
+/// ```javascript
+/// const generator = new KinoOverviewGenerator();
+/// const overview = generator.generate(samples, 44100, canvas.width);
+/// const peaks = overview.get_peaks();      // 0-255 per bucket
+/// const column = overview.get_column(0);   // 0-255 per band, lowest first
+/// ```
+#[wasm_bindgen]
+pub struct KinoOverviewGenerator {
+    config: OverviewConfig,
+}
+
+#[wasm_bindgen]
+impl KinoOverviewGenerator {
+    /// Create a generator with the native defaults (32 bands, at most 2000 buckets)
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self { config: OverviewConfig::default() }
+    }
+
+    /// Cap the number of buckets, whatever `generate` is asked for
+    #[wasm_bindgen]
+    pub fn set_max_buckets(&mut self, max_buckets: usize) {
+        self.config.max_buckets = max_buckets.max(1);
+    }
+
+    /// Number of log-spaced heatmap bands
+    #[wasm_bindgen]
+    pub fn set_bands(&mut self, bands: usize) {
+        self.config.bands = bands.max(1);
+    }
+
+    /// Overview of mono audio in `buckets` time buckets. Work is bounded per
+    /// bucket, but the clip is copied in, so keep it to short clips and
+    /// fetch `overview.json` for full assets.
+    #[wasm_bindgen]
+    pub fn generate(&self, samples: &Float32Array, sample_rate: u32, buckets: usize) -> KinoOverview {
+        self.generate_samples(&samples.to_vec(), sample_rate, buckets)
+    }
+}
+
+impl KinoOverviewGenerator {
+    /// Overview of mono samples, like `generate`
+    pub fn generate_samples(&self, samples: &[f32], sample_rate: u32, buckets: usize) -> KinoOverview {
+        KinoOverview {
+            overview: kino_spectrum::overview(samples, sample_rate, buckets, &self.config),
+        }
+    }
+}
+
+impl Default for KinoOverviewGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of `KinoOverviewGenerator.generate`
+#[wasm_bindgen]
+pub struct KinoOverview {
+    overview: Overview,
+}
+
+#[wasm_bindgen]
+impl KinoOverview {
+    /// Number of time buckets
+    #[wasm_bindgen(getter)]
+    pub fn buckets(&self) -> usize {
+        self.overview.buckets()
+    }
+
+    /// Number of heatmap bands
+    #[wasm_bindgen(getter)]
+    pub fn bands(&self) -> usize {
+        self.overview.bands()
+    }
+
+    /// Clip duration in seconds
+    #[wasm_bindgen(getter)]
+    pub fn duration_secs(&self) -> f64 {
+        self.overview.duration_secs
+    }
+
+    /// Nominal bucket length in seconds
+    #[wasm_bindgen(getter)]
+    pub fn bucket_secs(&self) -> f64 {
+        self.overview.bucket_secs
+    }
+
+    /// Peak amplitude per bucket (0-255 for 0-1 full scale)
+    #[wasm_bindgen]
+    pub fn get_peaks(&self) -> Uint8Array {
+        Uint8Array::from(&self.overview.peaks[..])
+    }
+
+    /// RMS amplitude per bucket (0-255 for 0-1 full scale)
+    #[wasm_bindgen]
+    pub fn get_rms(&self) -> Uint8Array {
+        Uint8Array::from(&self.overview.rms[..])
+    }
+
+    /// Band levels of every bucket, bucket by bucket
+    #[wasm_bindgen]
+    pub fn get_heatmap(&self) -> Uint8Array {
+        Uint8Array::from(&self.overview.heatmap[..])
+    }
+
+    /// Band levels of one bucket, lowest band first
+    #[wasm_bindgen]
+    pub fn get_column(&self, bucket: usize) -> Uint8Array {
+        Uint8Array::from(self.overview.column(bucket))
+    }
+
+    /// Heatmap band edges in Hz, one more than the number of bands
+    #[wasm_bindgen]
+    pub fn get_band_edges(&self) -> Float32Array {
+        Float32Array::from(&self.overview.band_edges_hz[..])
+    }
+
+    /// The overview as `overview.json`
+    #[wasm_bindgen]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.overview).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    const RATE: u32 = 8000;
+
+    fn write_input(analyzer: &KinoStreamingAnalyzer, input: InputBuffer, offset: usize, samples: &[f32]) {
+        assert_eq!(input.ptr(), analyzer.input.as_ptr() as usize);
+        // SAFETY: the region is owned by `analyzer` and nothing else borrows
+        // it while we write, mirroring what a worklet does from JS
+        let region = unsafe { std::slice::from_raw_parts_mut(input.ptr() as *mut f32, input.len()) };
+        region[offset..offset + samples.len()].copy_from_slice(samples);
+    }
+
+    fn tone(freq: f32, len: usize, start: usize) -> Vec<f32> {
+        (start..start + len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_input_buffer_lifecycle() {
+        let mut analyzer = KinoStreamingAnalyzer::new(256, RATE);
+        assert!(analyzer.get_latest_frame().is_none());
+
+        let input = analyzer.allocate_input_buffer(128);
+        assert_eq!(input.len(), 128);
+
+        // One 256-sample window needs two quanta; hop is 128
+        let mut emitted = 0;
+        for quantum in 0..2 {
+            write_input(&analyzer, input, 0, &tone(1000.0, 128, quantum * 128));
+            emitted += analyzer.process_from_buffer(0, 128).unwrap();
+        }
+        assert_eq!(emitted, 1);
+
+        let frame = analyzer.get_latest_frame().unwrap();
+        assert_eq!(frame.index(), 0);
+        assert_eq!(frame.time(), 0.0);
+        assert!((frame.dominant_frequency() - 1000.0).abs() < RATE as f32 / 256.0);
+
+        // Partial writes at an offset are consumed like any other chunk
+        write_input(&analyzer, input, 64, &tone(1000.0, 64, 256));
+        assert_eq!(analyzer.process_from_buffer(64, 64).unwrap(), 0);
+        write_input(&analyzer, input, 0, &tone(1000.0, 64, 320));
+        assert_eq!(analyzer.process_from_buffer(0, 64).unwrap(), 1);
+        assert_eq!(analyzer.get_latest_frame().unwrap().time(), 128.0 / RATE as f64);
+
+        // Reallocating hands out a fresh region; reset keeps it
+        let resized = analyzer.allocate_input_buffer(512);
+        assert_eq!(resized.len(), 512);
+        analyzer.reset();
+        assert_eq!(analyzer.frames_emitted(), 0);
+        assert!(analyzer.get_latest_frame().is_none());
+        assert_eq!(analyzer.input.len(), 512);
+        write_input(&analyzer, resized, 0, &tone(1000.0, 512, 0));
+        assert_eq!(analyzer.process_from_buffer(0, 512).unwrap(), 3);
+        assert_eq!(analyzer.get_latest_frame().unwrap().index(), 2);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_emit_interval() {
+        let mut analyzer = KinoStreamingAnalyzer::new(256, RATE);
+        analyzer.set_emit_interval(4);
+        let input = analyzer.allocate_input_buffer(128);
+
+        let mut times = Vec::new();
+        for quantum in 0..20 {
+            write_input(&analyzer, input, 0, &tone(440.0, 128, quantum * 128));
+            if analyzer.process_from_buffer(0, 128).unwrap() > 0 {
+                times.push(analyzer.get_latest_frame().unwrap().time());
+            }
+        }
+
+        // 20 quanta give 19 hops; every 4th starting from the first
+        let hop = 128.0 / RATE as f64;
+        assert_eq!(times, vec![0.0, 4.0 * hop, 8.0 * hop, 12.0 * hop, 16.0 * hop]);
+    }
+
+    fn mixed_tone(freqs: &[f32], rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| freqs.iter().map(|&f| (2.0 * std::f32::consts::PI * f * i as f32 / rate as f32).sin()).sum::<f32>() * 0.3)
+            .collect()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_content_tagger_uses_shared_profiles() {
+        let tagger = KinoContentTagger::new();
+        assert!(tagger.tags(&[0.0; 1000], 44100).is_empty());
+
+        let tags = tagger.tags(&mixed_tone(&[220.0, 440.0, 880.0], 44100, 44100 * 2), 44100);
+        assert!(!tags.is_empty() && tags.len() <= 5);
+        assert!(tags.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert!(tags.iter().all(|&(_, c)| c >= 0.3));
+        let known: Vec<&str> = kino_tagging::GENRE_PROFILES.iter().map(|p| p.name).collect();
+        assert!(tags.iter().any(|(label, _)| known.contains(label)));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_signature_similarity() {
+        let low = KinoSignature::from_samples(&mixed_tone(&[220.0, 440.0], 44100, 44100), 44100).unwrap();
+        let low_again = KinoSignature::from_samples(&mixed_tone(&[220.0, 440.0], 44100, 22050), 44100).unwrap();
+        let high = KinoSignature::from_samples(&mixed_tone(&[5000.0, 9000.0], 44100, 44100), 44100).unwrap();
+
+        assert_eq!(low.features.len(), kino_tagging::SIGNATURE_SIZE);
+        assert!(low.similarity(&low_again) > 0.95);
+        assert!(low.similarity(&high) < 0.5);
+        assert_eq!(low.similarity(&KinoSignature { features: vec![1.0; 64] }), 0.0);
+        assert!(KinoSignature::from_samples(&[0.0; 100], 44100).is_none());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn test_overview_generator() {
+        let mut generator = KinoOverviewGenerator::new();
+        generator.set_max_buckets(40);
+        generator.set_bands(16);
+
+        let overview = generator.generate_samples(&tone(440.0, RATE as usize * 2, 0), RATE, 100);
+        assert_eq!((overview.buckets(), overview.bands()), (40, 16));
+        assert_eq!(overview.duration_secs(), 2.0);
+        assert_eq!(overview.bucket_secs(), 0.05);
+
+        let json: serde_json::Value = serde_json::from_str(&overview.to_json()).unwrap();
+        assert_eq!(json["peaks"].as_array().unwrap().len(), 40);
+        assert_eq!(json["heatmap"].as_array().unwrap().len(), 40 * 16);
+        assert_eq!(json["band_edges_hz"].as_array().unwrap().len(), 17);
+    }
+
+    // Building the error needs a JS host
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_out_of_range_is_rejected() {
+        let mut analyzer = KinoStreamingAnalyzer::new(256, RATE);
+        assert!(analyzer.process_from_buffer(0, 1).is_err());
+
+        analyzer.allocate_input_buffer(128);
+        assert!(analyzer.process_from_buffer(100, 29).is_err());
+        assert!(analyzer.process_from_buffer(usize::MAX, 2).is_err());
+        assert!(analyzer.process_from_buffer(0, 128).is_ok());
     }
 }
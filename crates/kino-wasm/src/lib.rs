@@ -5,6 +5,8 @@
 //! - Buffer strategy optimization
 //! - Analytics aggregation
 //! - Kino Branding
+//! - Frequency analysis, content tagging and signature similarity
+//! - Seek-bar waveform and frequency heatmap overviews
 //!
 //! ## Integration with hls.js
 //!
@@ -16,24 +18,32 @@
 //! ```
 
 use wasm_bindgen::prelude::*;
-use serde::{Serialize, Deserialize};
 
 mod abr_controller;
+mod config;
 mod buffer_controller;
 mod analytics;
 mod branding;
 mod frequency;
 
-pub use abr_controller::KinoAbrController;
-pub use buffer_controller::KinoBufferController;
-pub use analytics::KinoAnalytics;
+pub use abr_controller::{KinoAbrController, AbrDecision};
+pub use config::{ConfigIssue, WasmConfig};
+pub use buffer_controller::{KinoBufferController, GapReport};
+pub use analytics::{KinoAnalytics, QoeBreakdown, SessionSummary};
 pub use branding::KinoBranding;
 pub use frequency::{
     KinoFrequencyAnalyzer,
     KinoFingerprinter,
     KinoStreamingAnalyzer,
+    KinoContentTagger,
+    KinoSignature,
     FrequencyResult,
     RealtimeFrequencyData,
+    StreamingFrame,
+    StreamingStatistics,
+    InputBuffer,
+    KinoOverviewGenerator,
+    KinoOverview,
 };
 
 /// Initialize the WASM module
@@ -43,85 +53,34 @@ pub fn init() {
     web_sys::console::log_1(&"[Kino WASM] Initialized".into());
 }
 
-/// Library version
-#[wasm_bindgen]
-pub fn version() -> String {
-    env!("CARGO_PKG_VERSION").to_string()
-}
-
-/// Configuration for the WASM player
-#[wasm_bindgen]
-#[derive(Clone, Serialize, Deserialize)]
-pub struct WasmConfig {
-    /// ABR algorithm: "throughput", "bola", or "hybrid"
-    abr_algorithm: String,
-    /// Minimum buffer before playback (seconds)
-    pub min_buffer_time: f64,
-    /// Maximum buffer level (seconds)
-    pub max_buffer_time: f64,
-    /// Enable analytics collection
-    pub analytics_enabled: bool,
-    /// Maximum bitrate cap (0 = unlimited)
-    pub max_bitrate: u32,
-    /// Start at lowest quality
-    pub start_at_lowest: bool,
-}
-
-#[wasm_bindgen]
-impl WasmConfig {
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> Self {
-        Self {
-            abr_algorithm: "bola".to_string(),
-            min_buffer_time: 10.0,
-            max_buffer_time: 30.0,
-            analytics_enabled: true,
-            max_bitrate: 0,
-            start_at_lowest: false,
-        }
-    }
-
-    /// Get ABR algorithm
-    #[wasm_bindgen(getter)]
-    pub fn abr_algorithm(&self) -> String {
-        self.abr_algorithm.clone()
-    }
-
-    /// Set ABR algorithm
-    #[wasm_bindgen(setter)]
-    pub fn set_abr_algorithm(&mut self, algorithm: String) {
-        self.abr_algorithm = algorithm;
+/// Wall clock in milliseconds since the Unix epoch
+///
+/// `Date.now()` in the browser; the system clock elsewhere so the
+/// controllers can be exercised by native unit tests.
+pub(crate) fn now_ms() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now()
     }
-
-    /// Create config optimized for low-latency live streaming
-    #[wasm_bindgen]
-    pub fn low_latency() -> Self {
-        Self {
-            abr_algorithm: "throughput".to_string(),
-            min_buffer_time: 2.0,
-            max_buffer_time: 6.0,
-            analytics_enabled: true,
-            max_bitrate: 0,
-            start_at_lowest: true,
-        }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .unwrap_or(0.0)
     }
+}
 
-    /// Create config optimized for VOD
-    #[wasm_bindgen]
-    pub fn vod() -> Self {
-        Self {
-            abr_algorithm: "bola".to_string(),
-            min_buffer_time: 15.0,
-            max_buffer_time: 60.0,
-            analytics_enabled: true,
-            max_bitrate: 0,
-            start_at_lowest: false,
-        }
-    }
+/// Log a warning to the browser console (a no-op in native builds)
+pub(crate) fn console_warn(message: &str) {
+    #[cfg(target_arch = "wasm32")]
+    web_sys::console::warn_1(&format!("[Kino WASM] {}", message).into());
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = message;
 }
 
-impl Default for WasmConfig {
-    fn default() -> Self {
-        Self::new()
-    }
+/// Library version
+#[wasm_bindgen]
+pub fn version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
 }
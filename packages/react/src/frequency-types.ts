@@ -214,10 +214,19 @@ export interface KinoFrequencyAnalyzer {
     spectral_centroid: number;
     spectral_rolloff: number;
     spectral_flatness: number;
+    spectral_bandwidth: number;
+    crest_factor: number;
+    get_spectral_contrast(): Float32Array;
     get_dominant_json(): string;
     get_band_energies_json(): string;
   };
 
+  /** Use custom bands, as flattened [low0, high0, low1, high1, ...] Hz bounds */
+  set_band_plan(name: string, bounds: Float32Array): void;
+
+  /** Change the window applied before the FFT (default 'hann') */
+  set_window(name: WindowFunction): void;
+
   /** Get magnitude spectrum */
   getSpectrum(samples: Float32Array): Float32Array;
 
@@ -232,6 +241,16 @@ export interface KinoFrequencyAnalyzer {
   free(): void;
 }
 
+/**
+ * FFT window function; non-Hann windows are scaled to Hann's coherent gain
+ */
+export type WindowFunction =
+  | 'hann'
+  | 'hamming'
+  | 'blackman_harris'
+  | 'flat_top'
+  | 'rectangular';
+
 /**
  * KinoFingerprinter WASM class
  */
@@ -242,6 +261,9 @@ export interface KinoFingerprinter {
   /** Compare two fingerprint hashes */
   compare(hash1: string, hash2: string): number;
 
+  /** Change the window applied before the FFT (default 'hann') */
+  set_window(name: WindowFunction): void;
+
   /** Free WASM memory */
   free(): void;
 }
@@ -256,15 +278,69 @@ export interface KinoStreamingAnalyzer {
     spectral_centroid: number;
     get_spectrum(): Float32Array;
     get_band_energy(band: number): number;
+    band_count: number;
   } | undefined;
 
-  /** Reset the internal buffer */
+  /** Use custom bands, as flattened [low0, high0, low1, high1, ...] Hz bounds */
+  set_band_plan(name: string, bounds: Float32Array): void;
+
+  /** Change the window applied before the FFT (default 'hann') */
+  set_window(name: WindowFunction): void;
+
+  /** Analyze only every N-th hop (half an FFT window); defaults to 1 */
+  set_emit_interval(hops: number): void;
+
+  /** Current emit cadence in hops */
+  readonly emit_interval: number;
+
+  /**
+   * Reserve an input region in WASM linear memory that an AudioWorklet
+   * can write into directly. Replaces any previous region.
+   */
+  allocate_input_buffer(frames: number): InputBuffer;
+
+  /** Consume samples from the input region; returns frames emitted */
+  process_from_buffer(offset: number, len: number): number;
+
+  /** Compact summary of the most recent frame */
+  get_latest_frame(): StreamingFrame | undefined;
+
+  /** Frames emitted since the last reset */
+  readonly frames_emitted: number;
+
+  /** Reset the internal buffer and frame counters */
   reset(): void;
 
   /** Free WASM memory */
   free(): void;
 }
 
+/**
+ * Input region in WASM linear memory
+ */
+export interface InputBuffer {
+  /** Byte offset into `memory.buffer` */
+  readonly ptr: number;
+  /** Capacity in samples */
+  readonly len: number;
+  is_empty(): boolean;
+  free(): void;
+}
+
+/**
+ * Per-frame summary from KinoStreamingAnalyzer
+ */
+export interface StreamingFrame {
+  readonly index: number;
+  /** Window start in seconds since the last reset */
+  readonly time: number;
+  readonly dominant_frequency: number;
+  readonly spectral_centroid: number;
+  get_band_energies(): Float32Array;
+  get_band_energy(band: number): number;
+  free(): void;
+}
+
 /**
  * Constructor for KinoFrequencyAnalyzer
  */
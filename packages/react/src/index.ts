@@ -41,6 +41,9 @@ export type {
   KinoFrequencyAnalyzer,
   KinoFingerprinter,
   KinoStreamingAnalyzer,
+  InputBuffer,
+  StreamingFrame,
+  WindowFunction,
   AnalyzeRequest,
   AnalysisResponse,
   VerifyRequest,